@@ -31,4 +31,12 @@ pub trait InputTransaction {
     fn create() -> Self;
 
     fn add_event(&mut self, event: Self::DeviceEvent);
+
+    /// Adds the Unicode key presses and releases typing a text, e.g. the text committed by an input method (IME).
+    fn add_text(&mut self, text: &str) {
+        for character in text.chars() {
+            self.add_event(Self::DeviceEvent::unicode_pressed(character));
+            self.add_event(Self::DeviceEvent::unicode_released(character));
+        }
+    }
 }
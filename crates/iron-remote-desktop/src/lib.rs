@@ -374,6 +374,12 @@ macro_rules! make_bridge {
             pub fn add_event(&mut self, event: DeviceEvent) {
                 $crate::InputTransaction::add_event(&mut self.0, event.0);
             }
+
+            /// Unpaired surrogates of the JavaScript string are replaced by U+FFFD REPLACEMENT CHARACTER.
+            #[wasm_bindgen(js_name = addText)]
+            pub fn add_text(&mut self, text: &str) {
+                $crate::InputTransaction::add_text(&mut self.0, text);
+            }
         }
 
         #[$crate::internal::wasm_bindgen::prelude::wasm_bindgen]
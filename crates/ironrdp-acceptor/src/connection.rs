@@ -1,8 +1,8 @@
 use core::mem;
 
 use ironrdp_connector::{
-    encode_x224_packet, general_err, reason_err, ConnectorError, ConnectorErrorExt as _, ConnectorResult, DesktopSize,
-    Sequence, State, Written,
+    encode_x224_packet, general_err, reason_err, ConnectorError, ConnectorErrorExt as _, ConnectorErrorKind,
+    ConnectorResult, DesktopSize, NegotiationFailure, Sequence, State, Written,
 };
 use ironrdp_core::{decode, WriteBuf};
 use ironrdp_pdu as pdu;
@@ -10,11 +10,11 @@ use ironrdp_pdu::nego::SecurityProtocol;
 use ironrdp_pdu::x224::X224;
 use ironrdp_svc::{StaticChannelSet, SvcServerProcessor};
 use pdu::rdp::capability_sets::CapabilitySet;
-use pdu::rdp::client_info::Credentials;
+use pdu::rdp::client_info::{Credentials, PerformanceFlags};
 use pdu::rdp::headers::ShareControlPdu;
 use pdu::rdp::server_error_info::{ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu};
 use pdu::rdp::server_license::{LicensePdu, LicensingErrorMessage};
-use pdu::{gcc, mcs, nego, rdp};
+use pdu::{gcc, mcs, nego, rdp, rdstls};
 use tracing::{debug, warn};
 
 use super::channel_connection::ChannelConnectionSequence;
@@ -34,9 +34,22 @@ pub struct Acceptor {
     static_channels: StaticChannelSet,
     saved_for_reactivation: AcceptorState,
     pub(crate) creds: Option<Credentials>,
+    pub(crate) delegated_creds: Option<Credentials>,
+    client_core_data: Option<gcc::ClientCoreData>,
+    performance_flags: Option<PerformanceFlags>,
+    rdstls_authenticator: Option<Box<dyn RdstlsAuthenticator>>,
     reactivation: bool,
 }
 
+/// Checks the credentials sent by clients authenticating with RDSTLS
+///
+/// Clients use RDSTLS after a server redirection, with the credentials given in the Server Redirection PDU:
+/// the password is the cookie generated by the redirecting server.
+pub trait RdstlsAuthenticator: Send {
+    /// Returns [`RdstlsResultCode::SUCCESS`](rdstls::RdstlsResultCode::SUCCESS) when the client is authenticated
+    fn authenticate(&self, request: &rdstls::RdstlsAuthenticationRequest) -> rdstls::RdstlsResultCode;
+}
+
 #[derive(Debug)]
 pub struct AcceptorResult {
     pub static_channels: StaticChannelSet,
@@ -64,6 +77,10 @@ impl Acceptor {
             static_channels: StaticChannelSet::new(),
             saved_for_reactivation: Default::default(),
             creds,
+            delegated_creds: None,
+            client_core_data: None,
+            performance_flags: None,
+            rdstls_authenticator: None,
             reactivation: false,
         }
     }
@@ -105,10 +122,20 @@ impl Acceptor {
             static_channels,
             saved_for_reactivation,
             creds: consumed.creds,
+            delegated_creds: consumed.delegated_creds,
+            client_core_data: consumed.client_core_data,
+            performance_flags: consumed.performance_flags,
+            rdstls_authenticator: consumed.rdstls_authenticator,
             reactivation: true,
         })
     }
 
+    /// Allows the clients to authenticate with RDSTLS, the requests being checked by `authenticator`
+    pub fn enable_rdstls(&mut self, authenticator: Box<dyn RdstlsAuthenticator>) {
+        self.security.insert(SecurityProtocol::RDSTLS);
+        self.rdstls_authenticator = Some(authenticator);
+    }
+
     pub fn attach_static_channel<T>(&mut self, channel: T)
     where
         T: SvcServerProcessor + 'static,
@@ -123,6 +150,24 @@ impl Acceptor {
         }
     }
 
+    /// Returns the security protocol negotiated with the client
+    ///
+    /// Available from the security upgrade until the secure settings exchange.
+    pub fn selected_protocol(&self) -> Option<SecurityProtocol> {
+        match self.state {
+            AcceptorState::SecurityUpgrade { protocol, .. }
+            | AcceptorState::RdstlsSendCapabilities { protocol, .. }
+            | AcceptorState::RdstlsWaitAuthRequest { protocol, .. }
+            | AcceptorState::Credssp { protocol, .. }
+            | AcceptorState::BasicSettingsWaitInitial { protocol, .. }
+            | AcceptorState::BasicSettingsSendResponse { protocol, .. }
+            | AcceptorState::ChannelConnection { protocol, .. }
+            | AcceptorState::RdpSecurityCommencement { protocol, .. }
+            | AcceptorState::SecureSettingsExchange { protocol, .. } => Some(protocol),
+            _ => None,
+        }
+    }
+
     /// # Panics
     ///
     /// Panics if state is not [AcceptorState::SecurityUpgrade].
@@ -146,6 +191,27 @@ impl Acceptor {
         assert_eq!(res, Written::Nothing);
     }
 
+    /// Returns the credentials delegated by the client during CredSSP
+    ///
+    /// Available once the CredSSP sequence is done, they are the ones the client authenticated with.
+    pub fn delegated_credentials(&self) -> Option<&Credentials> {
+        self.delegated_creds.as_ref()
+    }
+
+    /// Core data sent by the client during the basic settings exchange
+    ///
+    /// Holds the keyboard layout, type and IME of the client, among other settings.
+    pub fn client_core_data(&self) -> Option<&gcc::ClientCoreData> {
+        self.client_core_data.as_ref()
+    }
+
+    /// Performance flags sent by the client during the secure settings exchange
+    ///
+    /// Tells which visual effects the server should disable, e.g. the wallpaper on slow links.
+    pub fn performance_flags(&self) -> Option<PerformanceFlags> {
+        self.performance_flags
+    }
+
     pub fn get_result(&mut self) -> Option<AcceptorResult> {
         match mem::take(&mut self.state) {
             AcceptorState::Accepted {
@@ -177,10 +243,26 @@ pub enum AcceptorState {
     InitiationSendConfirm {
         requested_protocol: SecurityProtocol,
     },
+    /// None of the security protocols requested by the client is allowed, the failure was sent
+    NegotiationFailed {
+        code: nego::FailureCode,
+    },
     SecurityUpgrade {
         requested_protocol: SecurityProtocol,
         protocol: SecurityProtocol,
     },
+    RdstlsSendCapabilities {
+        requested_protocol: SecurityProtocol,
+        protocol: SecurityProtocol,
+    },
+    RdstlsWaitAuthRequest {
+        requested_protocol: SecurityProtocol,
+        protocol: SecurityProtocol,
+    },
+    /// The client failed to authenticate with RDSTLS, the failure was sent
+    RdstlsFailed {
+        code: rdstls::RdstlsResultCode,
+    },
     Credssp {
         requested_protocol: SecurityProtocol,
         protocol: SecurityProtocol,
@@ -243,7 +325,11 @@ impl State for AcceptorState {
             Self::Consumed => "Consumed",
             Self::InitiationWaitRequest => "InitiationWaitRequest",
             Self::InitiationSendConfirm { .. } => "InitiationSendConfirm",
+            Self::NegotiationFailed { .. } => "NegotiationFailed",
             Self::SecurityUpgrade { .. } => "SecurityUpgrade",
+            Self::RdstlsSendCapabilities { .. } => "RdstlsSendCapabilities",
+            Self::RdstlsWaitAuthRequest { .. } => "RdstlsWaitAuthRequest",
+            Self::RdstlsFailed { .. } => "RdstlsFailed",
             Self::Credssp { .. } => "Credssp",
             Self::BasicSettingsWaitInitial { .. } => "BasicSettingsWaitInitial",
             Self::BasicSettingsSendResponse { .. } => "BasicSettingsSendResponse",
@@ -274,7 +360,11 @@ impl Sequence for Acceptor {
             AcceptorState::Consumed => None,
             AcceptorState::InitiationWaitRequest => Some(&pdu::X224_HINT),
             AcceptorState::InitiationSendConfirm { .. } => None,
+            AcceptorState::NegotiationFailed { .. } => None,
             AcceptorState::SecurityUpgrade { .. } => None,
+            AcceptorState::RdstlsSendCapabilities { .. } => None,
+            AcceptorState::RdstlsWaitAuthRequest { .. } => Some(&rdstls::RDSTLS_HINT),
+            AcceptorState::RdstlsFailed { .. } => None,
             AcceptorState::Credssp { .. } => None,
             AcceptorState::BasicSettingsWaitInitial { .. } => Some(&pdu::X224_HINT),
             AcceptorState::BasicSettingsSendResponse { .. } => None,
@@ -315,7 +405,10 @@ impl Sequence for Acceptor {
 
             AcceptorState::InitiationSendConfirm { requested_protocol } => {
                 let protocols = requested_protocol & self.security;
-                let protocol = if protocols.intersects(SecurityProtocol::HYBRID_EX) {
+                // Clients only request RDSTLS when they have the credentials of a server redirection.
+                let protocol = if protocols.intersects(SecurityProtocol::RDSTLS) {
+                    SecurityProtocol::RDSTLS
+                } else if protocols.intersects(SecurityProtocol::HYBRID_EX) {
                     SecurityProtocol::HYBRID_EX
                 } else if protocols.intersects(SecurityProtocol::HYBRID) {
                     SecurityProtocol::HYBRID
@@ -324,7 +417,21 @@ impl Sequence for Acceptor {
                 } else if self.security.is_empty() {
                     SecurityProtocol::empty()
                 } else {
-                    return Err(ConnectorError::general("failed to negotiate security protocol"));
+                    // A server not allowing TLS alone requires the Network Level Authentication.
+                    let code = if self.security.intersects(SecurityProtocol::SSL) {
+                        nego::FailureCode::SSL_REQUIRED_BY_SERVER
+                    } else {
+                        nego::FailureCode::HYBRID_REQUIRED_BY_SERVER
+                    };
+                    let connection_confirm = nego::ConnectionConfirm::Failure { code };
+
+                    debug!(message = ?connection_confirm, "Send");
+
+                    let written =
+                        ironrdp_core::encode_buf(&X224(connection_confirm), output).map_err(ConnectorError::encode)?;
+
+                    self.state = AcceptorState::NegotiationFailed { code };
+                    return Written::from_size(written);
                 };
                 let connection_confirm = nego::ConnectionConfirm::Response {
                     flags: nego::ResponseFlags::empty(),
@@ -345,12 +452,24 @@ impl Sequence for Acceptor {
                 )
             }
 
+            AcceptorState::NegotiationFailed { code } => {
+                return Err(ConnectorError::new(
+                    "negotiation failure",
+                    ConnectorErrorKind::Negotiation(NegotiationFailure::from(code)),
+                ));
+            }
+
             AcceptorState::SecurityUpgrade {
                 requested_protocol,
                 protocol,
             } => {
                 debug!(?requested_protocol);
-                let next_state = if protocol.intersects(SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX) {
+                let next_state = if protocol.contains(SecurityProtocol::RDSTLS) {
+                    AcceptorState::RdstlsSendCapabilities {
+                        requested_protocol,
+                        protocol,
+                    }
+                } else if protocol.intersects(SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX) {
                     AcceptorState::Credssp {
                         requested_protocol,
                         protocol,
@@ -364,6 +483,65 @@ impl Sequence for Acceptor {
                 (Written::Nothing, next_state)
             }
 
+            AcceptorState::RdstlsSendCapabilities {
+                requested_protocol,
+                protocol,
+            } => {
+                let capabilities = rdstls::RdstlsCapabilities::default();
+
+                debug!(message = ?capabilities, "Send");
+
+                let written = ironrdp_core::encode_buf(&capabilities, output).map_err(ConnectorError::encode)?;
+
+                (
+                    Written::from_size(written)?,
+                    AcceptorState::RdstlsWaitAuthRequest {
+                        requested_protocol,
+                        protocol,
+                    },
+                )
+            }
+
+            AcceptorState::RdstlsWaitAuthRequest {
+                requested_protocol,
+                protocol,
+            } => {
+                let auth_request =
+                    decode::<rdstls::RdstlsAuthenticationRequest>(input).map_err(ConnectorError::decode)?;
+
+                debug!(message = ?auth_request, "Received");
+
+                let result_code = self
+                    .rdstls_authenticator
+                    .as_ref()
+                    .map_or(rdstls::RdstlsResultCode::ACCESS_DENIED, |authenticator| {
+                        authenticator.authenticate(&auth_request)
+                    });
+
+                let auth_response = rdstls::RdstlsAuthenticationResponse { result_code };
+
+                debug!(message = ?auth_response, "Send");
+
+                let written = ironrdp_core::encode_buf(&auth_response, output).map_err(ConnectorError::encode)?;
+
+                if result_code != rdstls::RdstlsResultCode::SUCCESS {
+                    self.state = AcceptorState::RdstlsFailed { code: result_code };
+                    return Written::from_size(written);
+                }
+
+                (
+                    Written::from_size(written)?,
+                    AcceptorState::BasicSettingsWaitInitial {
+                        requested_protocol,
+                        protocol,
+                    },
+                )
+            }
+
+            AcceptorState::RdstlsFailed { code } => {
+                return Err(reason_err!("RDSTLS", "authentication failed: {code}"));
+            }
+
             AcceptorState::Credssp {
                 requested_protocol,
                 protocol,
@@ -405,6 +583,8 @@ impl Sequence for Acceptor {
                     })
                     .unwrap_or_default();
 
+                self.client_core_data = Some(gcc_blocks.core);
+
                 #[expect(clippy::arithmetic_side_effects)] // IO channel ID is not big enough for overflowing.
                 let channels = joined
                     .into_iter()
@@ -527,7 +707,12 @@ impl Sequence for Acceptor {
 
                 debug!(message = ?client_info, "Received");
 
-                if !protocol.intersects(SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX) {
+                self.performance_flags = client_info.client_info.extra_info.optional_data.performance_flags();
+
+                // The client is already authenticated with NLA or RDSTLS.
+                if !protocol
+                    .intersects(SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX | SecurityProtocol::RDSTLS)
+                {
                     let creds = client_info.client_info.credentials;
 
                     if self.creds.as_ref() != Some(&creds) {
@@ -9,6 +9,7 @@ use ironrdp_connector::{
     custom_err, general_err, ConnectorError, ConnectorErrorKind, ConnectorResult, ServerName, Written,
 };
 use ironrdp_core::{other_err, WriteBuf};
+use ironrdp_pdu::rdp::client_info::Credentials;
 use ironrdp_pdu::PduHint;
 use tracing::debug;
 
@@ -41,6 +42,8 @@ pub type CredsspProcessGenerator<'a> =
 pub struct CredsspSequence<'a> {
     server: CredSspServer<CredentialsProxyImpl<'a>>,
     state: CredsspState,
+    identity: &'a AuthIdentity,
+    delegated_credentials: Option<Credentials>,
 }
 
 #[derive(Debug)]
@@ -69,6 +72,17 @@ impl CredentialsProxy for CredentialsProxyImpl<'_> {
     }
 }
 
+/// User and domain names are compared case-insensitively, as Windows does
+fn is_same_user(lhs: &Username, rhs: &Username) -> bool {
+    let same_domain = match (lhs.domain_name(), rhs.domain_name()) {
+        (Some(lhs), Some(rhs)) => lhs.eq_ignore_ascii_case(rhs),
+        (None, None) => true,
+        _ => false,
+    };
+
+    same_domain && lhs.account_name().eq_ignore_ascii_case(rhs.account_name())
+}
+
 pub(crate) async fn resolve_generator(
     generator: &mut CredsspProcessGenerator<'_>,
     network_client: &mut impl NetworkClient,
@@ -127,6 +141,8 @@ impl<'a> CredsspSequence<'a> {
         let sequence = Self {
             server,
             state: CredsspState::Ongoing,
+            identity: creds,
+            delegated_credentials: None,
         };
 
         Ok(sequence)
@@ -146,6 +162,14 @@ impl<'a> CredsspSequence<'a> {
         }
     }
 
+    /// Returns the credentials delegated by the client, once the sequence is finished
+    ///
+    /// The client delegates them in the last TS request, encrypted with the security context established during the
+    /// authentication.
+    pub fn delegated_credentials(&self) -> Option<&Credentials> {
+        self.delegated_credentials.as_ref()
+    }
+
     pub fn process_ts_request(&mut self, request: TsRequest) -> CredsspProcessGenerator<'_> {
         self.server.process(request)
     }
@@ -157,7 +181,24 @@ impl<'a> CredsspSequence<'a> {
     ) -> ConnectorResult<Written> {
         let (ts_request, next_state) = match result {
             Ok(ServerState::ReplyNeeded(ts_request)) => (Some(ts_request), CredsspState::Ongoing),
-            Ok(ServerState::Finished(_id)) => (None, CredsspState::Finished),
+            Ok(ServerState::Finished(delegated)) => {
+                // The client may only delegate the credentials of the user it authenticated as.
+                if !is_same_user(&delegated.username, &self.identity.username) {
+                    self.state = CredsspState::ServerError(sspi::Error::new(
+                        sspi::ErrorKind::LogonDenied,
+                        "delegated credentials of another user",
+                    ));
+                    return Err(general_err!("delegated credentials don't match the authenticated user"));
+                }
+
+                self.delegated_credentials = Some(Credentials {
+                    username: delegated.username.account_name().to_owned(),
+                    password: delegated.password.as_ref().clone(),
+                    domain: delegated.username.domain_name().map(str::to_owned),
+                });
+
+                (None, CredsspState::Finished)
+            }
             Err(err) => (
                 err.ts_request.map(|ts_request| *ts_request),
                 CredsspState::ServerError(err.error),
@@ -16,9 +16,10 @@ mod util;
 
 pub use ironrdp_connector::DesktopSize;
 use ironrdp_pdu::nego;
+use ironrdp_pdu::rdp::client_info::Credentials;
 
 pub use self::channel_connection::{ChannelConnectionSequence, ChannelConnectionState};
-pub use self::connection::{Acceptor, AcceptorResult, AcceptorState};
+pub use self::connection::{Acceptor, AcceptorResult, AcceptorState, RdstlsAuthenticator};
 pub use self::finalization::{FinalizationSequence, FinalizationState};
 use crate::credssp::resolve_generator;
 
@@ -148,9 +149,10 @@ where
             .map_err(|e| ironrdp_connector::custom_err!("write all", e))?;
     }
 
-    result?;
+    let delegated_creds = result?;
 
     acceptor.mark_credssp_as_done();
+    acceptor.delegated_creds = delegated_creds;
 
     return Ok(());
 
@@ -162,7 +164,7 @@ where
         client_computer_name: ServerName,
         public_key: Vec<u8>,
         kerberos_config: Option<KerberosServerConfig>,
-    ) -> ConnectorResult<()>
+    ) -> ConnectorResult<Option<Credentials>>
     where
         S: FramedRead + FramedWrite,
         N: NetworkClient,
@@ -220,6 +222,6 @@ where
             }
         }
 
-        Ok(())
+        Ok(sequence.delegated_credentials().cloned())
     }
 }
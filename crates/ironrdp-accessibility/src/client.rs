@@ -0,0 +1,108 @@
+use ironrdp_core::{impl_as_any, Decode as _, ReadCursor};
+use ironrdp_dvc::{DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_pdu::{decode_err, PduResult};
+use tracing::debug;
+
+use crate::pdu::{AccessibilityPdu, FocusPdu, Version};
+use crate::CHANNEL_NAME;
+
+/// Consumer of the accessibility events of the remote session, typically forwarding them to a screen reader
+pub trait AccessibilityHandler: Send + core::fmt::Debug {
+    /// A control received the keyboard focus
+    fn focus_changed(&mut self, focus: &FocusPdu);
+
+    /// No control has the keyboard focus anymore
+    fn focus_cleared(&mut self) {}
+
+    /// The text caret moved
+    fn caret_moved(&mut self, bounds: &InclusiveRectangle);
+
+    /// The text caret is not displayed anymore
+    fn caret_hidden(&mut self) {}
+
+    /// The channel was closed, the focus and caret are unknown
+    fn close(&mut self) {}
+}
+
+/// A client for the Accessibility Virtual Channel
+#[derive(Debug)]
+pub struct AccessibilityClient {
+    handler: Box<dyn AccessibilityHandler>,
+    focus: Option<FocusPdu>,
+    caret: Option<InclusiveRectangle>,
+}
+
+impl AccessibilityClient {
+    pub fn new(handler: Box<dyn AccessibilityHandler>) -> Self {
+        Self {
+            handler,
+            focus: None,
+            caret: None,
+        }
+    }
+
+    /// The focused control of the remote session
+    pub fn focus(&self) -> Option<&FocusPdu> {
+        self.focus.as_ref()
+    }
+
+    /// Bounds of the text caret of the remote session, when displayed
+    pub fn caret(&self) -> Option<&InclusiveRectangle> {
+        self.caret.as_ref()
+    }
+}
+
+impl_as_any!(AccessibilityClient);
+
+impl DvcProcessor for AccessibilityClient {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        // The server sends its version first.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu = AccessibilityPdu::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        let messages: Vec<DvcMessage> = match pdu {
+            AccessibilityPdu::Version(version) => {
+                vec![Box::new(AccessibilityPdu::Version(version.min(Version::V1)))]
+            }
+            AccessibilityPdu::FocusChanged(focus) => {
+                self.handler.focus_changed(&focus);
+                self.focus = Some(focus);
+                Vec::new()
+            }
+            AccessibilityPdu::FocusCleared => {
+                self.focus = None;
+                self.handler.focus_cleared();
+                Vec::new()
+            }
+            AccessibilityPdu::CaretMoved(pdu) => {
+                self.handler.caret_moved(&pdu.bounds);
+                self.caret = Some(pdu.bounds);
+                Vec::new()
+            }
+            AccessibilityPdu::CaretHidden => {
+                self.caret = None;
+                self.handler.caret_hidden();
+                Vec::new()
+            }
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        self.focus = None;
+        self.caret = None;
+        self.handler.close();
+    }
+}
+
+impl DvcClientProcessor for AccessibilityClient {}
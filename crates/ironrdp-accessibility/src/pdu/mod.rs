@@ -0,0 +1,307 @@
+//! Accessibility channel PDUs, see the README of the crate for the description of the protocol.
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+use ironrdp_dvc::DvcEncode;
+use ironrdp_pdu::geometry::InclusiveRectangle;
+
+const MSG_VERSION: u8 = 0x01;
+const MSG_FOCUS_CHANGED: u8 = 0x02;
+const MSG_FOCUS_CLEARED: u8 = 0x03;
+const MSG_CARET_MOVED: u8 = 0x04;
+const MSG_CARET_HIDDEN: u8 = 0x05;
+
+/// Accessibility channel message
+///
+/// Only the [`Version`](AccessibilityPdu::Version) message is sent by the client, the others are sent by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessibilityPdu {
+    Version(Version),
+    /// A control of the remote session received the keyboard focus
+    FocusChanged(FocusPdu),
+    /// No control has the keyboard focus anymore
+    FocusCleared,
+    /// The text caret moved
+    CaretMoved(CaretPdu),
+    /// The text caret is not displayed anymore
+    CaretHidden,
+}
+
+impl AccessibilityPdu {
+    const NAME: &'static str = "ACCESSIBILITY_PDU";
+
+    const FIXED_PART_SIZE: usize = 1 /* MessageType */;
+
+    fn message_type(&self) -> u8 {
+        match self {
+            AccessibilityPdu::Version(_) => MSG_VERSION,
+            AccessibilityPdu::FocusChanged(_) => MSG_FOCUS_CHANGED,
+            AccessibilityPdu::FocusCleared => MSG_FOCUS_CLEARED,
+            AccessibilityPdu::CaretMoved(_) => MSG_CARET_MOVED,
+            AccessibilityPdu::CaretHidden => MSG_CARET_HIDDEN,
+        }
+    }
+}
+
+impl Encode for AccessibilityPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.message_type());
+
+        match self {
+            AccessibilityPdu::Version(version) => version.encode(dst),
+            AccessibilityPdu::FocusChanged(pdu) => pdu.encode(dst),
+            AccessibilityPdu::CaretMoved(pdu) => pdu.encode(dst),
+            AccessibilityPdu::FocusCleared | AccessibilityPdu::CaretHidden => Ok(()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            .checked_add(match self {
+                AccessibilityPdu::Version(version) => version.size(),
+                AccessibilityPdu::FocusChanged(pdu) => pdu.size(),
+                AccessibilityPdu::CaretMoved(pdu) => pdu.size(),
+                AccessibilityPdu::FocusCleared | AccessibilityPdu::CaretHidden => 0,
+            })
+            .expect("never overflow")
+    }
+}
+
+impl DvcEncode for AccessibilityPdu {}
+
+impl<'de> Decode<'de> for AccessibilityPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        match src.read_u8() {
+            MSG_VERSION => Ok(Self::Version(Version::decode(src)?)),
+            MSG_FOCUS_CHANGED => Ok(Self::FocusChanged(FocusPdu::decode(src)?)),
+            MSG_FOCUS_CLEARED => Ok(Self::FocusCleared),
+            MSG_CARET_MOVED => Ok(Self::CaretMoved(CaretPdu::decode(src)?)),
+            MSG_CARET_HIDDEN => Ok(Self::CaretHidden),
+            _ => Err(invalid_field_err!("MessageType", "unknown accessibility message")),
+        }
+    }
+}
+
+/// Version of the protocol
+///
+/// The server sends its version first, and the client replies with the lowest of both versions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u32);
+
+impl Version {
+    const NAME: &'static str = "ACCESSIBILITY_VERSION";
+
+    const FIXED_PART_SIZE: usize = 4 /* Version */;
+
+    pub const V1: Self = Self(0x0000_0001);
+}
+
+impl Encode for Version {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.0);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for Version {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self(src.read_u32()))
+    }
+}
+
+/// Role of a control, as exposed to assistive technologies
+///
+/// Servers may send roles unknown to the client, which are treated as [`ControlRole::UNKNOWN`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ControlRole(pub u16);
+
+impl ControlRole {
+    pub const UNKNOWN: Self = Self(0x0000);
+    pub const WINDOW: Self = Self(0x0001);
+    pub const DIALOG: Self = Self(0x0002);
+    pub const BUTTON: Self = Self(0x0003);
+    pub const CHECK_BOX: Self = Self(0x0004);
+    pub const RADIO_BUTTON: Self = Self(0x0005);
+    pub const COMBO_BOX: Self = Self(0x0006);
+    /// Editable text field
+    pub const EDIT: Self = Self(0x0007);
+    /// Read-only or rich text content
+    pub const DOCUMENT: Self = Self(0x0008);
+    pub const LINK: Self = Self(0x0009);
+    pub const LIST: Self = Self(0x000A);
+    pub const LIST_ITEM: Self = Self(0x000B);
+    pub const MENU: Self = Self(0x000C);
+    pub const MENU_ITEM: Self = Self(0x000D);
+    pub const TAB: Self = Self(0x000E);
+    pub const TAB_ITEM: Self = Self(0x000F);
+    pub const TREE: Self = Self(0x0010);
+    pub const TREE_ITEM: Self = Self(0x0011);
+    pub const TABLE: Self = Self(0x0012);
+    pub const CELL: Self = Self(0x0013);
+    /// Static text
+    pub const TEXT: Self = Self(0x0014);
+    pub const IMAGE: Self = Self(0x0015);
+    pub const SLIDER: Self = Self(0x0016);
+    pub const PROGRESS_BAR: Self = Self(0x0017);
+}
+
+bitflags! {
+    /// State of a control, as exposed to assistive technologies
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    pub struct ControlState: u32 {
+        const DISABLED = 0x0000_0001;
+        const READ_ONLY = 0x0000_0002;
+        const CHECKED = 0x0000_0004;
+        /// A check box in the indeterminate state
+        const MIXED = 0x0000_0008;
+        const SELECTED = 0x0000_0010;
+        const EXPANDED = 0x0000_0020;
+        const COLLAPSED = 0x0000_0040;
+        /// The value of the control is a secret, such as a password, and is not sent
+        const PROTECTED = 0x0000_0080;
+        const REQUIRED = 0x0000_0100;
+        const MULTI_LINE = 0x0000_0200;
+        const _ = !0;
+    }
+}
+
+/// The focused control
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusPdu {
+    pub role: ControlRole,
+    pub state: ControlState,
+    /// Bounds of the control, in desktop coordinates
+    pub bounds: InclusiveRectangle,
+    /// Accessible name of the control, such as the label of a button
+    pub name: String,
+    /// Value of the control, such as the text of an edit field, empty for [`ControlState::PROTECTED`] controls
+    pub value: String,
+}
+
+impl FocusPdu {
+    const NAME: &'static str = "ACCESSIBILITY_FOCUS_CHANGED";
+
+    const FIXED_PART_SIZE: usize = 2 /* Role */ + 4 /* State */ + InclusiveRectangle::FIXED_PART_SIZE + 2 /* cbName */ + 2 /* cbValue */;
+}
+
+impl Encode for FocusPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(self.role.0);
+        dst.write_u32(self.state.bits());
+        self.bounds.encode(dst)?;
+        dst.write_u16(cast_length!("cbName", self.name.len())?);
+        dst.write_slice(self.name.as_bytes());
+        dst.write_u16(cast_length!("cbValue", self.value.len())?);
+        dst.write_slice(self.value.as_bytes());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            .checked_add(self.name.len())
+            .and_then(|size| size.checked_add(self.value.len()))
+            .expect("never overflow")
+    }
+}
+
+impl<'de> Decode<'de> for FocusPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let role = ControlRole(src.read_u16());
+        let state = ControlState::from_bits_retain(src.read_u32());
+        let bounds = InclusiveRectangle::decode(src)?;
+        let name = decode_string(src, "cbName")?;
+        let value = decode_string(src, "cbValue")?;
+
+        Ok(Self {
+            role,
+            state,
+            bounds,
+            name,
+            value,
+        })
+    }
+}
+
+/// Position of the text caret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaretPdu {
+    /// Bounds of the caret, in desktop coordinates
+    pub bounds: InclusiveRectangle,
+}
+
+impl CaretPdu {
+    const NAME: &'static str = "ACCESSIBILITY_CARET_MOVED";
+
+    const FIXED_PART_SIZE: usize = InclusiveRectangle::FIXED_PART_SIZE;
+}
+
+impl Encode for CaretPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        self.bounds.encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for CaretPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            bounds: InclusiveRectangle::decode(src)?,
+        })
+    }
+}
+
+/// Decodes a UTF-8 string prefixed by its length in bytes
+fn decode_string(src: &mut ReadCursor<'_>, field: &'static str) -> DecodeResult<String> {
+    ensure_size!(in: src, size: 2);
+    let length = usize::from(src.read_u16());
+
+    ensure_size!(in: src, size: length);
+    let string =
+        core::str::from_utf8(src.read_slice(length)).map_err(|_| invalid_field_err!(field, "invalid UTF-8"))?;
+
+    Ok(string.to_owned())
+}
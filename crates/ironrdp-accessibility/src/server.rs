@@ -0,0 +1,131 @@
+use ironrdp_core::{decode, impl_as_any};
+use ironrdp_dvc::{encode_dvc_messages, DvcMessage, DvcProcessor, DvcServerProcessor};
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tracing::{debug, warn};
+
+use crate::pdu::{AccessibilityPdu, CaretPdu, FocusPdu, Version};
+use crate::CHANNEL_NAME;
+
+/// A server for the Accessibility Virtual Channel
+///
+/// The server produces the accessibility events reported by the agent running in the remote session. The events are
+/// encoded as [`SvcMessage`]s, to send on the `DRDYNVC` static channel.
+///
+/// The last focus and caret are remembered, and sent again when the client reopens the channel.
+#[derive(Debug, Default)]
+pub struct AccessibilityServer {
+    channel_id: Option<u32>,
+    /// Version negotiated with the client, set once the client replied
+    version: Option<Version>,
+    focus: Option<FocusPdu>,
+    caret: Option<InclusiveRectangle>,
+}
+
+impl AccessibilityServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the client replied to the version, and events can be sent
+    pub fn is_ready(&self) -> bool {
+        self.channel_id.is_some() && self.version.is_some()
+    }
+
+    /// Wraps a focus change as [`SvcMessage`]s
+    pub fn encode_focus_changed(&mut self, focus: FocusPdu) -> PduResult<Vec<SvcMessage>> {
+        self.focus = Some(focus.clone());
+        self.encode(AccessibilityPdu::FocusChanged(focus))
+    }
+
+    /// Wraps the loss of the focus as [`SvcMessage`]s
+    pub fn encode_focus_cleared(&mut self) -> PduResult<Vec<SvcMessage>> {
+        self.focus = None;
+        self.encode(AccessibilityPdu::FocusCleared)
+    }
+
+    /// Wraps a caret move as [`SvcMessage`]s
+    pub fn encode_caret_moved(&mut self, bounds: InclusiveRectangle) -> PduResult<Vec<SvcMessage>> {
+        self.caret = Some(bounds.clone());
+        self.encode(AccessibilityPdu::CaretMoved(CaretPdu { bounds }))
+    }
+
+    /// Wraps the hiding of the caret as [`SvcMessage`]s
+    pub fn encode_caret_hidden(&mut self) -> PduResult<Vec<SvcMessage>> {
+        self.caret = None;
+        self.encode(AccessibilityPdu::CaretHidden)
+    }
+
+    fn encode(&self, pdu: AccessibilityPdu) -> PduResult<Vec<SvcMessage>> {
+        let channel_id = self
+            .channel_id
+            .ok_or_else(|| pdu_other_err!("accessibility channel not opened"))?;
+
+        if self.version.is_none() {
+            return Err(pdu_other_err!("invalid state, accessibility client not ready"));
+        }
+
+        let messages: Vec<DvcMessage> = vec![Box::new(pdu)];
+
+        encode_dvc_messages(channel_id, messages, ChannelFlags::empty()).map_err(|e| encode_err!(e))
+    }
+
+    /// Events describing the current focus and caret, sent once the client is ready
+    fn current_state(&self) -> Vec<DvcMessage> {
+        let mut messages: Vec<DvcMessage> = Vec::new();
+
+        if let Some(focus) = &self.focus {
+            messages.push(Box::new(AccessibilityPdu::FocusChanged(focus.clone())));
+        }
+
+        if let Some(bounds) = &self.caret {
+            messages.push(Box::new(AccessibilityPdu::CaretMoved(CaretPdu {
+                bounds: bounds.clone(),
+            })));
+        }
+
+        messages
+    }
+}
+
+impl_as_any!(AccessibilityServer);
+
+impl DvcProcessor for AccessibilityServer {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.channel_id = Some(channel_id);
+        self.version = None;
+
+        Ok(vec![Box::new(AccessibilityPdu::Version(Version::V1))])
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: AccessibilityPdu = decode(payload).map_err(|e| decode_err!(e))?;
+
+        let messages = match pdu {
+            AccessibilityPdu::Version(version) if self.version.is_none() => {
+                debug!(?version, "Accessibility client version");
+                self.version = Some(version.min(Version::V1));
+
+                self.current_state()
+            }
+            pdu => {
+                warn!(?pdu, "Unexpected accessibility PDU");
+                Vec::new()
+            }
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        self.channel_id = None;
+        self.version = None;
+    }
+}
+
+impl DvcServerProcessor for AccessibilityServer {}
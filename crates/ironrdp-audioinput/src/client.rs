@@ -0,0 +1,175 @@
+use ironrdp_core::{impl_as_any, Decode as _, ReadCursor};
+use ironrdp_dvc::{encode_dvc_messages, DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tracing::{debug, warn};
+
+use crate::pdu::{
+    AudioFormat, AudioInputPdu, DataPdu, FormatChangePdu, FormatsPdu, OpenPdu, OpenReplyPdu, Version, E_FAIL, S_OK,
+};
+use crate::CHANNEL_NAME;
+
+/// Microphone of the client, redirected to the server
+pub trait AudioInputClientHandler: Send + core::fmt::Debug {
+    /// Whether the microphone can record in a format accepted by the server
+    fn is_supported(&self, format: &AudioFormat) -> bool;
+
+    /// The server requested to start recording
+    ///
+    /// The recorded audio is sent with [`AudioInputClient::encode_data`], in packets of `frames_per_packet` audio
+    /// frames. Returns whether the recording started.
+    fn open(&mut self, format: &AudioFormat, frames_per_packet: u32) -> bool;
+
+    /// The server requested to record in another format
+    fn format_change(&mut self, format: &AudioFormat);
+
+    /// The channel was closed, the recording must stop
+    fn close(&mut self);
+}
+
+/// A client for the Audio Input Virtual Channel
+#[derive(Debug)]
+pub struct AudioInputClient {
+    handler: Box<dyn AudioInputClientHandler>,
+    channel_id: Option<u32>,
+    /// Formats announced to the server, in the order of their indices
+    formats: Vec<AudioFormat>,
+    /// Index of the format of the recording, set while recording
+    format: Option<usize>,
+}
+
+impl AudioInputClient {
+    pub fn new(handler: Box<dyn AudioInputClientHandler>) -> Self {
+        Self {
+            handler,
+            channel_id: None,
+            formats: Vec::new(),
+            format: None,
+        }
+    }
+
+    /// Whether the server requested to record, and the recording started
+    pub fn is_recording(&self) -> bool {
+        self.format.is_some()
+    }
+
+    /// Format of the recording
+    pub fn format(&self) -> Option<&AudioFormat> {
+        self.format.and_then(|index| self.formats.get(index))
+    }
+
+    /// Wraps recorded audio, in the current format, as [`SvcMessage`]s
+    pub fn encode_data(&self, data: Vec<u8>) -> PduResult<Vec<SvcMessage>> {
+        let channel_id = self
+            .channel_id
+            .ok_or_else(|| pdu_other_err!("audio input channel not opened"))?;
+
+        if !self.is_recording() {
+            return Err(pdu_other_err!("invalid state, audio input not recording"));
+        }
+
+        let messages: Vec<DvcMessage> = vec![
+            Box::new(AudioInputPdu::IncomingData),
+            Box::new(AudioInputPdu::Data(DataPdu { data })),
+        ];
+
+        encode_dvc_messages(channel_id, messages, ChannelFlags::empty()).map_err(|e| encode_err!(e))
+    }
+
+    fn open(&mut self, pdu: OpenPdu) -> Vec<DvcMessage> {
+        let format = usize::try_from(pdu.initial_format)
+            .ok()
+            .filter(|index| *index < self.formats.len());
+
+        let started = match format {
+            Some(index) => self.handler.open(&self.formats[index], pdu.frames_per_packet),
+            None => {
+                warn!(?pdu, "Invalid initial audio input format");
+                false
+            }
+        };
+
+        if !started {
+            self.format = None;
+            return vec![Box::new(AudioInputPdu::OpenReply(OpenReplyPdu { result: E_FAIL }))];
+        }
+
+        self.format = format;
+
+        vec![
+            Box::new(AudioInputPdu::FormatChange(FormatChangePdu {
+                new_format: pdu.initial_format,
+            })),
+            Box::new(AudioInputPdu::OpenReply(OpenReplyPdu { result: S_OK })),
+        ]
+    }
+
+    fn format_change(&mut self, pdu: FormatChangePdu) -> Vec<DvcMessage> {
+        let Some(index) = usize::try_from(pdu.new_format)
+            .ok()
+            .filter(|index| *index < self.formats.len())
+        else {
+            warn!(?pdu, "Invalid audio input format");
+            return Vec::new();
+        };
+
+        self.handler.format_change(&self.formats[index]);
+        self.format = Some(index);
+
+        vec![Box::new(AudioInputPdu::FormatChange(pdu))]
+    }
+}
+
+impl_as_any!(AudioInputClient);
+
+impl DvcProcessor for AudioInputClient {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.channel_id = Some(channel_id);
+
+        // The server sends its version first.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu = AudioInputPdu::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        let messages: Vec<DvcMessage> = match pdu {
+            AudioInputPdu::Version(version) => {
+                vec![Box::new(AudioInputPdu::Version(version.min(Version::V2)))]
+            }
+            AudioInputPdu::Formats(FormatsPdu { formats }) => {
+                self.formats = formats
+                    .into_iter()
+                    .filter(|format| self.handler.is_supported(format))
+                    .collect();
+                self.format = None;
+
+                vec![Box::new(AudioInputPdu::Formats(FormatsPdu {
+                    formats: self.formats.clone(),
+                }))]
+            }
+            AudioInputPdu::Open(pdu) => self.open(pdu),
+            AudioInputPdu::FormatChange(pdu) => self.format_change(pdu),
+            pdu => {
+                warn!(?pdu, "Unexpected audio input PDU");
+                Vec::new()
+            }
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        self.channel_id = None;
+        if self.format.take().is_some() {
+            self.handler.close();
+        }
+    }
+}
+
+impl DvcClientProcessor for AudioInputClient {}
@@ -0,0 +1,406 @@
+//! Audio Input Redirection Virtual Channel Extension PDUs  [MS-RDPEAI][1] implementation.
+//!
+//! [1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpeai
+
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+use ironrdp_dvc::DvcEncode;
+pub use ironrdp_rdpsnd::pdu::{AudioFormat, WaveFormat};
+
+const MSG_SNDIN_VERSION: u8 = 0x01;
+const MSG_SNDIN_FORMATS: u8 = 0x02;
+const MSG_SNDIN_OPEN: u8 = 0x03;
+const MSG_SNDIN_OPEN_REPLY: u8 = 0x04;
+const MSG_SNDIN_DATA_INCOMING: u8 = 0x05;
+const MSG_SNDIN_DATA: u8 = 0x06;
+const MSG_SNDIN_FORMATCHANGE: u8 = 0x07;
+
+/// HRESULT of an [`OpenReplyPdu`] when the recording started
+pub const S_OK: u32 = 0x0000_0000;
+/// HRESULT of an [`OpenReplyPdu`] when the recording failed to start (E_FAIL)
+pub const E_FAIL: u32 = 0x8000_4005;
+
+/// Audio input channel message, sent by both the client and the server
+///
+/// The direction of the messages is given in their documentation. The [`Version`](AudioInputPdu::Version),
+/// [`Formats`](AudioInputPdu::Formats) and [`FormatChange`](AudioInputPdu::FormatChange) messages are sent in both
+/// directions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioInputPdu {
+    Version(Version),
+    Formats(FormatsPdu),
+    /// Server request to start recording
+    Open(OpenPdu),
+    /// Client reply to [`AudioInputPdu::Open`]
+    OpenReply(OpenReplyPdu),
+    /// Client notification, sent before each [`AudioInputPdu::Data`]
+    IncomingData,
+    /// Client recorded audio
+    Data(DataPdu),
+    FormatChange(FormatChangePdu),
+}
+
+impl AudioInputPdu {
+    const NAME: &'static str = "SNDIN_PDU";
+
+    const FIXED_PART_SIZE: usize = 1 /* MessageId */;
+
+    fn message_id(&self) -> u8 {
+        match self {
+            AudioInputPdu::Version(_) => MSG_SNDIN_VERSION,
+            AudioInputPdu::Formats(_) => MSG_SNDIN_FORMATS,
+            AudioInputPdu::Open(_) => MSG_SNDIN_OPEN,
+            AudioInputPdu::OpenReply(_) => MSG_SNDIN_OPEN_REPLY,
+            AudioInputPdu::IncomingData => MSG_SNDIN_DATA_INCOMING,
+            AudioInputPdu::Data(_) => MSG_SNDIN_DATA,
+            AudioInputPdu::FormatChange(_) => MSG_SNDIN_FORMATCHANGE,
+        }
+    }
+}
+
+impl Encode for AudioInputPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.message_id());
+
+        match self {
+            AudioInputPdu::Version(version) => version.encode(dst),
+            AudioInputPdu::Formats(pdu) => pdu.encode(dst),
+            AudioInputPdu::Open(pdu) => pdu.encode(dst),
+            AudioInputPdu::OpenReply(pdu) => pdu.encode(dst),
+            AudioInputPdu::IncomingData => Ok(()),
+            AudioInputPdu::Data(pdu) => pdu.encode(dst),
+            AudioInputPdu::FormatChange(pdu) => pdu.encode(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            .checked_add(match self {
+                AudioInputPdu::Version(version) => version.size(),
+                AudioInputPdu::Formats(pdu) => pdu.size(),
+                AudioInputPdu::Open(pdu) => pdu.size(),
+                AudioInputPdu::OpenReply(pdu) => pdu.size(),
+                AudioInputPdu::IncomingData => 0,
+                AudioInputPdu::Data(pdu) => pdu.size(),
+                AudioInputPdu::FormatChange(pdu) => pdu.size(),
+            })
+            .expect("never overflow")
+    }
+}
+
+impl DvcEncode for AudioInputPdu {}
+
+impl<'de> Decode<'de> for AudioInputPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        match src.read_u8() {
+            MSG_SNDIN_VERSION => Ok(Self::Version(Version::decode(src)?)),
+            MSG_SNDIN_FORMATS => Ok(Self::Formats(FormatsPdu::decode(src)?)),
+            MSG_SNDIN_OPEN => Ok(Self::Open(OpenPdu::decode(src)?)),
+            MSG_SNDIN_OPEN_REPLY => Ok(Self::OpenReply(OpenReplyPdu::decode(src)?)),
+            MSG_SNDIN_DATA_INCOMING => Ok(Self::IncomingData),
+            MSG_SNDIN_DATA => Ok(Self::Data(DataPdu::decode(src)?)),
+            MSG_SNDIN_FORMATCHANGE => Ok(Self::FormatChange(FormatChangePdu::decode(src)?)),
+            _ => Err(invalid_field_err!("MessageId", "unknown audio input message")),
+        }
+    }
+}
+
+/// 2.2.2.1 MSG_SNDIN_VERSION
+///
+/// The server sends its version first, and the client replies with its own.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u32);
+
+impl Version {
+    const NAME: &'static str = "MSG_SNDIN_VERSION";
+
+    const FIXED_PART_SIZE: usize = 4 /* Version */;
+
+    pub const V1: Self = Self(0x0000_0001);
+    /// The client may append extra data to its [`FormatsPdu`]
+    pub const V2: Self = Self(0x0000_0002);
+}
+
+impl Encode for Version {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.0);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for Version {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self(src.read_u32()))
+    }
+}
+
+/// 2.2.2.2 MSG_SNDIN_FORMATS
+///
+/// The server announces the formats it accepts, and the client replies with the ones it can record. The indices of
+/// the formats in the client reply are the ones used by [`OpenPdu`] and [`FormatChangePdu`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatsPdu {
+    pub formats: Vec<AudioFormat>,
+}
+
+impl FormatsPdu {
+    const NAME: &'static str = "MSG_SNDIN_FORMATS";
+
+    const FIXED_PART_SIZE: usize = 4 /* NumFormats */ + 4 /* cbSizeFormatsPacket */;
+
+    fn formats_size(&self) -> usize {
+        self.formats.iter().map(|format| format.size()).sum()
+    }
+}
+
+impl Encode for FormatsPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(cast_length!("NumFormats", self.formats.len())?);
+        // The size of the whole PDU, message identifier included, the extra data excluded.
+        dst.write_u32(cast_length!(
+            "cbSizeFormatsPacket",
+            AudioInputPdu::FIXED_PART_SIZE + self.size()
+        )?);
+        for format in &self.formats {
+            format.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            .checked_add(self.formats_size())
+            .expect("never overflow")
+    }
+}
+
+impl<'de> Decode<'de> for FormatsPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let num_formats = cast_length!("NumFormats", src.read_u32())?;
+        let _size_formats_packet = src.read_u32();
+        let formats = core::iter::repeat_with(|| AudioFormat::decode(src))
+            .take(num_formats)
+            .collect::<DecodeResult<_>>()?;
+
+        // The optional extra data of the client, following the formats, is not used.
+        let _extra_data = src.read_remaining();
+
+        Ok(Self { formats })
+    }
+}
+
+/// 2.2.2.3 MSG_SNDIN_OPEN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenPdu {
+    /// Number of audio frames in each [`DataPdu`]
+    pub frames_per_packet: u32,
+    /// Index of the format to record with, in the formats of the client
+    pub initial_format: u32,
+    /// Format of the capture device, the recorded audio is converted to the initial format
+    pub capture_format: AudioFormat,
+}
+
+impl OpenPdu {
+    const NAME: &'static str = "MSG_SNDIN_OPEN";
+
+    const FIXED_PART_SIZE: usize = 4 /* FramesPerPacket */ + 4 /* initialFormat */;
+}
+
+impl Encode for OpenPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.frames_per_packet);
+        dst.write_u32(self.initial_format);
+        self.capture_format.encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            .checked_add(self.capture_format.size())
+            .expect("never overflow")
+    }
+}
+
+impl<'de> Decode<'de> for OpenPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let frames_per_packet = src.read_u32();
+        let initial_format = src.read_u32();
+        let capture_format = AudioFormat::decode(src)?;
+
+        Ok(Self {
+            frames_per_packet,
+            initial_format,
+            capture_format,
+        })
+    }
+}
+
+/// 2.2.2.4 MSG_SNDIN_OPEN_REPLY
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OpenReplyPdu {
+    /// HRESULT, [`S_OK`] when the recording started
+    pub result: u32,
+}
+
+impl OpenReplyPdu {
+    const NAME: &'static str = "MSG_SNDIN_OPEN_REPLY";
+
+    const FIXED_PART_SIZE: usize = 4 /* Result */;
+
+    pub fn is_success(&self) -> bool {
+        // Failure HRESULTs have the severity bit set.
+        self.result & 0x8000_0000 == 0
+    }
+}
+
+impl Encode for OpenReplyPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.result);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for OpenReplyPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self { result: src.read_u32() })
+    }
+}
+
+/// 2.2.3.2 MSG_SNDIN_DATA
+#[derive(Clone, PartialEq, Eq)]
+pub struct DataPdu {
+    /// Audio in the current format
+    pub data: Vec<u8>,
+}
+
+impl core::fmt::Debug for DataPdu {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DataPdu").field("data_len", &self.data.len()).finish()
+    }
+}
+
+impl DataPdu {
+    const NAME: &'static str = "MSG_SNDIN_DATA";
+}
+
+impl Encode for DataPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_slice(&self.data);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<'de> Decode<'de> for DataPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        Ok(Self {
+            data: src.read_remaining().to_vec(),
+        })
+    }
+}
+
+/// 2.2.4 MSG_SNDIN_FORMATCHANGE
+///
+/// Sent by the server to change the format of the recording, and by the client to acknowledge the change, or to
+/// announce the initial format after an [`OpenPdu`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatChangePdu {
+    /// Index of the format, in the formats of the client
+    pub new_format: u32,
+}
+
+impl FormatChangePdu {
+    const NAME: &'static str = "MSG_SNDIN_FORMATCHANGE";
+
+    const FIXED_PART_SIZE: usize = 4 /* NewFormat */;
+}
+
+impl Encode for FormatChangePdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.new_format);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for FormatChangePdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            new_format: src.read_u32(),
+        })
+    }
+}
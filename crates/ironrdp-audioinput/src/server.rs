@@ -0,0 +1,212 @@
+use ironrdp_core::{decode, impl_as_any};
+use ironrdp_dvc::{DvcMessage, DvcProcessor, DvcServerProcessor};
+use ironrdp_pdu::{decode_err, PduResult};
+use tracing::{debug, warn};
+
+use crate::pdu::{AudioFormat, AudioInputPdu, FormatChangePdu, FormatsPdu, OpenPdu, OpenReplyPdu, Version};
+use crate::CHANNEL_NAME;
+
+/// Default number of audio frames in each data PDU, 20ms at 44.1kHz
+pub const DEFAULT_FRAMES_PER_PACKET: u32 = 882;
+
+/// Receives the audio recorded by the microphone of the client
+pub trait AudioInputServerHandler: Send + core::fmt::Debug {
+    /// The client started recording
+    fn opened(&mut self, format: &AudioFormat) {
+        debug!(?format, "Audio input opened");
+    }
+
+    /// The client failed to start recording, `result` is an HRESULT
+    fn open_failed(&mut self, result: u32) {
+        warn!(result, "Audio input failed to open");
+    }
+
+    /// The format of the recording changed
+    fn format_changed(&mut self, format: &AudioFormat) {
+        debug!(?format, "Audio input format changed");
+    }
+
+    /// Audio recorded by the client, in the current format
+    fn data(&mut self, data: &[u8]);
+
+    /// The channel was closed, no more audio will be received
+    fn closed(&mut self) {}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum AudioInputState {
+    WaitingForVersion,
+    WaitingForFormats,
+    Opening,
+    Recording,
+    /// No common format, or the client failed to start recording
+    Stopped,
+}
+
+/// A server for the Audio Input Virtual Channel
+///
+/// The server announces the formats it accepts, and opens the recording in the first of them, by order of
+/// preference, which the client supports.
+#[derive(Debug)]
+pub struct AudioInputServer {
+    handler: Box<dyn AudioInputServerHandler>,
+    formats: Vec<AudioFormat>,
+    frames_per_packet: u32,
+    state: AudioInputState,
+    /// Formats supported by the client, the indices of the formats are the ones of this list
+    client_formats: Vec<AudioFormat>,
+    /// Index of the current format
+    format: Option<usize>,
+}
+
+impl AudioInputServer {
+    /// Creates a server accepting the given formats, by order of preference
+    pub fn new(handler: Box<dyn AudioInputServerHandler>, formats: Vec<AudioFormat>) -> Self {
+        Self {
+            handler,
+            formats,
+            frames_per_packet: DEFAULT_FRAMES_PER_PACKET,
+            state: AudioInputState::WaitingForVersion,
+            client_formats: Vec::new(),
+            format: None,
+        }
+    }
+
+    /// Sets the number of audio frames the client sends in each data PDU
+    #[must_use]
+    pub fn with_frames_per_packet(mut self, frames_per_packet: u32) -> Self {
+        self.frames_per_packet = frames_per_packet;
+        self
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.state == AudioInputState::Recording
+    }
+
+    /// Format of the recording
+    pub fn format(&self) -> Option<&AudioFormat> {
+        self.format.and_then(|index| self.client_formats.get(index))
+    }
+
+    fn open(&mut self, client_formats: Vec<AudioFormat>) -> Vec<DvcMessage> {
+        self.client_formats = client_formats;
+
+        let Some(index) = self
+            .formats
+            .iter()
+            .find_map(|format| self.client_formats.iter().position(|client| client == format))
+        else {
+            warn!(client_formats = ?self.client_formats, "No audio input format supported by the client");
+            self.state = AudioInputState::Stopped;
+            return Vec::new();
+        };
+
+        // The formats of the client were decoded with a 32-bit count.
+        let initial_format = u32::try_from(index).expect("format index fits in u32");
+
+        self.state = AudioInputState::Opening;
+
+        vec![Box::new(AudioInputPdu::Open(OpenPdu {
+            frames_per_packet: self.frames_per_packet,
+            initial_format,
+            capture_format: self.client_formats[index].clone(),
+        }))]
+    }
+
+    fn format_change(&mut self, pdu: FormatChangePdu) {
+        let Some(index) = usize::try_from(pdu.new_format)
+            .ok()
+            .filter(|index| *index < self.client_formats.len())
+        else {
+            warn!(?pdu, "Invalid audio input format");
+            return;
+        };
+
+        self.format = Some(index);
+
+        // Before the open reply, the client only announces the initial format.
+        if self.state == AudioInputState::Recording {
+            self.handler.format_changed(&self.client_formats[index]);
+        }
+    }
+
+    fn open_reply(&mut self, pdu: OpenReplyPdu) {
+        if self.state != AudioInputState::Opening {
+            warn!(?pdu, "Unexpected audio input open reply");
+            return;
+        }
+
+        if !pdu.is_success() {
+            self.state = AudioInputState::Stopped;
+            self.handler.open_failed(pdu.result);
+            return;
+        }
+
+        self.state = AudioInputState::Recording;
+        if let Some(format) = self.format().cloned() {
+            self.handler.opened(&format);
+        }
+    }
+}
+
+impl_as_any!(AudioInputServer);
+
+impl DvcProcessor for AudioInputServer {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.state = AudioInputState::WaitingForVersion;
+        self.client_formats.clear();
+        self.format = None;
+
+        Ok(vec![Box::new(AudioInputPdu::Version(Version::V2))])
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: AudioInputPdu = decode(payload).map_err(|e| decode_err!(e))?;
+
+        let messages: Vec<DvcMessage> = match pdu {
+            AudioInputPdu::Version(version) if self.state == AudioInputState::WaitingForVersion => {
+                debug!(?version, "Audio input client version");
+                self.state = AudioInputState::WaitingForFormats;
+
+                vec![Box::new(AudioInputPdu::Formats(FormatsPdu {
+                    formats: self.formats.clone(),
+                }))]
+            }
+            AudioInputPdu::Formats(FormatsPdu { formats }) if self.state == AudioInputState::WaitingForFormats => {
+                self.open(formats)
+            }
+            AudioInputPdu::FormatChange(pdu) => {
+                self.format_change(pdu);
+                Vec::new()
+            }
+            AudioInputPdu::OpenReply(pdu) => {
+                self.open_reply(pdu);
+                Vec::new()
+            }
+            AudioInputPdu::IncomingData => Vec::new(),
+            AudioInputPdu::Data(pdu) if self.state == AudioInputState::Recording => {
+                self.handler.data(&pdu.data);
+                Vec::new()
+            }
+            pdu => {
+                warn!(?pdu, state = ?self.state, "Unexpected audio input PDU");
+                Vec::new()
+            }
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        if self.state == AudioInputState::Recording {
+            self.handler.closed();
+        }
+        self.state = AudioInputState::Stopped;
+    }
+}
+
+impl DvcServerProcessor for AudioInputServer {}
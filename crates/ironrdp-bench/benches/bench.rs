@@ -2,8 +2,9 @@
 
 use core::num::{NonZeroU16, NonZeroUsize};
 
-use criterion::{criterion_group, criterion_main, Criterion};
-use ironrdp_graphics::color_conversion::to_64x64_ycbcr_tile;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use ironrdp_graphics::color_conversion::{bgra_to_yuv, to_64x64_ycbcr_tile, yuv_to_bgra, ChromaSubsampling, YuvBuffer};
+use ironrdp_graphics::zgfx::{CompressionLevel, Compressor};
 use ironrdp_pdu::codecs::rfx;
 use ironrdp_server::bench::encoder::rfx::{rfx_enc, rfx_enc_tile};
 use ironrdp_server::BitmapUpdate;
@@ -76,5 +77,72 @@ pub fn to_ycbcr_bench(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, rfx_enc_tile_bench, rfx_enc_bench, to_ycbcr_bench);
+pub fn bgra_yuv_bench(c: &mut Criterion) {
+    const WIDTH: usize = 3840;
+    const HEIGHT: usize = 2160;
+
+    let bgra: Vec<u8> = (0..WIDTH * HEIGHT * 4)
+        .map(|i| (i % 251).try_into().expect("can't panic"))
+        .collect();
+    let mut output = vec![0; WIDTH * HEIGHT * 4];
+
+    let mut group = c.benchmark_group("bgra_yuv");
+    group.throughput(Throughput::Elements((WIDTH * HEIGHT).try_into().expect("can't panic")));
+
+    for subsampling in [ChromaSubsampling::Yuv420, ChromaSubsampling::Yuv444] {
+        let mut yuv = YuvBuffer::new(WIDTH, HEIGHT, subsampling);
+
+        group.bench_function(format!("bgra_to_{subsampling:?}"), |b| {
+            b.iter(|| bgra_to_yuv(&bgra, WIDTH * 4, &mut yuv.as_planes_mut()).expect("can't panic"))
+        });
+
+        group.bench_function(format!("{subsampling:?}_to_bgra"), |b| {
+            b.iter(|| yuv_to_bgra(&yuv.as_planes(), &mut output, WIDTH * 4).expect("can't panic"))
+        });
+    }
+
+    group.finish();
+}
+
+pub fn zgfx_compress_bench(c: &mut Criterion) {
+    // Text/UI-like content: repeated markup with small variations
+    let mut input = Vec::new();
+    for i in 0..2048 {
+        input.extend_from_slice(
+            format!(
+                "<row index=\"{i}\"><cell>Name</cell><cell>Value {}</cell></row>",
+                i % 97
+            )
+            .as_bytes(),
+        );
+    }
+
+    let mut group = c.benchmark_group("zgfx_compress");
+    group.throughput(Throughput::Bytes(input.len().try_into().expect("can't panic")));
+
+    for level in [
+        CompressionLevel::Fast,
+        CompressionLevel::Balanced,
+        CompressionLevel::Best,
+    ] {
+        group.bench_function(format!("{level:?}"), |b| {
+            b.iter_batched(
+                || Compressor::with_level(level),
+                |mut compressor| compressor.compress(&input).expect("can't panic"),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    rfx_enc_tile_bench,
+    rfx_enc_bench,
+    to_ycbcr_bench,
+    bgra_yuv_bench,
+    zgfx_compress_bench
+);
 criterion_main!(benches);
@@ -0,0 +1,232 @@
+//! Codec A/B comparison
+//!
+//! Runs the same synthetic content through every codec of the registry able to both encode and decode, and
+//! reports the bitrate, the encode and decode times, and the quality (PSNR/SSIM) of the decoded frames.
+//!
+//! Only the codecs of `CodecRegistry::with_builtin_codecs` are compared by default. Other codecs (e.g. AVC420
+//! through an external H.264 encoder) are compared by registering them as a [`BitmapCodec`] in
+//! [`registry`].
+//!
+//! Run with `cargo bench -p ironrdp-bench --bench codecs`.
+
+#![allow(unused_crate_dependencies)] // False positives because the dev-dependencies are shared with the other benches.
+#![expect(clippy::print_stdout, reason = "the bench reports its results on stdout")]
+
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use ironrdp_graphics::codec::{BitmapCodec, BitmapDesc, BitmapRef, CodecId, CodecRegistry};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_graphics::quality::{psnr, ssim};
+
+const WIDTH: u16 = 640;
+const HEIGHT: u16 = 480;
+const FRAMES: usize = 10;
+const FRAME_RATE: u32 = 30;
+
+const DESC: BitmapDesc = BitmapDesc {
+    width: WIDTH,
+    height: HEIGHT,
+    format: PixelFormat::BgrX32,
+};
+
+/// Codecs to compare
+fn registry() -> CodecRegistry {
+    CodecRegistry::with_builtin_codecs()
+}
+
+/// Sequence of frames of synthetic content
+struct Content {
+    name: &'static str,
+    frames: Vec<Vec<u8>>,
+}
+
+fn contents() -> Vec<Content> {
+    vec![
+        Content {
+            name: "desktop",
+            frames: (0..FRAMES).map(desktop).collect(),
+        },
+        Content {
+            name: "gradient",
+            frames: (0..FRAMES).map(gradient).collect(),
+        },
+        Content {
+            name: "scroll",
+            frames: (0..FRAMES).map(scroll).collect(),
+        },
+        Content {
+            name: "noise",
+            frames: (0..FRAMES).map(noise).collect(),
+        },
+    ]
+}
+
+fn frame(mut pixel: impl FnMut(usize, usize) -> [u8; 3]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(DESC.len());
+
+    for y in 0..usize::from(HEIGHT) {
+        for x in 0..usize::from(WIDTH) {
+            let [r, g, b] = pixel(x, y);
+            data.extend_from_slice(&[b, g, r, 0xff]);
+        }
+    }
+
+    data
+}
+
+/// Flat window with lines of "text", a line being added on each frame
+fn desktop(idx: usize) -> Vec<u8> {
+    frame(|x, y| {
+        let in_window = (40..600).contains(&x) && (40..440).contains(&y);
+        let line = (y - y % 16) / 16;
+        let glyph = (x / 4 + line * 7) % 5 != 0 && (x % 4 != 3) && (y % 16 < 10);
+
+        if !in_window {
+            [0x00, 0x5a, 0x9e]
+        } else if line < 3 + idx && (48..590).contains(&x) && glyph {
+            [0x20, 0x20, 0x20]
+        } else {
+            [0xf0, 0xf0, 0xf0]
+        }
+    })
+}
+
+/// Smooth gradient, shifting on each frame
+fn gradient(idx: usize) -> Vec<u8> {
+    frame(|x, y| {
+        let r = (x + idx * 4) % 256;
+        let g = y % 256;
+        let b = (x + y) / 5 % 256;
+
+        [to_u8(r), to_u8(g), to_u8(b)]
+    })
+}
+
+/// Stripes scrolling up by 8 pixels on each frame
+fn scroll(idx: usize) -> Vec<u8> {
+    frame(|x, y| {
+        let row = y + idx * 8;
+        let value = to_u8(row * 37 % 256);
+
+        if x % 64 < 48 {
+            [value, value, value]
+        } else {
+            [0xff, 0xff, 0xff]
+        }
+    })
+}
+
+/// Pseudo-random pixels, the worst case of every codec
+fn noise(idx: usize) -> Vec<u8> {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64 ^ u64::try_from(idx).expect("can't panic");
+    let mut next = move || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state.to_le_bytes()
+    };
+
+    frame(|_, _| {
+        let [r, g, b, ..] = next();
+        [r, g, b]
+    })
+}
+
+fn to_u8(value: usize) -> u8 {
+    u8::try_from(value).expect("value is guaranteed to fit")
+}
+
+#[derive(Default)]
+struct Report {
+    encoded_bytes: usize,
+    encode_time: Duration,
+    decode_time: Duration,
+    min_psnr: Option<f64>,
+    total_ssim: f64,
+}
+
+fn run(codec: &mut dyn BitmapCodec, content: &Content) -> Report {
+    let mut report = Report::default();
+    let mut encoded = Vec::new();
+    let mut decoded = Vec::new();
+
+    for frame in &content.frames {
+        let reference = BitmapRef {
+            desc: DESC,
+            stride: DESC.row_len(),
+            data: frame,
+        };
+
+        encoded.clear();
+        let start = Instant::now();
+        codec.encode(&reference, &mut encoded).expect("encode");
+        report.encode_time += start.elapsed();
+        report.encoded_bytes += encoded.len();
+
+        decoded.clear();
+        let start = Instant::now();
+        codec.decode(&encoded, DESC, &mut decoded).expect("decode");
+        report.decode_time += start.elapsed();
+
+        let decoded = BitmapRef {
+            desc: DESC,
+            stride: DESC.row_len(),
+            data: &decoded,
+        };
+
+        let psnr = psnr(&reference, &decoded).expect("same size");
+        report.min_psnr = Some(report.min_psnr.map_or(psnr, |min_psnr| min_psnr.min(psnr)));
+        report.total_ssim += ssim(&reference, &decoded).expect("same size");
+    }
+
+    report
+}
+
+fn main() {
+    let mut registry = registry();
+
+    // A codec may be registered for several identifiers
+    let ids: BTreeMap<&'static str, CodecId> = registry
+        .ids()
+        .filter_map(|id| {
+            let codec = registry.get(id)?;
+            (codec.can_encode() && codec.can_decode()).then(|| (codec.name(), id))
+        })
+        .collect();
+
+    let frames = u32::try_from(FRAMES).expect("can't panic");
+
+    println!(
+        "{:<10} {:<14} {:>12} {:>10} {:>10} {:>10} {:>9} {:>6}",
+        "content", "codec", "bytes/frame", "kbps", "enc ms", "dec ms", "min PSNR", "SSIM"
+    );
+
+    for content in contents() {
+        for (name, id) in &ids {
+            let codec = registry.get_mut(*id).expect("registered");
+            let report = run(codec, &content);
+
+            let bytes_per_frame = report.encoded_bytes / FRAMES;
+            let kbps = u64::try_from(bytes_per_frame).expect("can't panic") * 8 * u64::from(FRAME_RATE) / 1000;
+            let psnr = match report.min_psnr {
+                Some(psnr) if psnr.is_finite() => format!("{psnr:.2}"),
+                _ => "lossless".to_owned(),
+            };
+
+            println!(
+                "{:<10} {:<14} {:>12} {:>10} {:>10.3} {:>10.3} {:>9} {:>6.4}",
+                content.name,
+                name,
+                bytes_per_frame,
+                kbps,
+                (report.encode_time / frames).as_secs_f64() * 1000.0,
+                (report.decode_time / frames).as_secs_f64() * 1000.0,
+                psnr,
+                report.total_ssim / f64::from(frames),
+            );
+        }
+    }
+}
@@ -6,20 +6,26 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use anyhow::Context as _;
+use ironrdp::rail::window::WindowEvent as RemoteAppWindowEvent;
 use raw_window_handle::{DisplayHandle, HasDisplayHandle as _};
 use tokio::sync::mpsc;
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 use winit::application::ApplicationHandler;
 use winit::dpi::{LogicalPosition, PhysicalSize};
 use winit::event::{self, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::platform::scancode::PhysicalKeyExtScancode as _;
-use winit::window::{CursorIcon, CustomCursor, Window, WindowAttributes};
+use winit::window::{CursorIcon, CustomCursor, Fullscreen, Window, WindowAttributes};
 
 use crate::rdp::{RdpInputEvent, RdpOutputEvent};
 
 type WindowSurface = (Arc<Window>, softbuffer::Surface<DisplayHandle<'static>, Arc<Window>>);
 
+/// Actions handled by the client instead of being sent to the server
+#[derive(Debug, Clone, Copy)]
+enum LocalAction {
+    ToggleFullscreen,
+}
+
 pub struct App {
     input_event_sender: mpsc::UnboundedSender<RdpInputEvent>,
     context: softbuffer::Context<DisplayHandle<'static>>,
@@ -27,6 +33,8 @@ pub struct App {
     buffer: Vec<u32>,
     buffer_size: (u16, u16),
     input_database: ironrdp::input::Database,
+    hotkeys: ironrdp::input::Hotkeys<LocalAction>,
+    ime: ironrdp::input::ImeComposition,
     last_size: Option<PhysicalSize<u32>>,
     resize_timeout: Option<Instant>,
 }
@@ -46,7 +54,20 @@ impl App {
         let context = softbuffer::Context::new(display_handle)
             .map_err(|e| anyhow::anyhow!("unable to initialize softbuffer context: {e}"))?;
 
+        const ENTER: ironrdp::input::Scancode = ironrdp::input::Scancode::from_u8(false, 0x1C);
+
         let input_database = ironrdp::input::Database::new();
+
+        // Same hotkey as the Microsoft client.
+        let mut hotkeys = ironrdp::input::Hotkeys::new();
+        hotkeys.bind(
+            ironrdp::input::Hotkey::new(
+                &[ironrdp::input::Modifier::Control, ironrdp::input::Modifier::Alt],
+                ENTER,
+            ),
+            LocalAction::ToggleFullscreen,
+        );
+
         Ok(Self {
             input_event_sender: input_event_sender.clone(),
             context,
@@ -54,6 +75,8 @@ impl App {
             buffer: Vec::new(),
             buffer_size: (0, 0),
             input_database,
+            hotkeys,
+            ime: ironrdp::input::ImeComposition::new(),
             last_size: None,
             resize_timeout: None,
         })
@@ -114,6 +137,9 @@ impl ApplicationHandler<RdpOutputEvent> for App {
         let window_attributes = WindowAttributes::default().with_title("IronRDP");
         match event_loop.create_window(window_attributes) {
             Ok(window) => {
+                // Composed text (e.g. CJK input) is received through the IME events.
+                window.set_ime_allowed(true);
+
                 let window = Arc::new(window);
                 let surface = softbuffer::Surface::new(&self.context, Arc::clone(&window)).expect("surface");
                 self.window = Some((window, surface));
@@ -166,26 +192,44 @@ impl ApplicationHandler<RdpOutputEvent> for App {
             // TODO(#376): Implement unicode input in native client
             // }
             WindowEvent::KeyboardInput { event, .. } => {
-                if let Some(scancode) = event.physical_key.to_scancode() {
-                    let scancode = match u16::try_from(scancode) {
-                        Ok(scancode) => scancode,
-                        Err(_) => {
-                            warn!("Unsupported scancode: `{scancode:#X}`; ignored");
-                            return;
-                        }
-                    };
-                    let scancode = ironrdp::input::Scancode::from_u16(scancode);
+                if self.ime.is_composing() && event.state == event::ElementState::Pressed {
+                    // The key is handled by the input method.
+                    return;
+                }
 
+                if let Some(scancode) = crate::keyboard::scancode(event.physical_key) {
                     let operation = match event.state {
                         event::ElementState::Pressed => ironrdp::input::Operation::KeyPressed(scancode),
                         event::ElementState::Released => ironrdp::input::Operation::KeyReleased(scancode),
                     };
 
-                    let input_events = self.input_database.apply(core::iter::once(operation));
+                    let input_events =
+                        self.hotkeys
+                            .apply(&mut self.input_database, core::iter::once(operation), |action| {
+                                handle_local_action(window, *action)
+                            });
 
                     send_fast_path_events(&self.input_event_sender, input_events);
+                } else {
+                    warn!(physical_key = ?event.physical_key, "Unsupported key; ignored");
                 }
             }
+            WindowEvent::Ime(ime) => match ime {
+                event::Ime::Preedit(text, cursor) => {
+                    // The input method shows the text being composed and its candidates in its own window.
+                    trace!(%text, ?cursor, "IME preedit");
+                    self.ime.set_preedit(&text, cursor);
+                }
+                event::Ime::Commit(text) => {
+                    let operations = self.ime.commit(&text);
+                    let input_events = self.input_database.apply(operations);
+
+                    send_fast_path_events(&self.input_event_sender, input_events);
+                }
+                event::Ime::Enabled | event::Ime::Disabled => {
+                    self.ime.set_preedit("", None);
+                }
+            },
             WindowEvent::ModifiersChanged(modifiers) => {
                 const SHIFT_LEFT: ironrdp::input::Scancode = ironrdp::input::Scancode::from_u8(false, 0x2A);
                 const CONTROL_LEFT: ironrdp::input::Scancode = ironrdp::input::Scancode::from_u8(false, 0x1D);
@@ -217,7 +261,9 @@ impl ApplicationHandler<RdpOutputEvent> for App {
                 add_operation(modifiers.state().alt_key(), ALT_LEFT);
                 add_operation(modifiers.state().super_key(), LOGO_LEFT);
 
-                let input_events = self.input_database.apply(operations);
+                let input_events = self.hotkeys.apply(&mut self.input_database, operations, |action| {
+                    handle_local_action(window, *action)
+                });
 
                 send_fast_path_events(&self.input_event_sender, input_events);
             }
@@ -313,13 +359,21 @@ impl ApplicationHandler<RdpOutputEvent> for App {
             WindowEvent::RedrawRequested => {
                 self.draw();
             }
+            WindowEvent::Occluded(occluded) => {
+                let _ = self.input_event_sender.send(RdpInputEvent::SuppressOutput(occluded));
+            }
+            WindowEvent::Focused(false) => {
+                // The keys released while the window is not focused are never received.
+                let input_events = self.hotkeys.release_all(&mut self.input_database);
+
+                send_fast_path_events(&self.input_event_sender, input_events);
+            }
             WindowEvent::ActivationTokenDone { .. }
             | WindowEvent::Moved(_)
             | WindowEvent::Destroyed
             | WindowEvent::HoveredFile(_)
             | WindowEvent::HoveredFileCancelled
-            | WindowEvent::Focused(_)
-            | WindowEvent::Ime(_)
+            | WindowEvent::Focused(true)
             | WindowEvent::CursorEntered { .. }
             | WindowEvent::CursorLeft { .. }
             | WindowEvent::PinchGesture { .. }
@@ -330,8 +384,7 @@ impl ApplicationHandler<RdpOutputEvent> for App {
             | WindowEvent::AxisMotion { .. }
             | WindowEvent::Touch(_)
             | WindowEvent::ScaleFactorChanged { .. }
-            | WindowEvent::ThemeChanged(_)
-            | WindowEvent::Occluded(_) => {
+            | WindowEvent::ThemeChanged(_) => {
                 // ignore
             }
         }
@@ -359,6 +412,12 @@ impl ApplicationHandler<RdpOutputEvent> for App {
                 // TODO set proc_exit::sysexits::PROTOCOL_ERR.as_raw());
                 event_loop.exit();
             }
+            RdpOutputEvent::Redirected { host } => {
+                info!(?host, "Redirected by the server");
+            }
+            RdpOutputEvent::Reconnect(event) => {
+                info!(?event, "Connection state changed");
+            }
             RdpOutputEvent::Terminated(result) => {
                 let _exit_code = match result {
                     Ok(reason) => {
@@ -400,6 +459,20 @@ impl ApplicationHandler<RdpOutputEvent> for App {
                 }
                 window.set_cursor_visible(true);
             }
+            RdpOutputEvent::RemoteApp {
+                event,
+                window: remote_window,
+            } => {
+                debug!(?event, "Remote application window event");
+                // The remote application windows are composed into the desktop image for now,
+                // the title of the active one is shown as the title of the client window.
+                if let RemoteAppWindowEvent::Activated { .. } = event {
+                    match remote_window {
+                        Some(remote_window) => window.set_title(&format!("{} - IronRDP", remote_window.title)),
+                        None => window.set_title("IronRDP"),
+                    }
+                }
+            }
         }
     }
 }
@@ -412,3 +485,17 @@ fn send_fast_path_events(
         let _ = input_event_sender.send(RdpInputEvent::FastPath(input_events));
     }
 }
+
+fn handle_local_action(window: &Window, action: LocalAction) {
+    debug!(?action, "Local action");
+
+    match action {
+        LocalAction::ToggleFullscreen => {
+            let fullscreen = match window.fullscreen() {
+                Some(_) => None,
+                None => Some(Fullscreen::Borderless(None)),
+            };
+            window.set_fullscreen(fullscreen);
+        }
+    }
+}
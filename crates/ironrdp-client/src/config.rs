@@ -7,10 +7,11 @@ use std::path::PathBuf;
 use anyhow::Context as _;
 use clap::clap_derive::ValueEnum;
 use clap::Parser;
+use ironrdp::connector::credssp::KerberosConfig;
 use ironrdp::connector::{self, Credentials};
 use ironrdp::pdu::rdp::capability_sets::{client_codecs_capabilities, MajorPlatformType};
 use ironrdp::pdu::rdp::client_info::{PerformanceFlags, TimezoneInfo};
-use ironrdp_mstsgu::GwConnectTarget;
+use ironrdp_mstsgu::{GwConnectTarget, GwTransport};
 use tap::prelude::*;
 use url::Url;
 
@@ -25,6 +26,8 @@ pub struct Config {
     pub connector: connector::Config,
     pub clipboard_type: ClipboardType,
     pub rdcleanpath: Option<RDCleanPathConfig>,
+    /// Program started as a remote application (RemoteApp) instead of the full desktop
+    pub remote_app: Option<String>,
 
     /// DVC channel <-> named pipe proxy configuration.
     ///
@@ -32,6 +35,15 @@ pub struct Config {
     /// server, which will be used for proxying DVC messages to/from user-defined DVC logic
     /// implemented as named pipe clients (either in the same process or in a different process).
     pub dvc_pipe_proxies: Vec<DvcProxyInfo>,
+
+    /// Target size of the PDUs sent on the wire, headers included
+    pub max_segment_size: Option<usize>,
+
+    /// File the session is recorded to, for replaying it
+    pub capture: Option<PathBuf>,
+
+    /// Kerberos settings of the network level authentication, when a KDC is to be reached through a proxy
+    pub kerberos: Option<KerberosConfig>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -40,6 +52,8 @@ pub enum ClipboardType {
     Stub,
     #[cfg(windows)]
     Windows,
+    #[cfg(target_os = "macos")]
+    MacOs,
     None,
 }
 
@@ -120,6 +134,15 @@ impl Destination {
     pub fn port(&self) -> u16 {
         self.port
     }
+
+    /// Returns the same destination on another host, e.g. the target of a server redirection
+    #[must_use]
+    pub fn with_name(&self, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            port: self.port,
+        }
+    }
 }
 
 impl FromStr for Destination {
@@ -218,6 +241,10 @@ struct Args {
     #[clap(long, requires("rdcleanpath_url"))]
     rdcleanpath_token: Option<String>,
 
+    /// URL of the KDC proxy (MS-KKDCP) used for Kerberos authentication
+    #[clap(long)]
+    kdc_proxy_url: Option<Url>,
+
     /// The keyboard type
     #[clap(long, value_enum, default_value_t = KeyboardType::IbmEnhanced)]
     keyboard_type: KeyboardType,
@@ -267,6 +294,12 @@ struct Args {
     #[clap(long)]
     autologon: bool,
 
+    /// Start a program as a remote application (RemoteApp) instead of showing the full desktop
+    ///
+    /// The program is an executable or a published application alias, e.g. `||notepad`.
+    #[clap(long)]
+    remote_app: Option<String>,
+
     /// Disable TLS + Graphical login (legacy authentication method)
     ///
     /// Disabling this in order to enforce usage of CredSSP (NLA) is recommended.
@@ -295,6 +328,20 @@ struct Args {
     /// `<pipe>` will automatically be prefixed with `\\.\pipe\` on Windows.
     #[clap(long)]
     dvc_proxy: Vec<DvcProxyInfo>,
+
+    /// Target size of the PDUs sent on the wire, headers included
+    ///
+    /// The dynamic virtual channel messages are split to fit in it, avoiding the IP fragmentation on links with a small
+    /// MTU, e.g. 1400 bytes for a VPN or UDP encapsulation.
+    #[clap(long)]
+    max_segment_size: Option<usize>,
+
+    /// Record the session to a capture file, to reproduce rendering issues by replaying it
+    ///
+    /// The frames received from the server and the keyboard and mouse input are recorded, passwords typed during the
+    /// session included. On reconnection, the file is overwritten with the new session.
+    #[clap(long)]
+    capture: Option<PathBuf>,
 }
 
 impl Config {
@@ -326,6 +373,7 @@ impl Config {
                 gw_user: String::new(),
                 gw_pass: String::new(),
                 server: String::new(), // TODO: non-standard port? also dont use here?
+                transport: GwTransport::Auto,
             });
         }
 
@@ -413,7 +461,11 @@ impl Config {
             {
                 ClipboardType::Windows
             }
-            #[cfg(not(windows))]
+            #[cfg(target_os = "macos")]
+            {
+                ClipboardType::MacOs
+            }
+            #[cfg(not(any(windows, target_os = "macos")))]
             {
                 ClipboardType::None
             }
@@ -421,9 +473,28 @@ impl Config {
             args.clipboard_type
         };
 
+        let kdc_proxy_url = match args.kdc_proxy_url {
+            Some(url) => Some(url),
+            None => properties
+                .kdc_proxy_url()
+                .map(Url::parse)
+                .transpose()
+                .context("invalid KDC proxy URL")?,
+        };
+
+        let client_name = whoami::fallible::hostname().unwrap_or_else(|_| "ironrdp".to_owned());
+
+        let kerberos = kdc_proxy_url.map(|url| KerberosConfig {
+            kdc_proxy_url: Some(url),
+            hostname: Some(client_name.clone()),
+            ..KerberosConfig::default()
+        });
+
         let connector = connector::Config {
             credentials: Credentials::UsernamePassword { username, password },
             domain: args.domain,
+            redirection_credentials: None,
+            auto_reconnect_cookie: None,
             enable_tls: !args.no_tls,
             enable_credssp: !args.no_credssp,
             keyboard_type: KeyboardType::parse(args.keyboard_type),
@@ -437,12 +508,13 @@ impl Config {
                 height: DEFAULT_HEIGHT,
             },
             desktop_scale_factor: 0, // Default to 0 per FreeRDP
+            monitors: Vec::new(),
             bitmap: Some(bitmap),
             client_build: semver::Version::parse(env!("CARGO_PKG_VERSION"))
                 .map_or(0, |version| version.major * 100 + version.minor * 10 + version.patch)
                 .pipe(u32::try_from)
                 .context("cargo package version")?,
-            client_name: whoami::fallible::hostname().unwrap_or_else(|_| "ironrdp".to_owned()),
+            client_name,
             // NOTE: hardcode this value like in freerdp
             // https://github.com/FreeRDP/FreeRDP/blob/4e24b966c86fdf494a782f0dfcfc43a057a2ea60/libfreerdp/core/settings.c#LL49C34-L49C70
             client_dir: "C:\\Windows\\System32\\mstscax.dll".to_owned(),
@@ -459,6 +531,7 @@ impl Config {
             enable_server_pointer: !args.no_server_pointer,
             autologon: args.autologon,
             enable_audio_playback: true,
+            remote_app: args.remote_app.is_some(),
             request_data: None,
             pointer_software_rendering: false,
             performance_flags: PerformanceFlags::default(),
@@ -477,7 +550,11 @@ impl Config {
             connector,
             clipboard_type,
             rdcleanpath,
+            remote_app: args.remote_app,
             dvc_pipe_proxies: args.dvc_proxy,
+            max_segment_size: args.max_segment_size,
+            capture: args.capture,
+            kerberos,
         })
     }
 }
@@ -0,0 +1,45 @@
+use ironrdp::input::Scancode;
+use winit::keyboard::{KeyCode, NativeKeyCode, PhysicalKey};
+
+/// Returns the RDP scan code of a physical key.
+///
+/// The scan codes reported by winit are platform-specific (e.g. evdev codes on Linux, virtual key codes on macOS),
+/// so the key codes are translated through their `KeyboardEvent.code` names instead.
+pub(crate) fn scancode(physical_key: PhysicalKey) -> Option<Scancode> {
+    match physical_key {
+        PhysicalKey::Code(code) => web_code(code).and_then(Scancode::from_web_code),
+        // Windows reports the scan codes of the keys winit doesn't know.
+        PhysicalKey::Unidentified(NativeKeyCode::Windows(scancode)) => Some(Scancode::from_u16(scancode)),
+        PhysicalKey::Unidentified(_) => None,
+    }
+}
+
+macro_rules! web_codes {
+    ($code:expr; $($key:ident),* $(,)?) => {
+        match $code {
+            $( KeyCode::$key => Some(stringify!($key)), )*
+            KeyCode::SuperLeft => Some("MetaLeft"),
+            KeyCode::SuperRight => Some("MetaRight"),
+            _ => None,
+        }
+    };
+}
+
+/// The winit key codes are named after the `KeyboardEvent.code` values, except the Windows keys.
+fn web_code(code: KeyCode) -> Option<&'static str> {
+    web_codes! { code;
+        Escape, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9, Digit0, Minus, Equal,
+        Backspace, Tab, KeyQ, KeyW, KeyE, KeyR, KeyT, KeyY, KeyU, KeyI, KeyO, KeyP, BracketLeft, BracketRight,
+        Enter, ControlLeft, KeyA, KeyS, KeyD, KeyF, KeyG, KeyH, KeyJ, KeyK, KeyL, Semicolon, Quote, Backquote,
+        ShiftLeft, Backslash, KeyZ, KeyX, KeyC, KeyV, KeyB, KeyN, KeyM, Comma, Period, Slash, ShiftRight,
+        NumpadMultiply, AltLeft, Space, CapsLock, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, NumLock, ScrollLock,
+        Numpad7, Numpad8, Numpad9, NumpadSubtract, Numpad4, Numpad5, Numpad6, NumpadAdd, Numpad1, Numpad2, Numpad3,
+        Numpad0, NumpadDecimal, IntlBackslash, F11, F12, NumpadEqual, F13, F14, F15, F16, F17, F18, F19, F20, F21,
+        F22, F23, KanaMode, Lang2, Lang1, IntlRo, F24, Convert, NonConvert, IntlYen, NumpadComma,
+        MediaTrackPrevious, MediaTrackNext, NumpadEnter, ControlRight, AudioVolumeMute, LaunchApp2, MediaPlayPause,
+        MediaStop, AudioVolumeDown, AudioVolumeUp, BrowserHome, NumpadDivide, PrintScreen, AltRight, Home, ArrowUp,
+        PageUp, ArrowLeft, ArrowRight, End, ArrowDown, PageDown, Insert, Delete, ContextMenu, Power, Sleep, WakeUp,
+        BrowserSearch, BrowserFavorites, BrowserRefresh, BrowserStop, BrowserForward, BrowserBack, LaunchApp1,
+        LaunchMail, MediaSelect,
+    }
+}
@@ -14,4 +14,4 @@ pub mod clipboard;
 pub mod config;
 pub mod rdp;
 
-mod ws;
+mod keyboard;
@@ -34,6 +34,8 @@ fn main() -> anyhow::Result<()> {
     // starts and clipboard functionality will not be available.
     #[cfg(windows)]
     let _win_clipboard;
+    #[cfg(target_os = "macos")]
+    let _mac_clipboard;
 
     let cliprdr_factory = match config.clipboard_type {
         ClipboardType::Stub => {
@@ -54,6 +56,17 @@ fn main() -> anyhow::Result<()> {
             _win_clipboard = cliprdr;
             Some(factory)
         }
+        #[cfg(target_os = "macos")]
+        ClipboardType::MacOs => {
+            use ironrdp_client::clipboard::ClientClipboardMessageProxy;
+            use ironrdp_cliprdr_native::MacClipboard;
+
+            let cliprdr = MacClipboard::new(ClientClipboardMessageProxy::new(input_event_sender.clone()))?;
+
+            let factory = cliprdr.backend_factory();
+            _mac_clipboard = cliprdr;
+            Some(factory)
+        }
         _ => None,
     };
 
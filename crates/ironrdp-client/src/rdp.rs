@@ -1,24 +1,38 @@
 use core::num::NonZeroU16;
+use core::time::Duration;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
 
 use ironrdp::cliprdr::backend::{ClipboardMessage, CliprdrBackendFactory};
 use ironrdp::connector::connection_activation::ConnectionActivationState;
-use ironrdp::connector::{ConnectionResult, ConnectorResult};
+use ironrdp::connector::redirection::RedirectionPolicy;
+use ironrdp::connector::{ConnectionResult, ConnectorErrorKind, ConnectorResult, Sequence as _};
 use ironrdp::displaycontrol::client::DisplayControlClient;
 use ironrdp::displaycontrol::pdu::MonitorLayoutEntry;
+use ironrdp::dvc::pdu::DrdynvcDataPdu;
 use ironrdp::graphics::image_processing::PixelFormat;
 use ironrdp::graphics::pointer::DecodedPointer;
 use ironrdp::pdu::input::fast_path::FastPathInputEvent;
-use ironrdp::pdu::{pdu_other_err, PduResult};
+use ironrdp::pdu::rdp::server_redirection::ServerRedirectionPdu;
+use ironrdp::pdu::rdp::session_info::ServerAutoReconnect;
+use ironrdp::pdu::{pdu_other_err, Action, PduResult};
+use ironrdp::rail::client::{RailClient, RailClientHandler};
+use ironrdp::rail::pdu::{ClientStatusFlags, ExecFlags, ExecPdu, ExecResult, ExecResultPdu};
+use ironrdp::rail::window::{Window, WindowEvent, WindowTree};
 use ironrdp::session::image::DecodedImage;
-use ironrdp::session::{fast_path, ActiveStage, ActiveStageOutput, GracefulDisconnectReason, SessionResult};
+use ironrdp::session::replay::{Capture, Recorder};
+use ironrdp::session::{
+    fast_path, ActiveStage, ActiveStageOutput, GracefulDisconnectReason, SessionError, SessionResult,
+};
 use ironrdp::svc::SvcMessage;
 use ironrdp::{cliprdr, connector, rdpdr, rdpsnd, session};
 use ironrdp_core::WriteBuf;
 use ironrdp_dvc_pipe_proxy::DvcNamedPipeProxy;
 use ironrdp_rdpsnd_native::cpal;
+use ironrdp_tokio::reconnect::{ReconnectEvent, ReconnectManager};
 use ironrdp_tokio::reqwest::ReqwestNetworkClient;
-use ironrdp_tokio::{single_sequence_step_read, split_tokio_framed, FramedWrite};
+use ironrdp_tokio::{split_tokio_framed, FramedWrite};
 use rdpdr::NoopRdpdrBackend;
 use smallvec::SmallVec;
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -44,6 +58,17 @@ pub enum RdpOutputEvent {
         y: u16,
     },
     PointerBitmap(Arc<DecodedPointer>),
+    /// Change of a window of the remote applications, with the window after the change
+    RemoteApp {
+        event: WindowEvent,
+        window: Option<Window>,
+    },
+    /// The server redirected the client, which is connecting to the target of the redirection
+    Redirected {
+        host: Option<String>,
+    },
+    /// Change of the state of the connection, e.g. a reconnection after a transport loss
+    Reconnect(ReconnectEvent),
     Terminated(SessionResult<GracefulDisconnectReason>),
 }
 
@@ -57,6 +82,8 @@ pub enum RdpInputEvent {
         physical_size: Option<(u32, u32)>,
     },
     FastPath(SmallVec<[FastPathInputEvent; 2]>),
+    /// Stop, or resume, the display updates, e.g. when the window is hidden
+    SuppressOutput(bool),
     Close,
     Clipboard(ClipboardMessage),
     SendDvcMessages {
@@ -105,35 +132,40 @@ pub struct RdpClient {
 
 impl RdpClient {
     pub async fn run(mut self) {
+        let mut redirection_count = 0;
+
+        let event_loop_proxy = self.event_loop_proxy.clone();
+        let mut reconnect = ReconnectManager::new().with_event_handler(move |event| {
+            let _ = event_loop_proxy.send_event(RdpOutputEvent::Reconnect(event.clone()));
+        });
+
         loop {
-            let (connection_result, framed) = if let Some(rdcleanpath) = self.config.rdcleanpath.as_ref() {
-                match connect_ws(
-                    &self.config,
-                    rdcleanpath,
-                    self.cliprdr_factory.as_deref(),
-                    &self.dvc_pipe_proxy_factory,
-                )
-                .await
-                {
-                    Ok(result) => result,
-                    Err(e) => {
-                        let _ = self.event_loop_proxy.send_event(RdpOutputEvent::ConnectionFailure(e));
-                        break;
+            let config = &self.config;
+            let cliprdr_factory = self.cliprdr_factory.as_deref();
+            let dvc_pipe_proxy_factory = &self.dvc_pipe_proxy_factory;
+
+            let result = reconnect
+                .connect(|| async move {
+                    if let Some(rdcleanpath) = config.rdcleanpath.as_ref() {
+                        connect_ws(config, rdcleanpath, cliprdr_factory, dvc_pipe_proxy_factory).await
+                    } else {
+                        connect(config, cliprdr_factory, dvc_pipe_proxy_factory).await
                     }
-                }
-            } else {
-                match connect(
-                    &self.config,
-                    self.cliprdr_factory.as_deref(),
-                    &self.dvc_pipe_proxy_factory,
-                )
-                .await
-                {
-                    Ok(result) => result,
-                    Err(e) => {
+                })
+                .await;
+
+            let (connection_result, framed) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    if let ConnectorErrorKind::ServerRedirection(redirection) = e.kind() {
+                        redirection_count += 1;
+                        if self.redirect(redirection, redirection_count) {
+                            continue;
+                        }
+                    } else {
                         let _ = self.event_loop_proxy.send_event(RdpOutputEvent::ConnectionFailure(e));
-                        break;
                     }
+                    break;
                 }
             };
 
@@ -142,12 +174,35 @@ impl RdpClient {
                 connection_result,
                 &self.event_loop_proxy,
                 &mut self.input_event_receiver,
+                self.config.capture.as_deref(),
             )
             .await
             {
-                Ok(RdpControlFlow::ReconnectWithNewSize { width, height }) => {
+                Ok(RdpControlFlow::ReconnectWithNewSize {
+                    width,
+                    height,
+                    auto_reconnect_cookie,
+                }) => {
                     self.config.connector.desktop_size.width = width;
                     self.config.connector.desktop_size.height = height;
+                    self.config.connector.auto_reconnect_cookie = auto_reconnect_cookie;
+                }
+                Ok(RdpControlFlow::ConnectionLost {
+                    auto_reconnect_cookie,
+                    error,
+                }) => {
+                    if !reconnect.connection_lost(&mut self.config.connector, auto_reconnect_cookie) {
+                        let _ = self.event_loop_proxy.send_event(RdpOutputEvent::Terminated(Err(error)));
+                        break;
+                    }
+
+                    info!("Connection lost, reconnecting");
+                }
+                Ok(RdpControlFlow::Redirect(redirection)) => {
+                    redirection_count += 1;
+                    if !self.redirect(&redirection, redirection_count) {
+                        break;
+                    }
                 }
                 Ok(RdpControlFlow::TerminatedGracefully(reason)) => {
                     let _ = self.event_loop_proxy.send_event(RdpOutputEvent::Terminated(Ok(reason)));
@@ -160,10 +215,45 @@ impl RdpClient {
             }
         }
     }
+
+    /// Updates the configuration to connect to the target of a server redirection
+    ///
+    /// Returns `false`, after reporting the failure, when the redirection can't be followed.
+    fn redirect(&mut self, redirection: &ServerRedirectionPdu, redirection_count: usize) -> bool {
+        match RedirectionPolicy::default().apply(redirection, redirection_count, &mut self.config.connector) {
+            Ok(target) => {
+                info!(?target, "Following server redirection");
+
+                if let Some(host) = &target.host {
+                    self.config.destination = self.config.destination.with_name(host);
+                }
+
+                let _ = self
+                    .event_loop_proxy
+                    .send_event(RdpOutputEvent::Redirected { host: target.host });
+
+                true
+            }
+            Err(e) => {
+                let _ = self.event_loop_proxy.send_event(RdpOutputEvent::ConnectionFailure(e));
+                false
+            }
+        }
+    }
 }
 
 enum RdpControlFlow {
-    ReconnectWithNewSize { width: u16, height: u16 },
+    ReconnectWithNewSize {
+        width: u16,
+        height: u16,
+        auto_reconnect_cookie: Option<ServerAutoReconnect>,
+    },
+    /// The transport was lost during the session, which can be rejoined when the auto-reconnect cookie is known
+    ConnectionLost {
+        auto_reconnect_cookie: Option<ServerAutoReconnect>,
+        error: SessionError,
+    },
+    Redirect(Box<ServerRedirectionPdu>),
     TerminatedGracefully(GracefulDisconnectReason),
 }
 
@@ -196,8 +286,13 @@ async fn connect(
     };
     let mut framed = ironrdp_tokio::TokioFramed::new(stream);
 
-    let mut drdynvc =
-        ironrdp::dvc::DrdynvcClient::new().with_dynamic_channel(DisplayControlClient::new(|_| Ok(Vec::new())));
+    let mut drdynvc = ironrdp::dvc::DrdynvcClient::new()
+        .with_decompressor(dvc_decompressor())
+        .with_dynamic_channel(DisplayControlClient::new(|_| Ok(Vec::new())));
+
+    if let Some(segment_size) = config.max_segment_size {
+        drdynvc = drdynvc.with_max_data_size(DrdynvcDataPdu::max_data_size_for_segment(segment_size));
+    }
 
     // Instantiate all DVC proxies
     for proxy in config.dvc_pipe_proxies.iter() {
@@ -222,6 +317,10 @@ async fn connect(
         connector.attach_static_channel(cliprdr);
     }
 
+    if let Some(program) = &config.remote_app {
+        connector.attach_static_channel(rail_client(program));
+    }
+
     let should_upgrade = ironrdp_tokio::connect_begin(&mut framed, &mut connector).await?;
 
     debug!("TLS upgrade");
@@ -240,6 +339,27 @@ async fn connect(
 
     let server_public_key = ironrdp_tls::extract_tls_server_public_key(&tls_cert)
         .ok_or_else(|| connector::general_err!("unable to extract tls server public key"))?;
+
+    // Bind the Kerberos authentication to this TLS connection.
+    let kerberos = match config.kerberos.clone() {
+        Some(kerberos) => {
+            use x509_cert::der::Encode as _;
+
+            let certificate = tls_cert
+                .to_der()
+                .map_err(|e| connector::custom_err!("TLS server certificate", e))?;
+            // The bindings are undefined for some signature algorithms, such as Ed25519.
+            match connector::credssp::ChannelBindings::tls_server_end_point(&certificate) {
+                Ok(channel_bindings) => Some(kerberos.with_channel_bindings(channel_bindings)),
+                Err(e) => {
+                    warn!(error = %e, "Connecting without channel bindings");
+                    Some(kerberos)
+                }
+            }
+        }
+        None => None,
+    };
+
     let connection_result = ironrdp_tokio::connect_finalize(
         upgraded,
         connector,
@@ -247,7 +367,7 @@ async fn connect(
         &mut ReqwestNetworkClient::new(),
         (&config.destination).into(),
         server_public_key.to_owned(),
-        None,
+        kerberos,
     )
     .await?;
 
@@ -285,12 +405,17 @@ async fn connect_ws(
         .await
         .map_err(|e| connector::custom_err!("WS connect", e))?;
 
-    let ws = crate::ws::websocket_compat(ws);
+    let ws = ironrdp_tokio::websocket::WebSocketStream::new(ws);
 
     let mut framed = ironrdp_tokio::TokioFramed::new(ws);
 
-    let mut drdynvc =
-        ironrdp::dvc::DrdynvcClient::new().with_dynamic_channel(DisplayControlClient::new(|_| Ok(Vec::new())));
+    let mut drdynvc = ironrdp::dvc::DrdynvcClient::new()
+        .with_decompressor(dvc_decompressor())
+        .with_dynamic_channel(DisplayControlClient::new(|_| Ok(Vec::new())));
+
+    if let Some(segment_size) = config.max_segment_size {
+        drdynvc = drdynvc.with_max_data_size(DrdynvcDataPdu::max_data_size_for_segment(segment_size));
+    }
 
     // Instantiate all DVC proxies
     for proxy in config.dvc_pipe_proxies.iter() {
@@ -315,6 +440,10 @@ async fn connect_ws(
         connector.attach_static_channel(cliprdr);
     }
 
+    if let Some(program) = &config.remote_app {
+        connector.attach_static_channel(rail_client(program));
+    }
+
     let destination = format!("{}:{}", config.destination.name(), config.destination.port());
 
     let (upgraded, server_public_key) = connect_rdcleanpath(
@@ -333,7 +462,7 @@ async fn connect_ws(
         &mut ReqwestNetworkClient::new(),
         (&config.destination).into(),
         server_public_key,
-        None,
+        config.kerberos.clone(),
     )
     .await?;
 
@@ -497,11 +626,93 @@ where
     }
 }
 
+/// Decompressor of the compressed DVC data, which uses the bulk compression of the graphics pipeline
+fn dvc_decompressor() -> impl ironrdp::dvc::DvcDecompressor {
+    let mut decompressor = ironrdp::graphics::zgfx::Decompressor::new();
+
+    move |input: &[u8], output: &mut Vec<u8>| -> ironrdp_core::DecodeResult<()> {
+        decompressor
+            .decompress(input, output)
+            .map(|_| ())
+            .map_err(|e| ironrdp_core::other_err!("ZGFX", source: e))
+    }
+}
+
+fn rail_client(program: &str) -> RailClient {
+    RailClient::new(Box::new(RemoteAppHandler), ClientStatusFlags::empty()).with_exec(ExecPdu {
+        flags: ExecFlags::empty(),
+        exe_or_file: program.to_owned(),
+        working_dir: String::new(),
+        arguments: String::new(),
+    })
+}
+
+#[derive(Debug)]
+struct RemoteAppHandler;
+
+impl RailClientHandler for RemoteAppHandler {
+    fn exec_result(&mut self, result: &ExecResultPdu) {
+        if result.result == ExecResult::OK {
+            info!(program = %result.exe_or_file, "Remote application started");
+        } else {
+            error!(
+                program = %result.exe_or_file,
+                code = result.result.0,
+                raw_result = result.raw_result,
+                "Failed to start remote application"
+            );
+        }
+    }
+}
+
+/// Period at which the dynamic channels are checked for stalled peers
+const DVC_WATCHDOG_PERIOD: Duration = Duration::from_secs(1);
+
+/// Minimum period between the checkpoints of a recorded session, the digest of the framebuffer being costly
+const CAPTURE_CHECKPOINT_PERIOD: Duration = Duration::from_secs(1);
+
 async fn active_session(
     framed: UpgradedFramed,
     connection_result: ConnectionResult,
     event_loop_proxy: &EventLoopProxy<RdpOutputEvent>,
     input_event_receiver: &mut mpsc::UnboundedReceiver<RdpInputEvent>,
+    capture: Option<&Path>,
+) -> SessionResult<RdpControlFlow> {
+    let mut recorder = capture.map(|_| Recorder::new(connection_result.desktop_size, PixelFormat::RgbA32));
+
+    let result = run_active_session(
+        framed,
+        connection_result,
+        event_loop_proxy,
+        input_event_receiver,
+        recorder.as_mut(),
+    )
+    .await;
+
+    if let Some((path, recorder)) = capture.zip(recorder) {
+        save_capture(path, &recorder.finish());
+    }
+
+    result
+}
+
+fn save_capture(path: &Path, capture: &Capture) {
+    let result = ironrdp_core::encode_vec(capture)
+        .map_err(std::io::Error::other)
+        .and_then(|data| std::fs::write(path, data));
+
+    match result {
+        Ok(()) => info!(path = %path.display(), records = capture.records.len(), "Session recorded"),
+        Err(error) => error!(path = %path.display(), %error, "Failed to save the recorded session"),
+    }
+}
+
+async fn run_active_session(
+    framed: UpgradedFramed,
+    connection_result: ConnectionResult,
+    event_loop_proxy: &EventLoopProxy<RdpOutputEvent>,
+    input_event_receiver: &mut mpsc::UnboundedReceiver<RdpInputEvent>,
+    mut recorder: Option<&mut Recorder>,
 ) -> SessionResult<RdpControlFlow> {
     let (mut reader, mut writer) = split_tokio_framed(framed);
     let mut image = DecodedImage::new(
@@ -511,14 +722,40 @@ async fn active_session(
     );
 
     let mut active_stage = ActiveStage::new(connection_result);
+    let mut remote_app_windows = WindowTree::new();
+    let mut dvc_watchdog = tokio::time::interval(DVC_WATCHDOG_PERIOD);
+    let mut last_checkpoint = Instant::now();
 
     let disconnect_reason = 'outer: loop {
         let outputs = tokio::select! {
             frame = reader.read_pdu() => {
-                let (action, payload) = frame.map_err(|e| session::custom_err!("read frame", e))?;
+                let (action, payload) = match frame {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        warn!(error = %e, "Connection lost");
+                        return Ok(RdpControlFlow::ConnectionLost {
+                            auto_reconnect_cookie: active_stage.auto_reconnect_cookie().cloned(),
+                            error: session::custom_err!("read frame", e),
+                        });
+                    }
+                };
                 trace!(?action, frame_length = payload.len(), "Frame received");
 
-                active_stage.process(&mut image, action, &payload)?
+                if let Some(recorder) = recorder.as_deref_mut() {
+                    recorder.frame(action, &payload);
+                }
+
+                let outputs = active_stage.process(&mut image, action, &payload)?;
+
+                if let Some(recorder) = recorder.as_deref_mut() {
+                    let updated = outputs.iter().any(|output| matches!(output, ActiveStageOutput::GraphicsUpdate(_)));
+                    if updated && last_checkpoint.elapsed() >= CAPTURE_CHECKPOINT_PERIOD {
+                        recorder.checkpoint(&image);
+                        last_checkpoint = Instant::now();
+                    }
+                }
+
+                outputs
             }
             input_event = input_event_receiver.recv() => {
                 let input_event = input_event.ok_or_else(|| session::general_err!("GUI is stopped"))?;
@@ -536,17 +773,24 @@ async fn active_session(
                         if let Some(response_frame) = active_stage.encode_resize(width, height, Some(scale_factor), physical_size) {
                             vec![ActiveStageOutput::ResponseFrame(response_frame?)]
                         } else {
-                            // TODO(#271): use the "auto-reconnect cookie": https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/15b0d1c9-2891-4adb-a45e-deb4aeeeab7c
                             debug!("Reconnecting with new size");
                             let width = u16::try_from(width).expect("always in the range");
                             let height = u16::try_from(height).expect("always in the range");
-                            return Ok(RdpControlFlow::ReconnectWithNewSize { width, height })
+                            let auto_reconnect_cookie = active_stage.auto_reconnect_cookie().cloned();
+                            return Ok(RdpControlFlow::ReconnectWithNewSize { width, height, auto_reconnect_cookie })
                         }
                     },
                     RdpInputEvent::FastPath(events) => {
                         trace!(?events);
+                        if let Some(recorder) = recorder.as_deref_mut() {
+                            recorder.input(&events);
+                        }
                         active_stage.process_fastpath_input(&mut image, &events)?
                     }
+                    RdpInputEvent::SuppressOutput(suppress) => {
+                        debug!(suppress, "Suppress output");
+                        active_stage.suppress_output(suppress)?
+                    }
                     RdpInputEvent::Close => {
                         active_stage.graceful_shutdown()?
                     }
@@ -606,6 +850,16 @@ async fn active_session(
                     }
                 }
             }
+            _ = dvc_watchdog.tick() => {
+                // The stalls are reported in the logs.
+                let (frame, _stalls) = active_stage.poll_dvc_timeouts(DVC_WATCHDOG_PERIOD)?;
+
+                if frame.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![ActiveStageOutput::ResponseFrame(frame)]
+                }
+            }
         };
 
         for out in outputs {
@@ -662,9 +916,23 @@ async fn active_session(
                     debug!("Received Server Deactivate All PDU, executing Deactivation-Reactivation Sequence");
                     let mut buf = WriteBuf::new();
                     'activation_seq: loop {
-                        let written = single_sequence_step_read(&mut reader, &mut *connection_activation, &mut buf)
-                            .await
-                            .map_err(|e| session::custom_err!("read deactivation-reactivation sequence step", e))?;
+                        buf.clear();
+                        let written = if let Some(hint) = connection_activation.next_pdu_hint() {
+                            let pdu = reader
+                                .read_by_hint(hint)
+                                .await
+                                .map_err(|e| session::custom_err!("read deactivation-reactivation sequence step", e))?;
+
+                            // The replay goes through the sequence as well.
+                            if let Some(recorder) = recorder.as_deref_mut() {
+                                recorder.frame(Action::X224, &pdu);
+                            }
+
+                            connection_activation.step(&pdu, &mut buf)
+                        } else {
+                            connection_activation.step_no_input(&mut buf)
+                        }
+                        .map_err(|e| session::custom_err!("deactivation-reactivation sequence step", e))?;
 
                         if written.size().is_some() {
                             writer.write_all(buf.filled()).await.map_err(|e| {
@@ -694,10 +962,39 @@ async fn active_session(
                                 .build(),
                             );
                             active_stage.set_enable_server_pointer(enable_server_pointer);
+                            active_stage.set_relative_mouse_supported(connection_activation.relative_mouse_supported());
+                            active_stage.set_desktop_size(desktop_size);
                             break 'activation_seq;
                         }
                     }
                 }
+                ActiveStageOutput::Control(status) => {
+                    info!(?status, "Session control changed");
+                }
+                ActiveStageOutput::WindowOrders(orders) => {
+                    for order in orders {
+                        for event in remote_app_windows.apply(order) {
+                            let window_id = match event {
+                                WindowEvent::Created { window_id }
+                                | WindowEvent::Moved { window_id }
+                                | WindowEvent::Updated { window_id }
+                                | WindowEvent::Icon { window_id, .. }
+                                | WindowEvent::Taskbar { window_id } => Some(window_id),
+                                WindowEvent::Activated { window_id } => window_id,
+                                WindowEvent::Destroyed { .. } | WindowEvent::ZOrder | WindowEvent::Unmonitored => None,
+                            };
+                            let window = window_id.and_then(|id| remote_app_windows.window(id)).cloned();
+
+                            event_loop_proxy
+                                .send_event(RdpOutputEvent::RemoteApp { event, window })
+                                .map_err(|e| session::custom_err!("event_loop_proxy", e))?;
+                        }
+                    }
+                }
+                ActiveStageOutput::ServerRedirection(redirection) => {
+                    debug!(target = ?redirection.target_host(), "Server redirection");
+                    return Ok(RdpControlFlow::Redirect(redirection));
+                }
                 ActiveStageOutput::Terminate(reason) => break 'outer reason,
             }
         }
@@ -0,0 +1,200 @@
+//! Conversion between Windows clipboard formats and portable representations.
+//!
+//! Clients and servers running outside of Windows typically exchange UTF-8 text, HTML fragments and PNG images
+//! with their platform clipboard. This module maps those to the Windows formats announced on the `CLIPRDR`
+//! channel, so that the conversion does not need to be reimplemented for each platform.
+
+use crate::bitmap::{dib_to_png, dibv5_to_png, png_to_cf_dib, png_to_cf_dibv5, BitmapError};
+use crate::html::{cf_html_to_plain_html, plain_html_to_cf_html, HtmlError};
+use crate::text::{cf_unicodetext_to_utf8, utf8_to_cf_unicodetext, TextError};
+
+/// Name of the registered `CF_HTML` clipboard format
+pub const HTML_FORMAT_NAME: &str = "HTML Format";
+
+#[derive(Debug)]
+pub enum ConversionError {
+    Text(TextError),
+    Html(HtmlError),
+    Bitmap(BitmapError),
+    /// The content can't be represented in the requested format
+    Mismatch {
+        kind: ContentKind,
+        format: WindowsFormat,
+    },
+}
+
+impl core::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConversionError::Text(_error) => write!(f, "text conversion error"),
+            ConversionError::Html(_error) => write!(f, "HTML conversion error"),
+            ConversionError::Bitmap(_error) => write!(f, "bitmap conversion error"),
+            ConversionError::Mismatch { kind, format } => {
+                write!(f, "{kind:?} content can't be converted to {format:?}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for ConversionError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ConversionError::Text(error) => Some(error),
+            ConversionError::Html(error) => Some(error),
+            ConversionError::Bitmap(error) => Some(error),
+            ConversionError::Mismatch { .. } => None,
+        }
+    }
+}
+
+impl From<TextError> for ConversionError {
+    fn from(error: TextError) -> Self {
+        ConversionError::Text(error)
+    }
+}
+
+impl From<HtmlError> for ConversionError {
+    fn from(error: HtmlError) -> Self {
+        ConversionError::Html(error)
+    }
+}
+
+impl From<BitmapError> for ConversionError {
+    fn from(error: BitmapError) -> Self {
+        ConversionError::Bitmap(error)
+    }
+}
+
+/// Kind of portable clipboard content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentKind {
+    Text,
+    Html,
+    Png,
+}
+
+impl ContentKind {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ContentKind::Text => "text/plain",
+            ContentKind::Html => "text/html",
+            ContentKind::Png => "image/png",
+        }
+    }
+
+    /// Windows formats representing this kind of content, by order of preference
+    pub fn windows_formats(self) -> &'static [WindowsFormat] {
+        match self {
+            ContentKind::Text => &[WindowsFormat::UnicodeText],
+            ContentKind::Html => &[WindowsFormat::Html],
+            ContentKind::Png => &[WindowsFormat::DibV5, WindowsFormat::Dib],
+        }
+    }
+}
+
+/// Clipboard content in a portable representation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardContent {
+    /// UTF-8 text, with `\n` line endings
+    Text(String),
+    /// HTML fragment
+    Html(String),
+    /// PNG image
+    Png(Vec<u8>),
+}
+
+impl ClipboardContent {
+    pub fn kind(&self) -> ContentKind {
+        match self {
+            ClipboardContent::Text(_) => ContentKind::Text,
+            ClipboardContent::Html(_) => ContentKind::Html,
+            ClipboardContent::Png(_) => ContentKind::Png,
+        }
+    }
+
+    /// Decodes the data of a Windows clipboard format
+    pub fn from_windows_format(format: WindowsFormat, data: &[u8]) -> Result<Self, ConversionError> {
+        let content = match format {
+            WindowsFormat::UnicodeText => ClipboardContent::Text(cf_unicodetext_to_utf8(data)?),
+            WindowsFormat::Html => ClipboardContent::Html(cf_html_to_plain_html(data)?.to_owned()),
+            WindowsFormat::Dib => ClipboardContent::Png(dib_to_png(data)?),
+            WindowsFormat::DibV5 => ClipboardContent::Png(dibv5_to_png(data)?),
+        };
+
+        Ok(content)
+    }
+
+    /// Encodes the content as the data of a Windows clipboard format
+    pub fn to_windows_format(&self, format: WindowsFormat) -> Result<Vec<u8>, ConversionError> {
+        let data = match (self, format) {
+            (ClipboardContent::Text(text), WindowsFormat::UnicodeText) => utf8_to_cf_unicodetext(text),
+            (ClipboardContent::Html(html), WindowsFormat::Html) => plain_html_to_cf_html(html).into_bytes(),
+            (ClipboardContent::Png(png), WindowsFormat::Dib) => png_to_cf_dib(png)?,
+            (ClipboardContent::Png(png), WindowsFormat::DibV5) => png_to_cf_dibv5(png)?,
+            _ => {
+                return Err(ConversionError::Mismatch {
+                    kind: self.kind(),
+                    format,
+                })
+            }
+        };
+
+        Ok(data)
+    }
+}
+
+/// Windows clipboard formats with a portable representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowsFormat {
+    /// `CF_UNICODETEXT`, null-terminated UTF-16 text
+    UnicodeText,
+    /// `CF_HTML`, registered under [`HTML_FORMAT_NAME`]
+    Html,
+    /// `CF_DIB`, device independent bitmap
+    Dib,
+    /// `CF_DIBV5`, device independent bitmap with a V5 header
+    DibV5,
+}
+
+impl WindowsFormat {
+    const CF_DIB: u32 = 8;
+    const CF_UNICODETEXT: u32 = 13;
+    const CF_DIBV5: u32 = 17;
+
+    /// Identifies a format announced on the clipboard by its id, or its name for registered formats
+    pub fn from_format(id: u32, name: Option<&str>) -> Option<Self> {
+        match (id, name) {
+            (Self::CF_UNICODETEXT, _) => Some(WindowsFormat::UnicodeText),
+            (Self::CF_DIB, _) => Some(WindowsFormat::Dib),
+            (Self::CF_DIBV5, _) => Some(WindowsFormat::DibV5),
+            (_, Some(HTML_FORMAT_NAME)) => Some(WindowsFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// Id of a standard format, `None` for registered formats whose id is chosen when announced
+    pub fn standard_id(self) -> Option<u32> {
+        match self {
+            WindowsFormat::UnicodeText => Some(Self::CF_UNICODETEXT),
+            WindowsFormat::Html => None,
+            WindowsFormat::Dib => Some(Self::CF_DIB),
+            WindowsFormat::DibV5 => Some(Self::CF_DIBV5),
+        }
+    }
+
+    /// Name of a registered format
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            WindowsFormat::Html => Some(HTML_FORMAT_NAME),
+            WindowsFormat::UnicodeText | WindowsFormat::Dib | WindowsFormat::DibV5 => None,
+        }
+    }
+
+    pub fn content_kind(self) -> ContentKind {
+        match self {
+            WindowsFormat::UnicodeText => ContentKind::Text,
+            WindowsFormat::Html => ContentKind::Html,
+            WindowsFormat::Dib | WindowsFormat::DibV5 => ContentKind::Png,
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Download of the files copied on the remote clipboard into a local directory, e.g. from a "download from session"
+//! button.
+//!
+//! Once the `FileGroupDescriptorW` file list of the remote is received, a [`RemoteDownload`] requests the contents of
+//! the entries one after the other, writes them with [`FileDownload`], and reports the progress of the transfer as
+//! [`DownloadEvent`]s.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use ironrdp_cliprdr::pdu::{FileContentsRequest, FileContentsResponse, FileDescriptor, PackedFileList};
+
+use crate::pasteboard::{local_path, FileDownload};
+
+/// Progress of a [`RemoteDownload`], reported as the remote answers the requests
+#[derive(Debug)]
+pub enum DownloadEvent {
+    /// Bytes of the regular files written so far
+    Progress { transferred: u64, total: u64 },
+    /// An entry of the file list was written entirely
+    FileCompleted { path: PathBuf },
+    /// An entry couldn't be written, the download goes on with the next one
+    Failed { path: PathBuf, error: io::Error },
+    /// All the entries were processed
+    Completed,
+    /// The download was cancelled or replaced before completion
+    Cancelled,
+}
+
+/// Entries of a remote file list written in a local directory
+#[derive(Debug)]
+pub struct RemoteDownload {
+    stream_id: u32,
+    clip_data_id: Option<u32>,
+    /// Entries left to download, along with their local path
+    entries: VecDeque<(u32, FileDescriptor, PathBuf)>,
+    current: Option<(FileDownload, PathBuf)>,
+    /// Bytes of the entries downloaded entirely
+    transferred: u64,
+    /// Sizes known so far, from the file list or answered by the remote
+    total: u64,
+    cancelled: bool,
+    completed: bool,
+}
+
+impl RemoteDownload {
+    /// Prepares the download of all the entries of the file list into `directory`.
+    ///
+    /// Entries which can't be written safely in the directory are skipped, see [`local_path`]. All the requests are
+    /// made with `stream_id`, and `clip_data_id` is the lock of the remote clipboard data, if any.
+    pub fn new(
+        file_list: &PackedFileList,
+        directory: &Path,
+        stream_id: u32,
+        clip_data_id: Option<u32>,
+    ) -> io::Result<Self> {
+        let entries: VecDeque<_> = (0u32..)
+            .zip(&file_list.files)
+            .filter_map(|(index, descriptor)| {
+                let path = local_path(directory, &descriptor.name)?;
+                Some((index, descriptor.clone(), path))
+            })
+            .collect();
+
+        if entries.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no file to download"));
+        }
+
+        let total = entries
+            .iter()
+            .filter_map(|(_, descriptor, _)| descriptor.file_size)
+            .fold(0, u64::saturating_add);
+
+        Ok(Self {
+            stream_id,
+            clip_data_id,
+            entries,
+            current: None,
+            transferred: 0,
+            total,
+            cancelled: false,
+            completed: false,
+        })
+    }
+
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    /// Total size of the regular files, as known so far
+    pub fn total_size(&self) -> u64 {
+        self.total
+    }
+
+    /// Bytes of the regular files written so far
+    pub fn transferred(&self) -> u64 {
+        let current = self.current.as_ref().map_or(0, |(download, _)| download.written());
+
+        self.transferred.saturating_add(current)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    /// Starts writing the first entry, returns the first request to send to the remote
+    pub fn start(&mut self) -> (Option<FileContentsRequest>, Vec<DownloadEvent>) {
+        let mut events = Vec::new();
+        let request = self.next_request(&mut events);
+
+        (request, events)
+    }
+
+    /// Stops the transfer, the file being written is removed.
+    ///
+    /// Returns [`DownloadEvent::Cancelled`] unless the download was already completed or cancelled.
+    pub fn cancel(&mut self) -> Option<DownloadEvent> {
+        if self.cancelled || self.completed {
+            return None;
+        }
+
+        self.cancelled = true;
+        self.entries.clear();
+        self.discard_current();
+
+        Some(DownloadEvent::Cancelled)
+    }
+
+    /// Handles the response of the remote to the last request, returns the next request along with the progress made
+    pub fn on_file_contents_response(
+        &mut self,
+        response: &FileContentsResponse<'_>,
+    ) -> (Option<FileContentsRequest>, Vec<DownloadEvent>) {
+        let Some((download, _)) = self.current.as_mut() else {
+            // Out-of-order message, ignore it.
+            return (None, Vec::new());
+        };
+
+        let mut events = Vec::new();
+
+        let size_known = download.size().is_some();
+        let result = download.on_response(response);
+        let size = download.size();
+
+        match result {
+            Ok(()) if size_known => events.push(DownloadEvent::Progress {
+                transferred: self.transferred(),
+                total: self.total,
+            }),
+            Ok(()) => {
+                // The size is answered by the remote when missing from the file list
+                self.total = self.total.saturating_add(size.unwrap_or(0));
+            }
+            Err(error) => {
+                if let Some(path) = self.discard_current() {
+                    events.push(DownloadEvent::Failed { path, error });
+                }
+            }
+        }
+
+        let request = self.next_request(&mut events);
+
+        (request, events)
+    }
+
+    /// Request for the current entry, moving on to the next entries once it is written
+    fn next_request(&mut self, events: &mut Vec<DownloadEvent>) -> Option<FileContentsRequest> {
+        if self.cancelled || self.completed {
+            return None;
+        }
+
+        loop {
+            if let Some((download, path)) = self.current.take() {
+                if let Some(request) = download.next_request() {
+                    self.current = Some((download, path));
+                    return Some(request);
+                }
+
+                self.transferred = self.transferred.saturating_add(download.written());
+                events.push(DownloadEvent::FileCompleted { path });
+            }
+
+            let Some((index, descriptor, path)) = self.entries.pop_front() else {
+                self.completed = true;
+                events.push(DownloadEvent::Completed);
+                return None;
+            };
+
+            match FileDownload::new(self.stream_id, index, &descriptor, &path, self.clip_data_id) {
+                Ok(download) => self.current = Some((download, path)),
+                Err(error) => events.push(DownloadEvent::Failed { path, error }),
+            }
+        }
+    }
+
+    /// Drops the entry being written, removing the partially written file
+    fn discard_current(&mut self) -> Option<PathBuf> {
+        let (download, path) = self.current.take()?;
+
+        let is_partial = !download.is_complete();
+        // The file is closed before being removed
+        drop(download);
+        if is_partial {
+            let _ = fs::remove_file(&path);
+        }
+
+        Some(path)
+    }
+}
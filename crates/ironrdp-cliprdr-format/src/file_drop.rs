@@ -0,0 +1,197 @@
+//! Injection of local files into the session, e.g. when files are dropped onto the client window.
+//!
+//! The dropped files are announced on the remote clipboard as a `FileGroupDescriptorW` file list, ready to be pasted
+//! in the session. A [`FileDrop`] answers the requests of the remote for the file list and the file contents, and
+//! reports the progress of the transfer as [`FileDropEvent`]s.
+
+use std::io;
+use std::path::PathBuf;
+
+use ironrdp_cliprdr::pdu::{
+    ClipboardFileAttributes, ClipboardFormat, ClipboardFormatId, FileContentsFlags, FileContentsRequest,
+    FileContentsResponse, FormatDataResponse,
+};
+use ironrdp_core::EncodeResult;
+
+use crate::mime::{local_file_list, packed_file_list, read_local_file_contents, LocalFile, TargetKind};
+
+/// Progress of a [`FileDrop`], reported as the remote reads the files
+#[derive(Debug)]
+pub enum FileDropEvent {
+    /// Bytes of the regular files read by the remote so far
+    Progress { transferred: u64, total: u64 },
+    /// A file was read entirely by the remote
+    FileCompleted { path: PathBuf },
+    /// All the files were read entirely by the remote
+    Completed,
+    /// A file couldn't be read, the remote received an error
+    Failed { path: PathBuf, error: io::Error },
+    /// The drop was cancelled or replaced before completion
+    Cancelled,
+}
+
+/// Local files exposed to the remote as a clipboard file list
+#[derive(Debug)]
+pub struct FileDrop {
+    files: Vec<LocalFile>,
+    /// Furthest position read by the remote in each file
+    transferred: Vec<u64>,
+    cancelled: bool,
+    completed: bool,
+}
+
+impl FileDrop {
+    /// Lists the dropped files, walking the directories recursively
+    pub fn new(paths: &[PathBuf]) -> io::Result<Self> {
+        let files = local_file_list(paths)?;
+        if files.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no file to drop"));
+        }
+
+        let transferred = vec![0; files.len()];
+        let mut file_drop = Self {
+            files,
+            transferred,
+            cancelled: false,
+            completed: false,
+        };
+        // Nothing is read from directories and empty files
+        file_drop.completed = file_drop.is_transferred();
+
+        Ok(file_drop)
+    }
+
+    pub fn files(&self) -> &[LocalFile] {
+        &self.files
+    }
+
+    /// Formats to announce with `ClipboardMessage::SendInitiateCopy`
+    pub fn formats(&self) -> Vec<ClipboardFormat> {
+        TargetKind::FileList.formats()
+    }
+
+    /// Whether a format data request of the remote is for the file list of the drop
+    pub fn is_file_list_format(&self, format: ClipboardFormatId) -> bool {
+        self.formats().iter().any(|file_list| file_list.id == format)
+    }
+
+    /// Data of the file list, answering the format data request of the remote
+    pub fn file_list_response(&self) -> EncodeResult<FormatDataResponse<'static>> {
+        FormatDataResponse::new_file_list(&packed_file_list(&self.files))
+    }
+
+    /// Total size of the regular files
+    pub fn total_size(&self) -> u64 {
+        self.files
+            .iter()
+            .filter_map(|file| file.descriptor.file_size)
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Bytes of the regular files read by the remote so far
+    pub fn transferred(&self) -> u64 {
+        self.transferred
+            .iter()
+            .fold(0, |total, size| total.saturating_add(*size))
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Stops the transfer, the following requests of the remote are answered with errors.
+    ///
+    /// Returns [`FileDropEvent::Cancelled`] unless the drop was already completed or cancelled.
+    pub fn cancel(&mut self) -> Option<FileDropEvent> {
+        if self.cancelled || self.completed {
+            return None;
+        }
+
+        self.cancelled = true;
+
+        Some(FileDropEvent::Cancelled)
+    }
+
+    /// Answers a file contents request of the remote, along with the progress made
+    pub fn on_file_contents_request(
+        &mut self,
+        request: &FileContentsRequest,
+    ) -> (FileContentsResponse<'static>, Vec<FileDropEvent>) {
+        if self.cancelled {
+            return (FileContentsResponse::new_error(request.stream_id), Vec::new());
+        }
+
+        let response = match read_local_file_contents(&self.files, request) {
+            Ok(response) => response,
+            Err(error) => {
+                let events = self
+                    .file_path(request.index)
+                    .map(|path| FileDropEvent::Failed { path, error })
+                    .into_iter()
+                    .collect();
+
+                return (FileContentsResponse::new_error(request.stream_id), events);
+            }
+        };
+
+        let mut events = Vec::new();
+        if request.flags.contains(FileContentsFlags::DATA) {
+            self.on_data_read(request, response.data().len(), &mut events);
+        }
+
+        (response, events)
+    }
+
+    fn on_data_read(&mut self, request: &FileContentsRequest, read: usize, events: &mut Vec<FileDropEvent>) {
+        let Some(index) = usize::try_from(request.index).ok() else {
+            return;
+        };
+        let (Some(file), Some(transferred)) = (self.files.get(index), self.transferred.get_mut(index)) else {
+            return;
+        };
+
+        let end = u64::try_from(read)
+            .ok()
+            .and_then(|read| request.position.checked_add(read))
+            .unwrap_or(u64::MAX);
+        let size = file.descriptor.file_size.unwrap_or(0);
+
+        // Chunks may be read again, only the furthest position counts
+        let was_complete = *transferred >= size;
+        *transferred = (*transferred).max(end.min(size));
+        if *transferred == size && !was_complete {
+            events.push(FileDropEvent::FileCompleted {
+                path: file.path.clone(),
+            });
+        }
+
+        events.push(FileDropEvent::Progress {
+            transferred: self.transferred(),
+            total: self.total_size(),
+        });
+
+        if !self.completed && self.is_transferred() {
+            self.completed = true;
+            events.push(FileDropEvent::Completed);
+        }
+    }
+
+    fn is_transferred(&self) -> bool {
+        self.files
+            .iter()
+            .zip(&self.transferred)
+            .all(|(file, transferred)| is_directory(file) || *transferred >= file.descriptor.file_size.unwrap_or(0))
+    }
+
+    fn file_path(&self, index: u32) -> Option<PathBuf> {
+        let file = self.files.get(usize::try_from(index).ok()?)?;
+
+        Some(file.path.clone())
+    }
+}
+
+fn is_directory(file: &LocalFile) -> bool {
+    file.descriptor
+        .attributes
+        .is_some_and(|attributes| attributes.contains(ClipboardFileAttributes::DIRECTORY))
+}
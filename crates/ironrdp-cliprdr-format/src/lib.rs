@@ -2,4 +2,10 @@
 #![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
 
 pub mod bitmap;
+pub mod convert;
+pub mod download;
+pub mod file_drop;
 pub mod html;
+pub mod mime;
+pub mod pasteboard;
+pub mod text;
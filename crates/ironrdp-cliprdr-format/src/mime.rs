@@ -0,0 +1,369 @@
+//! Mapping between the targets of the X11 and Wayland clipboards and the Windows clipboard formats.
+//!
+//! X11 selections and Wayland data offers announce their content as a list of targets: MIME types such as
+//! `image/png`, or legacy X11 atoms such as `UTF8_STRING`. This module maps those targets to the formats
+//! exchanged with a [`CliprdrBackend`](ironrdp_cliprdr::backend::CliprdrBackend):
+//!
+//! - [`formats_for_targets`] builds the format list sent with `ClipboardMessage::SendInitiateCopy` when the local
+//!   clipboard changes.
+//! - [`targets_for_formats`] lists the targets to offer locally in `on_remote_copy`.
+//! - [`format_for_target`] picks the remote format to request with `ClipboardMessage::SendInitiatePaste` when a
+//!   local application asks for a target.
+//!
+//! Text, HTML and images are converted with [`content_from_target_data`] and [`content_to_target_data`].
+//! Files are announced as `text/uri-list` locally and as a `FileGroupDescriptorW` file list on the remote, see
+//! [`local_file_list`] and [`file_list_to_uri_list`].
+
+use std::fs;
+use std::io::{self, Read as _, Seek as _, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ironrdp_cliprdr::pdu::{
+    ClipboardFileAttributes, ClipboardFormat, ClipboardFormatId, ClipboardFormatName, FileContentsFlags,
+    FileContentsRequest, FileContentsResponse, FileDescriptor, PackedFileList,
+};
+
+use crate::convert::{ClipboardContent, ContentKind, WindowsFormat};
+
+/// Id under which `HTML Format` is announced by [`formats_for_targets`]
+pub const HTML_FORMAT_ID: ClipboardFormatId = ClipboardFormatId(0xC001);
+
+/// Id under which `FileGroupDescriptorW` is announced by [`formats_for_targets`]
+pub const FILE_LIST_FORMAT_ID: ClipboardFormatId = ClipboardFormatId(0xC002);
+
+/// Target of a list of file URIs
+pub const URI_LIST_TARGET: &str = "text/uri-list";
+
+/// Seconds between the Windows epoch (1601-01-01) and the Unix epoch
+const WINDOWS_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+/// Kind of content behind a clipboard target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TargetKind {
+    /// Content converted with [`ClipboardContent`]
+    Content(ContentKind),
+    /// List of files, `text/uri-list` locally and `FileGroupDescriptorW` on the remote
+    FileList,
+}
+
+impl TargetKind {
+    /// Identifies a target announced on the local clipboard
+    pub fn from_target(target: &str) -> Option<Self> {
+        [
+            TargetKind::Content(ContentKind::Text),
+            TargetKind::Content(ContentKind::Html),
+            TargetKind::Content(ContentKind::Png),
+            TargetKind::FileList,
+        ]
+        .into_iter()
+        .find(|kind| kind.targets().iter().any(|known| known.eq_ignore_ascii_case(target)))
+    }
+
+    /// Identifies a format announced on the remote clipboard
+    pub fn from_format(format: &ClipboardFormat) -> Option<Self> {
+        if format.name.as_ref() == Some(&ClipboardFormatName::FILE_LIST) {
+            return Some(TargetKind::FileList);
+        }
+
+        let name = format.name.as_ref().map(ClipboardFormatName::value);
+        WindowsFormat::from_format(format.id.value(), name).map(|format| TargetKind::Content(format.content_kind()))
+    }
+
+    /// Targets offered on the local clipboard for this kind of content, by order of preference
+    pub fn targets(self) -> &'static [&'static str] {
+        match self {
+            TargetKind::Content(ContentKind::Text) => &[
+                "text/plain;charset=utf-8",
+                "UTF8_STRING",
+                "text/plain",
+                "STRING",
+                "TEXT",
+            ],
+            TargetKind::Content(ContentKind::Html) => &["text/html"],
+            TargetKind::Content(ContentKind::Png) => &["image/png"],
+            TargetKind::FileList => &[URI_LIST_TARGET],
+        }
+    }
+
+    /// Formats announced on the remote clipboard for this kind of content, by order of preference
+    pub fn formats(self) -> Vec<ClipboardFormat> {
+        match self {
+            TargetKind::Content(kind) => kind
+                .windows_formats()
+                .iter()
+                .map(|format| match format {
+                    WindowsFormat::UnicodeText => ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT),
+                    WindowsFormat::Html => ClipboardFormat::new(HTML_FORMAT_ID).with_name(ClipboardFormatName::HTML),
+                    WindowsFormat::Dib => ClipboardFormat::new(ClipboardFormatId::CF_DIB),
+                    WindowsFormat::DibV5 => ClipboardFormat::new(ClipboardFormatId::CF_DIBV5),
+                })
+                .collect(),
+            TargetKind::FileList => {
+                vec![ClipboardFormat::new(FILE_LIST_FORMAT_ID).with_name(ClipboardFormatName::FILE_LIST)]
+            }
+        }
+    }
+}
+
+/// Formats to announce to the remote for the targets of the local clipboard.
+///
+/// Unknown targets are skipped, as well as targets of the same kind of content.
+pub fn formats_for_targets(targets: &[&str]) -> Vec<ClipboardFormat> {
+    let mut kinds = Vec::new();
+    for kind in targets.iter().filter_map(|target| TargetKind::from_target(target)) {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+
+    kinds.into_iter().flat_map(TargetKind::formats).collect()
+}
+
+/// Targets to offer on the local clipboard for the formats announced by the remote.
+pub fn targets_for_formats(formats: &[ClipboardFormat]) -> Vec<&'static str> {
+    let mut targets = Vec::new();
+    for kind in formats.iter().filter_map(TargetKind::from_format) {
+        for target in kind.targets() {
+            if !targets.contains(target) {
+                targets.push(*target);
+            }
+        }
+    }
+
+    targets
+}
+
+/// Remote format to request when a local application asks for the target.
+///
+/// The formats are the ones announced by the remote, the preferred format of the content is picked.
+pub fn format_for_target<'a>(target: &str, formats: &'a [ClipboardFormat]) -> Option<&'a ClipboardFormat> {
+    let kind = TargetKind::from_target(target)?;
+
+    match kind {
+        TargetKind::Content(kind) => kind.windows_formats().iter().find_map(|preferred| {
+            formats.iter().find(|format| {
+                let name = format.name.as_ref().map(ClipboardFormatName::value);
+                WindowsFormat::from_format(format.id.value(), name) == Some(*preferred)
+            })
+        }),
+        TargetKind::FileList => formats
+            .iter()
+            .find(|format| TargetKind::from_format(format) == Some(TargetKind::FileList)),
+    }
+}
+
+/// Reads the data of a local target.
+///
+/// Text targets are expected to be UTF-8, invalid sequences are replaced. Returns `None` for targets which aren't
+/// text, HTML or images.
+pub fn content_from_target_data(target: &str, data: &[u8]) -> Option<ClipboardContent> {
+    let content = match TargetKind::from_target(target)? {
+        TargetKind::Content(ContentKind::Text) => ClipboardContent::Text(String::from_utf8_lossy(data).into_owned()),
+        TargetKind::Content(ContentKind::Html) => ClipboardContent::Html(String::from_utf8_lossy(data).into_owned()),
+        TargetKind::Content(ContentKind::Png) => ClipboardContent::Png(data.to_vec()),
+        TargetKind::FileList => return None,
+    };
+
+    Some(content)
+}
+
+/// Data to hand over to the local application for the content.
+pub fn content_to_target_data(content: &ClipboardContent) -> Vec<u8> {
+    match content {
+        ClipboardContent::Text(text) | ClipboardContent::Html(text) => text.as_bytes().to_vec(),
+        ClipboardContent::Png(png) => png.clone(),
+    }
+}
+
+/// Parses a `text/uri-list`, returning the paths of its `file://` URIs.
+///
+/// Comments and URIs of other schemes or hosts are skipped.
+pub fn uri_list_to_paths(uri_list: &str) -> Vec<PathBuf> {
+    uri_list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|uri| uri.strip_prefix("file://"))
+        .filter_map(|uri| {
+            // The host is either empty or `localhost`
+            let path = uri.strip_prefix("localhost").unwrap_or(uri);
+            if !path.starts_with('/') {
+                return None;
+            }
+
+            percent_decode(path).map(PathBuf::from)
+        })
+        .collect()
+}
+
+/// Builds a `text/uri-list` of `file://` URIs.
+///
+/// Paths which aren't absolute or valid UTF-8 are skipped.
+pub fn paths_to_uri_list(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .filter(|path| path.is_absolute())
+        .filter_map(|path| path.to_str())
+        .map(|path| format!("file://{}\r\n", percent_encode(path)))
+        .collect()
+}
+
+/// Builds a `text/uri-list` of the files announced by the remote, once received in `directory`.
+///
+/// `directory` is typically the [`temporary_directory`](ironrdp_cliprdr::backend::CliprdrBackend::temporary_directory)
+/// of the backend. Only the top-level entries are listed, the content of the directories being nested in them.
+pub fn file_list_to_uri_list(file_list: &PackedFileList, directory: &Path) -> String {
+    let paths: Vec<PathBuf> = file_list
+        .files
+        .iter()
+        .filter(|file| !file.name.contains('\\'))
+        .map(|file| directory.join(&file.name))
+        .collect();
+
+    paths_to_uri_list(&paths)
+}
+
+/// Local file announced to the remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalFile {
+    /// Path on the local file system, to read when the remote requests the file contents
+    pub path: PathBuf,
+    pub descriptor: FileDescriptor,
+}
+
+/// Describes the local files to announce as a `FileGroupDescriptorW` file list.
+///
+/// Directories are walked recursively, their entries being named relatively to the top-level directory with `\`
+/// separators as expected by Windows. The index of a file in the list is the one used by the remote to request its
+/// contents.
+pub fn local_file_list(paths: &[PathBuf]) -> io::Result<Vec<LocalFile>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "file name is not valid UTF-8"))?;
+
+        push_local_file(&mut files, path.clone(), name.to_owned())?;
+    }
+
+    Ok(files)
+}
+
+/// Packs the descriptors of the local files as the data of a `FileGroupDescriptorW` format.
+pub fn packed_file_list(files: &[LocalFile]) -> PackedFileList {
+    PackedFileList {
+        files: files.iter().map(|file| file.descriptor.clone()).collect(),
+    }
+}
+
+/// Reads the size or a chunk of a local file, as requested by the remote.
+///
+/// `files` is the list announced with [`packed_file_list`], the file is looked up by its index.
+pub fn read_local_file_contents(
+    files: &[LocalFile],
+    request: &FileContentsRequest,
+) -> io::Result<FileContentsResponse<'static>> {
+    let file = usize::try_from(request.index)
+        .ok()
+        .and_then(|index| files.get(index))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown file index"))?;
+
+    if request.flags.contains(FileContentsFlags::SIZE) {
+        let size = fs::metadata(&file.path)?.len();
+        return Ok(FileContentsResponse::new_size_response(request.stream_id, size));
+    }
+
+    let mut reader = fs::File::open(&file.path)?;
+    reader.seek(SeekFrom::Start(request.position))?;
+
+    let mut data = Vec::new();
+    reader.take(u64::from(request.requested_size)).read_to_end(&mut data)?;
+
+    Ok(FileContentsResponse::new_data_response(request.stream_id, data))
+}
+
+fn push_local_file(files: &mut Vec<LocalFile>, path: PathBuf, name: String) -> io::Result<()> {
+    let metadata = fs::metadata(&path)?;
+
+    let mut attributes = ClipboardFileAttributes::empty();
+    if metadata.is_dir() {
+        attributes |= ClipboardFileAttributes::DIRECTORY;
+    }
+    if metadata.permissions().readonly() {
+        attributes |= ClipboardFileAttributes::READONLY;
+    }
+    if attributes.is_empty() {
+        attributes = ClipboardFileAttributes::NORMAL;
+    }
+
+    let descriptor = FileDescriptor {
+        attributes: Some(attributes),
+        last_write_time: metadata.modified().ok().and_then(file_time),
+        file_size: metadata.is_file().then_some(metadata.len()),
+        name: name.clone(),
+    };
+    files.push(LocalFile {
+        path: path.clone(),
+        descriptor,
+    });
+
+    if metadata.is_dir() {
+        let mut entries = fs::read_dir(&path)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let Some(entry_name) = entry.file_name().to_str().map(str::to_owned) else {
+                continue;
+            };
+
+            push_local_file(files, entry.path(), format!("{name}\\{entry_name}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a time to a Windows `FILETIME`, in 100-nanosecond intervals since 1601-01-01
+fn file_time(time: SystemTime) -> Option<u64> {
+    let since_unix_epoch = time.duration_since(UNIX_EPOCH).ok()?;
+
+    since_unix_epoch
+        .as_secs()
+        .checked_add(WINDOWS_EPOCH_OFFSET)?
+        .checked_mul(10_000_000)?
+        .checked_add(u64::from(since_unix_epoch.subsec_nanos() / 100))
+}
+
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'/' | b'-' | b'_' | b'.' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    encoded
+}
+
+fn percent_decode(uri: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(uri.len());
+    let mut bytes = uri.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let high = char::from(bytes.next()?).to_digit(16)?;
+            let low = char::from(bytes.next()?).to_digit(16)?;
+            decoded.push(u8::try_from(high * 16 + low).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
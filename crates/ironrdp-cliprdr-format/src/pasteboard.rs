@@ -0,0 +1,336 @@
+//! Mapping between the macOS pasteboard types and the Windows clipboard formats.
+//!
+//! Items of the `NSPasteboard` announce their content as uniform type identifiers such as `public.png`. This module
+//! maps those types to the formats exchanged with a [`CliprdrBackend`](ironrdp_cliprdr::backend::CliprdrBackend), the
+//! same way [`mime`](crate::mime) does for the X11 and Wayland targets:
+//!
+//! - [`formats_for_pasteboard_types`] builds the format list sent when the local pasteboard changes.
+//! - [`pasteboard_types_for_formats`] lists the types to declare locally in `on_remote_copy`.
+//! - [`format_for_pasteboard_type`] picks the remote format to request when a local application reads a type.
+//!
+//! Local files are read from the `public.file-url` type of the pasteboard items, and announced with
+//! [`local_file_list`](crate::mime::local_file_list). Remote files are offered as file promises, one per top-level
+//! entry of the remote file list (see [`promised_files`]), each written with [`FileDownload`] once the promise is
+//! fulfilled.
+
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::{Component, Path, PathBuf};
+
+use ironrdp_cliprdr::pdu::{
+    ClipboardFileAttributes, ClipboardFormat, FileContentsFlags, FileContentsRequest, FileContentsResponse,
+    FileDescriptor, PackedFileList,
+};
+
+use crate::convert::{ClipboardContent, ContentKind};
+use crate::mime::{content_to_target_data, format_for_target, uri_list_to_paths, TargetKind};
+
+/// Type of UTF-8 text (`NSPasteboardTypeString`)
+pub const TEXT_TYPE: &str = "public.utf8-plain-text";
+
+/// Type of HTML (`NSPasteboardTypeHTML`)
+pub const HTML_TYPE: &str = "public.html";
+
+/// Type of PNG images (`NSPasteboardTypePNG`)
+pub const PNG_TYPE: &str = "public.png";
+
+/// Type of file URLs (`NSPasteboardTypeFileURL`)
+pub const FILE_URL_TYPE: &str = "public.file-url";
+
+/// Type of the promised regular files
+pub const DATA_TYPE: &str = "public.data";
+
+/// Type of the promised directories
+pub const FOLDER_TYPE: &str = "public.folder";
+
+/// Size of the chunks of file contents requested to the remote
+pub const FILE_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Identifies a type announced on the local pasteboard
+pub fn pasteboard_type_kind(pasteboard_type: &str) -> Option<TargetKind> {
+    let kind = match pasteboard_type {
+        TEXT_TYPE => TargetKind::Content(ContentKind::Text),
+        HTML_TYPE => TargetKind::Content(ContentKind::Html),
+        PNG_TYPE => TargetKind::Content(ContentKind::Png),
+        FILE_URL_TYPE => TargetKind::FileList,
+        _ => return None,
+    };
+
+    Some(kind)
+}
+
+/// Type declared on the local pasteboard for a kind of content
+pub fn pasteboard_type(kind: TargetKind) -> &'static str {
+    match kind {
+        TargetKind::Content(ContentKind::Text) => TEXT_TYPE,
+        TargetKind::Content(ContentKind::Html) => HTML_TYPE,
+        TargetKind::Content(ContentKind::Png) => PNG_TYPE,
+        TargetKind::FileList => FILE_URL_TYPE,
+    }
+}
+
+/// Formats to announce to the remote for the types of the local pasteboard.
+///
+/// Unknown types are skipped, as well as types of the same kind of content.
+pub fn formats_for_pasteboard_types(types: &[&str]) -> Vec<ClipboardFormat> {
+    let mut kinds = Vec::new();
+    for kind in types
+        .iter()
+        .filter_map(|pasteboard_type| pasteboard_type_kind(pasteboard_type))
+    {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+
+    kinds.into_iter().flat_map(TargetKind::formats).collect()
+}
+
+/// Types to declare on the local pasteboard for the formats announced by the remote.
+///
+/// Files are not listed: they are offered as file promises rather than as data of a type.
+pub fn pasteboard_types_for_formats(formats: &[ClipboardFormat]) -> Vec<&'static str> {
+    let mut types = Vec::new();
+    for kind in formats.iter().filter_map(TargetKind::from_format) {
+        if kind == TargetKind::FileList {
+            continue;
+        }
+
+        let pasteboard_type = pasteboard_type(kind);
+        if !types.contains(&pasteboard_type) {
+            types.push(pasteboard_type);
+        }
+    }
+
+    types
+}
+
+/// Remote format to request when a local application reads the type.
+pub fn format_for_pasteboard_type<'a>(
+    pasteboard_type: &str,
+    formats: &'a [ClipboardFormat],
+) -> Option<&'a ClipboardFormat> {
+    let kind = pasteboard_type_kind(pasteboard_type)?;
+
+    // The preferred target of the kind is enough to find the preferred format.
+    format_for_target(kind.targets().first()?, formats)
+}
+
+/// Reads the data of a type of the local pasteboard.
+///
+/// Returns `None` for types which aren't text, HTML or images.
+pub fn content_from_pasteboard_data(pasteboard_type: &str, data: &[u8]) -> Option<ClipboardContent> {
+    let content = match pasteboard_type_kind(pasteboard_type)? {
+        TargetKind::Content(ContentKind::Text) => ClipboardContent::Text(String::from_utf8_lossy(data).into_owned()),
+        TargetKind::Content(ContentKind::Html) => ClipboardContent::Html(String::from_utf8_lossy(data).into_owned()),
+        TargetKind::Content(ContentKind::Png) => ClipboardContent::Png(data.to_vec()),
+        TargetKind::FileList => return None,
+    };
+
+    Some(content)
+}
+
+/// Data to set on the local pasteboard for the content, under [`pasteboard_type`] of its kind.
+pub fn content_to_pasteboard_data(content: &ClipboardContent) -> Vec<u8> {
+    // Pasteboard types hold the same UTF-8 and PNG data as the MIME targets.
+    content_to_target_data(content)
+}
+
+/// Paths of the file URLs read from the `public.file-url` type of the pasteboard items
+pub fn file_urls_to_paths(file_urls: &[&str]) -> Vec<PathBuf> {
+    file_urls
+        .iter()
+        .flat_map(|file_url| uri_list_to_paths(file_url))
+        .collect()
+}
+
+/// Top-level entry of a remote file list, offered as a file promise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromisedFile {
+    /// Name of the file or the directory
+    pub name: String,
+    /// Whether the entry is a directory, promised with [`FOLDER_TYPE`] rather than [`DATA_TYPE`]
+    pub is_directory: bool,
+    /// Indices in the remote file list of the entry and the entries nested in it
+    pub entries: Vec<u32>,
+}
+
+/// Groups the entries of a remote file list by top-level entry.
+///
+/// Entries which can't be written safely in a local directory are skipped, see [`local_path`].
+pub fn promised_files(file_list: &PackedFileList) -> Vec<PromisedFile> {
+    let mut promised: Vec<PromisedFile> = Vec::new();
+
+    for (index, file) in (0u32..).zip(&file_list.files) {
+        if local_path(Path::new(""), &file.name).is_none() {
+            continue;
+        }
+
+        match file.name.split_once('\\') {
+            None => promised.push(PromisedFile {
+                name: file.name.clone(),
+                is_directory: is_directory(file),
+                entries: vec![index],
+            }),
+            Some((top_level, _)) => {
+                if let Some(parent) = promised.iter_mut().find(|promised| promised.name == top_level) {
+                    parent.entries.push(index);
+                }
+            }
+        }
+    }
+
+    promised
+}
+
+/// Path where an entry of a remote file list is written in a local directory.
+///
+/// Windows separators are converted, and names escaping the directory are rejected.
+pub fn local_path(directory: &Path, name: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(name.replace('\\', "/"));
+
+    let is_safe = relative.components().count() > 0
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+    is_safe.then(|| directory.join(relative))
+}
+
+fn is_directory(file: &FileDescriptor) -> bool {
+    file.attributes
+        .is_some_and(|attributes| attributes.contains(ClipboardFileAttributes::DIRECTORY))
+}
+
+/// Writes an entry of a remote file list on the local file system, chunk by chunk.
+///
+/// The contents are requested with [`FileDownload::next_request`], and each response is handed over to
+/// [`FileDownload::on_response`], until the download is complete.
+#[derive(Debug)]
+pub struct FileDownload {
+    stream_id: u32,
+    index: u32,
+    clip_data_id: Option<u32>,
+    /// Size of the file, requested to the remote when unknown from the file list
+    size: Option<u64>,
+    written: u64,
+    /// `None` for directories, which have no contents
+    file: Option<fs::File>,
+}
+
+impl FileDownload {
+    /// Creates the entry `index` of the file list at `path`.
+    ///
+    /// `clip_data_id` is the lock of the remote clipboard data, if any.
+    pub fn new(
+        stream_id: u32,
+        index: u32,
+        descriptor: &FileDescriptor,
+        path: &Path,
+        clip_data_id: Option<u32>,
+    ) -> io::Result<Self> {
+        let (size, file) = if is_directory(descriptor) {
+            fs::create_dir_all(path)?;
+            (Some(0), None)
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            (descriptor.file_size, Some(fs::File::create(path)?))
+        };
+
+        Ok(Self {
+            stream_id,
+            index,
+            clip_data_id,
+            size,
+            written: 0,
+            file,
+        })
+    }
+
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    /// Size of the file, `None` until answered by the remote when unknown from the file list
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Bytes of the file written so far
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Whether all the contents of the file were written
+    pub fn is_complete(&self) -> bool {
+        self.size == Some(self.written)
+    }
+
+    /// Request for the size of the file or its next chunk, `None` once complete
+    pub fn next_request(&self) -> Option<FileContentsRequest> {
+        let (flags, position, requested_size) = match self.size {
+            None => (FileContentsFlags::SIZE, 0, 8),
+            Some(size) if size > self.written => {
+                let remaining = size - self.written;
+                let requested_size =
+                    u32::try_from(remaining).map_or(FILE_CHUNK_SIZE, |remaining| remaining.min(FILE_CHUNK_SIZE));
+                (FileContentsFlags::DATA, self.written, requested_size)
+            }
+            Some(_) => return None,
+        };
+
+        Some(FileContentsRequest {
+            stream_id: self.stream_id,
+            index: self.index,
+            flags,
+            position,
+            requested_size,
+            data_id: self.clip_data_id,
+        })
+    }
+
+    /// Handles the response of the remote to the last request
+    pub fn on_response(&mut self, response: &FileContentsResponse<'_>) -> io::Result<()> {
+        if response.stream_id() != self.stream_id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected file contents stream",
+            ));
+        }
+
+        if response.is_error() {
+            return Err(io::Error::other("remote failed to read the file contents"));
+        }
+
+        let Some(size) = self.size else {
+            let size = response
+                .data_as_size()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.size = Some(size);
+            return Ok(());
+        };
+
+        let data = response.data();
+        let written = self
+            .written
+            .checked_add(u64::try_from(data.len()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)
+            .filter(|written| *written <= size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "file contents exceed the file size"))?;
+
+        if data.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "file contents ended early",
+            ));
+        }
+
+        if let Some(file) = &mut self.file {
+            file.write_all(data)?;
+        }
+        self.written = written;
+
+        Ok(())
+    }
+}
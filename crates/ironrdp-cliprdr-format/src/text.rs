@@ -0,0 +1,69 @@
+#[derive(Debug)]
+pub enum TextError {
+    OddLength,
+    InvalidUtf16(std::string::FromUtf16Error),
+}
+
+impl core::fmt::Display for TextError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TextError::OddLength => write!(f, "CF_UNICODETEXT data has an odd length"),
+            TextError::InvalidUtf16(_error) => write!(f, "invalid UTF-16"),
+        }
+    }
+}
+
+impl core::error::Error for TextError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            TextError::OddLength => None,
+            TextError::InvalidUtf16(error) => Some(error),
+        }
+    }
+}
+
+impl From<std::string::FromUtf16Error> for TextError {
+    fn from(error: std::string::FromUtf16Error) -> Self {
+        TextError::InvalidUtf16(error)
+    }
+}
+
+/// Converts `CF_UNICODETEXT` format to UTF-8 text.
+///
+/// The text ends at the first null character, if any, and Windows line endings (`\r\n`) are converted to `\n`.
+pub fn cf_unicodetext_to_utf8(input: &[u8]) -> Result<String, TextError> {
+    let chunks = input.chunks_exact(2);
+
+    if !chunks.remainder().is_empty() {
+        return Err(TextError::OddLength);
+    }
+
+    let units: Vec<u16> = chunks
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect();
+
+    let text = String::from_utf16(&units)?;
+
+    Ok(text.replace("\r\n", "\n"))
+}
+
+/// Converts UTF-8 text to null-terminated `CF_UNICODETEXT` format, with Windows line endings (`\r\n`).
+pub fn utf8_to_cf_unicodetext(text: &str) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(text.len());
+    let mut previous = None;
+
+    let mut push = |unit: u16| buffer.extend_from_slice(&unit.to_le_bytes());
+
+    for unit in text.encode_utf16() {
+        if unit == u16::from(b'\n') && previous != Some(u16::from(b'\r')) {
+            push(u16::from(b'\r'));
+        }
+        push(unit);
+        previous = Some(unit);
+    }
+
+    push(0);
+
+    buffer
+}
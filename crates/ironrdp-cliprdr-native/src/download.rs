@@ -0,0 +1,298 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use ironrdp_cliprdr::backend::{ClipboardMessage, ClipboardMessageProxy, CliprdrBackend, CliprdrBackendFactory};
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardFormatId, ClipboardGeneralCapabilityFlags, FileContentsRequest, FileContentsResponse,
+    FormatDataRequest, FormatDataResponse, LockDataId,
+};
+pub use ironrdp_cliprdr_format::download::DownloadEvent;
+use ironrdp_cliprdr_format::download::RemoteDownload;
+use ironrdp_cliprdr_format::mime::TargetKind;
+use ironrdp_core::impl_as_any;
+
+/// Stream IDs of the downloads, away from the ones used by the wrapped backend
+const FIRST_STREAM_ID: u32 = 0x8000_0000;
+
+/// Proxy to report the progress of the downloads to the application (e.g. winit event loop).
+pub trait DownloadEventProxy: core::fmt::Debug + Send {
+    fn send_download_event(&self, event: DownloadEvent);
+}
+
+#[derive(Debug)]
+struct DownloadState {
+    /// Format of the file list announced on the remote clipboard, if any
+    remote_file_list: Option<ClipboardFormatId>,
+    /// Directory of the download waiting for the file list of the remote
+    pending_directory: Option<PathBuf>,
+    download: Option<RemoteDownload>,
+    next_stream_id: u32,
+    /// Negotiated capabilities, `None` until the channel is ready
+    capabilities: Option<ClipboardGeneralCapabilityFlags>,
+    message_proxy: Box<dyn ClipboardMessageProxy>,
+    event_proxy: Box<dyn DownloadEventProxy>,
+}
+
+impl DownloadState {
+    fn send_events(&self, events: impl IntoIterator<Item = DownloadEvent>) {
+        for event in events {
+            self.event_proxy.send_download_event(event);
+        }
+    }
+
+    fn send_request(&self, request: Option<FileContentsRequest>) {
+        if let Some(request) = request {
+            self.message_proxy
+                .send_clipboard_message(ClipboardMessage::SendFileContentsRequest(request));
+        }
+    }
+
+    fn cancel(&mut self) {
+        self.pending_directory = None;
+
+        // The cancelled download is kept, the responses to its last request are not forwarded to the wrapped backend
+        let event = self.download.as_mut().and_then(RemoteDownload::cancel);
+        self.send_events(event);
+    }
+
+    /// Starts the download once the file list of the remote is received
+    fn on_file_list(&mut self, directory: PathBuf, response: &FormatDataResponse<'_>) {
+        let download = if response.is_error() {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "files are not available on the remote anymore",
+            ))
+        } else {
+            response
+                .to_file_list()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                .and_then(|file_list| RemoteDownload::new(&file_list, &directory, self.next_stream_id, None))
+        };
+
+        let mut download = match download {
+            Ok(download) => download,
+            Err(error) => {
+                self.send_events([DownloadEvent::Failed { path: directory, error }]);
+                return;
+            }
+        };
+        self.next_stream_id = self.next_stream_id.wrapping_add(1).max(FIRST_STREAM_ID);
+
+        let (request, events) = download.start();
+        self.download = Some(download);
+        self.send_request(request);
+        self.send_events(events);
+    }
+}
+
+/// Downloads the files copied on the remote clipboard into a local directory, e.g. from a "download from session"
+/// button.
+///
+/// The file list and the file contents are requested by [`DownloadClipboard`], the other messages are forwarded to
+/// the wrapped backend (see [`DownloadClipboard::wrap_backend_factory`]). The files are written one after the other,
+/// and the progress of the transfer is reported to the [`DownloadEventProxy`].
+///
+/// The remote clipboard data is not locked, a download fails if the content of the remote clipboard changes
+/// meanwhile.
+pub struct DownloadClipboard {
+    state: Arc<Mutex<DownloadState>>,
+}
+
+impl DownloadClipboard {
+    pub fn new(
+        message_proxy: impl ClipboardMessageProxy + 'static,
+        event_proxy: impl DownloadEventProxy + 'static,
+    ) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(DownloadState {
+                remote_file_list: None,
+                pending_directory: None,
+                download: None,
+                next_stream_id: FIRST_STREAM_ID,
+                capabilities: None,
+                message_proxy: Box::new(message_proxy),
+                event_proxy: Box::new(event_proxy),
+            })),
+        }
+    }
+
+    /// Returns clipboard backend factory suitable for making backend instances for `CLIPRDR` SVC.
+    ///
+    /// The backends made by `inner` handle the messages unrelated to the downloads, use
+    /// [`StubClipboard`](crate::StubClipboard) when no other clipboard backend is used.
+    pub fn wrap_backend_factory(
+        &self,
+        inner: Box<dyn CliprdrBackendFactory + Send>,
+    ) -> Box<dyn CliprdrBackendFactory + Send> {
+        Box::new(DownloadCliprdrBackendFactory {
+            inner,
+            state: Arc::clone(&self.state),
+        })
+    }
+
+    /// Whether files are available on the remote clipboard
+    pub fn has_remote_files(&self) -> bool {
+        lock(&self.state).remote_file_list.is_some()
+    }
+
+    /// Downloads the files of the remote clipboard into `directory`, replacing the current download.
+    ///
+    /// Directories are downloaded along with their content.
+    pub fn download_files(&self, directory: &Path) -> io::Result<()> {
+        let mut state = lock(&self.state);
+
+        let file_transfer_enabled = state
+            .capabilities
+            .is_none_or(|capabilities| capabilities.contains(ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED));
+        if !file_transfer_enabled {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "file transfer is not supported by the remote",
+            ));
+        }
+
+        let Some(file_list_format) = state.remote_file_list else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no file on the remote clipboard",
+            ));
+        };
+
+        state.cancel();
+        state.pending_directory = Some(directory.to_owned());
+        state
+            .message_proxy
+            .send_clipboard_message(ClipboardMessage::SendInitiatePaste(file_list_format));
+
+        Ok(())
+    }
+
+    /// Cancels the current download, the file being written is removed.
+    pub fn cancel(&self) {
+        lock(&self.state).cancel();
+    }
+
+    /// Bytes written so far and total size of the current, or last, download
+    pub fn progress(&self) -> Option<(u64, u64)> {
+        let state = lock(&self.state);
+
+        state
+            .download
+            .as_ref()
+            .map(|download| (download.transferred(), download.total_size()))
+    }
+}
+
+fn lock(state: &Mutex<DownloadState>) -> MutexGuard<'_, DownloadState> {
+    // The state stays consistent even if a proxy panicked while holding the lock
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+struct DownloadCliprdrBackendFactory {
+    inner: Box<dyn CliprdrBackendFactory + Send>,
+    state: Arc<Mutex<DownloadState>>,
+}
+
+impl CliprdrBackendFactory for DownloadCliprdrBackendFactory {
+    fn build_cliprdr_backend(&self) -> Box<dyn CliprdrBackend> {
+        Box::new(DownloadCliprdrBackend {
+            inner: self.inner.build_cliprdr_backend(),
+            state: Arc::clone(&self.state),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct DownloadCliprdrBackend {
+    inner: Box<dyn CliprdrBackend>,
+    state: Arc<Mutex<DownloadState>>,
+}
+
+impl_as_any!(DownloadCliprdrBackend);
+
+impl CliprdrBackend for DownloadCliprdrBackend {
+    fn temporary_directory(&self) -> &str {
+        self.inner.temporary_directory()
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        // Files are streamed to their local path, no path is exchanged
+        self.inner.client_capabilities()
+            | ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED
+            | ClipboardGeneralCapabilityFlags::FILECLIP_NO_FILE_PATHS
+            | ClipboardGeneralCapabilityFlags::HUGE_FILE_SUPPORT_ENABLED
+    }
+
+    fn on_ready(&mut self) {
+        self.inner.on_ready()
+    }
+
+    fn on_process_negotiated_capabilities(&mut self, capabilities: ClipboardGeneralCapabilityFlags) {
+        lock(&self.state).capabilities = Some(capabilities);
+        self.inner.on_process_negotiated_capabilities(capabilities)
+    }
+
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]) {
+        lock(&self.state).remote_file_list = available_formats
+            .iter()
+            .find(|format| TargetKind::from_format(format) == Some(TargetKind::FileList))
+            .map(|format| format.id);
+
+        self.inner.on_remote_copy(available_formats)
+    }
+
+    fn on_format_data_request(&mut self, request: FormatDataRequest) {
+        self.inner.on_format_data_request(request)
+    }
+
+    fn on_format_data_response(&mut self, response: FormatDataResponse<'_>) {
+        {
+            let mut state = lock(&self.state);
+
+            // The response following the request of the file list is for the download
+            if let Some(directory) = state.pending_directory.take() {
+                state.on_file_list(directory, &response);
+                return;
+            }
+        }
+
+        self.inner.on_format_data_response(response)
+    }
+
+    fn on_file_contents_request(&mut self, request: FileContentsRequest) {
+        self.inner.on_file_contents_request(request)
+    }
+
+    fn on_file_contents_response(&mut self, response: FileContentsResponse<'_>) {
+        {
+            let mut state = lock(&self.state);
+
+            let handled = state
+                .download
+                .as_mut()
+                .filter(|download| download.stream_id() == response.stream_id())
+                .map(|download| download.on_file_contents_response(&response));
+
+            if let Some((request, events)) = handled {
+                state.send_request(request);
+                state.send_events(events);
+                return;
+            }
+        }
+
+        self.inner.on_file_contents_response(response)
+    }
+
+    fn on_lock(&mut self, data_id: LockDataId) {
+        self.inner.on_lock(data_id)
+    }
+
+    fn on_unlock(&mut self, data_id: LockDataId) {
+        self.inner.on_unlock(data_id)
+    }
+
+    fn on_request_format_list(&mut self) {
+        self.inner.on_request_format_list()
+    }
+}
@@ -0,0 +1,239 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use ironrdp_cliprdr::backend::{ClipboardMessage, ClipboardMessageProxy, CliprdrBackend, CliprdrBackendFactory};
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardGeneralCapabilityFlags, FileContentsRequest, FileContentsResponse, FormatDataRequest,
+    FormatDataResponse, LockDataId,
+};
+use ironrdp_cliprdr_format::file_drop::FileDrop;
+pub use ironrdp_cliprdr_format::file_drop::FileDropEvent;
+use ironrdp_core::impl_as_any;
+use tracing::warn;
+
+/// Proxy to report the progress of the dropped files to the application (e.g. winit event loop).
+pub trait FileDropEventProxy: core::fmt::Debug + Send {
+    fn send_file_drop_event(&self, event: FileDropEvent);
+}
+
+#[derive(Debug)]
+struct FileDropState {
+    file_drop: Option<FileDrop>,
+    /// Negotiated capabilities, `None` until the channel is ready
+    capabilities: Option<ClipboardGeneralCapabilityFlags>,
+    message_proxy: Box<dyn ClipboardMessageProxy>,
+    event_proxy: Box<dyn FileDropEventProxy>,
+}
+
+impl FileDropState {
+    fn send_events(&self, events: impl IntoIterator<Item = FileDropEvent>) {
+        for event in events {
+            self.event_proxy.send_file_drop_event(event);
+        }
+    }
+}
+
+/// Injects local files into the session, e.g. when files are dropped onto the client window.
+///
+/// The files are announced on the remote clipboard as a file list, ready to be pasted in the session. The requests of
+/// the remote for the dropped files are answered by [`FileDropClipboard`], the other ones are forwarded to the
+/// wrapped backend (see [`FileDropClipboard::wrap_backend_factory`]).
+///
+/// A drop remains available to the remote until it is cancelled, or replaced by another drop. It should be cancelled
+/// once the transfer is over when the wrapped backend announces local files too.
+pub struct FileDropClipboard {
+    state: Arc<Mutex<FileDropState>>,
+}
+
+impl FileDropClipboard {
+    pub fn new(
+        message_proxy: impl ClipboardMessageProxy + 'static,
+        event_proxy: impl FileDropEventProxy + 'static,
+    ) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(FileDropState {
+                file_drop: None,
+                capabilities: None,
+                message_proxy: Box::new(message_proxy),
+                event_proxy: Box::new(event_proxy),
+            })),
+        }
+    }
+
+    /// Returns clipboard backend factory suitable for making backend instances for `CLIPRDR` SVC.
+    ///
+    /// The backends made by `inner` handle the requests unrelated to the dropped files, use
+    /// [`StubClipboard`](crate::StubClipboard) when no other clipboard backend is used.
+    pub fn wrap_backend_factory(
+        &self,
+        inner: Box<dyn CliprdrBackendFactory + Send>,
+    ) -> Box<dyn CliprdrBackendFactory + Send> {
+        Box::new(FileDropCliprdrBackendFactory {
+            inner,
+            state: Arc::clone(&self.state),
+        })
+    }
+
+    /// Announces the files on the remote clipboard, replacing the previous drop.
+    ///
+    /// Directories are sent along with their content.
+    pub fn drop_files(&self, paths: &[PathBuf]) -> io::Result<()> {
+        let mut state = lock(&self.state);
+
+        let file_transfer_enabled = state
+            .capabilities
+            .is_none_or(|capabilities| capabilities.contains(ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED));
+        if !file_transfer_enabled {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "file transfer is not supported by the remote",
+            ));
+        }
+
+        let file_drop = FileDrop::new(paths)?;
+        let formats = file_drop.formats();
+
+        if let Some(mut previous) = state.file_drop.replace(file_drop) {
+            state.send_events(previous.cancel());
+        }
+        state
+            .message_proxy
+            .send_clipboard_message(ClipboardMessage::SendInitiateCopy(formats));
+
+        Ok(())
+    }
+
+    /// Cancels the current drop, the remote is answered with errors for the remaining file contents.
+    pub fn cancel(&self) {
+        let mut state = lock(&self.state);
+
+        if let Some(mut file_drop) = state.file_drop.take() {
+            state.send_events(file_drop.cancel());
+        }
+    }
+
+    /// Bytes read by the remote so far and total size of the current drop
+    pub fn progress(&self) -> Option<(u64, u64)> {
+        let state = lock(&self.state);
+
+        state
+            .file_drop
+            .as_ref()
+            .map(|file_drop| (file_drop.transferred(), file_drop.total_size()))
+    }
+}
+
+fn lock(state: &Mutex<FileDropState>) -> MutexGuard<'_, FileDropState> {
+    // The state stays consistent even if a proxy panicked while holding the lock
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+struct FileDropCliprdrBackendFactory {
+    inner: Box<dyn CliprdrBackendFactory + Send>,
+    state: Arc<Mutex<FileDropState>>,
+}
+
+impl CliprdrBackendFactory for FileDropCliprdrBackendFactory {
+    fn build_cliprdr_backend(&self) -> Box<dyn CliprdrBackend> {
+        Box::new(FileDropCliprdrBackend {
+            inner: self.inner.build_cliprdr_backend(),
+            state: Arc::clone(&self.state),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct FileDropCliprdrBackend {
+    inner: Box<dyn CliprdrBackend>,
+    state: Arc<Mutex<FileDropState>>,
+}
+
+impl_as_any!(FileDropCliprdrBackend);
+
+impl CliprdrBackend for FileDropCliprdrBackend {
+    fn temporary_directory(&self) -> &str {
+        self.inner.temporary_directory()
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        // Files are streamed from their local path, no path is exchanged
+        self.inner.client_capabilities()
+            | ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED
+            | ClipboardGeneralCapabilityFlags::FILECLIP_NO_FILE_PATHS
+            | ClipboardGeneralCapabilityFlags::HUGE_FILE_SUPPORT_ENABLED
+    }
+
+    fn on_ready(&mut self) {
+        self.inner.on_ready()
+    }
+
+    fn on_process_negotiated_capabilities(&mut self, capabilities: ClipboardGeneralCapabilityFlags) {
+        lock(&self.state).capabilities = Some(capabilities);
+        self.inner.on_process_negotiated_capabilities(capabilities)
+    }
+
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]) {
+        self.inner.on_remote_copy(available_formats)
+    }
+
+    fn on_format_data_request(&mut self, request: FormatDataRequest) {
+        {
+            let state = lock(&self.state);
+
+            if let Some(file_drop) = state
+                .file_drop
+                .as_ref()
+                .filter(|file_drop| file_drop.is_file_list_format(request.format))
+            {
+                let response = file_drop.file_list_response().unwrap_or_else(|error| {
+                    warn!(%error, "Failed to encode the dropped file list");
+                    FormatDataResponse::new_error()
+                });
+                state
+                    .message_proxy
+                    .send_clipboard_message(ClipboardMessage::SendFormatData(response));
+                return;
+            }
+        }
+
+        self.inner.on_format_data_request(request)
+    }
+
+    fn on_format_data_response(&mut self, response: FormatDataResponse<'_>) {
+        self.inner.on_format_data_response(response)
+    }
+
+    fn on_file_contents_request(&mut self, request: FileContentsRequest) {
+        {
+            let mut state = lock(&self.state);
+
+            if let Some(file_drop) = state.file_drop.as_mut() {
+                let (response, events) = file_drop.on_file_contents_request(&request);
+                state
+                    .message_proxy
+                    .send_clipboard_message(ClipboardMessage::SendFileContentsResponse(response));
+                state.send_events(events);
+                return;
+            }
+        }
+
+        self.inner.on_file_contents_request(request)
+    }
+
+    fn on_file_contents_response(&mut self, response: FileContentsResponse<'_>) {
+        self.inner.on_file_contents_response(response)
+    }
+
+    fn on_lock(&mut self, data_id: LockDataId) {
+        self.inner.on_lock(data_id)
+    }
+
+    fn on_unlock(&mut self, data_id: LockDataId) {
+        self.inner.on_unlock(data_id)
+    }
+
+    fn on_request_format_list(&mut self) {
+        self.inner.on_request_format_list()
+    }
+}
@@ -15,5 +15,16 @@ mod windows;
 #[cfg(windows)]
 pub use crate::windows::{WinClipboard, WinCliprdrError, WinCliprdrResult, HWND};
 
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+pub use crate::macos::{MacClipboard, MacCliprdrError, MacCliprdrResult};
+
 mod stub;
 pub use crate::stub::{StubClipboard, StubCliprdrBackend};
+
+mod file_drop;
+pub use crate::file_drop::{FileDropClipboard, FileDropEvent, FileDropEventProxy};
+
+mod download;
+pub use crate::download::{DownloadClipboard, DownloadEvent, DownloadEventProxy};
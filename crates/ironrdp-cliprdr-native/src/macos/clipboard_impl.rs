@@ -0,0 +1,447 @@
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use ironrdp_cliprdr::backend::{ClipboardMessage, ClipboardMessageProxy};
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardFormatName, ClipboardGeneralCapabilityFlags, FileContentsRequest, FileContentsResponse,
+    FileDescriptor, FormatDataRequest, FormatDataResponse, PackedFileList,
+};
+use ironrdp_cliprdr_format::convert::{ClipboardContent, WindowsFormat};
+use ironrdp_cliprdr_format::mime::{
+    local_file_list, packed_file_list, read_local_file_contents, LocalFile, TargetKind,
+};
+use ironrdp_cliprdr_format::pasteboard::{
+    content_from_pasteboard_data, content_to_pasteboard_data, file_urls_to_paths, format_for_pasteboard_type,
+    formats_for_pasteboard_types, local_path, pasteboard_type, pasteboard_types_for_formats, promised_files,
+    FileDownload, PromisedFile,
+};
+use objc2::rc::{autoreleasepool, Retained};
+use objc2_app_kit::NSPasteboard;
+use tracing::warn;
+
+use crate::macos::pasteboard::{self, DeclaredContent};
+use crate::macos::{BackendEvent, MacCliprdrError, MacCliprdrResult};
+
+/// AppKit has no notification for pasteboard changes, the change count is polled instead
+const POLL_INTERVAL_MS: u64 = 250;
+
+/// Format data requested to the remote, answered in order
+enum PendingData {
+    /// Data of a type read by a local application
+    RenderType {
+        pasteboard_type: String,
+        reply: mpsc::SyncSender<Option<Vec<u8>>>,
+    },
+    /// File list of the remote clipboard, offered as file promises once received
+    FileList,
+}
+
+/// Promise of a remote file being fulfilled, entry by entry
+struct PromiseWrite {
+    /// Entries left to download, with their local path
+    entries: VecDeque<(u32, FileDescriptor, PathBuf)>,
+    current: Option<FileDownload>,
+    reply: mpsc::SyncSender<io::Result<()>>,
+}
+
+/// Internal implementation of the clipboard processing logic, running on the worker thread.
+pub(crate) struct MacClipboardImpl {
+    message_proxy: Box<dyn ClipboardMessageProxy>,
+    backend_tx: mpsc::SyncSender<BackendEvent>,
+    backend_rx: mpsc::Receiver<BackendEvent>,
+    pasteboard: Retained<NSPasteboard>,
+    // Change count of the last pasteboard content announced to the remote, or declared by us
+    change_count: isize,
+    capabilities: ClipboardGeneralCapabilityFlags,
+    // Local content announced to the remote
+    local_formats: Vec<ClipboardFormat>,
+    local_files: Vec<LocalFile>,
+    // Remote content declared on the pasteboard
+    remote_formats: Vec<ClipboardFormat>,
+    remote_file_list: PackedFileList,
+    declared_content: Option<DeclaredContent>,
+    pending_data: VecDeque<PendingData>,
+    promises: VecDeque<PromiseWrite>,
+    next_stream_id: u32,
+}
+
+impl MacClipboardImpl {
+    pub(crate) fn new(
+        message_proxy: impl ClipboardMessageProxy + 'static,
+        backend_tx: mpsc::SyncSender<BackendEvent>,
+        backend_rx: mpsc::Receiver<BackendEvent>,
+    ) -> Self {
+        let pasteboard = pasteboard::general_pasteboard();
+        // The initial content is announced when the remote requests the format list
+        let change_count = pasteboard::change_count(&pasteboard);
+
+        Self {
+            message_proxy: Box::new(message_proxy),
+            backend_tx,
+            backend_rx,
+            pasteboard,
+            change_count,
+            capabilities: ClipboardGeneralCapabilityFlags::empty(),
+            local_formats: Vec::new(),
+            local_files: Vec::new(),
+            remote_formats: Vec::new(),
+            remote_file_list: PackedFileList { files: Vec::new() },
+            declared_content: None,
+            pending_data: VecDeque::new(),
+            promises: VecDeque::new(),
+            next_stream_id: 0,
+        }
+    }
+
+    /// Processes the backend events and watches the pasteboard until the clipboard is dropped.
+    pub(crate) fn run(mut self) {
+        loop {
+            let event = match self.backend_rx.recv_timeout(Duration::from_millis(POLL_INTERVAL_MS)) {
+                Ok(BackendEvent::Shutdown) | Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                Ok(event) => Some(event),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+            };
+
+            autoreleasepool(|_| {
+                if let Some(event) = event {
+                    let result = self.handle_event(event);
+                    self.send_result(result);
+                }
+
+                let result = self.poll_pasteboard();
+                self.send_result(result);
+            });
+        }
+    }
+
+    fn send_result(&self, result: MacCliprdrResult<Option<ClipboardMessage>>) {
+        match result {
+            Ok(Some(message)) => self.message_proxy.send_clipboard_message(message),
+            Ok(None) => {
+                // No message to send
+            }
+            Err(err) => self
+                .message_proxy
+                .send_clipboard_message(ClipboardMessage::Error(Box::new(err))),
+        }
+    }
+
+    fn handle_event(&mut self, event: BackendEvent) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        match event {
+            BackendEvent::RenderType { pasteboard_type, reply } => Ok(self.on_render_type(pasteboard_type, reply)),
+            BackendEvent::WritePromise { promised, path, reply } => self.on_write_promise(&promised, path, reply),
+
+            BackendEvent::DowngradedCapabilities(capabilities) => {
+                self.capabilities = capabilities;
+                Ok(None)
+            }
+            BackendEvent::RemoteFormatList(formats) => self.on_remote_format_list(formats),
+            BackendEvent::FormatDataRequest(request) => self.on_format_data_request(&request),
+            BackendEvent::FormatDataResponse(response) => self.on_format_data_response(&response),
+            BackendEvent::FileContentsRequest(request) => Ok(self.on_file_contents_request(&request)),
+            BackendEvent::FileContentsResponse(response) => self.on_file_contents_response(&response),
+            BackendEvent::RemoteRequestsFormatList => self.on_pasteboard_update(),
+
+            BackendEvent::Shutdown => Ok(None),
+        }
+    }
+
+    fn file_transfer_enabled(&self) -> bool {
+        self.capabilities
+            .contains(ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED)
+    }
+
+    fn poll_pasteboard(&mut self) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        let change_count = pasteboard::change_count(&self.pasteboard);
+        if change_count == self.change_count {
+            return Ok(None);
+        }
+
+        self.on_pasteboard_update()
+    }
+
+    fn on_pasteboard_update(&mut self) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        self.change_count = pasteboard::change_count(&self.pasteboard);
+        if self
+            .declared_content
+            .as_ref()
+            .is_some_and(|declared_content| declared_content.change_count != self.change_count)
+        {
+            // The remote content is replaced by a local copy
+            self.declared_content = None;
+        }
+
+        let types = pasteboard::types(&self.pasteboard);
+        let types = types.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut formats = formats_for_pasteboard_types(&types);
+
+        self.local_files.clear();
+        if self.file_transfer_enabled() && formats.iter().any(is_file_list) {
+            let file_urls = pasteboard::file_urls(&self.pasteboard);
+            let file_urls = file_urls.iter().map(String::as_str).collect::<Vec<_>>();
+            self.local_files = local_file_list(&file_urls_to_paths(&file_urls)).map_err(MacCliprdrError::FileList)?;
+        }
+        if self.local_files.is_empty() {
+            formats.retain(|format| !is_file_list(format));
+        }
+
+        self.local_formats = formats.clone();
+
+        Ok(Some(ClipboardMessage::SendInitiateCopy(formats)))
+    }
+
+    fn on_format_data_request(&self, request: &FormatDataRequest) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        let format = self.local_formats.iter().find(|format| format.id == request.format);
+
+        let response = match format {
+            Some(format) if is_file_list(format) => {
+                FormatDataResponse::new_file_list(&packed_file_list(&self.local_files)).unwrap_or_else(|error| {
+                    warn!(%error, "Failed to encode the local file list");
+                    FormatDataResponse::new_error()
+                })
+            }
+            Some(format) => match self.local_format_data(format)? {
+                Some(data) => FormatDataResponse::new_data(data),
+                None => {
+                    // No data available for this format anymore
+                    FormatDataResponse::new_error()
+                }
+            },
+            None => FormatDataResponse::new_error(),
+        };
+
+        Ok(Some(ClipboardMessage::SendFormatData(response)))
+    }
+
+    fn local_format_data(&self, format: &ClipboardFormat) -> MacCliprdrResult<Option<Vec<u8>>> {
+        let name = format.name.as_ref().map(ClipboardFormatName::value);
+        let Some(windows_format) = WindowsFormat::from_format(format.id.value(), name) else {
+            return Ok(None);
+        };
+
+        let pasteboard_type = pasteboard_type(TargetKind::Content(windows_format.content_kind()));
+        let content = pasteboard::data(&self.pasteboard, pasteboard_type)
+            .and_then(|data| content_from_pasteboard_data(pasteboard_type, &data));
+
+        match content {
+            Some(content) => Ok(Some(content.to_windows_format(windows_format)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn on_file_contents_request(&self, request: &FileContentsRequest) -> Option<ClipboardMessage> {
+        let response = read_local_file_contents(&self.local_files, request).unwrap_or_else(|error| {
+            warn!(%error, index = request.index, "Failed to read local file contents");
+            FileContentsResponse::new_error(request.stream_id)
+        });
+
+        Some(ClipboardMessage::SendFileContentsResponse(response))
+    }
+
+    fn on_remote_format_list(&mut self, formats: Vec<ClipboardFormat>) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        self.remote_formats = formats;
+
+        let file_list_format = self
+            .remote_formats
+            .iter()
+            .find(|format| is_file_list(format))
+            .filter(|_| self.file_transfer_enabled());
+
+        if let Some(file_list_format) = file_list_format {
+            // Files are promised by name, the file list is needed before declaring the content
+            self.pending_data.push_back(PendingData::FileList);
+            return Ok(Some(ClipboardMessage::SendInitiatePaste(file_list_format.id)));
+        }
+
+        self.declare_remote_content(Vec::new())
+    }
+
+    fn declare_remote_content(&mut self, promised: Vec<PromisedFile>) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        let types = pasteboard_types_for_formats(&self.remote_formats);
+        if types.is_empty() && promised.is_empty() {
+            return Ok(None);
+        }
+
+        let declared_content =
+            pasteboard::declare_remote_content(&self.pasteboard, &types, promised, &self.backend_tx)?;
+
+        // Our own content is not announced back to the remote
+        self.change_count = declared_content.change_count;
+        self.declared_content = Some(declared_content);
+
+        Ok(None)
+    }
+
+    fn on_render_type(
+        &mut self,
+        pasteboard_type: String,
+        reply: mpsc::SyncSender<Option<Vec<u8>>>,
+    ) -> Option<ClipboardMessage> {
+        let Some(format) = format_for_pasteboard_type(&pasteboard_type, &self.remote_formats) else {
+            // Format is unknown or not available on the remote anymore
+            let _ = reply.send(None);
+            return None;
+        };
+
+        let message = ClipboardMessage::SendInitiatePaste(format.id);
+        self.pending_data
+            .push_back(PendingData::RenderType { pasteboard_type, reply });
+
+        Some(message)
+    }
+
+    fn on_format_data_response(
+        &mut self,
+        response: &FormatDataResponse<'_>,
+    ) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        match self.pending_data.pop_front() {
+            Some(PendingData::RenderType { pasteboard_type, reply }) => {
+                let result = self.remote_format_data(&pasteboard_type, response);
+
+                // The data provider may have given up waiting
+                let _ = reply.send(result.as_ref().ok().cloned().flatten());
+
+                result.map(|_| None)
+            }
+            Some(PendingData::FileList) => self.on_remote_file_list(response),
+            None => {
+                // Out-of-order message, ignore it.
+                Ok(None)
+            }
+        }
+    }
+
+    fn remote_format_data(
+        &self,
+        pasteboard_type: &str,
+        response: &FormatDataResponse<'_>,
+    ) -> MacCliprdrResult<Option<Vec<u8>>> {
+        if response.is_error() {
+            // No data available for this format anymore
+            return Ok(None);
+        }
+
+        let format = format_for_pasteboard_type(pasteboard_type, &self.remote_formats).and_then(|format| {
+            let name = format.name.as_ref().map(ClipboardFormatName::value);
+            WindowsFormat::from_format(format.id.value(), name)
+        });
+        let Some(format) = format else {
+            return Ok(None);
+        };
+
+        let content = ClipboardContent::from_windows_format(format, response.data())?;
+
+        Ok(Some(content_to_pasteboard_data(&content)))
+    }
+
+    fn on_remote_file_list(&mut self, response: &FormatDataResponse<'_>) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        if response.is_error() {
+            // Files are not available anymore, the rest of the content is still declared
+            return self.declare_remote_content(Vec::new());
+        }
+
+        let file_list = response
+            .to_file_list()
+            .map_err(|error| MacCliprdrError::FileList(io::Error::new(io::ErrorKind::InvalidData, error)))?;
+
+        let promised = promised_files(&file_list);
+        self.remote_file_list = file_list;
+
+        self.declare_remote_content(promised)
+    }
+
+    fn on_write_promise(
+        &mut self,
+        promised: &PromisedFile,
+        path: PathBuf,
+        reply: mpsc::SyncSender<io::Result<()>>,
+    ) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        let entries = promised
+            .entries
+            .iter()
+            .filter_map(|&index| {
+                let descriptor = self.remote_file_list.files.get(usize::try_from(index).ok()?)?;
+
+                // The promised location may be renamed, nested entries are written relatively to it
+                let entry_path = match descriptor.name.split_once('\\') {
+                    None => path.clone(),
+                    Some((_, nested)) => local_path(&path, nested)?,
+                };
+
+                Some((index, descriptor.clone(), entry_path))
+            })
+            .collect();
+
+        self.promises.push_back(PromiseWrite {
+            entries,
+            current: None,
+            reply,
+        });
+
+        // Promises are fulfilled one after the other
+        if self.promises.len() > 1 {
+            return Ok(None);
+        }
+
+        Ok(self.next_file_contents_request())
+    }
+
+    fn on_file_contents_response(
+        &mut self,
+        response: &FileContentsResponse<'_>,
+    ) -> MacCliprdrResult<Option<ClipboardMessage>> {
+        let Some(download) = self.promises.front_mut().and_then(|promise| promise.current.as_mut()) else {
+            // Out-of-order message, ignore it.
+            return Ok(None);
+        };
+
+        if let Err(error) = download.on_response(response) {
+            self.finish_promise(Err(error));
+        }
+
+        Ok(self.next_file_contents_request())
+    }
+
+    /// Requests the next chunk of the promised files, starting the download of the next entries as needed
+    fn next_file_contents_request(&mut self) -> Option<ClipboardMessage> {
+        loop {
+            let promise = self.promises.front_mut()?;
+
+            if let Some(request) = promise.current.as_ref().and_then(FileDownload::next_request) {
+                return Some(ClipboardMessage::SendFileContentsRequest(request));
+            }
+
+            let result = match promise.entries.pop_front() {
+                Some((index, descriptor, path)) => {
+                    let stream_id = self.next_stream_id;
+                    self.next_stream_id = stream_id.wrapping_add(1);
+
+                    match FileDownload::new(stream_id, index, &descriptor, &path, None) {
+                        Ok(download) => {
+                            promise.current = Some(download);
+                            continue;
+                        }
+                        Err(error) => Err(error),
+                    }
+                }
+                None => Ok(()),
+            };
+
+            self.finish_promise(result);
+        }
+    }
+
+    fn finish_promise(&mut self, result: io::Result<()>) {
+        if let Some(promise) = self.promises.pop_front() {
+            // The delegate is waiting for the download, unless AppKit cancelled the promise
+            let _ = promise.reply.send(result);
+        }
+    }
+}
+
+fn is_file_list(format: &ClipboardFormat) -> bool {
+    TargetKind::from_format(format) == Some(TargetKind::FileList)
+}
@@ -0,0 +1,79 @@
+use std::sync::mpsc as mpsc_sync;
+
+use ironrdp_cliprdr::backend::CliprdrBackend;
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardGeneralCapabilityFlags, FileContentsRequest, FileContentsResponse, FormatDataRequest,
+    FormatDataResponse, LockDataId,
+};
+use ironrdp_core::{impl_as_any, IntoOwned as _};
+
+use crate::macos::BackendEvent;
+
+#[derive(Debug)]
+pub(crate) struct MacCliprdrBackend {
+    backend_event_tx: mpsc_sync::SyncSender<BackendEvent>,
+}
+
+impl_as_any!(MacCliprdrBackend);
+
+impl MacCliprdrBackend {
+    pub(crate) fn new(backend_event_tx: mpsc_sync::SyncSender<BackendEvent>) -> Self {
+        Self { backend_event_tx }
+    }
+
+    fn send_event(&self, event: BackendEvent) {
+        // Channel is closed when the worker is stopped, nothing to do then
+        let _ = self.backend_event_tx.send(event);
+    }
+}
+
+impl CliprdrBackend for MacCliprdrBackend {
+    fn temporary_directory(&self) -> &str {
+        ".cliprdr"
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        // Files are streamed into the promised locations, no path is exchanged
+        ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED
+            | ClipboardGeneralCapabilityFlags::FILECLIP_NO_FILE_PATHS
+            | ClipboardGeneralCapabilityFlags::HUGE_FILE_SUPPORT_ENABLED
+    }
+
+    fn on_ready(&mut self) {}
+
+    fn on_process_negotiated_capabilities(&mut self, capabilities: ClipboardGeneralCapabilityFlags) {
+        self.send_event(BackendEvent::DowngradedCapabilities(capabilities))
+    }
+
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]) {
+        self.send_event(BackendEvent::RemoteFormatList(available_formats.to_vec()));
+    }
+
+    fn on_format_data_request(&mut self, request: FormatDataRequest) {
+        self.send_event(BackendEvent::FormatDataRequest(request));
+    }
+
+    fn on_format_data_response(&mut self, response: FormatDataResponse<'_>) {
+        self.send_event(BackendEvent::FormatDataResponse(response.into_owned()));
+    }
+
+    fn on_file_contents_request(&mut self, request: FileContentsRequest) {
+        self.send_event(BackendEvent::FileContentsRequest(request));
+    }
+
+    fn on_file_contents_response(&mut self, response: FileContentsResponse<'_>) {
+        self.send_event(BackendEvent::FileContentsResponse(response.into_owned()));
+    }
+
+    fn on_lock(&mut self, _data_id: LockDataId) {
+        // Local files are read when requested, no snapshot is kept
+    }
+
+    fn on_unlock(&mut self, _data_id: LockDataId) {
+        // Local files are read when requested, no snapshot is kept
+    }
+
+    fn on_request_format_list(&mut self) {
+        self.send_event(BackendEvent::RemoteRequestsFormatList);
+    }
+}
@@ -0,0 +1,159 @@
+mod clipboard_impl;
+mod cliprdr_backend;
+mod pasteboard;
+
+use std::path::PathBuf;
+use std::sync::mpsc as mpsc_sync;
+use std::{io, thread};
+
+use ironrdp_cliprdr::backend::{ClipboardMessageProxy, CliprdrBackend, CliprdrBackendFactory};
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardGeneralCapabilityFlags, FileContentsRequest, FileContentsResponse, FormatDataRequest,
+    FormatDataResponse,
+};
+use ironrdp_cliprdr_format::convert::ConversionError;
+use ironrdp_cliprdr_format::pasteboard::PromisedFile;
+use tracing::error;
+
+use self::clipboard_impl::MacClipboardImpl;
+use self::cliprdr_backend::MacCliprdrBackend;
+
+const BACKEND_CHANNEL_SIZE: usize = 8;
+
+pub type MacCliprdrResult<T> = Result<T, MacCliprdrError>;
+
+#[derive(Debug)]
+pub enum MacCliprdrError {
+    WorkerThread(io::Error),
+    WritePasteboard,
+    Conversion(ConversionError),
+    FileList(io::Error),
+    FileContents(io::Error),
+}
+
+impl core::fmt::Display for MacCliprdrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MacCliprdrError::WorkerThread(_) => write!(f, "failed to spawn the pasteboard worker thread"),
+            MacCliprdrError::WritePasteboard => write!(f, "failed to write the pasteboard"),
+            MacCliprdrError::Conversion(_) => write!(f, "failed to convert clipboard data"),
+            MacCliprdrError::FileList(_) => write!(f, "failed to list the copied files"),
+            MacCliprdrError::FileContents(_) => write!(f, "failed to transfer the file contents"),
+        }
+    }
+}
+
+impl core::error::Error for MacCliprdrError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            MacCliprdrError::WorkerThread(error) => Some(error),
+            MacCliprdrError::WritePasteboard => None,
+            MacCliprdrError::Conversion(error) => Some(error),
+            MacCliprdrError::FileList(error) => Some(error),
+            MacCliprdrError::FileContents(error) => Some(error),
+        }
+    }
+}
+
+impl From<ConversionError> for MacCliprdrError {
+    fn from(err: ConversionError) -> Self {
+        MacCliprdrError::Conversion(err)
+    }
+}
+
+/// Sent to the pasteboard worker thread by the clipboard backend shim and the pasteboard callbacks
+#[derive(Debug)]
+pub(crate) enum BackendEvent {
+    // Events generated by the pasteboard callbacks
+    /// A local application reads a type declared for the remote clipboard
+    RenderType {
+        pasteboard_type: String,
+        reply: mpsc_sync::SyncSender<Option<Vec<u8>>>,
+    },
+    /// A promise of a remote file is fulfilled, the file is expected at `path`
+    WritePromise {
+        promised: PromisedFile,
+        path: PathBuf,
+        reply: mpsc_sync::SyncSender<io::Result<()>>,
+    },
+
+    // PDU processing events
+    DowngradedCapabilities(ClipboardGeneralCapabilityFlags),
+    RemoteFormatList(Vec<ClipboardFormat>),
+    FormatDataRequest(FormatDataRequest),
+    FormatDataResponse(FormatDataResponse<'static>),
+    FileContentsRequest(FileContentsRequest),
+    FileContentsResponse(FileContentsResponse<'static>),
+    RemoteRequestsFormatList,
+
+    Shutdown,
+}
+
+/// macOS RDP client clipboard implementation.
+///
+/// IronRDP client implementation should provide a message proxy to send messages from the backend to
+/// `CLIPRDR` SVC.
+///
+/// The general pasteboard is watched from a worker thread, which also declares the content of the remote
+/// clipboard. Remote files are offered as file promises, and downloaded when the promises are fulfilled.
+///
+/// [`MacClipboard`] instance holds ownership of the worker thread and should be kept alive during the whole
+/// lifetime of the application. Backend factory returned by [`MacClipboard::backend_factory`] can be safely
+/// used in other threads.
+pub struct MacClipboard {
+    backend_tx: mpsc_sync::SyncSender<BackendEvent>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl MacClipboard {
+    /// Creates new clipboard instance.
+    ///
+    /// Under the hood, a worker thread is spawned for watching the pasteboard.
+    pub fn new(message_proxy: impl ClipboardMessageProxy + 'static) -> MacCliprdrResult<Self> {
+        let (backend_tx, backend_rx) = mpsc_sync::sync_channel(BACKEND_CHANNEL_SIZE);
+
+        let worker_tx = backend_tx.clone();
+        let worker = thread::Builder::new()
+            .name("ironrdp-cliprdr-pasteboard".to_owned())
+            .spawn(move || MacClipboardImpl::new(message_proxy, worker_tx, backend_rx).run())
+            .map_err(MacCliprdrError::WorkerThread)?;
+
+        Ok(Self {
+            backend_tx,
+            worker: Some(worker),
+        })
+    }
+
+    /// Returns clipboard backend factory suitable for making backend instances for `CLIPRDR` SVC.
+    pub fn backend_factory(&self) -> Box<dyn CliprdrBackendFactory + Send> {
+        Box::new(MacCliprdrBackendFactory {
+            tx: self.backend_tx.clone(),
+        })
+    }
+}
+
+impl Drop for MacClipboard {
+    fn drop(&mut self) {
+        // The pasteboard callbacks keep senders alive, the worker is stopped explicitly
+        if self.backend_tx.send(BackendEvent::Shutdown).is_err() {
+            return;
+        }
+
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Pasteboard worker thread panicked");
+            }
+        }
+    }
+}
+
+/// macOS-specific clipboard backend factory
+struct MacCliprdrBackendFactory {
+    tx: mpsc_sync::SyncSender<BackendEvent>,
+}
+
+impl CliprdrBackendFactory for MacCliprdrBackendFactory {
+    fn build_cliprdr_backend(&self) -> Box<dyn CliprdrBackend> {
+        Box::new(MacCliprdrBackend::new(self.tx.clone()))
+    }
+}
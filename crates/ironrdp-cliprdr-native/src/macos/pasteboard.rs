@@ -0,0 +1,294 @@
+//! AppKit side of the pasteboard: reading the local content, and declaring the remote content lazily.
+
+use core::ptr;
+use core::time::Duration;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc as mpsc_sync;
+
+use block2::Block;
+use ironrdp_cliprdr_format::pasteboard::{PromisedFile, DATA_TYPE, FILE_URL_TYPE, FOLDER_TYPE};
+use objc2::rc::Retained;
+use objc2::runtime::{NSObject, NSObjectProtocol, ProtocolObject};
+use objc2::{define_class, msg_send, AnyThread as _, DefinedClass as _};
+use objc2_app_kit::{
+    NSFilePromiseProvider, NSFilePromiseProviderDelegate, NSPasteboard, NSPasteboardItem, NSPasteboardItemDataProvider,
+    NSPasteboardType, NSPasteboardWriting,
+};
+use objc2_foundation::{NSArray, NSData, NSError, NSOperationQueue, NSString, NSURL};
+use tracing::warn;
+
+use crate::macos::{BackendEvent, MacCliprdrError, MacCliprdrResult};
+
+const RENDER_TYPE_TIMEOUT_SECS: u64 = 10;
+const ERROR_DOMAIN: &str = "IronRDPCliprdr";
+
+pub(crate) fn general_pasteboard() -> Retained<NSPasteboard> {
+    // SAFETY: the general pasteboard is always available
+    unsafe { NSPasteboard::generalPasteboard() }
+}
+
+/// Incremented by AppKit each time the content of the pasteboard changes
+pub(crate) fn change_count(pasteboard: &NSPasteboard) -> isize {
+    // SAFETY: simple getter
+    unsafe { pasteboard.changeCount() }
+}
+
+/// Types available on the pasteboard, in order of preference
+pub(crate) fn types(pasteboard: &NSPasteboard) -> Vec<String> {
+    // SAFETY: simple getter
+    let Some(types) = (unsafe { pasteboard.types() }) else {
+        return Vec::new();
+    };
+
+    types
+        .iter()
+        .map(|pasteboard_type| pasteboard_type.to_string())
+        .collect()
+}
+
+/// File URLs of the pasteboard items, one per copied file
+pub(crate) fn file_urls(pasteboard: &NSPasteboard) -> Vec<String> {
+    // SAFETY: simple getter
+    let Some(items) = (unsafe { pasteboard.pasteboardItems() }) else {
+        return Vec::new();
+    };
+
+    let file_url_type = NSString::from_str(FILE_URL_TYPE);
+    items
+        .iter()
+        // SAFETY: `file_url_type` is a valid pasteboard type
+        .filter_map(|item| unsafe { item.stringForType(&file_url_type) })
+        .map(|file_url| file_url.to_string())
+        .collect()
+}
+
+/// Data of the first pasteboard item holding the type
+pub(crate) fn data(pasteboard: &NSPasteboard, pasteboard_type: &str) -> Option<Vec<u8>> {
+    let pasteboard_type = NSString::from_str(pasteboard_type);
+
+    // SAFETY: `pasteboard_type` is a valid pasteboard type
+    let data = unsafe { pasteboard.dataForType(&pasteboard_type) }?;
+
+    Some(data.to_vec())
+}
+
+/// Remote content declared on the pasteboard.
+///
+/// AppKit doesn't retain the data provider and the delegates of the file promises, they are kept alive until the
+/// content is replaced.
+pub(crate) struct DeclaredContent {
+    pub(crate) change_count: isize,
+    _data_provider: Option<Retained<PasteboardDataProvider>>,
+    _promise_delegates: Vec<Retained<FilePromiseDelegate>>,
+}
+
+/// Replaces the content of the pasteboard with the remote content.
+///
+/// The data of `types` is only requested when a local application reads it, and each promised file is only
+/// downloaded when the promise is fulfilled.
+pub(crate) fn declare_remote_content(
+    pasteboard: &NSPasteboard,
+    types: &[&str],
+    promised_files: Vec<PromisedFile>,
+    backend_tx: &mpsc_sync::SyncSender<BackendEvent>,
+) -> MacCliprdrResult<DeclaredContent> {
+    let mut objects: Vec<Retained<ProtocolObject<dyn NSPasteboardWriting>>> = Vec::new();
+
+    let data_provider = if types.is_empty() {
+        None
+    } else {
+        let data_provider = PasteboardDataProvider::new(backend_tx.clone());
+        let types = types
+            .iter()
+            .map(|pasteboard_type| NSString::from_str(pasteboard_type))
+            .collect::<Vec<_>>();
+        let types = NSArray::from_retained_slice(&types);
+
+        let item = NSPasteboardItem::new();
+        // SAFETY: `data_provider` is kept alive along with the declared content
+        if !unsafe { item.setDataProvider_forTypes(ProtocolObject::from_ref(&*data_provider), &types) } {
+            return Err(MacCliprdrError::WritePasteboard);
+        }
+        objects.push(ProtocolObject::from_retained(item));
+
+        Some(data_provider)
+    };
+
+    let mut promise_delegates = Vec::with_capacity(promised_files.len());
+    for promised in promised_files {
+        let file_type = NSString::from_str(if promised.is_directory { FOLDER_TYPE } else { DATA_TYPE });
+        let delegate = FilePromiseDelegate::new(promised, backend_tx.clone());
+
+        // SAFETY: `delegate` is kept alive along with the declared content
+        let provider = unsafe {
+            NSFilePromiseProvider::initWithFileType_delegate(
+                NSFilePromiseProvider::alloc(),
+                &file_type,
+                ProtocolObject::from_ref(&*delegate),
+            )
+        };
+        objects.push(ProtocolObject::from_retained(provider));
+        promise_delegates.push(delegate);
+    }
+
+    // SAFETY: the pasteboard is cleared before writing, as required by AppKit
+    unsafe { pasteboard.clearContents() };
+
+    let objects = NSArray::from_retained_slice(&objects);
+    // SAFETY: `objects` only holds pasteboard items and file promise providers
+    if !unsafe { pasteboard.writeObjects(&objects) } {
+        return Err(MacCliprdrError::WritePasteboard);
+    }
+
+    Ok(DeclaredContent {
+        change_count: change_count(pasteboard),
+        _data_provider: data_provider,
+        _promise_delegates: promise_delegates,
+    })
+}
+
+pub(crate) struct DataProviderIvars {
+    backend_tx: mpsc_sync::SyncSender<BackendEvent>,
+}
+
+define_class!(
+    // SAFETY:
+    // - The superclass NSObject does not have any subclassing requirements.
+    // - `PasteboardDataProvider` does not implement `Drop`.
+    #[unsafe(super(NSObject))]
+    #[name = "IronRDPPasteboardDataProvider"]
+    #[ivars = DataProviderIvars]
+    pub(crate) struct PasteboardDataProvider;
+
+    unsafe impl NSObjectProtocol for PasteboardDataProvider {}
+
+    unsafe impl NSPasteboardItemDataProvider for PasteboardDataProvider {
+        // Called on the main thread when a local application reads a type of the remote content
+        #[unsafe(method(pasteboard:item:provideDataForType:))]
+        fn provide_data_for_type(
+            &self,
+            _pasteboard: Option<&NSPasteboard>,
+            item: &NSPasteboardItem,
+            pasteboard_type: &NSPasteboardType,
+        ) {
+            let (reply, reply_rx) = mpsc_sync::sync_channel(1);
+            let event = BackendEvent::RenderType {
+                pasteboard_type: pasteboard_type.to_string(),
+                reply,
+            };
+            if self.ivars().backend_tx.send(event).is_err() {
+                // Channel is closed, backend is dead
+                return;
+            }
+
+            // AppKit expects the data to be set before returning, the application is blocked meanwhile
+            let data = match reply_rx.recv_timeout(Duration::from_secs(RENDER_TYPE_TIMEOUT_SECS)) {
+                Ok(Some(data)) => NSData::with_bytes(&data),
+                Ok(None) => return,
+                Err(_) => {
+                    warn!(%pasteboard_type, "Failed to receive data from remote clipboard");
+                    return;
+                }
+            };
+
+            // SAFETY: `pasteboard_type` is one of the types declared for the item
+            if !unsafe { item.setData_forType(&data, pasteboard_type) } {
+                warn!(%pasteboard_type, "Failed to set pasteboard data");
+            }
+        }
+    }
+);
+
+impl PasteboardDataProvider {
+    fn new(backend_tx: mpsc_sync::SyncSender<BackendEvent>) -> Retained<Self> {
+        let this = Self::alloc().set_ivars(DataProviderIvars { backend_tx });
+
+        // SAFETY: the signature of `NSObject`'s `init` method is correct
+        unsafe { msg_send![super(this), init] }
+    }
+}
+
+pub(crate) struct FilePromiseIvars {
+    promised: PromisedFile,
+    backend_tx: mpsc_sync::SyncSender<BackendEvent>,
+}
+
+define_class!(
+    // SAFETY:
+    // - The superclass NSObject does not have any subclassing requirements.
+    // - `FilePromiseDelegate` does not implement `Drop`.
+    #[unsafe(super(NSObject))]
+    #[name = "IronRDPFilePromiseDelegate"]
+    #[ivars = FilePromiseIvars]
+    pub(crate) struct FilePromiseDelegate;
+
+    unsafe impl NSObjectProtocol for FilePromiseDelegate {}
+
+    unsafe impl NSFilePromiseProviderDelegate for FilePromiseDelegate {
+        #[unsafe(method(filePromiseProvider:fileNameForType:))]
+        fn file_name_for_type(
+            &self,
+            _file_promise_provider: &NSFilePromiseProvider,
+            _file_type: &NSString,
+        ) -> Retained<NSString> {
+            NSString::from_str(&self.ivars().promised.name)
+        }
+
+        // Called on the operation queue of the delegate, the remote file is downloaded synchronously
+        #[unsafe(method(filePromiseProvider:writePromiseToURL:completionHandler:))]
+        fn write_promise_to_url(
+            &self,
+            _file_promise_provider: &NSFilePromiseProvider,
+            url: &NSURL,
+            completion_handler: &Block<dyn Fn(*mut NSError)>,
+        ) {
+            let result = match url.to_file_path() {
+                Some(path) => self.download(path),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "promised location is not a file path",
+                )),
+            };
+
+            match result {
+                Ok(()) => completion_handler.call((ptr::null_mut(),)),
+                Err(error) => {
+                    warn!(%error, name = %self.ivars().promised.name, "Failed to write promised file");
+
+                    let error = NSError::new(-1, &NSString::from_str(ERROR_DOMAIN));
+                    completion_handler.call((Retained::as_ptr(&error).cast_mut(),));
+                }
+            }
+        }
+
+        #[unsafe(method(operationQueueForFilePromiseProvider:))]
+        fn operation_queue(&self, _file_promise_provider: &NSFilePromiseProvider) -> Retained<NSOperationQueue> {
+            // Downloads block, they are kept away from the main queue
+            NSOperationQueue::new()
+        }
+    }
+);
+
+impl FilePromiseDelegate {
+    fn new(promised: PromisedFile, backend_tx: mpsc_sync::SyncSender<BackendEvent>) -> Retained<Self> {
+        let this = Self::alloc().set_ivars(FilePromiseIvars { promised, backend_tx });
+
+        // SAFETY: the signature of `NSObject`'s `init` method is correct
+        unsafe { msg_send![super(this), init] }
+    }
+
+    fn download(&self, path: PathBuf) -> io::Result<()> {
+        let (reply, reply_rx) = mpsc_sync::sync_channel(1);
+        let event = BackendEvent::WritePromise {
+            promised: self.ivars().promised.clone(),
+            path,
+            reply,
+        };
+
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "clipboard backend is closed");
+        self.ivars().backend_tx.send(event).map_err(|_| closed())?;
+
+        reply_rx.recv().map_err(|_| closed())?
+    }
+}
@@ -4,6 +4,9 @@
 
 pub mod backend;
 pub mod pdu;
+pub mod policy;
+
+use std::sync::Arc;
 
 use backend::CliprdrBackend;
 use ironrdp_core::{decode, AsAny, EncodeResult};
@@ -16,8 +19,9 @@ use ironrdp_svc::{
 use pdu::{
     Capabilities, ClientTemporaryDirectory, ClipboardFormat, ClipboardFormatId, ClipboardGeneralCapabilityFlags,
     ClipboardPdu, ClipboardProtocolVersion, FileContentsRequest, FileContentsResponse, FormatDataRequest,
-    FormatListResponse, LockDataId, OwnedFormatDataResponse,
+    FormatDataResponse, FormatListResponse, LockDataId, OwnedFormatDataResponse,
 };
+use policy::{ClipboardDirection, CliprdrPolicy};
 use tracing::{error, info};
 
 #[rustfmt::skip] // do not reorder
@@ -54,6 +58,7 @@ pub trait Role: core::fmt::Debug + Send + 'static {
 #[derive(Debug)]
 pub struct Cliprdr<R: Role> {
     backend: Box<dyn CliprdrBackend>,
+    policy: Option<Arc<dyn CliprdrPolicy>>,
     capabilities: Capabilities,
     state: CliprdrState,
     _marker: core::marker::PhantomData<R>,
@@ -97,12 +102,20 @@ impl<R: Role> Cliprdr<R> {
 
         Self {
             backend,
+            policy: None,
             state: CliprdrState::Initialization,
             capabilities: Capabilities::new(ClipboardProtocolVersion::V2, flags),
             _marker: core::marker::PhantomData,
         }
     }
 
+    /// Restricts the clipboard data exchanged on the channel
+    #[must_use]
+    pub fn with_policy(mut self, policy: Arc<dyn CliprdrPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
     pub fn downcast_backend<T: CliprdrBackend>(&self) -> Option<&T> {
         self.backend.as_any().downcast_ref::<T>()
     }
@@ -111,6 +124,11 @@ impl<R: Role> Cliprdr<R> {
         self.backend.as_any_mut().downcast_mut::<T>()
     }
 
+    /// Whether the policy, if any, allows the action
+    fn allows(&self, check: impl FnOnce(&dyn CliprdrPolicy) -> bool) -> bool {
+        self.policy.as_deref().is_none_or(check)
+    }
+
     fn are_long_format_names_enabled(&self) -> bool {
         self.capabilities
             .flags()
@@ -175,7 +193,9 @@ impl<R: Role> Cliprdr<R> {
         }
 
         let formats = format_list.get_formats(self.are_long_format_names_enabled())?;
-        self.backend.on_remote_copy(&formats);
+        if self.allows(|policy| policy.allow_copy(ClipboardDirection::RemoteToLocal)) {
+            self.backend.on_remote_copy(&formats);
+        }
 
         let pdu = ClipboardPdu::FormatListResponse(FormatListResponse::Ok);
 
@@ -192,6 +212,13 @@ impl<R: Role> Cliprdr<R> {
     pub fn submit_format_data(&self, response: OwnedFormatDataResponse) -> PduResult<CliprdrSvcMessages<R>> {
         ready_guard!(self, submit_format_data);
 
+        let response = if response.is_error()
+            || self.allows(|policy| policy.allow_format_data(ClipboardDirection::LocalToRemote, &response))
+        {
+            response
+        } else {
+            FormatDataResponse::new_error()
+        };
         let pdu = ClipboardPdu::FormatDataResponse(response);
 
         Ok(vec![into_cliprdr_message(pdu)].into())
@@ -207,6 +234,13 @@ impl<R: Role> Cliprdr<R> {
     pub fn submit_file_contents(&self, response: FileContentsResponse<'static>) -> PduResult<CliprdrSvcMessages<R>> {
         ready_guard!(self, submit_file_contents);
 
+        let response = if response.is_error()
+            || self.allows(|policy| policy.allow_file_contents(ClipboardDirection::LocalToRemote, &response))
+        {
+            response
+        } else {
+            FileContentsResponse::new_error(response.stream_id())
+        };
         let pdu = ClipboardPdu::FileContentsResponse(response);
 
         Ok(vec![into_cliprdr_message(pdu)].into())
@@ -227,9 +261,17 @@ impl<R: Role> Cliprdr<R> {
     /// Starts processing of `CLIPRDR` copy command. Should be called by the clipboard
     /// implementation when user performs OS-specific copy command (e.g. `Ctrl+C` shortcut on
     /// keyboard)
+    ///
+    /// An empty format list is announced instead when the copy is denied by the policy.
     pub fn initiate_copy(&self, available_formats: &[ClipboardFormat]) -> PduResult<CliprdrSvcMessages<R>> {
         let mut pdus = Vec::new();
 
+        let available_formats = if self.allows(|policy| policy.allow_copy(ClipboardDirection::LocalToRemote)) {
+            available_formats
+        } else {
+            &[]
+        };
+
         if R::is_server() {
             pdus.push(ClipboardPdu::FormatList(
                 self.build_format_list(available_formats).map_err(|e| encode_err!(e))?,
@@ -313,6 +355,10 @@ impl<R: Role> Cliprdr<R> {
     pub fn request_file_contents(&self, request: FileContentsRequest) -> PduResult<CliprdrSvcMessages<R>> {
         ready_guard!(self, request_file_contents);
 
+        if let Some(policy) = &self.policy {
+            policy.file_contents_requested(ClipboardDirection::RemoteToLocal, &request);
+        }
+
         let pdu = ClipboardPdu::FileContentsRequest(request);
         Ok(vec![into_cliprdr_message(pdu)].into())
     }
@@ -364,15 +410,31 @@ impl<R: Role> SvcProcessor for Cliprdr<R> {
                 Ok(Vec::new())
             }
             ClipboardPdu::FormatDataResponse(response) => {
-                self.backend.on_format_data_response(response);
+                if response.is_error()
+                    || self.allows(|policy| policy.allow_format_data(ClipboardDirection::RemoteToLocal, &response))
+                {
+                    self.backend.on_format_data_response(response);
+                } else {
+                    self.backend.on_format_data_response(FormatDataResponse::new_error());
+                }
                 Ok(Vec::new())
             }
             ClipboardPdu::FileContentsRequest(request) => {
+                if let Some(policy) = &self.policy {
+                    policy.file_contents_requested(ClipboardDirection::LocalToRemote, &request);
+                }
                 self.backend.on_file_contents_request(request);
                 Ok(Vec::new())
             }
             ClipboardPdu::FileContentsResponse(response) => {
-                self.backend.on_file_contents_response(response);
+                if response.is_error()
+                    || self.allows(|policy| policy.allow_file_contents(ClipboardDirection::RemoteToLocal, &response))
+                {
+                    self.backend.on_file_contents_response(response);
+                } else {
+                    self.backend
+                        .on_file_contents_response(FileContentsResponse::new_error(response.stream_id()));
+                }
                 Ok(Vec::new())
             }
             ClipboardPdu::TemporaryDirectory(_) => {
@@ -92,6 +92,10 @@ impl<'a> FileContentsResponse<'a> {
         &self.data
     }
 
+    pub fn is_error(&self) -> bool {
+        self.is_error
+    }
+
     /// Read data as u64 size value
     pub fn data_as_size(&self) -> DecodeResult<u64> {
         let chunk = self
@@ -0,0 +1,40 @@
+//! Restrictions on the clipboard data exchanged on the channel, enforced by [`Cliprdr`] itself.
+//!
+//! [`Cliprdr`]: crate::Cliprdr
+
+use crate::pdu::{FileContentsRequest, FileContentsResponse, FormatDataResponse};
+
+/// Direction of the clipboard data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClipboardDirection {
+    /// Data of the local clipboard, sent to the remote
+    LocalToRemote,
+    /// Data of the remote clipboard, received from the remote
+    RemoteToLocal,
+}
+
+/// Restrictions on the clipboard, see [`Cliprdr::with_policy`]
+///
+/// The policy is checked by [`Cliprdr`] before the PDUs are sent or given to the [`CliprdrBackend`], so a backend
+/// can't bypass it:
+/// - a denied local copy is announced as an empty format list, a denied remote copy isn't given to the backend;
+/// - denied data and file contents are replaced by error responses.
+///
+/// [`Cliprdr`]: crate::Cliprdr
+/// [`Cliprdr::with_policy`]: crate::Cliprdr::with_policy
+/// [`CliprdrBackend`]: crate::backend::CliprdrBackend
+pub trait CliprdrPolicy: Send + Sync + core::fmt::Debug {
+    /// Whether the formats copied on one side may be announced to the other
+    fn allow_copy(&self, direction: ClipboardDirection) -> bool;
+
+    /// Whether the data of a format may be transferred, the error responses are never checked
+    fn allow_format_data(&self, direction: ClipboardDirection, response: &FormatDataResponse<'_>) -> bool;
+
+    /// A file contents request is sent or received, `direction` being the direction of the requested data
+    ///
+    /// The response to the request is checked with [`Self::allow_file_contents`], with the same stream ID.
+    fn file_contents_requested(&self, direction: ClipboardDirection, request: &FileContentsRequest);
+
+    /// Whether the size or the contents of a file may be transferred, the error responses are never checked
+    fn allow_file_contents(&self, direction: ClipboardDirection, response: &FileContentsResponse<'_>) -> bool;
+}
@@ -4,8 +4,9 @@ use std::borrow::Cow;
 use std::sync::Arc;
 
 use ironrdp_core::{decode, encode_vec, Encode, WriteBuf};
+use ironrdp_pdu::rdp::multitransport::{InitiateMultitransportRequest, InitiateMultitransportResponse};
 use ironrdp_pdu::x224::X224;
-use ironrdp_pdu::{gcc, mcs, nego, rdp, PduHint};
+use ironrdp_pdu::{gcc, mcs, nego, rdp, rdstls, PduHint};
 use ironrdp_svc::{StaticChannelSet, StaticVirtualChannel, SvcClientProcessor};
 use tracing::{debug, error, info, warn};
 
@@ -13,8 +14,8 @@ use crate::channel_connection::{ChannelConnectionSequence, ChannelConnectionStat
 use crate::connection_activation::{ConnectionActivationSequence, ConnectionActivationState};
 use crate::license_exchange::{LicenseExchangeSequence, NoopLicenseCache};
 use crate::{
-    encode_x224_packet, general_err, reason_err, Config, ConnectorError, ConnectorErrorExt as _, ConnectorErrorKind,
-    ConnectorResult, DesktopSize, NegotiationFailure, Sequence, State, Written,
+    encode_x224_packet, general_err, legacy, reason_err, Config, ConnectorError, ConnectorErrorExt as _,
+    ConnectorErrorKind, ConnectorResult, DesktopSize, MonitorConfig, NegotiationFailure, Sequence, State, Written,
 };
 
 #[derive(Debug)]
@@ -43,6 +44,12 @@ pub enum ClientConnectorState {
     EnhancedSecurityUpgrade {
         selected_protocol: nego::SecurityProtocol,
     },
+    RdstlsWaitCapabilities {
+        selected_protocol: nego::SecurityProtocol,
+    },
+    RdstlsWaitAuthResponse {
+        selected_protocol: nego::SecurityProtocol,
+    },
     Credssp {
         selected_protocol: nego::SecurityProtocol,
     },
@@ -91,6 +98,8 @@ impl State for ClientConnectorState {
             Self::ConnectionInitiationSendRequest => "ConnectionInitiationSendRequest",
             Self::ConnectionInitiationWaitConfirm { .. } => "ConnectionInitiationWaitResponse",
             Self::EnhancedSecurityUpgrade { .. } => "EnhancedSecurityUpgrade",
+            Self::RdstlsWaitCapabilities { .. } => "RdstlsWaitCapabilities",
+            Self::RdstlsWaitAuthResponse { .. } => "RdstlsWaitAuthResponse",
             Self::Credssp { .. } => "Credssp",
             Self::BasicSettingsExchangeSendInitial { .. } => "BasicSettingsExchangeSendInitial",
             Self::BasicSettingsExchangeWaitResponse { .. } => "BasicSettingsExchangeWaitResponse",
@@ -207,6 +216,8 @@ impl Sequence for ClientConnector {
             ClientConnectorState::ConnectionInitiationSendRequest => None,
             ClientConnectorState::ConnectionInitiationWaitConfirm { .. } => Some(&ironrdp_pdu::X224_HINT),
             ClientConnectorState::EnhancedSecurityUpgrade { .. } => None,
+            ClientConnectorState::RdstlsWaitCapabilities { .. } => Some(&rdstls::RDSTLS_HINT),
+            ClientConnectorState::RdstlsWaitAuthResponse { .. } => Some(&rdstls::RDSTLS_HINT),
             ClientConnectorState::Credssp { .. } => None,
             ClientConnectorState::BasicSettingsExchangeSendInitial { .. } => None,
             ClientConnectorState::BasicSettingsExchangeWaitResponse { .. } => Some(&ironrdp_pdu::X224_HINT),
@@ -260,6 +271,10 @@ impl Sequence for ClientConnector {
                     security_protocol.insert(nego::SecurityProtocol::HYBRID | nego::SecurityProtocol::HYBRID_EX);
                 }
 
+                if self.config.redirection_credentials.is_some() {
+                    security_protocol.insert(nego::SecurityProtocol::RDSTLS);
+                }
+
                 if security_protocol.is_standard_rdp_security() {
                     return Err(reason_err!("Initiation", "standard RDP security is not supported",));
                 }
@@ -324,7 +339,10 @@ impl Sequence for ClientConnector {
             // NOTE: we assume the selected protocol is never the standard RDP security (RC4).
             // User code should match this variant and perform the appropriate upgrade (TLS handshake, etc).
             ClientConnectorState::EnhancedSecurityUpgrade { selected_protocol } => {
-                let next_state = if selected_protocol
+                let next_state = if selected_protocol.contains(nego::SecurityProtocol::RDSTLS) {
+                    debug!("Begin RDSTLS authentication");
+                    ClientConnectorState::RdstlsWaitCapabilities { selected_protocol }
+                } else if selected_protocol
                     .intersects(nego::SecurityProtocol::HYBRID | nego::SecurityProtocol::HYBRID_EX)
                 {
                     debug!("Begin NLA using CredSSP");
@@ -337,6 +355,59 @@ impl Sequence for ClientConnector {
                 (Written::Nothing, next_state)
             }
 
+            //== RDSTLS ==//
+            // Authenticate with the credentials of a server redirection.
+            ClientConnectorState::RdstlsWaitCapabilities { selected_protocol } => {
+                let capabilities = decode::<rdstls::RdstlsCapabilities>(input).map_err(ConnectorError::decode)?;
+
+                debug!(message = ?capabilities, "Received");
+
+                if !capabilities.supports_version_1() {
+                    return Err(reason_err!("RDSTLS", "server does not support RDSTLS version 1"));
+                }
+
+                let credentials = self
+                    .config
+                    .redirection_credentials
+                    .as_ref()
+                    .ok_or_else(|| general_err!("RDSTLS selected without redirection credentials"))?;
+
+                let auth_request = rdstls::RdstlsAuthenticationRequest::Password(rdstls::RdstlsPasswordCredentials {
+                    redirection_guid: credentials.redirection_guid.clone(),
+                    username: credentials.username.clone(),
+                    domain: credentials.domain.clone().unwrap_or_default(),
+                    password: credentials.password.clone(),
+                });
+
+                debug!(message = ?auth_request, "Send");
+
+                let written = ironrdp_core::encode_buf(&auth_request, output).map_err(ConnectorError::encode)?;
+
+                (
+                    Written::from_size(written)?,
+                    ClientConnectorState::RdstlsWaitAuthResponse { selected_protocol },
+                )
+            }
+            ClientConnectorState::RdstlsWaitAuthResponse { selected_protocol } => {
+                let auth_response =
+                    decode::<rdstls::RdstlsAuthenticationResponse>(input).map_err(ConnectorError::decode)?;
+
+                debug!(message = ?auth_response, "Received");
+
+                if auth_response.result_code != rdstls::RdstlsResultCode::SUCCESS {
+                    return Err(reason_err!(
+                        "RDSTLS",
+                        "authentication failed: {}",
+                        auth_response.result_code
+                    ));
+                }
+
+                (
+                    Written::Nothing,
+                    ClientConnectorState::BasicSettingsExchangeSendInitial { selected_protocol },
+                )
+            }
+
             //== CredSSP ==//
             ClientConnectorState::Credssp { selected_protocol } => (
                 Written::Nothing,
@@ -465,7 +536,7 @@ impl Sequence for ClientConnector {
             } => {
                 debug!("Secure Settings Exchange");
 
-                let client_info = create_client_info_pdu(&self.config, &self.client_addr);
+                let client_info = create_client_info_pdu(&self.config, &self.client_addr)?;
 
                 debug!(message = ?client_info, "Send");
 
@@ -532,7 +603,8 @@ impl Sequence for ClientConnector {
             }
 
             //== Optional Multitransport Bootstrapping ==//
-            // NOTE: our implementation is not expecting the Auto-Detect Request PDU from server
+            // The server may send Initiate Multitransport Request PDUs before the Demand Active PDU. Since there is no
+            // telling whether it will, they are declined as they come in the capabilities exchange state.
             ClientConnectorState::MultitransportBootstrapping {
                 io_channel_id,
                 user_channel_id,
@@ -552,13 +624,20 @@ impl Sequence for ClientConnector {
             ClientConnectorState::CapabilitiesExchange {
                 mut connection_activation,
             } => {
-                let written = connection_activation.step(input, output)?;
-                match connection_activation.connection_activation_state() {
-                    ConnectionActivationState::ConnectionFinalization { .. } => (
+                if let Some(written) = decline_multitransport_request(&connection_activation, input, output)? {
+                    (
                         written,
-                        ClientConnectorState::ConnectionFinalization { connection_activation },
-                    ),
-                    _ => return Err(general_err!("invalid state (this is a bug)")),
+                        ClientConnectorState::CapabilitiesExchange { connection_activation },
+                    )
+                } else {
+                    let written = connection_activation.step(input, output)?;
+                    match connection_activation.connection_activation_state() {
+                        ConnectionActivationState::ConnectionFinalization { .. } => (
+                            written,
+                            ClientConnectorState::ConnectionFinalization { connection_activation },
+                        ),
+                        _ => return Err(general_err!("invalid state (this is a bug)")),
+                    }
                 }
             }
 
@@ -609,6 +688,39 @@ impl Sequence for ClientConnector {
     }
 }
 
+/// Answers an Initiate Multitransport Request PDU with E_ABORT, the UDP transports not being supported
+///
+/// The requests are sent on the message channel, the PDUs received on the I/O channel being left to the capabilities
+/// exchange. Returns `None` when `input` is not a request.
+fn decline_multitransport_request(
+    connection_activation: &ConnectionActivationSequence,
+    input: &[u8],
+    output: &mut WriteBuf,
+) -> ConnectorResult<Option<Written>> {
+    let ConnectionActivationState::CapabilitiesExchange {
+        io_channel_id,
+        user_channel_id,
+    } = connection_activation.connection_activation_state()
+    else {
+        return Ok(None);
+    };
+
+    let send_data_indication_ctx = legacy::decode_send_data_indication(input)?;
+    if send_data_indication_ctx.channel_id == io_channel_id {
+        return Ok(None);
+    }
+
+    let request = send_data_indication_ctx.decode_user_data::<InitiateMultitransportRequest>()?;
+    debug!(message = ?request, "Received");
+
+    let response = InitiateMultitransportResponse::abort(request.request_id);
+    debug!(message = ?response, "Send");
+
+    let written = encode_send_data_request(user_channel_id, send_data_indication_ctx.channel_id, &response, output)?;
+
+    Ok(Some(Written::from_size(written)?))
+}
+
 pub fn encode_send_data_request<T: Encode>(
     initiator_id: u16,
     channel_id: u16,
@@ -635,9 +747,9 @@ fn create_gcc_blocks<'a>(
     static_channels: impl Iterator<Item = &'a StaticVirtualChannel>,
 ) -> ConnectorResult<gcc::ClientGccBlocks> {
     use ironrdp_pdu::gcc::{
-        ClientCoreData, ClientCoreOptionalData, ClientEarlyCapabilityFlags, ClientGccBlocks, ClientNetworkData,
-        ClientSecurityData, ColorDepth, ConnectionType, EncryptionMethod, HighColorDepth, MonitorOrientation,
-        RdpVersion, SecureAccessSequence, SupportedColorDepths,
+        ClientCoreData, ClientCoreOptionalData, ClientEarlyCapabilityFlags, ClientGccBlocks, ClientMonitorData,
+        ClientMonitorExtendedData, ClientNetworkData, ClientSecurityData, ColorDepth, ConnectionType, EncryptionMethod,
+        HighColorDepth, MonitorOrientation, RdpVersion, SecureAccessSequence, SupportedColorDepths,
     };
 
     let max_color_depth = config.bitmap.as_ref().map(|bitmap| bitmap.color_depth).unwrap_or(32);
@@ -659,6 +771,23 @@ fn create_gcc_blocks<'a>(
         .map(ironrdp_svc::make_channel_definition)
         .collect::<Vec<_>>();
 
+    let primary_monitor = config.monitors.iter().find(|monitor| monitor.is_primary);
+    let (desktop_physical_width, desktop_physical_height) = primary_monitor
+        .and_then(|monitor| monitor.physical_dimensions)
+        .unwrap_or((0, 0)); // 0 per FreeRDP
+    let desktop_orientation = match primary_monitor {
+        Some(monitor) => monitor.orientation,
+        None if config.desktop_size.width > config.desktop_size.height => MonitorOrientation::Landscape,
+        None => MonitorOrientation::Portrait,
+    };
+    let desktop_scale_factor =
+        primary_monitor.map_or(config.desktop_scale_factor, |monitor| monitor.desktop_scale_factor);
+    let device_scale_factor = match primary_monitor {
+        Some(monitor) => monitor.device_scale_factor,
+        None if (100..=500).contains(&config.desktop_scale_factor) => 100,
+        None => 0,
+    };
+
     Ok(ClientGccBlocks {
         core: ClientCoreData {
             version: RdpVersion::V5_PLUS,
@@ -683,7 +812,8 @@ fn create_gcc_blocks<'a>(
                     let mut early_capability_flags = ClientEarlyCapabilityFlags::VALID_CONNECTION_TYPE
                         | ClientEarlyCapabilityFlags::SUPPORT_ERR_INFO_PDU
                         | ClientEarlyCapabilityFlags::STRONG_ASYMMETRIC_KEYS
-                        | ClientEarlyCapabilityFlags::SUPPORT_SKIP_CHANNELJOIN;
+                        | ClientEarlyCapabilityFlags::SUPPORT_SKIP_CHANNELJOIN
+                        | ClientEarlyCapabilityFlags::RELATIVE_MOUSE_INPUT;
 
                     // TODO(#136): support for ClientEarlyCapabilityFlags::SUPPORT_STATUS_INFO_PDU
 
@@ -696,19 +826,11 @@ fn create_gcc_blocks<'a>(
                 dig_product_id: Some(config.dig_product_id.clone()),
                 connection_type: Some(ConnectionType::Lan),
                 server_selected_protocol: Some(selected_protocol),
-                desktop_physical_width: Some(0),  // 0 per FreeRDP
-                desktop_physical_height: Some(0), // 0 per FreeRDP
-                desktop_orientation: if config.desktop_size.width > config.desktop_size.height {
-                    Some(MonitorOrientation::Landscape.as_u16())
-                } else {
-                    Some(MonitorOrientation::Portrait.as_u16())
-                },
-                desktop_scale_factor: Some(config.desktop_scale_factor),
-                device_scale_factor: if config.desktop_scale_factor >= 100 && config.desktop_scale_factor <= 500 {
-                    Some(100)
-                } else {
-                    Some(0)
-                },
+                desktop_physical_width: Some(desktop_physical_width),
+                desktop_physical_height: Some(desktop_physical_height),
+                desktop_orientation: Some(desktop_orientation.as_u16()),
+                desktop_scale_factor: Some(desktop_scale_factor),
+                device_scale_factor: Some(device_scale_factor),
             },
         },
         security: ClientSecurityData {
@@ -722,16 +844,57 @@ fn create_gcc_blocks<'a>(
         },
         // TODO(#139): support for Some(ClientClusterData { flags: RedirectionFlags::REDIRECTION_SUPPORTED, redirection_version: RedirectionVersion::V4, redirected_session_id: 0, }),
         cluster: None,
-        monitor: None,
+        monitor: if config.monitors.is_empty() {
+            None
+        } else {
+            Some(ClientMonitorData {
+                monitors: config.monitors.iter().map(gcc_monitor).collect(),
+            })
+        },
         // TODO(#140): support for Client Message Channel Data (https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/f50e791c-de03-4b25-b17e-e914c9020bc3)
         message_channel: None,
         // TODO(#140): support for Some(MultiTransportChannelData { flags: MultiTransportFlags::empty(), })
         multi_transport_channel: None,
-        monitor_extended: None,
+        monitor_extended: if config.monitors.is_empty() {
+            None
+        } else {
+            Some(ClientMonitorExtendedData {
+                extended_monitors_info: config.monitors.iter().map(gcc_extended_monitor_info).collect(),
+            })
+        },
     })
 }
 
-fn create_client_info_pdu(config: &Config, client_addr: &SocketAddr) -> rdp::ClientInfoPdu {
+fn gcc_monitor(monitor: &MonitorConfig) -> gcc::Monitor {
+    // The right and bottom edges of a monitor definition are inclusive
+    let inclusive = |start: i32, len: u16| start.saturating_add(i32::from(len)).saturating_sub(1);
+
+    gcc::Monitor {
+        left: monitor.left,
+        top: monitor.top,
+        right: inclusive(monitor.left, monitor.width),
+        bottom: inclusive(monitor.top, monitor.height),
+        flags: if monitor.is_primary {
+            gcc::MonitorFlags::PRIMARY
+        } else {
+            gcc::MonitorFlags::empty()
+        },
+    }
+}
+
+fn gcc_extended_monitor_info(monitor: &MonitorConfig) -> gcc::ExtendedMonitorInfo {
+    let (physical_width, physical_height) = monitor.physical_dimensions.unwrap_or((0, 0));
+
+    gcc::ExtendedMonitorInfo {
+        physical_width,
+        physical_height,
+        orientation: monitor.orientation,
+        desktop_scale_factor: monitor.desktop_scale_factor,
+        device_scale_factor: monitor.device_scale_factor,
+    }
+}
+
+fn create_client_info_pdu(config: &Config, client_addr: &SocketAddr) -> ConnectorResult<rdp::ClientInfoPdu> {
     use ironrdp_pdu::rdp::client_info::{
         AddressFamily, ClientInfo, ClientInfoFlags, CompressionType, Credentials, ExtendedClientInfo,
         ExtendedClientOptionalInfo,
@@ -766,6 +929,28 @@ fn create_client_info_pdu(config: &Config, client_addr: &SocketAddr) -> rdp::Cli
         flags |= ClientInfoFlags::NO_AUDIO_PLAYBACK;
     }
 
+    if config.remote_app {
+        flags |= ClientInfoFlags::RAIL;
+    }
+
+    let optional_data = ExtendedClientOptionalInfo::builder()
+        .timezone(config.timezone_info.clone())
+        .session_id(0)
+        .performance_flags(config.performance_flags);
+
+    let optional_data = match &config.auto_reconnect_cookie {
+        Some(cookie) => {
+            // There is no client random with Enhanced RDP Security, 32 zero bytes are used instead.
+            let packet = encode_vec(&cookie.client_auto_reconnect(&[0; 32])).map_err(ConnectorError::encode)?;
+            let packet = packet
+                .try_into()
+                .map_err(|_| general_err!("invalid Client Auto-Reconnect Packet size"))?;
+
+            optional_data.reconnect_cookie(packet).build()
+        }
+        None => optional_data.build(),
+    };
+
     let client_info = ClientInfo {
         credentials: Credentials {
             username: config.credentials.username().unwrap_or("").to_owned(),
@@ -784,16 +969,12 @@ fn create_client_info_pdu(config: &Config, client_addr: &SocketAddr) -> rdp::Cli
             },
             address: client_addr.ip().to_string(),
             dir: config.client_dir.clone(),
-            optional_data: ExtendedClientOptionalInfo::builder()
-                .timezone(config.timezone_info.clone())
-                .session_id(0)
-                .performance_flags(config.performance_flags)
-                .build(),
+            optional_data,
         },
     };
 
-    ClientInfoPdu {
+    Ok(ClientInfoPdu {
         security_header,
         client_info,
-    }
+    })
 }
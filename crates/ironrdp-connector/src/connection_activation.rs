@@ -5,7 +5,8 @@ use ironrdp_pdu::rdp::capability_sets::CapabilitySet;
 use tracing::{debug, warn};
 
 use crate::{
-    general_err, legacy, Config, ConnectionFinalizationSequence, ConnectorResult, DesktopSize, Sequence, State, Written,
+    general_err, legacy, Config, ConnectionFinalizationSequence, ConnectorError, ConnectorErrorKind, ConnectorResult,
+    DesktopSize, Sequence, State, Written,
 };
 
 /// Represents the Capability Exchange and Connection Finalization phases
@@ -24,6 +25,7 @@ use crate::{
 pub struct ConnectionActivationSequence {
     state: ConnectionActivationState,
     config: Config,
+    relative_mouse_supported: bool,
 }
 
 impl ConnectionActivationSequence {
@@ -34,9 +36,17 @@ impl ConnectionActivationSequence {
                 user_channel_id,
             },
             config,
+            relative_mouse_supported: false,
         }
     }
 
+    /// Whether the server accepts relative mouse events, as advertised in its Input Capability Set
+    ///
+    /// Known once the capabilities are exchanged.
+    pub fn relative_mouse_supported(&self) -> bool {
+        self.relative_mouse_supported
+    }
+
     /// Returns the current state as a district type, rather than `&dyn State` provided by [`Self::state`].
     pub fn connection_activation_state(&self) -> ConnectionActivationState {
         self.state
@@ -117,14 +127,22 @@ impl Sequence for ConnectionActivationSequence {
                     );
                 }
 
-                let capability_sets = if let rdp::headers::ShareControlPdu::ServerDemandActive(server_demand_active) =
-                    share_control_ctx.pdu
-                {
-                    server_demand_active.pdu.capability_sets
-                } else {
-                    return Err(general_err!(
-                        "unexpected Share Control Pdu (expected ServerDemandActive)",
-                    ));
+                let capability_sets = match share_control_ctx.pdu {
+                    rdp::headers::ShareControlPdu::ServerDemandActive(server_demand_active) => {
+                        server_demand_active.pdu.capability_sets
+                    }
+                    // A load balancer or a connection broker redirects the client in place of the Demand Active PDU.
+                    rdp::headers::ShareControlPdu::ServerRedirection(redirection) => {
+                        return Err(ConnectorError::new(
+                            "server redirection",
+                            ConnectorErrorKind::ServerRedirection(Box::new(redirection.redirection)),
+                        ));
+                    }
+                    _ => {
+                        return Err(general_err!(
+                            "unexpected Share Control Pdu (expected ServerDemandActive)",
+                        ));
+                    }
                 };
 
                 for c in &capability_sets {
@@ -136,6 +154,13 @@ impl Sequence for ConnectionActivationSequence {
                     }
                 }
 
+                self.relative_mouse_supported = capability_sets.iter().any(|c| match c {
+                    CapabilitySet::Input(input) => input
+                        .input_flags
+                        .contains(rdp::capability_sets::InputFlags::MOUSE_RELATIVE),
+                    _ => false,
+                });
+
                 // At this point we have already sent a requested desktop size to the server -- either as a part of the
                 // [`TS_UD_CS_CORE`] (on initial connection) or the [`DISPLAYCONTROL_MONITOR_LAYOUT`] (on resize event).
                 //
@@ -297,7 +322,9 @@ fn create_client_confirm_active(
     server_capability_sets.extend_from_slice(&[
         CapabilitySet::General(General {
             major_platform_type: config.platform,
-            extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR,
+            extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED
+                | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR
+                | GeneralExtraFlags::AUTORECONNECT_SUPPORTED,
             ..Default::default()
         }),
         CapabilitySet::Bitmap(Bitmap {
@@ -382,6 +409,15 @@ fn create_client_confirm_active(
         }),
     ]);
 
+    if config.remote_app {
+        server_capability_sets.extend_from_slice(&[
+            // TS_RAIL_CAPABILITYSET: RAIL_LEVEL_SUPPORTED | RAIL_LEVEL_HANDSHAKE_EX_SUPPORTED
+            CapabilitySet::Rail(vec![0x81, 0x00, 0x00, 0x00]),
+            // TS_WINDOW_CAPABILITYSET: WINDOW_LEVEL_SUPPORTED, 3 icon caches of 12 entries
+            CapabilitySet::WindowList(vec![0x01, 0x00, 0x00, 0x00, 0x03, 0x0c, 0x00]),
+        ]);
+    }
+
     if !server_capability_sets
         .iter()
         .any(|c| matches!(&c, CapabilitySet::MultiFragmentUpdate(_)))
@@ -1,22 +1,148 @@
+mod kerberos;
+
+use core::fmt;
+use std::sync::Arc;
+
 use ironrdp_core::{other_err, WriteBuf};
 use ironrdp_pdu::{nego, PduHint};
+use picky::hash::HashAlgorithm;
 use picky::key::PrivateKey;
-use picky_asn1_x509::{oids, Certificate, ExtensionView, GeneralName};
+use picky_asn1_x509::{
+    oids, AlgorithmIdentifier, AlgorithmIdentifierParameters, Certificate, ExtensionView, GeneralName,
+};
 use sspi::credssp::{self, ClientState, CredSspClient};
 use sspi::generator::{Generator, NetworkRequest};
 use sspi::negotiate::ProtocolConfig;
-use sspi::Secret;
-use sspi::Username;
+use sspi::{Secret, Username};
 use tracing::debug;
 
+use self::kerberos::KerberosCredsspClient;
 use crate::{
     custom_err, general_err, ConnectorError, ConnectorErrorKind, ConnectorResult, Credentials, ServerName, Written,
 };
 
-#[derive(Debug, Clone, Default)]
+/// Source of the credentials used to authenticate with Kerberos
+///
+/// The provider is queried when the CredSSP sequence starts, before any message is exchanged, letting clients
+/// implement single sign-on by fetching the credentials of the logged-on user (from a secret store, a broker…)
+/// instead of prompting for them.
+///
+/// The security package still authenticates with a password or a smart card: reusing the tickets of a Kerberos
+/// credential cache is not supported, as sspi can't acquire credentials from one.
+pub trait CredentialProvider: fmt::Debug + Send + Sync {
+    /// Returns the credentials for `service_principal_name` (`TERMSRV/<server>`), or `None` to use the ones of the
+    /// connector configuration
+    fn credentials(&self, service_principal_name: &str) -> ConnectorResult<Option<Credentials>>;
+}
+
+/// Channel bindings of the TLS connection, given to the Kerberos security package
+///
+/// The `tls-server-end-point` bindings (RFC 5929) are derived from the certificate of the server. With them, the
+/// authenticator of the Kerberos AP-REQ is bound to the TLS connection, in addition to the public key check of CredSSP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelBindings {
+    application_data: Vec<u8>,
+}
+
+impl ChannelBindings {
+    /// Size of the SEC_CHANNEL_BINDINGS header, preceding the application data
+    const HEADER_SIZE: usize = 32;
+
+    /// Builds the `tls-server-end-point` bindings from the DER-encoded certificate of the server
+    ///
+    /// Fails when the bindings are undefined for the signature algorithm of the certificate (RFC 5929 4.1).
+    pub fn tls_server_end_point(certificate_der: &[u8]) -> ConnectorResult<Self> {
+        let certificate: Certificate =
+            picky_asn1_der::from_bytes(certificate_der).map_err(|_e| general_err!("can't parse server certificate"))?;
+
+        let hash_algorithm = end_point_hash_algorithm(&certificate.signature_algorithm)?;
+
+        let mut application_data = b"tls-server-end-point:".to_vec();
+        application_data.extend_from_slice(&hash_algorithm.digest(certificate_der));
+
+        Ok(Self { application_data })
+    }
+
+    /// Encodes the bindings as a SEC_CHANNEL_BINDINGS structure, without initiator and acceptor addresses
+    fn to_sec_channel_bindings(&self) -> ConnectorResult<Vec<u8>> {
+        let application_length =
+            u32::try_from(self.application_data.len()).map_err(|_e| general_err!("channel bindings are too long"))?;
+        let application_offset =
+            u32::try_from(Self::HEADER_SIZE).map_err(|_e| general_err!("channel bindings are too long"))?;
+
+        let mut buffer = vec![0; Self::HEADER_SIZE];
+        buffer[24..28].copy_from_slice(&application_length.to_le_bytes());
+        buffer[28..32].copy_from_slice(&application_offset.to_le_bytes());
+        buffer.extend_from_slice(&self.application_data);
+
+        Ok(buffer)
+    }
+}
+
+/// Selects the hash function of the `tls-server-end-point` bindings (RFC 5929 4.1)
+///
+/// The hash function of the certificate signature is used, or SHA-256 when it is MD5 or SHA-1. For RSASSA-PSS, the
+/// hash function is the one of the signature parameters.
+fn end_point_hash_algorithm(signature_algorithm: &AlgorithmIdentifier) -> ConnectorResult<HashAlgorithm> {
+    use picky_asn1_x509::HashAlgorithm as PssHashAlgorithm;
+
+    if let AlgorithmIdentifierParameters::RsassaPss(params) = signature_algorithm.parameters() {
+        return match params.hash_algorithm {
+            PssHashAlgorithm::SHA224 => Ok(HashAlgorithm::SHA2_224),
+            PssHashAlgorithm::SHA256 => Ok(HashAlgorithm::SHA2_256),
+            PssHashAlgorithm::SHA384 => Ok(HashAlgorithm::SHA2_384),
+            PssHashAlgorithm::SHA512 => Ok(HashAlgorithm::SHA2_512),
+            PssHashAlgorithm::SHA3_384 => Ok(HashAlgorithm::SHA3_384),
+            PssHashAlgorithm::SHA3_512 => Ok(HashAlgorithm::SHA3_512),
+            PssHashAlgorithm::SHA3_224 | PssHashAlgorithm::SHA3_256 => {
+                Err(general_err!("unsupported RSASSA-PSS hash algorithm"))
+            }
+        };
+    }
+
+    let oid: String = signature_algorithm.oid().into();
+    match oid.as_str() {
+        // MD5 and SHA-1 are upgraded to SHA-256.
+        oids::MD5_WITH_RSA_ENCRYPTHION | oids::SHA1_WITH_RSA_ENCRYPTION => Ok(HashAlgorithm::SHA2_256),
+        oids::SHA224_WITH_RSA_ENCRYPTION => Ok(HashAlgorithm::SHA2_224),
+        oids::SHA256_WITH_RSA_ENCRYPTION | oids::ECDSA_WITH_SHA256 => Ok(HashAlgorithm::SHA2_256),
+        oids::SHA384_WITH_RSA_ENCRYPTION | oids::ECDSA_WITH_SHA384 => Ok(HashAlgorithm::SHA2_384),
+        oids::SHA512_WITH_RSA_ENCRYPTION | oids::ECDSA_WITH_SHA512 => Ok(HashAlgorithm::SHA2_512),
+        oids::ID_RSASSA_PKCS1_V1_5_WITH_SHA3_384 | oids::ID_ECDSA_WITH_SHA3_384 => Ok(HashAlgorithm::SHA3_384),
+        oids::ID_RSASSA_PKCS1_V1_5_WITH_SHA3_512 | oids::ID_ECDSA_WITH_SHA3_512 => Ok(HashAlgorithm::SHA3_512),
+        // EdDSA has no single hash function, the bindings are undefined.
+        _ => Err(general_err!("unsupported certificate signature algorithm")),
+    }
+}
+
+/// Kerberos settings of the network level authentication
+///
+/// Kerberos is negotiated through SPNEGO, with NTLM as a fallback. The server public key is authenticated by the
+/// security context as for NTLM, and when [`ChannelBindings`] are given, the Kerberos authenticator is bound to the TLS
+/// connection as well.
+///
+/// With user-to-user enabled, the client asks for the TGT of the server in the initial token, letting the server pick
+/// user-to-user authentication when it has no service key (e.g. a server running under a user account).
+#[derive(Debug, Clone)]
 pub struct KerberosConfig {
     pub kdc_proxy_url: Option<url::Url>,
     pub hostname: Option<String>,
+    pub credential_provider: Option<Arc<dyn CredentialProvider>>,
+    /// Whether to offer user-to-user authentication, enabled by default
+    pub user_to_user: bool,
+    pub channel_bindings: Option<ChannelBindings>,
+}
+
+impl Default for KerberosConfig {
+    fn default() -> Self {
+        Self {
+            kdc_proxy_url: None,
+            hostname: None,
+            credential_provider: None,
+            user_to_user: true,
+            channel_bindings: None,
+        }
+    }
 }
 
 impl KerberosConfig {
@@ -28,8 +154,43 @@ impl KerberosConfig {
         Ok(Self {
             kdc_proxy_url,
             hostname,
+            ..Self::default()
         })
     }
+
+    #[must_use]
+    pub fn with_credential_provider(mut self, provider: Arc<dyn CredentialProvider>) -> Self {
+        self.credential_provider = Some(provider);
+        self
+    }
+
+    #[must_use]
+    pub fn with_user_to_user(mut self, user_to_user: bool) -> Self {
+        self.user_to_user = user_to_user;
+        self
+    }
+
+    #[must_use]
+    pub fn with_channel_bindings(mut self, channel_bindings: ChannelBindings) -> Self {
+        self.channel_bindings = Some(channel_bindings);
+        self
+    }
+
+    /// Queries the credential provider, if any
+    fn provided_credentials(&self, service_principal_name: &str) -> ConnectorResult<Option<Credentials>> {
+        let Some(provider) = &self.credential_provider else {
+            return Ok(None);
+        };
+
+        let credentials = provider.credentials(service_principal_name)?;
+        debug!(
+            service_principal_name,
+            provided = credentials.is_some(),
+            "Queried the credential provider"
+        );
+
+        Ok(credentials)
+    }
 }
 
 impl From<KerberosConfig> for sspi::KerberosConfig {
@@ -69,9 +230,26 @@ impl PduHint for CredsspEarlyUserAuthResultHint {
 
 pub type CredsspProcessGenerator<'a> = Generator<'a, NetworkRequest, sspi::Result<Vec<u8>>, sspi::Result<ClientState>>;
 
+#[derive(Debug)]
+enum CredsspClient {
+    /// NTLM only, with the CredSSP client of sspi
+    Ntlm(CredSspClient),
+    /// Kerberos with NTLM fallback, with the options of the [`KerberosConfig`]
+    Kerberos(Box<KerberosCredsspClient>),
+}
+
+impl CredsspClient {
+    fn process(&mut self, request: credssp::TsRequest) -> CredsspProcessGenerator<'_> {
+        match self {
+            Self::Ntlm(client) => client.process(request),
+            Self::Kerberos(client) => client.process(request),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct CredsspSequence {
-    client: CredSspClient,
+    client: CredsspClient,
     state: CredsspState,
     selected_protocol: nego::SecurityProtocol,
 }
@@ -101,6 +279,17 @@ impl CredsspSequence {
         server_public_key: Vec<u8>,
         kerberos_config: Option<KerberosConfig>,
     ) -> ConnectorResult<(Self, credssp::TsRequest)> {
+        let server_name = server_name.into_inner();
+
+        let service_principal_name = format!("TERMSRV/{}", &server_name);
+
+        let credentials = match &kerberos_config {
+            Some(krb_config) => krb_config
+                .provided_credentials(&service_principal_name)?
+                .unwrap_or(credentials),
+            None => credentials,
+        };
+
         let credentials: sspi::Credentials = match &credentials {
             Credentials::UsernamePassword { username, password } => {
                 let username = Username::new(username, domain).map_err(|e| custom_err!("invalid username", e))?;
@@ -140,30 +329,58 @@ impl CredsspSequence {
             },
         };
 
-        let server_name = server_name.into_inner();
-
-        let service_principal_name = format!("TERMSRV/{}", &server_name);
-
-        let credssp_config: Box<dyn ProtocolConfig>;
-        if let Some(ref krb_config) = kerberos_config {
-            credssp_config = Box::new(Into::<sspi::KerberosConfig>::into(krb_config.clone()));
-        } else {
-            credssp_config = Box::<sspi::ntlm::NtlmConfig>::default();
-        }
-        debug!(?credssp_config);
-
-        let client = CredSspClient::new(
-            server_public_key,
-            credentials,
-            credssp::CredSspMode::WithCredentials,
-            credssp::ClientMode::Negotiate(sspi::NegotiateConfig {
-                protocol_config: credssp_config,
-                package_list: None,
-                client_computer_name: server_name,
-            }),
-            service_principal_name,
-        )
-        .map_err(|e| ConnectorError::new("CredSSP", ConnectorErrorKind::Credssp(e)))?;
+        let client = match kerberos_config {
+            Some(krb_config) => {
+                let channel_bindings = krb_config
+                    .channel_bindings
+                    .as_ref()
+                    .map(ChannelBindings::to_sec_channel_bindings)
+                    .transpose()?;
+                let user_to_user = krb_config.user_to_user;
+
+                let credssp_config: Box<dyn ProtocolConfig> = Box::new(sspi::KerberosConfig::from(krb_config));
+                debug!(
+                    ?credssp_config,
+                    user_to_user,
+                    channel_bindings = channel_bindings.is_some()
+                );
+
+                let client = KerberosCredsspClient::new(
+                    server_public_key,
+                    &credentials,
+                    sspi::NegotiateConfig {
+                        protocol_config: credssp_config,
+                        package_list: None,
+                        client_computer_name: server_name,
+                    },
+                    service_principal_name,
+                    user_to_user,
+                    channel_bindings,
+                )
+                .map_err(|e| ConnectorError::new("CredSSP", ConnectorErrorKind::Credssp(e)))?;
+
+                CredsspClient::Kerberos(Box::new(client))
+            }
+            None => {
+                let credssp_config: Box<dyn ProtocolConfig> = Box::<sspi::ntlm::NtlmConfig>::default();
+                debug!(?credssp_config);
+
+                let client = CredSspClient::new(
+                    server_public_key,
+                    credentials,
+                    credssp::CredSspMode::WithCredentials,
+                    credssp::ClientMode::Negotiate(sspi::NegotiateConfig {
+                        protocol_config: credssp_config,
+                        package_list: None,
+                        client_computer_name: server_name,
+                    }),
+                    service_principal_name,
+                )
+                .map_err(|e| ConnectorError::new("CredSSP", ConnectorErrorKind::Credssp(e)))?;
+
+                CredsspClient::Ntlm(client)
+            }
+        };
 
         let sequence = Self {
             client,
@@ -275,3 +492,128 @@ fn write_credssp_request(ts_request: credssp::TsRequest, output: &mut WriteBuf)
 
     Ok(length)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ECDSA_SHA384_CERTIFICATE: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBszCCATqgAwIBAgIUUHUPXT7nf+vHVZWzDTVJSweQTeowCgYIKoZIzj0EAwMw
+ETEPMA0GA1UEAwwGc2hhMzg0MB4XDTI2MTAxNjEyNDI0OVoXDTM2MTAxMzEyNDI0
+OVowETEPMA0GA1UEAwwGc2hhMzg0MHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEZTed
+mZTVc83Kbhv5n7OjO4zq1ZSgPeuzYPXhDoThx66gHZtfNdf/Ttoojgp6xT4sIM5h
+YpVtDQV3/wYXhwtG8VoqP2lsbbqNt8gv0uNBtzjVL4OGqrVMAq/FWE71l7umo1Mw
+UTAdBgNVHQ4EFgQUziRasMq9qQQ2xvBoGXUXpoujam0wHwYDVR0jBBgwFoAUziRa
+sMq9qQQ2xvBoGXUXpoujam0wDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAwNn
+ADBkAjBY43Oq6ZtBpC1djJaT5eS9inT16rgRiVKKf6QIN+tzJbvZOlPQEz2jl7Dr
+rNovxnECMHLWSIYd98WpSVY/ZdDmttKJSO3RlfDY21b50b5GneI+07QMrp356Hw3
+3dNFWjswww==
+-----END CERTIFICATE-----";
+
+    const RSA_SHA1_CERTIFICATE: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIB+jCCAWOgAwIBAgIUeCHLLpv2pnnmttcJXPxMnQXaZYAwDQYJKoZIhvcNAQEF
+BQAwDzENMAsGA1UEAwwEc2hhMTAeFw0yNjEwMTYxMjQ2MjFaFw0zNjEwMTMxMjQ2
+MjFaMA8xDTALBgNVBAMMBHNoYTEwgZ8wDQYJKoZIhvcNAQEBBQADgY0AMIGJAoGB
+AMZel1v3OAamj9bEm+bp0kfF+kcsljup2U/9w1g3osb4Rk7Z+9MBgVPy9Yx9GOTL
+3mNjnlEgKBvncM3ha68ZP9q6UWEwCn0SOKycpGs+cxiF97LUGZs/CO2eqvklu/62
+B714I92Eaq2JXnzTtgbddr2yULsV2ckI9G8VZVh6vOL7AgMBAAGjUzBRMB0GA1Ud
+DgQWBBS2CO34Acnl2WDB3cE4h6p/kne3UTAfBgNVHSMEGDAWgBS2CO34Acnl2WDB
+3cE4h6p/kne3UTAPBgNVHRMBAf8EBTADAQH/MA0GCSqGSIb3DQEBBQUAA4GBAHY/
+9AEP46EBHXDJInyk7ceexOMZVaY8on+LwzUMWKlSdDmb3UrYruM2FM10YbOXDqQo
+l4BRU718mOC1ka9BIEdf1NEclYRmAt0sj8iaJYdgKQZaidw9E2fiGCmwF8aOduVM
+XXihRT+1VrmkQ6cW0t/A5h66ByODPAIRoFd9xyog
+-----END CERTIFICATE-----";
+
+    /// RSASSA-PSS with SHA-512, MGF1 with SHA-512
+    const RSASSA_PSS_SHA512_CERTIFICATE: &str = "\
+-----BEGIN CERTIFICATE-----
+MIICYDCCAZWgAwIBAgIUWkIbp7tj0M3dgbvhvgVyOE8Dia8wQQYJKoZIhvcNAQEK
+MDSgDzANBglghkgBZQMEAgMFAKEcMBoGCSqGSIb3DQEBCDANBglghkgBZQMEAgMF
+AKIDAgEgMA4xDDAKBgNVBAMMA3BzczAeFw0yNjEwMTYxMjQyNTNaFw0zNjEwMTMx
+MjQyNTNaMA4xDDAKBgNVBAMMA3BzczCBnzANBgkqhkiG9w0BAQEFAAOBjQAwgYkC
+gYEAxl6XW/c4BqaP1sSb5unSR8X6RyyWO6nZT/3DWDeixvhGTtn70wGBU/L1jH0Y
+5MveY2OeUSAoG+dwzeFrrxk/2rpRYTAKfRI4rJykaz5zGIX3stQZmz8I7Z6q+SW7
+/rYHvXgj3YRqrYlefNO2Bt12vbJQuxXZyQj0bxVlWHq84vsCAwEAAaNTMFEwHQYD
+VR0OBBYEFLYI7fgByeXZYMHdwTiHqn+Sd7dRMB8GA1UdIwQYMBaAFLYI7fgByeXZ
+YMHdwTiHqn+Sd7dRMA8GA1UdEwEB/wQFMAMBAf8wQQYJKoZIhvcNAQEKMDSgDzAN
+BglghkgBZQMEAgMFAKEcMBoGCSqGSIb3DQEBCDANBglghkgBZQMEAgMFAKIDAgEg
+A4GBAJuSvxJA+Kxigv8h6+NqjsPhC00BQ1h2ZIiDDnIb4sYq+5RTy9n6w2kMvTyx
+XX90OPPmm3N3Cu8yftXZk54iSU7GAKIMue+FuaOOC6HEklvUh8oYnl1MYPkum/5m
+25vY3vrr2f1hr9lE9r01dbooh3EW5LqNuZ9+XvOLhfMhqoPr
+-----END CERTIFICATE-----";
+
+    const ED25519_CERTIFICATE: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBODCB66ADAgECAhQmQDJBdbFxAd493afa6ev1jHRPuzAFBgMrZXAwEjEQMA4G
+A1UEAwwHZWQyNTUxOTAeFw0yNjEwMTYxMjQ3NDBaFw0zNjEwMTMxMjQ3NDBaMBIx
+EDAOBgNVBAMMB2VkMjU1MTkwKjAFBgMrZXADIQCL7vEJFV8MEK5eKkAtDVIDohUb
+xaPggH4OHAKCYhBH46NTMFEwHQYDVR0OBBYEFPDahXTvc9e4PQl6/xcMVxLHNr9M
+MB8GA1UdIwQYMBaAFPDahXTvc9e4PQl6/xcMVxLHNr9MMA8GA1UdEwEB/wQFMAMB
+Af8wBQYDK2VwA0EA8cp6R+wbmdW3zJcpeR4V6hNMuP8hRnb3fSN64HtMZIws7b7T
+1M+bfGRQy7bk2efDfIwlO8Ww84HXpq0AVoQECw==
+-----END CERTIFICATE-----";
+
+    fn end_point_bindings(certificate_pem: &str) -> ConnectorResult<ChannelBindings> {
+        let pem = picky::pem::parse_pem(certificate_pem).unwrap();
+        ChannelBindings::tls_server_end_point(pem.data())
+    }
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn check_end_point_bindings(certificate_pem: &str, expected_hash: &str) {
+        let encoded = end_point_bindings(certificate_pem)
+            .unwrap()
+            .to_sec_channel_bindings()
+            .unwrap();
+
+        let mut expected_application_data = b"tls-server-end-point:".to_vec();
+        expected_application_data.extend_from_slice(&decode_hex(expected_hash));
+        assert_eq!(
+            encoded.len(),
+            ChannelBindings::HEADER_SIZE + expected_application_data.len()
+        );
+
+        let decoded = sspi::channel_bindings::ChannelBindings::from_bytes(&encoded).unwrap();
+        assert_eq!(decoded.initiator_addr_type, 0);
+        assert!(decoded.initiator.is_empty());
+        assert_eq!(decoded.acceptor_addr_type, 0);
+        assert!(decoded.acceptor.is_empty());
+        assert_eq!(decoded.application_data, expected_application_data);
+    }
+
+    #[test]
+    fn end_point_bindings_use_signature_hash() {
+        check_end_point_bindings(
+            ECDSA_SHA384_CERTIFICATE,
+            "cc69da42b84a5ac8e7ca3b0764aa987dcc6d07d7c87feec0100bd1490a68c3d258ac64e9ef99b5d8df50ee02c0efe16b",
+        );
+    }
+
+    #[test]
+    fn end_point_bindings_upgrade_sha1_to_sha256() {
+        check_end_point_bindings(
+            RSA_SHA1_CERTIFICATE,
+            "e487c845993f07cb9ff42d4925c5e9823baaca4bec2be3bc14785a5de189e143",
+        );
+    }
+
+    #[test]
+    fn end_point_bindings_use_pss_parameters_hash() {
+        check_end_point_bindings(
+            RSASSA_PSS_SHA512_CERTIFICATE,
+            "8448fdb4c9cb00effdf44c1fdcd95b977bb85fa5fe1f49dcca88bb9ecc32d1348e16adfc4e80c5091b8fa25a509576d60e9c8ada4717cd353509724d469e3d7c",
+        );
+    }
+
+    #[test]
+    fn end_point_bindings_undefined_for_ed25519() {
+        assert!(end_point_bindings(ED25519_CERTIFICATE).is_err());
+    }
+}
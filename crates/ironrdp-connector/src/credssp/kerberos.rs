@@ -0,0 +1,508 @@
+//! CredSSP client authenticating with Kerberos, with the options of [`KerberosConfig`]
+//!
+//! The CredSSP client of sspi neither passes channel bindings to the security package nor lets the caller choose the
+//! context requirements. This client runs the same [MS-CSSP] 3.1.5 sequence over an SPNEGO context, giving the
+//! security package the channel bindings of the TLS connection, and requesting user-to-user only when enabled.
+//!
+//! [`KerberosConfig`]: super::KerberosConfig
+
+use picky::hash::HashAlgorithm;
+use rand::RngCore as _;
+use sspi::credssp::{self, ClientState, CredSspMode, SspiContext, TsRequest};
+use sspi::generator::{Generator, GeneratorState, NetworkRequest, YieldPoint};
+use sspi::{
+    BufferType, ClientRequestFlags, CredentialUse, Credentials, CredentialsBuffers, DataRepresentation,
+    EncryptionFlags, Error, ErrorKind, Negotiate, NegotiateConfig, SecurityBuffer, SecurityBufferRef, SecurityStatus,
+    Sspi as _, SspiImpl,
+};
+use tracing::debug;
+
+use super::CredsspProcessGenerator;
+
+/// Version of the TSRequest structures sent by the client
+const TS_REQUEST_VERSION: u32 = 6;
+
+const NONCE_SIZE: usize = 32;
+
+const CLIENT_SERVER_HASH_MAGIC: &[u8] = b"CredSSP Client-To-Server Binding Hash\0";
+const SERVER_CLIENT_HASH_MAGIC: &[u8] = b"CredSSP Server-To-Client Binding Hash\0";
+
+type CredsspYieldPoint = YieldPoint<NetworkRequest, sspi::Result<Vec<u8>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    NegoToken,
+    AuthInfo,
+    Final,
+}
+
+#[derive(Debug)]
+pub(crate) struct KerberosCredsspClient {
+    state: State,
+    context: SspiContext,
+    credentials_handle: Option<CredentialsBuffers>,
+    public_key: Vec<u8>,
+    client_nonce: [u8; NONCE_SIZE],
+    /// Whether the initial request of the client was processed
+    started: bool,
+    /// Version of the TSRequest structures of the server, once one was received
+    peer_version: Option<u32>,
+    send_seq_num: u32,
+    recv_seq_num: u32,
+    service_principal_name: String,
+    context_requirements: ClientRequestFlags,
+    /// SEC_CHANNEL_BINDINGS structure given to the security package
+    channel_bindings: Option<Vec<u8>>,
+}
+
+impl KerberosCredsspClient {
+    pub(crate) fn new(
+        public_key: Vec<u8>,
+        credentials: &Credentials,
+        negotiate_config: NegotiateConfig,
+        service_principal_name: String,
+        user_to_user: bool,
+        channel_bindings: Option<Vec<u8>>,
+    ) -> sspi::Result<Self> {
+        let mut context = SspiContext::Negotiate(Negotiate::new_client(negotiate_config)?);
+
+        let credentials_handle = context
+            .acquire_credentials_handle()
+            .with_auth_data(credentials)
+            .with_credential_use(CredentialUse::Outbound)
+            .execute(&mut context)?
+            .credentials_handle;
+
+        // With USE_SESSION_KEY, the initial token asks for the TGT of the server, which the server may then select
+        // for user-to-user when it has no service key.
+        let mut context_requirements =
+            ClientRequestFlags::MUTUAL_AUTH | ClientRequestFlags::INTEGRITY | ClientRequestFlags::CONFIDENTIALITY;
+        if user_to_user {
+            context_requirements |= ClientRequestFlags::USE_SESSION_KEY;
+        }
+
+        let mut client_nonce = [0; NONCE_SIZE];
+        rand::rng().fill_bytes(&mut client_nonce);
+
+        Ok(Self {
+            state: State::NegoToken,
+            context,
+            credentials_handle,
+            public_key,
+            client_nonce,
+            started: false,
+            peer_version: None,
+            send_seq_num: 0,
+            recv_seq_num: 0,
+            service_principal_name,
+            context_requirements,
+            channel_bindings,
+        })
+    }
+
+    pub(crate) fn process(&mut self, ts_request: TsRequest) -> CredsspProcessGenerator<'_> {
+        Generator::new(move |mut yield_point| async move { self.process_impl(&mut yield_point, ts_request).await })
+    }
+
+    async fn process_impl(
+        &mut self,
+        yield_point: &mut CredsspYieldPoint,
+        mut ts_request: TsRequest,
+    ) -> sspi::Result<ClientState> {
+        ts_request.check_error()?;
+
+        // The first request is the initial one of the client, the following ones are received from the server.
+        if self.started {
+            self.check_peer_version(ts_request.version)?;
+        }
+        self.started = true;
+
+        ts_request.version = TS_REQUEST_VERSION;
+
+        match self.state {
+            State::NegoToken => {
+                let input = ts_request.nego_tokens.take().unwrap_or_default();
+                let (status, output) = self.initialize_security_context(yield_point, input).await?;
+
+                ts_request.nego_tokens = Some(output);
+
+                if status == SecurityStatus::Ok {
+                    debug!("Kerberos security context established");
+
+                    let peer_version = self.peer_version.ok_or_else(|| {
+                        Error::new(ErrorKind::OutOfSequence, "no TSRequest was received from the server")
+                    })?;
+
+                    ts_request.pub_key_auth = Some(self.encrypt_public_key(peer_version)?);
+                    ts_request.client_nonce = Some(self.client_nonce);
+
+                    if ts_request.nego_tokens.as_ref().is_some_and(Vec::is_empty) {
+                        ts_request.nego_tokens = None;
+                    }
+
+                    self.state = State::AuthInfo;
+                }
+
+                Ok(ClientState::ReplyNeeded(ts_request))
+            }
+            State::AuthInfo => {
+                ts_request.nego_tokens = None;
+
+                let pub_key_auth = ts_request
+                    .pub_key_auth
+                    .take()
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidToken, "expected an encrypted public key"))?;
+                let peer_version = self
+                    .peer_version
+                    .ok_or_else(|| Error::new(ErrorKind::OutOfSequence, "no TSRequest was received from the server"))?;
+                self.verify_public_key(&pub_key_auth, peer_version)?;
+
+                let credentials = self
+                    .credentials_handle
+                    .as_ref()
+                    .ok_or_else(|| Error::new(ErrorKind::NoCredentials, "no credentials to delegate"))?;
+                let ts_credentials = credssp::write_ts_credentials(credentials, CredSspMode::WithCredentials)?;
+                ts_request.auth_info = Some(self.encrypt_message(&ts_credentials)?);
+
+                self.state = State::Final;
+
+                Ok(ClientState::FinalMessage(ts_request))
+            }
+            State::Final => Err(Error::new(
+                ErrorKind::OutOfSequence,
+                "the CredSSP sequence is already finished",
+            )),
+        }
+    }
+
+    fn check_peer_version(&mut self, version: u32) -> sspi::Result<()> {
+        match self.peer_version {
+            Some(peer_version) if peer_version != version => Err(Error::new(
+                ErrorKind::MessageAltered,
+                format!("CredSSP peer changed protocol version from {peer_version} to {version}"),
+            )),
+            Some(_) => Ok(()),
+            None => {
+                self.peer_version = Some(version);
+                Ok(())
+            }
+        }
+    }
+
+    /// Runs one step of the security package, resolving its network requests through `yield_point`
+    async fn initialize_security_context(
+        &mut self,
+        yield_point: &mut CredsspYieldPoint,
+        input: Vec<u8>,
+    ) -> sspi::Result<(SecurityStatus, Vec<u8>)> {
+        let mut input_buffers = vec![SecurityBuffer::new(input, BufferType::Token)];
+        if let Some(channel_bindings) = &self.channel_bindings {
+            input_buffers.push(SecurityBuffer::new(
+                channel_bindings.clone(),
+                BufferType::ChannelBindings,
+            ));
+        }
+        let mut output_buffers = [SecurityBuffer::new(Vec::with_capacity(1024), BufferType::Token)];
+        let mut credentials_handle = self.credentials_handle.take();
+
+        let result = {
+            let mut builder = self
+                .context
+                .initialize_security_context()
+                .with_credentials_handle(&mut credentials_handle)
+                .with_context_requirements(self.context_requirements)
+                .with_target_data_representation(DataRepresentation::Native)
+                .with_target_name(&self.service_principal_name)
+                .with_input(&mut input_buffers)
+                .with_output(&mut output_buffers);
+
+            let mut generator = SspiImpl::initialize_security_context_impl(&mut self.context, &mut builder)?;
+            let mut state = generator.start();
+
+            loop {
+                match state {
+                    GeneratorState::Suspended(request) => {
+                        let response = yield_point.suspend(request).await;
+                        state = generator.resume(response);
+                    }
+                    GeneratorState::Completed(result) => break result,
+                }
+            }
+        };
+
+        self.credentials_handle = credentials_handle;
+        let result = result?;
+
+        let [output] = output_buffers;
+
+        Ok((result.status, output.buffer))
+    }
+
+    fn encrypt_public_key(&mut self, peer_version: u32) -> sspi::Result<Vec<u8>> {
+        if peer_version < 5 {
+            let public_key = self.public_key.clone();
+            self.encrypt_message(&public_key)
+        } else {
+            let hash = self.public_key_hash(CLIENT_SERVER_HASH_MAGIC);
+            self.encrypt_message(&hash)
+        }
+    }
+
+    fn verify_public_key(&mut self, pub_key_auth: &[u8], peer_version: u32) -> sspi::Result<()> {
+        let mut decrypted = self.decrypt_message(pub_key_auth)?;
+
+        let expected = if peer_version < 5 {
+            // The server echoes the public key incremented by one, as a little-endian integer.
+            decrement_le(&mut decrypted);
+            self.public_key.clone()
+        } else {
+            self.public_key_hash(SERVER_CLIENT_HASH_MAGIC)
+        };
+
+        if decrypted != expected {
+            return Err(Error::new(
+                ErrorKind::MessageAltered,
+                "could not verify the public key of the server",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn public_key_hash(&self, hash_magic: &[u8]) -> Vec<u8> {
+        let mut data = hash_magic.to_vec();
+        data.extend_from_slice(&self.client_nonce);
+        data.extend_from_slice(&self.public_key);
+
+        HashAlgorithm::SHA2_256.digest(&data)
+    }
+
+    fn encrypt_message(&mut self, input: &[u8]) -> sspi::Result<Vec<u8>> {
+        let mut token = [0; 1024];
+        let mut data = input.to_vec();
+
+        let mut buffers = [
+            SecurityBufferRef::token_buf(token.as_mut_slice()),
+            SecurityBufferRef::data_buf(data.as_mut_slice()),
+        ];
+
+        self.context
+            .encrypt_message(EncryptionFlags::empty(), &mut buffers, self.send_seq_num)?;
+
+        let mut output = SecurityBufferRef::find_buffer(&buffers, BufferType::Token)?
+            .data()
+            .to_vec();
+        output.extend_from_slice(SecurityBufferRef::find_buffer(&buffers, BufferType::Data)?.data());
+
+        self.send_seq_num += 1;
+
+        Ok(output)
+    }
+
+    fn decrypt_message(&mut self, input: &[u8]) -> sspi::Result<Vec<u8>> {
+        let mut input = input.to_vec();
+        let (signature, data) = input
+            .split_at_mut_checked(sspi::ntlm::SIGNATURE_SIZE)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidToken, "encrypted message is too short"))?;
+
+        let mut buffers = [
+            SecurityBufferRef::data_buf(data),
+            SecurityBufferRef::token_buf(signature),
+        ];
+
+        self.context.decrypt_message(&mut buffers, self.recv_seq_num)?;
+
+        let output = SecurityBufferRef::buf_data(&buffers, BufferType::Data)?.to_vec();
+
+        self.recv_seq_num += 1;
+
+        Ok(output)
+    }
+}
+
+fn decrement_le(buffer: &mut [u8]) {
+    for byte in buffer {
+        let (value, overflow) = byte.overflowing_sub(1);
+        *byte = value;
+        if !overflow {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sspi::credssp::{CredSspServer, CredentialsProxy, ServerMode, ServerState};
+    use sspi::{AuthIdentity, Username};
+
+    use super::*;
+
+    /// Starts with 0xFF bytes, so that the echo of the server carries over
+    const PUBLIC_KEY: &[u8] = &[0xff, 0xff, 0x30, 0x82, 0x01, 0x0a];
+
+    #[derive(Debug)]
+    struct CredentialsProxyImpl;
+
+    impl CredentialsProxy for CredentialsProxyImpl {
+        type AuthenticationData = AuthIdentity;
+
+        fn auth_data_by_user(&mut self, username: &Username) -> std::io::Result<Self::AuthenticationData> {
+            assert_eq!(username.account_name(), "user");
+            Ok(identity())
+        }
+    }
+
+    /// NTLM server, echoing the public key incremented by one for versions below 5
+    struct Server {
+        inner: CredSspServer<CredentialsProxyImpl>,
+        version: u32,
+    }
+
+    impl Server {
+        fn new(version: u32) -> Self {
+            let inner = CredSspServer::new_with_version(
+                PUBLIC_KEY.to_vec(),
+                CredentialsProxyImpl,
+                version,
+                ServerMode::Ntlm(sspi::ntlm::NtlmConfig::default()),
+            )
+            .unwrap();
+
+            Self { inner, version }
+        }
+
+        fn process(&mut self, mut ts_request: TsRequest) -> ServerState {
+            // The server of sspi follows the version of the client, which is lowered to emulate an older server.
+            ts_request.version = self.version;
+
+            self.inner
+                .process(ts_request)
+                .resolve_to_result()
+                .map_err(|e| e.error)
+                .unwrap()
+        }
+
+        fn reply(&mut self, ts_request: TsRequest) -> TsRequest {
+            match self.process(ts_request) {
+                ServerState::ReplyNeeded(reply) => reply,
+                ServerState::Finished(_) => panic!("unexpected end of the CredSSP sequence"),
+            }
+        }
+    }
+
+    fn identity() -> AuthIdentity {
+        AuthIdentity {
+            username: Username::new("user", Some("DOMAIN")).unwrap(),
+            password: String::from("password").into(),
+        }
+    }
+
+    fn client(channel_bindings: Option<Vec<u8>>) -> KerberosCredsspClient {
+        // NTLM only, to run the sequence without a KDC.
+        let negotiate_config = NegotiateConfig {
+            protocol_config: Box::<sspi::ntlm::NtlmConfig>::default(),
+            package_list: Some(String::from("!kerberos,!pku2u")),
+            client_computer_name: String::from("client"),
+        };
+
+        KerberosCredsspClient::new(
+            PUBLIC_KEY.to_vec(),
+            &identity().into(),
+            negotiate_config,
+            String::from("TERMSRV/server"),
+            true,
+            channel_bindings,
+        )
+        .unwrap()
+    }
+
+    /// Runs the negotiation, returning the server reply carrying its encrypted public key
+    fn negotiate(client: &mut KerberosCredsspClient, server: &mut Server) -> TsRequest {
+        let mut ts_request = TsRequest::default();
+
+        while client.state == State::NegoToken {
+            let ClientState::ReplyNeeded(request) = client.process(ts_request).resolve_to_result().unwrap() else {
+                panic!("unexpected final message during the negotiation");
+            };
+
+            ts_request = server.reply(request);
+        }
+
+        ts_request
+    }
+
+    fn authenticate(server_version: u32, channel_bindings: Option<Vec<u8>>) {
+        let mut client = client(channel_bindings);
+        let mut server = Server::new(server_version);
+
+        let reply = negotiate(&mut client, &mut server);
+        assert_eq!(reply.version, server_version);
+        assert!(reply.pub_key_auth.is_some());
+
+        let ClientState::FinalMessage(request) = client.process(reply).resolve_to_result().unwrap() else {
+            panic!("expected the final message");
+        };
+        assert_eq!(request.version, TS_REQUEST_VERSION);
+        assert!(request.auth_info.is_some());
+
+        let ServerState::Finished(delegated) = server.process(request) else {
+            panic!("expected the end of the CredSSP sequence");
+        };
+        assert_eq!(delegated.username.account_name(), "user");
+        assert_eq!(delegated.password.as_ref(), "password");
+    }
+
+    #[test]
+    fn sequence_with_public_key_hash() {
+        authenticate(TS_REQUEST_VERSION, None);
+    }
+
+    #[test]
+    fn sequence_with_public_key_echo() {
+        authenticate(4, None);
+    }
+
+    #[test]
+    fn sequence_with_channel_bindings() {
+        let channel_bindings = super::super::ChannelBindings {
+            application_data: b"tls-server-end-point:0123456789abcdef".to_vec(),
+        }
+        .to_sec_channel_bindings()
+        .unwrap();
+
+        authenticate(TS_REQUEST_VERSION, Some(channel_bindings));
+    }
+
+    #[test]
+    fn altered_server_public_key_is_rejected() {
+        let mut client = client(None);
+        let mut server = Server::new(TS_REQUEST_VERSION);
+
+        let mut reply = negotiate(&mut client, &mut server);
+        *reply.pub_key_auth.as_mut().unwrap().last_mut().unwrap() ^= 0xff;
+
+        assert!(client.process(reply).resolve_to_result().is_err());
+    }
+
+    #[test]
+    fn peer_version_change_is_rejected() {
+        let mut client = client(None);
+        let mut server = Server::new(TS_REQUEST_VERSION);
+
+        let mut reply = negotiate(&mut client, &mut server);
+        reply.version = 5;
+
+        assert!(client.process(reply).resolve_to_result().is_err());
+    }
+
+    #[test]
+    fn decrement_le_borrows() {
+        let mut buffer = [0x00, 0x00, 0x01, 0x02];
+        decrement_le(&mut buffer);
+        assert_eq!(buffer, [0xff, 0xff, 0x00, 0x02]);
+
+        let mut buffer = [0x05, 0x00];
+        decrement_le(&mut buffer);
+        assert_eq!(buffer, [0x04, 0x00]);
+    }
+}
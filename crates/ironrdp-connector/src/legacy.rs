@@ -3,6 +3,7 @@ use std::borrow::Cow;
 use ironrdp_core::{decode, encode_vec, Decode, Encode, WriteBuf};
 use ironrdp_pdu::rdp;
 use ironrdp_pdu::rdp::headers::ServerDeactivateAll;
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
 use ironrdp_pdu::x224::X224;
 
 use crate::{general_err, reason_err, ConnectorError, ConnectorErrorExt as _, ConnectorResult};
@@ -165,6 +166,7 @@ pub fn decode_share_data(ctx: SendDataIndicationCtx<'_>) -> ConnectorResult<Shar
 pub enum IoChannelPdu {
     Data(ShareDataCtx),
     DeactivateAll(ServerDeactivateAll),
+    ServerRedirection(ServerRedirectionPdu),
 }
 
 pub fn decode_io_channel(ctx: SendDataIndicationCtx<'_>) -> ConnectorResult<IoChannelPdu> {
@@ -174,6 +176,9 @@ pub fn decode_io_channel(ctx: SendDataIndicationCtx<'_>) -> ConnectorResult<IoCh
         rdp::headers::ShareControlPdu::ServerDeactivateAll(deactivate_all) => {
             Ok(IoChannelPdu::DeactivateAll(deactivate_all))
         }
+        rdp::headers::ShareControlPdu::ServerRedirection(redirection) => {
+            Ok(IoChannelPdu::ServerRedirection(redirection.redirection))
+        }
         rdp::headers::ShareControlPdu::Data(share_data_header) => {
             let share_data_ctx = ShareDataCtx {
                 initiator_id: ctx.initiator_id,
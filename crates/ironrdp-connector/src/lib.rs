@@ -11,6 +11,7 @@ pub mod connection_activation;
 mod connection_finalization;
 pub mod credssp;
 mod license_exchange;
+pub mod redirection;
 mod server_name;
 
 use core::any::Any;
@@ -21,6 +22,7 @@ use ironrdp_core::{encode_buf, encode_vec, Encode, WriteBuf};
 use ironrdp_pdu::nego::NegoRequestData;
 use ironrdp_pdu::rdp::capability_sets::{self, BitmapCodecs};
 use ironrdp_pdu::rdp::client_info::{PerformanceFlags, TimezoneInfo};
+use ironrdp_pdu::rdp::session_info::ServerAutoReconnect;
 use ironrdp_pdu::x224::X224;
 use ironrdp_pdu::{gcc, x224, PduHint};
 pub use sspi;
@@ -96,6 +98,27 @@ pub struct BitmapConfig {
     pub codecs: BitmapCodecs,
 }
 
+/// A monitor of the client
+///
+/// The monitors are sent to the server in the client monitor data, with their orientation and scale factors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct MonitorConfig {
+    /// Position of the top-left corner in the virtual desktop, the primary monitor is at (0, 0)
+    pub left: i32,
+    pub top: i32,
+    pub width: u16,
+    pub height: u16,
+    pub is_primary: bool,
+    /// Physical size (width, height) in millimeters
+    pub physical_dimensions: Option<(u32, u32)>,
+    pub orientation: gcc::MonitorOrientation,
+    /// Desktop scale factor in percent, between 100 and 500
+    pub desktop_scale_factor: u32,
+    /// Device scale factor in percent: 100, 140 or 180
+    pub device_scale_factor: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct SmartCardIdentity {
     /// DER-encoded X509 certificate
@@ -110,6 +133,29 @@ pub struct SmartCardIdentity {
     pub private_key: Vec<u8>,
 }
 
+/// Credentials received in a Server Redirection PDU, authenticating the client with RDSTLS
+///
+/// The password is an opaque cookie generated by the redirecting server, it is sent back as is.
+#[derive(Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RedirectionCredentials {
+    pub redirection_guid: Vec<u8>,
+    pub username: String,
+    pub domain: Option<String>,
+    pub password: Vec<u8>,
+}
+
+impl fmt::Debug for RedirectionCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedirectionCredentials")
+            .field("redirection_guid", &self.redirection_guid)
+            .field("username", &self.username)
+            .field("domain", &self.domain)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Credentials {
     UsernamePassword {
@@ -147,6 +193,11 @@ pub struct Config {
     ///
     /// This becomes the `desktop_scale_factor` in the [`TS_UD_CS_CORE`](gcc::ClientCoreOptionalData) structure.
     pub desktop_scale_factor: u32,
+    /// The monitors of the client
+    ///
+    /// When empty, the client has a single monitor of `desktop_size`. Otherwise, the primary monitor overrides the
+    /// orientation and scale factors of the desktop.
+    pub monitors: Vec<MonitorConfig>,
     /// TLS + Graphical login (legacy)
     ///
     /// Also called SSL or TLS security protocol.
@@ -195,6 +246,17 @@ pub struct Config {
     pub enable_credssp: bool,
     pub credentials: Credentials,
     pub domain: Option<String>,
+    /// Credentials of a server redirection
+    ///
+    /// When set, the RDSTLS security protocol is also requested. If the server selects it, the client is
+    /// authenticated with these credentials instead of [`credentials`](Self::credentials).
+    pub redirection_credentials: Option<RedirectionCredentials>,
+    /// Auto-reconnect cookie received during a previous connection to the session
+    ///
+    /// When set, the Client Auto-Reconnect Packet is sent in the Client Info PDU, and the server reconnects the
+    /// client to its session without prompting for the credentials again. The cookie is sent by the server in a
+    /// Save Session Info PDU during the session.
+    pub auto_reconnect_cookie: Option<ServerAutoReconnect>,
     /// The build number of the client.
     pub client_build: u32,
     /// Name of the client computer
@@ -225,6 +287,11 @@ pub struct Config {
     pub autologon: bool,
     /// If true, the INFO_NOAUDIOPLAYBACK flag is set in the [`ClientInfoPdu`](ironrdp_pdu::rdp::ClientInfoPdu)
     pub enable_audio_playback: bool,
+    /// If true, the session runs remote applications instead of a full desktop
+    ///
+    /// The INFO_RAIL flag is set in the [`ClientInfoPdu`](ironrdp_pdu::rdp::ClientInfoPdu), and the RAIL
+    /// and window list capabilities are advertised. The applications are started on the RAIL static channel.
+    pub remote_app: bool,
     pub performance_flags: PerformanceFlags,
 
     pub license_cache: Option<Arc<dyn LicenseCache>>,
@@ -325,6 +392,8 @@ pub enum ConnectorErrorKind {
     General,
     Custom,
     Negotiation(NegotiationFailure),
+    /// The server redirected the client, see [`redirection`]
+    ServerRedirection(Box<ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu>),
 }
 
 impl fmt::Display for ConnectorErrorKind {
@@ -338,6 +407,7 @@ impl fmt::Display for ConnectorErrorKind {
             ConnectorErrorKind::General => write!(f, "general error"),
             ConnectorErrorKind::Custom => write!(f, "custom error"),
             ConnectorErrorKind::Negotiation(failure) => write!(f, "negotiation failure: {failure}"),
+            ConnectorErrorKind::ServerRedirection(_) => write!(f, "server redirection"),
         }
     }
 }
@@ -353,6 +423,7 @@ impl core::error::Error for ConnectorErrorKind {
             ConnectorErrorKind::Custom => None,
             ConnectorErrorKind::General => None,
             ConnectorErrorKind::Negotiation(failure) => Some(failure),
+            ConnectorErrorKind::ServerRedirection(_) => None,
         }
     }
 }
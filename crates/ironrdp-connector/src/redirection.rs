@@ -0,0 +1,111 @@
+//! Server redirection ([MS-RDPBCGR] 1.3.1.5)
+//!
+//! A load balancer or a connection broker may redirect the client to another server, either in place of the
+//! Server Demand Active PDU or during the session. The connector reports the redirection with a
+//! [`ConnectorErrorKind::ServerRedirection`](crate::ConnectorErrorKind::ServerRedirection) error; the client
+//! then applies it to its configuration with [`RedirectionPolicy::apply`] and connects again to the target.
+
+use ironrdp_pdu::nego::NegoRequestData;
+use ironrdp_pdu::rdp::server_redirection::{RedirectionFlags, ServerRedirectionPdu};
+use tracing::{debug, warn};
+
+use crate::{reason_err, Config, ConnectorResult, Credentials, RedirectionCredentials};
+
+/// Prefix of the routing tokens, as sent in the load balancing information
+const ROUTING_TOKEN_PREFIX: &[u8] = b"Cookie: msts=";
+
+/// Policy followed when a server redirects the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedirectionPolicy {
+    /// Maximum number of redirections to follow, protecting against redirection loops
+    pub max_redirections: usize,
+}
+
+impl Default for RedirectionPolicy {
+    fn default() -> Self {
+        Self { max_redirections: 3 }
+    }
+}
+
+/// Where to connect after a server redirection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectionTarget {
+    /// Host name or address of the new server, `None` to connect again to the same server
+    pub host: Option<String>,
+    /// Session to reconnect to
+    pub session_id: u32,
+}
+
+impl RedirectionPolicy {
+    /// Updates the configuration for the connection to the target of the redirection
+    ///
+    /// `redirection_count` is the number of redirections followed so far, including this one: an error is
+    /// returned when it exceeds [`max_redirections`](Self::max_redirections).
+    ///
+    /// The load balancing information becomes the routing token of the new connection, and the username,
+    /// domain and password cookie provided by the server are used to authenticate with RDSTLS.
+    pub fn apply(
+        &self,
+        redirection: &ServerRedirectionPdu,
+        redirection_count: usize,
+        config: &mut Config,
+    ) -> ConnectorResult<RedirectionTarget> {
+        if redirection_count > self.max_redirections {
+            return Err(reason_err!(
+                "Redirection",
+                "too many redirections ({redirection_count} > {})",
+                self.max_redirections
+            ));
+        }
+
+        debug!(?redirection, "Apply server redirection");
+
+        if let Some(load_balance_info) = &redirection.load_balance_info {
+            config.request_data = Some(NegoRequestData::routing_token(routing_token(load_balance_info)));
+        }
+
+        if let Some(domain) = &redirection.domain {
+            config.domain = Some(domain.clone());
+        }
+
+        if let Some(username) = &redirection.username {
+            match &mut config.credentials {
+                Credentials::UsernamePassword { username: current, .. } => current.clone_from(username),
+                _ => warn!("Ignoring the username of the redirection, the credentials are not a username/password"),
+            }
+        }
+
+        config.redirection_credentials = match &redirection.password {
+            Some(_) if redirection.flags.contains(RedirectionFlags::PASSWORD_IS_PK_ENCRYPTED) => {
+                warn!("Ignoring the password of the redirection, it is encrypted for the target server");
+                None
+            }
+            Some(password) => Some(RedirectionCredentials {
+                redirection_guid: redirection.redirection_guid.clone().unwrap_or_default(),
+                username: redirection
+                    .username
+                    .clone()
+                    .or_else(|| config.credentials.username().map(str::to_owned))
+                    .unwrap_or_default(),
+                domain: config.domain.clone(),
+                password: password.clone(),
+            }),
+            None => None,
+        };
+
+        Ok(RedirectionTarget {
+            host: redirection.target_host().map(str::to_owned),
+            session_id: redirection.session_id,
+        })
+    }
+}
+
+fn routing_token(load_balance_info: &[u8]) -> String {
+    // The routing token is framed again when sent in the X.224 Connection Request.
+    let token = load_balance_info
+        .strip_prefix(ROUTING_TOKEN_PREFIX)
+        .unwrap_or(load_balance_info);
+    let token = token.strip_suffix(b"\r\n").unwrap_or(token);
+
+    String::from_utf8_lossy(token).into_owned()
+}
@@ -1,5 +1,7 @@
 use core::fmt;
 
+use crate::{invalid_field_err, not_enough_bytes_err, DecodeResult, EncodeResult, InvalidFieldErr, NotEnoughBytesErr};
+
 /// Error indicating that there are not enough bytes in the buffer to perform an operation.
 #[derive(Copy, Eq, PartialEq, Clone, Debug)]
 pub struct NotEnoughBytesError {
@@ -148,6 +150,87 @@ impl<'a> ReadCursor<'a> {
         self.read_slice(self.len())
     }
 
+    /// Try to read an array of `N` bytes.
+    #[inline]
+    pub fn try_read_array<const N: usize>(&mut self) -> Result<[u8; N], NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: N);
+        Ok(self.read_array::<N>())
+    }
+
+    /// Try to read a slice of `n` bytes.
+    #[inline]
+    pub fn try_read_slice(&mut self, n: usize) -> Result<&'a [u8], NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: n);
+        Ok(self.read_slice(n))
+    }
+
+    /// Split off a cursor over the next `n` bytes.
+    ///
+    /// The current cursor is advanced past these bytes. This is handy to parse a length-prefixed
+    /// structure without reading past its end.
+    #[inline]
+    #[track_caller]
+    pub fn split_off(&mut self, n: usize) -> ReadCursor<'a> {
+        ReadCursor::new(self.read_slice(n))
+    }
+
+    /// Try to split off a cursor over the next `n` bytes.
+    ///
+    /// The current cursor is advanced past these bytes.
+    #[inline]
+    pub fn try_split_off(&mut self, n: usize) -> Result<ReadCursor<'a>, NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: n);
+        Ok(self.split_off(n))
+    }
+
+    /// Read a `u8` and convert it to `T`, e.g. an enum or a length.
+    ///
+    /// Fails if there are not enough bytes, or if the value is out of range for `T`. The cursor
+    /// is not advanced on failure.
+    #[inline]
+    pub fn try_read_u8_as<T: TryFrom<u8>>(&mut self, field: &'static str) -> DecodeResult<T> {
+        ensure_field_size("ReadCursor", self.len(), 1)?;
+        let value = T::try_from(self.peek_u8()).map_err(|_| out_of_range_err("ReadCursor", field))?;
+        self.advance(1);
+        Ok(value)
+    }
+
+    /// Read a little-endian `u16` and convert it to `T`, e.g. an enum or a length.
+    ///
+    /// Fails if there are not enough bytes, or if the value is out of range for `T`. The cursor
+    /// is not advanced on failure.
+    #[inline]
+    pub fn try_read_u16_as<T: TryFrom<u16>>(&mut self, field: &'static str) -> DecodeResult<T> {
+        ensure_field_size("ReadCursor", self.len(), 2)?;
+        let value = T::try_from(self.peek_u16()).map_err(|_| out_of_range_err("ReadCursor", field))?;
+        self.advance(2);
+        Ok(value)
+    }
+
+    /// Read a little-endian `u32` and convert it to `T`, e.g. a `usize` length.
+    ///
+    /// Fails if there are not enough bytes, or if the value is out of range for `T`. The cursor
+    /// is not advanced on failure.
+    #[inline]
+    pub fn try_read_u32_as<T: TryFrom<u32>>(&mut self, field: &'static str) -> DecodeResult<T> {
+        ensure_field_size("ReadCursor", self.len(), 4)?;
+        let value = T::try_from(self.peek_u32()).map_err(|_| out_of_range_err("ReadCursor", field))?;
+        self.advance(4);
+        Ok(value)
+    }
+
+    /// Read a little-endian `u64` and convert it to `T`, e.g. a `usize` length.
+    ///
+    /// Fails if there are not enough bytes, or if the value is out of range for `T`. The cursor
+    /// is not advanced on failure.
+    #[inline]
+    pub fn try_read_u64_as<T: TryFrom<u64>>(&mut self, field: &'static str) -> DecodeResult<T> {
+        ensure_field_size("ReadCursor", self.len(), 8)?;
+        let value = T::try_from(self.peek_u64()).map_err(|_| out_of_range_err("ReadCursor", field))?;
+        self.advance(8);
+        Ok(value)
+    }
+
     /// Read a `u8`.
     #[inline]
     #[track_caller]
@@ -366,6 +449,20 @@ impl<'a> ReadCursor<'a> {
         &self.inner[self.pos..self.pos + n]
     }
 
+    /// Try to peek at the next `N` bytes without consuming them.
+    #[inline]
+    pub fn try_peek<const N: usize>(&mut self) -> Result<[u8; N], NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: N);
+        Ok(self.peek::<N>())
+    }
+
+    /// Try to peek at the next `n` bytes without consuming them.
+    #[inline]
+    pub fn try_peek_slice(&mut self, n: usize) -> Result<&'a [u8], NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: n);
+        Ok(self.peek_slice(n))
+    }
+
     /// Peek a `u8` without consuming it.
     #[inline]
     #[track_caller]
@@ -471,6 +568,14 @@ impl<'a> ReadCursor<'a> {
         self.pos += len;
     }
 
+    /// Try to advance the cursor by `len` bytes.
+    #[inline]
+    pub fn try_advance(&mut self, len: usize) -> Result<(), NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: len);
+        self.advance(len);
+        Ok(())
+    }
+
     /// Return a new cursor advanced by `len` bytes.
     #[inline]
     #[track_caller]
@@ -595,6 +700,52 @@ impl<'a> WriteCursor<'a> {
         self.pos += n;
     }
 
+    /// Try to write an array of bytes to the buffer.
+    #[inline]
+    pub fn try_write_array<const N: usize>(&mut self, array: [u8; N]) -> Result<(), NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: N);
+        self.write_array(array);
+        Ok(())
+    }
+
+    /// Try to write a slice of bytes to the buffer.
+    #[inline]
+    pub fn try_write_slice(&mut self, slice: &[u8]) -> Result<(), NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: slice.len());
+        self.write_slice(slice);
+        Ok(())
+    }
+
+    /// Convert `value` to a `u8` and write it to the buffer.
+    ///
+    /// Fails if there is not enough space, or if the value is out of range, e.g. a too large length.
+    #[inline]
+    pub fn try_write_u8_from<T: TryInto<u8>>(&mut self, field: &'static str, value: T) -> EncodeResult<()> {
+        ensure_field_size("WriteCursor", self.len(), 1)?;
+        self.write_u8(value.try_into().map_err(|_| out_of_range_err("WriteCursor", field))?);
+        Ok(())
+    }
+
+    /// Convert `value` to a `u16` and write it to the buffer in little-endian.
+    ///
+    /// Fails if there is not enough space, or if the value is out of range, e.g. a too large length.
+    #[inline]
+    pub fn try_write_u16_from<T: TryInto<u16>>(&mut self, field: &'static str, value: T) -> EncodeResult<()> {
+        ensure_field_size("WriteCursor", self.len(), 2)?;
+        self.write_u16(value.try_into().map_err(|_| out_of_range_err("WriteCursor", field))?);
+        Ok(())
+    }
+
+    /// Convert `value` to a `u32` and write it to the buffer in little-endian.
+    ///
+    /// Fails if there is not enough space, or if the value is out of range, e.g. a too large length.
+    #[inline]
+    pub fn try_write_u32_from<T: TryInto<u32>>(&mut self, field: &'static str, value: T) -> EncodeResult<()> {
+        ensure_field_size("WriteCursor", self.len(), 4)?;
+        self.write_u32(value.try_into().map_err(|_| out_of_range_err("WriteCursor", field))?);
+        Ok(())
+    }
+
     /// Write a byte to the buffer.
     #[inline]
     #[track_caller]
@@ -707,6 +858,14 @@ impl<'a> WriteCursor<'a> {
         self.pos += len;
     }
 
+    /// Try to advance the cursor by `len` bytes.
+    #[inline]
+    pub fn try_advance(&mut self, len: usize) -> Result<(), NotEnoughBytesError> {
+        ensure_enough_bytes!(in: self, size: len);
+        self.advance(len);
+        Ok(())
+    }
+
     /// Returns a new cursor advanced by `len` bytes.
     #[inline]
     #[track_caller]
@@ -737,6 +896,17 @@ impl<'a> WriteCursor<'a> {
     }
 }
 
+fn ensure_field_size<E: NotEnoughBytesErr>(ctx: &'static str, received: usize, expected: usize) -> Result<(), E> {
+    if received < expected {
+        return Err(not_enough_bytes_err(ctx, received, expected));
+    }
+    Ok(())
+}
+
+fn out_of_range_err<E: InvalidFieldErr>(ctx: &'static str, field: &'static str) -> E {
+    invalid_field_err(ctx, field, "out of range integral type conversion")
+}
+
 #[cfg(feature = "std")]
 impl std::io::Write for WriteCursor<'_> {
     #[inline]
@@ -57,8 +57,21 @@ impl DisplayControlClient {
         physical_dims: Option<(u32, u32)>,
     ) -> EncodeResult<Vec<SvcMessage>> {
         // TODO: prevent resolution with values greater than max monitor area received in caps.
-        let pdu: DisplayControlPdu =
-            DisplayControlMonitorLayout::new_single_primary_monitor(width, height, scale_factor, physical_dims)?.into();
+        let layout =
+            DisplayControlMonitorLayout::new_single_primary_monitor(width, height, scale_factor, physical_dims)?;
+        self.encode_monitor_layout(channel_id, layout)
+    }
+
+    /// Wraps a [`DisplayControlPdu::MonitorLayout`] as an [`SvcMessage`]
+    ///
+    /// Unlike [`Self::encode_single_primary_monitor`], each monitor of the layout carries its own
+    /// position, orientation and scale factors, built with [`crate::pdu::MonitorLayoutEntry`].
+    pub fn encode_monitor_layout(
+        &self,
+        channel_id: u32,
+        layout: DisplayControlMonitorLayout,
+    ) -> EncodeResult<Vec<SvcMessage>> {
+        let pdu = DisplayControlPdu::from(layout);
         debug!(?pdu, "Sending monitor layout");
         encode_dvc_messages(channel_id, vec![Box::new(pdu)], ChannelFlags::empty())
     }
@@ -157,11 +157,27 @@ impl DisplayControlCapabilities {
         })
     }
 
+    pub fn max_num_monitors(&self) -> u32 {
+        self.max_num_monitors
+    }
+
     pub fn max_monitor_area(&self) -> u64 {
         self.max_monitor_area
     }
 }
 
+impl Default for DisplayControlCapabilities {
+    /// A single monitor of up to 3840x2400 pixels.
+    fn default() -> Self {
+        Self {
+            max_num_monitors: 1,
+            max_monitor_area_factor_a: 3840,
+            max_monitor_area_factor_b: 2400,
+            max_monitor_area: 3840 * 2400,
+        }
+    }
+}
+
 impl Encode for DisplayControlCapabilities {
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_fixed_part_size!(in: dst);
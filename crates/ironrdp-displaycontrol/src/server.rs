@@ -1,26 +1,247 @@
 use ironrdp_core::{decode, impl_as_any};
 use ironrdp_dvc::{DvcMessage, DvcProcessor, DvcServerProcessor};
 use ironrdp_pdu::{decode_err, PduResult};
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::pdu::{DisplayControlCapabilities, DisplayControlMonitorLayout, DisplayControlPdu};
+use crate::pdu::{
+    DeviceScaleFactor, DisplayControlCapabilities, DisplayControlMonitorLayout, DisplayControlPdu, MonitorLayoutEntry,
+    MonitorOrientation,
+};
 use crate::CHANNEL_NAME;
 
 pub trait DisplayControlHandler: Send {
-    fn monitor_layout(&self, layout: DisplayControlMonitorLayout) {
+    /// Called when the client requests a new monitor layout
+    ///
+    /// The layout has been validated against the server capabilities, invalid layouts are dropped.
+    fn monitor_layout(&self, layout: MonitorLayout) {
         debug!(?layout);
     }
 }
 
+/// A monitor of a validated [`MonitorLayout`]
+///
+/// The optional values the client sent outside the ranges of [MS-RDPEDISP] are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    pub is_primary: bool,
+    /// Position of the top-left corner in the virtual desktop, the primary monitor is at (0, 0)
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Landscape when the client sent an invalid orientation
+    pub orientation: MonitorOrientation,
+    /// Physical size (width, height) in millimeters
+    pub physical_dimensions: Option<(u32, u32)>,
+    pub scale_factor: Option<ScaleFactor>,
+}
+
+/// Scale factors of a monitor, only present when both of them are valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleFactor {
+    /// Desktop scale factor in percent, between 100 and 500
+    pub desktop: u32,
+    pub device: DeviceScaleFactor,
+}
+
+impl Monitor {
+    fn from_entry(entry: &MonitorLayoutEntry) -> Self {
+        let (left, top) = entry.position().unwrap_or((0, 0));
+        let (width, height) = entry.dimensions();
+        let scale_factor = entry
+            .desktop_scale_factor()
+            .zip(entry.device_scale_factor())
+            .map(|(desktop, device)| ScaleFactor { desktop, device });
+
+        Self {
+            is_primary: entry.is_primary(),
+            left,
+            top,
+            width,
+            height,
+            orientation: entry.orientation().unwrap_or(MonitorOrientation::Landscape),
+            physical_dimensions: entry.physical_dimensions(),
+            scale_factor,
+        }
+    }
+
+    /// Returns the size (width, height) of the monitor in landscape orientation
+    ///
+    /// The width and height of the monitor are the ones of the rotated display, they are swapped for the monitors
+    /// in portrait orientation.
+    pub fn landscape_dimensions(&self) -> (u32, u32) {
+        match self.orientation {
+            MonitorOrientation::Landscape | MonitorOrientation::LandscapeFlipped => (self.width, self.height),
+            MonitorOrientation::Portrait | MonitorOrientation::PortraitFlipped => (self.height, self.width),
+        }
+    }
+
+    fn right(&self) -> i64 {
+        i64::from(self.left).saturating_add(i64::from(self.width))
+    }
+
+    fn bottom(&self) -> i64 {
+        i64::from(self.top).saturating_add(i64::from(self.height))
+    }
+
+    fn overlaps(&self, other: &Monitor) -> bool {
+        i64::from(self.left) < other.right()
+            && i64::from(other.left) < self.right()
+            && i64::from(self.top) < other.bottom()
+            && i64::from(other.top) < self.bottom()
+    }
+}
+
+/// Monitor layout requested by the client, validated against the server capabilities
+///
+/// A valid layout has exactly one primary monitor, positioned at (0, 0), and monitors that don't overlap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorLayout {
+    primary: Monitor,
+    secondary: Vec<Monitor>,
+}
+
+impl MonitorLayout {
+    pub fn validate(
+        layout: &DisplayControlMonitorLayout,
+        capabilities: &DisplayControlCapabilities,
+    ) -> Result<Self, MonitorLayoutError> {
+        let entries = layout.monitors();
+
+        if u32::try_from(entries.len())
+            .ok()
+            .is_none_or(|count| count > capabilities.max_num_monitors())
+        {
+            return Err(MonitorLayoutError::TooManyMonitors {
+                count: entries.len(),
+                max: capabilities.max_num_monitors(),
+            });
+        }
+
+        let area = entries.iter().fold(0u64, |area, entry| {
+            let (width, height) = entry.dimensions();
+            area.saturating_add(u64::from(width).saturating_mul(u64::from(height)))
+        });
+
+        if area > capabilities.max_monitor_area() {
+            return Err(MonitorLayoutError::AreaTooLarge {
+                area,
+                max: capabilities.max_monitor_area(),
+            });
+        }
+
+        let (primary, secondary): (Vec<Monitor>, Vec<Monitor>) = entries
+            .iter()
+            .map(Monitor::from_entry)
+            .partition(|monitor| monitor.is_primary);
+
+        let primary = match primary.as_slice() {
+            [primary] => primary.clone(),
+            _ => return Err(MonitorLayoutError::PrimaryMonitor),
+        };
+
+        if entries
+            .iter()
+            .any(|entry| entry.is_primary() && entry.position().is_none())
+        {
+            return Err(MonitorLayoutError::PrimaryPosition);
+        }
+
+        let layout = Self { primary, secondary };
+
+        let monitors: Vec<&Monitor> = layout.monitors().collect();
+        let mut rest = monitors.as_slice();
+        while let Some((monitor, others)) = rest.split_first() {
+            if others.iter().any(|other| monitor.overlaps(other)) {
+                return Err(MonitorLayoutError::OverlappingMonitors);
+            }
+            rest = others;
+        }
+
+        Ok(layout)
+    }
+
+    pub fn primary(&self) -> &Monitor {
+        &self.primary
+    }
+
+    /// Returns all the monitors, starting with the primary one
+    pub fn monitors(&self) -> impl Iterator<Item = &Monitor> {
+        core::iter::once(&self.primary).chain(&self.secondary)
+    }
+
+    /// Returns the size (width, height) of the rectangle bounding all the monitors
+    pub fn desktop_size(&self) -> (u32, u32) {
+        // The primary monitor is at (0, 0), hence the bounds always include the origin.
+        let (left, top, right, bottom) = self.monitors().fold((0, 0, 0, 0), |(left, top, right, bottom), m| {
+            (
+                i64::min(left, i64::from(m.left)),
+                i64::min(top, i64::from(m.top)),
+                i64::max(right, m.right()),
+                i64::max(bottom, m.bottom()),
+            )
+        });
+
+        (
+            u32::try_from(right.saturating_sub(left)).unwrap_or(u32::MAX),
+            u32::try_from(bottom.saturating_sub(top)).unwrap_or(u32::MAX),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorLayoutError {
+    TooManyMonitors { count: usize, max: u32 },
+    PrimaryMonitor,
+    PrimaryPosition,
+    AreaTooLarge { area: u64, max: u64 },
+    OverlappingMonitors,
+}
+
+impl core::fmt::Display for MonitorLayoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MonitorLayoutError::TooManyMonitors { count, max } => {
+                write!(f, "too many monitors: {count} (max: {max})")
+            }
+            MonitorLayoutError::PrimaryMonitor => write!(f, "there must be exactly one primary monitor"),
+            MonitorLayoutError::PrimaryPosition => write!(f, "the primary monitor must be at (0, 0)"),
+            MonitorLayoutError::AreaTooLarge { area, max } => {
+                write!(f, "total monitor area is too large: {area} (max: {max})")
+            }
+            MonitorLayoutError::OverlappingMonitors => write!(f, "monitors are overlapping"),
+        }
+    }
+}
+
+impl core::error::Error for MonitorLayoutError {}
+
 /// A server for the Display Control Virtual Channel.
 pub struct DisplayControlServer {
     handler: Box<dyn DisplayControlHandler>,
+    capabilities: DisplayControlCapabilities,
 }
 
 impl DisplayControlServer {
     /// Create a new DisplayControlServer.
+    ///
+    /// The server announces the [default capabilities](DisplayControlCapabilities::default).
     pub fn new(handler: Box<dyn DisplayControlHandler>) -> Self {
-        Self { handler }
+        Self {
+            handler,
+            capabilities: DisplayControlCapabilities::default(),
+        }
+    }
+
+    /// Sets the capabilities announced to the client, the monitor layouts are validated against them.
+    #[must_use]
+    pub fn with_capabilities(mut self, capabilities: DisplayControlCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn capabilities(&self) -> &DisplayControlCapabilities {
+        &self.capabilities
     }
 }
 
@@ -32,16 +253,17 @@ impl DvcProcessor for DisplayControlServer {
     }
 
     fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
-        let pdu: DisplayControlPdu = DisplayControlCapabilities::new(1, 3840, 2400)
-            .map_err(|e| decode_err!(e))?
-            .into();
+        let pdu = DisplayControlPdu::from(self.capabilities.clone());
 
         Ok(vec![Box::new(pdu)])
     }
 
     fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
         match decode(payload).map_err(|e| decode_err!(e))? {
-            DisplayControlPdu::MonitorLayout(layout) => self.handler.monitor_layout(layout),
+            DisplayControlPdu::MonitorLayout(layout) => match MonitorLayout::validate(&layout, &self.capabilities) {
+                Ok(layout) => self.handler.monitor_layout(layout),
+                Err(error) => warn!(%error, ?layout, "Ignoring invalid monitor layout"),
+            },
             DisplayControlPdu::Caps(caps) => {
                 debug!(?caps);
             }
@@ -1,22 +1,64 @@
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::any::TypeId;
 use core::fmt;
+use core::time::Duration;
 
 use ironrdp_core::{impl_as_any, Decode as _, DecodeResult, ReadCursor};
 use ironrdp_pdu::{self as pdu, decode_err, encode_err, pdu_other_err};
 use ironrdp_svc::{ChannelFlags, CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor};
 use pdu::gcc::ChannelName;
 use pdu::PduResult;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::pdu::{
-    CapabilitiesResponsePdu, CapsVersion, ClosePdu, CreateResponsePdu, CreationStatus, DrdynvcClientPdu,
-    DrdynvcServerPdu,
+    CapabilitiesRequestPdu, CapabilitiesResponsePdu, CapsVersion, ClosePdu, CreateResponsePdu, CreationStatus,
+    DataFirstPdu, DataPdu, DrdynvcClientPdu, DrdynvcDataPdu, DrdynvcServerPdu, SoftSyncRequestPdu, SoftSyncResponsePdu,
+    TunnelType,
+};
+use crate::{
+    encode_dvc_messages_with_max_data_size, ChannelStall, DvcProcessor, DynamicChannelSet, DynamicVirtualChannel,
+    ReassemblyLimits, StallPolicy,
 };
-use crate::{encode_dvc_messages, DvcProcessor, DynamicChannelSet, DynamicVirtualChannel};
 
 pub trait DvcClientProcessor: DvcProcessor {}
 
+/// Decompressor of the data compressed with the RDP 8.0 bulk compression, enabling the version 3 of the capabilities
+///
+/// A single decompressor, and thus a single history, is used for all the channels.
+pub trait DvcDecompressor: Send {
+    /// Appends to `output` the decompressed `RDP_SEGMENTED_DATA` of a compressed data PDU
+    fn decompress(&mut self, input: &[u8], output: &mut Vec<u8>) -> DecodeResult<()>;
+}
+
+impl<F> DvcDecompressor for F
+where
+    F: FnMut(&[u8], &mut Vec<u8>) -> DecodeResult<()> + Send,
+{
+    fn decompress(&mut self, input: &[u8], output: &mut Vec<u8>) -> DecodeResult<()> {
+        self(input, output)
+    }
+}
+
+/// Handler of the channels created by the server without a registered processor
+///
+/// The server may create channels the client does not know about beforehand, e.g. the ones of an application
+/// running in the remote session. The handler is asked for a processor when such a channel is created, and the
+/// channel is refused when it returns `None`.
+pub trait DvcFallbackHandler: Send {
+    /// Returns the processor of the channel named `channel_name`, or `None` to refuse it
+    fn create_processor(&mut self, channel_name: &str) -> Option<Box<dyn DvcProcessor>>;
+}
+
+impl<F> DvcFallbackHandler for F
+where
+    F: FnMut(&str) -> Option<Box<dyn DvcProcessor>> + Send,
+{
+    fn create_processor(&mut self, channel_name: &str) -> Option<Box<dyn DvcProcessor>> {
+        self(channel_name)
+    }
+}
+
 /// DRDYNVC Static Virtual Channel (the Remote Desktop Protocol: Dynamic Virtual Channel Extension)
 ///
 /// It adds support for dynamic virtual channels (DVC).
@@ -24,6 +66,17 @@ pub struct DrdynvcClient {
     dynamic_channels: DynamicChannelSet,
     /// Indicates whether the capability request/response handshake has been completed.
     cap_handshake_done: bool,
+    /// Negotiated version of the capabilities
+    version: CapsVersion,
+    priority_charges: Option<[u16; CapabilitiesRequestPdu::PRIORITY_CHARGE_COUNT]>,
+    decompressor: Option<Box<dyn DvcDecompressor>>,
+    fallback_handler: Option<Box<dyn DvcFallbackHandler>>,
+    /// Tunnels the channels are switched to on soft-sync
+    soft_sync_tunnels: Vec<TunnelType>,
+    stall_policy: StallPolicy,
+    /// Maximum size of the data of the PDUs the messages are split into
+    max_data_size: usize,
+    reassembly_limits: ReassemblyLimits,
 }
 
 impl fmt::Debug for DrdynvcClient {
@@ -48,10 +101,69 @@ impl DrdynvcClient {
         Self {
             dynamic_channels: DynamicChannelSet::new(),
             cap_handshake_done: false,
+            version: CapsVersion::V1,
+            priority_charges: None,
+            decompressor: None,
+            fallback_handler: None,
+            soft_sync_tunnels: Vec::new(),
+            stall_policy: StallPolicy::default(),
+            max_data_size: DrdynvcDataPdu::MAX_DATA_SIZE,
+            reassembly_limits: ReassemblyLimits::default(),
         }
     }
 
-    // FIXME(#61): it’s likely we want to enable adding dynamic channels at any point during the session (message passing? other approach?)
+    /// Enables the compressed data PDUs, and thus the version 3 of the capabilities, the version 2 being negotiated
+    /// otherwise.
+    #[must_use]
+    pub fn with_decompressor(mut self, decompressor: impl DvcDecompressor + 'static) -> Self {
+        self.decompressor = Some(Box::new(decompressor));
+        self
+    }
+
+    /// Sets the tunnels of the UDP multitransport connections the channels are switched to on soft-sync.
+    ///
+    /// None by default, the channels then stay on the TCP connection.
+    #[must_use]
+    pub fn with_soft_sync_tunnels(mut self, tunnels: Vec<TunnelType>) -> Self {
+        self.soft_sync_tunnels = tunnels;
+        self
+    }
+
+    /// Sets the maximum size of the data of the PDUs the messages are split into
+    ///
+    /// See [`DrdynvcDataPdu::max_data_size_for_segment`].
+    #[must_use]
+    pub fn with_max_data_size(mut self, max_data_size: usize) -> Self {
+        self.max_data_size = max_data_size;
+        self
+    }
+
+    /// Sets the bounds of the reassembly of the messages received from the server
+    #[must_use]
+    pub fn with_reassembly_limits(mut self, limits: ReassemblyLimits) -> Self {
+        self.reassembly_limits = limits;
+        self
+    }
+
+    /// Version of the capabilities negotiated with the server
+    pub fn capabilities_version(&self) -> CapsVersion {
+        self.version
+    }
+
+    /// Charges of the priority classes sent by the server, from the version 2 of the capabilities
+    pub fn priority_charges(&self) -> Option<[u16; CapabilitiesRequestPdu::PRIORITY_CHARGE_COUNT]> {
+        self.priority_charges
+    }
+
+    #[must_use]
+    pub fn with_stall_policy(mut self, policy: StallPolicy) -> Self {
+        self.stall_policy = policy;
+        self
+    }
+
+    pub fn set_stall_policy(&mut self, policy: StallPolicy) {
+        self.stall_policy = policy;
+    }
 
     #[must_use]
     pub fn with_dynamic_channel<T>(mut self, channel: T) -> Self
@@ -62,6 +174,10 @@ impl DrdynvcClient {
         self
     }
 
+    /// Registers the processor of a channel, at any point during the session
+    ///
+    /// The processor handles the channel once the server creates it, or the next time it does when the channel is
+    /// already opened.
     pub fn attach_dynamic_channel<T>(&mut self, channel: T)
     where
         T: DvcProcessor + 'static,
@@ -69,6 +185,24 @@ impl DrdynvcClient {
         self.dynamic_channels.insert(channel);
     }
 
+    /// Registers a boxed processor of a channel, see [`DrdynvcClient::attach_dynamic_channel`]
+    pub fn attach_boxed_dynamic_channel(&mut self, channel: Box<dyn DvcProcessor>) {
+        self.dynamic_channels.insert_boxed(channel);
+    }
+
+    /// Sets the handler of the channels created by the server without a registered processor
+    ///
+    /// Such channels are refused by default.
+    #[must_use]
+    pub fn with_fallback_handler(mut self, handler: impl DvcFallbackHandler + 'static) -> Self {
+        self.fallback_handler = Some(Box::new(handler));
+        self
+    }
+
+    pub fn set_fallback_handler(&mut self, handler: impl DvcFallbackHandler + 'static) {
+        self.fallback_handler = Some(Box::new(handler));
+    }
+
     pub fn get_dvc_by_type_id<T>(&self) -> Option<&DynamicVirtualChannel>
     where
         T: DvcProcessor,
@@ -80,12 +214,120 @@ impl DrdynvcClient {
         self.dynamic_channels.get_by_channel_id(channel_id)
     }
 
-    fn create_capabilities_response(&mut self) -> SvcMessage {
-        let caps_response = DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(CapsVersion::V1));
+    /// Detects the channels whose peer stopped responding, and sends the keepalives.
+    ///
+    /// Must be called periodically, `elapsed` being the time since the previous call. A channel is stalled when its
+    /// processor awaited a response (see [`DvcProcessor::awaiting_response`]) without receiving anything from the peer
+    /// for [`StallPolicy::timeout`]. The processor is then asked to recover with [`DvcProcessor::on_stall`], and reset
+    /// once [`StallPolicy::max_attempts`] is exceeded.
+    ///
+    /// Returns the messages to send along with the detected stalls.
+    pub fn poll_timeouts(&mut self, elapsed: Duration) -> PduResult<(Vec<SvcMessage>, Vec<ChannelStall>)> {
+        let mut responses = Vec::new();
+        let mut stalls = Vec::new();
+
+        for channel in self.dynamic_channels.opened_mut() {
+            let (messages, stall) = channel.poll_timeouts(elapsed, &self.stall_policy)?;
+
+            if let Some(channel_id) = channel.channel_id() {
+                responses.extend(
+                    encode_dvc_messages_with_max_data_size(
+                        channel_id,
+                        messages,
+                        ChannelFlags::empty(),
+                        self.max_data_size,
+                    )
+                    .map_err(|e| encode_err!(e))?,
+                );
+            }
+            stalls.extend(stall);
+        }
+
+        Ok((responses, stalls))
+    }
+
+    fn create_capabilities_response(&mut self, caps_request: Option<&CapabilitiesRequestPdu>) -> SvcMessage {
+        let supported = if self.decompressor.is_some() {
+            CapsVersion::V3
+        } else {
+            CapsVersion::V2
+        };
+        self.version = caps_request.map_or(CapsVersion::V1, |caps_request| caps_request.version().min(supported));
+        self.priority_charges = caps_request.and_then(CapabilitiesRequestPdu::priority_charges);
+
+        let caps_response = DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(self.version));
         debug!("Send DVC Capabilities Response PDU: {caps_response:?}");
         self.cap_handshake_done = true;
         SvcMessage::from(caps_response)
     }
+
+    fn create_soft_sync_response(&self, request: &SoftSyncRequestPdu) -> SvcMessage {
+        let mut tunnels = Vec::new();
+        for channel_list in request.channel_lists() {
+            if self.soft_sync_tunnels.contains(&channel_list.tunnel) && !tunnels.contains(&channel_list.tunnel) {
+                tunnels.push(channel_list.tunnel);
+            }
+        }
+
+        let response = DrdynvcClientPdu::SoftSyncResponse(SoftSyncResponsePdu::new(tunnels));
+        debug!("Send DVC Soft-Sync Response PDU: {response:?}");
+        SvcMessage::from(response)
+    }
+
+    /// Registers the processor returned by the fallback handler for a channel created by the server
+    fn create_fallback_processor(&mut self, channel_name: &str) {
+        let Some(processor) = self
+            .fallback_handler
+            .as_mut()
+            .and_then(|handler| handler.create_processor(channel_name))
+        else {
+            return;
+        };
+
+        if processor.channel_name() == channel_name {
+            debug!(channel_name, "Registering the fallback processor of the DVC");
+            self.dynamic_channels.insert_boxed(processor);
+        } else {
+            warn!(
+                channel_name,
+                processor_channel_name = processor.channel_name(),
+                "Fallback DVC processor for another channel"
+            );
+        }
+    }
+
+    /// Decompresses the data of the compressed data PDUs
+    fn decompress(&mut self, pdu: DrdynvcDataPdu) -> PduResult<DrdynvcDataPdu> {
+        if !pdu.is_compressed() {
+            return Ok(pdu);
+        }
+
+        let decompressor = self
+            .decompressor
+            .as_mut()
+            .filter(|_| self.version >= CapsVersion::V3)
+            .ok_or_else(|| pdu_other_err!("compressed DVC data while compression is not negotiated"))?;
+
+        let mut data = Vec::new();
+        match pdu {
+            DrdynvcDataPdu::DataFirst(pdu) => {
+                decompressor
+                    .decompress(pdu.data(), &mut data)
+                    .map_err(|e| decode_err!(e))?;
+                Ok(DrdynvcDataPdu::DataFirst(DataFirstPdu::new(
+                    pdu.channel_id(),
+                    pdu.length(),
+                    data,
+                )))
+            }
+            DrdynvcDataPdu::Data(pdu) => {
+                decompressor
+                    .decompress(pdu.data(), &mut data)
+                    .map_err(|e| decode_err!(e))?;
+                Ok(DrdynvcDataPdu::Data(DataPdu::new(pdu.channel_id(), data)))
+            }
+        }
+    }
 }
 
 impl_as_any!(DrdynvcClient);
@@ -112,11 +354,12 @@ impl SvcProcessor for DrdynvcClient {
         match pdu {
             DrdynvcServerPdu::Capabilities(caps_request) => {
                 debug!("Got DVC Capabilities Request PDU: {caps_request:?}");
-                responses.push(self.create_capabilities_response());
+                responses.push(self.create_capabilities_response(Some(&caps_request)));
             }
             DrdynvcServerPdu::Create(create_request) => {
                 debug!("Got DVC Create Request PDU: {create_request:?}");
                 let channel_id = create_request.channel_id();
+                let priority = create_request.priority();
                 let channel_name = create_request.into_channel_name();
 
                 if !self.cap_handshake_done {
@@ -124,7 +367,11 @@ impl SvcProcessor for DrdynvcClient {
                         "Got DVC Create Request PDU before a Capabilities Request PDU. \
                         Sending Capabilities Response PDU before the Create Response PDU."
                     );
-                    responses.push(self.create_capabilities_response());
+                    responses.push(self.create_capabilities_response(None));
+                }
+
+                if self.dynamic_channels.get_by_channel_name(&channel_name).is_none() {
+                    self.create_fallback_processor(&channel_name);
                 }
 
                 let channel_exists = self.dynamic_channels.get_by_channel_name(&channel_name).is_some();
@@ -137,6 +384,9 @@ impl SvcProcessor for DrdynvcClient {
                         .dynamic_channels
                         .get_by_channel_name_mut(&channel_name)
                         .expect("channel exists");
+                    if self.version >= CapsVersion::V2 {
+                        dynamic_channel.priority = priority;
+                    }
                     (CreationStatus::OK, dynamic_channel.start()?)
                 } else {
                     (CreationStatus::NO_LISTENER, Vec::new())
@@ -149,13 +399,21 @@ impl SvcProcessor for DrdynvcClient {
                 // If this DVC has start messages, send them.
                 if !start_messages.is_empty() {
                     responses.extend(
-                        encode_dvc_messages(channel_id, start_messages, ChannelFlags::empty())
-                            .map_err(|e| encode_err!(e))?,
+                        encode_dvc_messages_with_max_data_size(
+                            channel_id,
+                            start_messages,
+                            ChannelFlags::empty(),
+                            self.max_data_size,
+                        )
+                        .map_err(|e| encode_err!(e))?,
                     );
                 }
             }
             DrdynvcServerPdu::Close(close_request) => {
                 debug!("Got DVC Close Request PDU: {close_request:?}");
+                if let Some(channel) = self.dynamic_channels.get_by_channel_id_mut(close_request.channel_id()) {
+                    channel.channel_processor.close(close_request.channel_id());
+                }
                 self.dynamic_channels.remove_by_channel_id(close_request.channel_id());
 
                 let close_response = DrdynvcClientPdu::Close(ClosePdu::new(close_request.channel_id()));
@@ -165,17 +423,33 @@ impl SvcProcessor for DrdynvcClient {
             }
             DrdynvcServerPdu::Data(data) => {
                 let channel_id = data.channel_id();
+                let data = self.decompress(data)?;
+                let pending_elsewhere = self.dynamic_channels.pending_size_except(channel_id);
 
-                let messages = self
+                let channel = self
                     .dynamic_channels
                     .get_by_channel_id_mut(channel_id)
-                    .ok_or_else(|| pdu_other_err!("access to non existing DVC channel"))?
-                    .process(data)?;
+                    .ok_or_else(|| pdu_other_err!("access to non existing DVC channel"))?;
+
+                let messages = channel.process(data, &self.reassembly_limits, pending_elsewhere)?;
+                if !messages.is_empty() {
+                    channel.watchdog.on_sent();
+                }
 
                 responses.extend(
-                    encode_dvc_messages(channel_id, messages, ChannelFlags::empty()).map_err(|e| encode_err!(e))?,
+                    encode_dvc_messages_with_max_data_size(
+                        channel_id,
+                        messages,
+                        ChannelFlags::empty(),
+                        self.max_data_size,
+                    )
+                    .map_err(|e| encode_err!(e))?,
                 );
             }
+            DrdynvcServerPdu::SoftSyncRequest(soft_sync_request) => {
+                debug!("Got DVC Soft-Sync Request PDU: {soft_sync_request:?}");
+                responses.push(self.create_soft_sync_response(&soft_sync_request));
+            }
         }
 
         Ok(responses)
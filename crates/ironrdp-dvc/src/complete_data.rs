@@ -10,6 +10,8 @@ use crate::pdu::{DataFirstPdu, DataPdu, DrdynvcDataPdu};
 pub(crate) struct CompleteData {
     total_size: usize,
     data: Vec<u8>,
+    /// Remaining size of a dropped message, whose fragments are skipped
+    dropped: usize,
 }
 
 impl CompleteData {
@@ -17,9 +19,27 @@ impl CompleteData {
         Self {
             total_size: 0,
             data: Vec::new(),
+            dropped: 0,
         }
     }
 
+    /// Size of the message being reassembled, 0 if none
+    pub(crate) fn pending_size(&self) -> usize {
+        self.total_size
+    }
+
+    /// Drops the message of the PDU, skipping its remaining fragments
+    pub(crate) fn drop_message(&mut self, pdu: &DrdynvcDataPdu) {
+        self.total_size = 0;
+        self.data = Vec::new();
+        self.dropped = match pdu {
+            DrdynvcDataPdu::DataFirst(data_first) => usize::try_from(data_first.length())
+                .unwrap_or(usize::MAX)
+                .saturating_sub(data_first.data().len()),
+            DrdynvcDataPdu::Data(_) => 0,
+        };
+    }
+
     pub(crate) fn process_data(&mut self, pdu: DrdynvcDataPdu) -> DecodeResult<Option<Vec<u8>>> {
         match pdu {
             DrdynvcDataPdu::DataFirst(data_first) => self.process_data_first_pdu(data_first),
@@ -30,10 +50,11 @@ impl CompleteData {
     fn process_data_first_pdu(&mut self, data_first: DataFirstPdu) -> DecodeResult<Option<Vec<u8>>> {
         let total_data_size: DecodeResult<_> = cast_length!("DataFirstPdu::length", data_first.length());
         let total_data_size = total_data_size?;
-        if self.total_size != 0 || !self.data.is_empty() {
+        if self.total_size != 0 || !self.data.is_empty() || self.dropped != 0 {
             error!("Incomplete DVC message, it will be skipped");
 
             self.data.clear();
+            self.dropped = 0;
         }
 
         if total_data_size == data_first.data().len() {
@@ -47,6 +68,12 @@ impl CompleteData {
     }
 
     fn process_data_pdu(&mut self, mut data: DataPdu) -> DecodeResult<Option<Vec<u8>>> {
+        if self.dropped != 0 {
+            // fragment of a dropped message
+            self.dropped = self.dropped.saturating_sub(data.data().len());
+            return Ok(None);
+        }
+
         if self.total_size == 0 && self.data.is_empty() {
             // message is not fragmented
             return Ok(Some(data.into_data()));
@@ -9,8 +9,9 @@ use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::any::TypeId;
+use core::time::Duration;
 
-use pdu::DrdynvcDataPdu;
+use pdu::{ChannelPriority, DrdynvcDataPdu};
 
 use crate::alloc::borrow::ToOwned as _;
 // Re-export ironrdp_pdu crate for convenience
@@ -19,6 +20,7 @@ pub use ironrdp_pdu;
 use ironrdp_core::{assert_obj_safe, cast_length, encode_vec, other_err, AsAny, Encode, EncodeResult};
 use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
 use ironrdp_svc::SvcMessage;
+use tracing::warn;
 
 mod complete_data;
 use complete_data::CompleteData;
@@ -29,7 +31,21 @@ pub use client::*;
 mod server;
 pub use server::*;
 
+mod priority;
+pub use priority::*;
+
+mod stall;
+pub use stall::*;
+
+mod quota;
+pub use quota::*;
+
 pub mod pdu;
+pub mod protocol;
+
+// Private! Used by the macros.
+#[doc(hidden)]
+pub use ironrdp_core;
 
 /// Represents a message that, when encoded, forms a complete PDU for a given dynamic virtual channel.
 /// This means a message that is ready to be wrapped in [`pdu::DataFirstPdu`] and [`pdu::DataPdu`] PDUs
@@ -54,6 +70,41 @@ pub trait DvcProcessor: AsAny + Send {
     fn process(&mut self, channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>>;
 
     fn close(&mut self, _channel_id: u32) {}
+
+    /// Priority class of the channel
+    ///
+    /// The server assigns it when creating the channel, from the version 2 of the capabilities. The higher priority
+    /// channels preempt the lower priority ones, see [`DvcSendQueue`].
+    fn priority(&self) -> ChannelPriority {
+        ChannelPriority::default()
+    }
+
+    /// Whether a response of the peer is awaited, e.g. after sending a request
+    ///
+    /// Channels awaiting a response for too long are recovered by [`DrdynvcClient::poll_timeouts`].
+    fn awaiting_response(&self) -> bool {
+        false
+    }
+
+    /// The peer did not respond in time, `attempt` starting at 1
+    ///
+    /// By default, the channel is reset.
+    fn on_stall(&mut self, _channel_id: u32, _attempt: u32) -> PduResult<StallRecovery> {
+        Ok(StallRecovery::Reset)
+    }
+
+    /// Returns the messages keeping the channel alive once it stayed idle for
+    /// [`StallPolicy::keepalive_interval`]
+    fn keepalive(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    /// A message of the peer exceeds the [`ReassemblyLimits`], it is dropped without being buffered
+    ///
+    /// By default, the channel keeps going without the message.
+    fn on_quota_exceeded(&mut self, _channel_id: u32, _violation: QuotaViolation) -> QuotaAction {
+        QuotaAction::Drop
+    }
 }
 
 assert_obj_safe!(DvcProcessor);
@@ -62,40 +113,60 @@ pub fn encode_dvc_messages(
     channel_id: u32,
     messages: Vec<DvcMessage>,
     flags: ironrdp_svc::ChannelFlags,
+) -> EncodeResult<Vec<SvcMessage>> {
+    encode_dvc_messages_with_max_data_size(channel_id, messages, flags, DrdynvcDataPdu::MAX_DATA_SIZE)
+}
+
+/// Same as [`encode_dvc_messages`], splitting the messages into data PDUs of at most `max_data_size` bytes
+///
+/// See [`DrdynvcDataPdu::max_data_size_for_segment`].
+pub fn encode_dvc_messages_with_max_data_size(
+    channel_id: u32,
+    messages: Vec<DvcMessage>,
+    flags: ironrdp_svc::ChannelFlags,
+    max_data_size: usize,
 ) -> EncodeResult<Vec<SvcMessage>> {
     let mut res = Vec::new();
     for msg in messages {
-        let total_length = msg.size();
-        let needs_splitting = total_length >= DrdynvcDataPdu::MAX_DATA_SIZE;
-
-        let msg = encode_vec(msg.as_ref())?;
-        let mut off = 0;
-
-        while off < total_length {
-            let first = off == 0;
-
-            #[expect(clippy::missing_panics_doc, reason = "unreachable panic (checked underflow)")]
-            let remaining_length = total_length.checked_sub(off).expect("never overflow");
-            let size = core::cmp::min(remaining_length, DrdynvcDataPdu::MAX_DATA_SIZE);
-            let end = off
-                .checked_add(size)
-                .ok_or_else(|| other_err!("encode_dvc_messages", "overflow occurred"))?;
-
-            let pdu = if needs_splitting && first {
-                DrdynvcDataPdu::DataFirst(pdu::DataFirstPdu::new(
-                    channel_id,
-                    cast_length!("total_length", total_length)?,
-                    msg[off..end].to_vec(),
-                ))
-            } else {
-                DrdynvcDataPdu::Data(pdu::DataPdu::new(channel_id, msg[off..end].to_vec()))
-            };
+        for pdu in split_dvc_message(channel_id, msg.as_ref(), max_data_size)? {
+            res.push(SvcMessage::from(pdu).with_flags(flags));
+        }
+    }
 
-            let svc = SvcMessage::from(pdu).with_flags(flags);
+    Ok(res)
+}
 
-            res.push(svc);
-            off = end;
-        }
+/// Splits the message into data PDUs of at most `max_data_size` bytes, and at most [`DrdynvcDataPdu::MAX_DATA_SIZE`]
+fn split_dvc_message(channel_id: u32, msg: &dyn DvcEncode, max_data_size: usize) -> EncodeResult<Vec<DrdynvcDataPdu>> {
+    let max_data_size = max_data_size.clamp(1, DrdynvcDataPdu::MAX_DATA_SIZE);
+    let total_length = msg.size();
+    let needs_splitting = total_length >= max_data_size;
+
+    let msg = encode_vec(msg)?;
+    let mut res = Vec::new();
+    let mut off = 0;
+
+    while off < total_length {
+        let first = off == 0;
+
+        let remaining_length = total_length.checked_sub(off).expect("never overflow");
+        let size = core::cmp::min(remaining_length, max_data_size);
+        let end = off
+            .checked_add(size)
+            .ok_or_else(|| other_err!("encode_dvc_messages", "overflow occurred"))?;
+
+        let pdu = if needs_splitting && first {
+            DrdynvcDataPdu::DataFirst(pdu::DataFirstPdu::new(
+                channel_id,
+                cast_length!("total_length", total_length)?,
+                msg[off..end].to_vec(),
+            ))
+        } else {
+            DrdynvcDataPdu::Data(pdu::DataPdu::new(channel_id, msg[off..end].to_vec()))
+        };
+
+        res.push(pdu);
+        off = end;
     }
 
     Ok(res)
@@ -108,14 +179,24 @@ pub struct DynamicVirtualChannel {
     ///
     /// This field is `None` until the server assigns a channel ID.
     channel_id: Option<DynamicChannelId>,
+    priority: ChannelPriority,
+    watchdog: ChannelWatchdog,
 }
 
 impl DynamicVirtualChannel {
     fn new<T: DvcProcessor + 'static>(handler: T) -> Self {
+        Self::from_boxed(Box::new(handler))
+    }
+
+    fn from_boxed(handler: Box<dyn DvcProcessor>) -> Self {
+        let priority = handler.priority();
+
         Self {
-            channel_processor: Box::new(handler),
+            channel_processor: handler,
             complete_data: CompleteData::new(),
             channel_id: None,
+            priority,
+            watchdog: ChannelWatchdog::default(),
         }
     }
 
@@ -131,16 +212,57 @@ impl DynamicVirtualChannel {
         self.channel_processor.as_any().downcast_ref()
     }
 
+    /// Priority class assigned by the server, or the one of the processor when the server doesn't support priorities
+    pub fn priority(&self) -> ChannelPriority {
+        self.priority
+    }
+
+    /// Number of times the peer stopped responding since the channel was created
+    pub fn stall_count(&self) -> u32 {
+        self.watchdog.stalls
+    }
+
     fn start(&mut self) -> PduResult<Vec<DvcMessage>> {
         if let Some(channel_id) = self.channel_id {
+            self.watchdog = ChannelWatchdog {
+                stalls: self.watchdog.stalls,
+                ..ChannelWatchdog::default()
+            };
             self.channel_processor.start(channel_id)
         } else {
             Err(pdu_other_err!("DynamicVirtualChannel::start", "channel ID not set"))
         }
     }
 
-    fn process(&mut self, pdu: DrdynvcDataPdu) -> PduResult<Vec<DvcMessage>> {
+    fn process(
+        &mut self,
+        pdu: DrdynvcDataPdu,
+        limits: &ReassemblyLimits,
+        pending_elsewhere: usize,
+    ) -> PduResult<Vec<DvcMessage>> {
         let channel_id = pdu.channel_id();
+        self.watchdog.on_received();
+
+        if let Some(violation) = limits.check(&pdu, pending_elsewhere) {
+            self.complete_data.drop_message(&pdu);
+            let action = self.channel_processor.on_quota_exceeded(channel_id, violation);
+            warn!(
+                channel_name = self.channel_name(),
+                channel_id,
+                ?violation,
+                ?action,
+                "Dynamic channel message exceeds the reassembly limits"
+            );
+
+            return match action {
+                QuotaAction::Drop => Ok(Vec::new()),
+                QuotaAction::Reset => {
+                    self.channel_processor.close(channel_id);
+                    self.start()
+                }
+            };
+        }
+
         let complete_data = self.complete_data.process_data(pdu).map_err(|e| decode_err!(e))?;
         if let Some(complete_data) = complete_data {
             self.channel_processor.process(channel_id, &complete_data)
@@ -152,6 +274,74 @@ impl DynamicVirtualChannel {
     fn channel_name(&self) -> &str {
         self.channel_processor.channel_name()
     }
+
+    /// Advances the timers of the channel, returning the messages to send along with the detected stall
+    fn poll_timeouts(
+        &mut self,
+        elapsed: Duration,
+        policy: &StallPolicy,
+    ) -> PduResult<(Vec<DvcMessage>, Option<ChannelStall>)> {
+        let Some(channel_id) = self.channel_id else {
+            return Ok((Vec::new(), None));
+        };
+
+        self.watchdog.idle = self.watchdog.idle.saturating_add(elapsed);
+
+        if !self.channel_processor.awaiting_response() {
+            self.watchdog.waited = Duration::ZERO;
+            self.watchdog.attempts = 0;
+
+            let messages = match policy.keepalive_interval {
+                Some(interval) if self.watchdog.idle >= interval => self.channel_processor.keepalive(channel_id)?,
+                _ => Vec::new(),
+            };
+            if !messages.is_empty() {
+                self.watchdog.on_sent();
+            }
+
+            return Ok((messages, None));
+        }
+
+        self.watchdog.waited = self.watchdog.waited.saturating_add(elapsed);
+        if self.watchdog.waited < policy.timeout {
+            return Ok((Vec::new(), None));
+        }
+
+        let waited = self.watchdog.waited;
+        let attempt = self.watchdog.attempts.saturating_add(1);
+        self.watchdog.stalls = self.watchdog.stalls.saturating_add(1);
+
+        let recovery = if attempt > policy.max_attempts {
+            StallRecovery::Reset
+        } else {
+            self.channel_processor.on_stall(channel_id, attempt)?
+        };
+
+        let (messages, action) = match recovery {
+            StallRecovery::Resend(messages) => {
+                self.watchdog.waited = Duration::ZERO;
+                self.watchdog.attempts = attempt;
+                (messages, StallAction::Resent)
+            }
+            StallRecovery::Reset => {
+                self.channel_processor.close(channel_id);
+                self.complete_data = CompleteData::new();
+                (self.start()?, StallAction::Reset)
+            }
+        };
+        self.watchdog.on_sent();
+
+        let stall = ChannelStall {
+            channel_name: self.channel_name().to_owned(),
+            channel_id,
+            waited,
+            attempt,
+            action,
+        };
+        warn!(?stall, "Dynamic channel peer stopped responding");
+
+        Ok((messages, Some(stall)))
+    }
 }
 
 struct DynamicChannelSet {
@@ -178,6 +368,12 @@ impl DynamicChannelSet {
         self.channels.insert(name, DynamicVirtualChannel::new(channel))
     }
 
+    fn insert_boxed(&mut self, channel: Box<dyn DvcProcessor>) -> Option<DynamicVirtualChannel> {
+        let name = channel.channel_name().to_owned();
+        self.type_id_to_name.insert((*channel).as_any().type_id(), name.clone());
+        self.channels.insert(name, DynamicVirtualChannel::from_boxed(channel))
+    }
+
     fn attach_channel_id(&mut self, name: DynamicChannelName, id: DynamicChannelId) -> Option<DynamicChannelId> {
         self.channel_id_to_name.insert(id, name.clone());
         self.name_to_channel_id.insert(name.clone(), id);
@@ -226,6 +422,25 @@ impl DynamicChannelSet {
     fn values(&self) -> impl Iterator<Item = &DynamicVirtualChannel> {
         self.channels.values()
     }
+
+    /// Size of the messages being reassembled on the channels other than `id`
+    fn pending_size_except(&self, id: DynamicChannelId) -> usize {
+        self.channels
+            .values()
+            .filter(|channel| channel.channel_id != Some(id))
+            .map(|channel| channel.complete_data.pending_size())
+            .fold(0, usize::saturating_add)
+    }
+
+    /// Channels currently opened by the server
+    fn opened_mut(&mut self) -> impl Iterator<Item = &mut DynamicVirtualChannel> {
+        let name_to_channel_id = &self.name_to_channel_id;
+
+        self.channels
+            .iter_mut()
+            .filter(|(name, _)| name_to_channel_id.contains_key(*name))
+            .map(|(_, channel)| channel)
+    }
 }
 
 pub type DynamicChannelName = String;
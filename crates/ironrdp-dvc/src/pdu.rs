@@ -23,12 +23,33 @@ impl DrdynvcDataPdu {
     /// Maximum size of the `data` field in `DrdynvcDataPdu`.
     pub const MAX_DATA_SIZE: usize = 1590;
 
+    /// Size of the headers wrapping the data of a `DrdynvcDataPdu` sent on the wire
+    const SEGMENT_OVERHEAD: usize = 4 /* TPKT */ + 3 /* X.224 */ + 8 /* MCS SendData */ + 8 /* CHANNEL_PDU_HEADER */
+        + 1 /* DVC header */ + 4 /* ChannelId */ + 4 /* Length */;
+
+    /// Maximum size of the `data` field for the whole PDU, headers included, to fit in `segment_size` bytes
+    ///
+    /// Useful to avoid the IP fragmentation on links with a small MTU, e.g. 1400 bytes for a VPN or UDP encapsulation.
+    pub fn max_data_size_for_segment(segment_size: usize) -> usize {
+        segment_size
+            .saturating_sub(Self::SEGMENT_OVERHEAD)
+            .clamp(1, Self::MAX_DATA_SIZE)
+    }
+
     pub fn channel_id(&self) -> DynamicChannelId {
         match self {
             DrdynvcDataPdu::DataFirst(pdu) => pdu.channel_id,
             DrdynvcDataPdu::Data(pdu) => pdu.channel_id,
         }
     }
+
+    /// Whether the data is compressed with the RDP 8.0 bulk compression
+    pub fn is_compressed(&self) -> bool {
+        match self {
+            DrdynvcDataPdu::DataFirst(pdu) => pdu.is_compressed(),
+            DrdynvcDataPdu::Data(pdu) => pdu.is_compressed(),
+        }
+    }
 }
 
 impl Encode for DrdynvcDataPdu {
@@ -41,8 +62,8 @@ impl Encode for DrdynvcDataPdu {
 
     fn name(&self) -> &'static str {
         match self {
-            DrdynvcDataPdu::DataFirst(_) => DataFirstPdu::name(),
-            DrdynvcDataPdu::Data(_) => DataPdu::name(),
+            DrdynvcDataPdu::DataFirst(pdu) => pdu.name(),
+            DrdynvcDataPdu::Data(pdu) => pdu.name(),
         }
     }
 
@@ -61,6 +82,7 @@ pub enum DrdynvcClientPdu {
     Create(CreateResponsePdu),
     Close(ClosePdu),
     Data(DrdynvcDataPdu),
+    SoftSyncResponse(SoftSyncResponsePdu),
 }
 
 impl Encode for DrdynvcClientPdu {
@@ -70,6 +92,7 @@ impl Encode for DrdynvcClientPdu {
             DrdynvcClientPdu::Create(pdu) => pdu.encode(dst),
             DrdynvcClientPdu::Data(pdu) => pdu.encode(dst),
             DrdynvcClientPdu::Close(pdu) => pdu.encode(dst),
+            DrdynvcClientPdu::SoftSyncResponse(pdu) => pdu.encode(dst),
         }
     }
 
@@ -79,6 +102,7 @@ impl Encode for DrdynvcClientPdu {
             DrdynvcClientPdu::Create(_) => CreateResponsePdu::name(),
             DrdynvcClientPdu::Data(pdu) => pdu.name(),
             DrdynvcClientPdu::Close(_) => ClosePdu::name(),
+            DrdynvcClientPdu::SoftSyncResponse(_) => SoftSyncResponsePdu::name(),
         }
     }
 
@@ -88,6 +112,7 @@ impl Encode for DrdynvcClientPdu {
             DrdynvcClientPdu::Create(pdu) => pdu.size(),
             DrdynvcClientPdu::Data(pdu) => pdu.size(),
             DrdynvcClientPdu::Close(pdu) => pdu.size(),
+            DrdynvcClientPdu::SoftSyncResponse(pdu) => pdu.size(),
         }
     }
 }
@@ -97,12 +122,13 @@ impl Decode<'_> for DrdynvcClientPdu {
         let header = Header::decode(src)?;
         match header.cmd {
             Cmd::Create => Ok(Self::Create(CreateResponsePdu::decode(header, src)?)),
-            Cmd::DataFirst => Ok(Self::Data(DrdynvcDataPdu::DataFirst(DataFirstPdu::decode(
-                header, src,
-            )?))),
-            Cmd::Data => Ok(Self::Data(DrdynvcDataPdu::Data(DataPdu::decode(header, src)?))),
+            Cmd::DataFirst | Cmd::DataFirstCompressed => Ok(Self::Data(DrdynvcDataPdu::DataFirst(
+                DataFirstPdu::decode(header, src)?,
+            ))),
+            Cmd::Data | Cmd::DataCompressed => Ok(Self::Data(DrdynvcDataPdu::Data(DataPdu::decode(header, src)?))),
             Cmd::Close => Ok(Self::Close(ClosePdu::decode(header, src)?)),
             Cmd::Capability => Ok(Self::Capabilities(CapabilitiesResponsePdu::decode(header, src)?)),
+            Cmd::SoftSyncResponse => Ok(Self::SoftSyncResponse(SoftSyncResponsePdu::decode(header, src)?)),
             _ => Err(unsupported_value_err!("Cmd", header.cmd.into())),
         }
     }
@@ -115,6 +141,7 @@ pub enum DrdynvcServerPdu {
     Create(CreateRequestPdu),
     Close(ClosePdu),
     Data(DrdynvcDataPdu),
+    SoftSyncRequest(SoftSyncRequestPdu),
 }
 
 impl Encode for DrdynvcServerPdu {
@@ -124,6 +151,7 @@ impl Encode for DrdynvcServerPdu {
             DrdynvcServerPdu::Capabilities(pdu) => pdu.encode(dst),
             DrdynvcServerPdu::Create(pdu) => pdu.encode(dst),
             DrdynvcServerPdu::Close(pdu) => pdu.encode(dst),
+            DrdynvcServerPdu::SoftSyncRequest(pdu) => pdu.encode(dst),
         }
     }
 
@@ -133,6 +161,7 @@ impl Encode for DrdynvcServerPdu {
             DrdynvcServerPdu::Capabilities(pdu) => pdu.name(),
             DrdynvcServerPdu::Create(_) => CreateRequestPdu::name(),
             DrdynvcServerPdu::Close(_) => ClosePdu::name(),
+            DrdynvcServerPdu::SoftSyncRequest(_) => SoftSyncRequestPdu::name(),
         }
     }
 
@@ -142,6 +171,7 @@ impl Encode for DrdynvcServerPdu {
             DrdynvcServerPdu::Capabilities(pdu) => pdu.size(),
             DrdynvcServerPdu::Create(pdu) => pdu.size(),
             DrdynvcServerPdu::Close(pdu) => pdu.size(),
+            DrdynvcServerPdu::SoftSyncRequest(pdu) => pdu.size(),
         }
     }
 }
@@ -151,12 +181,13 @@ impl Decode<'_> for DrdynvcServerPdu {
         let header = Header::decode(src)?;
         match header.cmd {
             Cmd::Create => Ok(Self::Create(CreateRequestPdu::decode(header, src)?)),
-            Cmd::DataFirst => Ok(Self::Data(DrdynvcDataPdu::DataFirst(DataFirstPdu::decode(
-                header, src,
-            )?))),
-            Cmd::Data => Ok(Self::Data(DrdynvcDataPdu::Data(DataPdu::decode(header, src)?))),
+            Cmd::DataFirst | Cmd::DataFirstCompressed => Ok(Self::Data(DrdynvcDataPdu::DataFirst(
+                DataFirstPdu::decode(header, src)?,
+            ))),
+            Cmd::Data | Cmd::DataCompressed => Ok(Self::Data(DrdynvcDataPdu::Data(DataPdu::decode(header, src)?))),
             Cmd::Close => Ok(Self::Close(ClosePdu::decode(header, src)?)),
             Cmd::Capability => Ok(Self::Capabilities(CapabilitiesRequestPdu::decode(header, src)?)),
+            Cmd::SoftSyncRequest => Ok(Self::SoftSyncRequest(SoftSyncRequestPdu::decode(header, src)?)),
             _ => Err(unsupported_value_err!("Cmd", header.cmd.into())),
         }
     }
@@ -288,6 +319,8 @@ impl From<Cmd> for String {
 
 /// 2.2.3.1 DVC Data First PDU (DYNVC_DATA_FIRST)
 ///
+/// Also used for 2.2.3.3 DVC Data First Compressed PDU (DYNVC_DATA_FIRST_COMPRESSED), see [`DataFirstPdu::is_compressed`].
+///
 /// [2.2.3.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpedyc/69377767-56a6-4ab8-996b-7758676e9261
 #[derive(Debug, PartialEq)]
 pub struct DataFirstPdu {
@@ -332,6 +365,28 @@ impl DataFirstPdu {
         }
     }
 
+    /// Marks `data` as compressed with the RDP 8.0 bulk compression, `length` still being the total length of the
+    /// uncompressed data.
+    #[must_use]
+    pub fn with_compressed_data(self) -> Self {
+        Self {
+            header: Header {
+                cmd: Cmd::DataFirstCompressed,
+                ..self.header
+            },
+            ..self
+        }
+    }
+
+    /// Whether this is a DYNVC_DATA_FIRST_COMPRESSED PDU
+    pub fn is_compressed(&self) -> bool {
+        self.header.cmd == Cmd::DataFirstCompressed
+    }
+
+    pub fn channel_id(&self) -> DynamicChannelId {
+        self.channel_id
+    }
+
     pub fn length(&self) -> u32 {
         self.length
     }
@@ -369,8 +424,12 @@ impl DataFirstPdu {
         Ok(())
     }
 
-    fn name() -> &'static str {
-        "DYNVC_DATA_FIRST"
+    fn name(&self) -> &'static str {
+        if self.is_compressed() {
+            "DYNVC_DATA_FIRST_COMPRESSED"
+        } else {
+            "DYNVC_DATA_FIRST"
+        }
     }
 
     fn size(&self) -> usize {
@@ -452,6 +511,8 @@ impl From<FieldType> for u8 {
 
 /// 2.2.3.2 DVC Data PDU (DYNVC_DATA)
 ///
+/// Also used for 2.2.3.4 DVC Data Compressed PDU (DYNVC_DATA_COMPRESSED), see [`DataPdu::is_compressed`].
+///
 /// [2.2.3.2]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpedyc/15b59886-db44-47f1-8da3-47c8fcd82803
 #[derive(Debug, PartialEq)]
 pub struct DataPdu {
@@ -469,6 +530,27 @@ impl DataPdu {
         }
     }
 
+    /// Marks `data` as compressed with the RDP 8.0 bulk compression
+    #[must_use]
+    pub fn with_compressed_data(self) -> Self {
+        Self {
+            header: Header {
+                cmd: Cmd::DataCompressed,
+                ..self.header
+            },
+            ..self
+        }
+    }
+
+    /// Whether this is a DYNVC_DATA_COMPRESSED PDU
+    pub fn is_compressed(&self) -> bool {
+        self.header.cmd == Cmd::DataCompressed
+    }
+
+    pub fn channel_id(&self) -> DynamicChannelId {
+        self.channel_id
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.data
     }
@@ -500,8 +582,12 @@ impl DataPdu {
         Ok(())
     }
 
-    fn name() -> &'static str {
-        "DYNVC_DATA"
+    fn name(&self) -> &'static str {
+        if self.is_compressed() {
+            "DYNVC_DATA_COMPRESSED"
+        } else {
+            "DYNVC_DATA"
+        }
     }
 
     fn size(&self) -> usize {
@@ -675,6 +761,10 @@ impl CapabilitiesResponsePdu {
         }
     }
 
+    pub fn version(&self) -> CapsVersion {
+        self.version
+    }
+
     fn decode(header: Header, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(in: src, size: Self::HEADERLESS_FIXED_PART_SIZE);
         let _pad = src.read_u8();
@@ -699,8 +789,12 @@ impl CapabilitiesResponsePdu {
     }
 }
 
+/// Version of the DVC capabilities, each version extending the previous one
+///
+/// - version 2 adds the priority classes of the channels,
+/// - version 3 adds the compressed data PDUs.
 #[repr(u16)]
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CapsVersion {
     V1 = 0x0001,
     V2 = 0x0002,
@@ -766,9 +860,14 @@ impl CapabilitiesRequestPdu {
     const HEADERLESS_FIXED_PART_SIZE: usize = 1 /* Pad */ + 2 /* Version */;
     const FIXED_PART_SIZE: usize = Header::FIXED_PART_SIZE + Self::HEADERLESS_FIXED_PART_SIZE;
     const PRIORITY_CHARGE_SIZE: usize = 2; // 2 bytes for each priority charge
-    const PRIORITY_CHARGE_COUNT: usize = 4; // 4 priority charges
+    pub const PRIORITY_CHARGE_COUNT: usize = 4; // 4 priority charges
     const PRIORITY_CHARGES_SIZE: usize = Self::PRIORITY_CHARGE_COUNT * Self::PRIORITY_CHARGE_SIZE;
 
+    /// Charges sharing the bandwidth between the priority classes as 70%, 20%, 7% and 3%.
+    ///
+    /// The charge of a class is 65536 divided by its percentage of the bandwidth.
+    pub const DEFAULT_PRIORITY_CHARGES: [u16; Self::PRIORITY_CHARGE_COUNT] = [936, 3276, 9362, 21845];
+
     pub fn new(version: CapsVersion, charges: Option<[u16; Self::PRIORITY_CHARGE_COUNT]>) -> Self {
         let header = Header::new(0, 0, Cmd::Capability);
         let charges = charges.unwrap_or([0; Self::PRIORITY_CHARGE_COUNT]);
@@ -780,6 +879,22 @@ impl CapabilitiesRequestPdu {
         }
     }
 
+    pub fn version(&self) -> CapsVersion {
+        match self {
+            Self::V1 { .. } => CapsVersion::V1,
+            Self::V2 { .. } => CapsVersion::V2,
+            Self::V3 { .. } => CapsVersion::V3,
+        }
+    }
+
+    /// Charges of the priority classes, `None` for the version 1
+    pub fn priority_charges(&self) -> Option<[u16; Self::PRIORITY_CHARGE_COUNT]> {
+        match self {
+            Self::V1 { .. } => None,
+            Self::V2 { charges, .. } | Self::V3 { charges, .. } => Some(*charges),
+        }
+    }
+
     fn decode(header: Header, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(in: src, size: Self::HEADERLESS_FIXED_PART_SIZE);
         let _pad = src.read_u8();
@@ -869,6 +984,20 @@ impl CreateRequestPdu {
         &self.channel_name
     }
 
+    /// Sets the priority class of the channel, only meaningful from the version 2 of the capabilities
+    #[must_use]
+    pub fn with_priority(self, priority: ChannelPriority) -> Self {
+        Self {
+            header: self.header.with_sp(FieldType::from(priority.class())),
+            ..self
+        }
+    }
+
+    /// Priority class of the channel, only meaningful from the version 2 of the capabilities
+    pub fn priority(&self) -> ChannelPriority {
+        ChannelPriority::from_class(u8::from(self.header.sp))
+    }
+
     pub fn into_channel_name(self) -> String {
         self.channel_name
     }
@@ -908,3 +1037,268 @@ impl CreateRequestPdu {
         ])
     }
 }
+
+/// Priority class of a dynamic channel, assigned by the server when creating the channel
+///
+/// The bandwidth is shared between the classes according to the priority charges of the capabilities, see
+/// [`CapabilitiesRequestPdu::priority_charges`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum ChannelPriority {
+    /// Class 0, for the interactive traffic, e.g. graphics
+    High,
+    /// Class 1
+    #[default]
+    Medium,
+    /// Class 2
+    Low,
+    /// Class 3, for the bulk transfers
+    Lowest,
+}
+
+impl ChannelPriority {
+    pub const ALL: [Self; 4] = [Self::High, Self::Medium, Self::Low, Self::Lowest];
+
+    /// Index of the priority class, 0 being the highest priority
+    pub fn class(self) -> u8 {
+        match self {
+            Self::High => 0,
+            Self::Medium => 1,
+            Self::Low => 2,
+            Self::Lowest => 3,
+        }
+    }
+
+    /// Priority of the class, the 2 low bits only are significant
+    pub fn from_class(class: u8) -> Self {
+        match class & 0b11 {
+            0 => Self::High,
+            1 => Self::Medium,
+            2 => Self::Low,
+            _ => Self::Lowest,
+        }
+    }
+}
+
+/// 2.2.5.1 Soft-Sync Request PDU (DYNVC_SOFT_SYNC_REQUEST)
+///
+/// Sent by the server once the data of the channels is flushed on the TCP connection, before moving channels to the
+/// UDP multitransport tunnels.
+#[derive(Debug, PartialEq)]
+pub struct SoftSyncRequestPdu {
+    header: Header,
+    flags: SoftSyncFlags,
+    channel_lists: Vec<SoftSyncChannelList>,
+}
+
+impl SoftSyncRequestPdu {
+    const HEADERLESS_FIXED_PART_SIZE: usize = 1 /* Pad */ + 4 /* Length */ + 2 /* Flags */ + 2 /* NumberOfTunnels */;
+
+    /// The channel lists are sent with [`SoftSyncFlags::CHANNEL_LIST_PRESENT`] when not empty.
+    pub fn new(flags: SoftSyncFlags, channel_lists: Vec<SoftSyncChannelList>) -> Self {
+        let flags = if channel_lists.is_empty() {
+            flags
+        } else {
+            SoftSyncFlags(flags.0 | SoftSyncFlags::CHANNEL_LIST_PRESENT.0)
+        };
+
+        Self {
+            header: Header::new(0, 0, Cmd::SoftSyncRequest),
+            flags,
+            channel_lists,
+        }
+    }
+
+    pub fn flags(&self) -> SoftSyncFlags {
+        self.flags
+    }
+
+    /// Channels to move to each tunnel
+    pub fn channel_lists(&self) -> &[SoftSyncChannelList] {
+        &self.channel_lists
+    }
+
+    fn decode(header: Header, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: Self::HEADERLESS_FIXED_PART_SIZE);
+        let _pad = src.read_u8();
+        let _length = src.read_u32();
+        let flags = SoftSyncFlags(src.read_u16());
+        let tunnel_count = src.read_u16();
+
+        let channel_lists = if flags.contains(SoftSyncFlags::CHANNEL_LIST_PRESENT) {
+            core::iter::repeat_with(|| SoftSyncChannelList::decode(src))
+                .take(usize::from(tunnel_count))
+                .collect::<DecodeResult<_>>()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            header,
+            flags,
+            channel_lists,
+        })
+    }
+
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        self.header.encode(dst)?;
+        dst.write_u8(0x00); // Pad, MUST be 0x00
+        dst.write_u32(cast_length!("SoftSyncRequestPdu::Length", self.size())?);
+        dst.write_u16(self.flags.0);
+        dst.write_u16(cast_length!("NumberOfTunnels", self.channel_lists.len())?);
+        for channel_list in &self.channel_lists {
+            channel_list.encode(dst)?;
+        }
+        Ok(())
+    }
+
+    fn name() -> &'static str {
+        "DYNVC_SOFT_SYNC_REQUEST"
+    }
+
+    fn size(&self) -> usize {
+        let mut size = strict_sum(&[Header::size(), Self::HEADERLESS_FIXED_PART_SIZE]);
+        for channel_list in &self.channel_lists {
+            size = strict_sum(&[size, channel_list.size()]);
+        }
+        size
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SoftSyncFlags(u16);
+
+impl SoftSyncFlags {
+    /// The data of the channels is flushed on the TCP connection
+    pub const TCP_FLUSHED: Self = Self(0x0001);
+    /// The channel lists are present
+    pub const CHANNEL_LIST_PRESENT: Self = Self(0x0002);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl From<SoftSyncFlags> for u16 {
+    fn from(flags: SoftSyncFlags) -> Self {
+        flags.0
+    }
+}
+
+/// Multitransport tunnel of the channels, see [`SoftSyncChannelList`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TunnelType(u32);
+
+impl TunnelType {
+    /// Reliable UDP transport
+    pub const UDP_FECR: Self = Self(0x0000_0001);
+    /// Lossy UDP transport
+    pub const UDP_FECL: Self = Self(0x0000_0003);
+
+    fn size() -> usize {
+        4
+    }
+}
+
+impl From<TunnelType> for u32 {
+    fn from(tunnel: TunnelType) -> Self {
+        tunnel.0
+    }
+}
+
+/// 2.2.5.1.1 Soft-Sync Channel List (DYNVC_SOFT_SYNC_CHANNEL_LIST)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoftSyncChannelList {
+    pub tunnel: TunnelType,
+    pub channel_ids: Vec<DynamicChannelId>,
+}
+
+impl SoftSyncChannelList {
+    const FIXED_PART_SIZE: usize = 4 /* TunnelType */ + 2 /* NumberOfDVCs */;
+
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+        let tunnel = TunnelType(src.read_u32());
+        let channel_count = usize::from(src.read_u16());
+
+        ensure_size!(in: src, size: channel_count * 4);
+        let channel_ids = core::iter::repeat_with(|| src.read_u32()).take(channel_count).collect();
+
+        Ok(Self { tunnel, channel_ids })
+    }
+
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        dst.write_u32(self.tunnel.0);
+        dst.write_u16(cast_length!("NumberOfDVCs", self.channel_ids.len())?);
+        for channel_id in &self.channel_ids {
+            dst.write_u32(*channel_id);
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.channel_ids.len() * 4
+    }
+}
+
+/// 2.2.5.2 Soft-Sync Response PDU (DYNVC_SOFT_SYNC_RESPONSE)
+///
+/// Sent by the client in response to the [`SoftSyncRequestPdu`], with the tunnels it switches to.
+#[derive(Debug, PartialEq)]
+pub struct SoftSyncResponsePdu {
+    header: Header,
+    tunnels: Vec<TunnelType>,
+}
+
+impl SoftSyncResponsePdu {
+    const HEADERLESS_FIXED_PART_SIZE: usize = 1 /* Pad */ + 4 /* NumberOfTunnels */;
+
+    pub fn new(tunnels: Vec<TunnelType>) -> Self {
+        Self {
+            header: Header::new(0, 0, Cmd::SoftSyncResponse),
+            tunnels,
+        }
+    }
+
+    /// Tunnels the client switches to, the channels of the other tunnels stay on TCP
+    pub fn tunnels(&self) -> &[TunnelType] {
+        &self.tunnels
+    }
+
+    fn decode(header: Header, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: Self::HEADERLESS_FIXED_PART_SIZE);
+        let _pad = src.read_u8();
+        let tunnel_count: usize = cast_length!("NumberOfTunnels", src.read_u32())?;
+
+        ensure_size!(in: src, size: tunnel_count.saturating_mul(TunnelType::size()));
+        let tunnels = core::iter::repeat_with(|| TunnelType(src.read_u32()))
+            .take(tunnel_count)
+            .collect();
+
+        Ok(Self { header, tunnels })
+    }
+
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        self.header.encode(dst)?;
+        dst.write_u8(0x00); // Pad, MUST be 0x00
+        dst.write_u32(cast_length!("NumberOfTunnels", self.tunnels.len())?);
+        for tunnel in &self.tunnels {
+            dst.write_u32(tunnel.0);
+        }
+        Ok(())
+    }
+
+    fn name() -> &'static str {
+        "DYNVC_SOFT_SYNC_RESPONSE"
+    }
+
+    fn size(&self) -> usize {
+        strict_sum(&[
+            Header::size(),
+            Self::HEADERLESS_FIXED_PART_SIZE,
+            self.tunnels.len() * TunnelType::size(),
+        ])
+    }
+}
@@ -0,0 +1,125 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use ironrdp_core::{Encode as _, EncodeResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+
+use crate::pdu::{CapabilitiesRequestPdu, ChannelPriority, DrdynvcDataPdu};
+use crate::{split_dvc_message, DvcMessage};
+
+/// Queue of the data PDUs to send, sharing the bandwidth between the priority classes of the channels
+///
+/// Each PDU sent charges its class with its size multiplied by the priority charge of the class, and the class charged
+/// the least is sent first. The messages of a low priority channel, e.g. a bulk file transfer, are thus preempted by the
+/// messages of the higher priority channels, e.g. graphics, without being starved.
+///
+/// With charges of 0, as in the version 1 of the capabilities, the classes are sent strictly by priority.
+#[derive(Debug)]
+pub struct DvcSendQueue {
+    charges: [u16; CapabilitiesRequestPdu::PRIORITY_CHARGE_COUNT],
+    classes: [PriorityClass; CapabilitiesRequestPdu::PRIORITY_CHARGE_COUNT],
+    /// Charge of the class sent last, a class becoming busy again starts from there
+    virtual_time: u64,
+    max_data_size: usize,
+}
+
+#[derive(Debug, Default)]
+struct PriorityClass {
+    pdus: VecDeque<(DrdynvcDataPdu, ChannelFlags)>,
+    charged: u64,
+}
+
+impl DvcSendQueue {
+    pub fn new(charges: [u16; CapabilitiesRequestPdu::PRIORITY_CHARGE_COUNT]) -> Self {
+        Self {
+            charges,
+            classes: Default::default(),
+            virtual_time: 0,
+            max_data_size: DrdynvcDataPdu::MAX_DATA_SIZE,
+        }
+    }
+
+    pub fn charges(&self) -> [u16; CapabilitiesRequestPdu::PRIORITY_CHARGE_COUNT] {
+        self.charges
+    }
+
+    pub fn set_charges(&mut self, charges: [u16; CapabilitiesRequestPdu::PRIORITY_CHARGE_COUNT]) {
+        self.charges = charges;
+    }
+
+    /// Maximum size of the data of the PDUs the messages are split into
+    pub fn max_data_size(&self) -> usize {
+        self.max_data_size
+    }
+
+    /// Sets the maximum size of the data of the PDUs the messages pushed next are split into
+    ///
+    /// See [`DrdynvcDataPdu::max_data_size_for_segment`].
+    pub fn set_max_data_size(&mut self, max_data_size: usize) {
+        self.max_data_size = max_data_size;
+    }
+
+    /// Queues the messages of a channel, split into data PDUs
+    pub fn push(
+        &mut self,
+        channel_id: u32,
+        priority: ChannelPriority,
+        messages: Vec<DvcMessage>,
+        flags: ChannelFlags,
+    ) -> EncodeResult<()> {
+        let virtual_time = self.virtual_time;
+        let max_data_size = self.max_data_size;
+        let class = &mut self.classes[usize::from(priority.class())];
+
+        if class.pdus.is_empty() {
+            class.charged = class.charged.max(virtual_time);
+        }
+
+        for message in messages {
+            let pdus = split_dvc_message(channel_id, message.as_ref(), max_data_size)?;
+            class.pdus.extend(pdus.into_iter().map(|pdu| (pdu, flags)));
+        }
+
+        Ok(())
+    }
+
+    /// Next PDU to send, if any
+    pub fn pop(&mut self) -> Option<SvcMessage> {
+        let (index, class) = self
+            .classes
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, class)| !class.pdus.is_empty())
+            .min_by_key(|(_, class)| class.charged)?;
+
+        let (pdu, flags) = class.pdus.pop_front()?;
+
+        self.virtual_time = class.charged;
+        let size = u64::try_from(pdu.size()).unwrap_or(u64::MAX);
+        class.charged = class
+            .charged
+            .saturating_add(size.saturating_mul(u64::from(self.charges[index])));
+
+        Some(SvcMessage::from(pdu).with_flags(flags))
+    }
+
+    /// Number of PDUs queued
+    pub fn len(&self) -> usize {
+        self.classes.iter().map(|class| class.pdus.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.iter().all(|class| class.pdus.is_empty())
+    }
+
+    /// Number of PDUs queued with the given priority
+    pub fn queued(&self, priority: ChannelPriority) -> usize {
+        self.classes[usize::from(priority.class())].pdus.len()
+    }
+}
+
+impl Default for DvcSendQueue {
+    fn default() -> Self {
+        Self::new(CapabilitiesRequestPdu::DEFAULT_PRIORITY_CHARGES)
+    }
+}
@@ -0,0 +1,432 @@
+//! Building blocks for simple message-based protocols over a dynamic virtual channel
+//!
+//! Every message is sent in a frame with the following header (all fields little-endian):
+//!
+//! | Field        | Size | Description                                   |
+//! |--------------|------|-----------------------------------------------|
+//! | messageType  | 2    | Identifies the message                        |
+//! | version      | 2    | Protocol version of the sender                |
+//! | length       | 4    | Length of the payload following the header    |
+//!
+//! Frames may be split across several DVC messages, and a DVC message may hold several frames,
+//! so peers exchanging raw byte streams (e.g.: through a named pipe proxy) are supported.
+//! Frames with an unknown message type are skipped, which lets newer peers add messages
+//! without breaking older ones.
+//!
+//! The [`dvc_protocol!`](crate::dvc_protocol) macro generates the message enum, and implementing
+//! [`ProtocolHandler`] is enough to get a [`DvcProcessor`] by wrapping it in a [`ProtocolChannel`].
+
+use alloc::boxed::Box;
+use alloc::string::ToString as _;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::marker::PhantomData;
+
+use ironrdp_core::{
+    cast_length, ensure_size, invalid_field_err, other_err, unsupported_value_err, AsAny, DecodeResult, Encode,
+    EncodeResult, ReadCursor, WriteCursor,
+};
+use ironrdp_pdu::{decode_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tracing::debug;
+
+use crate::{
+    encode_dvc_messages, DvcClientProcessor, DvcEncode, DvcMessage, DvcProcessor, DvcServerProcessor, StallRecovery,
+};
+
+/// Size of the frame header
+pub const FRAME_HEADER_SIZE: usize = 2 /* messageType */ + 2 /* version */ + 4 /* length */;
+
+/// Message of a simple DVC protocol
+///
+/// Usually implemented with the [`dvc_protocol!`](crate::dvc_protocol) macro.
+pub trait ProtocolMessage: fmt::Debug + Send + Sized + 'static {
+    /// The name of the dynamic virtual channel
+    const CHANNEL_NAME: &'static str;
+
+    /// Protocol version sent in the header of every frame
+    const VERSION: u16;
+
+    /// Oldest protocol version accepted from the peer
+    const MIN_VERSION: u16 = Self::VERSION;
+
+    /// Largest payload accepted from the peer, preventing unbounded buffering
+    const MAX_PAYLOAD_SIZE: usize = 1024 * 1024;
+
+    fn message_type(&self) -> u16;
+
+    fn payload_size(&self) -> usize;
+
+    /// Encodes the payload, which must be exactly [`Self::payload_size`] bytes long
+    fn encode_payload(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()>;
+
+    /// Decodes the payload of a message sent by a peer using protocol `version`
+    ///
+    /// Returns `None` for unknown message types, which are then skipped.
+    fn decode_payload(message_type: u16, version: u16, src: &mut ReadCursor<'_>) -> DecodeResult<Option<Self>>;
+}
+
+/// A [`ProtocolMessage`] along with its frame header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame<M>(pub M);
+
+impl<M: ProtocolMessage> Frame<M> {
+    const NAME: &'static str = "DvcProtocolFrame";
+}
+
+impl<M: ProtocolMessage> Encode for Frame<M> {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        let payload_size = self.0.payload_size();
+
+        dst.write_u16(self.0.message_type());
+        dst.write_u16(M::VERSION);
+        dst.write_u32(cast_length!(Self::NAME, "length", payload_size)?);
+
+        let start = dst.pos();
+        self.0.encode_payload(dst)?;
+
+        if dst.pos() - start != payload_size {
+            return Err(invalid_field_err!(
+                Self::NAME,
+                "length",
+                "encoded payload size does not match the advertised one"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        FRAME_HEADER_SIZE + self.0.payload_size()
+    }
+}
+
+impl<M: ProtocolMessage> DvcEncode for Frame<M> {}
+
+/// Reassembles frames from a stream of bytes
+#[derive(Debug)]
+pub struct FrameDecoder<M> {
+    buffer: Vec<u8>,
+    peer_version: Option<u16>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<M: ProtocolMessage> FrameDecoder<M> {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            peer_version: None,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Decodes the next complete message, or returns `None` if more data is needed
+    pub fn next_message(&mut self) -> DecodeResult<Option<M>> {
+        loop {
+            let mut src = ReadCursor::new(&self.buffer);
+
+            if src.len() < FRAME_HEADER_SIZE {
+                return Ok(None);
+            }
+
+            let message_type = src.read_u16();
+            let version = src.read_u16();
+            let length: usize = cast_length!(Frame::<M>::NAME, "length", src.read_u32())?;
+
+            if length > M::MAX_PAYLOAD_SIZE {
+                // The end of the frame is unknown, nothing after it can be decoded.
+                self.buffer.clear();
+                return Err(invalid_field_err!(Frame::<M>::NAME, "length", "payload is too big"));
+            }
+
+            if src.len() < length {
+                return Ok(None);
+            }
+
+            let message = if version < M::MIN_VERSION {
+                Err(unsupported_value_err!(Frame::<M>::NAME, "version", version.to_string()))
+            } else {
+                M::decode_payload(message_type, version, &mut ReadCursor::new(src.read_slice(length)))
+            };
+
+            // The frame is dropped even when invalid, so that the next ones can still be decoded.
+            self.buffer.drain(..FRAME_HEADER_SIZE + length);
+
+            let message = message?;
+            self.peer_version = Some(version);
+
+            if let Some(message) = message {
+                return Ok(Some(message));
+            }
+
+            debug!(message_type, version, "Skipping unknown {} message", M::CHANNEL_NAME);
+        }
+    }
+
+    /// Protocol version of the last frame received from the peer
+    pub fn peer_version(&self) -> Option<u16> {
+        self.peer_version
+    }
+
+    /// Returns `true` if a partially received frame is buffered
+    pub fn has_partial_frame(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+}
+
+impl<M: ProtocolMessage> Default for FrameDecoder<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Application logic of a simple DVC protocol
+pub trait ProtocolHandler: Send + 'static {
+    type Message: ProtocolMessage;
+
+    /// Returns the messages to send when the channel is opened
+    fn start(&mut self) -> PduResult<Vec<Self::Message>> {
+        Ok(Vec::new())
+    }
+
+    /// Handles a message from the peer, returning the responses to send back
+    fn handle(&mut self, message: Self::Message) -> PduResult<Vec<Self::Message>>;
+
+    fn close(&mut self) {}
+
+    /// Whether a response of the peer is awaited, see [`DvcProcessor::awaiting_response`]
+    fn awaiting_response(&self) -> bool {
+        false
+    }
+
+    /// Returns the messages to send again once the peer stopped responding, or `None` to reset the channel
+    fn on_stall(&mut self, _attempt: u32) -> PduResult<Option<Vec<Self::Message>>> {
+        Ok(None)
+    }
+
+    /// Returns the messages keeping the channel alive, see [`DvcProcessor::keepalive`]
+    fn keepalive(&mut self) -> PduResult<Vec<Self::Message>> {
+        Ok(Vec::new())
+    }
+}
+
+/// [`DvcProcessor`] taking care of the framing of a [`ProtocolHandler`]'s messages
+///
+/// It can be registered on both the client and the server side.
+pub struct ProtocolChannel<H: ProtocolHandler> {
+    handler: H,
+    decoder: FrameDecoder<H::Message>,
+    channel_id: Option<u32>,
+}
+
+impl<H: ProtocolHandler> ProtocolChannel<H> {
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler,
+            decoder: FrameDecoder::new(),
+            channel_id: None,
+        }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+
+    /// The channel ID, if the channel is open
+    pub fn channel_id(&self) -> Option<u32> {
+        self.channel_id
+    }
+
+    /// Protocol version of the last frame received from the peer
+    pub fn peer_version(&self) -> Option<u16> {
+        self.decoder.peer_version()
+    }
+
+    /// Encodes messages to send outside of [`ProtocolHandler::handle`], e.g.: requests initiated
+    /// by the application
+    pub fn encode_messages(&self, messages: Vec<H::Message>) -> EncodeResult<Vec<SvcMessage>> {
+        let channel_id = self
+            .channel_id
+            .ok_or_else(|| other_err!("ProtocolChannel", "channel is not open"))?;
+
+        encode_dvc_messages(channel_id, into_dvc_messages(messages), ChannelFlags::empty())
+    }
+}
+
+impl<H: ProtocolHandler> fmt::Debug for ProtocolChannel<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProtocolChannel")
+            .field("channel_name", &H::Message::CHANNEL_NAME)
+            .field("channel_id", &self.channel_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<H: ProtocolHandler> AsAny for ProtocolChannel<H> {
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl<H: ProtocolHandler> DvcProcessor for ProtocolChannel<H> {
+    fn channel_name(&self) -> &str {
+        H::Message::CHANNEL_NAME
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.channel_id = Some(channel_id);
+        self.decoder = FrameDecoder::new();
+
+        self.handler.start().map(into_dvc_messages)
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        self.decoder.push(payload);
+
+        let mut responses = Vec::new();
+
+        while let Some(message) = self.decoder.next_message().map_err(|e| decode_err!(e))? {
+            debug!(?message, "Received");
+            responses.extend(self.handler.handle(message)?);
+        }
+
+        Ok(into_dvc_messages(responses))
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        self.channel_id = None;
+        self.handler.close();
+    }
+
+    fn awaiting_response(&self) -> bool {
+        self.handler.awaiting_response()
+    }
+
+    fn on_stall(&mut self, _channel_id: u32, attempt: u32) -> PduResult<StallRecovery> {
+        let recovery = match self.handler.on_stall(attempt)? {
+            Some(messages) => StallRecovery::Resend(into_dvc_messages(messages)),
+            None => StallRecovery::Reset,
+        };
+
+        Ok(recovery)
+    }
+
+    fn keepalive(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.handler.keepalive().map(into_dvc_messages)
+    }
+}
+
+impl<H: ProtocolHandler> DvcClientProcessor for ProtocolChannel<H> {}
+
+impl<H: ProtocolHandler> DvcServerProcessor for ProtocolChannel<H> {}
+
+fn into_dvc_messages<M: ProtocolMessage>(messages: Vec<M>) -> Vec<DvcMessage> {
+    messages
+        .into_iter()
+        .map(|message| -> DvcMessage { Box::new(Frame(message)) })
+        .collect()
+}
+
+/// Defines the message enum of a simple DVC protocol and implements [`ProtocolMessage`] for it
+///
+/// Each variant wraps a distinct type implementing [`Encode`] and
+/// [`DecodeOwned`](ironrdp_core::DecodeOwned), and is assigned a message type.
+///
+/// ```ignore
+/// ironrdp_dvc::dvc_protocol! {
+///     #[derive(Debug, Clone, PartialEq, Eq)]
+///     pub enum AgentMessage: "Contoso::Agent" (version 2) {
+///         Ping(Ping) = 0x0001,
+///         Pong(Pong) = 0x0002,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! dvc_protocol {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident: $channel_name:literal (version $version:literal) {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident($ty:ty) = $message_type:literal
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant($ty),
+            )+
+        }
+
+        $(
+            impl From<$ty> for $name {
+                fn from(message: $ty) -> Self {
+                    Self::$variant(message)
+                }
+            }
+        )+
+
+        impl $crate::protocol::ProtocolMessage for $name {
+            const CHANNEL_NAME: &'static str = $channel_name;
+            const VERSION: u16 = $version;
+
+            fn message_type(&self) -> u16 {
+                match self {
+                    $(Self::$variant(_) => $message_type,)+
+                }
+            }
+
+            fn payload_size(&self) -> usize {
+                match self {
+                    $(Self::$variant(message) => $crate::ironrdp_core::Encode::size(message),)+
+                }
+            }
+
+            fn encode_payload(
+                &self,
+                dst: &mut $crate::ironrdp_core::WriteCursor<'_>,
+            ) -> $crate::ironrdp_core::EncodeResult<()> {
+                match self {
+                    $(Self::$variant(message) => $crate::ironrdp_core::Encode::encode(message, dst),)+
+                }
+            }
+
+            fn decode_payload(
+                message_type: u16,
+                _version: u16,
+                src: &mut $crate::ironrdp_core::ReadCursor<'_>,
+            ) -> $crate::ironrdp_core::DecodeResult<Option<Self>> {
+                match message_type {
+                    $(
+                        $message_type => <$ty as $crate::ironrdp_core::DecodeOwned>::decode_owned(src)
+                            .map(|message| Some(Self::$variant(message))),
+                    )+
+                    _ => Ok(None),
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,79 @@
+use crate::pdu::DrdynvcDataPdu;
+
+/// Bounds of the reassembly of the fragmented messages received on the dynamic channels
+///
+/// The size of a fragmented message is announced by its first PDU, up to 4 GiB. Messages exceeding the limits are not
+/// buffered, the processor of the channel deciding how to recover with [`DvcProcessor::on_quota_exceeded`].
+///
+/// [`DvcProcessor::on_quota_exceeded`]: crate::DvcProcessor::on_quota_exceeded
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReassemblyLimits {
+    /// Maximum size of a message of a channel
+    pub max_message_size: usize,
+    /// Maximum size of the messages being reassembled on all the channels at once
+    pub max_total_size: usize,
+}
+
+impl ReassemblyLimits {
+    /// Checks the size announced by a data first PDU, `pending_elsewhere` being the size of the messages being
+    /// reassembled on the other channels
+    pub(crate) fn check(&self, pdu: &DrdynvcDataPdu, pending_elsewhere: usize) -> Option<QuotaViolation> {
+        let DrdynvcDataPdu::DataFirst(data_first) = pdu else {
+            return None;
+        };
+
+        let size = usize::try_from(data_first.length()).unwrap_or(usize::MAX);
+        if size > self.max_message_size {
+            return Some(QuotaViolation {
+                limit: QuotaLimit::Message,
+                size,
+            });
+        }
+
+        // The messages sent in a single PDU are not buffered
+        let total_size = pending_elsewhere.saturating_add(size);
+        if size > data_first.data().len() && total_size > self.max_total_size {
+            return Some(QuotaViolation {
+                limit: QuotaLimit::Total,
+                size: total_size,
+            });
+        }
+
+        None
+    }
+}
+
+impl Default for ReassemblyLimits {
+    fn default() -> Self {
+        Self {
+            max_message_size: 16 * 1024 * 1024,
+            max_total_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Limit of [`ReassemblyLimits`] exceeded by a message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QuotaLimit {
+    /// [`ReassemblyLimits::max_message_size`]
+    Message,
+    /// [`ReassemblyLimits::max_total_size`]
+    Total,
+}
+
+/// A message received on a channel exceeding the reassembly limits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QuotaViolation {
+    pub limit: QuotaLimit,
+    /// Size of the message, or of all the messages being reassembled for [`QuotaLimit::Total`]
+    pub size: usize,
+}
+
+/// How a processor handles a message exceeding the reassembly limits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QuotaAction {
+    /// Drops the message, along with its remaining fragments
+    Drop,
+    /// Drops the message, then closes and restarts the processor, which sends its start messages again
+    Reset,
+}
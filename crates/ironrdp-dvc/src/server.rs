@@ -8,12 +8,15 @@ use ironrdp_svc::{ChannelFlags, CompressionCondition, SvcMessage, SvcProcessor,
 use pdu::gcc::ChannelName;
 use pdu::PduResult;
 use slab::Slab;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::pdu::{
     CapabilitiesRequestPdu, CapsVersion, CreateRequestPdu, CreationStatus, DrdynvcClientPdu, DrdynvcServerPdu,
 };
-use crate::{encode_dvc_messages, CompleteData, DvcProcessor};
+use crate::{
+    encode_dvc_messages_with_max_data_size, CompleteData, DvcMessage, DvcProcessor, DvcSendQueue, QuotaAction,
+    ReassemblyLimits,
+};
 
 pub trait DvcServerProcessor: DvcProcessor {}
 
@@ -36,9 +39,13 @@ impl DynamicChannel {
     where
         T: DvcServerProcessor + 'static,
     {
+        Self::from_boxed(Box::new(processor))
+    }
+
+    fn from_boxed(processor: Box<dyn DvcServerProcessor>) -> Self {
         Self {
             state: ChannelState::Closed,
-            processor: Box::new(processor),
+            processor,
             complete_data: CompleteData::new(),
         }
     }
@@ -48,6 +55,12 @@ impl DynamicChannel {
 /// It adds support for dynamic virtual channels (DVC).
 pub struct DrdynvcServer {
     dynamic_channels: Slab<DynamicChannel>,
+    /// Whether the client answered the capabilities request, the channels are created afterwards
+    capabilities_received: bool,
+    /// Version of the capabilities answered by the client
+    version: CapsVersion,
+    send_queue: DvcSendQueue,
+    reassembly_limits: ReassemblyLimits,
 }
 
 impl fmt::Debug for DrdynvcServer {
@@ -71,10 +84,37 @@ impl DrdynvcServer {
     pub fn new() -> Self {
         Self {
             dynamic_channels: Slab::new(),
+            capabilities_received: false,
+            version: CapsVersion::V1,
+            send_queue: DvcSendQueue::default(),
+            reassembly_limits: ReassemblyLimits::default(),
         }
     }
 
-    // FIXME(#61): it’s likely we want to enable adding dynamic channels at any point during the session (message passing? other approach?)
+    /// Sets the charges of the priority classes sent to the client, see [`DvcSendQueue`]
+    #[must_use]
+    pub fn with_priority_charges(mut self, charges: [u16; CapabilitiesRequestPdu::PRIORITY_CHARGE_COUNT]) -> Self {
+        self.send_queue.set_charges(charges);
+        self
+    }
+
+    /// Sets the maximum size of the data of the PDUs the messages are split into
+    ///
+    /// See [`crate::pdu::DrdynvcDataPdu::max_data_size_for_segment`].
+    #[must_use]
+    pub fn with_max_data_size(mut self, max_data_size: usize) -> Self {
+        self.send_queue.set_max_data_size(max_data_size);
+        self
+    }
+
+    /// Sets the bounds of the reassembly of the messages received from the client
+    ///
+    /// The defaults protect against a client announcing huge messages, see [`ReassemblyLimits`].
+    #[must_use]
+    pub fn with_reassembly_limits(mut self, limits: ReassemblyLimits) -> Self {
+        self.reassembly_limits = limits;
+        self
+    }
 
     #[must_use]
     pub fn with_dynamic_channel<T>(mut self, channel: T) -> Self
@@ -85,6 +125,58 @@ impl DrdynvcServer {
         self
     }
 
+    /// Adds a dynamic channel during the session
+    ///
+    /// Returns the create request of the channel, empty when the capabilities are not exchanged yet: the channel is
+    /// then created along with the other ones.
+    pub fn attach_dynamic_channel<T>(&mut self, channel: T) -> PduResult<Vec<SvcMessage>>
+    where
+        T: DvcServerProcessor + 'static,
+    {
+        self.attach_boxed_dynamic_channel(Box::new(channel))
+    }
+
+    /// Adds a boxed dynamic channel during the session, see [`DrdynvcServer::attach_dynamic_channel`]
+    pub fn attach_boxed_dynamic_channel(&mut self, channel: Box<dyn DvcServerProcessor>) -> PduResult<Vec<SvcMessage>> {
+        let id = self.dynamic_channels.insert(DynamicChannel::from_boxed(channel));
+
+        if !self.capabilities_received {
+            return Ok(Vec::new());
+        }
+
+        let version = self.version;
+        let c = &mut self.dynamic_channels[id];
+        let req = DrdynvcServerPdu::Create(create_request(id, c, version)?);
+        c.state = ChannelState::Creation;
+
+        Ok(alloc::vec![as_svc_msg_with_flag(req)?])
+    }
+
+    /// Queues messages of an opened channel, sent by [`DrdynvcServer::next_queued_message`] according to the priority
+    /// of the channel.
+    ///
+    /// Used for the large transfers, which are then preempted by the messages of the higher priority channels.
+    pub fn queue_dvc_messages(&mut self, channel_id: u32, messages: Vec<DvcMessage>) -> PduResult<()> {
+        let c = self.channel_by_id(channel_id).map_err(|e| decode_err!(e))?;
+        if c.state != ChannelState::Opened {
+            return Err(pdu_other_err!("invalid channel state"));
+        }
+        let priority = c.processor.priority();
+
+        self.send_queue
+            .push(channel_id, priority, messages, ChannelFlags::SHOW_PROTOCOL)
+            .map_err(|e| encode_err!(e))
+    }
+
+    /// Next queued message to send, by priority, see [`DrdynvcServer::queue_dvc_messages`]
+    pub fn next_queued_message(&mut self) -> Option<SvcMessage> {
+        self.send_queue.pop()
+    }
+
+    pub fn has_queued_messages(&self) -> bool {
+        !self.send_queue.is_empty()
+    }
+
     fn channel_by_id(&mut self, id: u32) -> DecodeResult<&mut DynamicChannel> {
         let id = cast_length!("DRDYNVC", "", id)?;
         self.dynamic_channels
@@ -111,7 +203,8 @@ impl SvcProcessor for DrdynvcServer {
     }
 
     fn start(&mut self) -> PduResult<Vec<SvcMessage>> {
-        let cap = CapabilitiesRequestPdu::new(CapsVersion::V1, None);
+        // The compressed data PDUs of the version 3 are not supported
+        let cap = CapabilitiesRequestPdu::new(CapsVersion::V2, Some(self.send_queue.charges()));
         let req = DrdynvcServerPdu::Capabilities(cap);
         let msg = as_svc_msg_with_flag(req)?;
         Ok(alloc::vec![msg])
@@ -119,20 +212,19 @@ impl SvcProcessor for DrdynvcServer {
 
     fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
         let pdu = decode_dvc_message(payload).map_err(|e| decode_err!(e))?;
+        let max_data_size = self.send_queue.max_data_size();
         let mut resp = Vec::new();
 
         match pdu {
             DrdynvcClientPdu::Capabilities(caps_resp) => {
                 debug!("Got DVC Capabilities Response PDU: {caps_resp:?}");
+                self.capabilities_received = true;
+                self.version = caps_resp.version().min(CapsVersion::V2);
                 for (id, c) in self.dynamic_channels.iter_mut() {
                     if c.state != ChannelState::Closed {
                         continue;
                     }
-                    let req = DrdynvcServerPdu::Create(CreateRequestPdu::new(
-                        id.try_into()
-                            .map_err(|e| pdu_other_err!("invalid channel id", source: e))?,
-                        c.processor.channel_name().into(),
-                    ));
+                    let req = DrdynvcServerPdu::Create(create_request(id, c, self.version)?);
                     c.state = ChannelState::Creation;
                     resp.push(as_svc_msg_with_flag(req)?);
                 }
@@ -150,7 +242,10 @@ impl SvcProcessor for DrdynvcServer {
                 }
                 c.state = ChannelState::Opened;
                 let msg = c.processor.start(create_resp.channel_id())?;
-                resp.extend(encode_dvc_messages(id, msg, ChannelFlags::SHOW_PROTOCOL).map_err(|e| encode_err!(e))?);
+                resp.extend(
+                    encode_dvc_messages_with_max_data_size(id, msg, ChannelFlags::SHOW_PROTOCOL, max_data_size)
+                        .map_err(|e| encode_err!(e))?,
+                );
             }
             DrdynvcClientPdu::Close(close_resp) => {
                 debug!("Got DVC Close Response PDU: {close_resp:?}");
@@ -161,19 +256,62 @@ impl SvcProcessor for DrdynvcServer {
                     return Err(pdu_other_err!("invalid channel state"));
                 }
                 c.state = ChannelState::Closed;
+                c.processor.close(close_resp.channel_id());
+            }
+            DrdynvcClientPdu::SoftSyncResponse(soft_sync_resp) => {
+                debug!("Got DVC Soft-Sync Response PDU: {soft_sync_resp:?}");
             }
             DrdynvcClientPdu::Data(data) => {
                 let channel_id = data.channel_id();
+                let pending_elsewhere = self
+                    .dynamic_channels
+                    .iter()
+                    .filter(|(id, _)| u32::try_from(*id).ok() != Some(channel_id))
+                    .map(|(_, c)| c.complete_data.pending_size())
+                    .fold(0, usize::saturating_add);
+                let violation = self.reassembly_limits.check(&data, pending_elsewhere);
                 let c = self.channel_by_id(channel_id).map_err(|e| decode_err!(e))?;
                 if c.state != ChannelState::Opened {
                     debug!(?channel_id, ?c.state, "Invalid channel state");
                     return Err(pdu_other_err!("invalid channel state"));
                 }
-                if let Some(complete) = c.complete_data.process_data(data).map_err(|e| decode_err!(e))? {
+                if data.is_compressed() {
+                    return Err(pdu_other_err!("compressed DVC data is not supported"));
+                }
+                if let Some(violation) = violation {
+                    c.complete_data.drop_message(&data);
+                    let action = c.processor.on_quota_exceeded(channel_id, violation);
+                    warn!(
+                        channel_name = c.processor.channel_name(),
+                        channel_id,
+                        ?violation,
+                        ?action,
+                        "Dynamic channel message exceeds the reassembly limits"
+                    );
+
+                    if action == QuotaAction::Reset {
+                        c.processor.close(channel_id);
+                        let msg = c.processor.start(channel_id)?;
+                        resp.extend(
+                            encode_dvc_messages_with_max_data_size(
+                                channel_id,
+                                msg,
+                                ChannelFlags::SHOW_PROTOCOL,
+                                max_data_size,
+                            )
+                            .map_err(|e| encode_err!(e))?,
+                        );
+                    }
+                } else if let Some(complete) = c.complete_data.process_data(data).map_err(|e| decode_err!(e))? {
                     let msg = c.processor.process(channel_id, &complete)?;
                     resp.extend(
-                        encode_dvc_messages(channel_id, msg, ChannelFlags::SHOW_PROTOCOL)
-                            .map_err(|e| encode_err!(e))?,
+                        encode_dvc_messages_with_max_data_size(
+                            channel_id,
+                            msg,
+                            ChannelFlags::SHOW_PROTOCOL,
+                            max_data_size,
+                        )
+                        .map_err(|e| encode_err!(e))?,
                     );
                 }
             }
@@ -189,6 +327,21 @@ fn decode_dvc_message(user_data: &[u8]) -> DecodeResult<DrdynvcClientPdu> {
     DrdynvcClientPdu::decode(&mut ReadCursor::new(user_data))
 }
 
+fn create_request(id: usize, channel: &DynamicChannel, version: CapsVersion) -> PduResult<CreateRequestPdu> {
+    let req = CreateRequestPdu::new(
+        id.try_into()
+            .map_err(|e| pdu_other_err!("invalid channel id", source: e))?,
+        channel.processor.channel_name().into(),
+    );
+
+    // The priority classes are introduced by the version 2
+    Ok(if version >= CapsVersion::V2 {
+        req.with_priority(channel.processor.priority())
+    } else {
+        req
+    })
+}
+
 fn as_svc_msg_with_flag(pdu: DrdynvcServerPdu) -> PduResult<SvcMessage> {
     Ok(SvcMessage::from(pdu).with_flags(ChannelFlags::SHOW_PROTOCOL))
 }
@@ -0,0 +1,89 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::DvcMessage;
+
+/// Timeouts of the dynamic channels watchdog, see [`DrdynvcClient::poll_timeouts`](crate::DrdynvcClient::poll_timeouts)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StallPolicy {
+    /// Time without any message from the peer after which a channel awaiting a response is considered stalled
+    pub timeout: Duration,
+    /// Recoveries attempted through [`DvcProcessor::on_stall`](crate::DvcProcessor::on_stall) before the channel is
+    /// reset
+    pub max_attempts: u32,
+    /// Time without any message exchanged after which [`DvcProcessor::keepalive`](crate::DvcProcessor::keepalive)
+    /// is called, `None` to disable the keepalives
+    pub keepalive_interval: Option<Duration>,
+}
+
+impl Default for StallPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_attempts: 2,
+            keepalive_interval: None,
+        }
+    }
+}
+
+/// How a processor recovers from a stall of its peer
+pub enum StallRecovery {
+    /// Sends the messages again, usually the unanswered request
+    Resend(Vec<DvcMessage>),
+    /// Closes and restarts the processor, which then sends its start messages again
+    Reset,
+}
+
+impl core::fmt::Debug for StallRecovery {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Resend(messages) => f.debug_tuple("Resend").field(&messages.len()).finish(),
+            Self::Reset => f.write_str("Reset"),
+        }
+    }
+}
+
+/// Action taken on a stalled channel
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StallAction {
+    Resent,
+    Reset,
+}
+
+/// Diagnostic of a channel whose peer stopped responding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelStall {
+    pub channel_name: String,
+    pub channel_id: u32,
+    /// Time elapsed since the last message from the peer
+    pub waited: Duration,
+    /// Number of the recovery attempt, starting at 1
+    pub attempt: u32,
+    pub action: StallAction,
+}
+
+/// Timers of a dynamic channel
+#[derive(Debug, Default)]
+pub(crate) struct ChannelWatchdog {
+    /// Time elapsed since the last message from the peer while a response is awaited
+    pub(crate) waited: Duration,
+    /// Time elapsed since the last message exchanged
+    pub(crate) idle: Duration,
+    /// Recoveries attempted since the last message from the peer
+    pub(crate) attempts: u32,
+    /// Stalls detected since the channel was created
+    pub(crate) stalls: u32,
+}
+
+impl ChannelWatchdog {
+    pub(crate) fn on_received(&mut self) {
+        self.waited = Duration::ZERO;
+        self.idle = Duration::ZERO;
+        self.attempts = 0;
+    }
+
+    pub(crate) fn on_sent(&mut self) {
+        self.idle = Duration::ZERO;
+    }
+}
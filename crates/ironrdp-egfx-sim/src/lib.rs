@@ -0,0 +1,225 @@
+#![cfg_attr(doc, doc = include_str!("../README.md"))]
+
+use core::time::Duration;
+use std::collections::VecDeque;
+
+use ironrdp_core::{encode_vec, Decode as _, ReadCursor};
+use ironrdp_dvc::{DvcMessage, DvcProcessor as _};
+use ironrdp_egfx::pdu::{
+    CapabilitiesAdvertisePdu, CapabilitiesV107Flags, CapabilitiesV81Flags, CapabilitySet, FrameAcknowledgePdu, GfxPdu,
+    QueueDepth,
+};
+use ironrdp_egfx::server::GraphicsPipelineServer;
+use ironrdp_graphics::zgfx;
+use ironrdp_pdu::{decode_err, encode_err, PduResult};
+
+/// Behavior of a [`SimulatedClient`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedClientConfig {
+    /// Capability sets advertised to the server
+    pub capabilities: Vec<CapabilitySet>,
+    /// Time between the end of a frame and its acknowledgement
+    pub ack_delay: Duration,
+    /// Share of the frames never acknowledged, in percent
+    pub ack_loss_percent: u8,
+    /// Queue depth reported in the acknowledgements
+    pub queue_depth: QueueDepth,
+    /// Seed of the losses, the same seed losing the same frames
+    pub seed: u64,
+}
+
+impl Default for SimulatedClientConfig {
+    fn default() -> Self {
+        Self {
+            capabilities: vec![
+                CapabilitySet::V10_7 {
+                    flags: CapabilitiesV107Flags::SMALL_CACHE,
+                },
+                CapabilitySet::V8_1 {
+                    flags: CapabilitiesV81Flags::AVC420_ENABLED,
+                },
+            ],
+            ack_delay: Duration::ZERO,
+            ack_loss_percent: 0,
+            queue_depth: QueueDepth::Unavailable,
+            seed: 1,
+        }
+    }
+}
+
+/// Counters of a [`SimulatedClient`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulatedClientStats {
+    /// Frames ended by the server
+    pub frames_received: u32,
+    /// Frames acknowledged to the server
+    pub frames_acked: u32,
+    /// Frames dropped according to [`SimulatedClientConfig::ack_loss_percent`]
+    pub frames_lost: u32,
+    /// Size of the messages received from the server
+    pub bytes_received: u64,
+}
+
+/// Simulated client of the graphics pipeline
+///
+/// The client only implements the capability negotiation and the acknowledgement of the frames, the bitmaps
+/// being ignored. The acknowledgements are delayed and lost as configured, to exercise the flow control of the
+/// server in a test, or in a load test running many clients.
+///
+/// The time is a timestamp from an arbitrary origin passed by the caller, so that a test can simulate a slow
+/// client without waiting.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut client = SimulatedClient::new(SimulatedClientConfig {
+///     ack_delay: Duration::from_millis(50),
+///     ..SimulatedClientConfig::default()
+/// });
+/// client.connect(&mut server)?;
+///
+/// // on each tick
+/// client.exchange(&mut server, now)?;
+/// ```
+pub struct SimulatedClient {
+    config: SimulatedClientConfig,
+    decompressor: zgfx::Decompressor,
+    random: u64,
+    negotiated: Option<CapabilitySet>,
+    /// Acknowledgements waiting for their time, in order
+    pending_acks: VecDeque<(Duration, u32)>,
+    total_frames_decoded: u32,
+    stats: SimulatedClientStats,
+}
+
+impl SimulatedClient {
+    pub fn new(config: SimulatedClientConfig) -> Self {
+        Self {
+            // The xorshift generator is stuck at zero
+            random: config.seed.max(1),
+            config,
+            decompressor: zgfx::Decompressor::new(),
+            negotiated: None,
+            pending_acks: VecDeque::new(),
+            total_frames_decoded: 0,
+            stats: SimulatedClientStats::default(),
+        }
+    }
+
+    /// Capability set confirmed by the server
+    pub fn negotiated_capabilities(&self) -> Option<&CapabilitySet> {
+        self.negotiated.as_ref()
+    }
+
+    /// Number of acknowledgements waiting for their time
+    pub fn pending_acks(&self) -> usize {
+        self.pending_acks.len()
+    }
+
+    pub fn stats(&self) -> SimulatedClientStats {
+        self.stats
+    }
+
+    /// Encoded capabilities advertise PDU, the first message of the client
+    pub fn capabilities_advertise(&self) -> PduResult<Vec<u8>> {
+        let pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(self.config.capabilities.clone()));
+        encode_vec(&pdu).map_err(|e| encode_err!(e))
+    }
+
+    /// Handles the messages sent by the server at `now`
+    pub fn receive(&mut self, messages: &[DvcMessage], now: Duration) -> PduResult<()> {
+        let mut decompressed = Vec::new();
+
+        for message in messages {
+            let payload = encode_vec(message.as_ref()).map_err(|e| encode_err!(e))?;
+            self.stats.bytes_received = self
+                .stats
+                .bytes_received
+                .saturating_add(u64::try_from(payload.len()).unwrap_or(u64::MAX));
+
+            decompressed.clear();
+            self.decompressor
+                .decompress(&payload, &mut decompressed)
+                .map_err(|e| decode_err!(e))?;
+
+            let mut src = ReadCursor::new(&decompressed);
+            while !src.is_empty() {
+                let pdu = GfxPdu::decode(&mut src).map_err(|e| decode_err!(e))?;
+                self.handle_pdu(pdu, now);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the encoded acknowledgements due at `now`
+    pub fn poll_acks(&mut self, now: Duration) -> PduResult<Vec<Vec<u8>>> {
+        let mut acks = Vec::new();
+
+        while let Some(&(due, frame_id)) = self.pending_acks.front() {
+            if due > now {
+                break;
+            }
+            self.pending_acks.pop_front();
+
+            let pdu = GfxPdu::FrameAcknowledge(FrameAcknowledgePdu {
+                queue_depth: self.config.queue_depth,
+                frame_id,
+                total_frames_decoded: self.total_frames_decoded,
+            });
+            acks.push(encode_vec(&pdu).map_err(|e| encode_err!(e))?);
+            self.stats.frames_acked = self.stats.frames_acked.saturating_add(1);
+        }
+
+        Ok(acks)
+    }
+
+    /// Advertises the capabilities to a server, and handles its answer
+    pub fn connect(&mut self, server: &mut GraphicsPipelineServer) -> PduResult<()> {
+        let channel_id = server.channel_id().unwrap_or_default();
+        let messages = server.process(channel_id, &self.capabilities_advertise()?)?;
+        self.receive(&messages, Duration::ZERO)
+    }
+
+    /// Handles the pending messages of a server at `now`, then sends it the acknowledgements due
+    pub fn exchange(&mut self, server: &mut GraphicsPipelineServer, now: Duration) -> PduResult<()> {
+        let channel_id = server.channel_id().unwrap_or_default();
+        self.receive(&server.drain_output(), now)?;
+
+        for ack in self.poll_acks(now)? {
+            let messages = server.process(channel_id, &ack)?;
+            self.receive(&messages, now)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_pdu(&mut self, pdu: GfxPdu, now: Duration) {
+        match pdu {
+            GfxPdu::CapabilitiesConfirm(pdu) => self.negotiated = Some(pdu.0),
+            GfxPdu::EndFrame(pdu) => {
+                self.stats.frames_received = self.stats.frames_received.saturating_add(1);
+                self.total_frames_decoded = self.total_frames_decoded.wrapping_add(1);
+
+                if self.next_percent() < self.config.ack_loss_percent {
+                    self.stats.frames_lost = self.stats.frames_lost.saturating_add(1);
+                } else {
+                    let due = now.saturating_add(self.config.ack_delay);
+                    self.pending_acks.push_back((due, pdu.frame_id));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Pseudo-random number between 0 and 99 (xorshift64)
+    fn next_percent(&mut self) -> u8 {
+        let mut x = self.random;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.random = x;
+
+        u8::try_from(x % 100).expect("less than 100")
+    }
+}
@@ -1,17 +1,29 @@
+use core::time::Duration;
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use bitflags::bitflags;
 use ironrdp_core::{impl_as_any, ReadCursor};
+use ironrdp_dvc::pdu::ChannelPriority;
 use ironrdp_dvc::{DvcClientProcessor, DvcMessage, DvcProcessor};
 use ironrdp_graphics::zgfx;
-use ironrdp_pdu::{decode_cursor, decode_err, PduResult};
-use tracing::trace;
+use ironrdp_pdu::{decode_cursor, decode_err, pdu_other_err, PduResult};
+use tracing::{debug, trace, warn};
 
-use crate::{
-    pdu::{CapabilitiesAdvertisePdu, CapabilitiesV8Flags, CapabilitySet, GfxPdu},
-    CHANNEL_NAME,
+use crate::pdu::{
+    CapabilitiesAdvertisePdu, CapabilitiesV103Flags, CapabilitiesV104Flags, CapabilitiesV107Flags,
+    CapabilitiesV10Flags, CapabilitiesV81Flags, CapabilitiesV8Flags, CapabilitySet, FrameAcknowledgePdu, GfxPdu,
+    QoeFrameAcknowledgePdu, QueueDepth,
 };
+use crate::CHANNEL_NAME;
 
 /// Max capacity to keep for decompressed buffer when cleared.
 const MAX_DECOMPRESSED_BUFFER_CAPACITY: usize = 16384; // 16 KiB
 
+/// Number of cache slots when the small cache is negotiated, 25600 otherwise (2.2.2.2 RDPGFX_SURFACE_TO_CACHE_PDU)
+const SMALL_CACHE_SLOTS: u16 = 4096;
+const CACHE_SLOTS: u16 = 25600;
+
 pub trait GraphicsPipelineHandler: Send {
     fn capabilities(&self) -> Vec<CapabilitySet> {
         vec![CapabilitySet::V8 {
@@ -19,9 +31,206 @@ pub trait GraphicsPipelineHandler: Send {
         }]
     }
 
+    /// Probes the H.264 decoder, hardware or software, used for AVC420/AVC444 streams
+    ///
+    /// When the decoder is known, the AVC capabilities it can't handle are removed from
+    /// [`GraphicsPipelineHandler::capabilities`] before they are advertised, see [`adjust_capabilities`].
+    fn decoder_capabilities(&self) -> Option<DecoderCapabilities> {
+        None
+    }
+
     fn handle_pdu(&mut self, pdu: GfxPdu) {
         trace!(?pdu);
     }
+
+    /// Like [`GraphicsPipelineHandler::handle_pdu`], returns `false` when the content of the PDU couldn't be decoded
+    ///
+    /// The failures are counted in the [`GraphicsStatistics`] of the client.
+    fn decode_pdu(&mut self, pdu: GfxPdu) -> bool {
+        self.handle_pdu(pdu);
+        true
+    }
+}
+
+/// Client-side measurements of the graphics pipeline
+///
+/// The frames are decoded and rendered by the [`GraphicsPipelineHandler`] while it handles the PDUs: the decode time
+/// spans from the start to the end of a frame, and the render time is the time spent handling the end of the frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphicsStatistics {
+    pub frames_decoded: u32,
+    /// PDUs whose content couldn't be decoded by the handler
+    pub decode_errors: u32,
+    /// Bytes received on the channel, before decompression
+    pub bytes_received: u64,
+    /// Decode time of the last frame
+    pub last_decode_time: Option<Duration>,
+    /// Render time of the last frame
+    pub last_render_time: Option<Duration>,
+}
+
+/// What the H.264 decoder of the client supports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecoderCapabilities {
+    /// AVC420 (H.264 4:2:0) streams can be decoded
+    pub avc420: bool,
+    /// AVC444 (H.264 4:4:4 as two 4:2:0 streams) can be decoded
+    pub avc444: bool,
+    /// Highest supported level, as `level_idc` (e.g. 51 for level 5.1)
+    pub max_level: Option<u8>,
+    /// Largest supported frame width, in pixels
+    pub max_width: u16,
+    /// Largest supported frame height, in pixels
+    pub max_height: u16,
+}
+
+impl DecoderCapabilities {
+    /// A decoder which can't decode H.264
+    pub fn unsupported() -> Self {
+        Self {
+            avc420: false,
+            avc444: false,
+            max_level: None,
+            max_width: 0,
+            max_height: 0,
+        }
+    }
+
+    /// Whether frames of the given size can be decoded, according to the maximum resolution and level
+    pub fn supports_resolution(&self, width: u16, height: u16) -> bool {
+        if width > self.max_width || height > self.max_height {
+            return false;
+        }
+
+        let Some(level) = self.max_level else {
+            return true;
+        };
+
+        // H.264 works on 16x16 macroblocks.
+        let width_mbs = u32::from(width).div_ceil(16);
+        let height_mbs = u32::from(height).div_ceil(16);
+        let max_frame_size = max_frame_size_mbs(level);
+
+        // ITU-T H.264 A.3.1, each dimension is also bounded by Sqrt(MaxFS * 8).
+        width_mbs * height_mbs <= max_frame_size
+            && width_mbs * width_mbs <= max_frame_size * 8
+            && height_mbs * height_mbs <= max_frame_size * 8
+    }
+}
+
+/// Maximum frame size, in macroblocks, of an H.264 level (ITU-T H.264 Table A-1)
+fn max_frame_size_mbs(level_idc: u8) -> u32 {
+    match level_idc {
+        0..=11 => 99,
+        12..=20 => 396,
+        21 => 792,
+        22..=30 => 1620,
+        31 => 3600,
+        32 => 5120,
+        33..=41 => 8192,
+        42 => 8704,
+        43..=50 => 22080,
+        51..=52 => 36864,
+        _ => 139264,
+    }
+}
+
+/// Removes the AVC capabilities the decoder can't handle
+///
+/// Servers are free to stream AVC444 with any version 10 capability set which doesn't disable AVC. When only
+/// AVC420 can be decoded, those sets are dropped in favor of a version 8.1 set enabling AVC420, if any, and otherwise
+/// AVC is disabled in them. `desktop_size` is checked against the limits of the decoder when known.
+///
+/// The result is never empty: a version 8 set is advertised when no other set remains.
+pub fn adjust_capabilities(
+    capabilities: Vec<CapabilitySet>,
+    decoder: &DecoderCapabilities,
+    desktop_size: Option<(u16, u16)>,
+) -> Vec<CapabilitySet> {
+    let resolution_supported = desktop_size.is_none_or(|(width, height)| decoder.supports_resolution(width, height));
+    let avc420 = decoder.avc420 && resolution_supported;
+    let avc444 = avc420 && decoder.avc444;
+
+    let keeps_avc420 = avc420
+        && capabilities.iter().any(
+            |cap| matches!(cap, CapabilitySet::V8_1 { flags } if flags.contains(CapabilitiesV81Flags::AVC420_ENABLED)),
+        );
+
+    let mut adjusted: Vec<CapabilitySet> = capabilities
+        .into_iter()
+        .filter_map(|cap| {
+            if avc444 {
+                return Some(cap);
+            }
+
+            match cap {
+                CapabilitySet::V8_1 { flags } if !avc420 => Some(CapabilitySet::V8_1 {
+                    flags: flags.difference(CapabilitiesV81Flags::AVC420_ENABLED),
+                }),
+                CapabilitySet::V8 { .. } | CapabilitySet::V8_1 { .. } | CapabilitySet::Unknown(_) => Some(cap),
+                // Version 10 sets allow AVC444 whenever AVC is enabled.
+                _ if keeps_avc420 => None,
+                CapabilitySet::V10 { flags } => Some(CapabilitySet::V10 {
+                    flags: flags.union(CapabilitiesV10Flags::AVC_DISABLED),
+                }),
+                // No flags, AVC is always enabled.
+                CapabilitySet::V10_1 => None,
+                CapabilitySet::V10_2 { flags } => Some(CapabilitySet::V10_2 {
+                    flags: flags.union(CapabilitiesV10Flags::AVC_DISABLED),
+                }),
+                CapabilitySet::V10_3 { flags } => Some(CapabilitySet::V10_3 {
+                    flags: flags.union(CapabilitiesV103Flags::AVC_DISABLED),
+                }),
+                CapabilitySet::V10_4 { flags } => Some(CapabilitySet::V10_4 {
+                    flags: flags.union(CapabilitiesV104Flags::AVC_DISABLED),
+                }),
+                CapabilitySet::V10_5 { flags } => Some(CapabilitySet::V10_5 {
+                    flags: flags.union(CapabilitiesV104Flags::AVC_DISABLED),
+                }),
+                CapabilitySet::V10_6 { flags } => Some(CapabilitySet::V10_6 {
+                    flags: flags.union(CapabilitiesV104Flags::AVC_DISABLED),
+                }),
+                CapabilitySet::V10_6Err { flags } => Some(CapabilitySet::V10_6Err {
+                    flags: flags.union(CapabilitiesV104Flags::AVC_DISABLED),
+                }),
+                CapabilitySet::V10_7 { flags } => Some(CapabilitySet::V10_7 {
+                    flags: flags.union(CapabilitiesV107Flags::AVC_DISABLED),
+                }),
+            }
+        })
+        .collect();
+
+    if adjusted.is_empty() {
+        adjusted.push(CapabilitySet::V8 {
+            flags: CapabilitiesV8Flags::empty(),
+        });
+    }
+
+    adjusted
+}
+
+bitflags! {
+    /// Deviations from MS-RDPEGFX tolerated by the client
+    ///
+    /// Some servers, such as xrdp and older FreeRDP versions, don't strictly follow the specification.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct GfxQuirks: u32 {
+        /// Frames ending without a matching start frame, or nested frames, are acknowledged anyway
+        const LENIENT_FRAME_IDS = 0x1;
+        /// PDUs using cache slots outside of the negotiated range, or empty slots, are skipped
+        const LENIENT_CACHE = 0x2;
+    }
+}
+
+/// How the client handles servers deviating from MS-RDPEGFX
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuirksMode {
+    /// Any deviation is a protocol error
+    Strict,
+    /// Each quirk is enabled the first time the server needs it
+    Auto,
+    /// The given quirks are always enabled, other deviations are protocol errors
+    Fixed(GfxQuirks),
 }
 
 /// A client for the Graphics Pipeline Virtual Channel.
@@ -29,6 +238,26 @@ pub struct GraphicsPipelineClient {
     handler: Box<dyn GraphicsPipelineHandler>,
     decompressor: zgfx::Decompressor,
     decompressed_buffer: Vec<u8>,
+    desktop_size: Option<(u16, u16)>,
+    quirks_mode: QuirksMode,
+    quirks: GfxQuirks,
+    /// Frame started and not ended yet
+    current_frame: Option<u32>,
+    total_frames_decoded: u32,
+    max_cache_slots: u16,
+    /// Cache slots holding a surface
+    cache_slots: BTreeSet<u16>,
+    /// Whether the QoE frame acknowledgements are sent, when supported by the server
+    qoe_reporting: bool,
+    /// Whether the negotiated version supports the QoE frame acknowledgements
+    qoe_supported: bool,
+    /// Origin of the QoE timestamps
+    epoch: Instant,
+    /// Frame started and when, used for the statistics
+    frame_start: Option<(u32, Instant)>,
+    /// Frame ended and waiting to be rendered by the handler, along with its start and decode time
+    ended_frame: Option<(u32, Instant, Duration)>,
+    statistics: GraphicsStatistics,
 }
 
 impl GraphicsPipelineClient {
@@ -37,8 +266,240 @@ impl GraphicsPipelineClient {
             handler,
             decompressor: zgfx::Decompressor::new(),
             decompressed_buffer: Vec::new(),
+            desktop_size: None,
+            quirks_mode: QuirksMode::Auto,
+            quirks: GfxQuirks::empty(),
+            current_frame: None,
+            total_frames_decoded: 0,
+            max_cache_slots: CACHE_SLOTS,
+            cache_slots: BTreeSet::new(),
+            qoe_reporting: false,
+            qoe_supported: false,
+            epoch: Instant::now(),
+            frame_start: None,
+            ended_frame: None,
+            statistics: GraphicsStatistics::default(),
         }
     }
+
+    /// Sends the decode and render times of the frames to the server, feeding its rate control
+    ///
+    /// The QoE frame acknowledgements are only sent when a version 10 or later capability set is confirmed.
+    #[must_use]
+    pub fn with_qoe_reporting(mut self, enabled: bool) -> Self {
+        self.qoe_reporting = enabled;
+        self
+    }
+
+    pub fn statistics(&self) -> &GraphicsStatistics {
+        &self.statistics
+    }
+
+    /// Sets how deviations from the specification are handled, [`QuirksMode::Auto`] by default
+    #[must_use]
+    pub fn with_quirks_mode(mut self, mode: QuirksMode) -> Self {
+        self.quirks_mode = mode;
+        self.quirks = match mode {
+            QuirksMode::Fixed(quirks) => quirks,
+            QuirksMode::Strict | QuirksMode::Auto => GfxQuirks::empty(),
+        };
+        self
+    }
+
+    /// Quirks currently enabled
+    pub fn quirks(&self) -> GfxQuirks {
+        self.quirks
+    }
+
+    /// Sets the size of the desktop, checked against the limits of the decoder
+    #[must_use]
+    pub fn with_desktop_size(mut self, width: u16, height: u16) -> Self {
+        self.desktop_size = Some((width, height));
+        self
+    }
+
+    /// Capabilities advertised to the server
+    pub fn capabilities(&self) -> Vec<CapabilitySet> {
+        let capabilities = self.handler.capabilities();
+
+        match self.handler.decoder_capabilities() {
+            Some(decoder) => {
+                let adjusted = adjust_capabilities(capabilities, &decoder, self.desktop_size);
+                debug!(?decoder, capabilities = ?adjusted, "Adjusted capabilities to the decoder");
+                adjusted
+            }
+            None => capabilities,
+        }
+    }
+
+    /// Checks whether the server deviation is tolerated, enabling the quirk in auto mode
+    fn tolerate(&mut self, quirk: GfxQuirks, reason: &'static str) -> PduResult<()> {
+        if self.quirks.contains(quirk) {
+            trace!(reason, "Tolerated server quirk");
+            return Ok(());
+        }
+
+        if self.quirks_mode != QuirksMode::Auto {
+            return Err(pdu_other_err!("EGFX", reason));
+        }
+
+        warn!(?quirk, reason, "Server deviates from MS-RDPEGFX, enabling quirk");
+        self.quirks.insert(quirk);
+
+        Ok(())
+    }
+
+    fn check_cache_slot(&mut self, cache_slot: u16, needs_surface: bool) -> PduResult<bool> {
+        if cache_slot == 0 || cache_slot > self.max_cache_slots {
+            self.tolerate(GfxQuirks::LENIENT_CACHE, "cache slot out of range")?;
+            return Ok(false);
+        }
+
+        if needs_surface && !self.cache_slots.contains(&cache_slot) {
+            self.tolerate(GfxQuirks::LENIENT_CACHE, "empty cache slot")?;
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    fn handle_pdus(&mut self, buffer: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let mut cursor = ReadCursor::new(buffer);
+        let mut messages = Vec::new();
+
+        while !cursor.is_empty() {
+            let pdu = decode_cursor(&mut cursor).map_err(|e| decode_err!(e))?;
+            if !self.track(&pdu, &mut messages)? {
+                continue;
+            }
+
+            let handling_start = Instant::now();
+            if !self.handler.decode_pdu(pdu) {
+                self.statistics.decode_errors = self.statistics.decode_errors.saturating_add(1);
+            }
+
+            if let Some((frame_id, frame_start, decode_time)) = self.ended_frame.take() {
+                self.frame_rendered(
+                    frame_id,
+                    frame_start,
+                    decode_time,
+                    handling_start.elapsed(),
+                    &mut messages,
+                );
+            }
+        }
+
+        Ok(messages)
+    }
+
+    fn frame_rendered(
+        &mut self,
+        frame_id: u32,
+        frame_start: Instant,
+        decode_time: Duration,
+        render_time: Duration,
+        messages: &mut Vec<DvcMessage>,
+    ) {
+        self.statistics.last_decode_time = Some(decode_time);
+        self.statistics.last_render_time = Some(render_time);
+
+        if !(self.qoe_reporting && self.qoe_supported) {
+            return;
+        }
+
+        let millis = |duration: Duration| u16::try_from(duration.as_millis()).unwrap_or(u16::MAX);
+
+        // The timestamps wrap around after about 49 days.
+        let timestamp = frame_start.saturating_duration_since(self.epoch).as_millis() % (u128::from(u32::MAX) + 1);
+
+        messages.push(Box::new(GfxPdu::QoeFrameAcknowledge(QoeFrameAcknowledgePdu {
+            frame_id,
+            timestamp: u32::try_from(timestamp).expect("wrapped around"),
+            time_diff_se: millis(decode_time),
+            time_diff_dr: millis(render_time),
+        })));
+    }
+
+    /// Tracks the frames and the cache, returns whether the PDU is passed to the handler
+    fn track(&mut self, pdu: &GfxPdu, messages: &mut Vec<DvcMessage>) -> PduResult<bool> {
+        match pdu {
+            GfxPdu::CapabilitiesConfirm(pdu) => {
+                let small_cache = match &pdu.0 {
+                    CapabilitySet::V8 { flags } => flags.contains(CapabilitiesV8Flags::SMALL_CACHE),
+                    CapabilitySet::V8_1 { flags } => flags.contains(CapabilitiesV81Flags::SMALL_CACHE),
+                    CapabilitySet::V10 { flags } | CapabilitySet::V10_2 { flags } => {
+                        flags.contains(CapabilitiesV10Flags::SMALL_CACHE)
+                    }
+                    CapabilitySet::V10_4 { flags }
+                    | CapabilitySet::V10_5 { flags }
+                    | CapabilitySet::V10_6 { flags }
+                    | CapabilitySet::V10_6Err { flags } => flags.contains(CapabilitiesV104Flags::SMALL_CACHE),
+                    CapabilitySet::V10_7 { flags } => flags.contains(CapabilitiesV107Flags::SMALL_CACHE),
+                    CapabilitySet::V10_1 | CapabilitySet::V10_3 { .. } | CapabilitySet::Unknown(_) => false,
+                };
+                self.max_cache_slots = if small_cache { SMALL_CACHE_SLOTS } else { CACHE_SLOTS };
+                self.qoe_supported = !matches!(
+                    pdu.0,
+                    CapabilitySet::V8 { .. } | CapabilitySet::V8_1 { .. } | CapabilitySet::Unknown(_)
+                );
+            }
+            GfxPdu::ResetGraphics(_) => {
+                self.current_frame = None;
+                self.frame_start = None;
+            }
+            GfxPdu::StartFrame(pdu) => {
+                if self.current_frame.is_some() {
+                    self.tolerate(
+                        GfxQuirks::LENIENT_FRAME_IDS,
+                        "frame started before the previous one ended",
+                    )?;
+                }
+                self.current_frame = Some(pdu.frame_id);
+                self.frame_start = Some((pdu.frame_id, Instant::now()));
+            }
+            GfxPdu::EndFrame(pdu) => {
+                if self.current_frame.take() != Some(pdu.frame_id) {
+                    self.tolerate(GfxQuirks::LENIENT_FRAME_IDS, "frame ended without a matching start")?;
+                }
+
+                // Frames ending without a start are acknowledged, but not measured.
+                if let Some((_, frame_start)) = self.frame_start.take().filter(|(id, _)| *id == pdu.frame_id) {
+                    self.ended_frame = Some((pdu.frame_id, frame_start, frame_start.elapsed()));
+                }
+
+                self.total_frames_decoded = self.total_frames_decoded.wrapping_add(1);
+                self.statistics.frames_decoded = self.total_frames_decoded;
+                messages.push(Box::new(GfxPdu::FrameAcknowledge(FrameAcknowledgePdu {
+                    queue_depth: QueueDepth::Unavailable,
+                    frame_id: pdu.frame_id,
+                    total_frames_decoded: self.total_frames_decoded,
+                })));
+            }
+            GfxPdu::SurfaceToCache(pdu) => {
+                if !self.check_cache_slot(pdu.cache_slot, false)? {
+                    return Ok(false);
+                }
+                self.cache_slots.insert(pdu.cache_slot);
+            }
+            GfxPdu::CacheToSurface(pdu) => return self.check_cache_slot(pdu.cache_slot, true),
+            GfxPdu::EvictCacheEntry(pdu) => {
+                if !self.check_cache_slot(pdu.cache_slot, true)? {
+                    return Ok(false);
+                }
+                self.cache_slots.remove(&pdu.cache_slot);
+            }
+            GfxPdu::CacheImportReply(pdu) => {
+                for cache_slot in &pdu.cache_slots {
+                    if self.check_cache_slot(*cache_slot, false)? {
+                        self.cache_slots.insert(*cache_slot);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(true)
+    }
 }
 
 impl_as_any!(GraphicsPipelineClient);
@@ -49,25 +510,33 @@ impl DvcProcessor for GraphicsPipelineClient {
     }
 
     fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
-        let pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(self.handler.capabilities()));
+        let pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(self.capabilities()));
 
         Ok(vec![Box::new(pdu)])
     }
 
+    fn priority(&self) -> ChannelPriority {
+        ChannelPriority::High
+    }
+
     fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        self.statistics.bytes_received = self
+            .statistics
+            .bytes_received
+            .saturating_add(u64::try_from(payload.len()).unwrap_or(u64::MAX));
+
         self.decompressed_buffer.clear();
         self.decompressed_buffer.shrink_to(MAX_DECOMPRESSED_BUFFER_CAPACITY);
         self.decompressor
             .decompress(payload, &mut self.decompressed_buffer)
             .map_err(|e| decode_err!(e))?;
 
-        let mut cursor = ReadCursor::new(self.decompressed_buffer.as_slice());
-        while !cursor.is_empty() {
-            let pdu = decode_cursor(&mut cursor).map_err(|e| decode_err!(e))?;
-            self.handler.handle_pdu(pdu);
-        }
+        // The buffer is given back once the PDUs are handled, which needs a mutable borrow of the client.
+        let buffer = core::mem::take(&mut self.decompressed_buffer);
+        let result = self.handle_pdus(&buffer);
+        self.decompressed_buffer = buffer;
 
-        Ok(vec![])
+        result
     }
 }
 
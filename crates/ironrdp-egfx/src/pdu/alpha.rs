@@ -0,0 +1,56 @@
+/// Signature of the alpha codec bitmaps ("AL")
+const ALPHA_SIGNATURE: u16 = 0x414C;
+
+/// Create an alpha codec bitmap (RDPGFX_CODECID_ALPHA) from the alpha values of a rectangle
+///
+/// The values are the alpha channel of the pixels of the destination rectangle, row by row. Only the alpha
+/// channel of a surface using [`PixelFormat::ARgb`](super::PixelFormat::ARgb) is updated, its color channels being
+/// sent with another codec.
+///
+/// The values are run-length encoded, unless the encoded data would be larger than the raw values.
+///
+/// See [MS-RDPEGFX] 2.2.4.3 ALPHA_CODEC_BITMAP.
+#[must_use]
+pub fn encode_alpha_codec_bitmap(alpha: &[u8]) -> Vec<u8> {
+    let compressed = encode_alpha_segments(alpha);
+    let (flag, data) = if compressed.len() < alpha.len() {
+        (1u16, compressed.as_slice())
+    } else {
+        (0u16, alpha)
+    };
+
+    let mut buf = Vec::with_capacity(4 + data.len());
+    buf.extend_from_slice(&ALPHA_SIGNATURE.to_le_bytes());
+    buf.extend_from_slice(&flag.to_le_bytes());
+    buf.extend_from_slice(data);
+    buf
+}
+
+/// Encodes the values as CLEARCODEC_ALPHA_RLE_SEGMENT structures
+fn encode_alpha_segments(alpha: &[u8]) -> Vec<u8> {
+    let max_run = usize::try_from(u32::MAX).unwrap_or(usize::MAX);
+    let mut buf = Vec::new();
+    let mut rest = alpha;
+
+    while let Some(&value) = rest.first() {
+        let run = rest.iter().take(max_run).take_while(|&&v| v == value).count();
+        rest = &rest[run..];
+
+        buf.push(value);
+        match (u8::try_from(run), u16::try_from(run), u32::try_from(run)) {
+            (Ok(run), _, _) if run < 0xFF => buf.push(run),
+            (_, Ok(run), _) if run < 0xFFFF => {
+                buf.push(0xFF);
+                buf.extend_from_slice(&run.to_le_bytes());
+            }
+            (_, _, Ok(run)) => {
+                buf.push(0xFF);
+                buf.extend_from_slice(&0xFFFFu16.to_le_bytes());
+                buf.extend_from_slice(&run.to_le_bytes());
+            }
+            (_, _, Err(_)) => unreachable!("runs are at most u32::MAX long"),
+        }
+    }
+
+    buf
+}
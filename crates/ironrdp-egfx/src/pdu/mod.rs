@@ -11,6 +11,7 @@
 //! - [`annex_b_to_avc`] - Convert H.264 Annex B to AVC format
 //! - [`align_to_16`] - Align dimensions to H.264 macroblock boundaries
 //! - [`encode_avc420_bitmap_stream`] - Create AVC420 bitmap streams
+//! - [`encode_alpha_codec_bitmap`] - Create alpha codec bitmaps for ARGB surfaces
 //!
 //! [1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpegfx/da5c75f9-cd99-450c-98c4-014a496942b0
 
@@ -22,3 +23,6 @@ pub use cmd::*;
 
 mod avc;
 pub use avc::*;
+
+mod alpha;
+pub use alpha::*;
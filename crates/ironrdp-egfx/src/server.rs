@@ -38,41 +38,52 @@
 //! # Usage
 //!
 //! ```ignore
-//! use ironrdp_egfx::server::{GraphicsPipelineServer, GraphicsPipelineHandler};
+//! use ironrdp_egfx::server::{GfxContext, GraphicsPipelineServer, GraphicsPipelineHandler};
 //!
 //! struct MyHandler;
 //!
 //! impl GraphicsPipelineHandler for MyHandler {
-//!     fn capabilities_advertise(&mut self, caps: &CapabilitiesAdvertisePdu) {
+//!     fn capabilities_advertise(&mut self, caps: &CapabilitiesAdvertisePdu, _ctx: &mut GfxContext) {
 //!         // Client sent capabilities
 //!     }
 //!
-//!     fn on_ready(&mut self, negotiated: &CapabilitySet) {
+//!     fn on_ready(&mut self, negotiated: &CapabilitySet, ctx: &mut GfxContext) {
 //!         // Server is ready to send frames
+//!         let surface_id = ctx.create_surface(1920, 1080).expect("ready");
+//!         ctx.map_surface_to_output(surface_id, 0, 0);
+//!     }
+//!
+//!     fn on_frame_ack(&mut self, _frame_id: u32, _queue_depth: u32, ctx: &mut GfxContext) {
+//!         // Room for another frame: ctx.send_avc420_frame(...)
 //!     }
 //! }
 //!
 //! let server = GraphicsPipelineServer::new(Box::new(MyHandler));
 //! ```
 
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
 use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Instant;
 
 use ironrdp_core::{decode, impl_as_any, Encode, EncodeResult, WriteCursor};
+use ironrdp_dvc::pdu::ChannelPriority;
 use ironrdp_dvc::{DvcEncode, DvcMessage, DvcProcessor, DvcServerProcessor};
-use ironrdp_graphics::zgfx::{self, CompressionMode, Compressor};
-use ironrdp_pdu::gcc::Monitor;
-use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_graphics::zgfx::{self, CompressionLevel, CompressionMode, Compressor};
+use ironrdp_pdu::gcc::{Monitor, MonitorFlags};
+use ironrdp_pdu::geometry::{InclusiveRectangle, Rectangle as _};
 use ironrdp_pdu::{decode_err, PduResult};
 use tracing::{debug, trace, warn};
 
 use crate::pdu::{
-    encode_avc420_bitmap_stream, Avc420BitmapStream, Avc420Region, Avc444BitmapStream, CacheImportOfferPdu,
-    CacheImportReplyPdu, CapabilitiesAdvertisePdu, CapabilitiesConfirmPdu, CapabilitiesV103Flags,
+    encode_alpha_codec_bitmap, encode_avc420_bitmap_stream, Avc420BitmapStream, Avc420Region, Avc444BitmapStream,
+    CacheImportOfferPdu, CacheImportReplyPdu, CapabilitiesAdvertisePdu, CapabilitiesConfirmPdu, CapabilitiesV103Flags,
     CapabilitiesV104Flags, CapabilitiesV107Flags, CapabilitiesV10Flags, CapabilitiesV81Flags, CapabilitiesV8Flags,
     CapabilitySet, Codec1Type, CreateSurfacePdu, DeleteSurfacePdu, Encoding, EndFramePdu, FrameAcknowledgePdu, GfxPdu,
-    MapSurfaceToOutputPdu, PixelFormat, QoeFrameAcknowledgePdu, ResetGraphicsPdu, StartFramePdu, Timestamp,
-    WireToSurface1Pdu,
+    MapSurfaceToOutputPdu, MapSurfaceToScaledOutputPdu, MapSurfaceToWindowPdu, PixelFormat, QoeFrameAcknowledgePdu,
+    ResetGraphicsPdu, StartFramePdu, Timestamp, WireToSurface1Pdu,
 };
 use crate::CHANNEL_NAME;
 
@@ -273,6 +284,67 @@ pub struct QoeMetrics {
     pub time_diff_dr: u16,
 }
 
+/// Whether new frames can be sent, shared with the [`ReadyToSend`] futures
+#[derive(Debug, Clone)]
+struct ReadySignal(Arc<Mutex<ReadyState>>);
+
+#[derive(Debug)]
+struct ReadyState {
+    ready: bool,
+    wakers: Vec<Waker>,
+}
+
+impl ReadySignal {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(ReadyState {
+            ready: true,
+            wakers: Vec::new(),
+        })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ReadyState> {
+        self.0.lock().expect("ready signal mutex poisoned")
+    }
+
+    fn set(&self, ready: bool) {
+        let wakers = {
+            let mut state = self.lock();
+            state.ready = ready;
+            if !ready {
+                return;
+            }
+            core::mem::take(&mut state.wakers)
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Future resolving once new frames can be sent, see [`GraphicsPipelineServer::ready_to_send`]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless awaited"]
+pub struct ReadyToSend {
+    signal: ReadySignal,
+}
+
+impl Future for ReadyToSend {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.signal.lock();
+        if state.ready {
+            return Poll::Ready(());
+        }
+
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
 /// Frame tracking for flow control
 ///
 /// Implements the "Unacknowledged Frames ADM element" from MS-RDPEGFX.
@@ -292,6 +364,8 @@ pub struct FrameTracker {
     total_sent: u64,
     /// Total frames acknowledged
     total_acked: u64,
+    /// Notified when backpressure starts or stops
+    ready: ReadySignal,
 }
 
 impl Default for FrameTracker {
@@ -311,12 +385,14 @@ impl FrameTracker {
             max_in_flight: DEFAULT_MAX_FRAMES_IN_FLIGHT,
             total_sent: 0,
             total_acked: 0,
+            ready: ReadySignal::new(),
         }
     }
 
     /// Set maximum frames in flight
     pub fn set_max_in_flight(&mut self, max: u32) {
         self.max_in_flight = max;
+        self.update_ready();
     }
 
     /// Allocate a new frame ID and track it
@@ -335,6 +411,7 @@ impl FrameTracker {
         );
 
         self.total_sent += 1;
+        self.update_ready();
         frame_id
     }
 
@@ -361,6 +438,7 @@ impl FrameTracker {
         if info.is_some() {
             self.total_acked += 1;
         }
+        self.update_ready();
         info
     }
 
@@ -404,6 +482,18 @@ impl FrameTracker {
         self.unacknowledged.clear();
         self.client_queue_depth = 0;
         self.ack_suspended = false;
+        self.update_ready();
+    }
+
+    /// Returns a future resolving once backpressure stops, right away if it is not active
+    pub fn ready_to_send(&self) -> ReadyToSend {
+        ReadyToSend {
+            signal: self.ready.clone(),
+        }
+    }
+
+    fn update_ready(&self) {
+        self.ready.set(!self.should_backpressure());
     }
 }
 
@@ -521,6 +611,151 @@ fn negotiate_capabilities(client_caps: &[CapabilitySet], server_caps: &[Capabili
     None
 }
 
+// ============================================================================
+// Monitor Layout
+// ============================================================================
+
+/// Maximum number of monitors in a ResetGraphics PDU
+const MAX_MONITORS: usize = 16;
+
+/// Maximum width and height of the graphics output buffer
+const MAX_OUTPUT_SIZE: u16 = 32_766;
+
+/// A monitor covered by the graphics output buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputMonitor {
+    /// Position of the top-left corner in the virtual desktop
+    pub left: i32,
+    pub top: i32,
+    pub width: u16,
+    pub height: u16,
+    pub is_primary: bool,
+}
+
+impl OutputMonitor {
+    fn right(&self) -> i64 {
+        i64::from(self.left) + i64::from(self.width)
+    }
+
+    fn bottom(&self) -> i64 {
+        i64::from(self.top) + i64::from(self.height)
+    }
+
+    fn to_gcc_monitor(self) -> Monitor {
+        // The right and bottom edges of a monitor definition are inclusive
+        let inclusive = |start: i32, len: u16| start.saturating_add(i32::from(len)).saturating_sub(1);
+
+        Monitor {
+            left: self.left,
+            top: self.top,
+            right: inclusive(self.left, self.width),
+            bottom: inclusive(self.top, self.height),
+            flags: if self.is_primary {
+                MonitorFlags::PRIMARY
+            } else {
+                MonitorFlags::empty()
+            },
+        }
+    }
+}
+
+/// Layout of the monitors covered by the graphics output buffer
+///
+/// The graphics output buffer is the rectangle bounding all the monitors. Its origin is the
+/// top-left corner of that rectangle, so monitors with negative coordinates map to positive
+/// output origins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorLayout {
+    monitors: Vec<OutputMonitor>,
+    left: i32,
+    top: i32,
+    width: u16,
+    height: u16,
+}
+
+impl MonitorLayout {
+    /// Create a layout from a list of monitors
+    ///
+    /// Returns `None` if there are no monitors or more than 16, if there isn't exactly one primary
+    /// monitor, if a monitor is empty, or if the bounding rectangle exceeds 32766x32766.
+    #[must_use]
+    pub fn new(monitors: Vec<OutputMonitor>) -> Option<Self> {
+        if monitors.is_empty() || monitors.len() > MAX_MONITORS {
+            return None;
+        }
+
+        if monitors.iter().filter(|m| m.is_primary).count() != 1 {
+            return None;
+        }
+
+        if monitors.iter().any(|m| m.width == 0 || m.height == 0) {
+            return None;
+        }
+
+        let left = monitors.iter().map(|m| m.left).min()?;
+        let top = monitors.iter().map(|m| m.top).min()?;
+        let right = monitors.iter().map(OutputMonitor::right).max()?;
+        let bottom = monitors.iter().map(OutputMonitor::bottom).max()?;
+
+        let width = u16::try_from(right - i64::from(left)).ok()?;
+        let height = u16::try_from(bottom - i64::from(top)).ok()?;
+
+        if width > MAX_OUTPUT_SIZE || height > MAX_OUTPUT_SIZE {
+            return None;
+        }
+
+        Some(Self {
+            monitors,
+            left,
+            top,
+            width,
+            height,
+        })
+    }
+
+    /// Create a layout with a single primary monitor covering the whole output
+    #[must_use]
+    pub fn single(width: u16, height: u16) -> Option<Self> {
+        Self::new(vec![OutputMonitor {
+            left: 0,
+            top: 0,
+            width,
+            height,
+            is_primary: true,
+        }])
+    }
+
+    /// Get the monitors, in the order they were given
+    #[must_use]
+    pub fn monitors(&self) -> &[OutputMonitor] {
+        &self.monitors
+    }
+
+    /// Get the size of the graphics output buffer covering all the monitors
+    #[must_use]
+    pub fn output_size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Get the position of a monitor in the graphics output buffer
+    ///
+    /// Returns `None` if there is no monitor at this index.
+    #[must_use]
+    pub fn output_origin(&self, monitor_index: usize) -> Option<(u32, u32)> {
+        let monitor = self.monitors.get(monitor_index)?;
+
+        // Monitors are within the bounding rectangle, the offsets are positive and fit in a u16
+        let x = u32::try_from(i64::from(monitor.left) - i64::from(self.left)).ok()?;
+        let y = u32::try_from(i64::from(monitor.top) - i64::from(self.top)).ok()?;
+
+        Some((x, y))
+    }
+
+    fn gcc_monitors(&self) -> Vec<Monitor> {
+        self.monitors.iter().map(|m| m.to_gcc_monitor()).collect()
+    }
+}
+
 // ============================================================================
 // Handler Trait
 // ============================================================================
@@ -529,18 +764,36 @@ fn negotiate_capabilities(client_caps: &[CapabilitySet], server_caps: &[Capabili
 ///
 /// Implement this trait to receive callbacks when the EGFX channel state changes
 /// or when client messages are received.
+///
+/// Callbacks receive the [`GfxContext`] of the server, which can be used to create
+/// surfaces, queue frames or resize right away. PDUs queued from a callback are sent
+/// along with the response to the client message.
 pub trait GraphicsPipelineHandler: Send {
     /// Called when the client advertises its capabilities
     ///
     /// This is informational - the server will automatically negotiate
     /// based on [`preferred_capabilities()`](Self::preferred_capabilities).
-    fn capabilities_advertise(&mut self, pdu: &CapabilitiesAdvertisePdu);
+    fn capabilities_advertise(&mut self, pdu: &CapabilitiesAdvertisePdu, ctx: &mut GfxContext);
+
+    /// Called once the capability set is selected, before [`on_ready`](Self::on_ready)
+    ///
+    /// `client_caps` are all the capability sets advertised by the client, e.g. to configure the encoders from the
+    /// highest version the client supports rather than the selected one.
+    fn on_capabilities_negotiated(
+        &mut self,
+        _caps: &CapabilitySet,
+        _client_caps: &[CapabilitySet],
+        _ctx: &mut GfxContext,
+    ) {
+    }
 
     /// Called when the EGFX channel is ready to send frames
     ///
     /// At this point, capability negotiation is complete.
     /// The handler should create surfaces and start sending frames.
-    fn on_ready(&mut self, negotiated: &CapabilitySet);
+    ///
+    /// Also called when the channel is reopened, the surfaces created before being already restored.
+    fn on_ready(&mut self, negotiated: &CapabilitySet, ctx: &mut GfxContext);
 
     /// Called when a frame has been acknowledged by the client
     ///
@@ -548,19 +801,24 @@ pub trait GraphicsPipelineHandler: Send {
     ///
     /// * `frame_id` - The acknowledged frame
     /// * `queue_depth` - Client's reported queue depth (bytes buffered)
-    fn on_frame_ack(&mut self, _frame_id: u32, _queue_depth: u32) {}
+    /// * `ctx` - Server context, e.g.: to send the next frame
+    fn on_frame_ack(&mut self, _frame_id: u32, _queue_depth: u32, _ctx: &mut GfxContext) {}
 
     /// Called when QoE metrics are received from client (V10+)
-    fn on_qoe_metrics(&mut self, _metrics: QoeMetrics) {}
+    fn on_qoe_metrics(&mut self, _metrics: QoeMetrics, _ctx: &mut GfxContext) {}
 
     /// Called when a surface is created
-    fn on_surface_created(&mut self, _surface: &Surface) {}
+    fn on_surface_created(&mut self, _surface: &Surface, _ctx: &mut GfxContext) {}
 
     /// Called when a surface is deleted
-    fn on_surface_deleted(&mut self, _surface_id: u16) {}
+    fn on_surface_deleted(&mut self, _surface_id: u16, _ctx: &mut GfxContext) {}
 
     /// Called when the EGFX channel is closed
-    fn on_close(&mut self) {}
+    ///
+    /// The configuration and the surfaces are kept: when the channel is reopened, e.g. by a client recreating it
+    /// on resize, the capabilities are negotiated again and the surfaces are restored on the client before
+    /// [`on_ready`](Self::on_ready) is called, so that their IDs remain valid.
+    fn on_close(&mut self, _ctx: &mut GfxContext) {}
 
     /// Returns the server's preferred capabilities
     ///
@@ -596,7 +854,7 @@ pub trait GraphicsPipelineHandler: Send {
     ///
     /// Return the list of cache slot IDs to accept.
     /// Default rejects all (returns empty).
-    fn on_cache_import_offer(&mut self, _offer: &CacheImportOfferPdu) -> Vec<u16> {
+    fn on_cache_import_offer(&mut self, _offer: &CacheImportOfferPdu, _ctx: &mut GfxContext) -> Vec<u16> {
         vec![]
     }
 }
@@ -618,24 +876,35 @@ enum ServerState {
     Closed,
 }
 
+/// Surface lifecycle notification, delivered to the handler once the current operation completes
+#[derive(Debug)]
+enum SurfaceEvent {
+    Created(Surface),
+    Deleted(u16),
+}
+
 // ============================================================================
-// Graphics Pipeline Server
+// Graphics Pipeline Context
 // ============================================================================
 
-/// Server for the Graphics Pipeline Virtual Channel (EGFX)
+/// Protocol state of the Graphics Pipeline Virtual Channel
 ///
-/// This server handles capability negotiation, surface management,
-/// and H.264 frame transmission to RDP clients per MS-RDPEGFX specification.
-pub struct GraphicsPipelineServer {
-    handler: Box<dyn GraphicsPipelineHandler>,
-
+/// The context owns the negotiated capabilities, the surfaces, the frame tracking and the
+/// queue of PDUs to be sent. It is passed to every [`GraphicsPipelineHandler`] callback, and
+/// is otherwise reachable through [`GraphicsPipelineServer::context_mut`].
+#[derive(Debug)]
+pub struct GfxContext {
     // State management
     state: ServerState,
     negotiated_caps: Option<CapabilitySet>,
+    client_caps: Vec<CapabilitySet>,
     codec_caps: CodecCapabilities,
 
     // Surface management (Offscreen Surfaces ADM element)
     surfaces: SurfaceManager,
+    // Last mapping PDU of each surface, replayed when the channel is reopened
+    surface_mappings: HashMap<u16, GfxPdu>,
+    surface_events: VecDeque<SurfaceEvent>,
 
     // Frame tracking (Unacknowledged Frames ADM element)
     frames: FrameTracker,
@@ -644,6 +913,12 @@ pub struct GraphicsPipelineServer {
     output_width: u16,
     output_height: u16,
 
+    // Monitors announced in ResetGraphics, if configured
+    monitor_layout: Option<MonitorLayout>,
+
+    // Pixel format of the surfaces created by create_surface()
+    default_pixel_format: PixelFormat,
+
     // Whether ResetGraphics has been sent
     // Per MS-RDPEGFX, must be sent before any CreateSurface
     reset_graphics_sent: bool,
@@ -654,69 +929,32 @@ pub struct GraphicsPipelineServer {
     // DVC channel ID assigned by DRDYNVC
     // Set when start() is called, needed for encode_dvc_messages()
     channel_id: Option<u32>,
-
-    // ZGFX compression
-    zgfx_compressor: Compressor,
-    compression_mode: CompressionMode,
 }
 
-impl GraphicsPipelineServer {
-    /// Create a new GraphicsPipelineServer
-    pub fn new(handler: Box<dyn GraphicsPipelineHandler>) -> Self {
-        // Use Never mode - H.264 video is already compressed by the codec
-        // Attempting ZGFX compression on H.264 provides no benefit and wastes CPU
-        //
-        // Never mode:
-        // - Just wraps in ZGFX segment structure (2-byte overhead)
-        // - <1µs processing time per PDU
-        // - No hash table maintenance
-        // - Production-ready and stable
-        //
-        // Note: Auto/Always modes available via with_compression() if needed
-        Self::with_compression(handler, CompressionMode::Never)
-    }
-
-    /// Create a new GraphicsPipelineServer with specified compression mode
-    ///
-    /// # Arguments
-    ///
-    /// * `handler` - Handler for server callbacks
-    /// * `compression_mode` - ZGFX compression mode (Never/Auto/Always)
-    pub fn with_compression(handler: Box<dyn GraphicsPipelineHandler>, compression_mode: CompressionMode) -> Self {
-        let max_frames = handler.max_frames_in_flight();
+impl GfxContext {
+    fn new(max_frames_in_flight: u32) -> Self {
         let mut frames = FrameTracker::new();
-        frames.set_max_in_flight(max_frames);
+        frames.set_max_in_flight(max_frames_in_flight);
 
         Self {
-            handler,
             state: ServerState::WaitingForCapabilities,
             negotiated_caps: None,
+            client_caps: Vec::new(),
             codec_caps: CodecCapabilities::default(),
             surfaces: SurfaceManager::new(),
+            surface_mappings: HashMap::new(),
+            surface_events: VecDeque::new(),
             frames,
             output_width: 0,
             output_height: 0,
+            monitor_layout: None,
+            default_pixel_format: PixelFormat::XRgb,
             reset_graphics_sent: false,
             output_queue: VecDeque::new(),
             channel_id: None,
-            zgfx_compressor: Compressor::new(),
-            compression_mode,
         }
     }
 
-    /// Set ZGFX compression mode
-    ///
-    /// This can be called at any time to change compression behavior.
-    pub fn set_compression_mode(&mut self, mode: CompressionMode) {
-        self.compression_mode = mode;
-        debug!("ZGFX compression mode set to: {:?}", mode);
-    }
-
-    /// Get current compression mode
-    pub fn compression_mode(&self) -> CompressionMode {
-        self.compression_mode
-    }
-
     /// Set the desktop output dimensions for ResetGraphics
     ///
     /// Call this BEFORE create_surface() to control the desktop size announced
@@ -732,6 +970,25 @@ impl GraphicsPipelineServer {
         debug!(width, height, "Output dimensions configured for ResetGraphics");
     }
 
+    /// Set the monitor layout for ResetGraphics
+    ///
+    /// Call this BEFORE create_surface() to announce multiple monitors to the client. The output
+    /// dimensions are set to the size of the rectangle bounding all the monitors.
+    ///
+    /// Use [`GfxContext::resize_with_layout`] to change the layout once surfaces exist.
+    pub fn set_monitor_layout(&mut self, layout: MonitorLayout) {
+        let (width, height) = layout.output_size();
+        self.output_width = width;
+        self.output_height = height;
+        debug!(
+            width,
+            height,
+            monitors = layout.monitors().len(),
+            "Monitor layout configured for ResetGraphics"
+        );
+        self.monitor_layout = Some(layout);
+    }
+
     /// Get the DVC channel ID assigned to this EGFX channel
     ///
     /// Returns `None` if the channel hasn't been started yet.
@@ -757,6 +1014,14 @@ impl GraphicsPipelineServer {
         self.negotiated_caps.as_ref()
     }
 
+    /// Get the capability sets advertised by the client
+    ///
+    /// Empty until the client advertises its capabilities.
+    #[must_use]
+    pub fn client_capabilities(&self) -> &[CapabilitySet] {
+        &self.client_caps
+    }
+
     /// Get codec capabilities determined from negotiation
     #[must_use]
     pub fn codec_capabilities(&self) -> &CodecCapabilities {
@@ -781,16 +1046,36 @@ impl GraphicsPipelineServer {
         (self.output_width, self.output_height)
     }
 
+    /// Get the monitor layout announced to the client, if any
+    #[must_use]
+    pub fn monitor_layout(&self) -> Option<&MonitorLayout> {
+        self.monitor_layout.as_ref()
+    }
+
     // ========================================================================
     // Surface Management
     // ========================================================================
 
+    /// Set the pixel format of the surfaces created by [`GfxContext::create_surface`]
+    ///
+    /// [`PixelFormat::XRgb`] by default. The surfaces using [`PixelFormat::ARgb`] keep an alpha channel, updated
+    /// with [`GfxContext::send_alpha`], e.g. for the windows of the remote applications.
+    pub fn set_default_pixel_format(&mut self, pixel_format: PixelFormat) {
+        self.default_pixel_format = pixel_format;
+    }
+
+    /// Get the pixel format of the surfaces created by [`GfxContext::create_surface`]
+    #[must_use]
+    pub fn default_pixel_format(&self) -> PixelFormat {
+        self.default_pixel_format
+    }
+
     /// Create a new surface
     ///
     /// Queues CreateSurface PDU and returns the surface ID.
     /// Returns `None` if not ready.
     pub fn create_surface(&mut self, width: u16, height: u16) -> Option<u16> {
-        self.create_surface_with_format(width, height, PixelFormat::XRgb)
+        self.create_surface_with_format(width, height, self.default_pixel_format)
     }
 
     /// Create a new surface with specific pixel format
@@ -804,20 +1089,38 @@ impl GraphicsPipelineServer {
         // CRITICAL: Use output_width/output_height if already set (from manual call)
         // Otherwise use surface dimensions
         if !self.reset_graphics_sent {
-            let desktop_width = if self.output_width > 0 { self.output_width } else { width };
-            let desktop_height = if self.output_height > 0 { self.output_height } else { height };
+            let desktop_width = if self.output_width > 0 {
+                self.output_width
+            } else {
+                width
+            };
+            let desktop_height = if self.output_height > 0 {
+                self.output_height
+            } else {
+                height
+            };
+            let monitors = self
+                .monitor_layout
+                .as_ref()
+                .map(MonitorLayout::gcc_monitors)
+                .unwrap_or_default();
 
             self.output_queue.push_back(GfxPdu::ResetGraphics(ResetGraphicsPdu {
                 width: u32::from(desktop_width),
                 height: u32::from(desktop_height),
-                monitors: Vec::new(),
+                monitors,
             }));
 
             self.output_width = desktop_width;
             self.output_height = desktop_height;
             self.reset_graphics_sent = true;
-            debug!(desktop_width, desktop_height, surface_width=width, surface_height=height,
-                   "Sent ResetGraphics before first surface");
+            debug!(
+                desktop_width,
+                desktop_height,
+                surface_width = width,
+                surface_height = height,
+                "Sent ResetGraphics before first surface"
+            );
         }
 
         let surface_id = self.surfaces.allocate_id();
@@ -831,7 +1134,7 @@ impl GraphicsPipelineServer {
             pixel_format,
         }));
 
-        self.handler.on_surface_created(&surface);
+        self.surface_events.push_back(SurfaceEvent::Created(surface.clone()));
         self.surfaces.insert(surface);
 
         debug!(surface_id, width, height, ?pixel_format, "Created surface");
@@ -845,12 +1148,13 @@ impl GraphicsPipelineServer {
         if self.surfaces.remove(surface_id).is_none() {
             return false;
         }
+        self.surface_mappings.remove(&surface_id);
 
         // Queue DeleteSurface PDU
         self.output_queue
             .push_back(GfxPdu::DeleteSurface(DeleteSurfacePdu { surface_id }));
 
-        self.handler.on_surface_deleted(surface_id);
+        self.surface_events.push_back(SurfaceEvent::Deleted(surface_id));
         debug!(surface_id, "Deleted surface");
         true
     }
@@ -865,69 +1169,256 @@ impl GraphicsPipelineServer {
         surface.output_origin_x = origin_x;
         surface.output_origin_y = origin_y;
 
-        self.output_queue
-            .push_back(GfxPdu::MapSurfaceToOutput(MapSurfaceToOutputPdu {
+        self.queue_mapping(
+            surface_id,
+            GfxPdu::MapSurfaceToOutput(MapSurfaceToOutputPdu {
                 surface_id,
                 output_origin_x: origin_x,
                 output_origin_y: origin_y,
-            }));
+            }),
+        );
 
         debug!(surface_id, origin_x, origin_y, "Mapped surface to output");
         true
     }
 
-    /// Get a surface by ID
-    #[must_use]
-    pub fn get_surface(&self, surface_id: u16) -> Option<&Surface> {
-        self.surfaces.get(surface_id)
-    }
+    /// Map a surface to the origin of a monitor of the current [`MonitorLayout`]
+    ///
+    /// Returns `false` if the surface doesn't exist, or if there is no monitor at this index.
+    pub fn map_surface_to_monitor(&mut self, surface_id: u16, monitor_index: usize) -> bool {
+        let Some((origin_x, origin_y)) = self
+            .monitor_layout
+            .as_ref()
+            .and_then(|layout| layout.output_origin(monitor_index))
+        else {
+            debug!(surface_id, monitor_index, "Cannot map surface: unknown monitor");
+            return false;
+        };
 
-    /// Get all surface IDs
-    pub fn surface_ids(&self) -> impl Iterator<Item = u16> + '_ {
-        self.surfaces.surface_ids()
+        self.map_surface_to_output(surface_id, origin_x, origin_y)
     }
 
-    // ========================================================================
-    // Resize Handling
-    // ========================================================================
-
-    /// Resize the graphics output buffer
-    ///
-    /// This initiates a resize sequence:
-    /// 1. Sends ResetGraphics with new dimensions
-    /// 2. Deletes existing surfaces
-    /// 3. Transitions to Ready state
+    /// Map a surface to the graphics output buffer, scaled by the client to a target size
     ///
-    /// After calling this, create new surfaces for the new dimensions.
-    pub fn resize(&mut self, width: u16, height: u16) {
-        self.resize_with_monitors(width, height, Vec::new());
-    }
+    /// Useful when the surface is rendered at a different resolution than the output, e.g. with
+    /// a client using a high DPI scale factor.
+    pub fn map_surface_to_scaled_output(
+        &mut self,
+        surface_id: u16,
+        origin_x: u32,
+        origin_y: u32,
+        target_width: u32,
+        target_height: u32,
+    ) -> bool {
+        let Some(surface) = self.surfaces.get_mut(surface_id) else {
+            return false;
+        };
 
-    /// Resize with explicit monitor configuration
-    pub fn resize_with_monitors(&mut self, width: u16, height: u16, monitors: Vec<Monitor>) {
-        if self.state != ServerState::Ready {
-            debug!("Cannot resize: not in Ready state");
-            return;
-        }
+        surface.is_mapped = true;
+        surface.output_origin_x = origin_x;
+        surface.output_origin_y = origin_y;
 
-        debug!(width, height, monitors = monitors.len(), "Initiating resize");
+        self.queue_mapping(
+            surface_id,
+            GfxPdu::MapSurfaceToScaledOutput(MapSurfaceToScaledOutputPdu {
+                surface_id,
+                output_origin_x: origin_x,
+                output_origin_y: origin_y,
+                target_width,
+                target_height,
+            }),
+        );
 
-        self.state = ServerState::Resizing;
-        self.output_width = width;
-        self.output_height = height;
+        debug!(
+            surface_id,
+            origin_x, origin_y, target_width, target_height, "Mapped surface to scaled output"
+        );
+        true
+    }
 
-        // Delete all existing surfaces
-        let surface_ids: Vec<_> = self.surfaces.surface_ids().collect();
-        for id in surface_ids {
-            self.delete_surface(id);
-        }
+    /// Map a surface to a monitor of the current [`MonitorLayout`], scaled by the client to the monitor size
+    ///
+    /// Returns `false` if the surface doesn't exist, or if there is no monitor at this index.
+    pub fn map_surface_to_scaled_monitor(&mut self, surface_id: u16, monitor_index: usize) -> bool {
+        let Some(((origin_x, origin_y), monitor)) = self.monitor_layout.as_ref().and_then(|layout| {
+            layout
+                .output_origin(monitor_index)
+                .zip(layout.monitors().get(monitor_index))
+        }) else {
+            debug!(surface_id, monitor_index, "Cannot map surface: unknown monitor");
+            return false;
+        };
 
-        // Clear frame tracking
-        self.frames.clear();
+        let (target_width, target_height) = (u32::from(monitor.width), u32::from(monitor.height));
+        self.map_surface_to_scaled_output(surface_id, origin_x, origin_y, target_width, target_height)
+    }
 
-        // Send ResetGraphics
-        self.output_queue.push_back(GfxPdu::ResetGraphics(ResetGraphicsPdu {
-            width: u32::from(width),
+    /// Create a surface with an alpha channel for a window of the remote applications (RAIL)
+    ///
+    /// The surface uses [`PixelFormat::ARgb`] and is mapped to the window at its size, see
+    /// [`GfxContext::map_surface_to_window`]. Its transparency is updated with [`GfxContext::send_alpha`].
+    /// Returns `None` if not ready.
+    pub fn create_window_surface(&mut self, window_id: u64, width: u16, height: u16) -> Option<u16> {
+        let surface_id = self.create_surface_with_format(width, height, PixelFormat::ARgb)?;
+        self.map_surface_to_window(surface_id, window_id, u32::from(width), u32::from(height));
+        Some(surface_id)
+    }
+
+    /// Map a surface to a window of the remote applications (RAIL), scaled by the client to the mapped size
+    ///
+    /// The window ID is the one of the windowing orders.
+    pub fn map_surface_to_window(
+        &mut self,
+        surface_id: u16,
+        window_id: u64,
+        mapped_width: u32,
+        mapped_height: u32,
+    ) -> bool {
+        let Some(surface) = self.surfaces.get_mut(surface_id) else {
+            return false;
+        };
+
+        surface.is_mapped = true;
+
+        self.queue_mapping(
+            surface_id,
+            GfxPdu::MapSurfaceToWindow(MapSurfaceToWindowPdu {
+                surface_id,
+                window_id,
+                mapped_width,
+                mapped_height,
+            }),
+        );
+
+        debug!(
+            surface_id,
+            window_id, mapped_width, mapped_height, "Mapped surface to window"
+        );
+        true
+    }
+
+    fn queue_mapping(&mut self, surface_id: u16, pdu: GfxPdu) {
+        self.surface_mappings.insert(surface_id, pdu.clone());
+        self.output_queue.push_back(pdu);
+    }
+
+    /// Queue the setup of the graphics output and of the existing surfaces, once the channel is reopened
+    ///
+    /// The client drops its surfaces when the channel is closed, while the embedder keeps using their IDs.
+    fn replay_surfaces(&mut self) {
+        let mut surfaces: Vec<_> = self
+            .surfaces
+            .surface_ids()
+            .filter_map(|id| self.surfaces.get(id))
+            .collect();
+        surfaces.sort_by_key(|surface| surface.id);
+
+        let monitors = self
+            .monitor_layout
+            .as_ref()
+            .map(MonitorLayout::gcc_monitors)
+            .unwrap_or_default();
+        self.output_queue.push_back(GfxPdu::ResetGraphics(ResetGraphicsPdu {
+            width: u32::from(self.output_width),
+            height: u32::from(self.output_height),
+            monitors,
+        }));
+
+        for surface in surfaces {
+            self.output_queue.push_back(GfxPdu::CreateSurface(CreateSurfacePdu {
+                surface_id: surface.id,
+                width: surface.width,
+                height: surface.height,
+                pixel_format: surface.pixel_format,
+            }));
+
+            if let Some(mapping) = self.surface_mappings.get(&surface.id) {
+                self.output_queue.push_back(mapping.clone());
+            }
+        }
+
+        self.reset_graphics_sent = true;
+        debug!(
+            surfaces = self.surfaces.len(),
+            "Restored surfaces on the reopened channel"
+        );
+    }
+
+    /// Get a surface by ID
+    #[must_use]
+    pub fn get_surface(&self, surface_id: u16) -> Option<&Surface> {
+        self.surfaces.get(surface_id)
+    }
+
+    /// Get all surface IDs
+    pub fn surface_ids(&self) -> impl Iterator<Item = u16> + '_ {
+        self.surfaces.surface_ids()
+    }
+
+    // ========================================================================
+    // Resize Handling
+    // ========================================================================
+
+    /// Resize the graphics output buffer
+    ///
+    /// This initiates a resize sequence:
+    /// 1. Sends ResetGraphics with new dimensions
+    /// 2. Deletes existing surfaces
+    /// 3. Transitions to Ready state
+    ///
+    /// After calling this, create new surfaces for the new dimensions.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.resize_with_monitors(width, height, Vec::new());
+    }
+
+    /// Resize with explicit monitor configuration
+    ///
+    /// This clears the current [`MonitorLayout`], prefer [`GfxContext::resize_with_layout`].
+    pub fn resize_with_monitors(&mut self, width: u16, height: u16, monitors: Vec<Monitor>) {
+        if self.state != ServerState::Ready {
+            debug!("Cannot resize: not in Ready state");
+            return;
+        }
+
+        self.monitor_layout = None;
+        self.reset_graphics(width, height, monitors);
+    }
+
+    /// Resize the graphics output buffer to a new monitor layout
+    ///
+    /// Like [`GfxContext::resize`], with the monitor definitions of the layout. Once the new
+    /// surfaces are created, map them with [`GfxContext::map_surface_to_monitor`].
+    pub fn resize_with_layout(&mut self, layout: MonitorLayout) {
+        if self.state != ServerState::Ready {
+            debug!("Cannot resize: not in Ready state");
+            return;
+        }
+
+        let (width, height) = layout.output_size();
+        let monitors = layout.gcc_monitors();
+        self.monitor_layout = Some(layout);
+        self.reset_graphics(width, height, monitors);
+    }
+
+    fn reset_graphics(&mut self, width: u16, height: u16, monitors: Vec<Monitor>) {
+        debug!(width, height, monitors = monitors.len(), "Initiating resize");
+
+        self.state = ServerState::Resizing;
+        self.output_width = width;
+        self.output_height = height;
+
+        // Delete all existing surfaces
+        let surface_ids: Vec<_> = self.surfaces.surface_ids().collect();
+        for id in surface_ids {
+            self.delete_surface(id);
+        }
+
+        // Clear frame tracking
+        self.frames.clear();
+
+        // Send ResetGraphics
+        self.output_queue.push_back(GfxPdu::ResetGraphics(ResetGraphicsPdu {
+            width: u32::from(width),
             height: u32::from(height),
             monitors,
         }));
@@ -969,6 +1460,21 @@ impl GraphicsPipelineServer {
         self.frames.set_max_in_flight(max);
     }
 
+    /// Returns a future resolving once backpressure stops, right away if it is not active
+    ///
+    /// The future doesn't borrow the context, so that a capture loop sharing the server behind a mutex can await
+    /// the acknowledgement of the frames in flight without holding the lock, instead of polling
+    /// [`GfxContext::should_backpressure`].
+    pub fn ready_to_send(&self) -> ReadyToSend {
+        self.frames.ready_to_send()
+    }
+
+    /// Get the frame tracker, e.g.: for the total frames sent and acknowledged
+    #[must_use]
+    pub fn frame_stats(&self) -> &FrameTracker {
+        &self.frames
+    }
+
     // ========================================================================
     // Frame Sending
     // ========================================================================
@@ -1065,8 +1571,13 @@ impl GraphicsPipelineServer {
         for (i, region) in regions.iter().enumerate() {
             trace!(
                 "Region[{}]: left={}, top={}, right={}, bottom={}, qp={}, quality={}",
-                i, region.left, region.top, region.right, region.bottom,
-                region.quantization_parameter, region.quality
+                i,
+                region.left,
+                region.top,
+                region.right,
+                region.bottom,
+                region.quantization_parameter,
+                region.quality
             );
         }
 
@@ -1078,8 +1589,12 @@ impl GraphicsPipelineServer {
 
         trace!(
             "DestRect: left={}, top={}, right={}, bottom={} | BitmapStream: {} bytes | H264: {} bytes",
-            dest_rect.left, dest_rect.top, dest_rect.right, dest_rect.bottom,
-            bitmap_data.len(), h264_data.len()
+            dest_rect.left,
+            dest_rect.top,
+            dest_rect.right,
+            dest_rect.bottom,
+            bitmap_data.len(),
+            h264_data.len()
         );
 
         // Queue the frame PDUs
@@ -1100,6 +1615,69 @@ impl GraphicsPipelineServer {
         Some(frame_id)
     }
 
+    /// Queue an update of the alpha channel of a surface for transmission
+    ///
+    /// The values are the alpha channel of the pixels of `dest_rect`, row by row, the color channels being sent
+    /// with the other codecs. See [`encode_alpha_codec_bitmap`].
+    ///
+    /// # Returns
+    ///
+    /// `Some(frame_id)` if the frame was queued, `None` if backpressure is active, server is not ready, the
+    /// surface does not use [`PixelFormat::ARgb`], or the number of values does not match the rectangle.
+    pub fn send_alpha(
+        &mut self,
+        surface_id: u16,
+        dest_rect: InclusiveRectangle,
+        alpha: &[u8],
+        timestamp_ms: u32,
+    ) -> Option<u32> {
+        if !self.is_ready() {
+            debug!("EGFX not ready, dropping alpha update");
+            return None;
+        }
+
+        if self.should_backpressure() {
+            trace!(frames_in_flight = self.frames.in_flight(), "EGFX backpressure active");
+            return None;
+        }
+
+        let Some(surface) = self.surfaces.get(surface_id) else {
+            debug!(surface_id, "Surface not found, dropping alpha update");
+            return None;
+        };
+
+        if surface.pixel_format != PixelFormat::ARgb {
+            debug!(surface_id, "Surface has no alpha channel, dropping alpha update");
+            return None;
+        }
+
+        let width = usize::from(dest_rect.width());
+        let height = usize::from(dest_rect.height());
+        if width.checked_mul(height) != Some(alpha.len()) {
+            debug!(surface_id, len = alpha.len(), "Alpha values do not match the rectangle");
+            return None;
+        }
+
+        let timestamp = Self::make_timestamp(timestamp_ms);
+        let frame_id = self.frames.begin_frame(timestamp);
+
+        self.output_queue
+            .push_back(GfxPdu::StartFrame(StartFramePdu { timestamp, frame_id }));
+
+        self.output_queue.push_back(GfxPdu::WireToSurface1(WireToSurface1Pdu {
+            surface_id,
+            codec_id: Codec1Type::Alpha,
+            pixel_format: PixelFormat::ARgb,
+            destination_rectangle: dest_rect,
+            bitmap_data: encode_alpha_codec_bitmap(alpha),
+        }));
+
+        self.output_queue.push_back(GfxPdu::EndFrame(EndFramePdu { frame_id }));
+
+        trace!(frame_id, surface_id, "Queued alpha update");
+        Some(frame_id)
+    }
+
     /// Queue an H.264 AVC444 frame for transmission
     ///
     /// AVC444 uses two streams: one for luma (Y) and one for chroma (UV).
@@ -1206,6 +1784,380 @@ impl GraphicsPipelineServer {
         Some(frame_id)
     }
 
+    /// Check if there are pending PDUs to send
+    #[must_use]
+    pub fn has_pending_output(&self) -> bool {
+        !self.output_queue.is_empty()
+    }
+}
+
+// ============================================================================
+// Graphics Pipeline Server
+// ============================================================================
+
+/// Server for the Graphics Pipeline Virtual Channel (EGFX)
+///
+/// This server handles capability negotiation, surface management,
+/// and H.264 frame transmission to RDP clients per MS-RDPEGFX specification.
+///
+/// The protocol state lives in a [`GfxContext`], shared with the handler callbacks.
+/// The methods of the context are also available on the server itself.
+pub struct GraphicsPipelineServer {
+    handler: Box<dyn GraphicsPipelineHandler>,
+    ctx: GfxContext,
+
+    // Overrides the handler's preferred capabilities
+    preferred_capabilities: Option<Vec<CapabilitySet>>,
+
+    // ZGFX compression
+    zgfx_compressor: Compressor,
+    compression_mode: CompressionMode,
+}
+
+impl GraphicsPipelineServer {
+    /// Create a new GraphicsPipelineServer
+    pub fn new(handler: Box<dyn GraphicsPipelineHandler>) -> Self {
+        // Use Never mode - H.264 video is already compressed by the codec
+        // Attempting ZGFX compression on H.264 provides no benefit and wastes CPU
+        //
+        // Never mode:
+        // - Just wraps in ZGFX segment structure (2-byte overhead)
+        // - <1µs processing time per PDU
+        // - No hash table maintenance
+        // - Production-ready and stable
+        //
+        // Note: Auto/Always modes available via with_compression() if needed
+        Self::with_compression(handler, CompressionMode::Never)
+    }
+
+    /// Create a new GraphicsPipelineServer with specified compression mode
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Handler for server callbacks
+    /// * `compression_mode` - ZGFX compression mode (Never/Auto/Always)
+    pub fn with_compression(handler: Box<dyn GraphicsPipelineHandler>, compression_mode: CompressionMode) -> Self {
+        let ctx = GfxContext::new(handler.max_frames_in_flight());
+
+        Self {
+            handler,
+            ctx,
+            preferred_capabilities: None,
+            zgfx_compressor: Compressor::new(),
+            compression_mode,
+        }
+    }
+
+    /// Get the protocol state shared with the handler
+    #[must_use]
+    pub fn context(&self) -> &GfxContext {
+        &self.ctx
+    }
+
+    /// Get the protocol state shared with the handler
+    ///
+    /// Surface notifications caused by operations on the returned context are delivered
+    /// to the handler on the next call to [`drain_output()`](Self::drain_output).
+    pub fn context_mut(&mut self) -> &mut GfxContext {
+        &mut self.ctx
+    }
+
+    /// Set the capabilities offered during negotiation, in preference order
+    ///
+    /// Overrides [`GraphicsPipelineHandler::preferred_capabilities`] for this server.
+    pub fn set_preferred_capabilities(&mut self, capabilities: Vec<CapabilitySet>) {
+        self.preferred_capabilities = Some(capabilities);
+    }
+
+    /// Set ZGFX compression mode
+    ///
+    /// This can be called at any time to change compression behavior.
+    pub fn set_compression_mode(&mut self, mode: CompressionMode) {
+        self.compression_mode = mode;
+        debug!("ZGFX compression mode set to: {:?}", mode);
+    }
+
+    /// Get current compression mode
+    pub fn compression_mode(&self) -> CompressionMode {
+        self.compression_mode
+    }
+
+    /// Set ZGFX compression level
+    ///
+    /// Only relevant for Auto/Always modes. The compressor history is kept,
+    /// so this can be called at any time.
+    pub fn set_compression_level(&mut self, level: CompressionLevel) {
+        self.zgfx_compressor.set_level(level);
+        debug!("ZGFX compression level set to: {:?}", level);
+    }
+
+    /// Get current compression level
+    pub fn compression_level(&self) -> CompressionLevel {
+        self.zgfx_compressor.level()
+    }
+
+    /// See [`GfxContext::set_output_dimensions`]
+    pub fn set_output_dimensions(&mut self, width: u16, height: u16) {
+        self.ctx.set_output_dimensions(width, height);
+    }
+
+    /// See [`GfxContext::set_monitor_layout`]
+    pub fn set_monitor_layout(&mut self, layout: MonitorLayout) {
+        self.ctx.set_monitor_layout(layout);
+    }
+
+    /// See [`GfxContext::channel_id`]
+    #[must_use]
+    pub fn channel_id(&self) -> Option<u32> {
+        self.ctx.channel_id()
+    }
+
+    // ========================================================================
+    // State Queries
+    // ========================================================================
+
+    /// See [`GfxContext::is_ready`]
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        self.ctx.is_ready()
+    }
+
+    /// See [`GfxContext::negotiated_capabilities`]
+    #[must_use]
+    pub fn negotiated_capabilities(&self) -> Option<&CapabilitySet> {
+        self.ctx.negotiated_capabilities()
+    }
+
+    /// See [`GfxContext::client_capabilities`]
+    #[must_use]
+    pub fn client_capabilities(&self) -> &[CapabilitySet] {
+        self.ctx.client_capabilities()
+    }
+
+    /// See [`GfxContext::codec_capabilities`]
+    #[must_use]
+    pub fn codec_capabilities(&self) -> &CodecCapabilities {
+        self.ctx.codec_capabilities()
+    }
+
+    /// See [`GfxContext::supports_avc420`]
+    #[must_use]
+    pub fn supports_avc420(&self) -> bool {
+        self.ctx.supports_avc420()
+    }
+
+    /// See [`GfxContext::supports_avc444`]
+    #[must_use]
+    pub fn supports_avc444(&self) -> bool {
+        self.ctx.supports_avc444()
+    }
+
+    /// See [`GfxContext::output_dimensions`]
+    #[must_use]
+    pub fn output_dimensions(&self) -> (u16, u16) {
+        self.ctx.output_dimensions()
+    }
+
+    /// See [`GfxContext::monitor_layout`]
+    #[must_use]
+    pub fn monitor_layout(&self) -> Option<&MonitorLayout> {
+        self.ctx.monitor_layout()
+    }
+
+    // ========================================================================
+    // Surface Management
+    // ========================================================================
+
+    /// See [`GfxContext::set_default_pixel_format`]
+    pub fn set_default_pixel_format(&mut self, pixel_format: PixelFormat) {
+        self.ctx.set_default_pixel_format(pixel_format);
+    }
+
+    /// See [`GfxContext::default_pixel_format`]
+    #[must_use]
+    pub fn default_pixel_format(&self) -> PixelFormat {
+        self.ctx.default_pixel_format()
+    }
+
+    /// See [`GfxContext::create_surface`]
+    pub fn create_surface(&mut self, width: u16, height: u16) -> Option<u16> {
+        let surface_id = self.ctx.create_surface(width, height);
+        self.dispatch_surface_events();
+        surface_id
+    }
+
+    /// See [`GfxContext::create_surface_with_format`]
+    pub fn create_surface_with_format(&mut self, width: u16, height: u16, pixel_format: PixelFormat) -> Option<u16> {
+        let surface_id = self.ctx.create_surface_with_format(width, height, pixel_format);
+        self.dispatch_surface_events();
+        surface_id
+    }
+
+    /// See [`GfxContext::create_window_surface`]
+    pub fn create_window_surface(&mut self, window_id: u64, width: u16, height: u16) -> Option<u16> {
+        let surface_id = self.ctx.create_window_surface(window_id, width, height);
+        self.dispatch_surface_events();
+        surface_id
+    }
+
+    /// See [`GfxContext::delete_surface`]
+    pub fn delete_surface(&mut self, surface_id: u16) -> bool {
+        let deleted = self.ctx.delete_surface(surface_id);
+        self.dispatch_surface_events();
+        deleted
+    }
+
+    /// See [`GfxContext::map_surface_to_output`]
+    pub fn map_surface_to_output(&mut self, surface_id: u16, origin_x: u32, origin_y: u32) -> bool {
+        self.ctx.map_surface_to_output(surface_id, origin_x, origin_y)
+    }
+
+    /// See [`GfxContext::map_surface_to_monitor`]
+    pub fn map_surface_to_monitor(&mut self, surface_id: u16, monitor_index: usize) -> bool {
+        self.ctx.map_surface_to_monitor(surface_id, monitor_index)
+    }
+
+    /// See [`GfxContext::map_surface_to_scaled_output`]
+    pub fn map_surface_to_scaled_output(
+        &mut self,
+        surface_id: u16,
+        origin_x: u32,
+        origin_y: u32,
+        target_width: u32,
+        target_height: u32,
+    ) -> bool {
+        self.ctx
+            .map_surface_to_scaled_output(surface_id, origin_x, origin_y, target_width, target_height)
+    }
+
+    /// See [`GfxContext::map_surface_to_scaled_monitor`]
+    pub fn map_surface_to_scaled_monitor(&mut self, surface_id: u16, monitor_index: usize) -> bool {
+        self.ctx.map_surface_to_scaled_monitor(surface_id, monitor_index)
+    }
+
+    /// See [`GfxContext::map_surface_to_window`]
+    pub fn map_surface_to_window(
+        &mut self,
+        surface_id: u16,
+        window_id: u64,
+        mapped_width: u32,
+        mapped_height: u32,
+    ) -> bool {
+        self.ctx
+            .map_surface_to_window(surface_id, window_id, mapped_width, mapped_height)
+    }
+
+    /// See [`GfxContext::get_surface`]
+    #[must_use]
+    pub fn get_surface(&self, surface_id: u16) -> Option<&Surface> {
+        self.ctx.get_surface(surface_id)
+    }
+
+    /// See [`GfxContext::surface_ids`]
+    pub fn surface_ids(&self) -> impl Iterator<Item = u16> + '_ {
+        self.ctx.surface_ids()
+    }
+
+    // ========================================================================
+    // Resize Handling
+    // ========================================================================
+
+    /// See [`GfxContext::resize`]
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.ctx.resize(width, height);
+        self.dispatch_surface_events();
+    }
+
+    /// See [`GfxContext::resize_with_monitors`]
+    pub fn resize_with_monitors(&mut self, width: u16, height: u16, monitors: Vec<Monitor>) {
+        self.ctx.resize_with_monitors(width, height, monitors);
+        self.dispatch_surface_events();
+    }
+
+    /// See [`GfxContext::resize_with_layout`]
+    pub fn resize_with_layout(&mut self, layout: MonitorLayout) {
+        self.ctx.resize_with_layout(layout);
+        self.dispatch_surface_events();
+    }
+
+    // ========================================================================
+    // Flow Control
+    // ========================================================================
+
+    /// See [`GfxContext::should_backpressure`]
+    #[must_use]
+    pub fn should_backpressure(&self) -> bool {
+        self.ctx.should_backpressure()
+    }
+
+    /// See [`GfxContext::frames_in_flight`]
+    #[must_use]
+    pub fn frames_in_flight(&self) -> u32 {
+        self.ctx.frames_in_flight()
+    }
+
+    /// See [`GfxContext::ready_to_send`]
+    pub fn ready_to_send(&self) -> ReadyToSend {
+        self.ctx.ready_to_send()
+    }
+
+    /// See [`GfxContext::client_queue_depth`]
+    #[must_use]
+    pub fn client_queue_depth(&self) -> u32 {
+        self.ctx.client_queue_depth()
+    }
+
+    /// See [`GfxContext::set_max_frames_in_flight`]
+    pub fn set_max_frames_in_flight(&mut self, max: u32) {
+        self.ctx.set_max_frames_in_flight(max);
+    }
+
+    // ========================================================================
+    // Frame Sending
+    // ========================================================================
+
+    /// See [`GfxContext::send_avc420_frame`]
+    pub fn send_avc420_frame(
+        &mut self,
+        surface_id: u16,
+        h264_data: &[u8],
+        regions: &[Avc420Region],
+        timestamp_ms: u32,
+    ) -> Option<u32> {
+        self.ctx.send_avc420_frame(surface_id, h264_data, regions, timestamp_ms)
+    }
+
+    /// See [`GfxContext::send_alpha`]
+    pub fn send_alpha(
+        &mut self,
+        surface_id: u16,
+        dest_rect: InclusiveRectangle,
+        alpha: &[u8],
+        timestamp_ms: u32,
+    ) -> Option<u32> {
+        self.ctx.send_alpha(surface_id, dest_rect, alpha, timestamp_ms)
+    }
+
+    /// See [`GfxContext::send_avc444_frame`]
+    pub fn send_avc444_frame(
+        &mut self,
+        surface_id: u16,
+        luma_data: &[u8],
+        luma_regions: &[Avc420Region],
+        chroma_data: Option<&[u8]>,
+        chroma_regions: Option<&[Avc420Region]>,
+        timestamp_ms: u32,
+    ) -> Option<u32> {
+        self.ctx.send_avc444_frame(
+            surface_id,
+            luma_data,
+            luma_regions,
+            chroma_data,
+            chroma_regions,
+            timestamp_ms,
+        )
+    }
+
     // ========================================================================
     // Output Management
     // ========================================================================
@@ -1222,17 +2174,27 @@ impl GraphicsPipelineServer {
     /// 3. Wrapped in ZGFX segment structure
     ///
     /// This ensures Windows clients can properly decode the PDUs.
+    ///
+    /// Pending surface notifications are delivered to the handler first, so that the PDUs it
+    /// queues in response are sent as well.
     #[expect(clippy::as_conversions, reason = "Box<T> to Box<dyn Trait> coercion")]
     pub fn drain_output(&mut self) -> Vec<DvcMessage> {
+        self.dispatch_surface_events();
+
         let compression_mode = self.compression_mode;
 
-        let messages: Vec<DvcMessage> = self.output_queue
+        let messages: Vec<DvcMessage> = self
+            .ctx
+            .output_queue
             .drain(..)
             .map(|pdu| {
                 // Get PDU name for logging
                 let pdu_name = match &pdu {
                     GfxPdu::CapabilitiesConfirm(caps) => {
-                        debug!("Draining CapabilitiesConfirm: {:?} (ZGFX mode: {:?})", caps.0, compression_mode);
+                        debug!(
+                            "Draining CapabilitiesConfirm: {:?} (ZGFX mode: {:?})",
+                            caps.0, compression_mode
+                        );
                         "CapabilitiesConfirm"
                     }
                     GfxPdu::ResetGraphics(_) => {
@@ -1240,15 +2202,25 @@ impl GraphicsPipelineServer {
                         "ResetGraphics"
                     }
                     GfxPdu::CreateSurface(p) => {
-                        debug!("Draining CreateSurface: id={}, {}x{} (ZGFX mode: {:?})", p.surface_id, p.width, p.height, compression_mode);
+                        debug!(
+                            "Draining CreateSurface: id={}, {}x{} (ZGFX mode: {:?})",
+                            p.surface_id, p.width, p.height, compression_mode
+                        );
                         "CreateSurface"
                     }
                     GfxPdu::MapSurfaceToOutput(p) => {
-                        debug!("Draining MapSurfaceToOutput: id={} (ZGFX mode: {:?})", p.surface_id, compression_mode);
+                        debug!(
+                            "Draining MapSurfaceToOutput: id={} (ZGFX mode: {:?})",
+                            p.surface_id, compression_mode
+                        );
                         "MapSurfaceToOutput"
                     }
                     GfxPdu::StartFrame(p) => {
-                        trace!("Draining StartFrame: id={} (ZGFX mode: {:?})", p.frame_id, compression_mode);
+                        trace!(
+                            "Draining StartFrame: id={} (ZGFX mode: {:?})",
+                            p.frame_id,
+                            compression_mode
+                        );
                         "StartFrame"
                     }
                     GfxPdu::WireToSurface1(_) => {
@@ -1256,7 +2228,11 @@ impl GraphicsPipelineServer {
                         "WireToSurface1"
                     }
                     GfxPdu::EndFrame(p) => {
-                        trace!("Draining EndFrame: id={} (ZGFX mode: {:?})", p.frame_id, compression_mode);
+                        trace!(
+                            "Draining EndFrame: id={} (ZGFX mode: {:?})",
+                            p.frame_id,
+                            compression_mode
+                        );
                         "EndFrame"
                     }
                     _ => {
@@ -1272,13 +2248,14 @@ impl GraphicsPipelineServer {
                 pdu.encode(&mut gfx_cursor).expect("GfxPdu encode should not fail");
 
                 // Compress and wrap with ZGFX (with performance timing)
-                debug!("🗜️  ZGFX input: {} bytes, mode: {:?}, PDU: {}", gfx_size, compression_mode, pdu_name);
+                debug!(
+                    "🗜️  ZGFX input: {} bytes, mode: {:?}, PDU: {}",
+                    gfx_size, compression_mode, pdu_name
+                );
                 let start = Instant::now();
-                let zgfx_wrapped = zgfx::compress_and_wrap_egfx(
-                    &gfx_bytes,
-                    &mut self.zgfx_compressor,
-                    compression_mode,
-                ).expect("ZGFX compression should not fail");
+                let zgfx_wrapped =
+                    zgfx::compress_and_wrap_egfx(&gfx_bytes, &mut self.zgfx_compressor, compression_mode)
+                        .expect("ZGFX compression should not fail");
                 let duration = start.elapsed();
 
                 // Log compression effectiveness and performance
@@ -1317,25 +2294,40 @@ impl GraphicsPipelineServer {
         messages
     }
 
-    /// Check if there are pending PDUs to send
+    /// See [`GfxContext::has_pending_output`]
     #[must_use]
     pub fn has_pending_output(&self) -> bool {
-        !self.output_queue.is_empty()
+        self.ctx.has_pending_output()
     }
 
     // ========================================================================
     // Internal Message Handlers
     // ========================================================================
 
+    /// Deliver the pending surface notifications to the handler
+    ///
+    /// Surfaces created or deleted by the handler itself are notified as well.
+    fn dispatch_surface_events(&mut self) {
+        while let Some(event) = self.ctx.surface_events.pop_front() {
+            match event {
+                SurfaceEvent::Created(surface) => self.handler.on_surface_created(&surface, &mut self.ctx),
+                SurfaceEvent::Deleted(surface_id) => self.handler.on_surface_deleted(surface_id, &mut self.ctx),
+            }
+        }
+    }
+
     /// Handle capability negotiation
     fn handle_capabilities_advertise(&mut self, pdu: CapabilitiesAdvertisePdu) {
         debug!(?pdu, "Received CapabilitiesAdvertise");
 
         // Notify handler
-        self.handler.capabilities_advertise(&pdu);
+        self.handler.capabilities_advertise(&pdu, &mut self.ctx);
 
         // Get server's preferred capabilities
-        let server_caps = self.handler.preferred_capabilities();
+        let server_caps = self
+            .preferred_capabilities
+            .clone()
+            .unwrap_or_else(|| self.handler.preferred_capabilities());
 
         // Negotiate best match
         let negotiated = negotiate_capabilities(&pdu.0, &server_caps).unwrap_or_else(|| {
@@ -1349,28 +2341,37 @@ impl GraphicsPipelineServer {
         debug!(?negotiated, "Negotiated capabilities");
 
         // Extract codec capabilities
-        self.codec_caps = CodecCapabilities::from_capability_set(&negotiated);
-        self.negotiated_caps = Some(negotiated.clone());
+        self.ctx.codec_caps = CodecCapabilities::from_capability_set(&negotiated);
+        self.ctx.negotiated_caps = Some(negotiated.clone());
 
         // Queue CapabilitiesConfirm
-        self.output_queue
+        self.ctx
+            .output_queue
             .push_back(GfxPdu::CapabilitiesConfirm(CapabilitiesConfirmPdu(negotiated.clone())));
 
         debug!(
             "Queued CapabilitiesConfirm for {:?} (output_queue size: {})",
             negotiated,
-            self.output_queue.len()
+            self.ctx.output_queue.len()
         );
 
+        // The surfaces outlive a reopened channel
+        if !self.ctx.surfaces.is_empty() {
+            self.ctx.replay_surfaces();
+        }
+
         // Transition to ready state
-        self.state = ServerState::Ready;
+        self.ctx.state = ServerState::Ready;
 
         // Notify handler
-        self.handler.on_ready(&negotiated);
+        self.handler
+            .on_capabilities_negotiated(&negotiated, &pdu.0, &mut self.ctx);
+        self.ctx.client_caps = pdu.0;
+        self.handler.on_ready(&negotiated, &mut self.ctx);
 
         debug!(
-            avc420 = self.codec_caps.avc420,
-            avc444 = self.codec_caps.avc444,
+            avc420 = self.ctx.codec_caps.avc420,
+            avc444 = self.ctx.codec_caps.avc444,
             "EGFX server ready"
         );
     }
@@ -1382,12 +2383,12 @@ impl GraphicsPipelineServer {
         // Convert QueueDepth enum to u32 for tracking
         let queue_depth_u32 = pdu.queue_depth.to_u32();
 
-        if let Some(info) = self.frames.acknowledge(pdu.frame_id, queue_depth_u32) {
+        if let Some(info) = self.ctx.frames.acknowledge(pdu.frame_id, queue_depth_u32) {
             let latency = info.sent_at.elapsed();
             trace!(frame_id = pdu.frame_id, ?latency, "Frame acknowledged");
         }
 
-        self.handler.on_frame_ack(pdu.frame_id, queue_depth_u32);
+        self.handler.on_frame_ack(pdu.frame_id, queue_depth_u32, &mut self.ctx);
     }
 
     /// Handle QoE frame acknowledgment
@@ -1401,7 +2402,7 @@ impl GraphicsPipelineServer {
             time_diff_dr: pdu.time_diff_dr,
         };
 
-        self.handler.on_qoe_metrics(metrics);
+        self.handler.on_qoe_metrics(metrics, &mut self.ctx);
     }
 
     /// Handle cache import offer
@@ -1409,10 +2410,11 @@ impl GraphicsPipelineServer {
         debug!(entries = pdu.cache_entries.len(), "Received CacheImportOffer");
 
         // Ask handler which entries to accept
-        let accepted = self.handler.on_cache_import_offer(&pdu);
+        let accepted = self.handler.on_cache_import_offer(&pdu, &mut self.ctx);
 
         // Send reply
-        self.output_queue
+        self.ctx
+            .output_queue
             .push_back(GfxPdu::CacheImportReply(CacheImportReplyPdu { cache_slots: accepted }));
     }
 }
@@ -1426,17 +2428,29 @@ impl DvcProcessor for GraphicsPipelineServer {
 
     fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
         // Store channel_id for later use by proactive frame sending
-        self.channel_id = Some(channel_id);
+        self.ctx.channel_id = Some(channel_id);
+        self.ctx.state = ServerState::WaitingForCapabilities;
         debug!(channel_id, "EGFX channel started");
         // Server doesn't send anything at start - waits for client CapabilitiesAdvertise
         Ok(vec![])
     }
 
+    fn priority(&self) -> ChannelPriority {
+        ChannelPriority::High
+    }
+
     fn close(&mut self, _channel_id: u32) {
         debug!("EGFX channel closed");
-        self.state = ServerState::Closed;
-        self.reset_graphics_sent = false;
-        self.handler.on_close();
+        self.ctx.state = ServerState::Closed;
+        self.ctx.reset_graphics_sent = false;
+        self.ctx.negotiated_caps = None;
+        self.ctx.codec_caps = CodecCapabilities::default();
+
+        // Neither the pending PDUs nor the unacknowledged frames reach the client anymore
+        self.ctx.output_queue.clear();
+        self.ctx.frames.clear();
+
+        self.handler.on_close(&mut self.ctx);
     }
 
     fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
@@ -89,7 +89,7 @@ pub fn rle_decompress_bitmap(input: BitmapInput<'_>) {
 }
 
 pub fn rdp6_encode_bitmap_stream(input: &BitmapInput<'_>) {
-    use ironrdp_graphics::rdp6::{BitmapStreamEncoder, RgbAChannels, RgbChannels};
+    use ironrdp_graphics::rdp6::{BitmapStreamEncoder, ColorLoss, RgbAChannels, RgbChannels};
 
     let mut out = vec![0; input.src.len() * 2];
 
@@ -104,6 +104,13 @@ pub fn rdp6_encode_bitmap_stream(input: &BitmapInput<'_>) {
         out.as_mut_slice(),
         true,
     );
+
+    let _ = BitmapStreamEncoder::new(input.width.into(), input.height.into())
+        .with_color_loss(ColorLoss {
+            level: 3,
+            chroma_subsampling: true,
+        })
+        .encode_bitmap::<RgbChannels>(input.src, out.as_mut_slice(), true);
 }
 
 pub fn rdp6_decode_bitmap_stream_to_rgb24(input: &BitmapInput<'_>) {
@@ -122,6 +129,7 @@ pub fn rdp6_decode_bitmap_stream_to_rgb24(input: &BitmapInput<'_>) {
 pub fn cliprdr_format(input: &[u8]) {
     use ironrdp_cliprdr_format::bitmap::{dib_to_png, dibv5_to_png, png_to_cf_dib, png_to_cf_dibv5};
     use ironrdp_cliprdr_format::html::{cf_html_to_plain_html, plain_html_to_cf_html};
+    use ironrdp_cliprdr_format::text::{cf_unicodetext_to_utf8, utf8_to_cf_unicodetext};
 
     let _ = png_to_cf_dib(input);
     let _ = png_to_cf_dibv5(input);
@@ -130,9 +138,11 @@ pub fn cliprdr_format(input: &[u8]) {
     let _ = dibv5_to_png(input);
 
     let _ = cf_html_to_plain_html(input);
+    let _ = cf_unicodetext_to_utf8(input);
 
     if let Ok(input) = core::str::from_utf8(input) {
         let _ = plain_html_to_cf_html(input);
+        let _ = utf8_to_cf_unicodetext(input);
     }
 }
 
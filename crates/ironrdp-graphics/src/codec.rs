@@ -0,0 +1,449 @@
+//! Codec-agnostic bitmap encoding and decoding
+//!
+//! Bitmap codecs are identified on the wire in two different ways:
+//!
+//! - by a GUID advertised in the Bitmap Codecs Capability Set (TS_BITMAPCODECS), used
+//!   by surface commands,
+//! - by a `Codec1Type` value in the graphics pipeline (RDPGFX_WIRE_TO_SURFACE_PDU_1).
+//!
+//! [`BitmapCodec`] provides a common encode/decode interface over both, and
+//! [`CodecRegistry`] dispatches to the codec matching a given [`CodecId`]. Codecs
+//! not provided by this crate can be plugged in by implementing [`BitmapCodec`]
+//! and registering them.
+
+use core::fmt;
+use std::collections::HashMap;
+
+use ironrdp_pdu::rdp::capability_sets::{BitmapCodecs, Guid};
+
+use crate::image_processing::{PixelFormat, Rgba};
+use crate::rdp6::{
+    ABgrChannels, ARgbChannels, BgrAChannels, BitmapDecodeError, BitmapEncodeError, BitmapStreamDecoder,
+    BitmapStreamEncoder, ColorLoss, RgbAChannels,
+};
+
+/// `Codec1Type` value of the uncompressed graphics pipeline codec (RDPGFX_CODECID_UNCOMPRESSED)
+pub const CODEC1_TYPE_UNCOMPRESSED: u16 = 0x0;
+
+/// `Codec1Type` value of the planar graphics pipeline codec (RDPGFX_CODECID_PLANAR)
+pub const CODEC1_TYPE_PLANAR: u16 = 0xA;
+
+/// Wire identifier of a bitmap codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CodecId {
+    /// Codec GUID, as found in the Bitmap Codecs Capability Set
+    Guid(Guid),
+    /// `Codec1Type` value, as found in RDPGFX_WIRE_TO_SURFACE_PDU_1
+    Codec1Type(u16),
+}
+
+impl fmt::Display for CodecId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Guid(guid) => write!(f, "GUID {guid:?}"),
+            Self::Codec1Type(codec_id) => write!(f, "Codec1Type {codec_id:#x}"),
+        }
+    }
+}
+
+/// Dimensions and pixel format of a bitmap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmapDesc {
+    pub width: u16,
+    pub height: u16,
+    pub format: PixelFormat,
+}
+
+impl BitmapDesc {
+    /// Size in bytes of a tightly packed row
+    pub fn row_len(&self) -> usize {
+        usize::from(self.width) * usize::from(self.format.bytes_per_pixel())
+    }
+
+    /// Size in bytes of the tightly packed bitmap
+    pub fn len(&self) -> usize {
+        self.row_len() * usize::from(self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// Borrowed bitmap to be encoded
+#[derive(Clone, Copy)]
+pub struct BitmapRef<'a> {
+    pub desc: BitmapDesc,
+    /// Number of bytes between the start of two consecutive rows
+    pub stride: usize,
+    pub data: &'a [u8],
+}
+
+impl fmt::Debug for BitmapRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitmapRef")
+            .field("desc", &self.desc)
+            .field("stride", &self.stride)
+            .field("data_len", &self.data.len())
+            .finish()
+    }
+}
+
+impl<'a> BitmapRef<'a> {
+    /// Iterates over the rows of the bitmap, without the stride padding
+    ///
+    /// Returns an error if `data` is too small for the described bitmap.
+    pub fn rows(&self) -> Result<impl Iterator<Item = &'a [u8]> + Clone, CodecError> {
+        let row_len = self.desc.row_len();
+        let height = usize::from(self.desc.height);
+
+        if self.stride < row_len {
+            return Err(CodecError::InvalidInput("stride is smaller than the row length"));
+        }
+
+        if height > 0 && self.data.len() < self.stride * (height - 1) + row_len {
+            return Err(CodecError::InvalidInput("bitmap data is too small"));
+        }
+
+        let data = self.data;
+        let stride = self.stride;
+
+        Ok((0..height).map(move |y| &data[y * stride..y * stride + row_len]))
+    }
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    /// No codec is registered for this identifier
+    UnknownCodec(CodecId),
+    /// The codec does not implement the requested operation
+    Unsupported {
+        codec: &'static str,
+        operation: &'static str,
+    },
+    InvalidInput(&'static str),
+    PlanarEncode(BitmapEncodeError),
+    PlanarDecode(BitmapDecodeError),
+    Other {
+        codec: &'static str,
+        source: Box<dyn core::error::Error + Send + Sync>,
+    },
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownCodec(id) => write!(f, "no codec registered for {id}"),
+            Self::Unsupported { codec, operation } => write!(f, "{codec} codec does not support {operation}"),
+            Self::InvalidInput(reason) => write!(f, "invalid input: {reason}"),
+            Self::PlanarEncode(_) => write!(f, "planar encoding failed"),
+            Self::PlanarDecode(_) => write!(f, "planar decoding failed"),
+            Self::Other { codec, .. } => write!(f, "{codec} codec failed"),
+        }
+    }
+}
+
+impl core::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::UnknownCodec(_) => None,
+            Self::Unsupported { .. } => None,
+            Self::InvalidInput(_) => None,
+            Self::PlanarEncode(error) => Some(error),
+            Self::PlanarDecode(error) => Some(error),
+            Self::Other { source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+
+/// Common interface of bitmap codecs
+///
+/// Codecs may be stateful (e.g.: entropy contexts, caches), hence `&mut self`.
+/// A codec only supporting one direction leaves the other method to its default
+/// implementation, which returns [`CodecError::Unsupported`].
+pub trait BitmapCodec: Send {
+    /// Human-readable name, used in logs and errors
+    fn name(&self) -> &'static str;
+
+    /// Wire identifiers this codec handles
+    fn ids(&self) -> &[CodecId];
+
+    fn can_encode(&self) -> bool {
+        false
+    }
+
+    fn can_decode(&self) -> bool {
+        false
+    }
+
+    /// Encodes `bitmap`, appending the codec payload to `dst`
+    fn encode(&mut self, bitmap: &BitmapRef<'_>, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        let _ = (bitmap, dst);
+
+        Err(CodecError::Unsupported {
+            codec: self.name(),
+            operation: "encoding",
+        })
+    }
+
+    /// Decodes `src`, appending the tightly packed bitmap described by `desc` to `dst`
+    fn decode(&mut self, src: &[u8], desc: BitmapDesc, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        let _ = (src, desc, dst);
+
+        Err(CodecError::Unsupported {
+            codec: self.name(),
+            operation: "decoding",
+        })
+    }
+}
+
+/// Set of codecs indexed by their wire identifiers
+///
+/// Registering a codec for an identifier which is already taken replaces the
+/// previous codec for that identifier, so built-in codecs can be overridden.
+#[derive(Default)]
+pub struct CodecRegistry {
+    codecs: Vec<Box<dyn BitmapCodec>>,
+    by_id: HashMap<CodecId, usize>,
+}
+
+impl fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.by_id.iter().map(|(id, idx)| (id, self.codecs[*idx].name())))
+            .finish()
+    }
+}
+
+impl CodecRegistry {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry with the codecs implemented by this crate
+    pub fn with_builtin_codecs() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(UncompressedCodec));
+        registry.register(Box::new(PlanarCodec::default()));
+        registry
+    }
+
+    /// Registers `codec` for all the identifiers returned by [`BitmapCodec::ids`]
+    pub fn register(&mut self, codec: Box<dyn BitmapCodec>) {
+        let idx = self.codecs.len();
+
+        for id in codec.ids() {
+            self.by_id.insert(*id, idx);
+        }
+
+        self.codecs.push(codec);
+    }
+
+    pub fn contains(&self, id: CodecId) -> bool {
+        self.by_id.contains_key(&id)
+    }
+
+    /// Identifiers for which a codec is registered
+    pub fn ids(&self) -> impl Iterator<Item = CodecId> + '_ {
+        self.by_id.keys().copied()
+    }
+
+    pub fn get(&self, id: CodecId) -> Option<&dyn BitmapCodec> {
+        let idx = *self.by_id.get(&id)?;
+        Some(self.codecs[idx].as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: CodecId) -> Option<&mut (dyn BitmapCodec + 'static)> {
+        let idx = *self.by_id.get(&id)?;
+        Some(self.codecs[idx].as_mut())
+    }
+
+    /// Client-advertised codecs (TS_BITMAPCODECS) for which a codec is registered
+    ///
+    /// Entries are returned in client preference order, along with the codec ID
+    /// assigned by the client.
+    pub fn negotiate(&self, client: &BitmapCodecs) -> Vec<(CodecId, u8)> {
+        client
+            .0
+            .iter()
+            .filter_map(|codec| Some((CodecId::Guid(codec.guid()?), codec.id)))
+            .filter(|(id, _)| self.contains(*id))
+            .collect()
+    }
+
+    /// Encodes `bitmap` with the codec registered for `id`
+    pub fn encode(&mut self, id: CodecId, bitmap: &BitmapRef<'_>, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        self.get_mut(id)
+            .ok_or(CodecError::UnknownCodec(id))?
+            .encode(bitmap, dst)
+    }
+
+    /// Decodes `src` with the codec registered for `id`
+    pub fn decode(&mut self, id: CodecId, src: &[u8], desc: BitmapDesc, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        self.get_mut(id)
+            .ok_or(CodecError::UnknownCodec(id))?
+            .decode(src, desc, dst)
+    }
+}
+
+/// Raw pixels, in the pixel format of the surface (RDPGFX_CODECID_UNCOMPRESSED)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UncompressedCodec;
+
+impl BitmapCodec for UncompressedCodec {
+    fn name(&self) -> &'static str {
+        "uncompressed"
+    }
+
+    fn ids(&self) -> &[CodecId] {
+        &[CodecId::Codec1Type(CODEC1_TYPE_UNCOMPRESSED)]
+    }
+
+    fn can_encode(&self) -> bool {
+        true
+    }
+
+    fn can_decode(&self) -> bool {
+        true
+    }
+
+    fn encode(&mut self, bitmap: &BitmapRef<'_>, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        dst.reserve(bitmap.desc.len());
+
+        for row in bitmap.rows()? {
+            dst.extend_from_slice(row);
+        }
+
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &[u8], desc: BitmapDesc, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        let len = desc.len();
+
+        if src.len() < len {
+            return Err(CodecError::InvalidInput("not enough uncompressed pixel data"));
+        }
+
+        dst.extend_from_slice(&src[..len]);
+
+        Ok(())
+    }
+}
+
+/// RDP 6.0 planar codec (MS-RDPEGDI 2.2.2.5.1, RDPGFX_CODECID_PLANAR)
+///
+/// Encoding always uses RLE, and falls back to raw planes when RLE does not pay off.
+/// Alpha is not transmitted. Colors are lossless, unless a color loss is set.
+#[derive(Debug, Default)]
+pub struct PlanarCodec {
+    decoder: BitmapStreamDecoder,
+    color_loss: ColorLoss,
+    /// Scratch buffers reused across calls
+    encode_buffer: Vec<u8>,
+    rgb24_buffer: Vec<u8>,
+}
+
+impl PlanarCodec {
+    /// Encodes the colors as YCoCg planes with the given color loss
+    #[must_use]
+    pub fn with_color_loss(mut self, color_loss: ColorLoss) -> Self {
+        self.color_loss = color_loss;
+        self
+    }
+
+    fn encode_planes<'a, P>(&mut self, desc: BitmapDesc, pixels: P, rle: bool) -> Result<usize, BitmapEncodeError>
+    where
+        P: Iterator<Item = &'a [u8]> + Clone,
+    {
+        let mut encoder = BitmapStreamEncoder::new(usize::from(desc.width), usize::from(desc.height))
+            .with_color_loss(self.color_loss);
+        let dst = self.encode_buffer.as_mut_slice();
+
+        match desc.format {
+            PixelFormat::ARgb32 | PixelFormat::XRgb32 => {
+                encoder.encode_pixels_stream::<_, ARgbChannels>(pixels, dst, rle)
+            }
+            PixelFormat::RgbA32 | PixelFormat::RgbX32 => {
+                encoder.encode_pixels_stream::<_, RgbAChannels>(pixels, dst, rle)
+            }
+            PixelFormat::ABgr32 | PixelFormat::XBgr32 => {
+                encoder.encode_pixels_stream::<_, ABgrChannels>(pixels, dst, rle)
+            }
+            PixelFormat::BgrA32 | PixelFormat::BgrX32 => {
+                encoder.encode_pixels_stream::<_, BgrAChannels>(pixels, dst, rle)
+            }
+        }
+    }
+}
+
+impl BitmapCodec for PlanarCodec {
+    fn name(&self) -> &'static str {
+        "planar"
+    }
+
+    fn ids(&self) -> &[CodecId] {
+        &[CodecId::Codec1Type(CODEC1_TYPE_PLANAR)]
+    }
+
+    fn can_encode(&self) -> bool {
+        true
+    }
+
+    fn can_decode(&self) -> bool {
+        true
+    }
+
+    fn encode(&mut self, bitmap: &BitmapRef<'_>, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        let bytes_per_pixel = usize::from(bitmap.desc.format.bytes_per_pixel());
+        let pixels = bitmap.rows()?.flat_map(move |row| row.chunks_exact(bytes_per_pixel));
+
+        // Header + raw color planes + padding byte, which RLE must beat to be worth it
+        let raw_size = 1 + usize::from(bitmap.desc.width) * usize::from(bitmap.desc.height) * 3 + 1;
+        self.encode_buffer.resize(raw_size, 0);
+
+        let len = match self.encode_planes(bitmap.desc, pixels.clone(), true) {
+            Ok(len) => len,
+            Err(_) => self
+                .encode_planes(bitmap.desc, pixels, false)
+                .map_err(CodecError::PlanarEncode)?,
+        };
+
+        dst.extend_from_slice(&self.encode_buffer[..len]);
+
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &[u8], desc: BitmapDesc, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        self.rgb24_buffer.clear();
+        self.decoder
+            .decode_bitmap_stream_to_rgb24(
+                src,
+                &mut self.rgb24_buffer,
+                usize::from(desc.width),
+                usize::from(desc.height),
+            )
+            .map_err(CodecError::PlanarDecode)?;
+
+        let start = dst.len();
+        dst.resize(start + desc.len(), 0);
+
+        let bytes_per_pixel = usize::from(desc.format.bytes_per_pixel());
+        let out = dst[start..].chunks_exact_mut(bytes_per_pixel);
+
+        for (rgb, pixel) in self.rgb24_buffer.chunks_exact(3).zip(out) {
+            let color = Rgba {
+                r: rgb[0],
+                g: rgb[1],
+                b: rgb[2],
+                a: 0xff,
+            };
+
+            desc.format
+                .write_color(color, pixel)
+                .expect("pixel slice has the size of one pixel");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,627 @@
+//! Conversion between BGRA pixels and planar YUV (I420 and I444)
+//!
+//! This is the color conversion used by the AVC420 and AVC444 codecs: ITU-R BT.709 with full
+//! range values, computed in fixed point. Rows are converted with AVX2 on x86-64 CPUs supporting
+//! it and with NEON on AArch64, falling back to scalar code otherwise. All implementations give
+//! bit-identical results.
+//!
+//! The alpha channel is ignored when encoding, and set to 0xFF when decoding.
+
+use core::fmt;
+
+mod scalar;
+
+#[cfg(target_arch = "x86_64")]
+mod x86;
+#[cfg(target_arch = "x86_64")]
+use x86 as simd;
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon;
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+use neon as simd;
+
+#[cfg(not(any(target_arch = "x86_64", all(target_arch = "aarch64", target_feature = "neon"))))]
+mod simd {
+    pub(super) fn encode_420(_: &[u8], _: &[u8], _: &mut [u8], _: &mut [u8], _: &mut [u8], _: &mut [u8]) -> usize {
+        0
+    }
+
+    pub(super) fn encode_444(_: &[u8], _: &mut [u8], _: &mut [u8], _: &mut [u8]) -> usize {
+        0
+    }
+
+    pub(super) fn decode_420(_: &[u8], _: &[u8], _: &[u8], _: &mut [u8]) -> usize {
+        0
+    }
+
+    pub(super) fn decode_444(_: &[u8], _: &[u8], _: &[u8], _: &mut [u8]) -> usize {
+        0
+    }
+}
+
+// RGB to YUV coefficients, scaled by 256.
+const Y_R: u16 = 54;
+const Y_G: u16 = 183;
+const Y_B: u16 = 19;
+const U_R: i32 = -29;
+const U_G: i32 = -99;
+const U_B: i32 = 128;
+const V_R: i32 = 128;
+const V_G: i32 = -116;
+const V_B: i32 = -12;
+
+// YUV to RGB coefficients, scaled by 256.
+const R_V: i32 = 403;
+const G_U: i32 = -48;
+const G_V: i32 = -120;
+const B_U: i32 = 475;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChromaSubsampling {
+    /// Chroma planes have half the width and height of the luma plane (I420)
+    Yuv420,
+    /// Chroma planes have the size of the luma plane (I444)
+    Yuv444,
+}
+
+impl ChromaSubsampling {
+    /// Returns the width and height of the chroma planes for a `width` x `height` picture
+    pub fn chroma_size(self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Self::Yuv420 => (width.div_ceil(2), height.div_ceil(2)),
+            Self::Yuv444 => (width, height),
+        }
+    }
+}
+
+/// Borrowed planar YUV picture
+#[derive(Debug, Clone, Copy)]
+pub struct YuvPlanes<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub subsampling: ChromaSubsampling,
+    pub y: &'a [u8],
+    pub y_stride: usize,
+    pub u: &'a [u8],
+    pub u_stride: usize,
+    pub v: &'a [u8],
+    pub v_stride: usize,
+}
+
+impl<'a> YuvPlanes<'a> {
+    /// Planes without padding: each stride is the width of the plane
+    pub fn packed(
+        width: usize,
+        height: usize,
+        subsampling: ChromaSubsampling,
+        y: &'a [u8],
+        u: &'a [u8],
+        v: &'a [u8],
+    ) -> Self {
+        let (chroma_width, _) = subsampling.chroma_size(width, height);
+
+        Self {
+            width,
+            height,
+            subsampling,
+            y,
+            y_stride: width,
+            u,
+            u_stride: chroma_width,
+            v,
+            v_stride: chroma_width,
+        }
+    }
+}
+
+/// Mutably borrowed planar YUV picture
+#[derive(Debug)]
+pub struct YuvPlanesMut<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub subsampling: ChromaSubsampling,
+    pub y: &'a mut [u8],
+    pub y_stride: usize,
+    pub u: &'a mut [u8],
+    pub u_stride: usize,
+    pub v: &'a mut [u8],
+    pub v_stride: usize,
+}
+
+impl<'a> YuvPlanesMut<'a> {
+    /// Planes without padding: each stride is the width of the plane
+    pub fn packed(
+        width: usize,
+        height: usize,
+        subsampling: ChromaSubsampling,
+        y: &'a mut [u8],
+        u: &'a mut [u8],
+        v: &'a mut [u8],
+    ) -> Self {
+        let (chroma_width, _) = subsampling.chroma_size(width, height);
+
+        Self {
+            width,
+            height,
+            subsampling,
+            y,
+            y_stride: width,
+            u,
+            u_stride: chroma_width,
+            v,
+            v_stride: chroma_width,
+        }
+    }
+
+    pub fn as_planes(&self) -> YuvPlanes<'_> {
+        YuvPlanes {
+            width: self.width,
+            height: self.height,
+            subsampling: self.subsampling,
+            y: self.y,
+            y_stride: self.y_stride,
+            u: self.u,
+            u_stride: self.u_stride,
+            v: self.v,
+            v_stride: self.v_stride,
+        }
+    }
+}
+
+/// Owned planar YUV picture, without padding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YuvBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub subsampling: ChromaSubsampling,
+    pub y: Vec<u8>,
+    pub u: Vec<u8>,
+    pub v: Vec<u8>,
+}
+
+impl YuvBuffer {
+    pub fn new(width: usize, height: usize, subsampling: ChromaSubsampling) -> Self {
+        let (chroma_width, chroma_height) = subsampling.chroma_size(width, height);
+
+        Self {
+            width,
+            height,
+            subsampling,
+            y: vec![0; width * height],
+            u: vec![0; chroma_width * chroma_height],
+            v: vec![0; chroma_width * chroma_height],
+        }
+    }
+
+    pub fn as_planes(&self) -> YuvPlanes<'_> {
+        YuvPlanes::packed(self.width, self.height, self.subsampling, &self.y, &self.u, &self.v)
+    }
+
+    pub fn as_planes_mut(&mut self) -> YuvPlanesMut<'_> {
+        YuvPlanesMut::packed(
+            self.width,
+            self.height,
+            self.subsampling,
+            &mut self.y,
+            &mut self.u,
+            &mut self.v,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorConversionError {
+    /// The stride of a plane is smaller than the size of one of its rows
+    InvalidStride {
+        plane: &'static str,
+        stride: usize,
+        row_size: usize,
+    },
+    /// A plane does not hold enough rows for the picture
+    BufferTooSmall {
+        plane: &'static str,
+        actual: usize,
+        required: usize,
+    },
+}
+
+impl fmt::Display for ColorConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidStride {
+                plane,
+                stride,
+                row_size,
+            } => write!(f, "{plane} stride ({stride}) is smaller than the row size ({row_size})"),
+            Self::BufferTooSmall {
+                plane,
+                actual,
+                required,
+            } => write!(f, "{plane} buffer is too small: {actual} bytes, {required} required"),
+        }
+    }
+}
+
+impl core::error::Error for ColorConversionError {}
+
+/// Converts a BGRA (or BGRX) picture of `dst.width` x `dst.height` pixels into YUV planes
+///
+/// With 4:2:0 subsampling, each chroma sample is the average of a 2x2 pixel block. The last
+/// column or row is duplicated when the picture has an odd width or height.
+pub fn bgra_to_yuv(src: &[u8], src_stride: usize, dst: &mut YuvPlanesMut<'_>) -> Result<(), ColorConversionError> {
+    let (width, height) = (dst.width, dst.height);
+
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+
+    check_plane("BGRA", src.len(), src_stride, width * 4, height)?;
+    check_planes(&dst.as_planes())?;
+
+    match dst.subsampling {
+        ChromaSubsampling::Yuv420 => {
+            let (chroma_width, chroma_height) = ChromaSubsampling::Yuv420.chroma_size(width, height);
+            let mut last_row = Vec::new();
+
+            for row in 0..chroma_height {
+                let top = row * 2;
+                let bottom = (top + 1).min(height - 1);
+
+                let src0 = &src[top * src_stride..][..width * 4];
+                let src1 = &src[bottom * src_stride..][..width * 4];
+                let u = &mut dst.u[row * dst.u_stride..][..chroma_width];
+                let v = &mut dst.v[row * dst.v_stride..][..chroma_width];
+
+                let (y0, y1) = if bottom == top {
+                    // Odd height: the luma of the duplicated row is computed, then discarded.
+                    last_row.resize(width, 0);
+                    (&mut dst.y[top * dst.y_stride..][..width], last_row.as_mut_slice())
+                } else {
+                    let (y0, y1) = dst.y[top * dst.y_stride..].split_at_mut(dst.y_stride);
+                    (&mut y0[..width], &mut y1[..width])
+                };
+
+                let done = simd::encode_420(src0, src1, y0, y1, u, v);
+                scalar::encode_420(src0, src1, y0, y1, u, v, done);
+            }
+        }
+        ChromaSubsampling::Yuv444 => {
+            for row in 0..height {
+                let src = &src[row * src_stride..][..width * 4];
+                let y = &mut dst.y[row * dst.y_stride..][..width];
+                let u = &mut dst.u[row * dst.u_stride..][..width];
+                let v = &mut dst.v[row * dst.v_stride..][..width];
+
+                let done = simd::encode_444(src, y, u, v);
+                scalar::encode_444(src, y, u, v, done);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts YUV planes into a BGRA picture of `src.width` x `src.height` pixels, with opaque alpha
+pub fn yuv_to_bgra(src: &YuvPlanes<'_>, dst: &mut [u8], dst_stride: usize) -> Result<(), ColorConversionError> {
+    let (width, height) = (src.width, src.height);
+
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+
+    check_planes(src)?;
+    check_plane("BGRA", dst.len(), dst_stride, width * 4, height)?;
+
+    let (chroma_width, _) = src.subsampling.chroma_size(width, height);
+
+    for row in 0..height {
+        let chroma_row = match src.subsampling {
+            ChromaSubsampling::Yuv420 => row / 2,
+            ChromaSubsampling::Yuv444 => row,
+        };
+
+        let y = &src.y[row * src.y_stride..][..width];
+        let u = &src.u[chroma_row * src.u_stride..][..chroma_width];
+        let v = &src.v[chroma_row * src.v_stride..][..chroma_width];
+        let dst = &mut dst[row * dst_stride..][..width * 4];
+
+        match src.subsampling {
+            ChromaSubsampling::Yuv420 => {
+                let done = simd::decode_420(y, u, v, dst);
+                scalar::decode_420(y, u, v, dst, done);
+            }
+            ChromaSubsampling::Yuv444 => {
+                let done = simd::decode_444(y, u, v, dst);
+                scalar::decode_444(y, u, v, dst, done);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_planes(planes: &YuvPlanes<'_>) -> Result<(), ColorConversionError> {
+    let (chroma_width, chroma_height) = planes.subsampling.chroma_size(planes.width, planes.height);
+
+    check_plane("Y", planes.y.len(), planes.y_stride, planes.width, planes.height)?;
+    check_plane("U", planes.u.len(), planes.u_stride, chroma_width, chroma_height)?;
+    check_plane("V", planes.v.len(), planes.v_stride, chroma_width, chroma_height)
+}
+
+fn check_plane(
+    plane: &'static str,
+    len: usize,
+    stride: usize,
+    row_size: usize,
+    rows: usize,
+) -> Result<(), ColorConversionError> {
+    if stride < row_size {
+        return Err(ColorConversionError::InvalidStride {
+            plane,
+            stride,
+            row_size,
+        });
+    }
+
+    // The last row does not need to be padded up to the stride.
+    let required = stride
+        .checked_mul(rows - 1)
+        .and_then(|size| size.checked_add(row_size))
+        .unwrap_or(usize::MAX);
+
+    if len < required {
+        return Err(ColorConversionError::BufferTooSmall {
+            plane,
+            actual: len,
+            required,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_bytes(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+
+        core::iter::repeat_with(|| {
+            // xorshift, covering the whole value range of each component
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state.to_le_bytes()[0]
+        })
+        .take(len)
+        .collect()
+    }
+
+    fn scalar_encode(src: &[u8], width: usize, height: usize, subsampling: ChromaSubsampling) -> YuvBuffer {
+        let mut yuv = YuvBuffer::new(width, height, subsampling);
+        let (chroma_width, _) = subsampling.chroma_size(width, height);
+
+        match subsampling {
+            ChromaSubsampling::Yuv420 => {
+                for (row, (u, v)) in yuv
+                    .u
+                    .chunks_mut(chroma_width)
+                    .zip(yuv.v.chunks_mut(chroma_width))
+                    .enumerate()
+                {
+                    let bottom = (row * 2 + 1).min(height - 1);
+                    let mut y1 = vec![0; width];
+                    let mut y0 = vec![0; width];
+                    let src0 = &src[row * 2 * width * 4..][..width * 4];
+                    let src1 = &src[bottom * width * 4..][..width * 4];
+
+                    scalar::encode_420(src0, src1, &mut y0, &mut y1, u, v, 0);
+
+                    yuv.y[row * 2 * width..][..width].copy_from_slice(&y0);
+                    if bottom != row * 2 {
+                        yuv.y[bottom * width..][..width].copy_from_slice(&y1);
+                    }
+                }
+            }
+            ChromaSubsampling::Yuv444 => {
+                for (row, ((y, u), v)) in yuv
+                    .y
+                    .chunks_mut(width)
+                    .zip(yuv.u.chunks_mut(width))
+                    .zip(yuv.v.chunks_mut(width))
+                    .enumerate()
+                {
+                    scalar::encode_444(&src[row * width * 4..][..width * 4], y, u, v, 0);
+                }
+            }
+        }
+
+        yuv
+    }
+
+    fn scalar_decode(yuv: &YuvBuffer) -> Vec<u8> {
+        let (width, subsampling) = (yuv.width, yuv.subsampling);
+        let (chroma_width, _) = subsampling.chroma_size(width, yuv.height);
+        let mut dst = vec![0; width * yuv.height * 4];
+
+        for (row, (y, dst)) in yuv.y.chunks(width).zip(dst.chunks_mut(width * 4)).enumerate() {
+            let chroma_row = match subsampling {
+                ChromaSubsampling::Yuv420 => row / 2,
+                ChromaSubsampling::Yuv444 => row,
+            };
+            let u = &yuv.u[chroma_row * chroma_width..][..chroma_width];
+            let v = &yuv.v[chroma_row * chroma_width..][..chroma_width];
+
+            match subsampling {
+                ChromaSubsampling::Yuv420 => scalar::decode_420(y, u, v, dst, 0),
+                ChromaSubsampling::Yuv444 => scalar::decode_444(y, u, v, dst, 0),
+            }
+        }
+
+        dst
+    }
+
+    #[test]
+    fn accelerated_encoding_matches_scalar() {
+        for subsampling in [ChromaSubsampling::Yuv420, ChromaSubsampling::Yuv444] {
+            for (width, height) in [(1, 1), (7, 3), (8, 2), (16, 16), (33, 17), (64, 9)] {
+                let src = random_bytes(width * height * 4, 0x1234_5678);
+
+                let mut yuv = YuvBuffer::new(width, height, subsampling);
+                bgra_to_yuv(&src, width * 4, &mut yuv.as_planes_mut()).unwrap();
+
+                assert_eq!(
+                    yuv,
+                    scalar_encode(&src, width, height, subsampling),
+                    "{subsampling:?} {width}x{height}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn accelerated_decoding_matches_scalar() {
+        for subsampling in [ChromaSubsampling::Yuv420, ChromaSubsampling::Yuv444] {
+            for (width, height) in [(1, 1), (7, 3), (8, 2), (16, 16), (33, 17), (64, 9)] {
+                let mut yuv = YuvBuffer::new(width, height, subsampling);
+                let (chroma_width, chroma_height) = subsampling.chroma_size(width, height);
+                yuv.y = random_bytes(width * height, 1);
+                yuv.u = random_bytes(chroma_width * chroma_height, 2);
+                yuv.v = random_bytes(chroma_width * chroma_height, 3);
+
+                let mut dst = vec![0; width * height * 4];
+                yuv_to_bgra(&yuv.as_planes(), &mut dst, width * 4).unwrap();
+
+                assert_eq!(dst, scalar_decode(&yuv), "{subsampling:?} {width}x{height}");
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_444() {
+        let (width, height) = (37, 11);
+        let src = random_bytes(width * height * 4, 0x1234_5678);
+
+        let mut yuv = YuvBuffer::new(width, height, ChromaSubsampling::Yuv444);
+        bgra_to_yuv(&src, width * 4, &mut yuv.as_planes_mut()).unwrap();
+
+        let mut dst = vec![0; src.len()];
+        yuv_to_bgra(&yuv.as_planes(), &mut dst, width * 4).unwrap();
+
+        for (expected, actual) in src.chunks(4).zip(dst.chunks(4)) {
+            for channel in 0..3 {
+                assert!(
+                    expected[channel].abs_diff(actual[channel]) <= 2,
+                    "{expected:?} {actual:?}"
+                );
+            }
+            assert_eq!(actual[3], 0xFF);
+        }
+    }
+
+    #[test]
+    fn round_trip_420_flat_blocks() {
+        let (width, height) = (24, 6);
+        let colors = [
+            [0, 0, 0],
+            [255, 255, 255],
+            [255, 0, 0],
+            [0, 255, 0],
+            [0, 0, 255],
+            [40, 90, 200],
+        ];
+
+        // Each 2x2 block has a single color, so subsampling loses nothing.
+        let mut src = vec![0; width * height * 4];
+        for (idx, pixel) in src.chunks_mut(4).enumerate() {
+            let [b, g, r] = colors[(idx % width / 2 + idx / width / 2) % colors.len()];
+            pixel.copy_from_slice(&[b, g, r, 0]);
+        }
+
+        let mut yuv = YuvBuffer::new(width, height, ChromaSubsampling::Yuv420);
+        bgra_to_yuv(&src, width * 4, &mut yuv.as_planes_mut()).unwrap();
+
+        let mut dst = vec![0; src.len()];
+        yuv_to_bgra(&yuv.as_planes(), &mut dst, width * 4).unwrap();
+
+        for (expected, actual) in src.chunks(4).zip(dst.chunks(4)) {
+            for channel in 0..3 {
+                assert!(
+                    expected[channel].abs_diff(actual[channel]) <= 2,
+                    "{expected:?} {actual:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn padded_strides() {
+        let (width, height) = (20, 5);
+        let src = random_bytes(width * height * 4, 0x1234_5678);
+
+        let mut padded = vec![0; (width * 4 + 12) * height];
+        for (row, chunk) in padded.chunks_mut(width * 4 + 12).enumerate() {
+            chunk[..width * 4].copy_from_slice(&src[row * width * 4..][..width * 4]);
+        }
+
+        let mut expected = YuvBuffer::new(width, height, ChromaSubsampling::Yuv420);
+        bgra_to_yuv(&src, width * 4, &mut expected.as_planes_mut()).unwrap();
+
+        let (y_stride, chroma_stride) = (width + 3, width / 2 + 5);
+        let mut y = vec![0; y_stride * height];
+        let mut u = vec![0; chroma_stride * 3];
+        let mut v = vec![0; chroma_stride * 3];
+        let mut planes = YuvPlanesMut {
+            width,
+            height,
+            subsampling: ChromaSubsampling::Yuv420,
+            y: &mut y,
+            y_stride,
+            u: &mut u,
+            u_stride: chroma_stride,
+            v: &mut v,
+            v_stride: chroma_stride,
+        };
+        bgra_to_yuv(&padded, width * 4 + 12, &mut planes).unwrap();
+
+        for row in 0..height {
+            assert_eq!(y[row * y_stride..][..width], expected.y[row * width..][..width]);
+        }
+        for row in 0..3 {
+            assert_eq!(
+                u[row * chroma_stride..][..width / 2],
+                expected.u[row * width / 2..][..width / 2]
+            );
+            assert_eq!(
+                v[row * chroma_stride..][..width / 2],
+                expected.v[row * width / 2..][..width / 2]
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_buffers() {
+        let mut yuv = YuvBuffer::new(8, 8, ChromaSubsampling::Yuv420);
+
+        assert_eq!(
+            bgra_to_yuv(&[0; 8 * 8 * 4], 16, &mut yuv.as_planes_mut()),
+            Err(ColorConversionError::InvalidStride {
+                plane: "BGRA",
+                stride: 16,
+                row_size: 32
+            })
+        );
+
+        yuv.u.truncate(15);
+        assert_eq!(
+            bgra_to_yuv(&[0; 8 * 8 * 4], 32, &mut yuv.as_planes_mut()),
+            Err(ColorConversionError::BufferTooSmall {
+                plane: "U",
+                actual: 15,
+                required: 16
+            })
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! NEON kernels, processing 8 pixels per iteration with the same fixed point math as the scalar code
+//!
+//! Each function returns the number of pixels it converted; the remaining ones are left to the
+//! scalar implementation.
+
+use core::arch::aarch64::{
+    int32x4_t, uint16x8_t, uint8x8_t, uint8x8x4_t, vaddq_s32, vaddq_u16, vcombine_s16, vcreate_u8, vdup_n_u8,
+    vdupq_n_s32, vdupq_n_u16, vget_high_u16, vget_lane_u32, vget_lane_u64, vget_low_u16, vld4_u8, vmlaq_n_s32,
+    vmlaq_n_u16, vmovl_u16, vmovl_u8, vmulq_n_s32, vmulq_n_u16, vpaddlq_u16, vqmovn_s32, vqmovun_s16,
+    vreinterpret_u32_u8, vreinterpret_u64_u8, vreinterpretq_s32_u32, vshlq_n_s32, vshrn_n_u16, vshrq_n_s32, vst4_u8,
+    vzip1_u8,
+};
+
+use super::{B_U, G_U, G_V, R_V, U_B, U_G, U_R, V_B, V_G, V_R, Y_B, Y_G, Y_R};
+
+pub(super) fn encode_420(src0: &[u8], src1: &[u8], y0: &mut [u8], y1: &mut [u8], u: &mut [u8], v: &mut [u8]) -> usize {
+    let width = y0.len();
+    let mut x = 0;
+
+    while x + 8 <= width {
+        let [r0, g0, b0] = load_bgra(&src0[x * 4..]);
+        let [r1, g1, b1] = load_bgra(&src1[x * 4..]);
+
+        store_u8x8(&mut y0[x..], luma(r0, g0, b0));
+        store_u8x8(&mut y1[x..], luma(r1, g1, b1));
+
+        let r = pair_sums(r0, r1);
+        let g = pair_sums(g0, g1);
+        let b = pair_sums(b0, b1);
+
+        let cb = chroma::<10>(r, g, b, [U_R, U_G, U_B]);
+        let cr = chroma::<10>(r, g, b, [V_R, V_G, V_B]);
+
+        store_u8x4(&mut u[x / 2..], narrow(cb, cb));
+        store_u8x4(&mut v[x / 2..], narrow(cr, cr));
+
+        x += 8;
+    }
+
+    x
+}
+
+pub(super) fn encode_444(src: &[u8], y: &mut [u8], u: &mut [u8], v: &mut [u8]) -> usize {
+    let width = y.len();
+    let mut x = 0;
+
+    while x + 8 <= width {
+        let [r, g, b] = load_bgra(&src[x * 4..]);
+
+        store_u8x8(&mut y[x..], luma(r, g, b));
+
+        let [r_lo, r_hi] = widen(r);
+        let [g_lo, g_hi] = widen(g);
+        let [b_lo, b_hi] = widen(b);
+
+        let cb_lo = chroma::<8>(r_lo, g_lo, b_lo, [U_R, U_G, U_B]);
+        let cb_hi = chroma::<8>(r_hi, g_hi, b_hi, [U_R, U_G, U_B]);
+        store_u8x8(&mut u[x..], narrow(cb_lo, cb_hi));
+
+        let cr_lo = chroma::<8>(r_lo, g_lo, b_lo, [V_R, V_G, V_B]);
+        let cr_hi = chroma::<8>(r_hi, g_hi, b_hi, [V_R, V_G, V_B]);
+        store_u8x8(&mut v[x..], narrow(cr_lo, cr_hi));
+
+        x += 8;
+    }
+
+    x
+}
+
+pub(super) fn decode_420(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8]) -> usize {
+    let width = y.len();
+    let mut x = 0;
+
+    while x + 8 <= width {
+        let cb = load_u8x4(&u[x / 2..]);
+        let cr = load_u8x4(&v[x / 2..]);
+
+        store_bgra(
+            &mut dst[x * 4..],
+            load_u8x8(&y[x..]),
+            vzip1_u8(cb, cb),
+            vzip1_u8(cr, cr),
+        );
+
+        x += 8;
+    }
+
+    x
+}
+
+pub(super) fn decode_444(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8]) -> usize {
+    let width = y.len();
+    let mut x = 0;
+
+    while x + 8 <= width {
+        store_bgra(
+            &mut dst[x * 4..],
+            load_u8x8(&y[x..]),
+            load_u8x8(&u[x..]),
+            load_u8x8(&v[x..]),
+        );
+
+        x += 8;
+    }
+
+    x
+}
+
+/// Loads 8 BGRA pixels as their `[r, g, b]` components
+fn load_bgra(src: &[u8]) -> [uint16x8_t; 3] {
+    let src = &src[..32];
+    // SAFETY: `src` is 32 bytes long.
+    let pixels = unsafe { vld4_u8(src.as_ptr()) };
+
+    [vmovl_u8(pixels.2), vmovl_u8(pixels.1), vmovl_u8(pixels.0)]
+}
+
+/// The luma sum is at most 65408, so it is computed on 16-bit lanes
+fn luma(r: uint16x8_t, g: uint16x8_t, b: uint16x8_t) -> uint8x8_t {
+    let sum = vmlaq_n_u16(vmlaq_n_u16(vmulq_n_u16(r, Y_R), g, Y_G), b, Y_B);
+    vshrn_n_u16::<8>(vaddq_u16(sum, vdupq_n_u16(128)))
+}
+
+/// Sums horizontally adjacent pixels of both rows
+fn pair_sums(row0: uint16x8_t, row1: uint16x8_t) -> int32x4_t {
+    vreinterpretq_s32_u32(vpaddlq_u16(vaddq_u16(row0, row1)))
+}
+
+/// Computes the chroma of single pixels (`SHIFT` = 8) or of sums of four pixels (`SHIFT` = 10)
+fn chroma<const SHIFT: i32>(r: int32x4_t, g: int32x4_t, b: int32x4_t, [kr, kg, kb]: [i32; 3]) -> int32x4_t {
+    let sum = vmlaq_n_s32(vmlaq_n_s32(vmulq_n_s32(r, kr), g, kg), b, kb);
+    let scaled = vshrq_n_s32::<SHIFT>(vaddq_s32(sum, vdupq_n_s32(1 << (SHIFT - 1))));
+    vaddq_s32(scaled, vdupq_n_s32(128))
+}
+
+fn store_bgra(dst: &mut [u8], y: uint8x8_t, u: uint8x8_t, v: uint8x8_t) {
+    let [y_lo, y_hi] = widen(vmovl_u8(y));
+    let [u_lo, u_hi] = widen(vmovl_u8(u));
+    let [v_lo, v_hi] = widen(vmovl_u8(v));
+
+    let [r_lo, g_lo, b_lo] = to_rgb(y_lo, u_lo, v_lo);
+    let [r_hi, g_hi, b_hi] = to_rgb(y_hi, u_hi, v_hi);
+
+    let pixels = uint8x8x4_t(
+        narrow(b_lo, b_hi),
+        narrow(g_lo, g_hi),
+        narrow(r_lo, r_hi),
+        vdup_n_u8(0xFF),
+    );
+
+    let dst = &mut dst[..32];
+    // SAFETY: `dst` is 32 bytes long.
+    unsafe { vst4_u8(dst.as_mut_ptr(), pixels) }
+}
+
+fn to_rgb(y: int32x4_t, u: int32x4_t, v: int32x4_t) -> [int32x4_t; 3] {
+    let c = vaddq_s32(vshlq_n_s32::<8>(y), vdupq_n_s32(128));
+    let d = vaddq_s32(u, vdupq_n_s32(-128));
+    let e = vaddq_s32(v, vdupq_n_s32(-128));
+
+    [
+        vshrq_n_s32::<8>(vmlaq_n_s32(c, e, R_V)),
+        vshrq_n_s32::<8>(vmlaq_n_s32(vmlaq_n_s32(c, d, G_U), e, G_V)),
+        vshrq_n_s32::<8>(vmlaq_n_s32(c, d, B_U)),
+    ]
+}
+
+fn widen(values: uint16x8_t) -> [int32x4_t; 2] {
+    [
+        vreinterpretq_s32_u32(vmovl_u16(vget_low_u16(values))),
+        vreinterpretq_s32_u32(vmovl_u16(vget_high_u16(values))),
+    ]
+}
+
+/// Saturates 8 32-bit lanes to u8
+fn narrow(lo: int32x4_t, hi: int32x4_t) -> uint8x8_t {
+    vqmovun_s16(vcombine_s16(vqmovn_s32(lo), vqmovn_s32(hi)))
+}
+
+fn load_u8x8(src: &[u8]) -> uint8x8_t {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&src[..8]);
+    vcreate_u8(u64::from_le_bytes(bytes))
+}
+
+/// Loads 4 bytes into the low lanes
+fn load_u8x4(src: &[u8]) -> uint8x8_t {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&src[..4]);
+    vcreate_u8(u64::from(u32::from_le_bytes(bytes)))
+}
+
+fn store_u8x8(dst: &mut [u8], values: uint8x8_t) {
+    dst[..8].copy_from_slice(&vget_lane_u64::<0>(vreinterpret_u64_u8(values)).to_le_bytes());
+}
+
+/// Stores the 4 low lanes
+fn store_u8x4(dst: &mut [u8], values: uint8x8_t) {
+    dst[..4].copy_from_slice(&vget_lane_u32::<0>(vreinterpret_u32_u8(values)).to_le_bytes());
+}
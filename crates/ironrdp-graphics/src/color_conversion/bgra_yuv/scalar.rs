@@ -0,0 +1,96 @@
+//! Reference implementation, also used for the pixels left over by the SIMD kernels
+
+use super::{B_U, G_U, G_V, R_V, U_B, U_G, U_R, V_B, V_G, V_R, Y_B, Y_G, Y_R};
+
+/// Encodes two rows of BGRA pixels into two rows of luma and one row of subsampled chroma,
+/// starting at pixel `start` (which must be even)
+pub(super) fn encode_420(
+    src0: &[u8],
+    src1: &[u8],
+    y0: &mut [u8],
+    y1: &mut [u8],
+    u: &mut [u8],
+    v: &mut [u8],
+    start: usize,
+) {
+    let width = y0.len();
+
+    for x in (start..width).step_by(2) {
+        let next = (x + 1).min(width - 1);
+
+        let pixels = [pixel(src0, x), pixel(src0, next), pixel(src1, x), pixel(src1, next)];
+
+        y0[x] = luma(pixels[0]);
+        y0[next] = luma(pixels[1]);
+        y1[x] = luma(pixels[2]);
+        y1[next] = luma(pixels[3]);
+
+        let sum = pixels
+            .iter()
+            .fold([0; 3], |acc, p| [acc[0] + p[0], acc[1] + p[1], acc[2] + p[2]]);
+
+        let (cb, cr) = chroma(sum, 10);
+        u[x / 2] = cb;
+        v[x / 2] = cr;
+    }
+}
+
+/// Encodes one row of BGRA pixels into full resolution luma and chroma, starting at pixel `start`
+pub(super) fn encode_444(src: &[u8], y: &mut [u8], u: &mut [u8], v: &mut [u8], start: usize) {
+    for x in start..y.len() {
+        let rgb = pixel(src, x);
+
+        y[x] = luma(rgb);
+        (u[x], v[x]) = chroma(rgb, 8);
+    }
+}
+
+/// Decodes one row of luma and its subsampled chroma row into BGRA pixels, starting at pixel `start`
+pub(super) fn decode_420(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8], start: usize) {
+    for x in start..y.len() {
+        write_pixel(dst, x, y[x], u[x / 2], v[x / 2]);
+    }
+}
+
+/// Decodes one row of full resolution luma and chroma into BGRA pixels, starting at pixel `start`
+pub(super) fn decode_444(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8], start: usize) {
+    for x in start..y.len() {
+        write_pixel(dst, x, y[x], u[x], v[x]);
+    }
+}
+
+/// Returns the `[r, g, b]` components of the BGRA pixel at index `x`
+fn pixel(src: &[u8], x: usize) -> [i32; 3] {
+    let bgra = &src[x * 4..][..4];
+    [i32::from(bgra[2]), i32::from(bgra[1]), i32::from(bgra[0])]
+}
+
+fn luma([r, g, b]: [i32; 3]) -> u8 {
+    clamp((i32::from(Y_R) * r + i32::from(Y_G) * g + i32::from(Y_B) * b + 128) >> 8)
+}
+
+/// Computes the chroma of a single pixel (`shift` = 8) or of the sum of four pixels (`shift` = 10)
+fn chroma([r, g, b]: [i32; 3], shift: u32) -> (u8, u8) {
+    let round = 1 << (shift - 1);
+
+    let u = ((U_R * r + U_G * g + U_B * b + round) >> shift) + 128;
+    let v = ((V_R * r + V_G * g + V_B * b + round) >> shift) + 128;
+
+    (clamp(u), clamp(v))
+}
+
+fn write_pixel(dst: &mut [u8], x: usize, y: u8, u: u8, v: u8) {
+    let c = i32::from(y) << 8;
+    let d = i32::from(u) - 128;
+    let e = i32::from(v) - 128;
+
+    let r = clamp((c + R_V * e + 128) >> 8);
+    let g = clamp((c + G_U * d + G_V * e + 128) >> 8);
+    let b = clamp((c + B_U * d + 128) >> 8);
+
+    dst[x * 4..][..4].copy_from_slice(&[b, g, r, 0xFF]);
+}
+
+fn clamp(value: i32) -> u8 {
+    u8::try_from(value.clamp(0, 255)).expect("value is clamped to the u8 range")
+}
@@ -0,0 +1,250 @@
+//! AVX2 kernels, processing 8 pixels per iteration with the same fixed point math as the scalar code
+//!
+//! Each function returns the number of pixels it converted; the remaining ones are left to the
+//! scalar implementation.
+
+use core::arch::x86_64::{
+    __m256i, _mm256_add_epi32, _mm256_and_si256, _mm256_castsi256_si128, _mm256_cvtepu8_epi32, _mm256_hadd_epi32,
+    _mm256_loadu_si256, _mm256_mullo_epi32, _mm256_packs_epi32, _mm256_packus_epi16, _mm256_permutevar8x32_epi32,
+    _mm256_set1_epi32, _mm256_setr_epi32, _mm256_setr_epi8, _mm256_shuffle_epi8, _mm256_slli_epi32, _mm256_srai_epi32,
+    _mm256_srli_epi32, _mm256_storeu_si256, _mm256_sub_epi32, _mm_cvtsi128_si32, _mm_cvtsi128_si64, _mm_cvtsi32_si128,
+    _mm_cvtsi64_si128,
+};
+
+use super::{B_U, G_U, G_V, R_V, U_B, U_G, U_R, V_B, V_G, V_R, Y_B, Y_G, Y_R};
+
+pub(super) fn encode_420(src0: &[u8], src1: &[u8], y0: &mut [u8], y1: &mut [u8], u: &mut [u8], v: &mut [u8]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: the CPU supports AVX2.
+        unsafe { encode_420_avx2(src0, src1, y0, y1, u, v) }
+    } else {
+        0
+    }
+}
+
+pub(super) fn encode_444(src: &[u8], y: &mut [u8], u: &mut [u8], v: &mut [u8]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: the CPU supports AVX2.
+        unsafe { encode_444_avx2(src, y, u, v) }
+    } else {
+        0
+    }
+}
+
+pub(super) fn decode_420(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: the CPU supports AVX2.
+        unsafe { decode_420_avx2(y, u, v, dst) }
+    } else {
+        0
+    }
+}
+
+pub(super) fn decode_444(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: the CPU supports AVX2.
+        unsafe { decode_444_avx2(y, u, v, dst) }
+    } else {
+        0
+    }
+}
+
+#[target_feature(enable = "avx2")]
+fn encode_420_avx2(src0: &[u8], src1: &[u8], y0: &mut [u8], y1: &mut [u8], u: &mut [u8], v: &mut [u8]) -> usize {
+    let width = y0.len();
+    let mut x = 0;
+
+    while x + 8 <= width {
+        let [r0, g0, b0] = unpack_bgra(load_u8x32(&src0[x * 4..]));
+        let [r1, g1, b1] = unpack_bgra(load_u8x32(&src1[x * 4..]));
+
+        store_u8x8(&mut y0[x..], luma(r0, g0, b0));
+        store_u8x8(&mut y1[x..], luma(r1, g1, b1));
+
+        let r = pair_sums(r0, r1);
+        let g = pair_sums(g0, g1);
+        let b = pair_sums(b0, b1);
+
+        store_u8x4(&mut u[x / 2..], chroma::<10>(r, g, b, [U_R, U_G, U_B]));
+        store_u8x4(&mut v[x / 2..], chroma::<10>(r, g, b, [V_R, V_G, V_B]));
+
+        x += 8;
+    }
+
+    x
+}
+
+#[target_feature(enable = "avx2")]
+fn encode_444_avx2(src: &[u8], y: &mut [u8], u: &mut [u8], v: &mut [u8]) -> usize {
+    let width = y.len();
+    let mut x = 0;
+
+    while x + 8 <= width {
+        let [r, g, b] = unpack_bgra(load_u8x32(&src[x * 4..]));
+
+        store_u8x8(&mut y[x..], luma(r, g, b));
+        store_u8x8(&mut u[x..], chroma::<8>(r, g, b, [U_R, U_G, U_B]));
+        store_u8x8(&mut v[x..], chroma::<8>(r, g, b, [V_R, V_G, V_B]));
+
+        x += 8;
+    }
+
+    x
+}
+
+#[target_feature(enable = "avx2")]
+fn decode_420_avx2(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8]) -> usize {
+    let width = y.len();
+    let duplicate = _mm256_setr_epi32(0, 0, 1, 1, 2, 2, 3, 3);
+    let mut x = 0;
+
+    while x + 8 <= width {
+        let cb = _mm256_permutevar8x32_epi32(load_u8x4(&u[x / 2..]), duplicate);
+        let cr = _mm256_permutevar8x32_epi32(load_u8x4(&v[x / 2..]), duplicate);
+
+        store_u8x32(&mut dst[x * 4..], to_bgra(load_u8x8(&y[x..]), cb, cr));
+
+        x += 8;
+    }
+
+    x
+}
+
+#[target_feature(enable = "avx2")]
+fn decode_444_avx2(y: &[u8], u: &[u8], v: &[u8], dst: &mut [u8]) -> usize {
+    let width = y.len();
+    let mut x = 0;
+
+    while x + 8 <= width {
+        let pixels = to_bgra(load_u8x8(&y[x..]), load_u8x8(&u[x..]), load_u8x8(&v[x..]));
+        store_u8x32(&mut dst[x * 4..], pixels);
+
+        x += 8;
+    }
+
+    x
+}
+
+/// Splits 8 BGRA pixels into their `[r, g, b]` components, one pixel per 32-bit lane
+#[target_feature(enable = "avx2")]
+fn unpack_bgra(pixels: __m256i) -> [__m256i; 3] {
+    let mask = _mm256_set1_epi32(0xFF);
+
+    [
+        _mm256_and_si256(_mm256_srli_epi32::<16>(pixels), mask),
+        _mm256_and_si256(_mm256_srli_epi32::<8>(pixels), mask),
+        _mm256_and_si256(pixels, mask),
+    ]
+}
+
+/// Sums horizontally adjacent pixels of both rows, the 4 results ending up in the low lanes
+#[target_feature(enable = "avx2")]
+fn pair_sums(row0: __m256i, row1: __m256i) -> __m256i {
+    let sum = _mm256_add_epi32(row0, row1);
+    let pairs = _mm256_hadd_epi32(sum, sum);
+    _mm256_permutevar8x32_epi32(pairs, _mm256_setr_epi32(0, 1, 4, 5, 0, 1, 4, 5))
+}
+
+#[target_feature(enable = "avx2")]
+fn luma(r: __m256i, g: __m256i, b: __m256i) -> __m256i {
+    let sum = weighted_sum(r, g, b, [Y_R, Y_G, Y_B].map(i32::from));
+    _mm256_srai_epi32::<8>(_mm256_add_epi32(sum, _mm256_set1_epi32(128)))
+}
+
+/// Computes the chroma of single pixels (`SHIFT` = 8) or of sums of four pixels (`SHIFT` = 10)
+#[target_feature(enable = "avx2")]
+fn chroma<const SHIFT: i32>(r: __m256i, g: __m256i, b: __m256i, coefficients: [i32; 3]) -> __m256i {
+    let sum = weighted_sum(r, g, b, coefficients);
+    let scaled = _mm256_srai_epi32::<SHIFT>(_mm256_add_epi32(sum, _mm256_set1_epi32(1 << (SHIFT - 1))));
+    _mm256_add_epi32(scaled, _mm256_set1_epi32(128))
+}
+
+#[target_feature(enable = "avx2")]
+fn weighted_sum(r: __m256i, g: __m256i, b: __m256i, [kr, kg, kb]: [i32; 3]) -> __m256i {
+    let r = _mm256_mullo_epi32(r, _mm256_set1_epi32(kr));
+    let g = _mm256_mullo_epi32(g, _mm256_set1_epi32(kg));
+    let b = _mm256_mullo_epi32(b, _mm256_set1_epi32(kb));
+    _mm256_add_epi32(_mm256_add_epi32(r, g), b)
+}
+
+/// Converts 8 pixels given as 32-bit YUV lanes into 8 BGRA pixels
+#[target_feature(enable = "avx2")]
+fn to_bgra(y: __m256i, u: __m256i, v: __m256i) -> __m256i {
+    let c = _mm256_add_epi32(_mm256_slli_epi32::<8>(y), _mm256_set1_epi32(128));
+    let d = _mm256_sub_epi32(u, _mm256_set1_epi32(128));
+    let e = _mm256_sub_epi32(v, _mm256_set1_epi32(128));
+
+    let r = _mm256_add_epi32(c, _mm256_mullo_epi32(e, _mm256_set1_epi32(R_V)));
+    let g = _mm256_add_epi32(
+        c,
+        _mm256_add_epi32(
+            _mm256_mullo_epi32(d, _mm256_set1_epi32(G_U)),
+            _mm256_mullo_epi32(e, _mm256_set1_epi32(G_V)),
+        ),
+    );
+    let b = _mm256_add_epi32(c, _mm256_mullo_epi32(d, _mm256_set1_epi32(B_U)));
+
+    let r = _mm256_srai_epi32::<8>(r);
+    let g = _mm256_srai_epi32::<8>(g);
+    let b = _mm256_srai_epi32::<8>(b);
+
+    // Per 128-bit lane: r0..r3, g0..g3, b0..b3, a0..a3 (saturated to u8).
+    let rg = _mm256_packs_epi32(r, g);
+    let ba = _mm256_packs_epi32(b, _mm256_set1_epi32(0xFF));
+    let planar = _mm256_packus_epi16(rg, ba);
+
+    #[rustfmt::skip]
+    let interleave = _mm256_setr_epi8(
+        8, 4, 0, 12, 9, 5, 1, 13, 10, 6, 2, 14, 11, 7, 3, 15,
+        8, 4, 0, 12, 9, 5, 1, 13, 10, 6, 2, 14, 11, 7, 3, 15,
+    );
+
+    _mm256_shuffle_epi8(planar, interleave)
+}
+
+/// Loads 8 bytes, zero-extended into 32-bit lanes
+#[target_feature(enable = "avx2")]
+fn load_u8x8(src: &[u8]) -> __m256i {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&src[..8]);
+    _mm256_cvtepu8_epi32(_mm_cvtsi64_si128(i64::from_le_bytes(bytes)))
+}
+
+/// Loads 4 bytes, zero-extended into the low 32-bit lanes
+#[target_feature(enable = "avx2")]
+fn load_u8x4(src: &[u8]) -> __m256i {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&src[..4]);
+    _mm256_cvtepu8_epi32(_mm_cvtsi32_si128(i32::from_le_bytes(bytes)))
+}
+
+#[target_feature(enable = "avx2")]
+fn load_u8x32(src: &[u8]) -> __m256i {
+    let src = &src[..32];
+    // SAFETY: `src` is 32 bytes long, and unaligned loads are allowed.
+    unsafe { _mm256_loadu_si256(src.as_ptr().cast()) }
+}
+
+/// Stores the 8 32-bit lanes, saturated to u8
+#[target_feature(enable = "avx2")]
+fn store_u8x8(dst: &mut [u8], values: __m256i) {
+    let words = _mm256_packs_epi32(values, values);
+    let packed = _mm256_packus_epi16(words, words);
+    let packed = _mm256_permutevar8x32_epi32(packed, _mm256_setr_epi32(0, 4, 0, 4, 0, 4, 0, 4));
+    dst[..8].copy_from_slice(&_mm_cvtsi128_si64(_mm256_castsi256_si128(packed)).to_le_bytes());
+}
+
+/// Stores the 4 low 32-bit lanes, saturated to u8
+#[target_feature(enable = "avx2")]
+fn store_u8x4(dst: &mut [u8], values: __m256i) {
+    let words = _mm256_packs_epi32(values, values);
+    let packed = _mm256_packus_epi16(words, words);
+    dst[..4].copy_from_slice(&_mm_cvtsi128_si32(_mm256_castsi256_si128(packed)).to_le_bytes());
+}
+
+#[target_feature(enable = "avx2")]
+fn store_u8x32(dst: &mut [u8], values: __m256i) {
+    let dst = &mut dst[..32];
+    // SAFETY: `dst` is 32 bytes long, and unaligned stores are allowed.
+    unsafe { _mm256_storeu_si256(dst.as_mut_ptr().cast(), values) }
+}
@@ -7,6 +7,14 @@ use yuv::{
 
 use crate::image_processing::PixelFormat;
 
+mod bgra_yuv;
+mod ycocg;
+
+pub use bgra_yuv::{
+    bgra_to_yuv, yuv_to_bgra, ChromaSubsampling, ColorConversionError, YuvBuffer, YuvPlanes, YuvPlanesMut,
+};
+pub use ycocg::{reduce_chroma, restore_chroma, rgb_to_ycocg, ycocg_to_rgb, YCoCg};
+
 // FIXME: used for the test suite, we may want to drop it
 pub fn ycbcr_to_argb(input: YCbCrBuffer<'_>, output: &mut [u8]) -> io::Result<()> {
     let len = u32::try_from(output.len()).map_err(io::Error::other)?;
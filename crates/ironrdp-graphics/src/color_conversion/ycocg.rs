@@ -0,0 +1,85 @@
+//! YCoCg-R color transform of the RDP 6.0 planar codec
+//!
+//! The lifting scheme of YCoCg-R is exactly reverted, which makes the transform lossless. The chroma
+//! components need 9 bits, the color loss reduction of [MS-RDPEGDI] 3.1.9.1.2 removes their least
+//! significant bits to store them in a byte.
+
+use super::Rgb;
+
+/// Highest color loss level of the planar codec
+const MAX_COLOR_LOSS_LEVEL: u8 = 7;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct YCoCg {
+    pub y: u8,
+    /// Orange chroma, between -255 and 255
+    pub co: i16,
+    /// Green chroma, between -255 and 255
+    pub cg: i16,
+}
+
+/// Converts a color to YCoCg-R
+///
+/// ```text
+/// |Y |   | 1/4  1/2   1/4|   |R|
+/// |Co| = |  1    0    -1 | * |G|
+/// |Cg|   |-1/2   1   -1/2|   |B|
+/// ```
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "unreachable panic (the luma is an average of the channels)"
+)]
+pub fn rgb_to_ycocg(Rgb { r, g, b }: Rgb) -> YCoCg {
+    let (r, g, b) = (i16::from(r), i16::from(g), i16::from(b));
+
+    let co = r - b;
+    let t = b + (co >> 1);
+    let cg = g - t;
+    let y = t + (cg >> 1);
+
+    YCoCg {
+        y: u8::try_from(y).expect("luma is in the [0, 255] range"),
+        co,
+        cg,
+    }
+}
+
+/// Converts a YCoCg-R color back, the channels are clamped when the chroma was altered
+#[expect(clippy::missing_panics_doc, reason = "unreachable panic (the channels are clamped)")]
+pub fn ycocg_to_rgb(YCoCg { y, co, cg }: YCoCg) -> Rgb {
+    let clamp = |v: i16| u8::try_from(v.clamp(0, 255)).expect("fits into u8 because the value is clamped to [0..256]");
+
+    let t = i16::from(y) - (cg >> 1);
+    let g = cg + t;
+    let b = t - (co >> 1);
+    let r = b + co;
+
+    Rgb {
+        r: clamp(r),
+        g: clamp(g),
+        b: clamp(b),
+    }
+}
+
+/// Removes the `color_loss_level` least significant bits of a chroma component, as stored in the planes
+///
+/// The color loss level is between 1 and 7.
+#[expect(clippy::missing_panics_doc, reason = "unreachable panic (the chroma is clamped)")]
+pub fn reduce_chroma(chroma: i16, color_loss_level: u8) -> u8 {
+    let shift = color_loss_level.clamp(1, MAX_COLOR_LOSS_LEVEL);
+
+    // 9-bit chroma shifted at least once always fits.
+    i8::try_from((chroma >> shift).clamp(-128, 127))
+        .expect("fits into i8 because the value is clamped to [-128..128]")
+        .cast_unsigned()
+}
+
+/// Restores a chroma component stored in the planes, with its removed bits set to zero
+///
+/// As done by the Windows and FreeRDP decoders, the shift overflowing the stored byte is truncated. It
+/// can only happen with chroma values no encoder produces at this color loss level.
+pub fn restore_chroma(value: u8, color_loss_level: u8) -> i16 {
+    let shift = color_loss_level.clamp(1, MAX_COLOR_LOSS_LEVEL);
+
+    i16::from((value << (shift - 1)).cast_signed()) << 1
+}
@@ -109,104 +109,110 @@ fn dwt_horizontal<const SUBBAND_WIDTH: usize>(mut buffer: &mut [i16], dwt: &[i16
 }
 
 pub fn decode(buffer: &mut [i16], temp_buffer: &mut [i16]) {
-    decode_block(&mut buffer[3840..], temp_buffer, 8);
-    decode_block(&mut buffer[3072..], temp_buffer, 16);
-    decode_block(&mut *buffer, temp_buffer, 32);
+    decode_block::<8>(&mut buffer[3840..], temp_buffer);
+    decode_block::<16>(&mut buffer[3072..], temp_buffer);
+    decode_block::<32>(&mut *buffer, temp_buffer);
 }
 
-fn decode_block(buffer: &mut [i16], temp_buffer: &mut [i16], subband_width: usize) {
-    inverse_horizontal(buffer, temp_buffer, subband_width);
-    inverse_vertical(buffer, temp_buffer, subband_width);
+// The inverse DWT is computed row by row, on contiguous slices of coefficients, so that the
+// compiler vectorizes the lifting steps. The columns are never walked with a stride.
+fn decode_block<const SUBBAND_WIDTH: usize>(buffer: &mut [i16], temp_buffer: &mut [i16]) {
+    inverse_horizontal::<SUBBAND_WIDTH>(buffer, temp_buffer);
+    inverse_vertical::<SUBBAND_WIDTH>(buffer, temp_buffer);
 }
 
 // Inverse DWT in horizontal direction, results in 2 sub-bands in L, H order in output buffer
 // The 4 sub-bands are stored in HL(0), LH(1), HH(2), LL(3) order.
 // The lower part L uses LL(3) and HL(0).
 // The higher part H uses LH(1) and HH(2).
-fn inverse_horizontal(mut buffer: &[i16], temp_buffer: &mut [i16], subband_width: usize) {
-    let total_width = subband_width * 2;
-    let squared_subband_width = subband_width.pow(2);
+fn inverse_horizontal<const SUBBAND_WIDTH: usize>(mut buffer: &[i16], temp_buffer: &mut [i16]) {
+    let total_width = SUBBAND_WIDTH * 2;
+    let squared_subband_width = SUBBAND_WIDTH.pow(2);
 
-    let mut hl = buffer.split_to(squared_subband_width);
-    let mut lh = buffer.split_to(squared_subband_width);
-    let mut hh = buffer.split_to(squared_subband_width);
-    let mut ll = buffer;
+    let hl = buffer.split_to(squared_subband_width);
+    let lh = buffer.split_to(squared_subband_width);
+    let hh = buffer.split_to(squared_subband_width);
+    let ll = &buffer[..squared_subband_width];
 
-    let (mut l_dst, mut h_dst) = temp_buffer.split_at_mut(squared_subband_width * 2);
+    let (l_dst, h_dst) = temp_buffer[..squared_subband_width * 4].split_at_mut(squared_subband_width * 2);
 
-    for _ in 0..subband_width {
-        // Even coefficients
-        l_dst[0] = i32_to_i16_possible_truncation(i32::from(ll[0]) - ((i32::from(hl[0]) + i32::from(hl[0]) + 1) >> 1));
-        h_dst[0] = i32_to_i16_possible_truncation(i32::from(lh[0]) - ((i32::from(hh[0]) + i32::from(hh[0]) + 1) >> 1));
-        for n in 1..subband_width {
-            let x = n * 2;
-            l_dst[x] =
-                i32_to_i16_possible_truncation(i32::from(ll[n]) - ((i32::from(hl[n - 1]) + i32::from(hl[n]) + 1) >> 1));
-            h_dst[x] =
-                i32_to_i16_possible_truncation(i32::from(lh[n]) - ((i32::from(hh[n - 1]) + i32::from(hh[n]) + 1) >> 1));
-        }
+    let l_rows = ll
+        .chunks_exact(SUBBAND_WIDTH)
+        .zip(hl.chunks_exact(SUBBAND_WIDTH))
+        .zip(l_dst.chunks_exact_mut(total_width));
+    let h_rows = lh
+        .chunks_exact(SUBBAND_WIDTH)
+        .zip(hh.chunks_exact(SUBBAND_WIDTH))
+        .zip(h_dst.chunks_exact_mut(total_width));
 
-        // Odd coefficients
-        for n in 0..subband_width - 1 {
-            let x = n * 2;
-            l_dst[x + 1] = i32_to_i16_possible_truncation(
-                i32::from(hl[n] << 1) + ((i32::from(l_dst[x]) + i32::from(l_dst[x + 2])) >> 1),
-            );
-            h_dst[x + 1] = i32_to_i16_possible_truncation(
-                i32::from(hh[n] << 1) + ((i32::from(h_dst[x]) + i32::from(h_dst[x + 2])) >> 1),
-            );
-        }
-        let n = subband_width - 1;
-        let x = n * 2;
-        l_dst[x + 1] = i32_to_i16_possible_truncation(i32::from(hl[n] << 1) + i32::from(l_dst[x]));
-        h_dst[x + 1] = i32_to_i16_possible_truncation(i32::from(hh[n] << 1) + i32::from(h_dst[x]));
-
-        hl = &hl[subband_width..];
-        lh = &lh[subband_width..];
-        hh = &hh[subband_width..];
-        ll = &ll[subband_width..];
-
-        l_dst = &mut l_dst[total_width..];
-        h_dst = &mut h_dst[total_width..];
+    for ((low, high), dst) in l_rows.chain(h_rows) {
+        inverse_horizontal_row::<SUBBAND_WIDTH>(low, high, dst);
     }
 }
 
-fn inverse_vertical(mut buffer: &mut [i16], mut temp_buffer: &[i16], subband_width: usize) {
-    let total_width = subband_width * 2;
+// The even and odd coefficients of a row are computed in separate planes, then interleaved.
+fn inverse_horizontal_row<const SUBBAND_WIDTH: usize>(low: &[i16], high: &[i16], dst: &mut [i16]) {
+    let mut even = [0i16; SUBBAND_WIDTH];
+    let mut odd = [0i16; SUBBAND_WIDTH];
+    let last = SUBBAND_WIDTH - 1;
 
-    for _ in 0..total_width {
-        buffer[0] = i32_to_i16_possible_truncation(
-            i32::from(temp_buffer[0]) - ((i32::from(temp_buffer[subband_width * total_width]) * 2 + 1) >> 1),
-        );
+    // Even coefficients
+    inverse_lift_even(&mut even[..1], &low[..1], &high[..1], &high[..1]);
+    inverse_lift_even(&mut even[1..], &low[1..], &high[..last], &high[1..]);
 
-        let mut l = temp_buffer;
-        let mut lh = &temp_buffer[(subband_width - 1) * total_width..];
-        let mut h = &temp_buffer[subband_width * total_width..];
-        let mut dst = &mut *buffer;
+    // Odd coefficients
+    inverse_lift_odd(&mut odd[..last], &high[..last], &even[..last], &even[1..]);
+    inverse_lift_odd(&mut odd[last..], &high[last..], &even[last..], &even[last..]);
 
-        for _ in 1..subband_width {
-            l = &l[total_width..];
-            lh = &lh[total_width..];
-            h = &h[total_width..];
+    for ((pair, even), odd) in dst.chunks_exact_mut(2).zip(even).zip(odd) {
+        pair[0] = even;
+        pair[1] = odd;
+    }
+}
 
-            // Even coefficients
-            dst[2 * total_width] =
-                i32_to_i16_possible_truncation(i32::from(l[0]) - ((i32::from(lh[0]) + i32::from(h[0]) + 1) >> 1));
+fn inverse_vertical<const SUBBAND_WIDTH: usize>(buffer: &mut [i16], temp_buffer: &[i16]) {
+    let total_width = SUBBAND_WIDTH * 2;
+    let last = SUBBAND_WIDTH - 1;
+
+    let (l_src, h_src) = temp_buffer[..total_width * total_width].split_at(SUBBAND_WIDTH * total_width);
+
+    // Even coefficients
+    for n in 0..SUBBAND_WIDTH {
+        let dst = &mut buffer[2 * n * total_width..(2 * n + 1) * total_width];
+        inverse_lift_even(
+            dst,
+            row(l_src, total_width, n),
+            row(h_src, total_width, n.saturating_sub(1)),
+            row(h_src, total_width, n),
+        );
+    }
 
-            // Odd coefficients
-            dst[total_width] = i32_to_i16_possible_truncation(
-                i32::from(lh[0] << 1) + ((i32::from(dst[0]) + i32::from(dst[2 * total_width])) >> 1),
-            );
+    // Odd coefficients
+    for n in 0..SUBBAND_WIDTH {
+        let (before, after) = buffer.split_at_mut((2 * n + 1) * total_width);
+        let (dst, after) = after.split_at_mut(total_width);
+        let even_prev = &before[2 * n * total_width..];
+        let even_next = if n < last { &after[..total_width] } else { even_prev };
+        inverse_lift_odd(dst, row(h_src, total_width, n), even_prev, even_next);
+    }
+}
 
-            dst = &mut dst[2 * total_width..];
-        }
+fn row(rows: &[i16], width: usize, n: usize) -> &[i16] {
+    &rows[n * width..(n + 1) * width]
+}
 
-        dst[total_width] = i32_to_i16_possible_truncation(
-            i32::from(lh[total_width] << 1) + ((i32::from(dst[0]) + i32::from(dst[0])) >> 1),
-        );
+// Even coefficients of the inverse lifting: low - ((high_prev + high + 1) >> 1)
+fn inverse_lift_even(dst: &mut [i16], low: &[i16], high_prev: &[i16], high: &[i16]) {
+    for (((dst, &low), &high_prev), &high) in dst.iter_mut().zip(low).zip(high_prev).zip(high) {
+        *dst = i32_to_i16_possible_truncation(i32::from(low) - ((i32::from(high_prev) + i32::from(high) + 1) >> 1));
+    }
+}
 
-        temp_buffer = &temp_buffer[1..];
-        buffer = &mut buffer[1..];
+// Odd coefficients of the inverse lifting: 2 * high + ((even_prev + even_next) >> 1)
+fn inverse_lift_odd(dst: &mut [i16], high: &[i16], even_prev: &[i16], even_next: &[i16]) {
+    for (((dst, &high), &even_prev), &even_next) in dst.iter_mut().zip(high).zip(even_prev).zip(even_next) {
+        *dst =
+            i32_to_i16_possible_truncation(i32::from(high << 1) + ((i32::from(even_prev) + i32::from(even_next)) >> 1));
     }
 }
 
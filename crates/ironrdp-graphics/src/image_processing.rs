@@ -294,6 +294,7 @@ struct Point {
     y: usize,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rgba {
     pub r: u8,
     pub g: u8,
@@ -2,14 +2,18 @@
 #![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
 #![allow(clippy::arithmetic_side_effects)] // FIXME: remove
 
+pub mod codec;
 pub mod color_conversion;
 pub mod diff;
 pub mod dwt;
 pub mod image_processing;
 pub mod pointer;
+pub mod progressive;
+pub mod quality;
 pub mod quantization;
 pub mod rdp6;
 pub mod rectangle_processing;
+pub mod rfx;
 pub mod rle;
 pub mod rlgr;
 pub mod subband_reconstruction;
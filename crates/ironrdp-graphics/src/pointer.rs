@@ -18,6 +18,11 @@
 //! - `andMask == 0` -> dst_color Copy pixel from xorMask
 //! - andMask == 1, xorMask == 0(black color) -> Transparent pixel
 //! - andMask == 1, xorMask == 1(white color) -> Pixel is inverted
+//! - andMask == 1, any other opaque xorMask color -> Pixel is XORed with the screen color
+//!
+//! 32 bpp xor masks carry an alpha channel, which is blended as is. Windows also sends 32 bpp
+//! pointers with the alpha channel left at zero everywhere, these are treated as opaque and only
+//! the andMask defines their transparency.
 
 use ironrdp_core::ReadCursor;
 use ironrdp_pdu::pointer::{ColorPointerAttribute, LargePointerAttribute, PointerAttribute};
@@ -82,6 +87,12 @@ pub struct DecodedPointer {
     pub hotspot_x: u16,
     pub hotspot_y: u16,
     pub bitmap_data: Vec<u8>,
+    /// Whether some pixels are XORed with the screen instead of being drawn over it
+    ///
+    /// With [`PointerBitmapTarget::Software`], [`composite_pixel`] renders these pixels. With
+    /// [`PointerBitmapTarget::Accelerated`], they are approximated with a check pattern, and renderers able
+    /// to XOR with the screen may want to fall back to the software bitmap.
+    pub inverted: bool,
 }
 
 /// Pointer bitmap rendering target. Defines properties and format of the decoded bitmap.
@@ -90,9 +101,9 @@ pub enum PointerBitmapTarget {
     /// Software rendering target will produce RGBA bitmaps with premultiplied alpha.
     ///
     /// Colors with alpha channel set to 0x00 are always invisible no matter their color
-    /// component. We could take advantage of that, and use a special color to represent
-    /// inverted pixels. [0xFF, 0xFF, 0xFF, 0x00] is used for such purpose in software
-    /// rendering mode.
+    /// component. We could take advantage of that, and store the color XORed with the screen
+    /// in such pixels: [0xFF, 0xFF, 0xFF, 0x00] represents an inverted pixel, and
+    /// [0x00, 0x00, 0x00, 0x00] a transparent one. See [`composite_pixel`].
     Software,
     /// Accelerated rendering target will produce RGBA bitmaps with non-premultiplied alpha.
     /// Inverted pixels will be rendered following the check pattern.
@@ -123,6 +134,7 @@ impl DecodedPointer {
             bitmap_data: Vec::new(),
             hotspot_x: 0,
             hotspot_y: 0,
+            inverted: false,
         }
     }
 
@@ -214,7 +226,11 @@ impl DecodedPointer {
             });
         }
 
+        // Alpha-less 32 bpp pointers are sent with a zero alpha channel, which would make them invisible.
+        let force_opaque = data.xor_bpp == 32 && data.xor_mask.chunks_exact(4).all(|pixel| pixel[3] == 0);
+
         let mut bitmap_data = Vec::new();
+        let mut inverted = false;
 
         for row_idx in 0..data.height {
             // For non-monochrome cursors we read strides from bottom to top
@@ -233,42 +249,38 @@ impl DecodedPointer {
             let mut color_reader = ColorStrideReader::new(data.xor_bpp, xor_stride)?;
             let mut bitmask_reader = BitmaskStrideReader::new(and_stride);
 
-            let compute_inverted_pixel = if target.should_invert_pixels_using_check_pattern() {
-                |row_idx: u16, col_idx: u16| -> [u8; 4] {
+            let compute_xor_pixel = if target.should_invert_pixels_using_check_pattern() {
+                |[r, g, b, _]: [u8; 4], row_idx: u16, col_idx: u16| -> [u8; 4] {
                     // Checkered pattern is used to represent inverted pixels.
                     if (row_idx + col_idx) % 2 == 0 {
-                        [0xff, 0xff, 0xff, 0xff]
+                        [r, g, b, 0xff]
                     } else {
                         [0x00, 0x00, 0x00, 0xff]
                     }
                 }
             } else {
-                |_, _| [0xFF, 0xFF, 0xFF, 0x00]
+                |[r, g, b, _]: [u8; 4], _, _| [r, g, b, 0x00]
             };
 
             for col_idx in 0..data.width {
                 let and_bit = bitmask_reader.next_bit(&mut and_stride_cursor);
-                let color = color_reader.next_pixel(&mut xor_stride_cursor);
+                let mut color = color_reader.next_pixel(&mut xor_stride_cursor);
+
+                if force_opaque {
+                    color[3] = 0xff;
+                }
 
                 if and_bit == 1 && color == [0, 0, 0, 0xff] {
                     // Force transparent pixel (The only way to get a transparent pixel with
                     // non-32-bit cursors)
                     bitmap_data.extend_from_slice(&[0, 0, 0, 0]);
-                } else if and_bit == 1 && color == [0xff, 0xff, 0xff, 0xff] {
-                    // Inverted pixel.
-                    bitmap_data.extend_from_slice(&compute_inverted_pixel(row_idx, col_idx));
+                } else if and_bit == 1 && color[3] == 0xff {
+                    // The screen color is XORed with the pointer color, white inverts it.
+                    inverted = true;
+                    bitmap_data.extend_from_slice(&compute_xor_pixel(color, row_idx, col_idx));
                 } else if target.should_premultiply_alpha() {
-                    // Calculate premultiplied alpha via integer arithmetic
-                    let with_premultiplied_alpha = [
-                        u8::try_from((u16::from(color[0]) * u16::from(color[0])) >> 8)
-                            .expect("(u16 >> 8) fits into u8"),
-                        u8::try_from((u16::from(color[1]) * u16::from(color[1])) >> 8)
-                            .expect("(u16 >> 8) fits into u8"),
-                        u8::try_from((u16::from(color[2]) * u16::from(color[2])) >> 8)
-                            .expect("(u16 >> 8) fits into u8"),
-                        color[3],
-                    ];
-                    bitmap_data.extend_from_slice(&with_premultiplied_alpha);
+                    let [r, g, b, a] = color;
+                    bitmap_data.extend_from_slice(&[premultiply(r, a), premultiply(g, a), premultiply(b, a), a]);
                 } else {
                     bitmap_data.extend_from_slice(&color);
                 }
@@ -281,10 +293,31 @@ impl DecodedPointer {
             bitmap_data,
             hotspot_x: data.hot_spot_x,
             hotspot_y: data.hot_spot_y,
+            inverted,
         })
     }
 }
 
+/// Composites a pixel decoded for [`PointerBitmapTarget::Software`] over an opaque RGBA screen pixel
+pub fn composite_pixel(pointer: [u8; 4], screen: [u8; 4]) -> [u8; 4] {
+    let [r, g, b, a] = pointer;
+    let [dst_r, dst_g, dst_b, _] = screen;
+
+    if a == 0 {
+        // Zero alpha pixels hold the color XORed with the screen, black is fully transparent.
+        return [dst_r ^ r, dst_g ^ g, dst_b ^ b, 0xff];
+    }
+
+    let blend = |src: u8, dst: u8| src.saturating_add(premultiply(dst, 0xff - a));
+
+    [blend(r, dst_r), blend(g, dst_g), blend(b, dst_b), 0xff]
+}
+
+/// Multiplies a color channel by an alpha value, rounding to the nearest integer
+fn premultiply(color: u8, alpha: u8) -> u8 {
+    u8::try_from((u16::from(color) * u16::from(alpha) + 127) / 255).expect("color * alpha / 255 fits into u8")
+}
+
 #[derive(Clone, Copy)]
 struct Stride {
     length: usize,
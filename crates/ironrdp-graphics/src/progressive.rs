@@ -0,0 +1,803 @@
+//! RemoteFX Progressive decoding
+//!
+//! The tiles of a surface are sent either at full quality, or with a first pass at a low quality followed by
+//! upgrade passes. The coefficients of each tile are kept from one pass to the next, the upgrade passes only
+//! carrying the bits refining them, see [MS-RDPEGFX] 3.3.8.2 RemoteFX Progressive Decoding.
+
+use core::fmt;
+use std::collections::HashMap;
+
+use ironrdp_core::{Decode as _, DecodeError, ReadCursor};
+use ironrdp_pdu::codecs::progressive::{
+    Block, ComponentCodecQuant, FirstTile, ProgressiveCodecQuant, RegionFlags, RegionPdu, Tile, TileFlags, UpgradeTile,
+    FULL_QUALITY,
+};
+use ironrdp_pdu::codecs::rfx::EntropyAlgorithm;
+use ironrdp_pdu::geometry::InclusiveRectangle;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::color_conversion::{ycbcr_to_rgba, YCbCrBuffer};
+use crate::rfx::{DECODED_TILE_SIZE, TILE_SIZE};
+use crate::rlgr::{self, RlgrError};
+use crate::utils::BitReader;
+use crate::{dwt, subband_reconstruction};
+
+const TILE_PIXELS: usize = 64 * 64;
+
+const KP_MAX: u32 = 80;
+const UP_GR: u32 = 4;
+const DN_GR: u32 = 6;
+
+/// Offset and length of the sub-bands, in HL1, LH1, HH1, HL2, LH2, HH2, HL3, LH3, HH3, LL3 order
+const BANDS: [(usize, usize); 10] = [
+    (0, 1024),
+    (1024, 1024),
+    (2048, 1024),
+    (3072, 256),
+    (3328, 256),
+    (3584, 256),
+    (3840, 64),
+    (3904, 64),
+    (3968, 64),
+    (4032, 64),
+];
+
+/// Offset and length of the sub-bands with the reduce-extrapolate DWT, in the same order as [`BANDS`]
+///
+/// The low-pass sub-bands are one coefficient larger, e.g. the 64 coefficients of a line are split into 33
+/// low-pass and 31 high-pass coefficients on the first level.
+const EXTRAPOLATED_BANDS: [(usize, usize); 10] = [
+    (0, 1023),
+    (1023, 1023),
+    (2046, 961),
+    (3007, 272),
+    (3279, 272),
+    (3551, 256),
+    (3807, 72),
+    (3879, 72),
+    (3951, 64),
+    (4015, 81),
+];
+
+/// Index of the LL3 sub-band in [`BANDS`] and [`EXTRAPOLATED_BANDS`]
+const LL3: usize = 9;
+
+#[derive(Debug)]
+pub enum ProgressiveError {
+    Decode(DecodeError),
+    Rlgr(RlgrError),
+    UnknownSurface(u16),
+    TileOutOfBounds {
+        x_idx: u16,
+        y_idx: u16,
+    },
+    InvalidQuantIndex {
+        index: u8,
+        count: usize,
+    },
+    InvalidQuality {
+        quality: u8,
+        count: usize,
+    },
+    /// A tile present twice in the same region
+    DuplicateTile {
+        x_idx: u16,
+        y_idx: u16,
+    },
+    /// An upgrade pass for a tile which has no first pass
+    MissingFirstPass {
+        x_idx: u16,
+        y_idx: u16,
+    },
+    /// The bit positions of an upgrade pass are above the ones of the previous pass, or its data is not consumed
+    InvalidUpgrade {
+        x_idx: u16,
+        y_idx: u16,
+    },
+    /// The output image is smaller than the surface
+    ImageTooSmall,
+}
+
+impl fmt::Display for ProgressiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(_) => write!(f, "invalid progressive bitmap stream"),
+            Self::Rlgr(_) => write!(f, "RLGR error"),
+            Self::UnknownSurface(surface_id) => write!(f, "unknown surface {surface_id}"),
+            Self::TileOutOfBounds { x_idx, y_idx } => write!(f, "tile ({x_idx}, {y_idx}) is out of the surface"),
+            Self::InvalidQuantIndex { index, count } => {
+                write!(f, "invalid quantization values index {index} ({count} values)")
+            }
+            Self::InvalidQuality { quality, count } => {
+                write!(f, "invalid quality {quality} ({count} progressive quantization values)")
+            }
+            Self::DuplicateTile { x_idx, y_idx } => write!(f, "tile ({x_idx}, {y_idx}) is repeated in its region"),
+            Self::MissingFirstPass { x_idx, y_idx } => {
+                write!(f, "upgrade of tile ({x_idx}, {y_idx}) without first pass")
+            }
+            Self::InvalidUpgrade { x_idx, y_idx } => write!(f, "invalid upgrade of tile ({x_idx}, {y_idx})"),
+            Self::ImageTooSmall => write!(f, "the image is smaller than the surface"),
+        }
+    }
+}
+
+impl core::error::Error for ProgressiveError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Decode(error) => Some(error),
+            Self::Rlgr(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for ProgressiveError {
+    fn from(error: DecodeError) -> Self {
+        Self::Decode(error)
+    }
+}
+
+impl From<RlgrError> for ProgressiveError {
+    fn from(error: RlgrError) -> Self {
+        Self::Rlgr(error)
+    }
+}
+
+/// Decoder of the RemoteFX Progressive bitmaps of the surfaces of a graphics pipeline
+///
+/// The surfaces are registered with their size as they are created, and the state of their tiles is kept until
+/// they are deleted.
+#[derive(Default)]
+pub struct ProgressiveDecoder {
+    surfaces: HashMap<u16, Surface>,
+}
+
+impl fmt::Debug for ProgressiveDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressiveDecoder")
+            .field("surfaces", &self.surfaces.keys())
+            .finish()
+    }
+}
+
+impl ProgressiveDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a surface, replacing the state of the tiles of a previous surface with the same identifier
+    pub fn create_surface(&mut self, surface_id: u16, width: u16, height: u16) {
+        self.surfaces.insert(surface_id, Surface::new(width, height));
+    }
+
+    pub fn delete_surface(&mut self, surface_id: u16) {
+        self.surfaces.remove(&surface_id);
+    }
+
+    /// Decodes the bitmap data of a RDPGFX_WIRE_TO_SURFACE_PDU_2 into `image`
+    ///
+    /// `image` holds the RGBA pixels of the surface, `stride` bytes per row. The updated rectangles, the
+    /// intersections of the tiles with the rectangles of their region, are returned.
+    pub fn decode(
+        &mut self,
+        surface_id: u16,
+        data: &[u8],
+        image: &mut [u8],
+        stride: usize,
+    ) -> Result<Vec<InclusiveRectangle>, ProgressiveError> {
+        let surface = self
+            .surfaces
+            .get_mut(&surface_id)
+            .ok_or(ProgressiveError::UnknownSurface(surface_id))?;
+
+        let rows = usize::from(surface.height).saturating_sub(1);
+        if stride < usize::from(surface.width) * 4 || image.len() < rows * stride + usize::from(surface.width) * 4 {
+            return Err(ProgressiveError::ImageTooSmall);
+        }
+
+        let mut updated = Vec::new();
+        let mut src = ReadCursor::new(data);
+
+        while !src.is_empty() {
+            if let Block::Region(region) = Block::decode(&mut src)? {
+                surface.decode_region(&region, image, stride, &mut updated)?;
+            }
+        }
+
+        Ok(updated)
+    }
+}
+
+struct Surface {
+    width: u16,
+    height: u16,
+    grid_width: u16,
+    tiles: Vec<Option<Box<TileState>>>,
+}
+
+impl Surface {
+    fn new(width: u16, height: u16) -> Self {
+        let grid_width = width.div_ceil(TILE_SIZE);
+        let grid_height = height.div_ceil(TILE_SIZE);
+
+        Self {
+            width,
+            height,
+            grid_width,
+            tiles: core::iter::repeat_with(|| None)
+                .take(usize::from(grid_width) * usize::from(grid_height))
+                .collect(),
+        }
+    }
+
+    /// Decodes the tiles of `region`, then copies them in `image`
+    ///
+    /// The tiles of a region are distinct, so they are decoded independently: in parallel with the `rayon`
+    /// feature, as for RemoteFX.
+    fn decode_region(
+        &mut self,
+        region: &RegionPdu<'_>,
+        image: &mut [u8],
+        stride: usize,
+        updated: &mut Vec<InclusiveRectangle>,
+    ) -> Result<(), ProgressiveError> {
+        let extrapolate = region.flags.contains(RegionFlags::DWT_REDUCE_EXTRAPOLATE);
+
+        // The states are moved out of the surface while their tiles are decoded
+        let mut jobs = Vec::with_capacity(region.tiles.len());
+        let taken = self.take_states(region, &mut jobs);
+
+        let mut pixels = vec![0u8; jobs.len() * DECODED_TILE_SIZE];
+
+        let decoded = taken.and_then(|()| {
+            #[cfg(not(feature = "rayon"))]
+            let (chunks, jobs) = (pixels.chunks_mut(DECODED_TILE_SIZE), jobs.iter_mut());
+            #[cfg(feature = "rayon")]
+            let (chunks, jobs) = (pixels.par_chunks_mut(DECODED_TILE_SIZE), jobs.par_iter_mut());
+
+            chunks
+                .zip(jobs)
+                .try_for_each(|(pixels, job)| job.decode(region, extrapolate, pixels))
+        });
+
+        for job in &mut jobs {
+            self.tiles[job.index] = job.state.take();
+        }
+        decoded?;
+
+        for (job, pixels) in jobs.iter().zip(pixels.chunks_exact(DECODED_TILE_SIZE)) {
+            let (x_idx, y_idx) = job.tile.position();
+            self.apply_tile(x_idx, y_idx, pixels, region, image, stride, updated);
+        }
+
+        Ok(())
+    }
+
+    /// Moves the states of the tiles of `region` into `jobs`
+    fn take_states<'a>(
+        &mut self,
+        region: &'a RegionPdu<'a>,
+        jobs: &mut Vec<TileJob<'a>>,
+    ) -> Result<(), ProgressiveError> {
+        let mut seen = vec![false; self.tiles.len()];
+
+        for tile in &region.tiles {
+            let (x_idx, y_idx) = tile.position();
+            let index = (x_idx < self.grid_width)
+                .then(|| usize::from(y_idx) * usize::from(self.grid_width) + usize::from(x_idx))
+                .filter(|&index| index < self.tiles.len())
+                .ok_or(ProgressiveError::TileOutOfBounds { x_idx, y_idx })?;
+
+            if core::mem::replace(&mut seen[index], true) {
+                return Err(ProgressiveError::DuplicateTile { x_idx, y_idx });
+            }
+
+            let state = match tile {
+                Tile::Simple(_) | Tile::First(_) => self.tiles[index].take().unwrap_or_default(),
+                Tile::Upgrade(_) => self.tiles[index]
+                    .take()
+                    .ok_or(ProgressiveError::MissingFirstPass { x_idx, y_idx })?,
+            };
+
+            jobs.push(TileJob {
+                index,
+                tile,
+                state: Some(state),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Copies the pixels of a tile inside the rectangles of its region
+    #[expect(
+        clippy::too_many_arguments,
+        reason = "the tile is copied in the image at its position"
+    )]
+    fn apply_tile(
+        &self,
+        x_idx: u16,
+        y_idx: u16,
+        pixels: &[u8],
+        region: &RegionPdu<'_>,
+        image: &mut [u8],
+        stride: usize,
+        updated: &mut Vec<InclusiveRectangle>,
+    ) {
+        let tile_left = x_idx * TILE_SIZE;
+        let tile_top = y_idx * TILE_SIZE;
+        let tile_right = (tile_left + TILE_SIZE).min(self.width);
+        let tile_bottom = (tile_top + TILE_SIZE).min(self.height);
+
+        for rectangle in &region.rectangles {
+            let left = rectangle.x.max(tile_left);
+            let top = rectangle.y.max(tile_top);
+            let right = rectangle.x.saturating_add(rectangle.width).min(tile_right);
+            let bottom = rectangle.y.saturating_add(rectangle.height).min(tile_bottom);
+
+            if left >= right || top >= bottom {
+                continue;
+            }
+
+            let row_len = usize::from(right - left) * 4;
+            for y in top..bottom {
+                let src_start =
+                    usize::from(y - tile_top) * usize::from(TILE_SIZE) * 4 + usize::from(left - tile_left) * 4;
+                let dst_start = usize::from(y) * stride + usize::from(left) * 4;
+                image[dst_start..dst_start + row_len].copy_from_slice(&pixels[src_start..src_start + row_len]);
+            }
+
+            updated.push(InclusiveRectangle {
+                left,
+                top,
+                right: right - 1,
+                bottom: bottom - 1,
+            });
+        }
+    }
+}
+
+/// Tile of a region, with the state it is decoded from
+struct TileJob<'a> {
+    /// Index of the tile in the surface
+    index: usize,
+    tile: &'a Tile<'a>,
+    /// Moved back to the surface once decoded
+    state: Option<Box<TileState>>,
+}
+
+impl TileJob<'_> {
+    /// Decodes the tile into `pixels`, which must be [`DECODED_TILE_SIZE`] bytes long
+    fn decode(&mut self, region: &RegionPdu<'_>, extrapolate: bool, pixels: &mut [u8]) -> Result<(), ProgressiveError> {
+        let state = self.state.as_mut().expect("taken from the surface");
+        let mut planes = [[0i16; TILE_PIXELS]; 3];
+
+        match self.tile {
+            Tile::Simple(tile) | Tile::First(tile) => state.decode_first(tile, region, extrapolate, &mut planes)?,
+            Tile::Upgrade(tile) => state.decode_upgrade(tile, region, extrapolate, &mut planes)?,
+        }
+
+        let [y, cb, cr] = &planes;
+        ycbcr_to_rgba(YCbCrBuffer { y, cb, cr }, pixels).map_err(RlgrError::Io)?;
+
+        Ok(())
+    }
+}
+
+/// Coefficients of a tile, kept from one pass to the next
+#[derive(Default)]
+struct TileState {
+    components: [ComponentState; 3],
+}
+
+impl TileState {
+    fn decode_first(
+        &mut self,
+        tile: &FirstTile<'_>,
+        region: &RegionPdu<'_>,
+        extrapolate: bool,
+        planes: &mut [[i16; TILE_PIXELS]; 3],
+    ) -> Result<(), ProgressiveError> {
+        let quants = [
+            quant_values(region, tile.quant_idx_y)?,
+            quant_values(region, tile.quant_idx_cb)?,
+            quant_values(region, tile.quant_idx_cr)?,
+        ];
+        let prog_quant = prog_quant_values(region, tile.quality)?;
+        let prog_quants = [prog_quant.y, prog_quant.cb, prog_quant.cr];
+        let difference = tile.flags.contains(TileFlags::DIFFERENCE);
+
+        for (i, data) in [tile.y_data, tile.cb_data, tile.cr_data].into_iter().enumerate() {
+            let bit_pos = add_quant(quants[i], prog_quants[i]);
+            self.components[i].decode_first(data, bit_pos, difference, extrapolate, &mut planes[i])?;
+        }
+
+        Ok(())
+    }
+
+    fn decode_upgrade(
+        &mut self,
+        tile: &UpgradeTile<'_>,
+        region: &RegionPdu<'_>,
+        extrapolate: bool,
+        planes: &mut [[i16; TILE_PIXELS]; 3],
+    ) -> Result<(), ProgressiveError> {
+        let quants = [
+            quant_values(region, tile.quant_idx_y)?,
+            quant_values(region, tile.quant_idx_cb)?,
+            quant_values(region, tile.quant_idx_cr)?,
+        ];
+        let prog_quant = prog_quant_values(region, tile.quality)?;
+        let prog_quants = [prog_quant.y, prog_quant.cb, prog_quant.cr];
+        let data = [
+            (tile.y_srl_data, tile.y_raw_data),
+            (tile.cb_srl_data, tile.cb_raw_data),
+            (tile.cr_srl_data, tile.cr_raw_data),
+        ];
+
+        for (i, (srl, raw)) in data.into_iter().enumerate() {
+            let bit_pos = add_quant(quants[i], prog_quants[i]);
+            self.components[i]
+                .decode_upgrade(srl, raw, bit_pos, extrapolate, &mut planes[i])
+                .ok_or(ProgressiveError::InvalidUpgrade {
+                    x_idx: tile.x_idx,
+                    y_idx: tile.y_idx,
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+fn quant_values(region: &RegionPdu<'_>, index: u8) -> Result<ComponentCodecQuant, ProgressiveError> {
+    region
+        .quant_values
+        .get(usize::from(index))
+        .copied()
+        .ok_or(ProgressiveError::InvalidQuantIndex {
+            index,
+            count: region.quant_values.len(),
+        })
+}
+
+fn prog_quant_values(region: &RegionPdu<'_>, quality: u8) -> Result<ProgressiveCodecQuant, ProgressiveError> {
+    if quality == FULL_QUALITY {
+        // No bit of the coefficients is left for the upgrade passes
+        return Ok(ProgressiveCodecQuant::default());
+    }
+
+    region
+        .quant_prog_values
+        .get(usize::from(quality))
+        .copied()
+        .ok_or(ProgressiveError::InvalidQuality {
+            quality,
+            count: region.quant_prog_values.len(),
+        })
+}
+
+/// Bit positions of the sub-bands, in the order of [`BANDS`]
+fn add_quant(quant: ComponentCodecQuant, prog_quant: ComponentCodecQuant) -> [u8; 10] {
+    let order = |q: ComponentCodecQuant| [q.hl1, q.lh1, q.hh1, q.hl2, q.lh2, q.hh2, q.hl3, q.lh3, q.hh3, q.ll3];
+
+    let mut bit_pos = order(quant);
+    for (bit_pos, prog_quant) in bit_pos.iter_mut().zip(order(prog_quant)) {
+        *bit_pos += prog_quant;
+    }
+    bit_pos
+}
+
+struct ComponentState {
+    /// Dequantized coefficients
+    coefficients: [i16; TILE_PIXELS],
+    /// Coefficients as decoded by the passes, non-zero once a coefficient is significant
+    sign: [i16; TILE_PIXELS],
+    /// Bit position of the least significant bit known for each sub-band
+    bit_pos: [u8; 10],
+}
+
+impl Default for ComponentState {
+    fn default() -> Self {
+        Self {
+            coefficients: [0; TILE_PIXELS],
+            sign: [0; TILE_PIXELS],
+            bit_pos: [0; 10],
+        }
+    }
+}
+
+impl ComponentState {
+    fn decode_first(
+        &mut self,
+        data: &[u8],
+        bit_pos: [u8; 10],
+        difference: bool,
+        extrapolate: bool,
+        output: &mut [i16; TILE_PIXELS],
+    ) -> Result<(), ProgressiveError> {
+        if data.is_empty() {
+            output.fill(0);
+        } else {
+            rlgr::decode(EntropyAlgorithm::Rlgr1, data, output)?;
+        }
+        self.sign.copy_from_slice(output);
+
+        let bands = if extrapolate { &EXTRAPOLATED_BANDS } else { &BANDS };
+        let (ll3_offset, ll3_len) = bands[LL3];
+        subband_reconstruction::decode(&mut output[ll3_offset..ll3_offset + ll3_len]);
+
+        for (&(offset, len), &bit_pos) in bands.iter().zip(&bit_pos) {
+            let shift = bit_pos.saturating_sub(1);
+            for value in &mut output[offset..offset + len] {
+                *value = shl(*value, shift);
+            }
+        }
+
+        if difference {
+            for (value, previous) in output.iter_mut().zip(&self.coefficients) {
+                *value = value.wrapping_add(*previous);
+            }
+        }
+
+        self.coefficients.copy_from_slice(output);
+        self.bit_pos = bit_pos;
+
+        inverse_dwt(output, extrapolate);
+
+        Ok(())
+    }
+
+    /// Returns `None` when the upgrade pass is invalid
+    fn decode_upgrade(
+        &mut self,
+        srl: &[u8],
+        raw: &[u8],
+        bit_pos: [u8; 10],
+        extrapolate: bool,
+        output: &mut [i16; TILE_PIXELS],
+    ) -> Option<()> {
+        let mut upgrade = Upgrade {
+            srl: BitReader::new(srl),
+            raw: BitReader::new(raw),
+            kp: 8,
+            nz: 0,
+            unary: false,
+        };
+
+        let bands = if extrapolate { &EXTRAPOLATED_BANDS } else { &BANDS };
+
+        for (band, &(offset, len)) in bands.iter().enumerate() {
+            let num_bits = self.bit_pos[band].checked_sub(bit_pos[band])?;
+            let shift = bit_pos[band].saturating_sub(1);
+            let coefficients = &mut self.coefficients[offset..offset + len];
+            let sign = &mut self.sign[offset..offset + len];
+
+            if band == LL3 {
+                upgrade.upgrade_ll3(coefficients, num_bits, shift)?;
+            } else {
+                upgrade.upgrade_band(coefficients, sign, num_bits, shift)?;
+            }
+        }
+
+        // The data of the pass is padded to a whole byte, and nothing more
+        if upgrade.srl.remaining() >= 8 || upgrade.raw.remaining() >= 8 {
+            return None;
+        }
+
+        self.bit_pos = bit_pos;
+        output.copy_from_slice(&self.coefficients);
+
+        inverse_dwt(output, extrapolate);
+
+        Some(())
+    }
+}
+
+/// State of an upgrade pass of a component, shared by its sub-bands
+struct Upgrade<'a> {
+    /// Simplified run-length coded bits of the coefficients which were zero until this pass
+    srl: BitReader<'a>,
+    /// Raw bits of the other coefficients
+    raw: BitReader<'a>,
+    kp: u32,
+    /// Zeros left in the current run
+    nz: u32,
+    /// A non-zero value follows the current run
+    unary: bool,
+}
+
+impl Upgrade<'_> {
+    fn upgrade_band(&mut self, coefficients: &mut [i16], sign: &mut [i16], num_bits: u8, shift: u8) -> Option<()> {
+        if num_bits == 0 {
+            return Some(());
+        }
+
+        for (coefficient, sign) in coefficients.iter_mut().zip(sign.iter_mut()) {
+            let input = match (*sign).signum() {
+                0 => {
+                    let input = self.read_srl(num_bits)?;
+                    *sign = input;
+                    input
+                }
+                signum => self.read_raw(num_bits)? * signum,
+            };
+
+            *coefficient = coefficient.wrapping_add(shl(input, shift));
+        }
+
+        Some(())
+    }
+
+    /// The LL3 coefficients are all refined with raw bits
+    fn upgrade_ll3(&mut self, coefficients: &mut [i16], num_bits: u8, shift: u8) -> Option<()> {
+        if num_bits == 0 {
+            return Some(());
+        }
+
+        for coefficient in coefficients {
+            let input = self.read_raw(num_bits)?;
+            *coefficient = coefficient.wrapping_add(shl(input, shift));
+        }
+
+        Some(())
+    }
+
+    fn read_raw(&mut self, num_bits: u8) -> Option<i16> {
+        let value = self.raw.read(usize::from(num_bits))?;
+        i16::try_from(value).ok()
+    }
+
+    /// Reads a value with the simplified run-length coding, see [MS-RDPEGFX] 3.3.8.2.2
+    fn read_srl(&mut self, num_bits: u8) -> Option<i16> {
+        if self.nz > 0 {
+            self.nz -= 1;
+            return Some(0);
+        }
+
+        let k = self.kp >> 3;
+
+        if !self.unary {
+            if self.srl.read(1)? == 0 {
+                // A full run of 2^k zeros
+                self.nz = (1 << k) - 1;
+                self.kp = (self.kp + UP_GR).min(KP_MAX);
+                return Some(0);
+            }
+
+            // A run of less than 2^k zeros, followed by a non-zero value
+            self.unary = true;
+            self.nz = self.srl.read(usize::try_from(k).ok()?)?;
+            if self.nz > 0 {
+                self.nz -= 1;
+                return Some(0);
+            }
+        }
+
+        self.unary = false;
+        let negative = self.srl.read(1)? == 1;
+        self.kp = self.kp.saturating_sub(DN_GR);
+
+        // The magnitude is unary coded, without the terminating bit for the largest magnitude
+        let max = (1u32 << num_bits) - 1;
+        let mut magnitude = 1;
+        while magnitude < max && self.srl.read(1)? == 0 {
+            magnitude += 1;
+        }
+
+        let magnitude = i16::try_from(magnitude).ok()?;
+        Some(if negative { -magnitude } else { magnitude })
+    }
+}
+
+fn shl(value: i16, shift: u8) -> i16 {
+    value.checked_shl(u32::from(shift)).unwrap_or(0)
+}
+
+fn inverse_dwt(buffer: &mut [i16; TILE_PIXELS], extrapolate: bool) {
+    let mut temp = [0i16; TILE_PIXELS];
+
+    if !extrapolate {
+        dwt::decode(buffer, &mut temp);
+        return;
+    }
+
+    inverse_extrapolated_level(&mut buffer[3807..], &mut temp, 3);
+    inverse_extrapolated_level(&mut buffer[3007..], &mut temp, 2);
+    inverse_extrapolated_level(buffer, &mut temp, 1);
+}
+
+/// Inverse reduce-extrapolate DWT of one level
+///
+/// The sub-bands are stored in HL, LH, HH, LL order, HL being `high` coefficients wide and `low` coefficients
+/// high. They are replaced by the `(low + high)²` coefficients of the next level.
+fn inverse_extrapolated_level(buffer: &mut [i16], temp: &mut [i16], level: u32) {
+    let low = (64 >> level) + 1;
+    let high = if level == 1 {
+        31
+    } else {
+        (64 + (1 << (level - 1))) >> level
+    };
+    let total = low + high;
+
+    let (hl, rest) = buffer.split_at(high * low);
+    let (lh, rest) = rest.split_at(low * high);
+    let (hh, ll) = rest.split_at(high * high);
+    let (l_dst, h_dst) = temp[..total * total].split_at_mut(low * total);
+
+    // Horizontal, LL + HL -> L and LH + HH -> H
+    for ((ll, hl), dst) in ll
+        .chunks_exact(low)
+        .zip(hl.chunks_exact(high))
+        .zip(l_dst.chunks_exact_mut(total))
+    {
+        inverse_extrapolated_line(ll, hl, dst);
+    }
+    for ((lh, hh), dst) in lh
+        .chunks_exact(low)
+        .zip(hh.chunks_exact(high))
+        .zip(h_dst.chunks_exact_mut(total))
+    {
+        inverse_extrapolated_line(lh, hh, dst);
+    }
+
+    // Vertical, L + H -> LL of the next level
+    let mut low_column = [0i16; 33];
+    let mut high_column = [0i16; 31];
+    let mut dst_column = [0i16; 64];
+
+    for x in 0..total {
+        for (y, value) in low_column[..low].iter_mut().enumerate() {
+            *value = temp[y * total + x];
+        }
+        for (y, value) in high_column[..high].iter_mut().enumerate() {
+            *value = temp[(low + y) * total + x];
+        }
+
+        inverse_extrapolated_line(&low_column[..low], &high_column[..high], &mut dst_column[..total]);
+
+        for (y, &value) in dst_column[..total].iter().enumerate() {
+            buffer[y * total + x] = value;
+        }
+    }
+}
+
+/// Inverse lifting of a line of `low.len() + high.len()` coefficients
+///
+/// There are one or two more low-pass than high-pass coefficients, the last one being extrapolated.
+fn inverse_extrapolated_line(low: &[i16], high: &[i16], dst: &mut [i16]) {
+    let n = high.len();
+
+    let mut h0 = i32::from(high[0]);
+    let mut x0 = truncate(i32::from(low[0]) - h0);
+    let mut x2 = x0;
+
+    for j in 1..n {
+        let h1 = i32::from(high[j]);
+        x2 = truncate(i32::from(low[j]) - (h0 + h1) / 2);
+        dst[2 * j - 2] = x0;
+        dst[2 * j - 1] = truncate((i32::from(x0) + i32::from(x2)) / 2 + 2 * h0);
+        x0 = x2;
+        h0 = h1;
+    }
+
+    let last = &mut dst[2 * n - 2..];
+    if low.len() == n + 1 {
+        let x0 = truncate(i32::from(low[n]) - h0);
+        last[0] = x2;
+        last[1] = truncate((i32::from(x0) + i32::from(x2)) / 2 + 2 * h0);
+        last[2] = x0;
+    } else {
+        let x0 = truncate(i32::from(low[n]) - h0 / 2);
+        last[0] = x2;
+        last[1] = truncate((i32::from(x0) + i32::from(x2)) / 2 + 2 * h0);
+        last[2] = x0;
+        last[3] = truncate((i32::from(x0) + i32::from(low[n + 1])) / 2);
+    }
+}
+
+#[expect(clippy::as_conversions)]
+#[expect(clippy::cast_possible_truncation)]
+fn truncate(value: i32) -> i16 {
+    value as i16
+}
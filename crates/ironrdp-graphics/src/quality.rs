@@ -0,0 +1,115 @@
+//! Objective quality metrics of decoded bitmaps
+//!
+//! The metrics compare the luma (BT.601) of a decoded bitmap with the reference bitmap it was encoded
+//! from, e.g. to compare lossy codecs at a given bitrate.
+
+use crate::codec::{BitmapRef, CodecError};
+
+/// Peak value of a luma sample
+const PEAK: f64 = 255.0;
+
+/// Side of the square windows over which the SSIM is computed
+const SSIM_WINDOW: usize = 8;
+
+/// Stabilization constants of the SSIM, for a dynamic range of 255
+const SSIM_C1: f64 = (0.01 * PEAK) * (0.01 * PEAK);
+const SSIM_C2: f64 = (0.03 * PEAK) * (0.03 * PEAK);
+
+/// Peak signal-to-noise ratio, in decibels
+///
+/// Returns [`f64::INFINITY`] when the bitmaps are identical.
+pub fn psnr(reference: &BitmapRef<'_>, decoded: &BitmapRef<'_>) -> Result<f64, CodecError> {
+    let (reference, decoded, _) = luma_planes(reference, decoded)?;
+
+    let mut squared_error = 0.0;
+    let mut samples = 0.0;
+    for (a, b) in reference.iter().zip(&decoded) {
+        squared_error += (a - b) * (a - b);
+        samples += 1.0;
+    }
+
+    if squared_error == 0.0 {
+        return Ok(f64::INFINITY);
+    }
+
+    Ok(10.0 * (PEAK * PEAK / (squared_error / samples)).log10())
+}
+
+/// Mean structural similarity index (SSIM), from 0 (unrelated) to 1 (identical)
+///
+/// The index is averaged over non-overlapping 8×8 windows, the windows on the right and bottom edges
+/// being smaller when the size of the bitmaps is not a multiple of 8.
+pub fn ssim(reference: &BitmapRef<'_>, decoded: &BitmapRef<'_>) -> Result<f64, CodecError> {
+    let (reference, decoded, width) = luma_planes(reference, decoded)?;
+
+    if reference.is_empty() {
+        return Ok(1.0);
+    }
+
+    let height = reference.len() / width;
+
+    let mut total = 0.0;
+    let mut windows = 0.0;
+    for top in (0..height).step_by(SSIM_WINDOW) {
+        for left in (0..width).step_by(SSIM_WINDOW) {
+            let bottom = (top + SSIM_WINDOW).min(height);
+            let right = (left + SSIM_WINDOW).min(width);
+
+            let samples = (top..bottom).flat_map(|y| (left..right).map(move |x| y * width + x));
+            total += window_ssim(samples.map(|idx| (reference[idx], decoded[idx])));
+            windows += 1.0;
+        }
+    }
+
+    Ok(total / windows)
+}
+
+fn window_ssim(samples: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let mut count = 0.0;
+    let (mut sum_x, mut sum_y) = (0.0, 0.0);
+    for (x, y) in samples.clone() {
+        sum_x += x;
+        sum_y += y;
+        count += 1.0;
+    }
+
+    let (mean_x, mean_y) = (sum_x / count, sum_y / count);
+
+    let (mut var_x, mut var_y, mut covar) = (0.0, 0.0, 0.0);
+    for (x, y) in samples {
+        var_x += (x - mean_x) * (x - mean_x);
+        var_y += (y - mean_y) * (y - mean_y);
+        covar += (x - mean_x) * (y - mean_y);
+    }
+    let (var_x, var_y, covar) = (var_x / count, var_y / count, covar / count);
+
+    ((2.0 * mean_x * mean_y + SSIM_C1) * (2.0 * covar + SSIM_C2))
+        / ((mean_x * mean_x + mean_y * mean_y + SSIM_C1) * (var_x + var_y + SSIM_C2))
+}
+
+/// Returns the luma of both bitmaps, row by row, and their width
+fn luma_planes(reference: &BitmapRef<'_>, decoded: &BitmapRef<'_>) -> Result<(Vec<f64>, Vec<f64>, usize), CodecError> {
+    if (reference.desc.width, reference.desc.height) != (decoded.desc.width, decoded.desc.height) {
+        return Err(CodecError::InvalidInput("bitmaps have different sizes"));
+    }
+
+    Ok((luma(reference)?, luma(decoded)?, usize::from(reference.desc.width)))
+}
+
+fn luma(bitmap: &BitmapRef<'_>) -> Result<Vec<f64>, CodecError> {
+    let format = bitmap.desc.format;
+    let bytes_per_pixel = usize::from(format.bytes_per_pixel());
+
+    let mut luma = Vec::with_capacity(usize::from(bitmap.desc.width) * usize::from(bitmap.desc.height));
+    for row in bitmap.rows()? {
+        for pixel in row.chunks_exact(bytes_per_pixel) {
+            let color = format
+                .read_color(pixel)
+                .map_err(|_| CodecError::InvalidInput("invalid pixel"))?;
+
+            luma.push(0.299 * f64::from(color.r) + 0.587 * f64::from(color.g) + 0.114 * f64::from(color.b));
+        }
+    }
+
+    Ok(luma)
+}
@@ -1,7 +1,7 @@
 use ironrdp_core::{decode, DecodeError};
 use ironrdp_pdu::bitmap::rdp6::{BitmapStream as BitmapStreamPdu, ColorPlaneDefinition};
 
-use crate::color_conversion::Rgb;
+use crate::color_conversion::{restore_chroma, ycocg_to_rgb, Rgb, YCoCg};
 use crate::rdp6::rle::{decompress_8bpp_plane, RleDecodeError};
 
 #[derive(Debug)]
@@ -217,7 +217,11 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
             let co = co_plane[chroma_idx];
             let cg = cg_plane[chroma_idx];
 
-            let Rgb { r, g, b } = ycocg_with_cll_to_rgb(params.color_loss_level, y, co, cg);
+            let Rgb { r, g, b } = ycocg_to_rgb(YCoCg {
+                y,
+                co: restore_chroma(co, params.color_loss_level),
+                cg: restore_chroma(cg, params.color_loss_level),
+            });
 
             // As described in 3.1.9.1.2 [MS-RDPEGDI], R and B channels are swapped for
             // AYCoCg when 24-bit image is used (no alpha). We swap them back here
@@ -257,35 +261,6 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
     }
 }
 
-/// Perform YCoCg -> RGB conversion with color loss reduction (CLL) correction.
-fn ycocg_with_cll_to_rgb(cll: u8, y: u8, co: u8, cg: u8) -> Rgb {
-    #![allow(clippy::similar_names)] // It’s hard to find better names for co, cg, etc.
-
-    // We decrease CLL by 1 to skip division by 2 for co & cg components during computation of
-    // the following color conversion matrix:
-    // |R|   |1   1/2   -1/2|   |Y |
-    // |G| = |1    0     1/2| * |Co|
-    // |B|   |1  -1/2   -1/2|   |Cg|
-    let chroma_shift = cll - 1;
-
-    let clip_i16 =
-        |v: i16| u8::try_from(v.clamp(0, 255)).expect("fits into u8 because the value is clamped to [0..256]");
-
-    let co_signed = (co << chroma_shift).cast_signed();
-    let cg_signed = (cg << chroma_shift).cast_signed();
-
-    let y = i16::from(y);
-    let co = i16::from(co_signed);
-    let cg = i16::from(cg_signed);
-
-    let t = y - cg;
-    let r = clip_i16(t + co);
-    let g = clip_i16(y + cg);
-    let b = clip_i16(t - co);
-
-    Rgb { r, g, b }
-}
-
 impl BitmapStreamDecoder {
     /// Performs decoding of bitmap stream PDU from `bitmap_data` and writes decoded rgb24
     /// image to `dst` buffer.
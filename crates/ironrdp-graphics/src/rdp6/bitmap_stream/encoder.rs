@@ -1,6 +1,7 @@
 use ironrdp_core::{not_enough_bytes_err, EncodeError, WriteCursor};
 use ironrdp_pdu::bitmap::rdp6::{BitmapStreamHeader, ColorPlaneDefinition};
 
+use crate::color_conversion::{reduce_chroma, rgb_to_ycocg, Rgb, YCoCg};
 use crate::rdp6::rle::{compress_8bpp_plane, RleEncodeError};
 
 #[derive(Debug)]
@@ -145,19 +146,80 @@ impl BitmapEncodeError {
     }
 }
 
+/// Lossy reduction of the color planes, see [MS-RDPEGDI] 3.1.9.1.2 and 3.1.9.1.3
+///
+/// The default keeps the ARGB planes, which are lossless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorLoss {
+    /// Between 1 and 7, the number of least significant bits removed from the chroma of YCoCg planes
+    ///
+    /// 0 keeps the ARGB planes.
+    pub level: u8,
+    /// Halves the width and height of the chroma planes, ignored with the ARGB planes
+    pub chroma_subsampling: bool,
+}
+
+impl ColorLoss {
+    /// Color loss level of the ARGB planes
+    pub const NONE: Self = Self {
+        level: 0,
+        chroma_subsampling: false,
+    };
+
+    /// Highest color loss level
+    pub const MAX_LEVEL: u8 = 7;
+
+    fn plane_definition(self) -> ColorPlaneDefinition {
+        if self.level == 0 {
+            ColorPlaneDefinition::Argb
+        } else {
+            ColorPlaneDefinition::AYCoCg {
+                color_loss_level: self.level.min(Self::MAX_LEVEL),
+                use_chroma_subsampling: self.chroma_subsampling,
+            }
+        }
+    }
+}
+
 pub struct BitmapStreamEncoder {
     width: usize,
     height: usize,
+    color_loss: ColorLoss,
+    /// Luma and chroma planes, when the colors are encoded as YCoCg
+    ycocg_planes: Vec<u8>,
 }
 
 impl BitmapStreamEncoder {
     pub fn new(width: usize, height: usize) -> Self {
-        Self { width, height }
+        Self {
+            width,
+            height,
+            color_loss: ColorLoss::NONE,
+            ycocg_planes: Vec::new(),
+        }
+    }
+
+    /// Encodes the colors as YCoCg planes with the given color loss, instead of lossless ARGB planes
+    #[must_use]
+    pub fn with_color_loss(mut self, color_loss: ColorLoss) -> Self {
+        self.color_loss = color_loss;
+        self
+    }
+
+    /// Size of the chroma planes
+    fn chroma_size(&self) -> (usize, usize) {
+        match self.color_loss.plane_definition() {
+            ColorPlaneDefinition::AYCoCg {
+                use_chroma_subsampling: true,
+                ..
+            } => (self.width.div_ceil(2), self.height.div_ceil(2)),
+            _ => (self.width, self.height),
+        }
     }
 
     pub fn encode_channels_stream<R, G, B>(
         &mut self,
-        (r, g, b): (R, G, B),
+        rgb: (R, G, B),
         dst: &mut [u8],
         rle: bool,
     ) -> Result<usize, BitmapEncodeError>
@@ -166,38 +228,7 @@ impl BitmapStreamEncoder {
         G: Iterator<Item = u8>,
         B: Iterator<Item = u8>,
     {
-        let mut cursor = WriteCursor::new(dst);
-
-        let header = BitmapStreamHeader {
-            enable_rle_compression: rle,
-            use_alpha: false,
-            color_plane_definition: ColorPlaneDefinition::Argb,
-        };
-
-        ironrdp_core::encode_cursor(&header, &mut cursor).map_err(BitmapEncodeError::Encode)?;
-
-        if rle {
-            compress_8bpp_plane(r, &mut cursor, self.width, self.height).map_err(BitmapEncodeError::Rle)?;
-            compress_8bpp_plane(g, &mut cursor, self.width, self.height).map_err(BitmapEncodeError::Rle)?;
-            compress_8bpp_plane(b, &mut cursor, self.width, self.height).map_err(BitmapEncodeError::Rle)?;
-        } else {
-            let remaining = cursor.len();
-            let needed = self.width * self.height * 3 + 1;
-            if needed > remaining {
-                return Err(BitmapEncodeError::Encode(not_enough_bytes_err(
-                    "BitmapStreamData",
-                    remaining,
-                    needed,
-                )));
-            }
-
-            for byte in r.chain(g).chain(b) {
-                cursor.write_u8(byte);
-            }
-            cursor.write_u8(0u8);
-        }
-
-        Ok(cursor.pos())
+        self.encode_planes(None::<core::iter::Empty<u8>>, rgb, dst, rle)
     }
 
     pub fn encode_pixels_stream<'a, I, F>(
@@ -234,6 +265,34 @@ impl BitmapStreamEncoder {
         dst: &mut [u8],
         rle: bool,
     ) -> Result<usize, BitmapEncodeError>
+    where
+        R: Iterator<Item = u8>,
+        G: Iterator<Item = u8>,
+        B: Iterator<Item = u8>,
+        A: Iterator<Item = u8>,
+    {
+        self.encode_planes(Some(a), (r, g, b), dst, rle)
+    }
+
+    pub fn encode_bitmap_alpha<F>(&mut self, src: &[u8], dst: &mut [u8], rle: bool) -> Result<usize, BitmapEncodeError>
+    where
+        F: PixelFormat + PixelAlpha,
+    {
+        let r = src.chunks_exact(F::STRIDE).map(F::r);
+        let g = src.chunks_exact(F::STRIDE).map(F::g);
+        let b = src.chunks_exact(F::STRIDE).map(F::b);
+        let a = src.chunks_exact(F::STRIDE).map(F::a);
+
+        self.encode_channels_stream_alpha((r, g, b, a), dst, rle)
+    }
+
+    fn encode_planes<R, G, B, A>(
+        &mut self,
+        alpha: Option<A>,
+        (mut r, mut g, mut b): (R, G, B),
+        dst: &mut [u8],
+        rle: bool,
+    ) -> Result<usize, BitmapEncodeError>
     where
         R: Iterator<Item = u8>,
         G: Iterator<Item = u8>,
@@ -244,20 +303,20 @@ impl BitmapStreamEncoder {
 
         let header = BitmapStreamHeader {
             enable_rle_compression: rle,
-            use_alpha: false,
-            color_plane_definition: ColorPlaneDefinition::Argb,
+            use_alpha: alpha.is_some(),
+            color_plane_definition: self.color_loss.plane_definition(),
         };
 
         ironrdp_core::encode_cursor(&header, &mut cursor).map_err(BitmapEncodeError::Encode)?;
 
-        if rle {
-            compress_8bpp_plane(a, &mut cursor, self.width, self.height).map_err(BitmapEncodeError::rle)?;
-            compress_8bpp_plane(r, &mut cursor, self.width, self.height).map_err(BitmapEncodeError::rle)?;
-            compress_8bpp_plane(g, &mut cursor, self.width, self.height).map_err(BitmapEncodeError::rle)?;
-            compress_8bpp_plane(b, &mut cursor, self.width, self.height).map_err(BitmapEncodeError::rle)?;
-        } else {
+        let (chroma_width, chroma_height) = self.chroma_size();
+        let full_plane_size = self.width * self.height;
+        let chroma_plane_size = chroma_width * chroma_height;
+
+        if !rle {
+            let alpha_plane_size = if alpha.is_some() { full_plane_size } else { 0 };
             let remaining = cursor.len();
-            let needed = self.width * self.height * 4 + 1;
+            let needed = alpha_plane_size + full_plane_size + chroma_plane_size * 2 + 1;
             if needed > remaining {
                 return Err(BitmapEncodeError::Encode(not_enough_bytes_err(
                     "BitmapStreamData",
@@ -265,25 +324,115 @@ impl BitmapStreamEncoder {
                     needed,
                 )));
             }
+        }
 
-            for byte in a.chain(r).chain(g).chain(b) {
-                cursor.write_u8(byte);
+        let mut write_plane = |plane: &mut dyn Iterator<Item = u8>, width: usize, height: usize| {
+            if rle {
+                compress_8bpp_plane(plane, &mut cursor, width, height).map_err(BitmapEncodeError::rle)?;
+            } else {
+                for byte in plane {
+                    cursor.write_u8(byte);
+                }
             }
+
+            Ok::<_, BitmapEncodeError>(())
+        };
+
+        if let Some(mut alpha) = alpha {
+            write_plane(&mut alpha, self.width, self.height)?;
+        }
+
+        if self.color_loss.level == 0 {
+            write_plane(&mut r, self.width, self.height)?;
+            write_plane(&mut g, self.width, self.height)?;
+            write_plane(&mut b, self.width, self.height)?;
+        } else {
+            // As described in 3.1.9.1.2 [MS-RDPEGDI], R and B channels are swapped for AYCoCg when
+            // 24-bit image is used (no alpha).
+            let (r, b): (&mut dyn Iterator<Item = u8>, &mut dyn Iterator<Item = u8>) = if header.use_alpha {
+                (&mut r, &mut b)
+            } else {
+                (&mut b, &mut r)
+            };
+
+            rgb_to_ycocg_planes(
+                self.color_loss,
+                (r, g, b),
+                (self.width, self.height),
+                (chroma_width, chroma_height),
+                &mut self.ycocg_planes,
+            );
+
+            let (luma, chroma) = self.ycocg_planes.split_at(full_plane_size);
+            let (co, cg) = chroma.split_at(chroma_plane_size);
+
+            write_plane(&mut luma.iter().copied(), self.width, self.height)?;
+            write_plane(&mut co.iter().copied(), chroma_width, chroma_height)?;
+            write_plane(&mut cg.iter().copied(), chroma_width, chroma_height)?;
+        }
+
+        if !rle {
+            // Padding byte of the raw planes
             cursor.write_u8(0u8);
         }
 
         Ok(cursor.pos())
     }
+}
 
-    pub fn encode_bitmap_alpha<F>(&mut self, src: &[u8], dst: &mut [u8], rle: bool) -> Result<usize, BitmapEncodeError>
-    where
-        F: PixelFormat + PixelAlpha,
+/// Converts RGB channels to the luma plane followed by the Co and Cg chroma planes
+///
+/// The chroma of subsampled planes is the average of the chroma of the pixels of each 2x2 block.
+fn rgb_to_ycocg_planes(
+    color_loss: ColorLoss,
+    (r, g, b): (
+        impl Iterator<Item = u8>,
+        impl Iterator<Item = u8>,
+        impl Iterator<Item = u8>,
+    ),
+    (width, height): (usize, usize),
+    (chroma_width, chroma_height): (usize, usize),
+    planes: &mut Vec<u8>,
+) {
+    #![allow(clippy::similar_names)] // It’s hard to find better names for co, cg, etc.
+
+    let full_plane_size = width * height;
+    let chroma_plane_size = chroma_width * chroma_height;
+    let subsampling_shift = usize::from(chroma_width != width || chroma_height != height);
+
+    planes.clear();
+    planes.resize(full_plane_size + chroma_plane_size * 2, 0);
+
+    // Sums of the chroma of the pixels of each chroma sample, before the color loss reduction
+    let mut co_sums = vec![0i32; chroma_plane_size];
+    let mut cg_sums = vec![0i32; chroma_plane_size];
+    let mut counts = vec![0i32; chroma_plane_size];
+
+    for (idx, ((r, g), b)) in r.zip(g).zip(b).take(full_plane_size).enumerate() {
+        let YCoCg { y, co, cg } = rgb_to_ycocg(Rgb { r, g, b });
+        planes[idx] = y;
+
+        let chroma_idx = ((idx / width) >> subsampling_shift) * chroma_width + ((idx % width) >> subsampling_shift);
+        co_sums[chroma_idx] += i32::from(co);
+        cg_sums[chroma_idx] += i32::from(cg);
+        counts[chroma_idx] += 1;
+    }
+
+    let (co_plane, cg_plane) = planes[full_plane_size..].split_at_mut(chroma_plane_size);
+
+    for (((co, cg), (co_sum, cg_sum)), count) in co_plane
+        .iter_mut()
+        .zip(cg_plane.iter_mut())
+        .zip(co_sums.into_iter().zip(cg_sums))
+        .zip(counts)
     {
-        let r = src.chunks_exact(F::STRIDE).map(F::r);
-        let g = src.chunks_exact(F::STRIDE).map(F::g);
-        let b = src.chunks_exact(F::STRIDE).map(F::b);
-        let a = src.chunks_exact(F::STRIDE).map(F::a);
+        let count = count.max(1);
 
-        self.encode_channels_stream_alpha((r, g, b, a), dst, rle)
+        *co = reduce_chroma(average(co_sum, count), color_loss.level);
+        *cg = reduce_chroma(average(cg_sum, count), color_loss.level);
     }
 }
+
+fn average(sum: i32, count: i32) -> i16 {
+    i16::try_from(sum.div_euclid(count)).expect("average of 9-bit chroma values fits into i16")
+}
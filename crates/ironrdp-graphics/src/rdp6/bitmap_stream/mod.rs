@@ -176,4 +176,106 @@ mod tests {
         // RGB (No alpha), with RLE
         encode_decode_test(include_bytes!("../test_assets/64x64_aycocg_rle.bmp"), 64, 64, true);
     }
+
+    fn encode_decode_with_color_loss(
+        image: &[u8],
+        width: usize,
+        height: usize,
+        color_loss: ColorLoss,
+        rle: bool,
+    ) -> Vec<u8> {
+        let mut pdu = vec![0; width * height * 4 + 2];
+        let written = BitmapStreamEncoder::new(width, height)
+            .with_color_loss(color_loss)
+            .encode_bitmap::<RgbChannels>(image, &mut pdu, rle)
+            .unwrap();
+
+        let mut actual = Vec::new();
+        BitmapStreamDecoder::default()
+            .decode_bitmap_stream_to_rgb24(&pdu[..written], &mut actual, width, height)
+            .unwrap();
+
+        actual
+    }
+
+    fn assert_reencoded_image(bmp: &[u8], width: usize, height: usize, color_loss: ColorLoss, rle: bool) {
+        // The image was decoded from planes with the same color loss, so no more color is lost
+        let image = buffer_from_bmp(bmp, width, height);
+        let actual = encode_decode_with_color_loss(&image, width, height, color_loss, rle);
+
+        assert_eq!(&image.as_slice(), &actual.as_slice());
+    }
+
+    #[test]
+    fn encode_decode_64x35_ycocg_rle_ss() {
+        // AYCoCg (No alpha), RLE, with chroma subsampling + odd resolution
+        assert_reencoded_image(
+            include_bytes!("../test_assets/64x35_ycocg_rle_ss.bmp"),
+            64,
+            35,
+            ColorLoss {
+                level: 3,
+                chroma_subsampling: true,
+            },
+            true,
+        );
+    }
+
+    #[test]
+    fn encode_alpha_header() {
+        let image = [0x11, 0x22, 0x33, 0x44].repeat(4);
+        let mut pdu = vec![0; 32];
+
+        BitmapStreamEncoder::new(2, 2)
+            .encode_bitmap_alpha::<RgbAChannels>(&image, &mut pdu, true)
+            .unwrap();
+
+        // RLE, alpha plane present (NA flag not set)
+        assert_eq!(pdu[0], 0x10);
+    }
+
+    #[test]
+    fn encode_decode_gray_with_color_loss_is_lossless() {
+        // Gray pixels have no chroma, so only the luma plane carries the image
+        let image: Vec<u8> = (0..64 * 35)
+            .flat_map(|idx| [u8::try_from(idx % 256).unwrap(); 3])
+            .collect();
+
+        for level in 1..=ColorLoss::MAX_LEVEL {
+            for chroma_subsampling in [false, true] {
+                let color_loss = ColorLoss {
+                    level,
+                    chroma_subsampling,
+                };
+
+                for rle in [false, true] {
+                    let actual = encode_decode_with_color_loss(&image, 64, 35, color_loss, rle);
+                    assert_eq!(image, actual, "{color_loss:?}, rle: {rle}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn encode_decode_64x64_with_color_loss() {
+        // The error of each channel is bounded by the chroma bits removed by the color loss
+        let image = buffer_from_bmp(include_bytes!("../test_assets/64x64_aycocg_rle.bmp"), 64, 64);
+
+        for level in 1..=ColorLoss::MAX_LEVEL {
+            let color_loss = ColorLoss {
+                level,
+                chroma_subsampling: false,
+            };
+
+            let actual = encode_decode_with_color_loss(&image, 64, 64, color_loss, true);
+            let max_error = image
+                .iter()
+                .zip(&actual)
+                .map(|(expected, actual)| expected.abs_diff(*actual))
+                .max()
+                .unwrap();
+
+            assert!(max_error < 1 << level, "{color_loss:?}: {max_error}");
+        }
+    }
 }
@@ -0,0 +1,306 @@
+//! RemoteFX tile encoding and decoding
+//!
+//! Frames are split into 64x64 tiles which are processed independently. With the `rayon` feature,
+//! the tiles are spread over the global rayon thread pool. Without it, e.g.: for single-threaded
+//! or WebAssembly targets, they are processed sequentially.
+
+use core::fmt;
+
+use ironrdp_pdu::codecs::rfx::{EntropyAlgorithm, Quant, Tile};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::color_conversion::{ycbcr_to_rgba, YCbCrBuffer};
+use crate::image_processing::PixelFormat;
+use crate::rlgr::RlgrError;
+use crate::{dwt, quantization, rfx_encode_component, rlgr, subband_reconstruction};
+
+pub const TILE_SIZE: u16 = 64;
+
+const TILE_PIXELS: usize = 64 * 64;
+
+/// Size in bytes of a decoded RGBA tile
+pub const DECODED_TILE_SIZE: usize = TILE_PIXELS * 4;
+
+/// Image to be split into tiles
+#[derive(Debug, Clone, Copy)]
+pub struct TileSource<'a> {
+    pub data: &'a [u8],
+    pub width: u16,
+    pub height: u16,
+    pub stride: usize,
+    pub format: PixelFormat,
+}
+
+impl TileSource<'_> {
+    /// Number of tile columns and rows covering the image
+    pub fn tiles_xy(&self) -> (u16, u16) {
+        (self.width.div_ceil(TILE_SIZE), self.height.div_ceil(TILE_SIZE))
+    }
+}
+
+#[derive(Debug)]
+pub enum TileError {
+    Rlgr(RlgrError),
+    InvalidQuantIndex { index: u8, count: usize },
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rlgr(_) => write!(f, "RLGR error"),
+            Self::InvalidQuantIndex { index, count } => {
+                write!(f, "invalid quantization table index {index} ({count} tables)")
+            }
+        }
+    }
+}
+
+impl core::error::Error for TileError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Rlgr(error) => Some(error),
+            Self::InvalidQuantIndex { .. } => None,
+        }
+    }
+}
+
+impl From<RlgrError> for TileError {
+    fn from(error: RlgrError) -> Self {
+        Self::Rlgr(error)
+    }
+}
+
+/// Encodes images into tiles using a single quantization table
+///
+/// The encoded data is kept in a buffer reused from one frame to the next.
+#[derive(Debug, Clone)]
+pub struct TileEncoder {
+    quant: Quant,
+    entropy_algorithm: EntropyAlgorithm,
+    buffer: Vec<u8>,
+}
+
+impl TileEncoder {
+    pub fn new(quant: Quant, entropy_algorithm: EntropyAlgorithm) -> Self {
+        Self {
+            quant,
+            entropy_algorithm,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn quant(&self) -> &Quant {
+        &self.quant
+    }
+
+    pub fn entropy_algorithm(&self) -> EntropyAlgorithm {
+        self.entropy_algorithm
+    }
+
+    /// Encodes all the tiles of `source`, which use the quantization table at index 0
+    pub fn encode(&mut self, source: &TileSource<'_>) -> Result<Vec<Tile<'_>>, TileError> {
+        let (tiles_x, tiles_y) = source.tiles_xy();
+        let positions: Vec<_> = (0..tiles_y).flat_map(|y| (0..tiles_x).map(move |x| (x, y))).collect();
+
+        self.buffer.resize(positions.len() * TILE_PIXELS * 3, 0);
+
+        #[cfg(not(feature = "rayon"))]
+        let chunks = self.buffer.chunks_mut(TILE_PIXELS * 3);
+        #[cfg(feature = "rayon")]
+        let chunks = self.buffer.par_chunks_mut(TILE_PIXELS * 3);
+
+        let quant = &self.quant;
+        let entropy_algorithm = self.entropy_algorithm;
+
+        chunks
+            .zip(positions)
+            .map(|(buffer, (x, y))| encode_tile(source, x, y, quant, entropy_algorithm, buffer))
+            .collect()
+    }
+}
+
+/// Encodes the tile at column `tile_x` and row `tile_y` of `source`
+///
+/// `buffer` must be at least 3 * 4096 bytes long.
+pub fn encode_tile<'a>(
+    source: &TileSource<'_>,
+    tile_x: u16,
+    tile_y: u16,
+    quant: &Quant,
+    entropy_algorithm: EntropyAlgorithm,
+    buffer: &'a mut [u8],
+) -> Result<Tile<'a>, TileError> {
+    #![allow(clippy::similar_names)] // It’s hard to find better names for cr, cb, etc.
+
+    let x = tile_x * TILE_SIZE;
+    let y = tile_y * TILE_SIZE;
+    let tile_width = (source.width - x).min(TILE_SIZE);
+    let tile_height = (source.height - y).min(TILE_SIZE);
+
+    let bpp = usize::from(source.format.bytes_per_pixel());
+    let input = &source.data[usize::from(y) * source.stride + usize::from(x) * bpp..];
+    let stride = u32::try_from(source.stride).map_err(|_| RlgrError::InvalidIntegralConversion("stride"))?;
+
+    let mut y_plane = [0i16; TILE_PIXELS];
+    let mut cb_plane = [0i16; TILE_PIXELS];
+    let mut cr_plane = [0i16; TILE_PIXELS];
+
+    crate::color_conversion::to_64x64_ycbcr_tile(
+        input,
+        u32::from(tile_width),
+        u32::from(tile_height),
+        stride,
+        source.format,
+        &mut y_plane,
+        &mut cb_plane,
+        &mut cr_plane,
+    )
+    .map_err(RlgrError::Yuv)?;
+
+    let (y_data, buffer) = buffer.split_at_mut(TILE_PIXELS);
+    let (cb_data, cr_data) = buffer.split_at_mut(TILE_PIXELS);
+
+    let len = rfx_encode_component(&mut y_plane, y_data, quant, entropy_algorithm)?;
+    let y_data = &y_data[..len];
+    let len = rfx_encode_component(&mut cb_plane, cb_data, quant, entropy_algorithm)?;
+    let cb_data = &cb_data[..len];
+    let len = rfx_encode_component(&mut cr_plane, cr_data, quant, entropy_algorithm)?;
+    let cr_data = &cr_data[..len];
+
+    Ok(Tile {
+        y_quant_index: 0,
+        cb_quant_index: 0,
+        cr_quant_index: 0,
+        x: tile_x,
+        y: tile_y,
+        y_data,
+        cb_data,
+        cr_data,
+    })
+}
+
+/// Decodes `tiles` into consecutive 64x64 RGBA blocks of [`DECODED_TILE_SIZE`] bytes
+///
+/// `output` is resized to hold all the decoded tiles, in the same order as `tiles`.
+pub fn decode_tiles(
+    tiles: &[Tile<'_>],
+    quants: &[Quant],
+    entropy_algorithm: EntropyAlgorithm,
+    output: &mut Vec<u8>,
+) -> Result<(), TileError> {
+    output.resize(tiles.len() * DECODED_TILE_SIZE, 0);
+
+    #[cfg(not(feature = "rayon"))]
+    let chunks = output.chunks_mut(DECODED_TILE_SIZE);
+    #[cfg(feature = "rayon")]
+    let chunks = output.par_chunks_mut(DECODED_TILE_SIZE);
+
+    chunks
+        .zip(tiles)
+        .try_for_each(|(output, tile)| decode_tile(tile, quants, entropy_algorithm, output))
+}
+
+/// Decodes a single tile into `output`, which must be at least [`DECODED_TILE_SIZE`] bytes long
+pub fn decode_tile(
+    tile: &Tile<'_>,
+    quants: &[Quant],
+    entropy_algorithm: EntropyAlgorithm,
+    output: &mut [u8],
+) -> Result<(), TileError> {
+    let mut planes = [[0i16; TILE_PIXELS]; 3];
+    let mut temp = [0i16; TILE_PIXELS];
+
+    let components = [
+        (tile.y_data, tile.y_quant_index),
+        (tile.cb_data, tile.cb_quant_index),
+        (tile.cr_data, tile.cr_quant_index),
+    ];
+
+    for ((data, quant_index), plane) in components.into_iter().zip(planes.iter_mut()) {
+        let quant = quants
+            .get(usize::from(quant_index))
+            .ok_or(TileError::InvalidQuantIndex {
+                index: quant_index,
+                count: quants.len(),
+            })?;
+
+        rlgr::decode(entropy_algorithm, data, plane)?;
+        subband_reconstruction::decode(&mut plane[4032..]);
+        quantization::decode(plane, quant);
+        dwt::decode(plane, &mut temp);
+    }
+
+    let [y, cb, cr] = &planes;
+    ycbcr_to_rgba(YCbCrBuffer { y, cb, cr }, &mut output[..DECODED_TILE_SIZE]).map_err(RlgrError::Io)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(data: &[u8], width: u16, height: u16) -> TileSource<'_> {
+        TileSource {
+            data,
+            width,
+            height,
+            stride: usize::from(width) * 4,
+            format: PixelFormat::BgrA32,
+        }
+    }
+
+    fn gradient(width: u16, height: u16) -> Vec<u8> {
+        (0..usize::from(width) * usize::from(height) * 4)
+            .map(|i| u8::try_from(i % 251).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn encode_covers_all_tiles() {
+        let data = gradient(130, 70);
+        let mut encoder = TileEncoder::new(Quant::default(), EntropyAlgorithm::Rlgr3);
+
+        let tiles = encoder.encode(&source(&data, 130, 70)).unwrap();
+        let positions: Vec<_> = tiles.iter().map(|tile| (tile.x, tile.y)).collect();
+
+        assert_eq!(positions, [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        assert!(tiles.iter().all(|tile| !tile.y_data.is_empty()));
+    }
+
+    #[test]
+    fn decode_tiles_matches_single_tile_decoding() {
+        let data = gradient(128, 64);
+        let mut encoder = TileEncoder::new(Quant::default(), EntropyAlgorithm::Rlgr1);
+        let tiles = encoder.encode(&source(&data, 128, 64)).unwrap();
+        let quants = [Quant::default()];
+
+        let mut output = Vec::new();
+        decode_tiles(&tiles, &quants, EntropyAlgorithm::Rlgr1, &mut output).unwrap();
+
+        assert_eq!(output.len(), 2 * DECODED_TILE_SIZE);
+
+        for (tile, decoded) in tiles.iter().zip(output.chunks_exact(DECODED_TILE_SIZE)) {
+            let mut expected = vec![0; DECODED_TILE_SIZE];
+            decode_tile(tile, &quants, EntropyAlgorithm::Rlgr1, &mut expected).unwrap();
+
+            assert_eq!(decoded, expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn decode_rejects_invalid_quant_index() {
+        let data = gradient(64, 64);
+        let mut encoder = TileEncoder::new(Quant::default(), EntropyAlgorithm::Rlgr3);
+        let mut tiles = encoder.encode(&source(&data, 64, 64)).unwrap();
+        tiles[0].cr_quant_index = 1;
+
+        let result = decode_tiles(&tiles, &[Quant::default()], EntropyAlgorithm::Rlgr3, &mut Vec::new());
+
+        assert!(matches!(
+            result,
+            Err(TileError::InvalidQuantIndex { index: 1, count: 1 })
+        ));
+    }
+}
@@ -103,6 +103,63 @@ pub fn decompress_8_bpp(
     decompress_helper::<Mode8Bpp>(src, dst, width, height)
 }
 
+/// Compresses a bitmap using interleaved RLE.
+///
+/// `src`: source buffer containing the bitmap, laid out as produced by [`decompress`]
+/// `dst`: destination buffer, cleared before receiving the compressed bitmap
+/// `width`: bitmap width
+/// `height`: bitmap height
+/// `bpp`: bits per pixel
+pub fn compress(src: &[u8], dst: &mut Vec<u8>, width: usize, height: usize, bpp: usize) -> Result<(), RleError> {
+    match bpp {
+        Mode24Bpp::BPP => compress_24_bpp(src, dst, width, height),
+        Mode16Bpp::BPP => compress_16_bpp(src, dst, width, height),
+        Mode15Bpp::BPP => compress_15_bpp(src, dst, width, height),
+        Mode8Bpp::BPP => compress_8_bpp(src, dst, width, height),
+        invalid => Err(RleError::InvalidBpp { bpp: invalid }),
+    }
+}
+
+/// Compresses a 24-bpp bitmap using interleaved RLE.
+///
+/// `src`: source buffer containing the bitmap
+/// `dst`: destination buffer
+/// `width`: bitmap width
+/// `height`: bitmap height
+pub fn compress_24_bpp(src: &[u8], dst: &mut Vec<u8>, width: usize, height: usize) -> Result<(), RleError> {
+    compress_helper::<Mode24Bpp>(src, dst, width, height)
+}
+
+/// Compresses a 16-bpp bitmap using interleaved RLE.
+///
+/// `src`: source buffer containing the bitmap
+/// `dst`: destination buffer
+/// `width`: bitmap width
+/// `height`: bitmap height
+pub fn compress_16_bpp(src: &[u8], dst: &mut Vec<u8>, width: usize, height: usize) -> Result<(), RleError> {
+    compress_helper::<Mode16Bpp>(src, dst, width, height)
+}
+
+/// Compresses a 15-bpp bitmap using interleaved RLE.
+///
+/// `src`: source buffer containing the bitmap
+/// `dst`: destination buffer
+/// `width`: bitmap width
+/// `height`: bitmap height
+pub fn compress_15_bpp(src: &[u8], dst: &mut Vec<u8>, width: usize, height: usize) -> Result<(), RleError> {
+    compress_helper::<Mode15Bpp>(src, dst, width, height)
+}
+
+/// Compresses a 8-bpp bitmap using interleaved RLE.
+///
+/// `src`: source buffer containing the bitmap
+/// `dst`: destination buffer
+/// `width`: bitmap width
+/// `height`: bitmap height
+pub fn compress_8_bpp(src: &[u8], dst: &mut Vec<u8>, width: usize, height: usize) -> Result<(), RleError> {
+    compress_helper::<Mode8Bpp>(src, dst, width, height)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RleError {
     InvalidBpp {
@@ -144,6 +201,8 @@ impl fmt::Display for RleError {
     }
 }
 
+impl core::error::Error for RleError {}
+
 fn decompress_helper<Mode: DepthMode>(
     src: &[u8],
     dst: &mut Vec<u8>,
@@ -259,9 +318,8 @@ fn decompress_impl<Mode: DepthMode>(src: &[u8], dst: &mut [u8], row_delta: usize
         {
             // Handle Foreground Run Orders.
 
-            ensure_size!(from: src, size: Mode::COLOR_DEPTH);
-
             if code == Code::LITE_SET_FG_FG_RUN || code == Code::MEGA_MEGA_SET_FG_RUN {
+                ensure_size!(from: src, size: Mode::COLOR_DEPTH);
                 fg_pel = Mode::read_pixel(&mut src);
             }
 
@@ -383,6 +441,371 @@ fn decompress_impl<Mode: DepthMode>(src: &[u8], dst: &mut [u8], row_delta: usize
     Ok(())
 }
 
+fn compress_helper<Mode: DepthMode>(
+    src: &[u8],
+    dst: &mut Vec<u8>,
+    width: usize,
+    height: usize,
+) -> Result<(), RleError> {
+    if width == 0 || height == 0 {
+        return Err(RleError::EmptyImage);
+    }
+
+    let byte_count = Mode::COLOR_DEPTH * width * height;
+    let src = src.get(..byte_count).ok_or(RleError::NotEnoughBytes {
+        expected: byte_count,
+        actual: src.len(),
+    })?;
+
+    let pixels: Vec<Mode::Pixel> = src
+        .chunks_exact(Mode::COLOR_DEPTH)
+        .map(|bytes| Mode::read_pixel(&mut Buf::new(bytes)))
+        .collect();
+
+    dst.clear();
+    Encoder::<Mode>::new(&pixels, width, dst).compress();
+
+    Ok(())
+}
+
+/// Longest run which can be encoded by a single order
+const MAX_RUN_LENGTH: usize = 0xFFFF;
+
+/// Shortest background, foreground and color runs worth an order
+const MIN_RUN_LENGTH: usize = 3;
+
+/// Shortest dithered run worth an order, i.e. two pairs of pixels
+const MIN_DITHERED_RUN_LENGTH: usize = 4;
+
+/// Shortest foreground/background image worth an order
+const MIN_FG_BG_IMAGE_LENGTH: usize = 8;
+
+/// Background pixels after which a foreground/background image is cut, leaving room for a background run
+const FG_BG_IMAGE_BREAK_LENGTH: usize = 16;
+
+#[derive(Clone, Copy)]
+enum Order<Pixel> {
+    BgRun(usize),
+    FgRun(usize),
+    SetFgRun(usize, Pixel),
+    ColorRun(usize, Pixel),
+    DitheredRun(usize, Pixel, Pixel),
+    FgBgImage(usize),
+    SetFgBgImage(usize, Pixel),
+}
+
+impl<Pixel> Order<Pixel> {
+    /// Number of pixels covered by the order
+    fn pixel_count(&self) -> usize {
+        match *self {
+            Self::BgRun(len)
+            | Self::FgRun(len)
+            | Self::SetFgRun(len, _)
+            | Self::ColorRun(len, _)
+            | Self::FgBgImage(len)
+            | Self::SetFgBgImage(len, _) => len,
+            Self::DitheredRun(len, _, _) => len * 2,
+        }
+    }
+}
+
+/// Greedy interleaved RLE encoder
+///
+/// The encoder tracks the same state as the decoder (foreground pel, first scanline, inserted
+/// foreground pel), and picks at each position the order covering the most pixels. Pixels not
+/// covered by any worthwhile order are gathered into color images.
+struct Encoder<'a, Mode: DepthMode> {
+    pixels: &'a [Mode::Pixel],
+    width: usize,
+    dst: &'a mut Vec<u8>,
+    fg_pel: Mode::Pixel,
+    is_first_line: bool,
+    /// The last order was a background run, so that a following background run would start with a foreground pel
+    insert_fg_pel: bool,
+    /// Start of the pending color image
+    image_start: Option<usize>,
+}
+
+impl<'a, Mode: DepthMode> Encoder<'a, Mode> {
+    fn new(pixels: &'a [Mode::Pixel], width: usize, dst: &'a mut Vec<u8>) -> Self {
+        Self {
+            pixels,
+            width,
+            dst,
+            fg_pel: Mode::WHITE_PIXEL,
+            is_first_line: true,
+            insert_fg_pel: false,
+            image_start: None,
+        }
+    }
+
+    fn compress(mut self) {
+        let mut pos = 0;
+
+        while pos < self.pixels.len() {
+            // Watch out for the end of the first scanline, just like the decoder.
+            if self.is_first_line && pos >= self.width {
+                self.is_first_line = false;
+                self.insert_fg_pel = false;
+            }
+
+            if let Some(order) = self.best_order(pos) {
+                self.flush_color_image(pos);
+                self.write_order(pos, order);
+                pos += order.pixel_count();
+            } else {
+                let image_start = *self.image_start.get_or_insert(pos);
+                pos += 1;
+
+                if pos - image_start == MAX_RUN_LENGTH {
+                    self.flush_color_image(pos);
+                }
+            }
+        }
+
+        self.flush_color_image(self.pixels.len());
+    }
+
+    /// Finds the order covering the most pixels starting at `pos`, if any is worth it
+    fn best_order(&self, pos: usize) -> Option<Order<Mode::Pixel>> {
+        let pixels = self.pixels;
+        let pixel = pixels[pos];
+
+        // Orders relying on the pixels above are not allowed to leave the first scanline,
+        // where the decoder uses black pixels instead.
+        let end = if self.is_first_line { self.width } else { pixels.len() };
+        let end = end.min(pos + MAX_RUN_LENGTH);
+        let any_end = pixels.len().min(pos + MAX_RUN_LENGTH);
+
+        let mut candidates = Vec::with_capacity(7);
+
+        // A background run following another one would start with a foreground pel.
+        if !self.insert_fg_pel || self.image_start.is_some() {
+            let len = run_length(pos, end, |i| pixels[i] == self.background(i));
+            candidates.push((Order::BgRun(len), MIN_RUN_LENGTH));
+        }
+
+        let len = run_length(pos, end, |i| pixels[i] == self.foreground(i, self.fg_pel));
+        candidates.push((Order::FgRun(len), MIN_RUN_LENGTH));
+
+        let len = run_length(pos, any_end, |i| pixels[i] == pixel);
+        candidates.push((Order::ColorRun(len, pixel), MIN_RUN_LENGTH));
+
+        if let Some(&next) = pixels.get(pos + 1) {
+            let max_pairs = (pixels.len() - pos) / 2;
+            let len = (0..max_pairs.min(MAX_RUN_LENGTH))
+                .take_while(|pair| pixels[pos + 2 * pair] == pixel && pixels[pos + 2 * pair + 1] == next)
+                .count();
+            candidates.push((Order::DitheredRun(len, pixel, next), MIN_DITHERED_RUN_LENGTH));
+        }
+
+        let len = self.fg_bg_image_length(pos, end, self.fg_pel);
+        candidates.push((Order::FgBgImage(len), MIN_FG_BG_IMAGE_LENGTH));
+
+        // The foreground pel which would produce the current pixel
+        let fg_pel = if pos < self.width {
+            pixel
+        } else {
+            pixel ^ pixels[pos - self.width]
+        };
+
+        if fg_pel != self.fg_pel {
+            let len = run_length(pos, end, |i| pixels[i] == self.foreground(i, fg_pel));
+            candidates.push((Order::SetFgRun(len, fg_pel), MIN_RUN_LENGTH));
+
+            let len = self.fg_bg_image_length(pos, end, fg_pel);
+            candidates.push((Order::SetFgBgImage(len, fg_pel), MIN_FG_BG_IMAGE_LENGTH));
+        }
+
+        candidates
+            .into_iter()
+            .filter(|(order, min_length)| order.pixel_count() >= *min_length)
+            .map(|(order, _)| order)
+            .reduce(|best, order| {
+                if order.pixel_count() > best.pixel_count() {
+                    order
+                } else {
+                    best
+                }
+            })
+    }
+
+    fn fg_bg_image_length(&self, start: usize, end: usize, fg_pel: Mode::Pixel) -> usize {
+        let mut len = 0;
+        let mut bg_count = 0;
+
+        for i in start..end {
+            if self.pixels[i] == self.background(i) {
+                bg_count += 1;
+
+                if bg_count == FG_BG_IMAGE_BREAK_LENGTH {
+                    return len + 1 - bg_count;
+                }
+            } else if self.pixels[i] == self.foreground(i, fg_pel) {
+                bg_count = 0;
+            } else {
+                break;
+            }
+
+            len += 1;
+        }
+
+        len
+    }
+
+    /// Background pixel at `pos`, for orders starting on the same scanline
+    fn background(&self, pos: usize) -> Mode::Pixel {
+        if pos < self.width {
+            Mode::BLACK_PIXEL
+        } else {
+            self.pixels[pos - self.width]
+        }
+    }
+
+    /// Foreground pixel at `pos`, for orders starting on the same scanline
+    fn foreground(&self, pos: usize, fg_pel: Mode::Pixel) -> Mode::Pixel {
+        if pos < self.width {
+            fg_pel
+        } else {
+            self.pixels[pos - self.width] ^ fg_pel
+        }
+    }
+
+    fn flush_color_image(&mut self, end: usize) {
+        if let Some(start) = self.image_start.take() {
+            self.write_regular_header(Code::REGULAR_COLOR_IMAGE, Code::MEGA_MEGA_COLOR_IMAGE, end - start);
+
+            for &pixel in &self.pixels[start..end] {
+                self.write_pixel(pixel);
+            }
+
+            self.insert_fg_pel = false;
+        }
+    }
+
+    fn write_order(&mut self, pos: usize, order: Order<Mode::Pixel>) {
+        match order {
+            Order::BgRun(len) => {
+                self.write_regular_header(Code::REGULAR_BG_RUN, Code::MEGA_MEGA_BG_RUN, len);
+            }
+            Order::FgRun(len) => {
+                self.write_regular_header(Code::REGULAR_FG_RUN, Code::MEGA_MEGA_FG_RUN, len);
+            }
+            Order::SetFgRun(len, fg_pel) => {
+                self.write_lite_header(Code::LITE_SET_FG_FG_RUN, Code::MEGA_MEGA_SET_FG_RUN, len);
+                self.write_pixel(fg_pel);
+                self.fg_pel = fg_pel;
+            }
+            Order::ColorRun(len, pixel) => {
+                self.write_regular_header(Code::REGULAR_COLOR_RUN, Code::MEGA_MEGA_COLOR_RUN, len);
+                self.write_pixel(pixel);
+            }
+            Order::DitheredRun(len, pixel_a, pixel_b) => {
+                self.write_lite_header(Code::LITE_DITHERED_RUN, Code::MEGA_MEGA_DITHERED_RUN, len);
+                self.write_pixel(pixel_a);
+                self.write_pixel(pixel_b);
+            }
+            Order::FgBgImage(len) => {
+                self.write_fg_bg_header(
+                    Code::REGULAR_FGBG_IMAGE.0 << 5,
+                    MASK_REGULAR_RUN_LENGTH,
+                    Code::MEGA_MEGA_FGBG_IMAGE,
+                    len,
+                );
+                self.write_fg_bg_bitmasks(pos, len);
+            }
+            Order::SetFgBgImage(len, fg_pel) => {
+                self.write_fg_bg_header(
+                    Code::LITE_SET_FG_FGBG_IMAGE.0 << 4,
+                    MASK_LITE_RUN_LENGTH,
+                    Code::MEGA_MEGA_SET_FGBG_IMAGE,
+                    len,
+                );
+                self.write_pixel(fg_pel);
+                self.fg_pel = fg_pel;
+                self.write_fg_bg_bitmasks(pos, len);
+            }
+        }
+
+        self.insert_fg_pel = matches!(order, Order::BgRun(_));
+    }
+
+    /// Writes the header of a regular-form order, with an extended (MEGA) or 16-bit (MEGA_MEGA) run length if needed
+    fn write_regular_header(&mut self, code: Code, mega_mega: Code, len: usize) {
+        // Run lengths which do not fit in the 5-bit field
+        const MEGA_THRESHOLD: usize = 32;
+
+        if len < MEGA_THRESHOLD {
+            self.dst.push((code.0 << 5) | to_u8(len));
+        } else if len < MEGA_THRESHOLD + 0x100 {
+            self.dst.push(code.0 << 5);
+            self.dst.push(to_u8(len - MEGA_THRESHOLD));
+        } else {
+            self.write_mega_mega_header(mega_mega, len);
+        }
+    }
+
+    /// Writes the header of a lite-form order, with an extended (MEGA) or 16-bit (MEGA_MEGA) run length if needed
+    fn write_lite_header(&mut self, code: Code, mega_mega: Code, len: usize) {
+        // Run lengths which do not fit in the 4-bit field
+        const MEGA_THRESHOLD: usize = 16;
+
+        if len < MEGA_THRESHOLD {
+            self.dst.push((code.0 << 4) | to_u8(len));
+        } else if len < MEGA_THRESHOLD + 0x100 {
+            self.dst.push(code.0 << 4);
+            self.dst.push(to_u8(len - MEGA_THRESHOLD));
+        } else {
+            self.write_mega_mega_header(mega_mega, len);
+        }
+    }
+
+    /// Writes the header of a foreground/background image, whose short run length is expressed in multiples of 8
+    fn write_fg_bg_header(&mut self, header: u8, length_mask: u8, mega_mega: Code, len: usize) {
+        if len.is_multiple_of(8) && len / 8 <= usize::from(length_mask) {
+            self.dst.push(header | to_u8(len / 8));
+        } else if len <= 0x100 {
+            self.dst.push(header);
+            self.dst.push(to_u8(len - 1));
+        } else {
+            self.write_mega_mega_header(mega_mega, len);
+        }
+    }
+
+    fn write_mega_mega_header(&mut self, code: Code, len: usize) {
+        let len = u16::try_from(len).expect("run length is at most MAX_RUN_LENGTH");
+        self.dst.push(code.0);
+        self.dst.extend_from_slice(&len.to_le_bytes());
+    }
+
+    fn write_fg_bg_bitmasks(&mut self, start: usize, len: usize) {
+        for chunk_start in (start..start + len).step_by(8) {
+            let chunk_end = (chunk_start + 8).min(start + len);
+
+            let bitmask = (chunk_start..chunk_end)
+                .enumerate()
+                .filter(|&(_, i)| self.pixels[i] != self.background(i))
+                .fold(0u8, |bitmask, (bit, _)| bitmask | (1 << bit));
+
+            self.dst.push(bitmask);
+        }
+    }
+
+    fn write_pixel(&mut self, pixel: Mode::Pixel) {
+        let mut bytes = [0; 4];
+        Mode::write_pixel(&mut BufMut::new(&mut bytes[..Mode::COLOR_DEPTH]), pixel);
+        self.dst.extend_from_slice(&bytes[..Mode::COLOR_DEPTH]);
+    }
+}
+
+fn run_length(start: usize, end: usize, matches: impl Fn(usize) -> bool) -> usize {
+    (start..end).take_while(|&i| matches(i)).count()
+}
+
+fn to_u8(value: usize) -> u8 {
+    u8::try_from(value).expect("value checked by the caller to fit in a byte")
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 struct Code(u8);
 
@@ -627,7 +1050,7 @@ impl<'a> BufMut<'a> {
 }
 
 trait DepthMode {
-    type Pixel: Copy + BitXor<Output = Self::Pixel>;
+    type Pixel: Copy + PartialEq + BitXor<Output = Self::Pixel>;
 
     /// The color depth (in bytes per pixel) for this mode
     const COLOR_DEPTH: usize;
@@ -860,4 +1283,104 @@ mod tests {
     fn buf_mut_24_bpp() {
         test_buf_mut!(Mode24Bpp);
     }
+
+    fn assert_round_trip(bitmap: &[u8], width: usize, height: usize, bpp: usize) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        compress(bitmap, &mut compressed, width, height, bpp).unwrap();
+
+        let mut decompressed = Vec::new();
+        decompress(&compressed, &mut decompressed, width, height, bpp).unwrap();
+        assert_eq!(decompressed, bitmap);
+
+        compressed
+    }
+
+    /// Bitmap mixing solid areas, dithering, two-color text-like patterns and noise
+    fn pattern(width: usize, height: usize, color_depth: usize) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+
+                let value: u32 = match (y / 8) % 4 {
+                    0 => 0x10_2030,
+                    1 => {
+                        if (x + y) % 2 == 0 {
+                            0xFF_FFFF
+                        } else {
+                            0x00_0000
+                        }
+                    }
+                    2 => {
+                        if (x * 7 + y * 3) % 5 < 2 {
+                            0x40_8020
+                        } else {
+                            0x10_2030
+                        }
+                    }
+                    _ => state >> 8,
+                };
+
+                value.to_le_bytes().into_iter().take(color_depth)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        for (bpp, color_depth) in [(8, 1), (15, 2), (16, 2), (24, 3)] {
+            for (width, height) in [(1, 1), (3, 2), (64, 64), (37, 50)] {
+                let bitmap = pattern(width, height, color_depth);
+                assert_round_trip(&bitmap, width, height, bpp);
+            }
+        }
+    }
+
+    #[test]
+    fn compress_long_runs() {
+        // Solid bitmap, needing 16-bit run lengths
+        let bitmap = vec![0x42; 300 * 300 * 3];
+        let compressed = assert_round_trip(&bitmap, 300, 300, 24);
+        assert!(compressed.len() < 32);
+
+        // Black bitmap, made of background runs only
+        let bitmap = vec![0; 300 * 300 * 2];
+        let compressed = assert_round_trip(&bitmap, 300, 300, 16);
+        assert!(compressed.len() < 32);
+
+        // Repeated scanlines of noise, made of background runs after the first one
+        let line = pattern(500, 1, 3);
+        let bitmap = line.repeat(200);
+        let compressed = assert_round_trip(&bitmap, 500, 200, 24);
+        assert!(compressed.len() < line.len() + 64);
+    }
+
+    #[test]
+    fn compress_first_line_boundary() {
+        // Background runs must not leak black pixels past the first scanline
+        let mut bitmap = vec![0; 8 * 3];
+        bitmap[8..].fill(0x11);
+        bitmap[8..12].fill(0);
+        assert_round_trip(&bitmap, 4, 3, 16);
+    }
+
+    #[test]
+    fn compress_rejects_invalid_input() {
+        let mut dst = Vec::new();
+
+        assert_eq!(
+            compress(&[0; 16], &mut dst, 4, 4, 32),
+            Err(RleError::InvalidBpp { bpp: 32 })
+        );
+        assert_eq!(compress(&[], &mut dst, 0, 4, 16), Err(RleError::EmptyImage));
+        assert_eq!(
+            compress(&[0; 16], &mut dst, 4, 4, 16),
+            Err(RleError::NotEnoughBytes {
+                expected: 32,
+                actual: 16
+            })
+        );
+    }
 }
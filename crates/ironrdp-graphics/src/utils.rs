@@ -1,3 +1,4 @@
+use core::cmp::min;
 use core::ops;
 
 use bitvec::prelude::{BitSlice, Msb0};
@@ -36,3 +37,87 @@ impl ops::Deref for Bits<'_> {
         self.bits_slice
     }
 }
+
+/// Reads bits most significant bit first
+///
+/// The next bits are loaded in a 64-bit word, so that the runs of ones and zeros are counted with a single
+/// instruction instead of bit by bit.
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    /// Position of the next bit
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.pos
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Next bits in the most significant bits of a word, the bits past the end being zero
+    ///
+    /// At least 57 bits of the word are valid, unless fewer bits remain.
+    fn peek_word(&self) -> u64 {
+        let start = self.pos / 8;
+
+        let bytes = match self.data.get(start..start + 8) {
+            Some(bytes) => bytes.try_into().expect("8 bytes"),
+            None => {
+                let mut bytes = [0u8; 8];
+                let rest = &self.data[start..];
+                bytes[..rest.len()].copy_from_slice(rest);
+                bytes
+            }
+        };
+
+        u64::from_be_bytes(bytes) << (self.pos % 8)
+    }
+
+    /// Consumes the leading bits equal to `value`, returning their count
+    pub(crate) fn skip_leading(&mut self, value: bool) -> usize {
+        #![expect(clippy::as_conversions, reason = "u32-to-usize conversion, hot loop")]
+
+        let mut count = 0;
+
+        loop {
+            let available = min(57, self.remaining());
+            let word = if value { !self.peek_word() } else { self.peek_word() };
+            let leading = min(word.leading_zeros() as usize, available);
+
+            self.pos += leading;
+            count += leading;
+
+            if leading < available || self.is_empty() {
+                return count;
+            }
+        }
+    }
+
+    /// Reads a big-endian value of `count` bits, at most 32
+    pub(crate) fn read(&mut self, count: usize) -> Option<u32> {
+        #![expect(
+            clippy::as_conversions,
+            clippy::cast_possible_truncation,
+            reason = "the value has at most 32 bits"
+        )]
+
+        if self.remaining() < count {
+            return None;
+        }
+        if count == 0 {
+            return Some(0);
+        }
+
+        let value = self.peek_word() >> (64 - count);
+        self.pos += count;
+
+        Some(value as u32)
+    }
+}
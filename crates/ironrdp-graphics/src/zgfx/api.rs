@@ -4,7 +4,7 @@
 //! with automatic mode selection and error handling.
 
 use super::compressor::Compressor;
-use super::wrapper::{wrap_compressed, wrap_uncompressed};
+use super::wrapper::{wrap_segments, wrap_uncompressed, EncodedSegment, ZGFX_SEGMENTED_MAXSIZE};
 use super::ZgfxError;
 
 /// Compression mode for ZGFX encoding
@@ -22,8 +22,8 @@ pub enum CompressionMode {
 ///
 /// This is the main entry point for EGFX data preparation. It handles:
 /// - Compression (if enabled)
-/// - ZGFX segment wrapping
-/// - Automatic fallback to uncompressed if compression doesn't help
+/// - ZGFX segment wrapping, using multipart segments for payloads larger than 65535 bytes
+/// - Automatic fallback to uncompressed if compression doesn't help, decided per segment
 ///
 /// # Arguments
 ///
@@ -56,23 +56,43 @@ pub fn compress_and_wrap_egfx(
             // Just wrap uncompressed
             Ok(wrap_uncompressed(data))
         }
-        CompressionMode::Auto => {
-            // Try compression, use if beneficial
-            let compressed = compressor.compress(data)?;
-            let wrapped_compressed = wrap_compressed(&compressed);
-            let wrapped_uncompressed = wrap_uncompressed(data);
-
-            // Use compressed version only if it's actually smaller
-            if wrapped_compressed.len() < wrapped_uncompressed.len() {
-                Ok(wrapped_compressed)
+        CompressionMode::Auto | CompressionMode::Always => {
+            // Each segment must not exceed ZGFX_SEGMENTED_MAXSIZE bytes once decompressed,
+            // so the input is split before compression and each chunk gets its own bitstream.
+            // The compressor history is shared across segments, like the decompressor's.
+            let chunks: Vec<&[u8]> = if data.is_empty() {
+                vec![data]
             } else {
-                Ok(wrapped_uncompressed)
+                data.chunks(ZGFX_SEGMENTED_MAXSIZE).collect()
+            };
+
+            let mut compressed_chunks = Vec::with_capacity(chunks.len());
+            for chunk in &chunks {
+                compressed_chunks.push(compressor.compress(chunk)?);
             }
-        }
-        CompressionMode::Always => {
-            // Always compress
-            let compressed = compressor.compress(data)?;
-            Ok(wrap_compressed(&compressed))
+
+            let segments: Vec<EncodedSegment<'_>> = chunks
+                .iter()
+                .zip(&compressed_chunks)
+                .map(|(chunk, compressed)| {
+                    // Use compressed version only if it's actually smaller (Auto mode)
+                    if mode == CompressionMode::Always || compressed.len() < chunk.len() {
+                        EncodedSegment {
+                            data: compressed,
+                            compressed: true,
+                            uncompressed_size: chunk.len(),
+                        }
+                    } else {
+                        EncodedSegment {
+                            data: chunk,
+                            compressed: false,
+                            uncompressed_size: chunk.len(),
+                        }
+                    }
+                })
+                .collect();
+
+            Ok(wrap_segments(&segments))
         }
     }
 }
@@ -130,6 +150,50 @@ mod tests {
         assert_eq!(wrapped[0], 0xE0);
     }
 
+    #[test]
+    fn test_large_payload_uses_multipart_compressed_segments() {
+        use super::super::Decompressor;
+
+        let data: Vec<u8> = b"WireToSurface1 ".iter().copied().cycle().take(200_000).collect();
+
+        for mode in [CompressionMode::Auto, CompressionMode::Always] {
+            let mut compressor = Compressor::new();
+            let wrapped = compress_and_wrap_egfx(&data, &mut compressor, mode).unwrap();
+
+            assert_eq!(wrapped[0], 0xE1);
+            let segment_count = u16::from_le_bytes([wrapped[1], wrapped[2]]);
+            assert_eq!(segment_count, 4); // ceil(200000 / 65535)
+            let uncompressed_size = u32::from_le_bytes([wrapped[3], wrapped[4], wrapped[5], wrapped[6]]);
+            assert_eq!(uncompressed_size, 200_000);
+            assert_eq!(wrapped[11], 0x24); // first segment is compressed
+            assert!(wrapped.len() < data.len() / 10);
+
+            let mut decompressor = Decompressor::new();
+            let mut output = Vec::new();
+            let written = decompressor.decompress(&wrapped, &mut output).unwrap();
+
+            assert_eq!(written, data.len());
+            assert_eq!(output, data, "Round-trip failed for mode {:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_multipart_history_spans_pdus() {
+        use super::super::Decompressor;
+
+        let mut compressor = Compressor::new();
+        let mut decompressor = Decompressor::new();
+
+        for i in 0..3u8 {
+            let data: Vec<u8> = (0..100_000u32).map(|j| u8::try_from(j % 251).unwrap() ^ i).collect();
+            let wrapped = compress_and_wrap_egfx(&data, &mut compressor, CompressionMode::Auto).unwrap();
+
+            let mut output = Vec::new();
+            decompressor.decompress(&wrapped, &mut output).unwrap();
+            assert_eq!(output, data);
+        }
+    }
+
     #[test]
     fn test_round_trip_all_modes() {
         use super::super::Decompressor;
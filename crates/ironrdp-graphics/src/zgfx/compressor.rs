@@ -27,6 +27,16 @@
 //!
 //! - Distance: Encoded using match token + additional value bits
 //! - Length: Variable-length encoding (special case for length=3)
+//! - Matches may overlap the bytes being produced (distance < length), which
+//!   turns long runs of a repeated pattern into a single back-reference
+//! - Distance 0 is reserved for unencoded runs: a 15-bit byte count followed by
+//!   byte-aligned raw bytes, used when it is cheaper than a series of literals
+//!
+//! # Compression Levels
+//!
+//! The amount of effort spent searching the history is controlled by
+//! [`CompressionLevel`]. Higher levels inspect more candidate positions,
+//! index every position of long matches and allow unencoded runs.
 //!
 //! # References
 //!
@@ -44,9 +54,12 @@ const MIN_MATCH_LENGTH: usize = 3;
 const MAX_MATCH_LENGTH: usize = 65535; // Practical limit
 const MAX_MATCH_DISTANCE: usize = 2_097_152; // Max for last token
 
-/// Maximum number of candidate positions to check per prefix
-/// Limits worst-case performance when many positions share the same prefix
-const MAX_CANDIDATES: usize = 16;
+/// Maximum number of bytes in a single unencoded run (15-bit count)
+const MAX_UNENCODED_RUN: usize = 0x7FFF;
+
+/// Cost in bits of an unencoded run header, excluding byte alignment:
+/// match token prefix (5) + distance value (5) + byte count (15)
+const UNENCODED_RUN_HEADER_BITS: usize = 5 + 5 + 15;
 
 /// Maximum positions per hash table entry
 /// Prevents unbounded growth for common prefixes
@@ -56,6 +69,61 @@ const MAX_POSITIONS_PER_PREFIX: usize = 32;
 /// Keeps memory usage bounded
 const MAX_HASH_TABLE_ENTRIES: usize = 50_000;
 
+/// Trade-off between compression ratio and CPU time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionLevel {
+    /// Shallow match search, suited to payloads that are mostly incompressible (e.g. H.264)
+    Fast,
+    /// Moderate match search, good default for mixed content
+    #[default]
+    Balanced,
+    /// Deep match search with unencoded runs, best ratio on text and UI content
+    Best,
+}
+
+impl CompressionLevel {
+    fn params(self) -> LevelParams {
+        match self {
+            Self::Fast => LevelParams {
+                max_candidates: 4,
+                good_match_length: 16,
+                large_chunk_step: 8,
+                unencoded_runs: false,
+            },
+            Self::Balanced => LevelParams {
+                max_candidates: 16,
+                good_match_length: 32,
+                large_chunk_step: 4,
+                unencoded_runs: false,
+            },
+            Self::Best => LevelParams {
+                max_candidates: MAX_POSITIONS_PER_PREFIX,
+                good_match_length: 256,
+                large_chunk_step: 1,
+                unencoded_runs: true,
+            },
+        }
+    }
+}
+
+/// Tuning knobs derived from a [`CompressionLevel`]
+#[derive(Debug, Clone, Copy)]
+struct LevelParams {
+    /// Maximum number of candidate positions to check per prefix
+    ///
+    /// Limits worst-case performance when many positions share the same prefix.
+    max_candidates: usize,
+
+    /// Stop searching once a match at least this long has been found
+    good_match_length: usize,
+
+    /// Sampling step used when indexing chunks longer than 256 bytes
+    large_chunk_step: usize,
+
+    /// Whether runs of literals may be emitted as unencoded bytes
+    unencoded_runs: bool,
+}
+
 /// ZGFX Compressor with history buffer and hash table for fast match finding
 pub struct Compressor {
     /// History buffer containing previously compressed data
@@ -65,17 +133,41 @@ pub struct Compressor {
     /// Key: [u8; 3] representing a 3-byte sequence
     /// Value: Vec<usize> of positions where this prefix occurs in history
     match_table: HashMap<[u8; 3], Vec<usize>>,
+
+    level: CompressionLevel,
+    params: LevelParams,
 }
 
 impl Compressor {
-    /// Create a new ZGFX compressor
+    /// Create a new ZGFX compressor using [`CompressionLevel::Balanced`]
     pub fn new() -> Self {
+        Self::with_level(CompressionLevel::default())
+    }
+
+    /// Create a new ZGFX compressor using the given compression level
+    pub fn with_level(level: CompressionLevel) -> Self {
         Self {
             history: Vec::with_capacity(HISTORY_SIZE),
             match_table: HashMap::new(),
+            level,
+            params: level.params(),
         }
     }
 
+    /// Returns the current compression level
+    pub fn level(&self) -> CompressionLevel {
+        self.level
+    }
+
+    /// Change the compression level
+    ///
+    /// The history buffer is preserved, so this can be called between PDUs
+    /// without resynchronizing with the decompressor.
+    pub fn set_level(&mut self, level: CompressionLevel) {
+        self.level = level;
+        self.params = level.params();
+    }
+
     /// Compress data using ZGFX algorithm
     ///
     /// Returns compressed data with ZGFX token encoding.
@@ -91,12 +183,17 @@ impl Compressor {
         let mut bit_writer = BitWriter::new();
         let mut pos = 0;
 
+        // Start of the pending run of bytes for which no match was found
+        let mut literals_start = 0;
+
         while pos < input.len() {
             // Try to find a match in history
             let best_match = self.find_best_match(input, pos);
 
             if let Some(m) = best_match {
                 if m.length >= MIN_MATCH_LENGTH {
+                    self.encode_literals(&mut bit_writer, &input[literals_start..pos])?;
+
                     // Encode as match
                     self.encode_match(&mut bit_writer, m.distance, m.length)?;
 
@@ -104,20 +201,70 @@ impl Compressor {
                     self.add_to_history(&input[pos..pos + m.length]);
 
                     pos += m.length;
+                    literals_start = pos;
                     continue;
                 }
             }
 
-            // Encode as literal
-            let byte = input[pos];
-            self.encode_literal(&mut bit_writer, byte)?;
-            self.add_to_history(&[byte]);
+            // Literal: its encoding is deferred until the end of the run is known,
+            // but it must be part of the history for the next match lookups
+            self.add_to_history(&input[pos..pos + 1]);
             pos += 1;
         }
 
+        self.encode_literals(&mut bit_writer, &input[literals_start..])?;
+
         Ok(bit_writer.finish())
     }
 
+    /// Encode a run of bytes for which no match was found
+    ///
+    /// Uses literal tokens, or an unencoded run when the level allows it and
+    /// it takes fewer bits.
+    fn encode_literals(&self, writer: &mut BitWriter, literals: &[u8]) -> Result<(), ZgfxError> {
+        for chunk in literals.chunks(MAX_UNENCODED_RUN) {
+            let literal_bits: usize = chunk.iter().map(|&byte| Self::literal_cost(byte)).sum();
+
+            if self.params.unencoded_runs && Self::unencoded_run_cost(writer, chunk.len()) < literal_bits {
+                Self::encode_unencoded_run(writer, chunk);
+            } else {
+                for &byte in chunk {
+                    self.encode_literal(writer, byte)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Size in bits of a literal token for the given byte
+    fn literal_cost(byte: u8) -> usize {
+        Self::find_literal_token(byte).map_or(9, |token_idx| TOKEN_TABLE[token_idx].prefix.len())
+    }
+
+    /// Size in bits of an unencoded run of `count` bytes starting at the current writer position
+    fn unencoded_run_cost(writer: &BitWriter, count: usize) -> usize {
+        let header_end = writer.bits_in_current + UNENCODED_RUN_HEADER_BITS;
+        let padding = (8 - header_end % 8) % 8;
+
+        UNENCODED_RUN_HEADER_BITS + padding + count * 8
+    }
+
+    /// Encode an unencoded run: match token with distance 0, 15-bit count, then byte-aligned raw bytes
+    fn encode_unencoded_run(writer: &mut BitWriter, bytes: &[u8]) {
+        debug_assert!(bytes.len() <= MAX_UNENCODED_RUN);
+
+        let token = &TOKEN_TABLE[26];
+        writer.write_bits_from_slice(token.prefix);
+        writer.write_bits(0, 5);
+        writer.write_bits(u32::try_from(bytes.len()).expect("run length fits in 15 bits"), 15);
+        writer.align_to_byte();
+
+        for &byte in bytes {
+            writer.write_bits(u32::from(byte), 8);
+        }
+    }
+
     /// Add bytes to history buffer (managing size limit and hash table)
     fn add_to_history(&mut self, bytes: &[u8]) {
         // Handle history buffer overflow
@@ -160,8 +307,8 @@ impl Compressor {
         // OPTIMIZATION: For large chunks (matches), don't add every position
         // Sample positions to keep hash table manageable
         let step_size = if bytes.len() > 256 {
-            // For large chunks (matches), sample every 4th position
-            4
+            // For large chunks (matches), sample positions depending on the level
+            self.params.large_chunk_step
         } else {
             // For small chunks (literals), add all positions
             1
@@ -169,15 +316,9 @@ impl Compressor {
 
         for i in (0..bytes.len().saturating_sub(MIN_MATCH_LENGTH - 1)).step_by(step_size) {
             let pos = base_pos + i;
-            let prefix = [
-                self.history[pos],
-                self.history[pos + 1],
-                self.history[pos + 2],
-            ];
+            let prefix = [self.history[pos], self.history[pos + 1], self.history[pos + 2]];
 
-            let entry = self.match_table
-                .entry(prefix)
-                .or_insert_with(Vec::new);
+            let entry = self.match_table.entry(prefix).or_insert_with(Vec::new);
 
             // Limit positions per prefix to prevent unbounded growth
             if entry.len() < MAX_POSITIONS_PER_PREFIX {
@@ -244,8 +385,11 @@ impl Compressor {
     /// Algorithm:
     /// 1. Extract 3-byte prefix from input at current position
     /// 2. Look up candidate positions in hash table (O(1))
-    /// 3. Check only those candidates (typically 1-16) for best match
+    /// 3. Check only those candidates (bounded by the compression level) for best match
     /// 4. Return longest match found
+    ///
+    /// The last byte of history is always probed as well, so that runs of a
+    /// single repeated byte are found before their prefix is indexed.
     fn find_best_match(&self, input: &[u8], pos: usize) -> Option<Match> {
         let remaining = input.len() - pos;
         if remaining < MIN_MATCH_LENGTH || self.history.is_empty() {
@@ -255,16 +399,31 @@ impl Compressor {
         // Extract 3-byte prefix for hash table lookup
         let prefix = [input[pos], input[pos + 1], input[pos + 2]];
 
-        // O(1) hash table lookup to get candidate positions
-        let candidates = self.match_table.get(&prefix)?;
-
         let max_match_len = remaining.min(MAX_MATCH_LENGTH);
         let mut best_match: Option<Match> = None;
         let search_limit = self.history.len().min(MAX_MATCH_DISTANCE);
 
+        // Run probe: distance 1, i.e. the last byte repeated
+        let last = self.history.len() - 1;
+        if prefix == [self.history[last]; 3] {
+            best_match = Some(Match {
+                distance: 1,
+                length: self.match_length_at(input, pos, last, max_match_len),
+            });
+        }
+
+        // O(1) hash table lookup to get candidate positions
+        let candidates = self.match_table.get(&prefix).map(Vec::as_slice).unwrap_or_default();
+
         // Check candidates in reverse order (most recent first)
-        // Limit to MAX_CANDIDATES to bound worst-case performance
-        for &hist_pos in candidates.iter().rev().take(MAX_CANDIDATES) {
+        // Limit the number of candidates to bound worst-case performance
+        for &hist_pos in candidates.iter().rev().take(self.params.max_candidates) {
+            if best_match.is_some_and(|m| m.length >= self.params.good_match_length) {
+                // Early exit optimization: stop if we found a very good match
+                // (diminishing returns beyond this point)
+                break;
+            }
+
             let distance = self.history.len() - hist_pos;
 
             // Skip if outside search limit
@@ -272,34 +431,47 @@ impl Compressor {
                 continue;
             }
 
-            // We already know first 3 bytes match (that's why we're here)
-            // Start checking from byte 3
-            let mut match_len = MIN_MATCH_LENGTH;
+            let match_len = self.match_length_at(input, pos, hist_pos, max_match_len);
 
-            while match_len < max_match_len
-                && hist_pos + match_len < self.history.len()
-                && self.history[hist_pos + match_len] == input[pos + match_len]
-            {
-                match_len += 1;
+            // Update best match if this is longer
+            if best_match.is_none_or(|current_best| match_len > current_best.length) {
+                best_match = Some(Match {
+                    distance,
+                    length: match_len,
+                });
             }
+        }
 
-            // Update best match if this is longer
-            if let Some(ref current_best) = best_match {
-                if match_len > current_best.length {
-                    best_match = Some(Match { distance, length: match_len });
-                }
+        best_match
+    }
+
+    /// Length of the match between `input[pos..]` and the history starting at `hist_pos`
+    ///
+    /// The first 3 bytes are known to match. The match is allowed to run past the end
+    /// of the history into the input itself (overlapping match), as the decompressor
+    /// copies byte by byte.
+    fn match_length_at(&self, input: &[u8], pos: usize, hist_pos: usize, max_match_len: usize) -> usize {
+        let distance = self.history.len() - hist_pos;
+
+        // We already know first 3 bytes match (that's why we're here)
+        // Start checking from byte 3
+        let mut match_len = MIN_MATCH_LENGTH;
+
+        while match_len < max_match_len {
+            let expected = if hist_pos + match_len < self.history.len() {
+                self.history[hist_pos + match_len]
             } else {
-                best_match = Some(Match { distance, length: match_len });
-            }
+                input[pos + match_len - distance]
+            };
 
-            // Early exit optimization: stop if we found a very good match
-            // (diminishing returns beyond this point)
-            if match_len >= 32 {
+            if expected != input[pos + match_len] {
                 break;
             }
+
+            match_len += 1;
         }
 
-        best_match
+        match_len
     }
 
     /// Find the appropriate match token for a given distance
@@ -391,7 +563,7 @@ impl Compressor {
         } else {
             // Calculate token_size from length
             // length = base + value, where base = 2^(token_size+1)
-            let length_token_size = (length as f64).log2().floor() as usize - 1;
+            let length_token_size = usize::try_from(length.ilog2() - 1).expect("u32 fits in usize");
             let base = 1 << (length_token_size + 1);
             let value = length - base;
 
@@ -467,6 +639,13 @@ impl BitWriter {
         }
     }
 
+    /// Pad with zero bits up to the next byte boundary
+    fn align_to_byte(&mut self) {
+        while self.bits_in_current != 0 {
+            self.write_bit(false);
+        }
+    }
+
     /// Write bits from a BitSlice
     fn write_bits_from_slice(&mut self, bits: &BitSlice<u8, Msb0>) {
         for bit in bits {
@@ -595,6 +774,105 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compress_round_trip_all_levels() {
+        use super::super::Decompressor;
+
+        let mut data = Vec::new();
+        for i in 0..200 {
+            data.extend_from_slice(
+                format!("<button id=\"item-{i}\" class=\"menu-entry\">Item {i}</button>\n").as_bytes(),
+            );
+        }
+        data.extend((0..5000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+
+        for level in [
+            CompressionLevel::Fast,
+            CompressionLevel::Balanced,
+            CompressionLevel::Best,
+        ] {
+            let mut compressor = Compressor::with_level(level);
+            let mut decompressor = Decompressor::new();
+
+            let compressed = compressor.compress(&data).unwrap();
+
+            let mut output = Vec::new();
+            decompressor.decompress_segment(&compressed, &mut output).unwrap();
+
+            assert_eq!(output, data, "round-trip failed for {level:?}");
+        }
+    }
+
+    #[test]
+    fn test_best_level_not_worse_than_fast() {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(100);
+
+        let fast = Compressor::with_level(CompressionLevel::Fast)
+            .compress(text.as_bytes())
+            .unwrap();
+        let best = Compressor::with_level(CompressionLevel::Best)
+            .compress(text.as_bytes())
+            .unwrap();
+
+        assert!(best.len() <= fast.len());
+    }
+
+    #[test]
+    fn test_compress_run_uses_overlapping_match() {
+        use super::super::Decompressor;
+
+        let mut compressor = Compressor::new();
+        let data = vec![0x42; 10_000];
+
+        let compressed = compressor.compress(&data).unwrap();
+
+        // One null literal followed by a single overlapping match
+        assert!(compressed.len() < 16);
+
+        let mut decompressor = Decompressor::new();
+        let mut output = Vec::new();
+        decompressor.decompress_segment(&compressed, &mut output).unwrap();
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn test_compress_unencoded_run() {
+        use super::super::Decompressor;
+
+        // Distinct bytes without literal tokens: no matches, only null literals at lower levels
+        let data: Vec<u8> = (0x41..0x7F).collect();
+
+        let balanced = Compressor::with_level(CompressionLevel::Balanced)
+            .compress(&data)
+            .unwrap();
+        let best = Compressor::with_level(CompressionLevel::Best).compress(&data).unwrap();
+        assert!(best.len() < balanced.len());
+
+        let mut decompressor = Decompressor::new();
+        let mut output = Vec::new();
+        decompressor.decompress_segment(&best, &mut output).unwrap();
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn test_set_level_keeps_history() {
+        use super::super::Decompressor;
+
+        let mut compressor = Compressor::with_level(CompressionLevel::Fast);
+        let mut decompressor = Decompressor::new();
+        let data = b"history must survive a level change";
+
+        for level in [CompressionLevel::Best, CompressionLevel::Balanced] {
+            let compressed = compressor.compress(data).unwrap();
+            let mut output = Vec::new();
+            decompressor.decompress_segment(&compressed, &mut output).unwrap();
+            assert_eq!(&output, data);
+
+            compressor.set_level(level);
+            assert_eq!(compressor.level(), level);
+        }
+    }
+
     #[test]
     fn test_bit_writer() {
         let mut writer = BitWriter::new();
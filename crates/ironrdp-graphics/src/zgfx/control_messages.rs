@@ -31,6 +31,12 @@ impl<'a> SegmentedDataPdu<'a> {
                 for _ in 0..segment_count {
                     let size = usize::try_from(buffer.read_u32::<LittleEndian>()?)
                         .map_err(|_| ZgfxError::InvalidIntegralConversion("segment data size"))?;
+                    if size > buffer.len() {
+                        return Err(ZgfxError::InvalidSegmentSize {
+                            segment_size: size,
+                            remaining: buffer.len(),
+                        });
+                    }
                     let (segment_data, new_buffer) = buffer.split_at(size);
                     buffer = new_buffer;
 
@@ -148,6 +154,16 @@ mod test {
         );
     }
 
+    #[test]
+    fn from_buffer_rejects_truncated_multipart_segment() {
+        let buffer = &MULTIPART_SEGMENTED_DATA_PDU_BUFFER[..40];
+
+        assert!(matches!(
+            SegmentedDataPdu::from_buffer(buffer),
+            Err(ZgfxError::InvalidSegmentSize { .. })
+        ));
+    }
+
     #[test]
     fn from_buffer_correctly_parses_zgfx_multipart_segmented_data_pdu() {
         let buffer = MULTIPART_SEGMENTED_DATA_PDU_BUFFER.as_ref();
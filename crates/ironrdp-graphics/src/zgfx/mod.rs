@@ -4,10 +4,12 @@ mod api;
 mod circular_buffer;
 mod compressor;
 mod control_messages;
+mod stream;
 mod wrapper;
 
 pub use api::{compress_and_wrap_egfx, CompressionMode};
-pub use compressor::Compressor;
+pub use compressor::{CompressionLevel, Compressor};
+pub use stream::StreamingDecompressor;
 pub use wrapper::{wrap_compressed, wrap_uncompressed};
 
 use std::io::{self, Write as _};
@@ -453,6 +455,12 @@ pub enum ZgfxError {
     },
     TokenBitsNotFound,
     InvalidIntegralConversion(&'static str),
+    InvalidSegmentSize {
+        segment_size: usize,
+        remaining: usize,
+    },
+    IncompletePdu,
+    TrailingData,
 }
 
 impl core::fmt::Display for ZgfxError {
@@ -470,6 +478,12 @@ impl core::fmt::Display for ZgfxError {
             ),
             Self::TokenBitsNotFound => write!(f, "token bits not found"),
             Self::InvalidIntegralConversion(type_name) => write!(f, "invalid `{type_name}`: out of range integral type conversion"),
+            Self::InvalidSegmentSize {
+                segment_size,
+                remaining,
+            } => write!(f, "segment size ({segment_size}) exceeds remaining data ({remaining})"),
+            Self::IncompletePdu => write!(f, "segmented data PDU ended before all of its segments were received"),
+            Self::TrailingData => write!(f, "unexpected data after the last segment"),
         }
     }
 }
@@ -483,6 +497,9 @@ impl core::error::Error for ZgfxError {
             Self::InvalidDecompressedSize { .. } => None,
             Self::TokenBitsNotFound => None,
             Self::InvalidIntegralConversion(_) => None,
+            Self::InvalidSegmentSize { .. } => None,
+            Self::IncompletePdu => None,
+            Self::TrailingData => None,
         }
     }
 }
@@ -0,0 +1,410 @@
+use std::io::{self, Write as _};
+
+use byteorder::{ByteOrder as _, LittleEndian};
+
+use super::control_messages::{BulkEncodedData, CompressionFlags};
+use super::{Decompressor, ZgfxError};
+
+const DESCRIPTOR_SINGLE: u8 = 0xE0;
+const DESCRIPTOR_MULTIPART: u8 = 0xE1;
+
+/// segmentCount (2 bytes) + uncompressedSize (4 bytes)
+const MULTIPART_HEADER_SIZE: usize = 6;
+/// size field preceding each RDP8_BULK_ENCODED_DATA of a multipart PDU
+const SEGMENT_SIZE_FIELD_SIZE: usize = 4;
+
+/// Incremental ZGFX decompressor
+///
+/// Accepts a RDP_SEGMENTED_DATA PDU in arbitrarily sized chunks, as they arrive
+/// from the DVC layer, instead of requiring the whole PDU in memory. Uncompressed
+/// data is returned as soon as it is received, and only the current compressed
+/// segment (at most 65535 decompressed bytes, see [MS-RDPEGFX] 2.2.5.3) is buffered.
+///
+/// The compression history is shared across PDUs, like with [`Decompressor`].
+/// Each PDU must be terminated by a call to [`StreamingDecompressor::finish`]:
+/// a single-segment PDU carries no length information, so its end is only
+/// known to the transport.
+///
+/// ```
+/// # use ironrdp_graphics::zgfx::{wrap_uncompressed, StreamingDecompressor};
+/// let pdu = wrap_uncompressed(b"hello world");
+///
+/// let mut zgfx = StreamingDecompressor::new();
+/// let mut output = Vec::new();
+/// for chunk in pdu.chunks(3) {
+///     output.extend(zgfx.feed(chunk)?);
+/// }
+/// output.extend(zgfx.finish()?);
+///
+/// assert_eq!(output, b"hello world");
+/// # Ok::<(), ironrdp_graphics::zgfx::ZgfxError>(())
+/// ```
+pub struct StreamingDecompressor {
+    decompressor: Decompressor,
+    state: State,
+    /// Partially received header fields or compressed segment data
+    pending: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Descriptor,
+    SingleHeader,
+    Single {
+        compressed: bool,
+    },
+    MultipartHeader,
+    SegmentSize {
+        progress: MultipartProgress,
+    },
+    SegmentHeader {
+        progress: MultipartProgress,
+        size: usize,
+    },
+    Segment {
+        progress: MultipartProgress,
+        remaining: usize,
+        compressed: bool,
+    },
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MultipartProgress {
+    remaining_segments: u16,
+    uncompressed_size: usize,
+    decompressed_size: usize,
+}
+
+impl MultipartProgress {
+    fn next_state(self) -> Result<State, ZgfxError> {
+        if self.remaining_segments > 0 {
+            Ok(State::SegmentSize { progress: self })
+        } else if self.decompressed_size != self.uncompressed_size {
+            Err(ZgfxError::InvalidDecompressedSize {
+                decompressed_size: self.decompressed_size,
+                uncompressed_size: self.uncompressed_size,
+            })
+        } else {
+            Ok(State::Done)
+        }
+    }
+}
+
+impl StreamingDecompressor {
+    pub fn new() -> Self {
+        Self::with_decompressor(Decompressor::new())
+    }
+
+    /// Continues from the history of an existing decompressor
+    pub fn with_decompressor(decompressor: Decompressor) -> Self {
+        Self {
+            decompressor,
+            state: State::Descriptor,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn into_decompressor(self) -> Decompressor {
+        self.decompressor
+    }
+
+    /// Returns `true` when no partially received PDU is pending
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, State::Descriptor | State::Done)
+    }
+
+    /// Processes the next chunk of the current PDU and returns the data decompressed so far
+    ///
+    /// On error, the partially received PDU is dropped and the decompressor expects the
+    /// start of a new PDU.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<u8>, ZgfxError> {
+        let mut output = Vec::new();
+
+        self.process(chunk, &mut output).inspect_err(|_| self.reset())?;
+
+        Ok(output)
+    }
+
+    /// Signals the end of the current PDU and returns the remaining decompressed data
+    pub fn finish(&mut self) -> Result<Vec<u8>, ZgfxError> {
+        let mut output = Vec::new();
+
+        let result = match self.state {
+            State::Single { compressed: true } => self
+                .decompressor
+                .decompress_segment(&self.pending, &mut output)
+                .map(|_| ()),
+            State::Descriptor | State::Single { compressed: false } | State::Done => Ok(()),
+            State::SingleHeader
+            | State::MultipartHeader
+            | State::SegmentSize { .. }
+            | State::SegmentHeader { .. }
+            | State::Segment { .. } => Err(ZgfxError::IncompletePdu),
+        };
+
+        self.reset();
+        result?;
+
+        Ok(output)
+    }
+
+    fn reset(&mut self) {
+        self.state = State::Descriptor;
+        self.pending.clear();
+    }
+
+    fn process(&mut self, mut input: &[u8], output: &mut Vec<u8>) -> Result<(), ZgfxError> {
+        while !input.is_empty() {
+            self.state = match self.state {
+                State::Descriptor => match take_u8(&mut input) {
+                    DESCRIPTOR_SINGLE => State::SingleHeader,
+                    DESCRIPTOR_MULTIPART => State::MultipartHeader,
+                    _ => return Err(ZgfxError::InvalidSegmentedDescriptor),
+                },
+                State::SingleHeader => State::Single {
+                    compressed: is_compressed(take_u8(&mut input))?,
+                },
+                State::Single { compressed } => {
+                    let data = take(&mut input, usize::MAX);
+                    if compressed {
+                        self.pending.extend_from_slice(data);
+                    } else {
+                        self.write_uncompressed(data, output)?;
+                    }
+                    State::Single { compressed }
+                }
+                State::MultipartHeader => {
+                    if !fill(&mut self.pending, &mut input, MULTIPART_HEADER_SIZE) {
+                        break;
+                    }
+                    let remaining_segments = LittleEndian::read_u16(&self.pending[..2]);
+                    let uncompressed_size = usize::try_from(LittleEndian::read_u32(&self.pending[2..]))
+                        .map_err(|_| ZgfxError::InvalidIntegralConversion("segments uncompressed size"))?;
+                    self.pending.clear();
+
+                    MultipartProgress {
+                        remaining_segments,
+                        uncompressed_size,
+                        decompressed_size: 0,
+                    }
+                    .next_state()?
+                }
+                State::SegmentSize { progress } => {
+                    if !fill(&mut self.pending, &mut input, SEGMENT_SIZE_FIELD_SIZE) {
+                        break;
+                    }
+                    let size = usize::try_from(LittleEndian::read_u32(&self.pending))
+                        .map_err(|_| ZgfxError::InvalidIntegralConversion("segment data size"))?;
+                    self.pending.clear();
+
+                    if size == 0 {
+                        // The segment must at least hold its header byte.
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                    }
+
+                    State::SegmentHeader { progress, size }
+                }
+                State::SegmentHeader { progress, size } => {
+                    let compressed = is_compressed(take_u8(&mut input))?;
+                    let progress = MultipartProgress {
+                        remaining_segments: progress.remaining_segments - 1,
+                        ..progress
+                    };
+
+                    self.end_of_segment(progress, size - 1, compressed, output)?
+                }
+                State::Segment {
+                    mut progress,
+                    remaining,
+                    compressed,
+                } => {
+                    let data = take(&mut input, remaining);
+                    if compressed {
+                        self.pending.extend_from_slice(data);
+                    } else {
+                        self.write_uncompressed(data, output)?;
+                        progress.decompressed_size += data.len();
+                    }
+
+                    self.end_of_segment(progress, remaining - data.len(), compressed, output)?
+                }
+                State::Done => return Err(ZgfxError::TrailingData),
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Moves on to the next segment once all of the current one has been received
+    fn end_of_segment(
+        &mut self,
+        mut progress: MultipartProgress,
+        remaining: usize,
+        compressed: bool,
+        output: &mut Vec<u8>,
+    ) -> Result<State, ZgfxError> {
+        if remaining > 0 {
+            return Ok(State::Segment {
+                progress,
+                remaining,
+                compressed,
+            });
+        }
+
+        if compressed {
+            progress.decompressed_size += self.decompressor.decompress_segment(&self.pending, output)?;
+            self.pending.clear();
+        }
+
+        progress.next_state()
+    }
+
+    fn write_uncompressed(&mut self, data: &[u8], output: &mut Vec<u8>) -> Result<(), ZgfxError> {
+        self.decompressor.history.write_all(data)?;
+        output.extend_from_slice(data);
+
+        Ok(())
+    }
+}
+
+impl Default for StreamingDecompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Decompressor> for StreamingDecompressor {
+    fn from(decompressor: Decompressor) -> Self {
+        Self::with_decompressor(decompressor)
+    }
+}
+
+fn is_compressed(header: u8) -> Result<bool, ZgfxError> {
+    let segment = BulkEncodedData::from_buffer(core::slice::from_ref(&header))?;
+
+    Ok(segment.compression_flags.contains(CompressionFlags::COMPRESSED))
+}
+
+/// Splits off at most `max` bytes from the front of `input`
+fn take<'a>(input: &mut &'a [u8], max: usize) -> &'a [u8] {
+    let (head, tail) = input.split_at(max.min(input.len()));
+    *input = tail;
+    head
+}
+
+fn take_u8(input: &mut &[u8]) -> u8 {
+    let (&byte, tail) = input.split_first().expect("input is not empty");
+    *input = tail;
+    byte
+}
+
+/// Moves bytes from `input` into `pending` until it holds `size` bytes
+///
+/// Returns `true` when `pending` is complete.
+fn fill(pending: &mut Vec<u8>, input: &mut &[u8], size: usize) -> bool {
+    let data = take(input, size - pending.len());
+    pending.extend_from_slice(data);
+    pending.len() == size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zgfx::{compress_and_wrap_egfx, wrap_uncompressed, CompressionMode, Compressor};
+
+    fn feed_in_chunks(zgfx: &mut StreamingDecompressor, pdu: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut output = Vec::new();
+        for chunk in pdu.chunks(chunk_size) {
+            output.extend(zgfx.feed(chunk).unwrap());
+        }
+        output.extend(zgfx.finish().unwrap());
+        output
+    }
+
+    fn sample_data(len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|i| u8::try_from((i / 7) % 251).unwrap() ^ u8::try_from(i % 3).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn single_compressed_pdu_across_chunks() {
+        let data = sample_data(4096);
+        let pdu = compress_and_wrap_egfx(&data, &mut Compressor::new(), CompressionMode::Always).unwrap();
+
+        for chunk_size in [1, 2, 7, 1600, pdu.len()] {
+            let mut zgfx = StreamingDecompressor::new();
+            assert_eq!(feed_in_chunks(&mut zgfx, &pdu, chunk_size), data);
+            assert!(zgfx.is_idle());
+        }
+    }
+
+    #[test]
+    fn uncompressed_data_is_returned_before_finish() {
+        let pdu = wrap_uncompressed(b"streaming");
+
+        let mut zgfx = StreamingDecompressor::new();
+        assert_eq!(zgfx.feed(&pdu[..6]).unwrap(), b"stre");
+        assert_eq!(zgfx.feed(&pdu[6..]).unwrap(), b"aming");
+        assert!(!zgfx.is_idle());
+        assert!(zgfx.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn multipart_pdu_matches_decompressor() {
+        let data = sample_data(200_000);
+        let pdu = compress_and_wrap_egfx(&data, &mut Compressor::new(), CompressionMode::Auto).unwrap();
+        assert_eq!(pdu[0], DESCRIPTOR_MULTIPART);
+
+        let mut expected = Vec::new();
+        Decompressor::new().decompress(&pdu, &mut expected).unwrap();
+
+        for chunk_size in [3, 1590, 65_536] {
+            let mut zgfx = StreamingDecompressor::new();
+            assert_eq!(feed_in_chunks(&mut zgfx, &pdu, chunk_size), expected);
+        }
+        assert_eq!(expected, data);
+    }
+
+    #[test]
+    fn history_is_kept_across_pdus() {
+        let mut compressor = Compressor::new();
+        let mut zgfx = StreamingDecompressor::new();
+
+        for _ in 0..3 {
+            let data = sample_data(10_000);
+            let pdu = compress_and_wrap_egfx(&data, &mut compressor, CompressionMode::Always).unwrap();
+            assert_eq!(feed_in_chunks(&mut zgfx, &pdu, 100), data);
+        }
+    }
+
+    #[test]
+    fn finish_on_truncated_multipart_pdu_fails() {
+        let pdu =
+            compress_and_wrap_egfx(&sample_data(100_000), &mut Compressor::new(), CompressionMode::Never).unwrap();
+
+        let mut zgfx = StreamingDecompressor::new();
+        zgfx.feed(&pdu[..pdu.len() - 1]).unwrap();
+        assert!(matches!(zgfx.finish(), Err(ZgfxError::IncompletePdu)));
+
+        // The decompressor is ready for the next PDU.
+        assert_eq!(feed_in_chunks(&mut zgfx, &wrap_uncompressed(b"next"), 2), b"next");
+    }
+
+    #[test]
+    fn data_after_multipart_pdu_is_rejected() {
+        let mut pdu =
+            compress_and_wrap_egfx(&sample_data(70_000), &mut Compressor::new(), CompressionMode::Never).unwrap();
+        pdu.push(0);
+
+        let mut zgfx = StreamingDecompressor::new();
+        assert!(matches!(zgfx.feed(&pdu), Err(ZgfxError::TrailingData)));
+    }
+
+    #[test]
+    fn invalid_descriptor_is_rejected() {
+        let mut zgfx = StreamingDecompressor::new();
+        assert!(matches!(zgfx.feed(&[0xE2]), Err(ZgfxError::InvalidSegmentedDescriptor)));
+    }
+}
@@ -43,8 +43,8 @@ const ZGFX_PACKET_COMPR_TYPE_RDP8: u8 = 0x04;
 /// COMPRESSED flag (upper 4 bits of flags byte)
 const ZGFX_PACKET_COMPRESSED: u8 = 0x02;
 
-/// Maximum size for a single ZGFX segment (65535 bytes)
-const ZGFX_SEGMENTED_MAXSIZE: usize = 65535;
+/// Maximum size for a single ZGFX segment (65535 bytes), once decompressed
+pub(crate) const ZGFX_SEGMENTED_MAXSIZE: usize = 65535;
 
 /// Wrap data in ZGFX segment structure (uncompressed)
 ///
@@ -77,7 +77,7 @@ pub fn wrap_uncompressed(data: &[u8]) -> Vec<u8> {
     if data.len() <= ZGFX_SEGMENTED_MAXSIZE {
         wrap_single_segment(data, false)
     } else {
-        wrap_multipart_segments(data, false)
+        wrap_multipart_segments(data)
     }
 }
 
@@ -87,6 +87,11 @@ pub fn wrap_uncompressed(data: &[u8]) -> Vec<u8> {
 /// The COMPRESSED flag (0x02) IS set, indicating to the client to decompress
 /// the data using the ZGFX algorithm.
 ///
+/// A compressed bitstream cannot be split, so this always produces a single
+/// segment: `compressed_data` must decompress to at most 65535 bytes. Use
+/// [`compress_and_wrap_egfx`](super::compress_and_wrap_egfx) for larger payloads,
+/// which compresses them segment by segment.
+///
 /// # Arguments
 ///
 /// * `compressed_data` - ZGFX-compressed data (from Compressor::compress())
@@ -95,10 +100,50 @@ pub fn wrap_uncompressed(data: &[u8]) -> Vec<u8> {
 ///
 /// ZGFX segment-wrapped compressed data ready for transmission
 pub fn wrap_compressed(compressed_data: &[u8]) -> Vec<u8> {
-    if compressed_data.len() <= ZGFX_SEGMENTED_MAXSIZE {
-        wrap_single_segment(compressed_data, true)
+    wrap_single_segment(compressed_data, true)
+}
+
+/// One bulk-encoded segment of a ZGFX_SEGMENTED_DATA structure
+pub(crate) struct EncodedSegment<'a> {
+    /// Raw or ZGFX-compressed bytes
+    pub(crate) data: &'a [u8],
+    pub(crate) compressed: bool,
+    /// Number of bytes once decompressed (at most [`ZGFX_SEGMENTED_MAXSIZE`])
+    pub(crate) uncompressed_size: usize,
+}
+
+/// Wrap already-encoded segments, using the single segment form when possible
+pub(crate) fn wrap_segments(segments: &[EncodedSegment<'_>]) -> Vec<u8> {
+    match segments {
+        [] => wrap_single_segment(&[], false),
+        [segment] => wrap_single_segment(segment.data, segment.compressed),
+        segments => {
+            let uncompressed_size = segments.iter().map(|segment| segment.uncompressed_size).sum();
+            let data_size = segments.iter().map(|segment| segment.data.len()).sum::<usize>();
+
+            // Estimate size: descriptor(1) + count(2) + uncompressed_size(4) +
+            //                segments * (size(4) + flags(1)) + data
+            let mut output = Vec::with_capacity(data_size + 7 + segments.len() * 5);
+
+            write_multipart_header(&mut output, segments.len(), uncompressed_size);
+
+            for segment in segments {
+                write_multipart_segment(&mut output, segment.data, segment.compressed);
+            }
+
+            output
+        }
+    }
+}
+
+/// Flags byte: RDP8 type + optional COMPRESSED flag
+///
+/// Lower 4 bits = compression type, upper 4 bits = flags
+fn segment_flags(compressed: bool) -> u8 {
+    if compressed {
+        ZGFX_PACKET_COMPR_TYPE_RDP8 | (ZGFX_PACKET_COMPRESSED << 4)
     } else {
-        wrap_multipart_segments(compressed_data, true)
+        ZGFX_PACKET_COMPR_TYPE_RDP8
     }
 }
 
@@ -114,14 +159,8 @@ fn wrap_single_segment(data: &[u8], compressed: bool) -> Vec<u8> {
     // Descriptor
     output.push(ZGFX_SEGMENTED_SINGLE);
 
-    // Flags: RDP8 type + optional COMPRESSED flag
-    // Lower 4 bits = compression type, upper 4 bits = flags
-    let flags = if compressed {
-        ZGFX_PACKET_COMPR_TYPE_RDP8 | (ZGFX_PACKET_COMPRESSED << 4)
-    } else {
-        ZGFX_PACKET_COMPR_TYPE_RDP8
-    };
-    output.push(flags);
+    // Flags
+    output.push(segment_flags(compressed));
 
     // Data (raw or compressed)
     output.extend_from_slice(data);
@@ -129,53 +168,45 @@ fn wrap_single_segment(data: &[u8], compressed: bool) -> Vec<u8> {
     output
 }
 
-/// Wrap data in multiple ZGFX segments
-///
-/// # Arguments
-///
-/// * `data` - Data to wrap
-/// * `compressed` - Whether the data is already ZGFX-compressed
-fn wrap_multipart_segments(data: &[u8], compressed: bool) -> Vec<u8> {
-    let segments: Vec<&[u8]> = data.chunks(ZGFX_SEGMENTED_MAXSIZE).collect();
-    let segment_count = segments.len();
-
-    // Estimate size: descriptor(1) + count(2) + uncompressed_size(4) +
-    //                segments * (size(4) + flags(1)) + data
-    let mut output = Vec::with_capacity(data.len() + 7 + segment_count * 5);
+/// Wrap raw data in multiple uncompressed ZGFX segments
+fn wrap_multipart_segments(data: &[u8]) -> Vec<u8> {
+    let segments: Vec<EncodedSegment<'_>> = data
+        .chunks(ZGFX_SEGMENTED_MAXSIZE)
+        .map(|chunk| EncodedSegment {
+            data: chunk,
+            compressed: false,
+            uncompressed_size: chunk.len(),
+        })
+        .collect();
+
+    wrap_segments(&segments)
+}
 
+fn write_multipart_header(output: &mut Vec<u8>, segment_count: usize, uncompressed_size: usize) {
     // Descriptor
     output.push(ZGFX_SEGMENTED_MULTIPART);
 
     // Segment count (LE u16)
     output
-        .write_u16::<LittleEndian>(segment_count as u16)
+        .write_u16::<LittleEndian>(u16::try_from(segment_count).expect("too many ZGFX segments"))
         .expect("write to Vec cannot fail");
 
     // Total uncompressed size (LE u32)
     output
-        .write_u32::<LittleEndian>(data.len() as u32)
+        .write_u32::<LittleEndian>(u32::try_from(uncompressed_size).expect("ZGFX payload too large"))
         .expect("write to Vec cannot fail");
+}
 
-    // Each segment
-    for segment in segments {
-        // Segment size (includes flags byte)
-        output
-            .write_u32::<LittleEndian>((segment.len() + 1) as u32)
-            .expect("write to Vec cannot fail");
-
-        // Flags: RDP8 type + optional COMPRESSED flag
-        let flags = if compressed {
-            ZGFX_PACKET_COMPR_TYPE_RDP8 | (ZGFX_PACKET_COMPRESSED << 4)
-        } else {
-            ZGFX_PACKET_COMPR_TYPE_RDP8
-        };
-        output.push(flags);
-
-        // Segment data
-        output.extend_from_slice(segment);
-    }
-
+fn write_multipart_segment(output: &mut Vec<u8>, data: &[u8], compressed: bool) {
+    // Segment size (includes flags byte)
     output
+        .write_u32::<LittleEndian>(u32::try_from(data.len() + 1).expect("ZGFX segment too large"))
+        .expect("write to Vec cannot fail");
+
+    output.push(segment_flags(compressed));
+
+    // Segment data
+    output.extend_from_slice(data);
 }
 
 #[cfg(test)]
@@ -0,0 +1,137 @@
+use core::time::Duration;
+
+use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp_pdu::input::mouse::PointerFlags;
+use ironrdp_pdu::input::mouse_rel::PointerRelFlags;
+
+/// Batches input events into fast-path input PDUs.
+///
+/// The events returned by [`Database::apply()`](crate::Database::apply) are pushed as they come, and sent
+/// together once per flush interval, instead of one PDU per event. Consecutive mouse moves are coalesced into
+/// the last one, so that a fast mouse movement sends a single position per PDU, and consecutive relative
+/// moves are summed. The order of the other events is kept, e.g. a move before a click is still sent before
+/// it.
+///
+/// The time is a timestamp from an arbitrary origin passed by the caller, e.g. the time elapsed since the
+/// start of the session, or `performance.now()` on the web.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut batcher = InputBatcher::new(Duration::from_millis(8));
+///
+/// // on each input event
+/// batcher.push(database.apply(operations), now);
+///
+/// // on each tick, or at `batcher.next_flush()`
+/// while let Some(pdu) = batcher.poll(now) {
+///     send(pdu)?;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InputBatcher {
+    flush_interval: Duration,
+    events: Vec<FastPathInputEvent>,
+    /// Time of the oldest pending event
+    oldest: Option<Duration>,
+}
+
+impl InputBatcher {
+    /// Maximum number of events in a fast-path input PDU
+    pub const MAX_EVENTS: usize = 255;
+
+    /// Creates a batcher sending the pending events `flush_interval` after the oldest of them
+    ///
+    /// A zero interval sends the events on the next poll.
+    pub fn new(flush_interval: Duration) -> Self {
+        Self {
+            flush_interval,
+            events: Vec::new(),
+            oldest: None,
+        }
+    }
+
+    pub fn flush_interval(&self) -> Duration {
+        self.flush_interval
+    }
+
+    pub fn set_flush_interval(&mut self, flush_interval: Duration) {
+        self.flush_interval = flush_interval;
+    }
+
+    /// Number of events waiting to be sent
+    pub fn pending(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Queues the events, received at `now`.
+    pub fn push(&mut self, events: impl IntoIterator<Item = FastPathInputEvent>, now: Duration) {
+        for event in events {
+            match (self.events.last_mut(), &event) {
+                (Some(FastPathInputEvent::MouseEvent(last)), FastPathInputEvent::MouseEvent(mouse))
+                    if last.flags == PointerFlags::MOVE && mouse.flags == PointerFlags::MOVE =>
+                {
+                    last.x_position = mouse.x_position;
+                    last.y_position = mouse.y_position;
+                }
+                (Some(FastPathInputEvent::MouseEventRel(last)), FastPathInputEvent::MouseEventRel(mouse))
+                    if last.flags == PointerRelFlags::MOVE
+                        && mouse.flags == PointerRelFlags::MOVE
+                        && last.x_delta.checked_add(mouse.x_delta).is_some()
+                        && last.y_delta.checked_add(mouse.y_delta).is_some() =>
+                {
+                    last.x_delta = last.x_delta.saturating_add(mouse.x_delta);
+                    last.y_delta = last.y_delta.saturating_add(mouse.y_delta);
+                }
+                _ => self.events.push(event),
+            }
+        }
+
+        if !self.events.is_empty() {
+            self.oldest.get_or_insert(now);
+        }
+    }
+
+    /// Time at which the pending events are due, if any
+    pub fn next_flush(&self) -> Option<Duration> {
+        if self.events.len() >= Self::MAX_EVENTS {
+            return self.oldest;
+        }
+
+        self.oldest.map(|oldest| oldest.saturating_add(self.flush_interval))
+    }
+
+    /// Returns a PDU with the pending events if they are due at `now`.
+    ///
+    /// More than [`MAX_EVENTS`](Self::MAX_EVENTS) pending events are sent in several PDUs: call this until it
+    /// returns `None`.
+    pub fn poll(&mut self, now: Duration) -> Option<FastPathInput> {
+        if self.next_flush()? > now {
+            return None;
+        }
+
+        self.flush()
+    }
+
+    /// Returns a PDU with the pending events right away, e.g. before disconnecting.
+    ///
+    /// More than [`MAX_EVENTS`](Self::MAX_EVENTS) pending events are sent in several PDUs: call this until it
+    /// returns `None`.
+    pub fn flush(&mut self) -> Option<FastPathInput> {
+        if self.events.is_empty() {
+            return None;
+        }
+
+        let len = self.events.len().min(Self::MAX_EVENTS);
+        let events = self.events.drain(..len).collect();
+
+        if self.events.is_empty() {
+            self.oldest = None;
+        }
+
+        #[expect(clippy::missing_panics_doc, reason = "unreachable panic (1..=255 events)")]
+        let pdu = FastPathInput::new(events).expect("between 1 and 255 events");
+
+        Some(pdu)
+    }
+}
@@ -0,0 +1,177 @@
+use bitvec::array::BitArray;
+use ironrdp_pdu::input::fast_path::FastPathInputEvent;
+use smallvec::SmallVec;
+
+use crate::{Database, KeyboardState, Operation, Scancode};
+
+/// Modifier key of a [`Hotkey`], either the left or the right one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    /// Typically the Windows key
+    Logo,
+}
+
+impl Modifier {
+    const ALL: [Self; 4] = [Self::Shift, Self::Control, Self::Alt, Self::Logo];
+
+    /// Scan codes of the left and right keys.
+    pub const fn scancodes(self) -> [Scancode; 2] {
+        match self {
+            Self::Shift => [Scancode::from_u8(false, 0x2A), Scancode::from_u8(false, 0x36)],
+            Self::Control => [Scancode::from_u8(false, 0x1D), Scancode::from_u8(true, 0x1D)],
+            Self::Alt => [Scancode::from_u8(false, 0x38), Scancode::from_u8(true, 0x38)],
+            Self::Logo => [Scancode::from_u8(true, 0x5B), Scancode::from_u8(true, 0x5C)],
+        }
+    }
+
+    fn is_pressed(self, database: &Database) -> bool {
+        self.scancodes()
+            .into_iter()
+            .any(|scancode| database.is_key_pressed(scancode))
+    }
+}
+
+/// Key combination triggering a local action.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    modifiers: SmallVec<[Modifier; 4]>,
+    key: Scancode,
+}
+
+impl Hotkey {
+    /// The hotkey is triggered when `key` is pressed while holding exactly the given modifiers.
+    pub fn new(modifiers: &[Modifier], key: Scancode) -> Self {
+        let mut modifiers = SmallVec::from_slice(modifiers);
+        modifiers.sort_unstable();
+        modifiers.dedup();
+
+        Self { modifiers, key }
+    }
+
+    pub fn modifiers(&self) -> &[Modifier] {
+        &self.modifiers
+    }
+
+    pub fn key(&self) -> Scancode {
+        self.key
+    }
+
+    fn matches(&self, database: &Database, key: Scancode) -> bool {
+        self.key == key
+            && Modifier::ALL
+                .into_iter()
+                .all(|modifier| modifier.is_pressed(database) == self.modifiers.contains(&modifier))
+    }
+}
+
+/// Local hotkeys, intercepted before the input is sent to the server.
+///
+/// The key triggering a hotkey is never sent: its press, repeats and release are swallowed. The modifiers of the
+/// hotkey were already sent, they are released on the server side when the hotkey is triggered so that they don't
+/// remain stuck while the embedder handles the action (e.g. the window loses the focus when leaving full screen).
+#[derive(Debug, Clone)]
+pub struct Hotkeys<A> {
+    bindings: Vec<(Hotkey, A)>,
+    swallowed: KeyboardState,
+}
+
+impl<A> Default for Hotkeys<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> Hotkeys<A> {
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            swallowed: BitArray::ZERO,
+        }
+    }
+
+    /// Binds a hotkey to an action, returns the action previously bound to it.
+    pub fn bind(&mut self, hotkey: Hotkey, action: A) -> Option<A> {
+        match self.bindings.iter_mut().find(|(bound, _)| *bound == hotkey) {
+            Some((_, bound_action)) => Some(core::mem::replace(bound_action, action)),
+            None => {
+                self.bindings.push((hotkey, action));
+                None
+            }
+        }
+    }
+
+    /// Removes a hotkey, returns the action bound to it.
+    pub fn unbind(&mut self, hotkey: &Hotkey) -> Option<A> {
+        let idx = self.bindings.iter().position(|(bound, _)| bound == hotkey)?;
+        Some(self.bindings.remove(idx).1)
+    }
+
+    /// Apply a transaction like [`Database::apply`], intercepting the hotkeys.
+    ///
+    /// `on_action` is called with the action of each triggered hotkey.
+    pub fn apply(
+        &mut self,
+        database: &mut Database,
+        transaction: impl IntoIterator<Item = Operation>,
+        mut on_action: impl FnMut(&A),
+    ) -> SmallVec<[FastPathInputEvent; 2]> {
+        let mut events = SmallVec::new();
+
+        for operation in transaction {
+            match operation {
+                Operation::KeyPressed(scancode) => {
+                    if self.is_swallowed(scancode) {
+                        // Key repeat of a triggered hotkey.
+                        continue;
+                    }
+
+                    let Some((_, action)) = self
+                        .bindings
+                        .iter()
+                        .find(|(hotkey, _)| hotkey.matches(database, scancode))
+                    else {
+                        events.extend(database.apply(core::iter::once(operation)));
+                        continue;
+                    };
+
+                    self.swallowed.set(scancode.as_idx(), true);
+
+                    let releases: SmallVec<[Operation; 8]> = Modifier::ALL
+                        .into_iter()
+                        .flat_map(Modifier::scancodes)
+                        .filter(|modifier| database.is_key_pressed(*modifier))
+                        .map(Operation::KeyReleased)
+                        .collect();
+                    events.extend(database.apply(releases));
+
+                    on_action(action);
+                }
+                Operation::KeyReleased(scancode) => {
+                    if !self.swallowed.replace(scancode.as_idx(), false) {
+                        events.extend(database.apply(core::iter::once(operation)));
+                    }
+                }
+                operation => events.extend(database.apply(core::iter::once(operation))),
+            }
+        }
+
+        events
+    }
+
+    /// Releases all keys and buttons like [`Database::release_all`], forgetting the swallowed keys.
+    pub fn release_all(&mut self, database: &mut Database) -> SmallVec<[FastPathInputEvent; 2]> {
+        self.swallowed = BitArray::ZERO;
+        database.release_all()
+    }
+
+    fn is_swallowed(&self, scancode: Scancode) -> bool {
+        self.swallowed
+            .get(scancode.as_idx())
+            .as_deref()
+            .copied()
+            .unwrap_or(false)
+    }
+}
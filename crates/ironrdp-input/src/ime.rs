@@ -0,0 +1,76 @@
+use crate::Operation;
+
+/// Composition of an input method editor (IME), e.g. to type CJK text.
+///
+/// The input method composes text from several key presses, and commits it once the user validates it. These key
+/// presses belong to the input method: they must not be sent to the server while composing, only the committed text
+/// is, as Unicode keyboard events. RDP has no notion of composition, so the text being composed (the preedit text)
+/// is rendered by the client itself, typically over the session near the pointer or the text caret.
+#[derive(Debug, Clone, Default)]
+pub struct ImeComposition {
+    preedit: String,
+    cursor: Option<(usize, usize)>,
+}
+
+impl ImeComposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether text is being composed, the key presses are to be ignored meanwhile.
+    ///
+    /// Key releases should still be sent, the keys pressed before the composition started would be stuck otherwise.
+    pub fn is_composing(&self) -> bool {
+        !self.preedit.is_empty()
+    }
+
+    /// Text being composed, empty when not composing.
+    pub fn preedit(&self) -> &str {
+        &self.preedit
+    }
+
+    /// Byte range of the cursor or selection in the preedit text, if the input method shows it.
+    pub fn preedit_cursor(&self) -> Option<(usize, usize)> {
+        self.cursor
+    }
+
+    /// Updates the text being composed, an empty text ends the composition.
+    ///
+    /// `cursor` is a byte range in `text`, it is discarded when out of bounds or not on character boundaries.
+    pub fn set_preedit(&mut self, text: &str, cursor: Option<(usize, usize)>) {
+        text.clone_into(&mut self.preedit);
+        self.cursor =
+            cursor.filter(|(start, end)| start <= end && text.is_char_boundary(*start) && text.is_char_boundary(*end));
+    }
+
+    /// Ends the composition and returns the operations typing the committed text.
+    pub fn commit(&mut self, text: &str) -> Vec<Operation> {
+        self.set_preedit("", None);
+        unicode_text_operations(text.chars())
+    }
+
+    /// Same as [`ImeComposition::commit`] for UTF-16 text, such as the web browser strings.
+    ///
+    /// Unpaired surrogates are replaced by U+FFFD REPLACEMENT CHARACTER, the server can't combine them.
+    pub fn commit_utf16(&mut self, text: &[u16]) -> Vec<Operation> {
+        self.set_preedit("", None);
+        unicode_text_operations(
+            char::decode_utf16(text.iter().copied()).map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER)),
+        )
+    }
+}
+
+/// Returns the operations typing a text with Unicode keyboard events.
+///
+/// Each character is pressed and released, the characters outside the Basic Multilingual Plane are sent as a
+/// surrogate pair by [`Database::apply`](crate::Database::apply).
+pub fn unicode_text_operations(text: impl IntoIterator<Item = char>) -> Vec<Operation> {
+    text.into_iter()
+        .flat_map(|character| {
+            [
+                Operation::UnicodeKeyPressed(character),
+                Operation::UnicodeKeyReleased(character),
+            ]
+        })
+        .collect()
+}
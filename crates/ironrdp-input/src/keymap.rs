@@ -0,0 +1,312 @@
+//! Scan code tables
+//!
+//! Scan codes of RDP are the scan code set 1 of the PC/AT keyboards, the extended keys being
+//! prefixed with 0xE0 (`KBDFLAGS_EXTENDED`).
+
+/// `KeyboardEvent.code` values of the UI Events specification, and the scan codes of the keys
+///
+/// The first entry of a scan code is its canonical name, the legacy aliases follow.
+pub(crate) const WEB_CODES: &[(&str, bool, u8)] = &[
+    ("Escape", false, 0x01),
+    ("Digit1", false, 0x02),
+    ("Digit2", false, 0x03),
+    ("Digit3", false, 0x04),
+    ("Digit4", false, 0x05),
+    ("Digit5", false, 0x06),
+    ("Digit6", false, 0x07),
+    ("Digit7", false, 0x08),
+    ("Digit8", false, 0x09),
+    ("Digit9", false, 0x0A),
+    ("Digit0", false, 0x0B),
+    ("Minus", false, 0x0C),
+    ("Equal", false, 0x0D),
+    ("Backspace", false, 0x0E),
+    ("Tab", false, 0x0F),
+    ("KeyQ", false, 0x10),
+    ("KeyW", false, 0x11),
+    ("KeyE", false, 0x12),
+    ("KeyR", false, 0x13),
+    ("KeyT", false, 0x14),
+    ("KeyY", false, 0x15),
+    ("KeyU", false, 0x16),
+    ("KeyI", false, 0x17),
+    ("KeyO", false, 0x18),
+    ("KeyP", false, 0x19),
+    ("BracketLeft", false, 0x1A),
+    ("BracketRight", false, 0x1B),
+    ("Enter", false, 0x1C),
+    ("ControlLeft", false, 0x1D),
+    ("KeyA", false, 0x1E),
+    ("KeyS", false, 0x1F),
+    ("KeyD", false, 0x20),
+    ("KeyF", false, 0x21),
+    ("KeyG", false, 0x22),
+    ("KeyH", false, 0x23),
+    ("KeyJ", false, 0x24),
+    ("KeyK", false, 0x25),
+    ("KeyL", false, 0x26),
+    ("Semicolon", false, 0x27),
+    ("Quote", false, 0x28),
+    ("Backquote", false, 0x29),
+    ("ShiftLeft", false, 0x2A),
+    ("Backslash", false, 0x2B),
+    ("KeyZ", false, 0x2C),
+    ("KeyX", false, 0x2D),
+    ("KeyC", false, 0x2E),
+    ("KeyV", false, 0x2F),
+    ("KeyB", false, 0x30),
+    ("KeyN", false, 0x31),
+    ("KeyM", false, 0x32),
+    ("Comma", false, 0x33),
+    ("Period", false, 0x34),
+    ("Slash", false, 0x35),
+    ("ShiftRight", false, 0x36),
+    ("NumpadMultiply", false, 0x37),
+    ("AltLeft", false, 0x38),
+    ("Space", false, 0x39),
+    ("CapsLock", false, 0x3A),
+    ("F1", false, 0x3B),
+    ("F2", false, 0x3C),
+    ("F3", false, 0x3D),
+    ("F4", false, 0x3E),
+    ("F5", false, 0x3F),
+    ("F6", false, 0x40),
+    ("F7", false, 0x41),
+    ("F8", false, 0x42),
+    ("F9", false, 0x43),
+    ("F10", false, 0x44),
+    ("NumLock", false, 0x45),
+    ("ScrollLock", false, 0x46),
+    ("Numpad7", false, 0x47),
+    ("Numpad8", false, 0x48),
+    ("Numpad9", false, 0x49),
+    ("NumpadSubtract", false, 0x4A),
+    ("Numpad4", false, 0x4B),
+    ("Numpad5", false, 0x4C),
+    ("Numpad6", false, 0x4D),
+    ("NumpadAdd", false, 0x4E),
+    ("Numpad1", false, 0x4F),
+    ("Numpad2", false, 0x50),
+    ("Numpad3", false, 0x51),
+    ("Numpad0", false, 0x52),
+    ("NumpadDecimal", false, 0x53),
+    ("IntlBackslash", false, 0x56),
+    ("F11", false, 0x57),
+    ("F12", false, 0x58),
+    ("NumpadEqual", false, 0x59),
+    ("F13", false, 0x64),
+    ("F14", false, 0x65),
+    ("F15", false, 0x66),
+    ("F16", false, 0x67),
+    ("F17", false, 0x68),
+    ("F18", false, 0x69),
+    ("F19", false, 0x6A),
+    ("F20", false, 0x6B),
+    ("F21", false, 0x6C),
+    ("F22", false, 0x6D),
+    ("F23", false, 0x6E),
+    ("KanaMode", false, 0x70),
+    ("Lang2", false, 0x71),
+    ("Lang1", false, 0x72),
+    ("IntlRo", false, 0x73),
+    ("F24", false, 0x76),
+    ("Convert", false, 0x79),
+    ("NonConvert", false, 0x7B),
+    ("IntlYen", false, 0x7D),
+    ("NumpadComma", false, 0x7E),
+    ("MediaTrackPrevious", true, 0x10),
+    ("MediaTrackNext", true, 0x19),
+    ("NumpadEnter", true, 0x1C),
+    ("ControlRight", true, 0x1D),
+    ("AudioVolumeMute", true, 0x20),
+    ("LaunchApp2", true, 0x21),
+    ("MediaPlayPause", true, 0x22),
+    ("MediaStop", true, 0x24),
+    ("AudioVolumeDown", true, 0x2E),
+    ("AudioVolumeUp", true, 0x30),
+    ("BrowserHome", true, 0x32),
+    ("NumpadDivide", true, 0x35),
+    ("PrintScreen", true, 0x37),
+    ("AltRight", true, 0x38),
+    ("Home", true, 0x47),
+    ("ArrowUp", true, 0x48),
+    ("PageUp", true, 0x49),
+    ("ArrowLeft", true, 0x4B),
+    ("ArrowRight", true, 0x4D),
+    ("End", true, 0x4F),
+    ("ArrowDown", true, 0x50),
+    ("PageDown", true, 0x51),
+    ("Insert", true, 0x52),
+    ("Delete", true, 0x53),
+    ("MetaLeft", true, 0x5B),
+    ("MetaRight", true, 0x5C),
+    ("ContextMenu", true, 0x5D),
+    ("Power", true, 0x5E),
+    ("Sleep", true, 0x5F),
+    ("WakeUp", true, 0x63),
+    ("BrowserSearch", true, 0x65),
+    ("BrowserFavorites", true, 0x66),
+    ("BrowserRefresh", true, 0x67),
+    ("BrowserStop", true, 0x68),
+    ("BrowserForward", true, 0x69),
+    ("BrowserBack", true, 0x6A),
+    ("LaunchApp1", true, 0x6B),
+    ("LaunchMail", true, 0x6C),
+    ("MediaSelect", true, 0x6D),
+    // Legacy names of the Windows keys, still used by Firefox before version 118.
+    ("OSLeft", true, 0x5B),
+    ("OSRight", true, 0x5C),
+];
+
+/// Scan codes of the set 1, and of the set 2 sent by the keyboards
+///
+/// The keys are extended in both sets. Lang1 and Lang2 have no break code in the set 2.
+pub(crate) const SET1_TO_SET2: &[(bool, u8, u8)] = &[
+    (false, 0x01, 0x76),
+    (false, 0x02, 0x16),
+    (false, 0x03, 0x1E),
+    (false, 0x04, 0x26),
+    (false, 0x05, 0x25),
+    (false, 0x06, 0x2E),
+    (false, 0x07, 0x36),
+    (false, 0x08, 0x3D),
+    (false, 0x09, 0x3E),
+    (false, 0x0A, 0x46),
+    (false, 0x0B, 0x45),
+    (false, 0x0C, 0x4E),
+    (false, 0x0D, 0x55),
+    (false, 0x0E, 0x66),
+    (false, 0x0F, 0x0D),
+    (false, 0x10, 0x15),
+    (false, 0x11, 0x1D),
+    (false, 0x12, 0x24),
+    (false, 0x13, 0x2D),
+    (false, 0x14, 0x2C),
+    (false, 0x15, 0x35),
+    (false, 0x16, 0x3C),
+    (false, 0x17, 0x43),
+    (false, 0x18, 0x44),
+    (false, 0x19, 0x4D),
+    (false, 0x1A, 0x54),
+    (false, 0x1B, 0x5B),
+    (false, 0x1C, 0x5A),
+    (false, 0x1D, 0x14),
+    (false, 0x1E, 0x1C),
+    (false, 0x1F, 0x1B),
+    (false, 0x20, 0x23),
+    (false, 0x21, 0x2B),
+    (false, 0x22, 0x34),
+    (false, 0x23, 0x33),
+    (false, 0x24, 0x3B),
+    (false, 0x25, 0x42),
+    (false, 0x26, 0x4B),
+    (false, 0x27, 0x4C),
+    (false, 0x28, 0x52),
+    (false, 0x29, 0x0E),
+    (false, 0x2A, 0x12),
+    (false, 0x2B, 0x5D),
+    (false, 0x2C, 0x1A),
+    (false, 0x2D, 0x22),
+    (false, 0x2E, 0x21),
+    (false, 0x2F, 0x2A),
+    (false, 0x30, 0x32),
+    (false, 0x31, 0x31),
+    (false, 0x32, 0x3A),
+    (false, 0x33, 0x41),
+    (false, 0x34, 0x49),
+    (false, 0x35, 0x4A),
+    (false, 0x36, 0x59),
+    (false, 0x37, 0x7C),
+    (false, 0x38, 0x11),
+    (false, 0x39, 0x29),
+    (false, 0x3A, 0x58),
+    (false, 0x3B, 0x05),
+    (false, 0x3C, 0x06),
+    (false, 0x3D, 0x04),
+    (false, 0x3E, 0x0C),
+    (false, 0x3F, 0x03),
+    (false, 0x40, 0x0B),
+    (false, 0x41, 0x83),
+    (false, 0x42, 0x0A),
+    (false, 0x43, 0x01),
+    (false, 0x44, 0x09),
+    (false, 0x45, 0x77),
+    (false, 0x46, 0x7E),
+    (false, 0x47, 0x6C),
+    (false, 0x48, 0x75),
+    (false, 0x49, 0x7D),
+    (false, 0x4A, 0x7B),
+    (false, 0x4B, 0x6B),
+    (false, 0x4C, 0x73),
+    (false, 0x4D, 0x74),
+    (false, 0x4E, 0x79),
+    (false, 0x4F, 0x69),
+    (false, 0x50, 0x72),
+    (false, 0x51, 0x7A),
+    (false, 0x52, 0x70),
+    (false, 0x53, 0x71),
+    (false, 0x54, 0x84),
+    (false, 0x56, 0x61),
+    (false, 0x57, 0x78),
+    (false, 0x58, 0x07),
+    (false, 0x59, 0x0F),
+    (false, 0x64, 0x08),
+    (false, 0x65, 0x10),
+    (false, 0x66, 0x18),
+    (false, 0x67, 0x20),
+    (false, 0x68, 0x28),
+    (false, 0x69, 0x30),
+    (false, 0x6A, 0x38),
+    (false, 0x6B, 0x40),
+    (false, 0x6C, 0x48),
+    (false, 0x6D, 0x50),
+    (false, 0x6E, 0x57),
+    (false, 0x70, 0x13),
+    (false, 0x71, 0xF1),
+    (false, 0x72, 0xF2),
+    (false, 0x73, 0x51),
+    (false, 0x76, 0x5F),
+    (false, 0x79, 0x64),
+    (false, 0x7B, 0x67),
+    (false, 0x7D, 0x6A),
+    (false, 0x7E, 0x6D),
+    (true, 0x10, 0x15),
+    (true, 0x19, 0x4D),
+    (true, 0x1C, 0x5A),
+    (true, 0x1D, 0x14),
+    (true, 0x20, 0x23),
+    (true, 0x21, 0x2B),
+    (true, 0x22, 0x34),
+    (true, 0x24, 0x3B),
+    (true, 0x2E, 0x21),
+    (true, 0x30, 0x32),
+    (true, 0x32, 0x3A),
+    (true, 0x35, 0x4A),
+    (true, 0x37, 0x7C),
+    (true, 0x38, 0x11),
+    (true, 0x47, 0x6C),
+    (true, 0x48, 0x75),
+    (true, 0x49, 0x7D),
+    (true, 0x4B, 0x6B),
+    (true, 0x4D, 0x74),
+    (true, 0x4F, 0x69),
+    (true, 0x50, 0x72),
+    (true, 0x51, 0x7A),
+    (true, 0x52, 0x70),
+    (true, 0x53, 0x71),
+    (true, 0x5B, 0x1F),
+    (true, 0x5C, 0x27),
+    (true, 0x5D, 0x2F),
+    (true, 0x5E, 0x37),
+    (true, 0x5F, 0x3F),
+    (true, 0x63, 0x5E),
+    (true, 0x65, 0x10),
+    (true, 0x66, 0x18),
+    (true, 0x67, 0x20),
+    (true, 0x68, 0x28),
+    (true, 0x69, 0x30),
+    (true, 0x6A, 0x38),
+    (true, 0x6B, 0x40),
+    (true, 0x6C, 0x48),
+    (true, 0x6D, 0x50),
+];
@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+
+use crate::{Operation, Scancode};
+
+const SHIFT_LEFT: Scancode = Scancode::from_u8(false, 0x2A);
+const CONTROL_LEFT: Scancode = Scancode::from_u8(false, 0x1D);
+const ALT_RIGHT: Scancode = Scancode::from_u8(true, 0x38);
+
+/// Characters typed by the keys of the US layout, alone and with Shift (`'\0'` when the key types nothing).
+const US_KEYS: &[(u8, char, char)] = &[
+    (0x29, '`', '~'),
+    (0x02, '1', '!'),
+    (0x03, '2', '@'),
+    (0x04, '3', '#'),
+    (0x05, '4', '$'),
+    (0x06, '5', '%'),
+    (0x07, '6', '^'),
+    (0x08, '7', '&'),
+    (0x09, '8', '*'),
+    (0x0A, '9', '('),
+    (0x0B, '0', ')'),
+    (0x0C, '-', '_'),
+    (0x0D, '=', '+'),
+    (0x10, 'q', 'Q'),
+    (0x11, 'w', 'W'),
+    (0x12, 'e', 'E'),
+    (0x13, 'r', 'R'),
+    (0x14, 't', 'T'),
+    (0x15, 'y', 'Y'),
+    (0x16, 'u', 'U'),
+    (0x17, 'i', 'I'),
+    (0x18, 'o', 'O'),
+    (0x19, 'p', 'P'),
+    (0x1A, '[', '{'),
+    (0x1B, ']', '}'),
+    (0x2B, '\\', '|'),
+    (0x1E, 'a', 'A'),
+    (0x1F, 's', 'S'),
+    (0x20, 'd', 'D'),
+    (0x21, 'f', 'F'),
+    (0x22, 'g', 'G'),
+    (0x23, 'h', 'H'),
+    (0x24, 'j', 'J'),
+    (0x25, 'k', 'K'),
+    (0x26, 'l', 'L'),
+    (0x27, ';', ':'),
+    (0x28, '\'', '"'),
+    (0x2C, 'z', 'Z'),
+    (0x2D, 'x', 'X'),
+    (0x2E, 'c', 'C'),
+    (0x2F, 'v', 'V'),
+    (0x30, 'b', 'B'),
+    (0x31, 'n', 'N'),
+    (0x32, 'm', 'M'),
+    (0x33, ',', '<'),
+    (0x34, '.', '>'),
+    (0x35, '/', '?'),
+    (0x39, ' ', '\0'),
+    (0x0F, '\t', '\0'),
+    (0x1C, '\n', '\0'),
+];
+
+/// Characters typed by the keys of the US-International layout with AltGr, alone and with Shift.
+const US_INTERNATIONAL_ALTGR_KEYS: &[(u8, char, char)] = &[
+    (0x02, '\u{A1}', '\u{B9}'), // ¡ ¹
+    (0x03, '\u{B2}', '\0'),     // ²
+    (0x04, '\u{B3}', '\0'),     // ³
+    (0x05, '\u{A4}', '\u{A3}'), // ¤ £
+    (0x06, '\u{20AC}', '\0'),   // €
+    (0x07, '\u{BC}', '\0'),     // ¼
+    (0x08, '\u{BD}', '\0'),     // ½
+    (0x09, '\u{BE}', '\0'),     // ¾
+    (0x0A, '\u{2018}', '\0'),   // ‘
+    (0x0B, '\u{2019}', '\0'),   // ’
+    (0x0C, '\u{A5}', '\0'),     // ¥
+    (0x0D, '\u{D7}', '\u{F7}'), // × ÷
+    (0x10, '\u{E4}', '\u{C4}'), // ä Ä
+    (0x11, '\u{E5}', '\u{C5}'), // å Å
+    (0x12, '\u{E9}', '\u{C9}'), // é É
+    (0x13, '\u{AE}', '\0'),     // ®
+    (0x14, '\u{FE}', '\u{DE}'), // þ Þ
+    (0x15, '\u{FC}', '\u{DC}'), // ü Ü
+    (0x16, '\u{FA}', '\u{DA}'), // ú Ú
+    (0x17, '\u{ED}', '\u{CD}'), // í Í
+    (0x18, '\u{F3}', '\u{D3}'), // ó Ó
+    (0x19, '\u{F6}', '\u{D6}'), // ö Ö
+    (0x1A, '\u{AB}', '\0'),     // «
+    (0x1B, '\u{BB}', '\0'),     // »
+    (0x2B, '\u{AC}', '\u{A6}'), // ¬ ¦
+    (0x1E, '\u{E1}', '\u{C1}'), // á Á
+    (0x1F, '\u{DF}', '\u{A7}'), // ß §
+    (0x20, '\u{F0}', '\u{D0}'), // ð Ð
+    (0x26, '\u{F8}', '\u{D8}'), // ø Ø
+    (0x27, '\u{B6}', '\u{B0}'), // ¶ °
+    (0x28, '\u{B4}', '\u{A8}'), // ´ ¨
+    (0x2C, '\u{E6}', '\u{C6}'), // æ Æ
+    (0x2E, '\u{A9}', '\u{A2}'), // © ¢
+    (0x31, '\u{F1}', '\u{D1}'), // ñ Ñ
+    (0x32, '\u{B5}', '\0'),     // µ
+    (0x33, '\u{E7}', '\u{C7}'), // ç Ç
+    (0x35, '\u{BF}', '\0'),     // ¿
+];
+
+/// Dead key, composing the next typed character with an accent
+struct DeadKey {
+    scancode: u8,
+    shift: bool,
+    /// Typed when the dead key is followed by a space
+    accent: char,
+    compositions: &'static [(char, char)],
+}
+
+const US_INTERNATIONAL_DEAD_KEYS: &[DeadKey] = &[
+    DeadKey {
+        scancode: 0x28,
+        shift: false,
+        accent: '\'',
+        compositions: &[
+            ('a', '\u{E1}'),
+            ('e', '\u{E9}'),
+            ('i', '\u{ED}'),
+            ('o', '\u{F3}'),
+            ('u', '\u{FA}'),
+            ('y', '\u{FD}'),
+            ('c', '\u{E7}'),
+            ('A', '\u{C1}'),
+            ('E', '\u{C9}'),
+            ('I', '\u{CD}'),
+            ('O', '\u{D3}'),
+            ('U', '\u{DA}'),
+            ('Y', '\u{DD}'),
+            ('C', '\u{C7}'),
+        ],
+    },
+    DeadKey {
+        scancode: 0x28,
+        shift: true,
+        accent: '"',
+        compositions: &[
+            ('a', '\u{E4}'),
+            ('e', '\u{EB}'),
+            ('i', '\u{EF}'),
+            ('o', '\u{F6}'),
+            ('u', '\u{FC}'),
+            ('y', '\u{FF}'),
+            ('A', '\u{C4}'),
+            ('E', '\u{CB}'),
+            ('I', '\u{CF}'),
+            ('O', '\u{D6}'),
+            ('U', '\u{DC}'),
+        ],
+    },
+    DeadKey {
+        scancode: 0x29,
+        shift: false,
+        accent: '`',
+        compositions: &[
+            ('a', '\u{E0}'),
+            ('e', '\u{E8}'),
+            ('i', '\u{EC}'),
+            ('o', '\u{F2}'),
+            ('u', '\u{F9}'),
+            ('A', '\u{C0}'),
+            ('E', '\u{C8}'),
+            ('I', '\u{CC}'),
+            ('O', '\u{D2}'),
+            ('U', '\u{D9}'),
+        ],
+    },
+    DeadKey {
+        scancode: 0x29,
+        shift: true,
+        accent: '~',
+        compositions: &[
+            ('a', '\u{E3}'),
+            ('o', '\u{F5}'),
+            ('n', '\u{F1}'),
+            ('A', '\u{C3}'),
+            ('O', '\u{D5}'),
+            ('N', '\u{D1}'),
+        ],
+    },
+    DeadKey {
+        scancode: 0x07,
+        shift: true,
+        accent: '^',
+        compositions: &[
+            ('a', '\u{E2}'),
+            ('e', '\u{EA}'),
+            ('i', '\u{EE}'),
+            ('o', '\u{F4}'),
+            ('u', '\u{FB}'),
+            ('A', '\u{C2}'),
+            ('E', '\u{CA}'),
+            ('I', '\u{CE}'),
+            ('O', '\u{D4}'),
+            ('U', '\u{DB}'),
+        ],
+    },
+];
+
+/// Key pressed along with the Shift and AltGr modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub scancode: Scancode,
+    pub shift: bool,
+    /// AltGr is sent as the left Control key held with the right Alt key, which is how Windows represents it.
+    pub altgr: bool,
+}
+
+impl KeyPress {
+    fn push_operations(self, operations: &mut Vec<Operation>) {
+        let modifiers = [
+            (self.shift, SHIFT_LEFT),
+            (self.altgr, CONTROL_LEFT),
+            (self.altgr, ALT_RIGHT),
+        ]
+        .into_iter()
+        .filter_map(|(pressed, scancode)| pressed.then_some(scancode));
+
+        operations.extend(modifiers.clone().map(Operation::KeyPressed));
+        operations.push(Operation::KeyPressed(self.scancode));
+        operations.push(Operation::KeyReleased(self.scancode));
+        operations.extend(modifiers.rev().map(Operation::KeyReleased));
+    }
+}
+
+/// Keys typing a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Keystroke {
+    /// Dead key to press and release first, the character is composed from the two keys
+    pub dead_key: Option<KeyPress>,
+    pub key: KeyPress,
+}
+
+/// Keyboard layout of the server, mapping characters to the keys typing them.
+///
+/// This is useful to type text on servers or screens not supporting Unicode keyboard events, e.g. when
+/// pasting text as keystrokes.
+#[derive(Debug, Clone)]
+pub struct KeyboardLayout {
+    id: u32,
+    keystrokes: BTreeMap<char, Keystroke>,
+}
+
+impl KeyboardLayout {
+    /// Keyboard layout identifier of the US layout.
+    pub const US: u32 = 0x0000_0409;
+    /// Keyboard layout identifier of the US-International layout.
+    pub const US_INTERNATIONAL: u32 = 0x0002_0409;
+
+    pub fn us() -> Self {
+        Self::new(Self::US, &[], &[])
+    }
+
+    /// US layout with AltGr characters, and dead keys for the apostrophe, quote, backtick, tilde and caret.
+    pub fn us_international() -> Self {
+        Self::new(
+            Self::US_INTERNATIONAL,
+            US_INTERNATIONAL_ALTGR_KEYS,
+            US_INTERNATIONAL_DEAD_KEYS,
+        )
+    }
+
+    /// Returns the layout of a keyboard layout identifier, if known.
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            Self::US => Some(Self::us()),
+            Self::US_INTERNATIONAL => Some(Self::us_international()),
+            _ => None,
+        }
+    }
+
+    /// Keyboard layout identifier, as sent in the client core data.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the keys typing a character, if the layout has them.
+    ///
+    /// Characters typed without a dead key are preferred.
+    pub fn keystroke(&self, character: char) -> Option<Keystroke> {
+        self.keystrokes.get(&character).copied()
+    }
+
+    /// Returns the operations typing a text, characters missing from the layout are sent as Unicode key events.
+    ///
+    /// The keys are pressed and released one after the other, so no key should be held when applying the
+    /// operations. Carriage returns are skipped and line feeds press the Enter key.
+    pub fn type_text(&self, text: &str) -> Vec<Operation> {
+        let mut operations = Vec::new();
+
+        for character in text.chars().filter(|character| *character != '\r') {
+            match self.keystroke(character) {
+                Some(keystroke) => {
+                    if let Some(dead_key) = keystroke.dead_key {
+                        dead_key.push_operations(&mut operations);
+                    }
+
+                    keystroke.key.push_operations(&mut operations);
+                }
+                None => {
+                    operations.push(Operation::UnicodeKeyPressed(character));
+                    operations.push(Operation::UnicodeKeyReleased(character));
+                }
+            }
+        }
+
+        operations
+    }
+
+    fn new(id: u32, altgr_keys: &[(u8, char, char)], dead_keys: &[DeadKey]) -> Self {
+        let is_dead_key = |key: &KeyPress| {
+            dead_keys.iter().any(|dead_key| {
+                key.scancode == Scancode::from_u8(false, dead_key.scancode) && key.shift == dead_key.shift
+            })
+        };
+
+        let layers = [(US_KEYS, false), (altgr_keys, true)];
+
+        let mut keys = BTreeMap::new();
+
+        for (layer, altgr) in layers {
+            for &(code, character, shifted_character) in layer {
+                for (character, shift) in [(character, false), (shifted_character, true)] {
+                    let key = KeyPress {
+                        scancode: Scancode::from_u8(false, code),
+                        shift,
+                        altgr,
+                    };
+
+                    if character != '\0' && !is_dead_key(&key) {
+                        keys.entry(character).or_insert(key);
+                    }
+                }
+            }
+        }
+
+        let mut keystrokes = BTreeMap::new();
+
+        for dead_key in dead_keys {
+            let dead_key_press = KeyPress {
+                scancode: Scancode::from_u8(false, dead_key.scancode),
+                shift: dead_key.shift,
+                altgr: false,
+            };
+
+            for (base, composed) in
+                core::iter::once((' ', dead_key.accent)).chain(dead_key.compositions.iter().copied())
+            {
+                if let Some(key) = keys.get(&base) {
+                    keystrokes.entry(composed).or_insert(Keystroke {
+                        dead_key: Some(dead_key_press),
+                        key: *key,
+                    });
+                }
+            }
+        }
+
+        keystrokes.extend(
+            keys.into_iter()
+                .map(|(character, key)| (character, Keystroke { dead_key: None, key })),
+        );
+
+        Self { id, keystrokes }
+    }
+}
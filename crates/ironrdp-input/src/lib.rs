@@ -7,10 +7,22 @@ use bitvec::array::BitArray;
 use bitvec::BitArr;
 use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags};
 use ironrdp_pdu::input::mouse::PointerFlags;
+use ironrdp_pdu::input::mouse_rel::PointerRelFlags;
 use ironrdp_pdu::input::mouse_x::PointerXFlags;
-use ironrdp_pdu::input::{MousePdu, MouseXPdu};
+use ironrdp_pdu::input::{MousePdu, MouseRelPdu, MouseXPdu};
 use smallvec::SmallVec;
 
+mod batch;
+mod hotkey;
+mod ime;
+mod keymap;
+mod layout;
+
+pub use self::batch::InputBatcher;
+pub use self::hotkey::{Hotkey, Hotkeys, Modifier};
+pub use self::ime::{unicode_text_operations, ImeComposition};
+pub use self::layout::{KeyPress, KeyboardLayout, Keystroke};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum MouseButton {
@@ -112,6 +124,44 @@ impl Scancode {
             u16::from(self.code)
         }
     }
+
+    /// Scan code of the key identified by a `KeyboardEvent.code` value of the web browsers.
+    ///
+    /// The winit `KeyCode` variants are named after these values, except the Windows keys which winit calls
+    /// `SuperLeft` and `SuperRight`. The Pause key is not mapped, it has no scan code in the set 1.
+    pub fn from_web_code(code: &str) -> Option<Self> {
+        // https://developer.mozilla.org/en-US/docs/Web/API/UI_Events/Keyboard_event_code_values
+        keymap::WEB_CODES
+            .iter()
+            .find(|(name, _, _)| *name == code)
+            .map(|(_, extended, code)| Self::from_u8(*extended, *code))
+    }
+
+    /// `KeyboardEvent.code` value of the key, the reverse of [`Scancode::from_web_code`].
+    pub fn web_code(self) -> Option<&'static str> {
+        keymap::WEB_CODES
+            .iter()
+            .find(|(_, extended, code)| *extended == self.extended && *code == self.code)
+            .map(|(name, _, _)| *name)
+    }
+
+    /// Converts a make code of the scan code set 2, as sent by PS/2 keyboards.
+    ///
+    /// `extended` is whether the code is prefixed with 0xE0.
+    pub fn from_set2(extended: bool, code: u8) -> Option<Self> {
+        keymap::SET1_TO_SET2
+            .iter()
+            .find(|(set2_extended, _, set2_code)| *set2_extended == extended && *set2_code == code)
+            .map(|(_, set1_code, _)| Self::from_u8(extended, *set1_code))
+    }
+
+    /// Make code of the key in the scan code set 2, along with whether it is prefixed with 0xE0.
+    pub fn to_set2(self) -> Option<(bool, u8)> {
+        keymap::SET1_TO_SET2
+            .iter()
+            .find(|(extended, code, _)| *extended == self.extended && *code == self.code)
+            .map(|(extended, _, set2_code)| (*extended, *set2_code))
+    }
 }
 
 impl From<(bool, u8)> for Scancode {
@@ -133,6 +183,13 @@ pub struct MousePosition {
     pub y: u16,
 }
 
+/// Relative mouse movement, in device units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MouseDelta {
+    pub x: i16,
+    pub y: i16,
+}
+
 /// Mouse wheel rotations.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct WheelRotations {
@@ -140,11 +197,15 @@ pub struct WheelRotations {
     pub rotation_units: i16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Operation {
     MouseButtonPressed(MouseButton),
     MouseButtonReleased(MouseButton),
     MouseMove(MousePosition),
+    /// Relative mouse movement, e.g. while the mouse is captured.
+    ///
+    /// Only sent to servers accepting relative mouse events (`INPUT_FLAG_MOUSE_RELATIVE`).
+    MouseRelMove(MouseDelta),
     WheelRotations(WheelRotations),
     KeyPressed(Scancode),
     KeyReleased(Scancode),
@@ -161,6 +222,7 @@ pub struct Database {
     keyboard: KeyboardState,
     mouse_buttons: MouseButtonsState,
     mouse_position: MousePosition,
+    relative_mouse: bool,
 }
 
 impl Default for Database {
@@ -176,9 +238,23 @@ impl Database {
             mouse_buttons: BitArray::ZERO,
             mouse_position: MousePosition { x: 0, y: 0 },
             unicode_keyboard_state: BTreeSet::new(),
+            relative_mouse: false,
         }
     }
 
+    /// Whether the mouse buttons are sent as relative mouse events.
+    pub fn is_relative_mouse(&self) -> bool {
+        self.relative_mouse
+    }
+
+    /// Sends the mouse buttons as relative mouse events, typically while the mouse is captured.
+    ///
+    /// Relative events don't carry a position, so that pressing a button doesn't move the pointer of the
+    /// server back to the last absolute position.
+    pub fn set_relative_mouse(&mut self, relative_mouse: bool) {
+        self.relative_mouse = relative_mouse;
+    }
+
     pub fn is_unicode_key_pressed(&self, character: char) -> bool {
         self.unicode_keyboard_state.contains(&character)
     }
@@ -223,42 +299,14 @@ impl Database {
                     let was_pressed = self.mouse_buttons.replace(button.as_idx(), true);
 
                     if !was_pressed {
-                        let event = match MouseButtonFlags::from(button) {
-                            MouseButtonFlags::Button(flags) => FastPathInputEvent::MouseEvent(MousePdu {
-                                flags: PointerFlags::DOWN | flags,
-                                number_of_wheel_rotation_units: 0,
-                                x_position: self.mouse_position.x,
-                                y_position: self.mouse_position.y,
-                            }),
-                            MouseButtonFlags::Pointer(flags) => FastPathInputEvent::MouseEventEx(MouseXPdu {
-                                flags: PointerXFlags::DOWN | flags,
-                                x_position: self.mouse_position.x,
-                                y_position: self.mouse_position.y,
-                            }),
-                        };
-
-                        events.push(event)
+                        events.push(self.mouse_button_event(button, true))
                     }
                 }
                 Operation::MouseButtonReleased(button) => {
                     let was_pressed = self.mouse_buttons.replace(button.as_idx(), false);
 
                     if was_pressed {
-                        let event = match MouseButtonFlags::from(button) {
-                            MouseButtonFlags::Button(flags) => FastPathInputEvent::MouseEvent(MousePdu {
-                                flags,
-                                number_of_wheel_rotation_units: 0,
-                                x_position: self.mouse_position.x,
-                                y_position: self.mouse_position.y,
-                            }),
-                            MouseButtonFlags::Pointer(flags) => FastPathInputEvent::MouseEventEx(MouseXPdu {
-                                flags,
-                                x_position: self.mouse_position.x,
-                                y_position: self.mouse_position.y,
-                            }),
-                        };
-
-                        events.push(event)
+                        events.push(self.mouse_button_event(button, false))
                     }
                 }
                 Operation::MouseMove(position) => {
@@ -272,6 +320,15 @@ impl Database {
                         }))
                     }
                 }
+                Operation::MouseRelMove(delta) => {
+                    if delta.x != 0 || delta.y != 0 {
+                        events.push(FastPathInputEvent::MouseEventRel(MouseRelPdu {
+                            flags: PointerRelFlags::MOVE,
+                            x_delta: delta.x,
+                            y_delta: delta.y,
+                        }))
+                    }
+                }
                 Operation::WheelRotations(rotations) => events.push(FastPathInputEvent::MouseEvent(MousePdu {
                     flags: if rotations.is_vertical {
                         PointerFlags::VERTICAL_WHEEL
@@ -355,21 +412,7 @@ impl Database {
             #[expect(clippy::missing_panics_doc, reason = "unreachable panic (checked integer downcast)")]
             let button = MouseButton::from_idx(idx).expect("in-range index");
 
-            let event = match MouseButtonFlags::from(button) {
-                MouseButtonFlags::Button(flags) => FastPathInputEvent::MouseEvent(MousePdu {
-                    flags,
-                    number_of_wheel_rotation_units: 0,
-                    x_position: self.mouse_position.x,
-                    y_position: self.mouse_position.y,
-                }),
-                MouseButtonFlags::Pointer(flags) => FastPathInputEvent::MouseEventEx(MouseXPdu {
-                    flags,
-                    x_position: self.mouse_position.x,
-                    y_position: self.mouse_position.y,
-                }),
-            };
-
-            events.push(event)
+            events.push(self.mouse_button_event(button, false))
         }
 
         // The keyboard bit array size is 512.
@@ -407,6 +450,54 @@ impl Database {
 
         events
     }
+
+    fn mouse_button_event(&self, button: MouseButton, pressed: bool) -> FastPathInputEvent {
+        if self.relative_mouse {
+            let mut flags = match button {
+                MouseButton::Left => PointerRelFlags::BUTTON1,
+                MouseButton::Right => PointerRelFlags::BUTTON2,
+                MouseButton::Middle => PointerRelFlags::BUTTON3,
+                MouseButton::X1 => PointerRelFlags::XBUTTON1,
+                MouseButton::X2 => PointerRelFlags::XBUTTON2,
+            };
+
+            if pressed {
+                flags |= PointerRelFlags::DOWN;
+            }
+
+            return FastPathInputEvent::MouseEventRel(MouseRelPdu {
+                flags,
+                x_delta: 0,
+                y_delta: 0,
+            });
+        }
+
+        match MouseButtonFlags::from(button) {
+            MouseButtonFlags::Button(mut flags) => {
+                if pressed {
+                    flags |= PointerFlags::DOWN;
+                }
+
+                FastPathInputEvent::MouseEvent(MousePdu {
+                    flags,
+                    number_of_wheel_rotation_units: 0,
+                    x_position: self.mouse_position.x,
+                    y_position: self.mouse_position.y,
+                })
+            }
+            MouseButtonFlags::Pointer(mut flags) => {
+                if pressed {
+                    flags |= PointerXFlags::DOWN;
+                }
+
+                FastPathInputEvent::MouseEventEx(MouseXPdu {
+                    flags,
+                    x_position: self.mouse_position.x,
+                    y_position: self.mouse_position.y,
+                })
+            }
+        }
+    }
 }
 
 /// Returns the RDP input event to send in order to synchronize lock keys.
@@ -6,6 +6,7 @@ mod macros;
 
 mod proto;
 
+use core::convert::Infallible;
 use core::fmt;
 use core::fmt::Display;
 use core::pin::Pin;
@@ -17,13 +18,14 @@ use base64::engine::general_purpose::STANDARD;
 use base64::Engine as _;
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{FutureExt as _, SinkExt as _, StreamExt as _};
-use hyper::body::Bytes;
+use http_body_util::{BodyExt as _, Empty, StreamBody};
+use hyper::body::{Body, Bytes, Frame};
 use ironrdp_core::{Decode as _, Encode, ReadCursor, WriteCursor};
 use ironrdp_tls::TlsStream;
-use log::{error, warn};
+use log::{error, info, warn};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 use tokio_tungstenite::tungstenite::handshake::client::generate_key;
 use tokio_tungstenite::tungstenite::protocol::Role;
 use tokio_tungstenite::tungstenite::{http, Message};
@@ -42,6 +44,20 @@ pub struct GwConnectTarget {
     pub gw_pass: String,
 
     pub server: String,
+    pub transport: GwTransport,
+}
+
+/// Transport of the tunnel between the client and the gateway
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GwTransport {
+    /// WebSocket, falling back to the HTTP transport when the gateway refuses the WebSocket upgrade
+    #[default]
+    Auto,
+    /// A single WebSocket connection
+    WebSocket,
+    /// Two long-lived HTTP requests: `RDG_OUT_DATA`, whose response carries the data sent by the gateway, and
+    /// `RDG_IN_DATA`, whose chunked body carries the data sent by the client
+    Http,
 }
 
 type Error = ironrdp_error::Error<GwErrorKind>;
@@ -93,13 +109,26 @@ impl core::error::Error for GwErrorKind {}
 struct GwConn {
     client_name: String,
     target: GwConnectTarget,
-    ws_sink: SplitSink<WebSocketStream<TlsStream<TcpStream>>, Message>,
-    ws_stream: SplitStream<WebSocketStream<TlsStream<TcpStream>>>,
+    channel: GwChannel,
+}
+
+/// Channel carrying the packets between the client and the gateway
+enum GwChannel {
+    WebSocket {
+        sink: SplitSink<WebSocketStream<TlsStream<TcpStream>>, Message>,
+        stream: SplitStream<WebSocketStream<TlsStream<TcpStream>>>,
+    },
+    Http {
+        /// Packets sent in the body of the IN channel request
+        in_tx: mpsc::Sender<Bytes>,
+        /// Packets received in the body of the OUT channel response
+        out_rx: mpsc::Receiver<Result<Bytes, Error>>,
+    },
 }
 
 pub struct GwClient {
     work: tokio::task::JoinHandle<Result<(), Error>>,
-    rx: tokio::sync::mpsc::Receiver<Bytes>,
+    rx: mpsc::Receiver<Bytes>,
     rx_bufs: Vec<Bytes>,
     tx: PollSender<Bytes>,
 }
@@ -115,35 +144,34 @@ impl GwClient {
         target: &GwConnectTarget,
         client_name: &str,
     ) -> Result<(GwClient, core::net::SocketAddr), Error> {
-        let gw_host = target
-            .gw_endpoint
-            .split(":")
-            .nth(0)
-            .ok_or_else(|| Error::new("Connect", GwErrorKind::InvalidGwTarget))?;
+        match target.transport {
+            GwTransport::Auto => match Self::connect_websocket(target, client_name).await? {
+                Some(connected) => Ok(connected),
+                None => {
+                    info!("WebSocket upgrade refused by the gateway, falling back to the HTTP transport");
+                    Self::connect_http(target, client_name).await
+                }
+            },
+            GwTransport::WebSocket => Self::connect_websocket(target, client_name)
+                .await?
+                .ok_or_else(|| Error::new("WS Upgrade", GwErrorKind::Connect)),
+            GwTransport::Http => Self::connect_http(target, client_name).await,
+        }
+    }
 
-        let stream = TcpStream::connect(&target.gw_endpoint)
-            .await
-            .map_err(|e| custom_err!("TCP connect", e))?;
-        let client_addr = stream
-            .local_addr()
-            .map_err(|e| custom_err!("get socket local address", e))?;
+    /// Returns `None` when the gateway refuses the WebSocket upgrade
+    async fn connect_websocket(
+        target: &GwConnectTarget,
+        client_name: &str,
+    ) -> Result<Option<(GwClient, core::net::SocketAddr)>, Error> {
+        let (stream, client_addr, gw_host) = connect_tls(target).await?;
 
-        let (stream, _) = ironrdp_tls::upgrade(stream, gw_host)
-            .await
-            .map_err(|e| custom_err!("TLS connect", e))?;
-
-        let auth_val: String = STANDARD.encode(format!("{}:{}", target.gw_user, target.gw_pass));
-        let req = http::Request::builder()
-            .method("RDG_OUT_DATA")
-            .header(hyper::header::HOST, gw_host)
-            .header("Rdg-Connection-Id", format!("{{{}}}", uuid::Uuid::new_v4()))
-            .uri("/remoteDesktopGateway/")
-            .header(hyper::header::AUTHORIZATION, format!("Basic {auth_val}"))
+        let req = gw_request("RDG_OUT_DATA", target, gw_host, &connection_id())
             .header(hyper::header::CONNECTION, "Upgrade")
             .header(hyper::header::UPGRADE, "websocket")
             .header(hyper::header::SEC_WEBSOCKET_VERSION, "13")
             .header(hyper::header::SEC_WEBSOCKET_KEY, generate_key())
-            .body(http_body_util::Empty::<Bytes>::new())
+            .body(Empty::<Bytes>::new())
             .map_err(|e| custom_err!("failed to build request", e))?;
 
         let stream = hyper_util::rt::tokio::TokioIo::new(stream);
@@ -164,30 +192,59 @@ impl GwClient {
             .await
             .map_err(|e| custom_err!("WS Upgrade Send error", e))?;
 
-        if resp.status() != http::StatusCode::SWITCHING_PROTOCOLS {
-            return Err(Error::new("WS Upgrade", GwErrorKind::Connect));
+        match resp.status() {
+            http::StatusCode::SWITCHING_PROTOCOLS => {}
+            http::StatusCode::UNAUTHORIZED | http::StatusCode::FORBIDDEN => {
+                return Err(Error::new("WS Upgrade", GwErrorKind::Connect));
+            }
+            status => {
+                warn!("WebSocket upgrade refused: {status}");
+                return Ok(None);
+            }
         }
 
         let _ = tx.send(()); // TODO: Not needed since it doesnt keep alive conn?
         let stream = jh.await.map_err(|e| custom_err!("WS join", e))?.io.into_inner();
 
-        Self::connect_ws(target.clone(), client_name, stream)
+        let ws_stream: WebSocketStream<_> = WebSocketStream::from_raw_socket(stream, Role::Client, None).await;
+        let (sink, stream) = ws_stream.split();
+
+        Self::connect_channel(target.clone(), client_name, GwChannel::WebSocket { sink, stream })
+            .await
+            .map(|x| Some((x, client_addr)))
+    }
+
+    async fn connect_http(
+        target: &GwConnectTarget,
+        client_name: &str,
+    ) -> Result<(GwClient, core::net::SocketAddr), Error> {
+        let connection_id = connection_id();
+
+        // The OUT channel is established first, as the gateway associates the IN channel with it.
+        let (out_stream, client_addr, gw_host) = connect_tls(target).await?;
+        let out_req = gw_request("RDG_OUT_DATA", target, gw_host, &connection_id)
+            .body(Empty::<Bytes>::new())
+            .map_err(|e| custom_err!("failed to build request", e))?;
+
+        let (in_stream, _, _) = connect_tls(target).await?;
+        let in_req = gw_request("RDG_IN_DATA", target, gw_host, &connection_id);
+
+        let channel = GwChannel::http(out_stream, out_req, in_stream, in_req).await?;
+
+        Self::connect_channel(target.clone(), client_name, channel)
             .await
             .map(|x| (x, client_addr))
     }
 
-    async fn connect_ws(
+    async fn connect_channel(
         target: GwConnectTarget,
         client_name: &str,
-        tls_stream: TlsStream<TcpStream>,
+        channel: GwChannel,
     ) -> Result<GwClient, Error> {
-        let ws_stream: WebSocketStream<_> = WebSocketStream::from_raw_socket(tls_stream, Role::Client, None).await;
-        let (ws_sink, ws_stream) = ws_stream.split();
         let mut gw = GwConn {
             client_name: client_name.to_owned(),
             target,
-            ws_sink,
-            ws_stream,
+            channel,
         };
 
         gw.handshake().await?;
@@ -195,8 +252,8 @@ impl GwClient {
         gw.tunnel_auth().await?;
         gw.channel().await?;
 
-        let (in_tx, in_rx) = tokio::sync::mpsc::channel(4);
-        let (out_tx, mut out_rx) = tokio::sync::mpsc::channel::<Bytes>(4);
+        let (in_tx, in_rx) = mpsc::channel(4);
+        let (out_tx, mut out_rx) = mpsc::channel::<Bytes>(4);
 
         let work = tokio::spawn(async move {
             let iv = Duration::from_secs(15 * 60);
@@ -213,11 +270,10 @@ impl GwClient {
                             cur.pos()
                         };
 
-                        gw.ws_sink.send(Message::Binary(Bytes::copy_from_slice(&wsbuf[..pos]))).await.map_err(|e| custom_err!("ws send", e))?;
+                        gw.channel.send(Bytes::copy_from_slice(&wsbuf[..pos])).await?;
                     },
-                    next = gw.ws_stream.next() => {
-                        let tmp = next.ok_or_else(|| Error::new("WS Stream Dead", GwErrorKind::Connect))?;
-                        let msg = tmp.map_err(|e| custom_err!("Stream", e))?.into_data();
+                    msg = gw.channel.recv() => {
+                        let msg = msg?;
                         let mut cur = ReadCursor::new(&msg);
                         let hdr = PktHdr::decode(&mut cur).map_err(|e| custom_err!("Header Decode", e))?;
 
@@ -245,7 +301,7 @@ impl GwClient {
                             pkt.encode(&mut cur).map_err(|e| custom_err!("PktEncode", e))?;
                             cur.pos()
                         };
-                        gw.ws_sink.send(Message::Binary(Bytes::copy_from_slice(&wsbuf[..pos]))).await.map_err(|e| custom_err!("ws send", e))?;
+                        gw.channel.send(Bytes::copy_from_slice(&wsbuf[..pos])).await?;
                     }
                 );
             }
@@ -270,21 +326,11 @@ impl GwConn {
                 .map_err(|e| Error::new("packet encode", GwErrorKind::Encode).with_source(e))?;
             cur.pos()
         };
-        self.ws_sink
-            .send(Message::Binary(Bytes::copy_from_slice(&buf[..pos])))
-            .await
-            .map_err(|e| custom_err!("WebSocket send error", e))?;
-        Ok(())
+        self.channel.send(Bytes::copy_from_slice(&buf[..pos])).await
     }
 
     async fn read_packet(&mut self) -> Result<(PktHdr, Bytes), Error> {
-        let mut msg = self
-            .ws_stream
-            .next()
-            .await
-            .ok_or_else(|| Error::new("Stream closed", GwErrorKind::Connect))?
-            .map_err(|e| custom_err!("WS err", e))?
-            .into_data();
+        let mut msg = self.channel.recv().await?;
         let mut cur = ReadCursor::new(&msg);
 
         let hdr = PktHdr::decode(&mut cur).map_err(|_| Error::new("PktHdr", GwErrorKind::Decode))?;
@@ -381,6 +427,200 @@ impl GwConn {
     }
 }
 
+impl GwChannel {
+    async fn http<S>(
+        out_stream: S,
+        out_req: http::Request<Empty<Bytes>>,
+        in_stream: S,
+        in_req: http::request::Builder,
+    ) -> Result<Self, Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(hyper_util::rt::tokio::TokioIo::new(out_stream))
+            .await
+            .map_err(|e| custom_err!("OUT channel H1 Handshake", e))?;
+        tokio::spawn(async move {
+            if let Err(e) = conn.await {
+                error!("OUT channel error: {e}");
+            }
+        });
+
+        let resp = sender
+            .send_request(out_req)
+            .await
+            .map_err(|e| custom_err!("OUT channel send error", e))?;
+        if resp.status() != http::StatusCode::OK {
+            return Err(Error::new("OUT channel", GwErrorKind::Connect));
+        }
+
+        let (out_tx, out_rx) = mpsc::channel(4);
+        tokio::spawn(read_http_packets(resp.into_body(), out_tx));
+
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(hyper_util::rt::tokio::TokioIo::new(in_stream))
+            .await
+            .map_err(|e| custom_err!("IN channel H1 Handshake", e))?;
+        tokio::spawn(async move {
+            if let Err(e) = conn.await {
+                error!("IN channel error: {e}");
+            }
+        });
+
+        // The body is sent chunked, each packet in its own chunk, for as long as the tunnel is open.
+        let (in_tx, in_rx) = mpsc::channel::<Bytes>(4);
+        let body = futures_util::stream::unfold(in_rx, |mut in_rx| async move {
+            let data = in_rx.recv().await?;
+            Some((Ok::<_, Infallible>(Frame::data(data)), in_rx))
+        });
+        let in_req = in_req
+            .body(StreamBody::new(Box::pin(body)))
+            .map_err(|e| custom_err!("failed to build request", e))?;
+
+        // The gateway may only respond once the body is complete, the tunnel doesn't wait for the response.
+        let resp = sender.send_request(in_req);
+        tokio::spawn(async move {
+            match resp.await {
+                Ok(resp) if resp.status() == http::StatusCode::OK => {}
+                Ok(resp) => error!("IN channel refused: {}", resp.status()),
+                Err(e) => error!("IN channel send error: {e}"),
+            }
+        });
+
+        Ok(GwChannel::Http { in_tx, out_rx })
+    }
+
+    async fn send(&mut self, packet: Bytes) -> Result<(), Error> {
+        match self {
+            GwChannel::WebSocket { sink, .. } => sink
+                .send(Message::Binary(packet))
+                .await
+                .map_err(|e| custom_err!("WebSocket send error", e)),
+            GwChannel::Http { in_tx, .. } => in_tx
+                .send(packet)
+                .await
+                .map_err(|_| Error::new("IN channel closed", GwErrorKind::Connect)),
+        }
+    }
+
+    /// Receives the next packet
+    ///
+    /// This method is cancellation safe.
+    async fn recv(&mut self) -> Result<Bytes, Error> {
+        match self {
+            GwChannel::WebSocket { stream, .. } => Ok(stream
+                .next()
+                .await
+                .ok_or_else(|| Error::new("Stream closed", GwErrorKind::Connect))?
+                .map_err(|e| custom_err!("WS err", e))?
+                .into_data()),
+            GwChannel::Http { out_rx, .. } => out_rx
+                .recv()
+                .await
+                .ok_or_else(|| Error::new("Stream closed", GwErrorKind::Connect))?,
+        }
+    }
+}
+
+/// Largest packet accepted from the gateway
+///
+/// Data packets are the largest ones, their payload length being 16-bit.
+const MAX_PACKET_SIZE: usize = PktHdr::FIXED_PART_SIZE + 2 + 0xFFFF;
+
+/// Splits the body of the OUT channel response in packets
+async fn read_http_packets<B>(mut body: B, packets: mpsc::Sender<Result<Bytes, Error>>)
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: core::error::Error + Send + Sync + 'static,
+{
+    let mut buf = Vec::new();
+
+    loop {
+        // Total length of the packet, from its header
+        while let Some(length) = buf
+            .get(4..8)
+            .and_then(|length| <[u8; 4]>::try_from(length).ok())
+            .map(u32::from_le_bytes)
+        {
+            let length = match usize::try_from(length) {
+                Ok(length) if (PktHdr::FIXED_PART_SIZE..=MAX_PACKET_SIZE).contains(&length) => length,
+                _ => {
+                    let _ = packets
+                        .send(Err(Error::new("PktHdr length", GwErrorKind::Decode)))
+                        .await;
+                    return;
+                }
+            };
+
+            if buf.len() < length {
+                break;
+            }
+
+            let packet = Bytes::copy_from_slice(&buf[..length]);
+            buf.drain(..length);
+
+            if packets.send(Ok(packet)).await.is_err() {
+                return;
+            }
+        }
+
+        match body.frame().await {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    buf.extend_from_slice(data);
+                }
+            }
+            Some(Err(e)) => {
+                let _ = packets.send(Err(custom_err!("OUT channel", e))).await;
+                return;
+            }
+            None => {
+                let _ = packets
+                    .send(Err(Error::new("OUT channel closed", GwErrorKind::Connect)))
+                    .await;
+                return;
+            }
+        }
+    }
+}
+
+/// Opens a TLS connection to the gateway, returning it with the local address and the host name of the gateway
+async fn connect_tls(target: &GwConnectTarget) -> Result<(TlsStream<TcpStream>, core::net::SocketAddr, &str), Error> {
+    let gw_host = target
+        .gw_endpoint
+        .split(":")
+        .next()
+        .ok_or_else(|| Error::new("Connect", GwErrorKind::InvalidGwTarget))?;
+
+    let stream = TcpStream::connect(&target.gw_endpoint)
+        .await
+        .map_err(|e| custom_err!("TCP connect", e))?;
+    let client_addr = stream
+        .local_addr()
+        .map_err(|e| custom_err!("get socket local address", e))?;
+
+    let (stream, _) = ironrdp_tls::upgrade(stream, gw_host)
+        .await
+        .map_err(|e| custom_err!("TLS connect", e))?;
+
+    Ok((stream, client_addr, gw_host))
+}
+
+/// Identifier shared by the requests of a tunnel
+fn connection_id() -> String {
+    format!("{{{}}}", uuid::Uuid::new_v4())
+}
+
+fn gw_request(method: &str, target: &GwConnectTarget, gw_host: &str, connection_id: &str) -> http::request::Builder {
+    let auth_val: String = STANDARD.encode(format!("{}:{}", target.gw_user, target.gw_pass));
+
+    http::Request::builder()
+        .method(method)
+        .header(hyper::header::HOST, gw_host)
+        .header("Rdg-Connection-Id", connection_id)
+        .uri("/remoteDesktopGateway/")
+        .header(hyper::header::AUTHORIZATION, format!("Basic {auth_val}"))
+}
+
 impl AsyncRead for GwClient {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -462,3 +702,174 @@ impl AsyncWrite for GwClient {
         Poll::Ready(Ok(()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _, DuplexStream};
+
+    use super::*;
+
+    fn keepalive_packet() -> Vec<u8> {
+        ironrdp_core::encode_vec(&KeepalivePkt).unwrap()
+    }
+
+    fn data_packet(data: &[u8]) -> Vec<u8> {
+        ironrdp_core::encode_vec(&DataPkt { data }).unwrap()
+    }
+
+    /// Runs `read_http_packets` over a body made of `chunks`, returning what it sends
+    async fn read_chunks(chunks: Vec<Vec<u8>>) -> Vec<Result<Bytes, Error>> {
+        let frames = chunks
+            .into_iter()
+            .map(|chunk| Ok::<_, Infallible>(Frame::data(Bytes::from(chunk))));
+        let body = StreamBody::new(futures_util::stream::iter(frames));
+
+        let (tx, mut rx) = mpsc::channel(16);
+        read_http_packets(body, tx).await;
+
+        let mut packets = Vec::new();
+        while let Some(packet) = rx.recv().await {
+            packets.push(packet);
+        }
+        packets
+    }
+
+    #[tokio::test]
+    async fn http_packets_split_across_chunks() {
+        let packet = data_packet(b"split data");
+        let (first, second) = packet.split_at(6);
+
+        let packets = read_chunks(vec![first.to_vec(), second[..1].to_vec(), second[1..].to_vec()]).await;
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].as_deref().unwrap(), packet.as_slice());
+        // The body ends after the packet.
+        assert!(packets[1].is_err());
+    }
+
+    #[tokio::test]
+    async fn http_packets_coalesced_in_chunk() {
+        let keepalive = keepalive_packet();
+        let data = data_packet(b"coalesced data");
+        let mut chunk = keepalive.clone();
+        chunk.extend_from_slice(&data);
+
+        let packets = read_chunks(vec![chunk]).await;
+
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0].as_deref().unwrap(), keepalive.as_slice());
+        assert_eq!(packets[1].as_deref().unwrap(), data.as_slice());
+        assert!(packets[2].is_err());
+    }
+
+    #[tokio::test]
+    async fn http_packet_too_large() {
+        let mut packet = data_packet(b"");
+        let length = u32::try_from(MAX_PACKET_SIZE + 1).unwrap();
+        packet[4..8].copy_from_slice(&length.to_le_bytes());
+
+        let packets = read_chunks(vec![packet]).await;
+
+        assert_eq!(packets.len(), 1);
+        assert!(matches!(packets[0].as_ref().unwrap_err().kind(), GwErrorKind::Decode));
+    }
+
+    #[tokio::test]
+    async fn http_packet_too_small() {
+        let mut packet = keepalive_packet();
+        packet[4..8].copy_from_slice(&4u32.to_le_bytes());
+
+        let packets = read_chunks(vec![packet]).await;
+
+        assert_eq!(packets.len(), 1);
+        assert!(matches!(packets[0].as_ref().unwrap_err().kind(), GwErrorKind::Decode));
+    }
+
+    /// Reads an HTTP request or response head, returning it with the bytes following it
+    async fn read_head(stream: &mut DuplexStream) -> (String, Vec<u8>) {
+        let mut buf = Vec::new();
+
+        loop {
+            if let Some(end) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+                let rest = buf.split_off(end + 4);
+                return (String::from_utf8(buf).unwrap(), rest);
+            }
+
+            let mut chunk = [0; 1024];
+            let n = stream.read(&mut chunk).await.unwrap();
+            assert_ne!(n, 0, "unexpected end of stream");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Reads a chunk of a chunked body, `buf` holding the bytes already read
+    async fn read_chunk(stream: &mut DuplexStream, buf: &mut Vec<u8>) -> Vec<u8> {
+        loop {
+            if let Some(end) = buf.windows(2).position(|window| window == b"\r\n") {
+                let size = usize::from_str_radix(core::str::from_utf8(&buf[..end]).unwrap(), 16).unwrap();
+                if buf.len() >= end + 2 + size + 2 {
+                    let chunk = buf[end + 2..end + 2 + size].to_vec();
+                    buf.drain(..end + 2 + size + 2);
+                    return chunk;
+                }
+            }
+
+            let mut chunk = [0; 1024];
+            let n = stream.read(&mut chunk).await.unwrap();
+            assert_ne!(n, 0, "unexpected end of stream");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    #[tokio::test]
+    async fn http_transport() {
+        let target = GwConnectTarget {
+            gw_endpoint: String::from("gw.example.com:443"),
+            gw_user: String::from("user"),
+            gw_pass: String::from("pass"),
+            server: String::from("rdp.example.com"),
+            transport: GwTransport::Http,
+        };
+        let connection_id = connection_id();
+
+        let (out_stream, mut gw_out_stream) = tokio::io::duplex(4096);
+        let (in_stream, mut gw_in_stream) = tokio::io::duplex(4096);
+
+        let gateway = tokio::spawn(async move {
+            let (head, _) = read_head(&mut gw_out_stream).await;
+            assert!(head.starts_with("RDG_OUT_DATA /remoteDesktopGateway/ HTTP/1.1\r\n"));
+
+            // The packets are split and coalesced across the chunks of the response.
+            let mut packets = keepalive_packet();
+            packets.extend_from_slice(&data_packet(b"from the gateway"));
+            let (first, second) = packets.split_at(11);
+
+            let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+            for chunk in [first, second] {
+                response.extend_from_slice(format!("{:X}\r\n", chunk.len()).as_bytes());
+                response.extend_from_slice(chunk);
+                response.extend_from_slice(b"\r\n");
+            }
+            gw_out_stream.write_all(&response).await.unwrap();
+
+            let (head, mut rest) = read_head(&mut gw_in_stream).await;
+            assert!(head.starts_with("RDG_IN_DATA /remoteDesktopGateway/ HTTP/1.1\r\n"));
+            assert!(head.to_ascii_lowercase().contains("transfer-encoding: chunked"));
+
+            read_chunk(&mut gw_in_stream, &mut rest).await
+        });
+
+        let out_req = gw_request("RDG_OUT_DATA", &target, "gw.example.com", &connection_id)
+            .body(Empty::<Bytes>::new())
+            .unwrap();
+        let in_req = gw_request("RDG_IN_DATA", &target, "gw.example.com", &connection_id);
+        let mut channel = GwChannel::http(out_stream, out_req, in_stream, in_req).await.unwrap();
+
+        assert_eq!(channel.recv().await.unwrap(), keepalive_packet());
+        assert_eq!(channel.recv().await.unwrap(), data_packet(b"from the gateway"));
+
+        let packet = data_packet(b"from the client");
+        channel.send(Bytes::from(packet.clone())).await.unwrap();
+        assert_eq!(gateway.await.unwrap(), packet);
+    }
+}
@@ -81,7 +81,7 @@ pub(crate) struct PktHdr {
 }
 
 impl PktHdr {
-    const FIXED_PART_SIZE: usize = 4 /* ty */ + 2 /* _reserved */ + 2 /* length */;
+    pub(crate) const FIXED_PART_SIZE: usize = 4 /* ty */ + 2 /* _reserved */ + 2 /* length */;
 }
 
 impl Encode for PktHdr {
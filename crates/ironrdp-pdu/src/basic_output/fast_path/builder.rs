@@ -0,0 +1,113 @@
+use ironrdp_core::{invalid_field_err, EncodeResult};
+
+use super::{FastPathUpdatePdu, Fragmentation, UpdateCode};
+use crate::rdp::client_info::CompressionType;
+use crate::rdp::headers::CompressionFlags;
+
+/// Builder for [`FastPathUpdatePdu`]
+///
+/// Keeps the compression flags and type consistent, and checks that the update data
+/// fits in the 16-bit size field, optionally splitting it into fragments.
+///
+/// ```
+/// # use ironrdp_pdu::fast_path::{FastPathUpdatePduBuilder, Fragmentation, UpdateCode};
+/// let data = [0xAA; 100];
+/// let fragments = FastPathUpdatePduBuilder::new(UpdateCode::Bitmap, &data).build_fragments(40)?;
+///
+/// assert_eq!(fragments.len(), 3);
+/// assert_eq!(fragments[0].fragmentation, Fragmentation::First);
+/// assert_eq!(fragments[2].fragmentation, Fragmentation::Last);
+/// # Ok::<(), ironrdp_core::EncodeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastPathUpdatePduBuilder<'a> {
+    update_code: UpdateCode,
+    fragmentation: Fragmentation,
+    compression: Option<(CompressionFlags, CompressionType)>,
+    data: &'a [u8],
+}
+
+impl<'a> FastPathUpdatePduBuilder<'a> {
+    pub fn new(update_code: UpdateCode, data: &'a [u8]) -> Self {
+        Self {
+            update_code,
+            fragmentation: Fragmentation::Single,
+            compression: None,
+            data,
+        }
+    }
+
+    #[must_use]
+    pub fn fragmentation(mut self, fragmentation: Fragmentation) -> Self {
+        self.fragmentation = fragmentation;
+        self
+    }
+
+    /// Marks the update data as bulk compressed
+    #[must_use]
+    pub fn compression(mut self, flags: CompressionFlags, compression_type: CompressionType) -> Self {
+        self.compression = Some((flags, compression_type));
+        self
+    }
+
+    pub fn build(self) -> EncodeResult<FastPathUpdatePdu<'a>> {
+        if u16::try_from(self.data.len()).is_err() {
+            return Err(invalid_field_err!(
+                FastPathUpdatePdu::NAME,
+                "size",
+                "update data must be at most 65535 bytes, use fragmentation"
+            ));
+        }
+
+        Ok(self.pdu(self.fragmentation, self.data))
+    }
+
+    /// Splits the update data into fragments of at most `max_fragment_size` bytes
+    ///
+    /// The fragmentation set on the builder is ignored: a single PDU is produced
+    /// when the data fits in one fragment.
+    pub fn build_fragments(self, max_fragment_size: usize) -> EncodeResult<Vec<FastPathUpdatePdu<'a>>> {
+        if max_fragment_size == 0 || u16::try_from(max_fragment_size).is_err() {
+            return Err(invalid_field_err!(
+                FastPathUpdatePdu::NAME,
+                "size",
+                "fragment size must be in the 1..=65535 range"
+            ));
+        }
+
+        if self.data.len() <= max_fragment_size {
+            return Ok(vec![self.pdu(Fragmentation::Single, self.data)]);
+        }
+
+        let chunk_count = self.data.len().div_ceil(max_fragment_size);
+
+        let fragments = self
+            .data
+            .chunks(max_fragment_size)
+            .enumerate()
+            .map(|(idx, chunk)| {
+                let fragmentation = if idx == 0 {
+                    Fragmentation::First
+                } else if idx + 1 == chunk_count {
+                    Fragmentation::Last
+                } else {
+                    Fragmentation::Next
+                };
+
+                self.pdu(fragmentation, chunk)
+            })
+            .collect();
+
+        Ok(fragments)
+    }
+
+    fn pdu(&self, fragmentation: Fragmentation, data: &'a [u8]) -> FastPathUpdatePdu<'a> {
+        FastPathUpdatePdu {
+            fragmentation,
+            update_code: self.update_code,
+            compression_flags: self.compression.map(|(flags, _)| flags),
+            compression_type: self.compression.map(|(_, compression_type)| compression_type),
+            data,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod tests;
 
+mod builder;
+
 use bit_field::BitField as _;
 use bitflags::bitflags;
 use ironrdp_core::{
@@ -11,12 +13,15 @@ use num_derive::FromPrimitive;
 use num_traits::FromPrimitive as _;
 
 use super::bitmap::BitmapUpdateData;
+use super::orders::OrdersUpdateData;
 use super::pointer::PointerUpdateData;
 use super::surface_commands::{SurfaceCommand, SURFACE_COMMAND_HEADER_SIZE};
 use crate::per;
 use crate::rdp::client_info::CompressionType;
 use crate::rdp::headers::{CompressionFlags, SHARE_DATA_HEADER_COMPRESSION_MASK};
 
+pub use self::builder::FastPathUpdatePduBuilder;
+
 /// Implements the Fast-Path RDP message header PDU.
 /// TS_FP_UPDATE_PDU
 #[expect(
@@ -218,6 +223,7 @@ impl<'de> Decode<'de> for FastPathUpdatePdu<'de> {
 /// TS_FP_UPDATE data
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FastPathUpdate<'a> {
+    Orders(OrdersUpdateData<'a>),
     SurfaceCommands(Vec<SurfaceCommand<'a>>),
     Bitmap(BitmapUpdateData<'a>),
     Pointer(PointerUpdateData<'a>),
@@ -233,6 +239,7 @@ impl<'a> FastPathUpdate<'a> {
 
     pub fn decode_cursor_with_code(src: &mut ReadCursor<'a>, code: UpdateCode) -> DecodeResult<Self> {
         match code {
+            UpdateCode::Orders => Ok(Self::Orders(decode_cursor(src)?)),
             UpdateCode::SurfaceCommands => {
                 let mut commands = Vec::with_capacity(1);
                 while src.len() >= SURFACE_COMMAND_HEADER_SIZE {
@@ -258,6 +265,7 @@ impl<'a> FastPathUpdate<'a> {
 
     pub fn as_short_name(&self) -> &str {
         match self {
+            Self::Orders(_) => "Orders",
             Self::SurfaceCommands(_) => "Surface Commands",
             Self::Bitmap(_) => "Bitmap",
             Self::Pointer(_) => "Pointer",
@@ -270,6 +278,9 @@ impl Encode for FastPathUpdate<'_> {
         ensure_size!(in: dst, size: self.size());
 
         match self {
+            Self::Orders(orders) => {
+                orders.encode(dst)?;
+            }
             Self::SurfaceCommands(commands) => {
                 for command in commands {
                     command.encode(dst)?;
@@ -298,6 +309,7 @@ impl Encode for FastPathUpdate<'_> {
 
     fn size(&self) -> usize {
         match self {
+            Self::Orders(orders) => orders.size(),
             Self::SurfaceCommands(commands) => commands.iter().map(|c| c.size()).sum::<usize>(),
             Self::Bitmap(bitmap) => bitmap.size(),
             Self::Pointer(pointer) => match pointer {
@@ -343,6 +355,7 @@ impl UpdateCode {
 impl From<&FastPathUpdate<'_>> for UpdateCode {
     fn from(update: &FastPathUpdate<'_>) -> Self {
         match update {
+            FastPathUpdate::Orders(_) => Self::Orders,
             FastPathUpdate::SurfaceCommands(_) => Self::SurfaceCommands,
             FastPathUpdate::Bitmap(_) => Self::Bitmap,
             FastPathUpdate::Pointer(action) => match action {
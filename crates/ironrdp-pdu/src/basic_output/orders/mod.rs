@@ -0,0 +1,857 @@
+#[cfg(test)]
+mod tests;
+mod window;
+
+use core::fmt;
+
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+
+pub use self::window::*;
+use crate::bitmap::CompressedDataHeader;
+
+// controlFlags of TS_PRIMARY_DRAWING_ORDER and TS_SECONDARY_DRAWING_ORDER headers
+const TS_STANDARD: u8 = 0x01;
+const TS_SECONDARY: u8 = 0x02;
+const TS_BOUNDS: u8 = 0x04;
+const TS_TYPE_CHANGE: u8 = 0x08;
+const TS_DELTA_COORDINATES: u8 = 0x10;
+const TS_ZERO_BOUNDS_DELTAS: u8 = 0x20;
+const TS_ZERO_FIELD_BYTE_BIT0: u8 = 0x40;
+const TS_ZERO_FIELD_BYTE_BIT1: u8 = 0x80;
+
+const TS_ENC_SCRBLT_ORDER: u8 = 0x02;
+const TS_ENC_LINETO_ORDER: u8 = 0x09;
+const TS_ENC_OPAQUERECT_ORDER: u8 = 0x0A;
+const TS_ENC_MEMBLT_ORDER: u8 = 0x0D;
+
+const TS_CACHE_BITMAP_UNCOMPRESSED_REV2: u8 = 0x04;
+const TS_CACHE_BITMAP_COMPRESSED_REV2: u8 = 0x05;
+
+const CBR2_HEIGHT_SAME_AS_WIDTH: u16 = 0x01;
+const CBR2_PERSISTENT_KEY_PRESENT: u16 = 0x02;
+const CBR2_NO_BITMAP_COMPRESSION_HDR: u16 = 0x08;
+
+/// The secondary order header orderLength is the order size minus this value
+const SECONDARY_ORDER_LENGTH_DELTA: u16 = 13;
+
+/// TS_FP_UPDATE_ORDERS
+///
+/// The fast-path update PDU carrying drawing orders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrdersUpdateData<'a> {
+    pub orders: Vec<DrawingOrder<'a>>,
+}
+
+impl OrdersUpdateData<'_> {
+    const NAME: &'static str = "TS_FP_UPDATE_ORDERS";
+    const FIXED_PART_SIZE: usize = 2 /* numberOrders */;
+
+    pub fn encode_header(orders: u16, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u16(orders);
+
+        Ok(())
+    }
+}
+
+impl Encode for OrdersUpdateData<'_> {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        Self::encode_header(cast_length!("numberOrders", self.orders.len())?, dst)?;
+
+        for order in self.orders.iter() {
+            order.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.orders
+            .iter()
+            .fold(Self::FIXED_PART_SIZE, |size, order| size + order.size())
+    }
+}
+
+impl<'de> Decode<'de> for OrdersUpdateData<'de> {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let order_count = usize::from(src.read_u16());
+        let mut orders = Vec::with_capacity(order_count);
+
+        for _ in 0..order_count {
+            orders.push(DrawingOrder::decode(src)?);
+        }
+
+        Ok(Self { orders })
+    }
+}
+
+/// A drawing order, as found in orders updates
+///
+/// Only a few primary orders, the secondary orders required for bitmap caching,
+/// and the windowing alternate secondary orders are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawingOrder<'a> {
+    ScrBlt(ScrBltOrder),
+    OpaqueRect(OpaqueRectOrder),
+    LineTo(LineToOrder),
+    MemBlt(MemBltOrder),
+    CacheBitmapRev2(CacheBitmapRev2Order<'a>),
+    Window(WindowOrder),
+}
+
+impl DrawingOrder<'_> {
+    const NAME: &'static str = "DRAWING_ORDER";
+}
+
+impl Encode for DrawingOrder<'_> {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        match self {
+            Self::ScrBlt(order) => order.encode(dst),
+            Self::OpaqueRect(order) => order.encode(dst),
+            Self::LineTo(order) => order.encode(dst),
+            Self::MemBlt(order) => order.encode(dst),
+            Self::CacheBitmapRev2(order) => order.encode(dst),
+            Self::Window(order) => order.encode(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::ScrBlt(order) => order.size(),
+            Self::OpaqueRect(order) => order.size(),
+            Self::LineTo(order) => order.size(),
+            Self::MemBlt(order) => order.size(),
+            Self::CacheBitmapRev2(order) => order.size(),
+            Self::Window(order) => order.size(),
+        }
+    }
+}
+
+impl<'de> Decode<'de> for DrawingOrder<'de> {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: 1);
+
+        let control_flags = src.peek_u8();
+
+        if control_flags & TS_STANDARD == 0 {
+            if control_flags >> 2 == TS_ALTSEC_WINDOW {
+                return Ok(Self::Window(WindowOrder::decode(src)?));
+            }
+
+            return Err(invalid_field_err!(
+                "controlFlags",
+                "unsupported alternate secondary order type"
+            ));
+        }
+
+        if control_flags & TS_SECONDARY != 0 {
+            return Ok(Self::CacheBitmapRev2(CacheBitmapRev2Order::decode(src)?));
+        }
+
+        ensure_size!(in: src, size: 2);
+
+        match src.peek::<2>()[1] {
+            TS_ENC_SCRBLT_ORDER => Ok(Self::ScrBlt(ScrBltOrder::decode(src)?)),
+            TS_ENC_OPAQUERECT_ORDER => Ok(Self::OpaqueRect(OpaqueRectOrder::decode(src)?)),
+            TS_ENC_LINETO_ORDER => Ok(Self::LineTo(LineToOrder::decode(src)?)),
+            TS_ENC_MEMBLT_ORDER => Ok(Self::MemBlt(MemBltOrder::decode(src)?)),
+            _ => Err(invalid_field_err!("orderType", "unsupported primary order type")),
+        }
+    }
+}
+
+/// A primary drawing order
+///
+/// Primary orders are always encoded with all of their fields, without bounds nor delta coordinates,
+/// so that they don't depend on the previous primary drawing orders.
+trait PrimaryOrder {
+    const NAME: &'static str;
+    const ORDER_TYPE: u8;
+    const FIELD_COUNT: usize;
+
+    /// fieldFlags is one byte longer than needed for the fields of the order
+    const FIELD_FLAGS_SIZE: usize = (Self::FIELD_COUNT + 1).div_ceil(8);
+
+    const HEADER_SIZE: usize = 1 /* controlFlags */ + 1 /* orderType */ + Self::FIELD_FLAGS_SIZE;
+}
+
+fn encode_primary_header<T: PrimaryOrder>(dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+    ensure_size!(in: dst, size: T::HEADER_SIZE);
+
+    let field_flags = (1u32 << T::FIELD_COUNT) - 1;
+
+    dst.write_u8(TS_STANDARD | TS_TYPE_CHANGE);
+    dst.write_u8(T::ORDER_TYPE);
+    dst.write_slice(&field_flags.to_le_bytes()[..T::FIELD_FLAGS_SIZE]);
+
+    Ok(())
+}
+
+fn decode_primary_header<T: PrimaryOrder>(src: &mut ReadCursor<'_>) -> DecodeResult<()> {
+    ensure_size!(in: src, size: T::HEADER_SIZE);
+
+    let control_flags = src.read_u8();
+    let unsupported = TS_SECONDARY
+        | TS_BOUNDS
+        | TS_DELTA_COORDINATES
+        | TS_ZERO_BOUNDS_DELTAS
+        | TS_ZERO_FIELD_BYTE_BIT0
+        | TS_ZERO_FIELD_BYTE_BIT1;
+    if control_flags & (TS_STANDARD | TS_TYPE_CHANGE) != TS_STANDARD | TS_TYPE_CHANGE
+        || control_flags & unsupported != 0
+    {
+        return Err(invalid_field_err!(
+            "controlFlags",
+            "only standalone primary orders are supported"
+        ));
+    }
+
+    if src.read_u8() != T::ORDER_TYPE {
+        return Err(invalid_field_err!("orderType", "unexpected primary order type"));
+    }
+
+    let field_flags = (1u32 << T::FIELD_COUNT) - 1;
+    if src.read_slice(T::FIELD_FLAGS_SIZE) != &field_flags.to_le_bytes()[..T::FIELD_FLAGS_SIZE] {
+        return Err(invalid_field_err!("fieldFlags", "all the order fields must be present"));
+    }
+
+    Ok(())
+}
+
+/// TS_COLOR
+///
+/// The color of an order, in the color depth of the session. At 15 and 16 bpp, the pixel value is stored
+/// in the low-order bytes, and at 8 bpp, `red` holds a palette index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    const ENCODED_SIZE: usize = 3;
+
+    fn encode(self, dst: &mut WriteCursor<'_>) {
+        dst.write_u8(self.red);
+        dst.write_u8(self.green);
+        dst.write_u8(self.blue);
+    }
+
+    fn decode(src: &mut ReadCursor<'_>) -> Self {
+        Self {
+            red: src.read_u8(),
+            green: src.read_u8(),
+            blue: src.read_u8(),
+        }
+    }
+}
+
+/// TS_SCRBLT_ORDER
+///
+/// Copies a rectangle of the screen to another place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrBltOrder {
+    pub left: i16,
+    pub top: i16,
+    pub width: i16,
+    pub height: i16,
+    /// The ternary raster operation, 0xCC (SRCCOPY) to copy the pixels as is
+    pub rop: u8,
+    pub src_x: i16,
+    pub src_y: i16,
+}
+
+impl ScrBltOrder {
+    const FIXED_PART_SIZE: usize = Self::HEADER_SIZE + 2 * 4 /* destination rectangle */ + 1 /* bRop */ + 2 * 2 /* source */;
+
+    /// The SRCCOPY raster operation
+    pub const ROP_SRCCOPY: u8 = 0xCC;
+}
+
+impl PrimaryOrder for ScrBltOrder {
+    const NAME: &'static str = "TS_SCRBLT_ORDER";
+    const ORDER_TYPE: u8 = TS_ENC_SCRBLT_ORDER;
+    const FIELD_COUNT: usize = 7;
+}
+
+impl Encode for ScrBltOrder {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        encode_primary_header::<Self>(dst)?;
+        dst.write_i16(self.left);
+        dst.write_i16(self.top);
+        dst.write_i16(self.width);
+        dst.write_i16(self.height);
+        dst.write_u8(self.rop);
+        dst.write_i16(self.src_x);
+        dst.write_i16(self.src_y);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ScrBltOrder {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        decode_primary_header::<Self>(src)?;
+
+        Ok(Self {
+            left: src.read_i16(),
+            top: src.read_i16(),
+            width: src.read_i16(),
+            height: src.read_i16(),
+            rop: src.read_u8(),
+            src_x: src.read_i16(),
+            src_y: src.read_i16(),
+        })
+    }
+}
+
+/// TS_OPAQUERECT_ORDER
+///
+/// Fills a rectangle with a solid color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpaqueRectOrder {
+    pub left: i16,
+    pub top: i16,
+    pub width: i16,
+    pub height: i16,
+    pub color: Color,
+}
+
+impl OpaqueRectOrder {
+    const FIXED_PART_SIZE: usize = Self::HEADER_SIZE + 2 * 4 /* rectangle */ + Color::ENCODED_SIZE;
+}
+
+impl PrimaryOrder for OpaqueRectOrder {
+    const NAME: &'static str = "TS_OPAQUERECT_ORDER";
+    const ORDER_TYPE: u8 = TS_ENC_OPAQUERECT_ORDER;
+    // Each of the color components is a field on its own.
+    const FIELD_COUNT: usize = 7;
+}
+
+impl Encode for OpaqueRectOrder {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        encode_primary_header::<Self>(dst)?;
+        dst.write_i16(self.left);
+        dst.write_i16(self.top);
+        dst.write_i16(self.width);
+        dst.write_i16(self.height);
+        self.color.encode(dst);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for OpaqueRectOrder {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        decode_primary_header::<Self>(src)?;
+
+        Ok(Self {
+            left: src.read_i16(),
+            top: src.read_i16(),
+            width: src.read_i16(),
+            height: src.read_i16(),
+            color: Color::decode(src),
+        })
+    }
+}
+
+/// TS_LINE_TO_ORDER
+///
+/// Draws a line from the start point, up to but not including the end point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineToOrder {
+    /// Background mix mode, [`LineToOrder::TRANSPARENT`] or [`LineToOrder::OPAQUE`]
+    pub back_mode: u16,
+    pub start_x: i16,
+    pub start_y: i16,
+    pub end_x: i16,
+    pub end_y: i16,
+    pub back_color: Color,
+    /// The binary raster operation, 0x0D (R2_COPYPEN) to draw with the pen color
+    pub rop2: u8,
+    /// Only PS_SOLID (0) is supported by clients
+    pub pen_style: u8,
+    /// Only a width of 1 is supported by clients
+    pub pen_width: u8,
+    pub pen_color: Color,
+}
+
+impl LineToOrder {
+    const FIXED_PART_SIZE: usize = Self::HEADER_SIZE + 2 /* backMode */ + 2 * 4 /* start and end */
+        + Color::ENCODED_SIZE /* backColor */ + 1 /* bRop2 */ + 1 /* penStyle */ + 1 /* penWidth */
+        + Color::ENCODED_SIZE /* penColor */;
+
+    pub const TRANSPARENT: u16 = 0x0001;
+    pub const OPAQUE: u16 = 0x0002;
+
+    /// The R2_COPYPEN raster operation
+    pub const ROP2_COPYPEN: u8 = 0x0D;
+
+    pub const PS_SOLID: u8 = 0x00;
+}
+
+impl PrimaryOrder for LineToOrder {
+    const NAME: &'static str = "TS_LINE_TO_ORDER";
+    const ORDER_TYPE: u8 = TS_ENC_LINETO_ORDER;
+    const FIELD_COUNT: usize = 10;
+}
+
+impl Encode for LineToOrder {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        encode_primary_header::<Self>(dst)?;
+        dst.write_u16(self.back_mode);
+        dst.write_i16(self.start_x);
+        dst.write_i16(self.start_y);
+        dst.write_i16(self.end_x);
+        dst.write_i16(self.end_y);
+        self.back_color.encode(dst);
+        dst.write_u8(self.rop2);
+        dst.write_u8(self.pen_style);
+        dst.write_u8(self.pen_width);
+        self.pen_color.encode(dst);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for LineToOrder {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        decode_primary_header::<Self>(src)?;
+
+        Ok(Self {
+            back_mode: src.read_u16(),
+            start_x: src.read_i16(),
+            start_y: src.read_i16(),
+            end_x: src.read_i16(),
+            end_y: src.read_i16(),
+            back_color: Color::decode(src),
+            rop2: src.read_u8(),
+            pen_style: src.read_u8(),
+            pen_width: src.read_u8(),
+            pen_color: Color::decode(src),
+        })
+    }
+}
+
+/// TS_MEMBLT_ORDER
+///
+/// Draws a bitmap from the bitmap cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemBltOrder {
+    pub cache_id: u8,
+    /// Index of the color table to use with 8 bpp bitmaps
+    pub color_table_index: u8,
+    pub left: i16,
+    pub top: i16,
+    pub width: i16,
+    pub height: i16,
+    /// The ternary raster operation, 0xCC (SRCCOPY) to copy the bitmap as is
+    pub rop: u8,
+    pub src_x: i16,
+    pub src_y: i16,
+    pub cache_index: u16,
+}
+
+impl MemBltOrder {
+    const FIXED_PART_SIZE: usize = Self::HEADER_SIZE + 2 /* cacheId */ + 2 * 4 /* destination rectangle */
+        + 1 /* bRop */ + 2 * 2 /* source */ + 2 /* cacheIndex */;
+
+    /// The SRCCOPY raster operation
+    pub const ROP_SRCCOPY: u8 = 0xCC;
+}
+
+impl PrimaryOrder for MemBltOrder {
+    const NAME: &'static str = "TS_MEMBLT_ORDER";
+    const ORDER_TYPE: u8 = TS_ENC_MEMBLT_ORDER;
+    const FIELD_COUNT: usize = 9;
+}
+
+impl Encode for MemBltOrder {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        encode_primary_header::<Self>(dst)?;
+        dst.write_u8(self.cache_id);
+        dst.write_u8(self.color_table_index);
+        dst.write_i16(self.left);
+        dst.write_i16(self.top);
+        dst.write_i16(self.width);
+        dst.write_i16(self.height);
+        dst.write_u8(self.rop);
+        dst.write_i16(self.src_x);
+        dst.write_i16(self.src_y);
+        dst.write_u16(self.cache_index);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for MemBltOrder {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        decode_primary_header::<Self>(src)?;
+
+        Ok(Self {
+            cache_id: src.read_u8(),
+            color_table_index: src.read_u8(),
+            left: src.read_i16(),
+            top: src.read_i16(),
+            width: src.read_i16(),
+            height: src.read_i16(),
+            rop: src.read_u8(),
+            src_x: src.read_i16(),
+            src_y: src.read_i16(),
+            cache_index: src.read_u16(),
+        })
+    }
+}
+
+/// TS_CACHE_BITMAP_REV2_ORDER
+///
+/// Stores a bitmap in a cell of the bitmap cache (revision 2).
+#[derive(Clone, PartialEq, Eq)]
+pub struct CacheBitmapRev2Order<'a> {
+    pub cache_id: u8,
+    pub cache_index: u16,
+    /// The persistent key of the bitmap, for caches stored on disk by the client
+    pub key: Option<u64>,
+    /// One of 8, 16, 24 or 32
+    pub bits_per_pixel: u8,
+    pub width: u16,
+    pub height: u16,
+    /// Whether `bitmap_data` is compressed with the interleaved RLE (or RDP 6.0 planar at 32 bpp) codec
+    pub compressed: bool,
+    /// Only valid when `compressed` is set
+    pub compressed_data_header: Option<CompressedDataHeader>,
+    pub bitmap_data: &'a [u8],
+}
+
+impl CacheBitmapRev2Order<'_> {
+    const NAME: &'static str = "TS_CACHE_BITMAP_REV2_ORDER";
+
+    const FIXED_PART_SIZE: usize = 1 /* controlFlags */ + 2 /* orderLength */ + 2 /* extraFlags */ + 1 /* orderType */;
+
+    /// Largest cache index, also used as the waiting list index
+    pub const MAX_CACHE_INDEX: u16 = 0x7FFF;
+
+    fn bitmap_length(&self) -> usize {
+        self.bitmap_data.len() + self.compressed_data_header.as_ref().map(|hdr| hdr.size()).unwrap_or(0)
+    }
+
+    fn body_size(&self) -> usize {
+        let key = if self.key.is_some() { 8 } else { 0 };
+        let height = if self.height == self.width {
+            0
+        } else {
+            two_byte_unsigned_size(self.height)
+        };
+
+        key + two_byte_unsigned_size(self.width)
+            + height
+            + four_byte_unsigned_size(self.bitmap_length())
+            + two_byte_unsigned_size(self.cache_index)
+            + self.bitmap_length()
+    }
+}
+
+impl Encode for CacheBitmapRev2Order<'_> {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        if self.cache_id > 0x07 {
+            return Err(invalid_field_err!("cacheId", "cache identifier is out of range"));
+        }
+
+        let bits_per_pixel_id = match self.bits_per_pixel {
+            8 => 0x03,
+            16 => 0x04,
+            24 => 0x05,
+            32 => 0x06,
+            _ => return Err(invalid_field_err!("bitsPerPixelId", "unsupported color depth")),
+        };
+
+        if self.compressed_data_header.is_some() && !self.compressed {
+            return Err(invalid_field_err!(
+                "bitmapComprHdr",
+                "compression header for an uncompressed bitmap"
+            ));
+        }
+
+        let mut flags = 0;
+        if self.height == self.width {
+            flags |= CBR2_HEIGHT_SAME_AS_WIDTH;
+        }
+        if self.key.is_some() {
+            flags |= CBR2_PERSISTENT_KEY_PRESENT;
+        }
+        if self.compressed && self.compressed_data_header.is_none() {
+            flags |= CBR2_NO_BITMAP_COMPRESSION_HDR;
+        }
+
+        let order_length: u16 = cast_length!("orderLength", self.size())?;
+
+        dst.write_u8(TS_STANDARD | TS_SECONDARY);
+        dst.write_u16(order_length.wrapping_sub(SECONDARY_ORDER_LENGTH_DELTA));
+        dst.write_u16(u16::from(self.cache_id) | (bits_per_pixel_id << 3) | (flags << 7));
+        dst.write_u8(if self.compressed {
+            TS_CACHE_BITMAP_COMPRESSED_REV2
+        } else {
+            TS_CACHE_BITMAP_UNCOMPRESSED_REV2
+        });
+
+        if let Some(key) = self.key {
+            dst.write_u64(key);
+        }
+        write_two_byte_unsigned("bitmapWidth", self.width, dst)?;
+        if self.height != self.width {
+            write_two_byte_unsigned("bitmapHeight", self.height, dst)?;
+        }
+        write_four_byte_unsigned("bitmapLength", self.bitmap_length(), dst)?;
+        write_two_byte_unsigned("cacheIndex", self.cache_index, dst)?;
+        if let Some(header) = &self.compressed_data_header {
+            header.encode(dst)?;
+        }
+        dst.write_slice(self.bitmap_data);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.body_size()
+    }
+}
+
+impl<'de> Decode<'de> for CacheBitmapRev2Order<'de> {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let control_flags = src.read_u8();
+        if control_flags & (TS_STANDARD | TS_SECONDARY) != TS_STANDARD | TS_SECONDARY {
+            return Err(invalid_field_err!("controlFlags", "not a secondary order"));
+        }
+
+        let order_length = src.read_u16().wrapping_add(SECONDARY_ORDER_LENGTH_DELTA);
+        let extra_flags = src.read_u16();
+
+        let compressed = match src.read_u8() {
+            TS_CACHE_BITMAP_COMPRESSED_REV2 => true,
+            TS_CACHE_BITMAP_UNCOMPRESSED_REV2 => false,
+            _ => return Err(invalid_field_err!("orderType", "unsupported secondary order type")),
+        };
+
+        let body_size = usize::from(order_length)
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("orderLength", "order length is too small"))?;
+        ensure_size!(in: src, size: body_size);
+        let mut body = ReadCursor::new(src.read_slice(body_size));
+
+        let cache_id = u8::try_from(extra_flags & 0x07).expect("cache identifier is masked to 3 bits");
+        let bits_per_pixel = match (extra_flags >> 3) & 0x0F {
+            0x03 => 8,
+            0x04 => 16,
+            0x05 => 24,
+            0x06 => 32,
+            _ => return Err(invalid_field_err!("bitsPerPixelId", "invalid color depth")),
+        };
+        let flags = extra_flags >> 7;
+
+        let key = if flags & CBR2_PERSISTENT_KEY_PRESENT != 0 {
+            ensure_size!(in: body, size: 8);
+            Some(body.read_u64())
+        } else {
+            None
+        };
+        let width = read_two_byte_unsigned(&mut body)?;
+        let height = if flags & CBR2_HEIGHT_SAME_AS_WIDTH != 0 {
+            width
+        } else {
+            read_two_byte_unsigned(&mut body)?
+        };
+        let bitmap_length = read_four_byte_unsigned(&mut body)?;
+        let cache_index = read_two_byte_unsigned(&mut body)?;
+
+        let (compressed_data_header, bitmap_length) = if compressed && flags & CBR2_NO_BITMAP_COMPRESSION_HDR == 0 {
+            let bitmap_length = bitmap_length
+                .checked_sub(CompressedDataHeader::ENCODED_SIZE)
+                .ok_or_else(|| invalid_field_err!("bitmapLength", "length is less than the compression header"))?;
+
+            (Some(CompressedDataHeader::decode(&mut body)?), bitmap_length)
+        } else {
+            (None, bitmap_length)
+        };
+
+        ensure_size!(in: body, size: bitmap_length);
+        let bitmap_data = body.read_slice(bitmap_length);
+
+        Ok(Self {
+            cache_id,
+            cache_index,
+            key,
+            bits_per_pixel,
+            width,
+            height,
+            compressed,
+            compressed_data_header,
+            bitmap_data,
+        })
+    }
+}
+
+impl fmt::Debug for CacheBitmapRev2Order<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheBitmapRev2Order")
+            .field("cache_id", &self.cache_id)
+            .field("cache_index", &self.cache_index)
+            .field("key", &self.key)
+            .field("bits_per_pixel", &self.bits_per_pixel)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("compressed", &self.compressed)
+            .field("compressed_data_header", &self.compressed_data_header)
+            .field("bitmap_data.len()", &self.bitmap_data.len())
+            .finish()
+    }
+}
+
+/// Size of a value in the 2-byte unsigned encoding (TWO_BYTE_UNSIGNED_ENCODING)
+fn two_byte_unsigned_size(value: u16) -> usize {
+    if value <= 0x7F {
+        1
+    } else {
+        2
+    }
+}
+
+fn write_two_byte_unsigned(field: &'static str, value: u16, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+    match value {
+        0..=0x7F => dst.write_u8(value.to_le_bytes()[0]),
+        0x80..=0x7FFF => {
+            let [low, high] = value.to_le_bytes();
+            dst.write_u8(0x80 | high);
+            dst.write_u8(low);
+        }
+        _ => return Err(invalid_field_err!(field, "value is too large for the 2-byte encoding")),
+    }
+
+    Ok(())
+}
+
+fn read_two_byte_unsigned(src: &mut ReadCursor<'_>) -> DecodeResult<u16> {
+    ensure_size!(in: src, size: 1);
+    let first = src.read_u8();
+
+    if first & 0x80 == 0 {
+        Ok(u16::from(first))
+    } else {
+        ensure_size!(in: src, size: 1);
+        Ok(u16::from_be_bytes([first & 0x7F, src.read_u8()]))
+    }
+}
+
+/// Size of a value in the 4-byte unsigned encoding (FOUR_BYTE_UNSIGNED_ENCODING)
+fn four_byte_unsigned_size(value: usize) -> usize {
+    match value {
+        0..=0x3F => 1,
+        0x40..=0x3FFF => 2,
+        0x4000..=0x3F_FFFF => 3,
+        _ => 4,
+    }
+}
+
+fn write_four_byte_unsigned(field: &'static str, value: usize, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+    let value: u32 = cast_length!(field, value)?;
+    if value > 0x3FFF_FFFF {
+        return Err(invalid_field_err!(field, "value is too large for the 4-byte encoding"));
+    }
+
+    let count = four_byte_unsigned_size(usize::try_from(value).expect("value fits in 30 bits"));
+    let bytes = value.to_be_bytes();
+    let bytes = &bytes[bytes.len() - count..];
+
+    // The two most significant bits of the first byte hold the number of additional bytes.
+    let extra = u8::try_from(count - 1).expect("at most 3 additional bytes");
+    dst.write_u8((extra << 6) | bytes[0]);
+    dst.write_slice(&bytes[1..]);
+
+    Ok(())
+}
+
+fn read_four_byte_unsigned(src: &mut ReadCursor<'_>) -> DecodeResult<usize> {
+    ensure_size!(in: src, size: 1);
+    let first = src.read_u8();
+    let extra = usize::from(first >> 6);
+
+    ensure_size!(in: src, size: extra);
+    let value = src
+        .read_slice(extra)
+        .iter()
+        .fold(u32::from(first & 0x3F), |value, byte| (value << 8) | u32::from(*byte));
+
+    Ok(usize::try_from(value).expect("value fits in 30 bits"))
+}
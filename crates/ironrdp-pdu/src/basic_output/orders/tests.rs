@@ -0,0 +1,399 @@
+use std::sync::LazyLock;
+
+use ironrdp_core::{decode, encode, encode_vec};
+
+use super::*;
+use crate::geometry::ExclusiveRectangle;
+
+const MEM_BLT_BUFFER: [u8; 21] = [
+    0x09, // controlFlags = TS_STANDARD | TS_TYPE_CHANGE
+    0x0d, // orderType = TS_ENC_MEMBLT_ORDER
+    0xff, 0x01, // fieldFlags = all nine fields
+    0x02, // cacheId = 2
+    0x00, // colorTableIndex = 0
+    0x40, 0x00, // nLeftRect = 64
+    0x80, 0x00, // nTopRect = 128
+    0x40, 0x00, // nWidth = 64
+    0x20, 0x00, // nHeight = 32
+    0xcc, // bRop = SRCCOPY
+    0x00, 0x00, // nXSrc = 0
+    0x00, 0x00, // nYSrc = 0
+    0x39, 0x05, // cacheIndex = 1337
+];
+
+static MEM_BLT: LazyLock<MemBltOrder> = LazyLock::new(|| MemBltOrder {
+    cache_id: 2,
+    color_table_index: 0,
+    left: 64,
+    top: 128,
+    width: 64,
+    height: 32,
+    rop: MemBltOrder::ROP_SRCCOPY,
+    src_x: 0,
+    src_y: 0,
+    cache_index: 1337,
+});
+
+const CACHE_BITMAP_REV2_BUFFER: [u8; 23] = [
+    0x03, // controlFlags = TS_STANDARD | TS_SECONDARY
+    0x0a, 0x00, // orderLength = 23 - 13
+    0x32, 0x05, // extraFlags: cacheId = 2, CBR2_32BPP, PERSISTENT_KEY_PRESENT | NO_BITMAP_COMPRESSION_HDR
+    0x05, // orderType = TS_CACHE_BITMAP_COMPRESSED_REV2
+    0x01, 0x02, 0x03, 0x04, // key1
+    0x05, 0x06, 0x07, 0x08, // key2
+    0x40, // bitmapWidth = 64
+    0x20, // bitmapHeight = 32
+    0x04, // bitmapLength = 4
+    0x81, 0x00, // cacheIndex = 256
+    0x10, 0x20, 0x30, 0x40, // bitmapDataStream
+];
+
+static CACHE_BITMAP_REV2: LazyLock<CacheBitmapRev2Order<'static>> = LazyLock::new(|| CacheBitmapRev2Order {
+    cache_id: 2,
+    cache_index: 256,
+    key: Some(0x0807_0605_0403_0201),
+    bits_per_pixel: 32,
+    width: 64,
+    height: 32,
+    compressed: true,
+    compressed_data_header: None,
+    bitmap_data: &CACHE_BITMAP_REV2_BUFFER[19..],
+});
+
+#[test]
+fn from_buffer_correctly_parses_mem_blt_order() {
+    assert_eq!(*MEM_BLT, decode(MEM_BLT_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_mem_blt_order() {
+    let mut buffer = vec![0; MEM_BLT.size()];
+    encode(&*MEM_BLT, buffer.as_mut_slice()).unwrap();
+
+    assert_eq!(MEM_BLT_BUFFER.as_ref(), buffer.as_slice());
+}
+
+#[test]
+fn from_buffer_correctly_parses_cache_bitmap_rev2_order() {
+    assert_eq!(*CACHE_BITMAP_REV2, decode(CACHE_BITMAP_REV2_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_cache_bitmap_rev2_order() {
+    let mut buffer = vec![0; CACHE_BITMAP_REV2.size()];
+    encode(&*CACHE_BITMAP_REV2, buffer.as_mut_slice()).unwrap();
+
+    assert_eq!(CACHE_BITMAP_REV2_BUFFER.as_ref(), buffer.as_slice());
+}
+
+#[test]
+fn cache_bitmap_rev2_order_variable_length_fields_round_trip() {
+    let data = vec![0xAB; 0x4100];
+    let order = CacheBitmapRev2Order {
+        cache_id: 4,
+        cache_index: CacheBitmapRev2Order::MAX_CACHE_INDEX,
+        key: None,
+        bits_per_pixel: 16,
+        width: 0x100,
+        height: 0x100,
+        compressed: true,
+        compressed_data_header: Some(CompressedDataHeader {
+            main_body_size: 0x4100,
+            scan_width: 0x100,
+            uncompressed_size: 0x2000,
+        }),
+        bitmap_data: &data,
+    };
+
+    let mut buffer = vec![0; order.size()];
+    encode(&order, buffer.as_mut_slice()).unwrap();
+
+    assert_eq!(order, decode(buffer.as_slice()).unwrap());
+}
+
+#[test]
+fn orders_update_data_round_trip() {
+    let orders = OrdersUpdateData {
+        orders: vec![
+            DrawingOrder::CacheBitmapRev2(CACHE_BITMAP_REV2.clone()),
+            DrawingOrder::MemBlt(MEM_BLT.clone()),
+        ],
+    };
+
+    let mut buffer = vec![0; orders.size()];
+    encode(&orders, buffer.as_mut_slice()).unwrap();
+
+    assert_eq!(buffer[..2], [0x02, 0x00]);
+    assert_eq!(orders, decode(buffer.as_slice()).unwrap());
+}
+
+#[test]
+fn mem_blt_order_with_delta_coordinates_is_rejected() {
+    let mut buffer = MEM_BLT_BUFFER;
+    buffer[0] |= TS_DELTA_COORDINATES;
+
+    assert!(decode::<MemBltOrder>(buffer.as_ref()).is_err());
+}
+
+const SCR_BLT_BUFFER: [u8; 16] = [
+    0x09, // controlFlags = TS_STANDARD | TS_TYPE_CHANGE
+    0x02, // orderType = TS_ENC_SCRBLT_ORDER
+    0x7f, // fieldFlags = all seven fields
+    0x00, 0x00, // nLeftRect = 0
+    0x10, 0x00, // nTopRect = 16
+    0x00, 0x04, // nWidth = 1024
+    0xf0, 0x02, // nHeight = 752
+    0xcc, // bRop = SRCCOPY
+    0x00, 0x00, // nXSrc = 0
+    0x20, 0x00, // nYSrc = 32
+];
+
+static SCR_BLT: LazyLock<ScrBltOrder> = LazyLock::new(|| ScrBltOrder {
+    left: 0,
+    top: 16,
+    width: 1024,
+    height: 752,
+    rop: ScrBltOrder::ROP_SRCCOPY,
+    src_x: 0,
+    src_y: 32,
+});
+
+const OPAQUE_RECT_BUFFER: [u8; 14] = [
+    0x09, // controlFlags = TS_STANDARD | TS_TYPE_CHANGE
+    0x0a, // orderType = TS_ENC_OPAQUERECT_ORDER
+    0x7f, // fieldFlags = all seven fields
+    0x0a, 0x00, // nLeftRect = 10
+    0x14, 0x00, // nTopRect = 20
+    0x64, 0x00, // nWidth = 100
+    0x32, 0x00, // nHeight = 50
+    0x11, 0x22, 0x33, // RedOrPaletteIndex, Green, Blue
+];
+
+static OPAQUE_RECT: LazyLock<OpaqueRectOrder> = LazyLock::new(|| OpaqueRectOrder {
+    left: 10,
+    top: 20,
+    width: 100,
+    height: 50,
+    color: Color {
+        red: 0x11,
+        green: 0x22,
+        blue: 0x33,
+    },
+});
+
+const LINE_TO_BUFFER: [u8; 23] = [
+    0x09, // controlFlags = TS_STANDARD | TS_TYPE_CHANGE
+    0x09, // orderType = TS_ENC_LINETO_ORDER
+    0xff, 0x03, // fieldFlags = all ten fields
+    0x01, 0x00, // backMode = TRANSPARENT
+    0x05, 0x00, // nXStart = 5
+    0x06, 0x00, // nYStart = 6
+    0xc8, 0x00, // nXEnd = 200
+    0xff, 0xff, // nYEnd = -1
+    0x00, 0x00, 0x00, // backColor
+    0x0d, // bRop2 = R2_COPYPEN
+    0x00, // penStyle = PS_SOLID
+    0x01, // penWidth = 1
+    0xff, 0x00, 0x80, // penColor
+];
+
+static LINE_TO: LazyLock<LineToOrder> = LazyLock::new(|| LineToOrder {
+    back_mode: LineToOrder::TRANSPARENT,
+    start_x: 5,
+    start_y: 6,
+    end_x: 200,
+    end_y: -1,
+    back_color: Color::default(),
+    rop2: LineToOrder::ROP2_COPYPEN,
+    pen_style: LineToOrder::PS_SOLID,
+    pen_width: 1,
+    pen_color: Color {
+        red: 0xff,
+        green: 0x00,
+        blue: 0x80,
+    },
+});
+
+#[test]
+fn from_buffer_correctly_parses_primary_orders() {
+    assert_eq!(*SCR_BLT, decode(SCR_BLT_BUFFER.as_ref()).unwrap());
+    assert_eq!(*OPAQUE_RECT, decode(OPAQUE_RECT_BUFFER.as_ref()).unwrap());
+    assert_eq!(*LINE_TO, decode(LINE_TO_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_primary_orders() {
+    assert_eq!(SCR_BLT_BUFFER.as_ref(), encode_vec(&*SCR_BLT).unwrap().as_slice());
+    assert_eq!(
+        OPAQUE_RECT_BUFFER.as_ref(),
+        encode_vec(&*OPAQUE_RECT).unwrap().as_slice()
+    );
+    assert_eq!(LINE_TO_BUFFER.as_ref(), encode_vec(&*LINE_TO).unwrap().as_slice());
+}
+
+#[test]
+fn drawing_order_dispatches_on_order_type() {
+    let orders = OrdersUpdateData {
+        orders: vec![
+            DrawingOrder::ScrBlt(SCR_BLT.clone()),
+            DrawingOrder::OpaqueRect(OPAQUE_RECT.clone()),
+            DrawingOrder::LineTo(LINE_TO.clone()),
+            DrawingOrder::MemBlt(MEM_BLT.clone()),
+        ],
+    };
+
+    let buffer = encode_vec(&orders).unwrap();
+
+    assert_eq!(orders, decode(buffer.as_slice()).unwrap());
+}
+
+#[test]
+fn primary_order_with_missing_fields_is_rejected() {
+    let mut buffer = OPAQUE_RECT_BUFFER;
+    buffer[2] = 0x0f;
+
+    assert!(decode::<OpaqueRectOrder>(buffer.as_ref()).is_err());
+}
+
+const DELETED_WINDOW_BUFFER: [u8; 11] = [
+    0x2e, // controlFlags = TS_ALTSEC_WINDOW << 2 | TS_SECONDARY
+    0x0b, 0x00, // orderSize = 11
+    0x00, 0x00, 0x00, 0x21, // fieldsPresentFlags = WINDOW_ORDER_TYPE_WINDOW | WINDOW_ORDER_STATE_DELETED
+    0x39, 0x05, 0x00, 0x00, // windowId = 1337
+];
+
+const NEW_WINDOW_BUFFER: [u8; 42] = [
+    0x2e, // controlFlags = TS_ALTSEC_WINDOW << 2 | TS_SECONDARY
+    0x2a, 0x00, // orderSize = 42
+    0x1c, 0x0c, 0x00, 0x11, // fieldsPresentFlags = TYPE_WINDOW | STATE_NEW | TITLE | STYLE | SHOW | WND_*
+    0x39, 0x05, 0x00, 0x00, // windowId = 1337
+    0x00, 0x00, 0xcf, 0x14, // style = WS_OVERLAPPEDWINDOW | WS_VISIBLE
+    0x00, 0x01, 0x00, 0x00, // extendedStyle = WS_EX_WINDOWEDGE
+    0x05, // showState = SW_SHOW
+    0x04, 0x00, b'a', 0x00, b'b', 0x00, // titleInfo = "ab"
+    0x0a, 0x00, 0x00, 0x00, // windowOffsetX = 10
+    0xec, 0xff, 0xff, 0xff, // windowOffsetY = -20
+    0x20, 0x03, 0x00, 0x00, // windowWidth = 800
+    0x58, 0x02, 0x00, 0x00, // windowHeight = 600
+];
+
+static NEW_WINDOW: LazyLock<WindowOrder> = LazyLock::new(|| {
+    WindowOrder::Window(WindowInfoOrder {
+        window_id: 1337,
+        new: true,
+        style: Some(WindowStyle {
+            style: 0x14cf_0000,
+            extended_style: 0x0000_0100,
+        }),
+        show_state: Some(5),
+        title: Some("ab".to_owned()),
+        window_offset: Some((10, -20)),
+        window_size: Some((800, 600)),
+        ..WindowInfoOrder::default()
+    })
+});
+
+#[test]
+fn from_buffer_correctly_parses_window_orders() {
+    assert_eq!(
+        WindowOrder::Deleted { window_id: 1337 },
+        decode(DELETED_WINDOW_BUFFER.as_ref()).unwrap()
+    );
+    assert_eq!(*NEW_WINDOW, decode(NEW_WINDOW_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_window_orders() {
+    assert_eq!(
+        DELETED_WINDOW_BUFFER.as_ref(),
+        encode_vec(&WindowOrder::Deleted { window_id: 1337 })
+            .unwrap()
+            .as_slice()
+    );
+    assert_eq!(NEW_WINDOW_BUFFER.as_ref(), encode_vec(&*NEW_WINDOW).unwrap().as_slice());
+}
+
+#[test]
+fn window_orders_round_trip() {
+    let orders = OrdersUpdateData {
+        orders: vec![
+            DrawingOrder::Window(WindowOrder::Desktop(DesktopOrder::Monitored(MonitoredDesktop {
+                hooked: true,
+                arc_began: true,
+                ..MonitoredDesktop::default()
+            }))),
+            DrawingOrder::Window(WindowOrder::Window(WindowInfoOrder {
+                window_id: 1,
+                new: true,
+                owner_window_id: Some(0),
+                title: Some("Notepad".to_owned()),
+                client_offset: Some((-4, 30)),
+                client_area_size: Some((640, 480)),
+                resize_margin_x: Some((8, 8)),
+                resize_margin_y: Some((8, 8)),
+                root_parent: Some(0),
+                window_client_delta: Some((4, 30)),
+                window_rects: Some(vec![ExclusiveRectangle {
+                    left: 0,
+                    top: 0,
+                    right: 648,
+                    bottom: 518,
+                }]),
+                visible_offset: Some((0, 0)),
+                visibility_rects: Some(Vec::new()),
+                taskbar_button: Some(0),
+                ..WindowInfoOrder::default()
+            })),
+            DrawingOrder::Window(WindowOrder::Icon(WindowIconOrder {
+                window_id: 1,
+                big: true,
+                icon: IconInfo {
+                    cache_entry: 0,
+                    cache_id: 1,
+                    bits_per_pixel: 8,
+                    width: 2,
+                    height: 2,
+                    color_table: vec![0xff; 8],
+                    bits_mask: vec![0; 8],
+                    bits_color: vec![1; 8],
+                },
+            })),
+            DrawingOrder::Window(WindowOrder::CachedIcon(CachedIconOrder {
+                window_id: 1,
+                big: false,
+                cache_entry: 0,
+                cache_id: 1,
+            })),
+            DrawingOrder::Window(WindowOrder::Desktop(DesktopOrder::Monitored(MonitoredDesktop {
+                arc_completed: true,
+                active_window_id: Some(1),
+                z_order: Some(vec![1, 2]),
+                ..MonitoredDesktop::default()
+            }))),
+            DrawingOrder::MemBlt(MEM_BLT.clone()),
+        ],
+    };
+
+    let buffer = encode_vec(&orders).unwrap();
+
+    assert_eq!(orders, decode(buffer.as_slice()).unwrap());
+}
+
+#[test]
+fn unsupported_window_orders_are_skipped() {
+    let notify_icon = [
+        0x2e, // controlFlags = TS_ALTSEC_WINDOW << 2 | TS_SECONDARY
+        0x0f, 0x00, // orderSize = 15
+        0x00, 0x00, 0x00, 0x22, // fieldsPresentFlags = WINDOW_ORDER_TYPE_NOTIFY | WINDOW_ORDER_STATE_DELETED
+        0x01, 0x00, 0x00, 0x00, // windowId = 1
+        0x02, 0x00, 0x00, 0x00, // notifyIconId = 2
+    ];
+
+    assert_eq!(
+        WindowOrder::Other {
+            fields_present_flags: 0x2200_0000,
+            data: notify_icon[7..].to_vec(),
+        },
+        decode(notify_icon.as_ref()).unwrap()
+    );
+}
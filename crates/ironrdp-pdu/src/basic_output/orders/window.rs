@@ -0,0 +1,766 @@
+//! Windowing alternate secondary drawing orders
+//!
+//! The server sends them to describe the windows of a RemoteApp session, see [MS-RDPERP] 2.2.1.3.
+
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+
+use super::TS_SECONDARY;
+use crate::geometry::ExclusiveRectangle;
+use crate::utils::{self, CharacterSet};
+
+/// TS_ALTSEC_WINDOW, the alternate secondary order type of windowing orders
+pub(super) const TS_ALTSEC_WINDOW: u8 = 0x0B;
+
+// fieldsPresentFlags of TS_WINDOW_ORDER_HEADER
+const WINDOW_ORDER_TYPE_WINDOW: u32 = 0x0100_0000;
+const WINDOW_ORDER_TYPE_DESKTOP: u32 = 0x0400_0000;
+const WINDOW_ORDER_STATE_NEW: u32 = 0x1000_0000;
+const WINDOW_ORDER_STATE_DELETED: u32 = 0x2000_0000;
+const WINDOW_ORDER_ICON: u32 = 0x4000_0000;
+const WINDOW_ORDER_CACHED_ICON: u32 = 0x8000_0000;
+
+const WINDOW_ORDER_FIELD_APPBAR_EDGE: u32 = 0x0000_0001;
+const WINDOW_ORDER_FIELD_OWNER: u32 = 0x0000_0002;
+const WINDOW_ORDER_FIELD_TITLE: u32 = 0x0000_0004;
+const WINDOW_ORDER_FIELD_STYLE: u32 = 0x0000_0008;
+const WINDOW_ORDER_FIELD_SHOW: u32 = 0x0000_0010;
+const WINDOW_ORDER_FIELD_APPBAR_STATE: u32 = 0x0000_0040;
+const WINDOW_ORDER_FIELD_RESIZE_MARGIN_X: u32 = 0x0000_0080;
+const WINDOW_ORDER_FIELD_WND_RECTS: u32 = 0x0000_0100;
+const WINDOW_ORDER_FIELD_VISIBILITY: u32 = 0x0000_0200;
+const WINDOW_ORDER_FIELD_WND_SIZE: u32 = 0x0000_0400;
+const WINDOW_ORDER_FIELD_WND_OFFSET: u32 = 0x0000_0800;
+const WINDOW_ORDER_FIELD_VIS_OFFSET: u32 = 0x0000_1000;
+const WINDOW_ORDER_FIELD_ICON_BIG: u32 = 0x0000_2000;
+const WINDOW_ORDER_FIELD_CLIENT_AREA_OFFSET: u32 = 0x0000_4000;
+const WINDOW_ORDER_FIELD_WND_CLIENT_DELTA: u32 = 0x0000_8000;
+const WINDOW_ORDER_FIELD_CLIENT_AREA_SIZE: u32 = 0x0001_0000;
+const WINDOW_ORDER_FIELD_RP_CONTENT: u32 = 0x0002_0000;
+const WINDOW_ORDER_FIELD_ROOT_PARENT: u32 = 0x0004_0000;
+const WINDOW_ORDER_FIELD_ENFORCE_SERVER_ZORDER: u32 = 0x0008_0000;
+const WINDOW_ORDER_FIELD_OVERLAY_DESCRIPTION: u32 = 0x0040_0000;
+const WINDOW_ORDER_FIELD_TASKBAR_BUTTON: u32 = 0x0080_0000;
+const WINDOW_ORDER_FIELD_RESIZE_MARGIN_Y: u32 = 0x0800_0000;
+
+const WINDOW_ORDER_FIELD_DESKTOP_NONE: u32 = 0x0000_0001;
+const WINDOW_ORDER_FIELD_DESKTOP_HOOKED: u32 = 0x0000_0002;
+const WINDOW_ORDER_FIELD_DESKTOP_ARC_COMPLETED: u32 = 0x0000_0004;
+const WINDOW_ORDER_FIELD_DESKTOP_ARC_BEGAN: u32 = 0x0000_0008;
+const WINDOW_ORDER_FIELD_DESKTOP_ZORDER: u32 = 0x0000_0010;
+const WINDOW_ORDER_FIELD_DESKTOP_ACTIVE_WND: u32 = 0x0000_0020;
+
+/// A windowing order, prefixed with TS_WINDOW_ORDER_HEADER
+///
+/// Notification icon orders are not interpreted, they are kept as [`WindowOrder::Other`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowOrder {
+    /// TS_WINDOW_INFO, a new window or the changed properties of an existing one
+    Window(WindowInfoOrder),
+    /// TS_WINDOW_ICON
+    Icon(WindowIconOrder),
+    /// TS_CACHED_ICON
+    CachedIcon(CachedIconOrder),
+    /// A deleted window
+    Deleted { window_id: u32 },
+    /// TS_DESKTOP_ORDER
+    Desktop(DesktopOrder),
+    /// Any other windowing order, with its fields following the header
+    Other { fields_present_flags: u32, data: Vec<u8> },
+}
+
+impl WindowOrder {
+    const NAME: &'static str = "TS_WINDOW_ORDER";
+
+    const FIXED_PART_SIZE: usize = 1 /* controlFlags */ + 2 /* orderSize */ + 4 /* fieldsPresentFlags */;
+
+    fn fields_present_flags(&self) -> u32 {
+        match self {
+            Self::Window(order) => order.fields_present_flags(),
+            Self::Icon(order) => {
+                let big = if order.big { WINDOW_ORDER_FIELD_ICON_BIG } else { 0 };
+                WINDOW_ORDER_TYPE_WINDOW | WINDOW_ORDER_ICON | big
+            }
+            Self::CachedIcon(order) => {
+                let big = if order.big { WINDOW_ORDER_FIELD_ICON_BIG } else { 0 };
+                WINDOW_ORDER_TYPE_WINDOW | WINDOW_ORDER_CACHED_ICON | big
+            }
+            Self::Deleted { .. } => WINDOW_ORDER_TYPE_WINDOW | WINDOW_ORDER_STATE_DELETED,
+            Self::Desktop(order) => order.fields_present_flags(),
+            Self::Other {
+                fields_present_flags, ..
+            } => *fields_present_flags,
+        }
+    }
+
+    fn body_size(&self) -> usize {
+        match self {
+            Self::Window(order) => order.body_size(),
+            Self::Icon(order) => 4 /* WindowId */ + order.icon.size(),
+            // WindowId, CacheEntry and CacheId
+            Self::CachedIcon(_) => 4 + 2 + 1,
+            // WindowId
+            Self::Deleted { .. } => 4,
+            Self::Desktop(order) => order.body_size(),
+            Self::Other { data, .. } => data.len(),
+        }
+    }
+}
+
+impl Encode for WindowOrder {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8((TS_ALTSEC_WINDOW << 2) | TS_SECONDARY);
+        dst.write_u16(cast_length!("orderSize", self.size())?);
+        dst.write_u32(self.fields_present_flags());
+
+        match self {
+            Self::Window(order) => order.encode_body(dst),
+            Self::Icon(order) => {
+                dst.write_u32(order.window_id);
+                order.icon.encode(dst)
+            }
+            Self::CachedIcon(order) => {
+                dst.write_u32(order.window_id);
+                dst.write_u16(order.cache_entry);
+                dst.write_u8(order.cache_id);
+                Ok(())
+            }
+            Self::Deleted { window_id } => {
+                dst.write_u32(*window_id);
+                Ok(())
+            }
+            Self::Desktop(order) => order.encode_body(dst),
+            Self::Other { data, .. } => {
+                dst.write_slice(data);
+                Ok(())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.body_size()
+    }
+}
+
+impl<'de> Decode<'de> for WindowOrder {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        if src.read_u8() != (TS_ALTSEC_WINDOW << 2) | TS_SECONDARY {
+            return Err(invalid_field_err!("controlFlags", "not a windowing order"));
+        }
+
+        let order_size = usize::from(src.read_u16());
+        let fields_present_flags = src.read_u32();
+
+        let body_size = order_size
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("orderSize", "order size is too small"))?;
+        ensure_size!(in: src, size: body_size);
+        let mut body = ReadCursor::new(src.read_slice(body_size));
+
+        let order = if fields_present_flags & WINDOW_ORDER_TYPE_WINDOW != 0 {
+            if fields_present_flags & WINDOW_ORDER_STATE_DELETED != 0 {
+                ensure_size!(in: body, size: 4);
+                Self::Deleted {
+                    window_id: body.read_u32(),
+                }
+            } else if fields_present_flags & WINDOW_ORDER_ICON != 0 {
+                ensure_size!(in: body, size: 4);
+                Self::Icon(WindowIconOrder {
+                    window_id: body.read_u32(),
+                    big: fields_present_flags & WINDOW_ORDER_FIELD_ICON_BIG != 0,
+                    icon: IconInfo::decode(&mut body)?,
+                })
+            } else if fields_present_flags & WINDOW_ORDER_CACHED_ICON != 0 {
+                ensure_size!(in: body, size: 4 + 2 + 1);
+                Self::CachedIcon(CachedIconOrder {
+                    window_id: body.read_u32(),
+                    big: fields_present_flags & WINDOW_ORDER_FIELD_ICON_BIG != 0,
+                    cache_entry: body.read_u16(),
+                    cache_id: body.read_u8(),
+                })
+            } else {
+                Self::Window(WindowInfoOrder::decode_body(fields_present_flags, &mut body)?)
+            }
+        } else if fields_present_flags & WINDOW_ORDER_TYPE_DESKTOP != 0 {
+            Self::Desktop(DesktopOrder::decode_body(fields_present_flags, &mut body)?)
+        } else {
+            Self::Other {
+                fields_present_flags,
+                data: body.remaining().to_vec(),
+            }
+        };
+
+        Ok(order)
+    }
+}
+
+/// Style of a window, as WS_* and WS_EX_* flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowStyle {
+    pub style: u32,
+    pub extended_style: u32,
+}
+
+/// TS_WINDOW_INFO
+///
+/// For an existing window, only the properties which changed are present.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowInfoOrder {
+    pub window_id: u32,
+    /// Whether the window was just created (WINDOW_ORDER_STATE_NEW)
+    pub new: bool,
+    pub owner_window_id: Option<u32>,
+    pub style: Option<WindowStyle>,
+    /// SW_* value
+    pub show_state: Option<u8>,
+    pub title: Option<String>,
+    /// Client area position, in screen coordinates
+    pub client_offset: Option<(i32, i32)>,
+    pub client_area_size: Option<(u32, u32)>,
+    /// Left and right resize margins
+    pub resize_margin_x: Option<(u32, u32)>,
+    /// Top and bottom resize margins
+    pub resize_margin_y: Option<(u32, u32)>,
+    pub rp_content: Option<u8>,
+    pub root_parent: Option<u32>,
+    /// Window position, in screen coordinates
+    pub window_offset: Option<(i32, i32)>,
+    pub window_client_delta: Option<(i32, i32)>,
+    pub window_size: Option<(u32, u32)>,
+    /// Window shape, relative to the window position
+    pub window_rects: Option<Vec<ExclusiveRectangle>>,
+    pub visible_offset: Option<(i32, i32)>,
+    /// Visible region, relative to the visible offset
+    pub visibility_rects: Option<Vec<ExclusiveRectangle>>,
+    pub overlay_description: Option<String>,
+    pub taskbar_button: Option<u8>,
+    pub enforce_server_z_order: Option<u8>,
+    pub app_bar_state: Option<u8>,
+    pub app_bar_edge: Option<u8>,
+}
+
+impl WindowInfoOrder {
+    fn fields_present_flags(&self) -> u32 {
+        let fields = [
+            (self.owner_window_id.is_some(), WINDOW_ORDER_FIELD_OWNER),
+            (self.style.is_some(), WINDOW_ORDER_FIELD_STYLE),
+            (self.show_state.is_some(), WINDOW_ORDER_FIELD_SHOW),
+            (self.title.is_some(), WINDOW_ORDER_FIELD_TITLE),
+            (self.client_offset.is_some(), WINDOW_ORDER_FIELD_CLIENT_AREA_OFFSET),
+            (self.client_area_size.is_some(), WINDOW_ORDER_FIELD_CLIENT_AREA_SIZE),
+            (self.resize_margin_x.is_some(), WINDOW_ORDER_FIELD_RESIZE_MARGIN_X),
+            (self.resize_margin_y.is_some(), WINDOW_ORDER_FIELD_RESIZE_MARGIN_Y),
+            (self.rp_content.is_some(), WINDOW_ORDER_FIELD_RP_CONTENT),
+            (self.root_parent.is_some(), WINDOW_ORDER_FIELD_ROOT_PARENT),
+            (self.window_offset.is_some(), WINDOW_ORDER_FIELD_WND_OFFSET),
+            (self.window_client_delta.is_some(), WINDOW_ORDER_FIELD_WND_CLIENT_DELTA),
+            (self.window_size.is_some(), WINDOW_ORDER_FIELD_WND_SIZE),
+            (self.window_rects.is_some(), WINDOW_ORDER_FIELD_WND_RECTS),
+            (self.visible_offset.is_some(), WINDOW_ORDER_FIELD_VIS_OFFSET),
+            (self.visibility_rects.is_some(), WINDOW_ORDER_FIELD_VISIBILITY),
+            (
+                self.overlay_description.is_some(),
+                WINDOW_ORDER_FIELD_OVERLAY_DESCRIPTION,
+            ),
+            (self.taskbar_button.is_some(), WINDOW_ORDER_FIELD_TASKBAR_BUTTON),
+            (
+                self.enforce_server_z_order.is_some(),
+                WINDOW_ORDER_FIELD_ENFORCE_SERVER_ZORDER,
+            ),
+            (self.app_bar_state.is_some(), WINDOW_ORDER_FIELD_APPBAR_STATE),
+            (self.app_bar_edge.is_some(), WINDOW_ORDER_FIELD_APPBAR_EDGE),
+        ];
+
+        let state = if self.new { WINDOW_ORDER_STATE_NEW } else { 0 };
+
+        fields
+            .into_iter()
+            .filter(|(present, _)| *present)
+            .fold(WINDOW_ORDER_TYPE_WINDOW | state, |flags, (_, field)| flags | field)
+    }
+
+    fn body_size(&self) -> usize {
+        let rects_size = |rects: &Vec<ExclusiveRectangle>| 2 + rects.len() * ExclusiveRectangle::ENCODED_SIZE;
+
+        4 /* WindowId */
+            + self.owner_window_id.map_or(0, |_| 4)
+            + self.style.map_or(0, |_| 8)
+            + self.show_state.map_or(0, |_| 1)
+            + self.title.as_deref().map_or(0, unicode_string_size)
+            + self.client_offset.map_or(0, |_| 8)
+            + self.client_area_size.map_or(0, |_| 8)
+            + self.resize_margin_x.map_or(0, |_| 8)
+            + self.resize_margin_y.map_or(0, |_| 8)
+            + self.rp_content.map_or(0, |_| 1)
+            + self.root_parent.map_or(0, |_| 4)
+            + self.window_offset.map_or(0, |_| 8)
+            + self.window_client_delta.map_or(0, |_| 8)
+            + self.window_size.map_or(0, |_| 8)
+            + self.window_rects.as_ref().map_or(0, rects_size)
+            + self.visible_offset.map_or(0, |_| 8)
+            + self.visibility_rects.as_ref().map_or(0, rects_size)
+            + self.overlay_description.as_deref().map_or(0, unicode_string_size)
+            + self.taskbar_button.map_or(0, |_| 1)
+            + self.enforce_server_z_order.map_or(0, |_| 1)
+            + self.app_bar_state.map_or(0, |_| 1)
+            + self.app_bar_edge.map_or(0, |_| 1)
+    }
+
+    fn encode_body(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        dst.write_u32(self.window_id);
+
+        if let Some(owner_window_id) = self.owner_window_id {
+            dst.write_u32(owner_window_id);
+        }
+        if let Some(style) = self.style {
+            dst.write_u32(style.style);
+            dst.write_u32(style.extended_style);
+        }
+        if let Some(show_state) = self.show_state {
+            dst.write_u8(show_state);
+        }
+        if let Some(title) = &self.title {
+            write_unicode_string("TitleInfo", title, dst)?;
+        }
+        if let Some((x, y)) = self.client_offset {
+            dst.write_i32(x);
+            dst.write_i32(y);
+        }
+        if let Some((width, height)) = self.client_area_size {
+            dst.write_u32(width);
+            dst.write_u32(height);
+        }
+        if let Some((left, right)) = self.resize_margin_x {
+            dst.write_u32(left);
+            dst.write_u32(right);
+        }
+        if let Some((top, bottom)) = self.resize_margin_y {
+            dst.write_u32(top);
+            dst.write_u32(bottom);
+        }
+        if let Some(rp_content) = self.rp_content {
+            dst.write_u8(rp_content);
+        }
+        if let Some(root_parent) = self.root_parent {
+            dst.write_u32(root_parent);
+        }
+        if let Some((x, y)) = self.window_offset {
+            dst.write_i32(x);
+            dst.write_i32(y);
+        }
+        if let Some((x, y)) = self.window_client_delta {
+            dst.write_i32(x);
+            dst.write_i32(y);
+        }
+        if let Some((width, height)) = self.window_size {
+            dst.write_u32(width);
+            dst.write_u32(height);
+        }
+        if let Some(rects) = &self.window_rects {
+            write_rectangles("NumWindowRects", rects, dst)?;
+        }
+        if let Some((x, y)) = self.visible_offset {
+            dst.write_i32(x);
+            dst.write_i32(y);
+        }
+        if let Some(rects) = &self.visibility_rects {
+            write_rectangles("NumVisibilityRects", rects, dst)?;
+        }
+        if let Some(description) = &self.overlay_description {
+            write_unicode_string("OverlayDescription", description, dst)?;
+        }
+        if let Some(taskbar_button) = self.taskbar_button {
+            dst.write_u8(taskbar_button);
+        }
+        if let Some(enforce_server_z_order) = self.enforce_server_z_order {
+            dst.write_u8(enforce_server_z_order);
+        }
+        if let Some(app_bar_state) = self.app_bar_state {
+            dst.write_u8(app_bar_state);
+        }
+        if let Some(app_bar_edge) = self.app_bar_edge {
+            dst.write_u8(app_bar_edge);
+        }
+
+        Ok(())
+    }
+
+    fn decode_body(fields: u32, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        let present = |field: u32| fields & field != 0;
+
+        ensure_size!(in: src, size: 4);
+        let mut order = Self {
+            window_id: src.read_u32(),
+            new: present(WINDOW_ORDER_STATE_NEW),
+            ..Self::default()
+        };
+
+        if present(WINDOW_ORDER_FIELD_OWNER) {
+            ensure_size!(in: src, size: 4);
+            order.owner_window_id = Some(src.read_u32());
+        }
+        if present(WINDOW_ORDER_FIELD_STYLE) {
+            ensure_size!(in: src, size: 8);
+            order.style = Some(WindowStyle {
+                style: src.read_u32(),
+                extended_style: src.read_u32(),
+            });
+        }
+        if present(WINDOW_ORDER_FIELD_SHOW) {
+            ensure_size!(in: src, size: 1);
+            order.show_state = Some(src.read_u8());
+        }
+        if present(WINDOW_ORDER_FIELD_TITLE) {
+            order.title = Some(read_unicode_string(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_CLIENT_AREA_OFFSET) {
+            order.client_offset = Some(read_point(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_CLIENT_AREA_SIZE) {
+            order.client_area_size = Some(read_size(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_RESIZE_MARGIN_X) {
+            order.resize_margin_x = Some(read_size(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_RESIZE_MARGIN_Y) {
+            order.resize_margin_y = Some(read_size(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_RP_CONTENT) {
+            ensure_size!(in: src, size: 1);
+            order.rp_content = Some(src.read_u8());
+        }
+        if present(WINDOW_ORDER_FIELD_ROOT_PARENT) {
+            ensure_size!(in: src, size: 4);
+            order.root_parent = Some(src.read_u32());
+        }
+        if present(WINDOW_ORDER_FIELD_WND_OFFSET) {
+            order.window_offset = Some(read_point(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_WND_CLIENT_DELTA) {
+            order.window_client_delta = Some(read_point(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_WND_SIZE) {
+            order.window_size = Some(read_size(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_WND_RECTS) {
+            order.window_rects = Some(read_rectangles(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_VIS_OFFSET) {
+            order.visible_offset = Some(read_point(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_VISIBILITY) {
+            order.visibility_rects = Some(read_rectangles(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_OVERLAY_DESCRIPTION) {
+            order.overlay_description = Some(read_unicode_string(src)?);
+        }
+        if present(WINDOW_ORDER_FIELD_TASKBAR_BUTTON) {
+            ensure_size!(in: src, size: 1);
+            order.taskbar_button = Some(src.read_u8());
+        }
+        if present(WINDOW_ORDER_FIELD_ENFORCE_SERVER_ZORDER) {
+            ensure_size!(in: src, size: 1);
+            order.enforce_server_z_order = Some(src.read_u8());
+        }
+        if present(WINDOW_ORDER_FIELD_APPBAR_STATE) {
+            ensure_size!(in: src, size: 1);
+            order.app_bar_state = Some(src.read_u8());
+        }
+        if present(WINDOW_ORDER_FIELD_APPBAR_EDGE) {
+            ensure_size!(in: src, size: 1);
+            order.app_bar_edge = Some(src.read_u8());
+        }
+
+        Ok(order)
+    }
+}
+
+/// TS_WINDOW_ICON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowIconOrder {
+    pub window_id: u32,
+    /// Whether this is the large icon of the window (WINDOW_ORDER_FIELD_ICON_BIG)
+    pub big: bool,
+    pub icon: IconInfo,
+}
+
+/// TS_CACHED_ICON, an icon previously sent with a [`WindowIconOrder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedIconOrder {
+    pub window_id: u32,
+    pub big: bool,
+    pub cache_entry: u16,
+    pub cache_id: u8,
+}
+
+/// TS_ICON_INFO
+///
+/// The icon is a device-independent bitmap, with its rows stored bottom-up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconInfo {
+    /// Cache entry to store the icon in, 0xFFFF when the icon is not cached
+    pub cache_entry: u16,
+    /// Cache to store the icon in, 0xFF when the icon is not cached
+    pub cache_id: u8,
+    pub bits_per_pixel: u8,
+    pub width: u16,
+    pub height: u16,
+    /// Only present at 1, 4 and 8 bpp
+    pub color_table: Vec<u8>,
+    /// 1 bpp AND mask
+    pub bits_mask: Vec<u8>,
+    pub bits_color: Vec<u8>,
+}
+
+impl IconInfo {
+    const NAME: &'static str = "TS_ICON_INFO";
+
+    const FIXED_PART_SIZE: usize = 2 /* CacheEntry */ + 1 /* CacheId */ + 1 /* Bpp */ + 2 /* Width */ + 2 /* Height */
+        + 2 /* CbBitsMask */ + 2 /* CbBitsColor */;
+
+    fn has_color_table(&self) -> bool {
+        matches!(self.bits_per_pixel, 1 | 4 | 8)
+    }
+}
+
+impl Encode for IconInfo {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        if !self.has_color_table() && !self.color_table.is_empty() {
+            return Err(invalid_field_err!("ColorTable", "color table above 8 bpp"));
+        }
+
+        dst.write_u16(self.cache_entry);
+        dst.write_u8(self.cache_id);
+        dst.write_u8(self.bits_per_pixel);
+        dst.write_u16(self.width);
+        dst.write_u16(self.height);
+        if self.has_color_table() {
+            dst.write_u16(cast_length!("CbColorTable", self.color_table.len())?);
+        }
+        dst.write_u16(cast_length!("CbBitsMask", self.bits_mask.len())?);
+        dst.write_u16(cast_length!("CbBitsColor", self.bits_color.len())?);
+        dst.write_slice(&self.bits_mask);
+        dst.write_slice(&self.color_table);
+        dst.write_slice(&self.bits_color);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        let color_table_size = if self.has_color_table() { 2 } else { 0 };
+
+        Self::FIXED_PART_SIZE + color_table_size + self.color_table.len() + self.bits_mask.len() + self.bits_color.len()
+    }
+}
+
+impl<'de> Decode<'de> for IconInfo {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let cache_entry = src.read_u16();
+        let cache_id = src.read_u8();
+        let bits_per_pixel = src.read_u8();
+        let width = src.read_u16();
+        let height = src.read_u16();
+
+        let color_table_size = if matches!(bits_per_pixel, 1 | 4 | 8) {
+            ensure_size!(in: src, size: 2 + 2 + 2);
+            usize::from(src.read_u16())
+        } else {
+            0
+        };
+        let bits_mask_size = usize::from(src.read_u16());
+        let bits_color_size = usize::from(src.read_u16());
+
+        ensure_size!(in: src, size: bits_mask_size + color_table_size + bits_color_size);
+        let bits_mask = src.read_slice(bits_mask_size).to_vec();
+        let color_table = src.read_slice(color_table_size).to_vec();
+        let bits_color = src.read_slice(bits_color_size).to_vec();
+
+        Ok(Self {
+            cache_entry,
+            cache_id,
+            bits_per_pixel,
+            width,
+            height,
+            color_table,
+            bits_mask,
+            bits_color,
+        })
+    }
+}
+
+/// TS_DESKTOP_ORDER
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DesktopOrder {
+    /// The server stopped monitoring the desktop (WINDOW_ORDER_FIELD_DESKTOP_NONE)
+    NonMonitored,
+    /// TS_ACTIVELY_MONITORED_DESKTOP
+    Monitored(MonitoredDesktop),
+}
+
+/// TS_ACTIVELY_MONITORED_DESKTOP
+///
+/// The windows of the desktop are synchronized by sending all of them between two desktop orders,
+/// the first one with `arc_began`, the last one with `arc_completed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MonitoredDesktop {
+    /// The server hooked the desktop, and starts sending windowing orders
+    pub hooked: bool,
+    /// Start of the windows synchronization
+    pub arc_began: bool,
+    /// End of the windows synchronization
+    pub arc_completed: bool,
+    pub active_window_id: Option<u32>,
+    /// Window identifiers, from the top of the Z-order
+    pub z_order: Option<Vec<u32>>,
+}
+
+impl DesktopOrder {
+    fn fields_present_flags(&self) -> u32 {
+        let Self::Monitored(desktop) = self else {
+            return WINDOW_ORDER_TYPE_DESKTOP | WINDOW_ORDER_FIELD_DESKTOP_NONE;
+        };
+
+        let fields = [
+            (desktop.hooked, WINDOW_ORDER_FIELD_DESKTOP_HOOKED),
+            (desktop.arc_began, WINDOW_ORDER_FIELD_DESKTOP_ARC_BEGAN),
+            (desktop.arc_completed, WINDOW_ORDER_FIELD_DESKTOP_ARC_COMPLETED),
+            (
+                desktop.active_window_id.is_some(),
+                WINDOW_ORDER_FIELD_DESKTOP_ACTIVE_WND,
+            ),
+            (desktop.z_order.is_some(), WINDOW_ORDER_FIELD_DESKTOP_ZORDER),
+        ];
+
+        fields
+            .into_iter()
+            .filter(|(present, _)| *present)
+            .fold(WINDOW_ORDER_TYPE_DESKTOP, |flags, (_, field)| flags | field)
+    }
+
+    fn body_size(&self) -> usize {
+        let Self::Monitored(desktop) = self else {
+            return 0;
+        };
+
+        desktop.active_window_id.map_or(0, |_| 4)
+            + desktop
+                .z_order
+                .as_ref()
+                .map_or(0, |windows| 1 /* NumWindowIds */ + windows.len() * 4)
+    }
+
+    fn encode_body(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        let Self::Monitored(desktop) = self else {
+            return Ok(());
+        };
+
+        if let Some(active_window_id) = desktop.active_window_id {
+            dst.write_u32(active_window_id);
+        }
+        if let Some(windows) = &desktop.z_order {
+            dst.write_u8(cast_length!("NumWindowIds", windows.len())?);
+            for window_id in windows {
+                dst.write_u32(*window_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn decode_body(fields: u32, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        if fields & WINDOW_ORDER_FIELD_DESKTOP_NONE != 0 {
+            return Ok(Self::NonMonitored);
+        }
+
+        let present = |field: u32| fields & field != 0;
+
+        let mut desktop = MonitoredDesktop {
+            hooked: present(WINDOW_ORDER_FIELD_DESKTOP_HOOKED),
+            arc_began: present(WINDOW_ORDER_FIELD_DESKTOP_ARC_BEGAN),
+            arc_completed: present(WINDOW_ORDER_FIELD_DESKTOP_ARC_COMPLETED),
+            ..MonitoredDesktop::default()
+        };
+
+        if present(WINDOW_ORDER_FIELD_DESKTOP_ACTIVE_WND) {
+            ensure_size!(in: src, size: 4);
+            desktop.active_window_id = Some(src.read_u32());
+        }
+        if present(WINDOW_ORDER_FIELD_DESKTOP_ZORDER) {
+            ensure_size!(in: src, size: 1);
+            let count = usize::from(src.read_u8());
+            ensure_size!(in: src, size: count * 4);
+            desktop.z_order = Some(core::iter::repeat_with(|| src.read_u32()).take(count).collect());
+        }
+
+        Ok(Self::Monitored(desktop))
+    }
+}
+
+/// Size of an UNICODE_STRING, without null terminator
+fn unicode_string_size(value: &str) -> usize {
+    2 /* CbString */ + utils::encoded_str_len(value, CharacterSet::Unicode, false)
+}
+
+fn write_unicode_string(field: &'static str, value: &str, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+    let size: u16 = cast_length!(field, utils::encoded_str_len(value, CharacterSet::Unicode, false))?;
+
+    dst.write_u16(size);
+    utils::write_string_to_cursor(dst, value, CharacterSet::Unicode, false)
+}
+
+fn read_unicode_string(src: &mut ReadCursor<'_>) -> DecodeResult<String> {
+    ensure_size!(in: src, size: 2);
+    let size = usize::from(src.read_u16());
+
+    ensure_size!(in: src, size: size);
+    utils::decode_string(src.read_slice(size), CharacterSet::Unicode, false)
+}
+
+fn write_rectangles(field: &'static str, rects: &[ExclusiveRectangle], dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+    dst.write_u16(cast_length!(field, rects.len())?);
+
+    for rect in rects {
+        rect.encode(dst)?;
+    }
+
+    Ok(())
+}
+
+fn read_rectangles(src: &mut ReadCursor<'_>) -> DecodeResult<Vec<ExclusiveRectangle>> {
+    ensure_size!(in: src, size: 2);
+    let count = usize::from(src.read_u16());
+
+    core::iter::repeat_with(|| ExclusiveRectangle::decode(src))
+        .take(count)
+        .collect()
+}
+
+fn read_point(src: &mut ReadCursor<'_>) -> DecodeResult<(i32, i32)> {
+    ensure_size!(in: src, size: 8);
+
+    Ok((src.read_i32(), src.read_i32()))
+}
+
+fn read_size(src: &mut ReadCursor<'_>) -> DecodeResult<(u32, u32)> {
+    ensure_size!(in: src, size: 8);
+
+    Ok((src.read_u32(), src.read_u32()))
+}
@@ -0,0 +1,725 @@
+//! RemoteFX Progressive codec (RDPGFX_CODECID_CAPROGRESSIVE)
+//!
+//! The bitmap data of a [MS-RDPEGFX] RDPGFX_WIRE_TO_SURFACE_PDU_2 is a stream of blocks, see [MS-RDPEGFX] 2.2.4.2
+//! RFX_PROGRESSIVE_BITMAP_STREAM. The tiles of a region are sent either at full quality, or with a first pass
+//! followed by upgrade passes refining their coefficients.
+
+use core::iter;
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+
+use crate::codecs::rfx::RfxRectangle;
+
+const BLOCK_HEADER_SIZE: usize = 2 /* blockType */ + 4 /* blockLen */;
+
+const SYNC_MAGIC: u32 = 0xCACC_ACCA;
+const SYNC_VERSION: u16 = 0x0100;
+const SYNC_SIZE: usize = 4 /* magic */ + 2 /* version */;
+const CONTEXT_ID: u8 = 0;
+const TILE_SIZE: u16 = 64;
+const REGION_TILE_SIZE: u8 = 64;
+const RECTANGLE_SIZE: usize = 8;
+
+const WBT_SYNC: u16 = 0xCCC0;
+const WBT_FRAME_BEGIN: u16 = 0xCCC1;
+const WBT_FRAME_END: u16 = 0xCCC2;
+const WBT_CONTEXT: u16 = 0xCCC3;
+const WBT_REGION: u16 = 0xCCC4;
+const WBT_TILE_SIMPLE: u16 = 0xCCC5;
+const WBT_TILE_FIRST: u16 = 0xCCC6;
+const WBT_TILE_UPGRADE: u16 = 0xCCC7;
+
+/// Quality of the tiles sent at full quality, without progressive quantization values
+pub const FULL_QUALITY: u8 = 0xFF;
+
+/// Block of a progressive bitmap stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block<'a> {
+    Sync,
+    FrameBegin(FrameBeginPdu),
+    FrameEnd,
+    Context(ContextPdu),
+    Region(RegionPdu<'a>),
+}
+
+impl Block<'_> {
+    const NAME: &'static str = "ProgressiveBlock";
+
+    fn block_type(&self) -> u16 {
+        match self {
+            Self::Sync => WBT_SYNC,
+            Self::FrameBegin(_) => WBT_FRAME_BEGIN,
+            Self::FrameEnd => WBT_FRAME_END,
+            Self::Context(_) => WBT_CONTEXT,
+            Self::Region(_) => WBT_REGION,
+        }
+    }
+}
+
+impl Encode for Block<'_> {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(self.block_type());
+        dst.write_u32(cast_length!("blockLen", self.size())?);
+
+        match self {
+            Self::Sync => {
+                dst.write_u32(SYNC_MAGIC);
+                dst.write_u16(SYNC_VERSION);
+                Ok(())
+            }
+            Self::FrameBegin(pdu) => pdu.encode(dst),
+            Self::FrameEnd => Ok(()),
+            Self::Context(pdu) => pdu.encode(dst),
+            Self::Region(pdu) => pdu.encode(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        BLOCK_HEADER_SIZE
+            + match self {
+                Self::Sync => SYNC_SIZE,
+                Self::FrameBegin(pdu) => pdu.size(),
+                Self::FrameEnd => 0,
+                Self::Context(pdu) => pdu.size(),
+                Self::Region(pdu) => pdu.size(),
+            }
+    }
+}
+
+impl<'de> Decode<'de> for Block<'de> {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let (block_type, src) = &mut read_block(src)?;
+
+        match *block_type {
+            WBT_SYNC => {
+                ensure_size!(ctx: "ProgressiveSync", in: src, size: SYNC_SIZE);
+
+                if src.read_u32() != SYNC_MAGIC {
+                    return Err(invalid_field_err!("magic", "invalid sync magic"));
+                }
+                if src.read_u16() != SYNC_VERSION {
+                    return Err(invalid_field_err!("version", "unsupported version"));
+                }
+
+                Ok(Self::Sync)
+            }
+            WBT_FRAME_BEGIN => Ok(Self::FrameBegin(FrameBeginPdu::decode(src)?)),
+            WBT_FRAME_END => Ok(Self::FrameEnd),
+            WBT_CONTEXT => Ok(Self::Context(ContextPdu::decode(src)?)),
+            WBT_REGION => Ok(Self::Region(RegionPdu::decode(src)?)),
+            _ => Err(invalid_field_err!("blockType", "invalid block type")),
+        }
+    }
+}
+
+/// Reads the header of a block, returning its type and a cursor over its content
+fn read_block<'de>(src: &mut ReadCursor<'de>) -> DecodeResult<(u16, ReadCursor<'de>)> {
+    ensure_size!(ctx: "ProgressiveBlockHeader", in: src, size: BLOCK_HEADER_SIZE);
+
+    let block_type = src.read_u16();
+    let block_len: usize = cast_length!("blockLen", src.read_u32())?;
+    let data_len = block_len
+        .checked_sub(BLOCK_HEADER_SIZE)
+        .ok_or_else(|| invalid_field_err!("blockLen", "invalid block length"))?;
+
+    ensure_size!(ctx: "ProgressiveBlock", in: src, size: data_len);
+
+    Ok((block_type, ReadCursor::new(src.read_slice(data_len))))
+}
+
+/// [MS-RDPEGFX] 2.2.4.2.1.2 RFX_PROGRESSIVE_FRAME_BEGIN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameBeginPdu {
+    pub frame_index: u32,
+    pub region_count: u16,
+}
+
+impl FrameBeginPdu {
+    const NAME: &'static str = "ProgressiveFrameBegin";
+
+    const FIXED_PART_SIZE: usize = 4 /* frameIndex */ + 2 /* regionCount */;
+}
+
+impl Encode for FrameBeginPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.frame_index);
+        dst.write_u16(self.region_count);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for FrameBeginPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let frame_index = src.read_u32();
+        let region_count = src.read_u16();
+
+        Ok(Self {
+            frame_index,
+            region_count,
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ContextFlags: u8 {
+        const SUBBAND_DIFFING = 0x01;
+    }
+}
+
+/// [MS-RDPEGFX] 2.2.4.2.1.4 RFX_PROGRESSIVE_CONTEXT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextPdu {
+    pub flags: ContextFlags,
+}
+
+impl ContextPdu {
+    const NAME: &'static str = "ProgressiveContext";
+
+    const FIXED_PART_SIZE: usize = 1 /* ctxId */ + 2 /* tileSize */ + 1 /* flags */;
+}
+
+impl Encode for ContextPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(CONTEXT_ID);
+        dst.write_u16(TILE_SIZE);
+        dst.write_u8(self.flags.bits());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ContextPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        if src.read_u8() != CONTEXT_ID {
+            return Err(invalid_field_err!("ctxId", "invalid context ID"));
+        }
+        if src.read_u16() != TILE_SIZE {
+            return Err(invalid_field_err!("tileSize", "invalid tile size"));
+        }
+        let flags = ContextFlags::from_bits_truncate(src.read_u8());
+
+        Ok(Self { flags })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RegionFlags: u8 {
+        /// The tiles are transformed with the reduce-extrapolate DWT
+        const DWT_REDUCE_EXTRAPOLATE = 0x01;
+    }
+}
+
+/// [MS-RDPEGFX] 2.2.4.2.1.5 RFX_PROGRESSIVE_REGION
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionPdu<'a> {
+    pub rectangles: Vec<RfxRectangle>,
+    pub quant_values: Vec<ComponentCodecQuant>,
+    pub quant_prog_values: Vec<ProgressiveCodecQuant>,
+    pub flags: RegionFlags,
+    pub tiles: Vec<Tile<'a>>,
+}
+
+impl RegionPdu<'_> {
+    const NAME: &'static str = "ProgressiveRegion";
+
+    const FIXED_PART_SIZE: usize = 1 /* tileSize */ + 2 /* numRects */ + 1 /* numQuant */ + 1 /* numProgQuant */
+        + 1 /* flags */ + 2 /* numTiles */ + 4 /* tileDataSize */;
+
+    fn tile_data_size(&self) -> usize {
+        self.tiles.iter().map(|tile| tile.size()).sum()
+    }
+}
+
+impl Encode for RegionPdu<'_> {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(REGION_TILE_SIZE);
+        dst.write_u16(cast_length!("numRects", self.rectangles.len())?);
+        dst.write_u8(cast_length!("numQuant", self.quant_values.len())?);
+        dst.write_u8(cast_length!("numProgQuant", self.quant_prog_values.len())?);
+        dst.write_u8(self.flags.bits());
+        dst.write_u16(cast_length!("numTiles", self.tiles.len())?);
+        dst.write_u32(cast_length!("tileDataSize", self.tile_data_size())?);
+
+        for rectangle in &self.rectangles {
+            rectangle.encode(dst)?;
+        }
+        for quant in &self.quant_values {
+            quant.encode(dst)?;
+        }
+        for quant in &self.quant_prog_values {
+            quant.encode(dst)?;
+        }
+        for tile in &self.tiles {
+            tile.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            + self.rectangles.len() * RECTANGLE_SIZE
+            + self.quant_values.len() * ComponentCodecQuant::FIXED_PART_SIZE
+            + self.quant_prog_values.len() * ProgressiveCodecQuant::FIXED_PART_SIZE
+            + self.tile_data_size()
+    }
+}
+
+impl<'de> Decode<'de> for RegionPdu<'de> {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        if src.read_u8() != REGION_TILE_SIZE {
+            return Err(invalid_field_err!("tileSize", "invalid tile size"));
+        }
+        let num_rects = usize::from(src.read_u16());
+        let num_quant = usize::from(src.read_u8());
+        let num_prog_quant = usize::from(src.read_u8());
+        let flags = RegionFlags::from_bits_truncate(src.read_u8());
+        let num_tiles = usize::from(src.read_u16());
+        let tile_data_size: usize = cast_length!("tileDataSize", src.read_u32())?;
+
+        let rectangles = iter::repeat_with(|| RfxRectangle::decode(src))
+            .take(num_rects)
+            .collect::<DecodeResult<_>>()?;
+        let quant_values = iter::repeat_with(|| ComponentCodecQuant::decode(src))
+            .take(num_quant)
+            .collect::<DecodeResult<_>>()?;
+        let quant_prog_values = iter::repeat_with(|| ProgressiveCodecQuant::decode(src))
+            .take(num_prog_quant)
+            .collect::<DecodeResult<_>>()?;
+
+        ensure_size!(in: src, size: tile_data_size);
+        let tiles_src = &mut ReadCursor::new(src.read_slice(tile_data_size));
+        let tiles = iter::repeat_with(|| Tile::decode(tiles_src))
+            .take(num_tiles)
+            .collect::<DecodeResult<_>>()?;
+
+        Ok(Self {
+            rectangles,
+            quant_values,
+            quant_prog_values,
+            flags,
+            tiles,
+        })
+    }
+}
+
+/// [MS-RDPEGFX] 2.2.4.2.1.5.1 RFX_COMPONENT_CODEC_QUANT
+///
+/// Quantization values of the ten sub-bands of a component, between 6 and 15 for the quantization values of a
+/// region, and between 0 and 15 for the bit positions of a progressive pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComponentCodecQuant {
+    pub ll3: u8,
+    pub hl3: u8,
+    pub lh3: u8,
+    pub hh3: u8,
+    pub hl2: u8,
+    pub lh2: u8,
+    pub hh2: u8,
+    pub hl1: u8,
+    pub lh1: u8,
+    pub hh1: u8,
+}
+
+impl ComponentCodecQuant {
+    const NAME: &'static str = "ComponentCodecQuant";
+
+    const FIXED_PART_SIZE: usize = 5 /* 10 * 4 bits */;
+}
+
+impl Encode for ComponentCodecQuant {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8((self.ll3 & 0x0F) | (self.hl3 << 4));
+        dst.write_u8((self.lh3 & 0x0F) | (self.hh3 << 4));
+        dst.write_u8((self.hl2 & 0x0F) | (self.lh2 << 4));
+        dst.write_u8((self.hh2 & 0x0F) | (self.hl1 << 4));
+        dst.write_u8((self.lh1 & 0x0F) | (self.hh1 << 4));
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ComponentCodecQuant {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let mut quant = Self::default();
+
+        [quant.ll3, quant.hl3] = split_nibbles(src.read_u8());
+        [quant.lh3, quant.hh3] = split_nibbles(src.read_u8());
+        [quant.hl2, quant.lh2] = split_nibbles(src.read_u8());
+        [quant.hh2, quant.hl1] = split_nibbles(src.read_u8());
+        [quant.lh1, quant.hh1] = split_nibbles(src.read_u8());
+
+        Ok(quant)
+    }
+}
+
+fn split_nibbles(byte: u8) -> [u8; 2] {
+    [byte & 0x0F, byte >> 4]
+}
+
+/// [MS-RDPEGFX] 2.2.4.2.1.5.2 RFX_PROGRESSIVE_CODEC_QUANT
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgressiveCodecQuant {
+    /// Quality of the pass, in percent
+    pub quality: u8,
+    pub y: ComponentCodecQuant,
+    pub cb: ComponentCodecQuant,
+    pub cr: ComponentCodecQuant,
+}
+
+impl ProgressiveCodecQuant {
+    const NAME: &'static str = "ProgressiveCodecQuant";
+
+    const FIXED_PART_SIZE: usize = 1 /* quality */ + 3 * ComponentCodecQuant::FIXED_PART_SIZE;
+}
+
+impl Encode for ProgressiveCodecQuant {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.quality);
+        self.y.encode(dst)?;
+        self.cb.encode(dst)?;
+        self.cr.encode(dst)?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ProgressiveCodecQuant {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let quality = src.read_u8();
+        let y = ComponentCodecQuant::decode(src)?;
+        let cb = ComponentCodecQuant::decode(src)?;
+        let cr = ComponentCodecQuant::decode(src)?;
+
+        Ok(Self { quality, y, cb, cr })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TileFlags: u8 {
+        /// The coefficients are added to the ones of the previous tile at the same position
+        const DIFFERENCE = 0x01;
+    }
+}
+
+/// Tile of a [`RegionPdu`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tile<'a> {
+    /// [MS-RDPEGFX] 2.2.4.2.1.5.3 RFX_PROGRESSIVE_TILE_SIMPLE, a tile at full quality
+    Simple(FirstTile<'a>),
+    /// [MS-RDPEGFX] 2.2.4.2.1.5.4 RFX_PROGRESSIVE_TILE_FIRST, the first pass of a tile
+    First(FirstTile<'a>),
+    /// [MS-RDPEGFX] 2.2.4.2.1.5.5 RFX_PROGRESSIVE_TILE_UPGRADE, an upgrade pass of a tile
+    Upgrade(UpgradeTile<'a>),
+}
+
+impl Tile<'_> {
+    const NAME: &'static str = "ProgressiveTile";
+
+    /// Column and row of the tile
+    pub fn position(&self) -> (u16, u16) {
+        match self {
+            Self::Simple(tile) | Self::First(tile) => (tile.x_idx, tile.y_idx),
+            Self::Upgrade(tile) => (tile.x_idx, tile.y_idx),
+        }
+    }
+}
+
+impl Encode for Tile<'_> {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        let block_type = match self {
+            Self::Simple(_) => WBT_TILE_SIMPLE,
+            Self::First(_) => WBT_TILE_FIRST,
+            Self::Upgrade(_) => WBT_TILE_UPGRADE,
+        };
+        dst.write_u16(block_type);
+        dst.write_u32(cast_length!("blockLen", self.size())?);
+
+        match self {
+            Self::Simple(tile) => tile.encode_fields(dst, false),
+            Self::First(tile) => tile.encode_fields(dst, true),
+            Self::Upgrade(tile) => tile.encode_fields(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        BLOCK_HEADER_SIZE
+            + match self {
+                Self::Simple(tile) => tile.fields_size(false),
+                Self::First(tile) => tile.fields_size(true),
+                Self::Upgrade(tile) => tile.fields_size(),
+            }
+    }
+}
+
+impl<'de> Decode<'de> for Tile<'de> {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let (block_type, src) = &mut read_block(src)?;
+
+        match *block_type {
+            WBT_TILE_SIMPLE => Ok(Self::Simple(FirstTile::decode_fields(src, false)?)),
+            WBT_TILE_FIRST => Ok(Self::First(FirstTile::decode_fields(src, true)?)),
+            WBT_TILE_UPGRADE => Ok(Self::Upgrade(UpgradeTile::decode_fields(src)?)),
+            _ => Err(invalid_field_err!("blockType", "invalid tile block type")),
+        }
+    }
+}
+
+/// Tile sent at full quality, or first pass of a progressive tile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirstTile<'a> {
+    pub quant_idx_y: u8,
+    pub quant_idx_cb: u8,
+    pub quant_idx_cr: u8,
+    pub x_idx: u16,
+    pub y_idx: u16,
+    pub flags: TileFlags,
+    /// Index of the progressive quantization values of the region, or [`FULL_QUALITY`]
+    ///
+    /// Always [`FULL_QUALITY`] for a [`Tile::Simple`].
+    pub quality: u8,
+    pub y_data: &'a [u8],
+    pub cb_data: &'a [u8],
+    pub cr_data: &'a [u8],
+    pub tail_data: &'a [u8],
+}
+
+impl<'a> FirstTile<'a> {
+    const FIXED_PART_SIZE: usize = 3 /* quantIdx */ + 2 /* xIdx */ + 2 /* yIdx */ + 1 /* flags */ + 4 * 2 /* lengths */;
+
+    fn fields_size(&self, with_quality: bool) -> usize {
+        Self::FIXED_PART_SIZE
+            + usize::from(with_quality)
+            + self.y_data.len()
+            + self.cb_data.len()
+            + self.cr_data.len()
+            + self.tail_data.len()
+    }
+
+    fn encode_fields(&self, dst: &mut WriteCursor<'_>, with_quality: bool) -> EncodeResult<()> {
+        dst.write_u8(self.quant_idx_y);
+        dst.write_u8(self.quant_idx_cb);
+        dst.write_u8(self.quant_idx_cr);
+        dst.write_u16(self.x_idx);
+        dst.write_u16(self.y_idx);
+        dst.write_u8(self.flags.bits());
+        if with_quality {
+            dst.write_u8(self.quality);
+        }
+        dst.write_u16(cast_length!("yLen", self.y_data.len())?);
+        dst.write_u16(cast_length!("cbLen", self.cb_data.len())?);
+        dst.write_u16(cast_length!("crLen", self.cr_data.len())?);
+        dst.write_u16(cast_length!("tailLen", self.tail_data.len())?);
+        dst.write_slice(self.y_data);
+        dst.write_slice(self.cb_data);
+        dst.write_slice(self.cr_data);
+        dst.write_slice(self.tail_data);
+
+        Ok(())
+    }
+
+    fn decode_fields(src: &mut ReadCursor<'a>, with_quality: bool) -> DecodeResult<Self> {
+        #![allow(
+            clippy::similar_names,
+            reason = "the fields are named after the components of the tile"
+        )]
+
+        ensure_size!(ctx: "ProgressiveTileFirst", in: src, size: Self::FIXED_PART_SIZE + usize::from(with_quality));
+
+        let quant_idx_y = src.read_u8();
+        let quant_idx_cb = src.read_u8();
+        let quant_idx_cr = src.read_u8();
+        let x_idx = src.read_u16();
+        let y_idx = src.read_u16();
+        let flags = TileFlags::from_bits_truncate(src.read_u8());
+        let quality = if with_quality { src.read_u8() } else { FULL_QUALITY };
+        let y_len = usize::from(src.read_u16());
+        let cb_len = usize::from(src.read_u16());
+        let cr_len = usize::from(src.read_u16());
+        let tail_len = usize::from(src.read_u16());
+
+        ensure_size!(ctx: "ProgressiveTileFirst", in: src, size: y_len + cb_len + cr_len + tail_len);
+
+        Ok(Self {
+            quant_idx_y,
+            quant_idx_cb,
+            quant_idx_cr,
+            x_idx,
+            y_idx,
+            flags,
+            quality,
+            y_data: src.read_slice(y_len),
+            cb_data: src.read_slice(cb_len),
+            cr_data: src.read_slice(cr_len),
+            tail_data: src.read_slice(tail_len),
+        })
+    }
+}
+
+/// Upgrade pass of a progressive tile
+///
+/// The SRL data refines the coefficients which were zero until this pass, and the raw data the other ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeTile<'a> {
+    pub quant_idx_y: u8,
+    pub quant_idx_cb: u8,
+    pub quant_idx_cr: u8,
+    pub x_idx: u16,
+    pub y_idx: u16,
+    /// Index of the progressive quantization values of the region, or [`FULL_QUALITY`]
+    pub quality: u8,
+    pub y_srl_data: &'a [u8],
+    pub y_raw_data: &'a [u8],
+    pub cb_srl_data: &'a [u8],
+    pub cb_raw_data: &'a [u8],
+    pub cr_srl_data: &'a [u8],
+    pub cr_raw_data: &'a [u8],
+}
+
+impl<'a> UpgradeTile<'a> {
+    const FIXED_PART_SIZE: usize = 3 /* quantIdx */ + 2 /* xIdx */ + 2 /* yIdx */ + 1 /* quality */ + 6 * 2 /* lengths */;
+
+    fn data(&self) -> [&'a [u8]; 6] {
+        [
+            self.y_srl_data,
+            self.y_raw_data,
+            self.cb_srl_data,
+            self.cb_raw_data,
+            self.cr_srl_data,
+            self.cr_raw_data,
+        ]
+    }
+
+    fn fields_size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.data().iter().map(|data| data.len()).sum::<usize>()
+    }
+
+    fn encode_fields(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        dst.write_u8(self.quant_idx_y);
+        dst.write_u8(self.quant_idx_cb);
+        dst.write_u8(self.quant_idx_cr);
+        dst.write_u16(self.x_idx);
+        dst.write_u16(self.y_idx);
+        dst.write_u8(self.quality);
+        for data in self.data() {
+            dst.write_u16(cast_length!("dataLen", data.len())?);
+        }
+        for data in self.data() {
+            dst.write_slice(data);
+        }
+
+        Ok(())
+    }
+
+    fn decode_fields(src: &mut ReadCursor<'a>) -> DecodeResult<Self> {
+        #![allow(
+            clippy::similar_names,
+            reason = "the fields are named after the components of the tile"
+        )]
+
+        ensure_size!(ctx: "ProgressiveTileUpgrade", in: src, size: Self::FIXED_PART_SIZE);
+
+        let quant_idx_y = src.read_u8();
+        let quant_idx_cb = src.read_u8();
+        let quant_idx_cr = src.read_u8();
+        let x_idx = src.read_u16();
+        let y_idx = src.read_u16();
+        let quality = src.read_u8();
+        let lengths: [usize; 6] = core::array::from_fn(|_| usize::from(src.read_u16()));
+
+        ensure_size!(ctx: "ProgressiveTileUpgrade", in: src, size: lengths.iter().sum::<usize>());
+        let [y_srl_data, y_raw_data, cb_srl_data, cb_raw_data, cr_srl_data, cr_raw_data] =
+            lengths.map(|len| src.read_slice(len));
+
+        Ok(Self {
+            quant_idx_y,
+            quant_idx_cb,
+            quant_idx_cr,
+            x_idx,
+            y_idx,
+            quality,
+            y_srl_data,
+            y_raw_data,
+            cb_srl_data,
+            cb_raw_data,
+            cr_srl_data,
+            cr_raw_data,
+        })
+    }
+}
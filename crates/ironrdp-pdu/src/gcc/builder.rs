@@ -0,0 +1,492 @@
+//! Fluent builders for hand-crafted GCC blocks
+//!
+//! Unlike the plain structs, the builders check field ranges mandated by [MS-RDPBCGR] before
+//! handing out the PDU, and fill the mandatory preceding optional fields of the client core data
+//! with neutral values.
+//!
+//! [MS-RDPBCGR]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr
+
+use ironrdp_core::{invalid_field_err, EncodeResult};
+
+use super::{
+    ChannelDef, ChannelName, ChannelOptions, ClientCoreData, ClientCoreOptionalData, ClientEarlyCapabilityFlags,
+    ClientGccBlocks, ClientMonitorData, ClientNetworkData, ClientSecurityData, ColorDepth, ConnectionType,
+    HighColorDepth, KeyboardType, Monitor, MonitorFlags, RdpVersion, SecureAccessSequence, SupportedColorDepths,
+};
+use crate::nego::SecurityProtocol;
+
+/// Maximum desktop width and height (2.2.1.3.2 Client Core Data)
+pub const MAX_DESKTOP_SIZE: u16 = 8192;
+
+/// clientName is 32 bytes, including the UTF-16 null terminator
+const MAX_CLIENT_NAME_LEN: usize = 15;
+/// imeFileName and digProductId are 64 bytes, including the UTF-16 null terminator
+const MAX_IME_FILE_NAME_LEN: usize = 31;
+const MAX_DIG_PRODUCT_ID_LEN: usize = 31;
+
+const DESKTOP_PHYSICAL_SIZE_RANGE: core::ops::RangeInclusive<u32> = 10..=10_000;
+const DESKTOP_ORIENTATIONS: [u16; 4] = [0, 90, 180, 270];
+const DESKTOP_SCALE_FACTOR_RANGE: core::ops::RangeInclusive<u32> = 100..=500;
+const DEVICE_SCALE_FACTORS: [u32; 3] = [100, 140, 180];
+
+const MAX_CHANNELS: usize = 31;
+const MAX_MONITORS: usize = 16;
+
+/// Builder for [`ClientCoreData`]
+///
+/// ```
+/// # use ironrdp_pdu::gcc::{ClientCoreDataBuilder, HighColorDepth};
+/// let core = ClientCoreDataBuilder::new()
+///     .desktop_size(1920, 1080)
+///     .client_name("test-client")
+///     .high_color_depth(HighColorDepth::Bpp24)
+///     .build()?;
+///
+/// assert!(core.optional_data.serial_number.is_some());
+/// # Ok::<(), ironrdp_core::EncodeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCoreDataBuilder {
+    inner: ClientCoreData,
+}
+
+impl ClientCoreDataBuilder {
+    pub fn new() -> Self {
+        Self {
+            inner: ClientCoreData {
+                version: RdpVersion::V5_PLUS,
+                desktop_width: 1024,
+                desktop_height: 768,
+                color_depth: ColorDepth::Bpp8,
+                sec_access_sequence: SecureAccessSequence::Del,
+                keyboard_layout: 0,
+                client_build: 0,
+                client_name: String::new(),
+                keyboard_type: KeyboardType::IbmEnhanced,
+                keyboard_subtype: 0,
+                keyboard_functional_keys_count: 12,
+                ime_file_name: String::new(),
+                optional_data: ClientCoreOptionalData::default(),
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn version(mut self, version: RdpVersion) -> Self {
+        self.inner.version = version;
+        self
+    }
+
+    #[must_use]
+    pub fn desktop_size(mut self, width: u16, height: u16) -> Self {
+        self.inner.desktop_width = width;
+        self.inner.desktop_height = height;
+        self
+    }
+
+    #[must_use]
+    pub fn color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.inner.color_depth = color_depth;
+        self
+    }
+
+    #[must_use]
+    pub fn keyboard(mut self, keyboard_type: KeyboardType, subtype: u32, functional_keys_count: u32) -> Self {
+        self.inner.keyboard_type = keyboard_type;
+        self.inner.keyboard_subtype = subtype;
+        self.inner.keyboard_functional_keys_count = functional_keys_count;
+        self
+    }
+
+    #[must_use]
+    pub fn keyboard_layout(mut self, keyboard_layout: u32) -> Self {
+        self.inner.keyboard_layout = keyboard_layout;
+        self
+    }
+
+    #[must_use]
+    pub fn client_build(mut self, client_build: u32) -> Self {
+        self.inner.client_build = client_build;
+        self
+    }
+
+    #[must_use]
+    pub fn client_name(mut self, client_name: impl Into<String>) -> Self {
+        self.inner.client_name = client_name.into();
+        self
+    }
+
+    #[must_use]
+    pub fn ime_file_name(mut self, ime_file_name: impl Into<String>) -> Self {
+        self.inner.ime_file_name = ime_file_name.into();
+        self
+    }
+
+    #[must_use]
+    pub fn post_beta2_color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.inner.optional_data.post_beta2_color_depth = Some(color_depth);
+        self
+    }
+
+    #[must_use]
+    pub fn client_product_id(mut self, client_product_id: u16) -> Self {
+        self.inner.optional_data.client_product_id = Some(client_product_id);
+        self
+    }
+
+    #[must_use]
+    pub fn serial_number(mut self, serial_number: u32) -> Self {
+        self.inner.optional_data.serial_number = Some(serial_number);
+        self
+    }
+
+    #[must_use]
+    pub fn high_color_depth(mut self, high_color_depth: HighColorDepth) -> Self {
+        self.inner.optional_data.high_color_depth = Some(high_color_depth);
+        self
+    }
+
+    #[must_use]
+    pub fn supported_color_depths(mut self, supported_color_depths: SupportedColorDepths) -> Self {
+        self.inner.optional_data.supported_color_depths = Some(supported_color_depths);
+        self
+    }
+
+    #[must_use]
+    pub fn early_capability_flags(mut self, flags: ClientEarlyCapabilityFlags) -> Self {
+        self.inner.optional_data.early_capability_flags = Some(flags);
+        self
+    }
+
+    #[must_use]
+    pub fn dig_product_id(mut self, dig_product_id: impl Into<String>) -> Self {
+        self.inner.optional_data.dig_product_id = Some(dig_product_id.into());
+        self
+    }
+
+    #[must_use]
+    pub fn connection_type(mut self, connection_type: ConnectionType) -> Self {
+        self.inner.optional_data.connection_type = Some(connection_type);
+        self
+    }
+
+    #[must_use]
+    pub fn server_selected_protocol(mut self, protocol: SecurityProtocol) -> Self {
+        self.inner.optional_data.server_selected_protocol = Some(protocol);
+        self
+    }
+
+    /// Physical size of the desktop, in millimeters
+    #[must_use]
+    pub fn desktop_physical_size(mut self, width: u32, height: u32) -> Self {
+        self.inner.optional_data.desktop_physical_width = Some(width);
+        self.inner.optional_data.desktop_physical_height = Some(height);
+        self
+    }
+
+    /// Orientation of the desktop, in degrees (0, 90, 180 or 270)
+    #[must_use]
+    pub fn desktop_orientation(mut self, orientation: u16) -> Self {
+        self.inner.optional_data.desktop_orientation = Some(orientation);
+        self
+    }
+
+    /// Desktop (100 to 500 percent) and device (100, 140 or 180 percent) scale factors
+    #[must_use]
+    pub fn scale_factors(mut self, desktop_scale_factor: u32, device_scale_factor: u32) -> Self {
+        self.inner.optional_data.desktop_scale_factor = Some(desktop_scale_factor);
+        self.inner.optional_data.device_scale_factor = Some(device_scale_factor);
+        self
+    }
+
+    pub fn build(self) -> EncodeResult<ClientCoreData> {
+        let mut core = self.inner;
+
+        if !(1..=MAX_DESKTOP_SIZE).contains(&core.desktop_width) {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "desktopWidth",
+                "must be in the 1..=8192 range"
+            ));
+        }
+        if !(1..=MAX_DESKTOP_SIZE).contains(&core.desktop_height) {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "desktopHeight",
+                "must be in the 1..=8192 range"
+            ));
+        }
+        if utf16_len(&core.client_name) > MAX_CLIENT_NAME_LEN {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "clientName",
+                "must be at most 15 UTF-16 code units"
+            ));
+        }
+        if utf16_len(&core.ime_file_name) > MAX_IME_FILE_NAME_LEN {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "imeFileName",
+                "must be at most 31 UTF-16 code units"
+            ));
+        }
+
+        let optional = &mut core.optional_data;
+
+        if optional
+            .dig_product_id
+            .as_deref()
+            .is_some_and(|id| utf16_len(id) > MAX_DIG_PRODUCT_ID_LEN)
+        {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "digProductId",
+                "must be at most 31 UTF-16 code units"
+            ));
+        }
+        if [optional.desktop_physical_width, optional.desktop_physical_height]
+            .into_iter()
+            .flatten()
+            .any(|size| !DESKTOP_PHYSICAL_SIZE_RANGE.contains(&size))
+        {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "desktopPhysicalSize",
+                "must be in the 10..=10000 millimeters range"
+            ));
+        }
+        if optional
+            .desktop_orientation
+            .is_some_and(|orientation| !DESKTOP_ORIENTATIONS.contains(&orientation))
+        {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "desktopOrientation",
+                "must be 0, 90, 180 or 270"
+            ));
+        }
+        if optional
+            .desktop_scale_factor
+            .is_some_and(|factor| !DESKTOP_SCALE_FACTOR_RANGE.contains(&factor))
+        {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "desktopScaleFactor",
+                "must be in the 100..=500 range"
+            ));
+        }
+        if optional
+            .device_scale_factor
+            .is_some_and(|factor| !DEVICE_SCALE_FACTORS.contains(&factor))
+        {
+            return Err(invalid_field_err!(
+                "ClientCoreData",
+                "deviceScaleFactor",
+                "must be 100, 140 or 180"
+            ));
+        }
+
+        fill_preceding_optional_fields(optional);
+
+        Ok(core)
+    }
+}
+
+impl Default for ClientCoreDataBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Optional core data fields are only valid when all the preceding ones are present
+fn fill_preceding_optional_fields(optional: &mut ClientCoreOptionalData) {
+    // Walk the fields backwards: once a field is present, all the preceding ones are required.
+    let mut required = optional.device_scale_factor.is_some();
+
+    required |= optional.desktop_scale_factor.is_some();
+    if required {
+        optional.desktop_scale_factor.get_or_insert(100);
+    }
+    required |= optional.desktop_orientation.is_some();
+    if required {
+        optional.desktop_orientation.get_or_insert(0);
+    }
+    required |= optional.desktop_physical_height.is_some();
+    if required {
+        optional.desktop_physical_height.get_or_insert(0);
+    }
+    required |= optional.desktop_physical_width.is_some();
+    if required {
+        optional.desktop_physical_width.get_or_insert(0);
+    }
+    required |= optional.server_selected_protocol.is_some();
+    if required {
+        optional
+            .server_selected_protocol
+            .get_or_insert(SecurityProtocol::empty());
+    }
+    required |= optional.connection_type.is_some();
+    if required {
+        optional.connection_type.get_or_insert(ConnectionType::NotUsed);
+    }
+    required |= optional.dig_product_id.is_some();
+    if required {
+        optional.dig_product_id.get_or_insert_with(String::new);
+    }
+    required |= optional.early_capability_flags.is_some();
+    if required {
+        optional
+            .early_capability_flags
+            .get_or_insert(ClientEarlyCapabilityFlags::empty());
+    }
+    required |= optional.supported_color_depths.is_some();
+    if required {
+        optional
+            .supported_color_depths
+            .get_or_insert(SupportedColorDepths::BPP24);
+    }
+    required |= optional.high_color_depth.is_some();
+    if required {
+        optional.high_color_depth.get_or_insert(HighColorDepth::Bpp24);
+    }
+    required |= optional.serial_number.is_some();
+    if required {
+        optional.serial_number.get_or_insert(0);
+    }
+    required |= optional.client_product_id.is_some();
+    if required {
+        optional.client_product_id.get_or_insert(1);
+    }
+    required |= optional.post_beta2_color_depth.is_some();
+    if required {
+        optional.post_beta2_color_depth.get_or_insert(ColorDepth::Bpp8);
+    }
+}
+
+/// Builder for [`ClientGccBlocks`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientGccBlocksBuilder {
+    core: ClientCoreData,
+    security: ClientSecurityData,
+    channels: Vec<(String, ChannelOptions)>,
+    monitors: Vec<Monitor>,
+}
+
+impl ClientGccBlocksBuilder {
+    pub fn new(core: ClientCoreData) -> Self {
+        Self {
+            core,
+            security: ClientSecurityData::no_security(),
+            channels: Vec::new(),
+            monitors: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn security(mut self, security: ClientSecurityData) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Adds a static virtual channel to the network data block
+    #[must_use]
+    pub fn channel(mut self, name: impl Into<String>, options: ChannelOptions) -> Self {
+        self.channels.push((name.into(), options));
+        self
+    }
+
+    /// Adds a monitor to the monitor data block
+    #[must_use]
+    pub fn monitor(mut self, monitor: Monitor) -> Self {
+        self.monitors.push(monitor);
+        self
+    }
+
+    pub fn build(self) -> EncodeResult<ClientGccBlocks> {
+        let network = if self.channels.is_empty() {
+            None
+        } else {
+            if self.channels.len() > MAX_CHANNELS {
+                return Err(invalid_field_err!(
+                    "ClientNetworkData",
+                    "channelCount",
+                    "at most 31 channels are allowed"
+                ));
+            }
+
+            let channels = self
+                .channels
+                .into_iter()
+                .map(|(name, options)| {
+                    let name = ChannelName::from_utf8(&name).ok_or_else(|| {
+                        invalid_field_err!(
+                            "ClientNetworkData",
+                            "channelDefArray",
+                            "channel name must be at most 7 ANSI characters"
+                        )
+                    })?;
+
+                    Ok(ChannelDef { name, options })
+                })
+                .collect::<EncodeResult<Vec<_>>>()?;
+
+            Some(ClientNetworkData { channels })
+        };
+
+        let monitor = if self.monitors.is_empty() {
+            None
+        } else {
+            if self.monitors.len() > MAX_MONITORS {
+                return Err(invalid_field_err!(
+                    "ClientMonitorData",
+                    "monitorCount",
+                    "at most 16 monitors are allowed"
+                ));
+            }
+
+            let primary_count = self
+                .monitors
+                .iter()
+                .filter(|monitor| monitor.flags.contains(MonitorFlags::PRIMARY))
+                .count();
+            if primary_count != 1 {
+                return Err(invalid_field_err!(
+                    "ClientMonitorData",
+                    "monitorDefArray",
+                    "exactly one monitor must be primary"
+                ));
+            }
+
+            if self
+                .monitors
+                .iter()
+                .any(|monitor| monitor.left > monitor.right || monitor.top > monitor.bottom)
+            {
+                return Err(invalid_field_err!(
+                    "ClientMonitorData",
+                    "monitorDefArray",
+                    "monitor bounds are inverted"
+                ));
+            }
+
+            Some(ClientMonitorData {
+                monitors: self.monitors,
+            })
+        };
+
+        Ok(ClientGccBlocks {
+            core: self.core,
+            security: self.security,
+            network,
+            cluster: None,
+            monitor,
+            message_channel: None,
+            multi_transport_channel: None,
+            monitor_extended: None,
+        })
+    }
+}
+
+fn utf16_len(value: &str) -> usize {
+    value.encode_utf16().count()
+}
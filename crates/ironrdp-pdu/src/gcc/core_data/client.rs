@@ -571,6 +571,8 @@ pub enum KeyboardType {
     Nokia1050 = 5,
     Nokia9140 = 6,
     Japanese = 7,
+    /// Not listed by MS-RDPBCGR, but sent by Windows clients with a Korean keyboard
+    Korean = 8,
 }
 
 impl KeyboardType {
@@ -12,6 +12,7 @@ use crate::PduError;
 
 pub mod conference_create;
 
+mod builder;
 mod cluster_data;
 mod core_data;
 mod message_channel_data;
@@ -21,6 +22,7 @@ mod multi_transport_channel_data;
 mod network_data;
 mod security_data;
 
+pub use self::builder::{ClientCoreDataBuilder, ClientGccBlocksBuilder, MAX_DESKTOP_SIZE};
 pub use self::cluster_data::{ClientClusterData, ClusterDataError, RedirectionFlags, RedirectionVersion};
 pub use self::conference_create::{ConferenceCreateRequest, ConferenceCreateResponse};
 pub use self::core_data::client::{
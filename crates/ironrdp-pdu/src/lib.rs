@@ -18,6 +18,7 @@ pub mod mcs;
 pub mod nego;
 pub mod pcb;
 pub mod rdp;
+pub mod rdstls;
 pub mod tpdu;
 pub mod tpkt;
 pub mod utf16;
@@ -29,7 +30,7 @@ pub(crate) mod ber;
 pub(crate) mod crypto;
 pub(crate) mod per;
 
-pub use crate::basic_output::{bitmap, fast_path, pointer, surface_commands};
+pub use crate::basic_output::{bitmap, fast_path, orders, pointer, surface_commands};
 pub use crate::rdp::vc::dvc;
 
 pub type PduResult<T> = Result<T, PduError>;
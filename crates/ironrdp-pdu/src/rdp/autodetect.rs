@@ -0,0 +1,455 @@
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags};
+
+const REQUEST_HEADER_TYPE_ID: u8 = 0x00;
+const RESPONSE_HEADER_TYPE_ID: u8 = 0x01;
+
+/// Length of the common header of the requests and responses
+const HEADER_SIZE: usize = 1 /* headerLength */ + 1 /* headerTypeId */ + 2 /* sequenceNumber */ + 2 /* requestType */;
+
+const RTT_REQUEST_CONTINUOUS: u16 = 0x0001;
+const RTT_REQUEST_CONNECT_TIME: u16 = 0x1001;
+const BW_START_CONTINUOUS: u16 = 0x0014;
+const BW_START_TUNNEL: u16 = 0x0114;
+const BW_START_CONNECT_TIME: u16 = 0x1014;
+const BW_PAYLOAD: u16 = 0x0002;
+const BW_STOP_CONNECT_TIME: u16 = 0x002B;
+const BW_STOP_CONTINUOUS: u16 = 0x0429;
+const BW_STOP_TUNNEL: u16 = 0x0629;
+const NETCHAR_RESULT_BASE_RTT_AVG_RTT: u16 = 0x0840;
+const NETCHAR_RESULT_BANDWIDTH_AVG_RTT: u16 = 0x0880;
+const NETCHAR_RESULT_ALL: u16 = 0x08C0;
+
+const RTT_RESPONSE: u16 = 0x0000;
+const BW_RESULTS_CONNECT_TIME: u16 = 0x0003;
+const BW_RESULTS_CONTINUOUS: u16 = 0x000B;
+const NETCHAR_SYNC: u16 = 0x0018;
+
+/// [MS-RDPBCGR] 2.2.14.3 Auto-Detect Request PDU
+///
+/// Sent by the server on the message channel to measure the characteristics of the network, the
+/// client answering with an [`AutoDetectResponse`]. Round-trip times are in milliseconds, and
+/// bandwidths in kilobits per second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoDetectRequest {
+    /// [MS-RDPBCGR] 2.2.14.1.1 RTT Measure Request
+    RttRequest {
+        sequence_number: u16,
+        phase: AutoDetectPhase,
+    },
+    /// [MS-RDPBCGR] 2.2.14.1.2 Bandwidth Measure Start
+    BandwidthStart {
+        sequence_number: u16,
+        phase: BandwidthPhase,
+    },
+    /// [MS-RDPBCGR] 2.2.14.1.3 Bandwidth Measure Payload
+    ///
+    /// Only sent during the connection, between the start and the stop of a measure.
+    BandwidthPayload { sequence_number: u16, payload: Vec<u8> },
+    /// [MS-RDPBCGR] 2.2.14.1.4 Bandwidth Measure Stop
+    ///
+    /// `payload` is only sent during the connection, and must be empty otherwise.
+    BandwidthStop {
+        sequence_number: u16,
+        phase: BandwidthPhase,
+        payload: Vec<u8>,
+    },
+    /// [MS-RDPBCGR] 2.2.14.1.5 Network Characteristics Result
+    ///
+    /// Sends the results of the measures to the client. At least one of `base_rtt` and `bandwidth`
+    /// is set.
+    NetworkCharacteristicsResult {
+        sequence_number: u16,
+        base_rtt: Option<u32>,
+        bandwidth: Option<u32>,
+        average_rtt: u32,
+    },
+}
+
+impl AutoDetectRequest {
+    const NAME: &'static str = "AutoDetectRequest";
+
+    const FIXED_PART_SIZE: usize = BasicSecurityHeader::FIXED_PART_SIZE + HEADER_SIZE;
+
+    pub fn sequence_number(&self) -> u16 {
+        match self {
+            Self::RttRequest { sequence_number, .. }
+            | Self::BandwidthStart { sequence_number, .. }
+            | Self::BandwidthPayload { sequence_number, .. }
+            | Self::BandwidthStop { sequence_number, .. }
+            | Self::NetworkCharacteristicsResult { sequence_number, .. } => *sequence_number,
+        }
+    }
+
+    fn request_type(&self) -> EncodeResult<u16> {
+        let request_type = match self {
+            Self::RttRequest {
+                phase: AutoDetectPhase::Connection,
+                ..
+            } => RTT_REQUEST_CONNECT_TIME,
+            Self::RttRequest {
+                phase: AutoDetectPhase::Session,
+                ..
+            } => RTT_REQUEST_CONTINUOUS,
+            Self::BandwidthStart { phase, .. } => match phase {
+                BandwidthPhase::Connection => BW_START_CONNECT_TIME,
+                BandwidthPhase::Session => BW_START_CONTINUOUS,
+                BandwidthPhase::Tunnel => BW_START_TUNNEL,
+            },
+            Self::BandwidthPayload { .. } => BW_PAYLOAD,
+            Self::BandwidthStop { phase, payload, .. } => match phase {
+                BandwidthPhase::Connection => BW_STOP_CONNECT_TIME,
+                BandwidthPhase::Session | BandwidthPhase::Tunnel if !payload.is_empty() => {
+                    return Err(invalid_field_err!("payload", "only sent during the connection"));
+                }
+                BandwidthPhase::Session => BW_STOP_CONTINUOUS,
+                BandwidthPhase::Tunnel => BW_STOP_TUNNEL,
+            },
+            Self::NetworkCharacteristicsResult {
+                base_rtt, bandwidth, ..
+            } => match (base_rtt, bandwidth) {
+                (Some(_), None) => NETCHAR_RESULT_BASE_RTT_AVG_RTT,
+                (None, Some(_)) => NETCHAR_RESULT_BANDWIDTH_AVG_RTT,
+                (Some(_), Some(_)) => NETCHAR_RESULT_ALL,
+                (None, None) => return Err(invalid_field_err!("requestType", "no base RTT nor bandwidth")),
+            },
+        };
+
+        Ok(request_type)
+    }
+
+    /// Size of the fields following the common header
+    fn body_size(&self) -> usize {
+        match self {
+            Self::RttRequest { .. } | Self::BandwidthStart { .. } => 0,
+            Self::BandwidthPayload { payload, .. } => 2 /* payloadLength */ + payload.len(),
+            Self::BandwidthStop { phase, payload, .. } => match phase {
+                BandwidthPhase::Connection => 2 /* payloadLength */ + payload.len(),
+                BandwidthPhase::Session | BandwidthPhase::Tunnel => 0,
+            },
+            Self::NetworkCharacteristicsResult {
+                base_rtt, bandwidth, ..
+            } => 4 /* averageRTT */ + base_rtt.map_or(0, |_| 4) + bandwidth.map_or(0, |_| 4),
+        }
+    }
+}
+
+impl Encode for AutoDetectRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        let request_type = self.request_type()?;
+
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::AUTODETECT_REQ,
+        }
+        .encode(dst)?;
+
+        // headerLength covers the payloadLength field, but not the payload
+        let header_length = match self {
+            Self::BandwidthPayload { .. }
+            | Self::BandwidthStop {
+                phase: BandwidthPhase::Connection,
+                ..
+            } => HEADER_SIZE + 2,
+            _ => HEADER_SIZE + self.body_size(),
+        };
+        dst.write_u8(cast_length!("headerLength", header_length)?);
+        dst.write_u8(REQUEST_HEADER_TYPE_ID);
+        dst.write_u16(self.sequence_number());
+        dst.write_u16(request_type);
+
+        match self {
+            Self::BandwidthPayload { payload, .. }
+            | Self::BandwidthStop {
+                phase: BandwidthPhase::Connection,
+                payload,
+                ..
+            } => {
+                dst.write_u16(cast_length!("payloadLength", payload.len())?);
+                dst.write_slice(payload);
+            }
+            Self::RttRequest { .. } | Self::BandwidthStart { .. } | Self::BandwidthStop { .. } => {}
+            Self::NetworkCharacteristicsResult {
+                base_rtt,
+                bandwidth,
+                average_rtt,
+                ..
+            } => {
+                if let Some(base_rtt) = base_rtt {
+                    dst.write_u32(*base_rtt);
+                }
+                if let Some(bandwidth) = bandwidth {
+                    dst.write_u32(*bandwidth);
+                }
+                dst.write_u32(*average_rtt);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.body_size()
+    }
+}
+
+impl<'de> Decode<'de> for AutoDetectRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_header = BasicSecurityHeader::decode(src)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::AUTODETECT_REQ) {
+            return Err(invalid_field_err!("securityHeader", "got invalid security header"));
+        }
+
+        let _header_length = src.read_u8();
+        if src.read_u8() != REQUEST_HEADER_TYPE_ID {
+            return Err(invalid_field_err!("headerTypeId", "not an auto-detect request"));
+        }
+        let sequence_number = src.read_u16();
+        let request_type = src.read_u16();
+
+        let request = match request_type {
+            RTT_REQUEST_CONNECT_TIME | RTT_REQUEST_CONTINUOUS => Self::RttRequest {
+                sequence_number,
+                phase: if request_type == RTT_REQUEST_CONNECT_TIME {
+                    AutoDetectPhase::Connection
+                } else {
+                    AutoDetectPhase::Session
+                },
+            },
+            BW_START_CONNECT_TIME | BW_START_CONTINUOUS | BW_START_TUNNEL => Self::BandwidthStart {
+                sequence_number,
+                phase: match request_type {
+                    BW_START_CONNECT_TIME => BandwidthPhase::Connection,
+                    BW_START_CONTINUOUS => BandwidthPhase::Session,
+                    _ => BandwidthPhase::Tunnel,
+                },
+            },
+            BW_PAYLOAD => Self::BandwidthPayload {
+                sequence_number,
+                payload: read_payload(src)?,
+            },
+            BW_STOP_CONNECT_TIME => Self::BandwidthStop {
+                sequence_number,
+                phase: BandwidthPhase::Connection,
+                payload: read_payload(src)?,
+            },
+            BW_STOP_CONTINUOUS | BW_STOP_TUNNEL => Self::BandwidthStop {
+                sequence_number,
+                phase: if request_type == BW_STOP_CONTINUOUS {
+                    BandwidthPhase::Session
+                } else {
+                    BandwidthPhase::Tunnel
+                },
+                payload: Vec::new(),
+            },
+            NETCHAR_RESULT_BASE_RTT_AVG_RTT | NETCHAR_RESULT_BANDWIDTH_AVG_RTT | NETCHAR_RESULT_ALL => {
+                let has_base_rtt = request_type != NETCHAR_RESULT_BANDWIDTH_AVG_RTT;
+                let has_bandwidth = request_type != NETCHAR_RESULT_BASE_RTT_AVG_RTT;
+
+                ensure_size!(in: src, size: 4 + if has_base_rtt && has_bandwidth { 8 } else { 4 });
+
+                let base_rtt = has_base_rtt.then(|| src.read_u32());
+                let bandwidth = has_bandwidth.then(|| src.read_u32());
+                let average_rtt = src.read_u32();
+
+                Self::NetworkCharacteristicsResult {
+                    sequence_number,
+                    base_rtt,
+                    bandwidth,
+                    average_rtt,
+                }
+            }
+            _ => return Err(invalid_field_err!("requestType", "invalid auto-detect request type")),
+        };
+
+        Ok(request)
+    }
+}
+
+/// [MS-RDPBCGR] 2.2.14.4 Auto-Detect Response PDU
+///
+/// Sent by the client on the message channel in answer to an [`AutoDetectRequest`], with the same
+/// sequence number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoDetectResponse {
+    /// [MS-RDPBCGR] 2.2.14.2.1 RTT Measure Response
+    RttResponse { sequence_number: u16 },
+    /// [MS-RDPBCGR] 2.2.14.2.2 Bandwidth Measure Results
+    ///
+    /// `time_delta` is the time in milliseconds between the reception of the start and of the stop
+    /// of the measure, and `byte_count` the number of bytes received in the meantime.
+    BandwidthResults {
+        sequence_number: u16,
+        phase: AutoDetectPhase,
+        time_delta: u32,
+        byte_count: u32,
+    },
+    /// [MS-RDPBCGR] 2.2.14.2.3 Network Characteristics Sync
+    ///
+    /// Sent during the connection by a reconnecting client, instead of running the measures again.
+    NetworkCharacteristicsSync {
+        sequence_number: u16,
+        bandwidth: u32,
+        rtt: u32,
+    },
+}
+
+impl AutoDetectResponse {
+    const NAME: &'static str = "AutoDetectResponse";
+
+    const FIXED_PART_SIZE: usize = BasicSecurityHeader::FIXED_PART_SIZE + HEADER_SIZE;
+
+    pub fn sequence_number(&self) -> u16 {
+        match self {
+            Self::RttResponse { sequence_number }
+            | Self::BandwidthResults { sequence_number, .. }
+            | Self::NetworkCharacteristicsSync { sequence_number, .. } => *sequence_number,
+        }
+    }
+
+    fn body_size(&self) -> usize {
+        match self {
+            Self::RttResponse { .. } => 0,
+            Self::BandwidthResults { .. } | Self::NetworkCharacteristicsSync { .. } => 8,
+        }
+    }
+}
+
+impl Encode for AutoDetectResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::AUTODETECT_RSP,
+        }
+        .encode(dst)?;
+
+        let response_type = match self {
+            Self::RttResponse { .. } => RTT_RESPONSE,
+            Self::BandwidthResults {
+                phase: AutoDetectPhase::Connection,
+                ..
+            } => BW_RESULTS_CONNECT_TIME,
+            Self::BandwidthResults {
+                phase: AutoDetectPhase::Session,
+                ..
+            } => BW_RESULTS_CONTINUOUS,
+            Self::NetworkCharacteristicsSync { .. } => NETCHAR_SYNC,
+        };
+
+        dst.write_u8(cast_length!("headerLength", HEADER_SIZE + self.body_size())?);
+        dst.write_u8(RESPONSE_HEADER_TYPE_ID);
+        dst.write_u16(self.sequence_number());
+        dst.write_u16(response_type);
+
+        match self {
+            Self::RttResponse { .. } => {}
+            Self::BandwidthResults {
+                time_delta, byte_count, ..
+            } => {
+                dst.write_u32(*time_delta);
+                dst.write_u32(*byte_count);
+            }
+            Self::NetworkCharacteristicsSync { bandwidth, rtt, .. } => {
+                dst.write_u32(*bandwidth);
+                dst.write_u32(*rtt);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.body_size()
+    }
+}
+
+impl<'de> Decode<'de> for AutoDetectResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_header = BasicSecurityHeader::decode(src)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::AUTODETECT_RSP) {
+            return Err(invalid_field_err!("securityHeader", "got invalid security header"));
+        }
+
+        let _header_length = src.read_u8();
+        if src.read_u8() != RESPONSE_HEADER_TYPE_ID {
+            return Err(invalid_field_err!("headerTypeId", "not an auto-detect response"));
+        }
+        let sequence_number = src.read_u16();
+        let response_type = src.read_u16();
+
+        let response = match response_type {
+            RTT_RESPONSE => Self::RttResponse { sequence_number },
+            BW_RESULTS_CONNECT_TIME | BW_RESULTS_CONTINUOUS => {
+                ensure_size!(in: src, size: 8);
+
+                Self::BandwidthResults {
+                    sequence_number,
+                    phase: if response_type == BW_RESULTS_CONNECT_TIME {
+                        AutoDetectPhase::Connection
+                    } else {
+                        AutoDetectPhase::Session
+                    },
+                    time_delta: src.read_u32(),
+                    byte_count: src.read_u32(),
+                }
+            }
+            NETCHAR_SYNC => {
+                ensure_size!(in: src, size: 8);
+
+                Self::NetworkCharacteristicsSync {
+                    sequence_number,
+                    bandwidth: src.read_u32(),
+                    rtt: src.read_u32(),
+                }
+            }
+            _ => return Err(invalid_field_err!("responseType", "invalid auto-detect response type")),
+        };
+
+        Ok(response)
+    }
+}
+
+/// Whether a measure runs during the connection sequence or during the session
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AutoDetectPhase {
+    Connection,
+    Session,
+}
+
+/// Phase of a bandwidth measure
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BandwidthPhase {
+    /// During the connection sequence, with payloads sent by the server
+    Connection,
+    /// During the session, over the data sent by the server anyway
+    Session,
+    /// During the session, over a multitransport tunnel (MS-RDPEMT)
+    Tunnel,
+}
+
+fn read_payload(src: &mut ReadCursor<'_>) -> DecodeResult<Vec<u8>> {
+    ensure_size!(in: src, size: 2);
+    let payload_length = usize::from(src.read_u16());
+
+    ensure_size!(in: src, size: payload_length);
+
+    Ok(src.read_slice(payload_length).to_vec())
+}
@@ -33,27 +33,34 @@ const NSCODEC_LENGTH: usize = 3;
 const CODEC_STATIC_DATA_LENGTH: usize = 19;
 
 #[rustfmt::skip]
-const GUID_NSCODEC: Guid = Guid(0xca8d_1bb9, 0x000f, 0x154f, 0x58, 0x9f, 0xae, 0x2d, 0x1a, 0x87, 0xe2, 0xd6);
+pub const GUID_NSCODEC: Guid = Guid(0xca8d_1bb9, 0x000f, 0x154f, 0x58, 0x9f, 0xae, 0x2d, 0x1a, 0x87, 0xe2, 0xd6);
 #[rustfmt::skip]
-const GUID_REMOTEFX: Guid = Guid(0x7677_2f12, 0xbd72, 0x4463, 0xaf, 0xb3, 0xb7, 0x3c, 0x9c, 0x6f, 0x78, 0x86);
+pub const GUID_REMOTEFX: Guid = Guid(0x7677_2f12, 0xbd72, 0x4463, 0xaf, 0xb3, 0xb7, 0x3c, 0x9c, 0x6f, 0x78, 0x86);
 #[rustfmt::skip]
-const GUID_IMAGE_REMOTEFX: Guid = Guid(0x2744_ccd4, 0x9d8a, 0x4e74, 0x80, 0x3c, 0x0e, 0xcb, 0xee, 0xa1, 0x9c, 0x54);
+pub const GUID_IMAGE_REMOTEFX: Guid = Guid(0x2744_ccd4, 0x9d8a, 0x4e74, 0x80, 0x3c, 0x0e, 0xcb, 0xee, 0xa1, 0x9c, 0x54);
 #[rustfmt::skip]
-const GUID_IGNORE: Guid = Guid(0x9c43_51a6, 0x3535, 0x42ae, 0x91, 0x0c, 0xcd, 0xfc, 0xe5, 0x76, 0x0b, 0x58);
+pub const GUID_IGNORE: Guid = Guid(0x9c43_51a6, 0x3535, 0x42ae, 0x91, 0x0c, 0xcd, 0xfc, 0xe5, 0x76, 0x0b, 0x58);
 #[rustfmt::skip]
 #[cfg(feature="qoi")]
-const GUID_QOI: Guid = Guid(0x4dae_9af8, 0xb399, 0x4df6, 0xb4, 0x3a, 0x66, 0x2f, 0xd9, 0xc0, 0xf5, 0xd6);
+pub const GUID_QOI: Guid = Guid(0x4dae_9af8, 0xb399, 0x4df6, 0xb4, 0x3a, 0x66, 0x2f, 0xd9, 0xc0, 0xf5, 0xd6);
 #[rustfmt::skip]
 #[cfg(feature="qoiz")]
-const GUID_QOIZ: Guid = Guid(0x229c_c6dc, 0xa860, 0x4b52, 0xb4, 0xd8, 0x05, 0x3a, 0x22, 0xb3, 0x89, 0x2b);
+pub const GUID_QOIZ: Guid = Guid(0x229c_c6dc, 0xa860, 0x4b52, 0xb4, 0xd8, 0x05, 0x3a, 0x22, 0xb3, 0x89, 0x2b);
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Guid(u32, u16, u16, u8, u8, u8, u8, u8, u8, u8, u8);
 
 impl Guid {
     const NAME: &'static str = "Guid";
 
     const FIXED_PART_SIZE: usize = 16;
+
+    /// Builds a GUID from its canonical `{data1-data2-data3-data4}` components
+    pub const fn new(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        Self(
+            data1, data2, data3, data4[0], data4[1], data4[2], data4[3], data4[4], data4[5], data4[6], data4[7],
+        )
+    }
 }
 
 impl Encode for Guid {
@@ -113,6 +120,29 @@ impl BitmapCodecs {
     const NAME: &'static str = "BitmapCodecs";
 
     const FIXED_PART_SIZE: usize = 1 /* len */;
+
+    /// Returns the first codec advertised with the given GUID
+    pub fn find(&self, guid: Guid) -> Option<&Codec> {
+        self.0.iter().find(|codec| codec.guid() == Some(guid))
+    }
+
+    /// Codecs advertised by the client (`self`) which are also supported by the server
+    ///
+    /// The client order (i.e.: its preference) and the client-assigned codec IDs are kept,
+    /// as the server must use those IDs when sending surface commands. `Ignore` entries
+    /// and codecs with an unrecognized GUID on either side are dropped.
+    #[must_use]
+    pub fn negotiate(&self, server: &BitmapCodecs) -> BitmapCodecs {
+        let codecs = self
+            .0
+            .iter()
+            .filter(|codec| !matches!(codec.property, CodecProperty::Ignore))
+            .filter(|codec| codec.guid().is_some_and(|guid| server.find(guid).is_some()))
+            .cloned()
+            .collect();
+
+        BitmapCodecs(codecs)
+    }
 }
 
 impl Encode for BitmapCodecs {
@@ -162,23 +192,18 @@ impl Codec {
     const NAME: &'static str = "Codec";
 
     const FIXED_PART_SIZE: usize = CODEC_STATIC_DATA_LENGTH;
+
+    /// GUID identifying this codec on the wire, if known
+    pub fn guid(&self) -> Option<Guid> {
+        self.property.guid()
+    }
 }
 
 impl Encode for Codec {
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size());
 
-        let guid = match &self.property {
-            CodecProperty::NsCodec(_) => GUID_NSCODEC,
-            CodecProperty::RemoteFx(_) => GUID_REMOTEFX,
-            CodecProperty::ImageRemoteFx(_) => GUID_IMAGE_REMOTEFX,
-            CodecProperty::Ignore => GUID_IGNORE,
-            #[cfg(feature = "qoi")]
-            CodecProperty::Qoi => GUID_QOI,
-            #[cfg(feature = "qoiz")]
-            CodecProperty::QoiZ => GUID_QOIZ,
-            _ => return Err(other_err!("invalid codec")),
-        };
+        let guid = self.guid().ok_or_else(|| other_err!("invalid codec"))?;
         guid.encode(dst)?;
 
         dst.write_u8(self.id);
@@ -219,6 +244,10 @@ impl Encode for Codec {
             #[cfg(feature = "qoiz")]
             CodecProperty::QoiZ => dst.write_u16(0),
             CodecProperty::Ignore => dst.write_u16(0),
+            CodecProperty::Unknown { data, .. } => {
+                dst.write_u16(cast_length!("len", data.len())?);
+                dst.write_slice(data);
+            }
             CodecProperty::None => dst.write_u16(0),
         };
 
@@ -246,6 +275,7 @@ impl Encode for Codec {
                 #[cfg(feature = "qoiz")]
                 CodecProperty::QoiZ => 0,
                 CodecProperty::Ignore => 0,
+                CodecProperty::Unknown { data, .. } => data.len(),
                 CodecProperty::None => 0,
             }
     }
@@ -296,7 +326,10 @@ impl<'de> Decode<'de> for Codec {
                 }
                 CodecProperty::QoiZ
             }
-            _ => CodecProperty::None,
+            _ => CodecProperty::Unknown {
+                guid,
+                data: property_buffer.to_vec(),
+            },
         };
 
         Ok(Self { id, property })
@@ -319,9 +352,32 @@ pub enum CodecProperty {
     Qoi,
     #[cfg(feature = "qoiz")]
     QoiZ,
+    /// Codec not known to this implementation, kept as-is so it can be re-encoded
+    Unknown {
+        guid: Guid,
+        data: Vec<u8>,
+    },
     None,
 }
 
+impl CodecProperty {
+    /// GUID associated with this property, if any
+    pub fn guid(&self) -> Option<Guid> {
+        match self {
+            CodecProperty::NsCodec(_) => Some(GUID_NSCODEC),
+            CodecProperty::RemoteFx(_) => Some(GUID_REMOTEFX),
+            CodecProperty::ImageRemoteFx(_) => Some(GUID_IMAGE_REMOTEFX),
+            CodecProperty::Ignore => Some(GUID_IGNORE),
+            #[cfg(feature = "qoi")]
+            CodecProperty::Qoi => Some(GUID_QOI),
+            #[cfg(feature = "qoiz")]
+            CodecProperty::QoiZ => Some(GUID_QOIZ),
+            CodecProperty::Unknown { guid, .. } => Some(*guid),
+            CodecProperty::None => None,
+        }
+    }
+}
+
 /// The NsCodec structure advertises properties of the NSCodec Bitmap Codec.
 ///
 /// # Fields
@@ -547,3 +547,81 @@ fn ns_codec_with_too_low_color_loss_level_handled_correctly() {
 
     assert_eq!(codec, decode(codec_buffer.as_slice()).unwrap());
 }
+
+#[test]
+fn codec_with_unknown_guid_round_trips() {
+    let codec_buffer = vec![
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, // guid
+        0x05, // codec id
+        0x02, 0x00, // codec properties len
+        0xaa, 0xbb, // codec properties
+    ];
+
+    let codec: Codec = decode(codec_buffer.as_slice()).unwrap();
+    assert_eq!(
+        codec,
+        Codec {
+            id: 5,
+            property: CodecProperty::Unknown {
+                guid: Guid::new(
+                    0x0403_0201,
+                    0x0605,
+                    0x0807,
+                    [0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10]
+                ),
+                data: vec![0xaa, 0xbb],
+            },
+        }
+    );
+    assert_eq!(codec_buffer, encode_vec(&codec).unwrap());
+}
+
+#[test]
+fn negotiate_keeps_client_order_and_ids() {
+    let client = BitmapCodecs(vec![
+        Codec {
+            id: 0,
+            property: CodecProperty::Ignore,
+        },
+        Codec {
+            id: 2,
+            property: CodecProperty::NsCodec(NsCodec {
+                is_dynamic_fidelity_allowed: true,
+                is_subsampling_allowed: true,
+                color_loss_level: 3,
+            }),
+        },
+        Codec {
+            id: 4,
+            property: CodecProperty::Unknown {
+                guid: Guid(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11),
+                data: Vec::new(),
+            },
+        },
+        CODEC.clone(),
+    ]);
+    let server = BitmapCodecs(vec![
+        Codec {
+            id: 0,
+            property: CodecProperty::Ignore,
+        },
+        Codec {
+            id: 1,
+            property: CodecProperty::RemoteFx(RemoteFxContainer::ServerContainer(1)),
+        },
+        Codec {
+            id: 2,
+            property: CodecProperty::NsCodec(NsCodec {
+                is_dynamic_fidelity_allowed: false,
+                is_subsampling_allowed: false,
+                color_loss_level: 1,
+            }),
+        },
+    ]);
+
+    let negotiated = client.negotiate(&server);
+
+    assert_eq!(negotiated.0.len(), 2);
+    assert_eq!(negotiated.0[0], client.0[1]);
+    assert_eq!(negotiated.0[1], client.0[3]);
+}
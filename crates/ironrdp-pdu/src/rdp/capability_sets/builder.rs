@@ -0,0 +1,102 @@
+use core::mem;
+
+use ironrdp_core::{invalid_field_err, EncodeResult};
+
+use super::{Bitmap, CapabilitySet, DemandActive};
+use crate::gcc::MAX_DESKTOP_SIZE;
+
+const BITS_PER_PIXEL: [u16; 5] = [8, 15, 16, 24, 32];
+
+/// Builder for [`DemandActive`], as found in both the Demand Active and Confirm Active PDUs
+///
+/// Rejects duplicated capability sets and out of range bitmap capabilities.
+///
+/// ```
+/// # use ironrdp_pdu::rdp::capability_sets::{CapabilitySet, DemandActiveBuilder, General};
+/// let pdu = DemandActiveBuilder::new("RDP")
+///     .capability(CapabilitySet::General(General::default()))
+///     .build()?;
+///
+/// assert_eq!(pdu.capability_sets.len(), 1);
+/// # Ok::<(), ironrdp_core::EncodeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DemandActiveBuilder {
+    inner: DemandActive,
+}
+
+impl DemandActiveBuilder {
+    pub fn new(source_descriptor: impl Into<String>) -> Self {
+        Self {
+            inner: DemandActive {
+                source_descriptor: source_descriptor.into(),
+                capability_sets: Vec::new(),
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn capability(mut self, capability_set: CapabilitySet) -> Self {
+        self.inner.capability_sets.push(capability_set);
+        self
+    }
+
+    #[must_use]
+    pub fn capabilities(mut self, capability_sets: impl IntoIterator<Item = CapabilitySet>) -> Self {
+        self.inner.capability_sets.extend(capability_sets);
+        self
+    }
+
+    pub fn build(self) -> EncodeResult<DemandActive> {
+        let pdu = self.inner;
+
+        if !pdu.source_descriptor.is_ascii() || u16::try_from(pdu.source_descriptor.len() + 1).is_err() {
+            return Err(invalid_field_err!(
+                DemandActive::NAME,
+                "sourceDescriptor",
+                "must be an ASCII string shorter than 65535 bytes"
+            ));
+        }
+
+        for (idx, capability_set) in pdu.capability_sets.iter().enumerate() {
+            if pdu.capability_sets[..idx]
+                .iter()
+                .any(|other| mem::discriminant(other) == mem::discriminant(capability_set))
+            {
+                return Err(invalid_field_err!(
+                    DemandActive::NAME,
+                    "capabilitySets",
+                    "capability set advertised more than once"
+                ));
+            }
+
+            if let CapabilitySet::Bitmap(bitmap) = capability_set {
+                validate_bitmap(bitmap)?;
+            }
+        }
+
+        Ok(pdu)
+    }
+}
+
+fn validate_bitmap(bitmap: &Bitmap) -> EncodeResult<()> {
+    if !BITS_PER_PIXEL.contains(&bitmap.pref_bits_per_pix) {
+        return Err(invalid_field_err!(
+            "Bitmap",
+            "preferredBitsPerPixel",
+            "must be 8, 15, 16, 24 or 32"
+        ));
+    }
+
+    if !(1..=MAX_DESKTOP_SIZE).contains(&bitmap.desktop_width)
+        || !(1..=MAX_DESKTOP_SIZE).contains(&bitmap.desktop_height)
+    {
+        return Err(invalid_field_err!(
+            "Bitmap",
+            "desktopSize",
+            "must be in the 1..=8192 range"
+        ));
+    }
+
+    Ok(())
+}
@@ -14,6 +14,7 @@ mod bitmap;
 mod bitmap_cache;
 mod bitmap_codecs;
 mod brush;
+mod builder;
 mod frame_acknowledge;
 mod general;
 mod glyph_cache;
@@ -31,12 +32,18 @@ pub use self::bitmap::{Bitmap, BitmapDrawingFlags};
 pub use self::bitmap_cache::{
     BitmapCache, BitmapCacheRev2, CacheEntry, CacheFlags, CellInfo, BITMAP_CACHE_ENTRIES_NUM,
 };
+#[cfg(feature = "qoi")]
+pub use self::bitmap_codecs::GUID_QOI;
+#[cfg(feature = "qoiz")]
+pub use self::bitmap_codecs::GUID_QOIZ;
 pub use self::bitmap_codecs::{
     client_codecs_capabilities, server_codecs_capabilities, BitmapCodecs, CaptureFlags, Codec, CodecId, CodecProperty,
     EntropyBits, Guid, NsCodec, RemoteFxContainer, RfxCaps, RfxCapset, RfxClientCapsContainer, RfxICap, RfxICapFlags,
-    CODEC_ID_NONE, CODEC_ID_QOI, CODEC_ID_QOIZ, CODEC_ID_REMOTEFX,
+    CODEC_ID_NONE, CODEC_ID_QOI, CODEC_ID_QOIZ, CODEC_ID_REMOTEFX, GUID_IGNORE, GUID_IMAGE_REMOTEFX, GUID_NSCODEC,
+    GUID_REMOTEFX,
 };
 pub use self::brush::{Brush, SupportLevel};
+pub use self::builder::DemandActiveBuilder;
 pub use self::frame_acknowledge::FrameAcknowledge;
 pub use self::general::{General, GeneralExtraFlags, MajorPlatformType, MinorPlatformType, PROTOCOL_VER};
 pub use self::glyph_cache::{CacheDefinition, GlyphCache, GlyphSupportLevel, GLYPH_CACHE_NUM};
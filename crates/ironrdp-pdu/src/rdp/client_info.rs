@@ -673,6 +673,46 @@ impl Default for PerformanceFlags {
     }
 }
 
+/// Visual effects the client asks the server to render, as given by the [`PerformanceFlags`]
+///
+/// Clients on slow links typically disable the wallpaper and the animations, the server can render the
+/// desktop accordingly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VisualExperience {
+    pub wallpaper: bool,
+    /// Show the contents of the windows while they are dragged
+    pub full_window_drag: bool,
+    pub menu_animations: bool,
+    pub theming: bool,
+    pub cursor_shadow: bool,
+    /// Blink the text cursor
+    pub cursor_blinking: bool,
+    pub font_smoothing: bool,
+    pub desktop_composition: bool,
+}
+
+impl Default for VisualExperience {
+    /// Experience of a client not sending performance flags, all the effects except the ones to enable explicitly
+    fn default() -> Self {
+        Self::from(PerformanceFlags::empty())
+    }
+}
+
+impl From<PerformanceFlags> for VisualExperience {
+    fn from(flags: PerformanceFlags) -> Self {
+        Self {
+            wallpaper: !flags.contains(PerformanceFlags::DISABLE_WALLPAPER),
+            full_window_drag: !flags.contains(PerformanceFlags::DISABLE_FULLWINDOWDRAG),
+            menu_animations: !flags.contains(PerformanceFlags::DISABLE_MENUANIMATIONS),
+            theming: !flags.contains(PerformanceFlags::DISABLE_THEMING),
+            cursor_shadow: !flags.contains(PerformanceFlags::DISABLE_CURSOR_SHADOW),
+            cursor_blinking: !flags.contains(PerformanceFlags::DISABLE_CURSORSETTINGS),
+            font_smoothing: flags.contains(PerformanceFlags::ENABLE_FONT_SMOOTHING),
+            desktop_composition: flags.contains(PerformanceFlags::ENABLE_DESKTOP_COMPOSITION),
+        }
+    }
+}
+
 #[repr(transparent)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct AddressFamily(u16);
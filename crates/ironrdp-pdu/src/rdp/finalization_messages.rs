@@ -1,7 +1,7 @@
 use bitflags::bitflags;
 use ironrdp_core::{
-    cast_length, ensure_fixed_part_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor,
-    WriteCursor,
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, read_padding, write_padding, Decode,
+    DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
 };
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive as _;
@@ -13,6 +13,11 @@ const CONTROL_PDU_SIZE: usize = 2 + 2 + 4;
 const FONT_PDU_SIZE: usize = 2 * 4;
 const SYNCHRONIZE_MESSAGE_TYPE: u16 = 1;
 const MAX_MONITOR_COUNT: u32 = 64;
+// numEntriesCache0-4, totalEntriesCache0-4, bBitMask and padding
+const PERSISTENT_KEY_LIST_PDU_SIZE: usize = 2 * 5 + 2 * 5 + 1 + 3;
+const PERSISTENT_KEY_SIZE: usize = 8;
+
+pub const PERSISTENT_KEY_LIST_CACHE_COUNT: usize = 5;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SynchronizePdu {
@@ -232,6 +237,80 @@ impl<'de> Decode<'de> for MonitorLayoutPdu {
     }
 }
 
+/// [MS-RDPBCGR] 2.2.1.17.1 Persistent Key List PDU Data (TS_BITMAPCACHE_PERSISTENT_LIST_PDU)
+///
+/// Lists the keys of the bitmaps stored in the persistent bitmap caches of the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersistentKeyListPdu {
+    /// Total number of keys in each cache, over all the PDUs of the sequence
+    pub total_entries: [u16; PERSISTENT_KEY_LIST_CACHE_COUNT],
+    pub flags: PersistentKeyListFlags,
+    /// Keys in this PDU for each cache, in cache index order
+    pub entries: [Vec<u64>; PERSISTENT_KEY_LIST_CACHE_COUNT],
+}
+
+impl PersistentKeyListPdu {
+    const NAME: &'static str = "PersistentKeyListPdu";
+
+    const FIXED_PART_SIZE: usize = PERSISTENT_KEY_LIST_PDU_SIZE;
+}
+
+impl Encode for PersistentKeyListPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        for entries in self.entries.iter() {
+            dst.write_u16(cast_length!("numEntriesCache", entries.len())?);
+        }
+        for total in self.total_entries {
+            dst.write_u16(total);
+        }
+        dst.write_u8(self.flags.bits());
+        write_padding!(dst, 3);
+
+        for key in self.entries.iter().flatten() {
+            dst.write_u64(*key);
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.entries.iter().map(Vec::len).sum::<usize>() * PERSISTENT_KEY_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for PersistentKeyListPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let mut num_entries = [0; PERSISTENT_KEY_LIST_CACHE_COUNT];
+        for num in num_entries.iter_mut() {
+            *num = usize::from(src.read_u16());
+        }
+        let mut total_entries = [0; PERSISTENT_KEY_LIST_CACHE_COUNT];
+        for total in total_entries.iter_mut() {
+            *total = src.read_u16();
+        }
+        let flags = PersistentKeyListFlags::from_bits_truncate(src.read_u8());
+        read_padding!(src, 3);
+
+        ensure_size!(in: src, size: num_entries.iter().sum::<usize>() * PERSISTENT_KEY_SIZE);
+
+        let entries = num_entries.map(|num| core::iter::repeat_with(|| src.read_u64()).take(num).collect());
+
+        Ok(Self {
+            total_entries,
+            flags,
+            entries,
+        })
+    }
+}
+
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 pub enum ControlAction {
@@ -258,3 +337,11 @@ bitflags! {
         const LAST = 2;
     }
 }
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct PersistentKeyListFlags: u8 {
+        const PERSIST_FIRST_PDU = 0x01;
+        const PERSIST_LAST_PDU = 0x02;
+    }
+}
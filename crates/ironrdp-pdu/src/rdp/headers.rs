@@ -13,6 +13,7 @@ use crate::rdp::client_info;
 use crate::rdp::finalization_messages::{ControlPdu, FontPdu, MonitorLayoutPdu, SynchronizePdu};
 use crate::rdp::refresh_rectangle::RefreshRectanglePdu;
 use crate::rdp::server_error_info::ServerSetErrorInfoPdu;
+use crate::rdp::server_redirection::EnhancedSecurityServerRedirection;
 use crate::rdp::session_info::SaveSessionInfoPdu;
 use crate::rdp::suppress_output::SuppressOutputPdu;
 
@@ -20,6 +21,7 @@ pub const BASIC_SECURITY_HEADER_SIZE: usize = 4;
 pub const SHARE_DATA_HEADER_COMPRESSION_MASK: u8 = 0xF;
 const SHARE_CONTROL_HEADER_MASK: u16 = 0xF;
 const SHARE_CONTROL_HEADER_SIZE: usize = 2 * 3 + 4;
+const SHARE_ID_SIZE: usize = 4;
 
 const PROTOCOL_VERSION: u16 = 0x10;
 
@@ -83,21 +85,30 @@ impl ShareControlHeader {
     const NAME: &'static str = "ShareControlHeader";
 
     const FIXED_PART_SIZE: usize = SHARE_CONTROL_HEADER_SIZE;
+
+    /// The header of the server redirection has no share ID ([MS-RDPBCGR] 2.2.13.3.1)
+    fn header_size(pdu_type: ShareControlPduType) -> usize {
+        if pdu_type == ShareControlPduType::ServerRedirect {
+            SHARE_CONTROL_HEADER_SIZE - SHARE_ID_SIZE
+        } else {
+            SHARE_CONTROL_HEADER_SIZE
+        }
+    }
 }
 
 impl Encode for ShareControlHeader {
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size());
 
-        let pdu_type_with_version = PROTOCOL_VERSION | self.share_control_pdu.share_header_type().as_u16();
+        let pdu_type = self.share_control_pdu.share_header_type();
+        let pdu_type_with_version = PROTOCOL_VERSION | pdu_type.as_u16();
 
-        dst.write_u16(cast_length!(
-            "len",
-            self.share_control_pdu.size() + SHARE_CONTROL_HEADER_SIZE
-        )?);
+        dst.write_u16(cast_length!("len", self.size())?);
         dst.write_u16(pdu_type_with_version);
         dst.write_u16(self.pdu_source);
-        dst.write_u32(self.share_id);
+        if pdu_type != ShareControlPduType::ServerRedirect {
+            dst.write_u32(self.share_id);
+        }
 
         self.share_control_pdu.encode(dst)
     }
@@ -107,7 +118,7 @@ impl Encode for ShareControlHeader {
     }
 
     fn size(&self) -> usize {
-        Self::FIXED_PART_SIZE + self.share_control_pdu.size()
+        Self::header_size(self.share_control_pdu.share_header_type()) + self.share_control_pdu.size()
     }
 }
 
@@ -115,10 +126,10 @@ impl<'de> Decode<'de> for ShareControlHeader {
     fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
         ensure_fixed_part_size!(in: src);
 
+        let start = src.pos();
         let total_length = usize::from(src.read_u16());
         let pdu_type_with_version = src.read_u16();
         let pdu_source = src.read_u16();
-        let share_id = src.read_u32();
 
         let pdu_type = ShareControlPduType::from_u16(pdu_type_with_version & SHARE_CONTROL_HEADER_MASK)
             .ok_or_else(|| invalid_field_err!("pdu_type", "invalid pdu type"))?;
@@ -127,6 +138,12 @@ impl<'de> Decode<'de> for ShareControlHeader {
             return Err(invalid_field_err!("pdu_version", "invalid PDU version"));
         }
 
+        let share_id = if pdu_type == ShareControlPduType::ServerRedirect {
+            0
+        } else {
+            src.read_u32()
+        };
+
         let share_pdu = ShareControlPdu::from_type(src, pdu_type)?;
         let header = Self {
             share_control_pdu: share_pdu,
@@ -149,6 +166,15 @@ impl<'de> Decode<'de> for ShareControlHeader {
                 ensure_size!(in: src, size: padding);
                 read_padding!(src, padding);
             }
+        } else if pdu_type == ShareControlPduType::ServerRedirect {
+            // The server redirection may end with a padding byte (pad1Octet).
+            let read = src.pos() - start;
+
+            if total_length > read {
+                let padding = total_length - read;
+                ensure_size!(in: src, size: padding);
+                read_padding!(src, padding);
+            }
         }
 
         Ok(header)
@@ -161,6 +187,7 @@ pub enum ShareControlPdu {
     ClientConfirmActive(ClientConfirmActive),
     Data(ShareDataHeader),
     ServerDeactivateAll(ServerDeactivateAll),
+    ServerRedirection(EnhancedSecurityServerRedirection),
 }
 
 impl ShareControlPdu {
@@ -172,6 +199,7 @@ impl ShareControlPdu {
             ShareControlPdu::ClientConfirmActive(_) => "Client Confirm Active PDU",
             ShareControlPdu::Data(_) => "Data PDU",
             ShareControlPdu::ServerDeactivateAll(_) => "Server Deactivate All PDU",
+            ShareControlPdu::ServerRedirection(_) => "Server Redirection PDU",
         }
     }
 
@@ -181,6 +209,7 @@ impl ShareControlPdu {
             ShareControlPdu::ClientConfirmActive(_) => ShareControlPduType::ConfirmActivePdu,
             ShareControlPdu::Data(_) => ShareControlPduType::DataPdu,
             ShareControlPdu::ServerDeactivateAll(_) => ShareControlPduType::DeactivateAllPdu,
+            ShareControlPdu::ServerRedirection(_) => ShareControlPduType::ServerRedirect,
         }
     }
 
@@ -196,7 +225,9 @@ impl ShareControlPdu {
             ShareControlPduType::DeactivateAllPdu => {
                 Ok(ShareControlPdu::ServerDeactivateAll(ServerDeactivateAll::decode(src)?))
             }
-            _ => Err(invalid_field_err!("share_type", "unexpected share control PDU type")),
+            ShareControlPduType::ServerRedirect => Ok(ShareControlPdu::ServerRedirection(
+                EnhancedSecurityServerRedirection::decode(src)?,
+            )),
         }
     }
 }
@@ -208,6 +239,7 @@ impl Encode for ShareControlPdu {
             ShareControlPdu::ClientConfirmActive(pdu) => pdu.encode(dst),
             ShareControlPdu::Data(share_data_header) => share_data_header.encode(dst),
             ShareControlPdu::ServerDeactivateAll(deactivate_all) => deactivate_all.encode(dst),
+            ShareControlPdu::ServerRedirection(redirection) => redirection.encode(dst),
         }
     }
 
@@ -221,6 +253,7 @@ impl Encode for ShareControlPdu {
             ShareControlPdu::ClientConfirmActive(pdu) => pdu.size(),
             ShareControlPdu::Data(share_data_header) => share_data_header.size(),
             ShareControlPdu::ServerDeactivateAll(deactivate_all) => deactivate_all.size(),
+            ShareControlPdu::ServerRedirection(redirection) => redirection.size(),
         }
     }
 }
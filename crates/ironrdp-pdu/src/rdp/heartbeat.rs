@@ -0,0 +1,77 @@
+use ironrdp_core::{
+    ensure_fixed_part_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags};
+
+/// [MS-RDPBCGR] 2.2.16.1 Heartbeat PDU
+///
+/// Sent by the server on the message channel every `period` seconds, so that the client detects a
+/// connection that stopped working. After `warning_count` missed heartbeats, the client should warn
+/// the user, and after `reconnect_count`, reconnect.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HeartbeatPdu {
+    /// Time between two heartbeats in seconds, 0 disabling the heartbeats
+    pub period: u8,
+    /// Number of missed heartbeats before warning the user (count1)
+    pub warning_count: u8,
+    /// Number of missed heartbeats before reconnecting (count2)
+    pub reconnect_count: u8,
+}
+
+impl HeartbeatPdu {
+    const NAME: &'static str = "HeartbeatPdu";
+
+    const FIXED_PART_SIZE: usize = BasicSecurityHeader::FIXED_PART_SIZE
+        + 1 /* reserved */
+        + 1 /* period */
+        + 1 /* count1 */
+        + 1 /* count2 */;
+}
+
+impl Encode for HeartbeatPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::HEARTBEAT,
+        }
+        .encode(dst)?;
+        dst.write_u8(0); // reserved
+        dst.write_u8(self.period);
+        dst.write_u8(self.warning_count);
+        dst.write_u8(self.reconnect_count);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for HeartbeatPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_header = BasicSecurityHeader::decode(src)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::HEARTBEAT) {
+            return Err(invalid_field_err!("securityHeader", "got invalid security header"));
+        }
+
+        let _reserved = src.read_u8();
+        let period = src.read_u8();
+        let warning_count = src.read_u8();
+        let reconnect_count = src.read_u8();
+
+        Ok(Self {
+            period,
+            warning_count,
+            reconnect_count,
+        })
+    }
+}
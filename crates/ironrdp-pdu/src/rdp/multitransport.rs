@@ -0,0 +1,203 @@
+//! Multitransport bootstrapping PDUs ([MS-RDPBCGR] 2.2.15)
+//!
+//! Only the messages of the bootstrapping are provided, so that a side channel request can be declined: the client
+//! connector answers any Initiate Multitransport Request PDU with E_ABORT. The UDP transports themselves (MS-RDPEUDP
+//! and MS-RDPEUDP2, in reliable or lossy mode with FEC), the tunnels on top of them (MS-RDPEMT), and the routing of
+//! the graphics and audio channels over these tunnels are not implemented: IronRDP never advertises the multitransport
+//! support in the GCC blocks, and all the data stays on the main transport.
+
+use ironrdp_core::{
+    ensure_fixed_part_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags};
+
+/// [MS-RDPBCGR] 2.2.15.1 Initiate Multitransport Request PDU
+///
+/// Sent by the server on the message channel to ask the client to set up a UDP side channel
+/// (MS-RDPEMT), the client presenting `security_cookie` on the new transport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitiateMultitransportRequest {
+    pub request_id: u32,
+    pub requested_protocol: MultitransportProtocol,
+    pub security_cookie: [u8; 16],
+}
+
+impl InitiateMultitransportRequest {
+    const NAME: &'static str = "InitiateMultitransportRequest";
+
+    const FIXED_PART_SIZE: usize = BasicSecurityHeader::FIXED_PART_SIZE
+        + 4 /* requestId */
+        + 2 /* requestedProtocol */
+        + 2 /* reserved */
+        + 16 /* securityCookie */;
+}
+
+impl Encode for InitiateMultitransportRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::TRANSPORT_REQ,
+        }
+        .encode(dst)?;
+        dst.write_u32(self.request_id);
+        dst.write_u16(self.requested_protocol.as_u16());
+        dst.write_u16(0); // reserved
+        dst.write_array(self.security_cookie);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for InitiateMultitransportRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_header = BasicSecurityHeader::decode(src)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::TRANSPORT_REQ) {
+            return Err(invalid_field_err!("securityHeader", "got invalid security header"));
+        }
+
+        let request_id = src.read_u32();
+        let requested_protocol = MultitransportProtocol::from_u16(src.read_u16())
+            .ok_or_else(|| invalid_field_err!("requestedProtocol", "invalid multitransport protocol"))?;
+        let _reserved = src.read_u16();
+        let security_cookie = src.read_array();
+
+        Ok(Self {
+            request_id,
+            requested_protocol,
+            security_cookie,
+        })
+    }
+}
+
+/// [MS-RDPBCGR] 2.2.15.2 Initiate Multitransport Response PDU
+///
+/// Sent by the client on the message channel when the side channel requested by an
+/// [`InitiateMultitransportRequest`] can't be set up, so that the server keeps sending all the data
+/// on the main transport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitiateMultitransportResponse {
+    pub request_id: u32,
+    pub result: MultitransportResult,
+}
+
+impl InitiateMultitransportResponse {
+    const NAME: &'static str = "InitiateMultitransportResponse";
+
+    const FIXED_PART_SIZE: usize = BasicSecurityHeader::FIXED_PART_SIZE + 4 /* requestId */ + 4 /* hrResponse */;
+
+    /// Declines the request `request_id`
+    pub fn abort(request_id: u32) -> Self {
+        Self {
+            request_id,
+            result: MultitransportResult::Abort,
+        }
+    }
+}
+
+impl Encode for InitiateMultitransportResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::TRANSPORT_RSP,
+        }
+        .encode(dst)?;
+        dst.write_u32(self.request_id);
+        dst.write_u32(self.result.as_u32());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for InitiateMultitransportResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_header = BasicSecurityHeader::decode(src)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::TRANSPORT_RSP) {
+            return Err(invalid_field_err!("securityHeader", "got invalid security header"));
+        }
+
+        let request_id = src.read_u32();
+        let result = MultitransportResult::from_u32(src.read_u32())
+            .ok_or_else(|| invalid_field_err!("hrResponse", "invalid multitransport result"))?;
+
+        Ok(Self { request_id, result })
+    }
+}
+
+/// Transport requested by an [`InitiateMultitransportRequest`]
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MultitransportProtocol {
+    /// RDP-UDP with forward error correction, reliable (INITITATE_REQUEST_PROTOCOL_UDPFECR)
+    UdpFecReliable = 0x01,
+    /// RDP-UDP with forward error correction, lossy (INITITATE_REQUEST_PROTOCOL_UDPFECL)
+    UdpFecLossy = 0x04,
+}
+
+impl MultitransportProtocol {
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::UdpFecReliable),
+            0x04 => Some(Self::UdpFecLossy),
+            _ => None,
+        }
+    }
+
+    #[expect(
+        clippy::as_conversions,
+        reason = "guarantees discriminant layout, and as is the only way to cast enum -> primitive"
+    )]
+    pub fn as_u16(self) -> u16 {
+        self as u16
+    }
+}
+
+/// Result of an [`InitiateMultitransportResponse`]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MultitransportResult {
+    /// S_OK
+    Success = 0x0000_0000,
+    /// E_ABORT, the side channel is not set up
+    Abort = 0x8000_4004,
+}
+
+impl MultitransportResult {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0x0000_0000 => Some(Self::Success),
+            0x8000_4004 => Some(Self::Abort),
+            _ => None,
+        }
+    }
+
+    #[expect(
+        clippy::as_conversions,
+        reason = "guarantees discriminant layout, and as is the only way to cast enum -> primitive"
+    )]
+    pub fn as_u32(self) -> u32 {
+        self as u32
+    }
+}
@@ -0,0 +1,367 @@
+use core::fmt;
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, read_padding, write_padding, Decode,
+    DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+
+use crate::utils::{self, CharacterSet};
+
+const SEC_REDIRECTION_PKT: u16 = 0x0400;
+
+/// [MS-RDPBCGR] 2.2.13.1 Server Redirection Packet (RDP_SERVER_REDIRECTION_PACKET)
+///
+/// Sent by a server (typically a load balancer or a connection broker) to redirect the client to another
+/// server, with the credentials and the routing information to use for the new connection.
+///
+/// With enhanced security, the packet is sent in a Share Control PDU
+/// ([`ShareControlPdu::ServerRedirection`](crate::rdp::headers::ShareControlPdu::ServerRedirection)).
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ServerRedirectionPdu {
+    /// Session to reconnect to on the target server
+    pub session_id: u32,
+    /// Redirection flags
+    ///
+    /// The flags indicating the presence of a field are updated when encoding.
+    pub flags: RedirectionFlags,
+    pub target_net_address: Option<String>,
+    /// Routing token to send in the X.224 Connection Request PDU of the new connection
+    pub load_balance_info: Option<Vec<u8>>,
+    pub username: Option<String>,
+    pub domain: Option<String>,
+    /// Password cookie, or password encrypted for the target when
+    /// [`RedirectionFlags::PASSWORD_IS_PK_ENCRYPTED`] is set
+    pub password: Option<Vec<u8>>,
+    pub target_fqdn: Option<String>,
+    pub target_netbios_name: Option<String>,
+    pub tsv_url: Option<Vec<u8>>,
+    pub redirection_guid: Option<Vec<u8>>,
+    pub target_certificate: Option<Vec<u8>>,
+    pub target_net_addresses: Option<Vec<String>>,
+}
+
+impl ServerRedirectionPdu {
+    const NAME: &'static str = "ServerRedirectionPdu";
+
+    const FIXED_PART_SIZE: usize = 2 /* flags */ + 2 /* length */ + 4 /* sessionId */ + 4 /* redirFlags */;
+
+    /// Host name or address of the server to connect to, if the client has to connect to another server
+    ///
+    /// The FQDN is preferred over the network address, and the NetBIOS name is used as a last resort.
+    /// `None` is returned when [`RedirectionFlags::NO_REDIRECT`] is set: the client reconnects to the
+    /// same server, using the load balancing information.
+    pub fn target_host(&self) -> Option<&str> {
+        if self.flags.contains(RedirectionFlags::NO_REDIRECT) {
+            return None;
+        }
+
+        self.target_fqdn
+            .as_deref()
+            .or(self.target_net_address.as_deref())
+            .or(self.target_netbios_name.as_deref())
+            .or_else(|| self.target_net_addresses.as_ref()?.first().map(String::as_str))
+            .filter(|host| !host.is_empty())
+    }
+
+    fn encoded_flags(&self) -> RedirectionFlags {
+        let mut flags = self.flags & !RedirectionFlags::FIELDS;
+
+        flags.set(RedirectionFlags::TARGET_NET_ADDRESS, self.target_net_address.is_some());
+        flags.set(RedirectionFlags::LOAD_BALANCE_INFO, self.load_balance_info.is_some());
+        flags.set(RedirectionFlags::USERNAME, self.username.is_some());
+        flags.set(RedirectionFlags::DOMAIN, self.domain.is_some());
+        flags.set(RedirectionFlags::PASSWORD, self.password.is_some());
+        flags.set(RedirectionFlags::TARGET_FQDN, self.target_fqdn.is_some());
+        flags.set(
+            RedirectionFlags::TARGET_NETBIOS_NAME,
+            self.target_netbios_name.is_some(),
+        );
+        flags.set(RedirectionFlags::CLIENT_TSV_URL, self.tsv_url.is_some());
+        flags.set(RedirectionFlags::REDIRECTION_GUID, self.redirection_guid.is_some());
+        flags.set(RedirectionFlags::TARGET_CERTIFICATE, self.target_certificate.is_some());
+        flags.set(
+            RedirectionFlags::TARGET_NET_ADDRESSES,
+            self.target_net_addresses.is_some(),
+        );
+
+        flags
+    }
+}
+
+impl fmt::Debug for ServerRedirectionPdu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerRedirectionPdu")
+            .field("session_id", &self.session_id)
+            .field("flags", &self.flags)
+            .field("target_net_address", &self.target_net_address)
+            .field("load_balance_info", &self.load_balance_info)
+            .field("username", &self.username)
+            .field("domain", &self.domain)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("target_fqdn", &self.target_fqdn)
+            .field("target_netbios_name", &self.target_netbios_name)
+            .field("tsv_url", &self.tsv_url)
+            .field("redirection_guid", &self.redirection_guid)
+            .field("target_certificate", &self.target_certificate)
+            .field("target_net_addresses", &self.target_net_addresses)
+            .finish()
+    }
+}
+
+fn string_size(value: Option<&str>) -> usize {
+    value.map_or(0, |value| {
+        4 + utils::encoded_str_len(value, CharacterSet::Unicode, true)
+    })
+}
+
+fn bytes_size(value: Option<&[u8]>) -> usize {
+    value.map_or(0, |value| 4 + value.len())
+}
+
+fn net_addresses_size(addresses: &[String]) -> usize {
+    4 /* addressCount */
+        + addresses
+            .iter()
+            .map(|address| 4 + utils::encoded_str_len(address, CharacterSet::Unicode, true))
+            .sum::<usize>()
+}
+
+fn write_string(dst: &mut WriteCursor<'_>, field: &'static str, value: Option<&str>) -> EncodeResult<()> {
+    if let Some(value) = value {
+        let length = utils::encoded_str_len(value, CharacterSet::Unicode, true);
+        dst.write_u32(cast_length!(ServerRedirectionPdu::NAME, field, length)?);
+        utils::write_string_to_cursor(dst, value, CharacterSet::Unicode, true)?;
+    }
+
+    Ok(())
+}
+
+fn write_bytes(dst: &mut WriteCursor<'_>, field: &'static str, value: Option<&[u8]>) -> EncodeResult<()> {
+    if let Some(value) = value {
+        dst.write_u32(cast_length!(ServerRedirectionPdu::NAME, field, value.len())?);
+        dst.write_slice(value);
+    }
+
+    Ok(())
+}
+
+fn read_bytes<'de>(src: &mut ReadCursor<'de>) -> DecodeResult<&'de [u8]> {
+    ensure_size!(ctx: ServerRedirectionPdu::NAME, in: src, size: 4);
+    let length = cast_length!(ServerRedirectionPdu::NAME, "length", src.read_u32())?;
+    ensure_size!(ctx: ServerRedirectionPdu::NAME, in: src, size: length);
+    Ok(src.read_slice(length))
+}
+
+fn read_string(src: &mut ReadCursor<'_>) -> DecodeResult<String> {
+    utils::decode_string(read_bytes(src)?, CharacterSet::Unicode, false)
+}
+
+impl Encode for ServerRedirectionPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(SEC_REDIRECTION_PKT);
+        dst.write_u16(cast_length!("length", self.size())?);
+        dst.write_u32(self.session_id);
+        dst.write_u32(self.encoded_flags().bits());
+
+        write_string(dst, "targetNetAddress", self.target_net_address.as_deref())?;
+        write_bytes(dst, "loadBalanceInfo", self.load_balance_info.as_deref())?;
+        write_string(dst, "userName", self.username.as_deref())?;
+        write_string(dst, "domain", self.domain.as_deref())?;
+        write_bytes(dst, "password", self.password.as_deref())?;
+        write_string(dst, "targetFqdn", self.target_fqdn.as_deref())?;
+        write_string(dst, "targetNetBiosName", self.target_netbios_name.as_deref())?;
+        write_bytes(dst, "tsvUrl", self.tsv_url.as_deref())?;
+        write_bytes(dst, "redirectionGuid", self.redirection_guid.as_deref())?;
+        write_bytes(dst, "targetCertificate", self.target_certificate.as_deref())?;
+
+        if let Some(addresses) = &self.target_net_addresses {
+            dst.write_u32(cast_length!("targetNetAddressesLength", net_addresses_size(addresses))?);
+            dst.write_u32(cast_length!("addressCount", addresses.len())?);
+            for address in addresses {
+                write_string(dst, "address", Some(address))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            + string_size(self.target_net_address.as_deref())
+            + bytes_size(self.load_balance_info.as_deref())
+            + string_size(self.username.as_deref())
+            + string_size(self.domain.as_deref())
+            + bytes_size(self.password.as_deref())
+            + string_size(self.target_fqdn.as_deref())
+            + string_size(self.target_netbios_name.as_deref())
+            + bytes_size(self.tsv_url.as_deref())
+            + bytes_size(self.redirection_guid.as_deref())
+            + bytes_size(self.target_certificate.as_deref())
+            + self
+                .target_net_addresses
+                .as_ref()
+                .map_or(0, |addresses| 4 + net_addresses_size(addresses))
+    }
+}
+
+impl<'de> Decode<'de> for ServerRedirectionPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let start = src.pos();
+
+        if src.read_u16() != SEC_REDIRECTION_PKT {
+            return Err(invalid_field_err!("flags", "invalid server redirection packet flags"));
+        }
+
+        let length = usize::from(src.read_u16());
+        let session_id = src.read_u32();
+        let flags = RedirectionFlags::from_bits_retain(src.read_u32());
+
+        let mut pdu = Self {
+            session_id,
+            flags,
+            ..Default::default()
+        };
+
+        if flags.contains(RedirectionFlags::TARGET_NET_ADDRESS) {
+            pdu.target_net_address = Some(read_string(src)?);
+        }
+        if flags.contains(RedirectionFlags::LOAD_BALANCE_INFO) {
+            pdu.load_balance_info = Some(read_bytes(src)?.to_vec());
+        }
+        if flags.contains(RedirectionFlags::USERNAME) {
+            pdu.username = Some(read_string(src)?);
+        }
+        if flags.contains(RedirectionFlags::DOMAIN) {
+            pdu.domain = Some(read_string(src)?);
+        }
+        if flags.contains(RedirectionFlags::PASSWORD) {
+            pdu.password = Some(read_bytes(src)?.to_vec());
+        }
+        if flags.contains(RedirectionFlags::TARGET_FQDN) {
+            pdu.target_fqdn = Some(read_string(src)?);
+        }
+        if flags.contains(RedirectionFlags::TARGET_NETBIOS_NAME) {
+            pdu.target_netbios_name = Some(read_string(src)?);
+        }
+        if flags.contains(RedirectionFlags::CLIENT_TSV_URL) {
+            pdu.tsv_url = Some(read_bytes(src)?.to_vec());
+        }
+        if flags.contains(RedirectionFlags::REDIRECTION_GUID) {
+            pdu.redirection_guid = Some(read_bytes(src)?.to_vec());
+        }
+        if flags.contains(RedirectionFlags::TARGET_CERTIFICATE) {
+            pdu.target_certificate = Some(read_bytes(src)?.to_vec());
+        }
+        if flags.contains(RedirectionFlags::TARGET_NET_ADDRESSES) {
+            let mut addresses_src = ReadCursor::new(read_bytes(src)?);
+
+            ensure_size!(in: addresses_src, size: 4);
+            let count = addresses_src.read_u32();
+
+            let mut addresses = Vec::new();
+            for _ in 0..count {
+                addresses.push(read_string(&mut addresses_src)?);
+            }
+            pdu.target_net_addresses = Some(addresses);
+        }
+
+        // The packet may end with 8 bytes of padding.
+        let read = src.pos() - start;
+        if length > read {
+            let padding = length - read;
+            ensure_size!(in: src, size: padding);
+            read_padding!(src, padding);
+        }
+
+        Ok(pdu)
+    }
+}
+
+/// [MS-RDPBCGR] 2.2.13.3.1 Enhanced Security Server Redirection (TS_ENHANCED_SECURITY_SERVER_REDIRECTION), without the
+/// Share Control Header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnhancedSecurityServerRedirection {
+    pub redirection: ServerRedirectionPdu,
+}
+
+impl EnhancedSecurityServerRedirection {
+    const NAME: &'static str = "EnhancedSecurityServerRedirection";
+
+    const FIXED_PART_SIZE: usize = 2 /* pad2Octets */;
+}
+
+impl Encode for EnhancedSecurityServerRedirection {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        write_padding!(dst, 2);
+        self.redirection.encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.redirection.size()
+    }
+}
+
+impl<'de> Decode<'de> for EnhancedSecurityServerRedirection {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        read_padding!(src, 2);
+        let redirection = ServerRedirectionPdu::decode(src)?;
+
+        Ok(Self { redirection })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct RedirectionFlags: u32 {
+        const TARGET_NET_ADDRESS = 0x0000_0001;
+        const LOAD_BALANCE_INFO = 0x0000_0002;
+        const USERNAME = 0x0000_0004;
+        const DOMAIN = 0x0000_0008;
+        const PASSWORD = 0x0000_0010;
+        const DONT_STORE_USERNAME = 0x0000_0020;
+        const SMARTCARD_LOGON = 0x0000_0040;
+        const NO_REDIRECT = 0x0000_0080;
+        const TARGET_FQDN = 0x0000_0100;
+        const TARGET_NETBIOS_NAME = 0x0000_0200;
+        const TARGET_NET_ADDRESSES = 0x0000_0800;
+        const CLIENT_TSV_URL = 0x0000_1000;
+        const SERVER_TSV_CAPABLE = 0x0000_2000;
+        const PASSWORD_IS_PK_ENCRYPTED = 0x0000_4000;
+        const REDIRECTION_GUID = 0x0000_8000;
+        const TARGET_CERTIFICATE = 0x0001_0000;
+
+        const _ = !0;
+    }
+}
+
+impl RedirectionFlags {
+    /// Flags indicating the presence of a field
+    const FIELDS: Self = Self::TARGET_NET_ADDRESS
+        .union(Self::LOAD_BALANCE_INFO)
+        .union(Self::USERNAME)
+        .union(Self::DOMAIN)
+        .union(Self::PASSWORD)
+        .union(Self::TARGET_FQDN)
+        .union(Self::TARGET_NETBIOS_NAME)
+        .union(Self::TARGET_NET_ADDRESSES)
+        .union(Self::CLIENT_TSV_URL)
+        .union(Self::REDIRECTION_GUID)
+        .union(Self::TARGET_CERTIFICATE);
+}
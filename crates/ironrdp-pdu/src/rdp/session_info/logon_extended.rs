@@ -1,8 +1,11 @@
+use core::fmt;
+
 use bitflags::bitflags;
 use ironrdp_core::{
     cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, read_padding, Decode, DecodeResult, Encode,
     EncodeResult, ReadCursor, WriteCursor,
 };
+use md5::Digest as _;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive as _;
 
@@ -15,6 +18,8 @@ const LOGON_INFO_FIELD_DATA_SIZE: usize = 4;
 const AUTO_RECONNECT_VERSION_1: u32 = 0x0000_0001;
 const AUTO_RECONNECT_PACKET_SIZE: usize = 28;
 const AUTO_RECONNECT_RANDOM_BITS_SIZE: usize = 16;
+const AUTO_RECONNECT_VERIFIER_SIZE: usize = 16;
+const HMAC_MD5_BLOCK_SIZE: usize = 64;
 const LOGON_ERRORS_INFO_SIZE: usize = 8;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -96,7 +101,13 @@ impl<'de> Decode<'de> for LogonInfoExtended {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// ARC_SC_PRIVATE_PACKET
+///
+/// Auto-reconnect cookie sent by the server, allowing the client to reconnect to the session without the
+/// credentials of the user. The random bits are secret, and are not printed by the `Debug` implementation.
+///
+/// [MS-RDPBCGR] 2.2.4.2
+#[derive(Clone, PartialEq, Eq)]
 pub struct ServerAutoReconnect {
     pub logon_id: u32,
     pub random_bits: [u8; AUTO_RECONNECT_RANDOM_BITS_SIZE],
@@ -106,6 +117,26 @@ impl ServerAutoReconnect {
     const NAME: &'static str = "ServerAutoReconnect";
 
     const FIXED_PART_SIZE: usize = AUTO_RECONNECT_PACKET_SIZE + LOGON_INFO_FIELD_DATA_SIZE;
+
+    /// Computes the Client Auto-Reconnect Packet sent with the Client Info PDU of the reconnection
+    ///
+    /// The security verifier is the HMAC-MD5 of the client random, keyed with the random bits of the cookie.
+    /// With Enhanced RDP Security (TLS, CredSSP), there is no client random and 32 zero bytes are used instead.
+    pub fn client_auto_reconnect(&self, client_random: &[u8]) -> ClientAutoReconnect {
+        ClientAutoReconnect {
+            logon_id: self.logon_id,
+            security_verifier: hmac_md5(&self.random_bits, client_random),
+        }
+    }
+}
+
+impl fmt::Debug for ServerAutoReconnect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerAutoReconnect")
+            .field("logon_id", &self.logon_id)
+            .field("random_bits", &"<redacted>")
+            .finish()
+    }
 }
 
 impl Encode for ServerAutoReconnect {
@@ -153,6 +184,88 @@ impl<'de> Decode<'de> for ServerAutoReconnect {
     }
 }
 
+/// ARC_CS_PRIVATE_PACKET
+///
+/// Auto-reconnect cookie sent by the client in the Client Info PDU, computed from the [`ServerAutoReconnect`]
+/// cookie of the previous connection.
+///
+/// [MS-RDPBCGR] 2.2.11.1.1.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientAutoReconnect {
+    pub logon_id: u32,
+    pub security_verifier: [u8; AUTO_RECONNECT_VERIFIER_SIZE],
+}
+
+impl ClientAutoReconnect {
+    const NAME: &'static str = "ClientAutoReconnect";
+
+    const FIXED_PART_SIZE: usize = AUTO_RECONNECT_PACKET_SIZE;
+}
+
+impl Encode for ClientAutoReconnect {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(u32::try_from(AUTO_RECONNECT_PACKET_SIZE).expect("AUTO_RECONNECT_PACKET_SIZE fits into u32"));
+        dst.write_u32(AUTO_RECONNECT_VERSION_1);
+        dst.write_u32(self.logon_id);
+        dst.write_array(self.security_verifier);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ClientAutoReconnect {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let packet_length = src.read_u32();
+        if packet_length != u32::try_from(AUTO_RECONNECT_PACKET_SIZE).expect("AUTO_RECONNECT_PACKET_SIZE fits into u32")
+        {
+            return Err(invalid_field_err!("cbLen", "invalid auto-reconnect packet size"));
+        }
+
+        let version = src.read_u32();
+        if version != AUTO_RECONNECT_VERSION_1 {
+            return Err(invalid_field_err!("version", "invalid auto-reconnect version"));
+        }
+
+        let logon_id = src.read_u32();
+        let security_verifier = src.read_array();
+
+        Ok(Self {
+            logon_id,
+            security_verifier,
+        })
+    }
+}
+
+/// HMAC-MD5 (RFC 2104), for keys up to the block size
+fn hmac_md5(key: &[u8; AUTO_RECONNECT_RANDOM_BITS_SIZE], data: &[u8]) -> [u8; AUTO_RECONNECT_VERIFIER_SIZE] {
+    let mut inner_key = [0x36; HMAC_MD5_BLOCK_SIZE];
+    let mut outer_key = [0x5C; HMAC_MD5_BLOCK_SIZE];
+    for ((inner, outer), key) in inner_key.iter_mut().zip(outer_key.iter_mut()).zip(key) {
+        *inner ^= key;
+        *outer ^= key;
+    }
+
+    let inner_hash = md5::Md5::new().chain_update(inner_key).chain_update(data).finalize();
+
+    md5::Md5::new()
+        .chain_update(outer_key)
+        .chain_update(inner_hash)
+        .finalize()
+        .into()
+}
+
 /// TS_LOGON_ERRORS_INFO
 ///
 /// [Doc](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/845eb789-6edf-453a-8b0e-c976823d1f72)
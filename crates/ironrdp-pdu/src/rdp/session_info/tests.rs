@@ -432,3 +432,43 @@ fn from_buffer_parsing_with_invalid_logon_error_type_fails() {
         res => panic!("Expected InvalidLogonErrorType error, got: {res:?}"),
     };
 }
+
+#[test]
+fn client_auto_reconnect_security_verifier() {
+    // RFC 2202, test case 1 for HMAC-MD5
+    let server = ServerAutoReconnect {
+        logon_id: SESSION_ID,
+        random_bits: [0x0b; 16],
+    };
+
+    assert_eq!(
+        server.client_auto_reconnect(b"Hi There"),
+        ClientAutoReconnect {
+            logon_id: SESSION_ID,
+            security_verifier: [
+                0x92, 0x94, 0x72, 0x7a, 0x36, 0x38, 0xbb, 0x1c, 0x13, 0xf4, 0x8e, 0xf8, 0x15, 0x8b, 0xfc, 0x9d,
+            ],
+        }
+    );
+}
+
+#[test]
+fn client_auto_reconnect_encode_decode() {
+    let client = LOGON_EXTENDED
+        .auto_reconnect
+        .as_ref()
+        .unwrap()
+        .client_auto_reconnect(&[0; 32]);
+
+    let buffer = encode_vec(&client).unwrap();
+    assert_eq!(buffer.len(), 28);
+    assert_eq!(buffer[..12], [0x1c, 0, 0, 0, 0x01, 0, 0, 0, 0x02, 0, 0, 0]);
+    assert_eq!(decode::<ClientAutoReconnect>(&buffer).unwrap(), client);
+}
+
+#[test]
+fn server_auto_reconnect_random_bits_are_redacted() {
+    let debug = format!("{:?}", LOGON_EXTENDED.auto_reconnect.as_ref().unwrap());
+    assert!(debug.contains("redacted"));
+    assert!(!debug.contains("168"));
+}
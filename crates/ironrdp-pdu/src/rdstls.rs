@@ -0,0 +1,405 @@
+//! RDSTLS PDUs ([MS-RDPBCGR] 2.2.17)
+//!
+//! With RDSTLS, the client is authenticated right after the TLS handshake with the credentials provided
+//! by a server redirection: the password is the opaque cookie of the Server Redirection PDU, e.g. after
+//! a redirection by a load balancer.
+
+use core::fmt;
+
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+
+use crate::utils::{self, CharacterSet};
+use crate::PduHint;
+
+/// RDSTLS_VERSION_1
+pub const RDSTLS_VERSION_1: u16 = 0x0001;
+
+const RDSTLS_TYPE_CAPABILITIES: u16 = 0x0001;
+const RDSTLS_TYPE_AUTHREQ: u16 = 0x0002;
+const RDSTLS_TYPE_AUTHRSP: u16 = 0x0004;
+
+const RDSTLS_DATA_CAPABILITIES: u16 = 0x0001;
+const RDSTLS_DATA_PASSWORD_CREDS: u16 = 0x0001;
+const RDSTLS_DATA_AUTORECONNECT_COOKIE: u16 = 0x0002;
+const RDSTLS_DATA_RESULT_CODE: u16 = 0x0001;
+
+const HEADER_SIZE: usize = 2 /* version */ + 2 /* pduType */ + 2 /* dataType */;
+
+fn write_header(dst: &mut WriteCursor<'_>, pdu_type: u16, data_type: u16) {
+    dst.write_u16(RDSTLS_VERSION_1);
+    dst.write_u16(pdu_type);
+    dst.write_u16(data_type);
+}
+
+/// Reads the header, returns the data type
+fn read_header(src: &mut ReadCursor<'_>, ctx: &'static str, expected_pdu_type: u16) -> DecodeResult<u16> {
+    ensure_size!(ctx: ctx, in: src, size: HEADER_SIZE);
+
+    let version = src.read_u16();
+    if version != RDSTLS_VERSION_1 {
+        return Err(invalid_field_err!(ctx, "version", "unsupported RDSTLS version"));
+    }
+
+    if src.read_u16() != expected_pdu_type {
+        return Err(invalid_field_err!(ctx, "pduType", "unexpected PDU type"));
+    }
+
+    Ok(src.read_u16())
+}
+
+/// [MS-RDPBCGR] 2.2.17.1 RDSTLS Capabilities PDU
+///
+/// Sent by the server right after the TLS handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RdstlsCapabilities {
+    /// Bitmask of the supported versions, only [`RDSTLS_VERSION_1`] is defined
+    pub supported_versions: u16,
+}
+
+impl RdstlsCapabilities {
+    const NAME: &'static str = "RdstlsCapabilities";
+
+    const FIXED_PART_SIZE: usize = HEADER_SIZE + 2 /* supportedVersions */;
+
+    pub fn supports_version_1(&self) -> bool {
+        self.supported_versions & RDSTLS_VERSION_1 != 0
+    }
+}
+
+impl Default for RdstlsCapabilities {
+    fn default() -> Self {
+        Self {
+            supported_versions: RDSTLS_VERSION_1,
+        }
+    }
+}
+
+impl Encode for RdstlsCapabilities {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        write_header(dst, RDSTLS_TYPE_CAPABILITIES, RDSTLS_DATA_CAPABILITIES);
+        dst.write_u16(self.supported_versions);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for RdstlsCapabilities {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let data_type = read_header(src, Self::NAME, RDSTLS_TYPE_CAPABILITIES)?;
+        if data_type != RDSTLS_DATA_CAPABILITIES {
+            return Err(invalid_field_err!("dataType", "unexpected data type"));
+        }
+
+        let supported_versions = src.read_u16();
+
+        Ok(Self { supported_versions })
+    }
+}
+
+/// Credentials of a [`RdstlsAuthenticationRequest`], taken from a Server Redirection PDU
+#[derive(Clone, PartialEq, Eq)]
+pub struct RdstlsPasswordCredentials {
+    /// Redirection GUID (RedirectionGuid field of the Server Redirection PDU)
+    pub redirection_guid: Vec<u8>,
+    pub username: String,
+    pub domain: String,
+    /// Password cookie (Password field of the Server Redirection PDU), sent as is
+    pub password: Vec<u8>,
+}
+
+impl fmt::Debug for RdstlsPasswordCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RdstlsPasswordCredentials")
+            .field("redirection_guid", &self.redirection_guid)
+            .field("username", &self.username)
+            .field("domain", &self.domain)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// [MS-RDPBCGR] 2.2.17.2 RDSTLS Authentication Request PDU with Password Credentials and 2.2.17.3 RDSTLS
+/// Authentication Request PDU with Auto-Reconnect Cookie
+///
+/// Sent by the client in response to the [`RdstlsCapabilities`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RdstlsAuthenticationRequest {
+    Password(RdstlsPasswordCredentials),
+    AutoReconnectCookie {
+        session_id: u32,
+        /// ARC_CS_PRIVATE_PACKET structure
+        cookie: Vec<u8>,
+    },
+}
+
+impl RdstlsAuthenticationRequest {
+    const NAME: &'static str = "RdstlsAuthenticationRequest";
+
+    const FIXED_PART_SIZE: usize = HEADER_SIZE;
+}
+
+fn write_data(dst: &mut WriteCursor<'_>, field: &'static str, data: &[u8]) -> EncodeResult<()> {
+    dst.write_u16(cast_length!(RdstlsAuthenticationRequest::NAME, field, data.len())?);
+    dst.write_slice(data);
+    Ok(())
+}
+
+fn read_data<'de>(src: &mut ReadCursor<'de>) -> DecodeResult<&'de [u8]> {
+    ensure_size!(ctx: RdstlsAuthenticationRequest::NAME, in: src, size: 2);
+    let length = usize::from(src.read_u16());
+    ensure_size!(ctx: RdstlsAuthenticationRequest::NAME, in: src, size: length);
+    Ok(src.read_slice(length))
+}
+
+impl Encode for RdstlsAuthenticationRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        match self {
+            Self::Password(credentials) => {
+                write_header(dst, RDSTLS_TYPE_AUTHREQ, RDSTLS_DATA_PASSWORD_CREDS);
+                write_data(dst, "redirectionGuid", &credentials.redirection_guid)?;
+                // The strings are null-terminated, like in the Server Redirection PDU.
+                write_data(dst, "userName", &utf16_null_terminated(&credentials.username))?;
+                write_data(dst, "domain", &utf16_null_terminated(&credentials.domain))?;
+                write_data(dst, "password", &credentials.password)?;
+            }
+            Self::AutoReconnectCookie { session_id, cookie } => {
+                write_header(dst, RDSTLS_TYPE_AUTHREQ, RDSTLS_DATA_AUTORECONNECT_COOKIE);
+                dst.write_u32(*session_id);
+                write_data(dst, "autoReconnectCookie", cookie)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        let data_size = match self {
+            Self::Password(credentials) => {
+                2 + credentials.redirection_guid.len()
+                    + 2
+                    + utils::encoded_str_len(&credentials.username, CharacterSet::Unicode, true)
+                    + 2
+                    + utils::encoded_str_len(&credentials.domain, CharacterSet::Unicode, true)
+                    + 2
+                    + credentials.password.len()
+            }
+            Self::AutoReconnectCookie { cookie, .. } => 4 /* sessionId */ + 2 + cookie.len(),
+        };
+
+        Self::FIXED_PART_SIZE + data_size
+    }
+}
+
+impl<'de> Decode<'de> for RdstlsAuthenticationRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let data_type = read_header(src, Self::NAME, RDSTLS_TYPE_AUTHREQ)?;
+
+        match data_type {
+            RDSTLS_DATA_PASSWORD_CREDS => {
+                let redirection_guid = read_data(src)?.to_vec();
+                let username = utils::from_utf16_bytes(read_data(src)?)
+                    .trim_end_matches('\0')
+                    .to_owned();
+                let domain = utils::from_utf16_bytes(read_data(src)?)
+                    .trim_end_matches('\0')
+                    .to_owned();
+                let password = read_data(src)?.to_vec();
+
+                Ok(Self::Password(RdstlsPasswordCredentials {
+                    redirection_guid,
+                    username,
+                    domain,
+                    password,
+                }))
+            }
+            RDSTLS_DATA_AUTORECONNECT_COOKIE => {
+                ensure_size!(in: src, size: 4);
+                let session_id = src.read_u32();
+                let cookie = read_data(src)?.to_vec();
+
+                Ok(Self::AutoReconnectCookie { session_id, cookie })
+            }
+            _ => Err(invalid_field_err!("dataType", "unexpected data type")),
+        }
+    }
+}
+
+fn utf16_null_terminated(value: &str) -> Vec<u8> {
+    let mut bytes = utils::to_utf16_bytes(value);
+    bytes.extend_from_slice(&[0, 0]);
+    bytes
+}
+
+/// Result code of a [`RdstlsAuthenticationResponse`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RdstlsResultCode(u32);
+
+impl RdstlsResultCode {
+    pub const SUCCESS: Self = Self(0x0000_0000);
+    pub const ACCESS_DENIED: Self = Self(0x0000_0005);
+    pub const LOGON_FAILURE: Self = Self(0x0000_052E);
+    pub const ACCOUNT_RESTRICTION: Self = Self(0x0000_052F);
+    pub const INVALID_LOGON_HOURS: Self = Self(0x0000_0530);
+    pub const PASSWORD_EXPIRED: Self = Self(0x0000_0532);
+    pub const ACCOUNT_DISABLED: Self = Self(0x0000_0533);
+    pub const ACCOUNT_EXPIRED: Self = Self(0x0000_0701);
+    pub const PASSWORD_MUST_CHANGE: Self = Self(0x0000_0773);
+    pub const ACCOUNT_LOCKED_OUT: Self = Self(0x0000_0775);
+}
+
+impl From<u32> for RdstlsResultCode {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<RdstlsResultCode> for u32 {
+    fn from(value: RdstlsResultCode) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for RdstlsResultCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::SUCCESS => write!(f, "success"),
+            Self::ACCESS_DENIED => write!(f, "access denied"),
+            Self::LOGON_FAILURE => write!(f, "logon failure"),
+            Self::ACCOUNT_RESTRICTION => write!(f, "account restriction"),
+            Self::INVALID_LOGON_HOURS => write!(f, "invalid logon hours"),
+            Self::PASSWORD_EXPIRED => write!(f, "password expired"),
+            Self::ACCOUNT_DISABLED => write!(f, "account disabled"),
+            Self::ACCOUNT_EXPIRED => write!(f, "account expired"),
+            Self::PASSWORD_MUST_CHANGE => write!(f, "password must change"),
+            Self::ACCOUNT_LOCKED_OUT => write!(f, "account locked out"),
+            Self(code) => write!(f, "unknown result code {code:#010X}"),
+        }
+    }
+}
+
+/// [MS-RDPBCGR] 2.2.17.4 RDSTLS Authentication Response PDU
+///
+/// Sent by the server once the credentials of the client are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RdstlsAuthenticationResponse {
+    pub result_code: RdstlsResultCode,
+}
+
+impl RdstlsAuthenticationResponse {
+    const NAME: &'static str = "RdstlsAuthenticationResponse";
+
+    const FIXED_PART_SIZE: usize = HEADER_SIZE + 4 /* resultCode */;
+}
+
+impl Encode for RdstlsAuthenticationResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        write_header(dst, RDSTLS_TYPE_AUTHRSP, RDSTLS_DATA_RESULT_CODE);
+        dst.write_u32(self.result_code.into());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for RdstlsAuthenticationResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let data_type = read_header(src, Self::NAME, RDSTLS_TYPE_AUTHRSP)?;
+        if data_type != RDSTLS_DATA_RESULT_CODE {
+            return Err(invalid_field_err!("dataType", "unexpected data type"));
+        }
+
+        let result_code = RdstlsResultCode::from(src.read_u32());
+
+        Ok(Self { result_code })
+    }
+}
+
+/// Finds the size of the RDSTLS PDUs, which are not prefixed with their length
+#[derive(Clone, Copy, Debug)]
+pub struct RdstlsHint;
+
+pub const RDSTLS_HINT: RdstlsHint = RdstlsHint;
+
+impl PduHint for RdstlsHint {
+    fn find_size(&self, bytes: &[u8]) -> DecodeResult<Option<(bool, usize)>> {
+        let mut src = ReadCursor::new(bytes);
+
+        if src.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let _version = src.read_u16();
+        let pdu_type = src.read_u16();
+        let data_type = src.read_u16();
+
+        let size = match (pdu_type, data_type) {
+            (RDSTLS_TYPE_CAPABILITIES, _) => RdstlsCapabilities::FIXED_PART_SIZE,
+            (RDSTLS_TYPE_AUTHRSP, _) => RdstlsAuthenticationResponse::FIXED_PART_SIZE,
+            (RDSTLS_TYPE_AUTHREQ, RDSTLS_DATA_PASSWORD_CREDS) => {
+                let mut size = HEADER_SIZE;
+
+                // redirectionGuid, userName, domain and password
+                for _ in 0..4 {
+                    if src.len() < 2 {
+                        return Ok(None);
+                    }
+
+                    let length = usize::from(src.read_u16());
+                    size += 2 + length;
+
+                    if src.len() < length {
+                        return Ok(None);
+                    }
+                    src.advance(length);
+                }
+
+                size
+            }
+            (RDSTLS_TYPE_AUTHREQ, RDSTLS_DATA_AUTORECONNECT_COOKIE) => {
+                if src.len() < 6 {
+                    return Ok(None);
+                }
+
+                let _session_id = src.read_u32();
+                HEADER_SIZE + 6 + usize::from(src.read_u16())
+            }
+            _ => return Err(invalid_field_err!("RdstlsHint", "pduType", "unknown RDSTLS PDU")),
+        };
+
+        Ok(Some((true, size)))
+    }
+}
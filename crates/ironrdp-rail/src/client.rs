@@ -0,0 +1,167 @@
+use ironrdp_core::{impl_as_any, Decode as _, ReadCursor};
+use ironrdp_pdu::gcc::ChannelName;
+use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor, SvcProcessorMessages};
+use tracing::{debug, warn};
+
+use crate::pdu::{
+    ActivatePdu, ClientStatusFlags, ExecPdu, ExecResultPdu, HandshakePdu, LangBarInfoPdu, LocalMoveSizePdu,
+    MinMaxInfoPdu, NotifyEventPdu, RailPdu, SysCommandPdu, SysMenuPdu, SysParam, WindowMovePdu,
+};
+
+pub type RailClientMessages = SvcProcessorMessages<RailClient>;
+
+/// Build number announced in the client handshake
+const CLIENT_BUILD_NUMBER: u32 = 7601;
+
+/// Requests of the server about the remote applications
+///
+/// The windows of the applications are received as windowing orders, see [`crate::window::WindowTree`].
+pub trait RailClientHandler: Send + core::fmt::Debug {
+    /// Result of an application started with [`RailClient::exec`]
+    fn exec_result(&mut self, result: &ExecResultPdu);
+
+    /// System parameter of the server
+    fn system_param(&mut self, _param: &SysParam) {}
+
+    /// Start or end of a local move or resize of a window
+    fn local_move_size(&mut self, _pdu: &LocalMoveSizePdu) {}
+
+    /// Size limits of a window, before a local move or resize
+    fn min_max_info(&mut self, _pdu: &MinMaxInfoPdu) {}
+
+    /// Window at the top of the Z-order of the server, when it's not a window of the remote applications
+    fn z_order_sync(&mut self, _window_id: u32) {}
+
+    fn language_bar(&mut self, _info: &LangBarInfoPdu) {}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RailState {
+    WaitingForHandshake,
+    Ready,
+}
+
+#[derive(Debug)]
+pub struct RailClient {
+    handler: Box<dyn RailClientHandler>,
+    state: RailState,
+    client_status: ClientStatusFlags,
+    /// Applications started once the handshake is done
+    pending_exec: Vec<ExecPdu>,
+}
+
+impl RailClient {
+    pub const NAME: ChannelName = ChannelName::from_static(b"rail\0\0\0\0");
+
+    pub fn new(handler: Box<dyn RailClientHandler>, client_status: ClientStatusFlags) -> Self {
+        Self {
+            handler,
+            state: RailState::WaitingForHandshake,
+            client_status,
+            pending_exec: Vec::new(),
+        }
+    }
+
+    /// Starts an application as soon as the channel is ready
+    #[must_use]
+    pub fn with_exec(mut self, exec: ExecPdu) -> Self {
+        self.pending_exec.push(exec);
+        self
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state == RailState::Ready
+    }
+
+    pub fn exec(&mut self, exec: ExecPdu) -> PduResult<RailClientMessages> {
+        self.pdu(RailPdu::Exec(exec))
+    }
+
+    pub fn system_param(&mut self, param: SysParam) -> PduResult<RailClientMessages> {
+        self.pdu(RailPdu::SysParam(param))
+    }
+
+    pub fn activate(&mut self, window_id: u32, enabled: bool) -> PduResult<RailClientMessages> {
+        self.pdu(RailPdu::Activate(ActivatePdu {
+            window_id,
+            enabled: u8::from(enabled),
+        }))
+    }
+
+    /// Request to minimize, maximize, close, ... a window
+    pub fn system_command(&mut self, pdu: SysCommandPdu) -> PduResult<RailClientMessages> {
+        self.pdu(RailPdu::SysCommand(pdu))
+    }
+
+    pub fn system_menu(&mut self, pdu: SysMenuPdu) -> PduResult<RailClientMessages> {
+        self.pdu(RailPdu::SysMenu(pdu))
+    }
+
+    /// New position of a window moved or resized locally
+    pub fn window_move(&mut self, pdu: WindowMovePdu) -> PduResult<RailClientMessages> {
+        self.pdu(RailPdu::WindowMove(pdu))
+    }
+
+    pub fn notify_event(&mut self, pdu: NotifyEventPdu) -> PduResult<RailClientMessages> {
+        self.pdu(RailPdu::NotifyEvent(pdu))
+    }
+
+    fn pdu(&self, pdu: RailPdu) -> PduResult<RailClientMessages> {
+        if self.state != RailState::Ready {
+            return Err(pdu_other_err!("invalid state, RAIL handshake not done"));
+        }
+
+        Ok(RailClientMessages::new(vec![pdu.into()]))
+    }
+}
+
+impl_as_any!(RailClient);
+
+impl SvcProcessor for RailClient {
+    fn channel_name(&self) -> ChannelName {
+        Self::NAME
+    }
+
+    fn compression_condition(&self) -> CompressionCondition {
+        CompressionCondition::WhenRdpDataIsCompressed
+    }
+
+    fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        let pdu = RailPdu::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        if self.state == RailState::WaitingForHandshake {
+            if !matches!(pdu, RailPdu::Handshake(_) | RailPdu::HandshakeEx(_)) {
+                warn!(?pdu, "Unexpected RAIL PDU before the handshake");
+                return Ok(Vec::new());
+            }
+
+            self.state = RailState::Ready;
+
+            let mut response = vec![
+                RailPdu::Handshake(HandshakePdu {
+                    build_number: CLIENT_BUILD_NUMBER,
+                }),
+                RailPdu::ClientStatus(self.client_status),
+            ];
+            response.extend(self.pending_exec.drain(..).map(RailPdu::Exec));
+
+            return Ok(response.into_iter().map(SvcMessage::from).collect());
+        }
+
+        match pdu {
+            RailPdu::ExecResult(result) => self.handler.exec_result(&result),
+            RailPdu::SysParam(param) => self.handler.system_param(&param),
+            RailPdu::LocalMoveSize(pdu) => self.handler.local_move_size(&pdu),
+            RailPdu::MinMaxInfo(pdu) => self.handler.min_max_info(&pdu),
+            RailPdu::ZOrderSync(pdu) => self.handler.z_order_sync(pdu.window_id_marker),
+            RailPdu::LangBarInfo(info) => self.handler.language_bar(&info),
+            pdu => debug!(?pdu, "Ignored RAIL PDU"),
+        }
+
+        Ok(Vec::new())
+    }
+}
+
+impl SvcClientProcessor for RailClient {}
@@ -0,0 +1,996 @@
+//! Remote Programs Virtual Channel Extension PDUs [MS-RDPERP][1] implementation.
+//!
+//! [1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdperp
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+use ironrdp_pdu::geometry::ExclusiveRectangle;
+use ironrdp_pdu::utils::{self, CharacterSet};
+use ironrdp_pdu::{read_padding, write_padding};
+use ironrdp_svc::SvcEncode;
+
+const TS_RAIL_ORDER_EXEC: u16 = 0x0001;
+const TS_RAIL_ORDER_ACTIVATE: u16 = 0x0002;
+const TS_RAIL_ORDER_SYSPARAM: u16 = 0x0003;
+const TS_RAIL_ORDER_SYSCOMMAND: u16 = 0x0004;
+const TS_RAIL_ORDER_HANDSHAKE: u16 = 0x0005;
+const TS_RAIL_ORDER_NOTIFY_EVENT: u16 = 0x0006;
+const TS_RAIL_ORDER_WINDOWMOVE: u16 = 0x0008;
+const TS_RAIL_ORDER_LOCALMOVESIZE: u16 = 0x0009;
+const TS_RAIL_ORDER_MINMAXINFO: u16 = 0x000A;
+const TS_RAIL_ORDER_CLIENTSTATUS: u16 = 0x000B;
+const TS_RAIL_ORDER_SYSMENU: u16 = 0x000C;
+const TS_RAIL_ORDER_LANGBARINFO: u16 = 0x000D;
+const TS_RAIL_ORDER_GET_APPID_REQ: u16 = 0x000E;
+const TS_RAIL_ORDER_GET_APPID_RESP: u16 = 0x000F;
+const TS_RAIL_ORDER_COMPARTMENTINFO: u16 = 0x0012;
+const TS_RAIL_ORDER_HANDSHAKE_EX: u16 = 0x0013;
+const TS_RAIL_ORDER_ZORDER_SYNC: u16 = 0x0014;
+const TS_RAIL_ORDER_CLOAK: u16 = 0x0015;
+const TS_RAIL_ORDER_POWER_DISPLAY_REQUEST: u16 = 0x0016;
+const TS_RAIL_ORDER_EXEC_RESULT: u16 = 0x0080;
+
+const SPI_SETMOUSEBUTTONSWAP: u32 = 0x0000_0021;
+const SPI_SETSCREENSAVEACTIVE: u32 = 0x0000_0011;
+const SPI_SETDRAGFULLWINDOWS: u32 = 0x0000_0025;
+const SPI_SETWORKAREA: u32 = 0x0000_002F;
+const SPI_SETKEYBOARDPREF: u32 = 0x0000_0045;
+const SPI_SETSCREENSAVESECURE: u32 = 0x0000_0077;
+const SPI_SETKEYBOARDCUES: u32 = 0x0000_100B;
+const RAIL_SPI_TASKBARPOS: u32 = 0x0000_F000;
+const RAIL_SPI_DISPLAYCHANGE: u32 = 0x0000_F001;
+
+/// TS_RAIL_PDU, a RAIL PDU with its TS_RAIL_PDU_HEADER
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RailPdu {
+    Exec(ExecPdu),
+    Activate(ActivatePdu),
+    SysParam(SysParam),
+    SysCommand(SysCommandPdu),
+    Handshake(HandshakePdu),
+    NotifyEvent(NotifyEventPdu),
+    WindowMove(WindowMovePdu),
+    LocalMoveSize(LocalMoveSizePdu),
+    MinMaxInfo(MinMaxInfoPdu),
+    ClientStatus(ClientStatusFlags),
+    SysMenu(SysMenuPdu),
+    LangBarInfo(LangBarInfoPdu),
+    GetAppIdReq(GetAppIdReqPdu),
+    GetAppIdResp(GetAppIdRespPdu),
+    CompartmentInfo(CompartmentInfoPdu),
+    HandshakeEx(HandshakeExPdu),
+    ZOrderSync(ZOrderSyncPdu),
+    Cloak(CloakPdu),
+    PowerDisplayRequest(PowerDisplayRequestPdu),
+    ExecResult(ExecResultPdu),
+}
+
+impl RailPdu {
+    const NAME: &'static str = "TS_RAIL_PDU";
+
+    const FIXED_PART_SIZE: usize = 2 /* orderType */ + 2 /* orderLength */;
+
+    fn order_type(&self) -> u16 {
+        match self {
+            Self::Exec(_) => TS_RAIL_ORDER_EXEC,
+            Self::Activate(_) => TS_RAIL_ORDER_ACTIVATE,
+            Self::SysParam(_) => TS_RAIL_ORDER_SYSPARAM,
+            Self::SysCommand(_) => TS_RAIL_ORDER_SYSCOMMAND,
+            Self::Handshake(_) => TS_RAIL_ORDER_HANDSHAKE,
+            Self::NotifyEvent(_) => TS_RAIL_ORDER_NOTIFY_EVENT,
+            Self::WindowMove(_) => TS_RAIL_ORDER_WINDOWMOVE,
+            Self::LocalMoveSize(_) => TS_RAIL_ORDER_LOCALMOVESIZE,
+            Self::MinMaxInfo(_) => TS_RAIL_ORDER_MINMAXINFO,
+            Self::ClientStatus(_) => TS_RAIL_ORDER_CLIENTSTATUS,
+            Self::SysMenu(_) => TS_RAIL_ORDER_SYSMENU,
+            Self::LangBarInfo(_) => TS_RAIL_ORDER_LANGBARINFO,
+            Self::GetAppIdReq(_) => TS_RAIL_ORDER_GET_APPID_REQ,
+            Self::GetAppIdResp(_) => TS_RAIL_ORDER_GET_APPID_RESP,
+            Self::CompartmentInfo(_) => TS_RAIL_ORDER_COMPARTMENTINFO,
+            Self::HandshakeEx(_) => TS_RAIL_ORDER_HANDSHAKE_EX,
+            Self::ZOrderSync(_) => TS_RAIL_ORDER_ZORDER_SYNC,
+            Self::Cloak(_) => TS_RAIL_ORDER_CLOAK,
+            Self::PowerDisplayRequest(_) => TS_RAIL_ORDER_POWER_DISPLAY_REQUEST,
+            Self::ExecResult(_) => TS_RAIL_ORDER_EXEC_RESULT,
+        }
+    }
+
+    fn body(&self) -> &dyn Encode {
+        match self {
+            Self::Exec(pdu) => pdu,
+            Self::Activate(pdu) => pdu,
+            Self::SysParam(pdu) => pdu,
+            Self::SysCommand(pdu) => pdu,
+            Self::Handshake(pdu) => pdu,
+            Self::NotifyEvent(pdu) => pdu,
+            Self::WindowMove(pdu) => pdu,
+            Self::LocalMoveSize(pdu) => pdu,
+            Self::MinMaxInfo(pdu) => pdu,
+            Self::ClientStatus(pdu) => pdu,
+            Self::SysMenu(pdu) => pdu,
+            Self::LangBarInfo(pdu) => pdu,
+            Self::GetAppIdReq(pdu) => pdu,
+            Self::GetAppIdResp(pdu) => pdu,
+            Self::CompartmentInfo(pdu) => pdu,
+            Self::HandshakeEx(pdu) => pdu,
+            Self::ZOrderSync(pdu) => pdu,
+            Self::Cloak(pdu) => pdu,
+            Self::PowerDisplayRequest(pdu) => pdu,
+            Self::ExecResult(pdu) => pdu,
+        }
+    }
+}
+
+impl Encode for RailPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(self.order_type());
+        dst.write_u16(cast_length!("orderLength", self.size())?);
+
+        self.body().encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.body().size()
+    }
+}
+
+impl<'de> Decode<'de> for RailPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let order_type = src.read_u16();
+        let order_length = usize::from(src.read_u16());
+
+        let body_length = order_length
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("orderLength", "order length is too small"))?;
+        ensure_size!(in: src, size: body_length);
+        let src = &mut ReadCursor::new(src.read_slice(body_length));
+
+        let pdu = match order_type {
+            TS_RAIL_ORDER_EXEC => Self::Exec(ExecPdu::decode(src)?),
+            TS_RAIL_ORDER_ACTIVATE => Self::Activate(ActivatePdu::decode(src)?),
+            TS_RAIL_ORDER_SYSPARAM => Self::SysParam(SysParam::decode(src)?),
+            TS_RAIL_ORDER_SYSCOMMAND => Self::SysCommand(SysCommandPdu::decode(src)?),
+            TS_RAIL_ORDER_HANDSHAKE => Self::Handshake(HandshakePdu::decode(src)?),
+            TS_RAIL_ORDER_NOTIFY_EVENT => Self::NotifyEvent(NotifyEventPdu::decode(src)?),
+            TS_RAIL_ORDER_WINDOWMOVE => Self::WindowMove(WindowMovePdu::decode(src)?),
+            TS_RAIL_ORDER_LOCALMOVESIZE => Self::LocalMoveSize(LocalMoveSizePdu::decode(src)?),
+            TS_RAIL_ORDER_MINMAXINFO => Self::MinMaxInfo(MinMaxInfoPdu::decode(src)?),
+            TS_RAIL_ORDER_CLIENTSTATUS => Self::ClientStatus(ClientStatusFlags::decode(src)?),
+            TS_RAIL_ORDER_SYSMENU => Self::SysMenu(SysMenuPdu::decode(src)?),
+            TS_RAIL_ORDER_LANGBARINFO => Self::LangBarInfo(LangBarInfoPdu::decode(src)?),
+            TS_RAIL_ORDER_GET_APPID_REQ => Self::GetAppIdReq(GetAppIdReqPdu::decode(src)?),
+            TS_RAIL_ORDER_GET_APPID_RESP => Self::GetAppIdResp(GetAppIdRespPdu::decode(src)?),
+            TS_RAIL_ORDER_COMPARTMENTINFO => Self::CompartmentInfo(CompartmentInfoPdu::decode(src)?),
+            TS_RAIL_ORDER_HANDSHAKE_EX => Self::HandshakeEx(HandshakeExPdu::decode(src)?),
+            TS_RAIL_ORDER_ZORDER_SYNC => Self::ZOrderSync(ZOrderSyncPdu::decode(src)?),
+            TS_RAIL_ORDER_CLOAK => Self::Cloak(CloakPdu::decode(src)?),
+            TS_RAIL_ORDER_POWER_DISPLAY_REQUEST => Self::PowerDisplayRequest(PowerDisplayRequestPdu::decode(src)?),
+            TS_RAIL_ORDER_EXEC_RESULT => Self::ExecResult(ExecResultPdu::decode(src)?),
+            _ => return Err(invalid_field_err!("orderType", "unknown RAIL order type")),
+        };
+
+        Ok(pdu)
+    }
+}
+
+impl SvcEncode for RailPdu {}
+
+/// Implements [`Encode`] and [`Decode`] for a fixed-size PDU body made of integer fields.
+macro_rules! fixed_pdu {
+    ($name:ident, $wire_name:literal, { $($field:ident: $ty:ident),+ $(,)? }) => {
+        impl $name {
+            const NAME: &'static str = $wire_name;
+
+            const FIXED_PART_SIZE: usize = 0 $(+ size_of::<$ty>())+;
+        }
+
+        impl Encode for $name {
+            fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+                ensure_fixed_part_size!(in: dst);
+
+                $(dst.write_array(self.$field.to_le_bytes());)+
+
+                Ok(())
+            }
+
+            fn name(&self) -> &'static str {
+                Self::NAME
+            }
+
+            fn size(&self) -> usize {
+                Self::FIXED_PART_SIZE
+            }
+        }
+
+        impl<'de> Decode<'de> for $name {
+            fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+                ensure_fixed_part_size!(in: src);
+
+                Ok(Self {
+                    $($field: $ty::from_le_bytes(src.read_array()),)+
+                })
+            }
+        }
+    };
+}
+
+/// TS_RAIL_ORDER_HANDSHAKE
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HandshakePdu {
+    pub build_number: u32,
+}
+
+fixed_pdu!(HandshakePdu, "TS_RAIL_ORDER_HANDSHAKE", { build_number: u32 });
+
+bitflags! {
+    /// railHandshakeFlags of TS_RAIL_ORDER_HANDSHAKE_EX
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct HandshakeExFlags: u32 {
+        const HIDEF = 0x0000_0001;
+        const EXTENDED_SPI_SUPPORTED = 0x0000_0002;
+        const SNAP_ARRANGE_SUPPORTED = 0x0000_0004;
+        const TEXT_SCALE_SUPPORTED = 0x0000_0008;
+        const CARET_BLINK_SUPPORTED = 0x0000_0010;
+        const EXTENDED_SPI_2_SUPPORTED = 0x0000_0020;
+        const EXTENDED_SPI_3_SUPPORTED = 0x0000_0040;
+    }
+}
+
+/// TS_RAIL_ORDER_HANDSHAKE_EX
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HandshakeExPdu {
+    pub build_number: u32,
+    pub flags: HandshakeExFlags,
+}
+
+impl HandshakeExPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_HANDSHAKE_EX";
+
+    const FIXED_PART_SIZE: usize = 4 /* buildNumber */ + 4 /* railHandshakeFlags */;
+}
+
+impl Encode for HandshakeExPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.build_number);
+        dst.write_u32(self.flags.bits());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for HandshakeExPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            build_number: src.read_u32(),
+            flags: HandshakeExFlags::from_bits_retain(src.read_u32()),
+        })
+    }
+}
+
+bitflags! {
+    /// TS_RAIL_ORDER_CLIENTSTATUS
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct ClientStatusFlags: u32 {
+        const ALLOWLOCALMOVESIZE = 0x0000_0001;
+        const AUTORECONNECT = 0x0000_0002;
+        const ZORDER_SYNC = 0x0000_0004;
+        const WINDOW_RESIZE_MARGIN_SUPPORTED = 0x0000_0010;
+        const HIGH_DPI_ICONS_SUPPORTED = 0x0000_0020;
+        const APPBAR_REMOTING_SUPPORTED = 0x0000_0040;
+        const POWER_DISPLAY_REQUEST_SUPPORTED = 0x0000_0080;
+        const BIDIRECTIONAL_CLOAK_SUPPORTED = 0x0000_0200;
+        const SUPPRESS_ICON_ORDERS = 0x0000_0400;
+    }
+}
+
+impl ClientStatusFlags {
+    const NAME: &'static str = "TS_RAIL_ORDER_CLIENTSTATUS";
+
+    const FIXED_PART_SIZE: usize = 4 /* Flags */;
+}
+
+impl Encode for ClientStatusFlags {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.bits());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ClientStatusFlags {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self::from_bits_retain(src.read_u32()))
+    }
+}
+
+/// TS_RAIL_ORDER_SYSPARAM, a system parameter of the client or of the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SysParam {
+    /// SPI_SETDRAGFULLWINDOWS, sent by the client
+    DragFullWindows(bool),
+    /// SPI_SETKEYBOARDCUES, sent by the client
+    KeyboardCues(bool),
+    /// SPI_SETKEYBOARDPREF, sent by the client
+    KeyboardPref(bool),
+    /// SPI_SETMOUSEBUTTONSWAP, sent by the client
+    MouseButtonSwap(bool),
+    /// SPI_SETWORKAREA, sent by the client
+    WorkArea(ExclusiveRectangle),
+    /// RAIL_SPI_DISPLAYCHANGE, sent by the client
+    DisplayChange(ExclusiveRectangle),
+    /// RAIL_SPI_TASKBARPOS, sent by the client
+    TaskbarPos(ExclusiveRectangle),
+    /// SPI_SETSCREENSAVEACTIVE, sent by the server
+    ScreenSaveActive(bool),
+    /// SPI_SETSCREENSAVESECURE, sent by the server
+    ScreenSaveSecure(bool),
+    /// Any other system parameter, with its raw body
+    Other { param: u32, body: Vec<u8> },
+}
+
+impl SysParam {
+    const NAME: &'static str = "TS_RAIL_ORDER_SYSPARAM";
+
+    const FIXED_PART_SIZE: usize = 4 /* SystemParam */;
+
+    fn param(&self) -> u32 {
+        match self {
+            Self::DragFullWindows(_) => SPI_SETDRAGFULLWINDOWS,
+            Self::KeyboardCues(_) => SPI_SETKEYBOARDCUES,
+            Self::KeyboardPref(_) => SPI_SETKEYBOARDPREF,
+            Self::MouseButtonSwap(_) => SPI_SETMOUSEBUTTONSWAP,
+            Self::WorkArea(_) => SPI_SETWORKAREA,
+            Self::DisplayChange(_) => RAIL_SPI_DISPLAYCHANGE,
+            Self::TaskbarPos(_) => RAIL_SPI_TASKBARPOS,
+            Self::ScreenSaveActive(_) => SPI_SETSCREENSAVEACTIVE,
+            Self::ScreenSaveSecure(_) => SPI_SETSCREENSAVESECURE,
+            Self::Other { param, .. } => *param,
+        }
+    }
+}
+
+impl Encode for SysParam {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.param());
+
+        match self {
+            Self::DragFullWindows(value)
+            | Self::KeyboardCues(value)
+            | Self::KeyboardPref(value)
+            | Self::MouseButtonSwap(value)
+            | Self::ScreenSaveActive(value)
+            | Self::ScreenSaveSecure(value) => dst.write_u8(u8::from(*value)),
+            Self::WorkArea(rect) | Self::DisplayChange(rect) | Self::TaskbarPos(rect) => rect.encode(dst)?,
+            Self::Other { body, .. } => dst.write_slice(body),
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        let body_size = match self {
+            Self::DragFullWindows(_)
+            | Self::KeyboardCues(_)
+            | Self::KeyboardPref(_)
+            | Self::MouseButtonSwap(_)
+            | Self::ScreenSaveActive(_)
+            | Self::ScreenSaveSecure(_) => 1,
+            Self::WorkArea(_) | Self::DisplayChange(_) | Self::TaskbarPos(_) => ExclusiveRectangle::ENCODED_SIZE,
+            Self::Other { body, .. } => body.len(),
+        };
+
+        Self::FIXED_PART_SIZE + body_size
+    }
+}
+
+impl<'de> Decode<'de> for SysParam {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let param = src.read_u32();
+
+        let read_bool = |src: &mut ReadCursor<'de>| -> DecodeResult<bool> {
+            ensure_size!(in: src, size: 1);
+            Ok(src.read_u8() != 0)
+        };
+
+        let sys_param = match param {
+            SPI_SETDRAGFULLWINDOWS => Self::DragFullWindows(read_bool(src)?),
+            SPI_SETKEYBOARDCUES => Self::KeyboardCues(read_bool(src)?),
+            SPI_SETKEYBOARDPREF => Self::KeyboardPref(read_bool(src)?),
+            SPI_SETMOUSEBUTTONSWAP => Self::MouseButtonSwap(read_bool(src)?),
+            SPI_SETWORKAREA => Self::WorkArea(ExclusiveRectangle::decode(src)?),
+            RAIL_SPI_DISPLAYCHANGE => Self::DisplayChange(ExclusiveRectangle::decode(src)?),
+            RAIL_SPI_TASKBARPOS => Self::TaskbarPos(ExclusiveRectangle::decode(src)?),
+            SPI_SETSCREENSAVEACTIVE => Self::ScreenSaveActive(read_bool(src)?),
+            SPI_SETSCREENSAVESECURE => Self::ScreenSaveSecure(read_bool(src)?),
+            _ => Self::Other {
+                param,
+                body: src.read_remaining().to_vec(),
+            },
+        };
+
+        Ok(sys_param)
+    }
+}
+
+bitflags! {
+    /// Flags of TS_RAIL_ORDER_EXEC
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct ExecFlags: u16 {
+        const EXPAND_WORKINGDIRECTORY = 0x0001;
+        const TRANSLATE_FILES = 0x0002;
+        const FILE = 0x0004;
+        const EXPAND_ARGUMENTS = 0x0008;
+        const APP_USER_MODEL_ID = 0x0010;
+    }
+}
+
+/// TS_RAIL_ORDER_EXEC, a request to start an application
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecPdu {
+    pub flags: ExecFlags,
+    /// Executable, file to open, or application user model ID
+    pub exe_or_file: String,
+    pub working_dir: String,
+    pub arguments: String,
+}
+
+impl ExecPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_EXEC";
+
+    const FIXED_PART_SIZE: usize =
+        2 /* Flags */ + 2 /* ExeOrFileLength */ + 2 /* WorkingDirLength */ + 2 /* ArgumentsLen */;
+}
+
+impl Encode for ExecPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(self.flags.bits());
+        dst.write_u16(cast_length!("ExeOrFileLength", unicode_size(&self.exe_or_file))?);
+        dst.write_u16(cast_length!("WorkingDirLength", unicode_size(&self.working_dir))?);
+        dst.write_u16(cast_length!("ArgumentsLen", unicode_size(&self.arguments))?);
+        utils::write_string_to_cursor(dst, &self.exe_or_file, CharacterSet::Unicode, false)?;
+        utils::write_string_to_cursor(dst, &self.working_dir, CharacterSet::Unicode, false)?;
+        utils::write_string_to_cursor(dst, &self.arguments, CharacterSet::Unicode, false)?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            + unicode_size(&self.exe_or_file)
+            + unicode_size(&self.working_dir)
+            + unicode_size(&self.arguments)
+    }
+}
+
+impl<'de> Decode<'de> for ExecPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let flags = ExecFlags::from_bits_retain(src.read_u16());
+        let exe_or_file_length = usize::from(src.read_u16());
+        let working_dir_length = usize::from(src.read_u16());
+        let arguments_length = usize::from(src.read_u16());
+
+        Ok(Self {
+            flags,
+            exe_or_file: read_unicode(src, exe_or_file_length)?,
+            working_dir: read_unicode(src, working_dir_length)?,
+            arguments: read_unicode(src, arguments_length)?,
+        })
+    }
+}
+
+/// ExecResult of TS_RAIL_ORDER_EXEC_RESULT
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ExecResult(pub u16);
+
+impl ExecResult {
+    pub const OK: Self = Self(0x0000);
+    pub const HOOK_NOT_LOADED: Self = Self(0x0001);
+    pub const DECODE_FAILED: Self = Self(0x0002);
+    pub const NOT_IN_ALLOWLIST: Self = Self(0x0003);
+    pub const FILE_NOT_FOUND: Self = Self(0x0005);
+    pub const FAIL: Self = Self(0x0006);
+    pub const SESSION_LOCKED: Self = Self(0x0007);
+}
+
+/// TS_RAIL_ORDER_EXEC_RESULT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecResultPdu {
+    /// Flags of the [`ExecPdu`] this is the result of
+    pub flags: ExecFlags,
+    pub result: ExecResult,
+    /// Operating system specific error code
+    pub raw_result: u32,
+    /// Executable or file of the [`ExecPdu`] this is the result of
+    pub exe_or_file: String,
+}
+
+impl ExecResultPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_EXEC_RESULT";
+
+    const FIXED_PART_SIZE: usize =
+        2 /* Flags */ + 2 /* ExecResult */ + 4 /* RawResult */ + 2 /* Padding */ + 2 /* ExeOrFileLength */;
+}
+
+impl Encode for ExecResultPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(self.flags.bits());
+        dst.write_u16(self.result.0);
+        dst.write_u32(self.raw_result);
+        write_padding!(dst, 2);
+        dst.write_u16(cast_length!("ExeOrFileLength", unicode_size(&self.exe_or_file))?);
+        utils::write_string_to_cursor(dst, &self.exe_or_file, CharacterSet::Unicode, false)?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + unicode_size(&self.exe_or_file)
+    }
+}
+
+impl<'de> Decode<'de> for ExecResultPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let flags = ExecFlags::from_bits_retain(src.read_u16());
+        let result = ExecResult(src.read_u16());
+        let raw_result = src.read_u32();
+        read_padding!(src, 2);
+        let exe_or_file_length = usize::from(src.read_u16());
+
+        Ok(Self {
+            flags,
+            result,
+            raw_result,
+            exe_or_file: read_unicode(src, exe_or_file_length)?,
+        })
+    }
+}
+
+/// TS_RAIL_ORDER_ACTIVATE
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ActivatePdu {
+    pub window_id: u32,
+    /// Whether the window is activated or deactivated
+    pub enabled: u8,
+}
+
+fixed_pdu!(ActivatePdu, "TS_RAIL_ORDER_ACTIVATE", { window_id: u32, enabled: u8 });
+
+/// Command of TS_RAIL_ORDER_SYSCOMMAND
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SysCommand(pub u16);
+
+impl SysCommand {
+    pub const SIZE: Self = Self(0xF000);
+    pub const MOVE: Self = Self(0xF010);
+    pub const MINIMIZE: Self = Self(0xF020);
+    pub const MAXIMIZE: Self = Self(0xF030);
+    pub const CLOSE: Self = Self(0xF060);
+    pub const KEYMENU: Self = Self(0xF100);
+    pub const RESTORE: Self = Self(0xF120);
+    pub const DEFAULT: Self = Self(0xF160);
+
+    fn to_le_bytes(self) -> [u8; 2] {
+        self.0.to_le_bytes()
+    }
+
+    fn from_le_bytes(bytes: [u8; 2]) -> Self {
+        Self(u16::from_le_bytes(bytes))
+    }
+}
+
+/// TS_RAIL_ORDER_SYSCOMMAND
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SysCommandPdu {
+    pub window_id: u32,
+    pub command: SysCommand,
+}
+
+fixed_pdu!(SysCommandPdu, "TS_RAIL_ORDER_SYSCOMMAND", { window_id: u32, command: SysCommand });
+
+/// TS_RAIL_ORDER_NOTIFY_EVENT, a mouse or keyboard event on a notification icon
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NotifyEventPdu {
+    pub window_id: u32,
+    pub notify_icon_id: u32,
+    /// WM_* or NIN_* message
+    pub message: u32,
+}
+
+fixed_pdu!(NotifyEventPdu, "TS_RAIL_ORDER_NOTIFY_EVENT", { window_id: u32, notify_icon_id: u32, message: u32 });
+
+/// TS_RAIL_ORDER_WINDOWMOVE, the new position of a window moved or resized locally
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowMovePdu {
+    pub window_id: u32,
+    pub left: i16,
+    pub top: i16,
+    pub right: i16,
+    pub bottom: i16,
+}
+
+fixed_pdu!(WindowMovePdu, "TS_RAIL_ORDER_WINDOWMOVE", { window_id: u32, left: i16, top: i16, right: i16, bottom: i16 });
+
+/// MoveSizeType of TS_RAIL_ORDER_LOCALMOVESIZE
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MoveSizeType(pub u16);
+
+impl MoveSizeType {
+    pub const SIZE_LEFT: Self = Self(0x0001);
+    pub const SIZE_RIGHT: Self = Self(0x0002);
+    pub const SIZE_TOP: Self = Self(0x0003);
+    pub const SIZE_TOPLEFT: Self = Self(0x0004);
+    pub const SIZE_TOPRIGHT: Self = Self(0x0005);
+    pub const SIZE_BOTTOM: Self = Self(0x0006);
+    pub const SIZE_BOTTOMLEFT: Self = Self(0x0007);
+    pub const SIZE_BOTTOMRIGHT: Self = Self(0x0008);
+    pub const MOVE: Self = Self(0x0009);
+    pub const KEYMOVE: Self = Self(0x000A);
+    pub const KEYSIZE: Self = Self(0x000B);
+
+    fn to_le_bytes(self) -> [u8; 2] {
+        self.0.to_le_bytes()
+    }
+
+    fn from_le_bytes(bytes: [u8; 2]) -> Self {
+        Self(u16::from_le_bytes(bytes))
+    }
+}
+
+/// TS_RAIL_ORDER_LOCALMOVESIZE, the start or the end of a local move or resize of a window
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LocalMoveSizePdu {
+    pub window_id: u32,
+    /// Non-zero at the start of the move or resize
+    pub is_move_size_start: u16,
+    pub move_size_type: MoveSizeType,
+    /// Mouse position at the start, window position at the end
+    pub x: i16,
+    pub y: i16,
+}
+
+fixed_pdu!(LocalMoveSizePdu, "TS_RAIL_ORDER_LOCALMOVESIZE", {
+    window_id: u32,
+    is_move_size_start: u16,
+    move_size_type: MoveSizeType,
+    x: i16,
+    y: i16,
+});
+
+/// TS_RAIL_ORDER_MINMAXINFO, the size limits of a window
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MinMaxInfoPdu {
+    pub window_id: u32,
+    pub max_width: i16,
+    pub max_height: i16,
+    pub max_pos_x: i16,
+    pub max_pos_y: i16,
+    pub min_track_width: i16,
+    pub min_track_height: i16,
+    pub max_track_width: i16,
+    pub max_track_height: i16,
+}
+
+fixed_pdu!(MinMaxInfoPdu, "TS_RAIL_ORDER_MINMAXINFO", {
+    window_id: u32,
+    max_width: i16,
+    max_height: i16,
+    max_pos_x: i16,
+    max_pos_y: i16,
+    min_track_width: i16,
+    min_track_height: i16,
+    max_track_width: i16,
+    max_track_height: i16,
+});
+
+/// TS_RAIL_ORDER_SYSMENU, a request to show the system menu of a window
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SysMenuPdu {
+    pub window_id: u32,
+    pub left: i16,
+    pub top: i16,
+}
+
+fixed_pdu!(SysMenuPdu, "TS_RAIL_ORDER_SYSMENU", { window_id: u32, left: i16, top: i16 });
+
+/// TS_RAIL_ORDER_LANGBARINFO
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LangBarInfoPdu {
+    /// TF_SFT_* flags
+    pub language_bar_status: u32,
+}
+
+fixed_pdu!(LangBarInfoPdu, "TS_RAIL_ORDER_LANGBARINFO", { language_bar_status: u32 });
+
+/// TS_RAIL_ORDER_GET_APPID_REQ
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GetAppIdReqPdu {
+    pub window_id: u32,
+}
+
+fixed_pdu!(GetAppIdReqPdu, "TS_RAIL_ORDER_GET_APPID_REQ", { window_id: u32 });
+
+/// TS_RAIL_ORDER_GET_APPID_RESP
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetAppIdRespPdu {
+    pub window_id: u32,
+    /// At most 255 UTF-16 code units
+    pub application_id: String,
+}
+
+impl GetAppIdRespPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_GET_APPID_RESP";
+
+    const APPLICATION_ID_SIZE: usize = 520;
+
+    const FIXED_PART_SIZE: usize = 4 /* WindowId */ + Self::APPLICATION_ID_SIZE;
+}
+
+impl Encode for GetAppIdRespPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        let application_id_size = utils::encoded_str_len(&self.application_id, CharacterSet::Unicode, true);
+        if application_id_size > Self::APPLICATION_ID_SIZE {
+            return Err(invalid_field_err!("ApplicationId", "application ID is too long"));
+        }
+
+        dst.write_u32(self.window_id);
+        utils::write_string_to_cursor(dst, &self.application_id, CharacterSet::Unicode, true)?;
+        write_padding!(dst, Self::APPLICATION_ID_SIZE - application_id_size);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for GetAppIdRespPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let window_id = src.read_u32();
+        let application_id =
+            utils::decode_string(src.read_slice(Self::APPLICATION_ID_SIZE), CharacterSet::Unicode, true)?;
+
+        Ok(Self {
+            window_id,
+            application_id,
+        })
+    }
+}
+
+/// TS_RAIL_ORDER_COMPARTMENTINFO, the input method state of the client or of the server
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CompartmentInfoPdu {
+    pub ime_state: u32,
+    pub ime_conv_mode: u32,
+    pub ime_sentence_mode: u32,
+    pub kana_mode: u32,
+}
+
+fixed_pdu!(CompartmentInfoPdu, "TS_RAIL_ORDER_COMPARTMENTINFO", {
+    ime_state: u32,
+    ime_conv_mode: u32,
+    ime_sentence_mode: u32,
+    kana_mode: u32,
+});
+
+/// TS_RAIL_ORDER_ZORDER_SYNC, the window at the top of the Z-order of the server
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ZOrderSyncPdu {
+    pub window_id_marker: u32,
+}
+
+fixed_pdu!(ZOrderSyncPdu, "TS_RAIL_ORDER_ZORDER_SYNC", { window_id_marker: u32 });
+
+/// TS_RAIL_ORDER_CLOAK
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CloakPdu {
+    pub window_id: u32,
+    pub cloaked: u8,
+}
+
+fixed_pdu!(CloakPdu, "TS_RAIL_ORDER_CLOAK", { window_id: u32, cloaked: u8 });
+
+/// TS_RAIL_ORDER_POWER_DISPLAY_REQUEST
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PowerDisplayRequestPdu {
+    /// Non-zero when the display must stay on
+    pub active: u32,
+}
+
+fixed_pdu!(PowerDisplayRequestPdu, "TS_RAIL_ORDER_POWER_DISPLAY_REQUEST", { active: u32 });
+
+bitflags! {
+    /// RailSupportLevel of TS_RAIL_CAPABILITYSET
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct RailSupportLevel: u32 {
+        const SUPPORTED = 0x0000_0001;
+        const DOCKED_LANGBAR_SUPPORTED = 0x0000_0002;
+        const SHELL_INTEGRATION_SUPPORTED = 0x0000_0004;
+        const LANGUAGE_IME_SYNC_SUPPORTED = 0x0000_0008;
+        const SERVER_TO_CLIENT_IME_SYNC_SUPPORTED = 0x0000_0010;
+        const HIDE_MINIMIZED_APPS_SUPPORTED = 0x0000_0020;
+        const WINDOW_CLOAKING_SUPPORTED = 0x0000_0040;
+        const HANDSHAKE_EX_SUPPORTED = 0x0000_0080;
+    }
+}
+
+/// TS_RAIL_CAPABILITYSET, the body of [`CapabilitySet::Rail`](ironrdp_pdu::rdp::capability_sets::CapabilitySet::Rail)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RailCapabilitySet {
+    pub support_level: RailSupportLevel,
+}
+
+impl RailCapabilitySet {
+    const NAME: &'static str = "TS_RAIL_CAPABILITYSET";
+
+    const FIXED_PART_SIZE: usize = 4 /* RailSupportLevel */;
+}
+
+impl Encode for RailCapabilitySet {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.support_level.bits());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for RailCapabilitySet {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            support_level: RailSupportLevel::from_bits_retain(src.read_u32()),
+        })
+    }
+}
+
+/// WndSupportLevel of TS_WINDOW_CAPABILITYSET
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowSupportLevel(pub u32);
+
+impl WindowSupportLevel {
+    pub const NOT_SUPPORTED: Self = Self(0x0000_0000);
+    pub const SUPPORTED: Self = Self(0x0000_0001);
+    pub const SUPPORTED_EX: Self = Self(0x0000_0002);
+}
+
+/// TS_WINDOW_CAPABILITYSET, the body of [`CapabilitySet::WindowList`](ironrdp_pdu::rdp::capability_sets::CapabilitySet::WindowList)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowListCapabilitySet {
+    pub support_level: WindowSupportLevel,
+    pub num_icon_caches: u8,
+    pub num_icon_cache_entries: u16,
+}
+
+impl WindowListCapabilitySet {
+    const NAME: &'static str = "TS_WINDOW_CAPABILITYSET";
+
+    const FIXED_PART_SIZE: usize = 4 /* WndSupportLevel */ + 1 /* NumIconCaches */ + 2 /* NumIconCacheEntries */;
+}
+
+impl Encode for WindowListCapabilitySet {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.support_level.0);
+        dst.write_u8(self.num_icon_caches);
+        dst.write_u16(self.num_icon_cache_entries);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for WindowListCapabilitySet {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        // Older clients only send the support level.
+        ensure_size!(in: src, size: 4);
+        let support_level = WindowSupportLevel(src.read_u32());
+
+        let (num_icon_caches, num_icon_cache_entries) = if src.len() >= 3 {
+            (src.read_u8(), src.read_u16())
+        } else {
+            (0, 0)
+        };
+
+        Ok(Self {
+            support_level,
+            num_icon_caches,
+            num_icon_cache_entries,
+        })
+    }
+}
+
+fn unicode_size(value: &str) -> usize {
+    utils::encoded_str_len(value, CharacterSet::Unicode, false)
+}
+
+fn read_unicode(src: &mut ReadCursor<'_>, size: usize) -> DecodeResult<String> {
+    ensure_size!(in: src, size: size);
+
+    utils::decode_string(src.read_slice(size), CharacterSet::Unicode, false)
+}
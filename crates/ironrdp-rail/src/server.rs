@@ -0,0 +1,293 @@
+use ironrdp_core::{impl_as_any, Decode as _, ReadCursor};
+use ironrdp_pdu::gcc::ChannelName;
+use ironrdp_pdu::orders::{DesktopOrder, MonitoredDesktop, WindowInfoOrder, WindowOrder};
+use ironrdp_pdu::rdp::client_info::VisualExperience;
+use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{CompressionCondition, SvcMessage, SvcProcessor, SvcProcessorMessages, SvcServerProcessor};
+use tracing::{debug, warn};
+
+use crate::pdu::{
+    ActivatePdu, ClientStatusFlags, CloakPdu, ExecPdu, ExecResult, ExecResultPdu, GetAppIdRespPdu, HandshakePdu,
+    LangBarInfoPdu, LocalMoveSizePdu, MinMaxInfoPdu, NotifyEventPdu, RailPdu, SysCommandPdu, SysMenuPdu, SysParam,
+    WindowMovePdu, ZOrderSyncPdu,
+};
+
+pub type RailSvcMessages = SvcProcessorMessages<RailServer>;
+
+/// Build number announced in the server handshake
+const SERVER_BUILD_NUMBER: u32 = 7601;
+
+/// Message sent by the event loop.
+#[derive(Debug)]
+pub enum RailServerMessage {
+    /// Windowing orders, sent in an orders update
+    ///
+    /// They are dropped when the client doesn't support the windowing orders.
+    WindowOrders(Vec<WindowOrder>),
+    /// System parameter of the server
+    SysParam(SysParam),
+    /// Start or end of a local move or resize, following a system command of the client
+    LocalMoveSize(LocalMoveSizePdu),
+    /// Size limits of a window, sent before a local move or resize
+    MinMaxInfo(MinMaxInfoPdu),
+    /// Window at the top of the Z-order, when it's not a window of the remote applications
+    ZOrderSync {
+        window_id: u32,
+    },
+    LangBarInfo(LangBarInfoPdu),
+}
+
+/// Result of an application started on the request of the client
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ExecOutcome {
+    pub result: ExecResult,
+    /// Operating system specific error code
+    pub raw_result: u32,
+}
+
+/// Remote applications of the server
+///
+/// The windows of the applications are sent with [`RailServerMessage::WindowOrders`], the callbacks handle the
+/// requests of the client about them.
+pub trait RailServerHandler: Send + core::fmt::Debug {
+    /// The client is ready, windowing orders can be sent
+    fn client_status(&mut self, flags: ClientStatusFlags);
+
+    /// Request to start an application
+    fn exec(&mut self, exec: &ExecPdu) -> ExecOutcome;
+
+    /// System parameter of the client
+    fn system_param(&mut self, _param: &SysParam) {}
+
+    fn activate(&mut self, _activate: &ActivatePdu) {}
+
+    /// Request to minimize, maximize, close, ... a window
+    fn system_command(&mut self, _command: &SysCommandPdu) {}
+
+    /// Request to show the system menu of a window
+    fn system_menu(&mut self, _menu: &SysMenuPdu) {}
+
+    /// New position of a window moved or resized locally by the client
+    fn window_move(&mut self, _window_move: &WindowMovePdu) {}
+
+    fn notify_event(&mut self, _event: &NotifyEventPdu) {}
+
+    fn language_bar(&mut self, _info: &LangBarInfoPdu) {}
+
+    fn cloak(&mut self, _cloak: &CloakPdu) {}
+
+    /// Application user model ID of a window
+    fn application_id(&mut self, _window_id: u32) -> Option<String> {
+        None
+    }
+
+    /// Visual effects requested by the client, e.g. to render the windows without animations
+    ///
+    /// Called once the client is connected, before the handshake.
+    fn visual_experience(&mut self, _experience: VisualExperience) {}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RailState {
+    Start,
+    WaitingForHandshake,
+    Ready,
+}
+
+#[derive(Debug)]
+pub struct RailServer {
+    handler: Box<dyn RailServerHandler>,
+    state: RailState,
+    client_status: Option<ClientStatusFlags>,
+}
+
+impl RailServer {
+    pub const NAME: ChannelName = ChannelName::from_static(b"rail\0\0\0\0");
+
+    pub fn new(handler: Box<dyn RailServerHandler>) -> Self {
+        Self {
+            handler,
+            state: RailState::Start,
+            client_status: None,
+        }
+    }
+
+    /// Status of the client, once the handshake is done
+    pub fn client_status(&self) -> Option<ClientStatusFlags> {
+        self.client_status
+    }
+
+    /// Gives the visual effects requested by the client to the handler
+    pub fn set_visual_experience(&mut self, experience: VisualExperience) {
+        self.handler.visual_experience(experience);
+    }
+
+    pub fn system_param(&mut self, param: SysParam) -> PduResult<RailSvcMessages> {
+        self.pdu(RailPdu::SysParam(param))
+    }
+
+    pub fn local_move_size(&mut self, pdu: LocalMoveSizePdu) -> PduResult<RailSvcMessages> {
+        let allowed = self
+            .client_status
+            .is_some_and(|status| status.contains(ClientStatusFlags::ALLOWLOCALMOVESIZE));
+        if !allowed {
+            return Err(pdu_other_err!("client doesn't support local move and resize"));
+        }
+
+        self.pdu(RailPdu::LocalMoveSize(pdu))
+    }
+
+    pub fn min_max_info(&mut self, pdu: MinMaxInfoPdu) -> PduResult<RailSvcMessages> {
+        self.pdu(RailPdu::MinMaxInfo(pdu))
+    }
+
+    pub fn z_order_sync(&mut self, window_id: u32) -> PduResult<RailSvcMessages> {
+        let supported = self
+            .client_status
+            .is_some_and(|status| status.contains(ClientStatusFlags::ZORDER_SYNC));
+        if !supported {
+            return Err(pdu_other_err!("client doesn't support Z-order sync"));
+        }
+
+        self.pdu(RailPdu::ZOrderSync(ZOrderSyncPdu {
+            window_id_marker: window_id,
+        }))
+    }
+
+    pub fn language_bar_info(&mut self, pdu: LangBarInfoPdu) -> PduResult<RailSvcMessages> {
+        self.pdu(RailPdu::LangBarInfo(pdu))
+    }
+
+    fn pdu(&self, pdu: RailPdu) -> PduResult<RailSvcMessages> {
+        if self.state != RailState::Ready {
+            return Err(pdu_other_err!("invalid state, RAIL handshake not done"));
+        }
+
+        Ok(RailSvcMessages::new(vec![pdu.into()]))
+    }
+}
+
+/// Windowing orders synchronizing all the windows of the server
+///
+/// The windows are listed from the top of the Z-order. The client drops the windows that are not listed.
+pub fn window_list_sync(windows: Vec<WindowInfoOrder>, active_window_id: Option<u32>) -> Vec<WindowOrder> {
+    let z_order = windows.iter().map(|window| window.window_id).collect();
+
+    let mut orders = Vec::with_capacity(windows.len() + 2);
+    orders.push(WindowOrder::Desktop(DesktopOrder::Monitored(MonitoredDesktop {
+        hooked: true,
+        arc_began: true,
+        ..MonitoredDesktop::default()
+    })));
+    orders.extend(windows.into_iter().map(WindowOrder::Window));
+    orders.push(WindowOrder::Desktop(DesktopOrder::Monitored(MonitoredDesktop {
+        arc_completed: true,
+        active_window_id,
+        z_order: Some(z_order),
+        ..MonitoredDesktop::default()
+    })));
+
+    orders
+}
+
+impl_as_any!(RailServer);
+
+impl SvcProcessor for RailServer {
+    fn channel_name(&self) -> ChannelName {
+        Self::NAME
+    }
+
+    fn compression_condition(&self) -> CompressionCondition {
+        CompressionCondition::WhenRdpDataIsCompressed
+    }
+
+    fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        let pdu = RailPdu::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        if self.state == RailState::WaitingForHandshake {
+            match pdu {
+                RailPdu::Handshake(_) | RailPdu::HandshakeEx(_) => {
+                    self.state = RailState::Ready;
+                }
+                pdu => warn!(?pdu, "Unexpected RAIL PDU before the handshake"),
+            }
+
+            return Ok(vec![]);
+        }
+
+        let response = match pdu {
+            RailPdu::ClientStatus(flags) => {
+                self.client_status = Some(flags);
+                self.handler.client_status(flags);
+                None
+            }
+            RailPdu::SysParam(param) => {
+                self.handler.system_param(&param);
+                None
+            }
+            RailPdu::Exec(exec) => {
+                let outcome = self.handler.exec(&exec);
+                Some(RailPdu::ExecResult(ExecResultPdu {
+                    flags: exec.flags,
+                    result: outcome.result,
+                    raw_result: outcome.raw_result,
+                    exe_or_file: exec.exe_or_file,
+                }))
+            }
+            RailPdu::Activate(activate) => {
+                self.handler.activate(&activate);
+                None
+            }
+            RailPdu::SysCommand(command) => {
+                self.handler.system_command(&command);
+                None
+            }
+            RailPdu::SysMenu(menu) => {
+                self.handler.system_menu(&menu);
+                None
+            }
+            RailPdu::WindowMove(window_move) => {
+                self.handler.window_move(&window_move);
+                None
+            }
+            RailPdu::NotifyEvent(event) => {
+                self.handler.notify_event(&event);
+                None
+            }
+            RailPdu::LangBarInfo(info) => {
+                self.handler.language_bar(&info);
+                None
+            }
+            RailPdu::Cloak(cloak) => {
+                self.handler.cloak(&cloak);
+                None
+            }
+            RailPdu::GetAppIdReq(request) => Some(RailPdu::GetAppIdResp(GetAppIdRespPdu {
+                window_id: request.window_id,
+                application_id: self.handler.application_id(request.window_id).unwrap_or_default(),
+            })),
+            pdu => {
+                debug!(?pdu, "Ignored RAIL PDU");
+                None
+            }
+        };
+
+        Ok(response.into_iter().map(SvcMessage::from).collect())
+    }
+
+    fn start(&mut self) -> PduResult<Vec<SvcMessage>> {
+        if self.state != RailState::Start {
+            warn!("Attempted to start RAIL channel in invalid state");
+        }
+
+        let pdu = RailPdu::Handshake(HandshakePdu {
+            build_number: SERVER_BUILD_NUMBER,
+        });
+
+        self.state = RailState::WaitingForHandshake;
+        Ok(vec![SvcMessage::from(pdu)])
+    }
+}
+
+impl SvcServerProcessor for RailServer {}
@@ -0,0 +1,321 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use ironrdp_pdu::geometry::ExclusiveRectangle;
+use ironrdp_pdu::orders::{
+    CachedIconOrder, DesktopOrder, IconInfo, MonitoredDesktop, WindowIconOrder, WindowInfoOrder, WindowOrder,
+    WindowStyle,
+};
+use tracing::{debug, warn};
+
+/// Cache entry of an icon which is not cached
+const ICON_NOT_CACHED_ENTRY: u16 = 0xFFFF;
+/// Cache identifier of an icon which is not cached
+const ICON_NOT_CACHED_ID: u8 = 0xFF;
+
+/// A window of a remote application, as described by the windowing orders of the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    pub id: u32,
+    /// The window owning this one, e.g. the main window of a dialog box
+    pub owner_id: Option<u32>,
+    pub style: Option<WindowStyle>,
+    /// SW_* value
+    pub show_state: u8,
+    pub title: String,
+    /// Window position, in screen coordinates
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+    /// Client area position, in screen coordinates
+    pub client_offset: (i32, i32),
+    pub client_area_size: (u32, u32),
+    /// Window shape, relative to the window position, the whole window when empty
+    pub window_rects: Vec<ExclusiveRectangle>,
+    pub visible_offset: (i32, i32),
+    /// Visible region, relative to the visible offset
+    pub visibility_rects: Vec<ExclusiveRectangle>,
+    /// Whether the window has a button in the taskbar of the client
+    pub taskbar_button: bool,
+    pub small_icon: Option<IconInfo>,
+    pub big_icon: Option<IconInfo>,
+}
+
+impl Window {
+    fn new(id: u32) -> Self {
+        Self {
+            id,
+            owner_id: None,
+            style: None,
+            show_state: 0,
+            title: String::new(),
+            position: (0, 0),
+            size: (0, 0),
+            client_offset: (0, 0),
+            client_area_size: (0, 0),
+            window_rects: Vec::new(),
+            visible_offset: (0, 0),
+            visibility_rects: Vec::new(),
+            taskbar_button: true,
+            small_icon: None,
+            big_icon: None,
+        }
+    }
+
+    /// Applies the properties present in the order, and returns the resulting events
+    fn update(&mut self, order: WindowInfoOrder) -> Vec<WindowEvent> {
+        let window_id = self.id;
+
+        let updated = [
+            set(
+                &mut self.owner_id,
+                order.owner_window_id.map(|id| (id != 0).then_some(id)),
+            ),
+            set(&mut self.style, order.style.map(Some)),
+            set(&mut self.show_state, order.show_state),
+            set(&mut self.title, order.title),
+            set(&mut self.window_rects, order.window_rects),
+            set(&mut self.visible_offset, order.visible_offset),
+            set(&mut self.visibility_rects, order.visibility_rects),
+        ]
+        .contains(&true);
+        let moved = [
+            set(&mut self.position, order.window_offset),
+            set(&mut self.size, order.window_size),
+            set(&mut self.client_offset, order.client_offset),
+            set(&mut self.client_area_size, order.client_area_size),
+        ]
+        .contains(&true);
+        // TaskbarButton is 0 when the window has a button, 1 when it is hidden
+        let taskbar = set(&mut self.taskbar_button, order.taskbar_button.map(|hidden| hidden == 0));
+
+        [
+            (moved, WindowEvent::Moved { window_id }),
+            (updated, WindowEvent::Updated { window_id }),
+            (taskbar, WindowEvent::Taskbar { window_id }),
+        ]
+        .into_iter()
+        .filter_map(|(changed, event)| changed.then_some(event))
+        .collect()
+    }
+
+    fn set_icon(&mut self, big: bool, icon: IconInfo) {
+        if big {
+            self.big_icon = Some(icon);
+        } else {
+            self.small_icon = Some(icon);
+        }
+    }
+}
+
+/// Sets a property present in an order, and returns whether it changed
+fn set<T: PartialEq>(property: &mut T, value: Option<T>) -> bool {
+    match value {
+        Some(value) if *property != value => {
+            *property = value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Change of the windows of a [`WindowTree`], for the GUI of the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowEvent {
+    Created {
+        window_id: u32,
+    },
+    /// The position or the size of the window changed
+    Moved {
+        window_id: u32,
+    },
+    /// The title, the style, the show state, the owner or the shape of the window changed
+    Updated {
+        window_id: u32,
+    },
+    /// The small or the big icon of the window changed
+    Icon {
+        window_id: u32,
+        big: bool,
+    },
+    /// The window was added to, or removed from, the taskbar
+    Taskbar {
+        window_id: u32,
+    },
+    Destroyed {
+        window_id: u32,
+    },
+    /// The active window of the server changed
+    Activated {
+        window_id: Option<u32>,
+    },
+    /// The Z-order of the windows changed, see [`WindowTree::z_order`]
+    ZOrder,
+    /// The server stopped monitoring its desktop, all the windows were destroyed
+    Unmonitored,
+}
+
+/// Windows of the remote applications, maintained from the windowing orders of the server
+///
+/// The windows are kept with their owner, from which the GUI of the client can build its own window hierarchy.
+#[derive(Debug, Default)]
+pub struct WindowTree {
+    windows: BTreeMap<u32, Window>,
+    z_order: Vec<u32>,
+    active_window_id: Option<u32>,
+    icon_cache: HashMap<(u8, u16), IconInfo>,
+    /// Windows sent since the start of a synchronization of the desktop
+    synchronized: Option<BTreeSet<u32>>,
+}
+
+impl WindowTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn window(&self, window_id: u32) -> Option<&Window> {
+        self.windows.get(&window_id)
+    }
+
+    pub fn windows(&self) -> impl Iterator<Item = &Window> {
+        self.windows.values()
+    }
+
+    /// Returns the windows owned by a window
+    pub fn owned_windows(&self, owner_id: u32) -> impl Iterator<Item = &Window> {
+        self.windows
+            .values()
+            .filter(move |window| window.owner_id == Some(owner_id))
+    }
+
+    /// Window identifiers, from the top of the Z-order
+    pub fn z_order(&self) -> &[u32] {
+        &self.z_order
+    }
+
+    pub fn active_window_id(&self) -> Option<u32> {
+        self.active_window_id
+    }
+
+    /// Applies a windowing order, and returns the resulting events
+    pub fn apply(&mut self, order: WindowOrder) -> Vec<WindowEvent> {
+        match order {
+            WindowOrder::Window(order) => self.update_window(order),
+            WindowOrder::Icon(WindowIconOrder { window_id, big, icon }) => {
+                if icon.cache_entry != ICON_NOT_CACHED_ENTRY && icon.cache_id != ICON_NOT_CACHED_ID {
+                    self.icon_cache.insert((icon.cache_id, icon.cache_entry), icon.clone());
+                }
+
+                self.set_icon(window_id, big, icon)
+            }
+            WindowOrder::CachedIcon(CachedIconOrder {
+                window_id,
+                big,
+                cache_entry,
+                cache_id,
+            }) => {
+                let Some(icon) = self.icon_cache.get(&(cache_id, cache_entry)).cloned() else {
+                    warn!(cache_id, cache_entry, "Unknown cached icon");
+                    return Vec::new();
+                };
+
+                self.set_icon(window_id, big, icon)
+            }
+            WindowOrder::Deleted { window_id } => self.destroy_window(window_id).into_iter().collect(),
+            WindowOrder::Desktop(DesktopOrder::Monitored(desktop)) => self.update_desktop(desktop),
+            WindowOrder::Desktop(DesktopOrder::NonMonitored) => {
+                self.windows.clear();
+                self.z_order.clear();
+                self.active_window_id = None;
+                self.synchronized = None;
+                vec![WindowEvent::Unmonitored]
+            }
+            WindowOrder::Other {
+                fields_present_flags, ..
+            } => {
+                debug!(fields_present_flags, "Ignored windowing order");
+                Vec::new()
+            }
+        }
+    }
+
+    fn update_window(&mut self, order: WindowInfoOrder) -> Vec<WindowEvent> {
+        let window_id = order.window_id;
+
+        if let Some(synchronized) = self.synchronized.as_mut() {
+            synchronized.insert(window_id);
+        }
+
+        match self.windows.get_mut(&window_id) {
+            Some(window) => window.update(order),
+            None => {
+                if !order.new {
+                    warn!(window_id, "Update of an unknown window");
+                }
+
+                let mut window = Window::new(window_id);
+                // The properties of a new window are reported with its creation.
+                let _ = window.update(order);
+                self.windows.insert(window_id, window);
+
+                vec![WindowEvent::Created { window_id }]
+            }
+        }
+    }
+
+    fn set_icon(&mut self, window_id: u32, big: bool, icon: IconInfo) -> Vec<WindowEvent> {
+        let Some(window) = self.windows.get_mut(&window_id) else {
+            warn!(window_id, "Icon of an unknown window");
+            return Vec::new();
+        };
+
+        window.set_icon(big, icon);
+        vec![WindowEvent::Icon { window_id, big }]
+    }
+
+    fn destroy_window(&mut self, window_id: u32) -> Option<WindowEvent> {
+        self.windows.remove(&window_id)?;
+        self.z_order.retain(|id| *id != window_id);
+        if self.active_window_id == Some(window_id) {
+            self.active_window_id = None;
+        }
+
+        Some(WindowEvent::Destroyed { window_id })
+    }
+
+    fn update_desktop(&mut self, desktop: MonitoredDesktop) -> Vec<WindowEvent> {
+        let mut events = Vec::new();
+
+        if desktop.arc_began {
+            self.synchronized = Some(BTreeSet::new());
+        }
+
+        if desktop.arc_completed {
+            if let Some(synchronized) = self.synchronized.take() {
+                let stale: Vec<u32> = self
+                    .windows
+                    .keys()
+                    .filter(|window_id| !synchronized.contains(window_id))
+                    .copied()
+                    .collect();
+                events.extend(stale.into_iter().filter_map(|window_id| self.destroy_window(window_id)));
+            }
+        }
+
+        if let Some(z_order) = desktop.z_order {
+            if self.z_order != z_order {
+                self.z_order = z_order;
+                events.push(WindowEvent::ZOrder);
+            }
+        }
+
+        if let Some(window_id) = desktop.active_window_id {
+            // The active window is 0xFFFFFFFF when no window of the remote applications is active.
+            let window_id = self.windows.contains_key(&window_id).then_some(window_id);
+            if self.active_window_id != window_id {
+                self.active_window_id = window_id;
+                events.push(WindowEvent::Activated { window_id });
+            }
+        }
+
+        events
+    }
+}
@@ -1,4 +1,6 @@
 pub mod noop;
+pub mod printer;
+pub mod scard;
 
 use core::fmt;
 
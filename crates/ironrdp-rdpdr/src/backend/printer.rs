@@ -0,0 +1,83 @@
+use core::fmt;
+
+use ironrdp_core::{cast_length, DecodeResult, ReadCursor};
+use ironrdp_svc::SvcMessage;
+use tracing::warn;
+
+use crate::pdu::efs::{
+    DeviceCloseResponse, DeviceCreateRequest, DeviceCreateResponse, DeviceIoRequest, DeviceIoResponse,
+    DeviceWriteRequest, DeviceWriteResponse, Information, MajorFunction, NtStatus,
+};
+use crate::pdu::RdpdrPdu;
+
+/// Printers of the client, receiving the print jobs of the server
+///
+/// The job data is rendered by the printer driver of the server, e.g. PostScript or XPS depending on the driver
+/// announced with the printer. It's typically piped to the local print system as is (e.g. CUPS).
+pub trait PrinterBackend: fmt::Debug + Send {
+    /// A print job was opened on a printer, returns its ID
+    fn job_started(&mut self, device_id: u32) -> Result<u32, NtStatus>;
+
+    /// Data of a print job, sent in order
+    fn job_data(&mut self, device_id: u32, job_id: u32, data: &[u8]) -> Result<(), NtStatus>;
+
+    /// The print job was closed by the server, all its data was received
+    fn job_ended(&mut self, device_id: u32, job_id: u32) -> Result<(), NtStatus>;
+}
+
+/// Answers an I/O request of the server on a printer.
+pub(crate) fn handle_printer_io_request(
+    backend: &mut dyn PrinterBackend,
+    dev_io_req: DeviceIoRequest,
+    src: &mut ReadCursor<'_>,
+) -> DecodeResult<SvcMessage> {
+    let device_id = dev_io_req.device_id;
+
+    let pdu = match dev_io_req.major_function {
+        MajorFunction::Create => {
+            let req = DeviceCreateRequest::decode(dev_io_req, src)?;
+            let (io_status, file_id) = match backend.job_started(device_id) {
+                Ok(job_id) => (NtStatus::SUCCESS, job_id),
+                Err(io_status) => (io_status, 0),
+            };
+
+            RdpdrPdu::from(DeviceCreateResponse {
+                device_io_reply: DeviceIoResponse::new(req.device_io_request, io_status),
+                file_id,
+                information: Information::empty(),
+            })
+        }
+        MajorFunction::Write => {
+            let req = DeviceWriteRequest::decode(dev_io_req, src)?;
+            let (io_status, length) = match backend.job_data(device_id, req.device_io_request.file_id, &req.write_data)
+            {
+                Ok(()) => (
+                    NtStatus::SUCCESS,
+                    cast_length!("DeviceWriteResponse", "Length", req.write_data.len())?,
+                ),
+                Err(io_status) => (io_status, 0),
+            };
+
+            RdpdrPdu::from(DeviceWriteResponse {
+                device_io_reply: DeviceIoResponse::new(req.device_io_request, io_status),
+                length,
+            })
+        }
+        MajorFunction::Close => {
+            let io_status = match backend.job_ended(device_id, dev_io_req.file_id) {
+                Ok(()) => NtStatus::SUCCESS,
+                Err(io_status) => io_status,
+            };
+
+            RdpdrPdu::from(DeviceCloseResponse {
+                device_io_response: DeviceIoResponse::new(dev_io_req, io_status),
+            })
+        }
+        _ => {
+            warn!(?dev_io_req, "Unsupported printer request");
+            RdpdrPdu::DeviceIoResponse(DeviceIoResponse::new(dev_io_req, NtStatus::NOT_SUPPORTED))
+        }
+    };
+
+    Ok(SvcMessage::from(pdu))
+}
@@ -0,0 +1,243 @@
+use core::fmt;
+
+use ironrdp_pdu::utils::CharacterSet;
+use tracing::{debug, warn};
+
+use crate::pdu::efs::{DeviceControlRequest, DeviceControlResponse, NtStatus};
+use crate::pdu::esc::{
+    rpce, CardProtocol, CardState, ConnectReturn, EstablishContextReturn, GetDeviceTypeIdReturn, GetReaderIconReturn,
+    GetStatusChangeReturn, ListReadersReturn, LongReturn, ReadCacheReturn, ReaderState, ReaderStateCommonCall,
+    ReturnCode, SCardIORequest, ScardCall, ScardContext, ScardHandle, ScardIoCtlCode, Scope, StatusReturn,
+    TransmitReturn,
+};
+
+/// Smart card subsystem of the client, typically bridging to PC/SC
+///
+/// Each method handles a call of the server. Failures are reported with the [`ReturnCode`] forwarded to the server,
+/// e.g. [`ReturnCode::Timeout`] when [`SmartCardBackend::get_status_change`] saw no change.
+///
+/// The calls are answered synchronously, so the implementation should not block for long: a server waiting for a
+/// card typically polls with [`SmartCardBackend::get_status_change`].
+pub trait SmartCardBackend: fmt::Debug + Send {
+    fn establish_context(&mut self, scope: Scope) -> Result<ScardContext, ReturnCode>;
+
+    fn release_context(&mut self, context: ScardContext) -> Result<(), ReturnCode>;
+
+    fn is_valid_context(&mut self, _context: ScardContext) -> Result<(), ReturnCode> {
+        Ok(())
+    }
+
+    /// Cancels the blocking calls of the context
+    fn cancel(&mut self, _context: ScardContext) -> Result<(), ReturnCode> {
+        Ok(())
+    }
+
+    fn list_readers(&mut self, context: ScardContext, groups: &[String]) -> Result<Vec<String>, ReturnCode>;
+
+    /// Returns the new state of each reader, in the order of `states`
+    fn get_status_change(
+        &mut self,
+        context: ScardContext,
+        timeout: u32,
+        states: &[ReaderState],
+    ) -> Result<Vec<ReaderStateCommonCall>, ReturnCode>;
+
+    /// Returns the handle of the card and the active protocol
+    fn connect(
+        &mut self,
+        context: ScardContext,
+        reader: &str,
+        share_mode: u32,
+        preferred_protocols: CardProtocol,
+    ) -> Result<(ScardHandle, CardProtocol), ReturnCode>;
+
+    fn disconnect(&mut self, handle: &ScardHandle, disposition: u32) -> Result<(), ReturnCode>;
+
+    fn begin_transaction(&mut self, handle: &ScardHandle) -> Result<(), ReturnCode>;
+
+    fn end_transaction(&mut self, handle: &ScardHandle, disposition: u32) -> Result<(), ReturnCode>;
+
+    /// Sends an APDU to the card, returns its response
+    fn transmit(
+        &mut self,
+        handle: &ScardHandle,
+        send_pci: &SCardIORequest,
+        send_buffer: &[u8],
+    ) -> Result<Vec<u8>, ReturnCode>;
+
+    fn status(&mut self, handle: &ScardHandle) -> Result<CardStatus, ReturnCode>;
+
+    fn get_device_type_id(&mut self, _context: ScardContext, _reader: &str) -> Result<u32, ReturnCode> {
+        Err(ReturnCode::UnsupportedFeature)
+    }
+
+    /// Reads the data cached for a card, see [`SmartCardBackend::write_cache`]
+    fn read_cache(
+        &mut self,
+        _context: ScardContext,
+        _card_uuid: &[u8],
+        _lookup_name: &str,
+    ) -> Result<Vec<u8>, ReturnCode> {
+        Err(ReturnCode::CacheItemNotFound)
+    }
+
+    fn write_cache(
+        &mut self,
+        _context: ScardContext,
+        _card_uuid: &[u8],
+        _lookup_name: &str,
+        _data: &[u8],
+    ) -> Result<(), ReturnCode> {
+        Ok(())
+    }
+
+    fn get_reader_icon(&mut self, _context: ScardContext, _reader: &str) -> Result<Vec<u8>, ReturnCode> {
+        Err(ReturnCode::UnsupportedFeature)
+    }
+}
+
+/// Status of a connected card, see [`SmartCardBackend::status`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardStatus {
+    pub reader_names: Vec<String>,
+    pub state: CardState,
+    pub protocol: CardProtocol,
+    /// At most 32 bytes
+    pub atr: Vec<u8>,
+}
+
+/// Answers a smart card call of the server.
+pub(crate) fn handle_scard_call(
+    backend: &mut dyn SmartCardBackend,
+    req: DeviceControlRequest<ScardIoCtlCode>,
+    call: ScardCall,
+) -> DeviceControlResponse {
+    let io_control_code = req.io_control_code;
+
+    let output: Box<dyn rpce::Encode> = match call {
+        ScardCall::AccessStartedEventCall(_) => Box::new(LongReturn::new(ReturnCode::Success)),
+        ScardCall::EstablishContextCall(call) => Box::new(match backend.establish_context(call.scope) {
+            Ok(context) => EstablishContextReturn::new(ReturnCode::Success, context),
+            Err(return_code) => EstablishContextReturn::new(return_code, ScardContext::new(0)),
+        }),
+        ScardCall::ListReadersCall(call) => {
+            let (return_code, readers) = split(backend.list_readers(call.context, &call.groups));
+            Box::new(ListReadersReturn::new(return_code, readers))
+        }
+        ScardCall::GetStatusChangeCall(call) => {
+            let (return_code, reader_states) =
+                split(backend.get_status_change(call.context, call.timeout, &call.states));
+            Box::new(GetStatusChangeReturn::new(return_code, reader_states))
+        }
+        ScardCall::ConnectCall(call) => Box::new(
+            match backend.connect(
+                call.common.context,
+                &call.reader,
+                call.common.share_mode,
+                call.common.preferred_protocols,
+            ) {
+                Ok((handle, active_protocol)) => ConnectReturn::new(ReturnCode::Success, handle, active_protocol),
+                Err(return_code) => ConnectReturn::new(
+                    return_code,
+                    ScardHandle::new(call.common.context, 0),
+                    CardProtocol::SCARD_PROTOCOL_UNDEFINED,
+                ),
+            },
+        ),
+        ScardCall::HCardAndDispositionCall(call) => {
+            let result = match io_control_code {
+                ScardIoCtlCode::BeginTransaction => backend.begin_transaction(&call.handle),
+                ScardIoCtlCode::EndTransaction => backend.end_transaction(&call.handle, call.disposition),
+                _ => backend.disconnect(&call.handle, call.disposition),
+            };
+            Box::new(LongReturn::new(split(result).0))
+        }
+        ScardCall::TransmitCall(call) => {
+            let (return_code, recv_buffer) = split(backend.transmit(&call.handle, &call.send_pci, &call.send_buffer));
+            Box::new(TransmitReturn::new(return_code, None, recv_buffer))
+        }
+        ScardCall::StatusCall(call) => {
+            let encoding = if io_control_code == ScardIoCtlCode::StatusA {
+                CharacterSet::Ansi
+            } else {
+                CharacterSet::Unicode
+            };
+
+            Box::new(match backend.status(&call.handle) {
+                Ok(status) => {
+                    let mut atr = [0; 32];
+                    let mut atr_length = 0;
+                    for (dst, src) in atr.iter_mut().zip(&status.atr) {
+                        *dst = *src;
+                        atr_length += 1;
+                    }
+
+                    StatusReturn::new(
+                        ReturnCode::Success,
+                        status.reader_names,
+                        status.state,
+                        status.protocol,
+                        atr,
+                        atr_length,
+                        encoding,
+                    )
+                }
+                Err(return_code) => StatusReturn::new(
+                    return_code,
+                    Vec::new(),
+                    CardState::Unknown,
+                    CardProtocol::SCARD_PROTOCOL_UNDEFINED,
+                    [0; 32],
+                    0,
+                    encoding,
+                ),
+            })
+        }
+        ScardCall::ContextCall(call) => {
+            let result = match io_control_code {
+                ScardIoCtlCode::ReleaseContext => backend.release_context(call.context),
+                ScardIoCtlCode::IsValidContext => backend.is_valid_context(call.context),
+                _ => backend.cancel(call.context),
+            };
+            Box::new(LongReturn::new(split(result).0))
+        }
+        ScardCall::GetDeviceTypeIdCall(call) => {
+            let (return_code, device_type_id) = split(backend.get_device_type_id(call.context, &call.reader_name));
+            Box::new(GetDeviceTypeIdReturn::new(return_code, device_type_id))
+        }
+        ScardCall::ReadCacheCall(call) => {
+            let (return_code, data) =
+                split(backend.read_cache(call.common.context, &call.common.card_uuid, &call.lookup_name));
+            Box::new(ReadCacheReturn::new(return_code, data))
+        }
+        ScardCall::WriteCacheCall(call) => {
+            let result = backend.write_cache(
+                call.common.context,
+                &call.common.card_uuid,
+                &call.lookup_name,
+                &call.common.data,
+            );
+            Box::new(LongReturn::new(split(result).0))
+        }
+        ScardCall::GetReaderIconCall(call) => {
+            let (return_code, data) = split(backend.get_reader_icon(call.context, &call.reader_name));
+            Box::new(GetReaderIconReturn::new(return_code, data))
+        }
+        ScardCall::Unsupported => {
+            warn!(?io_control_code, "Unsupported smart card call");
+            Box::new(LongReturn::new(ReturnCode::UnsupportedFeature))
+        }
+    };
+
+    debug!(?io_control_code, ?output, "Smart card call completed");
+
+    DeviceControlResponse::new(req, NtStatus::SUCCESS, Some(output))
+}
+
+/// Splits a result in the return code and the value sent to the server, the default value on failure.
+fn split<T: Default>(result: Result<T, ReturnCode>) -> (ReturnCode, T) {
+    match result {
+        Ok(value) => (ReturnCode::Success, value),
+        Err(return_code) => (return_code, T::default()),
+    }
+}
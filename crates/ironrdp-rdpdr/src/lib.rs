@@ -4,21 +4,25 @@
 
 use ironrdp_core::{decode_cursor, impl_as_any, ReadCursor};
 use ironrdp_pdu::gcc::ChannelName;
-use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
+use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
 use ironrdp_svc::{CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor};
 use pdu::efs::{
     Capabilities, ClientDeviceListAnnounce, ClientDeviceListRemove, ClientNameRequest, ClientNameRequestUnicodeFlag,
     CoreCapability, CoreCapabilityKind, DeviceControlRequest, DeviceIoRequest, DeviceType, Devices,
     ServerDeviceAnnounceResponse, VersionAndIdPdu, VersionAndIdPduKind,
 };
+use pdu::epc::PrinterDeviceAnnounce;
 use pdu::esc::{ScardCall, ScardIoCtlCode};
 use pdu::RdpdrPdu;
 use tracing::{debug, trace, warn};
 
 pub mod backend;
 pub mod pdu;
+pub mod server;
 
 pub use self::backend::noop::NoopRdpdrBackend;
+pub use self::backend::printer::PrinterBackend;
+pub use self::backend::scard::{CardStatus, SmartCardBackend};
 pub use self::backend::RdpdrBackend;
 use crate::pdu::efs::ServerDriveIoRequest;
 
@@ -43,6 +47,10 @@ pub struct Rdpdr {
     /// All devices not of the type [`DeviceType::Filesystem`] must be declared here.
     device_list: Devices,
     backend: Box<dyn RdpdrBackend>,
+    /// Answers the smart card calls when set, instead of [`RdpdrBackend::handle_scard_call`].
+    smartcard: Option<Box<dyn SmartCardBackend>>,
+    /// Receives the print jobs of the printers, see [`Rdpdr::with_printers`].
+    printer: Option<Box<dyn PrinterBackend>>,
 }
 
 impl_as_any!(Rdpdr);
@@ -57,6 +65,8 @@ impl Rdpdr {
             capabilities: Capabilities::new(),
             device_list: Devices::new(),
             backend,
+            smartcard: None,
+            printer: None,
         }
     }
 
@@ -67,6 +77,13 @@ impl Rdpdr {
         self
     }
 
+    /// Adds smart card redirection, the calls of the server being answered by `backend`.
+    #[must_use]
+    pub fn with_smartcard_backend(mut self, device_id: u32, backend: Box<dyn SmartCardBackend>) -> Self {
+        self.smartcard = Some(backend);
+        self.with_smartcard(device_id)
+    }
+
     /// Adds drive redirection capability.
     ///
     /// Callers may also include `initial_drives` to pre-configure the list of drives to announce to the server.
@@ -90,6 +107,37 @@ impl Rdpdr {
         ClientDeviceListAnnounce::new_drive(device_id, name)
     }
 
+    /// Adds printer redirection, the print jobs being received by `backend`.
+    ///
+    /// Like drives, printers can also be announced during a session by calling [`Self::add_printer`].
+    pub fn with_printers(
+        mut self,
+        backend: Box<dyn PrinterBackend>,
+        initial_printers: Vec<(u32, PrinterDeviceAnnounce)>,
+    ) -> PduResult<Self> {
+        self.capabilities.add_printer();
+        self.printer = Some(backend);
+        for (device_id, printer) in initial_printers {
+            self.device_list
+                .add_printer(device_id, &printer)
+                .map_err(|e| encode_err!(e))?;
+        }
+        Ok(self)
+    }
+
+    /// Announces a new printer to the server. It's the caller's responsibility to take the returned
+    /// [`ClientDeviceListAnnounce`] and send it to the server.
+    pub fn add_printer(
+        &mut self,
+        device_id: u32,
+        printer: &PrinterDeviceAnnounce,
+    ) -> PduResult<ClientDeviceListAnnounce> {
+        self.device_list
+            .add_printer(device_id, printer)
+            .map_err(|e| encode_err!(e))?;
+        ClientDeviceListAnnounce::new_printer(device_id, printer).map_err(|e| encode_err!(e))
+    }
+
     pub fn remove_device(&mut self, device_id: u32) -> Option<ClientDeviceListRemove> {
         Some(ClientDeviceListRemove::remove_device(
             self.device_list.remove_device(device_id)?,
@@ -161,6 +209,12 @@ impl Rdpdr {
                 debug!(?req);
                 debug!(?req.io_control_code, ?call);
 
+                if let Some(smartcard) = self.smartcard.as_deref_mut() {
+                    let response = RdpdrPdu::from(backend::scard::handle_scard_call(smartcard, req, call));
+                    trace!("sending {:?}", response);
+                    return Ok(vec![SvcMessage::from(response)]);
+                }
+
                 self.backend.handle_scard_call(req, call)?;
 
                 Ok(Vec::new())
@@ -172,6 +226,17 @@ impl Rdpdr {
 
                 Ok(self.backend.handle_drive_io_request(req)?)
             }
+            DeviceType::Print => {
+                let Some(printer) = self.printer.as_deref_mut() else {
+                    warn!(?dev_io_req, "received printer request without printer backend");
+                    return Ok(Vec::new());
+                };
+
+                let res = backend::printer::handle_printer_io_request(printer, dev_io_req, src)
+                    .map_err(|e| decode_err!(e))?;
+                trace!("sending {:?}", res);
+                Ok(vec![res])
+            }
             _ => {
                 // This should never happen, as we only announce devices that we support.
                 warn!(?dev_io_req, "received packet for unsupported device type");
@@ -215,6 +280,9 @@ impl SvcProcessor for Rdpdr {
             | RdpdrPdu::ClientDeviceListRemove(_)
             | RdpdrPdu::VersionAndIdPdu(_)
             | RdpdrPdu::CoreCapability(_)
+            | RdpdrPdu::ServerDriveIoRequest(_)
+            | RdpdrPdu::ScardControlRequest(_)
+            | RdpdrPdu::DeviceIoResponse(_)
             | RdpdrPdu::DeviceControlResponse(_)
             | RdpdrPdu::DeviceCreateResponse(_)
             | RdpdrPdu::ClientDriveQueryInformationResponse(_)
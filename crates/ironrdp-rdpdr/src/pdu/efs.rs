@@ -14,6 +14,7 @@ use ironrdp_pdu::utils::{decode_string, encoded_str_len, from_utf16_bytes, write
 use ironrdp_pdu::{read_padding, write_padding, PduError};
 use tracing::error;
 
+use super::epc::PrinterDeviceAnnounce;
 use super::esc::rpce;
 use super::{PacketId, SharedHeader};
 
@@ -75,6 +76,33 @@ impl VersionAndIdPdu {
         })
     }
 
+    pub fn new_server_announce_request(client_id: u32) -> Self {
+        Self {
+            version_major: VERSION_MAJOR,
+            version_minor: VERSION_MINOR_12,
+            client_id,
+            kind: VersionAndIdPduKind::ServerAnnounceRequest,
+        }
+    }
+
+    /// Creates the [`VersionAndIdPduKind::ServerClientIdConfirm`] confirming the version of the client `reply`.
+    pub fn new_server_client_id_confirm(reply: &VersionAndIdPdu) -> DecodeResult<Self> {
+        if reply.kind != VersionAndIdPduKind::ClientAnnounceReply {
+            return Err(invalid_field_err!(
+                "VersionAndIdPdu::new_server_client_id_confirm",
+                "VersionAndIdPduKind",
+                "invalid value"
+            ));
+        }
+
+        Ok(Self {
+            version_major: VERSION_MAJOR,
+            version_minor: reply.version_minor.min(VERSION_MINOR_12),
+            client_id: reply.client_id,
+            kind: VersionAndIdPduKind::ServerClientIdConfirm,
+        })
+    }
+
     pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(ctx: self.name(), in: dst, size: Self::FIXED_PART_SIZE);
         dst.write_u16(self.version_major);
@@ -96,6 +124,17 @@ impl VersionAndIdPdu {
             }
         };
 
+        Self::decode_body(kind, src)
+    }
+
+    /// Decodes the [`VersionAndIdPduKind::ClientAnnounceReply`] following the header.
+    ///
+    /// It shares the packet ID of [`VersionAndIdPduKind::ServerClientIdConfirm`], so the server decodes it explicitly.
+    pub fn decode_client_announce_reply(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        Self::decode_body(VersionAndIdPduKind::ClientAnnounceReply, src)
+    }
+
+    fn decode_body(kind: VersionAndIdPduKind, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(ctx: kind.name(), in: src, size: Self::FIXED_PART_SIZE);
         let version_major = src.read_u16();
         let version_minor = src.read_u16();
@@ -145,13 +184,33 @@ impl ClientNameRequest {
         }
     }
 
-    fn computer_name(&self) -> &str {
+    pub fn computer_name(&self) -> &str {
         match self {
             ClientNameRequest::Ascii(name) => name,
             ClientNameRequest::Unicode(name) => name,
         }
     }
 
+    pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: Self::FIXED_PART_SIZE);
+        let unicode_flag = src.read_u32();
+        let _code_page = src.read_u32();
+        let computer_name_length = cast_length!("ClientNameRequest", "ComputerNameLen", src.read_u32())?;
+
+        ensure_size!(ctx: Self::NAME, in: src, size: computer_name_length);
+        let computer_name = src.read_slice(computer_name_length);
+
+        // Only the lowest bit is meaningful, the others are not always zeroed by clients.
+        let kind = if unicode_flag & 0x1 == 0 {
+            ClientNameRequestUnicodeFlag::Ascii
+        } else {
+            ClientNameRequestUnicodeFlag::Unicode
+        };
+        let computer_name = decode_string(computer_name, kind.into(), true)?;
+
+        Ok(Self::new(computer_name, kind))
+    }
+
     pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size());
 
@@ -225,6 +284,16 @@ impl CoreCapability {
         }
     }
 
+    /// Creates a new [`DR_CORE_CAPABILITY_REQ`] with the given `capabilities`.
+    ///
+    /// [`DR_CORE_CAPABILITY_REQ`]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpefs/702789c3-b924-4bc2-9280-3221bc7d6797
+    pub fn new_request(capabilities: Vec<CapabilityMessage>) -> Self {
+        Self {
+            capabilities,
+            kind: CoreCapabilityKind::ServerCoreCapabilityRequest,
+        }
+    }
+
     pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(ctx: self.name(), in: dst, size: self.size());
         dst.write_u16(cast_length!(
@@ -317,6 +386,10 @@ impl Capabilities {
         self.push(CapabilityMessage::new_drive());
     }
 
+    pub fn add_printer(&mut self) {
+        self.push(CapabilityMessage::new_printer());
+    }
+
     fn add_general(&mut self, special_type_device_cap: u32) {
         self.push(CapabilityMessage::new_general(special_type_device_cap));
     }
@@ -404,6 +477,33 @@ impl CapabilityMessage {
         }
     }
 
+    /// Creates a new `PRINTER_CAPS_SET`.
+    pub fn new_printer() -> Self {
+        Self {
+            header: CapabilityHeader::new_printer(),
+            capability_data: CapabilityData::Printer,
+        }
+    }
+
+    /// Whether this is a [`DRIVE_CAPS_SET`], advertising the support of the file system redirection.
+    ///
+    /// [`DRIVE_CAPS_SET`]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpefs/4f018cd2-60ba-4c7b-adcf-55bd05cea6f8
+    pub fn is_drive(&self) -> bool {
+        matches!(self.capability_data, CapabilityData::Drive)
+    }
+
+    /// Whether this is a [`SMARTCARD_CAPS_SET`], advertising the support of the smart card redirection.
+    ///
+    /// [`SMARTCARD_CAPS_SET`]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpefs/e02de60a-4d32-4dc7-ab17-9d591129eb93
+    pub fn is_smartcard(&self) -> bool {
+        matches!(self.capability_data, CapabilityData::Smartcard)
+    }
+
+    /// Whether this is a `PRINTER_CAPS_SET`, advertising the support of the printer redirection.
+    pub fn is_printer(&self) -> bool {
+        matches!(self.capability_data, CapabilityData::Printer)
+    }
+
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size());
         self.header.encode(dst)?;
@@ -462,6 +562,14 @@ impl CapabilityHeader {
         }
     }
 
+    fn new_printer() -> Self {
+        Self {
+            cap_type: CapabilityType::Printer,
+            length: u16::try_from(Self::SIZE).expect("value fits into u16"),
+            version: PRINT_CAPABILITY_VERSION_01,
+        }
+    }
+
     fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(in: src, size: Self::SIZE);
         let cap_type: CapabilityType = src.read_u16().try_into()?;
@@ -515,6 +623,8 @@ pub const GENERAL_CAPABILITY_VERSION_02: u32 = 0x0000_0002;
 pub const SMARTCARD_CAPABILITY_VERSION_01: u32 = 0x0000_0001;
 /// DRIVE_CAPABILITY_VERSION_02
 pub const DRIVE_CAPABILITY_VERSION_02: u32 = 0x0000_0002;
+/// PRINT_CAPABILITY_VERSION_01
+pub const PRINT_CAPABILITY_VERSION_01: u32 = 0x0000_0001;
 
 impl TryFrom<u16> for CapabilityType {
     type Error = DecodeError;
@@ -773,6 +883,13 @@ impl ClientDeviceListAnnounce {
         }
     }
 
+    /// Library users should not typically call this directly, use [`Rdpdr::add_printer`] instead.
+    pub(crate) fn new_printer(device_id: u32, printer: &PrinterDeviceAnnounce) -> EncodeResult<Self> {
+        Ok(Self {
+            device_list: vec![DeviceAnnounceHeader::new_printer(device_id, printer)?],
+        })
+    }
+
     pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         dst.write_u32(cast_length!(
             "ClientDeviceListAnnounce",
@@ -787,6 +904,18 @@ impl ClientDeviceListAnnounce {
         Ok(())
     }
 
+    pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: "DR_CORE_DEVICELIST_ANNOUNCE_REQ", in: src, size: Self::FIXED_PART_SIZE);
+        let device_count = src.read_u32();
+
+        let mut device_list = Vec::new();
+        for _ in 0..device_count {
+            device_list.push(DeviceAnnounceHeader::decode(src)?);
+        }
+
+        Ok(Self { device_list })
+    }
+
     pub fn name(&self) -> &'static str {
         "DR_CORE_DEVICELIST_ANNOUNCE_REQ"
     }
@@ -827,6 +956,19 @@ impl ClientDeviceListRemove {
         Ok(())
     }
 
+    pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: "DR_DEVICELIST_REMOVE", in: src, size: Self::FIXED_PART_SIZE);
+        let device_count: usize = cast_length!("ClientDeviceListRemove", "DeviceCount", src.read_u32())?;
+
+        ensure_size!(ctx: "DR_DEVICELIST_REMOVE", in: src, size: device_count * size_of::<u32>());
+        let mut device_list = Vec::with_capacity(device_count);
+        for _ in 0..device_count {
+            device_list.push(src.read_u32());
+        }
+
+        Ok(Self { device_list })
+    }
+
     pub fn name(&self) -> &'static str {
         "DR_DEVICELIST_REMOVE"
     }
@@ -852,6 +994,11 @@ impl Devices {
         self.push(DeviceAnnounceHeader::new_drive(device_id, name));
     }
 
+    pub fn add_printer(&mut self, device_id: u32, printer: &PrinterDeviceAnnounce) -> EncodeResult<()> {
+        self.push(DeviceAnnounceHeader::new_printer(device_id, printer)?);
+        Ok(())
+    }
+
     pub fn remove_device(&mut self, device_id: u32) -> Option<u32> {
         self.remove(device_id)
     }
@@ -939,6 +1086,19 @@ impl DeviceAnnounceHeader {
         }
     }
 
+    fn new_printer(device_id: u32, printer: &PrinterDeviceAnnounce) -> EncodeResult<Self> {
+        let mut device_data = vec![0; printer.size()];
+        printer.encode(&mut WriteCursor::new(&mut device_data))?;
+
+        Ok(Self {
+            device_type: DeviceType::Print,
+            device_id,
+            // The server names the printers after the PrintName of the device data.
+            preferred_dos_name: PreferredDosName(format!("PRN{device_id}")),
+            device_data,
+        })
+    }
+
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         dst.write_u32(self.device_type.into());
         dst.write_u32(self.device_id);
@@ -952,9 +1112,54 @@ impl DeviceAnnounceHeader {
         Ok(())
     }
 
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: "DEVICE_ANNOUNCE", in: src, size: Self::FIXED_PART_SIZE);
+        let device_type = DeviceType::try_from(src.read_u32())?;
+        let device_id = src.read_u32();
+        let preferred_dos_name = PreferredDosName::decode(src)?;
+        let device_data_length = cast_length!("DeviceAnnounceHeader", "DeviceDataLength", src.read_u32())?;
+
+        ensure_size!(ctx: "DEVICE_ANNOUNCE", in: src, size: device_data_length);
+        let device_data = src.read_slice(device_data_length).to_vec();
+
+        Ok(Self {
+            device_type,
+            device_id,
+            preferred_dos_name,
+            device_data,
+        })
+    }
+
     fn size(&self) -> usize {
         Self::FIXED_PART_SIZE + self.device_data.len()
     }
+
+    pub fn device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    /// The device-specific data, e.g. a [`PrinterDeviceAnnounce`] for printers.
+    pub fn device_data(&self) -> &[u8] {
+        &self.device_data
+    }
+
+    /// The name of the device as displayed to the user.
+    ///
+    /// For drives, this is the full name found in the DeviceData field when present, the PreferredDosName otherwise.
+    pub fn display_name(&self) -> String {
+        if self.device_type == DeviceType::Filesystem && !self.device_data.is_empty() {
+            // The spec says Unicode, but clients send null terminated UTF-8 (see `new_drive`).
+            if let Ok(name) = decode_string(&self.device_data, CharacterSet::Ansi, true) {
+                return name;
+            }
+        }
+
+        self.preferred_dos_name.0.clone()
+    }
 }
 
 /// From ["PreferredDosName"](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpefs/32e34332-774b-4ead-8c9d-5d64720d6bf9):
@@ -976,6 +1181,18 @@ impl PreferredDosName {
         write_string_to_cursor(dst, &self.format(), CharacterSet::Ansi, false)
     }
 
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: "PreferredDosName", in: src, size: 8);
+        let name = src
+            .read_slice(8)
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| char::from(c))
+            .collect();
+
+        Ok(Self(name))
+    }
+
     /// Returns the underlying String with a maximum length of 7 characters plus a null terminator.
     fn format(&self) -> String {
         let mut name: &str = &self.0;
@@ -1501,6 +1718,52 @@ impl ServerDriveIoRequest {
             MajorFunction::LockControl => Ok(ServerDriveLockControlRequest::decode(dev_io_req, src)?.into()),
         }
     }
+
+    /// Encodes the request, as sent by the server.
+    ///
+    /// Only the requests used by [`crate::server::RdpdrServer`] can be encoded.
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        match self {
+            Self::ServerCreateDriveRequest(req) => req.encode(dst),
+            Self::DeviceCloseRequest(req) => req.encode(dst),
+            Self::ServerDriveQueryDirectoryRequest(req) => req.encode(dst),
+            Self::DeviceReadRequest(req) => req.encode(dst),
+            Self::DeviceWriteRequest(req) => req.encode(dst),
+            _ => Err(unsupported_value_err!(
+                "ServerDriveIoRequest::encode",
+                "ServerDriveIoRequest",
+                self.name().to_owned()
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ServerCreateDriveRequest(req) => req.name(),
+            Self::ServerDriveQueryInformationRequest(_) => "DR_DRIVE_QUERY_INFORMATION_REQ",
+            Self::DeviceCloseRequest(req) => req.name(),
+            Self::ServerDriveQueryDirectoryRequest(req) => req.name(),
+            Self::ServerDriveNotifyChangeDirectoryRequest(_) => "DR_DRIVE_NOTIFY_CHANGE_DIRECTORY_REQ",
+            Self::ServerDriveQueryVolumeInformationRequest(_) => "DR_DRIVE_QUERY_VOLUME_INFORMATION_REQ",
+            Self::DeviceControlRequest(_) => "DR_CONTROL_REQ",
+            Self::DeviceReadRequest(req) => req.name(),
+            Self::DeviceWriteRequest(req) => req.name(),
+            Self::ServerDriveSetInformationRequest(_) => "DR_DRIVE_SET_INFORMATION_REQ",
+            Self::ServerDriveLockControlRequest(_) => "DR_DRIVE_LOCK_REQ",
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Self::ServerCreateDriveRequest(req) => req.size(),
+            Self::DeviceCloseRequest(req) => req.size(),
+            Self::ServerDriveQueryDirectoryRequest(req) => req.size(),
+            Self::DeviceReadRequest(req) => req.size(),
+            Self::DeviceWriteRequest(req) => req.size(),
+            // Not encodable, see `encode`.
+            _ => 0,
+        }
+    }
 }
 
 impl From<DeviceCreateRequest> for ServerDriveIoRequest {
@@ -1596,7 +1859,7 @@ impl DeviceCreateRequest {
                                  + 4  // CreateOptions
                                  + 4; // PathLength
 
-    fn decode(dev_io_req: DeviceIoRequest, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+    pub fn decode(dev_io_req: DeviceIoRequest, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(ctx: "DeviceCreateRequest", in: src, size: Self::FIXED_PART_SIZE);
         let desired_access = DesiredAccess::from_bits_retain(src.read_u32());
         let allocation_size = src.read_u64();
@@ -1622,6 +1885,31 @@ impl DeviceCreateRequest {
             path,
         })
     }
+
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        self.device_io_request.encode(dst)?;
+        dst.write_u32(self.desired_access.bits());
+        dst.write_u64(self.allocation_size);
+        dst.write_u32(self.file_attributes.bits());
+        dst.write_u32(self.shared_access.bits());
+        dst.write_u32(self.create_disposition.bits());
+        dst.write_u32(self.create_options.bits());
+        dst.write_u32(cast_length!(
+            "DeviceCreateRequest",
+            "path_length",
+            encoded_str_len(&self.path, CharacterSet::Unicode, true)
+        )?);
+        write_string_to_cursor(dst, &self.path, CharacterSet::Unicode, true)
+    }
+
+    pub fn name(&self) -> &'static str {
+        "DR_CREATE_REQ"
+    }
+
+    pub fn size(&self) -> usize {
+        self.device_io_request.size() + Self::FIXED_PART_SIZE + encoded_str_len(&self.path, CharacterSet::Unicode, true)
+    }
 }
 
 bitflags! {
@@ -1807,6 +2095,24 @@ impl DeviceCreateResponse {
         + 4 // FileId
         + 1 // Information
     }
+
+    pub fn decode(device_io_reply: DeviceIoResponse, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 4);
+        let file_id = src.read_u32();
+        // Information (1 byte): [...] If the IoStatus field is set to 0x00000000, this field MAY be skipped, in which
+        // case the server MUST assume that the Information field was set to 0x00.
+        let information = if src.is_empty() {
+            Information::FILE_SUPERSEDED
+        } else {
+            Information::from_bits_retain(src.read_u8())
+        };
+
+        Ok(Self {
+            device_io_reply,
+            file_id,
+            information,
+        })
+    }
 }
 
 bitflags! {
@@ -1851,7 +2157,7 @@ impl ServerDriveQueryInformationRequest {
 /// [2.4] File Information Classes \[MS-FSCC\]
 ///
 /// [2.4]: https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-fscc/4718fc40-e539-4014-8e33-b675af74e3e1
-#[derive(PartialEq, Eq, Clone)]
+#[derive(PartialEq, Eq, Clone, Copy)]
 pub struct FileInformationClassLevel(u32);
 
 impl FileInformationClassLevel {
@@ -2506,6 +2812,8 @@ pub struct FileDirectoryInformation {
 }
 
 impl FileDirectoryInformation {
+    const FIXED_PART_SIZE: usize = 4 /* NextEntryOffset */ + 4 /* FileIndex */ + 8 * 6 /* times and sizes */ + 4 /* FileAttributes */ + 4 /* FileNameLength */;
+
     pub fn new(
         creation_time: i64,
         last_access_time: i64,
@@ -2551,6 +2859,36 @@ impl FileDirectoryInformation {
         Ok(())
     }
 
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+        let next_entry_offset = src.read_u32();
+        let file_index = src.read_u32();
+        let creation_time = src.read_i64();
+        let last_access_time = src.read_i64();
+        let last_write_time = src.read_i64();
+        let change_time = src.read_i64();
+        let end_of_file = src.read_i64();
+        let allocation_size = src.read_i64();
+        let file_attributes = FileAttributes::from_bits_retain(src.read_u32());
+        let file_name_length = cast_length!("FileDirectoryInformation", "file_name_length", src.read_u32())?;
+
+        ensure_size!(in: src, size: file_name_length);
+        let file_name = decode_string(src.read_slice(file_name_length), CharacterSet::Unicode, false)?;
+
+        Ok(Self {
+            next_entry_offset,
+            file_index,
+            creation_time,
+            last_access_time,
+            last_write_time,
+            change_time,
+            end_of_file,
+            allocation_size,
+            file_attributes,
+            file_name,
+        })
+    }
+
     fn size(&self) -> usize {
         4 // NextEntryOffset
         + 4 // FileIndex
@@ -2577,11 +2915,28 @@ pub struct DeviceCloseRequest {
 }
 
 impl DeviceCloseRequest {
+    const PADDING_SIZE: usize = 32;
+
     pub fn decode(dev_io_req: DeviceIoRequest) -> Self {
         Self {
             device_io_request: dev_io_req,
         }
     }
+
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        self.device_io_request.encode(dst)?;
+        write_padding!(dst, Self::PADDING_SIZE);
+        Ok(())
+    }
+
+    pub fn name(&self) -> &'static str {
+        "DR_CLOSE_REQ"
+    }
+
+    pub fn size(&self) -> usize {
+        self.device_io_request.size() + Self::PADDING_SIZE
+    }
 }
 
 /// [2.2.1.5.2] Device Close Response (DR_CLOSE_RSP)
@@ -2611,6 +2966,15 @@ impl DeviceCloseResponse {
         self.device_io_response.size() // DeviceIoResponse
         + 4 // Padding
     }
+
+    pub fn decode(device_io_response: DeviceIoResponse, src: &mut ReadCursor<'_>) -> Self {
+        // The padding is ignored, and not required.
+        if src.len() >= 4 {
+            read_padding!(src, 4);
+        }
+
+        Self { device_io_response }
+    }
 }
 
 /// [2.2.3.3.10] Server Drive Query Directory Request (DR_DRIVE_QUERY_DIRECTORY_REQ)
@@ -2661,6 +3025,28 @@ impl ServerDriveQueryDirectoryRequest {
             path,
         })
     }
+
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        self.device_io_request.encode(dst)?;
+        dst.write_u32(self.file_info_class_lvl.into());
+        dst.write_u8(self.initial_query);
+        dst.write_u32(cast_length!(
+            "ServerDriveQueryDirectoryRequest",
+            "path_length",
+            encoded_str_len(&self.path, CharacterSet::Unicode, true)
+        )?);
+        write_padding!(dst, 23);
+        write_string_to_cursor(dst, &self.path, CharacterSet::Unicode, true)
+    }
+
+    pub fn name(&self) -> &'static str {
+        "DR_DRIVE_QUERY_DIRECTORY_REQ"
+    }
+
+    pub fn size(&self) -> usize {
+        self.device_io_request.size() + Self::FIXED_PART_SIZE + encoded_str_len(&self.path, CharacterSet::Unicode, true)
+    }
 }
 
 /// 2.2.3.3.11 Server Drive NotifyChange Directory Request (DR_DRIVE_NOTIFY_CHANGE_DIRECTORY_REQ)
@@ -2732,6 +3118,42 @@ impl ClientDriveQueryDirectoryResponse {
             1 // Padding: https://github.com/FreeRDP/FreeRDP/blob/511444a65e7aa2f537c5e531fa68157a50c1bd4d/channels/drive/client/drive_file.c#L937
         }
     }
+
+    /// Decodes the response to a [`ServerDriveQueryDirectoryRequest`] of the given level.
+    ///
+    /// Only [`FileInformationClassLevel::FILE_DIRECTORY_INFORMATION`] buffers are decoded, the other levels are rejected.
+    pub fn decode(
+        device_io_reply: DeviceIoResponse,
+        file_info_class_lvl: FileInformationClassLevel,
+        src: &mut ReadCursor<'_>,
+    ) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 4);
+        let length = cast_length!("ClientDriveQueryDirectoryResponse", "length", src.read_u32())?;
+
+        if length == 0 {
+            // Padding (1 byte): optional, and ignored.
+            return Ok(Self {
+                device_io_reply,
+                buffer: None,
+            });
+        }
+
+        if file_info_class_lvl != FileInformationClassLevel::FILE_DIRECTORY_INFORMATION {
+            return Err(unsupported_value_err!(
+                "ClientDriveQueryDirectoryResponse::decode",
+                "FileInformationClassLevel",
+                file_info_class_lvl.to_string()
+            ));
+        }
+
+        ensure_size!(ctx: Self::NAME, in: src, size: length);
+        let buffer = FileDirectoryInformation::decode(&mut ReadCursor::new(src.read_slice(length)))?;
+
+        Ok(Self {
+            device_io_reply,
+            buffer: Some(FileInformationClass::Directory(buffer)),
+        })
+    }
 }
 
 /// [2.2.3.3.6] Server Drive Query Volume Information Request
@@ -3172,6 +3594,23 @@ impl DeviceReadRequest {
             offset,
         })
     }
+
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        self.device_io_request.encode(dst)?;
+        dst.write_u32(self.length);
+        dst.write_u64(self.offset);
+        write_padding!(dst, 20);
+        Ok(())
+    }
+
+    pub fn name(&self) -> &'static str {
+        "DR_READ_REQ"
+    }
+
+    pub fn size(&self) -> usize {
+        self.device_io_request.size() + Self::FIXED_PART_SIZE
+    }
 }
 
 /// [2.2.1.5.3] Device Read Response (DR_READ_RSP)
@@ -3202,6 +3641,19 @@ impl DeviceReadResponse {
         + 4 // Length
         + self.read_data.len() // ReadData
     }
+
+    pub fn decode(device_io_reply: DeviceIoResponse, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 4);
+        let length = cast_length!("DeviceReadResponse", "length", src.read_u32())?;
+
+        ensure_size!(ctx: Self::NAME, in: src, size: length);
+        let read_data = src.read_slice(length).to_vec();
+
+        Ok(Self {
+            device_io_reply,
+            read_data,
+        })
+    }
 }
 
 impl Debug for DeviceReadResponse {
@@ -3242,6 +3694,24 @@ impl DeviceWriteRequest {
             write_data,
         })
     }
+
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        self.device_io_request.encode(dst)?;
+        dst.write_u32(cast_length!("DeviceWriteRequest", "length", self.write_data.len())?);
+        dst.write_u64(self.offset);
+        write_padding!(dst, 20);
+        dst.write_slice(&self.write_data);
+        Ok(())
+    }
+
+    pub fn name(&self) -> &'static str {
+        "DR_WRITE_REQ"
+    }
+
+    pub fn size(&self) -> usize {
+        self.device_io_request.size() + Self::FIXED_PART_SIZE + self.write_data.len()
+    }
 }
 
 impl Debug for DeviceWriteRequest {
@@ -3283,6 +3753,17 @@ impl DeviceWriteResponse {
         + 4 // Length
         + 1 // Padding
     }
+
+    pub fn decode(device_io_reply: DeviceIoResponse, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 4);
+        let length = src.read_u32();
+        // Padding (1 byte): optional, and ignored.
+
+        Ok(Self {
+            device_io_reply,
+            length,
+        })
+    }
 }
 
 /// [2.2.3.3.9] Server Drive Set Information Request (DR_DRIVE_SET_INFORMATION_REQ)
@@ -0,0 +1,165 @@
+//! PDUs for [MS-RDPEPC]: Remote Desktop Protocol: Print Virtual Channel Extension
+//!
+//! Print jobs don't have dedicated PDUs: a job is opened with a Device Create Request, its data is sent with Device
+//! Write Requests and it's ended with a Device Close Request, see [`crate::pdu::efs`].
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, DecodeResult, EncodeResult, ReadCursor, WriteCursor,
+};
+use ironrdp_pdu::utils::{decode_string, encoded_str_len, write_string_to_cursor, CharacterSet};
+
+bitflags! {
+    /// Flags of a [`PrinterDeviceAnnounce`]
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct PrinterFlags: u32 {
+        /// The names are ASCII strings, Unicode otherwise
+        const RDPDR_PRINTER_ANNOUNCE_FLAG_ASCII = 0x0000_0001;
+        /// The printer is the default printer of the client
+        const RDPDR_PRINTER_ANNOUNCE_FLAG_DEFAULTPRINTER = 0x0000_0002;
+        /// The printer is a network printer
+        const RDPDR_PRINTER_ANNOUNCE_FLAG_NETWORKPRINTER = 0x0000_0004;
+        /// The printer uses the TS Easy Print driver
+        const RDPDR_PRINTER_ANNOUNCE_FLAG_TSPRINTER = 0x0000_0008;
+        /// The printer accepts XPS documents
+        const RDPDR_PRINTER_ANNOUNCE_FLAG_XPSFORMAT = 0x0000_0010;
+    }
+}
+
+/// [MS-RDPEPC] 2.2.2.1 Client Device List Announce Request (DR_PRN_DEVICE_ANNOUNCE)
+///
+/// The device data of a printer announced in a [`crate::pdu::efs::ClientDeviceListAnnounce`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PrinterDeviceAnnounce {
+    pub flags: PrinterFlags,
+    /// Code page of the names, ignored unless [`PrinterFlags::RDPDR_PRINTER_ANNOUNCE_FLAG_ASCII`] is set
+    pub code_page: u32,
+    /// Plug and Play identifier of the printer
+    pub pnp_name: String,
+    /// Name of the printer driver, e.g. `MS Publisher Imagesetter` for PostScript output
+    pub driver_name: String,
+    /// Name of the printer, as displayed by the client
+    pub print_name: String,
+    /// Printer configuration cached by the server, opaque to the client
+    pub cached_fields: Vec<u8>,
+}
+
+impl PrinterDeviceAnnounce {
+    const NAME: &'static str = "DR_PRN_DEVICE_ANNOUNCE";
+
+    const FIXED_PART_SIZE: usize = 4 /* Flags */ + 4 /* CodePage */ + 4 /* PnPNameLen */ + 4 /* DriverNameLen */
+        + 4 /* PrintNameLen */ + 4 /* CachedFieldsLen */;
+
+    /// Announces a printer with Unicode names.
+    pub fn new(print_name: String, driver_name: String) -> Self {
+        Self {
+            flags: PrinterFlags::empty(),
+            code_page: 0,
+            pnp_name: String::new(),
+            driver_name,
+            print_name,
+            cached_fields: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        let charset = names_charset(self.flags);
+        dst.write_u32(self.flags.bits());
+        dst.write_u32(self.code_page);
+        dst.write_u32(cast_length!(
+            Self::NAME,
+            "PnPNameLen",
+            encoded_name_len(&self.pnp_name, charset)
+        )?);
+        dst.write_u32(cast_length!(
+            Self::NAME,
+            "DriverNameLen",
+            encoded_name_len(&self.driver_name, charset)
+        )?);
+        dst.write_u32(cast_length!(
+            Self::NAME,
+            "PrintNameLen",
+            encoded_name_len(&self.print_name, charset)
+        )?);
+        dst.write_u32(cast_length!(Self::NAME, "CachedFieldsLen", self.cached_fields.len())?);
+
+        for name in [&self.pnp_name, &self.driver_name, &self.print_name] {
+            if !name.is_empty() {
+                write_string_to_cursor(dst, name, charset, true)?;
+            }
+        }
+        dst.write_slice(&self.cached_fields);
+
+        Ok(())
+    }
+
+    pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let flags = PrinterFlags::from_bits_retain(src.read_u32());
+        let code_page = src.read_u32();
+        let pnp_name_len = cast_length!(Self::NAME, "PnPNameLen", src.read_u32())?;
+        let driver_name_len = cast_length!(Self::NAME, "DriverNameLen", src.read_u32())?;
+        let print_name_len = cast_length!(Self::NAME, "PrintNameLen", src.read_u32())?;
+        let cached_fields_len = cast_length!(Self::NAME, "CachedFieldsLen", src.read_u32())?;
+
+        let charset = names_charset(flags);
+        let pnp_name = decode_name(src, pnp_name_len, charset)?;
+        let driver_name = decode_name(src, driver_name_len, charset)?;
+        let print_name = decode_name(src, print_name_len, charset)?;
+
+        ensure_size!(in: src, size: cached_fields_len);
+        let cached_fields = src.read_slice(cached_fields_len).to_vec();
+
+        Ok(Self {
+            flags,
+            code_page,
+            pnp_name,
+            driver_name,
+            print_name,
+            cached_fields,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        let charset = names_charset(self.flags);
+
+        Self::FIXED_PART_SIZE
+            + encoded_name_len(&self.pnp_name, charset)
+            + encoded_name_len(&self.driver_name, charset)
+            + encoded_name_len(&self.print_name, charset)
+            + self.cached_fields.len()
+    }
+}
+
+fn names_charset(flags: PrinterFlags) -> CharacterSet {
+    if flags.contains(PrinterFlags::RDPDR_PRINTER_ANNOUNCE_FLAG_ASCII) {
+        CharacterSet::Ansi
+    } else {
+        CharacterSet::Unicode
+    }
+}
+
+/// Names are null-terminated, an empty name is omitted.
+fn encoded_name_len(name: &str, charset: CharacterSet) -> usize {
+    if name.is_empty() {
+        0
+    } else {
+        encoded_str_len(name, charset, true)
+    }
+}
+
+fn decode_name(src: &mut ReadCursor<'_>, len: usize, charset: CharacterSet) -> DecodeResult<String> {
+    if len == 0 {
+        return Ok(String::new());
+    }
+
+    ensure_size!(ctx: PrinterDeviceAnnounce::NAME, in: src, size: len);
+    decode_string(src.read_slice(len), charset, true)
+}
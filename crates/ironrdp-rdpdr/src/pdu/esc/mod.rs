@@ -7,15 +7,15 @@ pub mod rpce;
 
 use bitflags::bitflags;
 use ironrdp_core::{
-    cast_length, ensure_size, invalid_field_err, other_err, DecodeError, DecodeResult, EncodeResult, ReadCursor,
-    WriteCursor,
+    cast_length, ensure_size, invalid_field_err, other_err, write_padding, DecodeError, DecodeResult, EncodeResult,
+    ReadCursor, WriteCursor,
 };
 use ironrdp_pdu::utils::{
     encoded_multistring_len, read_multistring_from_cursor, write_multistring_to_cursor, CharacterSet,
 };
 use tracing::{error, warn};
 
-use super::efs::IoCtlCode;
+use super::efs::{DeviceIoRequest, IoCtlCode};
 use crate::pdu::esc::ndr::{Decode as _, Encode as _};
 
 /// [2.2.2] TS Server-Generated Structures
@@ -109,6 +109,181 @@ impl ScardCall {
             }
         }
     }
+
+    /// Whether the call can be sent with `io_ctl_code`.
+    ///
+    /// Strings are always encoded as Unicode, so only the `W` variants of the IOCTLs taking strings are valid.
+    pub fn matches_io_ctl_code(&self, io_ctl_code: ScardIoCtlCode) -> bool {
+        matches!(
+            (self, io_ctl_code),
+            (Self::AccessStartedEventCall(_), ScardIoCtlCode::AccessStartedEvent)
+                | (Self::EstablishContextCall(_), ScardIoCtlCode::EstablishContext)
+                | (Self::ListReadersCall(_), ScardIoCtlCode::ListReadersW)
+                | (Self::GetStatusChangeCall(_), ScardIoCtlCode::GetStatusChangeW)
+                | (Self::ConnectCall(_), ScardIoCtlCode::ConnectW)
+                | (
+                    Self::HCardAndDispositionCall(_),
+                    ScardIoCtlCode::BeginTransaction | ScardIoCtlCode::EndTransaction | ScardIoCtlCode::Disconnect
+                )
+                | (Self::TransmitCall(_), ScardIoCtlCode::Transmit)
+                | (Self::StatusCall(_), ScardIoCtlCode::StatusW)
+                | (
+                    Self::ContextCall(_),
+                    ScardIoCtlCode::ReleaseContext | ScardIoCtlCode::IsValidContext | ScardIoCtlCode::Cancel
+                )
+                | (Self::GetDeviceTypeIdCall(_), ScardIoCtlCode::GetDeviceTypeId)
+                | (Self::ReadCacheCall(_), ScardIoCtlCode::ReadCacheW)
+                | (Self::WriteCacheCall(_), ScardIoCtlCode::WriteCacheW)
+                | (Self::GetReaderIconCall(_), ScardIoCtlCode::GetReaderIcon)
+        )
+    }
+
+    /// Returns the encodable [MS-RPCE] message of the call, `None` for [`ScardCall::Unsupported`].
+    pub fn into_pdu(self) -> Option<Box<dyn rpce::Encode>> {
+        let pdu: Box<dyn rpce::Encode> = match self {
+            Self::AccessStartedEventCall(call) => Box::new(call),
+            Self::EstablishContextCall(call) => Box::new(rpce::Pdu(call)),
+            Self::ListReadersCall(call) => Box::new(rpce::Pdu(call)),
+            Self::GetStatusChangeCall(call) => Box::new(rpce::Pdu(call)),
+            Self::ConnectCall(call) => Box::new(rpce::Pdu(call)),
+            Self::HCardAndDispositionCall(call) => Box::new(rpce::Pdu(call)),
+            Self::TransmitCall(call) => Box::new(rpce::Pdu(call)),
+            Self::StatusCall(call) => Box::new(rpce::Pdu(call)),
+            Self::ContextCall(call) => Box::new(rpce::Pdu(call)),
+            Self::GetDeviceTypeIdCall(call) => Box::new(rpce::Pdu(call)),
+            Self::ReadCacheCall(call) => Box::new(rpce::Pdu(call)),
+            Self::WriteCacheCall(call) => Box::new(rpce::Pdu(call)),
+            Self::GetReaderIconCall(call) => Box::new(rpce::Pdu(call)),
+            Self::Unsupported => return None,
+        };
+
+        Some(pdu)
+    }
+}
+
+/// [MS-RDPEFS] 2.2.1.4.5 Device Control Request (DR_CONTROL_REQ) carrying a smart card call, as sent by the server
+#[derive(Debug)]
+pub struct ScardControlRequest {
+    pub header: DeviceIoRequest,
+    pub output_buffer_length: u32,
+    pub io_control_code: ScardIoCtlCode,
+    /// The call, see [`ScardCall::into_pdu`]
+    pub call: Box<dyn rpce::Encode>,
+}
+
+impl ScardControlRequest {
+    const NAME: &'static str = "DR_CONTROL_REQ";
+
+    const FIXED_PART_SIZE: usize = 4 // OutputBufferLength
+        + 4 // InputBufferLength
+        + 4 // IoControlCode
+        + 20; // Padding
+
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(ctx: Self::NAME, in: dst, size: self.size());
+        self.header.encode(dst)?;
+        dst.write_u32(self.output_buffer_length);
+        dst.write_u32(cast_length!(Self::NAME, "InputBufferLength", self.call.size())?);
+        dst.write_u32(self.io_control_code.into());
+        write_padding!(dst, 20);
+        self.call.encode(dst)
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    pub fn size(&self) -> usize {
+        self.header.size() + Self::FIXED_PART_SIZE + self.call.size()
+    }
+}
+
+/// Return of a smart card call, one of the client-generated structures of [MS-RDPESC] 2.2.3
+#[derive(Debug, PartialEq, Clone)]
+pub enum ScardReturn {
+    LongReturn(LongReturn),
+    EstablishContextReturn(EstablishContextReturn),
+    ListReadersReturn(ListReadersReturn),
+    GetStatusChangeReturn(GetStatusChangeReturn),
+    ConnectReturn(ConnectReturn),
+    TransmitReturn(TransmitReturn),
+    StatusReturn(StatusReturn),
+    GetDeviceTypeIdReturn(GetDeviceTypeIdReturn),
+    ReadCacheReturn(ReadCacheReturn),
+    GetReaderIconReturn(GetReaderIconReturn),
+}
+
+impl ScardReturn {
+    /// Decodes the return of a call sent with `io_ctl_code`.
+    ///
+    /// Only the return code is meaningful when the call failed, so failures are always decoded as a
+    /// [`ScardReturn::LongReturn`].
+    pub fn decode(io_ctl_code: ScardIoCtlCode, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        let long_return = rpce::Pdu::<LongReturn>::decode(&mut src.clone(), None)?.into_inner();
+        if long_return.return_code != ReturnCode::Success {
+            return Ok(Self::LongReturn(long_return));
+        }
+
+        match io_ctl_code {
+            ScardIoCtlCode::AccessStartedEvent
+            | ScardIoCtlCode::ReleaseContext
+            | ScardIoCtlCode::IsValidContext
+            | ScardIoCtlCode::Cancel
+            | ScardIoCtlCode::BeginTransaction
+            | ScardIoCtlCode::EndTransaction
+            | ScardIoCtlCode::Disconnect
+            | ScardIoCtlCode::WriteCacheA
+            | ScardIoCtlCode::WriteCacheW => Ok(Self::LongReturn(rpce::Pdu::decode(src, None)?.into_inner())),
+            ScardIoCtlCode::EstablishContext => {
+                Ok(Self::EstablishContextReturn(rpce::Pdu::decode(src, None)?.into_inner()))
+            }
+            ScardIoCtlCode::ListReadersA => Ok(Self::ListReadersReturn(
+                rpce::Pdu::decode(src, Some(CharacterSet::Ansi))?.into_inner(),
+            )),
+            ScardIoCtlCode::ListReadersW => Ok(Self::ListReadersReturn(
+                rpce::Pdu::decode(src, Some(CharacterSet::Unicode))?.into_inner(),
+            )),
+            ScardIoCtlCode::GetStatusChangeA | ScardIoCtlCode::GetStatusChangeW => {
+                Ok(Self::GetStatusChangeReturn(rpce::Pdu::decode(src, None)?.into_inner()))
+            }
+            ScardIoCtlCode::ConnectA | ScardIoCtlCode::ConnectW => {
+                Ok(Self::ConnectReturn(rpce::Pdu::decode(src, None)?.into_inner()))
+            }
+            ScardIoCtlCode::Transmit => Ok(Self::TransmitReturn(rpce::Pdu::decode(src, None)?.into_inner())),
+            ScardIoCtlCode::StatusA => Ok(Self::StatusReturn(
+                rpce::Pdu::decode(src, Some(CharacterSet::Ansi))?.into_inner(),
+            )),
+            ScardIoCtlCode::StatusW => Ok(Self::StatusReturn(
+                rpce::Pdu::decode(src, Some(CharacterSet::Unicode))?.into_inner(),
+            )),
+            ScardIoCtlCode::GetDeviceTypeId => {
+                Ok(Self::GetDeviceTypeIdReturn(rpce::Pdu::decode(src, None)?.into_inner()))
+            }
+            ScardIoCtlCode::ReadCacheA | ScardIoCtlCode::ReadCacheW => {
+                Ok(Self::ReadCacheReturn(rpce::Pdu::decode(src, None)?.into_inner()))
+            }
+            ScardIoCtlCode::GetReaderIcon => Ok(Self::GetReaderIconReturn(rpce::Pdu::decode(src, None)?.into_inner())),
+            _ => {
+                error!(?io_ctl_code, "Unsupported ScardIoCtlCode");
+                Err(invalid_field_err!("decode", "ScardReturn", "unsupported IoCtlCode"))
+            }
+        }
+    }
+
+    pub fn return_code(&self) -> ReturnCode {
+        match self {
+            Self::LongReturn(ret) => ret.return_code,
+            Self::EstablishContextReturn(ret) => ret.return_code,
+            Self::ListReadersReturn(ret) => ret.return_code,
+            Self::GetStatusChangeReturn(ret) => ret.return_code,
+            Self::ConnectReturn(ret) => ret.return_code,
+            Self::TransmitReturn(ret) => ret.return_code,
+            Self::StatusReturn(ret) => ret.return_code,
+            Self::GetDeviceTypeIdReturn(ret) => ret.return_code,
+            Self::ReadCacheReturn(ret) => ret.return_code,
+            Self::GetReaderIconReturn(ret) => ret.return_code,
+        }
+    }
 }
 
 /// [2.2.1.1] REDIR_SCARDCONTEXT
@@ -212,6 +387,25 @@ impl ndr::Decode for ReaderState {
     }
 }
 
+impl ndr::Encode for ReaderState {
+    fn encode_ptr(&self, index: &mut u32, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ndr::encode_ptr(None, index, dst)?;
+        self.common.encode(dst)
+    }
+
+    fn encode_value(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ndr::write_string_to_cursor(dst, &self.reader, CharacterSet::Unicode)
+    }
+
+    fn size_ptr(&self) -> usize {
+        ndr::ptr_size(false) + ReaderStateCommonCall::size()
+    }
+
+    fn size_value(&self) -> usize {
+        ndr::encoded_string_len(&self.reader, CharacterSet::Unicode)
+    }
+}
+
 /// From [3.1.4] Message Processing Events and Sequencing Rules
 ///
 /// [3.1.4]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/60d5977d-0017-4c90-ab0c-f34bf44a74a5
@@ -377,6 +571,16 @@ impl TryFrom<u32> for ScardIoCtlCode {
     }
 }
 
+impl From<ScardIoCtlCode> for u32 {
+    #[expect(
+        clippy::as_conversions,
+        reason = "guarantees discriminant layout, and as is the only way to cast enum -> primitive"
+    )]
+    fn from(val: ScardIoCtlCode) -> Self {
+        val as u32
+    }
+}
+
 /// Allow [`ScardIoCtlCode`] to be used as an [`IoCtlCode`].
 impl IoCtlCode for ScardIoCtlCode {}
 
@@ -387,18 +591,39 @@ impl IoCtlCode for ScardIoCtlCode {}
 pub struct ScardAccessStartedEventCall;
 
 impl ScardAccessStartedEventCall {
+    const NAME: &'static str = "ScardAccessStartedEvent_Call";
+
     pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ironrdp_pdu::read_padding!(src, 4); // Unused (4 bytes)
         Ok(Self)
     }
 }
 
+/// Unlike the other calls, this one is not an [MS-RPCE] message.
+impl ironrdp_core::Encode for ScardAccessStartedEventCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        write_padding!(dst, 4); // Unused (4 bytes)
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        4
+    }
+}
+
+impl rpce::Encode for ScardAccessStartedEventCall {}
+
 /// [2.2.3.3] Long_Return
 ///
 /// [2.2.3.3]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/e77a1365-2379-4037-99c4-d30d14ba10fc
 #[derive(Debug, PartialEq, Clone)]
 pub struct LongReturn {
-    return_code: ReturnCode,
+    pub return_code: ReturnCode,
 }
 
 impl LongReturn {
@@ -425,6 +650,14 @@ impl rpce::HeaderlessEncode for LongReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for LongReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+        Ok(Self { return_code })
+    }
+}
+
 /// [2.2.8] Return Code
 ///
 /// [2.2.8]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/9861f8da-76fe-41e6-847e-40c9aa35df8d
@@ -571,6 +804,91 @@ impl ReturnCode {
     pub fn size(&self) -> usize {
         size_of::<u32>()
     }
+
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: size_of::<u32>());
+        Self::try_from(src.read_u32())
+    }
+}
+
+impl TryFrom<u32> for ReturnCode {
+    type Error = DecodeError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0x0000_0000 => Ok(ReturnCode::Success),
+            0x8010_0001 => Ok(ReturnCode::InternalError),
+            0x8010_0002 => Ok(ReturnCode::Cancelled),
+            0x8010_0003 => Ok(ReturnCode::InvalidHandle),
+            0x8010_0004 => Ok(ReturnCode::InvalidParameter),
+            0x8010_0005 => Ok(ReturnCode::InvalidTarget),
+            0x8010_0006 => Ok(ReturnCode::NoMemory),
+            0x8010_0007 => Ok(ReturnCode::WaitedTooLong),
+            0x8010_0008 => Ok(ReturnCode::InsufficientBuffer),
+            0x8010_0009 => Ok(ReturnCode::UnknownReader),
+            0x8010_000A => Ok(ReturnCode::Timeout),
+            0x8010_000B => Ok(ReturnCode::SharingViolation),
+            0x8010_000C => Ok(ReturnCode::NoSmartcard),
+            0x8010_000D => Ok(ReturnCode::UnknownCard),
+            0x8010_000E => Ok(ReturnCode::CantDispose),
+            0x8010_000F => Ok(ReturnCode::ProtoMismatch),
+            0x8010_0010 => Ok(ReturnCode::NotReady),
+            0x8010_0011 => Ok(ReturnCode::InvalidValue),
+            0x8010_0012 => Ok(ReturnCode::SystemCancelled),
+            0x8010_0013 => Ok(ReturnCode::CommError),
+            0x8010_0014 => Ok(ReturnCode::UnknownError),
+            0x8010_0015 => Ok(ReturnCode::InvalidAtr),
+            0x8010_0016 => Ok(ReturnCode::NotTransacted),
+            0x8010_0017 => Ok(ReturnCode::ReaderUnavailable),
+            0x8010_0018 => Ok(ReturnCode::Shutdown),
+            0x8010_0019 => Ok(ReturnCode::PciTooSmall),
+            0x8010_0020 => Ok(ReturnCode::IccInstallation),
+            0x8010_0021 => Ok(ReturnCode::IccCreateorder),
+            0x8010_0022 => Ok(ReturnCode::UnsupportedFeature),
+            0x8010_0023 => Ok(ReturnCode::DirNotFound),
+            0x8010_0024 => Ok(ReturnCode::FileNotFound),
+            0x8010_0025 => Ok(ReturnCode::NoDir),
+            0x8010_001A => Ok(ReturnCode::ReaderUnsupported),
+            0x8010_001B => Ok(ReturnCode::DuplicateReader),
+            0x8010_001C => Ok(ReturnCode::CardUnsupported),
+            0x8010_001D => Ok(ReturnCode::NoService),
+            0x8010_001E => Ok(ReturnCode::ServiceStopped),
+            0x8010_001F => Ok(ReturnCode::Unexpected),
+            0x8010_0026 => Ok(ReturnCode::NoFile),
+            0x8010_0027 => Ok(ReturnCode::NoAccess),
+            0x8010_0028 => Ok(ReturnCode::WriteTooMany),
+            0x8010_0029 => Ok(ReturnCode::BadSeek),
+            0x8010_002A => Ok(ReturnCode::InvalidChv),
+            0x8010_002B => Ok(ReturnCode::UnknownResMsg),
+            0x8010_002C => Ok(ReturnCode::NoSuchCertificate),
+            0x8010_002D => Ok(ReturnCode::CertificateUnavailable),
+            0x8010_002E => Ok(ReturnCode::NoReadersAvailable),
+            0x8010_002F => Ok(ReturnCode::CommDataLost),
+            0x8010_0030 => Ok(ReturnCode::NoKeyContainer),
+            0x8010_0031 => Ok(ReturnCode::ServerTooBusy),
+            0x8010_0032 => Ok(ReturnCode::PinCacheExpired),
+            0x8010_0033 => Ok(ReturnCode::NoPinCache),
+            0x8010_0034 => Ok(ReturnCode::ReadOnlyCard),
+            0x8010_0065 => Ok(ReturnCode::UnsupportedCard),
+            0x8010_0066 => Ok(ReturnCode::UnresponsiveCard),
+            0x8010_0067 => Ok(ReturnCode::UnpoweredCard),
+            0x8010_0068 => Ok(ReturnCode::ResetCard),
+            0x8010_0069 => Ok(ReturnCode::RemovedCard),
+            0x8010_006A => Ok(ReturnCode::SecurityViolation),
+            0x8010_006B => Ok(ReturnCode::WrongChv),
+            0x8010_006C => Ok(ReturnCode::ChvBlocked),
+            0x8010_006D => Ok(ReturnCode::Eof),
+            0x8010_006E => Ok(ReturnCode::CancelledByUser),
+            0x8010_006F => Ok(ReturnCode::CardNotAuthenticated),
+            0x8010_0070 => Ok(ReturnCode::CacheItemNotFound),
+            0x8010_0071 => Ok(ReturnCode::CacheItemStale),
+            0x8010_0072 => Ok(ReturnCode::CacheItemTooBig),
+            _ => {
+                error!("Unsupported ReturnCode: 0x{:08x}", value);
+                Err(invalid_field_err!("try_from", "ReturnCode", "unsupported value"))
+            }
+        }
+    }
 }
 
 impl From<ReturnCode> for u32 {
@@ -592,24 +910,38 @@ pub struct EstablishContextCall {
 }
 
 impl EstablishContextCall {
+    const FIXED_PART_SIZE: usize = size_of::<u32>();
+
     pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         Ok(rpce::Pdu::<Self>::decode(src, None)?.into_inner())
     }
-
-    fn size() -> usize {
-        size_of::<u32>()
-    }
 }
 
 impl rpce::HeaderlessDecode for EstablishContextCall {
     fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
         expect_no_charset(charset)?;
-        ensure_size!(in: src, size: Self::size());
+        ensure_size!(in: src, size: Self::FIXED_PART_SIZE);
         let scope = Scope::try_from(src.read_u32())?;
         Ok(Self { scope })
     }
 }
 
+impl rpce::HeaderlessEncode for EstablishContextCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: Self::FIXED_PART_SIZE);
+        dst.write_u32(self.scope.into());
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "EstablishContext_Call"
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[repr(u32)]
 pub enum Scope {
@@ -624,6 +956,16 @@ impl Scope {
     }
 }
 
+impl From<Scope> for u32 {
+    #[expect(
+        clippy::as_conversions,
+        reason = "guarantees discriminant layout, and as is the only way to cast enum -> primitive"
+    )]
+    fn from(val: Scope) -> Self {
+        val as u32
+    }
+}
+
 impl TryFrom<u32> for Scope {
     type Error = DecodeError;
 
@@ -645,8 +987,8 @@ impl TryFrom<u32> for Scope {
 /// [2.2.3.2]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/9135d95f-3740-411b-bdca-34ac7571fddc
 #[derive(Debug, PartialEq, Clone)]
 pub struct EstablishContextReturn {
-    return_code: ReturnCode,
-    context: ScardContext,
+    pub return_code: ReturnCode,
+    pub context: ScardContext,
 }
 
 impl EstablishContextReturn {
@@ -676,8 +1018,21 @@ impl rpce::HeaderlessEncode for EstablishContextReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for EstablishContextReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+        let mut index = 0;
+        let mut context = ScardContext::decode_ptr(src, &mut index)?;
+        context.decode_value(src, None)?;
+        Ok(Self { return_code, context })
+    }
+}
+
 /// [2.2.2.4] ListReaders_Call
 ///
+/// When encoding, the lengths and the pointer of the groups are computed from [`ListReadersCall::groups`].
+///
 /// [2.2.2.4]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/be2f46a5-77fb-40bf-839c-aed45f0a26d7
 #[derive(Debug, PartialEq, Clone)]
 pub struct ListReadersCall {
@@ -694,6 +1049,14 @@ impl ListReadersCall {
     pub fn decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
         Ok(rpce::Pdu::<Self>::decode(src, charset)?.into_inner())
     }
+
+    fn encoded_groups_len(&self) -> usize {
+        if self.groups.is_empty() {
+            0
+        } else {
+            encoded_multistring_len(&self.groups, CharacterSet::Unicode)
+        }
+    }
 }
 
 impl rpce::HeaderlessDecode for ListReadersCall {
@@ -748,6 +1111,50 @@ impl rpce::HeaderlessDecode for ListReadersCall {
     }
 }
 
+impl rpce::HeaderlessEncode for ListReadersCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let groups_length: u32 = cast_length!("ListReadersCall", "groups", self.encoded_groups_len())?;
+
+        let mut index = 0;
+        self.context.encode_ptr(&mut index, dst)?;
+        dst.write_u32(groups_length);
+        if self.groups.is_empty() {
+            dst.write_u32(0); // null pointer
+        } else {
+            ndr::encode_ptr(None, &mut index, dst)?;
+        }
+        dst.write_u32(u32::from(self.readers_is_null));
+        dst.write_u32(self.readers_size);
+
+        self.context.encode_value(dst)?;
+
+        if !self.groups.is_empty() {
+            dst.write_u32(groups_length);
+            write_multistring_to_cursor(dst, &self.groups, CharacterSet::Unicode)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "ListReaders_Call"
+    }
+
+    fn size(&self) -> usize {
+        self.context.size()
+        + 4 // cBytes
+        + ndr::ptr_size(false) // mszGroups
+        + 4 // fmszReadersIsNULL
+        + 4 // cchReaders
+        + if self.groups.is_empty() {
+            0
+        } else {
+            4 + self.encoded_groups_len()
+        }
+    }
+}
+
 /// [2.2.3.4] ListReaderGroups_Return and ListReaders_Return
 ///
 /// [2.2.3.4]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/6630bb5b-fc0e-4141-8b53-263225c7628d
@@ -793,6 +1200,26 @@ impl rpce::HeaderlessEncode for ListReadersReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for ListReadersReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        let charset = expect_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+
+        ensure_size!(in: src, size: size_of::<u32>());
+        let _readers_length = src.read_u32();
+        let mut index = 0;
+        let readers_ptr = ndr::decode_ptr(src, &mut index)?;
+
+        let readers = if readers_ptr != 0 {
+            read_multistring_value(src, charset)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { return_code, readers })
+    }
+}
+
 /// [2.2.2.12] GetStatusChangeW_Call
 ///
 /// [2.2.2.12]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/af357ce8-63ee-4577-b6bf-c6f5ca68d754
@@ -848,6 +1275,45 @@ impl rpce::HeaderlessDecode for GetStatusChangeCall {
     }
 }
 
+/// When encoding, the lengths and the pointer of the states are computed from [`GetStatusChangeCall::states`].
+impl rpce::HeaderlessEncode for GetStatusChangeCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let states_length: u32 = cast_length!("GetStatusChangeCall", "states", self.states.len())?;
+
+        let mut index = 0;
+        self.context.encode_ptr(&mut index, dst)?;
+        dst.write_u32(self.timeout);
+        dst.write_u32(states_length);
+        ndr::encode_ptr(None, &mut index, dst)?;
+
+        self.context.encode_value(dst)?;
+
+        dst.write_u32(states_length);
+        for state in &self.states {
+            state.encode_ptr(&mut index, dst)?;
+        }
+        for state in &self.states {
+            state.encode_value(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "GetStatusChange_Call"
+    }
+
+    fn size(&self) -> usize {
+        self.context.size()
+        + 4 // dwTimeOut
+        + 4 // cReaders
+        + ndr::ptr_size(false) // rgReaderStates
+        + 4 // cReaders
+        + self.states.iter().map(ndr::Encode::size).sum::<usize>()
+    }
+}
+
 /// [2.2.1.5] ReaderState_Common_Call
 ///
 /// [2.2.1.5]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/a71e63ba-e58f-487c-a5d2-5a3e48856594
@@ -878,6 +1344,7 @@ impl ReaderStateCommonCall {
     }
 
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: Self::size());
         dst.write_u32(self.current_state.bits());
         dst.write_u32(self.event_state.bits());
         dst.write_u32(self.atr_length);
@@ -954,6 +1421,32 @@ impl rpce::HeaderlessEncode for GetStatusChangeReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for GetStatusChangeReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+
+        ensure_size!(in: src, size: size_of::<u32>());
+        let _reader_states_len = src.read_u32();
+        let mut index = 0;
+        let reader_states_ptr = ndr::decode_ptr(src, &mut index)?;
+
+        let mut reader_states = Vec::new();
+        if reader_states_ptr != 0 {
+            ensure_size!(in: src, size: size_of::<u32>());
+            let reader_states_len = src.read_u32();
+            for _ in 0..reader_states_len {
+                reader_states.push(ReaderStateCommonCall::decode(src)?);
+            }
+        }
+
+        Ok(Self {
+            return_code,
+            reader_states,
+        })
+    }
+}
+
 /// [2.2.2.14] ConnectW_Call
 ///
 /// [2.2.2.14]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/fd06f6a0-a9ea-478c-9b5e-470fd9cde5a6
@@ -981,6 +1474,25 @@ impl rpce::HeaderlessDecode for ConnectCall {
     }
 }
 
+impl rpce::HeaderlessEncode for ConnectCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let mut index = 0;
+        ndr::encode_ptr(None, &mut index, dst)?;
+        self.common.encode_ptr(&mut index, dst)?;
+        ndr::write_string_to_cursor(dst, &self.reader, CharacterSet::Unicode)?;
+        self.common.encode_value(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        "Connect_Call"
+    }
+
+    fn size(&self) -> usize {
+        ndr::ptr_size(false) + self.common.size() + ndr::encoded_string_len(&self.reader, CharacterSet::Unicode)
+    }
+}
+
 /// [2.2.1.3] Connect_Common
 ///
 /// [2.2.1.3]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/32752f32-4410-4682-b9fc-9096674b52de
@@ -1013,6 +1525,28 @@ impl ndr::Decode for ConnectCommon {
     }
 }
 
+impl ndr::Encode for ConnectCommon {
+    fn encode_ptr(&self, index: &mut u32, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size_ptr());
+        self.context.encode_ptr(index, dst)?;
+        dst.write_u32(self.share_mode);
+        dst.write_u32(self.preferred_protocols.bits());
+        Ok(())
+    }
+
+    fn encode_value(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        self.context.encode_value(dst)
+    }
+
+    fn size_ptr(&self) -> usize {
+        self.context.size_ptr() + 4 /* dwShareMode */ + 4 /* dwPreferredProtocols */
+    }
+
+    fn size_value(&self) -> usize {
+        self.context.size_value()
+    }
+}
+
 bitflags! {
     /// [2.2.5] Protocol Identifier
     ///
@@ -1152,6 +1686,23 @@ impl rpce::HeaderlessEncode for ConnectReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for ConnectReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+        let mut index = 0;
+        let mut handle = ScardHandle::decode_ptr(src, &mut index)?;
+        ensure_size!(in: src, size: size_of::<u32>());
+        let active_protocol = CardProtocol::from_bits_retain(src.read_u32());
+        handle.decode_value(src, None)?;
+        Ok(Self {
+            return_code,
+            handle,
+            active_protocol,
+        })
+    }
+}
+
 /// [2.2.2.16] HCardAndDisposition_Call
 ///
 /// [2.2.2.16]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/f15ae865-9e99-4c5b-bb43-15a6b4885bd0
@@ -1179,6 +1730,24 @@ impl rpce::HeaderlessDecode for HCardAndDispositionCall {
     }
 }
 
+impl rpce::HeaderlessEncode for HCardAndDispositionCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let mut index = 0;
+        self.handle.encode_ptr(&mut index, dst)?;
+        dst.write_u32(self.disposition);
+        self.handle.encode_value(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        "HCardAndDisposition_Call"
+    }
+
+    fn size(&self) -> usize {
+        self.handle.size() + 4 /* dwDisposition */
+    }
+}
+
 /// [2.2.2.19] Transmit_Call
 ///
 /// [2.2.2.19]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/e3861cfa-e61b-4d64-b19d-f6b31e076beb
@@ -1242,6 +1811,57 @@ impl rpce::HeaderlessDecode for TransmitCall {
     }
 }
 
+/// When encoding, the length of the sent buffer is computed from [`TransmitCall::send_buffer`].
+impl rpce::HeaderlessEncode for TransmitCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let send_length: u32 = cast_length!("TransmitCall", "send_buffer", self.send_buffer.len())?;
+
+        let mut index = 0;
+        self.handle.encode_ptr(&mut index, dst)?;
+        self.send_pci.encode_ptr(&mut index, dst)?;
+        dst.write_u32(send_length);
+        ndr::encode_ptr(None, &mut index, dst)?;
+        if self.recv_pci.is_some() {
+            ndr::encode_ptr(None, &mut index, dst)?;
+        } else {
+            dst.write_u32(0); // null pointer
+        }
+        dst.write_u32(u32::from(self.recv_buffer_is_null));
+        dst.write_u32(self.recv_length);
+
+        self.handle.encode_value(dst)?;
+        self.send_pci.encode_value(dst)?;
+
+        dst.write_u32(send_length);
+        dst.write_slice(&self.send_buffer);
+
+        if let Some(recv_pci) = &self.recv_pci {
+            recv_pci.encode_ptr(&mut index, dst)?;
+            recv_pci.encode_value(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Transmit_Call"
+    }
+
+    fn size(&self) -> usize {
+        self.handle.size()
+        + self.send_pci.size()
+        + 4 // cbSendLength
+        + ndr::ptr_size(false) // pbSendBuffer
+        + ndr::ptr_size(false) // pioRecvPci
+        + 4 // fpbRecvBufferIsNULL
+        + 4 // cbRecvLength
+        + 4 // cbSendLength
+        + self.send_buffer.len()
+        + self.recv_pci.as_ref().map_or(0, ndr::Encode::size)
+    }
+}
+
 /// [2.2.1.8] SCardIO_Request
 ///
 /// [2.2.1.8]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/f6e15da8-5bc0-4ef6-b28a-ce88e8415621
@@ -1330,15 +1950,20 @@ impl rpce::HeaderlessEncode for TransmitReturn {
         dst.write_u32(self.return_code.into());
 
         let mut index = 0;
-        if let Some(recv_pci) = &self.recv_pci {
-            recv_pci.encode_ptr(&mut index, dst)?;
-            recv_pci.encode_value(dst)?;
+        if self.recv_pci.is_some() {
+            ndr::encode_ptr(None, &mut index, dst)?;
         } else {
             dst.write_u32(0); // null value
         }
 
         let recv_buffer_len: u32 = cast_length!("TransmitReturn", "recv_buffer_len", self.recv_buffer.len())?;
         ndr::encode_ptr(Some(recv_buffer_len), &mut index, dst)?;
+
+        if let Some(recv_pci) = &self.recv_pci {
+            recv_pci.encode_ptr(&mut index, dst)?;
+            recv_pci.encode_value(dst)?;
+        }
+
         dst.write_u32(recv_buffer_len);
         dst.write_slice(&self.recv_buffer);
 
@@ -1351,17 +1976,47 @@ impl rpce::HeaderlessEncode for TransmitReturn {
 
     fn size(&self) -> usize {
         self.return_code.size() // dst.write_u32(self.return_code.into());
-        + if let Some(recv_pci) = &self.recv_pci {
-            recv_pci.size()
-        } else {
-            4 // null value
-        }
+        + ndr::ptr_size(false) // pioRecvPci
         + ndr::ptr_size(true) // ndr::encode_ptr(Some(recv_buffer_len), &mut index, dst)?;
+        + self.recv_pci.as_ref().map_or(0, ndr::Encode::size) // recv_pci.encode_ptr(...); recv_pci.encode_value(...);
         + 4 // dst.write_u32(recv_buffer_len);
         + self.recv_buffer.len() // dst.write_slice(&self.recv_buffer);
     }
 }
 
+impl rpce::HeaderlessDecode for TransmitReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+
+        let mut index = 0;
+        let recv_pci_ptr = ndr::decode_ptr(src, &mut index)?;
+        ensure_size!(in: src, size: size_of::<u32>());
+        let _recv_buffer_len = src.read_u32();
+        let recv_buffer_ptr = ndr::decode_ptr(src, &mut index)?;
+
+        let recv_pci = if recv_pci_ptr != 0 {
+            let mut recv_pci = SCardIORequest::decode_ptr(src, &mut index)?;
+            recv_pci.decode_value(src, None)?;
+            Some(recv_pci)
+        } else {
+            None
+        };
+
+        let recv_buffer = if recv_buffer_ptr != 0 {
+            read_bytes_value(src)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            return_code,
+            recv_pci,
+            recv_buffer,
+        })
+    }
+}
+
 /// [2.2.2.18] Status_Call
 ///
 /// [2.2.2.18]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/f1139aed-e578-47f3-a800-f36b56c80500
@@ -1398,6 +2053,27 @@ impl rpce::HeaderlessDecode for StatusCall {
     }
 }
 
+impl rpce::HeaderlessEncode for StatusCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let mut index = 0;
+        self.handle.encode_ptr(&mut index, dst)?;
+        dst.write_u32(u32::from(self.reader_names_is_null));
+        dst.write_u32(self.reader_length);
+        dst.write_u32(self.atr_length);
+        self.handle.encode_value(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        "Status_Call"
+    }
+
+    fn size(&self) -> usize {
+        self.handle.size() + 4 /* fmszReaderNamesIsNULL */ + 4 /* cchReaderLen */ + 4
+        /* cbAtrLen */
+    }
+}
+
 /// [2.2.3.10] Status_Return
 ///
 /// [2.2.3.10]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/987c1358-ad6b-4c8e-88e1-06210c28a66f
@@ -1469,6 +2145,40 @@ impl rpce::HeaderlessEncode for StatusReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for StatusReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        let encoding = expect_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+
+        ensure_size!(in: src, size: size_of::<u32>());
+        let _reader_names_length = src.read_u32();
+        let mut index = 0;
+        let reader_names_ptr = ndr::decode_ptr(src, &mut index)?;
+
+        ensure_size!(in: src, size: size_of::<u32>() * 3 + 32);
+        let state = CardState::try_from(src.read_u32())?;
+        let protocol = CardProtocol::from_bits_retain(src.read_u32());
+        let atr = src.read_array::<32>();
+        let atr_length = src.read_u32();
+
+        let reader_names = if reader_names_ptr != 0 {
+            read_multistring_value(src, encoding)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            return_code,
+            reader_names,
+            state,
+            protocol,
+            atr,
+            atr_length,
+            encoding,
+        })
+    }
+}
+
 /// [2.2.4] Card/Reader State
 ///
 /// [2.2.4]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/264bc504-1195-43ff-a057-3d86a02c5d9c
@@ -1490,6 +2200,26 @@ pub enum CardState {
     SpecificMode = 0x0000_0006,
 }
 
+impl TryFrom<u32> for CardState {
+    type Error = DecodeError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0x0000_0000 => Ok(CardState::Unknown),
+            0x0000_0001 => Ok(CardState::Absent),
+            0x0000_0002 => Ok(CardState::Present),
+            0x0000_0003 => Ok(CardState::Swallowed),
+            0x0000_0004 => Ok(CardState::Powered),
+            0x0000_0005 => Ok(CardState::Negotiable),
+            0x0000_0006 => Ok(CardState::SpecificMode),
+            _ => {
+                error!("Unsupported CardState: 0x{:08x}", value);
+                Err(invalid_field_err!("try_from", "CardState", "unsupported value"))
+            }
+        }
+    }
+}
+
 impl From<CardState> for u32 {
     #[expect(
         clippy::as_conversions,
@@ -1524,6 +2254,23 @@ impl rpce::HeaderlessDecode for ContextCall {
     }
 }
 
+impl rpce::HeaderlessEncode for ContextCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let mut index = 0;
+        self.context.encode_ptr(&mut index, dst)?;
+        self.context.encode_value(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        "Context_Call"
+    }
+
+    fn size(&self) -> usize {
+        self.context.size()
+    }
+}
+
 /// [2.2.2.32] GetDeviceTypeId_Call
 ///
 /// [2.2.2.32]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/b5e18874-c42d-42ea-b1b1-3fd86a8a95f1
@@ -1556,6 +2303,25 @@ impl rpce::HeaderlessDecode for GetDeviceTypeIdCall {
     }
 }
 
+impl rpce::HeaderlessEncode for GetDeviceTypeIdCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let mut index = 0;
+        self.context.encode_ptr(&mut index, dst)?;
+        ndr::encode_ptr(None, &mut index, dst)?;
+        self.context.encode_value(dst)?;
+        ndr::write_string_to_cursor(dst, &self.reader_name, CharacterSet::Unicode)
+    }
+
+    fn name(&self) -> &'static str {
+        "GetDeviceTypeId_Call"
+    }
+
+    fn size(&self) -> usize {
+        self.context.size() + ndr::ptr_size(false) + ndr::encoded_string_len(&self.reader_name, CharacterSet::Unicode)
+    }
+}
+
 /// [2.2.3.15] GetDeviceTypeId_Return
 ///
 /// [2.2.3.15]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/fed90d29-c41f-490a-86e9-7e88e42656b2
@@ -1594,6 +2360,19 @@ impl rpce::HeaderlessEncode for GetDeviceTypeIdReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for GetDeviceTypeIdReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+        ensure_size!(in: src, size: size_of::<u32>());
+        let device_type_id = src.read_u32();
+        Ok(Self {
+            return_code,
+            device_type_id,
+        })
+    }
+}
+
 /// [2.2.2.26] ReadCacheW_Call
 ///
 /// [2.2.2.26]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/f45705cf-9299-4802-b408-685f02025e6a
@@ -1621,6 +2400,21 @@ impl rpce::HeaderlessDecode for ReadCacheCall {
     }
 }
 
+impl rpce::HeaderlessEncode for ReadCacheCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        encode_cache_call(&self.lookup_name, &self.common, dst)
+    }
+
+    fn name(&self) -> &'static str {
+        "ReadCache_Call"
+    }
+
+    fn size(&self) -> usize {
+        cache_call_size(&self.lookup_name, &self.common)
+    }
+}
+
 /// [2.2.1.9] ReadCache_Common
 ///
 /// [2.2.1.9]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/3f9e07fa-66e2-498b-920c-39531709116b
@@ -1663,6 +2457,34 @@ impl ndr::Decode for ReadCacheCommon {
     }
 }
 
+impl ndr::Encode for ReadCacheCommon {
+    fn encode_ptr(&self, index: &mut u32, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size_ptr());
+        self.context.encode_ptr(index, dst)?;
+        ndr::encode_ptr(None, index, dst)?;
+        dst.write_u32(self.freshness_counter);
+        dst.write_u32(u32::from(self.data_is_null));
+        dst.write_u32(self.data_len);
+        Ok(())
+    }
+
+    fn encode_value(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size_value());
+        self.context.encode_value(dst)?;
+        dst.write_slice(&self.card_uuid);
+        Ok(())
+    }
+
+    fn size_ptr(&self) -> usize {
+        self.context.size_ptr() + ndr::ptr_size(false) + 4 /* FreshnessCounter */ + 4 /* fPbDataIsNULL */ + 4
+        /* cbDataLen */
+    }
+
+    fn size_value(&self) -> usize {
+        self.context.size_value() + self.card_uuid.len()
+    }
+}
+
 /// [2.2.3.1] ReadCache_Return
 ///
 /// [2.2.3.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/da342355-e37f-485e-a490-3222a97fa356
@@ -1704,6 +2526,15 @@ impl rpce::HeaderlessEncode for ReadCacheReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for ReadCacheReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+        let data = decode_bytes_ptr_and_value(src)?;
+        Ok(Self { return_code, data })
+    }
+}
+
 /// [2.2.2.28] WriteCacheW_Call
 ///
 /// [2.2.2.28]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/3969bdcd-ecf3-42db-8bc6-2d6f970f9c67
@@ -1731,6 +2562,21 @@ impl rpce::HeaderlessDecode for WriteCacheCall {
     }
 }
 
+impl rpce::HeaderlessEncode for WriteCacheCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        encode_cache_call(&self.lookup_name, &self.common, dst)
+    }
+
+    fn name(&self) -> &'static str {
+        "WriteCache_Call"
+    }
+
+    fn size(&self) -> usize {
+        cache_call_size(&self.lookup_name, &self.common)
+    }
+}
+
 /// [2.2.1.10] WriteCache_Common
 ///
 /// [2.2.1.10]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/5604251b-9173-457c-9476-57863df9010e
@@ -1775,6 +2621,36 @@ impl ndr::Decode for WriteCacheCommon {
     }
 }
 
+impl ndr::Encode for WriteCacheCommon {
+    fn encode_ptr(&self, index: &mut u32, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size_ptr());
+        let data_len: u32 = cast_length!("WriteCacheCommon", "data_len", self.data.len())?;
+        self.context.encode_ptr(index, dst)?;
+        ndr::encode_ptr(None, index, dst)?;
+        dst.write_u32(self.freshness_counter);
+        dst.write_u32(data_len);
+        ndr::encode_ptr(None, index, dst)
+    }
+
+    fn encode_value(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size_value());
+        let data_len: u32 = cast_length!("WriteCacheCommon", "data_len", self.data.len())?;
+        self.context.encode_value(dst)?;
+        dst.write_slice(&self.card_uuid);
+        dst.write_u32(data_len);
+        dst.write_slice(&self.data);
+        Ok(())
+    }
+
+    fn size_ptr(&self) -> usize {
+        self.context.size_ptr() + ndr::ptr_size(false) + 4 /* FreshnessCounter */ + 4 /* cbDataLen */ + ndr::ptr_size(false)
+    }
+
+    fn size_value(&self) -> usize {
+        self.context.size_value() + self.card_uuid.len() + 4 /* cbDataLen */ + self.data.len()
+    }
+}
+
 /// [2.2.2.31] GetReaderIcon_Call
 ///
 /// [2.2.2.31]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/e6a68d90-697f-4b98-8ad6-f74853d27ccb
@@ -1804,6 +2680,25 @@ impl rpce::HeaderlessDecode for GetReaderIconCall {
     }
 }
 
+impl rpce::HeaderlessEncode for GetReaderIconCall {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        let mut index = 0;
+        self.context.encode_ptr(&mut index, dst)?;
+        ndr::encode_ptr(None, &mut index, dst)?;
+        self.context.encode_value(dst)?;
+        ndr::write_string_to_cursor(dst, &self.reader_name, CharacterSet::Unicode)
+    }
+
+    fn name(&self) -> &'static str {
+        "GetReaderIcon_Call"
+    }
+
+    fn size(&self) -> usize {
+        self.context.size() + ndr::ptr_size(false) + ndr::encoded_string_len(&self.reader_name, CharacterSet::Unicode)
+    }
+}
+
 /// [2.2.3.14] GetReaderIcon_Return
 ///
 /// [2.2.3.14]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/f011f3d9-e2a4-4c43-a336-4c89ecaa8360
@@ -1845,6 +2740,56 @@ impl rpce::HeaderlessEncode for GetReaderIconReturn {
     }
 }
 
+impl rpce::HeaderlessDecode for GetReaderIconReturn {
+    fn headerless_decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let return_code = ReturnCode::decode(src)?;
+        let data = decode_bytes_ptr_and_value(src)?;
+        Ok(Self { return_code, data })
+    }
+}
+
+/// Encodes the calls sharing the layout of [`ReadCacheCall`] and [`WriteCacheCall`].
+fn encode_cache_call(lookup_name: &str, common: &impl ndr::Encode, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+    let mut index = 0;
+    ndr::encode_ptr(None, &mut index, dst)?;
+    common.encode_ptr(&mut index, dst)?;
+    ndr::write_string_to_cursor(dst, lookup_name, CharacterSet::Unicode)?;
+    common.encode_value(dst)
+}
+
+fn cache_call_size(lookup_name: &str, common: &impl ndr::Encode) -> usize {
+    ndr::ptr_size(false) + common.size() + ndr::encoded_string_len(lookup_name, CharacterSet::Unicode)
+}
+
+/// Reads a byte array pointer with its length, and its value unless the pointer is null.
+fn decode_bytes_ptr_and_value(src: &mut ReadCursor<'_>) -> DecodeResult<Vec<u8>> {
+    ensure_size!(in: src, size: size_of::<u32>());
+    let _length = src.read_u32();
+    let mut index = 0;
+    if ndr::decode_ptr(src, &mut index)? == 0 {
+        return Ok(Vec::new());
+    }
+
+    read_bytes_value(src)
+}
+
+/// Reads the value of a byte array, prefixed by its length.
+fn read_bytes_value(src: &mut ReadCursor<'_>) -> DecodeResult<Vec<u8>> {
+    ensure_size!(in: src, size: size_of::<u32>());
+    let length: usize = cast_length!("read_bytes_value", "length", src.read_u32())?;
+    ensure_size!(in: src, size: length);
+    Ok(src.read_slice(length).to_vec())
+}
+
+/// Reads the value of a multi-string, prefixed by its length in bytes.
+fn read_multistring_value(src: &mut ReadCursor<'_>, charset: CharacterSet) -> DecodeResult<Vec<String>> {
+    ensure_size!(in: src, size: size_of::<u32>());
+    let length: usize = cast_length!("read_multistring_value", "length", src.read_u32())?;
+    ensure_size!(in: src, size: length);
+    read_multistring_from_cursor(&mut ReadCursor::new(src.read_slice(length)), charset)
+}
+
 fn expect_charset(charset: Option<CharacterSet>) -> DecodeResult<CharacterSet> {
     charset.ok_or_else(|| other_err!("internal error: missing character set"))
 }
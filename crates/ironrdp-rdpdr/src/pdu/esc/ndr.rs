@@ -21,8 +21,10 @@
 //!
 //! [smartcard_pack.c]: https://github.com/FreeRDP/FreeRDP/blob/ff303a9bda911c54ffc1b9f2471acd79c897b075/libfreerdp/utils/smartcard_pack.c
 
-use ironrdp_core::{ensure_size, invalid_field_err, DecodeResult, EncodeResult, ReadCursor, WriteCursor};
-use ironrdp_pdu::utils::{self, CharacterSet};
+use ironrdp_core::{
+    cast_length, ensure_size, invalid_field_err, write_padding, DecodeResult, EncodeResult, ReadCursor, WriteCursor,
+};
+use ironrdp_pdu::utils::{self, encoded_str_len, CharacterSet};
 
 pub trait Decode {
     fn decode_ptr(src: &mut ReadCursor<'_>, index: &mut u32) -> DecodeResult<Self>
@@ -99,3 +101,40 @@ pub fn read_string_from_cursor(cursor: &mut ReadCursor<'_>, charset: CharacterSe
 
     Ok(string)
 }
+
+/// Writes a NULL-terminated string as read by [`read_string_from_cursor`].
+///
+/// The string is expected to start on a 4-byte boundary, which is the case for the strings following fixed-size
+/// fields.
+pub fn write_string_to_cursor(cursor: &mut WriteCursor<'_>, value: &str, charset: CharacterSet) -> EncodeResult<()> {
+    ensure_size!(ctx: "ndr::write_string_to_cursor", in: cursor, size: encoded_string_len(value, charset));
+    let code_unit_size = if charset == CharacterSet::Unicode { 2 } else { 1 };
+    let length: u32 = cast_length!(
+        "ndr::write_string_to_cursor",
+        "length",
+        encoded_str_len(value, charset, true) / code_unit_size
+    )?;
+    cursor.write_u32(length);
+    cursor.write_u32(0); // offset
+    cursor.write_u32(length);
+
+    utils::write_string_to_cursor(cursor, value, charset, true)?;
+    write_padding!(cursor, string_padding_size(value, charset));
+
+    Ok(())
+}
+
+/// Size of a string written with [`write_string_to_cursor`].
+pub fn encoded_string_len(value: &str, charset: CharacterSet) -> usize {
+    size_of::<u32>() * 3 + encoded_str_len(value, charset, true) + string_padding_size(value, charset)
+}
+
+fn string_padding_size(value: &str, charset: CharacterSet) -> usize {
+    const ALIGNMENT: usize = 4;
+    let tail = encoded_str_len(value, charset, true) % ALIGNMENT;
+    if tail > 0 {
+        ALIGNMENT - tail
+    } else {
+        0
+    }
+}
@@ -10,11 +10,13 @@ use self::efs::{
     ClientDeviceListAnnounce, ClientDeviceListRemove, ClientDriveQueryDirectoryResponse,
     ClientDriveQueryInformationResponse, ClientDriveQueryVolumeInformationResponse, ClientDriveSetInformationResponse,
     ClientNameRequest, CoreCapability, CoreCapabilityKind, DeviceCloseResponse, DeviceControlResponse,
-    DeviceCreateResponse, DeviceIoRequest, DeviceReadResponse, DeviceWriteResponse, ServerDeviceAnnounceResponse,
-    VersionAndIdPdu, VersionAndIdPduKind,
+    DeviceCreateResponse, DeviceIoRequest, DeviceIoResponse, DeviceReadResponse, DeviceWriteResponse,
+    ServerDeviceAnnounceResponse, ServerDriveIoRequest, VersionAndIdPdu, VersionAndIdPduKind,
 };
+use self::esc::ScardControlRequest;
 
 pub mod efs;
+pub mod epc;
 pub mod esc;
 
 /// All available RDPDR PDUs.
@@ -26,6 +28,12 @@ pub enum RdpdrPdu {
     ClientDeviceListRemove(ClientDeviceListRemove),
     ServerDeviceAnnounceResponse(ServerDeviceAnnounceResponse),
     DeviceIoRequest(DeviceIoRequest),
+    /// A drive I/O request sent by the server, see [`crate::server::RdpdrServer`]
+    ServerDriveIoRequest(ServerDriveIoRequest),
+    /// A smart card call sent by the server, see [`crate::server::RdpdrServer`]
+    ScardControlRequest(ScardControlRequest),
+    /// Header of an I/O completion, decoded by the server with [`RdpdrPdu::decode_client`]
+    DeviceIoResponse(DeviceIoResponse),
     DeviceControlResponse(DeviceControlResponse),
     DeviceCreateResponse(DeviceCreateResponse),
     ClientDriveQueryInformationResponse(ClientDriveQueryInformationResponse),
@@ -83,11 +91,14 @@ impl RdpdrPdu {
                 component: Component::RdpdrCtypCore,
                 packet_id: PacketId::CoreDeviceReply,
             },
-            RdpdrPdu::DeviceIoRequest(_) => SharedHeader {
-                component: Component::RdpdrCtypCore,
-                packet_id: PacketId::CoreDeviceIoRequest,
-            },
-            RdpdrPdu::DeviceControlResponse(_)
+            RdpdrPdu::DeviceIoRequest(_) | RdpdrPdu::ServerDriveIoRequest(_) | RdpdrPdu::ScardControlRequest(_) => {
+                SharedHeader {
+                    component: Component::RdpdrCtypCore,
+                    packet_id: PacketId::CoreDeviceIoRequest,
+                }
+            }
+            RdpdrPdu::DeviceIoResponse(_)
+            | RdpdrPdu::DeviceControlResponse(_)
             | RdpdrPdu::DeviceCreateResponse(_)
             | RdpdrPdu::ClientDriveQueryInformationResponse(_)
             | RdpdrPdu::DeviceCloseResponse(_)
@@ -106,6 +117,33 @@ impl RdpdrPdu {
             },
         }
     }
+
+    /// Decodes a PDU sent by the client.
+    ///
+    /// For I/O completions, only the [`RdpdrPdu::DeviceIoResponse`] header is decoded: the rest of the PDU depends on
+    /// the request, and is left in `src`.
+    pub fn decode_client(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        let header = SharedHeader::decode(src)?;
+        match header.packet_id {
+            PacketId::CoreClientidConfirm => Ok(RdpdrPdu::VersionAndIdPdu(
+                VersionAndIdPdu::decode_client_announce_reply(src)?,
+            )),
+            PacketId::CoreClientName => Ok(RdpdrPdu::ClientNameRequest(ClientNameRequest::decode(src)?)),
+            PacketId::CoreClientCapability => Ok(RdpdrPdu::CoreCapability(CoreCapability::decode(header, src)?)),
+            PacketId::CoreDevicelistAnnounce => Ok(RdpdrPdu::ClientDeviceListAnnounce(
+                ClientDeviceListAnnounce::decode(src)?,
+            )),
+            PacketId::CoreDevicelistRemove => {
+                Ok(RdpdrPdu::ClientDeviceListRemove(ClientDeviceListRemove::decode(src)?))
+            }
+            PacketId::CoreDeviceIoCompletion => Ok(RdpdrPdu::DeviceIoResponse(DeviceIoResponse::decode(src)?)),
+            _ => Err(unsupported_value_err!(
+                "RdpdrPdu",
+                "PacketId",
+                header.packet_id.to_string()
+            )),
+        }
+    }
 }
 
 impl Decode<'_> for RdpdrPdu {
@@ -141,6 +179,9 @@ impl Encode for RdpdrPdu {
             RdpdrPdu::ClientDeviceListRemove(pdu) => pdu.encode(dst),
             RdpdrPdu::ServerDeviceAnnounceResponse(pdu) => pdu.encode(dst),
             RdpdrPdu::DeviceIoRequest(pdu) => pdu.encode(dst),
+            RdpdrPdu::ServerDriveIoRequest(pdu) => pdu.encode(dst),
+            RdpdrPdu::ScardControlRequest(pdu) => pdu.encode(dst),
+            RdpdrPdu::DeviceIoResponse(pdu) => pdu.encode(dst),
             RdpdrPdu::DeviceControlResponse(pdu) => pdu.encode(dst),
             RdpdrPdu::DeviceCreateResponse(pdu) => pdu.encode(dst),
             RdpdrPdu::ClientDriveQueryInformationResponse(pdu) => pdu.encode(dst),
@@ -168,6 +209,9 @@ impl Encode for RdpdrPdu {
             RdpdrPdu::ClientDeviceListRemove(pdu) => pdu.name(),
             RdpdrPdu::ServerDeviceAnnounceResponse(pdu) => pdu.name(),
             RdpdrPdu::DeviceIoRequest(pdu) => pdu.name(),
+            RdpdrPdu::ServerDriveIoRequest(pdu) => pdu.name(),
+            RdpdrPdu::ScardControlRequest(pdu) => pdu.name(),
+            RdpdrPdu::DeviceIoResponse(_) => "DR_DEVICE_IOCOMPLETION",
             RdpdrPdu::DeviceControlResponse(pdu) => pdu.name(),
             RdpdrPdu::DeviceCreateResponse(pdu) => pdu.name(),
             RdpdrPdu::ClientDriveQueryInformationResponse(pdu) => pdu.name(),
@@ -192,6 +236,9 @@ impl Encode for RdpdrPdu {
                 RdpdrPdu::ClientDeviceListRemove(pdu) => pdu.size(),
                 RdpdrPdu::ServerDeviceAnnounceResponse(pdu) => pdu.size(),
                 RdpdrPdu::DeviceIoRequest(pdu) => pdu.size(),
+                RdpdrPdu::ServerDriveIoRequest(pdu) => pdu.size(),
+                RdpdrPdu::ScardControlRequest(pdu) => pdu.size(),
+                RdpdrPdu::DeviceIoResponse(pdu) => pdu.size(),
                 RdpdrPdu::DeviceControlResponse(pdu) => pdu.size(),
                 RdpdrPdu::DeviceCreateResponse(pdu) => pdu.size(),
                 RdpdrPdu::ClientDriveQueryInformationResponse(pdu) => pdu.size(),
@@ -233,6 +280,15 @@ impl fmt::Debug for RdpdrPdu {
             Self::DeviceIoRequest(it) => {
                 write!(f, "RdpdrPdu({it:?})")
             }
+            Self::ServerDriveIoRequest(it) => {
+                write!(f, "RdpdrPdu({it:?})")
+            }
+            Self::ScardControlRequest(it) => {
+                write!(f, "RdpdrPdu({it:?})")
+            }
+            Self::DeviceIoResponse(it) => {
+                write!(f, "RdpdrPdu({it:?})")
+            }
             Self::DeviceControlResponse(it) => {
                 write!(f, "RdpdrPdu({it:?})")
             }
@@ -270,6 +326,12 @@ impl fmt::Debug for RdpdrPdu {
     }
 }
 
+impl From<ScardControlRequest> for RdpdrPdu {
+    fn from(value: ScardControlRequest) -> Self {
+        Self::ScardControlRequest(value)
+    }
+}
+
 impl From<DeviceControlResponse> for RdpdrPdu {
     fn from(value: DeviceControlResponse) -> Self {
         Self::DeviceControlResponse(value)
@@ -0,0 +1,1048 @@
+//! Server side of the RDPDR channel, exposing the drives, smart cards and printers redirected by the client.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use ironrdp_core::{cast_length, ensure_size, impl_as_any, DecodeResult, ReadCursor};
+use ironrdp_pdu::gcc::ChannelName;
+use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{CompressionCondition, SvcMessage, SvcProcessor, SvcProcessorMessages, SvcServerProcessor};
+use tracing::{debug, warn};
+
+use crate::pdu::efs::{
+    Capabilities, ClientDeviceListAnnounce, ClientDeviceListRemove, ClientDriveQueryDirectoryResponse, CoreCapability,
+    CoreCapabilityKind, CreateDisposition, CreateOptions, DesiredAccess, DeviceAnnounceHeader, DeviceCloseRequest,
+    DeviceCloseResponse, DeviceCreateRequest, DeviceCreateResponse, DeviceIoRequest, DeviceIoResponse,
+    DeviceReadRequest, DeviceReadResponse, DeviceType, DeviceWriteRequest, DeviceWriteResponse, FileAttributes,
+    FileDirectoryInformation, FileInformationClass, FileInformationClassLevel, Information, MajorFunction,
+    MinorFunction, NtStatus, ServerDeviceAnnounceResponse, ServerDriveIoRequest, ServerDriveQueryDirectoryRequest,
+    SharedAccess, VersionAndIdPdu, VersionAndIdPduKind,
+};
+use crate::pdu::epc::{PrinterDeviceAnnounce, PrinterFlags};
+use crate::pdu::esc::{ScardCall, ScardControlRequest, ScardIoCtlCode, ScardReturn};
+use crate::pdu::RdpdrPdu;
+
+pub type RdpdrSvcMessages = SvcProcessorMessages<RdpdrServer>;
+
+/// Client ID announced by the server, the client may reply with another one
+const SERVER_CLIENT_ID: u32 = 1;
+
+/// Maximum size of the return of a smart card call
+const SCARD_OUTPUT_BUFFER_LENGTH: u32 = 0x10000;
+
+/// Maximum size of the data sent in a single write request of a print job
+const PRINT_JOB_CHUNK_SIZE: usize = 0x10000;
+
+/// Message sent by the event loop.
+#[derive(Debug)]
+pub enum RdpdrServerMessage {
+    /// File system request on a redirected drive, see [`RdpdrServer::drive_request`]
+    DriveRequest {
+        device_id: u32,
+        completion_id: u32,
+        request: DriveRequest,
+    },
+    /// Call on a redirected smart card reader, see [`RdpdrServer::smartcard_call`]
+    SmartcardCall {
+        device_id: u32,
+        completion_id: u32,
+        io_control_code: ScardIoCtlCode,
+        call: ScardCall,
+    },
+    /// Print job on a redirected printer, see [`RdpdrServer::print_job`]
+    PrintJob {
+        device_id: u32,
+        completion_id: u32,
+        data: Vec<u8>,
+    },
+}
+
+/// A drive of the client, redirected to the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectedDrive {
+    pub device_id: u32,
+    /// Name of the drive, as displayed by the client
+    pub name: String,
+}
+
+/// A printer of the client, redirected to the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectedPrinter {
+    pub device_id: u32,
+    /// Name of the printer, as displayed by the client
+    pub name: String,
+    /// Name of the printer driver, which determines the format of the print jobs
+    pub driver_name: String,
+    pub flags: PrinterFlags,
+}
+
+/// File system request on a redirected drive
+///
+/// Paths are relative to the root of the drive and use `\` as separator, e.g. `\dir\file.txt`. The root of the drive
+/// is the empty path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DriveRequest {
+    /// Opens or creates a file or a directory, the ID of the opened file is given by [`DriveResponse::Create`]
+    Create {
+        path: String,
+        desired_access: DesiredAccess,
+        create_disposition: CreateDisposition,
+        create_options: CreateOptions,
+    },
+    Read {
+        file_id: u32,
+        offset: u64,
+        length: u32,
+    },
+    Write {
+        file_id: u32,
+        offset: u64,
+        data: Vec<u8>,
+    },
+    /// Reads the next entry of a directory
+    ///
+    /// The initial query gives the pattern of the listed entries, e.g. `\dir\*`. The following queries continue the
+    /// enumeration, until it fails with [`NtStatus::NO_MORE_FILES`].
+    QueryDirectory {
+        file_id: u32,
+        initial_query: bool,
+        pattern: String,
+    },
+    Close {
+        file_id: u32,
+    },
+}
+
+/// Outcome of a successful [`DriveRequest`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DriveResponse {
+    Create {
+        file_id: u32,
+        information: Information,
+    },
+    Read(Vec<u8>),
+    /// Number of bytes written
+    Write(u32),
+    QueryDirectory(FileDirectoryInformation),
+    Close,
+}
+
+/// Drives redirected by the client
+///
+/// Requests are sent with [`RdpdrServer::drive_request`], using a completion ID chosen by the caller and unique among
+/// the pending requests. Their outcome is given to [`FileSystemBackend::io_completed`] with the same completion ID.
+pub trait FileSystemBackend: Send + core::fmt::Debug {
+    /// A drive was announced by the client, returns whether it's accepted
+    fn drive_announced(&mut self, _drive: &RedirectedDrive) -> bool {
+        true
+    }
+
+    /// A drive was removed by the client
+    ///
+    /// The requests pending on the drive are completed with [`NtStatus::UNSUCCESSFUL`] beforehand.
+    fn drive_removed(&mut self, _device_id: u32) {}
+
+    /// A request sent with [`RdpdrServer::drive_request`] completed
+    fn io_completed(&mut self, device_id: u32, completion_id: u32, result: Result<DriveResponse, NtStatus>);
+}
+
+/// Restrictions on the redirected drives, see [`RdpdrServer::with_drive_policy`]
+///
+/// The policy is checked by [`RdpdrServer`] itself, before the [`FileSystemBackend`]: a backend can't bypass it.
+pub trait DrivePolicy: Send + Sync + core::fmt::Debug {
+    /// Whether a drive announced by the client may be redirected, the denied drives are rejected with
+    /// [`NtStatus::ACCESS_DENIED`]
+    fn allow_drive(&self, drive: &RedirectedDrive) -> bool;
+
+    /// Whether a request may be sent to the client, the denied requests are completed with
+    /// [`NtStatus::ACCESS_DENIED`] without being sent
+    fn allow_request(&self, device_id: u32, request: &DriveRequest) -> bool;
+}
+
+/// Smart card readers redirected by the client, as specified in [\[MS-RDPESC\]]
+///
+/// Calls are sent with [`RdpdrServer::smartcard_call`] like the drive requests, their outcome is given to
+/// [`SmartCardHandler::call_completed`] with the same completion ID. The client typically announces a single device,
+/// giving access to all its readers.
+///
+/// [\[MS-RDPESC\]]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/0428ca28-b4dc-46a3-97c3-01887fa44a90
+pub trait SmartCardHandler: Send + core::fmt::Debug {
+    /// A smart card device was announced by the client, returns whether it's accepted
+    fn device_announced(&mut self, _device_id: u32) -> bool {
+        true
+    }
+
+    /// A smart card device was removed by the client
+    ///
+    /// The calls pending on the device are completed with [`NtStatus::UNSUCCESSFUL`] beforehand.
+    fn device_removed(&mut self, _device_id: u32) {}
+
+    /// A call sent with [`RdpdrServer::smartcard_call`] completed
+    ///
+    /// The call itself may have failed: see [`ScardReturn::return_code`].
+    fn call_completed(
+        &mut self,
+        device_id: u32,
+        completion_id: u32,
+        io_control_code: ScardIoCtlCode,
+        result: Result<ScardReturn, NtStatus>,
+    );
+}
+
+/// Printers redirected by the client, as specified in [MS-RDPEPC]
+///
+/// Jobs are sent with [`RdpdrServer::print_job`], using a completion ID chosen by the caller like the drive requests.
+/// The data must be rendered in the format of the printer driver announced by the client, e.g. PostScript.
+pub trait PrinterHandler: Send + core::fmt::Debug {
+    /// A printer was announced by the client, returns whether it's accepted
+    fn printer_announced(&mut self, _printer: &RedirectedPrinter) -> bool {
+        true
+    }
+
+    /// A printer was removed by the client
+    ///
+    /// The jobs pending on the printer are completed with [`NtStatus::UNSUCCESSFUL`] beforehand.
+    fn printer_removed(&mut self, _device_id: u32) {}
+
+    /// A job sent with [`RdpdrServer::print_job`] completed, once all its data was written and the job was closed
+    fn job_completed(&mut self, device_id: u32, completion_id: u32, result: Result<(), NtStatus>);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RdpdrState {
+    Start,
+    WaitingForAnnounceReply,
+    WaitingForClientName,
+    WaitingForCapabilities,
+    Ready,
+}
+
+#[derive(Debug)]
+enum PendingRequest {
+    Drive {
+        device_id: u32,
+        major_function: MajorFunction,
+    },
+    Smartcard {
+        device_id: u32,
+        io_control_code: ScardIoCtlCode,
+    },
+    /// A print job is sent with a create request, write requests until all its data is written, and a close request
+    PrintJob {
+        device_id: u32,
+        major_function: MajorFunction,
+        job: PrintJob,
+    },
+}
+
+impl PendingRequest {
+    fn device_id(&self) -> u32 {
+        match self {
+            Self::Drive { device_id, .. } | Self::Smartcard { device_id, .. } | Self::PrintJob { device_id, .. } => {
+                *device_id
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PrintJob {
+    data: Vec<u8>,
+    written: usize,
+    file_id: u32,
+    /// Status of the failed write request, the job is closed anyway
+    status: NtStatus,
+}
+
+/// Server of the RDPDR channel as specified in [\[MS-RDPEFS\]]
+///
+/// The drives are supported, as well as the smart cards when a [`SmartCardHandler`] is set with
+/// [`RdpdrServer::with_smartcard`] and the printers when a [`PrinterHandler`] is set with
+/// [`RdpdrServer::with_printer`]. The other devices announced by the client are rejected.
+///
+/// [\[MS-RDPEFS\]]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpefs/34d9de58-b2b5-40b6-b970-f82d4603bdb5
+#[derive(Debug)]
+pub struct RdpdrServer {
+    backend: Box<dyn FileSystemBackend>,
+    state: RdpdrState,
+    announce_reply: Option<VersionAndIdPdu>,
+    client_name: Option<String>,
+    drive_supported: bool,
+    drives: BTreeMap<u32, RedirectedDrive>,
+    drive_policy: Option<Arc<dyn DrivePolicy>>,
+    smartcard: Option<Box<dyn SmartCardHandler>>,
+    smartcard_supported: bool,
+    smartcards: BTreeSet<u32>,
+    printer: Option<Box<dyn PrinterHandler>>,
+    printer_supported: bool,
+    printers: BTreeMap<u32, RedirectedPrinter>,
+    pending: BTreeMap<u32, PendingRequest>,
+}
+
+impl RdpdrServer {
+    pub const NAME: ChannelName = ChannelName::from_static(b"rdpdr\0\0\0");
+
+    pub fn new(backend: Box<dyn FileSystemBackend>) -> Self {
+        Self {
+            backend,
+            state: RdpdrState::Start,
+            announce_reply: None,
+            client_name: None,
+            drive_supported: false,
+            drives: BTreeMap::new(),
+            drive_policy: None,
+            smartcard: None,
+            smartcard_supported: false,
+            smartcards: BTreeSet::new(),
+            printer: None,
+            printer_supported: false,
+            printers: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Restricts the redirected drives and the requests sent to them
+    #[must_use]
+    pub fn with_drive_policy(mut self, policy: Arc<dyn DrivePolicy>) -> Self {
+        self.drive_policy = Some(policy);
+        self
+    }
+
+    /// Enables the smart card redirection
+    #[must_use]
+    pub fn with_smartcard(mut self, handler: Box<dyn SmartCardHandler>) -> Self {
+        self.smartcard = Some(handler);
+        self
+    }
+
+    /// Enables the printer redirection
+    #[must_use]
+    pub fn with_printer(mut self, handler: Box<dyn PrinterHandler>) -> Self {
+        self.printer = Some(handler);
+        self
+    }
+
+    /// Name of the client computer, once announced
+    pub fn client_name(&self) -> Option<&str> {
+        self.client_name.as_deref()
+    }
+
+    /// The drives redirected by the client and accepted by the backend
+    pub fn drives(&self) -> impl Iterator<Item = &RedirectedDrive> {
+        self.drives.values()
+    }
+
+    /// The IDs of the smart card devices redirected by the client and accepted by the handler
+    pub fn smartcard_devices(&self) -> impl Iterator<Item = u32> + '_ {
+        self.smartcards.iter().copied()
+    }
+
+    /// The printers redirected by the client and accepted by the handler
+    pub fn printers(&self) -> impl Iterator<Item = &RedirectedPrinter> {
+        self.printers.values()
+    }
+
+    /// Sends a file system request to a redirected drive
+    ///
+    /// The outcome is given to [`FileSystemBackend::io_completed`], immediately when the request is denied by the
+    /// [`DrivePolicy`].
+    pub fn drive_request(
+        &mut self,
+        device_id: u32,
+        completion_id: u32,
+        request: DriveRequest,
+    ) -> PduResult<RdpdrSvcMessages> {
+        if self.state != RdpdrState::Ready {
+            return Err(pdu_other_err!("invalid state, RDPDR initialization not done"));
+        }
+
+        if !self.drives.contains_key(&device_id) {
+            return Err(pdu_other_err!("unknown drive"));
+        }
+
+        if self.pending.contains_key(&completion_id) {
+            return Err(pdu_other_err!("completion ID already in use"));
+        }
+
+        if let Some(policy) = &self.drive_policy {
+            if !policy.allow_request(device_id, &request) {
+                self.backend
+                    .io_completed(device_id, completion_id, Err(NtStatus::ACCESS_DENIED));
+                return Ok(RdpdrSvcMessages::new(Vec::new()));
+            }
+        }
+
+        let major_function = match &request {
+            DriveRequest::Create { .. } => MajorFunction::Create,
+            DriveRequest::Read { .. } => MajorFunction::Read,
+            DriveRequest::Write { .. } => MajorFunction::Write,
+            DriveRequest::QueryDirectory { .. } => MajorFunction::DirectoryControl,
+            DriveRequest::Close { .. } => MajorFunction::Close,
+        };
+
+        let header = |file_id, minor_function| DeviceIoRequest {
+            device_id,
+            file_id,
+            completion_id,
+            major_function,
+            minor_function,
+        };
+
+        let request = match request {
+            DriveRequest::Create {
+                path,
+                desired_access,
+                create_disposition,
+                create_options,
+            } => ServerDriveIoRequest::from(DeviceCreateRequest {
+                device_io_request: header(0, MinorFunction::from(0)),
+                desired_access,
+                allocation_size: 0,
+                file_attributes: FileAttributes::empty(),
+                shared_access: SharedAccess::FILE_SHARE_READ | SharedAccess::FILE_SHARE_WRITE,
+                create_disposition,
+                create_options,
+                path,
+            }),
+            DriveRequest::Read {
+                file_id,
+                offset,
+                length,
+            } => ServerDriveIoRequest::from(DeviceReadRequest {
+                device_io_request: header(file_id, MinorFunction::from(0)),
+                length,
+                offset,
+            }),
+            DriveRequest::Write { file_id, offset, data } => ServerDriveIoRequest::from(DeviceWriteRequest {
+                device_io_request: header(file_id, MinorFunction::from(0)),
+                offset,
+                write_data: data,
+            }),
+            DriveRequest::QueryDirectory {
+                file_id,
+                initial_query,
+                pattern,
+            } => ServerDriveIoRequest::from(ServerDriveQueryDirectoryRequest {
+                device_io_request: header(file_id, MinorFunction::IRP_MN_QUERY_DIRECTORY),
+                file_info_class_lvl: FileInformationClassLevel::FILE_DIRECTORY_INFORMATION,
+                initial_query: u8::from(initial_query),
+                path: if initial_query { pattern } else { String::new() },
+            }),
+            DriveRequest::Close { file_id } => ServerDriveIoRequest::from(DeviceCloseRequest {
+                device_io_request: header(file_id, MinorFunction::from(0)),
+            }),
+        };
+
+        self.pending.insert(
+            completion_id,
+            PendingRequest::Drive {
+                device_id,
+                major_function,
+            },
+        );
+
+        let pdu = RdpdrPdu::ServerDriveIoRequest(request);
+        debug!(?pdu, "Sending drive request");
+
+        Ok(RdpdrSvcMessages::new(vec![SvcMessage::from(pdu)]))
+    }
+
+    /// Sends a call to a redirected smart card device
+    ///
+    /// `io_control_code` must match the call, see [`ScardCall::matches_io_ctl_code`]. The outcome is given to
+    /// [`SmartCardHandler::call_completed`].
+    pub fn smartcard_call(
+        &mut self,
+        device_id: u32,
+        completion_id: u32,
+        io_control_code: ScardIoCtlCode,
+        call: ScardCall,
+    ) -> PduResult<RdpdrSvcMessages> {
+        if self.state != RdpdrState::Ready {
+            return Err(pdu_other_err!("invalid state, RDPDR initialization not done"));
+        }
+
+        if !self.smartcards.contains(&device_id) {
+            return Err(pdu_other_err!("unknown smart card device"));
+        }
+
+        if self.pending.contains_key(&completion_id) {
+            return Err(pdu_other_err!("completion ID already in use"));
+        }
+
+        if !call.matches_io_ctl_code(io_control_code) {
+            return Err(pdu_other_err!("smart card call doesn't match the IOCTL code"));
+        }
+
+        let call = call
+            .into_pdu()
+            .ok_or_else(|| pdu_other_err!("unsupported smart card call"))?;
+
+        let request = ScardControlRequest {
+            header: DeviceIoRequest {
+                device_id,
+                file_id: 0,
+                completion_id,
+                major_function: MajorFunction::DeviceControl,
+                minor_function: MinorFunction::from(0),
+            },
+            output_buffer_length: SCARD_OUTPUT_BUFFER_LENGTH,
+            io_control_code,
+            call,
+        };
+
+        self.pending.insert(
+            completion_id,
+            PendingRequest::Smartcard {
+                device_id,
+                io_control_code,
+            },
+        );
+
+        let pdu = RdpdrPdu::ScardControlRequest(request);
+        debug!(?pdu, "Sending smart card call");
+
+        Ok(RdpdrSvcMessages::new(vec![SvcMessage::from(pdu)]))
+    }
+
+    /// Sends a print job to a redirected printer
+    ///
+    /// The job is written in several requests, its outcome is given to [`PrinterHandler::job_completed`].
+    pub fn print_job(&mut self, device_id: u32, completion_id: u32, data: Vec<u8>) -> PduResult<RdpdrSvcMessages> {
+        if self.state != RdpdrState::Ready {
+            return Err(pdu_other_err!("invalid state, RDPDR initialization not done"));
+        }
+
+        if !self.printers.contains_key(&device_id) {
+            return Err(pdu_other_err!("unknown printer"));
+        }
+
+        if self.pending.contains_key(&completion_id) {
+            return Err(pdu_other_err!("completion ID already in use"));
+        }
+
+        let request = ServerDriveIoRequest::from(DeviceCreateRequest {
+            device_io_request: DeviceIoRequest {
+                device_id,
+                file_id: 0,
+                completion_id,
+                major_function: MajorFunction::Create,
+                minor_function: MinorFunction::from(0),
+            },
+            desired_access: DesiredAccess::GENERIC_WRITE,
+            allocation_size: 0,
+            file_attributes: FileAttributes::empty(),
+            shared_access: SharedAccess::empty(),
+            create_disposition: CreateDisposition::FILE_OVERWRITE_IF,
+            create_options: CreateOptions::empty(),
+            path: String::new(),
+        });
+
+        self.pending.insert(
+            completion_id,
+            PendingRequest::PrintJob {
+                device_id,
+                major_function: MajorFunction::Create,
+                job: PrintJob {
+                    data,
+                    written: 0,
+                    file_id: 0,
+                    status: NtStatus::SUCCESS,
+                },
+            },
+        );
+
+        let pdu = RdpdrPdu::ServerDriveIoRequest(request);
+        debug!(?pdu, "Sending print job");
+
+        Ok(RdpdrSvcMessages::new(vec![SvcMessage::from(pdu)]))
+    }
+
+    /// Sends the next request of a print job, writing its remaining data or closing it
+    fn continue_print_job(&mut self, device_id: u32, completion_id: u32, job: PrintJob) -> SvcMessage {
+        let header = |major_function| DeviceIoRequest {
+            device_id,
+            file_id: job.file_id,
+            completion_id,
+            major_function,
+            minor_function: MinorFunction::from(0),
+        };
+
+        let remaining = job.data.get(job.written..).unwrap_or_default();
+        let (major_function, request) = if remaining.is_empty() {
+            (
+                MajorFunction::Close,
+                ServerDriveIoRequest::from(DeviceCloseRequest {
+                    device_io_request: header(MajorFunction::Close),
+                }),
+            )
+        } else {
+            let chunk = &remaining[..remaining.len().min(PRINT_JOB_CHUNK_SIZE)];
+            (
+                MajorFunction::Write,
+                ServerDriveIoRequest::from(DeviceWriteRequest {
+                    device_io_request: header(MajorFunction::Write),
+                    offset: u64::try_from(job.written).unwrap_or(u64::MAX),
+                    write_data: chunk.to_vec(),
+                }),
+            )
+        };
+
+        self.pending.insert(
+            completion_id,
+            PendingRequest::PrintJob {
+                device_id,
+                major_function,
+                job,
+            },
+        );
+
+        SvcMessage::from(RdpdrPdu::ServerDriveIoRequest(request))
+    }
+
+    fn handle_print_job_completion(
+        &mut self,
+        reply: DeviceIoResponse,
+        src: &mut ReadCursor<'_>,
+        device_id: u32,
+        major_function: MajorFunction,
+        mut job: PrintJob,
+    ) -> PduResult<Vec<SvcMessage>> {
+        let completion_id = reply.completion_id;
+        let io_status = reply.io_status;
+
+        match major_function {
+            MajorFunction::Create => {
+                if io_status != NtStatus::SUCCESS {
+                    self.complete_print_job(device_id, completion_id, Err(io_status));
+                    return Ok(Vec::new());
+                }
+
+                let response = DeviceCreateResponse::decode(reply, src).map_err(|e| decode_err!(e))?;
+                job.file_id = response.file_id;
+            }
+            MajorFunction::Write => {
+                let length = if io_status == NtStatus::SUCCESS {
+                    let response = DeviceWriteResponse::decode(reply, src).map_err(|e| decode_err!(e))?;
+                    usize::try_from(response.length).unwrap_or(usize::MAX)
+                } else {
+                    0
+                };
+
+                if length == 0 {
+                    // Nothing is written on failure, the job is closed without writing the rest.
+                    job.status = if io_status == NtStatus::SUCCESS {
+                        NtStatus::UNSUCCESSFUL
+                    } else {
+                        io_status
+                    };
+                    job.written = job.data.len();
+                } else {
+                    job.written = job.written.saturating_add(length).min(job.data.len());
+                }
+            }
+            _ => {
+                DeviceCloseResponse::decode(reply, src);
+
+                let result = if job.status != NtStatus::SUCCESS {
+                    Err(job.status)
+                } else if io_status != NtStatus::SUCCESS {
+                    Err(io_status)
+                } else {
+                    Ok(())
+                };
+                self.complete_print_job(device_id, completion_id, result);
+                return Ok(Vec::new());
+            }
+        }
+
+        Ok(vec![self.continue_print_job(device_id, completion_id, job)])
+    }
+
+    fn complete_print_job(&mut self, device_id: u32, completion_id: u32, result: Result<(), NtStatus>) {
+        if let Some(handler) = self.printer.as_mut() {
+            handler.job_completed(device_id, completion_id, result);
+        }
+    }
+
+    fn handle_announce_reply(&mut self, reply: VersionAndIdPdu) -> Vec<SvcMessage> {
+        debug!(?reply, "RDPDR client announce reply");
+        self.announce_reply = Some(reply);
+        self.state = RdpdrState::WaitingForClientName;
+
+        Vec::new()
+    }
+
+    fn handle_client_name(&mut self, computer_name: String) -> PduResult<Vec<SvcMessage>> {
+        self.client_name = Some(computer_name);
+
+        if self.state != RdpdrState::WaitingForClientName {
+            return Ok(Vec::new());
+        }
+
+        let reply = self
+            .announce_reply
+            .as_ref()
+            .ok_or_else(|| pdu_other_err!("missing client announce reply"))?;
+        let client_id_confirm = VersionAndIdPdu::new_server_client_id_confirm(reply).map_err(|e| decode_err!(e))?;
+
+        let mut capabilities = Capabilities::new();
+        capabilities.add_drive();
+        if self.smartcard.is_some() {
+            capabilities.add_smartcard();
+        }
+        if self.printer.is_some() {
+            capabilities.add_printer();
+        }
+
+        self.state = RdpdrState::WaitingForCapabilities;
+
+        Ok(vec![
+            SvcMessage::from(RdpdrPdu::CoreCapability(CoreCapability::new_request(
+                capabilities.clone_inner(),
+            ))),
+            SvcMessage::from(RdpdrPdu::VersionAndIdPdu(client_id_confirm)),
+        ])
+    }
+
+    fn handle_client_capabilities(&mut self, capabilities: CoreCapability) -> Vec<SvcMessage> {
+        self.drive_supported = capabilities.capabilities.iter().any(|capability| capability.is_drive());
+        if !self.drive_supported {
+            warn!("RDPDR client doesn't support drive redirection");
+        }
+
+        if self.smartcard.is_some() {
+            self.smartcard_supported = capabilities
+                .capabilities
+                .iter()
+                .any(|capability| capability.is_smartcard());
+            if !self.smartcard_supported {
+                warn!("RDPDR client doesn't support smart card redirection");
+            }
+        }
+
+        if self.printer.is_some() {
+            self.printer_supported = capabilities
+                .capabilities
+                .iter()
+                .any(|capability| capability.is_printer());
+            if !self.printer_supported {
+                warn!("RDPDR client doesn't support printer redirection");
+            }
+        }
+
+        self.state = RdpdrState::Ready;
+
+        // Clients announce the drives once the user is logged on.
+        vec![SvcMessage::from(RdpdrPdu::UserLoggedon)]
+    }
+
+    fn handle_device_list_announce(&mut self, announce: ClientDeviceListAnnounce) -> Vec<SvcMessage> {
+        announce
+            .device_list
+            .into_iter()
+            .map(|device| {
+                let device_id = device.device_id();
+
+                let result_code = match device.device_type() {
+                    DeviceType::Filesystem if self.drive_supported => self.announce_drive(&device),
+                    DeviceType::Smartcard if self.smartcard_supported => self.announce_smartcard(device_id),
+                    DeviceType::Print if self.printer_supported => self.announce_printer(&device),
+                    device_type => {
+                        debug!(device_id, ?device_type, "Rejected RDPDR device");
+                        NtStatus::NOT_SUPPORTED
+                    }
+                };
+
+                SvcMessage::from(RdpdrPdu::ServerDeviceAnnounceResponse(ServerDeviceAnnounceResponse {
+                    device_id,
+                    result_code,
+                }))
+            })
+            .collect()
+    }
+
+    fn announce_drive(&mut self, device: &DeviceAnnounceHeader) -> NtStatus {
+        let drive = RedirectedDrive {
+            device_id: device.device_id(),
+            name: device.display_name(),
+        };
+
+        let allowed = self
+            .drive_policy
+            .as_ref()
+            .is_none_or(|policy| policy.allow_drive(&drive));
+
+        if allowed && self.backend.drive_announced(&drive) {
+            debug!(?drive, "Redirected drive");
+            self.drives.insert(drive.device_id, drive);
+            NtStatus::SUCCESS
+        } else {
+            NtStatus::ACCESS_DENIED
+        }
+    }
+
+    fn announce_smartcard(&mut self, device_id: u32) -> NtStatus {
+        let Some(handler) = self.smartcard.as_mut() else {
+            return NtStatus::NOT_SUPPORTED;
+        };
+
+        if handler.device_announced(device_id) {
+            debug!(device_id, "Redirected smart card device");
+            self.smartcards.insert(device_id);
+            NtStatus::SUCCESS
+        } else {
+            NtStatus::ACCESS_DENIED
+        }
+    }
+
+    fn announce_printer(&mut self, device: &DeviceAnnounceHeader) -> NtStatus {
+        let Some(handler) = self.printer.as_mut() else {
+            return NtStatus::NOT_SUPPORTED;
+        };
+
+        let announce = match PrinterDeviceAnnounce::decode(&mut ReadCursor::new(device.device_data())) {
+            Ok(announce) => announce,
+            Err(error) => {
+                warn!(device_id = device.device_id(), %error, "Invalid printer announce");
+                return NtStatus::UNSUCCESSFUL;
+            }
+        };
+
+        let printer = RedirectedPrinter {
+            device_id: device.device_id(),
+            name: announce.print_name,
+            driver_name: announce.driver_name,
+            flags: announce.flags,
+        };
+
+        if handler.printer_announced(&printer) {
+            debug!(?printer, "Redirected printer");
+            self.printers.insert(printer.device_id, printer);
+            NtStatus::SUCCESS
+        } else {
+            NtStatus::ACCESS_DENIED
+        }
+    }
+
+    fn handle_device_list_remove(&mut self, remove: ClientDeviceListRemove) {
+        for device_id in remove.device_list {
+            let is_drive = self.drives.remove(&device_id).is_some();
+            let is_smartcard = self.smartcards.remove(&device_id);
+            let is_printer = self.printers.remove(&device_id).is_some();
+            if !is_drive && !is_smartcard && !is_printer {
+                continue;
+            }
+
+            let pending: Vec<u32> = self
+                .pending
+                .iter()
+                .filter(|(_, request)| request.device_id() == device_id)
+                .map(|(completion_id, _)| *completion_id)
+                .collect();
+
+            for completion_id in pending {
+                if let Some(request) = self.pending.remove(&completion_id) {
+                    self.complete(request, completion_id, NtStatus::UNSUCCESSFUL);
+                }
+            }
+
+            if is_drive {
+                debug!(device_id, "Removed drive");
+                self.backend.drive_removed(device_id);
+            }
+
+            if let Some(handler) = self.smartcard.as_mut().filter(|_| is_smartcard) {
+                debug!(device_id, "Removed smart card device");
+                handler.device_removed(device_id);
+            }
+
+            if let Some(handler) = self.printer.as_mut().filter(|_| is_printer) {
+                debug!(device_id, "Removed printer");
+                handler.printer_removed(device_id);
+            }
+        }
+    }
+
+    /// Completes a request that failed before its response was decoded
+    fn complete(&mut self, request: PendingRequest, completion_id: u32, status: NtStatus) {
+        match request {
+            PendingRequest::Drive { device_id, .. } => self.backend.io_completed(device_id, completion_id, Err(status)),
+            PendingRequest::Smartcard {
+                device_id,
+                io_control_code,
+            } => {
+                if let Some(handler) = self.smartcard.as_mut() {
+                    handler.call_completed(device_id, completion_id, io_control_code, Err(status));
+                }
+            }
+            PendingRequest::PrintJob { device_id, .. } => {
+                if let Some(handler) = self.printer.as_mut() {
+                    handler.job_completed(device_id, completion_id, Err(status));
+                }
+            }
+        }
+    }
+
+    fn handle_io_completion(
+        &mut self,
+        reply: DeviceIoResponse,
+        src: &mut ReadCursor<'_>,
+    ) -> PduResult<Vec<SvcMessage>> {
+        let completion_id = reply.completion_id;
+
+        let Some(request) = self.pending.remove(&completion_id) else {
+            warn!(?reply, "Unexpected RDPDR I/O completion");
+            return Ok(Vec::new());
+        };
+
+        if request.device_id() != reply.device_id {
+            warn!(
+                ?reply,
+                expected_device_id = request.device_id(),
+                "Invalid device of I/O completion"
+            );
+        }
+
+        let (device_id, major_function) = match request {
+            // Print jobs are closed after a failed write, their failures are handled with their completion.
+            PendingRequest::PrintJob {
+                device_id,
+                major_function,
+                job,
+            } => return self.handle_print_job_completion(reply, src, device_id, major_function, job),
+            request if reply.io_status != NtStatus::SUCCESS => {
+                self.complete(request, completion_id, reply.io_status);
+                return Ok(Vec::new());
+            }
+            PendingRequest::Drive {
+                device_id,
+                major_function,
+            } => (device_id, major_function),
+            PendingRequest::Smartcard {
+                device_id,
+                io_control_code,
+            } => {
+                let result = decode_scard_return(io_control_code, src).map_err(|e| decode_err!(e))?;
+                if let Some(handler) = self.smartcard.as_mut() {
+                    handler.call_completed(device_id, completion_id, io_control_code, Ok(result));
+                }
+                return Ok(Vec::new());
+            }
+        };
+
+        let result = match major_function {
+            MajorFunction::Create => {
+                let response = DeviceCreateResponse::decode(reply, src).map_err(|e| decode_err!(e))?;
+                Ok(DriveResponse::Create {
+                    file_id: response.file_id,
+                    information: response.information,
+                })
+            }
+            MajorFunction::Read => {
+                let response = DeviceReadResponse::decode(reply, src).map_err(|e| decode_err!(e))?;
+                Ok(DriveResponse::Read(response.read_data))
+            }
+            MajorFunction::Write => {
+                let response = DeviceWriteResponse::decode(reply, src).map_err(|e| decode_err!(e))?;
+                Ok(DriveResponse::Write(response.length))
+            }
+            MajorFunction::DirectoryControl => {
+                let response = ClientDriveQueryDirectoryResponse::decode(
+                    reply,
+                    FileInformationClassLevel::FILE_DIRECTORY_INFORMATION,
+                    src,
+                )
+                .map_err(|e| decode_err!(e))?;
+
+                match response.buffer {
+                    Some(FileInformationClass::Directory(information)) => {
+                        Ok(DriveResponse::QueryDirectory(information))
+                    }
+                    // An empty successful response ends the enumeration like STATUS_NO_MORE_FILES.
+                    _ => Err(NtStatus::NO_MORE_FILES),
+                }
+            }
+            _ => {
+                DeviceCloseResponse::decode(reply, src);
+                Ok(DriveResponse::Close)
+            }
+        };
+
+        self.backend.io_completed(device_id, completion_id, result);
+
+        Ok(Vec::new())
+    }
+}
+
+/// Decodes the output buffer of a [Device Control Response], the return of a smart card call
+///
+/// [Device Control Response]: crate::pdu::efs::DeviceControlResponse
+fn decode_scard_return(io_control_code: ScardIoCtlCode, src: &mut ReadCursor<'_>) -> DecodeResult<ScardReturn> {
+    ensure_size!(ctx: "DeviceControlResponse", in: src, size: 4);
+    let output_buffer_length = cast_length!("DeviceControlResponse", "OutputBufferLength", src.read_u32())?;
+
+    ensure_size!(ctx: "DeviceControlResponse", in: src, size: output_buffer_length);
+    let mut output_buffer = ReadCursor::new(src.read_slice(output_buffer_length));
+
+    ScardReturn::decode(io_control_code, &mut output_buffer)
+}
+
+impl_as_any!(RdpdrServer);
+
+impl SvcProcessor for RdpdrServer {
+    fn channel_name(&self) -> ChannelName {
+        Self::NAME
+    }
+
+    fn compression_condition(&self) -> CompressionCondition {
+        CompressionCondition::WhenRdpDataIsCompressed
+    }
+
+    fn start(&mut self) -> PduResult<Vec<SvcMessage>> {
+        if self.state != RdpdrState::Start {
+            warn!("Attempted to start RDPDR channel in invalid state");
+        }
+
+        let pdu = RdpdrPdu::VersionAndIdPdu(VersionAndIdPdu::new_server_announce_request(SERVER_CLIENT_ID));
+
+        self.state = RdpdrState::WaitingForAnnounceReply;
+        Ok(vec![SvcMessage::from(pdu)])
+    }
+
+    fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        let mut src = ReadCursor::new(payload);
+        let pdu = RdpdrPdu::decode_client(&mut src).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        let messages = match pdu {
+            RdpdrPdu::VersionAndIdPdu(reply)
+                if reply.kind == VersionAndIdPduKind::ClientAnnounceReply
+                    && self.state == RdpdrState::WaitingForAnnounceReply =>
+            {
+                self.handle_announce_reply(reply)
+            }
+            RdpdrPdu::ClientNameRequest(request) => self.handle_client_name(request.computer_name().to_owned())?,
+            RdpdrPdu::CoreCapability(capabilities)
+                if capabilities.kind == CoreCapabilityKind::ClientCoreCapabilityResponse
+                    && self.state == RdpdrState::WaitingForCapabilities =>
+            {
+                self.handle_client_capabilities(capabilities)
+            }
+            RdpdrPdu::ClientDeviceListAnnounce(announce) => self.handle_device_list_announce(announce),
+            RdpdrPdu::ClientDeviceListRemove(remove) => {
+                self.handle_device_list_remove(remove);
+                Vec::new()
+            }
+            RdpdrPdu::DeviceIoResponse(reply) => self.handle_io_completion(reply, &mut src)?,
+            pdu => {
+                warn!(?pdu, state = ?self.state, "Unexpected RDPDR PDU");
+                Vec::new()
+            }
+        };
+
+        Ok(messages)
+    }
+}
+
+impl SvcServerProcessor for RdpdrServer {}
@@ -0,0 +1,391 @@
+use std::collections::BTreeSet;
+
+use ironrdp_core::{decode, impl_as_any};
+use ironrdp_dvc::{encode_dvc_messages, DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tracing::{debug, warn};
+
+use crate::pdu::{
+    CameraMessage, CameraPdu, DeviceAddedNotification, DeviceRemovedNotification, ErrorCode, MediaTypeDescription,
+    MediaTypeListResponse, PropertyDescription, PropertyListResponse, PropertyRequest, PropertyValue,
+    SampleErrorResponse, SampleResponse, StartStreamInfo, StreamDescription, StreamListResponse, VERSION_2,
+};
+use crate::ENUMERATOR_CHANNEL_NAME;
+
+/// Camera of the client, redirected to the server
+///
+/// The failing requests are answered to the server with the returned error code.
+pub trait CameraDevice: Send + core::fmt::Debug {
+    /// The server started using the camera
+    fn activate(&mut self) -> Result<(), ErrorCode>;
+
+    /// The server released the camera
+    fn deactivate(&mut self) -> Result<(), ErrorCode>;
+
+    /// Streams of the camera, the index of a stream is its position in the list
+    fn streams(&self) -> Vec<StreamDescription>;
+
+    /// Media types supported by a stream, by order of preference
+    fn media_types(&self, stream_index: u8) -> Result<Vec<MediaTypeDescription>, ErrorCode>;
+
+    /// Media type of a stream
+    fn current_media_type(&self, stream_index: u8) -> Result<MediaTypeDescription, ErrorCode>;
+
+    /// The server requested to capture the streams, in the given media types
+    fn start_streams(&mut self, streams: &[StartStreamInfo]) -> Result<(), ErrorCode>;
+
+    /// The server requested to stop capturing all the streams
+    fn stop_streams(&mut self) -> Result<(), ErrorCode>;
+
+    /// The server requested the next sample of a stream
+    ///
+    /// Once captured, the sample is sent with [`CameraDeviceClient::encode_sample`], or the failure with
+    /// [`CameraDeviceClient::encode_sample_error`].
+    fn request_sample(&mut self, stream_index: u8);
+
+    /// Properties of the camera, such as its brightness or its focus
+    fn properties(&self) -> Vec<PropertyDescription> {
+        Vec::new()
+    }
+
+    fn property_value(&self, property: PropertyRequest) -> Result<PropertyValue, ErrorCode> {
+        debug!(?property, "Unknown camera property");
+        Err(ErrorCode::ITEM_NOT_FOUND)
+    }
+
+    fn set_property_value(&mut self, property: PropertyRequest, value: PropertyValue) -> Result<(), ErrorCode> {
+        debug!(?property, ?value, "Unsupported camera property change");
+        Err(ErrorCode::OPERATION_NOT_SUPPORTED)
+    }
+
+    /// The channel of the camera was closed, the capture must stop
+    fn closed(&mut self) {}
+}
+
+/// Camera announced to the server on the enumeration channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CameraInfo {
+    /// Friendly name of the camera
+    pub device_name: String,
+    /// Name of the dynamic channel of the camera, handled by a [`CameraDeviceClient`]
+    pub channel_name: String,
+}
+
+/// A client for the device enumeration channel of the Video Capture Virtual Channel
+///
+/// The cameras are announced to the server once the version of the protocol is negotiated. Each camera must have its
+/// [`CameraDeviceClient`] registered with the same channel name.
+#[derive(Debug)]
+pub struct CameraEnumeratorClient {
+    cameras: Vec<CameraInfo>,
+    channel_id: Option<u32>,
+    /// Version selected by the server, set once negotiated
+    version: Option<u8>,
+}
+
+impl CameraEnumeratorClient {
+    pub fn new(cameras: Vec<CameraInfo>) -> Self {
+        Self {
+            cameras,
+            channel_id: None,
+            version: None,
+        }
+    }
+
+    pub fn cameras(&self) -> &[CameraInfo] {
+        &self.cameras
+    }
+
+    /// Version of the protocol selected by the server
+    pub fn version(&self) -> Option<u8> {
+        self.version
+    }
+
+    /// Announces a plugged camera, the messages are empty until the version is negotiated
+    pub fn add_camera(&mut self, camera: CameraInfo) -> PduResult<Vec<SvcMessage>> {
+        if self
+            .cameras
+            .iter()
+            .any(|known| known.channel_name == camera.channel_name)
+        {
+            return Err(pdu_other_err!("camera channel already announced"));
+        }
+
+        let message = CameraMessage::DeviceAdded(DeviceAddedNotification {
+            device_name: camera.device_name.clone(),
+            virtual_channel_name: camera.channel_name.clone(),
+        });
+        self.cameras.push(camera);
+
+        self.encode(message)
+    }
+
+    /// Announces an unplugged camera
+    pub fn remove_camera(&mut self, channel_name: &str) -> PduResult<Vec<SvcMessage>> {
+        let Some(index) = self
+            .cameras
+            .iter()
+            .position(|camera| camera.channel_name == channel_name)
+        else {
+            return Err(pdu_other_err!("unknown camera channel"));
+        };
+        self.cameras.remove(index);
+
+        self.encode(CameraMessage::DeviceRemoved(DeviceRemovedNotification {
+            virtual_channel_name: channel_name.to_owned(),
+        }))
+    }
+
+    fn encode(&self, message: CameraMessage) -> PduResult<Vec<SvcMessage>> {
+        let (Some(channel_id), Some(version)) = (self.channel_id, self.version) else {
+            return Ok(Vec::new());
+        };
+
+        encode_dvc_messages(
+            channel_id,
+            vec![Box::new(CameraPdu::new(version, message))],
+            ChannelFlags::empty(),
+        )
+        .map_err(|e| encode_err!(e))
+    }
+}
+
+impl_as_any!(CameraEnumeratorClient);
+
+impl DvcProcessor for CameraEnumeratorClient {
+    fn channel_name(&self) -> &str {
+        ENUMERATOR_CHANNEL_NAME
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.channel_id = Some(channel_id);
+        self.version = None;
+
+        Ok(vec![Box::new(CameraPdu::new(
+            VERSION_2,
+            CameraMessage::SelectVersionRequest,
+        ))])
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: CameraPdu = decode(payload).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        let messages: Vec<DvcMessage> = match pdu.message {
+            CameraMessage::SelectVersionResponse => {
+                let version = pdu.version.min(VERSION_2);
+                self.version = Some(version);
+
+                self.cameras
+                    .iter()
+                    .map(|camera| -> DvcMessage {
+                        Box::new(CameraPdu::new(
+                            version,
+                            CameraMessage::DeviceAdded(DeviceAddedNotification {
+                                device_name: camera.device_name.clone(),
+                                virtual_channel_name: camera.channel_name.clone(),
+                            }),
+                        ))
+                    })
+                    .collect()
+            }
+            message => {
+                warn!(?message, "Unexpected camera enumeration PDU");
+                Vec::new()
+            }
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        self.channel_id = None;
+        self.version = None;
+    }
+}
+
+impl DvcClientProcessor for CameraEnumeratorClient {}
+
+/// A client for the channel of a camera, opened by the server once the camera is announced
+#[derive(Debug)]
+pub struct CameraDeviceClient {
+    channel_name: String,
+    device: Box<dyn CameraDevice>,
+    channel_id: Option<u32>,
+    /// Version used by the server
+    version: u8,
+    activated: bool,
+    streaming: bool,
+    /// Streams for which the server is waiting for a sample
+    pending_samples: BTreeSet<u8>,
+}
+
+impl CameraDeviceClient {
+    /// Creates the client of the camera announced on the channel `channel_name`
+    pub fn new(channel_name: impl Into<String>, device: Box<dyn CameraDevice>) -> Self {
+        Self {
+            channel_name: channel_name.into(),
+            device,
+            channel_id: None,
+            version: VERSION_2,
+            activated: false,
+            streaming: false,
+            pending_samples: BTreeSet::new(),
+        }
+    }
+
+    /// Whether the server is capturing the streams of the camera
+    pub fn is_streaming(&self) -> bool {
+        self.streaming
+    }
+
+    /// Whether the server is waiting for a sample of the stream
+    pub fn is_sample_requested(&self, stream_index: u8) -> bool {
+        self.pending_samples.contains(&stream_index)
+    }
+
+    /// Answers a sample request with the captured sample, in the media type of the stream
+    pub fn encode_sample(&mut self, stream_index: u8, sample: Vec<u8>) -> PduResult<Vec<SvcMessage>> {
+        self.encode_sample_message(
+            stream_index,
+            CameraMessage::SampleResponse(SampleResponse { stream_index, sample }),
+        )
+    }
+
+    /// Answers a sample request with the reason why the capture failed
+    pub fn encode_sample_error(&mut self, stream_index: u8, error_code: ErrorCode) -> PduResult<Vec<SvcMessage>> {
+        self.encode_sample_message(
+            stream_index,
+            CameraMessage::SampleErrorResponse(SampleErrorResponse {
+                stream_index,
+                error_code,
+            }),
+        )
+    }
+
+    fn encode_sample_message(&mut self, stream_index: u8, message: CameraMessage) -> PduResult<Vec<SvcMessage>> {
+        let channel_id = self
+            .channel_id
+            .ok_or_else(|| pdu_other_err!("camera channel not opened"))?;
+
+        if !self.pending_samples.remove(&stream_index) {
+            return Err(pdu_other_err!("invalid state, no sample requested"));
+        }
+
+        encode_dvc_messages(
+            channel_id,
+            vec![Box::new(CameraPdu::new(self.version, message))],
+            ChannelFlags::empty(),
+        )
+        .map_err(|e| encode_err!(e))
+    }
+
+    fn stop(&mut self) {
+        self.streaming = false;
+        self.pending_samples.clear();
+    }
+
+    /// Handles a request of the server, returns the reply if any
+    fn handle(&mut self, message: CameraMessage) -> Option<CameraMessage> {
+        let result = match message {
+            CameraMessage::ActivateDeviceRequest => self.device.activate().map(|()| {
+                self.activated = true;
+                CameraMessage::Success
+            }),
+            CameraMessage::DeactivateDeviceRequest => {
+                self.stop();
+                self.activated = false;
+                self.device.deactivate().map(|()| CameraMessage::Success)
+            }
+            CameraMessage::StreamListRequest => Ok(CameraMessage::StreamListResponse(StreamListResponse {
+                streams: self.device.streams(),
+            })),
+            CameraMessage::MediaTypeListRequest(request) => self
+                .device
+                .media_types(request.stream_index)
+                .map(|media_types| CameraMessage::MediaTypeListResponse(MediaTypeListResponse { media_types })),
+            CameraMessage::CurrentMediaTypeRequest(request) => self
+                .device
+                .current_media_type(request.stream_index)
+                .map(CameraMessage::CurrentMediaTypeResponse),
+            CameraMessage::StartStreamsRequest(_) | CameraMessage::SampleRequest(_) if !self.activated => {
+                Err(ErrorCode::NOT_INITIALIZED)
+            }
+            CameraMessage::StartStreamsRequest(request) => self.device.start_streams(&request.streams).map(|()| {
+                self.streaming = true;
+                CameraMessage::Success
+            }),
+            CameraMessage::StopStreamsRequest => {
+                self.stop();
+                self.device.stop_streams().map(|()| CameraMessage::Success)
+            }
+            CameraMessage::SampleRequest(request) if self.streaming => {
+                // The sample is sent once captured.
+                self.pending_samples.insert(request.stream_index);
+                self.device.request_sample(request.stream_index);
+                return None;
+            }
+            CameraMessage::SampleRequest(request) => Ok(CameraMessage::SampleErrorResponse(SampleErrorResponse {
+                stream_index: request.stream_index,
+                error_code: ErrorCode::NOT_INITIALIZED,
+            })),
+            CameraMessage::PropertyListRequest => Ok(CameraMessage::PropertyListResponse(PropertyListResponse {
+                properties: self.device.properties(),
+            })),
+            CameraMessage::PropertyValueRequest(property) => self
+                .device
+                .property_value(property)
+                .map(CameraMessage::PropertyValueResponse),
+            CameraMessage::SetPropertyValueRequest(request) => self
+                .device
+                .set_property_value(request.property, request.value)
+                .map(|()| CameraMessage::Success),
+            message => {
+                warn!(?message, "Unexpected camera PDU");
+                Err(ErrorCode::INVALID_MESSAGE)
+            }
+        };
+
+        Some(result.unwrap_or_else(CameraMessage::Error))
+    }
+}
+
+impl_as_any!(CameraDeviceClient);
+
+impl DvcProcessor for CameraDeviceClient {
+    fn channel_name(&self) -> &str {
+        &self.channel_name
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.channel_id = Some(channel_id);
+
+        // The server sends the requests.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: CameraPdu = decode(payload).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        self.version = pdu.version;
+
+        let messages: Vec<DvcMessage> = match self.handle(pdu.message) {
+            Some(reply) => vec![Box::new(CameraPdu::new(self.version, reply))],
+            None => Vec::new(),
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        self.channel_id = None;
+        self.stop();
+        if core::mem::take(&mut self.activated) {
+            self.device.closed();
+        }
+    }
+}
+
+impl DvcClientProcessor for CameraDeviceClient {}
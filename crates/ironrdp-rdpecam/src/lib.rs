@@ -0,0 +1,9 @@
+#![cfg_attr(doc, doc = include_str!("../README.md"))]
+#![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
+
+/// Name of the channel announcing the cameras of the client
+pub const ENUMERATOR_CHANNEL_NAME: &str = "RDCamera_Device_Enumerator";
+
+pub mod client;
+pub mod pdu;
+pub mod server;
@@ -0,0 +1,1045 @@
+//! Video Capture Virtual Channel Extension PDUs [MS-RDPECAM][1] implementation.
+//!
+//! Every message starts with a header giving the version of the protocol and the message identifier. The messages
+//! of the enumeration channel and of the device channels share the same identifiers, they are all decoded as a
+//! [`CameraPdu`].
+//!
+//! [1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpecam/
+
+use ironrdp_core::{
+    ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor,
+    WriteCursor,
+};
+use ironrdp_dvc::DvcEncode;
+use ironrdp_pdu::utils::{encoded_str_len, read_string_from_cursor, write_string_to_cursor, CharacterSet};
+
+/// First version of the protocol
+pub const VERSION_1: u8 = 1;
+/// Version adding the camera properties
+pub const VERSION_2: u8 = 2;
+
+const SUCCESS_RESPONSE: u8 = 0x01;
+const ERROR_RESPONSE: u8 = 0x02;
+const SELECT_VERSION_REQUEST: u8 = 0x03;
+const SELECT_VERSION_RESPONSE: u8 = 0x04;
+const DEVICE_ADDED_NOTIFICATION: u8 = 0x05;
+const DEVICE_REMOVED_NOTIFICATION: u8 = 0x06;
+const ACTIVATE_DEVICE_REQUEST: u8 = 0x07;
+const DEACTIVATE_DEVICE_REQUEST: u8 = 0x08;
+const STREAM_LIST_REQUEST: u8 = 0x09;
+const STREAM_LIST_RESPONSE: u8 = 0x0A;
+const MEDIA_TYPE_LIST_REQUEST: u8 = 0x0B;
+const MEDIA_TYPE_LIST_RESPONSE: u8 = 0x0C;
+const CURRENT_MEDIA_TYPE_REQUEST: u8 = 0x0D;
+const CURRENT_MEDIA_TYPE_RESPONSE: u8 = 0x0E;
+const START_STREAMS_REQUEST: u8 = 0x0F;
+const STOP_STREAMS_REQUEST: u8 = 0x10;
+const SAMPLE_REQUEST: u8 = 0x11;
+const SAMPLE_RESPONSE: u8 = 0x12;
+const SAMPLE_ERROR_RESPONSE: u8 = 0x13;
+const PROPERTY_LIST_REQUEST: u8 = 0x14;
+const PROPERTY_LIST_RESPONSE: u8 = 0x15;
+const PROPERTY_VALUE_REQUEST: u8 = 0x16;
+const PROPERTY_VALUE_RESPONSE: u8 = 0x17;
+const SET_PROPERTY_VALUE_REQUEST: u8 = 0x18;
+
+/// Video capture channel message, sent by both the client and the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CameraPdu {
+    /// [`VERSION_1`] or [`VERSION_2`], the version selected on the enumeration channel once negotiated
+    pub version: u8,
+    pub message: CameraMessage,
+}
+
+/// Video capture channel message, without its header
+///
+/// The direction of the messages is given in their documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CameraMessage {
+    /// Client reply to a request without a specific response
+    Success,
+    /// Client reply to a request which failed
+    Error(ErrorCode),
+    /// Client request, on the enumeration channel, to use the version of the header
+    SelectVersionRequest,
+    /// Server reply to [`CameraMessage::SelectVersionRequest`], with the selected version in the header
+    SelectVersionResponse,
+    /// Client notification, on the enumeration channel, of a camera
+    DeviceAdded(DeviceAddedNotification),
+    /// Client notification, on the enumeration channel, of an unplugged camera
+    DeviceRemoved(DeviceRemovedNotification),
+    /// Server request to use the camera
+    ActivateDeviceRequest,
+    /// Server request to release the camera
+    DeactivateDeviceRequest,
+    StreamListRequest,
+    StreamListResponse(StreamListResponse),
+    MediaTypeListRequest(StreamRequest),
+    MediaTypeListResponse(MediaTypeListResponse),
+    CurrentMediaTypeRequest(StreamRequest),
+    CurrentMediaTypeResponse(MediaTypeDescription),
+    StartStreamsRequest(StartStreamsRequest),
+    StopStreamsRequest,
+    /// Server request for the next sample of a stream
+    SampleRequest(StreamRequest),
+    SampleResponse(SampleResponse),
+    SampleErrorResponse(SampleErrorResponse),
+    /// Server request for the properties of the camera, since [`VERSION_2`]
+    PropertyListRequest,
+    PropertyListResponse(PropertyListResponse),
+    PropertyValueRequest(PropertyRequest),
+    PropertyValueResponse(PropertyValue),
+    SetPropertyValueRequest(SetPropertyValueRequest),
+}
+
+impl CameraPdu {
+    const NAME: &'static str = "RDPECAM_PDU";
+
+    const FIXED_PART_SIZE: usize = 1 /* Version */ + 1 /* MessageId */;
+
+    pub fn new(version: u8, message: CameraMessage) -> Self {
+        Self { version, message }
+    }
+
+    fn message_id(&self) -> u8 {
+        match &self.message {
+            CameraMessage::Success => SUCCESS_RESPONSE,
+            CameraMessage::Error(_) => ERROR_RESPONSE,
+            CameraMessage::SelectVersionRequest => SELECT_VERSION_REQUEST,
+            CameraMessage::SelectVersionResponse => SELECT_VERSION_RESPONSE,
+            CameraMessage::DeviceAdded(_) => DEVICE_ADDED_NOTIFICATION,
+            CameraMessage::DeviceRemoved(_) => DEVICE_REMOVED_NOTIFICATION,
+            CameraMessage::ActivateDeviceRequest => ACTIVATE_DEVICE_REQUEST,
+            CameraMessage::DeactivateDeviceRequest => DEACTIVATE_DEVICE_REQUEST,
+            CameraMessage::StreamListRequest => STREAM_LIST_REQUEST,
+            CameraMessage::StreamListResponse(_) => STREAM_LIST_RESPONSE,
+            CameraMessage::MediaTypeListRequest(_) => MEDIA_TYPE_LIST_REQUEST,
+            CameraMessage::MediaTypeListResponse(_) => MEDIA_TYPE_LIST_RESPONSE,
+            CameraMessage::CurrentMediaTypeRequest(_) => CURRENT_MEDIA_TYPE_REQUEST,
+            CameraMessage::CurrentMediaTypeResponse(_) => CURRENT_MEDIA_TYPE_RESPONSE,
+            CameraMessage::StartStreamsRequest(_) => START_STREAMS_REQUEST,
+            CameraMessage::StopStreamsRequest => STOP_STREAMS_REQUEST,
+            CameraMessage::SampleRequest(_) => SAMPLE_REQUEST,
+            CameraMessage::SampleResponse(_) => SAMPLE_RESPONSE,
+            CameraMessage::SampleErrorResponse(_) => SAMPLE_ERROR_RESPONSE,
+            CameraMessage::PropertyListRequest => PROPERTY_LIST_REQUEST,
+            CameraMessage::PropertyListResponse(_) => PROPERTY_LIST_RESPONSE,
+            CameraMessage::PropertyValueRequest(_) => PROPERTY_VALUE_REQUEST,
+            CameraMessage::PropertyValueResponse(_) => PROPERTY_VALUE_RESPONSE,
+            CameraMessage::SetPropertyValueRequest(_) => SET_PROPERTY_VALUE_REQUEST,
+        }
+    }
+}
+
+impl Encode for CameraPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.version);
+        dst.write_u8(self.message_id());
+
+        match &self.message {
+            CameraMessage::Success
+            | CameraMessage::SelectVersionRequest
+            | CameraMessage::SelectVersionResponse
+            | CameraMessage::ActivateDeviceRequest
+            | CameraMessage::DeactivateDeviceRequest
+            | CameraMessage::StreamListRequest
+            | CameraMessage::StopStreamsRequest
+            | CameraMessage::PropertyListRequest => Ok(()),
+            CameraMessage::Error(error) => error.encode(dst),
+            CameraMessage::DeviceAdded(pdu) => pdu.encode(dst),
+            CameraMessage::DeviceRemoved(pdu) => pdu.encode(dst),
+            CameraMessage::StreamListResponse(pdu) => pdu.encode(dst),
+            CameraMessage::MediaTypeListRequest(pdu)
+            | CameraMessage::CurrentMediaTypeRequest(pdu)
+            | CameraMessage::SampleRequest(pdu) => pdu.encode(dst),
+            CameraMessage::MediaTypeListResponse(pdu) => pdu.encode(dst),
+            CameraMessage::CurrentMediaTypeResponse(pdu) => pdu.encode(dst),
+            CameraMessage::StartStreamsRequest(pdu) => pdu.encode(dst),
+            CameraMessage::SampleResponse(pdu) => pdu.encode(dst),
+            CameraMessage::SampleErrorResponse(pdu) => pdu.encode(dst),
+            CameraMessage::PropertyListResponse(pdu) => pdu.encode(dst),
+            CameraMessage::PropertyValueRequest(pdu) => pdu.encode(dst),
+            CameraMessage::PropertyValueResponse(pdu) => pdu.encode(dst),
+            CameraMessage::SetPropertyValueRequest(pdu) => pdu.encode(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            .checked_add(match &self.message {
+                CameraMessage::Success
+                | CameraMessage::SelectVersionRequest
+                | CameraMessage::SelectVersionResponse
+                | CameraMessage::ActivateDeviceRequest
+                | CameraMessage::DeactivateDeviceRequest
+                | CameraMessage::StreamListRequest
+                | CameraMessage::StopStreamsRequest
+                | CameraMessage::PropertyListRequest => 0,
+                CameraMessage::Error(error) => error.size(),
+                CameraMessage::DeviceAdded(pdu) => pdu.size(),
+                CameraMessage::DeviceRemoved(pdu) => pdu.size(),
+                CameraMessage::StreamListResponse(pdu) => pdu.size(),
+                CameraMessage::MediaTypeListRequest(pdu)
+                | CameraMessage::CurrentMediaTypeRequest(pdu)
+                | CameraMessage::SampleRequest(pdu) => pdu.size(),
+                CameraMessage::MediaTypeListResponse(pdu) => pdu.size(),
+                CameraMessage::CurrentMediaTypeResponse(pdu) => pdu.size(),
+                CameraMessage::StartStreamsRequest(pdu) => pdu.size(),
+                CameraMessage::SampleResponse(pdu) => pdu.size(),
+                CameraMessage::SampleErrorResponse(pdu) => pdu.size(),
+                CameraMessage::PropertyListResponse(pdu) => pdu.size(),
+                CameraMessage::PropertyValueRequest(pdu) => pdu.size(),
+                CameraMessage::PropertyValueResponse(pdu) => pdu.size(),
+                CameraMessage::SetPropertyValueRequest(pdu) => pdu.size(),
+            })
+            .expect("never overflow")
+    }
+}
+
+impl DvcEncode for CameraPdu {}
+
+impl<'de> Decode<'de> for CameraPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let version = src.read_u8();
+        let message = match src.read_u8() {
+            SUCCESS_RESPONSE => CameraMessage::Success,
+            ERROR_RESPONSE => CameraMessage::Error(ErrorCode::decode(src)?),
+            SELECT_VERSION_REQUEST => CameraMessage::SelectVersionRequest,
+            SELECT_VERSION_RESPONSE => CameraMessage::SelectVersionResponse,
+            DEVICE_ADDED_NOTIFICATION => CameraMessage::DeviceAdded(DeviceAddedNotification::decode(src)?),
+            DEVICE_REMOVED_NOTIFICATION => CameraMessage::DeviceRemoved(DeviceRemovedNotification::decode(src)?),
+            ACTIVATE_DEVICE_REQUEST => CameraMessage::ActivateDeviceRequest,
+            DEACTIVATE_DEVICE_REQUEST => CameraMessage::DeactivateDeviceRequest,
+            STREAM_LIST_REQUEST => CameraMessage::StreamListRequest,
+            STREAM_LIST_RESPONSE => CameraMessage::StreamListResponse(StreamListResponse::decode(src)?),
+            MEDIA_TYPE_LIST_REQUEST => CameraMessage::MediaTypeListRequest(StreamRequest::decode(src)?),
+            MEDIA_TYPE_LIST_RESPONSE => CameraMessage::MediaTypeListResponse(MediaTypeListResponse::decode(src)?),
+            CURRENT_MEDIA_TYPE_REQUEST => CameraMessage::CurrentMediaTypeRequest(StreamRequest::decode(src)?),
+            CURRENT_MEDIA_TYPE_RESPONSE => CameraMessage::CurrentMediaTypeResponse(MediaTypeDescription::decode(src)?),
+            START_STREAMS_REQUEST => CameraMessage::StartStreamsRequest(StartStreamsRequest::decode(src)?),
+            STOP_STREAMS_REQUEST => CameraMessage::StopStreamsRequest,
+            SAMPLE_REQUEST => CameraMessage::SampleRequest(StreamRequest::decode(src)?),
+            SAMPLE_RESPONSE => CameraMessage::SampleResponse(SampleResponse::decode(src)?),
+            SAMPLE_ERROR_RESPONSE => CameraMessage::SampleErrorResponse(SampleErrorResponse::decode(src)?),
+            PROPERTY_LIST_REQUEST => CameraMessage::PropertyListRequest,
+            PROPERTY_LIST_RESPONSE => CameraMessage::PropertyListResponse(PropertyListResponse::decode(src)?),
+            PROPERTY_VALUE_REQUEST => CameraMessage::PropertyValueRequest(PropertyRequest::decode(src)?),
+            PROPERTY_VALUE_RESPONSE => CameraMessage::PropertyValueResponse(PropertyValue::decode(src)?),
+            SET_PROPERTY_VALUE_REQUEST => CameraMessage::SetPropertyValueRequest(SetPropertyValueRequest::decode(src)?),
+            _ => return Err(invalid_field_err!("MessageId", "unknown video capture message")),
+        };
+
+        Ok(Self { version, message })
+    }
+}
+
+/// 2.2.2.2 Error Response (ERROR_RESPONSE)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ErrorCode(pub u32);
+
+impl ErrorCode {
+    const NAME: &'static str = "ERROR_RESPONSE";
+
+    const FIXED_PART_SIZE: usize = 4 /* ErrorCode */;
+
+    pub const UNEXPECTED_ERROR: Self = Self(0x0000_0001);
+    pub const INVALID_MESSAGE: Self = Self(0x0000_0002);
+    pub const NOT_INITIALIZED: Self = Self(0x0000_0003);
+    pub const INVALID_REQUEST: Self = Self(0x0000_0004);
+    pub const INVALID_STREAM_NUMBER: Self = Self(0x0000_0005);
+    pub const INVALID_MEDIA_TYPE: Self = Self(0x0000_0006);
+    pub const OUT_OF_MEMORY: Self = Self(0x0000_0007);
+    pub const ITEM_NOT_FOUND: Self = Self(0x0000_0008);
+    pub const SET_NOT_FOUND: Self = Self(0x0000_0009);
+    pub const OPERATION_NOT_SUPPORTED: Self = Self(0x0000_000A);
+}
+
+impl Encode for ErrorCode {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.0);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ErrorCode {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self(src.read_u32()))
+    }
+}
+
+/// 2.2.2.5 Device Added Notification (DEVICE_ADDED_NOTIFICATION)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceAddedNotification {
+    /// Friendly name of the camera
+    pub device_name: String,
+    /// Name of the dynamic channel the server opens to use the camera
+    pub virtual_channel_name: String,
+}
+
+impl DeviceAddedNotification {
+    const NAME: &'static str = "DEVICE_ADDED_NOTIFICATION";
+}
+
+impl Encode for DeviceAddedNotification {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        write_string_to_cursor(dst, &self.device_name, CharacterSet::Unicode, true)?;
+        write_string_to_cursor(dst, &self.virtual_channel_name, CharacterSet::Ansi, true)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        encoded_str_len(&self.device_name, CharacterSet::Unicode, true)
+            + encoded_str_len(&self.virtual_channel_name, CharacterSet::Ansi, true)
+    }
+}
+
+impl<'de> Decode<'de> for DeviceAddedNotification {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let device_name = read_string_from_cursor(src, CharacterSet::Unicode, true)?;
+        let virtual_channel_name = read_string_from_cursor(src, CharacterSet::Ansi, true)?;
+
+        Ok(Self {
+            device_name,
+            virtual_channel_name,
+        })
+    }
+}
+
+/// 2.2.2.6 Device Removed Notification (DEVICE_REMOVED_NOTIFICATION)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceRemovedNotification {
+    /// Name of the channel of the camera, as announced in [`DeviceAddedNotification`]
+    pub virtual_channel_name: String,
+}
+
+impl DeviceRemovedNotification {
+    const NAME: &'static str = "DEVICE_REMOVED_NOTIFICATION";
+}
+
+impl Encode for DeviceRemovedNotification {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        write_string_to_cursor(dst, &self.virtual_channel_name, CharacterSet::Ansi, true)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        encoded_str_len(&self.virtual_channel_name, CharacterSet::Ansi, true)
+    }
+}
+
+impl<'de> Decode<'de> for DeviceRemovedNotification {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        Ok(Self {
+            virtual_channel_name: read_string_from_cursor(src, CharacterSet::Ansi, true)?,
+        })
+    }
+}
+
+/// Request targeting a stream: media type list, current media type and sample requests
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StreamRequest {
+    /// Index of the stream in the [`StreamListResponse`]
+    pub stream_index: u8,
+}
+
+impl StreamRequest {
+    const NAME: &'static str = "STREAM_REQUEST";
+
+    const FIXED_PART_SIZE: usize = 1 /* StreamIndex */;
+}
+
+impl Encode for StreamRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.stream_index);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for StreamRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            stream_index: src.read_u8(),
+        })
+    }
+}
+
+/// 2.2.2.10 Stream List Response (STREAM_LIST_RESPONSE)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamListResponse {
+    pub streams: Vec<StreamDescription>,
+}
+
+impl StreamListResponse {
+    const NAME: &'static str = "STREAM_LIST_RESPONSE";
+}
+
+impl Encode for StreamListResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        for stream in &self.streams {
+            stream.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.streams.len() * StreamDescription::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for StreamListResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        Ok(Self {
+            streams: decode_array(src)?,
+        })
+    }
+}
+
+/// 2.2.2.10.1 STREAM_DESCRIPTION
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StreamDescription {
+    /// Combination of the `FRAME_SOURCE_TYPE_*` flags
+    pub frame_source_types: u16,
+    /// [`StreamDescription::CATEGORY_CAPTURE`]
+    pub stream_category: u8,
+    /// Whether the stream is selected by default
+    pub selected: bool,
+    /// Whether the stream can be used by several applications at once
+    pub can_be_shared: bool,
+}
+
+impl StreamDescription {
+    const NAME: &'static str = "STREAM_DESCRIPTION";
+
+    const FIXED_PART_SIZE: usize = 2 /* FrameSourceTypes */ + 1 /* StreamCategory */ + 1 /* Selected */
+        + 1 /* CanBeShared */;
+
+    pub const FRAME_SOURCE_TYPE_COLOR: u16 = 0x0001;
+    pub const FRAME_SOURCE_TYPE_INFRARED: u16 = 0x0002;
+    pub const FRAME_SOURCE_TYPE_CUSTOM: u16 = 0x0008;
+
+    pub const CATEGORY_CAPTURE: u8 = 0x01;
+
+    /// Color capture stream, the usual stream of a webcam
+    pub fn color() -> Self {
+        Self {
+            frame_source_types: Self::FRAME_SOURCE_TYPE_COLOR,
+            stream_category: Self::CATEGORY_CAPTURE,
+            selected: true,
+            can_be_shared: true,
+        }
+    }
+}
+
+impl Encode for StreamDescription {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u16(self.frame_source_types);
+        dst.write_u8(self.stream_category);
+        dst.write_u8(u8::from(self.selected));
+        dst.write_u8(u8::from(self.can_be_shared));
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for StreamDescription {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            frame_source_types: src.read_u16(),
+            stream_category: src.read_u8(),
+            selected: src.read_u8() != 0,
+            can_be_shared: src.read_u8() != 0,
+        })
+    }
+}
+
+/// 2.2.2.12 Media Type List Response (MEDIA_TYPE_LIST_RESPONSE)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaTypeListResponse {
+    pub media_types: Vec<MediaTypeDescription>,
+}
+
+impl MediaTypeListResponse {
+    const NAME: &'static str = "MEDIA_TYPE_LIST_RESPONSE";
+}
+
+impl Encode for MediaTypeListResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        for media_type in &self.media_types {
+            media_type.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.media_types.len() * MediaTypeDescription::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for MediaTypeListResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        Ok(Self {
+            media_types: decode_array(src)?,
+        })
+    }
+}
+
+/// Format of the samples of a stream
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MediaFormat(pub u8);
+
+impl MediaFormat {
+    pub const H264: Self = Self(0x01);
+    pub const MJPG: Self = Self(0x02);
+    pub const YUY2: Self = Self(0x03);
+    pub const NV12: Self = Self(0x04);
+    pub const I420: Self = Self(0x05);
+    pub const RGB24: Self = Self(0x06);
+    pub const RGB32: Self = Self(0x07);
+}
+
+/// 2.2.2.12.1 MEDIA_TYPE_DESCRIPTION
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MediaTypeDescription {
+    pub format: MediaFormat,
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate_numerator: u32,
+    pub frame_rate_denominator: u32,
+    pub pixel_aspect_ratio_numerator: u32,
+    pub pixel_aspect_ratio_denominator: u32,
+    /// Combination of [`MediaTypeDescription::DECODING_REQUIRED`] and [`MediaTypeDescription::BOTTOM_UP_IMAGE`]
+    pub flags: u8,
+}
+
+impl MediaTypeDescription {
+    const NAME: &'static str = "MEDIA_TYPE_DESCRIPTION";
+
+    const FIXED_PART_SIZE: usize = 1 /* Format */ + 4 /* Width */ + 4 /* Height */ + 4 /* FrameRateNumerator */
+        + 4 /* FrameRateDenominator */ + 4 /* PixelAspectRatioNumerator */ + 4 /* PixelAspectRatioDenominator */
+        + 1 /* Flags */;
+
+    /// The samples are compressed
+    pub const DECODING_REQUIRED: u8 = 0x01;
+    /// The first row of the image is the bottom one
+    pub const BOTTOM_UP_IMAGE: u8 = 0x02;
+}
+
+impl Encode for MediaTypeDescription {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.format.0);
+        dst.write_u32(self.width);
+        dst.write_u32(self.height);
+        dst.write_u32(self.frame_rate_numerator);
+        dst.write_u32(self.frame_rate_denominator);
+        dst.write_u32(self.pixel_aspect_ratio_numerator);
+        dst.write_u32(self.pixel_aspect_ratio_denominator);
+        dst.write_u8(self.flags);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for MediaTypeDescription {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            format: MediaFormat(src.read_u8()),
+            width: src.read_u32(),
+            height: src.read_u32(),
+            frame_rate_numerator: src.read_u32(),
+            frame_rate_denominator: src.read_u32(),
+            pixel_aspect_ratio_numerator: src.read_u32(),
+            pixel_aspect_ratio_denominator: src.read_u32(),
+            flags: src.read_u8(),
+        })
+    }
+}
+
+/// 2.2.2.15 Start Streams Request (START_STREAMS_REQUEST)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartStreamsRequest {
+    pub streams: Vec<StartStreamInfo>,
+}
+
+impl StartStreamsRequest {
+    const NAME: &'static str = "START_STREAMS_REQUEST";
+}
+
+impl Encode for StartStreamsRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        for stream in &self.streams {
+            stream.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.streams.len() * StartStreamInfo::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for StartStreamsRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        Ok(Self {
+            streams: decode_array(src)?,
+        })
+    }
+}
+
+/// 2.2.2.15.1 START_STREAM_INFO
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StartStreamInfo {
+    pub stream_index: u8,
+    pub media_type: MediaTypeDescription,
+}
+
+impl StartStreamInfo {
+    const NAME: &'static str = "START_STREAM_INFO";
+
+    const FIXED_PART_SIZE: usize = 1 /* StreamIndex */ + MediaTypeDescription::FIXED_PART_SIZE;
+}
+
+impl Encode for StartStreamInfo {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.stream_index);
+        self.media_type.encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for StartStreamInfo {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            stream_index: src.read_u8(),
+            media_type: MediaTypeDescription::decode(src)?,
+        })
+    }
+}
+
+/// 2.2.2.18 Sample Response (SAMPLE_RESPONSE)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleResponse {
+    pub stream_index: u8,
+    /// Sample in the media type of the stream
+    pub sample: Vec<u8>,
+}
+
+impl SampleResponse {
+    const NAME: &'static str = "SAMPLE_RESPONSE";
+
+    const FIXED_PART_SIZE: usize = 1 /* StreamIndex */;
+}
+
+impl Encode for SampleResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.stream_index);
+        dst.write_slice(&self.sample);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.sample.len()
+    }
+}
+
+impl<'de> Decode<'de> for SampleResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let stream_index = src.read_u8();
+        // The sample spans the rest of the message.
+        let sample = src.read_remaining().to_vec();
+
+        Ok(Self { stream_index, sample })
+    }
+}
+
+/// 2.2.2.19 Sample Error Response (SAMPLE_ERROR_RESPONSE)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SampleErrorResponse {
+    pub stream_index: u8,
+    pub error_code: ErrorCode,
+}
+
+impl SampleErrorResponse {
+    const NAME: &'static str = "SAMPLE_ERROR_RESPONSE";
+
+    const FIXED_PART_SIZE: usize = 1 /* StreamIndex */ + ErrorCode::FIXED_PART_SIZE;
+}
+
+impl Encode for SampleErrorResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.stream_index);
+        self.error_code.encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for SampleErrorResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            stream_index: src.read_u8(),
+            error_code: ErrorCode::decode(src)?,
+        })
+    }
+}
+
+/// 2.2.2.21 Property List Response (PROPERTY_LIST_RESPONSE)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyListResponse {
+    pub properties: Vec<PropertyDescription>,
+}
+
+impl PropertyListResponse {
+    const NAME: &'static str = "PROPERTY_LIST_RESPONSE";
+}
+
+impl Encode for PropertyListResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        for property in &self.properties {
+            property.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.properties.len() * PropertyDescription::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for PropertyListResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        Ok(Self {
+            properties: decode_array(src)?,
+        })
+    }
+}
+
+/// Camera property, identified by its set and its identifier in the set
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PropertyRequest {
+    /// [`PropertyRequest::CAMERA_CONTROL`] or [`PropertyRequest::VIDEO_PROC_AMP`]
+    pub property_set: u8,
+    pub property_id: u8,
+}
+
+impl PropertyRequest {
+    const NAME: &'static str = "PROPERTY_VALUE_REQUEST";
+
+    const FIXED_PART_SIZE: usize = 1 /* PropertySet */ + 1 /* PropertyId */;
+
+    /// Camera controls, e.g. exposure or focus
+    pub const CAMERA_CONTROL: u8 = 0x01;
+    /// Image processing, e.g. brightness or contrast
+    pub const VIDEO_PROC_AMP: u8 = 0x02;
+}
+
+impl Encode for PropertyRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.property_set);
+        dst.write_u8(self.property_id);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for PropertyRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            property_set: src.read_u8(),
+            property_id: src.read_u8(),
+        })
+    }
+}
+
+/// 2.2.2.21.1 PROPERTY_DESCRIPTION
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PropertyDescription {
+    pub property: PropertyRequest,
+    /// Combination of [`PropertyValue::MODE_MANUAL`] and [`PropertyValue::MODE_AUTO`]
+    pub capabilities: u8,
+    pub min_value: i32,
+    pub max_value: i32,
+    pub step: i32,
+    pub default_value: i32,
+}
+
+impl PropertyDescription {
+    const NAME: &'static str = "PROPERTY_DESCRIPTION";
+
+    const FIXED_PART_SIZE: usize = PropertyRequest::FIXED_PART_SIZE + 1 /* Capabilities */ + 4 /* MinValue */
+        + 4 /* MaxValue */ + 4 /* Step */ + 4 /* DefaultValue */;
+}
+
+impl Encode for PropertyDescription {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        self.property.encode(dst)?;
+        dst.write_u8(self.capabilities);
+        dst.write_i32(self.min_value);
+        dst.write_i32(self.max_value);
+        dst.write_i32(self.step);
+        dst.write_i32(self.default_value);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for PropertyDescription {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            property: PropertyRequest::decode(src)?,
+            capabilities: src.read_u8(),
+            min_value: src.read_i32(),
+            max_value: src.read_i32(),
+            step: src.read_i32(),
+            default_value: src.read_i32(),
+        })
+    }
+}
+
+/// 2.2.2.23.1 PROPERTY_VALUE
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PropertyValue {
+    /// [`PropertyValue::MODE_MANUAL`] or [`PropertyValue::MODE_AUTO`]
+    pub mode: u8,
+    /// Value of the property, meaningful in manual mode
+    pub value: i32,
+}
+
+impl PropertyValue {
+    const NAME: &'static str = "PROPERTY_VALUE";
+
+    const FIXED_PART_SIZE: usize = 1 /* Mode */ + 4 /* Value */;
+
+    pub const MODE_MANUAL: u8 = 0x01;
+    pub const MODE_AUTO: u8 = 0x02;
+}
+
+impl Encode for PropertyValue {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.mode);
+        dst.write_i32(self.value);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for PropertyValue {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            mode: src.read_u8(),
+            value: src.read_i32(),
+        })
+    }
+}
+
+/// 2.2.2.24 Set Property Value Request (SET_PROPERTY_VALUE_REQUEST)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SetPropertyValueRequest {
+    pub property: PropertyRequest,
+    pub value: PropertyValue,
+}
+
+impl SetPropertyValueRequest {
+    const NAME: &'static str = "SET_PROPERTY_VALUE_REQUEST";
+
+    const FIXED_PART_SIZE: usize = PropertyRequest::FIXED_PART_SIZE + PropertyValue::FIXED_PART_SIZE;
+}
+
+impl Encode for SetPropertyValueRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        self.property.encode(dst)?;
+        self.value.encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for SetPropertyValueRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            property: PropertyRequest::decode(src)?,
+            value: PropertyValue::decode(src)?,
+        })
+    }
+}
+
+/// Decodes the fixed-size elements spanning the rest of the message.
+fn decode_array<'de, T: Decode<'de>>(src: &mut ReadCursor<'de>) -> DecodeResult<Vec<T>> {
+    let mut elements = Vec::new();
+    while !src.is_empty() {
+        elements.push(T::decode(src)?);
+    }
+
+    Ok(elements)
+}
@@ -0,0 +1,402 @@
+use std::collections::VecDeque;
+
+use ironrdp_core::{decode, impl_as_any};
+use ironrdp_dvc::{encode_dvc_messages, DvcMessage, DvcProcessor, DvcServerProcessor};
+use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tracing::{debug, warn};
+
+use crate::pdu::{
+    CameraMessage, CameraPdu, DeviceAddedNotification, DeviceRemovedNotification, ErrorCode, MediaTypeDescription,
+    PropertyDescription, PropertyRequest, PropertyValue, SetPropertyValueRequest, StartStreamInfo, StartStreamsRequest,
+    StreamDescription, StreamRequest, VERSION_2,
+};
+use crate::ENUMERATOR_CHANNEL_NAME;
+
+/// Receives the cameras announced by the client
+pub trait CameraEnumeratorServerHandler: Send + core::fmt::Debug {
+    /// A camera of the client is available
+    ///
+    /// The camera is used by opening a [`CameraDeviceServer`] on the announced channel, with the negotiated version.
+    fn device_added(&mut self, version: u8, device: DeviceAddedNotification);
+
+    /// A camera of the client was unplugged, its channel is closed by the client
+    fn device_removed(&mut self, device: DeviceRemovedNotification) {
+        debug!(?device, "Camera removed");
+    }
+}
+
+/// A server for the device enumeration channel of the Video Capture Virtual Channel
+#[derive(Debug)]
+pub struct CameraEnumeratorServer {
+    handler: Box<dyn CameraEnumeratorServerHandler>,
+    /// Version selected for the client, set once negotiated
+    version: Option<u8>,
+}
+
+impl CameraEnumeratorServer {
+    pub fn new(handler: Box<dyn CameraEnumeratorServerHandler>) -> Self {
+        Self { handler, version: None }
+    }
+
+    /// Version of the protocol selected for the client
+    pub fn version(&self) -> Option<u8> {
+        self.version
+    }
+}
+
+impl_as_any!(CameraEnumeratorServer);
+
+impl DvcProcessor for CameraEnumeratorServer {
+    fn channel_name(&self) -> &str {
+        ENUMERATOR_CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.version = None;
+
+        // The client sends its version first.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: CameraPdu = decode(payload).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        let messages: Vec<DvcMessage> = match pdu.message {
+            CameraMessage::SelectVersionRequest => {
+                let version = pdu.version.min(VERSION_2);
+                self.version = Some(version);
+
+                vec![Box::new(CameraPdu::new(version, CameraMessage::SelectVersionResponse))]
+            }
+            CameraMessage::DeviceAdded(device) if self.version.is_some() => {
+                self.handler.device_added(pdu.version, device);
+                Vec::new()
+            }
+            CameraMessage::DeviceRemoved(device) => {
+                self.handler.device_removed(device);
+                Vec::new()
+            }
+            message => {
+                warn!(?message, version = ?self.version, "Unexpected camera enumeration PDU");
+                Vec::new()
+            }
+        };
+
+        Ok(messages)
+    }
+}
+
+impl DvcServerProcessor for CameraEnumeratorServer {}
+
+/// Uses the samples captured by a camera of the client
+pub trait CameraConsumer: Send + core::fmt::Debug {
+    /// Picks the media type of the captured stream, among the ones supported by the camera
+    ///
+    /// The capture is not started when `None` is returned.
+    fn select_media_type(
+        &mut self,
+        stream_index: u8,
+        media_types: &[MediaTypeDescription],
+    ) -> Option<MediaTypeDescription> {
+        debug!(stream_index, ?media_types, "Camera media types");
+        media_types.first().copied()
+    }
+
+    /// The camera started capturing the streams
+    fn started(&mut self, streams: &[StartStreamInfo]) {
+        debug!(?streams, "Camera streams started");
+    }
+
+    /// Sample captured by the camera, in the media type of the stream
+    fn sample(&mut self, stream_index: u8, sample: &[u8]);
+
+    /// The camera failed to capture a sample, the capture is stopped
+    fn sample_error(&mut self, stream_index: u8, error_code: ErrorCode) {
+        warn!(stream_index, ?error_code, "Camera sample error");
+    }
+
+    /// The camera stopped capturing the streams
+    fn stopped(&mut self) {}
+
+    /// Properties of the camera, answering [`CameraDeviceServer::request_properties`]
+    fn properties(&mut self, properties: &[PropertyDescription]) {
+        debug!(?properties, "Camera properties");
+    }
+
+    /// Value of a property, answering [`CameraDeviceServer::request_property_value`] and
+    /// [`CameraDeviceServer::set_property_value`]
+    fn property_value(&mut self, property: PropertyRequest, value: PropertyValue) {
+        debug!(?property, ?value, "Camera property value");
+    }
+
+    /// The client failed to handle a request
+    fn request_failed(&mut self, error_code: ErrorCode) {
+        warn!(?error_code, "Camera request failed");
+    }
+}
+
+/// Request waiting for the reply of the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PendingRequest {
+    Activate,
+    StreamList,
+    MediaTypeList(u8),
+    StartStreams(Vec<StartStreamInfo>),
+    StopStreams,
+    PropertyList,
+    PropertyValue(PropertyRequest),
+    SetPropertyValue(SetPropertyValueRequest),
+}
+
+/// A server for the channel of a camera of the client
+///
+/// Once the channel is opened, the camera is activated, and the first selected color stream is started in the media
+/// type picked by the [`CameraConsumer`]. The samples are then requested one after another.
+#[derive(Debug)]
+pub struct CameraDeviceServer {
+    channel_name: String,
+    version: u8,
+    consumer: Box<dyn CameraConsumer>,
+    channel_id: Option<u32>,
+    /// The client replies to the requests in order, except the sample requests
+    pending: VecDeque<PendingRequest>,
+    /// Stream being captured
+    streaming: Option<u8>,
+}
+
+impl CameraDeviceServer {
+    /// Creates the server for the camera announced on the channel `channel_name`, with the negotiated `version`
+    pub fn new(channel_name: impl Into<String>, version: u8, consumer: Box<dyn CameraConsumer>) -> Self {
+        Self {
+            channel_name: channel_name.into(),
+            version,
+            consumer,
+            channel_id: None,
+            pending: VecDeque::new(),
+            streaming: None,
+        }
+    }
+
+    /// Whether the camera is capturing a stream
+    pub fn is_streaming(&self) -> bool {
+        self.streaming.is_some()
+    }
+
+    /// Requests to stop capturing the streams
+    pub fn stop_streams(&mut self) -> PduResult<Vec<SvcMessage>> {
+        self.streaming = None;
+        self.encode(PendingRequest::StopStreams, CameraMessage::StopStreamsRequest)
+    }
+
+    /// Requests the properties of the camera, answered with [`CameraConsumer::properties`]
+    pub fn request_properties(&mut self) -> PduResult<Vec<SvcMessage>> {
+        self.ensure_properties_supported()?;
+        self.encode(PendingRequest::PropertyList, CameraMessage::PropertyListRequest)
+    }
+
+    /// Requests the value of a property, answered with [`CameraConsumer::property_value`]
+    pub fn request_property_value(&mut self, property: PropertyRequest) -> PduResult<Vec<SvcMessage>> {
+        self.ensure_properties_supported()?;
+        self.encode(
+            PendingRequest::PropertyValue(property),
+            CameraMessage::PropertyValueRequest(property),
+        )
+    }
+
+    /// Requests to change the value of a property
+    pub fn set_property_value(
+        &mut self,
+        property: PropertyRequest,
+        value: PropertyValue,
+    ) -> PduResult<Vec<SvcMessage>> {
+        self.ensure_properties_supported()?;
+
+        let request = SetPropertyValueRequest { property, value };
+        self.encode(
+            PendingRequest::SetPropertyValue(request),
+            CameraMessage::SetPropertyValueRequest(request),
+        )
+    }
+
+    fn ensure_properties_supported(&self) -> PduResult<()> {
+        if self.version < VERSION_2 {
+            return Err(pdu_other_err!("camera properties not supported by the client"));
+        }
+
+        Ok(())
+    }
+
+    fn encode(&mut self, request: PendingRequest, message: CameraMessage) -> PduResult<Vec<SvcMessage>> {
+        let channel_id = self
+            .channel_id
+            .ok_or_else(|| pdu_other_err!("camera channel not opened"))?;
+
+        let messages = encode_dvc_messages(
+            channel_id,
+            vec![Box::new(CameraPdu::new(self.version, message))],
+            ChannelFlags::empty(),
+        )
+        .map_err(|e| encode_err!(e))?;
+        self.pending.push_back(request);
+
+        Ok(messages)
+    }
+
+    fn request(&mut self, request: PendingRequest, message: CameraMessage) -> Vec<DvcMessage> {
+        self.pending.push_back(request);
+        vec![Box::new(CameraPdu::new(self.version, message))]
+    }
+
+    fn request_sample(&self, stream_index: u8) -> Vec<DvcMessage> {
+        vec![Box::new(CameraPdu::new(
+            self.version,
+            CameraMessage::SampleRequest(StreamRequest { stream_index }),
+        ))]
+    }
+
+    fn select_stream(&mut self, streams: &[StreamDescription]) -> Vec<DvcMessage> {
+        let Some(index) = streams
+            .iter()
+            .position(|stream| {
+                stream.selected && stream.frame_source_types & StreamDescription::FRAME_SOURCE_TYPE_COLOR != 0
+            })
+            .or_else(|| (!streams.is_empty()).then_some(0))
+        else {
+            warn!("Camera without streams");
+            return Vec::new();
+        };
+
+        // Stream indices are encoded on a single byte.
+        let Ok(stream_index) = u8::try_from(index) else {
+            warn!(index, "Invalid camera stream index");
+            return Vec::new();
+        };
+
+        self.request(
+            PendingRequest::MediaTypeList(stream_index),
+            CameraMessage::MediaTypeListRequest(StreamRequest { stream_index }),
+        )
+    }
+
+    fn start_stream(&mut self, stream_index: u8, media_types: &[MediaTypeDescription]) -> Vec<DvcMessage> {
+        let Some(media_type) = self.consumer.select_media_type(stream_index, media_types) else {
+            debug!(stream_index, "No camera media type selected");
+            return Vec::new();
+        };
+
+        let streams = vec![StartStreamInfo {
+            stream_index,
+            media_type,
+        }];
+
+        self.request(
+            PendingRequest::StartStreams(streams.clone()),
+            CameraMessage::StartStreamsRequest(StartStreamsRequest { streams }),
+        )
+    }
+
+    fn reply(&mut self, request: PendingRequest, message: CameraMessage) -> Vec<DvcMessage> {
+        match (request, message) {
+            (_, CameraMessage::Error(error_code)) => {
+                self.consumer.request_failed(error_code);
+                Vec::new()
+            }
+            (PendingRequest::Activate, CameraMessage::Success) => {
+                self.request(PendingRequest::StreamList, CameraMessage::StreamListRequest)
+            }
+            (PendingRequest::StreamList, CameraMessage::StreamListResponse(response)) => {
+                self.select_stream(&response.streams)
+            }
+            (PendingRequest::MediaTypeList(stream_index), CameraMessage::MediaTypeListResponse(response)) => {
+                self.start_stream(stream_index, &response.media_types)
+            }
+            (PendingRequest::StartStreams(streams), CameraMessage::Success) => {
+                let Some(stream_index) = streams.first().map(|stream| stream.stream_index) else {
+                    return Vec::new();
+                };
+
+                self.streaming = Some(stream_index);
+                self.consumer.started(&streams);
+
+                self.request_sample(stream_index)
+            }
+            (PendingRequest::StopStreams, CameraMessage::Success) => {
+                self.consumer.stopped();
+                Vec::new()
+            }
+            (PendingRequest::PropertyList, CameraMessage::PropertyListResponse(response)) => {
+                self.consumer.properties(&response.properties);
+                Vec::new()
+            }
+            (PendingRequest::PropertyValue(property), CameraMessage::PropertyValueResponse(value)) => {
+                self.consumer.property_value(property, value);
+                Vec::new()
+            }
+            (PendingRequest::SetPropertyValue(request), CameraMessage::Success) => {
+                self.consumer.property_value(request.property, request.value);
+                Vec::new()
+            }
+            (request, message) => {
+                warn!(?request, ?message, "Unexpected camera reply");
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl_as_any!(CameraDeviceServer);
+
+impl DvcProcessor for CameraDeviceServer {
+    fn channel_name(&self) -> &str {
+        &self.channel_name
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.channel_id = Some(channel_id);
+        self.pending.clear();
+        self.streaming = None;
+
+        Ok(self.request(PendingRequest::Activate, CameraMessage::ActivateDeviceRequest))
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: CameraPdu = decode(payload).map_err(|e| decode_err!(e))?;
+
+        let messages = match pdu.message {
+            CameraMessage::SampleResponse(response) if self.streaming == Some(response.stream_index) => {
+                self.consumer.sample(response.stream_index, &response.sample);
+                self.request_sample(response.stream_index)
+            }
+            CameraMessage::SampleErrorResponse(response) if self.streaming == Some(response.stream_index) => {
+                self.streaming = None;
+                self.consumer.sample_error(response.stream_index, response.error_code);
+                Vec::new()
+            }
+            CameraMessage::SampleResponse(_) | CameraMessage::SampleErrorResponse(_) => {
+                debug!("Ignoring camera sample of a stopped stream");
+                Vec::new()
+            }
+            message => match self.pending.pop_front() {
+                Some(request) => self.reply(request, message),
+                None => {
+                    warn!(?message, "Unexpected camera PDU");
+                    Vec::new()
+                }
+            },
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        self.channel_id = None;
+        self.pending.clear();
+        if self.streaming.take().is_some() {
+            self.consumer.stopped();
+        }
+    }
+}
+
+impl DvcServerProcessor for CameraDeviceServer {}
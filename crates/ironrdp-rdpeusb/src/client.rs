@@ -0,0 +1,373 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use ironrdp_core::{impl_as_any, Decode as _, ReadCursor};
+use ironrdp_dvc::pdu::{ClosePdu, DrdynvcClientPdu};
+use ironrdp_dvc::{encode_dvc_messages, DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tracing::{debug, warn};
+
+use crate::pdu::{
+    AddDevice, ChannelCreated, ClientMessage, ExchangeCapabilityResponse, IoControl, IoControlCompletion,
+    QueryDeviceText, QueryDeviceTextResponse, ServerMessage, TransferInRequest, TransferOutRequest, UrbCompletion,
+    UrbCompletionNoData, UrbdrcClientPdu, UrbdrcServerPdu, UsbDevice, CAPABILITIES_INTERFACE,
+    CLIENT_CHANNEL_NOTIFICATION_INTERFACE, DEVICE_SINK_INTERFACE, E_FAIL, RIM_CAPABILITY_VERSION_01, S_OK,
+};
+use crate::CHANNEL_NAME;
+
+/// USB stack of the client, e.g. a bridge to libusb
+///
+/// The requests are identified by the device they target, as given to [`UrbdrcClient::add_device`]. Transfers and
+/// I/O controls may take a long time to complete, e.g. when reading an interrupt endpoint: the backend submits them
+/// and returns, the result is sent later with [`UrbdrcClient::encode_io_control_completion`],
+/// [`UrbdrcClient::encode_urb_completion`] or [`UrbdrcClient::encode_urb_completion_no_data`].
+pub trait UsbBackend: Send + core::fmt::Debug {
+    /// The server sent an I/O control request to the device
+    fn io_control(&mut self, device_id: u32, request: &IoControl);
+
+    /// The server sent an internal I/O control request to the device
+    fn internal_io_control(&mut self, device_id: u32, request: &IoControl);
+
+    /// The server requested a text describing the device, `None` if there is none
+    fn query_device_text(&mut self, device_id: u32, request: &QueryDeviceText) -> Option<String>;
+
+    /// The server requested to read from the device
+    fn transfer_in(&mut self, device_id: u32, request: &TransferInRequest);
+
+    /// The server requested to write to the device
+    ///
+    /// No completion is expected when the URB is marked as `no_ack`.
+    fn transfer_out(&mut self, device_id: u32, request: &TransferOutRequest);
+
+    /// The server cancelled a pending I/O control or transfer
+    fn cancel_request(&mut self, device_id: u32, request_id: u32);
+
+    /// The server doesn't want the device anymore, it is no longer redirected
+    fn retract_device(&mut self, device_id: u32, reason: u32);
+}
+
+#[derive(Debug)]
+struct Device {
+    device: UsbDevice,
+    /// Channel opened by the server for the device
+    channel_id: Option<u32>,
+    /// Interface to which the completions are sent, registered by the server
+    request_completion: Option<u32>,
+}
+
+/// A client for the USB Devices Virtual Channel
+///
+/// The first channel opened by the server is the control channel, on which the client requests a new channel for
+/// each of its devices. The channels are then assigned to the devices in the order of the requests.
+#[derive(Debug)]
+pub struct UrbdrcClient {
+    backend: Box<dyn UsbBackend>,
+    control_channel: Option<u32>,
+    /// Whether the server notified the creation of the control channel, after which devices can be announced
+    control_channel_ready: bool,
+    devices: BTreeMap<u32, Device>,
+    /// Devices waiting for the server to open their channel, in the order of the requests
+    pending_devices: VecDeque<u32>,
+    next_message_id: u32,
+}
+
+impl UrbdrcClient {
+    pub fn new(backend: Box<dyn UsbBackend>) -> Self {
+        Self {
+            backend,
+            control_channel: None,
+            control_channel_ready: false,
+            devices: BTreeMap::new(),
+            pending_devices: VecDeque::new(),
+            next_message_id: 0,
+        }
+    }
+
+    /// Adds devices to redirect once the channel is opened
+    ///
+    /// Device identifiers must be greater than [`CLIENT_CHANNEL_NOTIFICATION_INTERFACE`], they are used as the
+    /// interfaces of the devices.
+    #[must_use]
+    pub fn with_devices(mut self, devices: Vec<(u32, UsbDevice)>) -> Self {
+        for (device_id, device) in devices {
+            self.insert_device(device_id, device);
+        }
+        self
+    }
+
+    /// Identifiers of the devices, redirected or waiting for the channel to be opened
+    pub fn device_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.devices.keys().copied()
+    }
+
+    /// Whether the device was announced to the server on its own channel
+    pub fn is_redirected(&self, device_id: u32) -> bool {
+        self.devices
+            .get(&device_id)
+            .is_some_and(|device| device.channel_id.is_some())
+    }
+
+    /// Redirects a device plugged in during the session
+    ///
+    /// The device identifier must be greater than [`CLIENT_CHANNEL_NOTIFICATION_INTERFACE`], it is used as the
+    /// interface of the device. Returns the request for a channel for the device, nothing if the channel is not
+    /// opened yet: the device is then announced once it is.
+    pub fn add_device(&mut self, device_id: u32, device: UsbDevice) -> PduResult<Vec<SvcMessage>> {
+        if device_id <= CLIENT_CHANNEL_NOTIFICATION_INTERFACE {
+            return Err(pdu_other_err!("device identifier reserved for a channel interface"));
+        }
+        if self.devices.contains_key(&device_id) {
+            return Err(pdu_other_err!("device already redirected"));
+        }
+
+        self.insert_device(device_id, device);
+
+        match self.control_channel {
+            Some(channel_id) if self.control_channel_ready => {
+                let message = self.request_device_channel(device_id);
+                encode_dvc_messages(channel_id, vec![message], ChannelFlags::empty()).map_err(|e| encode_err!(e))
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Stops the redirection of an unplugged device
+    ///
+    /// Returns the request to close the channel of the device, if it was opened.
+    pub fn remove_device(&mut self, device_id: u32) -> Vec<SvcMessage> {
+        let Some(device) = self.devices.remove(&device_id) else {
+            return Vec::new();
+        };
+        self.pending_devices.retain(|id| *id != device_id);
+
+        match device.channel_id {
+            Some(channel_id) => vec![SvcMessage::from(DrdynvcClientPdu::Close(ClosePdu::new(channel_id)))],
+            None => Vec::new(),
+        }
+    }
+
+    /// Sends the completion of an [`IoControl`] to the server
+    pub fn encode_io_control_completion(
+        &mut self,
+        device_id: u32,
+        completion: IoControlCompletion,
+    ) -> PduResult<Vec<SvcMessage>> {
+        self.encode_completion(device_id, ClientMessage::IoControlCompletion(completion))
+    }
+
+    /// Sends the completion of a [`TransferInRequest`] to the server, with the data read from the device
+    pub fn encode_urb_completion(&mut self, device_id: u32, completion: UrbCompletion) -> PduResult<Vec<SvcMessage>> {
+        self.encode_completion(device_id, ClientMessage::UrbCompletion(completion))
+    }
+
+    /// Sends the completion of a [`TransferOutRequest`], or of a [`TransferInRequest`] without data, to the server
+    pub fn encode_urb_completion_no_data(
+        &mut self,
+        device_id: u32,
+        completion: UrbCompletionNoData,
+    ) -> PduResult<Vec<SvcMessage>> {
+        self.encode_completion(device_id, ClientMessage::UrbCompletionNoData(completion))
+    }
+
+    fn insert_device(&mut self, device_id: u32, device: UsbDevice) {
+        self.devices.insert(
+            device_id,
+            Device {
+                device,
+                channel_id: None,
+                request_completion: None,
+            },
+        );
+    }
+
+    fn encode_completion(&mut self, device_id: u32, message: ClientMessage) -> PduResult<Vec<SvcMessage>> {
+        let device = self
+            .devices
+            .get(&device_id)
+            .ok_or_else(|| pdu_other_err!("unknown USB device"))?;
+        let channel_id = device
+            .channel_id
+            .ok_or_else(|| pdu_other_err!("USB device channel not opened"))?;
+        let interface_id = device
+            .request_completion
+            .ok_or_else(|| pdu_other_err!("no request completion callback registered"))?;
+
+        let pdu = UrbdrcClientPdu {
+            interface_id,
+            message_id: self.message_id(),
+            message,
+        };
+
+        encode_dvc_messages(channel_id, vec![Box::new(pdu)], ChannelFlags::empty()).map_err(|e| encode_err!(e))
+    }
+
+    fn message_id(&mut self) -> u32 {
+        let message_id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+        message_id
+    }
+
+    fn request_device_channel(&mut self, device_id: u32) -> DvcMessage {
+        self.pending_devices.push_back(device_id);
+
+        Box::new(UrbdrcClientPdu {
+            interface_id: DEVICE_SINK_INTERFACE,
+            message_id: self.message_id(),
+            message: ClientMessage::AddVirtualChannel,
+        })
+    }
+
+    fn channel_created(&mut self, channel_id: u32, message_id: u32) -> Vec<DvcMessage> {
+        let mut messages: Vec<DvcMessage> = vec![Box::new(UrbdrcClientPdu {
+            interface_id: CLIENT_CHANNEL_NOTIFICATION_INTERFACE,
+            message_id,
+            message: ClientMessage::ChannelCreated(ChannelCreated::V1_0),
+        })];
+
+        if self.control_channel == Some(channel_id) {
+            self.control_channel_ready = true;
+
+            let device_ids: Vec<u32> = self
+                .devices
+                .iter()
+                .filter(|(device_id, device)| device.channel_id.is_none() && !self.pending_devices.contains(device_id))
+                .map(|(device_id, _)| *device_id)
+                .collect();
+            for device_id in device_ids {
+                messages.push(self.request_device_channel(device_id));
+            }
+        } else if let Some((device_id, device)) = self
+            .devices
+            .iter()
+            .find(|(_, device)| device.channel_id == Some(channel_id))
+            .map(|(device_id, device)| (*device_id, device.device.clone()))
+        {
+            messages.push(Box::new(UrbdrcClientPdu {
+                interface_id: DEVICE_SINK_INTERFACE,
+                message_id: self.message_id(),
+                message: ClientMessage::AddDevice(AddDevice {
+                    usb_device: device_id,
+                    device,
+                }),
+            }));
+        } else {
+            warn!(channel_id, "Channel created without a device");
+        }
+
+        messages
+    }
+
+    fn device_request(&mut self, channel_id: u32, pdu: UrbdrcServerPdu) -> Vec<DvcMessage> {
+        let device_id = pdu.interface_id;
+        let Some(device) = self
+            .devices
+            .get_mut(&device_id)
+            .filter(|device| device.channel_id == Some(channel_id))
+        else {
+            warn!(?pdu, "Request for an unknown USB device");
+            return Vec::new();
+        };
+
+        match pdu.message {
+            ServerMessage::CancelRequest(request) => self.backend.cancel_request(device_id, request.request_id),
+            ServerMessage::RegisterRequestCallback(request) => device.request_completion = request.request_completion,
+            ServerMessage::IoControl(request) => self.backend.io_control(device_id, &request),
+            ServerMessage::InternalIoControl(request) => self.backend.internal_io_control(device_id, &request),
+            ServerMessage::QueryDeviceText(request) => {
+                let (device_description, hresult) = match self.backend.query_device_text(device_id, &request) {
+                    Some(text) => (text, S_OK),
+                    None => (String::new(), E_FAIL),
+                };
+
+                return vec![Box::new(UrbdrcClientPdu {
+                    interface_id: device_id,
+                    message_id: pdu.message_id,
+                    message: ClientMessage::QueryDeviceTextResponse(QueryDeviceTextResponse {
+                        device_description,
+                        hresult,
+                    }),
+                })];
+            }
+            ServerMessage::TransferIn(request) => self.backend.transfer_in(device_id, &request),
+            ServerMessage::TransferOut(request) => self.backend.transfer_out(device_id, &request),
+            ServerMessage::RetractDevice(request) => {
+                // The server closes the channel of the device.
+                self.devices.remove(&device_id);
+                self.backend.retract_device(device_id, request.reason);
+            }
+            // Handled for all the channels.
+            ServerMessage::ExchangeCapabilityRequest(_) | ServerMessage::ChannelCreated(_) => {}
+        }
+
+        Vec::new()
+    }
+}
+
+impl_as_any!(UrbdrcClient);
+
+impl DvcProcessor for UrbdrcClient {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        if self.control_channel.is_none() {
+            self.control_channel = Some(channel_id);
+        } else if let Some(device_id) = self.pending_devices.pop_front() {
+            if let Some(device) = self.devices.get_mut(&device_id) {
+                device.channel_id = Some(channel_id);
+            }
+        } else {
+            warn!(channel_id, "Unexpected URBDRC channel");
+        }
+
+        // The server starts the capability exchange.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu = UrbdrcServerPdu::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        let messages: Vec<DvcMessage> = match pdu.message {
+            ServerMessage::ExchangeCapabilityRequest(request) => {
+                let result = if request.capability_value == RIM_CAPABILITY_VERSION_01 {
+                    S_OK
+                } else {
+                    warn!(?request, "Unsupported URBDRC capability");
+                    E_FAIL
+                };
+
+                vec![Box::new(UrbdrcClientPdu {
+                    interface_id: CAPABILITIES_INTERFACE,
+                    message_id: pdu.message_id,
+                    message: ClientMessage::ExchangeCapabilityResponse(ExchangeCapabilityResponse {
+                        capability_value: RIM_CAPABILITY_VERSION_01,
+                        result,
+                    }),
+                })]
+            }
+            ServerMessage::ChannelCreated(_) => self.channel_created(channel_id, pdu.message_id),
+            _ => self.device_request(channel_id, pdu),
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, channel_id: u32) {
+        if self.control_channel == Some(channel_id) {
+            self.control_channel = None;
+            self.control_channel_ready = false;
+            self.pending_devices.clear();
+        }
+
+        for device in self.devices.values_mut() {
+            if device.channel_id == Some(channel_id) {
+                device.channel_id = None;
+                device.request_completion = None;
+            }
+        }
+    }
+}
+
+impl DvcClientProcessor for UrbdrcClient {}
@@ -0,0 +1,1448 @@
+//! USB Devices Virtual Channel Extension PDUs [MS-RDPEUSB][1] implementation.
+//!
+//! Every message starts with a shared header giving the interface the message is sent to, the message identifier and,
+//! except for responses, the function of the interface. The messages are split by direction: [`UrbdrcServerPdu`] are
+//! sent by the server and [`UrbdrcClientPdu`] by the client.
+//!
+//! [1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpeusb
+
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+use ironrdp_dvc::DvcEncode;
+use ironrdp_pdu::utils::{
+    decode_string, encoded_multistring_len, encoded_str_len, read_multistring_from_cursor, write_multistring_to_cursor,
+    write_string_to_cursor, CharacterSet,
+};
+
+/// Interface of the capability exchange
+pub const CAPABILITIES_INTERFACE: u32 = 0x0000_0000;
+/// Interface of the server receiving the devices of the client
+pub const DEVICE_SINK_INTERFACE: u32 = 0x0000_0001;
+/// Channel notification interface of the server
+pub const SERVER_CHANNEL_NOTIFICATION_INTERFACE: u32 = 0x0000_0002;
+/// Channel notification interface of the client
+pub const CLIENT_CHANNEL_NOTIFICATION_INTERFACE: u32 = 0x0000_0003;
+
+/// Capability value of the only version of the protocol
+pub const RIM_CAPABILITY_VERSION_01: u32 = 0x0000_0001;
+
+/// HRESULT of a successful request
+pub const S_OK: u32 = 0x0000_0000;
+/// HRESULT of a failed request (E_FAIL)
+pub const E_FAIL: u32 = 0x8000_4005;
+
+/// [`RetractDevice`] reason, the device is blocked by a policy of the server
+pub const USB_RETRACT_REASON_BLOCKED_BY_POLICY: u32 = 0x0000_0001;
+
+const INTERFACE_ID_MASK: u32 = 0x3FFF_FFFF;
+
+const STREAM_ID_NONE: u32 = 0x0;
+const STREAM_ID_PROXY: u32 = 0x1;
+const STREAM_ID_STUB: u32 = 0x2;
+
+const FUNCTION_RIM_EXCHANGE_CAPABILITY_REQUEST: u32 = 0x0000_0100;
+const FUNCTION_CHANNEL_CREATED: u32 = 0x0000_0100;
+const FUNCTION_ADD_VIRTUAL_CHANNEL: u32 = 0x0000_0100;
+const FUNCTION_ADD_DEVICE: u32 = 0x0000_0101;
+const FUNCTION_CANCEL_REQUEST: u32 = 0x0000_0100;
+const FUNCTION_REGISTER_REQUEST_CALLBACK: u32 = 0x0000_0101;
+const FUNCTION_IO_CONTROL: u32 = 0x0000_0102;
+const FUNCTION_INTERNAL_IO_CONTROL: u32 = 0x0000_0103;
+const FUNCTION_QUERY_DEVICE_TEXT: u32 = 0x0000_0104;
+const FUNCTION_TRANSFER_IN_REQUEST: u32 = 0x0000_0105;
+const FUNCTION_TRANSFER_OUT_REQUEST: u32 = 0x0000_0106;
+const FUNCTION_RETRACT_DEVICE: u32 = 0x0000_0107;
+const FUNCTION_IOCONTROL_COMPLETION: u32 = 0x0000_0100;
+const FUNCTION_URB_COMPLETION: u32 = 0x0000_0101;
+const FUNCTION_URB_COMPLETION_NO_DATA: u32 = 0x0000_0102;
+
+/// 2.2.1 Shared Message Header (SHARED_MSG_HEADER)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SharedMsgHeader {
+    interface_id: u32,
+    mask: u32,
+    message_id: u32,
+    /// Absent from responses
+    function_id: Option<u32>,
+}
+
+impl SharedMsgHeader {
+    const NAME: &'static str = "SHARED_MSG_HEADER";
+
+    const FIXED_PART_SIZE: usize = 4 /* InterfaceId + Mask */ + 4 /* MessageId */;
+
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(ctx: Self::NAME, in: dst, size: self.size());
+
+        dst.write_u32((self.mask << 30) | (self.interface_id & INTERFACE_ID_MASK));
+        dst.write_u32(self.message_id);
+        if let Some(function_id) = self.function_id {
+            dst.write_u32(function_id);
+        }
+
+        Ok(())
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + if self.function_id.is_some() { 4 } else { 0 }
+    }
+
+    /// Decodes the interface and the message identifier, the function is read separately as its presence depends
+    /// on the message.
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: Self::FIXED_PART_SIZE);
+
+        let interface = src.read_u32();
+        let message_id = src.read_u32();
+
+        Ok(Self {
+            interface_id: interface & INTERFACE_ID_MASK,
+            mask: interface >> 30,
+            message_id,
+            function_id: None,
+        })
+    }
+
+    fn decode_function_id(src: &mut ReadCursor<'_>) -> DecodeResult<u32> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 4);
+
+        Ok(src.read_u32())
+    }
+}
+
+/// Message sent by the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrbdrcServerPdu {
+    /// [`CAPABILITIES_INTERFACE`], [`SERVER_CHANNEL_NOTIFICATION_INTERFACE`] or the interface of a device, as
+    /// announced in [`AddDevice`]
+    pub interface_id: u32,
+    pub message_id: u32,
+    pub message: ServerMessage,
+}
+
+/// Message sent by the server, without its header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerMessage {
+    ExchangeCapabilityRequest(ExchangeCapabilityRequest),
+    ChannelCreated(ChannelCreated),
+    CancelRequest(CancelRequest),
+    RegisterRequestCallback(RegisterRequestCallback),
+    IoControl(IoControl),
+    InternalIoControl(IoControl),
+    QueryDeviceText(QueryDeviceText),
+    TransferIn(TransferInRequest),
+    TransferOut(TransferOutRequest),
+    RetractDevice(RetractDevice),
+}
+
+impl UrbdrcServerPdu {
+    const NAME: &'static str = "URBDRC_SERVER_PDU";
+
+    fn header(&self) -> SharedMsgHeader {
+        let (mask, function_id) = match &self.message {
+            ServerMessage::ExchangeCapabilityRequest(_) => (STREAM_ID_NONE, FUNCTION_RIM_EXCHANGE_CAPABILITY_REQUEST),
+            ServerMessage::ChannelCreated(_) => (STREAM_ID_PROXY, FUNCTION_CHANNEL_CREATED),
+            ServerMessage::CancelRequest(_) => (STREAM_ID_PROXY, FUNCTION_CANCEL_REQUEST),
+            ServerMessage::RegisterRequestCallback(_) => (STREAM_ID_PROXY, FUNCTION_REGISTER_REQUEST_CALLBACK),
+            ServerMessage::IoControl(_) => (STREAM_ID_PROXY, FUNCTION_IO_CONTROL),
+            ServerMessage::InternalIoControl(_) => (STREAM_ID_PROXY, FUNCTION_INTERNAL_IO_CONTROL),
+            ServerMessage::QueryDeviceText(_) => (STREAM_ID_PROXY, FUNCTION_QUERY_DEVICE_TEXT),
+            ServerMessage::TransferIn(_) => (STREAM_ID_PROXY, FUNCTION_TRANSFER_IN_REQUEST),
+            ServerMessage::TransferOut(_) => (STREAM_ID_PROXY, FUNCTION_TRANSFER_OUT_REQUEST),
+            ServerMessage::RetractDevice(_) => (STREAM_ID_PROXY, FUNCTION_RETRACT_DEVICE),
+        };
+
+        SharedMsgHeader {
+            interface_id: self.interface_id,
+            mask,
+            message_id: self.message_id,
+            function_id: Some(function_id),
+        }
+    }
+}
+
+impl Encode for UrbdrcServerPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        self.header().encode(dst)?;
+
+        match &self.message {
+            ServerMessage::ExchangeCapabilityRequest(pdu) => pdu.encode(dst),
+            ServerMessage::ChannelCreated(pdu) => pdu.encode(dst),
+            ServerMessage::CancelRequest(pdu) => pdu.encode(dst),
+            ServerMessage::RegisterRequestCallback(pdu) => pdu.encode(dst),
+            ServerMessage::IoControl(pdu) | ServerMessage::InternalIoControl(pdu) => pdu.encode(dst),
+            ServerMessage::QueryDeviceText(pdu) => pdu.encode(dst),
+            ServerMessage::TransferIn(pdu) => pdu.encode(dst),
+            ServerMessage::TransferOut(pdu) => pdu.encode(dst),
+            ServerMessage::RetractDevice(pdu) => pdu.encode(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.header()
+            .size()
+            .checked_add(match &self.message {
+                ServerMessage::ExchangeCapabilityRequest(pdu) => pdu.size(),
+                ServerMessage::ChannelCreated(pdu) => pdu.size(),
+                ServerMessage::CancelRequest(pdu) => pdu.size(),
+                ServerMessage::RegisterRequestCallback(pdu) => pdu.size(),
+                ServerMessage::IoControl(pdu) | ServerMessage::InternalIoControl(pdu) => pdu.size(),
+                ServerMessage::QueryDeviceText(pdu) => pdu.size(),
+                ServerMessage::TransferIn(pdu) => pdu.size(),
+                ServerMessage::TransferOut(pdu) => pdu.size(),
+                ServerMessage::RetractDevice(pdu) => pdu.size(),
+            })
+            .expect("never overflow")
+    }
+}
+
+impl DvcEncode for UrbdrcServerPdu {}
+
+impl<'de> Decode<'de> for UrbdrcServerPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let header = SharedMsgHeader::decode(src)?;
+        let function_id = SharedMsgHeader::decode_function_id(src)?;
+
+        let message = match (header.interface_id, function_id) {
+            (CAPABILITIES_INTERFACE, FUNCTION_RIM_EXCHANGE_CAPABILITY_REQUEST) => {
+                ServerMessage::ExchangeCapabilityRequest(ExchangeCapabilityRequest::decode(src)?)
+            }
+            (SERVER_CHANNEL_NOTIFICATION_INTERFACE, FUNCTION_CHANNEL_CREATED) => {
+                ServerMessage::ChannelCreated(ChannelCreated::decode(src)?)
+            }
+            (CAPABILITIES_INTERFACE | SERVER_CHANNEL_NOTIFICATION_INTERFACE, _) => {
+                return Err(invalid_field_err!("FunctionId", "unknown function"));
+            }
+            // The other interfaces are the ones of the devices.
+            (_, FUNCTION_CANCEL_REQUEST) => ServerMessage::CancelRequest(CancelRequest::decode(src)?),
+            (_, FUNCTION_REGISTER_REQUEST_CALLBACK) => {
+                ServerMessage::RegisterRequestCallback(RegisterRequestCallback::decode(src)?)
+            }
+            (_, FUNCTION_IO_CONTROL) => ServerMessage::IoControl(IoControl::decode(src)?),
+            (_, FUNCTION_INTERNAL_IO_CONTROL) => ServerMessage::InternalIoControl(IoControl::decode(src)?),
+            (_, FUNCTION_QUERY_DEVICE_TEXT) => ServerMessage::QueryDeviceText(QueryDeviceText::decode(src)?),
+            (_, FUNCTION_TRANSFER_IN_REQUEST) => ServerMessage::TransferIn(TransferInRequest::decode(src)?),
+            (_, FUNCTION_TRANSFER_OUT_REQUEST) => ServerMessage::TransferOut(TransferOutRequest::decode(src)?),
+            (_, FUNCTION_RETRACT_DEVICE) => ServerMessage::RetractDevice(RetractDevice::decode(src)?),
+            _ => return Err(invalid_field_err!("FunctionId", "unknown device function")),
+        };
+
+        Ok(Self {
+            interface_id: header.interface_id,
+            message_id: header.message_id,
+            message,
+        })
+    }
+}
+
+/// Message sent by the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrbdrcClientPdu {
+    /// [`CAPABILITIES_INTERFACE`], [`CLIENT_CHANNEL_NOTIFICATION_INTERFACE`], [`DEVICE_SINK_INTERFACE`], the
+    /// interface of a device for responses, or the interface registered with [`RegisterRequestCallback`] for
+    /// completions
+    pub interface_id: u32,
+    /// For responses, the identifier of the request
+    pub message_id: u32,
+    pub message: ClientMessage,
+}
+
+/// Message sent by the client, without its header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientMessage {
+    /// Response to [`ServerMessage::ExchangeCapabilityRequest`]
+    ExchangeCapabilityResponse(ExchangeCapabilityResponse),
+    ChannelCreated(ChannelCreated),
+    /// Request to the server to open a channel for a new device
+    AddVirtualChannel,
+    AddDevice(AddDevice),
+    /// Response to [`ServerMessage::QueryDeviceText`]
+    QueryDeviceTextResponse(QueryDeviceTextResponse),
+    IoControlCompletion(IoControlCompletion),
+    UrbCompletion(UrbCompletion),
+    UrbCompletionNoData(UrbCompletionNoData),
+}
+
+impl UrbdrcClientPdu {
+    const NAME: &'static str = "URBDRC_CLIENT_PDU";
+
+    fn header(&self) -> SharedMsgHeader {
+        let (mask, function_id) = match &self.message {
+            ClientMessage::ExchangeCapabilityResponse(_) => (STREAM_ID_NONE, None),
+            ClientMessage::ChannelCreated(_) => (STREAM_ID_PROXY, Some(FUNCTION_CHANNEL_CREATED)),
+            ClientMessage::AddVirtualChannel => (STREAM_ID_PROXY, Some(FUNCTION_ADD_VIRTUAL_CHANNEL)),
+            ClientMessage::AddDevice(_) => (STREAM_ID_PROXY, Some(FUNCTION_ADD_DEVICE)),
+            ClientMessage::QueryDeviceTextResponse(_) => (STREAM_ID_STUB, None),
+            ClientMessage::IoControlCompletion(_) => (STREAM_ID_PROXY, Some(FUNCTION_IOCONTROL_COMPLETION)),
+            ClientMessage::UrbCompletion(_) => (STREAM_ID_PROXY, Some(FUNCTION_URB_COMPLETION)),
+            ClientMessage::UrbCompletionNoData(_) => (STREAM_ID_PROXY, Some(FUNCTION_URB_COMPLETION_NO_DATA)),
+        };
+
+        SharedMsgHeader {
+            interface_id: self.interface_id,
+            mask,
+            message_id: self.message_id,
+            function_id,
+        }
+    }
+}
+
+impl Encode for UrbdrcClientPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        self.header().encode(dst)?;
+
+        match &self.message {
+            ClientMessage::ExchangeCapabilityResponse(pdu) => pdu.encode(dst),
+            ClientMessage::ChannelCreated(pdu) => pdu.encode(dst),
+            ClientMessage::AddVirtualChannel => Ok(()),
+            ClientMessage::AddDevice(pdu) => pdu.encode(dst),
+            ClientMessage::QueryDeviceTextResponse(pdu) => pdu.encode(dst),
+            ClientMessage::IoControlCompletion(pdu) => pdu.encode(dst),
+            ClientMessage::UrbCompletion(pdu) => pdu.encode(dst),
+            ClientMessage::UrbCompletionNoData(pdu) => pdu.encode(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.header()
+            .size()
+            .checked_add(match &self.message {
+                ClientMessage::ExchangeCapabilityResponse(pdu) => pdu.size(),
+                ClientMessage::ChannelCreated(pdu) => pdu.size(),
+                ClientMessage::AddVirtualChannel => 0,
+                ClientMessage::AddDevice(pdu) => pdu.size(),
+                ClientMessage::QueryDeviceTextResponse(pdu) => pdu.size(),
+                ClientMessage::IoControlCompletion(pdu) => pdu.size(),
+                ClientMessage::UrbCompletion(pdu) => pdu.size(),
+                ClientMessage::UrbCompletionNoData(pdu) => pdu.size(),
+            })
+            .expect("never overflow")
+    }
+}
+
+impl DvcEncode for UrbdrcClientPdu {}
+
+impl<'de> Decode<'de> for UrbdrcClientPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let header = SharedMsgHeader::decode(src)?;
+
+        // Responses don't have a function identifier.
+        let message = if header.interface_id == CAPABILITIES_INTERFACE {
+            ClientMessage::ExchangeCapabilityResponse(ExchangeCapabilityResponse::decode(src)?)
+        } else if header.mask == STREAM_ID_STUB {
+            ClientMessage::QueryDeviceTextResponse(QueryDeviceTextResponse::decode(src)?)
+        } else {
+            let function_id = SharedMsgHeader::decode_function_id(src)?;
+
+            match (header.interface_id, function_id) {
+                (CLIENT_CHANNEL_NOTIFICATION_INTERFACE, FUNCTION_CHANNEL_CREATED) => {
+                    ClientMessage::ChannelCreated(ChannelCreated::decode(src)?)
+                }
+                (DEVICE_SINK_INTERFACE, FUNCTION_ADD_VIRTUAL_CHANNEL) => ClientMessage::AddVirtualChannel,
+                (DEVICE_SINK_INTERFACE, FUNCTION_ADD_DEVICE) => ClientMessage::AddDevice(AddDevice::decode(src)?),
+                (CLIENT_CHANNEL_NOTIFICATION_INTERFACE | DEVICE_SINK_INTERFACE, _) => {
+                    return Err(invalid_field_err!("FunctionId", "unknown function"));
+                }
+                // The other interfaces are the ones registered for the completions.
+                (_, FUNCTION_IOCONTROL_COMPLETION) => {
+                    ClientMessage::IoControlCompletion(IoControlCompletion::decode(src)?)
+                }
+                (_, FUNCTION_URB_COMPLETION) => ClientMessage::UrbCompletion(UrbCompletion::decode(src)?),
+                (_, FUNCTION_URB_COMPLETION_NO_DATA) => {
+                    ClientMessage::UrbCompletionNoData(UrbCompletionNoData::decode(src)?)
+                }
+                _ => return Err(invalid_field_err!("FunctionId", "unknown completion function")),
+            }
+        };
+
+        Ok(Self {
+            interface_id: header.interface_id,
+            message_id: header.message_id,
+            message,
+        })
+    }
+}
+
+/// 2.2.3.1 Interface Manipulation Exchange Capabilities Request (RIM_EXCHANGE_CAPABILITY_REQUEST)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeCapabilityRequest {
+    /// [`RIM_CAPABILITY_VERSION_01`]
+    pub capability_value: u32,
+}
+
+impl ExchangeCapabilityRequest {
+    const NAME: &'static str = "RIM_EXCHANGE_CAPABILITY_REQUEST";
+
+    const FIXED_PART_SIZE: usize = 4 /* CapabilityValue */;
+}
+
+impl Encode for ExchangeCapabilityRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.capability_value);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ExchangeCapabilityRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            capability_value: src.read_u32(),
+        })
+    }
+}
+
+/// 2.2.3.2 Interface Manipulation Exchange Capabilities Response (RIM_EXCHANGE_CAPABILITY_RESPONSE)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeCapabilityResponse {
+    /// [`RIM_CAPABILITY_VERSION_01`]
+    pub capability_value: u32,
+    /// HRESULT of the exchange
+    pub result: u32,
+}
+
+impl ExchangeCapabilityResponse {
+    const NAME: &'static str = "RIM_EXCHANGE_CAPABILITY_RESPONSE";
+
+    const FIXED_PART_SIZE: usize = 4 /* CapabilityValue */ + 4 /* Result */;
+}
+
+impl Encode for ExchangeCapabilityResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.capability_value);
+        dst.write_u32(self.result);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ExchangeCapabilityResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            capability_value: src.read_u32(),
+            result: src.read_u32(),
+        })
+    }
+}
+
+/// 2.2.5.1 Channel Created Message (CHANNEL_CREATED)
+///
+/// Sent by the server when it opens a channel, and echoed by the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelCreated {
+    pub major_version: u32,
+    pub minor_version: u32,
+    pub capabilities: u32,
+}
+
+impl ChannelCreated {
+    const NAME: &'static str = "CHANNEL_CREATED";
+
+    const FIXED_PART_SIZE: usize = 4 /* MajorVersion */ + 4 /* MinorVersion */ + 4 /* Capabilities */;
+
+    /// Version 1.0, the only version of the protocol
+    pub const V1_0: Self = Self {
+        major_version: 1,
+        minor_version: 0,
+        capabilities: 0,
+    };
+}
+
+impl Encode for ChannelCreated {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.major_version);
+        dst.write_u32(self.minor_version);
+        dst.write_u32(self.capabilities);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ChannelCreated {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            major_version: src.read_u32(),
+            minor_version: src.read_u32(),
+            capabilities: src.read_u32(),
+        })
+    }
+}
+
+/// 2.2.4.2 Add Device Message (ADD_DEVICE)
+///
+/// Announces a device on the channel the server opened after [`ClientMessage::AddVirtualChannel`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddDevice {
+    /// Interface of the device, chosen by the client, to which the server sends the requests for the device
+    pub usb_device: u32,
+    pub device: UsbDevice,
+}
+
+impl AddDevice {
+    const NAME: &'static str = "ADD_DEVICE";
+
+    const FIXED_PART_SIZE: usize = 4 /* NumUsbDevice */ + 4 /* UsbDevice */ + 4 /* cchDeviceInstanceId */
+        + 4 /* cchHwIds */ + 4 /* cchCompatIds */ + 4 /* cchContainerId */;
+}
+
+impl Encode for AddDevice {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        let device = &self.device;
+
+        // A single device is announced per message.
+        dst.write_u32(1);
+        dst.write_u32(self.usb_device);
+        dst.write_u32(cast_length!(
+            "cchDeviceInstanceId",
+            cch_string(&device.device_instance_id)
+        )?);
+        write_string_to_cursor(dst, &device.device_instance_id, CharacterSet::Unicode, true)?;
+        dst.write_u32(cast_length!("cchHwIds", cch_multistring(&device.hardware_ids))?);
+        write_multistring_to_cursor(dst, &device.hardware_ids, CharacterSet::Unicode)?;
+        dst.write_u32(cast_length!(
+            "cchCompatIds",
+            cch_multistring(&device.compatibility_ids)
+        )?);
+        write_multistring_to_cursor(dst, &device.compatibility_ids, CharacterSet::Unicode)?;
+        dst.write_u32(cast_length!("cchContainerId", cch_string(&device.container_id))?);
+        write_string_to_cursor(dst, &device.container_id, CharacterSet::Unicode, true)?;
+        device.capabilities.encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        let device = &self.device;
+
+        Self::FIXED_PART_SIZE
+            + encoded_str_len(&device.device_instance_id, CharacterSet::Unicode, true)
+            + encoded_multistring_len(&device.hardware_ids, CharacterSet::Unicode)
+            + encoded_multistring_len(&device.compatibility_ids, CharacterSet::Unicode)
+            + encoded_str_len(&device.container_id, CharacterSet::Unicode, true)
+            + device.capabilities.size()
+    }
+}
+
+impl<'de> Decode<'de> for AddDevice {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: 4 /* NumUsbDevice */ + 4 /* UsbDevice */);
+
+        let num_usb_device = src.read_u32();
+        if num_usb_device != 1 {
+            return Err(invalid_field_err!("NumUsbDevice", "a single device is expected"));
+        }
+        let usb_device = src.read_u32();
+
+        let device_instance_id = decode_unicode_string(src, "cchDeviceInstanceId")?;
+        let hardware_ids = decode_unicode_multistring(src, "cchHwIds")?;
+        let compatibility_ids = decode_unicode_multistring(src, "cchCompatIds")?;
+        let container_id = decode_unicode_string(src, "cchContainerId")?;
+        let capabilities = UsbDeviceCapabilities::decode(src)?;
+
+        Ok(Self {
+            usb_device,
+            device: UsbDevice {
+                device_instance_id,
+                hardware_ids,
+                compatibility_ids,
+                container_id,
+                capabilities,
+            },
+        })
+    }
+}
+
+/// USB device redirected by the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsbDevice {
+    /// Instance identifier of the device, e.g. `USB\VID_046D&PID_C52B\5&2A8F5C7B&0&1`
+    pub device_instance_id: String,
+    /// Hardware identifiers, from the most to the least specific, e.g. `USB\VID_046D&PID_C52B&REV_1201`
+    pub hardware_ids: Vec<String>,
+    /// Compatible identifiers, e.g. `USB\Class_03&SubClass_01&Prot_01`
+    pub compatibility_ids: Vec<String>,
+    /// Identifier of the physical device the device is part of, as a GUID string
+    pub container_id: String,
+    pub capabilities: UsbDeviceCapabilities,
+}
+
+/// 2.2.11 USB_DEVICE_CAPABILITIES
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsbDeviceCapabilities {
+    /// USB bus interface version, 0, 1 or 2
+    pub usb_bus_interface_version: u32,
+    /// USBDI version, `0x500` or `0x600`
+    pub usbdi_version: u32,
+    /// USB specification version supported by the device, e.g. `0x200` for USB 2.0
+    pub supported_usb_version: u32,
+    /// Host controller capabilities, 0 or `USB_HCD_CAPS_SUPPORTS_RT_THREADS` (1)
+    pub hcd_capabilities: u32,
+    pub device_is_high_speed: bool,
+    /// Jitter buffer for the isochronous OUT transfers sent without acknowledgement, 0 to disable those, 10 to 512 ms
+    /// otherwise
+    pub no_ack_isoch_write_jitter_buffer_size_in_ms: u32,
+}
+
+impl UsbDeviceCapabilities {
+    const NAME: &'static str = "USB_DEVICE_CAPABILITIES";
+
+    const FIXED_PART_SIZE: usize = 4 /* CbSize */ + 4 /* UsbBusInterfaceVersion */ + 4 /* USBDI_Version */
+        + 4 /* Supported_USB_Version */ + 4 /* HcdCapabilities */ + 4 /* DeviceIsHighSpeed */
+        + 4 /* NoAckIsochWriteJitterBufferSizeInMs */;
+}
+
+impl Default for UsbDeviceCapabilities {
+    /// USB 2.0 high speed device
+    fn default() -> Self {
+        Self {
+            usb_bus_interface_version: 2,
+            usbdi_version: 0x600,
+            supported_usb_version: 0x200,
+            hcd_capabilities: 0,
+            device_is_high_speed: true,
+            no_ack_isoch_write_jitter_buffer_size_in_ms: 0,
+        }
+    }
+}
+
+impl Encode for UsbDeviceCapabilities {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(cast_length!("CbSize", Self::FIXED_PART_SIZE)?);
+        dst.write_u32(self.usb_bus_interface_version);
+        dst.write_u32(self.usbdi_version);
+        dst.write_u32(self.supported_usb_version);
+        dst.write_u32(self.hcd_capabilities);
+        dst.write_u32(u32::from(self.device_is_high_speed));
+        dst.write_u32(self.no_ack_isoch_write_jitter_buffer_size_in_ms);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for UsbDeviceCapabilities {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let cb_size: usize = cast_length!("CbSize", src.read_u32())?;
+        if cb_size != Self::FIXED_PART_SIZE {
+            return Err(invalid_field_err!("CbSize", "invalid capabilities size"));
+        }
+
+        Ok(Self {
+            usb_bus_interface_version: src.read_u32(),
+            usbdi_version: src.read_u32(),
+            supported_usb_version: src.read_u32(),
+            hcd_capabilities: src.read_u32(),
+            device_is_high_speed: src.read_u32() != 0,
+            no_ack_isoch_write_jitter_buffer_size_in_ms: src.read_u32(),
+        })
+    }
+}
+
+/// 2.2.6.1 Cancel Request Message (CANCEL_REQUEST)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CancelRequest {
+    /// Identifier of the [`IoControl`] or the [`TsUrb`] to cancel
+    pub request_id: u32,
+}
+
+impl CancelRequest {
+    const NAME: &'static str = "CANCEL_REQUEST";
+
+    const FIXED_PART_SIZE: usize = 4 /* RequestId */;
+}
+
+impl Encode for CancelRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.request_id);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for CancelRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            request_id: src.read_u32(),
+        })
+    }
+}
+
+/// 2.2.6.2 Register Request Callback Message (REGISTER_REQUEST_CALLBACK)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterRequestCallback {
+    /// Interface to which the client sends the completions of the requests, `None` to stop sending them
+    pub request_completion: Option<u32>,
+}
+
+impl RegisterRequestCallback {
+    const NAME: &'static str = "REGISTER_REQUEST_CALLBACK";
+
+    const FIXED_PART_SIZE: usize = 4 /* NumRequestCompletion */;
+}
+
+impl Encode for RegisterRequestCallback {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        match self.request_completion {
+            Some(request_completion) => {
+                dst.write_u32(1);
+                dst.write_u32(request_completion);
+            }
+            None => dst.write_u32(0),
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + if self.request_completion.is_some() { 4 } else { 0 }
+    }
+}
+
+impl<'de> Decode<'de> for RegisterRequestCallback {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let request_completion = if src.read_u32() == 0 {
+            None
+        } else {
+            ensure_size!(in: src, size: 4 /* RequestCompletion */);
+            Some(src.read_u32())
+        };
+
+        Ok(Self { request_completion })
+    }
+}
+
+/// 2.2.6.3 I/O Control Message (IO_CONTROL) and 2.2.6.4 Internal I/O Control Message (INTERNAL_IO_CONTROL)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoControl {
+    pub io_control_code: u32,
+    pub input_buffer: Vec<u8>,
+    /// Maximum size of the output of the request
+    pub output_buffer_size: u32,
+    pub request_id: u32,
+}
+
+impl IoControl {
+    const NAME: &'static str = "IO_CONTROL";
+
+    const FIXED_PART_SIZE: usize =
+        4 /* IoControlCode */ + 4 /* InputBufferSize */ + 4 /* OutputBufferSize */ + 4 /* RequestId */;
+}
+
+impl Encode for IoControl {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.io_control_code);
+        dst.write_u32(cast_length!("InputBufferSize", self.input_buffer.len())?);
+        dst.write_slice(&self.input_buffer);
+        dst.write_u32(self.output_buffer_size);
+        dst.write_u32(self.request_id);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.input_buffer.len()
+    }
+}
+
+impl<'de> Decode<'de> for IoControl {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: 4 /* IoControlCode */ + 4 /* InputBufferSize */);
+
+        let io_control_code = src.read_u32();
+        let input_buffer_size = cast_length!("InputBufferSize", src.read_u32())?;
+
+        ensure_size!(in: src, size: input_buffer_size + 4 /* OutputBufferSize */ + 4 /* RequestId */);
+        let input_buffer = src.read_slice(input_buffer_size).to_vec();
+        let output_buffer_size = src.read_u32();
+        let request_id = src.read_u32();
+
+        Ok(Self {
+            io_control_code,
+            input_buffer,
+            output_buffer_size,
+            request_id,
+        })
+    }
+}
+
+/// 2.2.6.5 Query Device Text Message (QUERY_DEVICE_TEXT)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryDeviceText {
+    /// 0 for the description of the device, 1 for its location
+    pub text_type: u32,
+    pub locale_id: u32,
+}
+
+impl QueryDeviceText {
+    const NAME: &'static str = "QUERY_DEVICE_TEXT";
+
+    const FIXED_PART_SIZE: usize = 4 /* TextType */ + 4 /* LocaleId */;
+
+    pub const DEVICE_TEXT_DESCRIPTION: u32 = 0;
+    pub const DEVICE_TEXT_LOCATION_INFORMATION: u32 = 1;
+}
+
+impl Encode for QueryDeviceText {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.text_type);
+        dst.write_u32(self.locale_id);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for QueryDeviceText {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            text_type: src.read_u32(),
+            locale_id: src.read_u32(),
+        })
+    }
+}
+
+/// 2.2.6.6 Query Device Text Response Message (QUERY_DEVICE_TEXT_RSP)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryDeviceTextResponse {
+    pub device_description: String,
+    pub hresult: u32,
+}
+
+impl QueryDeviceTextResponse {
+    const NAME: &'static str = "QUERY_DEVICE_TEXT_RSP";
+
+    const FIXED_PART_SIZE: usize = 4 /* cchDeviceDescription */ + 4 /* HResult */;
+}
+
+impl Encode for QueryDeviceTextResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(cast_length!(
+            "cchDeviceDescription",
+            cch_string(&self.device_description)
+        )?);
+        write_string_to_cursor(dst, &self.device_description, CharacterSet::Unicode, true)?;
+        dst.write_u32(self.hresult);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + encoded_str_len(&self.device_description, CharacterSet::Unicode, true)
+    }
+}
+
+impl<'de> Decode<'de> for QueryDeviceTextResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let device_description = decode_unicode_string(src, "cchDeviceDescription")?;
+
+        ensure_size!(in: src, size: 4 /* HResult */);
+        let hresult = src.read_u32();
+
+        Ok(Self {
+            device_description,
+            hresult,
+        })
+    }
+}
+
+/// 2.2.6.7 Transfer In Request (TRANSFER_IN_REQUEST)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferInRequest {
+    pub ts_urb: TsUrb,
+    /// Maximum size of the data read from the device
+    pub output_buffer_size: u32,
+}
+
+impl TransferInRequest {
+    const NAME: &'static str = "TRANSFER_IN_REQUEST";
+
+    const FIXED_PART_SIZE: usize = 4 /* CbTsUrb */ + 4 /* OutputBufferSize */;
+}
+
+impl Encode for TransferInRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(cast_length!("CbTsUrb", self.ts_urb.size())?);
+        self.ts_urb.encode(dst)?;
+        dst.write_u32(self.output_buffer_size);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.ts_urb.size()
+    }
+}
+
+impl<'de> Decode<'de> for TransferInRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let ts_urb = TsUrb::decode_sized(src)?;
+
+        ensure_size!(in: src, size: 4 /* OutputBufferSize */);
+        let output_buffer_size = src.read_u32();
+
+        Ok(Self {
+            ts_urb,
+            output_buffer_size,
+        })
+    }
+}
+
+/// 2.2.6.8 Transfer Out Request (TRANSFER_OUT_REQUEST)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferOutRequest {
+    pub ts_urb: TsUrb,
+    /// Data written to the device
+    pub output_buffer: Vec<u8>,
+}
+
+impl TransferOutRequest {
+    const NAME: &'static str = "TRANSFER_OUT_REQUEST";
+
+    const FIXED_PART_SIZE: usize = 4 /* CbTsUrb */ + 4 /* OutputBufferSize */;
+}
+
+impl Encode for TransferOutRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(cast_length!("CbTsUrb", self.ts_urb.size())?);
+        self.ts_urb.encode(dst)?;
+        dst.write_u32(cast_length!("OutputBufferSize", self.output_buffer.len())?);
+        dst.write_slice(&self.output_buffer);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.ts_urb.size() + self.output_buffer.len()
+    }
+}
+
+impl<'de> Decode<'de> for TransferOutRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let ts_urb = TsUrb::decode_sized(src)?;
+
+        ensure_size!(in: src, size: 4 /* OutputBufferSize */);
+        let output_buffer_size = cast_length!("OutputBufferSize", src.read_u32())?;
+        ensure_size!(in: src, size: output_buffer_size);
+        let output_buffer = src.read_slice(output_buffer_size).to_vec();
+
+        Ok(Self { ts_urb, output_buffer })
+    }
+}
+
+/// 2.2.6.9 Retract Device (RETRACT_DEVICE)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetractDevice {
+    /// [`USB_RETRACT_REASON_BLOCKED_BY_POLICY`]
+    pub reason: u32,
+}
+
+impl RetractDevice {
+    const NAME: &'static str = "RETRACT_DEVICE";
+
+    const FIXED_PART_SIZE: usize = 4 /* Reason */;
+}
+
+impl Encode for RetractDevice {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.reason);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for RetractDevice {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self { reason: src.read_u32() })
+    }
+}
+
+/// 2.2.7.1 I/O Control Completion Message (IOCONTROL_COMPLETION)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoControlCompletion {
+    pub request_id: u32,
+    pub hresult: u32,
+    /// Number of bytes of output written by the device
+    pub information: u32,
+    pub output_buffer: Vec<u8>,
+}
+
+impl IoControlCompletion {
+    const NAME: &'static str = "IOCONTROL_COMPLETION";
+
+    const FIXED_PART_SIZE: usize = 4 /* RequestId */ + 4 /* HResult */ + 4 /* Information */ + 4 /* OutputBufferSize */;
+}
+
+impl Encode for IoControlCompletion {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.request_id);
+        dst.write_u32(self.hresult);
+        dst.write_u32(self.information);
+        dst.write_u32(cast_length!("OutputBufferSize", self.output_buffer.len())?);
+        dst.write_slice(&self.output_buffer);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.output_buffer.len()
+    }
+}
+
+impl<'de> Decode<'de> for IoControlCompletion {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let request_id = src.read_u32();
+        let hresult = src.read_u32();
+        let information = src.read_u32();
+        let output_buffer_size = cast_length!("OutputBufferSize", src.read_u32())?;
+        ensure_size!(in: src, size: output_buffer_size);
+        let output_buffer = src.read_slice(output_buffer_size).to_vec();
+
+        Ok(Self {
+            request_id,
+            hresult,
+            information,
+            output_buffer,
+        })
+    }
+}
+
+/// 2.2.7.2 URB Completion (URB_COMPLETION)
+///
+/// Completion of a [`TransferInRequest`], with the data read from the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrbCompletion {
+    pub request_id: u32,
+    pub ts_urb_result: TsUrbResult,
+    pub hresult: u32,
+    pub output_buffer: Vec<u8>,
+}
+
+impl UrbCompletion {
+    const NAME: &'static str = "URB_COMPLETION";
+
+    const FIXED_PART_SIZE: usize = 4 /* RequestId */ + 4 /* CbTsUrbResult */ + 4 /* HResult */ + 4 /* OutputBufferSize */;
+}
+
+impl Encode for UrbCompletion {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.request_id);
+        dst.write_u32(cast_length!("CbTsUrbResult", self.ts_urb_result.size())?);
+        self.ts_urb_result.encode(dst)?;
+        dst.write_u32(self.hresult);
+        dst.write_u32(cast_length!("OutputBufferSize", self.output_buffer.len())?);
+        dst.write_slice(&self.output_buffer);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.ts_urb_result.size() + self.output_buffer.len()
+    }
+}
+
+impl<'de> Decode<'de> for UrbCompletion {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: 4 /* RequestId */);
+        let request_id = src.read_u32();
+        let ts_urb_result = TsUrbResult::decode_sized(src)?;
+
+        ensure_size!(in: src, size: 4 /* HResult */ + 4 /* OutputBufferSize */);
+        let hresult = src.read_u32();
+        let output_buffer_size = cast_length!("OutputBufferSize", src.read_u32())?;
+        ensure_size!(in: src, size: output_buffer_size);
+        let output_buffer = src.read_slice(output_buffer_size).to_vec();
+
+        Ok(Self {
+            request_id,
+            ts_urb_result,
+            hresult,
+            output_buffer,
+        })
+    }
+}
+
+/// 2.2.7.3 URB Completion No Data (URB_COMPLETION_NO_DATA)
+///
+/// Completion of a [`TransferOutRequest`], or of a [`TransferInRequest`] which didn't read any data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrbCompletionNoData {
+    pub request_id: u32,
+    pub ts_urb_result: TsUrbResult,
+    pub hresult: u32,
+    /// Number of bytes written to the device
+    pub output_buffer_size: u32,
+}
+
+impl UrbCompletionNoData {
+    const NAME: &'static str = "URB_COMPLETION_NO_DATA";
+
+    const FIXED_PART_SIZE: usize = 4 /* RequestId */ + 4 /* CbTsUrbResult */ + 4 /* HResult */ + 4 /* OutputBufferSize */;
+}
+
+impl Encode for UrbCompletionNoData {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.request_id);
+        dst.write_u32(cast_length!("CbTsUrbResult", self.ts_urb_result.size())?);
+        self.ts_urb_result.encode(dst)?;
+        dst.write_u32(self.hresult);
+        dst.write_u32(self.output_buffer_size);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.ts_urb_result.size()
+    }
+}
+
+impl<'de> Decode<'de> for UrbCompletionNoData {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: 4 /* RequestId */);
+        let request_id = src.read_u32();
+        let ts_urb_result = TsUrbResult::decode_sized(src)?;
+
+        ensure_size!(in: src, size: 4 /* HResult */ + 4 /* OutputBufferSize */);
+        let hresult = src.read_u32();
+        let output_buffer_size = src.read_u32();
+
+        Ok(Self {
+            request_id,
+            ts_urb_result,
+            hresult,
+            output_buffer_size,
+        })
+    }
+}
+
+/// 2.2.9 TS_URB
+///
+/// USB request block of a transfer. Only the header is decoded, the structure specific to the URB function is kept
+/// as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsUrb {
+    /// URB function, e.g. `URB_FUNCTION_BULK_OR_INTERRUPT_TRANSFER` (`0x0009`)
+    pub urb_function: u16,
+    /// Identifier of the request, 31 bits
+    pub request_id: u32,
+    /// The server doesn't expect a completion, only for OUT transfers
+    pub no_ack: bool,
+    /// Structure specific to the URB function, following the header
+    pub data: Vec<u8>,
+}
+
+impl TsUrb {
+    const NAME: &'static str = "TS_URB";
+
+    const FIXED_PART_SIZE: usize = 2 /* Size */ + 2 /* URB_Function */ + 4 /* RequestId + NoAck */;
+
+    const NO_ACK: u32 = 0x8000_0000;
+
+    /// Decodes a URB preceded by its size.
+    fn decode_sized(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 4 /* CbTsUrb */);
+        let size = cast_length!(Self::NAME, "CbTsUrb", src.read_u32())?;
+
+        ensure_size!(ctx: Self::NAME, in: src, size: size);
+        Self::decode(&mut ReadCursor::new(src.read_slice(size)))
+    }
+}
+
+impl Encode for TsUrb {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(cast_length!("Size", self.size())?);
+        dst.write_u16(self.urb_function);
+        let no_ack = if self.no_ack { Self::NO_ACK } else { 0 };
+        dst.write_u32((self.request_id & !Self::NO_ACK) | no_ack);
+        dst.write_slice(&self.data);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.data.len()
+    }
+}
+
+impl<'de> Decode<'de> for TsUrb {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let size = usize::from(src.read_u16());
+        let urb_function = src.read_u16();
+        let request_id = src.read_u32();
+
+        let data_size = size
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("Size", "URB smaller than its header"))?;
+        ensure_size!(in: src, size: data_size);
+        let data = src.read_slice(data_size).to_vec();
+
+        Ok(Self {
+            urb_function,
+            request_id: request_id & !Self::NO_ACK,
+            no_ack: request_id & Self::NO_ACK != 0,
+            data,
+        })
+    }
+}
+
+/// 2.2.10 TS_URB_RESULT
+///
+/// Result of a [`TsUrb`]. Only the header is decoded, the structure specific to the URB function is kept as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsUrbResult {
+    /// USBD status of the URB, `USBD_STATUS_SUCCESS` (0) on success
+    pub usbd_status: u32,
+    /// Structure specific to the URB function, following the header
+    pub data: Vec<u8>,
+}
+
+impl TsUrbResult {
+    const NAME: &'static str = "TS_URB_RESULT";
+
+    const FIXED_PART_SIZE: usize = 2 /* Size */ + 2 /* Padding */ + 4 /* UsbdStatus */;
+
+    /// Decodes a URB result preceded by its size.
+    fn decode_sized(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 4 /* CbTsUrbResult */);
+        let size = cast_length!(Self::NAME, "CbTsUrbResult", src.read_u32())?;
+
+        ensure_size!(ctx: Self::NAME, in: src, size: size);
+        Self::decode(&mut ReadCursor::new(src.read_slice(size)))
+    }
+}
+
+impl Encode for TsUrbResult {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(cast_length!("Size", self.size())?);
+        dst.write_u16(0);
+        dst.write_u32(self.usbd_status);
+        dst.write_slice(&self.data);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.data.len()
+    }
+}
+
+impl<'de> Decode<'de> for TsUrbResult {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let size = usize::from(src.read_u16());
+        let _padding = src.read_u16();
+        let usbd_status = src.read_u32();
+
+        let data_size = size
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("Size", "URB result smaller than its header"))?;
+        ensure_size!(in: src, size: data_size);
+        let data = src.read_slice(data_size).to_vec();
+
+        Ok(Self { usbd_status, data })
+    }
+}
+
+/// Number of UTF-16 code units of a null-terminated string
+fn cch_string(value: &str) -> usize {
+    encoded_str_len(value, CharacterSet::Unicode, true) / 2
+}
+
+/// Number of UTF-16 code units of a multi-string, its terminating null included
+fn cch_multistring(values: &[String]) -> usize {
+    encoded_multistring_len(values, CharacterSet::Unicode) / 2
+}
+
+/// Decodes a null-terminated UTF-16 string preceded by its number of code units.
+fn decode_unicode_string(src: &mut ReadCursor<'_>, field: &'static str) -> DecodeResult<String> {
+    let data = read_counted_unicode(src, field)?;
+
+    decode_string(data, CharacterSet::Unicode, true)
+}
+
+/// Decodes a UTF-16 multi-string preceded by its number of code units.
+fn decode_unicode_multistring(src: &mut ReadCursor<'_>, field: &'static str) -> DecodeResult<Vec<String>> {
+    let data = read_counted_unicode(src, field)?;
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    read_multistring_from_cursor(&mut ReadCursor::new(data), CharacterSet::Unicode)
+}
+
+fn read_counted_unicode<'a>(src: &mut ReadCursor<'a>, field: &'static str) -> DecodeResult<&'a [u8]> {
+    ensure_size!(ctx: field, in: src, size: 4);
+    let cch: usize = cast_length!(field, src.read_u32())?;
+    let size = cch
+        .checked_mul(2)
+        .ok_or_else(|| invalid_field_err!(field, "too many characters"))?;
+
+    ensure_size!(ctx: field, in: src, size: size);
+    Ok(src.read_slice(size))
+}
@@ -0,0 +1,369 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use ironrdp_core::{decode, impl_as_any};
+use ironrdp_dvc::{encode_dvc_messages, DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tracing::{debug, warn};
+
+use crate::pdu::{
+    ClientNotification, GeometryPdu, GeometryUpdate, MappedGeometry, Notification, PresentationCommand,
+    PresentationRequest, PresentationResponse, VideoData, VideoDataFlags, VideoPdu,
+};
+use crate::{CONTROL_CHANNEL_NAME, DATA_CHANNEL_NAME, GEOMETRY_CHANNEL_NAME};
+
+/// Decoder and renderer of the videos played in the session
+///
+/// The samples are handed over in decoding order, once all their packets are received.
+pub trait VideoPresenter: Send + core::fmt::Debug {
+    /// The server started a presentation, returns whether the video can be presented
+    ///
+    /// `geometry` is the area covered by the video, when the mapping referenced by the request is already known.
+    /// Declined presentations are not acknowledged and their samples are dropped.
+    fn start_presentation(&mut self, request: &PresentationRequest, geometry: Option<&MappedGeometry>) -> bool;
+
+    /// The server stopped a presentation, or the control channel was closed
+    fn stop_presentation(&mut self, presentation_id: u8);
+
+    /// A sample of a started presentation was received
+    fn sample(&mut self, presentation_id: u8, sample: VideoSample);
+
+    /// The area covered by a mapping changed, `geometry` is `None` once the mapping is removed
+    fn geometry_changed(&mut self, mapping_id: u64, geometry: Option<&MappedGeometry>) {
+        let _ = (mapping_id, geometry);
+    }
+
+    /// The control channel was closed, after all the presentations were stopped
+    fn closed(&mut self) {}
+}
+
+/// Encoded sample of a presentation, reassembled from its packets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoSample {
+    pub sample_number: u32,
+    /// Presentation time of the sample, in 100 ns units, when given by the server
+    ///
+    /// The timestamps are relative to the [`PresentationRequest::timestamp_offset`].
+    pub timestamp: Option<u64>,
+    /// Duration of the sample, in 100 ns units, when given by the server
+    pub duration: Option<u64>,
+    /// Whether decoding can start from the sample
+    pub keyframe: bool,
+    pub data: Vec<u8>,
+}
+
+impl VideoSample {
+    fn new(packet: VideoData) -> Self {
+        let has_timestamps = packet.flags.contains(VideoDataFlags::HAS_TIMESTAMPS);
+
+        Self {
+            sample_number: packet.sample_number,
+            timestamp: has_timestamps.then_some(packet.timestamp),
+            duration: has_timestamps.then_some(packet.duration),
+            keyframe: packet.flags.contains(VideoDataFlags::KEYFRAME),
+            data: packet.sample,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Presentation {
+    /// Sample being reassembled, along with the index of its last received packet
+    pending: Option<(VideoSample, u16)>,
+}
+
+#[derive(Debug)]
+struct VideoState {
+    presenter: Box<dyn VideoPresenter>,
+    presentations: BTreeMap<u8, Presentation>,
+    geometries: BTreeMap<u64, MappedGeometry>,
+}
+
+impl VideoState {
+    fn stop_presentations(&mut self) {
+        for (presentation_id, _) in core::mem::take(&mut self.presentations) {
+            self.presenter.stop_presentation(presentation_id);
+        }
+    }
+
+    fn on_presentation_request(&mut self, request: PresentationRequest) -> Option<PresentationResponse> {
+        let presentation_id = request.presentation_id;
+
+        // A presentation is restarted with the same identifier when the video changes.
+        if self.presentations.remove(&presentation_id).is_some() {
+            self.presenter.stop_presentation(presentation_id);
+        }
+
+        match request.command {
+            PresentationCommand::Start => {
+                let geometry = self.geometries.get(&request.geometry_mapping_id);
+                if !self.presenter.start_presentation(&request, geometry) {
+                    warn!(presentation_id, "Video presentation declined");
+                    return None;
+                }
+
+                self.presentations.insert(presentation_id, Presentation::default());
+
+                Some(PresentationResponse { presentation_id })
+            }
+            PresentationCommand::Stop => None,
+        }
+    }
+
+    fn on_video_data(&mut self, packet: VideoData) {
+        let presentation_id = packet.presentation_id;
+        let Some(presentation) = self.presentations.get_mut(&presentation_id) else {
+            debug!(presentation_id, "Video data of an unknown presentation");
+            return;
+        };
+
+        let packet_index = packet.current_packet_index;
+        let packets_in_sample = packet.packets_in_sample;
+
+        let sample = match presentation.pending.take() {
+            _ if packet_index <= 1 => VideoSample::new(packet),
+            Some((mut sample, last_index))
+                if sample.sample_number == packet.sample_number && last_index.checked_add(1) == Some(packet_index) =>
+            {
+                sample.data.extend_from_slice(&packet.sample);
+                sample
+            }
+            _ => {
+                warn!(
+                    presentation_id,
+                    sample_number = packet.sample_number,
+                    packet_index,
+                    "Missing video packet, sample dropped"
+                );
+                return;
+            }
+        };
+
+        if packet_index >= packets_in_sample {
+            self.presenter.sample(presentation_id, sample);
+        } else {
+            presentation.pending = Some((sample, packet_index));
+        }
+    }
+
+    fn on_geometry(&mut self, pdu: GeometryPdu) {
+        match pdu.update {
+            GeometryUpdate::Update(geometry) => {
+                self.presenter.geometry_changed(pdu.mapping_id, Some(&geometry));
+                self.geometries.insert(pdu.mapping_id, geometry);
+            }
+            GeometryUpdate::Clear => {
+                if self.geometries.remove(&pdu.mapping_id).is_some() {
+                    self.presenter.geometry_changed(pdu.mapping_id, None);
+                }
+            }
+        }
+    }
+}
+
+fn lock(state: &Mutex<VideoState>) -> MutexGuard<'_, VideoState> {
+    // The state stays consistent even if the presenter panicked while holding the lock
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Client side of the Video Optimized Remoting Virtual Channel Extension
+///
+/// The videos are controlled, received and positioned over three dynamic channels, each handled by its own
+/// processor. They share the [`VideoPresenter`] given here, and must all be registered on the DRDYNVC client.
+#[derive(Debug, Clone)]
+pub struct VideoRedirection {
+    state: Arc<Mutex<VideoState>>,
+}
+
+impl VideoRedirection {
+    pub fn new(presenter: Box<dyn VideoPresenter>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(VideoState {
+                presenter,
+                presentations: BTreeMap::new(),
+                geometries: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Processor of the geometry channel
+    pub fn geometry_client(&self) -> GeometryClient {
+        GeometryClient {
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    /// Processor of the control channel
+    pub fn control_client(&self) -> VideoControlClient {
+        VideoControlClient {
+            state: Arc::clone(&self.state),
+            channel_id: None,
+        }
+    }
+
+    /// Processor of the data channel
+    pub fn data_client(&self) -> VideoDataClient {
+        VideoDataClient {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+/// A client for the geometry channel, tracking the area of the session covered by each video
+#[derive(Debug)]
+pub struct GeometryClient {
+    state: Arc<Mutex<VideoState>>,
+}
+
+impl GeometryClient {
+    /// Last known area covered by a mapping
+    pub fn geometry(&self, mapping_id: u64) -> Option<MappedGeometry> {
+        lock(&self.state).geometries.get(&mapping_id).cloned()
+    }
+}
+
+impl_as_any!(GeometryClient);
+
+impl DvcProcessor for GeometryClient {
+    fn channel_name(&self) -> &str {
+        GEOMETRY_CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        // The server sends the mappings.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: GeometryPdu = decode(payload).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        lock(&self.state).on_geometry(pdu);
+
+        Ok(Vec::new())
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        lock(&self.state).geometries.clear();
+    }
+}
+
+impl DvcClientProcessor for GeometryClient {}
+
+/// A client for the control channel, starting and stopping the presentations
+#[derive(Debug)]
+pub struct VideoControlClient {
+    state: Arc<Mutex<VideoState>>,
+    channel_id: Option<u32>,
+}
+
+impl VideoControlClient {
+    /// Whether the presentation was started and accepted by the presenter
+    pub fn is_presenting(&self, presentation_id: u8) -> bool {
+        lock(&self.state).presentations.contains_key(&presentation_id)
+    }
+
+    /// Notifies the server about a presentation, e.g. to get a keyframe after the decoder failed
+    pub fn encode_notification(&self, presentation_id: u8, notification: Notification) -> PduResult<Vec<SvcMessage>> {
+        let channel_id = self
+            .channel_id
+            .ok_or_else(|| pdu_other_err!("video control channel not opened"))?;
+
+        if !self.is_presenting(presentation_id) {
+            return Err(pdu_other_err!("unknown presentation"));
+        }
+
+        encode_dvc_messages(
+            channel_id,
+            vec![Box::new(VideoPdu::ClientNotification(ClientNotification {
+                presentation_id,
+                notification,
+            }))],
+            ChannelFlags::empty(),
+        )
+        .map_err(|e| encode_err!(e))
+    }
+}
+
+impl_as_any!(VideoControlClient);
+
+impl DvcProcessor for VideoControlClient {
+    fn channel_name(&self) -> &str {
+        CONTROL_CHANNEL_NAME
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.channel_id = Some(channel_id);
+
+        // The server sends the presentation requests.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: VideoPdu = decode(payload).map_err(|e| decode_err!(e))?;
+        debug!(?pdu);
+
+        let messages: Vec<DvcMessage> = match pdu {
+            VideoPdu::PresentationRequest(request) => lock(&self.state)
+                .on_presentation_request(request)
+                .map(|response| -> DvcMessage { Box::new(VideoPdu::PresentationResponse(response)) })
+                .into_iter()
+                .collect(),
+            pdu => {
+                warn!(?pdu, "Unexpected video control PDU");
+                Vec::new()
+            }
+        };
+
+        Ok(messages)
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        self.channel_id = None;
+
+        let mut state = lock(&self.state);
+        state.stop_presentations();
+        state.presenter.closed();
+    }
+}
+
+impl DvcClientProcessor for VideoControlClient {}
+
+/// A client for the data channel, reassembling the samples of the presentations
+#[derive(Debug)]
+pub struct VideoDataClient {
+    state: Arc<Mutex<VideoState>>,
+}
+
+impl_as_any!(VideoDataClient);
+
+impl DvcProcessor for VideoDataClient {
+    fn channel_name(&self) -> &str {
+        DATA_CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        // The server sends the samples.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu: VideoPdu = decode(payload).map_err(|e| decode_err!(e))?;
+
+        match pdu {
+            VideoPdu::Data(packet) => lock(&self.state).on_video_data(packet),
+            pdu => warn!(?pdu, "Unexpected video data PDU"),
+        }
+
+        Ok(Vec::new())
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        for presentation in lock(&self.state).presentations.values_mut() {
+            presentation.pending = None;
+        }
+    }
+}
+
+impl DvcClientProcessor for VideoDataClient {}
@@ -0,0 +1,14 @@
+#![cfg_attr(doc, doc = include_str!("../README.md"))]
+#![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
+
+/// Name of the channel tracking the area of the session covered by the videos
+pub const GEOMETRY_CHANNEL_NAME: &str = "Microsoft::Windows::RDS::Geometry::v08.01";
+
+/// Name of the channel starting and stopping the presentations of the videos
+pub const CONTROL_CHANNEL_NAME: &str = "Microsoft::Windows::RDS::Video::Control::v08.01";
+
+/// Name of the channel carrying the samples of the videos
+pub const DATA_CHANNEL_NAME: &str = "Microsoft::Windows::RDS::Video::Data::v08.01";
+
+pub mod client;
+pub mod pdu;
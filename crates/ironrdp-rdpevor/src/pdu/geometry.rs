@@ -0,0 +1,280 @@
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, read_padding, write_padding, Decode,
+    DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+use ironrdp_dvc::DvcEncode;
+
+const GEOMETRY_VERSION: u32 = 0x01;
+
+const GEOMETRY_UPDATE: u32 = 0x00;
+const GEOMETRY_CLEAR: u32 = 0x01;
+
+const RDH_RECTANGLES: u32 = 0x01;
+
+/// Rectangle in the coordinates of the session, the right and bottom edges are exclusive
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct GeometryRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl GeometryRect {
+    const NAME: &'static str = "RECT";
+
+    const FIXED_PART_SIZE: usize = 4 /* left */ + 4 /* top */ + 4 /* right */ + 4 /* bottom */;
+
+    /// Smallest rectangle containing both rectangles
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+}
+
+impl Encode for GeometryRect {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_i32(self.left);
+        dst.write_i32(self.top);
+        dst.write_i32(self.right);
+        dst.write_i32(self.bottom);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for GeometryRect {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            left: src.read_i32(),
+            top: src.read_i32(),
+            right: src.read_i32(),
+            bottom: src.read_i32(),
+        })
+    }
+}
+
+/// Change of a mapping of the geometry channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeometryUpdate {
+    /// The mapping was created or moved
+    Update(MappedGeometry),
+    /// The mapping was removed
+    Clear,
+}
+
+/// MS-RDPEGT 2.2.1 MAPPED_GEOMETRY_PACKET
+///
+/// Sent by the server to track the area of the session covered by a video, identified by its mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeometryPdu {
+    /// Identifier of the mapping, referenced by the presentations of the videos
+    pub mapping_id: u64,
+    pub update: GeometryUpdate,
+}
+
+impl GeometryPdu {
+    const NAME: &'static str = "MAPPED_GEOMETRY_PACKET";
+
+    const FIXED_PART_SIZE: usize = 4 /* cbGeometryBuffer */ + 4 /* Version */ + 8 /* MappingId */
+        + 4 /* UpdateType */ + 4 /* Flags */;
+}
+
+impl Encode for GeometryPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(cast_length!("cbGeometryBuffer", self.size())?);
+        dst.write_u32(GEOMETRY_VERSION);
+        dst.write_u64(self.mapping_id);
+
+        match &self.update {
+            GeometryUpdate::Update(geometry) => {
+                dst.write_u32(GEOMETRY_UPDATE);
+                // Flags
+                write_padding!(dst, 4);
+                geometry.encode(dst)
+            }
+            GeometryUpdate::Clear => {
+                dst.write_u32(GEOMETRY_CLEAR);
+                write_padding!(dst, 4);
+                Ok(())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            + match &self.update {
+                GeometryUpdate::Update(geometry) => geometry.size(),
+                GeometryUpdate::Clear => 0,
+            }
+    }
+}
+
+impl DvcEncode for GeometryPdu {}
+
+impl<'de> Decode<'de> for GeometryPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let cb_geometry_buffer: usize = cast_length!("cbGeometryBuffer", src.read_u32())?;
+        let body_size = cb_geometry_buffer
+            .checked_sub(4 /* cbGeometryBuffer */)
+            .ok_or_else(|| invalid_field_err!("cbGeometryBuffer", "smaller than the header"))?;
+        ensure_size!(in: src, size: body_size);
+
+        // The fields following a clear are not used.
+        let body = &mut ReadCursor::new(src.read_slice(body_size));
+        ensure_size!(in: body, size: Self::FIXED_PART_SIZE - 4 /* cbGeometryBuffer */);
+
+        let _version = body.read_u32();
+        let mapping_id = body.read_u64();
+        let update_type = body.read_u32();
+        read_padding!(body, 4);
+
+        let update = match update_type {
+            GEOMETRY_UPDATE => GeometryUpdate::Update(MappedGeometry::decode(body)?),
+            GEOMETRY_CLEAR => GeometryUpdate::Clear,
+            _ => return Err(invalid_field_err!("UpdateType", "unknown geometry update type")),
+        };
+
+        Ok(Self { mapping_id, update })
+    }
+}
+
+/// Position of a mapping, following the `UpdateType` of a [`GeometryPdu`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedGeometry {
+    /// Identifier of the top-level window containing the video
+    pub top_level_id: u64,
+    /// Area of the video, relative to the top-level window
+    pub rect: GeometryRect,
+    /// Area of the top-level window, in the coordinates of the session
+    pub top_level_rect: GeometryRect,
+    /// Visible parts of the video, in the coordinates of the session, empty when the video is hidden
+    pub region: Vec<GeometryRect>,
+}
+
+impl MappedGeometry {
+    const NAME: &'static str = "MAPPED_GEOMETRY";
+
+    const FIXED_PART_SIZE: usize = 8 /* TopLevelId */ + GeometryRect::FIXED_PART_SIZE /* Left..Bottom */
+        + GeometryRect::FIXED_PART_SIZE /* TopLevelLeft..TopLevelBottom */ + 4 /* GeometryType */
+        + 4 /* cbGeometryData */;
+
+    const RGNDATAHEADER_SIZE: usize = 4 /* dwSize */ + 4 /* iType */ + 4 /* nCount */ + 4 /* nRgnSize */
+        + GeometryRect::FIXED_PART_SIZE /* rcBound */;
+
+    fn region_size(&self) -> usize {
+        GeometryRect::FIXED_PART_SIZE * self.region.len()
+    }
+
+    fn geometry_data_size(&self) -> usize {
+        if self.region.is_empty() {
+            0
+        } else {
+            Self::RGNDATAHEADER_SIZE + self.region_size()
+        }
+    }
+}
+
+impl Encode for MappedGeometry {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u64(self.top_level_id);
+        self.rect.encode(dst)?;
+        self.top_level_rect.encode(dst)?;
+        dst.write_u32(RDH_RECTANGLES);
+        dst.write_u32(cast_length!("cbGeometryData", self.geometry_data_size())?);
+
+        let Some(bound) = self.region.iter().copied().reduce(|bound, rect| bound.union(&rect)) else {
+            return Ok(());
+        };
+
+        dst.write_u32(cast_length!("dwSize", Self::RGNDATAHEADER_SIZE)?);
+        dst.write_u32(RDH_RECTANGLES);
+        dst.write_u32(cast_length!("nCount", self.region.len())?);
+        dst.write_u32(cast_length!("nRgnSize", self.region_size())?);
+        bound.encode(dst)?;
+        for rect in &self.region {
+            rect.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.geometry_data_size()
+    }
+}
+
+impl<'de> Decode<'de> for MappedGeometry {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let top_level_id = src.read_u64();
+        let rect = GeometryRect::decode(src)?;
+        let top_level_rect = GeometryRect::decode(src)?;
+        let _geometry_type = src.read_u32();
+        let cb_geometry_data: usize = cast_length!("cbGeometryData", src.read_u32())?;
+
+        let region = if cb_geometry_data == 0 {
+            Vec::new()
+        } else {
+            ensure_size!(in: src, size: Self::RGNDATAHEADER_SIZE);
+
+            let dw_size: usize = cast_length!("dwSize", src.read_u32())?;
+            if dw_size != Self::RGNDATAHEADER_SIZE {
+                return Err(invalid_field_err!("dwSize", "invalid region header size"));
+            }
+            if src.read_u32() != RDH_RECTANGLES {
+                return Err(invalid_field_err!("iType", "unsupported region type"));
+            }
+            let count: usize = cast_length!("nCount", src.read_u32())?;
+            let _rgn_size = src.read_u32();
+            let _bound = GeometryRect::decode(src)?;
+
+            let rects_size = count
+                .checked_mul(GeometryRect::FIXED_PART_SIZE)
+                .ok_or_else(|| invalid_field_err!("nCount", "too many rectangles"))?;
+            ensure_size!(in: src, size: rects_size);
+
+            core::iter::repeat_with(|| GeometryRect::decode(src))
+                .take(count)
+                .collect::<DecodeResult<_>>()?
+        };
+
+        Ok(Self {
+            top_level_id,
+            rect,
+            top_level_rect,
+            region,
+        })
+    }
+}
@@ -0,0 +1,507 @@
+//! Video Optimized Remoting Virtual Channel Extension PDUs [MS-RDPEVOR][1] implementation.
+//!
+//! The messages of the control and the data channels start with the same header, giving their size and their type,
+//! they are all decoded as a [`VideoPdu`]. The messages of the geometry channel [MS-RDPEGT][2] are decoded as a
+//! [`GeometryPdu`].
+//!
+//! [1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpevor/
+//! [2]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpegt/
+
+mod geometry;
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, read_padding, write_padding, Decode,
+    DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+use ironrdp_dvc::DvcEncode;
+
+pub use self::geometry::*;
+
+/// Version of the presentation requests and the video data
+const RDP_VIDEO_VERSION: u8 = 0x01;
+
+const PRESENTATION_REQUEST: u32 = 0x01;
+const PRESENTATION_RESPONSE: u32 = 0x02;
+const CLIENT_NOTIFICATION: u32 = 0x03;
+const VIDEO_DATA: u32 = 0x04;
+
+/// 2.2.1.1 TSMM_VIDEO_PACKET_HEADER, followed by the message
+///
+/// The presentation messages and the client notifications are sent on the control channel, the video data on the data
+/// channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VideoPdu {
+    /// Server request to start or stop a presentation
+    PresentationRequest(PresentationRequest),
+    /// Client reply to [`PresentationCommand::Start`]
+    PresentationResponse(PresentationResponse),
+    ClientNotification(ClientNotification),
+    /// Server packet of a sample, on the data channel
+    Data(VideoData),
+}
+
+impl VideoPdu {
+    const NAME: &'static str = "TSMM_VIDEO_PACKET";
+
+    const FIXED_PART_SIZE: usize = 4 /* cbSize */ + 4 /* PacketType */;
+
+    fn packet_type(&self) -> u32 {
+        match self {
+            VideoPdu::PresentationRequest(_) => PRESENTATION_REQUEST,
+            VideoPdu::PresentationResponse(_) => PRESENTATION_RESPONSE,
+            VideoPdu::ClientNotification(_) => CLIENT_NOTIFICATION,
+            VideoPdu::Data(_) => VIDEO_DATA,
+        }
+    }
+}
+
+impl Encode for VideoPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(cast_length!("cbSize", self.size())?);
+        dst.write_u32(self.packet_type());
+
+        match self {
+            VideoPdu::PresentationRequest(pdu) => pdu.encode(dst),
+            VideoPdu::PresentationResponse(pdu) => pdu.encode(dst),
+            VideoPdu::ClientNotification(pdu) => pdu.encode(dst),
+            VideoPdu::Data(pdu) => pdu.encode(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            .checked_add(match self {
+                VideoPdu::PresentationRequest(pdu) => pdu.size(),
+                VideoPdu::PresentationResponse(pdu) => pdu.size(),
+                VideoPdu::ClientNotification(pdu) => pdu.size(),
+                VideoPdu::Data(pdu) => pdu.size(),
+            })
+            .expect("never overflow")
+    }
+}
+
+impl DvcEncode for VideoPdu {}
+
+impl<'de> Decode<'de> for VideoPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let cb_size: usize = cast_length!("cbSize", src.read_u32())?;
+        let packet_type = src.read_u32();
+
+        let body_size = cb_size
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("cbSize", "smaller than the header"))?;
+        ensure_size!(in: src, size: body_size);
+
+        // Trailing reserved fields are ignored, whatever the message.
+        let body = &mut ReadCursor::new(src.read_slice(body_size));
+
+        let pdu = match packet_type {
+            PRESENTATION_REQUEST => VideoPdu::PresentationRequest(PresentationRequest::decode(body)?),
+            PRESENTATION_RESPONSE => VideoPdu::PresentationResponse(PresentationResponse::decode(body)?),
+            CLIENT_NOTIFICATION => VideoPdu::ClientNotification(ClientNotification::decode(body)?),
+            VIDEO_DATA => VideoPdu::Data(VideoData::decode(body)?),
+            _ => return Err(invalid_field_err!("PacketType", "unknown video packet type")),
+        };
+
+        Ok(pdu)
+    }
+}
+
+/// Command of a [`PresentationRequest`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PresentationCommand {
+    Start,
+    Stop,
+}
+
+impl PresentationCommand {
+    const START: u8 = 0x01;
+    const STOP: u8 = 0x02;
+}
+
+/// Media subtype of the samples of a presentation, a Media Foundation GUID
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VideoSubtype(pub [u8; 16]);
+
+impl VideoSubtype {
+    /// `MFVideoFormat_H264`, {34363248-0000-0010-8000-00AA00389B71}
+    pub const H264: Self = Self([
+        0x48, 0x32, 0x36, 0x34, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+    ]);
+}
+
+/// 2.2.1.2 TSMM_PRESENTATION_REQUEST
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentationRequest {
+    /// Identifier of the presentation, referenced by the [`VideoData`]
+    pub presentation_id: u8,
+    pub command: PresentationCommand,
+    /// Expected frames per second
+    pub frame_rate: u8,
+    pub average_bitrate_kbps: u16,
+    /// Size of the encoded video
+    pub source_width: u32,
+    pub source_height: u32,
+    /// Size of the video once presented in the session
+    pub scaled_width: u32,
+    pub scaled_height: u32,
+    /// Offset, in 100 ns units, of the timestamps of the samples
+    pub timestamp_offset: u64,
+    /// Mapping of the geometry channel giving the position of the video in the session
+    pub geometry_mapping_id: u64,
+    pub video_subtype: VideoSubtype,
+    /// Media-type specific data, the H.264 sequence and picture parameter sets
+    pub extra_data: Vec<u8>,
+}
+
+impl PresentationRequest {
+    const NAME: &'static str = "TSMM_PRESENTATION_REQUEST";
+
+    const FIXED_PART_SIZE: usize = 1 /* PresentationId */ + 1 /* Version */ + 1 /* Command */ + 1 /* FrameRate */
+        + 2 /* AverageBitrateKbps */ + 2 /* Reserved */ + 4 /* SourceWidth */ + 4 /* SourceHeight */
+        + 4 /* ScaledWidth */ + 4 /* ScaledHeight */ + 8 /* hnsTimestampOffset */ + 8 /* GeometryMappingId */
+        + 16 /* VideoSubtypeId */ + 4 /* cbExtra */;
+}
+
+impl Encode for PresentationRequest {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.presentation_id);
+        dst.write_u8(RDP_VIDEO_VERSION);
+        dst.write_u8(match self.command {
+            PresentationCommand::Start => PresentationCommand::START,
+            PresentationCommand::Stop => PresentationCommand::STOP,
+        });
+        dst.write_u8(self.frame_rate);
+        dst.write_u16(self.average_bitrate_kbps);
+        write_padding!(dst, 2);
+        dst.write_u32(self.source_width);
+        dst.write_u32(self.source_height);
+        dst.write_u32(self.scaled_width);
+        dst.write_u32(self.scaled_height);
+        dst.write_u64(self.timestamp_offset);
+        dst.write_u64(self.geometry_mapping_id);
+        dst.write_array(self.video_subtype.0);
+        dst.write_u32(cast_length!("cbExtra", self.extra_data.len())?);
+        dst.write_slice(&self.extra_data);
+        // Reserved2
+        write_padding!(dst, 1);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.extra_data.len() + 1 /* Reserved2 */
+    }
+}
+
+impl<'de> Decode<'de> for PresentationRequest {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let presentation_id = src.read_u8();
+        let _version = src.read_u8();
+        let command = match src.read_u8() {
+            PresentationCommand::START => PresentationCommand::Start,
+            PresentationCommand::STOP => PresentationCommand::Stop,
+            _ => return Err(invalid_field_err!("Command", "unknown presentation command")),
+        };
+        let frame_rate = src.read_u8();
+        let average_bitrate_kbps = src.read_u16();
+        read_padding!(src, 2);
+        let source_width = src.read_u32();
+        let source_height = src.read_u32();
+        let scaled_width = src.read_u32();
+        let scaled_height = src.read_u32();
+        let timestamp_offset = src.read_u64();
+        let geometry_mapping_id = src.read_u64();
+        let video_subtype = VideoSubtype(src.read_array());
+
+        let cb_extra: usize = cast_length!("cbExtra", src.read_u32())?;
+        ensure_size!(in: src, size: cb_extra);
+        let extra_data = src.read_slice(cb_extra).to_vec();
+
+        Ok(Self {
+            presentation_id,
+            command,
+            frame_rate,
+            average_bitrate_kbps,
+            source_width,
+            source_height,
+            scaled_width,
+            scaled_height,
+            timestamp_offset,
+            geometry_mapping_id,
+            video_subtype,
+            extra_data,
+        })
+    }
+}
+
+/// 2.2.1.3 TSMM_PRESENTATION_RESPONSE
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PresentationResponse {
+    pub presentation_id: u8,
+}
+
+impl PresentationResponse {
+    const NAME: &'static str = "TSMM_PRESENTATION_RESPONSE";
+
+    const FIXED_PART_SIZE: usize = 1 /* PresentationId */ + 3 /* Reserved */;
+}
+
+impl Encode for PresentationResponse {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.presentation_id);
+        write_padding!(dst, 3);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for PresentationResponse {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let presentation_id = src.read_u8();
+        read_padding!(src, 3);
+
+        Ok(Self { presentation_id })
+    }
+}
+
+/// Notification sent by the client about a presentation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Notification {
+    /// The client lost samples, the server restarts the presentation from a keyframe
+    NetworkError,
+    /// The client can't keep up with the frame rate of the presentation
+    FrameRateOverride { desired_frame_rate: u32 },
+    /// Back to the frame rate chosen by the server
+    UndoFrameRateOverride,
+}
+
+/// 2.2.1.4 TSMM_CLIENT_NOTIFICATION
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClientNotification {
+    pub presentation_id: u8,
+    pub notification: Notification,
+}
+
+impl ClientNotification {
+    const NAME: &'static str = "TSMM_CLIENT_NOTIFICATION";
+
+    const FIXED_PART_SIZE: usize = 1 /* PresentationId */ + 1 /* NotificationType */ + 2 /* Reserved */
+        + 4 /* cbData */;
+
+    const NETWORK_ERROR: u8 = 0x01;
+    const FRAMERATE_OVERRIDE: u8 = 0x02;
+
+    const FRAMERATE_OVERRIDE_SIZE: usize = 4 /* Flags */ + 4 /* DesiredFrameRate */ + 8 /* Reserved */;
+
+    const OVERRIDE: u32 = 0x01;
+    const UNDO: u32 = 0x02;
+
+    fn data_size(&self) -> usize {
+        match self.notification {
+            Notification::NetworkError => 0,
+            Notification::FrameRateOverride { .. } | Notification::UndoFrameRateOverride => {
+                Self::FRAMERATE_OVERRIDE_SIZE
+            }
+        }
+    }
+}
+
+impl Encode for ClientNotification {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.presentation_id);
+        dst.write_u8(match self.notification {
+            Notification::NetworkError => Self::NETWORK_ERROR,
+            Notification::FrameRateOverride { .. } | Notification::UndoFrameRateOverride => Self::FRAMERATE_OVERRIDE,
+        });
+        write_padding!(dst, 2);
+        dst.write_u32(cast_length!("cbData", self.data_size())?);
+
+        let (flags, desired_frame_rate) = match self.notification {
+            Notification::NetworkError => return Ok(()),
+            Notification::FrameRateOverride { desired_frame_rate } => (Self::OVERRIDE, desired_frame_rate),
+            Notification::UndoFrameRateOverride => (Self::UNDO, 0),
+        };
+        dst.write_u32(flags);
+        dst.write_u32(desired_frame_rate);
+        write_padding!(dst, 8);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.data_size()
+    }
+}
+
+impl<'de> Decode<'de> for ClientNotification {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let presentation_id = src.read_u8();
+        let notification_type = src.read_u8();
+        read_padding!(src, 2);
+        let cb_data: usize = cast_length!("cbData", src.read_u32())?;
+
+        let notification = match notification_type {
+            Self::NETWORK_ERROR => Notification::NetworkError,
+            Self::FRAMERATE_OVERRIDE => {
+                if cb_data < Self::FRAMERATE_OVERRIDE_SIZE {
+                    return Err(invalid_field_err!("cbData", "truncated frame rate override"));
+                }
+                ensure_size!(in: src, size: Self::FRAMERATE_OVERRIDE_SIZE);
+
+                let flags = src.read_u32();
+                let desired_frame_rate = src.read_u32();
+                read_padding!(src, 8);
+
+                match flags {
+                    Self::OVERRIDE => Notification::FrameRateOverride { desired_frame_rate },
+                    Self::UNDO => Notification::UndoFrameRateOverride,
+                    _ => return Err(invalid_field_err!("Flags", "unknown frame rate override")),
+                }
+            }
+            _ => return Err(invalid_field_err!("NotificationType", "unknown notification type")),
+        };
+
+        Ok(Self {
+            presentation_id,
+            notification,
+        })
+    }
+}
+
+bitflags! {
+    /// Flags of a [`VideoData`]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct VideoDataFlags: u8 {
+        /// The timestamp and the duration of the sample are set
+        const HAS_TIMESTAMPS = 0x01;
+        /// The sample is a keyframe, decoding can start from it
+        const KEYFRAME = 0x02;
+        /// The sample starts a new frame rate
+        const NEW_FRAMERATE = 0x04;
+        const _ = !0;
+    }
+}
+
+/// 2.2.1.5 TSMM_VIDEO_DATA
+///
+/// A sample larger than a DVC message is split in several packets, sent in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoData {
+    pub presentation_id: u8,
+    pub flags: VideoDataFlags,
+    /// Presentation time of the sample, in 100 ns units
+    pub timestamp: u64,
+    /// Duration of the sample, in 100 ns units
+    pub duration: u64,
+    /// Index of the packet in the sample, starting at 1
+    pub current_packet_index: u16,
+    pub packets_in_sample: u16,
+    pub sample_number: u32,
+    /// Part of the encoded sample carried by the packet
+    pub sample: Vec<u8>,
+}
+
+impl VideoData {
+    const NAME: &'static str = "TSMM_VIDEO_DATA";
+
+    const FIXED_PART_SIZE: usize = 1 /* PresentationId */ + 1 /* Version */ + 1 /* Flags */ + 1 /* Reserved */
+        + 8 /* hnsTimestamp */ + 8 /* hnsDuration */ + 2 /* CurrentPacketIndex */ + 2 /* PacketsInSample */
+        + 4 /* SampleNumber */ + 4 /* cbSample */;
+}
+
+impl Encode for VideoData {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.presentation_id);
+        dst.write_u8(RDP_VIDEO_VERSION);
+        dst.write_u8(self.flags.bits());
+        write_padding!(dst, 1);
+        dst.write_u64(self.timestamp);
+        dst.write_u64(self.duration);
+        dst.write_u16(self.current_packet_index);
+        dst.write_u16(self.packets_in_sample);
+        dst.write_u32(self.sample_number);
+        dst.write_u32(cast_length!("cbSample", self.sample.len())?);
+        dst.write_slice(&self.sample);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.sample.len()
+    }
+}
+
+impl<'de> Decode<'de> for VideoData {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let presentation_id = src.read_u8();
+        let _version = src.read_u8();
+        let flags = VideoDataFlags::from_bits_retain(src.read_u8());
+        read_padding!(src, 1);
+        let timestamp = src.read_u64();
+        let duration = src.read_u64();
+        let current_packet_index = src.read_u16();
+        let packets_in_sample = src.read_u16();
+        let sample_number = src.read_u32();
+
+        let cb_sample: usize = cast_length!("cbSample", src.read_u32())?;
+        ensure_size!(in: src, size: cb_sample);
+        let sample = src.read_slice(cb_sample).to_vec();
+
+        Ok(Self {
+            presentation_id,
+            flags,
+            timestamp,
+            duration,
+            current_packet_index,
+            packets_in_sample,
+            sample_number,
+            sample,
+        })
+    }
+}
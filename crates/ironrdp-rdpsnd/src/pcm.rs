@@ -0,0 +1,144 @@
+//! Conversion of 16-bit PCM audio between channel counts and sample rates
+
+/// Channel count and sample rate of interleaved PCM samples
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PcmFormat {
+    pub n_channels: u16,
+    pub n_samples_per_sec: u32,
+}
+
+/// Converts 16-bit PCM audio to another channel count and sample rate
+///
+/// The sample rate conversion is a linear interpolation. The converter keeps the input samples it still needs
+/// between calls, so audio split in several frames is converted without discontinuities.
+#[derive(Debug)]
+pub struct PcmConverter {
+    input: PcmFormat,
+    output: PcmFormat,
+    /// Input samples not consumed yet, already mapped to the output channels
+    pending: Vec<i16>,
+    /// Position of the next output frame in the pending input frames, scaled by the output sample rate
+    position: u64,
+}
+
+impl PcmConverter {
+    pub fn new(input: PcmFormat, output: PcmFormat) -> Self {
+        let sanitize = |format: PcmFormat| PcmFormat {
+            n_channels: format.n_channels.max(1),
+            n_samples_per_sec: format.n_samples_per_sec.max(1),
+        };
+
+        Self {
+            input: sanitize(input),
+            output: sanitize(output),
+            pending: Vec::new(),
+            position: 0,
+        }
+    }
+
+    pub fn input_format(&self) -> PcmFormat {
+        self.input
+    }
+
+    pub fn output_format(&self) -> PcmFormat {
+        self.output
+    }
+
+    /// Converts interleaved input samples, and returns the output samples which can be computed so far
+    ///
+    /// A trailing incomplete input frame is ignored.
+    pub fn convert(&mut self, samples: &[i16]) -> Vec<i16> {
+        let channels = usize::from(self.output.n_channels);
+
+        for frame in samples.chunks_exact(usize::from(self.input.n_channels)) {
+            map_channels(frame, channels, &mut self.pending);
+        }
+
+        if self.input.n_samples_per_sec == self.output.n_samples_per_sec {
+            return core::mem::take(&mut self.pending);
+        }
+
+        let input_rate = u64::from(self.input.n_samples_per_sec);
+        let output_rate = u64::from(self.output.n_samples_per_sec);
+        let pending_frames = self.pending.len() / channels;
+        let mut output = Vec::new();
+
+        while let Ok(index) = usize::try_from(self.position / output_rate) {
+            // The interpolation needs the next input frame as well.
+            if index + 1 >= pending_frames {
+                break;
+            }
+
+            let fraction = self.position % output_rate;
+            let current = &self.pending[index * channels..][..channels];
+            let next = &self.pending[(index + 1) * channels..][..channels];
+            output.extend(
+                current
+                    .iter()
+                    .zip(next)
+                    .map(|(&current, &next)| interpolate(current, next, fraction, output_rate)),
+            );
+
+            self.position += input_rate;
+        }
+
+        // Drop the input frames which are not needed anymore.
+        let consumed = usize::try_from(self.position / output_rate)
+            .unwrap_or(pending_frames)
+            .min(pending_frames);
+        self.pending.drain(..consumed * channels);
+        self.position -= u64::try_from(consumed).unwrap_or_default() * output_rate;
+
+        output
+    }
+}
+
+fn map_channels(frame: &[i16], channels: usize, output: &mut Vec<i16>) {
+    match (frame, channels) {
+        (frame, channels) if frame.len() == channels => output.extend_from_slice(frame),
+        // Down-mixing to mono averages the channels.
+        (frame, 1) => {
+            let sum: i32 = frame.iter().copied().map(i32::from).sum();
+            let count = i32::try_from(frame.len()).unwrap_or(i32::MAX);
+            output.push(i16::try_from(sum / count).unwrap_or_default());
+        }
+        // Mono is copied to all the channels.
+        ([sample], channels) => output.extend(core::iter::repeat_n(*sample, channels)),
+        // Otherwise, the extra channels are dropped, and the missing ones are silent.
+        (frame, channels) => output.extend((0..channels).map(|channel| frame.get(channel).copied().unwrap_or(0))),
+    }
+}
+
+fn interpolate(current: i16, next: i16, fraction: u64, scale: u64) -> i16 {
+    let fraction = i64::try_from(fraction).unwrap_or_default();
+    let scale = i64::try_from(scale).unwrap_or(1);
+    let value = i64::from(current) + (i64::from(next) - i64::from(current)) * fraction / scale;
+
+    // The value is between the two samples, and always fits.
+    i16::try_from(value).unwrap_or(current)
+}
+
+/// Reads interleaved 16-bit little-endian samples
+///
+/// A trailing odd byte is ignored.
+pub fn decode_samples(data: &[u8]) -> Vec<i16> {
+    data.chunks_exact(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect()
+}
+
+/// Writes samples with the given size, 16-bit signed or 8-bit unsigned
+///
+/// Returns `None` for other sample sizes.
+pub fn encode_samples(samples: &[i16], bits_per_sample: u16) -> Option<Vec<u8>> {
+    match bits_per_sample {
+        16 => Some(samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()),
+        8 => Some(
+            samples
+                .iter()
+                .map(|sample| u8::try_from((i32::from(*sample) >> 8) + 128).unwrap_or_default())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
@@ -1,13 +1,20 @@
+use core::time::Duration;
+use std::time::Instant;
+
 use ironrdp_core::{impl_as_any, Decode as _, ReadCursor};
 use ironrdp_pdu::gcc::ChannelName;
 use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
 use ironrdp_svc::{CompressionCondition, SvcMessage, SvcProcessor, SvcProcessorMessages, SvcServerProcessor};
 use tracing::{debug, error};
 
-use crate::pdu::{self, ClientAudioFormatPdu, QualityMode};
+use crate::pcm::{self, PcmConverter, PcmFormat};
+use crate::pdu::{self, AudioFormat, ClientAudioFormatPdu, QualityMode, WaveFormat};
 
 pub type RdpsndSvcMessages = SvcProcessorMessages<RdpsndServer>;
 
+/// Duration of the wave PDUs sent by [`RdpsndServer::push_audio`]
+const BLOCK_DURATION_MS: u32 = 20;
+
 pub trait RdpsndError: core::error::Error + Send + Sync + 'static {}
 
 impl<T> RdpsndError for T where T: core::error::Error + Send + Sync + 'static {}
@@ -17,6 +24,8 @@ impl<T> RdpsndError for T where T: core::error::Error + Send + Sync + 'static {}
 pub enum RdpsndServerMessage {
     /// Wave data, with timestamp
     Wave(Vec<u8>, u32),
+    /// PCM audio, converted to the negotiated format, see [`RdpsndServer::push_audio`]
+    Audio(AudioFrame),
     SetVolume {
         left: u16,
         right: u16,
@@ -28,14 +37,56 @@ pub enum RdpsndServerMessage {
     Error(Box<dyn RdpsndError>),
 }
 
+/// Audio to be played by the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioFrame {
+    /// Interleaved 16-bit little-endian PCM samples
+    pub data: Vec<u8>,
+    pub n_channels: u16,
+    pub n_samples_per_sec: u32,
+    /// Presentation time of the first sample, in milliseconds
+    ///
+    /// Using the clock of the EGFX frame timestamps lets the client synchronize the audio with the video.
+    pub timestamp: u32,
+}
+
 pub trait RdpsndServerHandler: Send + core::fmt::Debug {
-    fn get_formats(&self) -> &[pdu::AudioFormat];
+    /// Formats offered to the client, by order of preference
+    fn get_formats(&self) -> &[AudioFormat];
 
+    /// Starts the audio output, and returns the index of the client format to use
+    ///
+    /// When `None` is returned, the server picks a format with [`negotiate_format`].
     fn start(&mut self, client_format: &ClientAudioFormatPdu) -> Option<u16>;
 
     fn stop(&mut self);
 }
 
+/// Picks the client format to use, and returns its index
+///
+/// The server formats are tried by order of preference. When none of them is supported by the client,
+/// the first 8 or 16-bit PCM format of the client is picked, the audio pushed with
+/// [`RdpsndServer::push_audio`] being converted to it.
+pub fn negotiate_format(server_formats: &[AudioFormat], client_formats: &[AudioFormat]) -> Option<u16> {
+    let same_format = |server: &AudioFormat, client: &AudioFormat| {
+        server.format == client.format
+            && server.n_channels == client.n_channels
+            && server.n_samples_per_sec == client.n_samples_per_sec
+            && server.bits_per_sample == client.bits_per_sample
+    };
+
+    let index = server_formats
+        .iter()
+        .find_map(|server| client_formats.iter().position(|client| same_format(server, client)))
+        .or_else(|| {
+            client_formats.iter().position(|client| {
+                client.format == WaveFormat::PCM && client.n_channels > 0 && matches!(client.bits_per_sample, 8 | 16)
+            })
+        })?;
+
+    u16::try_from(index).ok()
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum RdpsndState {
     Start,
@@ -54,6 +105,19 @@ pub struct RdpsndServer {
     quality_mode: Option<QualityMode>,
     block_no: u8,
     format_no: Option<u16>,
+    /// Origin of the timestamps of the training and wave PDUs
+    epoch: Instant,
+    /// Timestamps of the wave PDUs, by block number
+    wave_timestamps: [u16; 256],
+    latency: Option<Duration>,
+    playback_delay: Option<Duration>,
+    converter: Option<PcmConverter>,
+    /// Converted samples not sent yet
+    pending: Vec<i16>,
+    /// Presentation time of the first pending sample
+    pending_timestamp: u32,
+    #[cfg(feature = "opus")]
+    opus: Option<OpusEncoder>,
 }
 
 impl RdpsndServer {
@@ -67,9 +131,42 @@ impl RdpsndServer {
             quality_mode: None,
             format_no: None,
             block_no: 0,
+            epoch: Instant::now(),
+            wave_timestamps: [0; 256],
+            latency: None,
+            playback_delay: None,
+            converter: None,
+            pending: Vec::new(),
+            pending_timestamp: 0,
+            #[cfg(feature = "opus")]
+            opus: None,
         }
     }
 
+    /// Client format used for the wave PDUs, once negotiated
+    pub fn format(&self) -> Option<&AudioFormat> {
+        let client_format = self.client_format.as_ref()?;
+        client_format.formats.get(usize::from(self.format_no?))
+    }
+
+    /// Round-trip time of the channel, measured with the training PDU
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Time between sending the last confirmed wave PDU and its confirmation by the client, once played
+    ///
+    /// This is the delay to apply to the video frames to keep them in sync with the audio.
+    pub fn playback_delay(&self) -> Option<Duration> {
+        self.playback_delay
+    }
+
+    /// Milliseconds since the creation of the channel, on 16 bits as in the training and wave PDUs
+    fn timestamp(&self) -> u16 {
+        let millis = self.epoch.elapsed().as_millis() % (u128::from(u16::MAX) + 1);
+        u16::try_from(millis).unwrap_or_default()
+    }
+
     pub fn version(&self) -> PduResult<pdu::Version> {
         let client_format = self
             .client_format
@@ -90,7 +187,7 @@ impl RdpsndServer {
 
     pub fn training_pdu(&mut self) -> PduResult<RdpsndSvcMessages> {
         let pdu = pdu::TrainingPdu {
-            timestamp: 4231, // a random number
+            timestamp: self.timestamp(),
             data: vec![],
         };
         Ok(RdpsndSvcMessages::new(vec![
@@ -103,12 +200,13 @@ impl RdpsndServer {
         let format_no = self
             .format_no
             .ok_or_else(|| pdu_other_err!("invalid state - no format"))?;
+        let timestamp = self.timestamp();
 
         // The server doesn't wait for wave confirm, apparently FreeRDP neither.
         let msg = if version >= pdu::Version::V8 {
             let pdu = pdu::Wave2Pdu {
                 block_no: self.block_no,
-                timestamp: 0,
+                timestamp,
                 audio_timestamp: ts,
                 format_no,
                 data: data.into(),
@@ -118,17 +216,117 @@ impl RdpsndServer {
             let pdu = pdu::WavePdu {
                 block_no: self.block_no,
                 format_no,
-                timestamp: 0,
+                timestamp,
                 data: data.into(),
             };
             RdpsndSvcMessages::new(vec![pdu::ServerAudioOutputPdu::Wave(pdu).into()])
         };
 
+        self.wave_timestamps[usize::from(self.block_no)] = timestamp;
         self.block_no = self.block_no.overflowing_add(1).0;
 
         Ok(msg)
     }
 
+    /// Buffers PCM audio, and sends it in wave PDUs of 20 ms
+    ///
+    /// The audio is converted to the channel count and the sample rate of the negotiated format. It is encoded
+    /// with OPUS when this format is negotiated and the `opus` feature is enabled. Other formats are not
+    /// supported, the handler must then encode the audio itself and use [`Self::wave`].
+    pub fn push_audio(&mut self, frame: AudioFrame) -> PduResult<RdpsndSvcMessages> {
+        let format = self
+            .format()
+            .cloned()
+            .ok_or_else(|| pdu_other_err!("invalid state - no format"))?;
+
+        let is_pcm = format.format == WaveFormat::PCM && matches!(format.bits_per_sample, 8 | 16);
+        #[cfg(feature = "opus")]
+        let is_opus = format.format == WaveFormat::OPUS;
+        #[cfg(not(feature = "opus"))]
+        let is_opus = false;
+
+        if !is_pcm && !is_opus {
+            return Err(pdu_other_err!(
+                "audio conversion not supported for the negotiated format"
+            ));
+        }
+
+        let input = PcmFormat {
+            n_channels: frame.n_channels,
+            n_samples_per_sec: frame.n_samples_per_sec,
+        };
+        let output = PcmFormat {
+            n_channels: format.n_channels,
+            n_samples_per_sec: format.n_samples_per_sec,
+        };
+
+        // The pending samples are sent before changing the input format, short blocks being valid.
+        let mut messages = Vec::new();
+        let converter = match self.converter.take() {
+            Some(converter) if converter.input_format() == input => converter,
+            _ => {
+                if !self.pending.is_empty() {
+                    let samples = core::mem::take(&mut self.pending);
+                    messages.extend(self.send_samples(&format, &samples)?);
+                }
+                PcmConverter::new(input, output)
+            }
+        };
+        let converter = self.converter.insert(converter);
+
+        let samples = converter.convert(&pcm::decode_samples(&frame.data));
+        if self.pending.is_empty() {
+            self.pending_timestamp = frame.timestamp;
+        }
+        self.pending.extend(samples);
+
+        let frames_per_block = usize::try_from(format.n_samples_per_sec / (1000 / BLOCK_DURATION_MS))
+            .unwrap_or(usize::MAX)
+            .max(1);
+        let block_len = frames_per_block * usize::from(format.n_channels.max(1));
+
+        while self.pending.len() >= block_len {
+            let block: Vec<i16> = self.pending.drain(..block_len).collect();
+            messages.extend(self.send_samples(&format, &block)?);
+        }
+
+        Ok(RdpsndSvcMessages::new(messages))
+    }
+
+    fn send_samples(&mut self, format: &AudioFormat, samples: &[i16]) -> PduResult<Vec<SvcMessage>> {
+        #[cfg(feature = "opus")]
+        let data = if format.format == WaveFormat::OPUS {
+            self.encode_opus(format, samples)?
+        } else {
+            pcm::encode_samples(samples, format.bits_per_sample)
+                .ok_or_else(|| pdu_other_err!("unsupported PCM sample size"))?
+        };
+        #[cfg(not(feature = "opus"))]
+        let data = pcm::encode_samples(samples, format.bits_per_sample)
+            .ok_or_else(|| pdu_other_err!("unsupported PCM sample size"))?;
+
+        let timestamp = self.pending_timestamp;
+        let frames = samples.len() / usize::from(format.n_channels.max(1));
+        let duration = u64::try_from(frames).unwrap_or(u64::MAX) * 1000 / u64::from(format.n_samples_per_sec.max(1));
+        self.pending_timestamp = timestamp.wrapping_add(u32::try_from(duration).unwrap_or(u32::MAX));
+
+        Ok(self.wave(data, timestamp)?.into())
+    }
+
+    #[cfg(feature = "opus")]
+    fn encode_opus(&mut self, format: &AudioFormat, samples: &[i16]) -> PduResult<Vec<u8>> {
+        let encoder = match self.opus.take() {
+            Some(encoder) => encoder,
+            None => OpusEncoder::new(format)?,
+        };
+        let encoder = self.opus.insert(encoder);
+
+        encoder
+            .0
+            .encode_vec(samples, OPUS_MAX_PACKET_SIZE)
+            .map_err(|e| pdu_other_err!("OPUS encoding", source: e))
+    }
+
     pub fn set_volume(&mut self, volume_left: u16, volume_right: u16) -> PduResult<RdpsndSvcMessages> {
         if !self.flags()?.contains(pdu::AudioFormatFlags::VOLUME) {
             return Err(pdu_other_err!("client doesn't support volume"));
@@ -188,19 +386,29 @@ impl SvcProcessor for RdpsndServer {
                 self.training_pdu()?.into()
             }
             RdpsndState::WaitingForTrainingConfirm => {
-                let pdu::ClientAudioOutputPdu::TrainingConfirm(_) = pdu else {
+                let pdu::ClientAudioOutputPdu::TrainingConfirm(confirm) = pdu else {
                     error!("Invalid PDU");
                     self.state = RdpsndState::Stop;
                     return Ok(vec![]);
                 };
+                let latency = self.timestamp().wrapping_sub(confirm.timestamp);
+                self.latency = Some(Duration::from_millis(u64::from(latency)));
+                debug!(latency, "Measured rdpsnd latency");
+
                 let client_format = self.client_format.as_ref().expect("available in this state");
                 self.state = RdpsndState::Ready;
-                self.format_no = self.handler.start(client_format);
+                self.format_no = self
+                    .handler
+                    .start(client_format)
+                    .or_else(|| negotiate_format(self.handler.get_formats(), &client_format.formats));
                 vec![]
             }
             RdpsndState::Ready => {
                 if let pdu::ClientAudioOutputPdu::WaveConfirm(c) = pdu {
                     debug!(?c);
+                    let sent = self.wave_timestamps[usize::from(c.block_no)];
+                    let delay = self.timestamp().wrapping_sub(sent);
+                    self.playback_delay = Some(Duration::from_millis(u64::from(delay)));
                 }
                 vec![]
             }
@@ -234,3 +442,32 @@ impl Drop for RdpsndServer {
 }
 
 impl SvcServerProcessor for RdpsndServer {}
+
+/// Largest OPUS packet, as recommended by the OPUS documentation
+#[cfg(feature = "opus")]
+const OPUS_MAX_PACKET_SIZE: usize = 4000;
+
+#[cfg(feature = "opus")]
+struct OpusEncoder(opus2::Encoder);
+
+#[cfg(feature = "opus")]
+impl OpusEncoder {
+    fn new(format: &AudioFormat) -> PduResult<Self> {
+        let channels = match format.n_channels {
+            1 => opus2::Channels::Mono,
+            2 => opus2::Channels::Stereo,
+            _ => return Err(pdu_other_err!("OPUS supports mono and stereo only")),
+        };
+
+        opus2::Encoder::new(format.n_samples_per_sec, channels, opus2::Application::Audio)
+            .map(Self)
+            .map_err(|e| pdu_other_err!("OPUS encoder", source: e))
+    }
+}
+
+#[cfg(feature = "opus")]
+impl core::fmt::Debug for OpusEncoder {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OpusEncoder").finish_non_exhaustive()
+    }
+}
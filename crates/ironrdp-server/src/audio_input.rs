@@ -0,0 +1,160 @@
+use ironrdp_audioinput::pdu::AudioFormat;
+pub use ironrdp_audioinput::server::DEFAULT_FRAMES_PER_PACKET;
+use ironrdp_audioinput::server::{AudioInputServer, AudioInputServerHandler};
+use tokio::sync::mpsc;
+
+use crate::ConnectionContext;
+
+/// Receives the audio recorded by the microphone of the clients
+///
+/// The handler is pull-based: each connection gets an [`AudioInputStream`], from which the application reads the
+/// recorded audio at its own pace, e.g. in a task feeding a virtual microphone.
+pub trait AudioInputHandler: Send + Sync {
+    /// Formats in which the audio is accepted, by order of preference
+    fn formats(&self) -> Vec<AudioFormat>;
+
+    /// Number of audio frames in each packet sent by the client
+    fn frames_per_packet(&self) -> u32 {
+        DEFAULT_FRAMES_PER_PACKET
+    }
+
+    /// Called for each connection, the stream ends with the connection
+    fn start(&self, ctx: &ConnectionContext, stream: AudioInputStream);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioInputEvent {
+    /// The client started recording
+    Opened(AudioFormat),
+    FormatChanged(AudioFormat),
+    /// Audio in the current format
+    Data(Vec<u8>),
+    /// The client stopped recording
+    Closed,
+}
+
+/// Audio recorded by the microphone of a client, see [`AudioInputHandler`]
+#[derive(Debug)]
+pub struct AudioInputStream {
+    receiver: mpsc::UnboundedReceiver<AudioInputEvent>,
+}
+
+impl AudioInputStream {
+    /// Waits for the next event, returns `None` once the connection is over
+    pub async fn next_event(&mut self) -> Option<AudioInputEvent> {
+        self.receiver.recv().await
+    }
+
+    /// Returns the next event if one is already received
+    pub fn try_next_event(&mut self) -> Option<AudioInputEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Forwards the audio input channel events to the stream of the connection
+#[derive(Debug)]
+struct AudioInputBackend {
+    sender: mpsc::UnboundedSender<AudioInputEvent>,
+}
+
+impl AudioInputBackend {
+    fn send(&self, event: AudioInputEvent) {
+        // The application may not read the audio, e.g. when the stream is dropped.
+        let _ = self.sender.send(event);
+    }
+}
+
+impl AudioInputServerHandler for AudioInputBackend {
+    fn opened(&mut self, format: &AudioFormat) {
+        self.send(AudioInputEvent::Opened(format.clone()));
+    }
+
+    fn format_changed(&mut self, format: &AudioFormat) {
+        self.send(AudioInputEvent::FormatChanged(format.clone()));
+    }
+
+    fn data(&mut self, data: &[u8]) {
+        self.send(AudioInputEvent::Data(data.to_vec()));
+    }
+
+    fn closed(&mut self) {
+        self.send(AudioInputEvent::Closed);
+    }
+}
+
+pub(crate) fn audio_input_server(handler: &dyn AudioInputHandler, ctx: &ConnectionContext) -> AudioInputServer {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    handler.start(ctx, AudioInputStream { receiver });
+
+    AudioInputServer::new(Box::new(AudioInputBackend { sender }), handler.formats())
+        .with_frames_per_packet(handler.frames_per_packet())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use ironrdp_audioinput::pdu::{
+        AudioInputPdu, DataPdu, FormatChangePdu, FormatsPdu, OpenReplyPdu, Version, WaveFormat, S_OK,
+    };
+    use ironrdp_core::encode_vec;
+    use ironrdp_dvc::DvcProcessor as _;
+    use ironrdp_pdu::nego::SecurityProtocol;
+
+    use super::*;
+
+    const PCM: AudioFormat = AudioFormat {
+        format: WaveFormat::PCM,
+        n_channels: 1,
+        n_samples_per_sec: 16000,
+        n_avg_bytes_per_sec: 32000,
+        n_block_align: 2,
+        bits_per_sample: 16,
+        data: None,
+    };
+
+    #[derive(Default)]
+    struct Handler {
+        stream: Mutex<Option<AudioInputStream>>,
+    }
+
+    impl AudioInputHandler for Handler {
+        fn formats(&self) -> Vec<AudioFormat> {
+            vec![PCM]
+        }
+
+        fn start(&self, _ctx: &ConnectionContext, stream: AudioInputStream) {
+            *self.stream.lock().unwrap() = Some(stream);
+        }
+    }
+
+    fn send(server: &mut AudioInputServer, pdu: AudioInputPdu) {
+        server.process(1, &encode_vec(&pdu).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn streams_recorded_audio() {
+        let handler = Handler::default();
+        let ctx = ConnectionContext::new(0, None, SecurityProtocol::empty(), None);
+        let mut server = audio_input_server(&handler, &ctx);
+        let mut stream = handler.stream.lock().unwrap().take().unwrap();
+
+        server.start(1).unwrap();
+        send(&mut server, AudioInputPdu::Version(Version::V2));
+        send(&mut server, AudioInputPdu::Formats(FormatsPdu { formats: vec![PCM] }));
+        assert_eq!(stream.try_next_event(), None);
+
+        send(
+            &mut server,
+            AudioInputPdu::FormatChange(FormatChangePdu { new_format: 0 }),
+        );
+        send(&mut server, AudioInputPdu::OpenReply(OpenReplyPdu { result: S_OK }));
+        send(&mut server, AudioInputPdu::IncomingData);
+        send(&mut server, AudioInputPdu::Data(DataPdu { data: vec![1, 2] }));
+        server.close(1);
+
+        assert_eq!(stream.try_next_event(), Some(AudioInputEvent::Opened(PCM)));
+        assert_eq!(stream.try_next_event(), Some(AudioInputEvent::Data(vec![1, 2])));
+        assert_eq!(stream.try_next_event(), Some(AudioInputEvent::Closed));
+    }
+}
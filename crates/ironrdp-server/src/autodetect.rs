@@ -0,0 +1,305 @@
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use ironrdp_pdu::rdp::autodetect::{AutoDetectPhase, AutoDetectRequest, AutoDetectResponse, BandwidthPhase};
+use tracing::debug;
+
+/// Measures the round-trip time and the bandwidth of a connection ([MS-RDPBCGR] 2.2.14)
+///
+/// The detector generates the auto-detect requests to send to the client on the message channel, and
+/// consumes the responses of the client. Each measure updates the [network
+/// characteristics](Self::network_characteristics), e.g. to adapt the frame rate or the quality of the
+/// EGFX encoder with [`NetworkCharacteristics::transfer_time()`], and to send the results to the client
+/// with [`result_request()`](Self::result_request).
+///
+/// The bandwidth is measured during the session over the data sent between
+/// [`bandwidth_start()`](Self::bandwidth_start) and [`bandwidth_stop()`](Self::bandwidth_stop), e.g. a
+/// few frames.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut detector = NetworkAutoDetect::new();
+///
+/// send(detector.rtt_request()).await?;
+/// send(detector.bandwidth_start()).await?;
+/// send(frames).await?;
+/// send(detector.bandwidth_stop()).await?;
+///
+/// // for each response of the client
+/// if let Some(characteristics) = detector.handle_response(&response) {
+///     let transfer_time = characteristics.transfer_time(frame_len).unwrap_or_default();
+///     frame_interval = min_frame_interval.max(transfer_time);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct NetworkAutoDetect {
+    next_sequence_number: u16,
+    /// RTT requests waiting for a response, oldest first
+    rtt_requests: VecDeque<(u16, Instant)>,
+    bandwidth_stop: Option<u16>,
+    characteristics: NetworkCharacteristics,
+}
+
+/// Network characteristics measured by a [`NetworkAutoDetect`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkCharacteristics {
+    /// Lowest round-trip time measured
+    pub base_rtt: Option<Duration>,
+    /// Smoothed round-trip time
+    pub average_rtt: Option<Duration>,
+    /// Bandwidth in kilobits per second
+    pub bandwidth: Option<u32>,
+}
+
+impl NetworkCharacteristics {
+    /// Estimated time to transfer `len` bytes to the client, without the round-trip time
+    ///
+    /// Returns `None` until the bandwidth is measured.
+    pub fn transfer_time(&self, len: usize) -> Option<Duration> {
+        let bandwidth = u64::from(self.bandwidth.filter(|bandwidth| *bandwidth > 0)?);
+        let bits = u64::try_from(len).unwrap_or(u64::MAX).saturating_mul(8);
+
+        // kilobits per second are bits per millisecond
+        Some(Duration::from_micros(bits.saturating_mul(1000) / bandwidth))
+    }
+}
+
+impl Default for NetworkAutoDetect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetworkAutoDetect {
+    /// Number of RTT requests waiting for a response, older requests being forgotten
+    const MAX_PENDING_RTT_REQUESTS: usize = 16;
+
+    pub fn new() -> Self {
+        Self {
+            next_sequence_number: 0,
+            rtt_requests: VecDeque::new(),
+            bandwidth_stop: None,
+            characteristics: NetworkCharacteristics::default(),
+        }
+    }
+
+    pub fn network_characteristics(&self) -> NetworkCharacteristics {
+        self.characteristics
+    }
+
+    /// Starts a round-trip time measure, the request being sent right away
+    pub fn rtt_request(&mut self) -> AutoDetectRequest {
+        self.rtt_request_at(Instant::now())
+    }
+
+    /// Starts a bandwidth measure
+    pub fn bandwidth_start(&mut self) -> AutoDetectRequest {
+        AutoDetectRequest::BandwidthStart {
+            sequence_number: self.sequence_number(),
+            phase: BandwidthPhase::Session,
+        }
+    }
+
+    /// Stops the bandwidth measure, the client answering with the results
+    pub fn bandwidth_stop(&mut self) -> AutoDetectRequest {
+        let sequence_number = self.sequence_number();
+        self.bandwidth_stop = Some(sequence_number);
+
+        AutoDetectRequest::BandwidthStop {
+            sequence_number,
+            phase: BandwidthPhase::Session,
+            payload: Vec::new(),
+        }
+    }
+
+    /// Returns the request sending the network characteristics to the client, if anything is measured
+    pub fn result_request(&mut self) -> Option<AutoDetectRequest> {
+        let NetworkCharacteristics {
+            base_rtt,
+            average_rtt,
+            bandwidth,
+        } = self.characteristics;
+
+        if base_rtt.is_none() && bandwidth.is_none() {
+            return None;
+        }
+
+        Some(AutoDetectRequest::NetworkCharacteristicsResult {
+            sequence_number: self.sequence_number(),
+            base_rtt: base_rtt.map(as_millis),
+            bandwidth,
+            average_rtt: average_rtt.map_or(0, as_millis),
+        })
+    }
+
+    /// Handles a response of the client, and returns the network characteristics when updated
+    pub fn handle_response(&mut self, response: &AutoDetectResponse) -> Option<NetworkCharacteristics> {
+        self.handle_response_at(response, Instant::now())
+    }
+
+    fn rtt_request_at(&mut self, now: Instant) -> AutoDetectRequest {
+        let sequence_number = self.sequence_number();
+
+        if self.rtt_requests.len() == Self::MAX_PENDING_RTT_REQUESTS {
+            self.rtt_requests.pop_front();
+        }
+        self.rtt_requests.push_back((sequence_number, now));
+
+        AutoDetectRequest::RttRequest {
+            sequence_number,
+            phase: AutoDetectPhase::Session,
+        }
+    }
+
+    fn handle_response_at(&mut self, response: &AutoDetectResponse, now: Instant) -> Option<NetworkCharacteristics> {
+        match *response {
+            AutoDetectResponse::RttResponse { sequence_number } => {
+                let Some(position) = self.rtt_requests.iter().position(|(seq, _)| *seq == sequence_number) else {
+                    debug!(sequence_number, "Unexpected RTT response");
+                    return None;
+                };
+                let (_, sent) = self.rtt_requests.remove(position)?;
+
+                self.update_rtt(now.saturating_duration_since(sent));
+            }
+            AutoDetectResponse::BandwidthResults {
+                sequence_number,
+                time_delta,
+                byte_count,
+                ..
+            } => {
+                if self.bandwidth_stop != Some(sequence_number) {
+                    debug!(sequence_number, "Unexpected bandwidth results");
+                    return None;
+                }
+                self.bandwidth_stop = None;
+
+                if time_delta == 0 {
+                    return None;
+                }
+
+                // Bytes per millisecond times 8 are kilobits per second
+                let bandwidth = u64::from(byte_count).saturating_mul(8) / u64::from(time_delta);
+                self.characteristics.bandwidth = Some(u32::try_from(bandwidth).unwrap_or(u32::MAX));
+            }
+            AutoDetectResponse::NetworkCharacteristicsSync { bandwidth, rtt, .. } => {
+                self.update_rtt(Duration::from_millis(u64::from(rtt)));
+                self.characteristics.bandwidth = Some(bandwidth);
+            }
+        }
+
+        debug!(characteristics = ?self.characteristics, "Network characteristics updated");
+
+        Some(self.characteristics)
+    }
+
+    fn update_rtt(&mut self, rtt: Duration) {
+        let characteristics = &mut self.characteristics;
+
+        characteristics.base_rtt = Some(characteristics.base_rtt.map_or(rtt, |base_rtt| base_rtt.min(rtt)));
+        // Smoothed like the RTT of TCP (RFC 6298)
+        characteristics.average_rtt = Some(
+            characteristics
+                .average_rtt
+                .map_or(rtt, |average_rtt| average_rtt * 7 / 8 + rtt / 8),
+        );
+    }
+
+    fn sequence_number(&mut self) -> u16 {
+        let sequence_number = self.next_sequence_number;
+        self.next_sequence_number = self.next_sequence_number.wrapping_add(1);
+        sequence_number
+    }
+}
+
+fn as_millis(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn measures_rtt() {
+        let mut detector = NetworkAutoDetect::new();
+        let start = Instant::now();
+
+        let first = detector.rtt_request_at(start).sequence_number();
+        let second = detector.rtt_request_at(start + millis(10)).sequence_number();
+        assert_ne!(first, second);
+
+        let characteristics = detector
+            .handle_response_at(
+                &AutoDetectResponse::RttResponse {
+                    sequence_number: second,
+                },
+                start + millis(30),
+            )
+            .unwrap();
+        assert_eq!(characteristics.base_rtt, Some(millis(20)));
+        assert_eq!(characteristics.average_rtt, Some(millis(20)));
+
+        let characteristics = detector
+            .handle_response_at(
+                &AutoDetectResponse::RttResponse { sequence_number: first },
+                start + millis(60),
+            )
+            .unwrap();
+        assert_eq!(characteristics.base_rtt, Some(millis(20)));
+        assert_eq!(characteristics.average_rtt, Some(millis(25)));
+
+        // Already answered
+        let response = AutoDetectResponse::RttResponse { sequence_number: first };
+        assert_eq!(detector.handle_response_at(&response, start + millis(70)), None);
+    }
+
+    #[test]
+    fn measures_bandwidth() {
+        let mut detector = NetworkAutoDetect::new();
+
+        detector.bandwidth_start();
+        let stop = detector.bandwidth_stop().sequence_number();
+
+        let results = AutoDetectResponse::BandwidthResults {
+            sequence_number: stop,
+            phase: AutoDetectPhase::Session,
+            time_delta: 20,
+            byte_count: 50_000,
+        };
+        let characteristics = detector.handle_response(&results).unwrap();
+        assert_eq!(characteristics.bandwidth, Some(20_000));
+        assert_eq!(characteristics.transfer_time(25_000), Some(millis(10)));
+
+        // Results of a measure not stopped
+        assert_eq!(detector.handle_response(&results), None);
+    }
+
+    #[test]
+    fn sends_results_to_the_client() {
+        let mut detector = NetworkAutoDetect::new();
+        assert_eq!(detector.result_request(), None);
+
+        detector.handle_response(&AutoDetectResponse::NetworkCharacteristicsSync {
+            sequence_number: 0,
+            bandwidth: 10_000,
+            rtt: 40,
+        });
+
+        assert!(matches!(
+            detector.result_request(),
+            Some(AutoDetectRequest::NetworkCharacteristicsResult {
+                base_rtt: Some(40),
+                bandwidth: Some(10_000),
+                average_rtt: 40,
+                ..
+            })
+        ));
+    }
+}
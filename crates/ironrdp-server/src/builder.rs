@@ -1,6 +1,9 @@
 use core::net::SocketAddr;
+use std::sync::Arc;
 
 use anyhow::Result;
+use ironrdp_displaycontrol::pdu::DisplayControlCapabilities;
+use ironrdp_graphics::rdp6::ColorLoss;
 use ironrdp_pdu::rdp::capability_sets::{server_codecs_capabilities, BitmapCodecs};
 use tokio_rustls::TlsAcceptor;
 
@@ -10,7 +13,10 @@ use super::display::{DesktopSize, RdpServerDisplay};
 use super::gfx::GfxServerFactory;
 use super::handler::{KeyboardEvent, MouseEvent, RdpServerInputHandler};
 use super::server::{RdpServer, RdpServerOptions, RdpServerSecurity};
-use crate::{DisplayUpdate, RdpServerDisplayUpdates, SoundServerFactory};
+use crate::{
+    AudioInputHandler, AuditSink, ChannelScheduling, DisplayUpdate, RailServerFactory, RdpServerDisplayUpdates,
+    RdpdrServerFactory, ServerChannels, SoundServerFactory,
+};
 
 pub struct WantsAddr {}
 pub struct WantsSecurity {
@@ -29,10 +35,19 @@ pub struct BuilderDone {
     addr: SocketAddr,
     security: RdpServerSecurity,
     codecs: BitmapCodecs,
+    display_control: DisplayControlCapabilities,
+    max_segment_size: Option<usize>,
+    channel_scheduling: ChannelScheduling,
+    channels: ServerChannels,
+    bitmap_color_loss: ColorLoss,
+    audit_sink: Option<Arc<dyn AuditSink>>,
     handler: Box<dyn RdpServerInputHandler>,
     display: Box<dyn RdpServerDisplay>,
     cliprdr_factory: Option<Box<dyn CliprdrServerFactory>>,
     sound_factory: Option<Box<dyn SoundServerFactory>>,
+    rail_factory: Option<Box<dyn RailServerFactory>>,
+    rdpdr_factory: Option<Box<dyn RdpdrServerFactory>>,
+    audio_input_handler: Option<Box<dyn AudioInputHandler>>,
     #[cfg(feature = "egfx")]
     gfx_factory: Option<Box<dyn GfxServerFactory>>,
 }
@@ -127,7 +142,16 @@ impl RdpServerBuilder<WantsDisplay> {
                 display: Box::new(display),
                 sound_factory: None,
                 cliprdr_factory: None,
+                rail_factory: None,
+                rdpdr_factory: None,
+                audio_input_handler: None,
                 codecs: server_codecs_capabilities(&[]).expect("can't panic for &[]"),
+                display_control: DisplayControlCapabilities::default(),
+                max_segment_size: None,
+                channel_scheduling: ChannelScheduling::default(),
+                channels: ServerChannels::ALL,
+                bitmap_color_loss: ColorLoss::NONE,
+                audit_sink: None,
                 #[cfg(feature = "egfx")]
                 gfx_factory: None,
             },
@@ -143,7 +167,16 @@ impl RdpServerBuilder<WantsDisplay> {
                 display: Box::new(NoopDisplay),
                 sound_factory: None,
                 cliprdr_factory: None,
+                rail_factory: None,
+                rdpdr_factory: None,
+                audio_input_handler: None,
                 codecs: server_codecs_capabilities(&[]).expect("can't panic for &[]"),
+                display_control: DisplayControlCapabilities::default(),
+                max_segment_size: None,
+                channel_scheduling: ChannelScheduling::default(),
+                channels: ServerChannels::ALL,
+                bitmap_color_loss: ColorLoss::NONE,
+                audit_sink: None,
                 #[cfg(feature = "egfx")]
                 gfx_factory: None,
             },
@@ -152,6 +185,57 @@ impl RdpServerBuilder<WantsDisplay> {
 }
 
 impl RdpServerBuilder<BuilderDone> {
+    /// Share the clipboard with the clients
+    pub fn with_clipboard<F>(self, factory: F) -> Self
+    where
+        F: CliprdrServerFactory + 'static,
+    {
+        self.with_cliprdr_factory(Some(Box::new(factory)))
+    }
+
+    /// Play the audio of the session on the clients
+    pub fn with_sound<F>(self, factory: F) -> Self
+    where
+        F: SoundServerFactory + 'static,
+    {
+        self.with_sound_factory(Some(Box::new(factory)))
+    }
+
+    /// Access the drives, smart cards and printers redirected by the clients
+    pub fn with_device_redirection<F>(self, factory: F) -> Self
+    where
+        F: RdpdrServerFactory + 'static,
+    {
+        self.with_rdpdr_factory(Some(Box::new(factory)))
+    }
+
+    /// Receive the audio recorded by the microphone of the clients
+    pub fn with_audio_input<H>(self, handler: H) -> Self
+    where
+        H: AudioInputHandler + 'static,
+    {
+        self.with_audio_input_handler(Some(Box::new(handler)))
+    }
+
+    /// Send the display through the graphics pipeline, see [`Self::with_gfx_factory`]
+    #[cfg(feature = "egfx")]
+    pub fn with_gfx<F>(self, factory: F) -> Self
+    where
+        F: GfxServerFactory + 'static,
+    {
+        self.with_gfx_factory(Some(Box::new(factory)))
+    }
+
+    /// Set the optional channels offered to the clients
+    ///
+    /// A channel is offered only when its module is set, e.g. with [`Self::with_clipboard`]. The channels can
+    /// also be disabled per connection with [`RdpServerInputHandler::channels`]. All the channels are enabled by
+    /// default.
+    pub fn with_channels(mut self, channels: ServerChannels) -> Self {
+        self.state.channels = channels;
+        self
+    }
+
     pub fn with_cliprdr_factory(mut self, cliprdr_factory: Option<Box<dyn CliprdrServerFactory>>) -> Self {
         self.state.cliprdr_factory = cliprdr_factory;
         self
@@ -162,6 +246,24 @@ impl RdpServerBuilder<BuilderDone> {
         self
     }
 
+    /// Offer remote applications instead of a full desktop
+    pub fn with_rail_factory(mut self, rail_factory: Option<Box<dyn RailServerFactory>>) -> Self {
+        self.state.rail_factory = rail_factory;
+        self
+    }
+
+    /// Access the drives redirected by the clients
+    pub fn with_rdpdr_factory(mut self, rdpdr_factory: Option<Box<dyn RdpdrServerFactory>>) -> Self {
+        self.state.rdpdr_factory = rdpdr_factory;
+        self
+    }
+
+    /// Receive the audio recorded by the microphone of the clients
+    pub fn with_audio_input_handler(mut self, handler: Option<Box<dyn AudioInputHandler>>) -> Self {
+        self.state.audio_input_handler = handler;
+        self
+    }
+
     /// Configure EGFX (Graphics Pipeline Extension) for H.264 video streaming
     ///
     /// The graphics factory creates a handler that receives EGFX callbacks
@@ -170,12 +272,12 @@ impl RdpServerBuilder<BuilderDone> {
     /// # Example
     ///
     /// ```ignore
-    /// use ironrdp_server::{RdpServer, GfxServerFactory};
+    /// use ironrdp_server::{RdpServer, GfxServerConfig, GfxServerFactory};
     /// use ironrdp_egfx::server::GraphicsPipelineHandler;
     ///
     /// struct MyGfxFactory;
     /// impl GfxServerFactory for MyGfxFactory {
-    ///     fn build_gfx_handler(&self) -> Box<dyn GraphicsPipelineHandler> {
+    ///     fn build_gfx_handler(&self, config: &GfxServerConfig) -> Box<dyn GraphicsPipelineHandler> {
     ///         // Return your handler implementation
     ///     }
     /// }
@@ -196,17 +298,69 @@ impl RdpServerBuilder<BuilderDone> {
         self
     }
 
+    /// Set the capabilities announced on the display control channel
+    ///
+    /// By default, a single monitor of up to 3840x2400 pixels is supported.
+    pub fn with_display_control_capabilities(mut self, capabilities: DisplayControlCapabilities) -> Self {
+        self.state.display_control = capabilities;
+        self
+    }
+
+    /// Set the target size of the PDUs sent on the wire, headers included
+    ///
+    /// The fast-path updates and the dynamic virtual channel messages are split to fit in it, avoiding the IP
+    /// fragmentation on links with a small MTU, e.g. 1400 bytes for a VPN or UDP encapsulation.
+    pub fn with_max_segment_size(mut self, size: usize) -> Self {
+        self.state.max_segment_size = Some(size);
+        self
+    }
+
+    /// Set the priorities and bandwidth caps of the virtual channel data sent to the clients
+    ///
+    /// By default, the graphics and audio data is sent ahead of the clipboard and drive file transfers.
+    pub fn with_channel_scheduling(mut self, scheduling: ChannelScheduling) -> Self {
+        self.state.channel_scheduling = scheduling;
+        self
+    }
+
+    /// Set the color loss of the RDP 6.0 planar bitmaps sent to clients without surface commands
+    ///
+    /// The color loss and the chroma subsampling are only used with clients allowing them in their
+    /// bitmap capability set. By default, bitmaps are lossless.
+    pub fn with_bitmap_color_loss(mut self, color_loss: ColorLoss) -> Self {
+        self.state.bitmap_color_loss = color_loss;
+        self
+    }
+
+    /// Receive the actions blocked by the session policies, see [`RdpServerInputHandler::session_policy`]
+    pub fn with_audit_sink<S>(mut self, sink: S) -> Self
+    where
+        S: AuditSink + 'static,
+    {
+        self.state.audit_sink = Some(Arc::new(sink));
+        self
+    }
+
     pub fn build(self) -> RdpServer {
         RdpServer::new(
             RdpServerOptions {
                 addr: self.state.addr,
                 security: self.state.security,
                 codecs: self.state.codecs,
+                display_control: self.state.display_control,
+                max_segment_size: self.state.max_segment_size,
+                channel_scheduling: self.state.channel_scheduling,
+                channels: self.state.channels,
+                bitmap_color_loss: self.state.bitmap_color_loss,
+                audit_sink: self.state.audit_sink,
             },
             self.state.handler,
             self.state.display,
             self.state.sound_factory,
             self.state.cliprdr_factory,
+            self.state.rail_factory,
+            self.state.rdpdr_factory,
+            self.state.audio_input_handler,
             #[cfg(feature = "egfx")]
             self.state.gfx_factory,
         )
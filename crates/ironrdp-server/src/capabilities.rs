@@ -1,4 +1,6 @@
+use ironrdp_core::encode_vec;
 use ironrdp_pdu::rdp::capability_sets::{self, GeneralExtraFlags};
+use ironrdp_rail::pdu::{RailCapabilitySet, RailSupportLevel, WindowListCapabilitySet, WindowSupportLevel};
 
 use crate::{DesktopSize, RdpServerOptions};
 
@@ -7,6 +9,7 @@ pub(crate) fn capabilities(opts: &RdpServerOptions, size: DesktopSize) -> Vec<ca
         capability_sets::CapabilitySet::General(general_capabilities()),
         capability_sets::CapabilitySet::Bitmap(bitmap_capabilities(&size)),
         capability_sets::CapabilitySet::Order(order_capabilities()),
+        capability_sets::CapabilitySet::BitmapCacheHostSupport(bitmap_cache_host_support()),
         capability_sets::CapabilitySet::SurfaceCommands(surface_capabilities()),
         capability_sets::CapabilitySet::Pointer(pointer_capabilities()),
         capability_sets::CapabilitySet::Input(input_capabilities()),
@@ -16,9 +19,28 @@ pub(crate) fn capabilities(opts: &RdpServerOptions, size: DesktopSize) -> Vec<ca
     ]
 }
 
+/// Capabilities of remote applications, announced when they are offered
+pub(crate) fn rail_capabilities() -> Vec<capability_sets::CapabilitySet> {
+    let rail = RailCapabilitySet {
+        support_level: RailSupportLevel::SUPPORTED,
+    };
+    let window_list = WindowListCapabilitySet {
+        support_level: WindowSupportLevel::SUPPORTED,
+        num_icon_caches: 3,
+        num_icon_cache_entries: 12,
+    };
+
+    vec![
+        capability_sets::CapabilitySet::Rail(encode_vec(&rail).expect("can't panic for a fixed size PDU")),
+        capability_sets::CapabilitySet::WindowList(encode_vec(&window_list).expect("can't panic for a fixed size PDU")),
+    ]
+}
+
 fn general_capabilities() -> capability_sets::General {
     capability_sets::General {
         extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED,
+        refresh_rect_support: true,
+        suppress_output_support: true,
         ..Default::default()
     }
 }
@@ -44,6 +66,12 @@ fn order_capabilities() -> capability_sets::Order {
     )
 }
 
+fn bitmap_cache_host_support() -> Vec<u8> {
+    // TS_BITMAPCACHE_HOSTSUPPORT_CAPABILITYSET with cacheVersion = TS_BITMAPCACHE_REV2, followed by padding.
+    // Clients advertise revision 2 bitmap caches, and send their persistent keys, only when it's present.
+    vec![0x01, 0x00, 0x00, 0x00]
+}
+
 fn surface_capabilities() -> capability_sets::SurfaceCommands {
     capability_sets::SurfaceCommands {
         flags: capability_sets::CmdFlags::all(),
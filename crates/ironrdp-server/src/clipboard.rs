@@ -1,5 +1,460 @@
-use ironrdp_cliprdr::backend::CliprdrBackendFactory;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
 
-use crate::ServerEventSender;
+use ironrdp_cliprdr::backend::{ClipboardMessage, CliprdrBackend, CliprdrBackendFactory};
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardFormatId, ClipboardGeneralCapabilityFlags, FileContentsFlags, FileContentsRequest,
+    FileContentsResponse, FileDescriptor, FormatDataRequest, FormatDataResponse, LockDataId, PackedFileList,
+};
+use ironrdp_core::impl_as_any;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
 
-pub trait CliprdrServerFactory: CliprdrBackendFactory + ServerEventSender {}
+use crate::{ConnectionContext, ServerEvent, ServerEventSender};
+
+pub trait CliprdrServerFactory: CliprdrBackendFactory + ServerEventSender {
+    /// Builds a backend for the given connection
+    ///
+    /// Defaults to [`CliprdrBackendFactory::build_cliprdr_backend`], for backends which don't depend on the connection.
+    fn build_cliprdr_backend_for(&self, _ctx: &ConnectionContext) -> Box<dyn CliprdrBackend> {
+        self.build_cliprdr_backend()
+    }
+}
+
+/// Content of the server clipboard, rendered in a requested format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardData {
+    Data(Vec<u8>),
+    /// Files for the `FileGroupDescriptorW` format, their contents are read with [`ClipboardBackend::read_file`]
+    Files(Vec<FileDescriptor>),
+}
+
+/// Clipboard of the server, shared with the clients
+///
+/// The server clipboard is delay-rendered: its formats are announced with [`RemoteClipboard::copy`], and the data
+/// is only rendered once a client pastes it.
+#[async_trait::async_trait]
+pub trait ClipboardBackend: Send + Sync {
+    /// The channel is ready, `clipboard` gives access to the clipboard of the client
+    fn on_ready(&self, clipboard: RemoteClipboard);
+
+    /// The client copied, its data can be pasted with [`RemoteClipboard::paste`]
+    async fn on_remote_copy(&self, formats: Vec<ClipboardFormat>);
+
+    /// Renders the server clipboard in one of the announced formats, `None` if it is not available anymore
+    async fn render_format(&self, format: ClipboardFormatId) -> Option<ClipboardData>;
+
+    /// Size of the file at `index` in the last rendered [`ClipboardData::Files`]
+    async fn file_size(&self, index: u32) -> io::Result<u64> {
+        let _ = index;
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Reads up to `len` bytes at `offset` of the file at `index` in the last rendered [`ClipboardData::Files`]
+    async fn read_file(&self, index: u32, offset: u64, len: u32) -> io::Result<Vec<u8>> {
+        let _ = (index, offset, len);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Requests waiting for a response of the client
+#[derive(Debug, Default)]
+struct PendingRequests {
+    /// The client answers format data requests in order
+    format_data: VecDeque<oneshot::Sender<Option<Vec<u8>>>>,
+    file_contents: HashMap<u32, oneshot::Sender<Option<Vec<u8>>>>,
+    next_stream_id: u32,
+}
+
+/// Handle to the clipboard of a client
+#[derive(Debug, Clone)]
+pub struct RemoteClipboard {
+    sender: mpsc::UnboundedSender<ServerEvent>,
+    pending: Arc<Mutex<PendingRequests>>,
+}
+
+impl RemoteClipboard {
+    fn send(&self, message: ClipboardMessage) -> bool {
+        self.sender.send(ServerEvent::Clipboard(message)).is_ok()
+    }
+
+    fn pending(&self) -> MutexGuard<'_, PendingRequests> {
+        self.pending.lock().expect("clipboard requests mutex poisoned")
+    }
+
+    /// Announces the formats of the server clipboard, rendered later by [`ClipboardBackend::render_format`]
+    pub fn copy(&self, formats: Vec<ClipboardFormat>) {
+        self.send(ClipboardMessage::SendInitiateCopy(formats));
+    }
+
+    /// Fetches the clipboard of the client in the given format, `None` if it is not available
+    pub async fn paste(&self, format: ClipboardFormatId) -> Option<Vec<u8>> {
+        let (tx, rx) = oneshot::channel();
+
+        {
+            let mut pending = self.pending();
+            if !self.send(ClipboardMessage::SendInitiatePaste(format)) {
+                return None;
+            }
+            pending.format_data.push_back(tx);
+        }
+
+        rx.await.ok().flatten()
+    }
+
+    /// Fetches the files copied by the client, `format` is the id of its `FileGroupDescriptorW` format
+    pub async fn paste_files(&self, format: ClipboardFormatId) -> Option<Vec<FileDescriptor>> {
+        let data = self.paste(format).await?;
+
+        match FormatDataResponse::new_data(data).to_file_list() {
+            Ok(list) => Some(list.files),
+            Err(error) => {
+                warn!(?error, "Invalid file list");
+                None
+            }
+        }
+    }
+
+    /// Size of the file at `index` in the last pasted file list
+    pub async fn file_size(&self, index: u32) -> Option<u64> {
+        let data = self.file_contents(index, FileContentsFlags::SIZE, 0, 8).await?;
+
+        data.try_into().ok().map(u64::from_le_bytes)
+    }
+
+    /// Reads up to `len` bytes at `offset` of the file at `index` in the last pasted file list
+    pub async fn read_file(&self, index: u32, offset: u64, len: u32) -> Option<Vec<u8>> {
+        self.file_contents(index, FileContentsFlags::DATA, offset, len).await
+    }
+
+    async fn file_contents(
+        &self,
+        index: u32,
+        flags: FileContentsFlags,
+        position: u64,
+        requested_size: u32,
+    ) -> Option<Vec<u8>> {
+        let (tx, rx) = oneshot::channel();
+
+        {
+            let mut pending = self.pending();
+            let stream_id = pending.next_stream_id;
+            pending.next_stream_id = stream_id.wrapping_add(1);
+
+            let request = FileContentsRequest {
+                stream_id,
+                index,
+                flags,
+                position,
+                requested_size,
+                data_id: None,
+            };
+            if !self.send(ClipboardMessage::SendFileContentsRequest(request)) {
+                return None;
+            }
+            pending.file_contents.insert(stream_id, tx);
+        }
+
+        rx.await.ok().flatten()
+    }
+}
+
+/// Builds [`CliprdrBackend`]s driven by a [`ClipboardBackend`]
+pub struct ClipboardFactory {
+    backend: Arc<dyn ClipboardBackend>,
+    sender: Option<mpsc::UnboundedSender<ServerEvent>>,
+}
+
+impl ClipboardFactory {
+    pub fn new(backend: Arc<dyn ClipboardBackend>) -> Self {
+        Self { backend, sender: None }
+    }
+}
+
+impl CliprdrBackendFactory for ClipboardFactory {
+    fn build_cliprdr_backend(&self) -> Box<dyn CliprdrBackend> {
+        let sender = self.sender.clone().unwrap_or_else(|| {
+            warn!("Clipboard factory used without event sender");
+            // Messages to a closed channel are dropped.
+            mpsc::unbounded_channel().0
+        });
+
+        Box::new(ClipboardBridge::new(Arc::clone(&self.backend), sender))
+    }
+}
+
+impl ServerEventSender for ClipboardFactory {
+    fn set_sender(&mut self, sender: mpsc::UnboundedSender<ServerEvent>) {
+        self.sender = Some(sender);
+    }
+}
+
+impl CliprdrServerFactory for ClipboardFactory {}
+
+/// Forwards the clipboard channel callbacks to a [`ClipboardBackend`], from tasks of the runtime
+struct ClipboardBridge {
+    backend: Arc<dyn ClipboardBackend>,
+    clipboard: RemoteClipboard,
+}
+
+impl ClipboardBridge {
+    fn new(backend: Arc<dyn ClipboardBackend>, sender: mpsc::UnboundedSender<ServerEvent>) -> Self {
+        Self {
+            backend,
+            clipboard: RemoteClipboard {
+                sender,
+                pending: Arc::default(),
+            },
+        }
+    }
+}
+
+impl core::fmt::Debug for ClipboardBridge {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ClipboardBridge")
+            .field("clipboard", &self.clipboard)
+            .finish()
+    }
+}
+
+impl Drop for ClipboardBridge {
+    fn drop(&mut self) {
+        // Pending requests fail once the channel is gone.
+        let mut pending = self.clipboard.pending();
+        pending.format_data.clear();
+        pending.file_contents.clear();
+    }
+}
+
+impl_as_any!(ClipboardBridge);
+
+async fn render_format(backend: &dyn ClipboardBackend, format: ClipboardFormatId) -> FormatDataResponse<'static> {
+    match backend.render_format(format).await {
+        Some(ClipboardData::Data(data)) => FormatDataResponse::new_data(data),
+        Some(ClipboardData::Files(files)) => FormatDataResponse::new_file_list(&PackedFileList { files })
+            .unwrap_or_else(|error| {
+                warn!(?error, "Failed to encode file list");
+                FormatDataResponse::new_error()
+            }),
+        None => FormatDataResponse::new_error(),
+    }
+}
+
+async fn file_contents(backend: &dyn ClipboardBackend, request: FileContentsRequest) -> FileContentsResponse<'static> {
+    let result = if request.flags.contains(FileContentsFlags::SIZE) {
+        backend
+            .file_size(request.index)
+            .await
+            .map(|size| FileContentsResponse::new_size_response(request.stream_id, size))
+    } else {
+        backend
+            .read_file(request.index, request.position, request.requested_size)
+            .await
+            .map(|data| FileContentsResponse::new_data_response(request.stream_id, data))
+    };
+
+    result.unwrap_or_else(|error| {
+        warn!(?error, ?request, "Failed to read clipboard file");
+        FileContentsResponse::new_error(request.stream_id)
+    })
+}
+
+impl CliprdrBackend for ClipboardBridge {
+    fn temporary_directory(&self) -> &str {
+        // Only sent by clients.
+        ""
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED
+            | ClipboardGeneralCapabilityFlags::FILECLIP_NO_FILE_PATHS
+            | ClipboardGeneralCapabilityFlags::HUGE_FILE_SUPPORT_ENABLED
+    }
+
+    fn on_ready(&mut self) {
+        self.backend.on_ready(self.clipboard.clone());
+    }
+
+    fn on_request_format_list(&mut self) {
+        // Only requested from clients.
+    }
+
+    fn on_process_negotiated_capabilities(&mut self, capabilities: ClipboardGeneralCapabilityFlags) {
+        debug!(?capabilities, "Clipboard capabilities negotiated");
+    }
+
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]) {
+        let backend = Arc::clone(&self.backend);
+        let formats = available_formats.to_vec();
+
+        tokio::spawn(async move { backend.on_remote_copy(formats).await });
+    }
+
+    fn on_format_data_request(&mut self, request: FormatDataRequest) {
+        let backend = Arc::clone(&self.backend);
+        let clipboard = self.clipboard.clone();
+
+        tokio::spawn(async move {
+            let response = render_format(backend.as_ref(), request.format).await;
+            clipboard.send(ClipboardMessage::SendFormatData(response));
+        });
+    }
+
+    fn on_format_data_response(&mut self, response: FormatDataResponse<'_>) {
+        let Some(tx) = self.clipboard.pending().format_data.pop_front() else {
+            warn!("Unexpected clipboard format data response");
+            return;
+        };
+
+        let data = (!response.is_error()).then(|| response.data().to_vec());
+        let _ = tx.send(data);
+    }
+
+    fn on_file_contents_request(&mut self, request: FileContentsRequest) {
+        let backend = Arc::clone(&self.backend);
+        let clipboard = self.clipboard.clone();
+
+        tokio::spawn(async move {
+            let response = file_contents(backend.as_ref(), request).await;
+            clipboard.send(ClipboardMessage::SendFileContentsResponse(response));
+        });
+    }
+
+    fn on_file_contents_response(&mut self, response: FileContentsResponse<'_>) {
+        let Some(tx) = self.clipboard.pending().file_contents.remove(&response.stream_id()) else {
+            warn!(
+                stream_id = response.stream_id(),
+                "Unexpected clipboard file contents response"
+            );
+            return;
+        };
+
+        let data = (!response.is_error()).then(|| response.data().to_vec());
+        let _ = tx.send(data);
+    }
+
+    fn on_lock(&mut self, data_id: LockDataId) {
+        debug!(?data_id, "Clipboard data locked");
+    }
+
+    fn on_unlock(&mut self, data_id: LockDataId) {
+        debug!(?data_id, "Clipboard data unlocked");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ironrdp_cliprdr::pdu::{OwnedFileContentsResponse, OwnedFormatDataResponse};
+
+    use super::*;
+
+    const FILE: &[u8] = b"file contents";
+
+    struct Backend;
+
+    #[async_trait::async_trait]
+    impl ClipboardBackend for Backend {
+        fn on_ready(&self, _clipboard: RemoteClipboard) {}
+
+        async fn on_remote_copy(&self, _formats: Vec<ClipboardFormat>) {}
+
+        async fn render_format(&self, format: ClipboardFormatId) -> Option<ClipboardData> {
+            match format {
+                ClipboardFormatId::CF_UNICODETEXT => Some(ClipboardData::Data(vec![b'a', 0, 0, 0])),
+                _ => None,
+            }
+        }
+
+        async fn file_size(&self, _index: u32) -> io::Result<u64> {
+            Ok(u64::try_from(FILE.len()).expect("small file"))
+        }
+
+        async fn read_file(&self, _index: u32, offset: u64, len: u32) -> io::Result<Vec<u8>> {
+            let start = usize::try_from(offset).expect("small offset");
+            let end = (start + usize::try_from(len).expect("small length")).min(FILE.len());
+            Ok(FILE[start..end].to_vec())
+        }
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().build().expect("runtime")
+    }
+
+    async fn next_message(receiver: &mut mpsc::UnboundedReceiver<ServerEvent>) -> ClipboardMessage {
+        match receiver.recv().await {
+            Some(ServerEvent::Clipboard(message)) => message,
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    #[test]
+    fn renders_server_clipboard() {
+        let (sender, mut receiver) = ServerEvent::create_channel();
+        let mut bridge = ClipboardBridge::new(Arc::new(Backend), sender);
+
+        runtime().block_on(async {
+            bridge.on_format_data_request(FormatDataRequest {
+                format: ClipboardFormatId::CF_UNICODETEXT,
+            });
+            let ClipboardMessage::SendFormatData(response) = next_message(&mut receiver).await else {
+                panic!("expected format data");
+            };
+            assert_eq!(response, OwnedFormatDataResponse::new_data(vec![b'a', 0, 0, 0]));
+
+            bridge.on_format_data_request(FormatDataRequest {
+                format: ClipboardFormatId::CF_TEXT,
+            });
+            let ClipboardMessage::SendFormatData(response) = next_message(&mut receiver).await else {
+                panic!("expected format data");
+            };
+            assert!(response.is_error());
+
+            bridge.on_file_contents_request(FileContentsRequest {
+                stream_id: 7,
+                index: 0,
+                flags: FileContentsFlags::DATA,
+                position: 5,
+                requested_size: 100,
+                data_id: None,
+            });
+            let ClipboardMessage::SendFileContentsResponse(response) = next_message(&mut receiver).await else {
+                panic!("expected file contents");
+            };
+            assert_eq!(
+                response,
+                OwnedFileContentsResponse::new_data_response(7, b"contents".to_vec())
+            );
+        });
+    }
+
+    #[test]
+    fn pastes_client_files() {
+        let (sender, mut receiver) = ServerEvent::create_channel();
+        let mut bridge = ClipboardBridge::new(Arc::new(Backend), sender);
+        let clipboard = bridge.clipboard.clone();
+
+        runtime().block_on(async {
+            let size = tokio::spawn(async move { clipboard.file_size(2).await });
+
+            let ClipboardMessage::SendFileContentsRequest(request) = next_message(&mut receiver).await else {
+                panic!("expected file contents request");
+            };
+            assert_eq!(request.index, 2);
+            assert_eq!(request.flags, FileContentsFlags::SIZE);
+
+            bridge.on_file_contents_response(FileContentsResponse::new_size_response(request.stream_id, 1234));
+            assert_eq!(size.await.expect("task"), Some(1234));
+
+            let clipboard = bridge.clipboard.clone();
+            let paste = tokio::spawn(async move { clipboard.paste(ClipboardFormatId::CF_TEXT).await });
+            assert!(matches!(
+                next_message(&mut receiver).await,
+                ClipboardMessage::SendInitiatePaste(ClipboardFormatId::CF_TEXT)
+            ));
+
+            bridge.on_format_data_response(FormatDataResponse::new_error());
+            assert_eq!(paste.await.expect("task"), None);
+        });
+    }
+}
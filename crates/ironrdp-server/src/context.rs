@@ -0,0 +1,244 @@
+use core::net::SocketAddr;
+
+use ironrdp_pdu::nego::SecurityProtocol;
+use ironrdp_pdu::rdp::client_info::Credentials;
+
+use crate::SessionPolicy;
+
+/// User the client authenticates as
+///
+/// The password is never exposed to the channel handlers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    pub username: String,
+    pub domain: Option<String>,
+}
+
+impl From<&Credentials> for ClientIdentity {
+    fn from(creds: &Credentials) -> Self {
+        Self {
+            username: creds.username.clone(),
+            domain: creds.domain.clone(),
+        }
+    }
+}
+
+/// Information about a connection, given to the channel factories when building the handlers
+///
+/// The context is created once the security protocol is negotiated and doesn't change afterwards.
+/// The identity is the one the client is required to authenticate as: when authentication fails,
+/// the connection is dropped before any channel is started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionContext {
+    session_id: u64,
+    peer_addr: Option<SocketAddr>,
+    security: SecurityProtocol,
+    identity: Option<ClientIdentity>,
+    access: SessionAccess,
+    channels: ServerChannels,
+    policy: SessionPolicy,
+}
+
+/// Access of a client to the session, see [`RdpServerInputHandler::session_access`]
+///
+/// [`RdpServerInputHandler::session_access`]: crate::RdpServerInputHandler::session_access
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SessionAccess {
+    /// The client controls the session, unless it gives up control
+    #[default]
+    Full,
+    /// The client observes the session, e.g. for support or training
+    ///
+    /// All the input of the client is discarded, whether it requests control or not, and the channels able to
+    /// act on the session are not offered: clipboard, display control, advanced input and audio input (see
+    /// [`ServerChannels::view_only`]). The remote application and device redirection factories can check
+    /// [`ConnectionContext::access`].
+    ViewOnly {
+        /// Whether the client is told that the server keeps control once connected, through a Control
+        /// (Granted Control) PDU granting control to the server
+        notify: bool,
+    },
+}
+
+impl SessionAccess {
+    pub fn is_view_only(self) -> bool {
+        matches!(self, Self::ViewOnly { .. })
+    }
+}
+
+/// Optional channels offered to the clients
+///
+/// A channel is offered when its module is set on the builder, e.g. a clipboard factory, and when it is enabled
+/// both for the server with [`RdpServerBuilder::with_channels`] and for the connection with
+/// [`RdpServerInputHandler::channels`]. All the channels are enabled by default.
+///
+/// [`RdpServerBuilder::with_channels`]: crate::builder::RdpServerBuilder::with_channels
+/// [`RdpServerInputHandler::channels`]: crate::RdpServerInputHandler::channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ServerChannels {
+    /// Audio output, with a [`SoundServerFactory`](crate::SoundServerFactory)
+    pub audio_output: bool,
+    /// Audio input, with an [`AudioInputHandler`](crate::AudioInputHandler)
+    pub audio_input: bool,
+    /// Clipboard, with a [`CliprdrServerFactory`](crate::CliprdrServerFactory)
+    pub clipboard: bool,
+    /// Drive, smart card and printer redirection, with a [`RdpdrServerFactory`](crate::RdpdrServerFactory)
+    pub device_redirection: bool,
+    /// Graphics pipeline (EGFX), with a `GfxServerFactory`
+    pub graphics_pipeline: bool,
+    /// Display control, the client requesting monitor layouts
+    pub display_control: bool,
+    /// Advanced input, the client sending its input on a dynamic channel
+    pub advanced_input: bool,
+}
+
+impl ServerChannels {
+    pub const ALL: Self = Self {
+        audio_output: true,
+        audio_input: true,
+        clipboard: true,
+        device_redirection: true,
+        graphics_pipeline: true,
+        display_control: true,
+        advanced_input: true,
+    };
+
+    pub const NONE: Self = Self {
+        audio_output: false,
+        audio_input: false,
+        clipboard: false,
+        device_redirection: false,
+        graphics_pipeline: false,
+        display_control: false,
+        advanced_input: false,
+    };
+
+    /// Channels enabled in both `self` and `other`
+    #[must_use]
+    pub fn intersection(self, other: Self) -> Self {
+        Self {
+            audio_output: self.audio_output && other.audio_output,
+            audio_input: self.audio_input && other.audio_input,
+            clipboard: self.clipboard && other.clipboard,
+            device_redirection: self.device_redirection && other.device_redirection,
+            graphics_pipeline: self.graphics_pipeline && other.graphics_pipeline,
+            display_control: self.display_control && other.display_control,
+            advanced_input: self.advanced_input && other.advanced_input,
+        }
+    }
+
+    /// Disables the channels able to act on the session, see [`SessionAccess::ViewOnly`]
+    #[must_use]
+    pub fn view_only(self) -> Self {
+        Self {
+            audio_input: false,
+            clipboard: false,
+            display_control: false,
+            advanced_input: false,
+            ..self
+        }
+    }
+}
+
+impl Default for ServerChannels {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl ConnectionContext {
+    pub fn new(
+        session_id: u64,
+        peer_addr: Option<SocketAddr>,
+        security: SecurityProtocol,
+        identity: Option<ClientIdentity>,
+    ) -> Self {
+        Self {
+            session_id,
+            peer_addr,
+            security,
+            identity,
+            access: SessionAccess::Full,
+            channels: ServerChannels::ALL,
+            policy: SessionPolicy::ALLOW_ALL,
+        }
+    }
+
+    #[must_use]
+    pub fn with_access(mut self, access: SessionAccess) -> Self {
+        self.access = access;
+        self
+    }
+
+    #[must_use]
+    pub fn with_channels(mut self, channels: ServerChannels) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    #[must_use]
+    pub fn with_policy(mut self, policy: SessionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Identifier of the session, unique for the lifetime of the `RdpServer`
+    pub fn session_id(&self) -> u64 {
+        self.session_id
+    }
+
+    /// Address of the client, if known
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Security protocol negotiated with the client
+    pub fn security(&self) -> SecurityProtocol {
+        self.security
+    }
+
+    /// Authenticated user, `None` when the server doesn't require credentials
+    pub fn identity(&self) -> Option<&ClientIdentity> {
+        self.identity.as_ref()
+    }
+
+    /// Access of the client to the session
+    pub fn access(&self) -> SessionAccess {
+        self.access
+    }
+
+    /// Optional channels offered to the client
+    pub fn channels(&self) -> ServerChannels {
+        self.channels
+    }
+
+    /// Restrictions enforced on the client, see [`SessionPolicy`]
+    pub fn policy(&self) -> SessionPolicy {
+        self.policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_only_channels() {
+        let channels = ServerChannels {
+            clipboard: false,
+            ..ServerChannels::ALL
+        };
+
+        assert_eq!(
+            channels.view_only(),
+            ServerChannels {
+                audio_output: true,
+                device_redirection: true,
+                graphics_pipeline: true,
+                ..ServerChannels::NONE
+            }
+        );
+        assert_eq!(channels.intersection(ServerChannels::NONE), ServerChannels::NONE);
+        assert_eq!(channels.intersection(ServerChannels::ALL), channels);
+    }
+}
@@ -2,14 +2,18 @@ use core::num::{NonZeroU16, NonZeroUsize};
 
 use anyhow::Result;
 use bytes::{Bytes, BytesMut};
-use ironrdp_displaycontrol::pdu::DisplayControlMonitorLayout;
+use ironrdp_displaycontrol::server::MonitorLayout;
 use ironrdp_graphics::diff;
+use ironrdp_graphics::image_processing::Rgba;
 use ironrdp_pdu::pointer::PointerPositionAttribute;
 use tracing::{debug, warn};
 
+use crate::GpuFrameUpdate;
+
 #[rustfmt::skip]
 pub use ironrdp_acceptor::DesktopSize;
 pub use ironrdp_graphics::image_processing::PixelFormat;
+pub use ironrdp_pdu::rdp::client_info::VisualExperience;
 
 /// Display Update
 ///
@@ -20,6 +24,10 @@ pub use ironrdp_graphics::image_processing::PixelFormat;
 pub enum DisplayUpdate {
     Resize(DesktopSize),
     Bitmap(BitmapUpdate),
+    GpuFrame(GpuFrameUpdate),
+    ScreenCopy(ScreenCopyUpdate),
+    SolidFill(SolidFillUpdate),
+    Line(LineUpdate),
     PointerPosition(PointerPositionAttribute),
     ColorPointer(ColorPointer),
     RGBAPointer(RGBAPointer),
@@ -142,6 +150,114 @@ impl Framebuffer {
             })
             .for_each(|sub| self.update(&sub));
     }
+
+    fn contains(&self, x: u16, y: u16, width: NonZeroU16, height: NonZeroU16) -> bool {
+        u32::from(x) + u32::from(width.get()) <= u32::from(self.width.get())
+            && u32::from(y) + u32::from(height.get()) <= u32::from(self.height.get())
+    }
+
+    fn pixel(&self, color: RgbColor) -> Option<Vec<u8>> {
+        let mut pixel = vec![0; usize::from(self.format.bytes_per_pixel())];
+        let color = Rgba {
+            r: color.red,
+            g: color.green,
+            b: color.blue,
+            a: 0xFF,
+        };
+        self.format.write_color(color, &mut pixel).ok()?;
+
+        Some(pixel)
+    }
+
+    /// Applies a [`ScreenCopyUpdate`], returns `false` when it doesn't fit in the framebuffer
+    pub(crate) fn copy_rect(&mut self, copy: &ScreenCopyUpdate) -> bool {
+        if !self.contains(copy.src_x, copy.src_y, copy.width, copy.height)
+            || !self.contains(copy.x, copy.y, copy.width, copy.height)
+        {
+            return false;
+        }
+
+        let bpp = usize::from(self.format.bytes_per_pixel());
+        let stride = self.stride;
+        let row_len = usize::from(copy.width.get()) * bpp;
+        let offset = |x: u16, y: u16, row: usize| (usize::from(y) + row) * stride + usize::from(x) * bpp;
+
+        let mut copy_row = |row: usize| {
+            let src = offset(copy.src_x, copy.src_y, row);
+            self.data.copy_within(src..src + row_len, offset(copy.x, copy.y, row));
+        };
+
+        // Overlapping rows must be copied before being overwritten.
+        let rows = 0..NonZeroUsize::from(copy.height).get();
+        if copy.y > copy.src_y {
+            rows.rev().for_each(&mut copy_row);
+        } else {
+            rows.for_each(&mut copy_row);
+        }
+
+        true
+    }
+
+    /// Applies a [`SolidFillUpdate`], returns `false` when it doesn't fit in the framebuffer
+    pub(crate) fn fill_rect(&mut self, fill: &SolidFillUpdate) -> bool {
+        if !self.contains(fill.x, fill.y, fill.width, fill.height) {
+            return false;
+        }
+        let Some(pixel) = self.pixel(fill.color) else {
+            return false;
+        };
+
+        let start = usize::from(fill.x) * pixel.len();
+        let row_len = usize::from(fill.width.get()) * pixel.len();
+
+        for row in self
+            .data
+            .chunks_mut(self.stride)
+            .skip(usize::from(fill.y))
+            .take(NonZeroUsize::from(fill.height).get())
+        {
+            for dst in row[start..start + row_len].chunks_exact_mut(pixel.len()) {
+                dst.copy_from_slice(&pixel);
+            }
+        }
+
+        true
+    }
+
+    /// Applies a [`LineUpdate`], the pixels out of the framebuffer are skipped
+    pub(crate) fn draw_line(&mut self, line: &LineUpdate) {
+        let Some(pixel) = self.pixel(line.color) else {
+            return;
+        };
+
+        // Bresenham's line algorithm.
+        let (mut x, mut y) = (i32::from(line.start_x), i32::from(line.start_y));
+        let (end_x, end_y) = (i32::from(line.end_x), i32::from(line.end_y));
+        let dx = (end_x - x).abs();
+        let dy = -(end_y - y).abs();
+        let step_x = if x < end_x { 1 } else { -1 };
+        let step_y = if y < end_y { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        while (x, y) != (end_x, end_y) {
+            if let (Ok(px), Ok(py)) = (usize::try_from(x), usize::try_from(y)) {
+                if px < usize::from(self.width.get()) && py < usize::from(self.height.get()) {
+                    let start = py * self.stride + px * pixel.len();
+                    self.data[start..start + pixel.len()].copy_from_slice(&pixel);
+                }
+            }
+
+            let double_error = 2 * error;
+            if double_error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if double_error <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
 }
 
 /// Bitmap Display Update
@@ -230,11 +346,65 @@ impl core::fmt::Debug for BitmapUpdate {
     }
 }
 
+/// A RGB color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RgbColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+/// Screen Copy Display Update
+///
+/// Copies an area of the screen to another place, e.g.: when scrolling. The update is sent as
+/// a ScrBlt drawing order when supported by the client, or as a bitmap update otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenCopyUpdate {
+    pub src_x: u16,
+    pub src_y: u16,
+    pub x: u16,
+    pub y: u16,
+    pub width: NonZeroU16,
+    pub height: NonZeroU16,
+}
+
+/// Solid Fill Display Update
+///
+/// Fills a rectangle with a color. The update is sent as an OpaqueRect drawing order when
+/// supported by the client, or as a bitmap update otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolidFillUpdate {
+    pub x: u16,
+    pub y: u16,
+    pub width: NonZeroU16,
+    pub height: NonZeroU16,
+    pub color: RgbColor,
+}
+
+/// Line Display Update
+///
+/// Draws a one pixel wide line, up to but not including the end point. The update is sent as
+/// a LineTo drawing order when supported by the client, or as a bitmap update otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineUpdate {
+    pub start_x: u16,
+    pub start_y: u16,
+    pub end_x: u16,
+    pub end_y: u16,
+    pub color: RgbColor,
+}
+
 /// Display Updates receiver for an RDP server
 ///
 /// The RDP server will repeatedly call the `next_update` method to receive
 /// display updates which will then be encoded and sent to the client
 ///
+/// Implementations capturing the screen periodically can use a [`FramePacer`](crate::FramePacer)
+/// to capture less often while the screen doesn't change, and a [`FrameUpdater`](crate::FrameUpdater)
+/// to send only the parts of the frames which changed, or just the cursor position when it is the
+/// only change. A [`LatencyBudget`](crate::LatencyBudget) sends the damage caused by the user input
+/// first, and measures the input-to-frame latency.
+///
 /// See [`RdpServerDisplay`] example.
 #[async_trait::async_trait]
 pub trait RdpServerDisplayUpdates {
@@ -291,10 +461,29 @@ pub trait RdpServerDisplay: Send {
     /// Return a display updates receiver
     async fn updates(&mut self) -> Result<Box<dyn RdpServerDisplayUpdates>>;
 
-    /// Request a new size for the display
-    fn request_layout(&mut self, layout: DisplayControlMonitorLayout) {
+    /// Request a new monitor layout for the display
+    ///
+    /// Called when the client sends a monitor layout through the display control channel, once
+    /// validated against the announced capabilities. The display can apply it by resizing (see
+    /// [`MonitorLayout::desktop_size`]) and sending a [`DisplayUpdate::Resize`], or by re-mapping
+    /// its monitors to the ones of the layout.
+    ///
+    /// Each monitor carries its orientation and scale factors, e.g. a tablet client rotated to portrait sends
+    /// its monitor with swapped dimensions and [`MonitorOrientation::Portrait`]. With EGFX, the layout can be
+    /// converted with `gfx_monitor_layout`.
+    ///
+    /// [`MonitorOrientation::Portrait`]: ironrdp_displaycontrol::pdu::MonitorOrientation::Portrait
+    fn request_layout(&mut self, layout: MonitorLayout) {
         debug!(?layout, "Requesting layout")
     }
+
+    /// Visual effects requested by the client with its performance flags
+    ///
+    /// Called once the client is connected, the display can render accordingly, e.g. without the wallpaper or
+    /// the font smoothing on a slow link.
+    fn visual_experience(&mut self, experience: VisualExperience) {
+        debug!(?experience, "Client visual experience")
+    }
 }
 
 #[cfg(test)]
@@ -304,7 +493,15 @@ mod tests {
     use ironrdp_graphics::diff::Rect;
     use ironrdp_graphics::image_processing::PixelFormat;
 
-    use super::{BitmapUpdate, Framebuffer};
+    use super::{BitmapUpdate, Framebuffer, LineUpdate, RgbColor, ScreenCopyUpdate, SolidFillUpdate};
+
+    fn framebuffer(width: u16, height: u16) -> Framebuffer {
+        Framebuffer::new(
+            NonZeroU16::new(width).unwrap(),
+            NonZeroU16::new(height).unwrap(),
+            PixelFormat::BgrX32,
+        )
+    }
 
     #[test]
     fn framebuffer_update() {
@@ -338,4 +535,77 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn framebuffer_copy_overlapping_rect() {
+        let mut fb = framebuffer(4, 4);
+        for (i, byte) in fb.data.iter_mut().enumerate() {
+            *byte = u8::try_from(i / 4).unwrap();
+        }
+
+        let copy = ScreenCopyUpdate {
+            src_x: 0,
+            src_y: 0,
+            x: 1,
+            y: 1,
+            width: NonZeroU16::new(3).unwrap(),
+            height: NonZeroU16::new(3).unwrap(),
+        };
+        assert!(fb.copy_rect(&copy));
+
+        for y in 1..4 {
+            for x in 1..4 {
+                assert_eq!(usize::from(fb.data[(y * 4 + x) * 4]), (y - 1) * 4 + x - 1);
+            }
+        }
+
+        assert!(!fb.copy_rect(&ScreenCopyUpdate { x: 2, ..copy }));
+    }
+
+    #[test]
+    fn framebuffer_fill_rect() {
+        let mut fb = framebuffer(4, 4);
+        let fill = SolidFillUpdate {
+            x: 1,
+            y: 2,
+            width: NonZeroU16::new(2).unwrap(),
+            height: NonZeroU16::new(2).unwrap(),
+            color: RgbColor {
+                red: 1,
+                green: 2,
+                blue: 3,
+            },
+        };
+        assert!(fb.fill_rect(&fill));
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected: &[u8] = if (1..3).contains(&x) && (2..4).contains(&y) {
+                    &[3, 2, 1, 0xFF]
+                } else {
+                    &[0; 4]
+                };
+                assert_eq!(&fb.data[(y * 4 + x) * 4..][..4], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn framebuffer_line_excludes_end_point() {
+        let mut fb = framebuffer(4, 4);
+        fb.draw_line(&LineUpdate {
+            start_x: 0,
+            start_y: 0,
+            end_x: 3,
+            end_y: 3,
+            color: RgbColor {
+                red: 0xFF,
+                green: 0xFF,
+                blue: 0xFF,
+            },
+        });
+
+        let drawn: Vec<_> = (0..16).filter(|i| fb.data[i * 4] == 0xFF).collect();
+        assert_eq!(drawn, [0, 5, 10]);
+    }
 }
@@ -1,85 +1,112 @@
-use core::num::NonZeroUsize;
-
-use ironrdp_core::{cast_int, cast_length, invalid_field_err, Encode as _, WriteCursor};
+use ironrdp_core::{cast_int, cast_length, invalid_field_err, other_err, Encode, WriteCursor};
 use ironrdp_graphics::image_processing::PixelFormat;
 use ironrdp_graphics::rdp6::{
-    ABgrChannels, ARgbChannels, BgrAChannels, BitmapEncodeError, BitmapStreamEncoder, RgbAChannels,
+    ABgrChannels, ARgbChannels, BgrAChannels, BitmapEncodeError, BitmapStreamEncoder, ColorChannels, ColorLoss,
+    RgbAChannels,
 };
+use ironrdp_graphics::rle;
 use ironrdp_pdu::bitmap::{self, BitmapData, BitmapUpdateData, Compression};
 use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_pdu::orders::{CacheBitmapRev2Order, MemBltOrder, OrdersUpdateData};
 
+use super::bitmap_cache::{bitmap_key, BitmapCache, CacheLookup};
 use crate::BitmapUpdate;
 
+/// Widest rectangle of a bitmap update, larger areas are split into several rectangles
+const MAX_BITMAP_WIDTH: u16 = 1024;
+
+/// Encodes bitmap updates for clients without surface commands
+///
+/// Bitmaps are compressed using the RDP 6.0 planar codec at 32 bpp, and the interleaved RLE codec
+/// at 15, 16 and 24 bpp. Other color depths (8 bpp would require a palette) fall back to 32 bpp.
+/// Planar bitmaps are lossless, unless a color loss is set.
+///
+/// Bitmap data is sent with a width multiple of 4, the last pixel of each row is repeated as needed.
 // PERF: we could also remove the need for this buffer
 #[derive(Clone)]
 pub(crate) struct BitmapEncoder {
     buffer: Vec<u8>,
+    bits_per_pixel: u16,
+    color_loss: ColorLoss,
+    /// Pixels converted to the client color depth, for the interleaved RLE codec
+    pixels: Vec<u8>,
 }
 
 impl BitmapEncoder {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(bits_per_pixel: u16) -> Self {
+        let bits_per_pixel = match bits_per_pixel {
+            15 | 16 | 24 => bits_per_pixel,
+            _ => 32,
+        };
+
         Self {
             buffer: vec![0; usize::from(u16::MAX)],
+            bits_per_pixel,
+            color_loss: ColorLoss::NONE,
+            pixels: Vec::new(),
         }
     }
 
+    /// Encodes planar bitmaps as YCoCg planes with the given color loss
+    #[must_use]
+    pub(crate) fn with_color_loss(mut self, color_loss: ColorLoss) -> Self {
+        self.color_loss = color_loss;
+        self
+    }
+
+    /// The color depth bitmaps are encoded with
+    pub(crate) fn bits_per_pixel(&self) -> u16 {
+        self.bits_per_pixel
+    }
+
     pub(crate) fn encode(&mut self, bitmap: &BitmapUpdate, output: &mut [u8]) -> Result<usize, BitmapEncodeError> {
-        // FIXME: support non-multiple of 4 widths.
-        //
-        // It’s not clear how to achieve that yet, but generally, server uses multiple of 4-widths,
-        // and client has surface capabilities, so this path is unlikely.
-        if bitmap.width.get() % 4 != 0 {
-            return Err(BitmapEncodeError::Encode(invalid_field_err!(
-                "bitmap",
-                "Width must be a multiple of 4"
-            )));
-        }
+        let bits_per_pixel = self.bits_per_pixel;
+        let bytes_per_pixel = bits_per_pixel.div_ceil(8);
 
-        let bytes_per_pixel = u16::from(bitmap.format.bytes_per_pixel());
-        let row_len = bitmap.width.get() * bytes_per_pixel;
-        let chunk_height = u16::MAX / row_len;
+        // Each rectangle must fit in 64 KiB once decompressed.
+        let max_width = MAX_BITMAP_WIDTH.min(bitmap.width.get().next_multiple_of(4));
+        let max_height = (u16::MAX / (max_width * bytes_per_pixel)).max(1);
+        let tiles = || tiles(bitmap.width.get(), bitmap.height.get(), max_width, max_height);
 
         let mut cursor = WriteCursor::new(output);
-        let stride = bitmap.stride.get();
-        let chunks = bitmap.data.chunks(stride * usize::from(chunk_height));
 
-        let total = cast_int!("chunks length lower bound", chunks.size_hint().0).map_err(BitmapEncodeError::Encode)?;
+        let total = cast_int!("number of rectangles", tiles().count()).map_err(BitmapEncodeError::Encode)?;
         BitmapUpdateData::encode_header(total, &mut cursor).map_err(BitmapEncodeError::Encode)?;
 
-        for (i, chunk) in chunks.enumerate() {
-            let height = cast_int!("bitmap height", chunk.len() / stride).map_err(BitmapEncodeError::Encode)?;
-            let i: u16 = cast_int!("chunk idx", i).map_err(BitmapEncodeError::Encode)?;
-            let top = bitmap.y + i * chunk_height;
+        let mut padded = Vec::new();
+        for tile in tiles() {
+            let width = tile.padded_width();
+            let rows = tile_rows(bitmap, tile, &mut padded);
+            let (compression_flags, bitmap_data) = self.compress(bitmap.format, width, tile.height, rows)?;
 
-            let encoder = BitmapStreamEncoder::new(NonZeroUsize::from(bitmap.width).get(), usize::from(height));
-
-            let len = {
-                let pixels = chunk
-                    .chunks(stride)
-                    .map(|row| &row[..usize::from(row_len)])
-                    .rev()
-                    .flat_map(|row| row.chunks(usize::from(bytes_per_pixel)));
-
-                Self::encode_iter(encoder, bitmap.format, pixels, self.buffer.as_mut_slice())?
+            let compressed_data_header = if compression_flags.contains(Compression::BITMAP_COMPRESSION) {
+                Some(bitmap::CompressedDataHeader {
+                    main_body_size: cast_length!("main body size", bitmap_data.len())
+                        .map_err(BitmapEncodeError::Encode)?,
+                    scan_width: width,
+                    uncompressed_size: tile.height * width * bytes_per_pixel,
+                })
+            } else {
+                None
             };
 
+            let left = bitmap.x + tile.x;
+            let top = bitmap.y + tile.y;
+
+            // The padding columns are clipped by the client.
             let data = BitmapData {
                 rectangle: InclusiveRectangle {
-                    left: bitmap.x,
+                    left,
                     top,
-                    right: bitmap.x + bitmap.width.get() - 1,
-                    bottom: top + height - 1,
+                    right: left + tile.width - 1,
+                    bottom: top + tile.height - 1,
                 },
-                width: u16::from(bitmap.width),
-                height,
-                bits_per_pixel: u16::from(bitmap.format.bytes_per_pixel()) * 8,
-                compression_flags: Compression::BITMAP_COMPRESSION,
-                compressed_data_header: Some(bitmap::CompressedDataHeader {
-                    main_body_size: cast_length!("main body size", len).map_err(BitmapEncodeError::Encode)?,
-                    scan_width: u16::from(bitmap.width),
-                    uncompressed_size: height * row_len,
-                }),
-                bitmap_data: &self.buffer[..len],
+                width,
+                height: tile.height,
+                bits_per_pixel,
+                compression_flags,
+                compressed_data_header,
+                bitmap_data,
             };
 
             data.encode(&mut cursor).map_err(BitmapEncodeError::Encode)?;
@@ -88,6 +115,173 @@ impl BitmapEncoder {
         Ok(cursor.pos())
     }
 
+    /// Encodes the bitmap as tiles drawn from the bitmap cache, as the payload of an orders update
+    ///
+    /// Tiles missing from the cache are sent with Cache Bitmap (Revision 2) orders first.
+    pub(crate) fn encode_orders(
+        &mut self,
+        bitmap: &BitmapUpdate,
+        cache: &mut BitmapCache,
+    ) -> Result<Vec<u8>, BitmapEncodeError> {
+        let bits_per_pixel = u8::try_from(self.bits_per_pixel)
+            .map_err(|_| BitmapEncodeError::Encode(invalid_field_err!("bitsPerPixel", "unsupported color depth")))?;
+        let side = cache.tile_side();
+
+        let mut output = vec![0; 2];
+        let mut order_count: usize = 0;
+        let mut padded = Vec::new();
+
+        for tile in tiles(bitmap.width.get(), bitmap.height.get(), side, side) {
+            // Tile sides are multiples of 4, only the last column may be padded.
+            let width = tile.padded_width();
+            let rows = tile_rows(bitmap, tile, &mut padded);
+
+            let key = bitmap_key(self.bits_per_pixel, width, tile.height, rows.clone());
+            let pixels = usize::from(width) * usize::from(tile.height);
+
+            let (cache_id, cache_index) = match cache.lookup(pixels, key) {
+                Some(CacheLookup::Hit { cache_id, index }) => (cache_id, index),
+                Some(CacheLookup::Miss {
+                    cache_id,
+                    index,
+                    persistent,
+                }) => {
+                    let (compression_flags, bitmap_data) = self.compress(bitmap.format, width, tile.height, rows)?;
+                    let order = CacheBitmapRev2Order {
+                        cache_id,
+                        cache_index: index,
+                        key: persistent.then_some(key),
+                        bits_per_pixel,
+                        width,
+                        height: tile.height,
+                        compressed: compression_flags.contains(Compression::BITMAP_COMPRESSION),
+                        compressed_data_header: None,
+                        bitmap_data,
+                    };
+                    Self::append_order(&order, &mut output)?;
+                    order_count += 1;
+
+                    (cache_id, index)
+                }
+                None => {
+                    return Err(BitmapEncodeError::Encode(invalid_field_err!(
+                        "bitmap",
+                        "tile doesn't fit in the bitmap cache"
+                    )))
+                }
+            };
+
+            let order = MemBltOrder {
+                cache_id,
+                color_table_index: 0,
+                left: cast_int!("left", bitmap.x + tile.x).map_err(BitmapEncodeError::Encode)?,
+                top: cast_int!("top", bitmap.y + tile.y).map_err(BitmapEncodeError::Encode)?,
+                width: cast_int!("width", tile.width).map_err(BitmapEncodeError::Encode)?,
+                height: cast_int!("height", tile.height).map_err(BitmapEncodeError::Encode)?,
+                rop: MemBltOrder::ROP_SRCCOPY,
+                src_x: 0,
+                src_y: 0,
+                cache_index,
+            };
+            Self::append_order(&order, &mut output)?;
+            order_count += 1;
+        }
+
+        let order_count = cast_int!("number of orders", order_count).map_err(BitmapEncodeError::Encode)?;
+        OrdersUpdateData::encode_header(order_count, &mut WriteCursor::new(&mut output))
+            .map_err(BitmapEncodeError::Encode)?;
+
+        Ok(output)
+    }
+
+    fn append_order(order: &impl Encode, output: &mut Vec<u8>) -> Result<(), BitmapEncodeError> {
+        let start = output.len();
+        output.resize(start + order.size(), 0);
+
+        order
+            .encode(&mut WriteCursor::new(&mut output[start..]))
+            .map_err(BitmapEncodeError::Encode)
+    }
+
+    /// Compresses bottom-up rows of 32-bit pixels, at the client color depth
+    ///
+    /// Falls back to uncompressed data when interleaved RLE doesn't reduce the size.
+    fn compress<'a, R>(
+        &mut self,
+        format: PixelFormat,
+        width: u16,
+        height: u16,
+        rows: R,
+    ) -> Result<(Compression, &[u8]), BitmapEncodeError>
+    where
+        R: Iterator<Item = &'a [u8]> + Clone,
+    {
+        if self.bits_per_pixel == 32 {
+            let encoder =
+                BitmapStreamEncoder::new(usize::from(width), usize::from(height)).with_color_loss(self.color_loss);
+            let pixels = rows.flat_map(|row| row.chunks(usize::from(format.bytes_per_pixel())));
+            let len = Self::encode_iter(encoder, format, pixels, self.buffer.as_mut_slice())?;
+
+            return Ok((Compression::BITMAP_COMPRESSION, &self.buffer[..len]));
+        }
+
+        self.pixels.clear();
+
+        for row in rows {
+            match format {
+                PixelFormat::ARgb32 | PixelFormat::XRgb32 => self.convert_row::<ARgbChannels>(row),
+                PixelFormat::RgbA32 | PixelFormat::RgbX32 => self.convert_row::<RgbAChannels>(row),
+                PixelFormat::ABgr32 | PixelFormat::XBgr32 => self.convert_row::<ABgrChannels>(row),
+                PixelFormat::BgrA32 | PixelFormat::BgrX32 => self.convert_row::<BgrAChannels>(row),
+            }
+        }
+
+        rle::compress(
+            &self.pixels,
+            &mut self.buffer,
+            usize::from(width),
+            usize::from(height),
+            usize::from(self.bits_per_pixel),
+        )
+        .map_err(|e| BitmapEncodeError::Encode(other_err!("interleaved RLE", source: e)))?;
+
+        // Noisy images may not compress at all.
+        if self.buffer.len() < self.pixels.len() {
+            return Ok((Compression::BITMAP_COMPRESSION, self.buffer.as_slice()));
+        }
+
+        // Uncompressed scanlines are padded to a multiple of 4 bytes ([MS-RDPBCGR] 2.2.9.1.1.3.1.2.2).
+        let row_len = usize::from(width) * usize::from(self.bits_per_pixel.div_ceil(8));
+        let padded_row_len = row_len.next_multiple_of(4);
+
+        self.buffer.clear();
+        for row in self.pixels.chunks_exact(row_len) {
+            self.buffer.extend_from_slice(row);
+            self.buffer.resize(self.buffer.len() + padded_row_len - row_len, 0);
+        }
+
+        Ok((Compression::empty(), self.buffer.as_slice()))
+    }
+
+    /// Appends a row of 32-bit pixels converted to the RGB layout of the interleaved RLE codec
+    fn convert_row<C: ColorChannels>(&mut self, row: &[u8]) {
+        for pixel in row.chunks_exact(C::STRIDE) {
+            let (r, g, b) = (u16::from(pixel[C::R]), u16::from(pixel[C::G]), u16::from(pixel[C::B]));
+
+            match self.bits_per_pixel {
+                24 => self.pixels.extend_from_slice(&[pixel[C::B], pixel[C::G], pixel[C::R]]),
+                16 => {
+                    let rgb565 = ((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3);
+                    self.pixels.extend_from_slice(&rgb565.to_le_bytes());
+                }
+                _ => {
+                    let rgb555 = ((r >> 3) << 10) | ((g >> 3) << 5) | (b >> 3);
+                    self.pixels.extend_from_slice(&rgb555.to_le_bytes());
+                }
+            }
+        }
+    }
+
     fn encode_iter<'a, P>(
         mut encoder: BitmapStreamEncoder,
         format: PixelFormat,
@@ -115,3 +309,208 @@ impl BitmapEncoder {
         Ok(written)
     }
 }
+
+/// An area of a bitmap update, relative to its top-left corner
+#[derive(Debug, Clone, Copy)]
+struct Tile {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+impl Tile {
+    fn padded_width(&self) -> u16 {
+        self.width.next_multiple_of(4)
+    }
+}
+
+/// Splits an area into tiles of at most `max_width` x `max_height` pixels, from left to right and top to bottom
+fn tiles(width: u16, height: u16, max_width: u16, max_height: u16) -> impl Iterator<Item = Tile> {
+    (0..height).step_by(usize::from(max_height)).flat_map(move |y| {
+        (0..width).step_by(usize::from(max_width)).map(move |x| Tile {
+            x,
+            y,
+            width: max_width.min(width - x),
+            height: max_height.min(height - y),
+        })
+    })
+}
+
+/// Returns the rows of a tile bottom-up, padded to [`Tile::padded_width`] pixels
+///
+/// Padded rows are copied to `padded`, other rows are borrowed from the bitmap.
+fn tile_rows<'a>(
+    bitmap: &'a BitmapUpdate,
+    tile: Tile,
+    padded: &'a mut Vec<u8>,
+) -> impl Iterator<Item = &'a [u8]> + Clone {
+    let bytes_per_pixel = usize::from(bitmap.format.bytes_per_pixel());
+    let stride = bitmap.stride.get();
+    let start = usize::from(tile.y) * stride + usize::from(tile.x) * bytes_per_pixel;
+    let row_len = usize::from(tile.width) * bytes_per_pixel;
+    let padded_row_len = usize::from(tile.padded_width()) * bytes_per_pixel;
+
+    let (data, start, stride): (&[u8], usize, usize) = if row_len == padded_row_len {
+        (&bitmap.data, start, stride)
+    } else {
+        padded.clear();
+
+        for row in 0..usize::from(tile.height) {
+            let row = &bitmap.data[start + row * stride..][..row_len];
+            padded.extend_from_slice(row);
+
+            let last_pixel = &row[row_len - bytes_per_pixel..];
+            for _ in tile.width..tile.padded_width() {
+                padded.extend_from_slice(last_pixel);
+            }
+        }
+
+        (padded.as_slice(), 0, padded_row_len)
+    };
+
+    (0..usize::from(tile.height))
+        .rev()
+        .map(move |row| &data[start + row * stride..][..padded_row_len])
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::{NonZeroU16, NonZeroUsize};
+
+    use bytes::Bytes;
+    use ironrdp_core::decode;
+
+    use super::*;
+
+    #[test]
+    fn tiles_cover_the_area() {
+        let tiles: Vec<_> = tiles(5, 3, 4, 2).map(|t| (t.x, t.y, t.width, t.height)).collect();
+
+        assert_eq!(tiles, [(0, 0, 4, 2), (4, 0, 1, 2), (0, 2, 4, 1), (4, 2, 1, 1)]);
+    }
+
+    #[test]
+    fn padded_tile_rows() {
+        let bitmap = BitmapUpdate {
+            x: 0,
+            y: 0,
+            width: NonZeroU16::new(3).unwrap(),
+            height: NonZeroU16::new(2).unwrap(),
+            format: PixelFormat::BgrX32,
+            data: Bytes::from_iter(0..32),
+            stride: NonZeroUsize::new(16).unwrap(),
+        };
+        let tile = Tile {
+            x: 1,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+
+        let mut padded = Vec::new();
+        let rows: Vec<_> = tile_rows(&bitmap, tile, &mut padded).collect();
+
+        assert_eq!(
+            rows,
+            [
+                &[20, 21, 22, 23, 24, 25, 26, 27, 24, 25, 26, 27, 24, 25, 26, 27],
+                &[4, 5, 6, 7, 8, 9, 10, 11, 8, 9, 10, 11, 8, 9, 10, 11]
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_odd_width() {
+        let bitmap = BitmapUpdate {
+            x: 10,
+            y: 20,
+            width: NonZeroU16::new(1030).unwrap(),
+            height: NonZeroU16::new(3).unwrap(),
+            format: PixelFormat::BgrX32,
+            data: Bytes::from(vec![0x80; 1030 * 3 * 4]),
+            stride: NonZeroUsize::new(1030 * 4).unwrap(),
+        };
+
+        let mut output = vec![0; 1 << 16];
+        let len = BitmapEncoder::new(16).encode(&bitmap, &mut output).unwrap();
+        let update: BitmapUpdateData<'_> = decode(&output[..len]).unwrap();
+
+        let rectangles: Vec<_> = update
+            .rectangles
+            .iter()
+            .map(|data| (data.rectangle.clone(), data.width, data.height))
+            .collect();
+        assert_eq!(
+            rectangles,
+            [
+                (
+                    InclusiveRectangle {
+                        left: 10,
+                        top: 20,
+                        right: 1033,
+                        bottom: 22
+                    },
+                    1024,
+                    3
+                ),
+                (
+                    InclusiveRectangle {
+                        left: 1034,
+                        top: 20,
+                        right: 1039,
+                        bottom: 22
+                    },
+                    8,
+                    3
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn uncompressed_rows_are_padded() {
+        let rows: [&[u8]; 2] = [
+            &[1, 2, 3, 0, 4, 5, 6, 0, 7, 8, 9, 0],
+            &[10, 11, 12, 0, 13, 14, 15, 0, 16, 17, 18, 0],
+        ];
+
+        let mut encoder = BitmapEncoder::new(24);
+        let (compression, data) = encoder
+            .compress(PixelFormat::BgrX32, 3, 2, rows.iter().copied())
+            .unwrap();
+
+        assert_eq!(compression, Compression::empty());
+        assert_eq!(
+            data,
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 0, 0, 10, 11, 12, 13, 14, 15, 16, 17, 18, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn encode_planar_with_color_loss() {
+        let bitmap = BitmapUpdate {
+            x: 0,
+            y: 0,
+            width: NonZeroU16::new(5).unwrap(),
+            height: NonZeroU16::new(3).unwrap(),
+            format: PixelFormat::BgrX32,
+            data: Bytes::from_iter((0..5 * 3 * 4).map(|idx: u8| idx * 4)),
+            stride: NonZeroUsize::new(5 * 4).unwrap(),
+        };
+        let color_loss = ColorLoss {
+            level: 2,
+            chroma_subsampling: true,
+        };
+
+        let mut output = vec![0; 1 << 16];
+        let len = BitmapEncoder::new(32)
+            .with_color_loss(color_loss)
+            .encode(&bitmap, &mut output)
+            .unwrap();
+        let update: BitmapUpdateData<'_> = decode(&output[..len]).unwrap();
+
+        // RLE + no alpha, CLL = 2, chroma subsampling
+        assert_eq!(update.rectangles[0].bitmap_data[0], 0x10 | 0x20 | 0x08 | 0x02);
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ironrdp_pdu::rdp::capability_sets::{BitmapCacheRev2, CellInfo};
+
+/// Number of pixels fitting in a cell of the first bitmap cache, each following cache has 4 times larger cells
+///
+/// See [MS-RDPBCGR] 2.2.7.1.4.2 "Revision 2 (TS_BITMAPCACHE_CAPABILITYSET_REV2)".
+const FIRST_CELL_PIXELS: usize = 256;
+
+/// Largest tile side, matching the cells of the third cache
+const MAX_TILE_SIDE: u16 = 64;
+
+/// The last index is reserved for the waiting list
+const MAX_ENTRIES: u16 = 0x7FFF;
+
+/// Result of a [`BitmapCache::lookup`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CacheLookup {
+    /// The bitmap is already stored by the client
+    Hit { cache_id: u8, index: u16 },
+    /// The bitmap must be sent to the client, and stored at the given place
+    Miss { cache_id: u8, index: u16, persistent: bool },
+}
+
+/// Mirror of the client bitmap cache (revision 2)
+///
+/// Each cell cache holds bitmaps up to a size, identified by a 64-bit key computed from their content.
+/// When a cell cache is full, the least recently used entry is evicted.
+#[derive(Debug, Clone)]
+pub(crate) struct BitmapCache {
+    cells: Vec<CellCache>,
+    tile_side: u16,
+    tick: u64,
+}
+
+impl BitmapCache {
+    /// Returns `None` when the client caches can't hold any tile
+    pub(crate) fn new(caps: &BitmapCacheRev2) -> Option<Self> {
+        let count = usize::from(caps.num_cell_caches).min(caps.cache_cell_info.len());
+
+        Self::with_cells(&caps.cache_cell_info[..count])
+    }
+
+    fn with_cells(cells: &[CellInfo]) -> Option<Self> {
+        let cells: Vec<_> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, info)| CellCache::new(FIRST_CELL_PIXELS << (2 * i), info))
+            .collect();
+
+        // The largest tile fitting in a cell which can hold entries.
+        let tile_side = [MAX_TILE_SIDE, MAX_TILE_SIDE / 2, MAX_TILE_SIDE / 4]
+            .into_iter()
+            .find(|side| {
+                let pixels = usize::from(*side) * usize::from(*side);
+                cells.iter().any(|cell| cell.capacity > 0 && cell.max_pixels >= pixels)
+            })?;
+
+        Some(Self {
+            cells,
+            tile_side,
+            tick: 0,
+        })
+    }
+
+    /// Side of the square tiles bitmaps are split into
+    pub(crate) fn tile_side(&self) -> u16 {
+        self.tile_side
+    }
+
+    /// Registers the keys reported by the client for a persistent cache, in cache index order
+    pub(crate) fn load_persistent_keys(&mut self, cache_id: usize, keys: &[u64]) {
+        let Some(cell) = self.cells.get_mut(cache_id).filter(|cell| cell.persistent) else {
+            return;
+        };
+
+        for key in keys.iter().copied() {
+            let Some(index) = cell.free_index() else {
+                break;
+            };
+
+            self.tick += 1;
+            cell.store(index, key, self.tick);
+        }
+    }
+
+    /// Looks up a bitmap of `pixels` pixels, and reserves an entry for it when missing
+    ///
+    /// Returns `None` when no cache can hold the bitmap.
+    pub(crate) fn lookup(&mut self, pixels: usize, key: u64) -> Option<CacheLookup> {
+        let (cache_id, cell) = self
+            .cells
+            .iter_mut()
+            .enumerate()
+            .find(|(_, cell)| cell.capacity > 0 && cell.max_pixels >= pixels)?;
+        let cache_id = u8::try_from(cache_id).expect("at most 5 cell caches");
+
+        self.tick += 1;
+
+        if let Some(index) = cell.keys.get(&key).copied() {
+            cell.store(index, key, self.tick);
+
+            return Some(CacheLookup::Hit { cache_id, index });
+        }
+
+        let index = match cell.free_index() {
+            Some(index) => index,
+            None => cell.evict(),
+        };
+        cell.store(index, key, self.tick);
+
+        Some(CacheLookup::Miss {
+            cache_id,
+            index,
+            persistent: cell.persistent,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CellCache {
+    max_pixels: usize,
+    capacity: u16,
+    persistent: bool,
+    /// Key and last use of each entry
+    entries: Vec<(u64, u64)>,
+    keys: HashMap<u64, u16>,
+    /// Entries by last use
+    lru: BTreeMap<u64, u16>,
+}
+
+impl CellCache {
+    fn new(max_pixels: usize, info: &CellInfo) -> Self {
+        Self {
+            max_pixels,
+            capacity: u16::try_from(info.num_entries).unwrap_or(MAX_ENTRIES).min(MAX_ENTRIES),
+            persistent: info.is_cache_persistent,
+            entries: Vec::new(),
+            keys: HashMap::new(),
+            lru: BTreeMap::new(),
+        }
+    }
+
+    fn free_index(&self) -> Option<u16> {
+        u16::try_from(self.entries.len())
+            .ok()
+            .filter(|index| *index < self.capacity)
+    }
+
+    /// Frees the least recently used entry
+    fn evict(&mut self) -> u16 {
+        let (_, index) = self.lru.pop_first().expect("evicting from a full cache");
+        let (key, _) = self.entries[usize::from(index)];
+        self.keys.remove(&key);
+
+        index
+    }
+
+    fn store(&mut self, index: u16, key: u64, tick: u64) {
+        let entry = (key, tick);
+
+        match self.entries.get_mut(usize::from(index)) {
+            Some(previous) => {
+                self.lru.remove(&previous.1);
+                *previous = entry;
+            }
+            None => self.entries.push(entry),
+        }
+
+        self.keys.insert(key, index);
+        self.lru.insert(tick, index);
+    }
+}
+
+/// Computes the key identifying a bitmap in the cache
+///
+/// The key must be stable across sessions, since the client keeps persistent caches on disk.
+pub(crate) fn bitmap_key<'a, R>(bits_per_pixel: u16, width: u16, height: u16, rows: R) -> u64
+where
+    R: Iterator<Item = &'a [u8]>,
+{
+    // 64-bit FNV-1a
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    let fnv = |hash: u64, bytes: &[u8]| {
+        bytes
+            .iter()
+            .fold(hash, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(PRIME))
+    };
+
+    let header = [bits_per_pixel, width, height].map(u16::to_le_bytes);
+
+    rows.fold(fnv(OFFSET_BASIS, header.as_flattened()), fnv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(entries: [u32; 3], persistent: bool) -> BitmapCache {
+        let cells = entries.map(|num_entries| CellInfo {
+            num_entries,
+            is_cache_persistent: persistent,
+        });
+
+        BitmapCache::with_cells(&cells).unwrap()
+    }
+
+    #[test]
+    fn lookup_hits_after_miss() {
+        let mut cache = cache([10, 10, 10], false);
+
+        assert_eq!(
+            cache.lookup(64 * 64, 42),
+            Some(CacheLookup::Miss {
+                cache_id: 2,
+                index: 0,
+                persistent: false
+            })
+        );
+        assert_eq!(
+            cache.lookup(64 * 64, 42),
+            Some(CacheLookup::Hit { cache_id: 2, index: 0 })
+        );
+        assert_eq!(
+            cache.lookup(16 * 16, 42),
+            Some(CacheLookup::Miss {
+                cache_id: 0,
+                index: 0,
+                persistent: false
+            })
+        );
+        assert_eq!(cache.lookup(128 * 128, 42), None);
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let mut cache = cache([0, 0, 2], false);
+
+        cache.lookup(4096, 1);
+        cache.lookup(4096, 2);
+        cache.lookup(4096, 1);
+
+        assert_eq!(
+            cache.lookup(4096, 3),
+            Some(CacheLookup::Miss {
+                cache_id: 2,
+                index: 1,
+                persistent: false
+            })
+        );
+        assert_eq!(cache.lookup(4096, 1), Some(CacheLookup::Hit { cache_id: 2, index: 0 }));
+        assert!(matches!(
+            cache.lookup(4096, 2),
+            Some(CacheLookup::Miss { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn persistent_keys_are_hits() {
+        let mut cache = cache([0, 0, 2], true);
+
+        cache.load_persistent_keys(2, &[7, 8, 9]);
+
+        assert_eq!(cache.lookup(4096, 8), Some(CacheLookup::Hit { cache_id: 2, index: 1 }));
+        assert_eq!(
+            cache.lookup(4096, 9),
+            Some(CacheLookup::Miss {
+                cache_id: 2,
+                index: 0,
+                persistent: true
+            })
+        );
+    }
+
+    #[test]
+    fn tile_side_fits_available_cells() {
+        assert_eq!(cache([10, 10, 10], false).tile_side(), 64);
+        assert_eq!(cache([10, 10, 0], false).tile_side(), 32);
+        assert!(BitmapCache::with_cells(&[]).is_none());
+    }
+
+    #[test]
+    fn bitmap_key_depends_on_dimensions() {
+        let row = [0u8; 16];
+
+        assert_ne!(
+            bitmap_key(32, 4, 1, core::iter::once(row.as_slice())),
+            bitmap_key(32, 2, 2, [&row[..8], &row[8..]].into_iter())
+        );
+    }
+}
@@ -24,6 +24,7 @@ pub(crate) struct UpdateFragmenter {
     #[doc(hidden)] // not part of the public API, used by benchmarks
     pub data: Vec<u8>,
     position: usize,
+    max_update_size: usize,
 }
 
 impl fmt::Debug for UpdateFragmenter {
@@ -41,11 +42,20 @@ impl UpdateFragmenter {
             index: 0,
             data,
             position: 0,
+            max_update_size: MAX_FASTPATH_UPDATE_SIZE,
         }
     }
 
+    /// Limits the size of the fast-path PDUs, headers included, to `segment_size` bytes
+    pub(crate) fn with_segment_size(mut self, segment_size: usize) -> Self {
+        self.max_update_size = segment_size
+            .saturating_sub(FASTPATH_HEADER_SIZE)
+            .clamp(1, MAX_FASTPATH_UPDATE_SIZE);
+        self
+    }
+
     pub(crate) fn size_hint(&self) -> usize {
-        FASTPATH_HEADER_SIZE + cmp::min(self.data.len(), MAX_FASTPATH_UPDATE_SIZE)
+        FASTPATH_HEADER_SIZE + cmp::min(self.data.len(), self.max_update_size)
     }
 
     pub(crate) fn next(&mut self, dst: &mut [u8]) -> Option<usize> {
@@ -59,7 +69,7 @@ impl UpdateFragmenter {
         match self.data.len() - self.position {
             0 => None,
 
-            1..=MAX_FASTPATH_UPDATE_SIZE => {
+            remaining if remaining <= self.max_update_size => {
                 let frag = if self.index > 0 {
                     Fragmentation::Last
                 } else {
@@ -79,10 +89,10 @@ impl UpdateFragmenter {
 
                 self.encode_fastpath(
                     frag,
-                    &self.data[self.position..MAX_FASTPATH_UPDATE_SIZE + self.position],
+                    &self.data[self.position..self.max_update_size + self.position],
                     dst,
                 )
-                .map(|written| (MAX_FASTPATH_UPDATE_SIZE, written))
+                .map(|written| (self.max_update_size, written))
             }
         }
     }
@@ -190,4 +200,23 @@ mod tests {
 
         assert!(fragmenter.next(&mut buffer).is_none());
     }
+
+    #[test]
+    fn test_segment_size() {
+        let data = vec![0u8; 3000];
+        let mut fragmenter = UpdateFragmenter::new(UpdateCode::Bitmap, data).with_segment_size(1400);
+        let mut buffer = vec![0u8; fragmenter.size_hint()];
+        assert_eq!(buffer.len(), 1400);
+
+        let mut sizes = Vec::new();
+        while let Some(written) = fragmenter.next(&mut buffer) {
+            assert!(written <= 1400);
+
+            let mut cursor = ReadCursor::new(&buffer);
+            let _header: FastPathHeader = decode_cursor(&mut cursor).unwrap();
+            let update: FastPathUpdatePdu<'_> = decode_cursor(&mut cursor).unwrap();
+            sizes.push(update.data.len());
+        }
+        assert_eq!(sizes, [1394, 1394, 212]);
+    }
 }
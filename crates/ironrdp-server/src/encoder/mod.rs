@@ -1,29 +1,35 @@
 use core::fmt;
-use core::num::NonZeroU16;
+use core::num::{NonZeroU16, NonZeroUsize};
 
 use anyhow::{anyhow, Context as _, Result};
+use bytes::Bytes;
 use ironrdp_acceptor::DesktopSize;
-use ironrdp_graphics::diff::{find_different_rects_sub, Rect};
+use ironrdp_graphics::diff::Rect;
 use ironrdp_pdu::encode_vec;
 use ironrdp_pdu::fast_path::UpdateCode;
-use ironrdp_pdu::geometry::ExclusiveRectangle;
+use ironrdp_pdu::geometry::{ExclusiveRectangle, InclusiveRectangle, Rectangle as _};
 use ironrdp_pdu::pointer::{ColorPointerAttribute, Point16, PointerAttribute, PointerPositionAttribute};
-use ironrdp_pdu::rdp::capability_sets::{CmdFlags, EntropyBits};
+use ironrdp_pdu::rdp::capability_sets::{BitmapCodecs, CmdFlags, CodecProperty, EntropyBits, RemoteFxContainer};
 use ironrdp_pdu::surface_commands::{ExtendedBitmapDataPdu, SurfaceBitsPdu, SurfaceCommand};
 use tracing::{debug, warn};
 
 use self::bitmap::BitmapEncoder;
+use self::bitmap_cache::BitmapCache;
+use self::orders::{OrderEncoder, OrderSupport};
 use self::rfx::RfxEncoder;
 use super::BitmapUpdate;
+use crate::frame_diff::framebuffer_dirty_rects;
 use crate::macros::time_warn;
-use crate::{ColorPointer, DisplayUpdate, Framebuffer, RGBAPointer};
+use crate::{ColorPointer, DisplayUpdate, Framebuffer, LineUpdate, RGBAPointer, ScreenCopyUpdate, SolidFillUpdate};
 
 mod bitmap;
+pub(crate) mod bitmap_cache;
 mod fast_path;
+pub(crate) mod orders;
 pub(crate) mod rfx;
 
 pub(crate) use fast_path::*;
-use ironrdp_graphics::rdp6::BitmapEncodeError;
+use ironrdp_graphics::rdp6::{BitmapEncodeError, ColorLoss};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
@@ -44,6 +50,10 @@ impl CodecId {
 #[cfg_attr(feature = "__bench", visibility::make(pub))]
 #[derive(Debug)]
 pub(crate) struct UpdateEncoderCodecs {
+    bitmap_bits_per_pixel: u16,
+    bitmap_color_loss: ColorLoss,
+    bitmap_cache: Option<BitmapCache>,
+    order_support: OrderSupport,
     remotefx: Option<(EntropyBits, u8)>,
     #[cfg(feature = "qoi")]
     qoi: Option<u8>,
@@ -55,6 +65,10 @@ impl UpdateEncoderCodecs {
     #[cfg_attr(feature = "__bench", visibility::make(pub))]
     pub(crate) fn new() -> Self {
         Self {
+            bitmap_bits_per_pixel: 32,
+            bitmap_color_loss: ColorLoss::NONE,
+            bitmap_cache: None,
+            order_support: OrderSupport::default(),
             remotefx: None,
             #[cfg(feature = "qoi")]
             qoi: None,
@@ -63,6 +77,88 @@ impl UpdateEncoderCodecs {
         }
     }
 
+    /// Builds the encoder configuration from the codecs negotiated with the client
+    ///
+    /// See [`BitmapCodecs::negotiate`].
+    pub(crate) fn from_negotiated(codecs: &BitmapCodecs) -> Self {
+        let mut this = Self::new();
+
+        for codec in &codecs.0 {
+            match &codec.property {
+                // FIXME: The encoder operates in image mode only.
+                //
+                // See [MS-RDPRFX] 3.1.1.1 "State Machine" for
+                // implementation of the video mode. which allows to
+                // skip sending Header for each image.
+                //
+                // We should distinguish parameters for both modes,
+                // and somehow choose the "best", instead of picking
+                // the last parsed here.
+                CodecProperty::RemoteFx(RemoteFxContainer::ClientContainer(c))
+                | CodecProperty::ImageRemoteFx(RemoteFxContainer::ClientContainer(c)) => {
+                    for caps in &c.caps_data.0 .0 {
+                        this.set_remotefx(Some((caps.entropy_bits, codec.id)));
+                    }
+                }
+                #[cfg(feature = "qoi")]
+                CodecProperty::Qoi => this.set_qoi(Some(codec.id)),
+                #[cfg(feature = "qoiz")]
+                CodecProperty::QoiZ => this.set_qoiz(Some(codec.id)),
+                _ => (),
+            }
+        }
+
+        this
+    }
+
+    /// Whether a codec is available for surface commands
+    ///
+    /// Without one, surface commands carry uncompressed bitmaps.
+    pub(crate) fn has_surface_codec(&self) -> bool {
+        #[cfg(feature = "qoi")]
+        if self.qoi.is_some() {
+            return true;
+        }
+        #[cfg(feature = "qoiz")]
+        if self.qoiz.is_some() {
+            return true;
+        }
+
+        self.remotefx.is_some()
+    }
+
+    /// Sets the color depth of bitmap updates, used when the client doesn't support surface commands
+    ///
+    /// Bitmaps are compressed with interleaved RLE at 15, 16 and 24 bpp, and with the RDP 6.0 planar
+    /// codec at 32 bpp, which is also used for other color depths.
+    #[cfg_attr(feature = "__bench", visibility::make(pub))]
+    pub(crate) fn set_bitmap_bits_per_pixel(&mut self, bits_per_pixel: u16) {
+        self.bitmap_bits_per_pixel = bits_per_pixel
+    }
+
+    /// Sets the color loss of the bitmap updates compressed with the RDP 6.0 planar codec
+    ///
+    /// Bitmaps are lossless by default, a color loss level encodes them as YCoCg planes with fewer chroma bits.
+    pub(crate) fn set_bitmap_color_loss(&mut self, color_loss: ColorLoss) {
+        self.bitmap_color_loss = color_loss
+    }
+
+    /// Sets the bitmap cache mirroring the client one, used along bitmap updates
+    ///
+    /// Bitmaps are then split into tiles, sent once with Cache Bitmap (Revision 2) orders and drawn
+    /// with MemBlt orders. Caching is not available at 15 bpp.
+    pub(crate) fn set_bitmap_cache(&mut self, cache: Option<BitmapCache>) {
+        self.bitmap_cache = cache
+    }
+
+    /// Sets the primary drawing orders supported by the client
+    ///
+    /// Screen copy, solid fill and line updates are sent as bitmap updates when the matching
+    /// order is not supported.
+    pub(crate) fn set_order_support(&mut self, support: OrderSupport) {
+        self.order_support = support
+    }
+
     #[cfg_attr(feature = "__bench", visibility::make(pub))]
     pub(crate) fn set_remotefx(&mut self, remotefx: Option<(EntropyBits, u8)>) {
         self.remotefx = remotefx
@@ -92,12 +188,14 @@ pub(crate) struct UpdateEncoder {
     desktop_size: DesktopSize,
     framebuffer: Option<Framebuffer>,
     bitmap_updater: Option<BitmapUpdater>,
+    orders: OrderEncoder,
 }
 
 impl fmt::Debug for UpdateEncoder {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("UpdateEncoder")
             .field("bitmap_update", &self.bitmap_updater)
+            .field("orders", &self.orders)
             .finish()
     }
 }
@@ -105,6 +203,8 @@ impl fmt::Debug for UpdateEncoder {
 impl UpdateEncoder {
     #[cfg_attr(feature = "__bench", visibility::make(pub))]
     pub(crate) fn new(desktop_size: DesktopSize, surface_flags: CmdFlags, codecs: UpdateEncoderCodecs) -> Result<Self> {
+        let orders = OrderEncoder::new(codecs.order_support, codecs.bitmap_bits_per_pixel);
+
         let bitmap_updater = if surface_flags.contains(CmdFlags::SET_SURFACE_BITS) {
             let mut bitmap = BitmapUpdater::None(NoneHandler);
 
@@ -123,13 +223,18 @@ impl UpdateEncoder {
 
             bitmap
         } else {
-            BitmapUpdater::Bitmap(BitmapHandler::new())
+            BitmapUpdater::Bitmap(BitmapHandler::new(
+                codecs.bitmap_bits_per_pixel,
+                codecs.bitmap_color_loss,
+                codecs.bitmap_cache,
+            ))
         };
 
         Ok(Self {
             desktop_size,
             framebuffer: None,
             bitmap_updater: Some(bitmap_updater),
+            orders,
         })
     }
 
@@ -149,6 +254,68 @@ impl UpdateEncoder {
             .set_desktop_size(size);
     }
 
+    /// Returns the area covering the whole desktop
+    pub(crate) fn desktop_area(&self) -> InclusiveRectangle {
+        InclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: self.desktop_size.width.saturating_sub(1),
+            bottom: self.desktop_size.height.saturating_sub(1),
+        }
+    }
+
+    /// Applies a drawing update to the framebuffer without encoding it
+    ///
+    /// Used while the client suppressed the display output, so that its areas can be refreshed later.
+    pub(crate) fn skip(&mut self, update: DisplayUpdate) {
+        match update {
+            DisplayUpdate::Bitmap(bitmap) => {
+                let diffs = [Rect {
+                    x: 0,
+                    y: 0,
+                    width: bitmap.width.get().into(),
+                    height: bitmap.height.get().into(),
+                }];
+                self.bitmap_update_framebuffer(bitmap, &diffs);
+            }
+            DisplayUpdate::ScreenCopy(copy) => {
+                if let Some(fb) = self.framebuffer.as_mut() {
+                    if !fb.copy_rect(&copy) {
+                        warn!(?copy, "Screen copy out of the framebuffer");
+                    }
+                }
+            }
+            DisplayUpdate::SolidFill(fill) => {
+                if let Some(fb) = self.framebuffer.as_mut() {
+                    if !fb.fill_rect(&fill) {
+                        warn!(?fill, "Solid fill out of the framebuffer");
+                    }
+                }
+            }
+            DisplayUpdate::Line(line) => {
+                if let Some(fb) = self.framebuffer.as_mut() {
+                    fb.draw_line(&line);
+                }
+            }
+            // GPU frames are read back by the server before reaching the encoder.
+            DisplayUpdate::GpuFrame(_)
+            | DisplayUpdate::Resize(_)
+            | DisplayUpdate::PointerPosition(_)
+            | DisplayUpdate::RGBAPointer(_)
+            | DisplayUpdate::ColorPointer(_)
+            | DisplayUpdate::HidePointer
+            | DisplayUpdate::DefaultPointer => {}
+        }
+    }
+
+    /// Encodes an area of the framebuffer again, as requested by the client
+    pub(crate) async fn refresh(&mut self, area: &InclusiveRectangle) -> Option<Result<UpdateFragmenter>> {
+        let width = area.right.checked_sub(area.left)?.saturating_add(1);
+        let height = area.bottom.checked_sub(area.top)?.saturating_add(1);
+
+        self.framebuffer_bitmap(area.left, area.top, width, height).await
+    }
+
     fn rgba_pointer(ptr: RGBAPointer) -> Result<UpdateFragmenter> {
         let xor_mask = ptr.data;
 
@@ -203,26 +370,16 @@ impl UpdateEncoder {
         // TODO: we may want to make it optional for servers that already provide damaged regions
         const USE_DIFFS: bool = true;
 
-        if let Some(Framebuffer {
-            data,
-            stride,
-            width,
-            height,
-            ..
-        }) = USE_DIFFS.then_some(self.framebuffer.as_ref()).flatten()
-        {
-            find_different_rects_sub::<4>(
-                data,
-                *stride,
-                width.get().into(),
-                height.get().into(),
-                &bitmap.data,
-                bitmap.stride.get(),
-                bitmap.width.get().into(),
-                bitmap.height.get().into(),
-                bitmap.x.into(),
-                bitmap.y.into(),
-            )
+        if let Some(framebuffer) = USE_DIFFS.then_some(self.framebuffer.as_ref()).flatten() {
+            framebuffer_dirty_rects(framebuffer, bitmap)
+                .into_iter()
+                .map(|rect| Rect {
+                    x: rect.left.into(),
+                    y: rect.top.into(),
+                    width: usize::from(rect.width()),
+                    height: usize::from(rect.height()),
+                })
+                .collect()
         } else {
             vec![Rect {
                 x: 0,
@@ -248,6 +405,97 @@ impl UpdateEncoder {
         }
     }
 
+    async fn screen_copy(&mut self, copy: ScreenCopyUpdate) -> Option<Result<UpdateFragmenter>> {
+        if let Some(fb) = self.framebuffer.as_mut() {
+            if !fb.copy_rect(&copy) {
+                warn!(?copy, "Screen copy out of the framebuffer");
+            }
+        }
+
+        if self.orders.support().scr_blt {
+            return Some(self.orders.screen_copy(&copy));
+        }
+
+        self.framebuffer_bitmap(copy.x, copy.y, copy.width.get(), copy.height.get())
+            .await
+    }
+
+    async fn solid_fill(&mut self, fill: SolidFillUpdate) -> Option<Result<UpdateFragmenter>> {
+        if let Some(fb) = self.framebuffer.as_mut() {
+            if !fb.fill_rect(&fill) {
+                warn!(?fill, "Solid fill out of the framebuffer");
+            }
+        }
+
+        if self.orders.support().opaque_rect {
+            return Some(self.orders.solid_fill(&fill));
+        }
+
+        self.framebuffer_bitmap(fill.x, fill.y, fill.width.get(), fill.height.get())
+            .await
+    }
+
+    async fn line(&mut self, line: LineUpdate) -> Option<Result<UpdateFragmenter>> {
+        if let Some(fb) = self.framebuffer.as_mut() {
+            fb.draw_line(&line);
+        }
+
+        if self.orders.support().line_to {
+            return Some(self.orders.line(&line));
+        }
+
+        let x = line.start_x.min(line.end_x);
+        let y = line.start_y.min(line.end_y);
+        let width = line.start_x.abs_diff(line.end_x).saturating_add(1);
+        let height = line.start_y.abs_diff(line.end_y).saturating_add(1);
+
+        self.framebuffer_bitmap(x, y, width, height).await
+    }
+
+    /// Sends an area of the framebuffer, for drawing updates the client can't draw itself
+    async fn framebuffer_bitmap(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+    ) -> Option<Result<UpdateFragmenter>> {
+        let Some(fb) = self.framebuffer.as_ref() else {
+            warn!("No framebuffer to draw the update from");
+            return None;
+        };
+
+        // Bitmap updates are encoded in blocks 4 pixels wide.
+        let left = x - x % 4;
+        let right = (u32::from(x) + u32::from(width))
+            .next_multiple_of(4)
+            .min(u32::from(fb.width.get()));
+        let bottom = (u32::from(y) + u32::from(height)).min(u32::from(fb.height.get()));
+
+        let width = u16::try_from(right.saturating_sub(u32::from(left)))
+            .ok()
+            .and_then(NonZeroU16::new)?;
+        let height = u16::try_from(bottom.saturating_sub(u32::from(y)))
+            .ok()
+            .and_then(NonZeroU16::new)?;
+
+        let bpp = usize::from(fb.format.bytes_per_pixel());
+        let start = usize::from(y) * fb.stride + usize::from(left) * bpp;
+        let end = start + usize::from(height.get() - 1) * fb.stride + usize::from(width.get()) * bpp;
+
+        let bitmap = BitmapUpdate {
+            x: left,
+            y,
+            width,
+            height,
+            format: fb.format,
+            data: Bytes::copy_from_slice(&fb.data[start..end]),
+            stride: NonZeroUsize::new(fb.stride)?,
+        };
+
+        Some(self.bitmap(bitmap).await)
+    }
+
     async fn bitmap(&mut self, bitmap: BitmapUpdate) -> Result<UpdateFragmenter> {
         // Move the bitmap updater to satisfy spawn_blocking 'static requirement.
         // It is restored after the blocking operation completes.
@@ -297,12 +545,15 @@ impl EncoderIter<'_> {
                         self.state = State::BitmapDiffs { diffs, bitmap, pos: 0 };
                         continue;
                     }
+                    DisplayUpdate::ScreenCopy(copy) => return encoder.screen_copy(copy).await,
+                    DisplayUpdate::SolidFill(fill) => return encoder.solid_fill(fill).await,
+                    DisplayUpdate::Line(line) => return encoder.line(line).await,
                     DisplayUpdate::PointerPosition(pos) => UpdateEncoder::pointer_position(pos),
                     DisplayUpdate::RGBAPointer(ptr) => UpdateEncoder::rgba_pointer(ptr),
                     DisplayUpdate::ColorPointer(ptr) => UpdateEncoder::color_pointer(ptr),
                     DisplayUpdate::HidePointer => UpdateEncoder::hide_pointer(),
                     DisplayUpdate::DefaultPointer => UpdateEncoder::default_pointer(),
-                    DisplayUpdate::Resize(_) => return None,
+                    DisplayUpdate::GpuFrame(_) | DisplayUpdate::Resize(_) => return None,
                 },
                 State::BitmapDiffs { diffs, bitmap, pos } => {
                     let Some(rect) = diffs.get(pos) else {
@@ -406,24 +657,38 @@ impl BitmapUpdateHandler for NoneHandler {
 #[derive(Clone)]
 struct BitmapHandler {
     bitmap: BitmapEncoder,
+    cache: Option<BitmapCache>,
 }
 
 impl fmt::Debug for BitmapHandler {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("BitmapHandler").finish()
+        f.debug_struct("BitmapHandler")
+            .field("cache", &self.cache.is_some())
+            .finish()
     }
 }
 
 impl BitmapHandler {
-    fn new() -> Self {
-        Self {
-            bitmap: BitmapEncoder::new(),
-        }
+    fn new(bits_per_pixel: u16, color_loss: ColorLoss, cache: Option<BitmapCache>) -> Self {
+        let bitmap = BitmapEncoder::new(bits_per_pixel).with_color_loss(color_loss);
+        let cache = cache.filter(|_| bitmap.bits_per_pixel() != 15);
+
+        Self { bitmap, cache }
     }
 }
 
 impl BitmapUpdateHandler for BitmapHandler {
     fn handle(&mut self, bitmap: &BitmapUpdate) -> Result<UpdateFragmenter> {
+        if let Some(cache) = self.cache.as_mut() {
+            let data = match self.bitmap.encode_orders(bitmap, cache) {
+                Ok(data) => data,
+                Err(BitmapEncodeError::Encode(e)) => Err(e).context("bitmap cache orders encode error")?,
+                Err(BitmapEncodeError::Rle(e)) => Err(e).context("bitmap RLE encode error")?,
+            };
+
+            return Ok(UpdateFragmenter::new(UpdateCode::Orders, data));
+        }
+
         let mut buffer = vec![0; bitmap.data.len() * 2]; // TODO: estimate bitmap encoded size
         let len = loop {
             match self.bitmap.encode(bitmap, buffer.as_mut_slice()) {
@@ -0,0 +1,156 @@
+use anyhow::{Context as _, Result};
+use ironrdp_pdu::encode_vec;
+use ironrdp_pdu::fast_path::UpdateCode;
+use ironrdp_pdu::orders::{Color, DrawingOrder, LineToOrder, OpaqueRectOrder, OrdersUpdateData, ScrBltOrder};
+use ironrdp_pdu::rdp::capability_sets::{Order, OrderSupportIndex};
+
+use super::UpdateFragmenter;
+use crate::{LineUpdate, RgbColor, ScreenCopyUpdate, SolidFillUpdate};
+
+/// Primary drawing orders supported by the client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct OrderSupport {
+    pub(crate) scr_blt: bool,
+    pub(crate) opaque_rect: bool,
+    pub(crate) line_to: bool,
+}
+
+impl OrderSupport {
+    pub(crate) fn from_capabilities(caps: &mut Order) -> Self {
+        Self {
+            scr_blt: caps.get_support_flag(OrderSupportIndex::ScrBlt),
+            // OpaqueRect support is announced along PatBlt.
+            opaque_rect: caps.get_support_flag(OrderSupportIndex::PatBlt),
+            line_to: caps.get_support_flag(OrderSupportIndex::LineTo),
+        }
+    }
+}
+
+/// Encodes drawing updates as primary drawing orders
+#[derive(Debug, Clone)]
+pub(crate) struct OrderEncoder {
+    support: OrderSupport,
+    bits_per_pixel: u16,
+}
+
+impl OrderEncoder {
+    pub(crate) fn new(support: OrderSupport, bits_per_pixel: u16) -> Self {
+        Self {
+            support,
+            bits_per_pixel,
+        }
+    }
+
+    pub(crate) fn support(&self) -> OrderSupport {
+        self.support
+    }
+
+    pub(crate) fn screen_copy(&self, copy: &ScreenCopyUpdate) -> Result<UpdateFragmenter> {
+        orders_update(DrawingOrder::ScrBlt(ScrBltOrder {
+            left: coordinate(copy.x)?,
+            top: coordinate(copy.y)?,
+            width: coordinate(copy.width.get())?,
+            height: coordinate(copy.height.get())?,
+            rop: ScrBltOrder::ROP_SRCCOPY,
+            src_x: coordinate(copy.src_x)?,
+            src_y: coordinate(copy.src_y)?,
+        }))
+    }
+
+    pub(crate) fn solid_fill(&self, fill: &SolidFillUpdate) -> Result<UpdateFragmenter> {
+        orders_update(DrawingOrder::OpaqueRect(OpaqueRectOrder {
+            left: coordinate(fill.x)?,
+            top: coordinate(fill.y)?,
+            width: coordinate(fill.width.get())?,
+            height: coordinate(fill.height.get())?,
+            color: self.color(fill.color),
+        }))
+    }
+
+    pub(crate) fn line(&self, line: &LineUpdate) -> Result<UpdateFragmenter> {
+        orders_update(DrawingOrder::LineTo(LineToOrder {
+            back_mode: LineToOrder::TRANSPARENT,
+            start_x: coordinate(line.start_x)?,
+            start_y: coordinate(line.start_y)?,
+            end_x: coordinate(line.end_x)?,
+            end_y: coordinate(line.end_y)?,
+            back_color: Color::default(),
+            rop2: LineToOrder::ROP2_COPYPEN,
+            pen_style: LineToOrder::PS_SOLID,
+            pen_width: 1,
+            pen_color: self.color(line.color),
+        }))
+    }
+
+    /// Converts a color to the session color depth
+    ///
+    /// 8 bpp sessions are not supported, the colors are then sent as RGB triplets.
+    fn color(&self, color: RgbColor) -> Color {
+        let RgbColor { red, green, blue } = color;
+
+        let pixel = match self.bits_per_pixel {
+            15 => (u16::from(red >> 3) << 10) | (u16::from(green >> 3) << 5) | u16::from(blue >> 3),
+            16 => (u16::from(red >> 3) << 11) | (u16::from(green >> 2) << 5) | u16::from(blue >> 3),
+            _ => return Color { red, green, blue },
+        };
+        let [low, high] = pixel.to_le_bytes();
+
+        Color {
+            red: low,
+            green: high,
+            blue: 0,
+        }
+    }
+}
+
+fn coordinate(value: u16) -> Result<i16> {
+    i16::try_from(value).context("drawing order coordinate out of range")
+}
+
+fn orders_update(order: DrawingOrder<'_>) -> Result<UpdateFragmenter> {
+    let data = encode_vec(&OrdersUpdateData { orders: vec![order] })?;
+
+    Ok(UpdateFragmenter::new(UpdateCode::Orders, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_in_session_color_depth() {
+        let color = RgbColor {
+            red: 0xFF,
+            green: 0x80,
+            blue: 0x08,
+        };
+        let encoder = |bits_per_pixel| OrderEncoder::new(OrderSupport::default(), bits_per_pixel);
+
+        assert_eq!(
+            encoder(32).color(color),
+            Color {
+                red: 0xFF,
+                green: 0x80,
+                blue: 0x08
+            }
+        );
+        // 0b0_11111_10000_00001
+        assert_eq!(
+            encoder(15).color(color),
+            Color {
+                red: 0x01,
+                green: 0x7E,
+                blue: 0
+            }
+        );
+        // 0b11111_100000_00001
+        assert_eq!(
+            encoder(16).color(color),
+            Color {
+                red: 0x01,
+                green: 0xFC,
+                blue: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,675 @@
+use core::num::{NonZeroU16, NonZeroUsize};
+
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_pdu::pointer::PointerPositionAttribute;
+
+use crate::{BitmapUpdate, DisplayUpdate, Framebuffer};
+
+/// Detects the regions of a frame which changed since the previous frame
+///
+/// Frames are split in square tiles. A tile is dirty when any of its pixels changed, and adjacent
+/// dirty tiles are merged into rectangles. The dirty rectangles can be sent as bitmap updates with
+/// [`dirty_updates()`](Self::dirty_updates), or as EGFX regions with [`diff()`](Self::diff).
+///
+/// By default, a copy of the previous frame is kept and compared in blocks of 32 bytes, which the
+/// compiler vectorizes. With [tile hashing](Self::with_tile_hashing), only a hash of each tile is
+/// kept, using much less memory at the cost of missing a change on hash collision.
+///
+/// The first frame, and any frame whose size or format differs from the previous one, is dirty as
+/// a whole.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut differ = FrameDiffer::new();
+///
+/// // Bitmap path
+/// for update in differ.dirty_updates(&frame) {
+///     updates.push(DisplayUpdate::Bitmap(update));
+/// }
+///
+/// // EGFX path, the regions being relative to the surface of the frame
+/// let pipeline = GpuFramePipeline::new(server, encoder).with_frame_differ(differ);
+/// ```
+///
+/// The update encoder compares the bitmap updates with its framebuffer in the same tiles, see
+/// [`DEFAULT_TILE_SIZE`](Self::DEFAULT_TILE_SIZE).
+#[derive(Debug, Clone)]
+pub struct FrameDiffer {
+    tile_size: NonZeroU16,
+    tile_hashing: bool,
+    previous: Option<PreviousFrame>,
+}
+
+#[derive(Debug, Clone)]
+struct PreviousFrame {
+    width: NonZeroU16,
+    height: NonZeroU16,
+    format: PixelFormat,
+    tiles: TileState,
+}
+
+#[derive(Debug, Clone)]
+enum TileState {
+    /// Pixels of the frame, without row padding
+    Pixels(Vec<u8>),
+    /// Hash of each tile, in row-major order
+    Hashes(Vec<u64>),
+}
+
+impl Default for FrameDiffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameDiffer {
+    /// Default tile size in pixels, matching the tiles of the update encoder
+    pub const DEFAULT_TILE_SIZE: NonZeroU16 = NonZeroU16::new(64).expect("non-zero");
+
+    pub fn new() -> Self {
+        Self {
+            tile_size: Self::DEFAULT_TILE_SIZE,
+            tile_hashing: false,
+            previous: None,
+        }
+    }
+
+    /// Sets the size of the tiles, smaller tiles give tighter rectangles but more of them
+    #[must_use]
+    pub fn with_tile_size(mut self, tile_size: NonZeroU16) -> Self {
+        self.tile_size = tile_size;
+        self.previous = None;
+        self
+    }
+
+    /// Keeps a hash of each tile instead of a copy of the previous frame
+    #[must_use]
+    pub fn with_tile_hashing(mut self, tile_hashing: bool) -> Self {
+        self.tile_hashing = tile_hashing;
+        self.previous = None;
+        self
+    }
+
+    /// Forgets the previous frame, so that the next frame is dirty as a whole
+    ///
+    /// Call this when the client needs a full refresh, e.g. after a reactivation.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// Returns the rectangles which changed since the previous frame, in desktop coordinates
+    ///
+    /// The frame is then kept as the previous frame.
+    pub fn diff(&mut self, frame: &BitmapUpdate) -> Vec<InclusiveRectangle> {
+        let mut rects = self.dirty_rects(frame);
+
+        for rect in &mut rects {
+            rect.left += frame.x;
+            rect.right += frame.x;
+            rect.top += frame.y;
+            rect.bottom += frame.y;
+        }
+
+        rects
+    }
+
+    /// Returns the parts of the frame which changed since the previous frame
+    ///
+    /// The frame is then kept as the previous frame.
+    pub fn dirty_updates(&mut self, frame: &BitmapUpdate) -> Vec<BitmapUpdate> {
+        self.dirty_rects(frame)
+            .into_iter()
+            .filter_map(|rect| {
+                let width = NonZeroU16::new(rect.right - rect.left + 1)?;
+                let height = NonZeroU16::new(rect.bottom - rect.top + 1)?;
+                frame.sub(rect.left, rect.top, width, height)
+            })
+            .collect()
+    }
+
+    /// Returns the dirty rectangles, relative to the frame
+    pub(crate) fn dirty_rects(&mut self, frame: &BitmapUpdate) -> Vec<InclusiveRectangle> {
+        let grid = TileGrid::new(frame.width, frame.height, self.tile_size);
+
+        let previous = self.previous.take().filter(|previous| {
+            previous.width == frame.width && previous.height == frame.height && previous.format == frame.format
+        });
+
+        let Some(mut previous) = previous else {
+            self.previous = Some(PreviousFrame::new(frame, &grid, self.tile_hashing));
+
+            return vec![whole_rect(frame)];
+        };
+
+        let bpp = usize::from(frame.format.bytes_per_pixel());
+        let packed_stride = usize::from(frame.width.get()) * bpp;
+
+        let dirty = grid
+            .tiles()
+            .enumerate()
+            .map(|(idx, tile)| match &mut previous.tiles {
+                TileState::Pixels(pixels) => {
+                    let mut dirty = false;
+                    let rows = tile_rows(&frame.data, frame.stride.get(), tile, bpp);
+                    let previous_rows = tile_rows_mut(pixels, packed_stride, tile, bpp);
+
+                    for (row, previous_row) in rows.zip(previous_rows) {
+                        if !rows_equal(row, previous_row) {
+                            previous_row.copy_from_slice(row);
+                            dirty = true;
+                        }
+                    }
+
+                    dirty
+                }
+                TileState::Hashes(hashes) => {
+                    let hash = hash_rows(tile_rows(&frame.data, frame.stride.get(), tile, bpp));
+                    let dirty = hashes[idx] != hash;
+                    hashes[idx] = hash;
+                    dirty
+                }
+            })
+            .collect();
+
+        self.previous = Some(previous);
+
+        grid.merge(dirty)
+    }
+}
+
+/// Returns the rectangles of a bitmap which differ from the framebuffer, relative to the bitmap
+///
+/// This is how the update encoder finds the parts of the bitmap updates to send: the bitmap is compared with the area
+/// of the framebuffer at its position, in tiles of [`FrameDiffer::DEFAULT_TILE_SIZE`]. It's dirty as a whole when it
+/// doesn't fit in the framebuffer or their pixel formats differ.
+pub(crate) fn framebuffer_dirty_rects(framebuffer: &Framebuffer, bitmap: &BitmapUpdate) -> Vec<InclusiveRectangle> {
+    let fits = u32::from(bitmap.x) + u32::from(bitmap.width.get()) <= u32::from(framebuffer.width.get())
+        && u32::from(bitmap.y) + u32::from(bitmap.height.get()) <= u32::from(framebuffer.height.get());
+
+    if !fits || framebuffer.format != bitmap.format {
+        return vec![whole_rect(bitmap)];
+    }
+
+    let grid = TileGrid::new(bitmap.width, bitmap.height, FrameDiffer::DEFAULT_TILE_SIZE);
+    let bpp = usize::from(bitmap.format.bytes_per_pixel());
+
+    let dirty = grid
+        .tiles()
+        .map(|tile| {
+            let framebuffer_tile = Tile {
+                x: tile.x + bitmap.x,
+                y: tile.y + bitmap.y,
+                ..tile
+            };
+
+            tile_rows(&bitmap.data, bitmap.stride.get(), tile, bpp)
+                .zip(tile_rows(&framebuffer.data, framebuffer.stride, framebuffer_tile, bpp))
+                .any(|(row, framebuffer_row)| !rows_equal(row, framebuffer_row))
+        })
+        .collect();
+
+    grid.merge(dirty)
+}
+
+/// Builds the display updates of a capture loop, sending cursor moves as pointer updates
+///
+/// Each frame is compared with the previous one by a [`FrameDiffer`], and only its dirty parts are
+/// sent. When the cursor is the only change since the previous frame, nothing is encoded: the client
+/// only receives a pointer position update. The cursor must not be drawn into the frames, its shape
+/// is sent with [`DisplayUpdate::RGBAPointer`] or [`DisplayUpdate::ColorPointer`].
+///
+/// # Example
+///
+/// ```ignore
+/// let mut updater = FrameUpdater::new().with_cursor_threshold(2);
+///
+/// loop {
+///     let (frame, cursor) = capture()?;
+///     for update in updater.updates(&frame, Some(cursor)) {
+///         send(update).await?;
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameUpdater {
+    differ: FrameDiffer,
+    cursor_threshold: u16,
+    /// Last cursor position sent to the client
+    cursor: Option<(u16, u16)>,
+}
+
+impl Default for FrameUpdater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameUpdater {
+    pub fn new() -> Self {
+        Self {
+            differ: FrameDiffer::new(),
+            cursor_threshold: 1,
+            cursor: None,
+        }
+    }
+
+    /// Sets the differ comparing the frames, e.g. with tile hashing
+    #[must_use]
+    pub fn with_differ(mut self, differ: FrameDiffer) -> Self {
+        self.differ = differ;
+        self
+    }
+
+    /// Sets the distance in pixels the cursor must move, on either axis, before its position is sent
+    ///
+    /// The distance is measured from the last position sent, so slow moves are eventually sent as
+    /// well. By default, every move is sent.
+    #[must_use]
+    pub fn with_cursor_threshold(mut self, cursor_threshold: u16) -> Self {
+        self.cursor_threshold = cursor_threshold;
+        self
+    }
+
+    /// Forgets the previous frame and cursor position, so that both are sent again
+    pub fn reset(&mut self) {
+        self.differ.reset();
+        self.cursor = None;
+    }
+
+    /// Returns the updates for a frame, and the cursor position in desktop coordinates
+    ///
+    /// The result is empty when neither the frame nor the cursor changed. `cursor` is `None` when
+    /// its position is unknown, no pointer update is sent then.
+    pub fn updates(&mut self, frame: &BitmapUpdate, cursor: Option<(u16, u16)>) -> Vec<DisplayUpdate> {
+        let mut updates: Vec<DisplayUpdate> = self
+            .differ
+            .dirty_updates(frame)
+            .into_iter()
+            .map(DisplayUpdate::Bitmap)
+            .collect();
+
+        if let Some((x, y)) = cursor.filter(|cursor| self.cursor_moved(*cursor)) {
+            self.cursor = Some((x, y));
+            updates.push(DisplayUpdate::PointerPosition(PointerPositionAttribute { x, y }));
+        }
+
+        updates
+    }
+
+    fn cursor_moved(&self, (x, y): (u16, u16)) -> bool {
+        self.cursor
+            .is_none_or(|(last_x, last_y)| x.abs_diff(last_x).max(y.abs_diff(last_y)) >= self.cursor_threshold.max(1))
+    }
+}
+
+impl PreviousFrame {
+    fn new(frame: &BitmapUpdate, grid: &TileGrid, tile_hashing: bool) -> Self {
+        let bpp = usize::from(frame.format.bytes_per_pixel());
+
+        let tiles = if tile_hashing {
+            let hashes = grid
+                .tiles()
+                .map(|tile| hash_rows(tile_rows(&frame.data, frame.stride.get(), tile, bpp)))
+                .collect();
+
+            TileState::Hashes(hashes)
+        } else {
+            let row_len = usize::from(frame.width.get()) * bpp;
+            let pixels = frame
+                .data
+                .chunks(frame.stride.get())
+                .take(NonZeroUsize::from(frame.height).get())
+                .flat_map(|row| &row[..row_len])
+                .copied()
+                .collect();
+
+            TileState::Pixels(pixels)
+        };
+
+        Self {
+            width: frame.width,
+            height: frame.height,
+            format: frame.format,
+            tiles,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Tile {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TileGrid {
+    width: u16,
+    height: u16,
+    tile_size: u16,
+    columns: u16,
+    rows: u16,
+}
+
+impl TileGrid {
+    fn new(width: NonZeroU16, height: NonZeroU16, tile_size: NonZeroU16) -> Self {
+        Self {
+            width: width.get(),
+            height: height.get(),
+            tile_size: tile_size.get(),
+            columns: width.get().div_ceil(tile_size.get()),
+            rows: height.get().div_ceil(tile_size.get()),
+        }
+    }
+
+    fn tile(&self, column: u16, row: u16) -> Tile {
+        let x = column * self.tile_size;
+        let y = row * self.tile_size;
+
+        Tile {
+            x,
+            y,
+            width: self.tile_size.min(self.width - x),
+            height: self.tile_size.min(self.height - y),
+        }
+    }
+
+    /// Tiles in row-major order
+    fn tiles(&self) -> impl Iterator<Item = Tile> {
+        let grid = *self;
+        (0..grid.rows).flat_map(move |row| (0..grid.columns).map(move |column| grid.tile(column, row)))
+    }
+
+    /// Merges adjacent dirty tiles into rectangles
+    ///
+    /// Dirty tiles are extended to the right first, then down as long as whole rows of the
+    /// rectangle are dirty.
+    fn merge(&self, mut dirty: Vec<bool>) -> Vec<InclusiveRectangle> {
+        let columns = usize::from(self.columns);
+        let rows = usize::from(self.rows);
+        let mut rects = Vec::new();
+
+        for idx in 0..dirty.len() {
+            if !dirty[idx] {
+                continue;
+            }
+
+            let (row, column) = (idx / columns, idx % columns);
+
+            let width = dirty[idx..idx + (columns - column)]
+                .iter()
+                .take_while(|dirty| **dirty)
+                .count();
+
+            let height = 1
+                + (row + 1..rows)
+                    .take_while(|next_row| {
+                        let start = next_row * columns + column;
+                        dirty[start..start + width].iter().all(|dirty| *dirty)
+                    })
+                    .count();
+
+            for dirty_row in dirty.chunks_mut(columns).skip(row).take(height) {
+                dirty_row[column..column + width].fill(false);
+            }
+
+            let top_left = self.tile(to_u16(column), to_u16(row));
+            let bottom_right = self.tile(to_u16(column + width - 1), to_u16(row + height - 1));
+
+            rects.push(InclusiveRectangle {
+                left: top_left.x,
+                top: top_left.y,
+                right: bottom_right.x + bottom_right.width - 1,
+                bottom: bottom_right.y + bottom_right.height - 1,
+            });
+        }
+
+        rects
+    }
+}
+
+/// The rectangle covering a bitmap, relative to the bitmap
+fn whole_rect(bitmap: &BitmapUpdate) -> InclusiveRectangle {
+    InclusiveRectangle {
+        left: 0,
+        top: 0,
+        right: bitmap.width.get() - 1,
+        bottom: bitmap.height.get() - 1,
+    }
+}
+
+/// Converts a tile coordinate, which is bounded by the frame size
+fn to_u16(value: usize) -> u16 {
+    u16::try_from(value).unwrap_or(u16::MAX)
+}
+
+fn tile_rows(data: &[u8], stride: usize, tile: Tile, bpp: usize) -> impl Iterator<Item = &[u8]> {
+    let start = usize::from(tile.x) * bpp;
+    let end = start + usize::from(tile.width) * bpp;
+
+    data.chunks(stride)
+        .skip(usize::from(tile.y))
+        .take(usize::from(tile.height))
+        .map(move |row| &row[start..end])
+}
+
+fn tile_rows_mut(data: &mut [u8], stride: usize, tile: Tile, bpp: usize) -> impl Iterator<Item = &mut [u8]> {
+    let start = usize::from(tile.x) * bpp;
+    let end = start + usize::from(tile.width) * bpp;
+
+    data.chunks_mut(stride)
+        .skip(usize::from(tile.y))
+        .take(usize::from(tile.height))
+        .map(move |row| &mut row[start..end])
+}
+
+/// Compares two rows in fixed size blocks, which lets the compiler use SIMD instructions
+fn rows_equal(a: &[u8], b: &[u8]) -> bool {
+    const BLOCK_SIZE: usize = 32;
+
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut a_blocks = a.chunks_exact(BLOCK_SIZE);
+    let mut b_blocks = b.chunks_exact(BLOCK_SIZE);
+
+    let blocks_equal = a_blocks
+        .by_ref()
+        .zip(b_blocks.by_ref())
+        .all(|(a, b)| a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0);
+
+    blocks_equal && a_blocks.remainder() == b_blocks.remainder()
+}
+
+/// Hashes rows of pixels, eight bytes at a time
+fn hash_rows<R>(rows: R) -> u64
+where
+    R: Iterator,
+    R::Item: AsRef<[u8]>,
+{
+    const SEED: u64 = 0xCBF2_9CE4_8422_2325;
+    const MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+    rows.fold(SEED, |hash, row| {
+        row.as_ref().chunks(8).fold(hash, |hash, chunk| {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+
+            (hash ^ u64::from_le_bytes(word))
+                .wrapping_mul(MULTIPLIER)
+                .rotate_left(29)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    const WIDTH: u16 = 100;
+    const HEIGHT: u16 = 70;
+
+    fn frame(data: Vec<u8>) -> BitmapUpdate {
+        BitmapUpdate {
+            x: 0,
+            y: 0,
+            width: NonZeroU16::new(WIDTH).unwrap(),
+            height: NonZeroU16::new(HEIGHT).unwrap(),
+            format: PixelFormat::BgrX32,
+            data: Bytes::from(data),
+            stride: NonZeroUsize::new(usize::from(WIDTH) * 4).unwrap(),
+        }
+    }
+
+    fn set_pixel(data: &mut [u8], x: u16, y: u16) {
+        let offset = (usize::from(y) * usize::from(WIDTH) + usize::from(x)) * 4;
+        data[offset] ^= 0xFF;
+    }
+
+    fn rect(left: u16, top: u16, right: u16, bottom: u16) -> InclusiveRectangle {
+        InclusiveRectangle {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn check_differ(mut differ: FrameDiffer) {
+        let mut data = vec![0; usize::from(WIDTH) * usize::from(HEIGHT) * 4];
+
+        assert_eq!(differ.diff(&frame(data.clone())), [rect(0, 0, 99, 69)]);
+        assert_eq!(differ.diff(&frame(data.clone())), []);
+
+        set_pixel(&mut data, 10, 10);
+        set_pixel(&mut data, 40, 10);
+        set_pixel(&mut data, 99, 69);
+        assert_eq!(
+            differ.diff(&frame(data.clone())),
+            [rect(0, 0, 63, 31), rect(96, 64, 99, 69)]
+        );
+        assert_eq!(differ.diff(&frame(data.clone())), []);
+
+        set_pixel(&mut data, 70, 40);
+        let updates = differ.dirty_updates(&frame(data.clone()));
+        assert_eq!(updates.len(), 1);
+        assert_eq!((updates[0].x, updates[0].y), (64, 32));
+        assert_eq!((updates[0].width.get(), updates[0].height.get()), (32, 32));
+
+        differ.reset();
+        assert_eq!(differ.diff(&frame(data)), [rect(0, 0, 99, 69)]);
+    }
+
+    fn pointer_position(update: &DisplayUpdate) -> Option<(u16, u16)> {
+        match update {
+            DisplayUpdate::PointerPosition(position) => Some((position.x, position.y)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn frame_updater_sends_cursor_only_moves() {
+        let mut frames = FrameUpdater::new();
+        let data = vec![0; usize::from(WIDTH) * usize::from(HEIGHT) * 4];
+
+        let updates = frames.updates(&frame(data.clone()), Some((5, 5)));
+        assert_eq!(updates.len(), 2);
+        assert!(matches!(updates[0], DisplayUpdate::Bitmap(_)));
+        assert_eq!(pointer_position(&updates[1]), Some((5, 5)));
+
+        assert!(frames.updates(&frame(data.clone()), Some((5, 5))).is_empty());
+
+        let updates = frames.updates(&frame(data.clone()), Some((6, 5)));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(pointer_position(&updates[0]), Some((6, 5)));
+
+        assert!(frames.updates(&frame(data), None).is_empty());
+    }
+
+    #[test]
+    fn frame_updater_cursor_threshold() {
+        let mut frames = FrameUpdater::new().with_cursor_threshold(4);
+        let mut data = vec![0; usize::from(WIDTH) * usize::from(HEIGHT) * 4];
+
+        assert_eq!(frames.updates(&frame(data.clone()), Some((10, 10))).len(), 2);
+
+        // Moves below the threshold are accumulated from the last position sent.
+        assert!(frames.updates(&frame(data.clone()), Some((12, 10))).is_empty());
+        assert!(frames.updates(&frame(data.clone()), Some((13, 11))).is_empty());
+        let updates = frames.updates(&frame(data.clone()), Some((14, 9)));
+        assert_eq!(
+            updates.iter().filter_map(pointer_position).collect::<Vec<_>>(),
+            [(14, 9)]
+        );
+
+        // Frame changes are sent regardless of the cursor.
+        set_pixel(&mut data, 50, 50);
+        let updates = frames.updates(&frame(data), Some((15, 9)));
+        assert_eq!(updates.len(), 1);
+        assert!(matches!(updates[0], DisplayUpdate::Bitmap(_)));
+    }
+
+    #[test]
+    fn frame_differ_compares_tiles() {
+        check_differ(FrameDiffer::new().with_tile_size(NonZeroU16::new(32).unwrap()));
+    }
+
+    #[test]
+    fn frame_differ_hashes_tiles() {
+        check_differ(
+            FrameDiffer::new()
+                .with_tile_size(NonZeroU16::new(32).unwrap())
+                .with_tile_hashing(true),
+        );
+    }
+
+    #[test]
+    fn frame_differ_offsets_rectangles() {
+        let mut differ = FrameDiffer::new();
+        let mut update = frame(vec![0; usize::from(WIDTH) * usize::from(HEIGHT) * 4]);
+        update.x = 10;
+        update.y = 20;
+
+        assert_eq!(differ.diff(&update), [rect(10, 20, 109, 89)]);
+    }
+
+    #[test]
+    fn framebuffer_dirty_rects_compare_at_position() {
+        let framebuffer = Framebuffer::new(
+            NonZeroU16::new(200).unwrap(),
+            NonZeroU16::new(150).unwrap(),
+            PixelFormat::BgrX32,
+        );
+        let mut data = vec![0; usize::from(WIDTH) * usize::from(HEIGHT) * 4];
+        let mut update = frame(data.clone());
+        update.x = 50;
+        update.y = 40;
+
+        assert_eq!(framebuffer_dirty_rects(&framebuffer, &update), []);
+
+        set_pixel(&mut data, 70, 40);
+        update.data = Bytes::from(data);
+        assert_eq!(framebuffer_dirty_rects(&framebuffer, &update), [rect(64, 0, 99, 63)]);
+
+        // Out of the framebuffer
+        update.x = 150;
+        assert_eq!(framebuffer_dirty_rects(&framebuffer, &update), [rect(0, 0, 99, 69)]);
+    }
+
+    #[test]
+    fn rows_equal_checks_every_byte() {
+        let a = [1u8; 70];
+        for idx in 0..a.len() {
+            let mut b = a;
+            b[idx] = 0;
+            assert!(!rows_equal(&a, &b));
+        }
+        assert!(rows_equal(&a, &[1; 70]));
+    }
+}
@@ -1,7 +1,7 @@
 //! EGFX (Graphics Pipeline Extension) server support
 //!
 //! This module provides:
-//! - Factory trait for creating EGFX handlers
+//! - Factory trait for creating EGFX handlers, configured per connection
 //! - Bridge wrapper for shared access to GraphicsPipelineServer
 //! - Types for proactive frame sending via ServerEvent
 //!
@@ -24,12 +24,24 @@
 //! frame acknowledgments.
 
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
+use anyhow::Context as _;
 use ironrdp_core::impl_as_any;
-use ironrdp_dvc::{DvcMessage, DvcProcessor, DvcServerProcessor};
-use ironrdp_egfx::server::{GraphicsPipelineHandler, GraphicsPipelineServer};
+use ironrdp_displaycontrol::server::MonitorLayout as DisplayMonitorLayout;
+use ironrdp_dvc::pdu::ChannelPriority;
+use ironrdp_dvc::{encode_dvc_messages_with_max_data_size, DvcMessage, DvcProcessor, DvcServerProcessor};
+use ironrdp_egfx::pdu::{Avc420Region, CapabilitySet, PixelFormat};
+use ironrdp_egfx::server::{GraphicsPipelineHandler, GraphicsPipelineServer, MonitorLayout, OutputMonitor};
 use ironrdp_pdu::PduResult;
-use ironrdp_svc::SvcMessage;
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tokio::sync::mpsc;
+use tokio::task;
+
+use crate::{
+    encode_gpu_frame, BitmapUpdate, ConnectionContext, EncodedGpuFrame, EncoderWatchdog, FrameDiffer, GpuFrameUpdate,
+    H264Encoder, ServerEvent,
+};
 
 /// Handle to a shared GraphicsPipelineServer
 ///
@@ -40,18 +52,102 @@ use ironrdp_svc::SvcMessage;
 /// has synchronous methods that cannot use async locks.
 pub type GfxServerHandle = Arc<Mutex<GraphicsPipelineServer>>;
 
+/// Per-connection parameters of the graphics pipeline
+///
+/// A default configuration is created for each connection, which the factory can adjust in
+/// [`GfxServerFactory::configure`] before the handler is built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GfxServerConfig {
+    /// The connection the pipeline is created for
+    pub context: ConnectionContext,
+    /// Initial output width, the desktop width by default
+    pub width: u16,
+    /// Initial output height, the desktop height by default
+    pub height: u16,
+    /// Frames in flight before backpressure, `None` to use the handler's value
+    pub max_frames_in_flight: Option<u32>,
+    /// Capability sets offered to the client in preference order, `None` to use the handler's
+    pub preferred_capabilities: Option<Vec<CapabilitySet>>,
+    /// Pixel format of the surfaces, [`PixelFormat::XRgb`] by default
+    ///
+    /// [`PixelFormat::ARgb`] keeps the alpha channel of the surfaces, e.g. for the windows of the remote
+    /// applications.
+    pub pixel_format: PixelFormat,
+}
+
+impl GfxServerConfig {
+    /// Create a configuration with the handler defaults
+    pub fn new(context: ConnectionContext, width: u16, height: u16) -> Self {
+        Self {
+            context,
+            width,
+            height,
+            max_frames_in_flight: None,
+            preferred_capabilities: None,
+            pixel_format: PixelFormat::XRgb,
+        }
+    }
+
+    /// Create a GraphicsPipelineServer with this configuration applied
+    pub fn build_server(&self, handler: Box<dyn GraphicsPipelineHandler>) -> GraphicsPipelineServer {
+        let mut server = GraphicsPipelineServer::new(handler);
+        self.apply(&mut server);
+        server
+    }
+
+    /// Apply this configuration to an existing GraphicsPipelineServer
+    pub fn apply(&self, server: &mut GraphicsPipelineServer) {
+        server.set_output_dimensions(self.width, self.height);
+        server.set_default_pixel_format(self.pixel_format);
+
+        if let Some(max) = self.max_frames_in_flight {
+            server.set_max_frames_in_flight(max);
+        }
+
+        if let Some(capabilities) = &self.preferred_capabilities {
+            server.set_preferred_capabilities(capabilities.clone());
+        }
+    }
+}
+
+/// Convert a monitor layout requested by the client to the layout of the graphics output buffer
+///
+/// The monitors keep the size requested by the client, which already accounts for their orientation. Once the
+/// layout is applied with [`GraphicsPipelineServer::resize_with_layout`], surfaces rendered at another scale can
+/// be mapped with [`GraphicsPipelineServer::map_surface_to_scaled_monitor`], using the same monitor indexes.
+///
+/// Returns `None` if the layout exceeds the limits of the graphics output buffer.
+pub fn gfx_monitor_layout(layout: &DisplayMonitorLayout) -> Option<MonitorLayout> {
+    let monitors = layout
+        .monitors()
+        .map(|monitor| {
+            Some(OutputMonitor {
+                left: monitor.left,
+                top: monitor.top,
+                width: u16::try_from(monitor.width).ok()?,
+                height: u16::try_from(monitor.height).ok()?,
+                is_primary: monitor.is_primary,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    MonitorLayout::new(monitors)
+}
+
 /// Factory trait for creating EGFX graphics pipeline handlers
 ///
 /// Implementors provide:
 /// 1. A handler for EGFX callbacks (capability negotiation, frame acks)
 /// 2. Optionally, a shared handle to the GraphicsPipelineServer for proactive frame sending
 ///
+/// Both receive the [`GfxServerConfig`] of the connection, as adjusted by [`configure()`](Self::configure).
+///
 /// # Basic Usage (Handler Only)
 ///
 /// ```ignore
 /// impl GfxServerFactory for MyFactory {
-///     fn build_gfx_handler(&self) -> Box<dyn GraphicsPipelineHandler> {
-///         Box::new(MyHandler::new())
+///     fn build_gfx_handler(&self, config: &GfxServerConfig) -> Box<dyn GraphicsPipelineHandler> {
+///         Box::new(MyHandler::new(config.context.session_id()))
 ///     }
 /// }
 /// ```
@@ -62,12 +158,16 @@ pub type GfxServerHandle = Arc<Mutex<GraphicsPipelineServer>>;
 ///
 /// ```ignore
 /// impl GfxServerFactory for MyFactory {
-///     fn build_gfx_handler(&self) -> Box<dyn GraphicsPipelineHandler> {
-///         Box::new(MyHandler::new())
+///     fn build_gfx_handler(&self, config: &GfxServerConfig) -> Box<dyn GraphicsPipelineHandler> {
+///         Box::new(MyHandler::new(config.context.session_id()))
+///     }
+///
+///     fn configure(&self, config: &mut GfxServerConfig) {
+///         config.max_frames_in_flight = Some(2);
 ///     }
 ///
-///     fn build_server_with_handle(&self) -> Option<(GfxDvcBridge, GfxServerHandle)> {
-///         let handler = Box::new(MyHandler::new());
+///     fn build_server_with_handle(&self, config: &GfxServerConfig) -> Option<(GfxDvcBridge, GfxServerHandle)> {
+///         let handler = self.build_gfx_handler(config);
 ///         let server = Arc::new(Mutex::new(GraphicsPipelineServer::new(handler)));
 ///         let bridge = GfxDvcBridge::new(Arc::clone(&server));
 ///         Some((bridge, server))
@@ -75,10 +175,15 @@ pub type GfxServerHandle = Arc<Mutex<GraphicsPipelineServer>>;
 /// }
 /// ```
 pub trait GfxServerFactory: Send {
+    /// Adjust the configuration of a new connection
+    ///
+    /// Called before building the handler. The default keeps the handler defaults.
+    fn configure(&self, _config: &mut GfxServerConfig) {}
+
     /// Create a new graphics pipeline handler
     ///
     /// This is used when shared server access is not needed.
-    fn build_gfx_handler(&self) -> Box<dyn GraphicsPipelineHandler>;
+    fn build_gfx_handler(&self, config: &GfxServerConfig) -> Box<dyn GraphicsPipelineHandler>;
 
     /// Create a bridge and shared server handle
     ///
@@ -88,7 +193,19 @@ pub trait GfxServerFactory: Send {
     /// When returning `Some((bridge, handle))`:
     /// - `bridge` is registered with DrdynvcServer (handles client messages)
     /// - `handle` is stored for frame sending (display handler access)
-    fn build_server_with_handle(&self) -> Option<(GfxDvcBridge, GfxServerHandle)> {
+    ///
+    /// The configuration is applied to the returned server.
+    fn build_server_with_handle(&self, _config: &GfxServerConfig) -> Option<(GfxDvcBridge, GfxServerHandle)> {
+        None
+    }
+
+    /// Create the H.264 encoder of the [`DisplayUpdate::GpuFrame`](crate::DisplayUpdate::GpuFrame) updates
+    ///
+    /// Only used along a shared server handle (see [`build_server_with_handle()`](Self::build_server_with_handle)):
+    /// the server loop sends the GPU frames through a [`GpuFramePipeline`] once the client negotiated AVC420,
+    /// and reads them back to the bitmap path otherwise. Returns `None` by default, the GPU frames being always
+    /// read back.
+    fn build_h264_encoder(&self, _config: &GfxServerConfig) -> Option<Box<dyn H264Encoder>> {
         None
     }
 }
@@ -137,6 +254,10 @@ impl DvcProcessor for GfxDvcBridge {
             .start(channel_id)
     }
 
+    fn priority(&self) -> ChannelPriority {
+        ChannelPriority::High
+    }
+
     fn process(&mut self, channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
         self.inner
             .lock()
@@ -181,3 +302,459 @@ impl std::fmt::Display for EgfxServerMessage {
         }
     }
 }
+
+/// Quantization parameter of the regions of the frames sent by [`GpuFramePipeline`]
+const GPU_FRAME_QP: u8 = 22;
+
+/// Outcome of [`GpuFramePipeline::send`]
+#[derive(Debug)]
+pub enum GpuFrameOutcome {
+    /// The frame was queued on the graphics pipeline, to be drained with
+    /// [`GraphicsPipelineServer::drain_output`]
+    Queued {
+        frame_id: u32,
+        /// Whether the frame was encoded without a copy to system memory
+        zero_copy: bool,
+    },
+    /// The frame was dropped by the graphics pipeline, e.g. because of backpressure
+    Dropped,
+    /// The frame is identical to the previous one, it was neither encoded nor sent
+    ///
+    /// Only reported with a [frame differ](GpuFramePipeline::with_frame_differ).
+    Unchanged,
+    /// The client did not negotiate AVC420: the frame was read back, to be sent as a
+    /// [`DisplayUpdate::Bitmap`](crate::DisplayUpdate::Bitmap)
+    Fallback(BitmapUpdate),
+}
+
+/// Sends GPU frames as AVC420 through the graphics pipeline
+///
+/// Frames are encoded from GPU memory when the [`H264Encoder`] can import their surface, and read back
+/// to system memory otherwise (see [`encode_gpu_frame`]). The frames are displayed on a surface mapped
+/// at the position of the update, created again when the size of the frames changes.
+///
+/// A hardware encoder can be [watched](Self::new_watched), to fall back to a software encoder when
+/// it stalls.
+///
+/// With a [frame differ](Self::with_frame_differ), the frames are read back to be compared with the
+/// previous one: only the dirty rectangles are sent as AVC420 regions, and the identical frames are
+/// skipped before being encoded.
+///
+/// Encoding may block: [`send`](Self::send) is meant to be called from a blocking task.
+pub struct GpuFramePipeline {
+    server: GfxServerHandle,
+    encoder: PipelineEncoder,
+    surface: Option<PipelineSurface>,
+    differ: Option<FrameDiffer>,
+}
+
+enum PipelineEncoder {
+    Direct(Box<dyn H264Encoder>),
+    Watched(EncoderWatchdog),
+}
+
+impl PipelineEncoder {
+    fn encode(&mut self, frame: &GpuFrameUpdate) -> anyhow::Result<EncodedGpuFrame> {
+        match self {
+            Self::Direct(encoder) => encode_gpu_frame(encoder.as_mut(), frame),
+            Self::Watched(watchdog) => watchdog.encode(frame),
+        }
+    }
+
+    /// Same as [`Self::encode`], reusing the frame already read back when the surface can't be imported
+    fn encode_read_back(&mut self, frame: &GpuFrameUpdate, bitmap: &BitmapUpdate) -> anyhow::Result<EncodedGpuFrame> {
+        match self {
+            Self::Direct(encoder) => {
+                if let Some(data) = encoder.encode_surface(frame.surface.as_ref())? {
+                    return Ok(EncodedGpuFrame { data, zero_copy: true });
+                }
+
+                let data = encoder.encode_bitmap(bitmap)?;
+
+                Ok(EncodedGpuFrame { data, zero_copy: false })
+            }
+            Self::Watched(watchdog) => watchdog.encode(frame),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PipelineSurface {
+    id: u16,
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+impl GpuFramePipeline {
+    pub fn new(server: GfxServerHandle, encoder: Box<dyn H264Encoder>) -> Self {
+        Self {
+            server,
+            encoder: PipelineEncoder::Direct(encoder),
+            surface: None,
+            differ: None,
+        }
+    }
+
+    /// Creates a pipeline encoding the frames through an [`EncoderWatchdog`]
+    pub fn new_watched(server: GfxServerHandle, watchdog: EncoderWatchdog) -> Self {
+        Self {
+            server,
+            encoder: PipelineEncoder::Watched(watchdog),
+            surface: None,
+            differ: None,
+        }
+    }
+
+    /// Sends only the rectangles of the frames which changed, as detected by `differ`
+    ///
+    /// The differ is reset whenever the client may be missing the previous frame, i.e. when the
+    /// surface is created again or a frame is dropped.
+    #[must_use]
+    pub fn with_frame_differ(mut self, differ: FrameDiffer) -> Self {
+        self.differ = Some(differ);
+        self
+    }
+
+    /// Encodes a frame and queues it on the graphics pipeline
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex of the graphics pipeline is poisoned.
+    pub fn send(&mut self, frame: &GpuFrameUpdate, timestamp_ms: u32) -> anyhow::Result<GpuFrameOutcome> {
+        let width = frame.surface.width().get();
+        let height = frame.surface.height().get();
+
+        let (ready, surface_current) = {
+            let server = self.server.lock().expect("GfxServerHandle mutex poisoned");
+            let surface_current = self.surface.is_some_and(|surface| {
+                (surface.x, surface.y, surface.width, surface.height) == (frame.x, frame.y, width, height)
+                    && server.get_surface(surface.id).is_some()
+            });
+
+            (server.is_ready() && server.supports_avc420(), surface_current)
+        };
+
+        if !ready {
+            return Ok(GpuFrameOutcome::Fallback(frame.read_back()?));
+        }
+
+        // The pipeline is not locked while encoding, to keep processing the messages of the client.
+        let (encoded, dirty_rects) = match self.differ.as_mut() {
+            Some(differ) => {
+                let bitmap = frame.read_back()?;
+
+                if !surface_current {
+                    differ.reset();
+                }
+
+                // Skipping a frame after encoding it would break the reference chain of the H.264 stream.
+                let dirty_rects = differ.dirty_rects(&bitmap);
+                if dirty_rects.is_empty() {
+                    return Ok(GpuFrameOutcome::Unchanged);
+                }
+
+                (self.encoder.encode_read_back(frame, &bitmap)?, Some(dirty_rects))
+            }
+            None => (self.encoder.encode(frame)?, None),
+        };
+
+        let mut server = self.server.lock().expect("GfxServerHandle mutex poisoned");
+        let previous_surface = self.surface.map(|surface| surface.id);
+
+        let Some(surface_id) = surface_for(&mut self.surface, &mut server, frame.x, frame.y, width, height) else {
+            reset_differ(&mut self.differ);
+            return Ok(GpuFrameOutcome::Dropped);
+        };
+
+        // A surface created since the frame was compared is blank, it needs the whole frame.
+        let regions: Vec<Avc420Region> = match dirty_rects {
+            Some(rects) if previous_surface == Some(surface_id) => rects
+                .iter()
+                .map(|rect| Avc420Region::new(rect.left, rect.top, rect.right, rect.bottom, GPU_FRAME_QP, 100))
+                .collect(),
+            _ => vec![Avc420Region::full_frame(width, height, GPU_FRAME_QP)],
+        };
+
+        let outcome = match server.send_avc420_frame(surface_id, &encoded.data, &regions, timestamp_ms) {
+            Some(frame_id) => GpuFrameOutcome::Queued {
+                frame_id,
+                zero_copy: encoded.zero_copy,
+            },
+            None => {
+                reset_differ(&mut self.differ);
+                GpuFrameOutcome::Dropped
+            }
+        };
+
+        Ok(outcome)
+    }
+}
+
+/// Routes the GPU frames of the server loop to a [`GpuFramePipeline`]
+pub(crate) struct GpuFrameRouter {
+    /// Shared with the blocking task encoding the current frame
+    pipeline: Arc<Mutex<GpuFramePipeline>>,
+    server: GfxServerHandle,
+    events: mpsc::UnboundedSender<ServerEvent>,
+    max_data_size: usize,
+    start: Instant,
+}
+
+impl GpuFrameRouter {
+    pub(crate) fn new(
+        server: GfxServerHandle,
+        encoder: Box<dyn H264Encoder>,
+        events: mpsc::UnboundedSender<ServerEvent>,
+        max_data_size: usize,
+    ) -> Self {
+        Self {
+            pipeline: Arc::new(Mutex::new(GpuFramePipeline::new(Arc::clone(&server), encoder))),
+            server,
+            events,
+            max_data_size,
+            start: Instant::now(),
+        }
+    }
+
+    /// Sends a frame as AVC420, or returns it read back when the client did not negotiate AVC420
+    ///
+    /// The EGFX PDUs of the frame are sent with a [`ServerEvent::Egfx`].
+    pub(crate) async fn route(&mut self, frame: GpuFrameUpdate) -> anyhow::Result<Option<BitmapUpdate>> {
+        let pipeline = Arc::clone(&self.pipeline);
+        let timestamp_ms = u32::try_from(self.start.elapsed().as_millis()).unwrap_or(u32::MAX);
+
+        let outcome = task::spawn_blocking(move || {
+            pipeline
+                .lock()
+                .expect("GpuFramePipeline mutex poisoned")
+                .send(&frame, timestamp_ms)
+        })
+        .await?
+        .context("failed to send GPU frame")?;
+
+        match outcome {
+            GpuFrameOutcome::Fallback(bitmap) => return Ok(Some(bitmap)),
+            GpuFrameOutcome::Queued { .. } | GpuFrameOutcome::Dropped | GpuFrameOutcome::Unchanged => {}
+        }
+
+        let (channel_id, messages) = {
+            let mut server = self.server.lock().expect("GfxServerHandle mutex poisoned");
+            (server.channel_id(), server.drain_output())
+        };
+
+        if !messages.is_empty() {
+            let channel_id = channel_id.context("EGFX channel not opened")?;
+            let messages = encode_dvc_messages_with_max_data_size(
+                channel_id,
+                messages,
+                ChannelFlags::SHOW_PROTOCOL,
+                self.max_data_size,
+            )?;
+
+            self.events
+                .send(ServerEvent::Egfx(EgfxServerMessage::SendMessages {
+                    channel_id,
+                    messages,
+                }))
+                .map_err(|_| anyhow::anyhow!("server event channel closed"))?;
+        }
+
+        Ok(None)
+    }
+}
+
+/// Makes the next frame dirty as a whole, the client missing the changes of the current one
+fn reset_differ(differ: &mut Option<FrameDiffer>) {
+    if let Some(differ) = differ {
+        differ.reset();
+    }
+}
+
+/// Returns the surface displaying the frames, created again when their position or size changes
+fn surface_for(
+    current: &mut Option<PipelineSurface>,
+    server: &mut GraphicsPipelineServer,
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+) -> Option<u16> {
+    if let Some(surface) = *current {
+        if (surface.x, surface.y, surface.width, surface.height) == (x, y, width, height)
+            && server.get_surface(surface.id).is_some()
+        {
+            return Some(surface.id);
+        }
+
+        server.delete_surface(surface.id);
+        *current = None;
+    }
+
+    let id = server.create_surface(width, height)?;
+    if !server.map_surface_to_output(id, u32::from(x), u32::from(y)) {
+        server.delete_surface(id);
+        return None;
+    }
+
+    *current = Some(PipelineSurface {
+        id,
+        x,
+        y,
+        width,
+        height,
+    });
+
+    Some(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::{NonZeroU16, NonZeroUsize};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use bytes::Bytes;
+    use ironrdp_core::encode_vec;
+    use ironrdp_egfx::pdu::{CapabilitiesAdvertisePdu, CapabilitiesV81Flags, GfxPdu};
+    use ironrdp_egfx::server::GfxContext;
+
+    use super::*;
+    use crate::{GpuSurface, GpuSurfaceHandle};
+
+    const CHANNEL_ID: u32 = 3;
+
+    struct Handler;
+
+    impl GraphicsPipelineHandler for Handler {
+        fn capabilities_advertise(&mut self, _pdu: &CapabilitiesAdvertisePdu, _ctx: &mut GfxContext) {}
+
+        fn on_ready(&mut self, _negotiated: &CapabilitySet, _ctx: &mut GfxContext) {}
+    }
+
+    #[derive(Debug, Default)]
+    struct VaFrame {
+        read_backs: AtomicUsize,
+    }
+
+    impl GpuSurface for VaFrame {
+        fn width(&self) -> NonZeroU16 {
+            NonZeroU16::new(16).unwrap()
+        }
+
+        fn height(&self) -> NonZeroU16 {
+            NonZeroU16::new(16).unwrap()
+        }
+
+        fn handle(&self) -> GpuSurfaceHandle<'_> {
+            GpuSurfaceHandle::VaSurface { surface_id: 7 }
+        }
+
+        fn read_back(&self) -> anyhow::Result<BitmapUpdate> {
+            self.read_backs.fetch_add(1, Ordering::Relaxed);
+
+            Ok(BitmapUpdate {
+                x: 0,
+                y: 0,
+                width: self.width(),
+                height: self.height(),
+                format: crate::PixelFormat::BgrX32,
+                data: Bytes::from(vec![0; 16 * 16 * 4]),
+                stride: NonZeroUsize::new(16 * 4).unwrap(),
+            })
+        }
+    }
+
+    /// Imports the VA surfaces when `va` is set
+    struct Encoder {
+        va: bool,
+    }
+
+    impl H264Encoder for Encoder {
+        fn encode_bitmap(&mut self, _bitmap: &BitmapUpdate) -> anyhow::Result<Vec<u8>> {
+            Ok(vec![0, 0, 0, 1])
+        }
+
+        fn encode_surface(&mut self, surface: &dyn GpuSurface) -> anyhow::Result<Option<Vec<u8>>> {
+            match surface.handle() {
+                GpuSurfaceHandle::VaSurface { .. } if self.va => Ok(Some(vec![0, 0, 0, 1])),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    fn server(avc420: bool) -> GfxServerHandle {
+        let mut server = GraphicsPipelineServer::new(Box::new(Handler));
+
+        if avc420 {
+            server.start(CHANNEL_ID).unwrap();
+
+            let pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![CapabilitySet::V8_1 {
+                flags: CapabilitiesV81Flags::AVC420_ENABLED,
+            }]));
+            server.process(CHANNEL_ID, &encode_vec(&pdu).unwrap()).unwrap();
+            assert!(server.is_ready() && server.supports_avc420());
+        }
+
+        Arc::new(Mutex::new(server))
+    }
+
+    /// Routes a frame, returning the bitmap to send, the read backs of the frame, the frames queued on the
+    /// graphics pipeline and the events sent
+    async fn route(avc420: bool, encoder: Encoder) -> (Option<BitmapUpdate>, usize, u32, Vec<ServerEvent>) {
+        let server = server(avc420);
+        let (events, mut received) = mpsc::unbounded_channel();
+        let mut router = GpuFrameRouter::new(Arc::clone(&server), Box::new(encoder), events, 1590);
+
+        let surface = Arc::new(VaFrame::default());
+        let frame = GpuFrameUpdate {
+            x: 0,
+            y: 0,
+            surface: Arc::<VaFrame>::clone(&surface),
+        };
+        let bitmap = router.route(frame).await.unwrap();
+
+        let mut sent = Vec::new();
+        while let Ok(event) = received.try_recv() {
+            sent.push(event);
+        }
+
+        let queued = server.lock().unwrap().frames_in_flight();
+
+        (bitmap, surface.read_backs.load(Ordering::Relaxed), queued, sent)
+    }
+
+    #[tokio::test]
+    async fn frames_are_sent_as_avc420() {
+        let (bitmap, read_backs, queued, sent) = route(true, Encoder { va: true }).await;
+
+        assert!(bitmap.is_none());
+        assert_eq!(read_backs, 0);
+        assert_eq!(queued, 1);
+        assert!(matches!(
+            sent.as_slice(),
+            [ServerEvent::Egfx(EgfxServerMessage::SendMessages { channel_id: CHANNEL_ID, messages })] if !messages.is_empty()
+        ));
+    }
+
+    #[tokio::test]
+    async fn declined_surfaces_are_read_back() {
+        let (bitmap, read_backs, queued, sent) = route(true, Encoder { va: false }).await;
+
+        // Still sent as AVC420, from the frame read back.
+        assert!(bitmap.is_none());
+        assert_eq!(read_backs, 1);
+        assert_eq!(queued, 1);
+        assert_eq!(sent.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn frames_fall_back_to_bitmaps_without_avc420() {
+        let (bitmap, read_backs, queued, sent) = route(false, Encoder { va: true }).await;
+
+        assert_eq!(bitmap.map(|bitmap| bitmap.width.get()), Some(16));
+        assert_eq!(read_backs, 1);
+        assert_eq!(queued, 0);
+        assert!(sent.is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+//! GPU surfaces handed from the capture to the encoder
+//!
+//! A display capturing the screen on the GPU (Desktop Duplication, PipeWire with DMA-BUF, KMS, …)
+//! can send its frames as [`DisplayUpdate::GpuFrame`](crate::DisplayUpdate::GpuFrame) instead of
+//! copying them to system memory. An [`H264Encoder`] accepting the handle of the surface encodes it
+//! in place, so the pixels never leave the GPU between capture and encode.
+//!
+//! Every surface can be read back to system memory. This is the fallback used automatically when
+//! the frame can't be encoded on the GPU: the encoder does not support the kind of surface, no
+//! encoder is configured, or the client did not negotiate H.264. The frame then goes through the
+//! existing bitmap path.
+
+use core::fmt;
+use core::num::NonZeroU16;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::BitmapUpdate;
+
+/// Handle of a frame in GPU memory
+///
+/// The handle borrows from the [`GpuSurface`] and is only valid while the surface is alive.
+#[derive(Debug, Clone, Copy)]
+pub enum GpuSurfaceHandle<'a> {
+    /// Direct3D 11 texture, shared through a NT handle
+    ///
+    /// The texture is opened with `ID3D11Device1::OpenSharedResource1` on the device of the encoder.
+    #[cfg(windows)]
+    D3d11Texture {
+        shared_handle: std::os::windows::io::BorrowedHandle<'a>,
+        subresource: u32,
+    },
+    /// VA-API surface
+    ///
+    /// Surfaces are only meaningful in their `VADisplay`, which the capture and the encoder must share.
+    VaSurface { surface_id: u32 },
+    /// DMA-BUF, as exported by the DRM or Vulkan driver
+    #[cfg(unix)]
+    DmaBuf(&'a DmaBuf),
+}
+
+/// Buffer shared between devices through DMA-BUF file descriptors
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct DmaBuf {
+    /// DRM format (fourcc code), e.g. `XR24` for `DRM_FORMAT_XRGB8888`
+    pub fourcc: u32,
+    /// DRM format modifier, describing the tiling of the buffer
+    pub modifier: u64,
+    pub planes: Vec<DmaBufPlane>,
+}
+
+/// Plane of a [`DmaBuf`]
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct DmaBufPlane {
+    pub fd: std::os::fd::OwnedFd,
+    /// Offset of the plane in the buffer, in bytes
+    pub offset: u32,
+    /// Stride of the plane, in bytes
+    pub stride: u32,
+}
+
+/// Frame captured in GPU memory
+pub trait GpuSurface: fmt::Debug + Send + Sync {
+    fn width(&self) -> NonZeroU16;
+
+    fn height(&self) -> NonZeroU16;
+
+    /// Handle to pass to the encoder
+    fn handle(&self) -> GpuSurfaceHandle<'_>;
+
+    /// Copies the frame to system memory
+    ///
+    /// The position of the returned bitmap is ignored. This method may block while the GPU
+    /// completes the transfer: it is not called from the async runtime threads.
+    fn read_back(&self) -> Result<BitmapUpdate>;
+}
+
+/// GPU Frame Display Update
+///
+/// The frame is encoded from the GPU when possible, or read back and sent as a bitmap update
+/// otherwise.
+#[derive(Debug, Clone)]
+pub struct GpuFrameUpdate {
+    pub x: u16,
+    pub y: u16,
+    pub surface: Arc<dyn GpuSurface>,
+}
+
+impl GpuFrameUpdate {
+    /// Copies the frame to system memory, at the position of the update
+    ///
+    /// This may block, see [`GpuSurface::read_back`].
+    pub fn read_back(&self) -> Result<BitmapUpdate> {
+        let bitmap = self.surface.read_back()?;
+
+        Ok(BitmapUpdate {
+            x: self.x,
+            y: self.y,
+            ..bitmap
+        })
+    }
+}
+
+/// H.264 encoder consuming captured frames
+///
+/// Encoders working from system memory only implement [`encode_bitmap`](Self::encode_bitmap).
+/// Hardware encoders also implement [`encode_surface`](Self::encode_surface) for the kinds of
+/// surfaces they can import.
+pub trait H264Encoder: Send {
+    /// Encodes a frame from system memory, returns the bitstream in Annex B format
+    fn encode_bitmap(&mut self, bitmap: &BitmapUpdate) -> Result<Vec<u8>>;
+
+    /// Encodes a frame directly from GPU memory, returns the bitstream in Annex B format
+    ///
+    /// Returns `Ok(None)` when the handle of the surface can't be imported, in which case the frame
+    /// is read back and passed to [`encode_bitmap`](Self::encode_bitmap). The default
+    /// implementation doesn't import any surface.
+    fn encode_surface(&mut self, _surface: &dyn GpuSurface) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Encodes the next frame as an IDR frame
+    ///
+    /// Called when the decoder of the client can't rely on the previous frames, e.g. after switching
+    /// encoders. The default implementation does nothing, which is correct for encoders starting with
+    /// an IDR frame and never losing their reference frames.
+    fn request_keyframe(&mut self) {}
+}
+
+/// Encoded frame returned by [`encode_gpu_frame`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedGpuFrame {
+    /// H.264 bitstream in Annex B format
+    pub data: Vec<u8>,
+    /// Whether the frame was encoded without a copy to system memory
+    pub zero_copy: bool,
+}
+
+/// Encodes a GPU frame, falling back to a read back when the encoder can't import the surface
+///
+/// This may block, see [`GpuSurface::read_back`].
+pub fn encode_gpu_frame(encoder: &mut dyn H264Encoder, frame: &GpuFrameUpdate) -> Result<EncodedGpuFrame> {
+    if let Some(data) = encoder.encode_surface(frame.surface.as_ref())? {
+        return Ok(EncodedGpuFrame { data, zero_copy: true });
+    }
+
+    let bitmap = frame.read_back()?;
+    let data = encoder.encode_bitmap(&bitmap)?;
+
+    Ok(EncodedGpuFrame { data, zero_copy: false })
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroUsize;
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::PixelFormat;
+
+    #[derive(Debug)]
+    struct VaFrame;
+
+    impl GpuSurface for VaFrame {
+        fn width(&self) -> NonZeroU16 {
+            NonZeroU16::new(2).unwrap()
+        }
+
+        fn height(&self) -> NonZeroU16 {
+            NonZeroU16::new(1).unwrap()
+        }
+
+        fn handle(&self) -> GpuSurfaceHandle<'_> {
+            GpuSurfaceHandle::VaSurface { surface_id: 7 }
+        }
+
+        fn read_back(&self) -> Result<BitmapUpdate> {
+            Ok(BitmapUpdate {
+                x: 0,
+                y: 0,
+                width: self.width(),
+                height: self.height(),
+                format: PixelFormat::BgrX32,
+                data: Bytes::from_static(&[1; 8]),
+                stride: NonZeroUsize::new(8).unwrap(),
+            })
+        }
+    }
+
+    struct Encoder {
+        va: bool,
+    }
+
+    impl H264Encoder for Encoder {
+        fn encode_bitmap(&mut self, bitmap: &BitmapUpdate) -> Result<Vec<u8>> {
+            Ok(bitmap.data.to_vec())
+        }
+
+        fn encode_surface(&mut self, surface: &dyn GpuSurface) -> Result<Option<Vec<u8>>> {
+            match surface.handle() {
+                GpuSurfaceHandle::VaSurface { surface_id } if self.va => Ok(Some(vec![surface_id.to_le_bytes()[0]])),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    fn frame() -> GpuFrameUpdate {
+        GpuFrameUpdate {
+            x: 3,
+            y: 4,
+            surface: Arc::new(VaFrame),
+        }
+    }
+
+    #[test]
+    fn read_back_keeps_position() {
+        let bitmap = frame().read_back().unwrap();
+        assert_eq!((bitmap.x, bitmap.y), (3, 4));
+        assert_eq!(bitmap.width.get(), 2);
+    }
+
+    #[test]
+    fn encode_from_surface() {
+        let encoded = encode_gpu_frame(&mut Encoder { va: true }, &frame()).unwrap();
+        assert_eq!(
+            encoded,
+            EncodedGpuFrame {
+                data: vec![7],
+                zero_copy: true
+            }
+        );
+    }
+
+    #[test]
+    fn encode_falls_back_to_read_back() {
+        let encoded = encode_gpu_frame(&mut Encoder { va: false }, &frame()).unwrap();
+        assert_eq!(
+            encoded,
+            EncodedGpuFrame {
+                data: vec![1; 8],
+                zero_copy: false
+            }
+        );
+    }
+}
@@ -6,10 +6,14 @@ use ironrdp_pdu::input::mouse_x::PointerXFlags;
 use ironrdp_pdu::input::sync::SyncToggleFlags;
 use ironrdp_pdu::input::{scan_code, unicode, MousePdu, MouseRelPdu, MouseXPdu};
 
+use crate::{ClientKeyboard, ConnectionContext, ServerChannels, SessionAccess, SessionPolicy};
+
 /// Keyboard Event
 ///
 /// Describes a keyboard event received from the client
 ///
+/// The Unicode events carry UTF-16 code units, see [`UnicodeKeyDecoder`](crate::UnicodeKeyDecoder) to inject them.
+///
 #[derive(Debug)]
 pub enum KeyboardEvent {
     Pressed { code: u8, extended: bool },
@@ -73,6 +77,55 @@ pub enum MouseEvent {
 pub trait RdpServerInputHandler: Send {
     fn keyboard(&mut self, event: KeyboardEvent);
     fn mouse(&mut self, event: MouseEvent);
+
+    /// Called with the keyboard of the client when it connects, before any keyboard event
+    ///
+    /// Scancodes are positions on the keyboard of the client: the injector should switch to the
+    /// same layout (see [`ClientKeyboard::xkb_layout`]) to produce the characters the user expects.
+    fn keyboard_layout(&mut self, _keyboard: &ClientKeyboard) {}
+
+    /// Called when a client connects, before the channels are built
+    ///
+    /// Returns the access of the client to the session, kept for the whole connection. Clients have full
+    /// access by default.
+    fn session_access(&mut self, _ctx: &ConnectionContext) -> SessionAccess {
+        SessionAccess::Full
+    }
+
+    /// Called when a client connects, after [`Self::session_access`] and before the channels are built
+    ///
+    /// Returns the optional channels offered to the client, e.g. to disable the clipboard for some users. Only
+    /// the channels enabled for the server are offered, see [`ConnectionContext::channels`].
+    fn channels(&mut self, ctx: &ConnectionContext) -> ServerChannels {
+        ctx.channels()
+    }
+
+    /// Called when a client connects, after [`Self::channels`] and before the channels are built
+    ///
+    /// Returns the restrictions enforced on the client by the server, for the whole connection, the blocked actions
+    /// being reported as [`AuditEvent`](crate::AuditEvent)s. Nothing is restricted by default.
+    fn session_policy(&mut self, _ctx: &ConnectionContext) -> SessionPolicy {
+        SessionPolicy::ALLOW_ALL
+    }
+
+    /// Called when the client of the session disconnects
+    ///
+    /// The server runs one session at a time: the callbacks received since [`Self::session_access`] are about the
+    /// session of `ctx`, see [`SessionRegistry`](crate::SessionRegistry).
+    fn session_ended(&mut self, _ctx: &ConnectionContext) {}
+
+    /// Called when the client requests control of the session, after the connection sequence
+    ///
+    /// Returns whether control is granted. Input from a client without control is dropped,
+    /// which is useful for view-only shadowing. Control is granted by default.
+    ///
+    /// Not called for a view-only client (see [`SessionAccess::ViewOnly`]), which is always denied control.
+    fn request_control(&mut self) -> bool {
+        true
+    }
+
+    /// Called when the client gives up control of the session
+    fn control_detached(&mut self) {}
 }
 
 impl From<(u8, fast_path::KeyboardFlags)> for KeyboardEvent {
@@ -237,6 +290,25 @@ impl From<MouseRelPdu> for MouseEvent {
     }
 }
 
+/// Converts a relative mouse event, which can move the pointer while pressing or releasing a button
+pub(crate) fn rel_mouse_events(value: MouseRelPdu) -> impl Iterator<Item = MouseEvent> {
+    let buttons = PointerRelFlags::BUTTON1
+        | PointerRelFlags::BUTTON2
+        | PointerRelFlags::BUTTON3
+        | PointerRelFlags::XBUTTON1
+        | PointerRelFlags::XBUTTON2;
+
+    // The movement comes first, the button event being the conversion of the whole event
+    let movement = (value.flags.contains(PointerRelFlags::MOVE) && value.flags.intersects(buttons)).then_some(
+        MouseEvent::RelMove {
+            x: value.x_delta.into(),
+            y: value.y_delta.into(),
+        },
+    );
+
+    movement.into_iter().chain(core::iter::once(value.into()))
+}
+
 impl From<ainput::MousePdu> for MouseEvent {
     fn from(value: ainput::MousePdu) -> Self {
         use ainput::MouseEventFlags;
@@ -274,3 +346,32 @@ impl From<ainput::MousePdu> for MouseEvent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rel_mouse_button_with_movement() {
+        let events: Vec<_> = rel_mouse_events(MouseRelPdu {
+            flags: PointerRelFlags::MOVE | PointerRelFlags::DOWN | PointerRelFlags::BUTTON1,
+            x_delta: -3,
+            y_delta: 7,
+        })
+        .collect();
+
+        assert!(matches!(
+            events[..],
+            [MouseEvent::RelMove { x: -3, y: 7 }, MouseEvent::LeftPressed]
+        ));
+
+        let events: Vec<_> = rel_mouse_events(MouseRelPdu {
+            flags: PointerRelFlags::MOVE,
+            x_delta: 5,
+            y_delta: 0,
+        })
+        .collect();
+
+        assert!(matches!(events[..], [MouseEvent::RelMove { x: 5, y: 0 }]));
+    }
+}
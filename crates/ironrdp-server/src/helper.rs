@@ -5,16 +5,100 @@ use std::sync::Arc;
 
 use anyhow::Context as _;
 use rustls_pemfile::{certs, pkcs8_private_keys};
+use tokio_rustls::rustls::crypto::CryptoProvider;
 use tokio_rustls::rustls::pki_types::pem::PemObject as _;
 use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{CipherSuite, SupportedProtocolVersion};
 use tokio_rustls::{rustls, TlsAcceptor};
 
 pub struct TlsIdentityCtx {
     pub certs: Vec<CertificateDer<'static>>,
     pub priv_key: PrivateKeyDer<'static>,
+    /// Subject public key of the certificate, bound by CredSSP whatever the TLS version
     pub pub_key: Vec<u8>,
 }
 
+/// TLS protocol version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+
+/// Parameters of the TLS handshakes accepted by [`TlsIdentityCtx::make_acceptor_with_config`]
+///
+/// The default configuration accepts TLS 1.2 and TLS 1.3, the cipher suites of the crypto provider and no ALPN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsAcceptorConfig {
+    /// Oldest version accepted
+    pub min_version: TlsVersion,
+    /// Newest version accepted
+    pub max_version: TlsVersion,
+    /// Cipher suites accepted, in order of preference
+    ///
+    /// When empty, all the cipher suites of the crypto provider are accepted.
+    pub cipher_suites: Vec<CipherSuite>,
+    /// ALPN protocols accepted, in order of preference
+    pub alpn_protocols: Vec<Vec<u8>>,
+    /// DER-encoded OCSP response stapled to the certificate, none when empty
+    ///
+    /// The response expires: a new acceptor is to be made with a fresh response before then.
+    pub ocsp_response: Vec<u8>,
+}
+
+impl Default for TlsAcceptorConfig {
+    fn default() -> Self {
+        Self {
+            min_version: TlsVersion::Tls12,
+            max_version: TlsVersion::Tls13,
+            cipher_suites: Vec::new(),
+            alpn_protocols: Vec::new(),
+            ocsp_response: Vec::new(),
+        }
+    }
+}
+
+impl TlsAcceptorConfig {
+    fn protocol_versions(&self) -> anyhow::Result<Vec<&'static SupportedProtocolVersion>> {
+        let versions: Vec<_> = [
+            (TlsVersion::Tls12, &rustls::version::TLS12),
+            (TlsVersion::Tls13, &rustls::version::TLS13),
+        ]
+        .into_iter()
+        .filter(|(version, _)| (self.min_version..=self.max_version).contains(version))
+        .map(|(_, supported)| supported)
+        .collect();
+
+        anyhow::ensure!(
+            !versions.is_empty(),
+            "no TLS version between the minimum and the maximum"
+        );
+
+        Ok(versions)
+    }
+
+    fn crypto_provider(&self, provider: &CryptoProvider) -> anyhow::Result<Arc<CryptoProvider>> {
+        let mut provider = provider.clone();
+
+        if !self.cipher_suites.is_empty() {
+            provider.cipher_suites = self
+                .cipher_suites
+                .iter()
+                .map(|id| {
+                    provider
+                        .cipher_suites
+                        .iter()
+                        .find(|suite| suite.suite() == *id)
+                        .copied()
+                        .with_context(|| format!("unsupported cipher suite {id:?}"))
+                })
+                .collect::<anyhow::Result<_>>()?;
+        }
+
+        Ok(Arc::new(provider))
+    }
+}
+
 impl TlsIdentityCtx {
     /// A constructor to create a `TlsIdentityCtx` from the given certificate and key paths.
     ///
@@ -63,14 +147,166 @@ impl TlsIdentityCtx {
     }
 
     pub fn make_acceptor(&self) -> anyhow::Result<TlsAcceptor> {
-        let mut server_config = rustls::ServerConfig::builder()
+        self.make_acceptor_with_config(&TlsAcceptorConfig::default())
+    }
+
+    /// Creates an acceptor restricted to the given versions and cipher suites, negotiating ALPN and stapling OCSP
+    pub fn make_acceptor_with_config(&self, config: &TlsAcceptorConfig) -> anyhow::Result<TlsAcceptor> {
+        Ok(TlsAcceptor::from(Arc::new(self.server_config(config)?)))
+    }
+
+    fn server_config(&self, config: &TlsAcceptorConfig) -> anyhow::Result<rustls::ServerConfig> {
+        let default_provider = Arc::clone(rustls::ServerConfig::builder().crypto_provider());
+
+        let mut server_config = rustls::ServerConfig::builder_with_provider(config.crypto_provider(&default_provider)?)
+            .with_protocol_versions(&config.protocol_versions()?)
+            .context("no cipher suite for the TLS versions")?
             .with_no_client_auth()
-            .with_single_cert(self.certs.clone(), self.priv_key.clone_key())
+            .with_single_cert_with_ocsp(
+                self.certs.clone(),
+                self.priv_key.clone_key(),
+                config.ocsp_response.clone(),
+            )
             .context("bad certificate/key")?;
 
         // This adds support for the SSLKEYLOGFILE env variable (https://wiki.wireshark.org/TLS#using-the-pre-master-secret)
         server_config.key_log = Arc::new(rustls::KeyLogFile::new());
 
-        Ok(TlsAcceptor::from(Arc::new(server_config)))
+        // CredSSP doesn't support TLS resumption, the TLS 1.3 session tickets would never be used.
+        server_config.send_tls13_tickets = 0;
+
+        server_config.alpn_protocols.clone_from(&config.alpn_protocols);
+
+        Ok(server_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::duplex;
+    use tokio_rustls::rustls::pki_types::{PrivatePkcs8KeyDer, ServerName};
+    use tokio_rustls::rustls::ProtocolVersion;
+    use tokio_rustls::TlsConnector;
+
+    use super::*;
+
+    fn install_crypto_provider() {
+        // Another crate of the workspace may enable a second provider, none being the default then.
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    }
+
+    fn identity() -> TlsIdentityCtx {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+
+        TlsIdentityCtx {
+            certs: vec![certified.cert.der().clone()],
+            priv_key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der())),
+            pub_key: certified.signing_key.public_key_raw().to_vec(),
+        }
+    }
+
+    fn versions(config: &TlsAcceptorConfig) -> anyhow::Result<Vec<ProtocolVersion>> {
+        Ok(config
+            .protocol_versions()?
+            .into_iter()
+            .map(|supported| supported.version)
+            .collect())
+    }
+
+    #[test]
+    fn versions_between_min_and_max() {
+        let tls12 = TlsAcceptorConfig {
+            max_version: TlsVersion::Tls12,
+            ..TlsAcceptorConfig::default()
+        };
+        let tls13 = TlsAcceptorConfig {
+            min_version: TlsVersion::Tls13,
+            ..TlsAcceptorConfig::default()
+        };
+        let none = TlsAcceptorConfig {
+            min_version: TlsVersion::Tls13,
+            max_version: TlsVersion::Tls12,
+            ..TlsAcceptorConfig::default()
+        };
+
+        assert_eq!(
+            versions(&TlsAcceptorConfig::default()).unwrap(),
+            [ProtocolVersion::TLSv1_2, ProtocolVersion::TLSv1_3]
+        );
+        assert_eq!(versions(&tls12).unwrap(), [ProtocolVersion::TLSv1_2]);
+        assert_eq!(versions(&tls13).unwrap(), [ProtocolVersion::TLSv1_3]);
+        assert!(versions(&none).is_err());
+    }
+
+    #[test]
+    fn unknown_cipher_suite_is_rejected() {
+        install_crypto_provider();
+
+        let config = TlsAcceptorConfig {
+            cipher_suites: vec![
+                CipherSuite::TLS13_AES_128_GCM_SHA256,
+                CipherSuite::TLS_RSA_WITH_RC4_128_SHA,
+            ],
+            ..TlsAcceptorConfig::default()
+        };
+
+        assert!(identity().make_acceptor_with_config(&config).is_err());
+    }
+
+    #[test]
+    fn server_config_accepts_alpn_protocols() {
+        install_crypto_provider();
+
+        let config = TlsAcceptorConfig {
+            cipher_suites: vec![CipherSuite::TLS13_AES_256_GCM_SHA384],
+            alpn_protocols: vec![b"x-rdp".to_vec(), b"http/1.1".to_vec()],
+            ..TlsAcceptorConfig::default()
+        };
+
+        let server_config = identity().server_config(&config).unwrap();
+        let cipher_suites: Vec<_> = server_config
+            .crypto_provider()
+            .cipher_suites
+            .iter()
+            .map(|suite| suite.suite())
+            .collect();
+
+        assert_eq!(server_config.alpn_protocols, [&b"x-rdp"[..], b"http/1.1"]);
+        assert_eq!(cipher_suites, [CipherSuite::TLS13_AES_256_GCM_SHA384]);
+        assert_eq!(server_config.send_tls13_tickets, 0);
+    }
+
+    #[tokio::test]
+    async fn acceptor_negotiates_the_configured_parameters() {
+        install_crypto_provider();
+
+        let identity = identity();
+        let config = TlsAcceptorConfig {
+            max_version: TlsVersion::Tls12,
+            alpn_protocols: vec![b"x-rdp".to_vec(), b"http/1.1".to_vec()],
+            ..TlsAcceptorConfig::default()
+        };
+        let acceptor = identity.make_acceptor_with_config(&config).unwrap();
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(identity.certs[0].clone()).unwrap();
+        let mut client_config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        client_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        let (client, server) = duplex(16 * 1024);
+        let server = tokio::spawn(async move { acceptor.accept(server).await.unwrap() });
+
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let _client_stream = TlsConnector::from(Arc::new(client_config))
+            .connect(server_name, client)
+            .await
+            .unwrap();
+        let server_stream = server.await.unwrap();
+
+        let (_, connection) = server_stream.get_ref();
+        assert_eq!(connection.protocol_version(), Some(ProtocolVersion::TLSv1_2));
+        assert_eq!(connection.alpn_protocol(), Some(&b"http/1.1"[..]));
     }
 }
@@ -0,0 +1,193 @@
+use ironrdp_pdu::gcc::{ClientCoreData, KeyboardType};
+
+/// Keyboard of the client, as advertised in its core data
+///
+/// The scancodes sent by the client are positions on its physical keyboard: they must be interpreted
+/// with the same layout on the server side for the user to get the characters printed on the keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientKeyboard {
+    /// Active input locale identifier (KLID), e.g. `0x0000_0409` for US English
+    pub layout: u32,
+    pub keyboard_type: KeyboardType,
+    /// Original equipment manufacturer-dependent value
+    pub subtype: u32,
+    pub functional_keys_count: u32,
+    /// Input method editor file name, empty when no IME is used
+    pub ime_file_name: String,
+}
+
+impl From<&ClientCoreData> for ClientKeyboard {
+    fn from(core: &ClientCoreData) -> Self {
+        Self {
+            layout: core.keyboard_layout,
+            keyboard_type: core.keyboard_type,
+            subtype: core.keyboard_subtype,
+            functional_keys_count: core.keyboard_functional_keys_count,
+            ime_file_name: core.ime_file_name.clone(),
+        }
+    }
+}
+
+impl ClientKeyboard {
+    /// Language identifier (LANGID) of the layout, e.g. `0x0409` for US English
+    pub fn language_id(&self) -> u16 {
+        let [low, high, _, _] = self.layout.to_le_bytes();
+        u16::from_le_bytes([low, high])
+    }
+
+    /// XKB layout matching the layout of the client
+    ///
+    /// Layouts without an exact match fall back to the primary layout of their language. The layout
+    /// `0` is sent by clients letting the server choose, in which case `None` is returned.
+    pub fn xkb_layout(&self) -> Option<XkbLayout> {
+        let find = |layout| {
+            XKB_LAYOUTS
+                .iter()
+                .find(|(klid, _, _)| *klid == layout)
+                .map(|&(_, layout, variant)| XkbLayout { layout, variant })
+        };
+
+        find(self.layout).or_else(|| find(u32::from(self.language_id())))
+    }
+
+    pub fn uses_ime(&self) -> bool {
+        !self.ime_file_name.is_empty()
+    }
+}
+
+/// Layout and variant of the X keyboard extension, as accepted by `setxkbmap` or `xkb_keymap_new_from_names`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct XkbLayout {
+    pub layout: &'static str,
+    pub variant: Option<&'static str>,
+}
+
+/// Keyboard layout identifiers (KLID) and their XKB counterpart
+const XKB_LAYOUTS: &[(u32, &str, Option<&str>)] = &[
+    (0x0000_0405, "cz", None),
+    (0x0000_0406, "dk", None),
+    (0x0000_0407, "de", None),
+    (0x0000_0408, "gr", None),
+    (0x0000_0409, "us", None),
+    (0x0001_0409, "us", Some("dvorak")),
+    (0x0002_0409, "us", Some("intl")),
+    (0x0000_040A, "es", None),
+    (0x0000_040B, "fi", None),
+    (0x0000_040C, "fr", None),
+    (0x0000_040E, "hu", None),
+    (0x0000_0410, "it", None),
+    (0x0000_0411, "jp", None),
+    (0x0000_0412, "kr", None),
+    (0x0000_0413, "nl", None),
+    (0x0000_0414, "no", None),
+    (0x0000_0415, "pl", None),
+    (0x0000_0416, "br", None),
+    (0x0000_0419, "ru", None),
+    (0x0000_041D, "se", None),
+    (0x0000_041F, "tr", None),
+    (0x0000_0422, "ua", None),
+    (0x0000_0807, "ch", None),
+    (0x0000_0809, "gb", None),
+    (0x0000_080A, "latam", None),
+    (0x0000_080C, "be", None),
+    (0x0000_0816, "pt", None),
+    (0x0000_0C0C, "ca", Some("fr-legacy")),
+    (0x0000_1009, "ca", None),
+    (0x0000_100C, "ch", Some("fr")),
+];
+
+/// Converts a scancode received from the client to a Linux input event code
+///
+/// The scancode is a set 1 make code, `extended` being set for the codes prefixed with `0xE0`.
+/// Once converted, the code is interpreted by the keymap of the host, which must match the layout of
+/// the client (see [`ClientKeyboard::xkb_layout`]). X11 key codes are offset by 8 from these codes.
+pub fn scancode_to_evdev(code: u8, extended: bool) -> Option<u16> {
+    let evdev = match (code, extended) {
+        // The base keys are numbered the same way by Linux.
+        (0x01..=0x53 | 0x56..=0x58, false) => u16::from(code),
+        // Japanese keys
+        (0x70, false) => 93,  // KEY_KATAKANAHIRAGANA
+        (0x73, false) => 89,  // KEY_RO
+        (0x79, false) => 92,  // KEY_HENKAN
+        (0x7B, false) => 94,  // KEY_MUHENKAN
+        (0x7D, false) => 124, // KEY_YEN
+        (0x7E, false) => 95,  // KEY_KPJPCOMMA
+        // Korean keys
+        (0x71, false) => 123, // KEY_HANJA
+        (0x72, false) => 122, // KEY_HANGEUL
+        // Function keys beyond F12
+        (0x64..=0x6E, false) => u16::from(code) + 83, // KEY_F13..=KEY_F23
+        (0x76, false) => 194,                         // KEY_F24
+        // Extended keys
+        (0x1C, true) => 96,  // KEY_KPENTER
+        (0x1D, true) => 97,  // KEY_RIGHTCTRL
+        (0x35, true) => 98,  // KEY_KPSLASH
+        (0x37, true) => 99,  // KEY_SYSRQ
+        (0x38, true) => 100, // KEY_RIGHTALT
+        (0x47, true) => 102, // KEY_HOME
+        (0x48, true) => 103, // KEY_UP
+        (0x49, true) => 104, // KEY_PAGEUP
+        (0x4B, true) => 105, // KEY_LEFT
+        (0x4D, true) => 106, // KEY_RIGHT
+        (0x4F, true) => 107, // KEY_END
+        (0x50, true) => 108, // KEY_DOWN
+        (0x51, true) => 109, // KEY_PAGEDOWN
+        (0x52, true) => 110, // KEY_INSERT
+        (0x53, true) => 111, // KEY_DELETE
+        (0x5B, true) => 125, // KEY_LEFTMETA
+        (0x5C, true) => 126, // KEY_RIGHTMETA
+        (0x5D, true) => 127, // KEY_COMPOSE
+        (0x20, true) => 113, // KEY_MUTE
+        (0x2E, true) => 114, // KEY_VOLUMEDOWN
+        (0x30, true) => 115, // KEY_VOLUMEUP
+        (0x5E, true) => 116, // KEY_POWER
+        (0x5F, true) => 142, // KEY_SLEEP
+        (0x10, true) => 165, // KEY_PREVIOUSSONG
+        (0x19, true) => 163, // KEY_NEXTSONG
+        (0x22, true) => 164, // KEY_PLAYPAUSE
+        (0x24, true) => 166, // KEY_STOPCD
+        _ => return None,
+    };
+
+    Some(evdev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyboard(layout: u32) -> ClientKeyboard {
+        ClientKeyboard {
+            layout,
+            keyboard_type: KeyboardType::IbmEnhanced,
+            subtype: 0,
+            functional_keys_count: 12,
+            ime_file_name: String::new(),
+        }
+    }
+
+    #[test]
+    fn xkb_layout() {
+        assert_eq!(
+            keyboard(0x0001_0409).xkb_layout(),
+            Some(XkbLayout {
+                layout: "us",
+                variant: Some("dvorak")
+            })
+        );
+        // German (IBM) falls back to German.
+        assert_eq!(keyboard(0x0001_0407).xkb_layout().map(|xkb| xkb.layout), Some("de"));
+        assert_eq!(keyboard(0).xkb_layout(), None);
+        assert_eq!(keyboard(0x0000_040C).language_id(), 0x040C);
+    }
+
+    #[test]
+    fn scancodes() {
+        assert_eq!(scancode_to_evdev(0x1E, false), Some(30)); // KEY_A
+        assert_eq!(scancode_to_evdev(0x1D, false), Some(29)); // KEY_LEFTCTRL
+        assert_eq!(scancode_to_evdev(0x1D, true), Some(97)); // KEY_RIGHTCTRL
+        assert_eq!(scancode_to_evdev(0x64, false), Some(183)); // KEY_F13
+        assert_eq!(scancode_to_evdev(0x6E, false), Some(193)); // KEY_F23
+        assert_eq!(scancode_to_evdev(0x54, false), None);
+    }
+}
@@ -0,0 +1,314 @@
+use core::time::Duration;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+use ironrdp_pdu::geometry::{InclusiveRectangle, Rectangle as _};
+use tracing::warn;
+
+use crate::DisplayUpdate;
+
+/// Enforces an input-to-frame latency budget in a capture and encode loop
+///
+/// The input handler records each input event with an [`InputRecorder`], along with the region of the screen
+/// it is likely to damage: the area around the cursor for pointer events, the focused window for keyboard
+/// events. For each frame, the loop:
+///
+/// 1. calls [`start_frame()`](Self::start_frame) right after the capture, the frame answering the inputs
+///    recorded so far,
+/// 2. sorts the updates of the frame with [`prioritize()`](Self::prioritize), so that the damage caused by
+///    these inputs is encoded first. When the budget is almost exhausted ([`remaining()`](Self::remaining)),
+///    the other updates can be deferred to the next frame,
+/// 3. calls [`frame_queued()`](Self::frame_queued) once the updates are queued, which measures the latency
+///    of the oldest input and reports a violation of the budget in the [metrics](Self::metrics).
+///
+/// # Example
+///
+/// ```ignore
+/// let mut budget = LatencyBudget::new(Duration::from_millis(50));
+/// let recorder = budget.recorder(); // given to the input handler
+///
+/// loop {
+///     pacer.tick().await;
+///     let (frame, cursor) = capture()?;
+///     budget.start_frame();
+///     let mut updates = updater.updates(&frame, Some(cursor));
+///     budget.prioritize(&mut updates);
+///     send(updates).await?;
+///     budget.frame_queued();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LatencyBudget {
+    budget: Duration,
+    cursor_margin: u16,
+    recorded: Arc<Mutex<Vec<PendingInput>>>,
+    /// Inputs answered by the current frame
+    pending: Vec<PendingInput>,
+    metrics: LatencyMetrics,
+}
+
+/// Latency measurements of a [`LatencyBudget`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyMetrics {
+    /// Number of frames answering at least one input
+    pub frames: u64,
+    /// Number of frames queued after the budget
+    pub violations: u64,
+    /// Latency of the oldest input of the last frame answering inputs
+    pub last_latency: Option<Duration>,
+    pub max_latency: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PendingInput {
+    time: Instant,
+    region: InputRegion,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum InputRegion {
+    Unknown,
+    Pointer { x: u16, y: u16 },
+    Rect(InclusiveRectangle),
+}
+
+impl LatencyBudget {
+    /// Default distance around the cursor in which the damage is prioritized after a pointer event
+    pub const DEFAULT_CURSOR_MARGIN: u16 = 64;
+
+    /// Creates a budget of `budget` from an input event to the queuing of the frame answering it
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            cursor_margin: Self::DEFAULT_CURSOR_MARGIN,
+            recorded: Arc::new(Mutex::new(Vec::new())),
+            pending: Vec::new(),
+            metrics: LatencyMetrics::default(),
+        }
+    }
+
+    /// Sets the distance around the cursor in which the damage is prioritized after a pointer event
+    #[must_use]
+    pub fn with_cursor_margin(mut self, cursor_margin: u16) -> Self {
+        self.cursor_margin = cursor_margin;
+        self
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn metrics(&self) -> LatencyMetrics {
+        self.metrics
+    }
+
+    /// Returns a handle recording the input events from another task
+    pub fn recorder(&self) -> InputRecorder {
+        InputRecorder(Arc::clone(&self.recorded))
+    }
+
+    /// Starts a frame, which answers the inputs recorded until now
+    ///
+    /// Call this right after the capture: later inputs are answered by the next frame.
+    pub fn start_frame(&mut self) {
+        self.pending.append(&mut lock_inputs(&self.recorded));
+    }
+
+    /// Time left before the budget of the oldest input answered by the current frame is exceeded
+    ///
+    /// Returns `None` when the frame answers no input, and [`Duration::ZERO`] once the budget is exceeded.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.remaining_at(Instant::now())
+    }
+
+    /// Moves the updates damaged by the inputs of the current frame first, and returns their number
+    ///
+    /// The order of the updates is kept otherwise. Pointer position updates are urgent after a pointer event.
+    pub fn prioritize(&self, updates: &mut [DisplayUpdate]) -> usize {
+        updates.sort_by_key(|update| !self.is_urgent(update));
+        updates.iter().take_while(|update| self.is_urgent(update)).count()
+    }
+
+    /// Reports that the updates of the current frame are queued, and returns the latency of its oldest input
+    pub fn frame_queued(&mut self) -> Option<Duration> {
+        self.frame_queued_at(Instant::now())
+    }
+
+    fn remaining_at(&self, now: Instant) -> Option<Duration> {
+        let oldest = self.pending.iter().map(|input| input.time).min()?;
+        Some(self.budget.saturating_sub(now.saturating_duration_since(oldest)))
+    }
+
+    fn frame_queued_at(&mut self, now: Instant) -> Option<Duration> {
+        let oldest = self.pending.drain(..).map(|input| input.time).min()?;
+        let latency = now.saturating_duration_since(oldest);
+
+        self.metrics.frames += 1;
+        self.metrics.last_latency = Some(latency);
+        self.metrics.max_latency = self.metrics.max_latency.max(latency);
+
+        if latency > self.budget {
+            self.metrics.violations += 1;
+            warn!(?latency, budget = ?self.budget, "Input-to-frame latency budget exceeded");
+        }
+
+        Some(latency)
+    }
+
+    fn is_urgent(&self, update: &DisplayUpdate) -> bool {
+        match update {
+            DisplayUpdate::Bitmap(bitmap) => {
+                let rect = InclusiveRectangle {
+                    left: bitmap.x,
+                    top: bitmap.y,
+                    right: bitmap.x.saturating_add(bitmap.width.get() - 1),
+                    bottom: bitmap.y.saturating_add(bitmap.height.get() - 1),
+                };
+
+                self.pending.iter().any(|input| self.is_damaged(&input.region, &rect))
+            }
+            DisplayUpdate::PointerPosition(_) => self
+                .pending
+                .iter()
+                .any(|input| matches!(input.region, InputRegion::Pointer { .. })),
+            _ => false,
+        }
+    }
+
+    fn is_damaged(&self, region: &InputRegion, rect: &InclusiveRectangle) -> bool {
+        match region {
+            InputRegion::Unknown => false,
+            InputRegion::Pointer { x, y } => {
+                x.saturating_add(self.cursor_margin) >= rect.left
+                    && x.saturating_sub(self.cursor_margin) <= rect.right
+                    && y.saturating_add(self.cursor_margin) >= rect.top
+                    && y.saturating_sub(self.cursor_margin) <= rect.bottom
+            }
+            InputRegion::Rect(region) => region.intersect(rect).is_some(),
+        }
+    }
+}
+
+/// Records the input events for a [`LatencyBudget`], see [`LatencyBudget::recorder()`]
+#[derive(Debug, Clone)]
+pub struct InputRecorder(Arc<Mutex<Vec<PendingInput>>>);
+
+impl InputRecorder {
+    /// Records a pointer event, the cursor being at `x`, `y` in desktop coordinates
+    pub fn pointer(&self, x: u16, y: u16) {
+        self.record(InputRegion::Pointer { x, y });
+    }
+
+    /// Records an input event damaging `region` in desktop coordinates, e.g. the focused window for a keyboard
+    /// event, or an unknown region
+    pub fn input(&self, region: Option<InclusiveRectangle>) {
+        self.record(region.map_or(InputRegion::Unknown, InputRegion::Rect));
+    }
+
+    fn record(&self, region: InputRegion) {
+        lock_inputs(&self.0).push(PendingInput {
+            time: Instant::now(),
+            region,
+        });
+    }
+}
+
+fn lock_inputs(inputs: &Mutex<Vec<PendingInput>>) -> MutexGuard<'_, Vec<PendingInput>> {
+    inputs.lock().expect("input recorder mutex poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::{NonZeroU16, NonZeroUsize};
+
+    use ironrdp_pdu::pointer::PointerPositionAttribute;
+
+    use super::*;
+    use crate::{BitmapUpdate, PixelFormat};
+
+    const BUDGET: Duration = Duration::from_millis(50);
+
+    fn bitmap(x: u16, y: u16) -> DisplayUpdate {
+        DisplayUpdate::Bitmap(BitmapUpdate {
+            x,
+            y,
+            width: NonZeroU16::new(64).unwrap(),
+            height: NonZeroU16::new(64).unwrap(),
+            format: PixelFormat::BgrX32,
+            data: vec![0; 64 * 64 * 4].into(),
+            stride: NonZeroUsize::new(64 * 4).unwrap(),
+        })
+    }
+
+    fn position(update: &DisplayUpdate) -> Option<(u16, u16)> {
+        match update {
+            DisplayUpdate::Bitmap(bitmap) => Some((bitmap.x, bitmap.y)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn prioritizes_input_damage() {
+        let mut budget = LatencyBudget::new(BUDGET).with_cursor_margin(16);
+        let recorder = budget.recorder();
+
+        recorder.pointer(600, 400);
+        recorder.input(Some(InclusiveRectangle {
+            left: 100,
+            top: 100,
+            right: 150,
+            bottom: 150,
+        }));
+        budget.start_frame();
+
+        let mut updates = vec![
+            bitmap(0, 0),
+            bitmap(128, 128),
+            DisplayUpdate::PointerPosition(PointerPositionAttribute { x: 600, y: 400 }),
+            bitmap(256, 0),
+            bitmap(576, 384),
+        ];
+
+        assert_eq!(budget.prioritize(&mut updates), 3);
+        assert_eq!(position(&updates[0]), Some((128, 128)));
+        assert!(matches!(updates[1], DisplayUpdate::PointerPosition(_)));
+        assert_eq!(position(&updates[2]), Some((576, 384)));
+        assert_eq!(position(&updates[3]), Some((0, 0)));
+        assert_eq!(position(&updates[4]), Some((256, 0)));
+    }
+
+    #[test]
+    fn reports_violations() {
+        let mut budget = LatencyBudget::new(BUDGET);
+        let recorder = budget.recorder();
+
+        // No input, nothing to measure
+        budget.start_frame();
+        assert_eq!(budget.remaining(), None);
+        assert_eq!(budget.frame_queued(), None);
+
+        recorder.input(None);
+        budget.start_frame();
+        let input = budget.pending[0].time;
+
+        assert_eq!(budget.remaining_at(input + BUDGET / 2), Some(BUDGET / 2));
+        assert_eq!(budget.frame_queued_at(input + BUDGET / 2), Some(BUDGET / 2));
+
+        recorder.input(None);
+        budget.start_frame();
+        let input = budget.pending[0].time;
+
+        assert_eq!(budget.remaining_at(input + BUDGET * 2), Some(Duration::ZERO));
+        assert_eq!(budget.frame_queued_at(input + BUDGET * 2), Some(BUDGET * 2));
+
+        assert_eq!(
+            budget.metrics(),
+            LatencyMetrics {
+                frames: 2,
+                violations: 1,
+                last_latency: Some(BUDGET * 2),
+                max_latency: BUDGET * 2,
+            }
+        );
+    }
+}
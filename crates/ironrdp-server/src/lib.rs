@@ -6,28 +6,62 @@ pub use {tokio, tokio_rustls};
 
 mod macros;
 
+mod audio_input;
+mod autodetect;
 mod builder;
 mod capabilities;
 mod clipboard;
+mod context;
 mod display;
 mod encoder;
+mod frame_diff;
 #[cfg(feature = "egfx")]
 mod gfx;
+mod gpu;
 mod handler;
 #[cfg(feature = "helper")]
 mod helper;
+mod keyboard;
+mod latency;
+mod pacing;
+mod policy;
+mod rail;
+mod rdpdr;
+mod scheduling;
 mod server;
+mod session;
 mod sound;
+mod unicode_input;
+mod watchdog;
 
+pub use audio_input::*;
+pub use autodetect::*;
 pub use clipboard::*;
+pub use context::*;
 pub use display::*;
+pub use frame_diff::*;
 #[cfg(feature = "egfx")]
 pub use gfx::*;
+pub use gpu::*;
 pub use handler::*;
 #[cfg(feature = "helper")]
 pub use helper::*;
+pub use keyboard::*;
+pub use latency::*;
+pub use pacing::*;
+pub use policy::*;
+pub use rail::*;
+pub use rdpdr::*;
+pub use scheduling::*;
 pub use server::*;
+pub use session::*;
 pub use sound::*;
+pub use unicode_input::*;
+pub use watchdog::*;
+
+// Only used by the tests of the helper.
+#[cfg(all(test, not(feature = "helper")))]
+use rcgen as _;
 
 #[cfg(feature = "__bench")]
 pub mod bench {
@@ -0,0 +1,211 @@
+use core::time::Duration;
+use std::sync::Arc;
+use std::time::Instant;
+
+use tokio::sync::Notify;
+
+/// Paces a capture and encode loop, dropping to a heartbeat rate while the content doesn't change
+///
+/// The loop waits for each frame with [`tick()`](Self::tick), and reports whether the frame had any
+/// damage with [`frame()`](Self::frame), e.g. whether [`FrameDiffer::diff()`](crate::FrameDiffer::diff)
+/// found dirty rectangles. After the [idle delay](Self::with_idle_delay) without damage, frames are
+/// captured at the [heartbeat interval](Self::with_idle_interval) only. The first damaged frame, or a
+/// [`FramePacerWaker::wake()`], e.g. on user input, brings the loop back to the active rate immediately.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut pacer = FramePacer::new(Duration::from_millis(33));
+/// let waker = pacer.waker(); // given to the input handler
+///
+/// loop {
+///     pacer.tick().await;
+///     let frame = capture()?;
+///     let updates = differ.dirty_updates(&frame);
+///     pacer.frame(!updates.is_empty());
+///     send(updates).await?;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FramePacer {
+    active_interval: Duration,
+    idle_interval: Duration,
+    idle_delay: Duration,
+    last_damage: Instant,
+    next_frame: Instant,
+    waker: Arc<Notify>,
+}
+
+impl FramePacer {
+    /// Default interval between the frames while idle, one frame per second
+    pub const DEFAULT_IDLE_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Default time without damage before slowing down
+    pub const DEFAULT_IDLE_DELAY: Duration = Duration::from_secs(2);
+
+    /// Creates a pacer capturing a frame every `active_interval` while the content changes
+    pub fn new(active_interval: Duration) -> Self {
+        let now = Instant::now();
+
+        Self {
+            active_interval,
+            idle_interval: Self::DEFAULT_IDLE_INTERVAL,
+            idle_delay: Self::DEFAULT_IDLE_DELAY,
+            last_damage: now,
+            next_frame: now,
+            waker: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Sets the interval between the frames while idle
+    ///
+    /// An interval shorter than the active interval is ignored.
+    #[must_use]
+    pub fn with_idle_interval(mut self, idle_interval: Duration) -> Self {
+        self.idle_interval = idle_interval;
+        self
+    }
+
+    /// Sets the time without damage before slowing down
+    #[must_use]
+    pub fn with_idle_delay(mut self, idle_delay: Duration) -> Self {
+        self.idle_delay = idle_delay;
+        self
+    }
+
+    /// Returns a handle waking the pacer up from another task
+    pub fn waker(&self) -> FramePacerWaker {
+        FramePacerWaker(Arc::clone(&self.waker))
+    }
+
+    /// Whether no damage was reported for the idle delay
+    pub fn is_idle(&self) -> bool {
+        self.is_idle_at(Instant::now())
+    }
+
+    /// Current interval between the frames
+    pub fn interval(&self) -> Duration {
+        self.interval_at(Instant::now())
+    }
+
+    /// Reports whether the last frame had damage, and schedules the next frame
+    pub fn frame(&mut self, damaged: bool) {
+        self.frame_at(Instant::now(), damaged);
+    }
+
+    /// Waits for the next frame
+    ///
+    /// Returns immediately when woken up, the pacer being back to the active rate.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancellation safe.
+    pub async fn tick(&mut self) {
+        tokio::select! {
+            () = tokio::time::sleep_until(self.next_frame.into()) => {}
+            () = self.waker.notified() => self.wake_at(Instant::now()),
+        }
+    }
+
+    fn is_idle_at(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_damage) >= self.idle_delay
+    }
+
+    fn interval_at(&self, now: Instant) -> Duration {
+        if self.is_idle_at(now) {
+            self.idle_interval.max(self.active_interval)
+        } else {
+            self.active_interval
+        }
+    }
+
+    fn frame_at(&mut self, now: Instant, damaged: bool) {
+        if damaged {
+            self.last_damage = now;
+        }
+
+        self.next_frame = now + self.interval_at(now);
+    }
+
+    fn wake_at(&mut self, now: Instant) {
+        self.last_damage = now;
+        self.next_frame = now;
+    }
+}
+
+/// Wakes a [`FramePacer`] up, e.g. on user input, see [`FramePacer::waker()`]
+#[derive(Debug, Clone)]
+pub struct FramePacerWaker(Arc<Notify>);
+
+impl FramePacerWaker {
+    /// Makes the pending or next [`FramePacer::tick()`] return immediately, and the pacer leave the idle rate
+    pub fn wake(&self) {
+        self.0.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACTIVE: Duration = Duration::from_millis(20);
+    const IDLE: Duration = Duration::from_millis(500);
+    const IDLE_DELAY: Duration = Duration::from_millis(100);
+
+    fn pacer() -> FramePacer {
+        FramePacer::new(ACTIVE)
+            .with_idle_interval(IDLE)
+            .with_idle_delay(IDLE_DELAY)
+    }
+
+    #[test]
+    fn slows_down_when_idle() {
+        let mut pacer = pacer();
+        let start = pacer.last_damage;
+
+        pacer.frame_at(start, true);
+        assert_eq!(pacer.next_frame, start + ACTIVE);
+
+        let now = start + IDLE_DELAY - ACTIVE;
+        pacer.frame_at(now, false);
+        assert!(!pacer.is_idle_at(now));
+        assert_eq!(pacer.next_frame, now + ACTIVE);
+
+        let now = start + IDLE_DELAY;
+        pacer.frame_at(now, false);
+        assert!(pacer.is_idle_at(now));
+        assert_eq!(pacer.next_frame, now + IDLE);
+    }
+
+    #[test]
+    fn ramps_up_on_damage() {
+        let mut pacer = pacer();
+        let now = pacer.last_damage + IDLE_DELAY * 3;
+        assert!(pacer.is_idle_at(now));
+
+        pacer.frame_at(now, true);
+        assert!(!pacer.is_idle_at(now));
+        assert_eq!(pacer.next_frame, now + ACTIVE);
+    }
+
+    #[test]
+    fn ramps_up_on_wake() {
+        let mut pacer = pacer();
+        let now = pacer.last_damage + IDLE_DELAY * 3;
+        pacer.frame_at(now, false);
+        assert_eq!(pacer.next_frame, now + IDLE);
+
+        pacer.wake_at(now);
+        assert_eq!(pacer.next_frame, now);
+        assert_eq!(pacer.interval_at(now), ACTIVE);
+    }
+
+    #[test]
+    fn idle_interval_is_not_shorter_than_active() {
+        let pacer = FramePacer::new(ACTIVE)
+            .with_idle_interval(Duration::from_millis(1))
+            .with_idle_delay(Duration::ZERO);
+
+        assert_eq!(pacer.interval(), ACTIVE);
+    }
+}
@@ -0,0 +1,593 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ironrdp_cliprdr::pdu::{FileContentsFlags, FileContentsRequest, FileContentsResponse, FormatDataResponse};
+use ironrdp_cliprdr::policy::{ClipboardDirection, CliprdrPolicy};
+use ironrdp_rdpdr::pdu::efs::{CreateDisposition, DesiredAccess};
+use ironrdp_rdpdr::server::DrivePolicy;
+use tracing::warn;
+
+use crate::{DriveRequest, KeyboardEvent, RedirectedDrive};
+
+/// Direction of a transfer between the client and the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferDirection {
+    /// E.g. the server pastes the clipboard of the client, or reads a file of a redirected drive
+    ClientToServer,
+    /// E.g. the client pastes the clipboard of the server, or the server writes a file of a redirected drive
+    ServerToClient,
+}
+
+/// Directions in which the data of a channel may be transferred
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TransferPolicy {
+    #[default]
+    Both,
+    ClientToServer,
+    ServerToClient,
+    Denied,
+}
+
+impl TransferPolicy {
+    pub fn allows(self, direction: TransferDirection) -> bool {
+        match self {
+            Self::Both => true,
+            Self::ClientToServer => direction == TransferDirection::ClientToServer,
+            Self::ServerToClient => direction == TransferDirection::ServerToClient,
+            Self::Denied => false,
+        }
+    }
+}
+
+/// Restrictions on the client of a session, see [`RdpServerInputHandler::session_policy`]
+///
+/// The policy is enforced by the clipboard and device redirection channels and the input handling of the server,
+/// whatever the backends built by the factories: the blocked actions are dropped or failed, and reported as
+/// [`AuditEvent`]s. Unlike [`ServerChannels`], the channels are still
+/// offered, e.g. to allow copying from the server but not to it.
+///
+/// [`RdpServerInputHandler::session_policy`]: crate::RdpServerInputHandler::session_policy
+/// [`ServerChannels`]: crate::ServerChannels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionPolicy {
+    /// Directions of the clipboard data, files included
+    pub clipboard: TransferPolicy,
+    /// Largest clipboard data in bytes, the files excepted
+    pub max_clipboard_size: Option<u64>,
+    /// Whether the keyboard input is accepted, the lock keys state is synchronized anyway
+    pub keyboard: bool,
+    /// Directions of the redirected drive data, the drives are rejected when denied
+    pub drive: TransferPolicy,
+    /// Largest file in bytes, copied through the clipboard or read from or written to a redirected drive
+    pub max_file_size: Option<u64>,
+}
+
+impl SessionPolicy {
+    pub const ALLOW_ALL: Self = Self {
+        clipboard: TransferPolicy::Both,
+        max_clipboard_size: None,
+        keyboard: true,
+        drive: TransferPolicy::Both,
+        max_file_size: None,
+    };
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self::ALLOW_ALL
+    }
+}
+
+/// Action blocked by a [`SessionPolicy`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockedAction {
+    /// Announcement of the formats of a clipboard
+    ClipboardCopy {
+        direction: TransferDirection,
+    },
+    ClipboardData {
+        direction: TransferDirection,
+        size: u64,
+    },
+    /// Size or contents of a file copied through the clipboard, `size` being the size of the file or the end of the
+    /// requested range
+    ClipboardFile {
+        direction: TransferDirection,
+        size: u64,
+    },
+    /// A key press, the unicode ones included (the releases are blocked silently)
+    Keyboard,
+    DriveAnnounced {
+        device_id: u32,
+        name: String,
+    },
+    /// Request on a redirected drive, `size` being the end of the requested range
+    DriveRequest {
+        device_id: u32,
+        direction: TransferDirection,
+        size: u64,
+    },
+}
+
+/// Why an action was blocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockReason {
+    /// Denied by the policy
+    Denied,
+    /// Larger than allowed by the policy
+    TooLarge { max_size: u64 },
+}
+
+/// Action of a client blocked by the policy of its session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    pub session_id: u64,
+    pub action: BlockedAction,
+    pub reason: BlockReason,
+}
+
+/// Receives the audit events of all the sessions, see [`RdpServerBuilder::with_audit_sink`]
+///
+/// The events are also logged with the `ironrdp_server::audit` target. The sink is called from the server loop: it
+/// must not block.
+///
+/// [`RdpServerBuilder::with_audit_sink`]: crate::builder::RdpServerBuilder::with_audit_sink
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: AuditEvent);
+}
+
+/// Enforces the policy of a session, shared by the input handling and the channel processors
+pub(crate) struct PolicyEnforcer {
+    session_id: u64,
+    policy: SessionPolicy,
+    sink: Option<Arc<dyn AuditSink>>,
+    /// File contents requests of each side, by stream ID, to check the responses
+    file_requests: Mutex<HashMap<(TransferDirection, u32), (FileContentsFlags, u64)>>,
+}
+
+impl core::fmt::Debug for PolicyEnforcer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PolicyEnforcer")
+            .field("session_id", &self.session_id)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PolicyEnforcer {
+    pub(crate) fn new(session_id: u64, policy: SessionPolicy, sink: Option<Arc<dyn AuditSink>>) -> Self {
+        Self {
+            session_id,
+            policy,
+            sink,
+            file_requests: Mutex::default(),
+        }
+    }
+
+    fn block(&self, action: BlockedAction, reason: BlockReason) -> bool {
+        let event = AuditEvent {
+            session_id: self.session_id,
+            action,
+            reason,
+        };
+        warn!(
+            target: "ironrdp_server::audit",
+            session_id = event.session_id,
+            action = ?event.action,
+            reason = ?event.reason,
+            "Blocked by the session policy"
+        );
+
+        if let Some(sink) = &self.sink {
+            sink.record(event);
+        }
+
+        false
+    }
+
+    fn check_size(&self, size: u64, max_size: Option<u64>, action: impl FnOnce() -> BlockedAction) -> bool {
+        match max_size {
+            Some(max_size) if size > max_size => self.block(action(), BlockReason::TooLarge { max_size }),
+            _ => true,
+        }
+    }
+
+    /// Whether the keyboard event is accepted
+    pub(crate) fn keyboard(&self, event: &KeyboardEvent) -> bool {
+        match event {
+            KeyboardEvent::Synchronize(_) => true,
+            _ if self.policy.keyboard => true,
+            // Only the presses are reported, the releases follow them.
+            KeyboardEvent::Pressed { .. } | KeyboardEvent::UnicodePressed(_) => {
+                self.block(BlockedAction::Keyboard, BlockReason::Denied)
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn clipboard_copy(&self, direction: TransferDirection) -> bool {
+        self.policy.clipboard.allows(direction)
+            || self.block(BlockedAction::ClipboardCopy { direction }, BlockReason::Denied)
+    }
+
+    pub(crate) fn clipboard_data(&self, direction: TransferDirection, size: u64) -> bool {
+        let action = || BlockedAction::ClipboardData { direction, size };
+
+        if !self.policy.clipboard.allows(direction) {
+            return self.block(action(), BlockReason::Denied);
+        }
+
+        self.check_size(size, self.policy.max_clipboard_size, action)
+    }
+
+    /// Records a file contents request, `direction` being the direction of the requested data
+    pub(crate) fn clipboard_file_request(&self, direction: TransferDirection, request: &FileContentsRequest) {
+        self.file_requests
+            .lock()
+            .expect("file requests mutex poisoned")
+            .insert((direction, request.stream_id), (request.flags, request.position));
+    }
+
+    /// Whether the response to a recorded file contents request is allowed
+    pub(crate) fn clipboard_file_response(
+        &self,
+        direction: TransferDirection,
+        response: &FileContentsResponse<'_>,
+    ) -> bool {
+        let request = self
+            .file_requests
+            .lock()
+            .expect("file requests mutex poisoned")
+            .remove(&(direction, response.stream_id()));
+
+        if response.is_error() {
+            return true;
+        }
+
+        let size = match request {
+            Some((flags, _)) if flags.contains(FileContentsFlags::SIZE) => response.data_as_size().unwrap_or(u64::MAX),
+            Some((_, position)) => position.saturating_add(u64::try_from(response.data().len()).unwrap_or(u64::MAX)),
+            None => u64::MAX,
+        };
+        let action = || BlockedAction::ClipboardFile { direction, size };
+
+        if !self.policy.clipboard.allows(direction) {
+            return self.block(action(), BlockReason::Denied);
+        }
+
+        self.check_size(size, self.policy.max_file_size, action)
+    }
+
+    pub(crate) fn drive_announced(&self, drive: &RedirectedDrive) -> bool {
+        self.policy.drive != TransferPolicy::Denied
+            || self.block(
+                BlockedAction::DriveAnnounced {
+                    device_id: drive.device_id,
+                    name: drive.name.clone(),
+                },
+                BlockReason::Denied,
+            )
+    }
+
+    /// Whether the request may be sent to the client
+    pub(crate) fn drive_request(&self, device_id: u32, request: &DriveRequest) -> bool {
+        let (direction, size) = match request {
+            DriveRequest::Read { offset, length, .. } => (
+                TransferDirection::ClientToServer,
+                offset.saturating_add(u64::from(*length)),
+            ),
+            DriveRequest::Write { offset, data, .. } => (
+                TransferDirection::ServerToClient,
+                offset.saturating_add(u64::try_from(data.len()).unwrap_or(u64::MAX)),
+            ),
+            DriveRequest::Create {
+                desired_access,
+                create_disposition,
+                ..
+            } if is_modifying(desired_access, create_disposition) => (TransferDirection::ServerToClient, 0),
+            DriveRequest::Create { .. } | DriveRequest::QueryDirectory { .. } | DriveRequest::Close { .. } => {
+                return true
+            }
+        };
+        let action = || BlockedAction::DriveRequest {
+            device_id,
+            direction,
+            size,
+        };
+
+        if !self.policy.drive.allows(direction) {
+            return self.block(action(), BlockReason::Denied);
+        }
+
+        self.check_size(size, self.policy.max_file_size, action)
+    }
+}
+
+/// Whether a file is opened to be modified, created or deleted
+fn is_modifying(desired_access: &DesiredAccess, create_disposition: &CreateDisposition) -> bool {
+    let modifying_access = DesiredAccess::FILE_WRITE_DATA_OR_FILE_ADD_FILE
+        | DesiredAccess::FILE_APPEND_DATA_OR_FILE_ADD_SUBDIRECTORY
+        | DesiredAccess::FILE_WRITE_EA
+        | DesiredAccess::FILE_DELETE_CHILD
+        | DesiredAccess::FILE_WRITE_ATTRIBUTES
+        | DesiredAccess::DELETE
+        | DesiredAccess::WRITE_DAC
+        | DesiredAccess::WRITE_OWNER
+        | DesiredAccess::MAXIMUM_ALLOWED
+        | DesiredAccess::GENERIC_ALL
+        | DesiredAccess::GENERIC_WRITE;
+
+    desired_access.intersects(modifying_access) || *create_disposition != CreateDisposition::FILE_OPEN
+}
+
+/// The server is the local side of the clipboard channel
+fn transfer_direction(direction: ClipboardDirection) -> TransferDirection {
+    match direction {
+        ClipboardDirection::LocalToRemote => TransferDirection::ServerToClient,
+        ClipboardDirection::RemoteToLocal => TransferDirection::ClientToServer,
+    }
+}
+
+impl CliprdrPolicy for PolicyEnforcer {
+    fn allow_copy(&self, direction: ClipboardDirection) -> bool {
+        self.clipboard_copy(transfer_direction(direction))
+    }
+
+    fn allow_format_data(&self, direction: ClipboardDirection, response: &FormatDataResponse<'_>) -> bool {
+        let size = u64::try_from(response.data().len()).unwrap_or(u64::MAX);
+
+        self.clipboard_data(transfer_direction(direction), size)
+    }
+
+    fn file_contents_requested(&self, direction: ClipboardDirection, request: &FileContentsRequest) {
+        self.clipboard_file_request(transfer_direction(direction), request);
+    }
+
+    fn allow_file_contents(&self, direction: ClipboardDirection, response: &FileContentsResponse<'_>) -> bool {
+        self.clipboard_file_response(transfer_direction(direction), response)
+    }
+}
+
+impl DrivePolicy for PolicyEnforcer {
+    fn allow_drive(&self, drive: &RedirectedDrive) -> bool {
+        self.drive_announced(drive)
+    }
+
+    fn allow_request(&self, device_id: u32, request: &DriveRequest) -> bool {
+        self.drive_request(device_id, request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ironrdp_rdpdr::pdu::efs::CreateOptions;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Sink(Mutex<Vec<AuditEvent>>);
+
+    impl AuditSink for Sink {
+        fn record(&self, event: AuditEvent) {
+            self.0.lock().expect("sink mutex").push(event);
+        }
+    }
+
+    fn new_enforcer(policy: SessionPolicy) -> (PolicyEnforcer, Arc<Sink>) {
+        let sink = Arc::new(Sink::default());
+        let shared: Arc<dyn AuditSink> = Arc::<Sink>::clone(&sink);
+        let enforcer = PolicyEnforcer::new(3, policy, Some(shared));
+        (enforcer, sink)
+    }
+
+    fn events(sink: &Sink) -> Vec<AuditEvent> {
+        core::mem::take(&mut *sink.0.lock().expect("sink mutex"))
+    }
+
+    #[test]
+    fn transfer_directions() {
+        assert!(TransferPolicy::Both.allows(TransferDirection::ServerToClient));
+        assert!(TransferPolicy::ClientToServer.allows(TransferDirection::ClientToServer));
+        assert!(!TransferPolicy::ClientToServer.allows(TransferDirection::ServerToClient));
+        assert!(!TransferPolicy::Denied.allows(TransferDirection::ClientToServer));
+    }
+
+    #[test]
+    fn clipboard_policy() {
+        let (enforcer, sink) = new_enforcer(SessionPolicy {
+            clipboard: TransferPolicy::ServerToClient,
+            max_clipboard_size: Some(100),
+            max_file_size: Some(1000),
+            ..SessionPolicy::ALLOW_ALL
+        });
+
+        assert!(enforcer.clipboard_copy(TransferDirection::ServerToClient));
+        assert!(enforcer.clipboard_data(TransferDirection::ServerToClient, 100));
+        assert!(events(&sink).is_empty());
+
+        assert!(!enforcer.clipboard_copy(TransferDirection::ClientToServer));
+        assert!(!enforcer.clipboard_data(TransferDirection::ServerToClient, 101));
+        assert_eq!(
+            events(&sink),
+            [
+                AuditEvent {
+                    session_id: 3,
+                    action: BlockedAction::ClipboardCopy {
+                        direction: TransferDirection::ClientToServer
+                    },
+                    reason: BlockReason::Denied,
+                },
+                AuditEvent {
+                    session_id: 3,
+                    action: BlockedAction::ClipboardData {
+                        direction: TransferDirection::ServerToClient,
+                        size: 101
+                    },
+                    reason: BlockReason::TooLarge { max_size: 100 },
+                },
+            ]
+        );
+
+        let request = |stream_id, flags, position| FileContentsRequest {
+            stream_id,
+            index: 0,
+            flags,
+            position,
+            requested_size: 500,
+            data_id: None,
+        };
+
+        // The files larger than allowed are blocked from their size.
+        enforcer.clipboard_file_request(
+            TransferDirection::ServerToClient,
+            &request(1, FileContentsFlags::SIZE, 0),
+        );
+        assert!(!enforcer.clipboard_file_response(
+            TransferDirection::ServerToClient,
+            &FileContentsResponse::new_size_response(1, 1001)
+        ));
+
+        enforcer.clipboard_file_request(
+            TransferDirection::ServerToClient,
+            &request(2, FileContentsFlags::DATA, 500),
+        );
+        assert!(enforcer.clipboard_file_response(
+            TransferDirection::ServerToClient,
+            &FileContentsResponse::new_data_response(2, vec![0; 500])
+        ));
+        enforcer.clipboard_file_request(
+            TransferDirection::ServerToClient,
+            &request(3, FileContentsFlags::DATA, 600),
+        );
+        assert!(!enforcer.clipboard_file_response(
+            TransferDirection::ServerToClient,
+            &FileContentsResponse::new_data_response(3, vec![0; 500])
+        ));
+
+        assert_eq!(
+            events(&sink)
+                .into_iter()
+                .map(|event| (event.action, event.reason))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    BlockedAction::ClipboardFile {
+                        direction: TransferDirection::ServerToClient,
+                        size: 1001
+                    },
+                    BlockReason::TooLarge { max_size: 1000 }
+                ),
+                (
+                    BlockedAction::ClipboardFile {
+                        direction: TransferDirection::ServerToClient,
+                        size: 1100
+                    },
+                    BlockReason::TooLarge { max_size: 1000 }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn keyboard_policy() {
+        let (enforcer, sink) = new_enforcer(SessionPolicy {
+            keyboard: false,
+            ..SessionPolicy::ALLOW_ALL
+        });
+
+        assert!(!enforcer.keyboard(&KeyboardEvent::Pressed {
+            code: 0x1E,
+            extended: false
+        }));
+        assert!(!enforcer.keyboard(&KeyboardEvent::Released {
+            code: 0x1E,
+            extended: false
+        }));
+        assert!(!enforcer.keyboard(&KeyboardEvent::UnicodeReleased(0x61)));
+        assert!(enforcer.keyboard(&KeyboardEvent::Synchronize(
+            ironrdp_pdu::input::fast_path::SynchronizeFlags::NUM_LOCK
+        )));
+
+        assert_eq!(events(&sink).len(), 1);
+    }
+
+    #[test]
+    fn drive_policy() {
+        let (enforcer, sink) = new_enforcer(SessionPolicy {
+            drive: TransferPolicy::ClientToServer,
+            max_file_size: Some(1000),
+            ..SessionPolicy::ALLOW_ALL
+        });
+        let create = |desired_access, create_disposition| DriveRequest::Create {
+            path: String::from("\\file.txt"),
+            desired_access,
+            create_disposition,
+            create_options: CreateOptions::FILE_NON_DIRECTORY_FILE,
+        };
+
+        assert!(enforcer.drive_announced(&RedirectedDrive {
+            device_id: 1,
+            name: String::from("C"),
+        }));
+        assert!(enforcer.drive_request(1, &create(DesiredAccess::GENERIC_READ, CreateDisposition::FILE_OPEN)));
+        assert!(enforcer.drive_request(
+            1,
+            &DriveRequest::Read {
+                file_id: 1,
+                offset: 500,
+                length: 500
+            }
+        ));
+        assert!(events(&sink).is_empty());
+
+        assert!(!enforcer.drive_request(
+            1,
+            &create(DesiredAccess::GENERIC_READ, CreateDisposition::FILE_OVERWRITE_IF)
+        ));
+        assert!(!enforcer.drive_request(1, &create(DesiredAccess::GENERIC_WRITE, CreateDisposition::FILE_OPEN)));
+        assert!(!enforcer.drive_request(
+            1,
+            &DriveRequest::Write {
+                file_id: 1,
+                offset: 0,
+                data: vec![0; 10]
+            }
+        ));
+        assert!(!enforcer.drive_request(
+            1,
+            &DriveRequest::Read {
+                file_id: 1,
+                offset: 501,
+                length: 500
+            }
+        ));
+
+        let blocked = events(&sink);
+        assert_eq!(blocked.len(), 4);
+        assert_eq!(
+            blocked[3].action,
+            BlockedAction::DriveRequest {
+                device_id: 1,
+                direction: TransferDirection::ClientToServer,
+                size: 1001
+            }
+        );
+        assert_eq!(blocked[3].reason, BlockReason::TooLarge { max_size: 1000 });
+
+        let (enforcer, sink) = new_enforcer(SessionPolicy {
+            drive: TransferPolicy::Denied,
+            ..SessionPolicy::ALLOW_ALL
+        });
+        assert!(!enforcer.drive_announced(&RedirectedDrive {
+            device_id: 2,
+            name: String::from("D"),
+        }));
+        assert_eq!(
+            events(&sink)[0].action,
+            BlockedAction::DriveAnnounced {
+                device_id: 2,
+                name: String::from("D")
+            }
+        );
+    }
+}
@@ -0,0 +1,10 @@
+pub use ironrdp_rail::server::{window_list_sync, RailServerHandler, RailServerMessage};
+
+use crate::{ConnectionContext, ServerEventSender};
+
+/// Builds the remote applications of a connection
+///
+/// Remote applications are offered only when a factory is set, their windows are shown individually on the client.
+pub trait RailServerFactory: ServerEventSender {
+    fn build_backend(&self, ctx: &ConnectionContext) -> Box<dyn RailServerHandler>;
+}
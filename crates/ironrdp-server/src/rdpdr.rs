@@ -0,0 +1,25 @@
+pub use ironrdp_rdpdr::server::{
+    DriveRequest, DriveResponse, FileSystemBackend, PrinterHandler, RdpdrServerMessage, RedirectedDrive,
+    RedirectedPrinter, SmartCardHandler,
+};
+
+use crate::{ConnectionContext, ServerEventSender};
+
+/// Builds the file system backend of a connection
+///
+/// The client drives are redirected only when a factory is set, requests are sent with [`ServerEvent::Rdpdr`].
+///
+/// [`ServerEvent::Rdpdr`]: crate::ServerEvent::Rdpdr
+pub trait RdpdrServerFactory: ServerEventSender {
+    fn build_backend(&self, ctx: &ConnectionContext) -> Box<dyn FileSystemBackend>;
+
+    /// Builds the smart card handler of a connection, the smart cards are redirected only when it's set
+    fn build_smartcard_handler(&self, _ctx: &ConnectionContext) -> Option<Box<dyn SmartCardHandler>> {
+        None
+    }
+
+    /// Builds the printer handler of a connection, the printers are redirected only when it's set
+    fn build_printer_handler(&self, _ctx: &ConnectionContext) -> Option<Box<dyn PrinterHandler>> {
+        None
+    }
+}
@@ -0,0 +1,324 @@
+use core::time::Duration;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use ironrdp_core::{encode_vec, EncodeResult};
+use ironrdp_pdu::mcs::SendDataIndication;
+use ironrdp_pdu::x224::X224;
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage};
+
+/// Virtual channel data sent by the server, see [`ChannelScheduling`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputChannel {
+    /// Graphics pipeline messages
+    Egfx,
+    /// Audio output
+    Rdpsnd,
+    /// RemoteApp messages
+    Rail,
+    /// Clipboard messages, including the file transfers
+    Cliprdr,
+    /// Device redirection messages, including the drive file copies
+    Rdpdr,
+}
+
+impl OutputChannel {
+    /// The output channels, the first ones being sent first when equally charged
+    pub const ALL: [Self; 5] = [Self::Egfx, Self::Rdpsnd, Self::Rail, Self::Cliprdr, Self::Rdpdr];
+
+    fn index(self) -> usize {
+        match self {
+            Self::Egfx => 0,
+            Self::Rdpsnd => 1,
+            Self::Rail => 2,
+            Self::Cliprdr => 3,
+            Self::Rdpdr => 4,
+        }
+    }
+}
+
+/// Scheduling parameters of an [`OutputChannel`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelPolicy {
+    /// Share of the bandwidth of the channel while other channels have data to send, relative to their weights
+    ///
+    /// A weight of 0 is handled as 1.
+    pub weight: u32,
+    /// Maximum bandwidth of the channel, in bytes per second
+    pub max_bandwidth: Option<u32>,
+}
+
+impl ChannelPolicy {
+    pub const fn new(weight: u32) -> Self {
+        Self {
+            weight,
+            max_bandwidth: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_max_bandwidth(mut self, bytes_per_second: u32) -> Self {
+        self.max_bandwidth = Some(bytes_per_second);
+        self
+    }
+}
+
+/// Scheduling of the virtual channel data sent by the server
+///
+/// The data of the channels sensitive to the latency, the graphics and the audio, is sent ahead of the bulk transfers of
+/// the clipboard and device redirection channels, each channel getting a share of the bandwidth proportional to its
+/// weight while several channels have data to send. A channel may also be capped to a maximum bandwidth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelScheduling {
+    policies: [ChannelPolicy; OutputChannel::ALL.len()],
+}
+
+impl ChannelScheduling {
+    pub fn policy(&self, channel: OutputChannel) -> ChannelPolicy {
+        self.policies[channel.index()]
+    }
+
+    #[must_use]
+    pub fn with_policy(mut self, channel: OutputChannel, policy: ChannelPolicy) -> Self {
+        self.policies[channel.index()] = policy;
+        self
+    }
+}
+
+impl Default for ChannelScheduling {
+    fn default() -> Self {
+        Self {
+            policies: [
+                ChannelPolicy::new(16),
+                ChannelPolicy::new(16),
+                ChannelPolicy::new(4),
+                ChannelPolicy::new(1),
+                ChannelPolicy::new(1),
+            ],
+        }
+    }
+}
+
+/// Charge of a byte sent on a channel of weight 1
+const BYTE_CHARGE: u64 = 1 << 16;
+
+/// Queue of the PDUs of the output channels, sent by weighted fair queuing
+#[derive(Debug)]
+pub(crate) struct ChannelScheduler {
+    policies: [ChannelPolicy; OutputChannel::ALL.len()],
+    queues: [ChannelQueue; OutputChannel::ALL.len()],
+    /// Charge of the channel sent last, a channel becoming busy again starts from there
+    virtual_time: u64,
+}
+
+#[derive(Debug, Default)]
+struct ChannelQueue {
+    pdus: VecDeque<Vec<u8>>,
+    charged: u64,
+    /// Time before which the bandwidth cap of the channel holds its PDUs back
+    next_send: Option<Instant>,
+}
+
+impl ChannelScheduler {
+    pub(crate) fn new(scheduling: &ChannelScheduling) -> Self {
+        Self {
+            policies: scheduling.policies,
+            queues: Default::default(),
+            virtual_time: 0,
+        }
+    }
+
+    /// Queues the messages of a static channel, each chunk being sent in its own MCS PDU
+    pub(crate) fn push(
+        &mut self,
+        channel: OutputChannel,
+        messages: Vec<SvcMessage>,
+        channel_id: u16,
+        initiator_id: u16,
+    ) -> EncodeResult<()> {
+        let virtual_time = self.virtual_time;
+        let queue = &mut self.queues[channel.index()];
+
+        if queue.pdus.is_empty() {
+            queue.charged = queue.charged.max(virtual_time);
+        }
+
+        for chunk in StaticVirtualChannel::chunkify(messages)? {
+            let pdu = SendDataIndication {
+                initiator_id,
+                channel_id,
+                user_data: Cow::Borrowed(chunk.filled()),
+            };
+            queue.pdus.push_back(encode_vec(&X224(pdu))?);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queues.iter().all(|queue| queue.pdus.is_empty())
+    }
+
+    /// Time when the next PDU can be sent, `None` when nothing is queued
+    pub(crate) fn next_send_time(&self) -> Option<Instant> {
+        self.next_send_time_at(Instant::now())
+    }
+
+    /// Next PDU to send, if any is not held back by a bandwidth cap
+    pub(crate) fn pop(&mut self) -> Option<Vec<u8>> {
+        self.pop_at(Instant::now())
+    }
+
+    fn next_send_time_at(&self, now: Instant) -> Option<Instant> {
+        self.queues
+            .iter()
+            .filter(|queue| !queue.pdus.is_empty())
+            .map(|queue| queue.next_send.map_or(now, |next_send| next_send.max(now)))
+            .min()
+    }
+
+    fn pop_at(&mut self, now: Instant) -> Option<Vec<u8>> {
+        let (index, queue) = self
+            .queues
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, queue)| !queue.pdus.is_empty() && queue.next_send.is_none_or(|next_send| next_send <= now))
+            .min_by_key(|(_, queue)| queue.charged)?;
+
+        let pdu = queue.pdus.pop_front()?;
+        let policy = self.policies[index];
+        let size = u64::try_from(pdu.len()).unwrap_or(u64::MAX);
+
+        self.virtual_time = queue.charged;
+        queue.charged = queue
+            .charged
+            .saturating_add(size.saturating_mul(BYTE_CHARGE) / u64::from(policy.weight.max(1)));
+
+        if let Some(max_bandwidth) = policy.max_bandwidth {
+            let duration = Duration::from_nanos(size.saturating_mul(1_000_000_000) / u64::from(max_bandwidth.max(1)));
+            queue.next_send = Some(queue.next_send.map_or(now, |next_send| next_send.max(now)) + duration);
+        }
+
+        Some(pdu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ironrdp_core::{Encode, WriteCursor};
+    use ironrdp_svc::SvcEncode;
+
+    use super::*;
+
+    struct Payload(usize);
+
+    impl Encode for Payload {
+        fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+            dst.write_slice(&vec![0xAB; self.0]);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "Payload"
+        }
+
+        fn size(&self) -> usize {
+            self.0
+        }
+    }
+
+    impl SvcEncode for Payload {}
+
+    /// Queues a message of `size` bytes, on the static channel of the same ID as the output channel
+    fn push(scheduler: &mut ChannelScheduler, channel: OutputChannel, size: usize) {
+        let channel_id = u16::try_from(1004 + channel.index()).unwrap();
+        scheduler
+            .push(channel, vec![SvcMessage::from(Payload(size))], channel_id, 1007)
+            .unwrap();
+    }
+
+    fn channel_of(pdu: &[u8]) -> OutputChannel {
+        let pdu = ironrdp_core::decode::<X224<SendDataIndication<'_>>>(pdu).unwrap().0;
+        OutputChannel::ALL[usize::from(pdu.channel_id - 1004)]
+    }
+
+    #[test]
+    fn latency_sensitive_channels_preempt_bulk_transfers() {
+        let mut scheduler = ChannelScheduler::new(&ChannelScheduling::default());
+        let now = Instant::now();
+
+        // A file transfer of 4 chunks is being sent.
+        push(&mut scheduler, OutputChannel::Cliprdr, 4 * 1600);
+        let mut sent = vec![channel_of(&scheduler.pop_at(now).unwrap())];
+
+        push(&mut scheduler, OutputChannel::Rdpsnd, 1000);
+        push(&mut scheduler, OutputChannel::Egfx, 1000);
+
+        while let Some(pdu) = scheduler.pop_at(now) {
+            sent.push(channel_of(&pdu));
+        }
+        assert!(scheduler.is_empty());
+        assert_eq!(
+            sent,
+            [
+                OutputChannel::Cliprdr,
+                OutputChannel::Egfx,
+                OutputChannel::Rdpsnd,
+                OutputChannel::Cliprdr,
+                OutputChannel::Cliprdr,
+                OutputChannel::Cliprdr,
+            ]
+        );
+    }
+
+    #[test]
+    fn bandwidth_shared_by_weight() {
+        let scheduling = ChannelScheduling::default()
+            .with_policy(OutputChannel::Rdpdr, ChannelPolicy::new(1))
+            .with_policy(OutputChannel::Rail, ChannelPolicy::new(3));
+        let mut scheduler = ChannelScheduler::new(&scheduling);
+        let now = Instant::now();
+
+        for _ in 0..8 {
+            push(&mut scheduler, OutputChannel::Rdpdr, 1000);
+            push(&mut scheduler, OutputChannel::Rail, 1000);
+        }
+
+        let sent: Vec<_> = core::iter::repeat_with(|| channel_of(&scheduler.pop_at(now).unwrap()))
+            .take(8)
+            .collect();
+        assert_eq!(
+            sent.iter().filter(|channel| **channel == OutputChannel::Rail).count(),
+            6
+        );
+    }
+
+    #[test]
+    fn bandwidth_capped() {
+        let scheduling = ChannelScheduling::default()
+            .with_policy(OutputChannel::Rdpdr, ChannelPolicy::new(1).with_max_bandwidth(10_000));
+        let mut scheduler = ChannelScheduler::new(&scheduling);
+        let now = Instant::now();
+
+        push(&mut scheduler, OutputChannel::Rdpdr, 990);
+        push(&mut scheduler, OutputChannel::Rdpdr, 990);
+        assert_eq!(scheduler.next_send_time_at(now), Some(now));
+
+        let first = scheduler.pop_at(now).unwrap();
+        assert!(scheduler.pop_at(now).is_none());
+
+        // The PDUs are paced by the cap, headers included.
+        let next_send = now + Duration::from_nanos(u64::try_from(first.len()).unwrap() * 100_000);
+        assert_eq!(scheduler.next_send_time_at(now), Some(next_send));
+        assert!(scheduler.pop_at(next_send - Duration::from_millis(1)).is_none());
+
+        // The other channels are not held back.
+        push(&mut scheduler, OutputChannel::Rdpsnd, 100);
+        assert_eq!(scheduler.next_send_time_at(now), Some(now));
+        assert_eq!(channel_of(&scheduler.pop_at(now).unwrap()), OutputChannel::Rdpsnd);
+
+        assert_eq!(channel_of(&scheduler.pop_at(next_send).unwrap()), OutputChannel::Rdpdr);
+        assert_eq!(scheduler.next_send_time_at(next_send), None);
+    }
+}
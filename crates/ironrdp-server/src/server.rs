@@ -1,6 +1,8 @@
 use core::net::SocketAddr;
+use core::sync::atomic::{AtomicBool, Ordering};
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{anyhow, bail, Context as _, Result};
 use ironrdp_acceptor::{Acceptor, AcceptorResult, BeginResult, DesktopSize};
@@ -8,18 +10,30 @@ use ironrdp_async::Framed;
 use ironrdp_cliprdr::backend::ClipboardMessage;
 use ironrdp_cliprdr::CliprdrServer;
 use ironrdp_core::{decode, encode_vec, impl_as_any};
-use ironrdp_displaycontrol::pdu::DisplayControlMonitorLayout;
-use ironrdp_displaycontrol::server::{DisplayControlHandler, DisplayControlServer};
+use ironrdp_displaycontrol::pdu::DisplayControlCapabilities;
+use ironrdp_displaycontrol::server::{DisplayControlHandler, DisplayControlServer, MonitorLayout};
+use ironrdp_graphics::rdp6::ColorLoss;
+use ironrdp_pdu::fast_path::UpdateCode;
+use ironrdp_pdu::geometry::InclusiveRectangle;
 use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
 use ironrdp_pdu::input::InputEventPdu;
 use ironrdp_pdu::mcs::{SendDataIndication, SendDataRequest};
-use ironrdp_pdu::rdp::capability_sets::{BitmapCodecs, CapabilitySet, CmdFlags, CodecProperty, GeneralExtraFlags};
+use ironrdp_pdu::orders::{DrawingOrder, OrdersUpdateData};
+use ironrdp_pdu::rdp::capability_sets::{
+    BitmapCodecs, BitmapDrawingFlags, CapabilitySet, CmdFlags, GeneralExtraFlags, OrderSupportIndex, SERVER_CHANNEL_ID,
+};
 pub use ironrdp_pdu::rdp::client_info::Credentials;
+use ironrdp_pdu::rdp::finalization_messages::{
+    ControlAction, ControlPdu, PersistentKeyListFlags, PersistentKeyListPdu, PERSISTENT_KEY_LIST_CACHE_COUNT,
+};
 use ironrdp_pdu::rdp::headers::{ServerDeactivateAll, ShareControlPdu};
 use ironrdp_pdu::x224::X224;
 use ironrdp_pdu::{decode_err, mcs, nego, rdp, Action, PduResult};
+use ironrdp_rail::pdu::{WindowListCapabilitySet, WindowSupportLevel};
+use ironrdp_rail::server::RailServer;
+use ironrdp_rdpdr::server::RdpdrServer;
 use ironrdp_svc::{server_encode_svc_messages, StaticChannelId, StaticChannelSet, SvcProcessor};
-use ironrdp_tokio::{split_tokio_framed, unsplit_tokio_framed, FramedRead, FramedWrite, TokioFramed};
+use ironrdp_tokio::{split_tokio_framed, unsplit_tokio_framed, FramedRead, FramedWrite, TokioFramed, TokioStream};
 use rdpsnd::server::{RdpsndServer, RdpsndServerMessage};
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt as _};
 use tokio::net::{TcpListener, TcpStream};
@@ -29,51 +43,42 @@ use tokio_rustls::TlsAcceptor;
 use tracing::{debug, error, trace, warn};
 use {ironrdp_dvc as dvc, ironrdp_rdpsnd as rdpsnd};
 
+use crate::audio_input::{audio_input_server, AudioInputHandler};
 use crate::clipboard::CliprdrServerFactory;
-use crate::display::{DisplayUpdate, RdpServerDisplay};
-use crate::encoder::{UpdateEncoder, UpdateEncoderCodecs};
+use crate::context::{ClientIdentity, ConnectionContext, ServerChannels, SessionAccess};
+use crate::display::{DisplayUpdate, RdpServerDisplay, VisualExperience};
+use crate::encoder::bitmap_cache::BitmapCache;
+use crate::encoder::orders::OrderSupport;
+use crate::encoder::{UpdateEncoder, UpdateEncoderCodecs, UpdateFragmenter};
 #[cfg(feature = "egfx")]
-use crate::gfx::{EgfxServerMessage, GfxServerFactory};
-use crate::handler::RdpServerInputHandler;
-use crate::{builder, capabilities, SoundServerFactory};
+use crate::gfx::{EgfxServerMessage, GfxServerConfig, GfxServerFactory, GpuFrameRouter};
+use crate::handler::{rel_mouse_events, KeyboardEvent, RdpServerInputHandler};
+use crate::keyboard::ClientKeyboard;
+use crate::policy::{AuditSink, PolicyEnforcer, SessionPolicy};
+use crate::scheduling::ChannelScheduler;
+use crate::session::{DynamicChannel, SessionRegistry};
+use crate::{
+    builder, capabilities, ChannelScheduling, OutputChannel, RailServerFactory, RailServerMessage, RdpdrServerFactory,
+    RdpdrServerMessage, SoundServerFactory,
+};
 
 #[derive(Clone)]
 pub struct RdpServerOptions {
     pub addr: SocketAddr,
     pub security: RdpServerSecurity,
     pub codecs: BitmapCodecs,
-}
-
-impl RdpServerOptions {
-    fn has_image_remote_fx(&self) -> bool {
-        self.codecs
-            .0
-            .iter()
-            .any(|codec| matches!(codec.property, CodecProperty::ImageRemoteFx(_)))
-    }
-
-    fn has_remote_fx(&self) -> bool {
-        self.codecs
-            .0
-            .iter()
-            .any(|codec| matches!(codec.property, CodecProperty::RemoteFx(_)))
-    }
-
-    #[cfg(feature = "qoi")]
-    fn has_qoi(&self) -> bool {
-        self.codecs
-            .0
-            .iter()
-            .any(|codec| matches!(codec.property, CodecProperty::Qoi))
-    }
-
-    #[cfg(feature = "qoiz")]
-    fn has_qoiz(&self) -> bool {
-        self.codecs
-            .0
-            .iter()
-            .any(|codec| matches!(codec.property, CodecProperty::QoiZ))
-    }
+    /// Capabilities announced on the display control channel, client monitor layouts are validated against them
+    pub display_control: DisplayControlCapabilities,
+    /// Target size of the PDUs sent on the wire, see [`builder::RdpServerBuilder::with_max_segment_size`]
+    pub max_segment_size: Option<usize>,
+    /// Priorities of the virtual channel data sent to the client, see [`ChannelScheduling`]
+    pub channel_scheduling: ChannelScheduling,
+    /// Optional channels offered to the clients, see [`ServerChannels`]
+    pub channels: ServerChannels,
+    /// Color loss of the planar bitmap updates, see [`builder::RdpServerBuilder::with_bitmap_color_loss`]
+    pub bitmap_color_loss: ColorLoss,
+    /// Receives the actions blocked by the session policies, see [`builder::RdpServerBuilder::with_audit_sink`]
+    pub audit_sink: Option<Arc<dyn AuditSink>>,
 }
 
 #[derive(Clone)]
@@ -96,6 +101,7 @@ impl RdpServerSecurity {
 
 struct AInputHandler {
     handler: Arc<Mutex<Box<dyn RdpServerInputHandler>>>,
+    has_control: Arc<AtomicBool>,
 }
 
 impl_as_any!(AInputHandler);
@@ -119,6 +125,9 @@ impl dvc::DvcProcessor for AInputHandler {
         use ironrdp_ainput::ClientPdu;
 
         match decode(payload).map_err(|e| decode_err!(e))? {
+            ClientPdu::Mouse(_) if !self.has_control.load(Ordering::Relaxed) => {
+                trace!("Dropping input from a client without control");
+            }
             ClientPdu::Mouse(pdu) => {
                 let handler = Arc::clone(&self.handler);
                 task::spawn_blocking(move || {
@@ -144,7 +153,7 @@ impl DisplayControlBackend {
 }
 
 impl DisplayControlHandler for DisplayControlBackend {
-    fn monitor_layout(&self, layout: DisplayControlMonitorLayout) {
+    fn monitor_layout(&self, layout: MonitorLayout) {
         let display = Arc::clone(&self.display);
         task::spawn_blocking(move || display.blocking_lock().request_layout(layout));
     }
@@ -217,10 +226,33 @@ pub struct RdpServer {
     handler: Arc<Mutex<Box<dyn RdpServerInputHandler>>>,
     display: Arc<Mutex<Box<dyn RdpServerDisplay>>>,
     static_channels: StaticChannelSet,
+    /// Keys of the client persistent bitmap caches, received during connection finalization
+    persistent_keys: [Vec<u64>; PERSISTENT_KEY_LIST_CACHE_COUNT],
+    /// Whether the client has control of the session, its input is dropped otherwise
+    has_control: Arc<AtomicBool>,
+    /// Access of the connected client, view-only clients never get control
+    access: SessionAccess,
+    /// Policy of the session of the connected client
+    policy: Arc<PolicyEnforcer>,
     sound_factory: Option<Box<dyn SoundServerFactory>>,
     cliprdr_factory: Option<Box<dyn CliprdrServerFactory>>,
+    rail_factory: Option<Box<dyn RailServerFactory>>,
+    rdpdr_factory: Option<Box<dyn RdpdrServerFactory>>,
+    audio_input_handler: Option<Box<dyn AudioInputHandler>>,
+    /// Whether the client supports the windowing orders of remote applications
+    window_orders: bool,
     #[cfg(feature = "egfx")]
     gfx_factory: Option<Box<dyn GfxServerFactory>>,
+    /// GPU frames sent through the graphics pipeline of the connected client, see
+    /// [`GfxServerFactory::build_h264_encoder`]
+    #[cfg(feature = "egfx")]
+    gpu_frames: Option<GpuFrameRouter>,
+    /// Sessions of the clients, see [`Self::sessions`]
+    sessions: SessionRegistry,
+    /// Identifier of the session of the connected client
+    session_id: Option<u64>,
+    /// Refresh Rect and Suppress Output requests, forwarded to the display loop of the connected client
+    output_requests: Option<mpsc::UnboundedSender<OutputRequest>>,
     ev_sender: mpsc::UnboundedSender<ServerEvent>,
     ev_receiver: Arc<Mutex<mpsc::UnboundedReceiver<ServerEvent>>>,
     creds: Option<Credentials>,
@@ -232,8 +264,19 @@ pub enum ServerEvent {
     Quit(String),
     Clipboard(ClipboardMessage),
     Rdpsnd(RdpsndServerMessage),
+    Rail(RailServerMessage),
+    Rdpdr(RdpdrServerMessage),
     SetCredentials(Credentials),
     GetLocalAddr(oneshot::Sender<Option<SocketAddr>>),
+    /// Disconnects the client of the session, see [`SessionRegistry::disconnect`]
+    Disconnect {
+        session_id: u64,
+    },
+    /// Opens a dynamic channel in the session, see [`SessionRegistry::open_dvc`]
+    OpenDvc {
+        session_id: u64,
+        channel: DynamicChannel,
+    },
     /// EGFX (Graphics Pipeline) server events for proactive frame sending
     #[cfg(feature = "egfx")]
     Egfx(EgfxServerMessage),
@@ -249,6 +292,17 @@ impl ServerEvent {
     }
 }
 
+/// Client requests about the display output
+#[derive(Debug)]
+enum OutputRequest {
+    /// Send the areas again
+    Refresh(Vec<InclusiveRectangle>),
+    /// Stop sending display updates, e.g. the client window is minimized
+    Suppress,
+    /// Send display updates again, starting with a full update
+    Resume,
+}
+
 #[derive(Debug, PartialEq)]
 enum RunState {
     Continue,
@@ -264,6 +318,9 @@ impl RdpServer {
         display: Box<dyn RdpServerDisplay>,
         mut sound_factory: Option<Box<dyn SoundServerFactory>>,
         mut cliprdr_factory: Option<Box<dyn CliprdrServerFactory>>,
+        mut rail_factory: Option<Box<dyn RailServerFactory>>,
+        mut rdpdr_factory: Option<Box<dyn RdpdrServerFactory>>,
+        audio_input_handler: Option<Box<dyn AudioInputHandler>>,
         gfx_factory: Option<Box<dyn GfxServerFactory>>,
     ) -> Self {
         let (ev_sender, ev_receiver) = ServerEvent::create_channel();
@@ -273,14 +330,32 @@ impl RdpServer {
         if let Some(snd) = sound_factory.as_mut() {
             snd.set_sender(ev_sender.clone());
         }
+        if let Some(rail) = rail_factory.as_mut() {
+            rail.set_sender(ev_sender.clone());
+        }
+        if let Some(rdpdr) = rdpdr_factory.as_mut() {
+            rdpdr.set_sender(ev_sender.clone());
+        }
         Self {
             opts,
             handler: Arc::new(Mutex::new(handler)),
             display: Arc::new(Mutex::new(display)),
             static_channels: StaticChannelSet::new(),
+            persistent_keys: Default::default(),
+            has_control: Arc::new(AtomicBool::new(true)),
+            access: SessionAccess::Full,
+            policy: Arc::new(PolicyEnforcer::new(0, SessionPolicy::ALLOW_ALL, None)),
             sound_factory,
             cliprdr_factory,
+            rail_factory,
+            rdpdr_factory,
+            audio_input_handler,
+            window_orders: false,
             gfx_factory,
+            gpu_frames: None,
+            sessions: SessionRegistry::new(ev_sender.clone()),
+            session_id: None,
+            output_requests: None,
             ev_sender,
             ev_receiver: Arc::new(Mutex::new(ev_receiver)),
             creds: None,
@@ -295,6 +370,9 @@ impl RdpServer {
         display: Box<dyn RdpServerDisplay>,
         mut sound_factory: Option<Box<dyn SoundServerFactory>>,
         mut cliprdr_factory: Option<Box<dyn CliprdrServerFactory>>,
+        mut rail_factory: Option<Box<dyn RailServerFactory>>,
+        mut rdpdr_factory: Option<Box<dyn RdpdrServerFactory>>,
+        audio_input_handler: Option<Box<dyn AudioInputHandler>>,
     ) -> Self {
         let (ev_sender, ev_receiver) = ServerEvent::create_channel();
         if let Some(cliprdr) = cliprdr_factory.as_mut() {
@@ -303,13 +381,30 @@ impl RdpServer {
         if let Some(snd) = sound_factory.as_mut() {
             snd.set_sender(ev_sender.clone());
         }
+        if let Some(rail) = rail_factory.as_mut() {
+            rail.set_sender(ev_sender.clone());
+        }
+        if let Some(rdpdr) = rdpdr_factory.as_mut() {
+            rdpdr.set_sender(ev_sender.clone());
+        }
         Self {
             opts,
             handler: Arc::new(Mutex::new(handler)),
             display: Arc::new(Mutex::new(display)),
             static_channels: StaticChannelSet::new(),
+            persistent_keys: Default::default(),
+            has_control: Arc::new(AtomicBool::new(true)),
+            access: SessionAccess::Full,
+            policy: Arc::new(PolicyEnforcer::new(0, SessionPolicy::ALLOW_ALL, None)),
             sound_factory,
             cliprdr_factory,
+            rail_factory,
+            rdpdr_factory,
+            audio_input_handler,
+            window_orders: false,
+            sessions: SessionRegistry::new(ev_sender.clone()),
+            session_id: None,
+            output_requests: None,
             ev_sender,
             ev_receiver: Arc::new(Mutex::new(ev_receiver)),
             creds: None,
@@ -325,41 +420,103 @@ impl RdpServer {
         &self.ev_sender
     }
 
-    fn attach_channels(&mut self, acceptor: &mut Acceptor) {
-        if let Some(cliprdr_factory) = self.cliprdr_factory.as_deref() {
-            let backend = cliprdr_factory.build_cliprdr_backend();
+    /// Sessions of the clients, the registry can be cloned to list and disconnect them while the server runs
+    pub fn sessions(&self) -> &SessionRegistry {
+        &self.sessions
+    }
+
+    #[cfg_attr(
+        not(feature = "egfx"),
+        expect(unused_variables, reason = "only used by the graphics pipeline")
+    )]
+    fn attach_channels(&mut self, acceptor: &mut Acceptor, desktop_size: DesktopSize, ctx: &ConnectionContext) {
+        let channels = ctx.channels();
+
+        if let Some(cliprdr_factory) = self.cliprdr_factory.as_deref().filter(|_| channels.clipboard) {
+            let backend = cliprdr_factory.build_cliprdr_backend_for(ctx);
 
-            let cliprdr = CliprdrServer::new(backend);
+            let cliprdr = CliprdrServer::new(backend).with_policy(Arc::<PolicyEnforcer>::clone(&self.policy));
 
             acceptor.attach_static_channel(cliprdr);
         }
 
-        if let Some(factory) = self.sound_factory.as_deref() {
-            let backend = factory.build_backend();
+        if let Some(factory) = self.sound_factory.as_deref().filter(|_| channels.audio_output) {
+            let backend = factory.build_backend(ctx);
 
             acceptor.attach_static_channel(RdpsndServer::new(backend));
         }
 
-        let dcs_backend = DisplayControlBackend::new(Arc::clone(&self.display));
-        let mut dvc = dvc::DrdynvcServer::new()
-            .with_dynamic_channel(AInputHandler {
+        if let Some(factory) = self.rail_factory.as_deref() {
+            let backend = factory.build_backend(ctx);
+
+            acceptor.attach_static_channel(RailServer::new(backend));
+        }
+
+        if let Some(factory) = self.rdpdr_factory.as_deref().filter(|_| channels.device_redirection) {
+            let backend = factory.build_backend(ctx);
+            let mut rdpdr = RdpdrServer::new(backend).with_drive_policy(Arc::<PolicyEnforcer>::clone(&self.policy));
+            if let Some(handler) = factory.build_smartcard_handler(ctx) {
+                rdpdr = rdpdr.with_smartcard(handler);
+            }
+            if let Some(handler) = factory.build_printer_handler(ctx) {
+                rdpdr = rdpdr.with_printer(handler);
+            }
+
+            acceptor.attach_static_channel(rdpdr);
+        }
+
+        let mut dvc = dvc::DrdynvcServer::new();
+
+        if channels.advanced_input {
+            dvc = dvc.with_dynamic_channel(AInputHandler {
                 handler: Arc::clone(&self.handler),
-            })
-            .with_dynamic_channel(DisplayControlServer::new(Box::new(dcs_backend)));
+                has_control: Arc::clone(&self.has_control),
+            });
+        }
+
+        if channels.display_control {
+            let dcs_backend = DisplayControlBackend::new(Arc::clone(&self.display));
+            dvc = dvc.with_dynamic_channel(
+                DisplayControlServer::new(Box::new(dcs_backend)).with_capabilities(self.opts.display_control.clone()),
+            );
+        }
+
+        let max_data_size = self.opts.max_segment_size.map_or(
+            dvc::pdu::DrdynvcDataPdu::MAX_DATA_SIZE,
+            dvc::pdu::DrdynvcDataPdu::max_data_size_for_segment,
+        );
+        dvc = dvc.with_max_data_size(max_data_size);
+
+        if let Some(handler) = self.audio_input_handler.as_deref().filter(|_| channels.audio_input) {
+            dvc = dvc.with_dynamic_channel(audio_input_server(handler, ctx));
+        }
 
         // Add EGFX (Graphics Pipeline) DVC if configured
         #[cfg(feature = "egfx")]
-        if let Some(gfx_factory) = self.gfx_factory.as_deref() {
+        {
+            self.gpu_frames = None;
+        }
+        #[cfg(feature = "egfx")]
+        if let Some(gfx_factory) = self.gfx_factory.as_deref().filter(|_| channels.graphics_pipeline) {
+            let mut config = GfxServerConfig::new(ctx.clone(), desktop_size.width, desktop_size.height);
+            gfx_factory.configure(&mut config);
+
             // Try bridge pattern first (enables proactive frame sending via Arc<Mutex<>>)
-            if let Some((bridge, _handle)) = gfx_factory.build_server_with_handle() {
+            if let Some((bridge, handle)) = gfx_factory.build_server_with_handle(&config) {
+                config.apply(&mut handle.lock().expect("GfxServerHandle mutex poisoned"));
+
                 // Bridge wraps Arc<Mutex<GraphicsPipelineServer>> for shared access
-                // The _handle is retained by the factory/display handler for frame sending
+                // The handle is retained by the factory/display handler for frame sending
                 dvc = dvc.with_dynamic_channel(bridge);
+
+                // With an H.264 encoder, the GPU frames of the display are sent on the graphics pipeline.
+                self.gpu_frames = gfx_factory
+                    .build_h264_encoder(&config)
+                    .map(|encoder| GpuFrameRouter::new(handle, encoder, self.ev_sender.clone(), max_data_size));
             } else {
                 // Fall back to basic handler-only mode (no proactive frame sending)
-                let handler = gfx_factory.build_gfx_handler();
-                let gfx_server = ironrdp_egfx::server::GraphicsPipelineServer::new(handler);
-                dvc = dvc.with_dynamic_channel(gfx_server);
+                let handler = gfx_factory.build_gfx_handler(&config);
+                dvc = dvc.with_dynamic_channel(config.build_server(handler));
             }
         }
 
@@ -367,18 +524,67 @@ impl RdpServer {
     }
 
     pub async fn run_connection(&mut self, stream: TcpStream) -> Result<()> {
+        let peer_addr = stream.peer_addr().ok();
         let framed = TokioFramed::new(stream);
 
         let size = self.display.lock().await.size().await;
-        let capabilities = capabilities::capabilities(&self.opts, size);
+        let mut capabilities = capabilities::capabilities(&self.opts, size);
+        if self.rail_factory.is_some() {
+            capabilities.extend(capabilities::rail_capabilities());
+        }
         let mut acceptor = Acceptor::new(self.opts.security.flag(), size, capabilities, self.creds.clone());
 
-        self.attach_channels(&mut acceptor);
-
         let res = ironrdp_acceptor::accept_begin(framed, &mut acceptor)
             .await
             .context("accept_begin failed")?;
 
+        let security = acceptor
+            .selected_protocol()
+            .context("security protocol not negotiated")?;
+        let ctx = ConnectionContext::new(
+            self.sessions.next_session_id(),
+            peer_addr,
+            security,
+            self.creds.as_ref().map(ClientIdentity::from),
+        );
+
+        self.access = self.handler.lock().await.session_access(&ctx);
+
+        // The channels able to act on the session are never offered to a view-only client
+        let mut channels = self.opts.channels;
+        if self.access.is_view_only() {
+            channels = channels.view_only();
+        }
+        let ctx = ctx.with_access(self.access).with_channels(channels);
+
+        let channels = self.handler.lock().await.channels(&ctx).intersection(channels);
+        let ctx = ctx.with_channels(channels);
+
+        let policy = self.handler.lock().await.session_policy(&ctx);
+        let ctx = ctx.with_policy(policy);
+        debug!(?ctx, "Connection context");
+
+        self.policy = Arc::new(PolicyEnforcer::new(
+            ctx.session_id(),
+            policy,
+            self.opts.audit_sink.clone(),
+        ));
+
+        self.attach_channels(&mut acceptor, size, &ctx);
+
+        self.sessions.insert(ctx.clone());
+        self.session_id = Some(ctx.session_id());
+
+        let result = self.accept_session(res, acceptor).await;
+
+        self.session_id = None;
+        self.sessions.remove(ctx.session_id());
+        self.handler.lock().await.session_ended(&ctx);
+
+        result
+    }
+
+    async fn accept_session(&mut self, res: BeginResult<TokioStream<TcpStream>>, mut acceptor: Acceptor) -> Result<()> {
         match res {
             BeginResult::ShouldUpgrade(stream) => {
                 let tls_acceptor = match &self.opts.security {
@@ -518,12 +724,38 @@ impl RdpServer {
         io_channel_id: u16,
         buffer: &mut Vec<u8>,
         mut encoder: UpdateEncoder,
+        suppressed: bool,
+        max_segment_size: Option<usize>,
     ) -> Result<(RunState, UpdateEncoder)> {
-        if let DisplayUpdate::Resize(desktop_size) = update {
-            debug!(?desktop_size, "Display resize");
-            encoder.set_desktop_size(desktop_size);
-            deactivate_all(io_channel_id, user_channel_id, writer).await?;
-            return Ok((RunState::DeactivationReactivation { desktop_size }, encoder));
+        // GPU frames not sent on the graphics pipeline take the bitmap path.
+        let update = match update {
+            DisplayUpdate::GpuFrame(frame) => {
+                let bitmap = task::spawn_blocking(move || frame.read_back())
+                    .await?
+                    .context("failed to read back GPU frame")?;
+                DisplayUpdate::Bitmap(bitmap)
+            }
+            update => update,
+        };
+
+        match update {
+            DisplayUpdate::Resize(desktop_size) => {
+                debug!(?desktop_size, "Display resize");
+                encoder.set_desktop_size(desktop_size);
+                deactivate_all(io_channel_id, user_channel_id, writer).await?;
+                return Ok((RunState::DeactivationReactivation { desktop_size }, encoder));
+            }
+            DisplayUpdate::Bitmap(_)
+            | DisplayUpdate::ScreenCopy(_)
+            | DisplayUpdate::SolidFill(_)
+            | DisplayUpdate::Line(_)
+                if suppressed =>
+            {
+                // Keep the framebuffer up to date for the full update sent when the output is resumed.
+                encoder.skip(update);
+                return Ok((RunState::Continue, encoder));
+            }
+            _ => {}
         }
 
         let mut encoder_iter = encoder.update(update);
@@ -532,26 +764,48 @@ impl RdpServer {
                 break;
             };
 
-            let mut fragmenter = fragmenter.context("error while encoding")?;
-            if fragmenter.size_hint() > buffer.len() {
-                buffer.resize(fragmenter.size_hint(), 0);
-            }
-
-            while let Some(len) = fragmenter.next(buffer) {
-                writer
-                    .write_all(&buffer[..len])
-                    .await
-                    .context("failed to write display update")?;
-            }
+            write_fragmenter(
+                writer,
+                buffer,
+                fragmenter.context("error while encoding")?,
+                max_segment_size,
+            )
+            .await?;
         }
 
         Ok((RunState::Continue, encoder))
     }
 
+    async fn dispatch_refresh(
+        areas: &[InclusiveRectangle],
+        writer: &mut impl FramedWrite,
+        buffer: &mut Vec<u8>,
+        encoder: &mut UpdateEncoder,
+        max_segment_size: Option<usize>,
+    ) -> Result<()> {
+        for area in areas {
+            let Some(fragmenter) = encoder.refresh(area).await else {
+                debug!(?area, "Nothing to refresh");
+                continue;
+            };
+
+            write_fragmenter(
+                writer,
+                buffer,
+                fragmenter.context("error while encoding")?,
+                max_segment_size,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
     async fn dispatch_server_events(
         &mut self,
         events: &mut Vec<ServerEvent>,
         writer: &mut impl FramedWrite,
+        scheduler: &mut ChannelScheduler,
         user_channel_id: u16,
     ) -> Result<RunState> {
         // Avoid wave message queuing up and causing extra delays.
@@ -571,6 +825,30 @@ impl RdpServer {
                 ServerEvent::SetCredentials(creds) => {
                     self.set_credentials(Some(creds));
                 }
+                ServerEvent::Disconnect { session_id } => {
+                    if self.session_id == Some(session_id) {
+                        debug!(session_id, "Disconnecting the client");
+                        return Ok(RunState::Disconnect);
+                    }
+                }
+                ServerEvent::OpenDvc { session_id, channel } => {
+                    if self.session_id != Some(session_id) {
+                        debug!(session_id, ?channel, "Session ended, dropping dynamic channel");
+                        continue;
+                    }
+                    let Some(drdynvc) = self.get_svc_processor::<dvc::DrdynvcServer>() else {
+                        warn!("No DRDYNVC channel, dropping dynamic channel");
+                        continue;
+                    };
+                    let msgs = drdynvc
+                        .attach_boxed_dynamic_channel(channel.into_processor())
+                        .context("failed to open dynamic channel")?;
+                    let channel_id = self
+                        .get_channel_id_by_type::<dvc::DrdynvcServer>()
+                        .ok_or_else(|| anyhow!("DRDYNVC channel not found"))?;
+                    let data = server_encode_svc_messages(msgs, channel_id, user_channel_id)?;
+                    writer.write_all(&data).await?;
+                }
                 ServerEvent::Rdpsnd(s) => {
                     let Some(rdpsnd) = self.get_svc_processor::<RdpsndServer>() else {
                         warn!("No rdpsnd channel, dropping event");
@@ -585,6 +863,14 @@ impl RdpServer {
                             wave_limit -= 1;
                             rdpsnd.wave(data, ts)
                         }
+                        RdpsndServerMessage::Audio(frame) => {
+                            if wave_limit == 0 {
+                                debug!("Dropping audio");
+                                continue;
+                            }
+                            wave_limit -= 1;
+                            rdpsnd.push_audio(frame)
+                        }
                         RdpsndServerMessage::SetVolume { left, right } => rdpsnd.set_volume(left, right),
                         RdpsndServerMessage::Close => rdpsnd.close(),
                         RdpsndServerMessage::Error(error) => {
@@ -596,8 +882,71 @@ impl RdpServer {
                     let channel_id = self
                         .get_channel_id_by_type::<RdpsndServer>()
                         .ok_or_else(|| anyhow!("SVC channel not found"))?;
-                    let data = server_encode_svc_messages(msgs.into(), channel_id, user_channel_id)?;
-                    writer.write_all(&data).await?;
+                    scheduler.push(OutputChannel::Rdpsnd, msgs.into(), channel_id, user_channel_id)?;
+                }
+                ServerEvent::Rail(RailServerMessage::WindowOrders(orders)) => {
+                    if !self.window_orders {
+                        debug!("Windowing orders are not supported by the client, dropping event");
+                        continue;
+                    }
+                    let orders = OrdersUpdateData {
+                        orders: orders.into_iter().map(DrawingOrder::Window).collect(),
+                    };
+                    let fragmenter = UpdateFragmenter::new(UpdateCode::Orders, encode_vec(&orders)?);
+                    write_fragmenter(writer, &mut Vec::new(), fragmenter, self.opts.max_segment_size).await?;
+                }
+                ServerEvent::Rail(r) => {
+                    let Some(rail) = self.get_svc_processor::<RailServer>() else {
+                        warn!("No RAIL channel, dropping event");
+                        continue;
+                    };
+                    let msgs = match r {
+                        RailServerMessage::SysParam(param) => rail.system_param(param),
+                        RailServerMessage::LocalMoveSize(pdu) => rail.local_move_size(pdu),
+                        RailServerMessage::MinMaxInfo(pdu) => rail.min_max_info(pdu),
+                        RailServerMessage::ZOrderSync { window_id } => rail.z_order_sync(window_id),
+                        RailServerMessage::LangBarInfo(pdu) => rail.language_bar_info(pdu),
+                        RailServerMessage::WindowOrders(_) => unreachable!("handled above"),
+                    }
+                    .context("failed to send RAIL event")?;
+                    let channel_id = self
+                        .get_channel_id_by_type::<RailServer>()
+                        .ok_or_else(|| anyhow!("SVC channel not found"))?;
+                    scheduler.push(OutputChannel::Rail, msgs.into(), channel_id, user_channel_id)?;
+                }
+                ServerEvent::Rdpdr(message) => {
+                    let Some(rdpdr) = self.get_svc_processor::<RdpdrServer>() else {
+                        warn!("No RDPDR channel, dropping event");
+                        continue;
+                    };
+                    let msgs = match message {
+                        RdpdrServerMessage::DriveRequest {
+                            device_id,
+                            completion_id,
+                            request,
+                        } => rdpdr
+                            .drive_request(device_id, completion_id, request)
+                            .context("failed to send drive request")?,
+                        RdpdrServerMessage::SmartcardCall {
+                            device_id,
+                            completion_id,
+                            io_control_code,
+                            call,
+                        } => rdpdr
+                            .smartcard_call(device_id, completion_id, io_control_code, call)
+                            .context("failed to send smart card call")?,
+                        RdpdrServerMessage::PrintJob {
+                            device_id,
+                            completion_id,
+                            data,
+                        } => rdpdr
+                            .print_job(device_id, completion_id, data)
+                            .context("failed to send print job")?,
+                    };
+                    let channel_id = self
+                        .get_channel_id_by_type::<RdpdrServer>()
+                        .ok_or_else(|| anyhow!("SVC channel not found"))?;
+                    scheduler.push(OutputChannel::Rdpdr, msgs.into(), channel_id, user_channel_id)?;
                 }
                 ServerEvent::Clipboard(c) => {
                     let Some(cliprdr) = self.get_svc_processor::<CliprdrServer>() else {
@@ -623,8 +972,7 @@ impl RdpServer {
                     let channel_id = self
                         .get_channel_id_by_type::<CliprdrServer>()
                         .ok_or_else(|| anyhow!("SVC channel not found"))?;
-                    let data = server_encode_svc_messages(msgs.into(), channel_id, user_channel_id)?;
-                    writer.write_all(&data).await?;
+                    scheduler.push(OutputChannel::Cliprdr, msgs.into(), channel_id, user_channel_id)?;
                 }
                 #[cfg(feature = "egfx")]
                 ServerEvent::Egfx(msg) => {
@@ -639,14 +987,9 @@ impl RdpServer {
                                 dvc_channel_id = dvc_channel_id,
                                 svc_channel_id = drdynvc_channel_id,
                                 message_count = messages.len(),
-                                "ServerEvent::Egfx - sending EGFX PDUs"
-                            );
-                            let data = server_encode_svc_messages(messages, drdynvc_channel_id, user_channel_id)?;
-                            trace!(
-                                bytes = data.len(),
-                                "Writing EGFX data to wire"
+                                "ServerEvent::Egfx - queuing EGFX PDUs"
                             );
-                            writer.write_all(&data).await?;
+                            scheduler.push(OutputChannel::Egfx, messages, drdynvc_channel_id, user_channel_id)?;
                         }
                     }
                 }
@@ -670,10 +1013,16 @@ impl RdpServer {
     {
         debug!("Starting client loop");
         let mut display_updates = self.display.lock().await.updates().await?;
+        let (output_sender, mut output_requests) = mpsc::unbounded_channel();
+        self.output_requests = Some(output_sender);
         let mut writer = SharedWriter::new(writer);
         let mut display_writer = writer.clone();
         let mut event_writer = writer.clone();
         let ev_receiver = Arc::clone(&self.ev_receiver);
+        let max_segment_size = self.opts.max_segment_size;
+        let mut scheduler = ChannelScheduler::new(&self.opts.channel_scheduling);
+        #[cfg(feature = "egfx")]
+        let mut gpu_frames = self.gpu_frames.take();
         let s = Rc::new(Mutex::new(self));
 
         let this = Rc::clone(&s);
@@ -691,12 +1040,48 @@ impl RdpServer {
             }
         };
 
+        #[cfg(feature = "egfx")]
+        let display_gpu_frames = &mut gpu_frames;
         let dispatch_display = async move {
             let mut buffer = vec![0u8; 4096];
+            let mut suppressed = false;
 
             loop {
-                match display_updates.next_update().await {
+                let update = tokio::select! {
+                    update = display_updates.next_update() => update,
+                    Some(request) = output_requests.recv() => {
+                        debug!(?request, "Display output request");
+                        let areas = match request {
+                            OutputRequest::Refresh(_) if suppressed => continue,
+                            OutputRequest::Refresh(areas) => areas,
+                            OutputRequest::Suppress => {
+                                suppressed = true;
+                                continue;
+                            }
+                            OutputRequest::Resume => {
+                                suppressed = false;
+                                vec![encoder.desktop_area()]
+                            }
+                        };
+                        Self::dispatch_refresh(&areas, &mut display_writer, &mut buffer, &mut encoder, max_segment_size)
+                            .await?;
+                        continue;
+                    }
+                };
+
+                match update {
                     Ok(Some(update)) => {
+                        #[cfg(feature = "egfx")]
+                        let update = match (update, display_gpu_frames.as_mut()) {
+                            (DisplayUpdate::GpuFrame(frame), Some(router)) if !suppressed => {
+                                match router.route(frame).await? {
+                                    Some(bitmap) => DisplayUpdate::Bitmap(bitmap),
+                                    None => continue,
+                                }
+                            }
+                            (update, _) => update,
+                        };
+
                         match Self::dispatch_display_update(
                             update,
                             &mut display_writer,
@@ -704,6 +1089,8 @@ impl RdpServer {
                             io_channel_id,
                             &mut buffer,
                             encoder,
+                            suppressed,
+                            max_segment_size,
                         )
                         .await?
                         {
@@ -731,21 +1118,36 @@ impl RdpServer {
         let dispatch_events = async move {
             let mut events = Vec::with_capacity(100);
             loop {
-                let nevents = ev_receiver.recv_many(&mut events, 100).await;
-                if nevents == 0 {
-                    debug!("No sever events.. stopping");
-                    break Ok(RunState::Disconnect);
+                // Wait for new events while the queued channel data is held back by the bandwidth caps
+                let next_send = scheduler.next_send_time();
+                if next_send.is_none_or(|next_send| next_send > Instant::now()) {
+                    tokio::select! {
+                        nevents = ev_receiver.recv_many(&mut events, 100) => {
+                            if nevents == 0 {
+                                debug!("No sever events.. stopping");
+                                break Ok(RunState::Disconnect);
+                            }
+                        }
+                        () = tokio::time::sleep_until(next_send.unwrap_or_else(Instant::now).into()),
+                            if next_send.is_some() => {}
+                    }
                 }
                 while let Ok(ev) = ev_receiver.try_recv() {
                     events.push(ev);
                 }
-                let mut this = this.lock().await;
-                match this
-                    .dispatch_server_events(&mut events, &mut event_writer, user_channel_id)
-                    .await?
-                {
-                    RunState::Continue => continue,
-                    state => break Ok(state),
+                if !events.is_empty() {
+                    let mut this = this.lock().await;
+                    match this
+                        .dispatch_server_events(&mut events, &mut event_writer, &mut scheduler, user_channel_id)
+                        .await?
+                    {
+                        RunState::Continue => {}
+                        state => break Ok(state),
+                    }
+                }
+                // Send a single PDU at a time, so the events received meanwhile are scheduled along the queued data
+                if let Some(pdu) = scheduler.pop() {
+                    event_writer.write_all(&pdu).await?;
                 }
             }
         };
@@ -756,6 +1158,12 @@ impl RdpServer {
             state = dispatch_events => state,
         );
 
+        // Kept for the client loop following a deactivation-reactivation.
+        #[cfg(feature = "egfx")]
+        {
+            s.lock().await.gpu_frames = gpu_frames;
+        }
+
         debug!("End of client loop: {state:?}");
         state
     }
@@ -772,6 +1180,15 @@ impl RdpServer {
     {
         debug!("Client accepted");
 
+        if !result.reactivation {
+            // Control is granted during connection finalization, and taken back from a view-only client.
+            self.has_control.store(!self.access.is_view_only(), Ordering::Relaxed);
+
+            if self.access == (SessionAccess::ViewOnly { notify: true }) {
+                send_control_granted(writer, result.io_channel_id, result.user_channel_id, SERVER_CHANNEL_ID).await?;
+            }
+        }
+
         if !result.input_events.is_empty() {
             debug!("Handling input event backlog from acceptor sequence");
             self.handle_input_backlog(
@@ -798,6 +1215,12 @@ impl RdpServer {
 
         let mut update_codecs = UpdateEncoderCodecs::new();
         let mut surface_flags = CmdFlags::empty();
+        let mut bitmap_bits_per_pixel = None;
+        let mut bitmap_drawing_flags = BitmapDrawingFlags::empty();
+        let mut bitmap_cache = None;
+        let mut mem_blt = false;
+        let mut order_support = OrderSupport::default();
+        self.window_orders = false;
         for c in result.capabilities {
             match c {
                 CapabilitySet::General(c) => {
@@ -807,6 +1230,9 @@ impl RdpServer {
                     }
                 }
                 CapabilitySet::Bitmap(b) => {
+                    bitmap_bits_per_pixel = Some(b.pref_bits_per_pix);
+                    bitmap_drawing_flags = b.drawing_flags;
+
                     if !b.desktop_resize_flag {
                         debug!("Desktop resize is not supported by the client");
                         continue;
@@ -828,53 +1254,72 @@ impl RdpServer {
                         );
                     }
                 }
+                CapabilitySet::BitmapCacheRev2(c) => {
+                    bitmap_cache = Some(c);
+                }
+                CapabilitySet::Order(mut c) => {
+                    mem_blt = c.get_support_flag(OrderSupportIndex::MemBlt);
+                    order_support = OrderSupport::from_capabilities(&mut c);
+                }
                 CapabilitySet::SurfaceCommands(c) => {
                     surface_flags = c.flags;
                 }
-                CapabilitySet::BitmapCodecs(BitmapCodecs(codecs)) => {
-                    for codec in codecs {
-                        match codec.property {
-                            // FIXME: The encoder operates in image mode only.
-                            //
-                            // See [MS-RDPRFX] 3.1.1.1 "State Machine" for
-                            // implementation of the video mode. which allows to
-                            // skip sending Header for each image.
-                            //
-                            // We should distinguish parameters for both modes,
-                            // and somehow choose the "best", instead of picking
-                            // the last parsed here.
-                            CodecProperty::RemoteFx(rdp::capability_sets::RemoteFxContainer::ClientContainer(c))
-                                if self.opts.has_remote_fx() =>
-                            {
-                                for caps in c.caps_data.0 .0 {
-                                    update_codecs.set_remotefx(Some((caps.entropy_bits, codec.id)));
-                                }
-                            }
-                            CodecProperty::ImageRemoteFx(rdp::capability_sets::RemoteFxContainer::ClientContainer(
-                                c,
-                            )) if self.opts.has_image_remote_fx() => {
-                                for caps in c.caps_data.0 .0 {
-                                    update_codecs.set_remotefx(Some((caps.entropy_bits, codec.id)));
-                                }
-                            }
-                            CodecProperty::NsCodec(_) => (),
-                            #[cfg(feature = "qoi")]
-                            CodecProperty::Qoi if self.opts.has_qoi() => {
-                                update_codecs.set_qoi(Some(codec.id));
-                            }
-                            #[cfg(feature = "qoiz")]
-                            CodecProperty::QoiZ if self.opts.has_qoiz() => {
-                                update_codecs.set_qoiz(Some(codec.id));
-                            }
-                            _ => (),
-                        }
-                    }
+                CapabilitySet::WindowList(c) => match decode::<WindowListCapabilitySet>(&c) {
+                    Ok(c) => self.window_orders = c.support_level != WindowSupportLevel::NOT_SUPPORTED,
+                    Err(error) => warn!(?error, "Invalid window list capability set"),
+                },
+                CapabilitySet::BitmapCodecs(client_codecs) => {
+                    let negotiated = client_codecs.negotiate(&self.opts.codecs);
+                    debug!(?negotiated, "Negotiated bitmap codecs");
+                    update_codecs = UpdateEncoderCodecs::from_negotiated(&negotiated);
                 }
                 _ => {}
             }
         }
 
+        if let Some(bits_per_pixel) = bitmap_bits_per_pixel {
+            debug!(bits_per_pixel, "Client bitmap color depth");
+            update_codecs.set_bitmap_bits_per_pixel(bits_per_pixel);
+        }
+
+        // Lossy planar bitmaps are only sent to clients allowing them.
+        let mut bitmap_color_loss = self.opts.bitmap_color_loss;
+        if !bitmap_drawing_flags.contains(BitmapDrawingFlags::ALLOW_DYNAMIC_COLOR_FIDELITY) {
+            bitmap_color_loss = ColorLoss::NONE;
+        }
+        if !bitmap_drawing_flags.contains(BitmapDrawingFlags::ALLOW_COLOR_SUBSAMPLING) {
+            bitmap_color_loss.chroma_subsampling = false;
+        }
+        debug!(?bitmap_color_loss, "Planar bitmap color loss");
+        update_codecs.set_bitmap_color_loss(bitmap_color_loss);
+
+        // Compressed bitmap updates, possibly drawn from the bitmap cache, are lighter than uncompressed
+        // surface bits.
+        if surface_flags.contains(CmdFlags::SET_SURFACE_BITS) && !update_codecs.has_surface_codec() {
+            debug!("No surface codec negotiated, using bitmap updates");
+            surface_flags.remove(CmdFlags::SET_SURFACE_BITS);
+        }
+
+        debug!(?order_support, "Client drawing orders");
+        update_codecs.set_order_support(order_support);
+
+        let persistent_keys = core::mem::take(&mut self.persistent_keys);
+        match bitmap_cache.as_ref().filter(|_| mem_blt).and_then(BitmapCache::new) {
+            Some(mut cache) => {
+                for (cache_id, keys) in persistent_keys.iter().enumerate() {
+                    cache.load_persistent_keys(cache_id, keys);
+                }
+                debug!(?bitmap_cache, "Bitmap cache enabled");
+                update_codecs.set_bitmap_cache(Some(cache));
+            }
+            None => debug!("Bitmap cache is not supported by the client"),
+        }
+
         let desktop_size = self.display.lock().await.size().await;
+        if let Some(session_id) = self.session_id {
+            self.sessions.activate(session_id, desktop_size);
+        }
+
         let encoder = UpdateEncoder::new(desktop_size, surface_flags, update_codecs)
             .context("failed to initialize update encoder")?;
 
@@ -914,15 +1359,26 @@ impl RdpServer {
     }
 
     async fn handle_fastpath(&mut self, input: FastPathInput) {
+        if !self.has_control.load(Ordering::Relaxed) {
+            trace!("Dropping input from a client without control");
+            return;
+        }
+
         for event in input.input_events().iter().copied() {
             let mut handler = self.handler.lock().await;
             match event {
                 FastPathInputEvent::KeyboardEvent(flags, key) => {
-                    handler.keyboard((key, flags).into());
+                    let event = KeyboardEvent::from((key, flags));
+                    if self.policy.keyboard(&event) {
+                        handler.keyboard(event);
+                    }
                 }
 
                 FastPathInputEvent::UnicodeKeyboardEvent(flags, key) => {
-                    handler.keyboard((key, flags).into());
+                    let event = KeyboardEvent::from((key, flags));
+                    if self.policy.keyboard(&event) {
+                        handler.keyboard(event);
+                    }
                 }
 
                 FastPathInputEvent::SyncEvent(flags) => {
@@ -938,7 +1394,9 @@ impl RdpServer {
                 }
 
                 FastPathInputEvent::MouseEventRel(mouse) => {
-                    handler.mouse(mouse.into());
+                    for event in rel_mouse_events(mouse) {
+                        handler.mouse(event);
+                    }
                 }
 
                 FastPathInputEvent::QoeEvent(quality) => {
@@ -948,7 +1406,13 @@ impl RdpServer {
         }
     }
 
-    async fn handle_io_channel_data(&mut self, data: SendDataRequest<'_>) -> Result<bool> {
+    async fn handle_io_channel_data(
+        &mut self,
+        writer: &mut impl FramedWrite,
+        io_channel_id: u16,
+        user_channel_id: u16,
+        data: SendDataRequest<'_>,
+    ) -> Result<bool> {
         let control: rdp::headers::ShareControlHeader = decode(data.user_data.as_ref())?;
 
         match control.share_control_pdu {
@@ -961,6 +1425,33 @@ impl RdpServer {
                     return Ok(true);
                 }
 
+                rdp::headers::ShareDataPdu::Control(pdu) => {
+                    self.handle_control(writer, io_channel_id, user_channel_id, pdu).await?;
+                }
+
+                rdp::headers::ShareDataPdu::RefreshRectangle(pdu) => {
+                    self.request_output(OutputRequest::Refresh(pdu.areas_to_refresh));
+                }
+
+                rdp::headers::ShareDataPdu::SuppressOutput(pdu) => {
+                    self.request_output(match pdu.desktop_rect {
+                        Some(_) => OutputRequest::Resume,
+                        None => OutputRequest::Suppress,
+                    });
+                }
+
+                rdp::headers::ShareDataPdu::BitmapCachePersistentList(data) => {
+                    let pdu: PersistentKeyListPdu = decode(&data)?;
+                    debug!(total_entries = ?pdu.total_entries, flags = ?pdu.flags, "Received persistent key list");
+
+                    if pdu.flags.contains(PersistentKeyListFlags::PERSIST_FIRST_PDU) {
+                        self.persistent_keys.iter_mut().for_each(Vec::clear);
+                    }
+                    for (keys, entries) in self.persistent_keys.iter_mut().zip(pdu.entries) {
+                        keys.extend(entries);
+                    }
+                }
+
                 unexpected => {
                     warn!(?unexpected, "Unexpected share data pdu");
                 }
@@ -974,6 +1465,16 @@ impl RdpServer {
         Ok(false)
     }
 
+    fn request_output(&self, request: OutputRequest) {
+        let sent = self
+            .output_requests
+            .as_ref()
+            .is_some_and(|sender| sender.send(request).is_ok());
+        if !sent {
+            debug!("No display loop, dropping output request");
+        }
+    }
+
     async fn handle_x224(
         &mut self,
         writer: &mut impl FramedWrite,
@@ -986,7 +1487,9 @@ impl RdpServer {
             mcs::McsMessage::SendDataRequest(data) => {
                 debug!(?data, "McsMessage::SendDataRequest");
                 if data.channel_id == io_channel_id {
-                    return self.handle_io_channel_data(data).await;
+                    return self
+                        .handle_io_channel_data(writer, io_channel_id, user_channel_id, data)
+                        .await;
                 }
 
                 if let Some(svc) = self.static_channels.get_by_channel_id_mut(data.channel_id) {
@@ -1036,16 +1539,64 @@ impl RdpServer {
         Ok(false)
     }
 
+    async fn handle_control(
+        &mut self,
+        writer: &mut impl FramedWrite,
+        io_channel_id: u16,
+        user_channel_id: u16,
+        pdu: ControlPdu,
+    ) -> Result<()> {
+        match pdu.action {
+            ControlAction::RequestControl => {
+                let granted = !self.access.is_view_only() && self.handler.lock().await.request_control();
+                debug!(granted, "Client requested control");
+                self.has_control.store(granted, Ordering::Relaxed);
+
+                // When the request is denied, control stays with the server.
+                let grant_id = if granted { user_channel_id } else { SERVER_CHANNEL_ID };
+                send_control_granted(writer, io_channel_id, user_channel_id, grant_id).await?;
+            }
+
+            ControlAction::Detach => {
+                debug!("Client detached from control");
+                if self.has_control.swap(false, Ordering::Relaxed) {
+                    self.handler.lock().await.control_detached();
+                }
+            }
+
+            ControlAction::Cooperate => {
+                debug!("Client cooperates");
+            }
+
+            ControlAction::GrantedControl => {
+                warn!(?pdu, "Unexpected granted control from client");
+            }
+        }
+
+        Ok(())
+    }
+
     async fn handle_input_event(&mut self, input: InputEventPdu) {
+        if !self.has_control.load(Ordering::Relaxed) {
+            trace!("Dropping input from a client without control");
+            return;
+        }
+
         for event in input.0 {
             let mut handler = self.handler.lock().await;
             match event {
                 ironrdp_pdu::input::InputEvent::ScanCode(key) => {
-                    handler.keyboard((key.key_code, key.flags).into());
+                    let event = KeyboardEvent::from((key.key_code, key.flags));
+                    if self.policy.keyboard(&event) {
+                        handler.keyboard(event);
+                    }
                 }
 
                 ironrdp_pdu::input::InputEvent::Unicode(key) => {
-                    handler.keyboard((key.unicode_code, key.flags).into());
+                    let event = KeyboardEvent::from((key.unicode_code, key.flags));
+                    if self.policy.keyboard(&event) {
+                        handler.keyboard(event);
+                    }
                 }
 
                 ironrdp_pdu::input::InputEvent::Sync(sync) => {
@@ -1061,7 +1612,9 @@ impl RdpServer {
                 }
 
                 ironrdp_pdu::input::InputEvent::MouseRel(mouse) => {
-                    handler.mouse(mouse.into());
+                    for event in rel_mouse_events(mouse) {
+                        handler.mouse(event);
+                    }
                 }
 
                 ironrdp_pdu::input::InputEvent::Unused(_) => {}
@@ -1074,10 +1627,31 @@ impl RdpServer {
         S: AsyncRead + AsyncWrite + Sync + Send + Unpin,
     {
         loop {
-            let (new_framed, result) = ironrdp_acceptor::accept_finalize(framed, &mut acceptor)
+            let (new_framed, mut result) = ironrdp_acceptor::accept_finalize(framed, &mut acceptor)
                 .await
                 .context("failed to accept client during finalize")?;
 
+            if !result.reactivation {
+                if let Some(core_data) = acceptor.client_core_data() {
+                    let keyboard = ClientKeyboard::from(core_data);
+                    debug!(?keyboard, "Client keyboard");
+                    self.handler.lock().await.keyboard_layout(&keyboard);
+                }
+
+                let experience = acceptor
+                    .performance_flags()
+                    .map(VisualExperience::from)
+                    .unwrap_or_default();
+                self.display.lock().await.visual_experience(experience);
+                if let Some(rail) = result
+                    .static_channels
+                    .get_by_type_mut::<RailServer>()
+                    .and_then(|svc| svc.channel_processor_downcast_mut::<RailServer>())
+                {
+                    rail.set_visual_experience(experience);
+                }
+            }
+
             let (mut reader, mut writer) = split_tokio_framed(new_framed);
 
             match self.client_accepted(&mut reader, &mut writer, result).await? {
@@ -1117,6 +1691,69 @@ async fn deactivate_all(
     writer: &mut impl FramedWrite,
 ) -> Result<(), anyhow::Error> {
     let pdu = ShareControlPdu::ServerDeactivateAll(ServerDeactivateAll);
+    send_share_control(io_channel_id, user_channel_id, writer, pdu).await
+}
+
+async fn write_fragmenter(
+    writer: &mut impl FramedWrite,
+    buffer: &mut Vec<u8>,
+    mut fragmenter: UpdateFragmenter,
+    max_segment_size: Option<usize>,
+) -> Result<()> {
+    if let Some(segment_size) = max_segment_size {
+        fragmenter = fragmenter.with_segment_size(segment_size);
+    }
+
+    if fragmenter.size_hint() > buffer.len() {
+        buffer.resize(fragmenter.size_hint(), 0);
+    }
+
+    while let Some(len) = fragmenter.next(buffer) {
+        writer
+            .write_all(&buffer[..len])
+            .await
+            .context("failed to write display update")?;
+    }
+
+    Ok(())
+}
+
+async fn send_share_data(
+    io_channel_id: u16,
+    user_channel_id: u16,
+    writer: &mut impl FramedWrite,
+    pdu: rdp::headers::ShareDataPdu,
+) -> Result<(), anyhow::Error> {
+    let pdu = ShareControlPdu::Data(rdp::headers::ShareDataHeader {
+        share_data_pdu: pdu,
+        stream_priority: rdp::headers::StreamPriority::Medium,
+        compression_flags: rdp::headers::CompressionFlags::empty(),
+        compression_type: rdp::client_info::CompressionType::K8,
+    });
+    send_share_control(io_channel_id, user_channel_id, writer, pdu).await
+}
+
+async fn send_control_granted(
+    writer: &mut impl FramedWrite,
+    io_channel_id: u16,
+    user_channel_id: u16,
+    grant_id: u16,
+) -> Result<()> {
+    let pdu = rdp::headers::ShareDataPdu::Control(ControlPdu {
+        action: ControlAction::GrantedControl,
+        grant_id,
+        control_id: u32::from(SERVER_CHANNEL_ID),
+    });
+
+    send_share_data(io_channel_id, user_channel_id, writer, pdu).await
+}
+
+async fn send_share_control(
+    io_channel_id: u16,
+    user_channel_id: u16,
+    writer: &mut impl FramedWrite,
+    pdu: ShareControlPdu,
+) -> Result<(), anyhow::Error> {
     let pdu = rdp::headers::ShareControlHeader {
         share_id: 0,
         pdu_source: io_channel_id,
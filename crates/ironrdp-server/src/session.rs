@@ -0,0 +1,204 @@
+use core::fmt;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use ironrdp_dvc::DvcServerProcessor;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::{ConnectionContext, DesktopSize, ServerEvent};
+
+/// State of a session, see [`SessionInfo`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// The client is going through the connection sequence
+    Connecting,
+    /// The client is connected and receives the display updates
+    Active,
+}
+
+/// Session of a client, as negotiated during the connection sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// Identifier, security protocol, identity, access and channels of the session
+    pub context: ConnectionContext,
+    pub state: SessionState,
+    /// Size of the desktop sent to the client, once the session is active
+    pub desktop_size: Option<DesktopSize>,
+    pub started_at: SystemTime,
+}
+
+/// Dynamic channel opened during a session, see [`SessionRegistry::open_dvc`]
+pub struct DynamicChannel(Box<dyn DvcServerProcessor>);
+
+impl DynamicChannel {
+    pub fn new<T>(processor: T) -> Self
+    where
+        T: DvcServerProcessor + 'static,
+    {
+        Self(Box::new(processor))
+    }
+
+    pub(crate) fn into_processor(self) -> Box<dyn DvcServerProcessor> {
+        self.0
+    }
+}
+
+impl fmt::Debug for DynamicChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DynamicChannel").field(&self.0.channel_name()).finish()
+    }
+}
+
+/// Sessions of an [`RdpServer`](crate::RdpServer)
+///
+/// The registry is shared by the server and the handles returned by [`RdpServer::sessions`], e.g. to list the
+/// connected clients and disconnect them from an administration interface. Sessions are registered once the
+/// security protocol is negotiated, and removed when the client disconnects.
+///
+/// The session identifiers are the ones of the [`ConnectionContext`] given to the channel factories and to the
+/// [`RdpServerInputHandler`](crate::RdpServerInputHandler) callbacks, unique for the lifetime of the server.
+///
+/// [`RdpServer::sessions`]: crate::RdpServer::sessions
+#[derive(Debug, Clone)]
+pub struct SessionRegistry {
+    inner: Arc<Mutex<Sessions>>,
+    ev_sender: mpsc::UnboundedSender<ServerEvent>,
+}
+
+#[derive(Debug, Default)]
+struct Sessions {
+    next_session_id: u64,
+    sessions: BTreeMap<u64, SessionInfo>,
+}
+
+impl SessionRegistry {
+    pub(crate) fn new(ev_sender: mpsc::UnboundedSender<ServerEvent>) -> Self {
+        Self {
+            inner: Arc::default(),
+            ev_sender,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Sessions> {
+        self.inner.lock().expect("session registry mutex poisoned")
+    }
+
+    /// Assigns the identifier of a new session
+    pub(crate) fn next_session_id(&self) -> u64 {
+        let mut inner = self.lock();
+        let session_id = inner.next_session_id;
+        inner.next_session_id += 1;
+        session_id
+    }
+
+    pub(crate) fn insert(&self, context: ConnectionContext) {
+        let session = SessionInfo {
+            context,
+            state: SessionState::Connecting,
+            desktop_size: None,
+            started_at: SystemTime::now(),
+        };
+
+        self.lock().sessions.insert(session.context.session_id(), session);
+    }
+
+    /// The connection sequence is done, or the session is reactivated with a new desktop size
+    pub(crate) fn activate(&self, session_id: u64, desktop_size: DesktopSize) {
+        if let Some(session) = self.lock().sessions.get_mut(&session_id) {
+            session.state = SessionState::Active;
+            session.desktop_size = Some(desktop_size);
+        }
+    }
+
+    pub(crate) fn remove(&self, session_id: u64) {
+        self.lock().sessions.remove(&session_id);
+    }
+
+    /// Sessions of the connected clients, ordered by identifier
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        self.lock().sessions.values().cloned().collect()
+    }
+
+    pub fn get(&self, session_id: u64) -> Option<SessionInfo> {
+        self.lock().sessions.get(&session_id).cloned()
+    }
+
+    /// Disconnects the client of a session
+    ///
+    /// Returns `false` when there is no such session.
+    pub fn disconnect(&self, session_id: u64) -> bool {
+        if !self.lock().sessions.contains_key(&session_id) {
+            return false;
+        }
+
+        debug!(session_id, "Disconnecting session");
+        self.ev_sender.send(ServerEvent::Disconnect { session_id }).is_ok()
+    }
+
+    /// Opens a dynamic channel in a session, at any point once the client is connected
+    ///
+    /// Channels needed only on demand, e.g. for a file transfer, are opened when needed instead of with the other
+    /// channels. The client is sent the create request of the channel, whose processor then handles the messages as
+    /// the ones opened at startup.
+    ///
+    /// Returns `false` when there is no such session.
+    pub fn open_dvc<T>(&self, session_id: u64, processor: T) -> bool
+    where
+        T: DvcServerProcessor + 'static,
+    {
+        if !self.lock().sessions.contains_key(&session_id) {
+            return false;
+        }
+
+        let channel = DynamicChannel::new(processor);
+        debug!(session_id, ?channel, "Opening dynamic channel");
+        self.ev_sender
+            .send(ServerEvent::OpenDvc { session_id, channel })
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ironrdp_pdu::nego::SecurityProtocol;
+
+    use super::*;
+
+    fn context(session_id: u64) -> ConnectionContext {
+        ConnectionContext::new(session_id, None, SecurityProtocol::HYBRID, None)
+    }
+
+    #[test]
+    fn session_lifecycle() {
+        let (ev_sender, mut ev_receiver) = ServerEvent::create_channel();
+        let registry = SessionRegistry::new(ev_sender);
+
+        let session_id = registry.next_session_id();
+        assert_eq!(registry.next_session_id(), session_id + 1);
+
+        registry.insert(context(session_id));
+        assert_eq!(registry.get(session_id).unwrap().state, SessionState::Connecting);
+
+        let desktop_size = DesktopSize {
+            width: 1024,
+            height: 768,
+        };
+        registry.activate(session_id, desktop_size);
+        let sessions = registry.sessions();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].state, SessionState::Active);
+        assert_eq!(sessions[0].desktop_size, Some(desktop_size));
+
+        assert!(registry.disconnect(session_id));
+        assert!(matches!(
+            ev_receiver.try_recv(),
+            Ok(ServerEvent::Disconnect { session_id: id }) if id == session_id
+        ));
+
+        registry.remove(session_id);
+        assert!(registry.sessions().is_empty());
+        assert!(!registry.disconnect(session_id));
+    }
+}
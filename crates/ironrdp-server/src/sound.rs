@@ -1,7 +1,7 @@
-pub use ironrdp_rdpsnd::server::{RdpsndServerHandler, RdpsndServerMessage};
+pub use ironrdp_rdpsnd::server::{negotiate_format, AudioFrame, RdpsndServerHandler, RdpsndServerMessage};
 
-use crate::ServerEventSender;
+use crate::{ConnectionContext, ServerEventSender};
 
 pub trait SoundServerFactory: ServerEventSender {
-    fn build_backend(&self) -> Box<dyn RdpsndServerHandler>;
+    fn build_backend(&self, ctx: &ConnectionContext) -> Box<dyn RdpsndServerHandler>;
 }
@@ -0,0 +1,404 @@
+use tracing::{debug, warn};
+
+use crate::KeyboardEvent;
+
+/// Unicode keyboard event, as a character rather than the UTF-16 code units sent by the client
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnicodeKeyEvent {
+    Pressed(char),
+    Released(char),
+}
+
+impl UnicodeKeyEvent {
+    pub fn character(self) -> char {
+        match self {
+            UnicodeKeyEvent::Pressed(character) | UnicodeKeyEvent::Released(character) => character,
+        }
+    }
+
+    pub fn is_pressed(self) -> bool {
+        matches!(self, UnicodeKeyEvent::Pressed(_))
+    }
+
+    /// Inputs typing the character with `SendInput` on Windows
+    ///
+    /// The characters outside the Basic Multilingual Plane are made of two inputs, which must be sent in the same
+    /// `SendInput` call.
+    pub fn send_inputs(self) -> Vec<SendInputUnicode> {
+        let flags = if self.is_pressed() {
+            SendInputUnicode::KEYEVENTF_UNICODE
+        } else {
+            SendInputUnicode::KEYEVENTF_UNICODE | SendInputUnicode::KEYEVENTF_KEYUP
+        };
+
+        let mut buffer = [0u16; 2];
+        self.character()
+            .encode_utf16(&mut buffer)
+            .iter()
+            .map(|&scan| SendInputUnicode { scan, flags })
+            .collect()
+    }
+}
+
+/// Combines the UTF-16 code units of the Unicode keyboard events into characters
+///
+/// The characters outside the Basic Multilingual Plane are sent by the client as a surrogate pair: one event for
+/// each code unit, the high surrogate first. Unpaired surrogates are dropped.
+#[derive(Debug, Default)]
+pub struct UnicodeKeyDecoder {
+    pressed_high_surrogate: Option<u16>,
+    released_high_surrogate: Option<u16>,
+}
+
+impl UnicodeKeyDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the character pressed or released, if `event` completes one
+    ///
+    /// Returns `None` for the events which are not Unicode keyboard events.
+    pub fn decode(&mut self, event: &KeyboardEvent) -> Option<UnicodeKeyEvent> {
+        let (code, pending, make_event): (_, _, fn(char) -> UnicodeKeyEvent) = match *event {
+            KeyboardEvent::UnicodePressed(code) => (code, &mut self.pressed_high_surrogate, UnicodeKeyEvent::Pressed),
+            KeyboardEvent::UnicodeReleased(code) => {
+                (code, &mut self.released_high_surrogate, UnicodeKeyEvent::Released)
+            }
+            _ => return None,
+        };
+
+        let high_surrogate = pending.take();
+
+        let character = match code {
+            0xD800..=0xDBFF => {
+                if let Some(unpaired) = high_surrogate {
+                    debug!(surrogate = unpaired, "Unpaired high surrogate; ignored");
+                }
+                *pending = Some(code);
+                return None;
+            }
+            0xDC00..=0xDFFF => {
+                let Some(high_surrogate) = high_surrogate else {
+                    debug!(surrogate = code, "Unpaired low surrogate; ignored");
+                    return None;
+                };
+                char::decode_utf16([high_surrogate, code]).next()?.ok()?
+            }
+            _ => {
+                if let Some(unpaired) = high_surrogate {
+                    debug!(surrogate = unpaired, "Unpaired high surrogate; ignored");
+                }
+                char::from_u32(u32::from(code))?
+            }
+        };
+
+        Some(make_event(character))
+    }
+}
+
+/// `KEYBDINPUT` of a `SendInput` call typing a UTF-16 code unit, see [`UnicodeKeyEvent::send_inputs`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SendInputUnicode {
+    /// `wScan`, the UTF-16 code unit (the virtual key `wVk` is 0)
+    pub scan: u16,
+    /// `dwFlags`
+    pub flags: u32,
+}
+
+impl SendInputUnicode {
+    pub const KEYEVENTF_KEYUP: u32 = 0x0002;
+    pub const KEYEVENTF_UNICODE: u32 = 0x0004;
+}
+
+/// X11 keysym of a character
+///
+/// The Latin-1 characters have the same value, the control characters with a key (e.g. `'\r'`) are mapped to the
+/// keysym of that key, and the other characters to their Unicode keysym (`0x0100_0000` + code point).
+pub fn char_to_keysym(character: char) -> u32 {
+    match character {
+        '\u{8}' => 0xFF08,     // XK_BackSpace
+        '\t' => 0xFF09,        // XK_Tab
+        '\n' | '\r' => 0xFF0D, // XK_Return
+        '\u{1B}' => 0xFF1B,    // XK_Escape
+        '\u{7F}' => 0xFFFF,    // XK_Delete
+        ' '..='~' | '\u{A0}'..='\u{FF}' => u32::from(character),
+        _ => 0x0100_0000 + u32::from(character),
+    }
+}
+
+/// Step of the injection of a Unicode keyboard event with keycodes, see [`UnicodeKeymap`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeycodeInjection {
+    /// Maps the keycode to the keysym in the keymap of the host (e.g. `XChangeKeyboardMapping`)
+    ///
+    /// The keysym is `NoSymbol` (0) when the keycode is given back, see [`UnicodeKeymap::reset`].
+    Remap {
+        keycode: u8,
+        keysym: u32,
+    },
+    Press {
+        keycode: u8,
+    },
+    Release {
+        keycode: u8,
+    },
+}
+
+/// Spare keycodes of the host remapped on demand to type any character with XTEST or uinput
+///
+/// Unlike `SendInput`, these only inject keycodes, which are interpreted by the keymap of the host: the characters
+/// are typed by first mapping a spare keycode (one without keysym in the keymap of the host) to their keysym. The
+/// keycodes are X11 keycodes, the uinput (evdev) codes being 8 less. The least recently used keycode is remapped
+/// when all of them are mapped; a character can't be typed while all of them are held down.
+#[derive(Debug, Clone)]
+pub struct UnicodeKeymap {
+    slots: Vec<KeycodeSlot>,
+    clock: u64,
+}
+
+#[derive(Debug, Clone)]
+struct KeycodeSlot {
+    keycode: u8,
+    character: Option<char>,
+    pressed: bool,
+    last_use: u64,
+}
+
+impl UnicodeKeymap {
+    pub fn new(spare_keycodes: impl IntoIterator<Item = u8>) -> Self {
+        let slots = spare_keycodes
+            .into_iter()
+            .map(|keycode| KeycodeSlot {
+                keycode,
+                character: None,
+                pressed: false,
+                last_use: 0,
+            })
+            .collect();
+
+        Self { slots, clock: 0 }
+    }
+
+    /// Keycode mapped to the character, if any
+    pub fn keycode(&self, character: char) -> Option<u8> {
+        self.slots
+            .iter()
+            .find(|slot| slot.character == Some(character))
+            .map(|slot| slot.keycode)
+    }
+
+    /// Returns the steps injecting the event, in order
+    pub fn inject(&mut self, event: UnicodeKeyEvent) -> Vec<KeycodeInjection> {
+        self.clock += 1;
+
+        let character = event.character();
+        let mapped = self.slots.iter_mut().find(|slot| slot.character == Some(character));
+
+        match (event, mapped) {
+            (UnicodeKeyEvent::Pressed(_), Some(slot)) => {
+                slot.pressed = true;
+                slot.last_use = self.clock;
+                vec![KeycodeInjection::Press { keycode: slot.keycode }]
+            }
+            (UnicodeKeyEvent::Pressed(_), None) => {
+                let Some(slot) = self
+                    .slots
+                    .iter_mut()
+                    .filter(|slot| !slot.pressed)
+                    .min_by_key(|slot| slot.last_use)
+                else {
+                    warn!(?character, "No spare keycode to type the character; ignored");
+                    return Vec::new();
+                };
+
+                slot.character = Some(character);
+                slot.pressed = true;
+                slot.last_use = self.clock;
+
+                vec![
+                    KeycodeInjection::Remap {
+                        keycode: slot.keycode,
+                        keysym: char_to_keysym(character),
+                    },
+                    KeycodeInjection::Press { keycode: slot.keycode },
+                ]
+            }
+            (UnicodeKeyEvent::Released(_), Some(slot)) if slot.pressed => {
+                slot.pressed = false;
+                vec![KeycodeInjection::Release { keycode: slot.keycode }]
+            }
+            (UnicodeKeyEvent::Released(_), _) => Vec::new(),
+        }
+    }
+
+    /// Returns the steps releasing the keys held down and giving the keycodes back, e.g. when the client disconnects
+    pub fn reset(&mut self) -> Vec<KeycodeInjection> {
+        let mut injections = Vec::new();
+
+        for slot in &mut self.slots {
+            if core::mem::take(&mut slot.pressed) {
+                injections.push(KeycodeInjection::Release { keycode: slot.keycode });
+            }
+
+            if slot.character.take().is_some() {
+                injections.push(KeycodeInjection::Remap {
+                    keycode: slot.keycode,
+                    keysym: 0,
+                });
+            }
+        }
+
+        injections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(decoder: &mut UnicodeKeyDecoder, events: &[KeyboardEvent]) -> Vec<UnicodeKeyEvent> {
+        events.iter().filter_map(|event| decoder.decode(event)).collect()
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        let mut decoder = UnicodeKeyDecoder::new();
+
+        let events = decode_all(
+            &mut decoder,
+            &[
+                KeyboardEvent::UnicodePressed(0xD83D),
+                KeyboardEvent::UnicodePressed(0xDE00),
+                KeyboardEvent::UnicodeReleased(0xD83D),
+                KeyboardEvent::UnicodeReleased(0xDE00),
+                KeyboardEvent::UnicodePressed(0x00E9),
+            ],
+        );
+        assert_eq!(
+            events,
+            [
+                UnicodeKeyEvent::Pressed('\u{1F600}'),
+                UnicodeKeyEvent::Released('\u{1F600}'),
+                UnicodeKeyEvent::Pressed('\u{E9}'),
+            ]
+        );
+
+        // Unpaired surrogates are dropped, not the characters following them.
+        let events = decode_all(
+            &mut decoder,
+            &[
+                KeyboardEvent::UnicodePressed(0xDE00),
+                KeyboardEvent::UnicodePressed(0xD83D),
+                KeyboardEvent::UnicodePressed(0x0041),
+                KeyboardEvent::Pressed {
+                    code: 0x1E,
+                    extended: false,
+                },
+            ],
+        );
+        assert_eq!(events, [UnicodeKeyEvent::Pressed('A')]);
+    }
+
+    #[test]
+    fn send_inputs() {
+        assert_eq!(
+            UnicodeKeyEvent::Released('\u{1F600}').send_inputs(),
+            [
+                SendInputUnicode {
+                    scan: 0xD83D,
+                    flags: SendInputUnicode::KEYEVENTF_UNICODE | SendInputUnicode::KEYEVENTF_KEYUP,
+                },
+                SendInputUnicode {
+                    scan: 0xDE00,
+                    flags: SendInputUnicode::KEYEVENTF_UNICODE | SendInputUnicode::KEYEVENTF_KEYUP,
+                },
+            ]
+        );
+        assert_eq!(
+            UnicodeKeyEvent::Pressed('a').send_inputs(),
+            [SendInputUnicode {
+                scan: 0x61,
+                flags: SendInputUnicode::KEYEVENTF_UNICODE,
+            }]
+        );
+    }
+
+    #[test]
+    fn keysyms() {
+        assert_eq!(char_to_keysym('a'), 0x61);
+        assert_eq!(char_to_keysym('\u{E9}'), 0xE9);
+        assert_eq!(char_to_keysym('\r'), 0xFF0D);
+        assert_eq!(char_to_keysym('\u{20AC}'), 0x0100_20AC);
+        assert_eq!(char_to_keysym('\u{1F600}'), 0x0101_F600);
+    }
+
+    #[test]
+    fn keymap_remaps_least_recently_used_keycode() {
+        let mut keymap = UnicodeKeymap::new([200, 201]);
+
+        assert_eq!(
+            keymap.inject(UnicodeKeyEvent::Pressed('\u{65E5}')),
+            [
+                KeycodeInjection::Remap {
+                    keycode: 200,
+                    keysym: 0x0100_65E5
+                },
+                KeycodeInjection::Press { keycode: 200 },
+            ]
+        );
+        assert_eq!(
+            keymap.inject(UnicodeKeyEvent::Released('\u{65E5}')),
+            [KeycodeInjection::Release { keycode: 200 }]
+        );
+        assert_eq!(
+            keymap.inject(UnicodeKeyEvent::Pressed('\u{672C}')),
+            [
+                KeycodeInjection::Remap {
+                    keycode: 201,
+                    keysym: 0x0100_672C
+                },
+                KeycodeInjection::Press { keycode: 201 },
+            ]
+        );
+
+        // Already mapped.
+        assert_eq!(
+            keymap.inject(UnicodeKeyEvent::Pressed('\u{65E5}')),
+            [KeycodeInjection::Press { keycode: 200 }]
+        );
+
+        // Both keycodes are held down.
+        assert_eq!(keymap.inject(UnicodeKeyEvent::Pressed('a')), []);
+        assert_eq!(keymap.inject(UnicodeKeyEvent::Released('a')), []);
+
+        keymap.inject(UnicodeKeyEvent::Released('\u{672C}'));
+        assert_eq!(
+            keymap.inject(UnicodeKeyEvent::Pressed('a')),
+            [
+                KeycodeInjection::Remap {
+                    keycode: 201,
+                    keysym: 0x61
+                },
+                KeycodeInjection::Press { keycode: 201 },
+            ]
+        );
+        assert_eq!(keymap.keycode('\u{672C}'), None);
+
+        assert_eq!(
+            keymap.reset(),
+            [
+                KeycodeInjection::Release { keycode: 200 },
+                KeycodeInjection::Remap {
+                    keycode: 200,
+                    keysym: 0
+                },
+                KeycodeInjection::Release { keycode: 201 },
+                KeycodeInjection::Remap {
+                    keycode: 201,
+                    keysym: 0
+                },
+            ]
+        );
+        assert_eq!(keymap.reset(), []);
+    }
+}
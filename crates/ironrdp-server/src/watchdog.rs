@@ -0,0 +1,321 @@
+use core::fmt;
+use core::time::Duration;
+use std::sync::mpsc;
+use std::thread;
+
+use anyhow::{Context as _, Result};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, warn};
+
+use crate::{encode_gpu_frame, EncodedGpuFrame, GpuFrameUpdate, H264Encoder};
+
+/// Watches a hardware H.264 encoder, falling back to a software encoder when it stalls
+///
+/// A hardware encoder whose driver hangs never returns from its encode call, freezing the display of the
+/// client. The watchdog runs the hardware encoder on a dedicated thread and waits for each frame until the
+/// [frame deadline](Self::with_frame_deadline). When the deadline is missed, or the thread terminates
+/// unexpectedly, the hardware encoder is abandoned: the frame and the following ones are encoded by the
+/// software encoder, starting with a keyframe, and an [`EncoderEvent`] is reported.
+///
+/// The thread of a stalled encoder can't be stopped. It is detached, and exits if the encode call ever
+/// returns.
+///
+/// Encoding may block: [`encode`](Self::encode) is meant to be called from a blocking task.
+pub struct EncoderWatchdog {
+    hardware: Option<HardwareEncoder>,
+    software: Box<dyn H264Encoder>,
+    frame_deadline: Duration,
+    frames: u64,
+    event_sender: Option<UnboundedSender<EncoderEvent>>,
+}
+
+impl fmt::Debug for EncoderWatchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncoderWatchdog")
+            .field("fallback", &self.is_fallback())
+            .field("frame_deadline", &self.frame_deadline)
+            .field("frames", &self.frames)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Diagnostic event reported by the [`EncoderWatchdog`]
+///
+/// In both cases, the hardware encoder was replaced by the software encoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncoderEvent {
+    /// The hardware encoder didn't return a frame before the deadline
+    Stalled {
+        deadline: Duration,
+        /// Number of frames returned by the hardware encoder
+        frames: u64,
+    },
+    /// The thread of the hardware encoder terminated, e.g. because the encoder panicked
+    Terminated {
+        /// Number of frames returned by the hardware encoder
+        frames: u64,
+    },
+}
+
+enum Job {
+    Frame(GpuFrameUpdate),
+    Keyframe,
+}
+
+struct HardwareEncoder {
+    jobs: mpsc::Sender<Job>,
+    results: mpsc::Receiver<Result<EncodedGpuFrame>>,
+}
+
+impl EncoderWatchdog {
+    /// Default deadline of a frame, generous enough for the initialization of the hardware encoder on the first frame
+    pub const DEFAULT_FRAME_DEADLINE: Duration = Duration::from_secs(2);
+
+    /// Starts the thread of the hardware encoder
+    ///
+    /// `software` encodes the frames once the hardware encoder is abandoned.
+    pub fn new(hardware: Box<dyn H264Encoder>, software: Box<dyn H264Encoder>) -> Result<Self> {
+        let (job_sender, jobs) = mpsc::channel();
+        let (result_sender, results) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("h264-encoder".to_owned())
+            .spawn(move || run_hardware_encoder(hardware, jobs, result_sender))
+            .context("failed to spawn the hardware encoder thread")?;
+
+        Ok(Self {
+            hardware: Some(HardwareEncoder {
+                jobs: job_sender,
+                results,
+            }),
+            software,
+            frame_deadline: Self::DEFAULT_FRAME_DEADLINE,
+            frames: 0,
+            event_sender: None,
+        })
+    }
+
+    /// Sets the maximum time the hardware encoder may take to encode a frame
+    #[must_use]
+    pub fn with_frame_deadline(mut self, frame_deadline: Duration) -> Self {
+        self.frame_deadline = frame_deadline;
+        self
+    }
+
+    /// Sets the channel receiving the [`EncoderEvent`]s
+    #[must_use]
+    pub fn with_event_sender(mut self, event_sender: UnboundedSender<EncoderEvent>) -> Self {
+        self.event_sender = Some(event_sender);
+        self
+    }
+
+    /// Whether the hardware encoder was abandoned for the software encoder
+    pub fn is_fallback(&self) -> bool {
+        self.hardware.is_none()
+    }
+
+    /// Encodes the next frame as an IDR frame
+    pub fn request_keyframe(&mut self) {
+        match &self.hardware {
+            // A terminated thread is detected on the next frame.
+            Some(hardware) => {
+                let _ = hardware.jobs.send(Job::Keyframe);
+            }
+            None => self.software.request_keyframe(),
+        }
+    }
+
+    /// Encodes a frame, see [`encode_gpu_frame`]
+    ///
+    /// Errors of the hardware encoder are returned as is: the encoder is only abandoned when it stalls.
+    pub fn encode(&mut self, frame: &GpuFrameUpdate) -> Result<EncodedGpuFrame> {
+        if let Some(hardware) = &self.hardware {
+            let event = match hardware.encode(frame, self.frame_deadline) {
+                Ok(result) => {
+                    self.frames += 1;
+                    return result;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => EncoderEvent::Stalled {
+                    deadline: self.frame_deadline,
+                    frames: self.frames,
+                },
+                Err(mpsc::RecvTimeoutError::Disconnected) => EncoderEvent::Terminated { frames: self.frames },
+            };
+
+            self.fall_back(event);
+        }
+
+        encode_gpu_frame(self.software.as_mut(), frame)
+    }
+
+    fn fall_back(&mut self, event: EncoderEvent) {
+        warn!(?event, "Hardware encoder failed, falling back to the software encoder");
+
+        // Dropping the channels detaches the thread, which exits if the encoder ever returns.
+        self.hardware = None;
+        self.software.request_keyframe();
+
+        if let Some(event_sender) = &self.event_sender {
+            let _ = event_sender.send(event);
+        }
+    }
+}
+
+impl HardwareEncoder {
+    fn encode(
+        &self,
+        frame: &GpuFrameUpdate,
+        deadline: Duration,
+    ) -> Result<Result<EncodedGpuFrame>, mpsc::RecvTimeoutError> {
+        if self.jobs.send(Job::Frame(frame.clone())).is_err() {
+            return Err(mpsc::RecvTimeoutError::Disconnected);
+        }
+
+        self.results.recv_timeout(deadline)
+    }
+}
+
+fn run_hardware_encoder(
+    mut encoder: Box<dyn H264Encoder>,
+    jobs: mpsc::Receiver<Job>,
+    results: mpsc::Sender<Result<EncodedGpuFrame>>,
+) {
+    for job in jobs {
+        match job {
+            Job::Frame(frame) => {
+                let result = encode_gpu_frame(encoder.as_mut(), &frame).context("hardware encoder failed");
+
+                if results.send(result).is_err() {
+                    break;
+                }
+            }
+            Job::Keyframe => encoder.request_keyframe(),
+        }
+    }
+
+    debug!("Hardware encoder thread terminated");
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::{NonZeroU16, NonZeroUsize};
+    use std::sync::{Arc, Mutex};
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{BitmapUpdate, GpuSurface, GpuSurfaceHandle, PixelFormat};
+
+    #[derive(Debug)]
+    struct Frame;
+
+    impl GpuSurface for Frame {
+        fn width(&self) -> NonZeroU16 {
+            NonZeroU16::new(1).unwrap()
+        }
+
+        fn height(&self) -> NonZeroU16 {
+            NonZeroU16::new(1).unwrap()
+        }
+
+        fn handle(&self) -> GpuSurfaceHandle<'_> {
+            GpuSurfaceHandle::VaSurface { surface_id: 1 }
+        }
+
+        fn read_back(&self) -> Result<BitmapUpdate> {
+            Ok(BitmapUpdate {
+                x: 0,
+                y: 0,
+                width: self.width(),
+                height: self.height(),
+                format: PixelFormat::BgrX32,
+                data: Bytes::from_static(&[0; 4]),
+                stride: NonZeroUsize::new(4).unwrap(),
+            })
+        }
+    }
+
+    /// Hardware encoder encoding frames from the surface until `stall` is set
+    struct Hardware {
+        stall: Arc<Mutex<bool>>,
+    }
+
+    impl H264Encoder for Hardware {
+        fn encode_bitmap(&mut self, _bitmap: &BitmapUpdate) -> Result<Vec<u8>> {
+            unreachable!()
+        }
+
+        fn encode_surface(&mut self, _surface: &dyn GpuSurface) -> Result<Option<Vec<u8>>> {
+            if *self.stall.lock().unwrap() {
+                thread::sleep(Duration::from_secs(1));
+            }
+
+            Ok(Some(vec![1]))
+        }
+    }
+
+    struct Software {
+        keyframes: Arc<Mutex<u32>>,
+    }
+
+    impl H264Encoder for Software {
+        fn encode_bitmap(&mut self, _bitmap: &BitmapUpdate) -> Result<Vec<u8>> {
+            Ok(vec![2])
+        }
+
+        fn request_keyframe(&mut self) {
+            *self.keyframes.lock().unwrap() += 1;
+        }
+    }
+
+    fn frame() -> GpuFrameUpdate {
+        GpuFrameUpdate {
+            x: 0,
+            y: 0,
+            surface: Arc::new(Frame),
+        }
+    }
+
+    #[test]
+    fn falls_back_on_stall() {
+        let stall = Arc::new(Mutex::new(false));
+        let keyframes = Arc::new(Mutex::new(0));
+        let (event_sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+
+        let mut watchdog = EncoderWatchdog::new(
+            Box::new(Hardware {
+                stall: Arc::clone(&stall),
+            }),
+            Box::new(Software {
+                keyframes: Arc::clone(&keyframes),
+            }),
+        )
+        .unwrap()
+        .with_frame_deadline(Duration::from_millis(50))
+        .with_event_sender(event_sender);
+
+        let encoded = watchdog.encode(&frame()).unwrap();
+        assert_eq!(encoded.data, [1]);
+        assert!(encoded.zero_copy);
+        assert!(!watchdog.is_fallback());
+
+        *stall.lock().unwrap() = true;
+
+        for _ in 0..2 {
+            let encoded = watchdog.encode(&frame()).unwrap();
+            assert_eq!(encoded.data, [2]);
+            assert!(!encoded.zero_copy);
+        }
+
+        assert!(watchdog.is_fallback());
+        assert_eq!(*keyframes.lock().unwrap(), 1);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            EncoderEvent::Stalled {
+                deadline: Duration::from_millis(50),
+                frames: 1,
+            }
+        );
+        assert!(events.try_recv().is_err());
+    }
+}
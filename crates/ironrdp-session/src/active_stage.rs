@@ -1,14 +1,22 @@
+use core::time::Duration;
 use std::sync::Arc;
 
 use ironrdp_connector::connection_activation::ConnectionActivationSequence;
-use ironrdp_connector::ConnectionResult;
-use ironrdp_core::WriteBuf;
+use ironrdp_connector::{ConnectionResult, DesktopSize};
+use ironrdp_core::{EncodeResult, WriteBuf};
 use ironrdp_displaycontrol::client::DisplayControlClient;
-use ironrdp_dvc::{DrdynvcClient, DvcProcessor, DynamicVirtualChannel};
+use ironrdp_displaycontrol::pdu::DisplayControlMonitorLayout;
+use ironrdp_dvc::{ChannelStall, DrdynvcClient, DvcProcessor, DynamicVirtualChannel};
 use ironrdp_graphics::pointer::DecodedPointer;
 use ironrdp_pdu::geometry::InclusiveRectangle;
 use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp_pdu::orders::WindowOrder;
+use ironrdp_pdu::rdp::finalization_messages::{ControlAction, ControlPdu};
 use ironrdp_pdu::rdp::headers::ShareDataPdu;
+use ironrdp_pdu::rdp::refresh_rectangle::RefreshRectanglePdu;
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
+use ironrdp_pdu::rdp::session_info::ServerAutoReconnect;
+use ironrdp_pdu::rdp::suppress_output::SuppressOutputPdu;
 use ironrdp_pdu::{mcs, Action};
 use ironrdp_svc::{SvcMessage, SvcProcessor, SvcProcessorMessages};
 use tracing::debug;
@@ -21,10 +29,14 @@ pub struct ActiveStage {
     x224_processor: x224::Processor,
     fast_path_processor: fast_path::Processor,
     enable_server_pointer: bool,
+    relative_mouse_supported: bool,
+    desktop_size: DesktopSize,
 }
 
 impl ActiveStage {
     pub fn new(connection_result: ConnectionResult) -> Self {
+        let relative_mouse_supported = connection_result.connection_activation.relative_mouse_supported();
+
         let x224_processor = x224::Processor::new(
             connection_result.static_channels,
             connection_result.user_channel_id,
@@ -44,6 +56,8 @@ impl ActiveStage {
             x224_processor,
             fast_path_processor,
             enable_server_pointer: connection_result.enable_server_pointer,
+            relative_mouse_supported,
+            desktop_size: connection_result.desktop_size,
         }
     }
 
@@ -66,9 +80,25 @@ impl ActiveStage {
         // response frame + graphics update
         let mut output = Vec::with_capacity(2);
 
-        // Encoding fastpath response frame
         // PERF: unnecessary copy
-        let fastpath_input = FastPathInput::new(events.to_vec()).map_err(SessionError::decode)?;
+        let mut events = events.to_vec();
+
+        // Relative mouse events are only sent to servers accepting them
+        if !self.relative_mouse_supported {
+            let len = events.len();
+            events.retain(|event| !matches!(event, FastPathInputEvent::MouseEventRel(_)));
+
+            if events.len() != len {
+                debug!("Dropping relative mouse events, not supported by the server");
+            }
+
+            if events.is_empty() {
+                return Ok(Vec::new());
+            }
+        }
+
+        // Encoding fastpath response frame
+        let fastpath_input = FastPathInput::new(events).map_err(SessionError::decode)?;
         let frame = ironrdp_core::encode_vec(&fastpath_input).map_err(SessionError::encode)?;
         output.push(ActiveStageOutput::ResponseFrame(frame));
 
@@ -79,7 +109,7 @@ impl ActiveStage {
 
         // If mouse was moved by client - we should update framebuffer to reflect new
         // pointer position
-        let mouse_pos = events.iter().find_map(|event| match event {
+        let mouse_pos = fastpath_input.input_events().iter().find_map(|event| match event {
             FastPathInputEvent::MouseEvent(event) => Some((event.x_position, event.y_position)),
             FastPathInputEvent::MouseEventEx(event) => Some((event.x_position, event.y_position)),
             _ => None,
@@ -143,6 +173,9 @@ impl ActiveStage {
                 UpdateKind::PointerBitmap(pointer) => {
                     stage_outputs.push(ActiveStageOutput::PointerBitmap(pointer));
                 }
+                UpdateKind::WindowOrders(orders) => {
+                    stage_outputs.push(ActiveStageOutput::WindowOrders(orders));
+                }
             }
         }
 
@@ -157,6 +190,31 @@ impl ActiveStage {
         self.enable_server_pointer = enable_server_pointer;
     }
 
+    /// Whether the server accepts relative mouse events, e.g. `ironrdp_input::Operation::MouseRelMove`
+    ///
+    /// Relative mouse events are dropped by [`Self::process_fastpath_input`] otherwise.
+    pub fn relative_mouse_supported(&self) -> bool {
+        self.relative_mouse_supported
+    }
+
+    /// Updates the support of relative mouse events after a Deactivation-Reactivation Sequence
+    pub fn set_relative_mouse_supported(&mut self, relative_mouse_supported: bool) {
+        self.relative_mouse_supported = relative_mouse_supported;
+    }
+
+    /// Sets the desktop size, after a Deactivation-Reactivation Sequence.
+    pub fn set_desktop_size(&mut self, desktop_size: DesktopSize) {
+        self.desktop_size = desktop_size;
+    }
+
+    /// Returns the latest auto-reconnect cookie sent by the server
+    ///
+    /// Set as [`Config::auto_reconnect_cookie`](ironrdp_connector::Config::auto_reconnect_cookie), it allows
+    /// the client to rejoin the session after the connection is lost, without prompting for the credentials again.
+    pub fn auto_reconnect_cookie(&self) -> Option<&ServerAutoReconnect> {
+        self.x224_processor.auto_reconnect_cookie()
+    }
+
     /// Encodes client-side graceful shutdown request. Note that upon sending this request,
     /// client should wait for server's ShutdownDenied PDU before closing the connection.
     ///
@@ -164,9 +222,62 @@ impl ActiveStage {
     ///
     /// [MS-RDPBCGR]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/27915739-8f77-487e-9927-55008af7fd68
     pub fn graceful_shutdown(&self) -> SessionResult<Vec<ActiveStageOutput>> {
+        self.encode_share_data(ShareDataPdu::ShutdownRequest)
+    }
+
+    /// Encodes a request for control of the session, answered by [`ActiveStageOutput::Control`].
+    ///
+    /// Control is granted during the connection sequence. Requesting it again is useful when sharing
+    /// a session, e.g. for a shadowing viewer which detached from control.
+    pub fn request_control(&self) -> SessionResult<Vec<ActiveStageOutput>> {
+        self.encode_control(ControlAction::RequestControl)
+    }
+
+    /// Encodes a notification that the client gives up control of the session.
+    pub fn detach_control(&self) -> SessionResult<Vec<ActiveStageOutput>> {
+        self.encode_control(ControlAction::Detach)
+    }
+
+    fn encode_control(&self, action: ControlAction) -> SessionResult<Vec<ActiveStageOutput>> {
+        self.encode_share_data(ShareDataPdu::Control(ControlPdu {
+            action,
+            grant_id: 0,
+            control_id: 0,
+        }))
+    }
+
+    /// Encodes a request for the server to redraw areas of the session screen.
+    ///
+    /// Useful to repaint the session after losing the rendering surface. Up to 255 areas can be
+    /// requested at once.
+    pub fn request_refresh(&self, areas: &[InclusiveRectangle]) -> SessionResult<Vec<ActiveStageOutput>> {
+        if areas.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.encode_share_data(ShareDataPdu::RefreshRectangle(RefreshRectanglePdu {
+            areas_to_refresh: areas.to_vec(),
+        }))
+    }
+
+    /// Encodes a request for the server to stop, or resume, sending display updates.
+    ///
+    /// Typically sent when the client window is minimized, respectively restored. The server is
+    /// expected to send a full update when resuming.
+    pub fn suppress_output(&self, suppress: bool) -> SessionResult<Vec<ActiveStageOutput>> {
+        let desktop_rect = (!suppress).then(|| InclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: self.desktop_size.width.saturating_sub(1),
+            bottom: self.desktop_size.height.saturating_sub(1),
+        });
+
+        self.encode_share_data(ShareDataPdu::SuppressOutput(SuppressOutputPdu { desktop_rect }))
+    }
+
+    fn encode_share_data(&self, pdu: ShareDataPdu) -> SessionResult<Vec<ActiveStageOutput>> {
         let mut frame = WriteBuf::new();
-        self.x224_processor
-            .encode_static(&mut frame, ShareDataPdu::ShutdownRequest)?;
+        self.x224_processor.encode_static(&mut frame, pdu)?;
 
         Ok(vec![ActiveStageOutput::ResponseFrame(frame.into_inner())])
     }
@@ -192,6 +303,18 @@ impl ActiveStage {
         self.x224_processor.get_dvc_by_channel_id(channel_id)
     }
 
+    /// Registers the processor of a dynamic channel once the session is established
+    ///
+    /// Returns `false` when the DRDYNVC static channel is not enabled. See [`DrdynvcClient::attach_dynamic_channel`].
+    pub fn attach_dynamic_channel<T: DvcProcessor + 'static>(&mut self, channel: T) -> bool {
+        let Some(drdynvc) = self.get_svc_processor_mut::<DrdynvcClient>() else {
+            return false;
+        };
+
+        drdynvc.attach_dynamic_channel(channel);
+        true
+    }
+
     /// Completes user's SVC request with data, required to sent it over the network and returns
     /// a buffer with encoded data.
     pub fn process_svc_processor_messages<C: SvcProcessor + 'static>(
@@ -222,17 +345,31 @@ impl ActiveStage {
         height: u32,
         scale_factor: Option<u32>,
         physical_dims: Option<(u32, u32)>,
+    ) -> Option<SessionResult<Vec<u8>>> {
+        self.encode_display_control(|display_control, channel_id| {
+            display_control.encode_single_primary_monitor(channel_id, width, height, scale_factor, physical_dims)
+        })
+    }
+
+    /// Fully encodes a monitor layout for sending over the Display Control Virtual Channel.
+    ///
+    /// Like [`Self::encode_resize`], with a position, an orientation and scale factors per monitor. For instance, a
+    /// client rotating its display sends the monitor with its new dimensions and
+    /// [`ironrdp_displaycontrol::pdu::MonitorOrientation`].
+    pub fn encode_monitor_layout(&mut self, layout: DisplayControlMonitorLayout) -> Option<SessionResult<Vec<u8>>> {
+        self.encode_display_control(|display_control, channel_id| {
+            display_control.encode_monitor_layout(channel_id, layout)
+        })
+    }
+
+    fn encode_display_control(
+        &mut self,
+        encode: impl FnOnce(&DisplayControlClient, u32) -> EncodeResult<Vec<SvcMessage>>,
     ) -> Option<SessionResult<Vec<u8>>> {
         if let Some(dvc) = self.get_dvc::<DisplayControlClient>() {
             if let Some(channel_id) = dvc.channel_id() {
                 let display_control = dvc.channel_processor_downcast_ref::<DisplayControlClient>()?;
-                let svc_messages = match display_control.encode_single_primary_monitor(
-                    channel_id,
-                    width,
-                    height,
-                    scale_factor,
-                    physical_dims,
-                ) {
+                let svc_messages = match encode(display_control, channel_id) {
                     Ok(messages) => messages,
                     Err(e) => return Some(Err(SessionError::encode(e))),
                 };
@@ -253,6 +390,26 @@ impl ActiveStage {
     pub fn encode_dvc_messages(&mut self, messages: Vec<SvcMessage>) -> SessionResult<Vec<u8>> {
         self.process_svc_processor_messages(SvcProcessorMessages::<DrdynvcClient>::new(messages))
     }
+
+    /// Fully encodes the recovery messages of the stalled dynamic channels, and the keepalives.
+    ///
+    /// Must be called periodically, `elapsed` being the time since the previous call. See
+    /// [`DrdynvcClient::poll_timeouts`].
+    pub fn poll_dvc_timeouts(&mut self, elapsed: Duration) -> SessionResult<(Vec<u8>, Vec<ChannelStall>)> {
+        let Some(drdynvc) = self.get_svc_processor_mut::<DrdynvcClient>() else {
+            return Ok((Vec::new(), Vec::new()));
+        };
+
+        let (messages, stalls) = drdynvc.poll_timeouts(elapsed).map_err(SessionError::pdu)?;
+
+        let frame = if messages.is_empty() {
+            Vec::new()
+        } else {
+            self.encode_dvc_messages(messages)?
+        };
+
+        Ok((frame, stalls))
+    }
 }
 
 #[derive(Debug)]
@@ -265,6 +422,9 @@ pub enum ActiveStageOutput {
     PointerBitmap(Arc<DecodedPointer>),
     Terminate(GracefulDisconnectReason),
     DeactivateAll(Box<ConnectionActivationSequence>),
+    Control(x224::ControlStatus),
+    WindowOrders(Vec<WindowOrder>),
+    ServerRedirection(Box<ServerRedirectionPdu>),
 }
 
 impl TryFrom<x224::ProcessorOutput> for ActiveStageOutput {
@@ -286,6 +446,8 @@ impl TryFrom<x224::ProcessorOutput> for ActiveStageOutput {
                 Ok(Self::Terminate(desc))
             }
             x224::ProcessorOutput::DeactivateAll(cas) => Ok(Self::DeactivateAll(cas)),
+            x224::ProcessorOutput::Control(status) => Ok(Self::Control(status)),
+            x224::ProcessorOutput::ServerRedirection(redirection) => Ok(Self::ServerRedirection(redirection)),
         }
     }
 }
@@ -0,0 +1,126 @@
+//! Client side of the network characteristics detection ([MS-RDPBCGR] 2.2.14)
+//!
+//! The server measures the round-trip time and the bandwidth with auto-detect requests sent on the
+//! message channel, and sends the results back to the client. [`AutoDetectResponder`] answers the
+//! requests, and keeps the last results, e.g. for the [overlay](crate::overlay) or to pick a codec.
+//!
+//! The time is passed by the caller, typically `Instant::now()`.
+
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_pdu::rdp::autodetect::{AutoDetectPhase, AutoDetectRequest, AutoDetectResponse, BandwidthPhase};
+use tracing::debug;
+
+/// Results of the measures of the server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkCharacteristics {
+    /// Lowest round-trip time measured
+    pub base_rtt: Option<Duration>,
+    pub average_rtt: Option<Duration>,
+    /// Bandwidth in kilobits per second
+    pub bandwidth: Option<u32>,
+}
+
+/// Answers the auto-detect requests of the server
+#[derive(Debug, Clone, Default)]
+pub struct AutoDetectResponder {
+    measure: Option<BandwidthMeasure>,
+    characteristics: NetworkCharacteristics,
+}
+
+#[derive(Debug, Clone)]
+struct BandwidthMeasure {
+    start: Instant,
+    byte_count: u32,
+}
+
+impl AutoDetectResponder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Last results sent by the server
+    pub fn network_characteristics(&self) -> NetworkCharacteristics {
+        self.characteristics
+    }
+
+    /// Counts data received from the server for the bandwidth measure in progress, if any
+    ///
+    /// During the session, the bandwidth is measured over all the data sent by the server between the
+    /// start and the stop of the measure: call this for each PDU received, with its length on the wire.
+    pub fn bytes_received(&mut self, len: usize) {
+        if let Some(measure) = &mut self.measure {
+            measure.byte_count = measure
+                .byte_count
+                .saturating_add(u32::try_from(len).unwrap_or(u32::MAX));
+        }
+    }
+
+    /// Handles a request of the server, received at `now`, and returns the response to send, if any
+    ///
+    /// `len` is the length of the request on the wire, counted in the bandwidth measure in progress.
+    pub fn handle_request(
+        &mut self,
+        request: &AutoDetectRequest,
+        len: usize,
+        now: Instant,
+    ) -> Option<AutoDetectResponse> {
+        match request {
+            AutoDetectRequest::RttRequest { sequence_number, .. } => Some(AutoDetectResponse::RttResponse {
+                sequence_number: *sequence_number,
+            }),
+            AutoDetectRequest::BandwidthStart { .. } => {
+                self.measure = Some(BandwidthMeasure {
+                    start: now,
+                    byte_count: 0,
+                });
+
+                None
+            }
+            AutoDetectRequest::BandwidthPayload { .. } => {
+                self.bytes_received(len);
+
+                None
+            }
+            AutoDetectRequest::BandwidthStop {
+                sequence_number, phase, ..
+            } => {
+                self.bytes_received(len);
+
+                let Some(measure) = self.measure.take() else {
+                    debug!(sequence_number, "Bandwidth measure stopped without being started");
+                    return None;
+                };
+
+                let time_delta = now.saturating_duration_since(measure.start).as_millis();
+
+                Some(AutoDetectResponse::BandwidthResults {
+                    sequence_number: *sequence_number,
+                    phase: match phase {
+                        BandwidthPhase::Connection => AutoDetectPhase::Connection,
+                        BandwidthPhase::Session | BandwidthPhase::Tunnel => AutoDetectPhase::Session,
+                    },
+                    time_delta: u32::try_from(time_delta).unwrap_or(u32::MAX),
+                    byte_count: measure.byte_count,
+                })
+            }
+            AutoDetectRequest::NetworkCharacteristicsResult {
+                base_rtt,
+                bandwidth,
+                average_rtt,
+                ..
+            } => {
+                self.characteristics = NetworkCharacteristics {
+                    base_rtt: base_rtt.map(|rtt| Duration::from_millis(u64::from(rtt))),
+                    average_rtt: Some(Duration::from_millis(u64::from(*average_rtt))),
+                    bandwidth: *bandwidth,
+                };
+
+                debug!(characteristics = ?self.characteristics, "Network characteristics detected");
+
+                None
+            }
+        }
+    }
+}
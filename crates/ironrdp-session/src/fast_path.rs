@@ -8,6 +8,7 @@ use ironrdp_graphics::rle::RlePixelFormat;
 use ironrdp_pdu::codecs::rfx::FrameAcknowledgePdu;
 use ironrdp_pdu::fast_path::{FastPathHeader, FastPathUpdate, FastPathUpdatePdu, Fragmentation};
 use ironrdp_pdu::geometry::{InclusiveRectangle, Rectangle as _};
+use ironrdp_pdu::orders::{DrawingOrder, WindowOrder};
 use ironrdp_pdu::pointer::PointerUpdateData;
 use ironrdp_pdu::rdp::capability_sets::{CodecId, CODEC_ID_NONE, CODEC_ID_REMOTEFX};
 use ironrdp_pdu::rdp::headers::ShareDataPdu;
@@ -26,6 +27,7 @@ pub enum UpdateKind {
     PointerHidden,
     PointerPosition { x: u16, y: u16 },
     PointerBitmap(Arc<DecodedPointer>),
+    WindowOrders(Vec<WindowOrder>),
 }
 
 pub struct Processor {
@@ -83,6 +85,20 @@ impl Processor {
         let update = FastPathUpdate::decode_with_code(data.as_slice(), update_code);
 
         match update {
+            Ok(FastPathUpdate::Orders(orders)) => {
+                trace!("Received orders: {} pieces", orders.orders.len());
+                let mut window_orders = Vec::new();
+                for order in orders.orders {
+                    match order {
+                        DrawingOrder::Window(order) => window_orders.push(order),
+                        order => debug!(?order, "Ignored drawing order"),
+                    }
+                }
+
+                if !window_orders.is_empty() {
+                    processor_updates.push(UpdateKind::WindowOrders(window_orders));
+                }
+            }
             Ok(FastPathUpdate::SurfaceCommands(surface_commands)) => {
                 trace!("Received Surface Commands: {} pieces", surface_commands.len());
                 let update_region = self.process_surface_commands(image, output, surface_commands)?;
@@ -0,0 +1,238 @@
+//! Connection health monitoring
+//!
+//! [`HealthMonitor`] watches the liveness of the connection, and reports changes with [`HealthEvent`]s, e.g. to
+//! show a "connection unstable" indicator or to start a reconnection:
+//!
+//! - the heartbeats of the server ([MS-RDPBCGR] 2.2.16.1), the server telling how many heartbeats can be
+//!   missed before warning the user and before reconnecting,
+//! - the jitter of the round-trip time measures, e.g. from the [auto-detect responder](crate::autodetect),
+//! - the virtual channels waiting for an answer of the server for too long.
+//!
+//! The time is passed by the caller, typically `Instant::now()`. [`HealthMonitor::poll()`] is called
+//! periodically, e.g. every second, to detect missed heartbeats and stalled channels.
+
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+use tracing::{debug, warn};
+
+/// Health of the connection
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ConnectionHealth {
+    #[default]
+    Healthy,
+    /// Heartbeats are missing, the user should be warned
+    Unstable,
+    /// Too many heartbeats are missing, the client should reconnect
+    Lost,
+}
+
+/// Change of the health of the connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthEvent {
+    /// The connection became unstable after missing `missed_heartbeats` heartbeats
+    Unstable { missed_heartbeats: u32 },
+    /// The connection is considered lost after missing `missed_heartbeats` heartbeats, a reconnection is advised
+    ReconnectAdvised { missed_heartbeats: u32 },
+    /// Heartbeats are received again
+    Recovered,
+    /// The jitter of the round-trip time went over the threshold
+    HighJitter { jitter: Duration },
+    /// The channel has been waiting for an answer of the server for `waiting`
+    ChannelStalled { channel_id: u16, waiting: Duration },
+    /// A stalled channel received data again
+    ChannelResumed { channel_id: u16 },
+}
+
+/// Measurements of a [`HealthMonitor`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HealthMetrics {
+    pub health: ConnectionHealth,
+    pub missed_heartbeats: u32,
+    pub last_rtt: Option<Duration>,
+    /// Smoothed variation of the round-trip time (RFC 3550)
+    pub jitter: Duration,
+    pub stalled_channels: usize,
+}
+
+/// Watches the heartbeats, the round-trip time and the channels of a connection
+#[derive(Debug, Clone)]
+pub struct HealthMonitor {
+    stall_timeout: Duration,
+    jitter_threshold: Duration,
+    heartbeat: Option<HeartbeatPdu>,
+    last_heartbeat: Instant,
+    metrics: HealthMetrics,
+    high_jitter: bool,
+    /// Channels waiting for an answer, with the start of the wait and whether the stall was reported
+    channels: BTreeMap<u16, (Instant, bool)>,
+    events: Vec<HealthEvent>,
+}
+
+impl HealthMonitor {
+    /// Default time a channel can wait for an answer before being reported as stalled
+    pub const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Default jitter reported as high
+    pub const DEFAULT_JITTER_THRESHOLD: Duration = Duration::from_millis(50);
+
+    pub fn new(now: Instant) -> Self {
+        Self {
+            stall_timeout: Self::DEFAULT_STALL_TIMEOUT,
+            jitter_threshold: Self::DEFAULT_JITTER_THRESHOLD,
+            heartbeat: None,
+            last_heartbeat: now,
+            metrics: HealthMetrics::default(),
+            high_jitter: false,
+            channels: BTreeMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_stall_timeout(mut self, stall_timeout: Duration) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn with_jitter_threshold(mut self, jitter_threshold: Duration) -> Self {
+        self.jitter_threshold = jitter_threshold;
+        self
+    }
+
+    pub fn health(&self) -> ConnectionHealth {
+        self.metrics.health
+    }
+
+    pub fn metrics(&self) -> HealthMetrics {
+        HealthMetrics {
+            stalled_channels: self.channels.values().filter(|(_, reported)| *reported).count(),
+            ..self.metrics
+        }
+    }
+
+    /// A heartbeat was received from the server.
+    pub fn heartbeat_received(&mut self, heartbeat: HeartbeatPdu, now: Instant) {
+        self.heartbeat = Some(heartbeat).filter(|heartbeat| heartbeat.period > 0);
+        self.last_heartbeat = now;
+        self.metrics.missed_heartbeats = 0;
+
+        if self.metrics.health != ConnectionHealth::Healthy {
+            debug!("Heartbeats received again");
+            self.metrics.health = ConnectionHealth::Healthy;
+            self.events.push(HealthEvent::Recovered);
+        }
+    }
+
+    /// A round-trip time was measured.
+    pub fn rtt_measured(&mut self, rtt: Duration) {
+        if let Some(last_rtt) = self.metrics.last_rtt {
+            let delta = rtt.abs_diff(last_rtt);
+            let jitter = self.metrics.jitter;
+
+            // J = J + (|D| - J) / 16
+            self.metrics.jitter = if delta > jitter {
+                jitter + (delta - jitter) / 16
+            } else {
+                jitter - (jitter - delta) / 16
+            };
+        }
+        self.metrics.last_rtt = Some(rtt);
+
+        let high_jitter = self.metrics.jitter > self.jitter_threshold;
+        if high_jitter && !self.high_jitter {
+            warn!(jitter = ?self.metrics.jitter, "High round-trip time jitter");
+            self.events.push(HealthEvent::HighJitter {
+                jitter: self.metrics.jitter,
+            });
+        }
+        self.high_jitter = high_jitter;
+    }
+
+    /// Data was sent on a channel, and an answer of the server is expected.
+    ///
+    /// The channel is waiting since the first request not answered yet.
+    pub fn channel_waiting(&mut self, channel_id: u16, now: Instant) {
+        self.channels.entry(channel_id).or_insert((now, false));
+    }
+
+    /// Data was received on a channel.
+    pub fn channel_activity(&mut self, channel_id: u16) {
+        if let Some((_, true)) = self.channels.remove(&channel_id) {
+            debug!(channel_id, "Channel resumed");
+            self.events.push(HealthEvent::ChannelResumed { channel_id });
+        }
+    }
+
+    /// Checks the heartbeats and the channels, and returns the events since the last call.
+    pub fn poll(&mut self, now: Instant) -> Vec<HealthEvent> {
+        self.check_heartbeats(now);
+        self.check_channels(now);
+
+        core::mem::take(&mut self.events)
+    }
+
+    fn check_heartbeats(&mut self, now: Instant) {
+        let Some(heartbeat) = self.heartbeat else {
+            return;
+        };
+
+        let period = Duration::from_secs(u64::from(heartbeat.period));
+        let elapsed = now.saturating_duration_since(self.last_heartbeat);
+        // One period of margin before counting the first missed heartbeat
+        let missed = u32::try_from(elapsed.as_millis() / period.as_millis())
+            .unwrap_or(u32::MAX)
+            .saturating_sub(1);
+        self.metrics.missed_heartbeats = missed;
+
+        let reached = |count: u8| count > 0 && missed >= u32::from(count);
+
+        let health = if reached(heartbeat.reconnect_count) {
+            ConnectionHealth::Lost
+        } else if reached(heartbeat.warning_count) {
+            ConnectionHealth::Unstable
+        } else {
+            ConnectionHealth::Healthy
+        };
+
+        if health == self.metrics.health {
+            return;
+        }
+
+        match health {
+            ConnectionHealth::Healthy => return,
+            ConnectionHealth::Unstable => {
+                warn!(missed, "Connection unstable");
+                self.events.push(HealthEvent::Unstable {
+                    missed_heartbeats: missed,
+                });
+            }
+            ConnectionHealth::Lost => {
+                warn!(missed, "Connection lost");
+                self.events.push(HealthEvent::ReconnectAdvised {
+                    missed_heartbeats: missed,
+                });
+            }
+        }
+
+        self.metrics.health = health;
+    }
+
+    fn check_channels(&mut self, now: Instant) {
+        for (channel_id, (since, reported)) in &mut self.channels {
+            let waiting = now.saturating_duration_since(*since);
+
+            if !*reported && waiting >= self.stall_timeout {
+                warn!(channel_id, ?waiting, "Channel stalled");
+                *reported = true;
+                self.events.push(HealthEvent::ChannelStalled {
+                    channel_id: *channel_id,
+                    waiting,
+                });
+            }
+        }
+    }
+}
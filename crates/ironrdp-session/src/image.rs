@@ -3,7 +3,7 @@ use std::sync::Arc;
 use ironrdp_core::assert_impl;
 use ironrdp_graphics::color_conversion::rdp_16bit_to_rgb;
 use ironrdp_graphics::image_processing::{ImageRegion, ImageRegionMut, PixelFormat};
-use ironrdp_graphics::pointer::DecodedPointer;
+use ironrdp_graphics::pointer::{composite_pixel, DecodedPointer};
 use ironrdp_graphics::rectangle_processing::Region;
 use ironrdp_pdu::geometry::{InclusiveRectangle, Rectangle as _};
 use tracing::trace;
@@ -100,40 +100,18 @@ fn copy_cursor_data(
 
         if composite {
             for pixel in 0..width {
-                let dest_r = to[to_start + pixel * PIXEL_SIZE];
-                let dest_g = to[to_start + pixel * PIXEL_SIZE + 1];
-                let dest_b = to[to_start + pixel * PIXEL_SIZE + 2];
-
-                let src_r = from[from_start + pixel * PIXEL_SIZE];
-                let src_g = from[from_start + pixel * PIXEL_SIZE + 1];
-                let src_b = from[from_start + pixel * PIXEL_SIZE + 2];
-                let src_a = from[from_start + pixel * PIXEL_SIZE + 3];
-
-                // Inverted pixel, this color has a special meaning when encoded by ironrdp-graphics
-                if src_a == 0 && src_r == 255 && src_g == 255 && src_b == 255 {
-                    to[to_start + pixel * PIXEL_SIZE] = 255 - dest_r;
-                    to[to_start + pixel * PIXEL_SIZE + 1] = 255 - dest_g;
-                    to[to_start + pixel * PIXEL_SIZE + 2] = 255 - dest_b;
-                    to[to_start + pixel * PIXEL_SIZE + 3] = 255;
-                    continue;
-                }
-
-                // Skip 100% transparent pixels
-                if src_a == 0 {
-                    continue;
-                }
-
-                #[expect(clippy::as_conversions, reason = "(u16 >> 8) fits into u8 + hot loop")]
-                {
-                    // Integer alpha blending, source represented as premultiplied alpha color, calculation in floating point
-                    to[to_start + pixel * PIXEL_SIZE] =
-                        src_r + ((u16::from(dest_r) * u16::from(255 - src_a)) >> 8) as u8;
-                    to[to_start + pixel * PIXEL_SIZE + 1] =
-                        src_g + ((u16::from(dest_g) * u16::from(255 - src_a)) >> 8) as u8;
-                    to[to_start + pixel * PIXEL_SIZE + 2] =
-                        src_b + ((u16::from(dest_b) * u16::from(255 - src_a)) >> 8) as u8;
-                    // Framebuffer is always opaque, so we can skip alpha channel change
-                }
+                let from_pixel = from_start + pixel * PIXEL_SIZE;
+                let to_pixel = to_start + pixel * PIXEL_SIZE;
+
+                let src = from[from_pixel..from_pixel + PIXEL_SIZE]
+                    .try_into()
+                    .expect("slice of PIXEL_SIZE bytes");
+                let dst = to[to_pixel..to_pixel + PIXEL_SIZE]
+                    .try_into()
+                    .expect("slice of PIXEL_SIZE bytes");
+
+                // Source is a premultiplied alpha color, as decoded for the software rendering target
+                to[to_pixel..to_pixel + PIXEL_SIZE].copy_from_slice(&composite_pixel(src, dst));
             }
         } else {
             to[to_start..to_start + width * PIXEL_SIZE]
@@ -4,10 +4,15 @@
 
 mod macros;
 
+pub mod autodetect;
 pub mod fast_path;
+pub mod health;
 pub mod image;
 pub mod legacy;
+#[cfg(feature = "overlay")]
+pub mod overlay;
 pub mod pointer;
+pub mod replay;
 pub mod rfx; // FIXME: maybe this module should not be in this crate
 pub mod x224;
 
@@ -0,0 +1,342 @@
+//! Statistics overlay composited on top of the rendered frames
+//!
+//! [`StatsCollector`] accumulates the statistics of the session and [`StatsOverlay`] draws them with a built-in bitmap
+//! font, so a GUI gets a debug HUD without bringing its own text rendering.
+
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_graphics::image_processing::{PixelFormat, Rgba};
+use ironrdp_pdu::geometry::InclusiveRectangle;
+
+/// Duration over which the frame rate and the bitrate are averaged
+const STATS_WINDOW: Duration = Duration::from_secs(1);
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+const LINE_HEIGHT: usize = GLYPH_HEIGHT + 2;
+const PADDING: usize = 4;
+
+/// Statistics displayed by the overlay
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Frames rendered per second
+    pub fps: u32,
+    /// Bits received per second
+    pub bitrate: u64,
+    /// Round-trip time to the server, if measured
+    pub rtt: Option<Duration>,
+    /// Codec of the graphics updates, e.g. `RemoteFX` or `AVC420`
+    pub codec: Option<String>,
+    /// Frames dropped since the start of the session
+    pub dropped_frames: u64,
+}
+
+impl Stats {
+    /// Text lines of the overlay, for GUIs rendering them with their own fonts
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("FPS: {}", self.fps),
+            format!("Bitrate: {}", format_bitrate(self.bitrate)),
+            match self.rtt {
+                Some(rtt) => format!("RTT: {} ms", rtt.as_millis()),
+                None => "RTT: -".to_owned(),
+            },
+            format!("Codec: {}", self.codec.as_deref().unwrap_or("-")),
+            format!("Dropped: {}", self.dropped_frames),
+        ]
+    }
+}
+
+fn format_bitrate(bitrate: u64) -> String {
+    if bitrate >= 1_000_000 {
+        format!("{}.{} Mbps", bitrate / 1_000_000, bitrate % 1_000_000 / 100_000)
+    } else {
+        format!("{} kbps", bitrate / 1_000)
+    }
+}
+
+/// Computes the frame rate and the bitrate of the session over one second windows
+///
+/// The time is passed by the caller, typically `Instant::now()`.
+#[derive(Debug, Clone)]
+pub struct StatsCollector {
+    stats: Stats,
+    window_start: Instant,
+    window_frames: u32,
+    window_bytes: u64,
+}
+
+impl StatsCollector {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            stats: Stats::default(),
+            window_start: now,
+            window_frames: 0,
+            window_bytes: 0,
+        }
+    }
+
+    /// A frame was presented.
+    pub fn frame_rendered(&mut self, now: Instant) {
+        self.update(now);
+        self.window_frames = self.window_frames.saturating_add(1);
+    }
+
+    /// Data was received from the server.
+    pub fn bytes_received(&mut self, len: usize, now: Instant) {
+        self.update(now);
+        self.window_bytes = self.window_bytes.saturating_add(u64::try_from(len).unwrap_or(u64::MAX));
+    }
+
+    /// A frame was decoded but skipped, e.g. because the GUI fell behind.
+    pub fn frame_dropped(&mut self) {
+        self.stats.dropped_frames = self.stats.dropped_frames.saturating_add(1);
+    }
+
+    pub fn set_rtt(&mut self, rtt: Duration) {
+        self.stats.rtt = Some(rtt);
+    }
+
+    pub fn set_codec(&mut self, codec: impl Into<String>) {
+        self.stats.codec = Some(codec.into());
+    }
+
+    /// Closes the current window if it's over.
+    ///
+    /// Should be called before drawing the overlay, so the frame rate drops to zero while no frames are received.
+    pub fn update(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < STATS_WINDOW {
+            return;
+        }
+
+        let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        self.stats.fps = u32::try_from(u64::from(self.window_frames) * 1000 / elapsed_ms).unwrap_or(u32::MAX);
+        self.stats.bitrate = self.window_bytes.saturating_mul(8 * 1000) / elapsed_ms;
+
+        self.window_start = now;
+        self.window_frames = 0;
+        self.window_bytes = 0;
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+}
+
+/// Corner of the frame where the overlay is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Draws the [`Stats`] on top of a frame
+#[derive(Debug, Clone)]
+pub struct StatsOverlay {
+    pub position: OverlayPosition,
+    /// Integer scaling of the font, for high DPI displays
+    pub scale: u16,
+    pub foreground: Rgba,
+    /// Blended with the frame according to its alpha
+    pub background: Rgba,
+}
+
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        Self {
+            position: OverlayPosition::TopLeft,
+            scale: 1,
+            foreground: Rgba {
+                r: 0xFF,
+                g: 0xFF,
+                b: 0xFF,
+                a: 0xFF,
+            },
+            background: Rgba {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0xA0,
+            },
+        }
+    }
+}
+
+impl StatsOverlay {
+    /// Draws the statistics on a frame with tightly packed rows, such as the data of a
+    /// [`DecodedImage`](crate::image::DecodedImage) copied by the GUI.
+    ///
+    /// The overlay is clipped to the frame. Returns the area to repaint, `None` if nothing was drawn.
+    pub fn draw(
+        &self,
+        stats: &Stats,
+        data: &mut [u8],
+        pixel_format: PixelFormat,
+        width: u16,
+        height: u16,
+    ) -> Option<InclusiveRectangle> {
+        let mut frame = Frame {
+            data,
+            pixel_format,
+            width: usize::from(width),
+            height: usize::from(height),
+        };
+        let bytes_per_pixel = usize::from(pixel_format.bytes_per_pixel());
+        if frame.width == 0 || frame.height == 0 || frame.data.len() < frame.width * frame.height * bytes_per_pixel {
+            return None;
+        }
+
+        let lines = stats.lines();
+        let scale = usize::from(self.scale.max(1));
+        let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+
+        let box_width = ((columns * CELL_WIDTH).saturating_sub(1) + 2 * PADDING) * scale;
+        let box_height = ((lines.len() * LINE_HEIGHT).saturating_sub(LINE_HEIGHT - GLYPH_HEIGHT) + 2 * PADDING) * scale;
+        let box_width = box_width.min(frame.width);
+        let box_height = box_height.min(frame.height);
+
+        let left = match self.position {
+            OverlayPosition::TopLeft | OverlayPosition::BottomLeft => 0,
+            OverlayPosition::TopRight | OverlayPosition::BottomRight => frame.width - box_width,
+        };
+        let top = match self.position {
+            OverlayPosition::TopLeft | OverlayPosition::TopRight => 0,
+            OverlayPosition::BottomLeft | OverlayPosition::BottomRight => frame.height - box_height,
+        };
+        let right = left + box_width;
+        let bottom = top + box_height;
+
+        frame.fill(left, top, right, bottom, self.background);
+
+        for (row, line) in lines.iter().enumerate() {
+            let y = top + (PADDING + row * LINE_HEIGHT) * scale;
+
+            for (column, character) in line.chars().enumerate() {
+                let x = left + (PADDING + column * CELL_WIDTH) * scale;
+
+                for (glyph_y, bits) in glyph(character).iter().enumerate() {
+                    for glyph_x in 0..GLYPH_WIDTH {
+                        if bits & (0x10 >> glyph_x) == 0 {
+                            continue;
+                        }
+
+                        let pixel_x = x + glyph_x * scale;
+                        let pixel_y = y + glyph_y * scale;
+                        frame.fill(
+                            pixel_x,
+                            pixel_y,
+                            (pixel_x + scale).min(right),
+                            (pixel_y + scale).min(bottom),
+                            self.foreground,
+                        );
+                    }
+                }
+            }
+        }
+
+        Some(InclusiveRectangle {
+            left: u16::try_from(left).ok()?,
+            top: u16::try_from(top).ok()?,
+            right: u16::try_from(right - 1).ok()?,
+            bottom: u16::try_from(bottom - 1).ok()?,
+        })
+    }
+}
+
+struct Frame<'a> {
+    data: &'a mut [u8],
+    pixel_format: PixelFormat,
+    width: usize,
+    height: usize,
+}
+
+impl Frame<'_> {
+    /// Blends the color over the pixels of the `[left, right) x [top, bottom)` area.
+    fn fill(&mut self, left: usize, top: usize, right: usize, bottom: usize, color: Rgba) {
+        let bytes_per_pixel = usize::from(self.pixel_format.bytes_per_pixel());
+
+        for y in top..bottom.min(self.height) {
+            for x in left..right.min(self.width) {
+                let start = (y * self.width + x) * bytes_per_pixel;
+                let Some(pixel) = self.data.get_mut(start..start + bytes_per_pixel) else {
+                    continue;
+                };
+                let Ok(destination) = self.pixel_format.read_color(pixel) else {
+                    continue;
+                };
+
+                let blended = Rgba {
+                    r: blend(color.r, destination.r, color.a),
+                    g: blend(color.g, destination.g, color.a),
+                    b: blend(color.b, destination.b, color.a),
+                    a: destination.a,
+                };
+                let _ = self.pixel_format.write_color(blended, pixel);
+            }
+        }
+    }
+}
+
+fn blend(source: u8, destination: u8, alpha: u8) -> u8 {
+    let value =
+        (u16::from(source) * u16::from(alpha) + u16::from(destination) * (0xFF - u16::from(alpha)) + 0x7F) / 0xFF;
+    u8::try_from(value).unwrap_or(u8::MAX)
+}
+
+/// 5x7 glyph, one byte per row with the leftmost pixel in bit 4
+///
+/// Letters are drawn uppercase, unsupported characters as `?`.
+fn glyph(character: char) -> &'static [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        ' ' => &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '0' => &[0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => &[0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => &[0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => &[0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => &[0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => &[0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => &[0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => &[0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => &[0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => &[0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => &[0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => &[0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => &[0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => &[0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => &[0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => &[0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => &[0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => &[0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => &[0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => &[0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => &[0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => &[0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => &[0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => &[0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => &[0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => &[0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => &[0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => &[0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => &[0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => &[0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => &[0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => &[0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => &[0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => &[0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => &[0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => &[0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '.' => &[0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ':' => &[0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => &[0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '/' => &[0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '%' => &[0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '(' => &[0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => &[0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => &[0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
@@ -0,0 +1,483 @@
+//! Recording of sessions and their deterministic replay
+//!
+//! A [`Capture`] holds the frames received from the server and the input sent by the user, in the order the client
+//! processed them, along with checkpoints holding the digest of the framebuffer. Replaying it through an
+//! [`ActiveStage`] reproduces the rendering exactly, the checkpoints detecting where the replay diverges from the
+//! recording.
+//!
+//! The capture holds the whole content of the session, the user input included, and must be handled as such.
+
+use core::fmt;
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_connector::connection_activation::{ConnectionActivationSequence, ConnectionActivationState};
+use ironrdp_connector::{DesktopSize, Sequence as _};
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteBuf, WriteCursor,
+};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp_pdu::Action;
+use tracing::debug;
+
+use crate::image::DecodedImage;
+use crate::{custom_err, fast_path, reason_err, ActiveStage, ActiveStageOutput, SessionResult};
+
+/// Digest of the content of a framebuffer
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FrameDigest(pub u64);
+
+impl FrameDigest {
+    /// Computes the digest of an image, its size included
+    ///
+    /// The digest is stable across platforms and versions: it's the 64-bit FNV-1a hash of the width and height in
+    /// little-endian, followed by the pixels.
+    pub fn of(image: &DecodedImage) -> Self {
+        const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+        let size = [image.width().to_le_bytes(), image.height().to_le_bytes()];
+        let digest = size
+            .iter()
+            .flatten()
+            .chain(image.data())
+            .fold(OFFSET_BASIS, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(PRIME));
+
+        Self(digest)
+    }
+}
+
+impl fmt::Display for FrameDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Event of a recorded session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureEvent {
+    /// Frame received from the server
+    Frame { action: Action, data: Vec<u8> },
+    /// Input sent to the server
+    Input(FastPathInput),
+    /// Digest of the framebuffer once the previous events are processed
+    Checkpoint(FrameDigest),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRecord {
+    /// Time elapsed since the start of the recording
+    pub timestamp: Duration,
+    pub event: CaptureEvent,
+}
+
+/// Recorded session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// Size of the desktop when the recording started
+    pub desktop_size: DesktopSize,
+    /// Pixel format of the framebuffer the checkpoints were computed on
+    pub pixel_format: PixelFormat,
+    pub records: Vec<CaptureRecord>,
+}
+
+impl Capture {
+    const NAME: &'static str = "Capture";
+
+    const MAGIC: [u8; 7] = *b"IRDPCAP";
+
+    const VERSION: u8 = 1;
+
+    const FIXED_PART_SIZE: usize = Self::MAGIC.len()
+        + 1 /* version */
+        + 2 /* width */
+        + 2 /* height */
+        + 1 /* pixelFormat */
+        + 4 /* recordCount */;
+
+    const FRAME: u8 = 0;
+    const INPUT: u8 = 1;
+    const CHECKPOINT: u8 = 2;
+
+    /// Creates the framebuffer the session starts with
+    pub fn new_image(&self) -> DecodedImage {
+        DecodedImage::new(self.pixel_format, self.desktop_size.width, self.desktop_size.height)
+    }
+
+    fn pixel_format_to_u8(format: PixelFormat) -> u8 {
+        match format {
+            PixelFormat::ARgb32 => 0,
+            PixelFormat::XRgb32 => 1,
+            PixelFormat::ABgr32 => 2,
+            PixelFormat::XBgr32 => 3,
+            PixelFormat::BgrA32 => 4,
+            PixelFormat::BgrX32 => 5,
+            PixelFormat::RgbA32 => 6,
+            PixelFormat::RgbX32 => 7,
+        }
+    }
+
+    fn pixel_format_from_u8(format: u8) -> Option<PixelFormat> {
+        match format {
+            0 => Some(PixelFormat::ARgb32),
+            1 => Some(PixelFormat::XRgb32),
+            2 => Some(PixelFormat::ABgr32),
+            3 => Some(PixelFormat::XBgr32),
+            4 => Some(PixelFormat::BgrA32),
+            5 => Some(PixelFormat::BgrX32),
+            6 => Some(PixelFormat::RgbA32),
+            7 => Some(PixelFormat::RgbX32),
+            _ => None,
+        }
+    }
+
+    fn record_size(record: &CaptureRecord) -> usize {
+        let event_size = match &record.event {
+            CaptureEvent::Frame { data, .. } => 1 /* action */ + 4 /* length */ + data.len(),
+            CaptureEvent::Input(input) => input.size(),
+            CaptureEvent::Checkpoint(_) => 8,
+        };
+
+        8 /* timestamp */ + 1 /* eventType */ + event_size
+    }
+}
+
+impl Encode for Capture {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_array(Self::MAGIC);
+        dst.write_u8(Self::VERSION);
+        dst.write_u16(self.desktop_size.width);
+        dst.write_u16(self.desktop_size.height);
+        dst.write_u8(Self::pixel_format_to_u8(self.pixel_format));
+        dst.write_u32(cast_length!("recordCount", self.records.len())?);
+
+        for record in &self.records {
+            dst.write_u64(u64::try_from(record.timestamp.as_millis()).unwrap_or(u64::MAX));
+
+            match &record.event {
+                CaptureEvent::Frame { action, data } => {
+                    dst.write_u8(Self::FRAME);
+                    dst.write_u8(match action {
+                        Action::FastPath => 0x00,
+                        Action::X224 => 0x03,
+                    });
+                    dst.write_u32(cast_length!("frameLength", data.len())?);
+                    dst.write_slice(data);
+                }
+                CaptureEvent::Input(input) => {
+                    dst.write_u8(Self::INPUT);
+                    input.encode(dst)?;
+                }
+                CaptureEvent::Checkpoint(digest) => {
+                    dst.write_u8(Self::CHECKPOINT);
+                    dst.write_u64(digest.0);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.records.iter().map(Self::record_size).sum::<usize>()
+    }
+}
+
+impl<'de> Decode<'de> for Capture {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        if src.read_array::<7>() != Self::MAGIC {
+            return Err(invalid_field_err!("magic", "not a capture"));
+        }
+        if src.read_u8() != Self::VERSION {
+            return Err(invalid_field_err!("version", "unsupported capture version"));
+        }
+
+        let desktop_size = DesktopSize {
+            width: src.read_u16(),
+            height: src.read_u16(),
+        };
+        let pixel_format = Self::pixel_format_from_u8(src.read_u8())
+            .ok_or_else(|| invalid_field_err!("pixelFormat", "unknown pixel format"))?;
+        let record_count = cast_length!("recordCount", src.read_u32())?;
+
+        let records = core::iter::repeat_with(|| {
+            ensure_size!(in: src, size: 8 + 1);
+            let timestamp = Duration::from_millis(src.read_u64());
+
+            let event = match src.read_u8() {
+                Self::FRAME => {
+                    ensure_size!(in: src, size: 1 + 4);
+                    let action = Action::from_fp_output_header(src.read_u8())
+                        .map_err(|_| invalid_field_err!("action", "unknown frame action"))?;
+                    let length = cast_length!("frameLength", src.read_u32())?;
+                    ensure_size!(in: src, size: length);
+
+                    CaptureEvent::Frame {
+                        action,
+                        data: src.read_slice(length).to_vec(),
+                    }
+                }
+                Self::INPUT => CaptureEvent::Input(FastPathInput::decode(src)?),
+                Self::CHECKPOINT => {
+                    ensure_size!(in: src, size: 8);
+                    CaptureEvent::Checkpoint(FrameDigest(src.read_u64()))
+                }
+                _ => return Err(invalid_field_err!("eventType", "unknown event type")),
+            };
+
+            Ok(CaptureRecord { timestamp, event })
+        })
+        .take(record_count)
+        .collect::<DecodeResult<Vec<_>>>()?;
+
+        Ok(Self {
+            desktop_size,
+            pixel_format,
+            records,
+        })
+    }
+}
+
+/// Records a session, as the client processes it
+///
+/// Every frame received from the server must be recorded, the ones of the deactivation-reactivation sequence
+/// included, before being processed.
+#[derive(Debug)]
+pub struct Recorder {
+    start: Instant,
+    capture: Capture,
+}
+
+impl Recorder {
+    pub fn new(desktop_size: DesktopSize, pixel_format: PixelFormat) -> Self {
+        Self {
+            start: Instant::now(),
+            capture: Capture {
+                desktop_size,
+                pixel_format,
+                records: Vec::new(),
+            },
+        }
+    }
+
+    /// Records a frame received from the server
+    pub fn frame(&mut self, action: Action, data: &[u8]) {
+        self.push(CaptureEvent::Frame {
+            action,
+            data: data.to_vec(),
+        });
+    }
+
+    /// Records input sent to the server
+    ///
+    /// Like [`ActiveStage::process_fastpath_input`], nothing is sent when there is no event.
+    pub fn input(&mut self, events: &[FastPathInputEvent]) {
+        if let Ok(input) = FastPathInput::new(events.to_vec()) {
+            self.push(CaptureEvent::Input(input));
+        }
+    }
+
+    /// Records the digest of the framebuffer, to be verified when replaying
+    pub fn checkpoint(&mut self, image: &DecodedImage) {
+        self.push(CaptureEvent::Checkpoint(FrameDigest::of(image)));
+    }
+
+    pub fn finish(self) -> Capture {
+        self.capture
+    }
+
+    fn push(&mut self, event: CaptureEvent) {
+        self.capture.records.push(CaptureRecord {
+            timestamp: self.start.elapsed(),
+            event,
+        });
+    }
+}
+
+/// Outcome of a replayed record
+#[derive(Debug)]
+pub enum ReplayStep {
+    /// Outputs of the active stage for a frame or an input
+    ///
+    /// The response frames are not sent anywhere, and the deactivation-reactivation sequence is handled by the
+    /// replayer.
+    Outputs(Vec<ActiveStageOutput>),
+    /// Frame processed by the deactivation-reactivation sequence
+    Reactivation,
+    /// The framebuffer matches the checkpoint
+    Checkpoint(FrameDigest),
+}
+
+/// Replays a capture through an active stage
+///
+/// The active stage must be built with the static and dynamic channels of the recorded client, and the framebuffer
+/// with [`Capture::new_image`]. The replay is deterministic: the records are processed in order, ignoring their
+/// timestamps.
+#[derive(Debug)]
+pub struct Replayer {
+    capture: Capture,
+    position: usize,
+    reactivation: Option<Box<ConnectionActivationSequence>>,
+}
+
+impl Replayer {
+    pub fn new(capture: Capture) -> Self {
+        Self {
+            capture,
+            position: 0,
+            reactivation: None,
+        }
+    }
+
+    /// Index of the next record to replay
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn capture(&self) -> &Capture {
+        &self.capture
+    }
+
+    /// Replays the next record, `None` at the end of the capture
+    ///
+    /// Returns an error when the framebuffer doesn't match a checkpoint, the framebuffer being left as it diverged.
+    pub fn step(&mut self, stage: &mut ActiveStage, image: &mut DecodedImage) -> SessionResult<Option<ReplayStep>> {
+        let Some(record) = self.capture.records.get(self.position) else {
+            return Ok(None);
+        };
+        let index = self.position;
+        self.position += 1;
+
+        let step = match &record.event {
+            CaptureEvent::Frame { data, .. } if self.reactivation.is_some() => {
+                let sequence = self.reactivation.as_mut().expect("checked above");
+                if let Some(state) = Self::reactivate(sequence, Some(data))? {
+                    Self::apply_reactivation(stage, image, state);
+                    self.reactivation = None;
+                }
+
+                ReplayStep::Reactivation
+            }
+            CaptureEvent::Frame { action, data } => {
+                let mut outputs = stage.process(image, *action, data)?;
+
+                if let Some(position) = outputs
+                    .iter()
+                    .position(|output| matches!(output, ActiveStageOutput::DeactivateAll(_)))
+                {
+                    let ActiveStageOutput::DeactivateAll(mut sequence) = outputs.remove(position) else {
+                        unreachable!()
+                    };
+
+                    debug!(index, "Replaying the deactivation-reactivation sequence");
+                    match Self::reactivate(&mut sequence, None)? {
+                        Some(state) => Self::apply_reactivation(stage, image, state),
+                        None => self.reactivation = Some(sequence),
+                    }
+                }
+
+                ReplayStep::Outputs(outputs)
+            }
+            CaptureEvent::Input(input) => {
+                ReplayStep::Outputs(stage.process_fastpath_input(image, input.input_events())?)
+            }
+            CaptureEvent::Checkpoint(expected) => {
+                let actual = FrameDigest::of(image);
+                if actual != *expected {
+                    return Err(reason_err!(
+                        "replay",
+                        "framebuffer diverged at record {index} ({} ms): expected {expected}, got {actual}",
+                        record.timestamp.as_millis()
+                    ));
+                }
+
+                ReplayStep::Checkpoint(actual)
+            }
+        };
+
+        Ok(Some(step))
+    }
+
+    /// Replays the remaining records, returning the number of checkpoints verified
+    pub fn run(&mut self, stage: &mut ActiveStage, image: &mut DecodedImage) -> SessionResult<usize> {
+        let mut checkpoints = 0;
+
+        while let Some(step) = self.step(stage, image)? {
+            if let ReplayStep::Checkpoint(_) = step {
+                checkpoints += 1;
+            }
+        }
+
+        Ok(checkpoints)
+    }
+
+    /// Steps the sequence with a frame if any, then as long as it doesn't wait for another one
+    ///
+    /// Returns the final state once the sequence is done.
+    fn reactivate(
+        sequence: &mut ConnectionActivationSequence,
+        frame: Option<&[u8]>,
+    ) -> SessionResult<Option<ConnectionActivationState>> {
+        let mut output = WriteBuf::new();
+
+        if let Some(frame) = frame {
+            sequence
+                .step(frame, &mut output)
+                .map_err(|e| custom_err!("replay deactivation-reactivation sequence", e))?;
+        }
+
+        loop {
+            let state = sequence.connection_activation_state();
+            if let ConnectionActivationState::Finalized { .. } = state {
+                return Ok(Some(state));
+            }
+
+            if sequence.next_pdu_hint().is_some() {
+                return Ok(None);
+            }
+
+            output.clear();
+            sequence
+                .step_no_input(&mut output)
+                .map_err(|e| custom_err!("replay deactivation-reactivation sequence", e))?;
+        }
+    }
+
+    fn apply_reactivation(stage: &mut ActiveStage, image: &mut DecodedImage, state: ConnectionActivationState) {
+        let ConnectionActivationState::Finalized {
+            io_channel_id,
+            user_channel_id,
+            desktop_size,
+            enable_server_pointer,
+            pointer_software_rendering,
+        } = state
+        else {
+            return;
+        };
+
+        debug!(?desktop_size, "Replayed the deactivation-reactivation sequence");
+
+        *image = DecodedImage::new(image.pixel_format(), desktop_size.width, desktop_size.height);
+        stage.set_fastpath_processor(
+            fast_path::ProcessorBuilder {
+                io_channel_id,
+                user_channel_id,
+                enable_server_pointer,
+                pointer_software_rendering,
+            }
+            .build(),
+        );
+        stage.set_enable_server_pointer(enable_server_pointer);
+        stage.set_desktop_size(desktop_size);
+    }
+}
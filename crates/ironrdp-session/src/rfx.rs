@@ -1,10 +1,9 @@
 use core::cmp::min;
 
-use ironrdp_graphics::color_conversion::{self, YCbCrBuffer};
 use ironrdp_graphics::image_processing::PixelFormat;
 use ironrdp_graphics::rectangle_processing::Region;
-use ironrdp_graphics::{dwt, quantization, rlgr, subband_reconstruction};
-use ironrdp_pdu::codecs::rfx::{self, EntropyAlgorithm, Quant, RfxRectangle, Tile};
+use ironrdp_graphics::rfx::{decode_tiles, DECODED_TILE_SIZE, TILE_SIZE};
+use ironrdp_pdu::codecs::rfx::{self, EntropyAlgorithm, RfxRectangle, Tile};
 use ironrdp_pdu::geometry::{InclusiveRectangle, Rectangle as _};
 use ironrdp_pdu::{decode_cursor, Decode as _, ReadCursor};
 use tracing::{instrument, trace};
@@ -12,14 +11,12 @@ use tracing::{instrument, trace};
 use crate::image::DecodedImage;
 use crate::{custom_err, general_err, reason_err, SessionResult};
 
-const TILE_SIZE: u16 = 64;
-
 pub type FrameId = u32;
 
 pub struct DecodingContext {
     context: rfx::ContextPdu,
     channels: rfx::ChannelsPdu,
-    decoding_tiles: DecodingTileContext,
+    decoded_tiles: Vec<u8>,
 }
 
 impl Default for DecodingContext {
@@ -30,7 +27,7 @@ impl Default for DecodingContext {
                 entropy_algorithm: EntropyAlgorithm::Rlgr1,
             },
             channels: rfx::ChannelsPdu(Vec::new()),
-            decoding_tiles: DecodingTileContext::new(),
+            decoded_tiles: Vec::new(),
         }
     }
 }
@@ -153,19 +150,19 @@ impl DecodingContext {
 
         let mut final_update_rectangle = clipping_rectangles.extents.clone();
 
-        for (update_rectangle, tile_data) in tiles_to_rectangles(tile_set.tiles.as_slice(), destination)
-            .zip(map_tiles_data(tile_set.tiles.as_slice(), tile_set.quants.as_slice()))
-        {
-            decode_tile(
-                &tile_data,
-                entropy_algorithm,
-                self.decoding_tiles.tile_output.as_mut(),
-                self.decoding_tiles.ycbcr_buffer.as_mut(),
-                self.decoding_tiles.ycbcr_temp_buffer.as_mut(),
-            )?;
+        decode_tiles(
+            tile_set.tiles.as_slice(),
+            tile_set.quants.as_slice(),
+            entropy_algorithm,
+            &mut self.decoded_tiles,
+        )
+        .map_err(|e| custom_err!("decode_tiles", e))?;
 
+        for (update_rectangle, tile_output) in tiles_to_rectangles(tile_set.tiles.as_slice(), destination)
+            .zip(self.decoded_tiles.chunks_exact(DECODED_TILE_SIZE))
+        {
             let current_update_rectangle = image.apply_tile(
-                &self.decoding_tiles.tile_output,
+                tile_output,
                 PixelFormat::RgbA32,
                 &clipping_rectangles,
                 &update_rectangle,
@@ -178,61 +175,6 @@ impl DecodingContext {
     }
 }
 
-#[derive(Debug, Clone)]
-struct DecodingTileContext {
-    tile_output: Vec<u8>,
-    ycbcr_buffer: Vec<Vec<i16>>,
-    ycbcr_temp_buffer: Vec<i16>,
-}
-
-impl DecodingTileContext {
-    fn new() -> Self {
-        let tile_size = usize::from(TILE_SIZE);
-        Self {
-            tile_output: vec![0; tile_size * tile_size * 4],
-            ycbcr_buffer: vec![vec![0; tile_size * tile_size]; 3],
-            ycbcr_temp_buffer: vec![0; tile_size * tile_size],
-        }
-    }
-}
-
-fn decode_tile(
-    tile: &TileData<'_>,
-    entropy_algorithm: EntropyAlgorithm,
-    output: &mut [u8],
-    ycbcr_temp: &mut [Vec<i16>],
-    temp: &mut [i16],
-) -> SessionResult<()> {
-    for ((quant, data), ycbcr_buffer) in tile.quants.iter().zip(tile.data.iter()).zip(ycbcr_temp.iter_mut()) {
-        decode_component(quant, entropy_algorithm, data, ycbcr_buffer.as_mut_slice(), temp)?;
-    }
-
-    let ycbcr_buffer = YCbCrBuffer {
-        y: ycbcr_temp[0].as_slice(),
-        cb: ycbcr_temp[1].as_slice(),
-        cr: ycbcr_temp[2].as_slice(),
-    };
-
-    color_conversion::ycbcr_to_rgba(ycbcr_buffer, output).map_err(|e| custom_err!("decode_tile", e))?;
-
-    Ok(())
-}
-
-fn decode_component(
-    quant: &Quant,
-    entropy_algorithm: EntropyAlgorithm,
-    data: &[u8],
-    output: &mut [i16],
-    temp: &mut [i16],
-) -> SessionResult<()> {
-    rlgr::decode(entropy_algorithm, data, output).map_err(|e| custom_err!("decode_component", e))?;
-    subband_reconstruction::decode(&mut output[4032..]);
-    quantization::decode(output, quant);
-    dwt::decode(output, temp);
-
-    Ok(())
-}
-
 fn clipping_rectangles(
     rectangles: &[RfxRectangle],
     destination: &InclusiveRectangle,
@@ -265,22 +207,3 @@ fn tiles_to_rectangles<'a>(
         bottom: destination.top + t.y * TILE_SIZE + TILE_SIZE - 1,
     })
 }
-
-fn map_tiles_data<'a>(tiles: &[Tile<'a>], quants: &[Quant]) -> Vec<TileData<'a>> {
-    tiles
-        .iter()
-        .map(|t| TileData {
-            quants: [
-                quants[usize::from(t.y_quant_index)].clone(),
-                quants[usize::from(t.cb_quant_index)].clone(),
-                quants[usize::from(t.cr_quant_index)].clone(),
-            ],
-            data: [t.y_data, t.cb_data, t.cr_data],
-        })
-        .collect()
-}
-
-struct TileData<'a> {
-    quants: [Quant; 3],
-    data: [&'a [u8]; 3],
-}
@@ -3,8 +3,11 @@ use ironrdp_connector::legacy::SendDataIndicationCtx;
 use ironrdp_core::WriteBuf;
 use ironrdp_dvc::{DrdynvcClient, DvcProcessor, DynamicVirtualChannel};
 use ironrdp_pdu::mcs::{DisconnectProviderUltimatum, DisconnectReason, McsMessage};
+use ironrdp_pdu::rdp::finalization_messages::ControlAction;
 use ironrdp_pdu::rdp::headers::ShareDataPdu;
 use ironrdp_pdu::rdp::server_error_info::{ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu};
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
+use ironrdp_pdu::rdp::session_info::{InfoData, ServerAutoReconnect};
 use ironrdp_pdu::x224::X224;
 use ironrdp_svc::{client_encode_svc_messages, StaticChannelSet, SvcMessage, SvcProcessor, SvcProcessorMessages};
 use tracing::debug;
@@ -23,6 +26,20 @@ pub enum ProcessorOutput {
     ///
     /// [Deactivation-Reactivation Sequence]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/dfc234ce-481a-4674-9a5d-2a7bafb14432
     DeactivateAll(Box<ConnectionActivationSequence>),
+    /// Received a Granted Control PDU, in response to a control request.
+    Control(ControlStatus),
+    /// Received a Server Redirection PDU. Client should close the connection and connect to the
+    /// target of the redirection, see [`ironrdp_connector::redirection`].
+    ServerRedirection(Box<ServerRedirectionPdu>),
+}
+
+/// Control of the session, as announced by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlStatus {
+    /// The client has control and its input is processed.
+    Granted,
+    /// Control is held by another party, the input of the client is ignored.
+    Denied,
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +59,7 @@ pub struct Processor {
     user_channel_id: u16,
     io_channel_id: u16,
     connection_activation: ConnectionActivationSequence,
+    auto_reconnect_cookie: Option<ServerAutoReconnect>,
 }
 
 impl Processor {
@@ -56,9 +74,15 @@ impl Processor {
             user_channel_id,
             io_channel_id,
             connection_activation,
+            auto_reconnect_cookie: None,
         }
     }
 
+    /// Returns the latest auto-reconnect cookie sent by the server
+    pub fn auto_reconnect_cookie(&self) -> Option<&ServerAutoReconnect> {
+        self.auto_reconnect_cookie.as_ref()
+    }
+
     pub fn get_svc_processor<T: SvcProcessor + 'static>(&self) -> Option<&T> {
         self.static_channels
             .get_by_type::<T>()
@@ -112,7 +136,7 @@ impl Processor {
         }
     }
 
-    fn process_io_channel(&self, data_ctx: SendDataIndicationCtx<'_>) -> SessionResult<Vec<ProcessorOutput>> {
+    fn process_io_channel(&mut self, data_ctx: SendDataIndicationCtx<'_>) -> SessionResult<Vec<ProcessorOutput>> {
         debug_assert_eq!(data_ctx.channel_id, self.io_channel_id);
 
         let io_channel = ironrdp_connector::legacy::decode_io_channel(data_ctx).map_err(crate::legacy::map_error)?;
@@ -122,6 +146,13 @@ impl Processor {
                 match ctx.pdu {
                     ShareDataPdu::SaveSessionInfo(session_info) => {
                         debug!("Got Session Save Info PDU: {session_info:?}");
+
+                        if let InfoData::LogonExtended(logon_extended) = session_info.info_data {
+                            if let Some(auto_reconnect) = logon_extended.auto_reconnect {
+                                self.auto_reconnect_cookie = Some(auto_reconnect);
+                            }
+                        }
+
                         Ok(Vec::new())
                     }
                     // FIXME: workaround fix to not terminate the session on "unhandled PDU: Set Keyboard Indicators PDU"
@@ -143,6 +174,21 @@ impl Processor {
                         let desc = DisconnectDescription::ErrorInfo(e);
                         Ok(vec![ProcessorOutput::Disconnect(desc)])
                     }
+                    ShareDataPdu::Control(pdu) => match pdu.action {
+                        ControlAction::GrantedControl => {
+                            let status = if pdu.grant_id == self.user_channel_id {
+                                ControlStatus::Granted
+                            } else {
+                                ControlStatus::Denied
+                            };
+                            debug!(?pdu, ?status, "Got Granted Control PDU");
+                            Ok(vec![ProcessorOutput::Control(status)])
+                        }
+                        _ => {
+                            debug!(?pdu, "Got Control PDU");
+                            Ok(Vec::new())
+                        }
+                    },
                     ShareDataPdu::ShutdownDenied => {
                         debug!("ShutdownDenied received, session will be closed");
 
@@ -174,6 +220,10 @@ impl Processor {
             ironrdp_connector::legacy::IoChannelPdu::DeactivateAll(_) => Ok(vec![ProcessorOutput::DeactivateAll(
                 Box::new(self.connection_activation.reset_clone()),
             )]),
+            ironrdp_connector::legacy::IoChannelPdu::ServerRedirection(redirection) => {
+                debug!(?redirection, "Received Server Redirection PDU");
+                Ok(vec![ProcessorOutput::ServerRedirection(Box::new(redirection))])
+            }
         }
     }
 
@@ -0,0 +1,206 @@
+use ironrdp_accessibility::client::{AccessibilityClient, AccessibilityHandler};
+use ironrdp_accessibility::pdu::{AccessibilityPdu, CaretPdu, ControlRole, ControlState, FocusPdu, Version};
+use ironrdp_accessibility::server::AccessibilityServer;
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_dvc::{DvcMessage, DvcProcessor};
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_testsuite_core::encode_decode_test;
+
+use crate::harness::{exchange, open, Events, Recorder, CHANNEL_ID};
+
+fn rectangle(left: u16, top: u16, right: u16, bottom: u16) -> InclusiveRectangle {
+    InclusiveRectangle {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+fn ok_button() -> FocusPdu {
+    FocusPdu {
+        role: ControlRole::BUTTON,
+        state: ControlState::empty(),
+        bounds: rectangle(10, 20, 89, 43),
+        name: "OK".to_owned(),
+        value: String::new(),
+    }
+}
+
+encode_decode_test! {
+    version: AccessibilityPdu::Version(Version::V1),
+    [0x01, 0x01, 0x00, 0x00, 0x00];
+
+    focus_changed: AccessibilityPdu::FocusChanged(FocusPdu {
+        role: ControlRole::EDIT,
+        state: ControlState::REQUIRED,
+        bounds: rectangle(1, 2, 3, 4),
+        name: "Name".to_owned(),
+        value: "ab".to_owned(),
+    }),
+    [
+        0x02,
+        0x07, 0x00, // Role
+        0x00, 0x01, 0x00, 0x00, // State
+        0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04, 0x00, // Bounds
+        0x04, 0x00, b'N', b'a', b'm', b'e', // Name
+        0x02, 0x00, b'a', b'b', // Value
+    ];
+
+    focus_cleared: AccessibilityPdu::FocusCleared,
+    [0x03];
+
+    caret_moved: AccessibilityPdu::CaretMoved(CaretPdu { bounds: rectangle(100, 200, 101, 215) }),
+    [0x04, 0x64, 0x00, 0xc8, 0x00, 0x65, 0x00, 0xd7, 0x00];
+
+    caret_hidden: AccessibilityPdu::CaretHidden,
+    [0x05];
+}
+
+#[test]
+fn focus_invalid_utf8() {
+    let mut encoded = encode_vec(&AccessibilityPdu::FocusChanged(ok_button())).unwrap();
+    // The first byte of the name.
+    encoded[17] = 0xff;
+
+    assert!(decode::<AccessibilityPdu>(&encoded).is_err());
+}
+
+#[test]
+fn focus_truncated_name() {
+    let encoded = encode_vec(&AccessibilityPdu::FocusChanged(ok_button())).unwrap();
+
+    assert!(decode::<AccessibilityPdu>(&encoded[..18]).is_err());
+}
+
+#[test]
+fn unknown_state_is_retained() {
+    let mut encoded = encode_vec(&AccessibilityPdu::FocusChanged(ok_button())).unwrap();
+    encoded[6] = 0x80;
+
+    let AccessibilityPdu::FocusChanged(focus) = decode(&encoded).unwrap() else {
+        panic!("unexpected PDU");
+    };
+    assert_eq!(focus.state.bits(), 0x8000_0000);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    Focus(FocusPdu),
+    FocusCleared,
+    Caret(InclusiveRectangle),
+    CaretHidden,
+    Close,
+}
+
+impl AccessibilityHandler for Recorder<Event> {
+    fn focus_changed(&mut self, focus: &FocusPdu) {
+        self.record(Event::Focus(focus.clone()));
+    }
+
+    fn focus_cleared(&mut self) {
+        self.record(Event::FocusCleared);
+    }
+
+    fn caret_moved(&mut self, bounds: &InclusiveRectangle) {
+        self.record(Event::Caret(bounds.clone()));
+    }
+
+    fn caret_hidden(&mut self) {
+        self.record(Event::CaretHidden);
+    }
+
+    fn close(&mut self) {
+        self.record(Event::Close);
+    }
+}
+
+#[test]
+fn server_waits_for_client_version() {
+    let mut server = AccessibilityServer::new();
+
+    assert!(server.encode_focus_changed(ok_button()).is_err());
+
+    let messages = server.start(CHANNEL_ID).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert!(!server.is_ready());
+    assert!(server.encode_caret_moved(rectangle(0, 0, 1, 15)).is_err());
+
+    let replies = server
+        .process(
+            CHANNEL_ID,
+            &encode_vec(&AccessibilityPdu::Version(Version::V1)).unwrap(),
+        )
+        .unwrap();
+    assert!(server.is_ready());
+
+    // The focus and caret given before the client was ready are sent with the reply.
+    assert_eq!(replies.len(), 2);
+
+    assert_eq!(server.encode_caret_hidden().unwrap().len(), 1);
+
+    server.close(CHANNEL_ID);
+    assert!(!server.is_ready());
+    assert!(server.encode_focus_cleared().is_err());
+}
+
+#[test]
+fn client_follows_focus_and_caret() {
+    let events = Events::<Event>::default();
+    let mut server = AccessibilityServer::new();
+    let mut client = AccessibilityClient::new(Box::new(Recorder::new(&events)));
+
+    open(&mut server, &mut client);
+    assert!(server.is_ready());
+    assert!(events.lock().unwrap().is_empty());
+
+    let messages: Vec<DvcMessage> = vec![
+        Box::new(AccessibilityPdu::FocusChanged(ok_button())),
+        Box::new(AccessibilityPdu::CaretMoved(CaretPdu {
+            bounds: rectangle(12, 22, 13, 37),
+        })),
+    ];
+    exchange(&mut server, &mut client, messages);
+
+    assert_eq!(client.focus(), Some(&ok_button()));
+    assert_eq!(client.caret(), Some(&rectangle(12, 22, 13, 37)));
+
+    let messages: Vec<DvcMessage> = vec![
+        Box::new(AccessibilityPdu::CaretHidden),
+        Box::new(AccessibilityPdu::FocusCleared),
+    ];
+    exchange(&mut server, &mut client, messages);
+
+    assert_eq!(client.focus(), None);
+    assert_eq!(client.caret(), None);
+
+    client.close(CHANNEL_ID);
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Event::Focus(ok_button()),
+            Event::Caret(rectangle(12, 22, 13, 37)),
+            Event::CaretHidden,
+            Event::FocusCleared,
+            Event::Close,
+        ]
+    );
+}
+
+#[test]
+fn reopened_channel_resends_state() {
+    let events = Events::<Event>::default();
+    let mut server = AccessibilityServer::new();
+    let mut client = AccessibilityClient::new(Box::new(Recorder::new(&events)));
+
+    open(&mut server, &mut client);
+    server.encode_focus_changed(ok_button()).unwrap();
+
+    server.close(CHANNEL_ID);
+    client.close(CHANNEL_ID);
+    open(&mut server, &mut client);
+
+    assert_eq!(client.focus(), Some(&ok_button()));
+    assert_eq!(*events.lock().unwrap(), [Event::Close, Event::Focus(ok_button())]);
+}
@@ -0,0 +1,246 @@
+use ironrdp_audioinput::client::{AudioInputClient, AudioInputClientHandler};
+use ironrdp_audioinput::pdu::{
+    AudioFormat, AudioInputPdu, DataPdu, FormatChangePdu, FormatsPdu, OpenPdu, OpenReplyPdu, Version, WaveFormat,
+    E_FAIL, S_OK,
+};
+use ironrdp_audioinput::server::{AudioInputServer, AudioInputServerHandler};
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_dvc::{DvcMessage, DvcProcessor};
+use ironrdp_testsuite_core::encode_decode_test;
+
+use crate::harness::{exchange, open, Events, Recorder, CHANNEL_ID};
+
+fn pcm_format(n_channels: u16, n_samples_per_sec: u32) -> AudioFormat {
+    AudioFormat {
+        format: WaveFormat::PCM,
+        n_channels,
+        n_samples_per_sec,
+        n_avg_bytes_per_sec: n_samples_per_sec * u32::from(n_channels) * 2,
+        n_block_align: n_channels * 2,
+        bits_per_sample: 16,
+        data: None,
+    }
+}
+
+encode_decode_test! {
+    version: AudioInputPdu::Version(Version::V2),
+    [0x01, 0x02, 0x00, 0x00, 0x00];
+
+    formats: AudioInputPdu::Formats(FormatsPdu { formats: vec![pcm_format(1, 8000)] }),
+    [
+        0x02,
+        0x01, 0x00, 0x00, 0x00, // NumFormats
+        0x1b, 0x00, 0x00, 0x00, // cbSizeFormatsPacket
+        0x01, 0x00, // wFormatTag
+        0x01, 0x00, // nChannels
+        0x40, 0x1f, 0x00, 0x00, // nSamplesPerSec
+        0x80, 0x3e, 0x00, 0x00, // nAvgBytesPerSec
+        0x02, 0x00, // nBlockAlign
+        0x10, 0x00, // wBitsPerSample
+        0x00, 0x00, // cbSize
+    ];
+
+    open: AudioInputPdu::Open(OpenPdu {
+        frames_per_packet: 0x0372,
+        initial_format: 1,
+        capture_format: pcm_format(2, 44100),
+    }),
+    [
+        0x03,
+        0x72, 0x03, 0x00, 0x00, // FramesPerPacket
+        0x01, 0x00, 0x00, 0x00, // initialFormat
+        0x01, 0x00,
+        0x02, 0x00,
+        0x44, 0xac, 0x00, 0x00,
+        0x10, 0xb1, 0x02, 0x00,
+        0x04, 0x00,
+        0x10, 0x00,
+        0x00, 0x00,
+    ];
+
+    open_reply: AudioInputPdu::OpenReply(OpenReplyPdu { result: E_FAIL }),
+    [0x04, 0x05, 0x40, 0x00, 0x80];
+
+    incoming_data: AudioInputPdu::IncomingData,
+    [0x05];
+
+    data: AudioInputPdu::Data(DataPdu { data: vec![0x01, 0x02, 0x03] }),
+    [0x06, 0x01, 0x02, 0x03];
+
+    format_change: AudioInputPdu::FormatChange(FormatChangePdu { new_format: 2 }),
+    [0x07, 0x02, 0x00, 0x00, 0x00];
+}
+
+#[test]
+fn formats_extra_data_is_ignored() {
+    let mut encoded = encode_vec(&AudioInputPdu::Formats(FormatsPdu {
+        formats: vec![pcm_format(1, 8000)],
+    }))
+    .unwrap();
+    encoded.extend_from_slice(&[0xaa, 0xbb]);
+
+    let pdu: AudioInputPdu = decode(&encoded).unwrap();
+    assert_eq!(
+        pdu,
+        AudioInputPdu::Formats(FormatsPdu {
+            formats: vec![pcm_format(1, 8000)]
+        })
+    );
+}
+
+#[test]
+fn open_reply_result() {
+    assert!(OpenReplyPdu { result: S_OK }.is_success());
+    assert!(!OpenReplyPdu { result: E_FAIL }.is_success());
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    Open(AudioFormat, u32),
+    FormatChange(AudioFormat),
+    Close,
+    OpenFailed(u32),
+    Data(Vec<u8>),
+}
+
+#[derive(Debug)]
+struct Microphone {
+    formats: Vec<AudioFormat>,
+    available: bool,
+    recorder: Recorder<Event>,
+}
+
+impl AudioInputClientHandler for Microphone {
+    fn is_supported(&self, format: &AudioFormat) -> bool {
+        self.formats.contains(format)
+    }
+
+    fn open(&mut self, format: &AudioFormat, frames_per_packet: u32) -> bool {
+        self.recorder.record(Event::Open(format.clone(), frames_per_packet));
+        self.available
+    }
+
+    fn format_change(&mut self, format: &AudioFormat) {
+        self.recorder.record(Event::FormatChange(format.clone()));
+    }
+
+    fn close(&mut self) {
+        self.recorder.record(Event::Close);
+    }
+}
+
+impl AudioInputServerHandler for Recorder<Event> {
+    fn opened(&mut self, format: &AudioFormat) {
+        self.record(Event::Open(format.clone(), 0));
+    }
+
+    fn open_failed(&mut self, result: u32) {
+        self.record(Event::OpenFailed(result));
+    }
+
+    fn data(&mut self, data: &[u8]) {
+        self.record(Event::Data(data.to_vec()));
+    }
+
+    fn closed(&mut self) {
+        self.record(Event::Close);
+    }
+}
+
+fn connect(
+    server_formats: Vec<AudioFormat>,
+    client_formats: Vec<AudioFormat>,
+    available: bool,
+) -> (AudioInputServer, AudioInputClient, Events<Event>, Events<Event>) {
+    let server_events = Events::default();
+    let client_events = Events::default();
+
+    let mut server =
+        AudioInputServer::new(Box::new(Recorder::new(&server_events)), server_formats).with_frames_per_packet(320);
+    let mut client = AudioInputClient::new(Box::new(Microphone {
+        formats: client_formats,
+        available,
+        recorder: Recorder::new(&client_events),
+    }));
+
+    open(&mut server, &mut client);
+
+    (server, client, server_events, client_events)
+}
+
+#[test]
+fn negotiation_and_recording() {
+    let (mut server, mut client, server_events, client_events) = connect(
+        vec![pcm_format(2, 44100), pcm_format(1, 16000)],
+        vec![pcm_format(1, 16000), pcm_format(1, 8000)],
+        true,
+    );
+
+    assert!(server.is_recording());
+    assert!(client.is_recording());
+    assert_eq!(server.format(), Some(&pcm_format(1, 16000)));
+    assert_eq!(client.format(), Some(&pcm_format(1, 16000)));
+    assert_eq!(*client_events.lock().unwrap(), [Event::Open(pcm_format(1, 16000), 320)]);
+    assert_eq!(*server_events.lock().unwrap(), [Event::Open(pcm_format(1, 16000), 0)]);
+
+    assert_eq!(client.encode_data(vec![1, 2, 3, 4]).unwrap().len(), 2);
+
+    for message in [
+        AudioInputPdu::IncomingData,
+        AudioInputPdu::Data(DataPdu { data: vec![1, 2, 3, 4] }),
+    ] {
+        let replies = server.process(CHANNEL_ID, &encode_vec(&message).unwrap()).unwrap();
+        assert!(replies.is_empty());
+    }
+
+    server.close(CHANNEL_ID);
+    client.close(CHANNEL_ID);
+
+    assert_eq!(
+        *server_events.lock().unwrap(),
+        [
+            Event::Open(pcm_format(1, 16000), 0),
+            Event::Data(vec![1, 2, 3, 4]),
+            Event::Close
+        ]
+    );
+    assert_eq!(client_events.lock().unwrap().last(), Some(&Event::Close));
+    assert!(!client.is_recording());
+    assert!(client.encode_data(vec![0; 4]).is_err());
+}
+
+#[test]
+fn server_format_change() {
+    let (mut server, mut client, _, client_events) =
+        connect(vec![pcm_format(1, 16000)], vec![pcm_format(1, 16000)], true);
+
+    // The server requests a format change, which the client acknowledges.
+    let messages: Vec<DvcMessage> = vec![Box::new(AudioInputPdu::FormatChange(FormatChangePdu { new_format: 0 }))];
+    exchange(&mut server, &mut client, messages);
+
+    assert_eq!(
+        client_events.lock().unwrap().last(),
+        Some(&Event::FormatChange(pcm_format(1, 16000)))
+    );
+    assert!(server.is_recording());
+}
+
+#[test]
+fn no_common_format() {
+    let (server, client, server_events, client_events) =
+        connect(vec![pcm_format(2, 44100)], vec![pcm_format(1, 8000)], true);
+
+    assert!(!server.is_recording());
+    assert!(!client.is_recording());
+    assert!(server_events.lock().unwrap().is_empty());
+    assert!(client_events.lock().unwrap().is_empty());
+}
+
+#[test]
+fn microphone_unavailable() {
+    let (server, client, server_events, _) = connect(vec![pcm_format(1, 16000)], vec![pcm_format(1, 16000)], false);
+
+    assert!(!server.is_recording());
+    assert!(!client.is_recording());
+    assert_eq!(*server_events.lock().unwrap(), [Event::OpenFailed(E_FAIL)]);
+}
@@ -0,0 +1,143 @@
+use std::fs;
+
+use ironrdp_cliprdr::pdu::{
+    ClipboardFileAttributes, FileContentsFlags, FileContentsResponse, FileDescriptor, PackedFileList,
+};
+use ironrdp_cliprdr_format::download::{DownloadEvent, RemoteDownload};
+
+fn descriptor(name: &str, attributes: ClipboardFileAttributes, file_size: Option<u64>) -> FileDescriptor {
+    FileDescriptor {
+        attributes: Some(attributes),
+        last_write_time: None,
+        file_size,
+        name: name.to_owned(),
+    }
+}
+
+#[test]
+fn remote_files_download() {
+    let root = std::env::temp_dir().join(format!("ironrdp-cliprdr-download-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+
+    let file_list = PackedFileList {
+        files: vec![
+            descriptor("photos", ClipboardFileAttributes::DIRECTORY, None),
+            descriptor("photos\\a.png", ClipboardFileAttributes::ARCHIVE, Some(5)),
+            // Unsafe names are skipped.
+            descriptor("..\\escape.txt", ClipboardFileAttributes::ARCHIVE, Some(1)),
+            descriptor("notes.txt", ClipboardFileAttributes::ARCHIVE, None),
+        ],
+    };
+
+    let mut download = RemoteDownload::new(&file_list, &root, 7, Some(3)).unwrap();
+    assert_eq!((download.transferred(), download.total_size()), (0, 5));
+
+    // The directory is created right away.
+    let (request, events) = download.start();
+    assert!(matches!(events.as_slice(), [DownloadEvent::FileCompleted { path }] if path.ends_with("photos")));
+    assert!(root.join("photos").is_dir());
+
+    let request = request.unwrap();
+    assert_eq!(
+        (request.stream_id, request.index, request.flags, request.data_id),
+        (7, 1, FileContentsFlags::DATA, Some(3))
+    );
+
+    let (request, events) =
+        download.on_file_contents_response(&FileContentsResponse::new_data_response(7, b"123".as_slice()));
+    assert!(matches!(
+        events.as_slice(),
+        [DownloadEvent::Progress {
+            transferred: 3,
+            total: 5
+        }]
+    ));
+    assert_eq!(request.unwrap().position, 3);
+
+    let (request, events) =
+        download.on_file_contents_response(&FileContentsResponse::new_data_response(7, b"45".as_slice()));
+    assert!(matches!(
+        events.as_slice(),
+        [
+            DownloadEvent::Progress { transferred: 5, total: 5 },
+            DownloadEvent::FileCompleted { path },
+        ] if path.ends_with("a.png")
+    ));
+    assert_eq!(fs::read(root.join("photos").join("a.png")).unwrap(), b"12345");
+
+    // The size missing from the file list is requested first.
+    let request = request.unwrap();
+    assert_eq!((request.index, request.flags), (3, FileContentsFlags::SIZE));
+
+    let (request, events) = download.on_file_contents_response(&FileContentsResponse::new_size_response(7, 3));
+    assert!(events.is_empty());
+    assert_eq!(download.total_size(), 8);
+    assert_eq!(request.unwrap().flags, FileContentsFlags::DATA);
+
+    let (request, events) =
+        download.on_file_contents_response(&FileContentsResponse::new_data_response(7, b"abc".as_slice()));
+    assert!(request.is_none());
+    assert!(matches!(
+        events.as_slice(),
+        [
+            DownloadEvent::Progress {
+                transferred: 8,
+                total: 8
+            },
+            DownloadEvent::FileCompleted { .. },
+            DownloadEvent::Completed,
+        ]
+    ));
+    assert_eq!(fs::read(root.join("notes.txt")).unwrap(), b"abc");
+    assert!(download.is_completed());
+    assert!(!root.parent().unwrap().join("escape.txt").exists());
+
+    // A completed download is not cancelled.
+    assert!(download.cancel().is_none());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn remote_files_download_failure_and_cancellation() {
+    let root = std::env::temp_dir().join(format!("ironrdp-cliprdr-download-cancel-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+
+    let empty = PackedFileList { files: Vec::new() };
+    assert!(RemoteDownload::new(&empty, &root, 1, None).is_err());
+
+    let file_list = PackedFileList {
+        files: vec![
+            descriptor("a.bin", ClipboardFileAttributes::ARCHIVE, Some(4)),
+            descriptor("b.bin", ClipboardFileAttributes::ARCHIVE, Some(4)),
+        ],
+    };
+
+    let mut download = RemoteDownload::new(&file_list, &root, 1, None).unwrap();
+    let (request, _) = download.start();
+    assert_eq!(request.unwrap().index, 0);
+
+    download.on_file_contents_response(&FileContentsResponse::new_data_response(1, b"01".as_slice()));
+
+    // The partially written file is removed, and the download goes on with the next entry.
+    let (request, events) = download.on_file_contents_response(&FileContentsResponse::new_error(1));
+    assert!(matches!(events.as_slice(), [DownloadEvent::Failed { path, .. }] if path.ends_with("a.bin")));
+    assert!(!root.join("a.bin").exists());
+    assert_eq!(request.unwrap().index, 1);
+
+    download.on_file_contents_response(&FileContentsResponse::new_data_response(1, b"01".as_slice()));
+    assert_eq!(download.transferred(), 2);
+
+    assert!(matches!(download.cancel(), Some(DownloadEvent::Cancelled)));
+    assert!(download.is_cancelled());
+    assert!(!root.join("b.bin").exists());
+    assert!(download.cancel().is_none());
+
+    // Responses to the last request are ignored.
+    let (request, events) =
+        download.on_file_contents_response(&FileContentsResponse::new_data_response(1, b"23".as_slice()));
+    assert!(request.is_none());
+    assert!(events.is_empty());
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,120 @@
+use std::fs;
+
+use ironrdp_cliprdr::pdu::{ClipboardFormatId, FileContentsFlags, FileContentsRequest};
+use ironrdp_cliprdr_format::file_drop::{FileDrop, FileDropEvent};
+use ironrdp_cliprdr_format::mime::FILE_LIST_FORMAT_ID;
+
+fn request(index: u32, flags: FileContentsFlags, position: u64, requested_size: u32) -> FileContentsRequest {
+    FileContentsRequest {
+        stream_id: 1,
+        index,
+        flags,
+        position,
+        requested_size,
+        data_id: None,
+    }
+}
+
+#[test]
+fn dropped_files_transfer() {
+    let root = std::env::temp_dir().join(format!("ironrdp-cliprdr-file-drop-{}", std::process::id()));
+    fs::create_dir_all(root.join("photos")).unwrap();
+    fs::write(root.join("photos").join("a.png"), b"12345").unwrap();
+    fs::write(root.join("notes.txt"), b"abc").unwrap();
+
+    let mut file_drop = FileDrop::new(&[root.join("photos"), root.join("notes.txt")]).unwrap();
+
+    assert!(file_drop.is_file_list_format(FILE_LIST_FORMAT_ID));
+    assert!(!file_drop.is_file_list_format(ClipboardFormatId::CF_UNICODETEXT));
+
+    let file_list = file_drop.file_list_response().unwrap().to_file_list().unwrap();
+    let names: Vec<_> = file_list.files.iter().map(|file| file.name.as_str()).collect();
+    assert_eq!(names, ["photos", "photos\\a.png", "notes.txt"]);
+    assert_eq!((file_drop.transferred(), file_drop.total_size()), (0, 8));
+
+    // Size requests don't make progress.
+    let (response, events) = file_drop.on_file_contents_request(&request(1, FileContentsFlags::SIZE, 0, 8));
+    assert_eq!(response.data_as_size().unwrap(), 5);
+    assert!(events.is_empty());
+
+    let (response, events) = file_drop.on_file_contents_request(&request(1, FileContentsFlags::DATA, 0, 3));
+    assert_eq!(response.data(), b"123");
+    assert!(matches!(
+        events.as_slice(),
+        [FileDropEvent::Progress {
+            transferred: 3,
+            total: 8
+        }]
+    ));
+
+    let (response, events) = file_drop.on_file_contents_request(&request(1, FileContentsFlags::DATA, 3, 64));
+    assert_eq!(response.data(), b"45");
+    assert!(matches!(
+        events.as_slice(),
+        [
+            FileDropEvent::FileCompleted { path },
+            FileDropEvent::Progress { transferred: 5, total: 8 },
+        ] if path.ends_with("a.png")
+    ));
+
+    // Chunks read again are not counted twice.
+    let (_, events) = file_drop.on_file_contents_request(&request(1, FileContentsFlags::DATA, 0, 5));
+    assert!(matches!(
+        events.as_slice(),
+        [FileDropEvent::Progress {
+            transferred: 5,
+            total: 8
+        }]
+    ));
+
+    let (_, events) = file_drop.on_file_contents_request(&request(2, FileContentsFlags::DATA, 0, 64));
+    assert!(matches!(
+        events.as_slice(),
+        [
+            FileDropEvent::FileCompleted { .. },
+            FileDropEvent::Progress {
+                transferred: 8,
+                total: 8
+            },
+            FileDropEvent::Completed,
+        ]
+    ));
+
+    // A completed drop is not cancelled, it can still be pasted again.
+    assert!(file_drop.cancel().is_none());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn dropped_files_failure_and_cancellation() {
+    let root = std::env::temp_dir().join(format!("ironrdp-cliprdr-file-drop-cancel-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("a.bin"), b"0123456789").unwrap();
+
+    assert!(FileDrop::new(&[]).is_err());
+    assert!(FileDrop::new(&[root.join("missing.bin")]).is_err());
+
+    let mut file_drop = FileDrop::new(&[root.join("a.bin")]).unwrap();
+
+    // The file is gone by the time the remote reads it.
+    fs::remove_file(root.join("a.bin")).unwrap();
+    let (response, events) = file_drop.on_file_contents_request(&request(0, FileContentsFlags::DATA, 0, 4));
+    assert!(response.is_error());
+    assert!(matches!(events.as_slice(), [FileDropEvent::Failed { path, .. }] if path.ends_with("a.bin")));
+
+    let (response, events) = file_drop.on_file_contents_request(&request(7, FileContentsFlags::SIZE, 0, 8));
+    assert!(response.is_error());
+    assert!(events.is_empty());
+
+    assert!(matches!(file_drop.cancel(), Some(FileDropEvent::Cancelled)));
+    assert!(file_drop.is_cancelled());
+    assert!(file_drop.cancel().is_none());
+
+    fs::write(root.join("a.bin"), b"0123456789").unwrap();
+    let (response, events) = file_drop.on_file_contents_request(&request(0, FileContentsFlags::DATA, 0, 4));
+    assert!(response.is_error());
+    assert!(events.is_empty());
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -1,5 +1,9 @@
 use ironrdp_cliprdr_format::bitmap::{dib_to_png, dibv5_to_png, png_to_cf_dib, png_to_cf_dibv5};
+use ironrdp_cliprdr_format::convert::{
+    ClipboardContent, ContentKind, ConversionError, WindowsFormat, HTML_FORMAT_NAME,
+};
 use ironrdp_cliprdr_format::html::{cf_html_to_plain_html, plain_html_to_cf_html};
+use ironrdp_cliprdr_format::text::{cf_unicodetext_to_utf8, utf8_to_cf_unicodetext};
 
 #[test]
 fn dib_to_png_conversion_1() {
@@ -52,3 +56,78 @@ fn test_cf_html_to_text() {
     let roundtrip_html_text = cf_html_to_plain_html(&cf_html).unwrap();
     assert_eq!(actual, roundtrip_html_text);
 }
+
+#[test]
+fn unicodetext_conversion() {
+    let cf_unicodetext = utf8_to_cf_unicodetext("h\u{e9}\nllo\r\n\u{1f600}");
+    assert_eq!(
+        cf_unicodetext,
+        [
+            b'h', 0, 0xe9, 0, b'\r', 0, b'\n', 0, b'l', 0, b'l', 0, b'o', 0, b'\r', 0, b'\n', 0, 0x3d, 0xd8, 0x00,
+            0xde, 0, 0
+        ]
+    );
+    assert_eq!(
+        cf_unicodetext_to_utf8(&cf_unicodetext).unwrap(),
+        "h\u{e9}\nllo\n\u{1f600}"
+    );
+
+    // Data after the null terminator is ignored.
+    assert_eq!(cf_unicodetext_to_utf8(&[b'a', 0, 0, 0, b'b', 0]).unwrap(), "a");
+    // No null terminator.
+    assert_eq!(cf_unicodetext_to_utf8(&[b'a', 0]).unwrap(), "a");
+
+    assert!(cf_unicodetext_to_utf8(&[b'a', 0, b'b']).is_err());
+    // Unpaired surrogate.
+    assert!(cf_unicodetext_to_utf8(&[0x3d, 0xd8, b'a', 0]).is_err());
+}
+
+#[test]
+fn windows_format_identification() {
+    assert_eq!(WindowsFormat::from_format(13, None), Some(WindowsFormat::UnicodeText));
+    assert_eq!(WindowsFormat::from_format(8, None), Some(WindowsFormat::Dib));
+    assert_eq!(WindowsFormat::from_format(17, None), Some(WindowsFormat::DibV5));
+    assert_eq!(
+        WindowsFormat::from_format(0xc00e, Some(HTML_FORMAT_NAME)),
+        Some(WindowsFormat::Html)
+    );
+    assert_eq!(WindowsFormat::from_format(0xc00f, Some("PNG")), None);
+    assert_eq!(WindowsFormat::from_format(1, None), None);
+
+    assert_eq!(WindowsFormat::Html.standard_id(), None);
+    assert_eq!(WindowsFormat::Html.name(), Some(HTML_FORMAT_NAME));
+    assert_eq!(
+        ContentKind::Png.windows_formats(),
+        [WindowsFormat::DibV5, WindowsFormat::Dib]
+    );
+}
+
+#[test]
+fn content_conversion() {
+    let text = ClipboardContent::Text("line\nline".to_owned());
+    let data = text.to_windows_format(WindowsFormat::UnicodeText).unwrap();
+    assert_eq!(
+        ClipboardContent::from_windows_format(WindowsFormat::UnicodeText, &data).unwrap(),
+        text
+    );
+
+    let html = ClipboardContent::Html("<b>bold</b>".to_owned());
+    let data = html.to_windows_format(WindowsFormat::Html).unwrap();
+    assert_eq!(
+        ClipboardContent::from_windows_format(WindowsFormat::Html, &data).unwrap(),
+        html
+    );
+
+    let input = include_bytes!("../../test_data/pdu/clipboard/cf_dibv5.pdu");
+    let png = ClipboardContent::from_windows_format(WindowsFormat::DibV5, input).unwrap();
+    assert_eq!(png.kind(), ContentKind::Png);
+    assert_eq!(png.to_windows_format(WindowsFormat::DibV5).unwrap(), input);
+
+    assert!(matches!(
+        text.to_windows_format(WindowsFormat::Dib),
+        Err(ConversionError::Mismatch {
+            kind: ContentKind::Text,
+            format: WindowsFormat::Dib
+        })
+    ));
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ironrdp_cliprdr::pdu::{ClipboardFileAttributes, ClipboardFormat, ClipboardFormatId, ClipboardFormatName};
+use ironrdp_cliprdr_format::convert::{ClipboardContent, ContentKind};
+use ironrdp_cliprdr_format::mime::{
+    content_from_target_data, content_to_target_data, file_list_to_uri_list, format_for_target, formats_for_targets,
+    local_file_list, packed_file_list, paths_to_uri_list, targets_for_formats, uri_list_to_paths, TargetKind,
+    FILE_LIST_FORMAT_ID, HTML_FORMAT_ID,
+};
+
+fn remote_formats() -> Vec<ClipboardFormat> {
+    vec![
+        ClipboardFormat::new(ClipboardFormatId::CF_TEXT),
+        ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT),
+        ClipboardFormat::new(ClipboardFormatId(0xC09E)).with_name(ClipboardFormatName::HTML),
+        ClipboardFormat::new(ClipboardFormatId::CF_DIB),
+        ClipboardFormat::new(ClipboardFormatId(0xC0A5)).with_name(ClipboardFormatName::FILE_LIST),
+        ClipboardFormat::new(ClipboardFormatId(0xC0B0)).with_name(ClipboardFormatName::new("Rich Text Format")),
+    ]
+}
+
+#[test]
+fn target_kinds() {
+    assert_eq!(
+        TargetKind::from_target("UTF8_STRING"),
+        Some(TargetKind::Content(ContentKind::Text))
+    );
+    assert_eq!(
+        TargetKind::from_target("text/plain;charset=UTF-8"),
+        Some(TargetKind::Content(ContentKind::Text))
+    );
+    assert_eq!(
+        TargetKind::from_target("text/html"),
+        Some(TargetKind::Content(ContentKind::Html))
+    );
+    assert_eq!(
+        TargetKind::from_target("image/png"),
+        Some(TargetKind::Content(ContentKind::Png))
+    );
+    assert_eq!(TargetKind::from_target("text/uri-list"), Some(TargetKind::FileList));
+    assert_eq!(TargetKind::from_target("TARGETS"), None);
+    assert_eq!(TargetKind::from_target("image/jpeg"), None);
+}
+
+#[test]
+fn local_targets_to_formats() {
+    let formats = formats_for_targets(&[
+        "TARGETS",
+        "TIMESTAMP",
+        "UTF8_STRING",
+        "text/plain;charset=utf-8",
+        "text/html",
+        "image/png",
+        "text/uri-list",
+    ]);
+
+    assert_eq!(
+        formats,
+        [
+            ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT),
+            ClipboardFormat::new(HTML_FORMAT_ID).with_name(ClipboardFormatName::HTML),
+            ClipboardFormat::new(ClipboardFormatId::CF_DIBV5),
+            ClipboardFormat::new(ClipboardFormatId::CF_DIB),
+            ClipboardFormat::new(FILE_LIST_FORMAT_ID).with_name(ClipboardFormatName::FILE_LIST),
+        ]
+    );
+}
+
+#[test]
+fn remote_formats_to_targets() {
+    assert_eq!(
+        targets_for_formats(&remote_formats()),
+        [
+            "text/plain;charset=utf-8",
+            "UTF8_STRING",
+            "text/plain",
+            "STRING",
+            "TEXT",
+            "text/html",
+            "image/png",
+            "text/uri-list",
+        ]
+    );
+}
+
+#[test]
+fn requested_format() {
+    let formats = remote_formats();
+
+    assert_eq!(
+        format_for_target("STRING", &formats).unwrap().id,
+        ClipboardFormatId::CF_UNICODETEXT
+    );
+    assert_eq!(
+        format_for_target("text/html", &formats).unwrap().id,
+        ClipboardFormatId(0xC09E)
+    );
+    // CF_DIBV5 is preferred, but not announced
+    assert_eq!(
+        format_for_target("image/png", &formats).unwrap().id,
+        ClipboardFormatId::CF_DIB
+    );
+    assert_eq!(
+        format_for_target("text/uri-list", &formats).unwrap().id,
+        ClipboardFormatId(0xC0A5)
+    );
+    assert!(format_for_target("text/rtf", &formats).is_none());
+    assert!(format_for_target("image/png", &formats[..2]).is_none());
+}
+
+#[test]
+fn target_data() {
+    let text = content_from_target_data("UTF8_STRING", "h\u{e9}llo".as_bytes()).unwrap();
+    assert_eq!(text, ClipboardContent::Text("h\u{e9}llo".to_owned()));
+    assert_eq!(content_to_target_data(&text), "h\u{e9}llo".as_bytes());
+
+    let html = content_from_target_data("text/html", b"<b>bold</b>").unwrap();
+    assert_eq!(html, ClipboardContent::Html("<b>bold</b>".to_owned()));
+
+    assert!(content_from_target_data("text/uri-list", b"file:///tmp/a").is_none());
+}
+
+#[test]
+fn uri_list() {
+    let paths = uri_list_to_paths(
+        "# copied files\r\nfile:///home/user/My%20Report.pdf\r\nfile://localhost/tmp/caf%C3%A9\r\n\
+         https://example.com/index.html\r\nfile://remote-host/share/file\r\nfile:///tmp/bad%2\r\n",
+    );
+    assert_eq!(
+        paths,
+        [
+            PathBuf::from("/home/user/My Report.pdf"),
+            PathBuf::from("/tmp/caf\u{e9}")
+        ]
+    );
+
+    assert_eq!(
+        paths_to_uri_list(&paths),
+        "file:///home/user/My%20Report.pdf\r\nfile:///tmp/caf%C3%A9\r\n"
+    );
+    assert_eq!(uri_list_to_paths(&paths_to_uri_list(&paths)), paths);
+
+    // Relative paths can't be represented
+    assert_eq!(paths_to_uri_list(&[PathBuf::from("relative/path")]), "");
+}
+
+#[test]
+fn file_list_roundtrip() {
+    let root = std::env::temp_dir().join(format!("ironrdp-cliprdr-mime-{}", std::process::id()));
+    let directory = root.join("photos");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("b.png"), [0; 3]).unwrap();
+    fs::write(directory.join("a.png"), [0; 5]).unwrap();
+    fs::write(root.join("notes.txt"), "hello").unwrap();
+
+    let files = local_file_list(&[directory.clone(), root.join("notes.txt")]).unwrap();
+    let names: Vec<&str> = files.iter().map(|file| file.descriptor.name.as_str()).collect();
+    assert_eq!(names, ["photos", "photos\\a.png", "photos\\b.png", "notes.txt"]);
+
+    assert_eq!(files[0].path, directory);
+    assert!(files[0]
+        .descriptor
+        .attributes
+        .unwrap()
+        .contains(ClipboardFileAttributes::DIRECTORY));
+    assert_eq!(files[0].descriptor.file_size, None);
+    assert_eq!(files[1].path, directory.join("a.png"));
+    assert_eq!(files[1].descriptor.file_size, Some(5));
+    assert!(files[1].descriptor.last_write_time.is_some());
+
+    let file_list = packed_file_list(&files);
+    assert_eq!(
+        file_list_to_uri_list(&file_list, Path::new("/tmp/received")),
+        "file:///tmp/received/photos\r\nfile:///tmp/received/notes.txt\r\n"
+    );
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -1,4 +1,9 @@
+mod download;
+mod file_drop;
 mod format;
+mod mime;
+mod pasteboard;
+mod policy;
 
 use expect_test::expect;
 use ironrdp_cliprdr::pdu::{
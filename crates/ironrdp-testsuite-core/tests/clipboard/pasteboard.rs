@@ -0,0 +1,228 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ironrdp_cliprdr::pdu::{
+    ClipboardFileAttributes, ClipboardFormat, ClipboardFormatId, ClipboardFormatName, FileContentsFlags,
+    FileContentsResponse, FileDescriptor, PackedFileList,
+};
+use ironrdp_cliprdr_format::convert::{ClipboardContent, ContentKind};
+use ironrdp_cliprdr_format::mime::{TargetKind, FILE_LIST_FORMAT_ID, HTML_FORMAT_ID};
+use ironrdp_cliprdr_format::pasteboard::{
+    content_from_pasteboard_data, content_to_pasteboard_data, file_urls_to_paths, format_for_pasteboard_type,
+    formats_for_pasteboard_types, local_path, pasteboard_type_kind, pasteboard_types_for_formats, promised_files,
+    FileDownload, PromisedFile, FILE_CHUNK_SIZE,
+};
+
+fn descriptor(name: &str, attributes: ClipboardFileAttributes, file_size: Option<u64>) -> FileDescriptor {
+    FileDescriptor {
+        attributes: Some(attributes),
+        last_write_time: None,
+        file_size,
+        name: name.to_owned(),
+    }
+}
+
+#[test]
+fn pasteboard_type_kinds() {
+    assert_eq!(
+        pasteboard_type_kind("public.utf8-plain-text"),
+        Some(TargetKind::Content(ContentKind::Text))
+    );
+    assert_eq!(
+        pasteboard_type_kind("public.html"),
+        Some(TargetKind::Content(ContentKind::Html))
+    );
+    assert_eq!(
+        pasteboard_type_kind("public.png"),
+        Some(TargetKind::Content(ContentKind::Png))
+    );
+    assert_eq!(pasteboard_type_kind("public.file-url"), Some(TargetKind::FileList));
+    assert_eq!(pasteboard_type_kind("public.tiff"), None);
+}
+
+#[test]
+fn local_pasteboard_types_to_formats() {
+    let formats = formats_for_pasteboard_types(&[
+        "public.file-url",
+        "com.apple.finder.node",
+        "public.utf8-plain-text",
+        "public.utf8-plain-text",
+    ]);
+
+    assert_eq!(
+        formats,
+        [
+            ClipboardFormat::new(FILE_LIST_FORMAT_ID).with_name(ClipboardFormatName::FILE_LIST),
+            ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT),
+        ]
+    );
+}
+
+#[test]
+fn remote_formats_to_pasteboard_types() {
+    let formats = [
+        ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT),
+        ClipboardFormat::new(ClipboardFormatId::CF_TEXT),
+        ClipboardFormat::new(HTML_FORMAT_ID).with_name(ClipboardFormatName::HTML),
+        ClipboardFormat::new(ClipboardFormatId::CF_DIB),
+        ClipboardFormat::new(ClipboardFormatId(0xC0A5)).with_name(ClipboardFormatName::FILE_LIST),
+    ];
+
+    // Files are offered as promises instead.
+    assert_eq!(
+        pasteboard_types_for_formats(&formats),
+        ["public.utf8-plain-text", "public.html", "public.png"]
+    );
+
+    assert_eq!(
+        format_for_pasteboard_type("public.png", &formats).map(|format| format.id),
+        Some(ClipboardFormatId::CF_DIB)
+    );
+    assert_eq!(
+        format_for_pasteboard_type("public.utf8-plain-text", &formats).map(|format| format.id),
+        Some(ClipboardFormatId::CF_UNICODETEXT)
+    );
+    assert_eq!(format_for_pasteboard_type("public.tiff", &formats), None);
+}
+
+#[test]
+fn pasteboard_data() {
+    assert_eq!(
+        content_from_pasteboard_data("public.utf8-plain-text", "héllo".as_bytes()),
+        Some(ClipboardContent::Text(String::from("héllo")))
+    );
+    assert_eq!(
+        content_from_pasteboard_data("public.png", &[0x89, b'P']),
+        Some(ClipboardContent::Png(vec![0x89, b'P']))
+    );
+    assert_eq!(content_from_pasteboard_data("public.file-url", b"file:///tmp/a"), None);
+    assert_eq!(content_from_pasteboard_data("public.tiff", &[0]), None);
+
+    assert_eq!(
+        content_to_pasteboard_data(&ClipboardContent::Html(String::from("<b>hi</b>"))),
+        b"<b>hi</b>"
+    );
+}
+
+#[test]
+fn file_urls() {
+    assert_eq!(
+        file_urls_to_paths(&[
+            "file:///Users/me/My%20Report.pdf",
+            "file:///tmp/",
+            "https://example.com/a"
+        ]),
+        [PathBuf::from("/Users/me/My Report.pdf"), PathBuf::from("/tmp/")]
+    );
+}
+
+#[test]
+fn promised_remote_files() {
+    let file_list = PackedFileList {
+        files: vec![
+            descriptor("photos", ClipboardFileAttributes::DIRECTORY, None),
+            descriptor("photos\\a.png", ClipboardFileAttributes::NORMAL, Some(5)),
+            descriptor("..\\escape.txt", ClipboardFileAttributes::NORMAL, Some(1)),
+            descriptor("notes.txt", ClipboardFileAttributes::NORMAL, Some(5)),
+        ],
+    };
+
+    assert_eq!(
+        promised_files(&file_list),
+        [
+            PromisedFile {
+                name: String::from("photos"),
+                is_directory: true,
+                entries: vec![0, 1],
+            },
+            PromisedFile {
+                name: String::from("notes.txt"),
+                is_directory: false,
+                entries: vec![3],
+            },
+        ]
+    );
+
+    assert_eq!(
+        local_path(Path::new("/tmp/received"), "photos\\a.png"),
+        Some(PathBuf::from("/tmp/received/photos/a.png"))
+    );
+    assert_eq!(local_path(Path::new("/tmp/received"), "..\\escape.txt"), None);
+    assert_eq!(local_path(Path::new("/tmp/received"), "/etc/passwd"), None);
+    assert_eq!(local_path(Path::new("/tmp/received"), ""), None);
+}
+
+#[test]
+fn file_download() {
+    let root = std::env::temp_dir().join(format!("ironrdp-cliprdr-pasteboard-{}", std::process::id()));
+    let path = root.join("photos").join("a.bin");
+    let contents: Vec<u8> = (0..FILE_CHUNK_SIZE + 10)
+        .map(|i| u8::try_from(i % 251).unwrap())
+        .collect();
+
+    // The size isn't known from the file list, it is requested first.
+    let mut download = FileDownload::new(
+        7,
+        1,
+        &descriptor("photos\\a.bin", ClipboardFileAttributes::NORMAL, None),
+        &path,
+        Some(3),
+    )
+    .unwrap();
+
+    let request = download.next_request().unwrap();
+    assert_eq!(request.flags, FileContentsFlags::SIZE);
+    assert_eq!((request.stream_id, request.index, request.data_id), (7, 1, Some(3)));
+    download
+        .on_response(&FileContentsResponse::new_size_response(
+            7,
+            u64::try_from(contents.len()).unwrap(),
+        ))
+        .unwrap();
+
+    let mut position = 0;
+    while let Some(request) = download.next_request() {
+        assert_eq!(request.flags, FileContentsFlags::DATA);
+        assert_eq!(request.position, u64::try_from(position).unwrap());
+
+        let end = position + usize::try_from(request.requested_size).unwrap();
+        download
+            .on_response(&FileContentsResponse::new_data_response(7, &contents[position..end]))
+            .unwrap();
+        position = end;
+    }
+    assert!(download.is_complete());
+    drop(download);
+    assert_eq!(fs::read(&path).unwrap(), contents);
+
+    // Responses of other streams, errors and oversized contents are rejected.
+    let mut download = FileDownload::new(
+        8,
+        0,
+        &descriptor("b.bin", ClipboardFileAttributes::NORMAL, Some(2)),
+        &root.join("b.bin"),
+        None,
+    )
+    .unwrap();
+    assert!(download
+        .on_response(&FileContentsResponse::new_data_response(7, &[0][..]))
+        .is_err());
+    assert!(download.on_response(&FileContentsResponse::new_error(8)).is_err());
+    assert!(download
+        .on_response(&FileContentsResponse::new_data_response(8, &[0, 1, 2][..]))
+        .is_err());
+    assert!(!download.is_complete());
+
+    let directory = FileDownload::new(
+        9,
+        0,
+        &descriptor("photos", ClipboardFileAttributes::DIRECTORY, None),
+        &root.join("photos"),
+        None,
+    )
+    .unwrap();
+    assert!(directory.is_complete());
+    assert!(directory.next_request().is_none());
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,249 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp_cliprdr::backend::CliprdrBackend;
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardFormatId, ClipboardGeneralCapabilityFlags, FileContentsFlags, FileContentsRequest,
+    FileContentsResponse, FormatDataRequest, FormatDataResponse, LockDataId,
+};
+use ironrdp_cliprdr::policy::{ClipboardDirection, CliprdrPolicy};
+use ironrdp_cliprdr::{Cliprdr, CliprdrClient, CliprdrServer, Role};
+use ironrdp_core::impl_as_any;
+use ironrdp_svc::StaticVirtualChannel;
+
+use crate::harness::{exchange_static, Events, Recorder};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    RemoteCopy(Vec<ClipboardFormat>),
+    /// `None` for an error response
+    FormatData(Option<Vec<u8>>),
+    FileContents(u32, Option<Vec<u8>>),
+}
+
+#[derive(Debug)]
+struct ClipboardRecorder(Recorder<Event>);
+
+impl_as_any!(ClipboardRecorder);
+
+impl CliprdrBackend for ClipboardRecorder {
+    fn temporary_directory(&self) -> &str {
+        ".cliprdr"
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        ClipboardGeneralCapabilityFlags::empty()
+    }
+
+    fn on_ready(&mut self) {}
+
+    fn on_request_format_list(&mut self) {}
+
+    fn on_process_negotiated_capabilities(&mut self, _capabilities: ClipboardGeneralCapabilityFlags) {}
+
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]) {
+        self.0.record(Event::RemoteCopy(available_formats.to_vec()));
+    }
+
+    fn on_format_data_request(&mut self, _request: FormatDataRequest) {}
+
+    fn on_format_data_response(&mut self, response: FormatDataResponse<'_>) {
+        let data = (!response.is_error()).then(|| response.data().to_vec());
+        self.0.record(Event::FormatData(data));
+    }
+
+    fn on_file_contents_request(&mut self, _request: FileContentsRequest) {}
+
+    fn on_file_contents_response(&mut self, response: FileContentsResponse<'_>) {
+        let data = (!response.is_error()).then(|| response.data().to_vec());
+        self.0.record(Event::FileContents(response.stream_id(), data));
+    }
+
+    fn on_lock(&mut self, _data_id: LockDataId) {}
+
+    fn on_unlock(&mut self, _data_id: LockDataId) {}
+}
+
+/// Allows the transfers in a single direction, recording the file contents requests
+#[derive(Debug)]
+struct OneWay {
+    allowed: ClipboardDirection,
+    file_requests: Mutex<Vec<(ClipboardDirection, u32)>>,
+}
+
+impl CliprdrPolicy for OneWay {
+    fn allow_copy(&self, direction: ClipboardDirection) -> bool {
+        direction == self.allowed
+    }
+
+    fn allow_format_data(&self, direction: ClipboardDirection, _response: &FormatDataResponse<'_>) -> bool {
+        direction == self.allowed
+    }
+
+    fn file_contents_requested(&self, direction: ClipboardDirection, request: &FileContentsRequest) {
+        self.file_requests.lock().unwrap().push((direction, request.stream_id));
+    }
+
+    fn allow_file_contents(&self, direction: ClipboardDirection, _response: &FileContentsResponse<'_>) -> bool {
+        direction == self.allowed
+    }
+}
+
+const TEXT: ClipboardFormat = ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT);
+
+struct Session {
+    server: StaticVirtualChannel,
+    client: StaticVirtualChannel,
+    server_events: Events<Event>,
+    client_events: Events<Event>,
+    policy: Arc<OneWay>,
+}
+
+/// Connects a server restricted to `allowed` to a client, the client announcing a copy during the initialization
+fn connect(allowed: ClipboardDirection) -> Session {
+    let server_events = Events::default();
+    let client_events = Events::default();
+    let policy = Arc::new(OneWay {
+        allowed,
+        file_requests: Mutex::default(),
+    });
+
+    let mut server = StaticVirtualChannel::new(
+        CliprdrServer::new(Box::new(ClipboardRecorder(Recorder::new(&server_events))))
+            .with_policy(Arc::<OneWay>::clone(&policy)),
+    );
+    let mut client = StaticVirtualChannel::new(CliprdrClient::new(Box::new(ClipboardRecorder(Recorder::new(
+        &client_events,
+    )))));
+
+    let messages = server.start().unwrap();
+    exchange_static(&mut server, &mut client, messages);
+
+    let messages = cliprdr::<ironrdp_cliprdr::Client>(&mut client)
+        .initiate_copy(&[TEXT])
+        .unwrap();
+    exchange_static(&mut client, &mut server, messages.into());
+
+    Session {
+        server,
+        client,
+        server_events,
+        client_events,
+        policy,
+    }
+}
+
+fn cliprdr<R: Role>(channel: &mut StaticVirtualChannel) -> &mut Cliprdr<R> {
+    channel.channel_processor_downcast_mut().unwrap()
+}
+
+fn file_request(stream_id: u32) -> FileContentsRequest {
+    FileContentsRequest {
+        stream_id,
+        index: 0,
+        flags: FileContentsFlags::DATA,
+        position: 0,
+        requested_size: 4,
+        data_id: None,
+    }
+}
+
+#[test]
+fn server_to_client_only() {
+    let Session {
+        mut server,
+        mut client,
+        server_events,
+        client_events,
+        policy,
+    } = connect(ClipboardDirection::LocalToRemote);
+
+    let messages = cliprdr::<ironrdp_cliprdr::Server>(&mut server)
+        .initiate_copy(&[TEXT])
+        .unwrap();
+    exchange_static(&mut server, &mut client, messages.into());
+
+    let messages = cliprdr::<ironrdp_cliprdr::Server>(&mut server)
+        .submit_format_data(FormatDataResponse::new_data(b"server".to_vec()))
+        .unwrap();
+    exchange_static(&mut server, &mut client, messages.into());
+
+    let messages = cliprdr::<ironrdp_cliprdr::Client>(&mut client)
+        .submit_format_data(FormatDataResponse::new_data(b"client".to_vec()))
+        .unwrap();
+    exchange_static(&mut client, &mut server, messages.into());
+
+    let messages = cliprdr::<ironrdp_cliprdr::Client>(&mut client)
+        .request_file_contents(file_request(1))
+        .unwrap();
+    exchange_static(&mut client, &mut server, messages.into());
+
+    let messages = cliprdr::<ironrdp_cliprdr::Client>(&mut client)
+        .submit_file_contents(FileContentsResponse::new_data_response(2, b"data".to_vec()))
+        .unwrap();
+    exchange_static(&mut client, &mut server, messages.into());
+
+    // The copy announced by the client during the initialization isn't given to the server backend either.
+    assert_eq!(
+        *server_events.lock().unwrap(),
+        [Event::FormatData(None), Event::FileContents(2, None)]
+    );
+    assert_eq!(
+        *client_events.lock().unwrap(),
+        [
+            Event::RemoteCopy(vec![TEXT]),
+            Event::FormatData(Some(b"server".to_vec()))
+        ]
+    );
+    assert_eq!(
+        *policy.file_requests.lock().unwrap(),
+        [(ClipboardDirection::LocalToRemote, 1)]
+    );
+}
+
+#[test]
+fn client_to_server_only() {
+    let Session {
+        mut server,
+        mut client,
+        server_events,
+        client_events,
+        policy,
+    } = connect(ClipboardDirection::RemoteToLocal);
+
+    let messages = cliprdr::<ironrdp_cliprdr::Server>(&mut server)
+        .initiate_copy(&[TEXT])
+        .unwrap();
+    exchange_static(&mut server, &mut client, messages.into());
+
+    let messages = cliprdr::<ironrdp_cliprdr::Server>(&mut server)
+        .submit_format_data(FormatDataResponse::new_data(b"server".to_vec()))
+        .unwrap();
+    exchange_static(&mut server, &mut client, messages.into());
+
+    let messages = cliprdr::<ironrdp_cliprdr::Server>(&mut server)
+        .request_file_contents(file_request(3))
+        .unwrap();
+    exchange_static(&mut server, &mut client, messages.into());
+
+    let messages = cliprdr::<ironrdp_cliprdr::Client>(&mut client)
+        .submit_file_contents(FileContentsResponse::new_data_response(3, b"data".to_vec()))
+        .unwrap();
+    exchange_static(&mut client, &mut server, messages.into());
+
+    assert_eq!(
+        *server_events.lock().unwrap(),
+        [
+            Event::RemoteCopy(vec![TEXT]),
+            Event::FileContents(3, Some(b"data".to_vec()))
+        ]
+    );
+    // The denied copy is announced as an empty format list, clearing the formats of the previous one.
+    assert_eq!(
+        *client_events.lock().unwrap(),
+        [Event::RemoteCopy(Vec::new()), Event::FormatData(None)]
+    );
+    assert_eq!(
+        *policy.file_requests.lock().unwrap(),
+        [(ClipboardDirection::RemoteToLocal, 3)]
+    );
+}
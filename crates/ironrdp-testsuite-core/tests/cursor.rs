@@ -0,0 +1,79 @@
+use ironrdp_core::{DecodeErrorKind, EncodeErrorKind, ReadCursor, WriteCursor};
+
+#[test]
+fn read_cursor_try_methods_do_not_consume_on_failure() {
+    let mut cursor = ReadCursor::new(&[1, 2, 3]);
+
+    let err = cursor.try_read_array::<4>().unwrap_err();
+    assert_eq!(err.received(), 3);
+    assert_eq!(err.expected(), 4);
+    assert!(cursor.try_read_slice(4).is_err());
+    assert!(cursor.try_peek::<4>().is_err());
+    assert!(cursor.try_advance(4).is_err());
+    assert_eq!(cursor.pos(), 0);
+
+    assert_eq!(cursor.try_peek_slice(2).unwrap(), [1, 2]);
+    assert_eq!(cursor.try_read_array::<2>().unwrap(), [1, 2]);
+    assert_eq!(cursor.try_read_slice(1).unwrap(), [3]);
+    assert!(cursor.is_empty());
+}
+
+#[test]
+fn read_cursor_split_off() {
+    let mut cursor = ReadCursor::new(&[2, 0xAA, 0xBB, 0xCC]);
+
+    let len = usize::from(cursor.read_u8());
+    let mut inner = cursor.try_split_off(len).unwrap();
+    assert_eq!(inner.read_remaining(), [0xAA, 0xBB]);
+    assert_eq!(cursor.remaining(), [0xCC]);
+
+    assert!(cursor.try_split_off(2).is_err());
+    assert_eq!(cursor.len(), 1);
+}
+
+#[test]
+fn read_cursor_checked_conversions() {
+    let mut cursor = ReadCursor::new(&[0x10, 0x00, 0x00, 0x00, 0x00, 0x01]);
+
+    let len: usize = cursor.try_read_u32_as("length").unwrap();
+    assert_eq!(len, 0x10);
+
+    let err = cursor.try_read_u16_as::<u8>("small").unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        DecodeErrorKind::InvalidField { field: "small", .. }
+    ));
+    assert_eq!(cursor.len(), 2, "cursor must not advance on failure");
+
+    let err = cursor.try_read_u32_as::<u32>("truncated").unwrap_err();
+    assert!(matches!(err.kind(), DecodeErrorKind::NotEnoughBytes { .. }));
+
+    let value: u32 = cursor.try_read_u16_as("value").unwrap();
+    assert_eq!(value, 0x0100);
+}
+
+#[test]
+fn write_cursor_try_methods() {
+    let mut buffer = [0u8; 7];
+    let mut cursor = WriteCursor::new(&mut buffer);
+
+    cursor.try_write_u16_from("length", 0x0102_usize).unwrap();
+    cursor.try_write_u32_from("size", 3_u64).unwrap();
+
+    let err = cursor.try_write_u8_from("count", 256_u16).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        EncodeErrorKind::InvalidField { field: "count", .. }
+    ));
+    assert_eq!(cursor.pos(), 6);
+
+    assert!(cursor.try_write_slice(&[1, 2]).is_err());
+    assert!(cursor.try_write_array([1, 2]).is_err());
+    let err = cursor.try_write_u16_from("length", 1_u8).unwrap_err();
+    assert!(matches!(err.kind(), EncodeErrorKind::NotEnoughBytes { .. }));
+
+    cursor.try_write_array([0xFF]).unwrap();
+    assert!(cursor.try_advance(1).is_err());
+
+    assert_eq!(buffer, [0x02, 0x01, 0x03, 0x00, 0x00, 0x00, 0xFF]);
+}
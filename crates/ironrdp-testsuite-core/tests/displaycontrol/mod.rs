@@ -1,5 +1,6 @@
 use ironrdp_core::decode;
 use ironrdp_displaycontrol::pdu;
+use ironrdp_displaycontrol::server::{MonitorLayout, MonitorLayoutError, ScaleFactor};
 use ironrdp_testsuite_core::encode_decode_test;
 
 encode_decode_test! {
@@ -125,3 +126,88 @@ fn only_non_optional_layout_fields_required_to_be_valid() {
     assert!(decoded.physical_dimensions().is_none());
     assert!(decoded.position().is_none())
 }
+
+fn two_monitors(secondary_left: i32) -> pdu::DisplayControlMonitorLayout {
+    pdu::DisplayControlMonitorLayout::new(&[
+        pdu::MonitorLayoutEntry::new_primary(1920, 1080)
+            .unwrap()
+            .with_desktop_scale_factor(150)
+            .unwrap()
+            .with_device_scale_factor(pdu::DeviceScaleFactor::Scale140Percent),
+        pdu::MonitorLayoutEntry::new_secondary(1024, 768)
+            .unwrap()
+            .with_orientation(pdu::MonitorOrientation::Portrait)
+            .with_position(secondary_left, 0)
+            .unwrap()
+            .with_desktop_scale_factor(150)
+            .unwrap(),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn validate_multi_monitor_layout() {
+    let caps = pdu::DisplayControlCapabilities::new(2, 3840, 2400).unwrap();
+
+    let layout = MonitorLayout::validate(&two_monitors(-1024), &caps).unwrap();
+
+    assert_eq!(layout.primary().width, 1920);
+    assert_eq!(
+        layout.primary().scale_factor,
+        Some(ScaleFactor {
+            desktop: 150,
+            device: pdu::DeviceScaleFactor::Scale140Percent,
+        })
+    );
+
+    let secondary = layout.monitors().nth(1).unwrap();
+    assert_eq!((secondary.left, secondary.top), (-1024, 0));
+    assert_eq!(secondary.orientation, pdu::MonitorOrientation::Portrait);
+    assert_eq!(secondary.landscape_dimensions(), (768, 1024));
+    assert_eq!(layout.primary().landscape_dimensions(), (1920, 1080));
+    // The device scale factor is missing, so the desktop one is ignored as well.
+    assert_eq!(secondary.scale_factor, None);
+
+    assert_eq!(layout.desktop_size(), (1024 + 1920, 1080));
+}
+
+#[test]
+fn invalid_monitor_layout() {
+    let caps = pdu::DisplayControlCapabilities::new(2, 3840, 2400).unwrap();
+
+    assert_eq!(
+        MonitorLayout::validate(&two_monitors(1000), &caps),
+        Err(MonitorLayoutError::OverlappingMonitors)
+    );
+
+    assert_eq!(
+        MonitorLayout::validate(&two_monitors(1920), &pdu::DisplayControlCapabilities::default()),
+        Err(MonitorLayoutError::TooManyMonitors { count: 2, max: 1 })
+    );
+
+    let caps = pdu::DisplayControlCapabilities::new(2, 1920, 1080).unwrap();
+    let layout = pdu::DisplayControlMonitorLayout::new(&[
+        pdu::MonitorLayoutEntry::new_primary(1920, 1080).unwrap(),
+        pdu::MonitorLayoutEntry::new_secondary(1920, 1200)
+            .unwrap()
+            .with_position(1920, 0)
+            .unwrap(),
+    ])
+    .unwrap();
+    assert!(matches!(
+        MonitorLayout::validate(&layout, &caps),
+        Err(MonitorLayoutError::AreaTooLarge { .. })
+    ));
+
+    // Not validated when decoding.
+    let no_primary = [
+        0x28, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x80, 0x07, 0x00, 0x00, 0x38, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let layout = decode::<pdu::DisplayControlMonitorLayout>(&no_primary).unwrap();
+    assert_eq!(
+        MonitorLayout::validate(&layout, &caps),
+        Err(MonitorLayoutError::PrimaryMonitor)
+    );
+}
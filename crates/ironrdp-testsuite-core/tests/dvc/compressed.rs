@@ -0,0 +1,105 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::{encode_vec, impl_as_any};
+use ironrdp_dvc::{DrdynvcClient, DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_graphics::zgfx;
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::SvcProcessor as _;
+
+use super::*;
+
+const CHANNEL_ID: u32 = 0x07;
+
+struct Receiver(Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl_as_any!(Receiver);
+
+impl DvcProcessor for Receiver {
+    fn channel_name(&self) -> &str {
+        "receiver"
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        self.0.lock().unwrap().push(payload.to_vec());
+        Ok(Vec::new())
+    }
+}
+
+impl DvcClientProcessor for Receiver {}
+
+fn opened_client(client: DrdynvcClient, received: &Arc<Mutex<Vec<Vec<u8>>>>) -> DrdynvcClient {
+    let mut client = client.with_dynamic_channel(Receiver(Arc::clone(received)));
+
+    let caps = CapabilitiesRequestPdu::new(CapsVersion::V3, Some(CapabilitiesRequestPdu::DEFAULT_PRIORITY_CHARGES));
+    client
+        .process(&encode_vec(&DrdynvcServerPdu::Capabilities(caps)).unwrap())
+        .unwrap();
+    let create = CreateRequestPdu::new(CHANNEL_ID, "receiver".to_owned());
+    client
+        .process(&encode_vec(&DrdynvcServerPdu::Create(create)).unwrap())
+        .unwrap();
+
+    client
+}
+
+fn compressed_pdus() -> [Vec<u8>; 2] {
+    let data_first = DataFirstPdu::new(CHANNEL_ID, 6, zgfx::wrap_uncompressed(b"abc")).with_compressed_data();
+    let data = DataPdu::new(CHANNEL_ID, zgfx::wrap_uncompressed(b"def")).with_compressed_data();
+
+    [
+        encode_vec(&DrdynvcServerPdu::Data(DrdynvcDataPdu::DataFirst(data_first))).unwrap(),
+        encode_vec(&DrdynvcServerPdu::Data(DrdynvcDataPdu::Data(data))).unwrap(),
+    ]
+}
+
+#[test]
+fn decodes_compressed_data() {
+    let [data_first, data] = compressed_pdus();
+
+    assert_eq!(data_first[0], 0x60);
+    match DrdynvcServerPdu::decode(&mut ReadCursor::new(&data_first)).unwrap() {
+        DrdynvcServerPdu::Data(DrdynvcDataPdu::DataFirst(pdu)) => {
+            assert!(pdu.is_compressed());
+            assert_eq!(pdu.length(), 6);
+        }
+        pdu => panic!("unexpected PDU: {pdu:?}"),
+    }
+
+    assert_eq!(data[0], 0x70);
+    let pdu = DrdynvcServerPdu::decode(&mut ReadCursor::new(&data)).unwrap();
+    assert!(matches!(pdu, DrdynvcServerPdu::Data(pdu) if pdu.is_compressed()));
+}
+
+#[test]
+fn client_decompresses_data() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let mut decompressor = zgfx::Decompressor::new();
+    let mut client = opened_client(
+        DrdynvcClient::new().with_decompressor(move |input: &[u8], output: &mut Vec<u8>| {
+            decompressor
+                .decompress(input, output)
+                .map(|_| ())
+                .map_err(|e| ironrdp_core::other_err!("ZGFX", source: e))
+        }),
+        &received,
+    );
+
+    for pdu in compressed_pdus() {
+        client.process(&pdu).unwrap();
+    }
+    assert_eq!(*received.lock().unwrap(), [b"abcdef".to_vec()]);
+}
+
+#[test]
+fn compressed_data_rejected_when_not_negotiated() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let mut client = opened_client(DrdynvcClient::new(), &received);
+
+    let [data_first, _] = compressed_pdus();
+    assert!(client.process(&data_first).is_err());
+    assert!(received.lock().unwrap().is_empty());
+}
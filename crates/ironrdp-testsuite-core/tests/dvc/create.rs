@@ -1,3 +1,8 @@
+use ironrdp_core::{encode_vec, impl_as_any};
+use ironrdp_dvc::{DrdynvcClient, DrdynvcServer, DvcMessage, DvcProcessor, DvcServerProcessor};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::SvcProcessor as _;
+
 use super::*;
 
 const CHANNEL_ID: u32 = 0x0000_0003;
@@ -35,3 +40,70 @@ fn decodes_create_response() {
 fn encodes_create_response() {
     test_encodes(resp_decoded_client(), &RESP_ENCODED);
 }
+
+struct TestChannel;
+
+impl_as_any!(TestChannel);
+
+impl DvcProcessor for TestChannel {
+    fn channel_name(&self) -> &str {
+        "testdvc"
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, _payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+}
+
+impl DvcServerProcessor for TestChannel {}
+
+#[test]
+fn creates_channel_attached_during_session() {
+    let mut drdynvc = DrdynvcServer::new();
+    drdynvc.start().unwrap();
+
+    let capabilities = DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(CapsVersion::V1));
+    assert!(drdynvc.process(&encode_vec(&capabilities).unwrap()).unwrap().is_empty());
+
+    let messages = drdynvc.attach_boxed_dynamic_channel(Box::new(TestChannel)).unwrap();
+    assert_eq!(messages.len(), 1);
+
+    let response = DrdynvcClientPdu::Create(CreateResponsePdu::new(0, CreationStatus::OK));
+    assert!(drdynvc.process(&encode_vec(&response).unwrap()).unwrap().is_empty());
+}
+
+fn create_channel(client: &mut DrdynvcClient, channel_id: u32, channel_name: &str) {
+    let request = DrdynvcServerPdu::Create(CreateRequestPdu::new(channel_id, channel_name.to_owned()));
+    client.process(&encode_vec(&request).unwrap()).unwrap();
+}
+
+#[test]
+fn creates_channel_attached_after_connection() {
+    let mut client = DrdynvcClient::new();
+
+    create_channel(&mut client, CHANNEL_ID, "testdvc");
+    assert!(client.get_dvc_by_channel_id(CHANNEL_ID).is_none());
+
+    client.attach_dynamic_channel(TestChannel);
+    create_channel(&mut client, CHANNEL_ID, "testdvc");
+    assert!(client.get_dvc_by_channel_id(CHANNEL_ID).unwrap().is_open());
+    assert!(client.get_dvc_by_type_id::<TestChannel>().is_some());
+}
+
+#[test]
+fn creates_unknown_channel_with_fallback_handler() {
+    let mut client = DrdynvcClient::new().with_fallback_handler(|channel_name: &str| {
+        (channel_name == "testdvc").then_some(Box::new(TestChannel) as Box<dyn DvcProcessor>)
+    });
+
+    create_channel(&mut client, CHANNEL_ID, "testdvc");
+    assert!(client.get_dvc_by_channel_id(CHANNEL_ID).unwrap().is_open());
+    assert!(client.get_dvc_by_type_id::<TestChannel>().is_some());
+
+    create_channel(&mut client, CHANNEL_ID + 1, "otherdvc");
+    assert!(client.get_dvc_by_channel_id(CHANNEL_ID + 1).is_none());
+}
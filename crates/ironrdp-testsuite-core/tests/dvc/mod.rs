@@ -22,6 +22,13 @@ fn test_decodes<'a, T: Decode<'a> + PartialEq + core::fmt::Debug>(encoded: &'a [
 
 mod capabilities;
 mod close;
+mod compressed;
 mod create;
 mod data;
 mod data_first;
+mod priority;
+mod protocol;
+mod quota;
+mod segment_size;
+mod soft_sync;
+mod stall;
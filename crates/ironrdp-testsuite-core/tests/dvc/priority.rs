@@ -0,0 +1,291 @@
+use ironrdp_core::{encode_vec, impl_as_any, EncodeResult};
+use ironrdp_dvc::pdu::ChannelPriority;
+use ironrdp_dvc::{
+    DrdynvcClient, DrdynvcServer, DvcClientProcessor, DvcEncode, DvcMessage, DvcProcessor, DvcSendQueue,
+    DvcServerProcessor,
+};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::{ChannelFlags, StaticVirtualChannel, SvcMessage, SvcProcessor as _};
+
+use super::*;
+
+struct Payload(Vec<u8>);
+
+impl Encode for Payload {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        dst.write_slice(&self.0);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Payload"
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl DvcEncode for Payload {}
+
+fn payload(size: usize) -> DvcMessage {
+    Box::new(Payload(vec![0xAB; size]))
+}
+
+struct TestChannel {
+    name: &'static str,
+    priority: ChannelPriority,
+}
+
+impl_as_any!(TestChannel);
+
+impl DvcProcessor for TestChannel {
+    fn channel_name(&self) -> &str {
+        self.name
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, _payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn priority(&self) -> ChannelPriority {
+        self.priority
+    }
+}
+
+impl DvcClientProcessor for TestChannel {}
+impl DvcServerProcessor for TestChannel {}
+
+fn decode_messages<T: for<'a> Decode<'a>>(messages: Vec<SvcMessage>) -> Vec<T> {
+    StaticVirtualChannel::chunkify(messages)
+        .unwrap()
+        .iter()
+        // Skip the channel PDU headers
+        .map(|chunk| T::decode(&mut ReadCursor::new(&chunk.filled()[8..])).unwrap())
+        .collect()
+}
+
+#[test]
+fn create_request_priority() {
+    const ENCODED: [u8; 10] = [0x18, 0x03, 0x74, 0x65, 0x73, 0x74, 0x64, 0x76, 0x63, 0x00];
+
+    let request = DrdynvcServerPdu::Create(
+        CreateRequestPdu::new(0x03, String::from("testdvc")).with_priority(ChannelPriority::Low),
+    );
+    test_encodes(&request, &ENCODED);
+    test_decodes(&ENCODED, &request);
+
+    assert_eq!(
+        CreateRequestPdu::new(0x03, String::from("testdvc")).priority(),
+        ChannelPriority::High
+    );
+}
+
+#[test]
+fn client_negotiates_capabilities() {
+    let request = |version| {
+        let caps = CapabilitiesRequestPdu::new(version, Some(CapabilitiesRequestPdu::DEFAULT_PRIORITY_CHARGES));
+        encode_vec(&DrdynvcServerPdu::Capabilities(caps)).unwrap()
+    };
+
+    // The compressed data PDUs of the version 3 require a decompressor.
+    let mut client = DrdynvcClient::new();
+    let responses: Vec<DrdynvcClientPdu> = decode_messages(client.process(&request(CapsVersion::V3)).unwrap());
+    assert_eq!(
+        responses,
+        [DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(
+            CapsVersion::V2
+        ))]
+    );
+    assert_eq!(client.capabilities_version(), CapsVersion::V2);
+    assert_eq!(
+        client.priority_charges(),
+        Some(CapabilitiesRequestPdu::DEFAULT_PRIORITY_CHARGES)
+    );
+
+    let mut client = DrdynvcClient::new().with_decompressor(|input: &[u8], output: &mut Vec<u8>| {
+        output.extend_from_slice(input);
+        Ok(())
+    });
+    client.process(&request(CapsVersion::V3)).unwrap();
+    assert_eq!(client.capabilities_version(), CapsVersion::V3);
+
+    let mut client = DrdynvcClient::new();
+    let responses: Vec<DrdynvcClientPdu> = decode_messages(
+        client
+            .process(
+                &encode_vec(&DrdynvcServerPdu::Capabilities(CapabilitiesRequestPdu::new(
+                    CapsVersion::V1,
+                    None,
+                )))
+                .unwrap(),
+            )
+            .unwrap(),
+    );
+    assert_eq!(
+        responses,
+        [DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(
+            CapsVersion::V1
+        ))]
+    );
+    assert_eq!(client.priority_charges(), None);
+}
+
+#[test]
+fn client_channel_priority_assigned_by_server() {
+    let create = |channel_id, name: &str| {
+        let request = CreateRequestPdu::new(channel_id, name.to_owned()).with_priority(ChannelPriority::High);
+        encode_vec(&DrdynvcServerPdu::Create(request)).unwrap()
+    };
+    let channels = || {
+        DrdynvcClient::new()
+            .with_dynamic_channel(TestChannel {
+                name: "gfx",
+                priority: ChannelPriority::Medium,
+            })
+            .with_dynamic_channel(TestChannel {
+                name: "bulk",
+                priority: ChannelPriority::Lowest,
+            })
+    };
+
+    let mut client = channels();
+    let caps = CapabilitiesRequestPdu::new(CapsVersion::V2, Some(CapabilitiesRequestPdu::DEFAULT_PRIORITY_CHARGES));
+    client
+        .process(&encode_vec(&DrdynvcServerPdu::Capabilities(caps)).unwrap())
+        .unwrap();
+    client.process(&create(1, "gfx")).unwrap();
+    assert_eq!(
+        client.get_dvc_by_channel_id(1).unwrap().priority(),
+        ChannelPriority::High
+    );
+
+    // Without capabilities, the priority of the processor is kept.
+    let mut client = channels();
+    client.process(&create(2, "bulk")).unwrap();
+    assert_eq!(
+        client.get_dvc_by_channel_id(2).unwrap().priority(),
+        ChannelPriority::Lowest
+    );
+}
+
+#[test]
+fn server_preempts_bulk_transfers() {
+    let mut server = DrdynvcServer::new()
+        .with_dynamic_channel(TestChannel {
+            name: "bulk",
+            priority: ChannelPriority::Lowest,
+        })
+        .with_dynamic_channel(TestChannel {
+            name: "gfx",
+            priority: ChannelPriority::High,
+        });
+
+    let requests: Vec<DrdynvcServerPdu> = decode_messages(server.start().unwrap());
+    assert_eq!(
+        requests,
+        [DrdynvcServerPdu::Capabilities(CapabilitiesRequestPdu::new(
+            CapsVersion::V2,
+            Some(CapabilitiesRequestPdu::DEFAULT_PRIORITY_CHARGES)
+        ))]
+    );
+
+    let caps = DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(CapsVersion::V2));
+    let requests: Vec<DrdynvcServerPdu> = decode_messages(server.process(&encode_vec(&caps).unwrap()).unwrap());
+    assert_eq!(
+        requests,
+        [
+            DrdynvcServerPdu::Create(
+                CreateRequestPdu::new(0, "bulk".to_owned()).with_priority(ChannelPriority::Lowest)
+            ),
+            DrdynvcServerPdu::Create(CreateRequestPdu::new(1, "gfx".to_owned()).with_priority(ChannelPriority::High)),
+        ]
+    );
+
+    // The channels must be opened.
+    assert!(server.queue_dvc_messages(0, vec![payload(1)]).is_err());
+
+    for channel_id in [0, 1] {
+        let response = DrdynvcClientPdu::Create(CreateResponsePdu::new(channel_id, CreationStatus::OK));
+        server.process(&encode_vec(&response).unwrap()).unwrap();
+    }
+
+    server
+        .queue_dvc_messages(0, (0..4).map(|_| payload(1500)).collect())
+        .unwrap();
+    server.queue_dvc_messages(1, vec![payload(100), payload(100)]).unwrap();
+
+    let mut sent = Vec::new();
+    while let Some(message) = server.next_queued_message() {
+        sent.push(message);
+    }
+    assert!(!server.has_queued_messages());
+
+    let channel_ids: Vec<_> = decode_messages::<DrdynvcServerPdu>(sent)
+        .iter()
+        .map(|pdu| match pdu {
+            DrdynvcServerPdu::Data(data) => data.channel_id(),
+            _ => panic!("unexpected PDU"),
+        })
+        .collect();
+    assert_eq!(channel_ids, [1, 0, 1, 0, 0, 0]);
+}
+
+#[test]
+fn send_queue_shares_bandwidth() {
+    let mut queue = DvcSendQueue::new([1, 1, 1, 4]);
+    queue
+        .push(
+            0,
+            ChannelPriority::High,
+            (0..8).map(|_| payload(100)).collect(),
+            ChannelFlags::empty(),
+        )
+        .unwrap();
+    queue
+        .push(
+            3,
+            ChannelPriority::Lowest,
+            (0..8).map(|_| payload(100)).collect(),
+            ChannelFlags::empty(),
+        )
+        .unwrap();
+    assert_eq!(queue.len(), 16);
+    assert_eq!(queue.queued(ChannelPriority::Lowest), 8);
+
+    let sent: Vec<_> = (0..10).map(|_| queue.pop().unwrap()).collect();
+    let channel_ids: Vec<_> = decode_messages::<DrdynvcServerPdu>(sent)
+        .iter()
+        .map(|pdu| match pdu {
+            DrdynvcServerPdu::Data(data) => data.channel_id(),
+            _ => panic!("unexpected PDU"),
+        })
+        .collect();
+
+    // The lowest priority class gets a fifth of the bandwidth.
+    assert_eq!(channel_ids.iter().filter(|id| **id == 3).count(), 2);
+    assert_eq!(queue.len(), 6);
+
+    // Without charges, the classes are sent strictly by priority.
+    let mut queue = DvcSendQueue::new([0; 4]);
+    queue
+        .push(3, ChannelPriority::Lowest, vec![payload(10)], ChannelFlags::empty())
+        .unwrap();
+    queue
+        .push(
+            1,
+            ChannelPriority::Medium,
+            vec![payload(10), payload(10)],
+            ChannelFlags::empty(),
+        )
+        .unwrap();
+    assert_eq!(queue.queued(ChannelPriority::Medium), 2);
+    queue.pop().unwrap();
+    queue.pop().unwrap();
+    assert_eq!(queue.queued(ChannelPriority::Medium), 0);
+    assert_eq!(queue.queued(ChannelPriority::Lowest), 1);
+}
@@ -0,0 +1,244 @@
+use ironrdp_core::{encode_vec, ensure_size, DecodeOwned, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor};
+use ironrdp_dvc::protocol::{Frame, FrameDecoder, ProtocolChannel, ProtocolHandler, ProtocolMessage as _};
+use ironrdp_dvc::{dvc_protocol, DvcMessage, DvcProcessor as _};
+use ironrdp_pdu::PduResult;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Ping(u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pong(u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Text(String);
+
+macro_rules! impl_u32_message {
+    ($ty:ident) => {
+        impl Encode for $ty {
+            fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+                ensure_size!(in: dst, size: 4);
+                dst.write_u32(self.0);
+                Ok(())
+            }
+
+            fn name(&self) -> &'static str {
+                stringify!($ty)
+            }
+
+            fn size(&self) -> usize {
+                4
+            }
+        }
+
+        impl DecodeOwned for $ty {
+            fn decode_owned(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+                ensure_size!(in: src, size: 4);
+                Ok(Self(src.read_u32()))
+            }
+        }
+    };
+}
+
+impl_u32_message!(Ping);
+impl_u32_message!(Pong);
+
+impl Encode for Text {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        dst.write_slice(self.0.as_bytes());
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Text"
+    }
+
+    fn size(&self) -> usize {
+        // Wrong on purpose for the "lie" text, to check the framing.
+        if self.0 == "lie" {
+            self.0.len() + 2
+        } else {
+            self.0.len()
+        }
+    }
+}
+
+impl DecodeOwned for Text {
+    fn decode_owned(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        Ok(Self(String::from_utf8_lossy(src.read_remaining()).into_owned()))
+    }
+}
+
+dvc_protocol! {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum TestMessage: "IronRdp::Test" (version 2) {
+        Ping(Ping) = 0x0001,
+        Pong(Pong) = 0x0002,
+        Text(Text) = 0x0010,
+    }
+}
+
+#[derive(Default)]
+struct Echo {
+    closed: bool,
+}
+
+impl ProtocolHandler for Echo {
+    type Message = TestMessage;
+
+    fn start(&mut self) -> PduResult<Vec<TestMessage>> {
+        Ok(vec![Text("hello".to_owned()).into()])
+    }
+
+    fn handle(&mut self, message: TestMessage) -> PduResult<Vec<TestMessage>> {
+        match message {
+            TestMessage::Ping(Ping(seq)) => Ok(vec![Pong(seq).into()]),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+    }
+}
+
+fn frame(message_type: u16, version: u16, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&message_type.to_le_bytes());
+    frame.extend_from_slice(&version.to_le_bytes());
+    frame.extend_from_slice(&u32::try_from(payload.len()).unwrap().to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn encode_all(messages: Vec<DvcMessage>) -> Vec<Vec<u8>> {
+    messages
+        .iter()
+        .map(|message| encode_vec(message.as_ref()).unwrap())
+        .collect()
+}
+
+fn started_channel() -> ProtocolChannel<Echo> {
+    let mut channel = ProtocolChannel::new(Echo::default());
+    channel.start(7).unwrap();
+    channel
+}
+
+#[test]
+fn start_sends_framed_messages() {
+    let mut channel = ProtocolChannel::new(Echo::default());
+
+    assert_eq!(channel.channel_name(), "IronRdp::Test");
+    assert!(channel.encode_messages(vec![Ping(1).into()]).is_err());
+
+    let messages = channel.start(7).unwrap();
+
+    assert_eq!(encode_all(messages), [frame(0x0010, 2, b"hello")]);
+    assert_eq!(channel.channel_id(), Some(7));
+    assert_eq!(channel.encode_messages(vec![Ping(1).into()]).unwrap().len(), 1);
+}
+
+#[test]
+fn responds_to_requests() {
+    let mut channel = started_channel();
+
+    let responses = channel.process(7, &frame(0x0001, 2, &5u32.to_le_bytes())).unwrap();
+
+    assert_eq!(encode_all(responses), [frame(0x0002, 2, &5u32.to_le_bytes())]);
+    assert_eq!(channel.peer_version(), Some(2));
+}
+
+#[test]
+fn reassembles_split_frames() {
+    let mut channel = started_channel();
+    let ping = frame(0x0001, 3, &9u32.to_le_bytes());
+
+    assert!(channel.process(7, &ping[..3]).unwrap().is_empty());
+    assert!(channel.process(7, &ping[3..10]).unwrap().is_empty());
+
+    let responses = channel.process(7, &ping[10..]).unwrap();
+
+    assert_eq!(encode_all(responses), [frame(0x0002, 2, &9u32.to_le_bytes())]);
+    assert_eq!(channel.peer_version(), Some(3));
+}
+
+#[test]
+fn handles_several_frames_per_message() {
+    let mut channel = started_channel();
+
+    let mut payload = frame(0x0001, 2, &1u32.to_le_bytes());
+    payload.extend(frame(0x0010, 2, b"ignored"));
+    payload.extend(frame(0x0001, 2, &2u32.to_le_bytes()));
+
+    let responses = channel.process(7, &payload).unwrap();
+
+    assert_eq!(
+        encode_all(responses),
+        [
+            frame(0x0002, 2, &1u32.to_le_bytes()),
+            frame(0x0002, 2, &2u32.to_le_bytes())
+        ]
+    );
+}
+
+#[test]
+fn skips_unknown_messages() {
+    let mut decoder = FrameDecoder::<TestMessage>::new();
+
+    decoder.push(&frame(0x00FF, 2, b"from the future"));
+    decoder.push(&frame(0x0001, 2, &3u32.to_le_bytes()));
+
+    assert_eq!(decoder.next_message().unwrap(), Some(TestMessage::Ping(Ping(3))));
+    assert_eq!(decoder.next_message().unwrap(), None);
+    assert!(!decoder.has_partial_frame());
+}
+
+#[test]
+fn rejects_old_versions() {
+    let mut channel = started_channel();
+
+    assert!(channel.process(7, &frame(0x0001, 1, &1u32.to_le_bytes())).is_err());
+}
+
+#[test]
+fn rejects_oversized_payloads() {
+    let mut decoder = FrameDecoder::<TestMessage>::new();
+
+    let length = u32::try_from(TestMessage::MAX_PAYLOAD_SIZE + 1).unwrap();
+    let mut header = frame(0x0010, 2, &[]);
+    header[4..8].copy_from_slice(&length.to_le_bytes());
+    decoder.push(&header);
+
+    assert!(decoder.next_message().is_err());
+}
+
+#[test]
+fn recovers_after_invalid_frames() {
+    let mut decoder = FrameDecoder::<TestMessage>::new();
+
+    decoder.push(&frame(0x0001, 2, &[0xAB, 0xCD]));
+    decoder.push(&frame(0x0001, 1, &1u32.to_le_bytes()));
+    decoder.push(&frame(0x0002, 2, &5u32.to_le_bytes()));
+
+    assert!(decoder.next_message().is_err());
+    assert!(decoder.next_message().is_err());
+    assert_eq!(decoder.next_message().unwrap(), Some(TestMessage::Pong(Pong(5))));
+    assert!(!decoder.has_partial_frame());
+}
+
+#[test]
+fn rejects_payload_size_mismatch() {
+    let frame = Frame(TestMessage::from(Text("lie".to_owned())));
+
+    assert!(encode_vec(&frame).is_err());
+}
+
+#[test]
+fn close_notifies_handler() {
+    let mut channel = started_channel();
+
+    channel.close(7);
+
+    assert!(channel.handler().closed);
+    assert_eq!(channel.channel_id(), None);
+}
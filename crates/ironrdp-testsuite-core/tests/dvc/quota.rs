@@ -0,0 +1,206 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::{encode_vec, impl_as_any, EncodeResult};
+use ironrdp_dvc::{
+    DrdynvcClient, DrdynvcServer, DvcClientProcessor, DvcEncode, DvcMessage, DvcProcessor, DvcServerProcessor,
+    QuotaAction, QuotaLimit, QuotaViolation, ReassemblyLimits,
+};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::SvcProcessor as _;
+
+use super::*;
+
+const LIMITS: ReassemblyLimits = ReassemblyLimits {
+    max_message_size: 16,
+    max_total_size: 24,
+};
+
+struct Hello;
+
+impl Encode for Hello {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        dst.write_slice(b"hello");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Hello"
+    }
+
+    fn size(&self) -> usize {
+        5
+    }
+}
+
+impl DvcEncode for Hello {}
+
+#[derive(Default)]
+struct Log {
+    starts: usize,
+    received: Vec<Vec<u8>>,
+    violations: Vec<QuotaViolation>,
+}
+
+/// Sends a greeting when started, and handles the messages exceeding the limits with `action`
+struct Guarded {
+    name: &'static str,
+    action: QuotaAction,
+    log: Arc<Mutex<Log>>,
+}
+
+impl_as_any!(Guarded);
+
+impl DvcProcessor for Guarded {
+    fn channel_name(&self) -> &str {
+        self.name
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.log.lock().unwrap().starts += 1;
+        Ok(vec![Box::new(Hello)])
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        self.log.lock().unwrap().received.push(payload.to_vec());
+        Ok(Vec::new())
+    }
+
+    fn on_quota_exceeded(&mut self, _channel_id: u32, violation: QuotaViolation) -> QuotaAction {
+        self.log.lock().unwrap().violations.push(violation);
+        self.action
+    }
+}
+
+impl DvcClientProcessor for Guarded {}
+impl DvcServerProcessor for Guarded {}
+
+fn data_first(channel_id: u32, length: u32, data: &[u8]) -> DrdynvcDataPdu {
+    DrdynvcDataPdu::DataFirst(DataFirstPdu::new(channel_id, length, data.to_vec()))
+}
+
+fn data(channel_id: u32, data: &[u8]) -> DrdynvcDataPdu {
+    DrdynvcDataPdu::Data(DataPdu::new(channel_id, data.to_vec()))
+}
+
+fn opened_client(action: QuotaAction, log: &Arc<Mutex<Log>>) -> DrdynvcClient {
+    let mut client = DrdynvcClient::new()
+        .with_reassembly_limits(LIMITS)
+        .with_dynamic_channel(Guarded {
+            name: "first",
+            action,
+            log: Arc::clone(log),
+        })
+        .with_dynamic_channel(Guarded {
+            name: "second",
+            action,
+            log: Arc::clone(log),
+        });
+
+    for (channel_id, name) in [(1, "first"), (2, "second")] {
+        let create = CreateRequestPdu::new(channel_id, name.to_owned());
+        client
+            .process(&encode_vec(&DrdynvcServerPdu::Create(create)).unwrap())
+            .unwrap();
+    }
+
+    client
+}
+
+fn client_process(client: &mut DrdynvcClient, pdu: DrdynvcDataPdu) -> usize {
+    client
+        .process(&encode_vec(&DrdynvcServerPdu::Data(pdu)).unwrap())
+        .unwrap()
+        .len()
+}
+
+#[test]
+fn default_limits() {
+    let limits = ReassemblyLimits::default();
+    assert!(limits.max_message_size <= limits.max_total_size);
+}
+
+#[test]
+fn client_drops_oversized_message() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut client = opened_client(QuotaAction::Drop, &log);
+
+    assert_eq!(client_process(&mut client, data_first(1, 20, &[1; 10])), 0);
+    // The remaining fragment of the dropped message is skipped.
+    assert_eq!(client_process(&mut client, data(1, &[2; 10])), 0);
+    client_process(&mut client, data(1, b"next"));
+
+    let log = log.lock().unwrap();
+    assert_eq!(log.starts, 2);
+    assert_eq!(
+        log.violations,
+        [QuotaViolation {
+            limit: QuotaLimit::Message,
+            size: 20
+        }]
+    );
+    assert_eq!(log.received, [b"next".to_vec()]);
+}
+
+#[test]
+fn client_resets_channel_over_total_quota() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut client = opened_client(QuotaAction::Reset, &log);
+
+    client_process(&mut client, data_first(1, 16, &[1; 4]));
+    // The greeting is sent again by the restarted channel.
+    assert_eq!(client_process(&mut client, data_first(2, 16, &[2; 4])), 1);
+    assert_eq!(log.lock().unwrap().starts, 3);
+
+    // The message of the first channel is still reassembled.
+    client_process(&mut client, data(1, &[1; 12]));
+    client_process(&mut client, data(2, &[2; 12]));
+
+    // A message sent in a single PDU is not buffered, and thus fits.
+    client_process(&mut client, data_first(2, 8, &[3; 8]));
+
+    let log = log.lock().unwrap();
+    assert_eq!(
+        log.violations,
+        [QuotaViolation {
+            limit: QuotaLimit::Total,
+            size: 32
+        }]
+    );
+    assert_eq!(log.received, [vec![1; 16], vec![3; 8]]);
+}
+
+#[test]
+fn server_enforces_limits() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut server = DrdynvcServer::new()
+        .with_reassembly_limits(LIMITS)
+        .with_dynamic_channel(Guarded {
+            name: "guarded",
+            action: QuotaAction::Reset,
+            log: Arc::clone(&log),
+        });
+
+    server.start().unwrap();
+    let caps = DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(CapsVersion::V2));
+    server.process(&encode_vec(&caps).unwrap()).unwrap();
+    let create = DrdynvcClientPdu::Create(CreateResponsePdu::new(0, CreationStatus::OK));
+    server.process(&encode_vec(&create).unwrap()).unwrap();
+
+    let mut process = |pdu| {
+        server
+            .process(&encode_vec(&DrdynvcClientPdu::Data(pdu)).unwrap())
+            .unwrap()
+            .len()
+    };
+
+    assert_eq!(process(data_first(0, u32::MAX, &[1; 10])), 1);
+    assert_eq!(process(data(0, &[1; 10])), 0);
+    process(data_first(0, 12, &[2; 6]));
+    process(data(0, &[2; 6]));
+
+    let log = log.lock().unwrap();
+    assert_eq!(log.starts, 2);
+    assert_eq!(log.violations.len(), 1);
+    assert_eq!(log.violations[0].limit, QuotaLimit::Message);
+    assert_eq!(log.received, [vec![2; 12]]);
+}
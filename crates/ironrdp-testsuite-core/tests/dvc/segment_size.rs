@@ -0,0 +1,121 @@
+use ironrdp_core::{encode_vec, impl_as_any, EncodeResult};
+use ironrdp_dvc::{
+    encode_dvc_messages_with_max_data_size, DrdynvcClient, DvcClientProcessor, DvcEncode, DvcMessage, DvcProcessor,
+};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::{server_encode_svc_messages, ChannelFlags, StaticVirtualChannel, SvcProcessor as _};
+
+use super::*;
+
+const CHANNEL_ID: u32 = 0x0012_3456;
+
+struct Payload(Vec<u8>);
+
+impl Encode for Payload {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        dst.write_slice(&self.0);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Payload"
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl DvcEncode for Payload {}
+
+/// Answers each message with a large payload
+struct Echo;
+
+impl_as_any!(Echo);
+
+impl DvcProcessor for Echo {
+    fn channel_name(&self) -> &str {
+        "echo"
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, _payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        Ok(vec![Box::new(Payload(vec![0xAB; 3000]))])
+    }
+}
+
+impl DvcClientProcessor for Echo {}
+
+#[test]
+fn max_data_size_for_segment() {
+    assert_eq!(DrdynvcDataPdu::max_data_size_for_segment(1400), 1368);
+    assert_eq!(
+        DrdynvcDataPdu::max_data_size_for_segment(usize::MAX),
+        DrdynvcDataPdu::MAX_DATA_SIZE
+    );
+    assert_eq!(DrdynvcDataPdu::max_data_size_for_segment(0), 1);
+}
+
+#[test]
+fn messages_split_to_fit_segment() {
+    let max_data_size = DrdynvcDataPdu::max_data_size_for_segment(1400);
+    let messages = encode_dvc_messages_with_max_data_size(
+        CHANNEL_ID,
+        vec![Box::new(Payload(vec![0xAB; 3000]))],
+        ChannelFlags::empty(),
+        max_data_size,
+    )
+    .unwrap();
+    assert_eq!(messages.len(), 3);
+
+    for message in messages {
+        let wire = server_encode_svc_messages(vec![message], 1004, 1007).unwrap();
+        assert!(wire.len() <= 1400, "{} bytes PDU", wire.len());
+    }
+}
+
+/// Sizes of the data PDUs answered by the echo channel
+fn echo_data_sizes(client: DrdynvcClient) -> Vec<usize> {
+    let mut client = client.with_dynamic_channel(Echo);
+
+    let create = CreateRequestPdu::new(CHANNEL_ID, "echo".to_owned());
+    client
+        .process(&encode_vec(&DrdynvcServerPdu::Create(create)).unwrap())
+        .unwrap();
+
+    let data = DataPdu::new(CHANNEL_ID, vec![0x01]);
+    let messages = client
+        .process(&encode_vec(&DrdynvcServerPdu::Data(DrdynvcDataPdu::Data(data))).unwrap())
+        .unwrap();
+
+    StaticVirtualChannel::chunkify(messages)
+        .unwrap()
+        .iter()
+        .map(|chunk| {
+            // Skip the channel PDU header
+            match DrdynvcClientPdu::decode(&mut ReadCursor::new(&chunk.filled()[8..])).unwrap() {
+                DrdynvcClientPdu::Data(DrdynvcDataPdu::DataFirst(pdu)) => pdu.data().len(),
+                DrdynvcClientPdu::Data(DrdynvcDataPdu::Data(pdu)) => pdu.data().len(),
+                pdu => panic!("unexpected PDU: {pdu:?}"),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn client_splits_messages() {
+    assert_eq!(
+        echo_data_sizes(DrdynvcClient::new().with_max_data_size(1000)),
+        [1000, 1000, 1000]
+    );
+
+    // The size is capped by the largest data PDU.
+    assert_eq!(
+        echo_data_sizes(DrdynvcClient::new().with_max_data_size(usize::MAX)),
+        [1590, 1410]
+    );
+    assert_eq!(echo_data_sizes(DrdynvcClient::new()), [1590, 1410]);
+}
@@ -0,0 +1,67 @@
+use ironrdp_dvc::pdu::{SoftSyncChannelList, SoftSyncFlags, SoftSyncRequestPdu, SoftSyncResponsePdu, TunnelType};
+use ironrdp_dvc::DrdynvcClient;
+use ironrdp_svc::{StaticVirtualChannel, SvcProcessor as _};
+
+use super::*;
+
+const REQ_ENCODED: [u8; 24] = [
+    0x80, 0x00, 0x18, 0x00, 0x00, 0x00, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00,
+    0x00, 0x04, 0x00, 0x00, 0x00,
+];
+const RESP_ENCODED: [u8; 10] = [0x90, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+
+fn req_decoded_server() -> DrdynvcServerPdu {
+    DrdynvcServerPdu::SoftSyncRequest(SoftSyncRequestPdu::new(
+        SoftSyncFlags::TCP_FLUSHED,
+        vec![SoftSyncChannelList {
+            tunnel: TunnelType::UDP_FECR,
+            channel_ids: vec![3, 4],
+        }],
+    ))
+}
+
+fn resp_decoded_client() -> DrdynvcClientPdu {
+    DrdynvcClientPdu::SoftSyncResponse(SoftSyncResponsePdu::new(vec![TunnelType::UDP_FECR]))
+}
+
+#[test]
+fn decodes_soft_sync_request() {
+    test_decodes(&REQ_ENCODED, &req_decoded_server());
+}
+
+#[test]
+fn encodes_soft_sync_request() {
+    test_encodes(&req_decoded_server(), &REQ_ENCODED);
+}
+
+#[test]
+fn decodes_soft_sync_response() {
+    test_decodes(&RESP_ENCODED, &resp_decoded_client());
+}
+
+#[test]
+fn encodes_soft_sync_response() {
+    test_encodes(&resp_decoded_client(), &RESP_ENCODED);
+}
+
+fn soft_sync_response(client: &mut DrdynvcClient) -> DrdynvcClientPdu {
+    let messages = client.process(&REQ_ENCODED).unwrap();
+    assert_eq!(messages.len(), 1);
+
+    let chunks = StaticVirtualChannel::chunkify(messages).unwrap();
+    // Skip the channel PDU header
+    DrdynvcClientPdu::decode(&mut ReadCursor::new(&chunks[0].filled()[8..])).unwrap()
+}
+
+#[test]
+fn client_answers_soft_sync() {
+    // The channels stay on TCP by default.
+    let mut client = DrdynvcClient::new();
+    assert_eq!(
+        soft_sync_response(&mut client),
+        DrdynvcClientPdu::SoftSyncResponse(SoftSyncResponsePdu::new(Vec::new()))
+    );
+
+    let mut client = DrdynvcClient::new().with_soft_sync_tunnels(vec![TunnelType::UDP_FECL, TunnelType::UDP_FECR]);
+    assert_eq!(soft_sync_response(&mut client), resp_decoded_client());
+}
@@ -0,0 +1,242 @@
+use core::time::Duration;
+
+use ironrdp_core::{encode_vec, ensure_size, DecodeOwned, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor};
+use ironrdp_dvc::pdu::{ClosePdu, CreateRequestPdu, DataPdu, DrdynvcDataPdu, DrdynvcServerPdu};
+use ironrdp_dvc::protocol::{ProtocolChannel, ProtocolHandler};
+use ironrdp_dvc::{dvc_protocol, DrdynvcClient, StallAction, StallPolicy};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::SvcProcessor as _;
+
+const CHANNEL_ID: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Request(u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Response(u32);
+
+macro_rules! impl_u32_message {
+    ($ty:ident) => {
+        impl Encode for $ty {
+            fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+                ensure_size!(in: dst, size: 4);
+                dst.write_u32(self.0);
+                Ok(())
+            }
+
+            fn name(&self) -> &'static str {
+                stringify!($ty)
+            }
+
+            fn size(&self) -> usize {
+                4
+            }
+        }
+
+        impl DecodeOwned for $ty {
+            fn decode_owned(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+                ensure_size!(in: src, size: 4);
+                Ok(Self(src.read_u32()))
+            }
+        }
+    };
+}
+
+impl_u32_message!(Request);
+impl_u32_message!(Response);
+
+dvc_protocol! {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum StallMessage: "IronRdp::Stall" (version 1) {
+        Request(Request) = 0x0001,
+        Response(Response) = 0x0002,
+    }
+}
+
+/// Sends a request when started, and waits for its response
+#[derive(Default)]
+struct Requester {
+    pending: Option<u32>,
+    starts: u32,
+    resend: bool,
+    keepalives: u32,
+}
+
+impl ProtocolHandler for Requester {
+    type Message = StallMessage;
+
+    fn start(&mut self) -> PduResult<Vec<StallMessage>> {
+        self.starts += 1;
+        self.pending = Some(self.starts);
+        Ok(vec![Request(self.starts).into()])
+    }
+
+    fn handle(&mut self, message: StallMessage) -> PduResult<Vec<StallMessage>> {
+        if let StallMessage::Response(Response(id)) = message {
+            if self.pending == Some(id) {
+                self.pending = None;
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    fn awaiting_response(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn on_stall(&mut self, _attempt: u32) -> PduResult<Option<Vec<StallMessage>>> {
+        Ok(self.resend.then(|| vec![Request(self.pending.unwrap()).into()]))
+    }
+
+    fn keepalive(&mut self) -> PduResult<Vec<StallMessage>> {
+        self.keepalives += 1;
+        Ok(vec![Request(0).into()])
+    }
+}
+
+fn server_pdu(pdu: DrdynvcServerPdu) -> Vec<u8> {
+    encode_vec(&pdu).unwrap()
+}
+
+fn response(id: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&0x0002u16.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&4u32.to_le_bytes());
+    data.extend_from_slice(&id.to_le_bytes());
+
+    server_pdu(DrdynvcServerPdu::Data(DrdynvcDataPdu::Data(DataPdu::new(
+        CHANNEL_ID, data,
+    ))))
+}
+
+fn opened_client(requester: Requester, policy: StallPolicy) -> DrdynvcClient {
+    let mut client = DrdynvcClient::new()
+        .with_dynamic_channel(ProtocolChannel::new(requester))
+        .with_stall_policy(policy);
+
+    let create = CreateRequestPdu::new(CHANNEL_ID, "IronRdp::Stall".to_owned());
+    client.process(&server_pdu(DrdynvcServerPdu::Create(create))).unwrap();
+
+    client
+}
+
+fn handler(client: &DrdynvcClient) -> &Requester {
+    client
+        .get_dvc_by_channel_id(CHANNEL_ID)
+        .unwrap()
+        .channel_processor_downcast_ref::<ProtocolChannel<Requester>>()
+        .unwrap()
+        .handler()
+}
+
+#[test]
+fn answered_requests_do_not_stall() {
+    let mut client = opened_client(Requester::default(), StallPolicy::default());
+
+    let (messages, stalls) = client.poll_timeouts(Duration::from_secs(6)).unwrap();
+    assert!(messages.is_empty());
+    assert!(stalls.is_empty());
+
+    // Any message from the peer restarts the timer.
+    client.process(&response(42)).unwrap();
+    let (_, stalls) = client.poll_timeouts(Duration::from_secs(6)).unwrap();
+    assert!(stalls.is_empty());
+
+    client.process(&response(1)).unwrap();
+    let (messages, stalls) = client.poll_timeouts(Duration::from_secs(60)).unwrap();
+    assert!(messages.is_empty());
+    assert!(stalls.is_empty());
+    assert_eq!(client.get_dvc_by_channel_id(CHANNEL_ID).unwrap().stall_count(), 0);
+}
+
+#[test]
+fn stalled_requests_are_resent_then_channel_reset() {
+    let requester = Requester {
+        resend: true,
+        ..Requester::default()
+    };
+    let policy = StallPolicy {
+        timeout: Duration::from_secs(5),
+        max_attempts: 1,
+        keepalive_interval: None,
+    };
+    let mut client = opened_client(requester, policy);
+
+    let (messages, stalls) = client.poll_timeouts(Duration::from_secs(5)).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert!(matches!(
+        stalls.as_slice(),
+        [stall] if stall.channel_name == "IronRdp::Stall"
+            && stall.channel_id == CHANNEL_ID
+            && stall.waited == Duration::from_secs(5)
+            && stall.attempt == 1
+            && stall.action == StallAction::Resent
+    ));
+    assert_eq!(handler(&client).starts, 1);
+
+    // The resent request is not answered either.
+    let (_, stalls) = client.poll_timeouts(Duration::from_secs(4)).unwrap();
+    assert!(stalls.is_empty());
+
+    let (messages, stalls) = client.poll_timeouts(Duration::from_secs(1)).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert!(matches!(
+        stalls.as_slice(),
+        [stall] if stall.attempt == 2 && stall.action == StallAction::Reset
+    ));
+    assert_eq!(handler(&client).starts, 2);
+    assert_eq!(client.get_dvc_by_channel_id(CHANNEL_ID).unwrap().stall_count(), 2);
+
+    // The restarted channel works again.
+    client.process(&response(2)).unwrap();
+    let (_, stalls) = client.poll_timeouts(Duration::from_secs(60)).unwrap();
+    assert!(stalls.is_empty());
+}
+
+#[test]
+fn stalled_channel_reset_by_default() {
+    let mut client = opened_client(Requester::default(), StallPolicy::default());
+
+    let (messages, stalls) = client.poll_timeouts(StallPolicy::default().timeout).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert!(matches!(
+        stalls.as_slice(),
+        [stall] if stall.attempt == 1 && stall.action == StallAction::Reset
+    ));
+    assert_eq!(handler(&client).starts, 2);
+}
+
+#[test]
+fn idle_channel_keepalive() {
+    let policy = StallPolicy {
+        keepalive_interval: Some(Duration::from_secs(30)),
+        ..StallPolicy::default()
+    };
+    let mut client = opened_client(Requester::default(), policy);
+    client.process(&response(1)).unwrap();
+
+    let (messages, _) = client.poll_timeouts(Duration::from_secs(20)).unwrap();
+    assert!(messages.is_empty());
+
+    let (messages, _) = client.poll_timeouts(Duration::from_secs(10)).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(handler(&client).keepalives, 1);
+
+    // The keepalive restarts the idle timer.
+    let (messages, _) = client.poll_timeouts(Duration::from_secs(10)).unwrap();
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn closed_channel_not_polled() {
+    let mut client = opened_client(Requester::default(), StallPolicy::default());
+
+    client
+        .process(&server_pdu(DrdynvcServerPdu::Close(ClosePdu::new(CHANNEL_ID))))
+        .unwrap();
+
+    let (messages, stalls) = client.poll_timeouts(Duration::from_secs(60)).unwrap();
+    assert!(messages.is_empty());
+    assert!(stalls.is_empty());
+}
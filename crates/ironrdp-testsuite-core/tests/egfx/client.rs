@@ -0,0 +1,357 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_dvc::DvcProcessor as _;
+use ironrdp_egfx::client::{
+    adjust_capabilities, DecoderCapabilities, GfxQuirks, GraphicsPipelineClient, GraphicsPipelineHandler,
+    GraphicsStatistics, QuirksMode,
+};
+use ironrdp_egfx::pdu::{
+    CacheToSurfacePdu, CapabilitiesConfirmPdu, CapabilitiesV104Flags, CapabilitiesV107Flags, CapabilitiesV10Flags,
+    CapabilitiesV81Flags, CapabilitiesV8Flags, CapabilitySet, EndFramePdu, EvictCacheEntryPdu, FrameAcknowledgePdu,
+    GfxPdu, Point, QoeFrameAcknowledgePdu, QueueDepth, StartFramePdu, SurfaceToCachePdu, Timestamp,
+};
+use ironrdp_graphics::zgfx;
+use ironrdp_pdu::geometry::InclusiveRectangle;
+
+fn decoder(avc420: bool, avc444: bool) -> DecoderCapabilities {
+    DecoderCapabilities {
+        avc420,
+        avc444,
+        max_level: Some(51),
+        max_width: 4096,
+        max_height: 2304,
+    }
+}
+
+fn advertised() -> Vec<CapabilitySet> {
+    vec![
+        CapabilitySet::V10_7 {
+            flags: CapabilitiesV107Flags::SMALL_CACHE,
+        },
+        CapabilitySet::V10_1,
+        CapabilitySet::V8_1 {
+            flags: CapabilitiesV81Flags::AVC420_ENABLED,
+        },
+        CapabilitySet::V8 {
+            flags: CapabilitiesV8Flags::empty(),
+        },
+    ]
+}
+
+#[test]
+fn capable_decoder_keeps_capabilities() {
+    assert_eq!(
+        adjust_capabilities(advertised(), &decoder(true, true), Some((1920, 1080))),
+        advertised()
+    );
+}
+
+#[test]
+fn avc420_only_decoder_drops_version_10() {
+    assert_eq!(
+        adjust_capabilities(advertised(), &decoder(true, false), None),
+        [
+            CapabilitySet::V8_1 {
+                flags: CapabilitiesV81Flags::AVC420_ENABLED,
+            },
+            CapabilitySet::V8 {
+                flags: CapabilitiesV8Flags::empty(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn avc420_only_decoder_without_version_8_1() {
+    let capabilities = vec![
+        CapabilitySet::V10_4 {
+            flags: CapabilitiesV104Flags::SMALL_CACHE,
+        },
+        CapabilitySet::V10 {
+            flags: CapabilitiesV10Flags::empty(),
+        },
+    ];
+
+    assert_eq!(
+        adjust_capabilities(capabilities, &decoder(true, false), None),
+        [
+            CapabilitySet::V10_4 {
+                flags: CapabilitiesV104Flags::SMALL_CACHE | CapabilitiesV104Flags::AVC_DISABLED,
+            },
+            CapabilitySet::V10 {
+                flags: CapabilitiesV10Flags::AVC_DISABLED,
+            },
+        ]
+    );
+}
+
+#[test]
+fn unsupported_decoder_disables_avc() {
+    assert_eq!(
+        adjust_capabilities(advertised(), &DecoderCapabilities::unsupported(), None),
+        [
+            CapabilitySet::V10_7 {
+                flags: CapabilitiesV107Flags::SMALL_CACHE | CapabilitiesV107Flags::AVC_DISABLED,
+            },
+            CapabilitySet::V8_1 {
+                flags: CapabilitiesV81Flags::empty(),
+            },
+            CapabilitySet::V8 {
+                flags: CapabilitiesV8Flags::empty(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn desktop_too_large_disables_avc() {
+    let capabilities = vec![CapabilitySet::V8_1 {
+        flags: CapabilitiesV81Flags::AVC420_ENABLED | CapabilitiesV81Flags::SMALL_CACHE,
+    }];
+
+    assert_eq!(
+        adjust_capabilities(capabilities, &decoder(true, true), Some((7680, 4320))),
+        [CapabilitySet::V8_1 {
+            flags: CapabilitiesV81Flags::SMALL_CACHE,
+        }]
+    );
+}
+
+#[test]
+fn never_empty() {
+    assert_eq!(
+        adjust_capabilities(vec![CapabilitySet::V10_1], &DecoderCapabilities::unsupported(), None),
+        [CapabilitySet::V8 {
+            flags: CapabilitiesV8Flags::empty(),
+        }]
+    );
+}
+
+#[test]
+fn resolution_limited_by_level() {
+    let mut decoder = decoder(true, true);
+
+    assert!(decoder.supports_resolution(3840, 2160));
+    assert!(!decoder.supports_resolution(4097, 1080));
+
+    // Level 4.1 is limited to 8192 macroblocks, e.g. 2048x1024.
+    decoder.max_level = Some(41);
+    assert!(decoder.supports_resolution(1920, 1080));
+    assert!(!decoder.supports_resolution(2560, 1440));
+
+    // Each dimension is bounded too, even for a small frame.
+    decoder.max_width = 8192;
+    assert!(decoder.supports_resolution(4096, 64));
+    assert!(!decoder.supports_resolution(4112, 64));
+
+    decoder.max_level = None;
+    assert!(decoder.supports_resolution(4096, 2304));
+}
+
+#[derive(Default)]
+struct Recorder {
+    pdus: Arc<Mutex<Vec<GfxPdu>>>,
+}
+
+impl GraphicsPipelineHandler for Recorder {
+    fn handle_pdu(&mut self, pdu: GfxPdu) {
+        self.pdus.lock().unwrap().push(pdu);
+    }
+}
+
+fn client(mode: QuirksMode) -> (GraphicsPipelineClient, Arc<Mutex<Vec<GfxPdu>>>) {
+    let recorder = Recorder::default();
+    let pdus = Arc::clone(&recorder.pdus);
+
+    (
+        GraphicsPipelineClient::new(Box::new(recorder)).with_quirks_mode(mode),
+        pdus,
+    )
+}
+
+/// Sends the PDUs to the client, returns its replies
+fn send(client: &mut GraphicsPipelineClient, pdus: &[GfxPdu]) -> ironrdp_pdu::PduResult<Vec<GfxPdu>> {
+    let mut payload = Vec::new();
+    for pdu in pdus {
+        payload.extend(encode_vec(pdu).unwrap());
+    }
+
+    let replies = client.process(0, &zgfx::wrap_uncompressed(&payload))?;
+
+    Ok(replies
+        .iter()
+        .map(|reply| decode(&encode_vec(reply.as_ref()).unwrap()).unwrap())
+        .collect())
+}
+
+fn start_frame(frame_id: u32) -> GfxPdu {
+    GfxPdu::StartFrame(StartFramePdu {
+        timestamp: Timestamp {
+            milliseconds: 0,
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+        },
+        frame_id,
+    })
+}
+
+fn end_frame(frame_id: u32) -> GfxPdu {
+    GfxPdu::EndFrame(EndFramePdu { frame_id })
+}
+
+fn ack(frame_id: u32, total_frames_decoded: u32) -> GfxPdu {
+    GfxPdu::FrameAcknowledge(FrameAcknowledgePdu {
+        queue_depth: QueueDepth::Unavailable,
+        frame_id,
+        total_frames_decoded,
+    })
+}
+
+fn surface_to_cache(cache_slot: u16) -> GfxPdu {
+    GfxPdu::SurfaceToCache(SurfaceToCachePdu {
+        surface_id: 0,
+        cache_key: 0,
+        cache_slot,
+        source_rectangle: InclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: 63,
+            bottom: 63,
+        },
+    })
+}
+
+fn cache_to_surface(cache_slot: u16) -> GfxPdu {
+    GfxPdu::CacheToSurface(CacheToSurfacePdu {
+        cache_slot,
+        surface_id: 0,
+        destination_points: vec![Point { x: 0, y: 0 }],
+    })
+}
+
+#[test]
+fn acknowledges_frames() {
+    let (mut client, pdus) = client(QuirksMode::Strict);
+
+    let replies = send(
+        &mut client,
+        &[start_frame(1), end_frame(1), start_frame(2), end_frame(2)],
+    )
+    .unwrap();
+
+    assert_eq!(replies, [ack(1, 1), ack(2, 2)]);
+    assert_eq!(pdus.lock().unwrap().len(), 4);
+    assert_eq!(client.quirks(), GfxQuirks::empty());
+}
+
+#[test]
+fn strict_mode_rejects_unmatched_frames() {
+    let (mut client, _) = client(QuirksMode::Strict);
+
+    assert!(send(&mut client, &[end_frame(3)]).is_err());
+}
+
+#[test]
+fn auto_mode_enables_quirks() {
+    let (mut client, pdus) = client(QuirksMode::Auto);
+
+    let replies = send(&mut client, &[start_frame(1), start_frame(2), end_frame(5)]).unwrap();
+    assert_eq!(replies, [ack(5, 1)]);
+    assert_eq!(client.quirks(), GfxQuirks::LENIENT_FRAME_IDS);
+
+    let replies = send(
+        &mut client,
+        &[
+            surface_to_cache(1),
+            cache_to_surface(2),
+            cache_to_surface(1),
+            surface_to_cache(0),
+        ],
+    )
+    .unwrap();
+    assert!(replies.is_empty());
+    assert_eq!(client.quirks(), GfxQuirks::LENIENT_FRAME_IDS | GfxQuirks::LENIENT_CACHE);
+
+    // PDUs using invalid cache slots are skipped.
+    assert_eq!(pdus.lock().unwrap()[3..], [surface_to_cache(1), cache_to_surface(1)]);
+}
+
+#[test]
+fn fixed_quirks() {
+    let (mut client, pdus) = client(QuirksMode::Fixed(GfxQuirks::LENIENT_CACHE));
+
+    send(
+        &mut client,
+        &[GfxPdu::EvictCacheEntry(EvictCacheEntryPdu { cache_slot: 7 })],
+    )
+    .unwrap();
+    assert!(pdus.lock().unwrap().is_empty());
+
+    assert!(send(&mut client, &[end_frame(1)]).is_err());
+    assert_eq!(client.quirks(), GfxQuirks::LENIENT_CACHE);
+}
+
+#[test]
+fn small_cache_range() {
+    let (mut client, _) = client(QuirksMode::Strict);
+
+    let confirm = GfxPdu::CapabilitiesConfirm(CapabilitiesConfirmPdu(CapabilitySet::V8 {
+        flags: CapabilitiesV8Flags::SMALL_CACHE,
+    }));
+    send(&mut client, &[confirm, surface_to_cache(4096)]).unwrap();
+
+    assert!(send(&mut client, &[surface_to_cache(4097)]).is_err());
+}
+
+#[test]
+fn reports_qoe_when_negotiated() {
+    let (client, _) = client(QuirksMode::Strict);
+    let mut client = client.with_qoe_reporting(true);
+
+    // Version 8 servers don't handle the QoE frame acknowledgements.
+    let confirm = GfxPdu::CapabilitiesConfirm(CapabilitiesConfirmPdu(CapabilitySet::V8 {
+        flags: CapabilitiesV8Flags::empty(),
+    }));
+    let replies = send(&mut client, &[confirm, start_frame(1), end_frame(1)]).unwrap();
+    assert_eq!(replies, [ack(1, 1)]);
+
+    let confirm = GfxPdu::CapabilitiesConfirm(CapabilitiesConfirmPdu(CapabilitySet::V10_7 {
+        flags: CapabilitiesV107Flags::empty(),
+    }));
+    let replies = send(&mut client, &[confirm, start_frame(2), end_frame(2)]).unwrap();
+    assert!(matches!(
+        replies.as_slice(),
+        [reply, GfxPdu::QoeFrameAcknowledge(QoeFrameAcknowledgePdu { frame_id: 2, .. })] if *reply == ack(2, 2)
+    ));
+}
+
+#[test]
+fn gathers_statistics() {
+    struct FailingDecoder;
+
+    impl GraphicsPipelineHandler for FailingDecoder {
+        fn decode_pdu(&mut self, pdu: GfxPdu) -> bool {
+            !matches!(pdu, GfxPdu::CacheToSurface(_))
+        }
+    }
+
+    let mut client = GraphicsPipelineClient::new(Box::new(FailingDecoder)).with_quirks_mode(QuirksMode::Strict);
+    assert_eq!(*client.statistics(), GraphicsStatistics::default());
+
+    // No QoE frame acknowledgement unless enabled.
+    let replies = send(
+        &mut client,
+        &[surface_to_cache(1), start_frame(1), cache_to_surface(1), end_frame(1)],
+    )
+    .unwrap();
+    assert_eq!(replies, [ack(1, 1)]);
+
+    let statistics = client.statistics();
+    assert_eq!(statistics.frames_decoded, 1);
+    assert_eq!(statistics.decode_errors, 1);
+    assert!(statistics.bytes_received > 0);
+    assert!(statistics.last_decode_time.is_some());
+    assert!(statistics.last_render_time.is_some());
+}
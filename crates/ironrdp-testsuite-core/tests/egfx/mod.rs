@@ -1 +1,3 @@
+mod client;
 mod server;
+mod simulated_client;
@@ -1,10 +1,20 @@
+use core::future::Future as _;
+use core::pin::pin;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::task::{Context, Waker};
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+
 use ironrdp_core::{Encode, WriteCursor};
 use ironrdp_dvc::DvcProcessor as _;
 use ironrdp_egfx::pdu::{
-    Avc420Region, CapabilitiesAdvertisePdu, CapabilitiesV10Flags, CapabilitiesV81Flags, CapabilitiesV8Flags,
-    CapabilitySet, GfxPdu,
+    encode_alpha_codec_bitmap, Avc420Region, CapabilitiesAdvertisePdu, CapabilitiesV10Flags, CapabilitiesV81Flags,
+    CapabilitiesV8Flags, CapabilitySet, FrameAcknowledgePdu, GfxPdu, PixelFormat, QueueDepth,
+};
+use ironrdp_egfx::server::{
+    GfxContext, GraphicsPipelineHandler, GraphicsPipelineServer, MonitorLayout, OutputMonitor, QoeMetrics, Surface,
 };
-use ironrdp_egfx::server::{GraphicsPipelineHandler, GraphicsPipelineServer, QoeMetrics, Surface};
+use ironrdp_pdu::geometry::InclusiveRectangle;
 
 // ============================================================================
 // Test Handler
@@ -31,28 +41,54 @@ impl TestHandler {
 }
 
 impl GraphicsPipelineHandler for TestHandler {
-    fn capabilities_advertise(&mut self, _pdu: &CapabilitiesAdvertisePdu) {}
+    fn capabilities_advertise(&mut self, _pdu: &CapabilitiesAdvertisePdu, _ctx: &mut GfxContext) {}
 
-    fn on_ready(&mut self, negotiated: &CapabilitySet) {
+    fn on_ready(&mut self, negotiated: &CapabilitySet, _ctx: &mut GfxContext) {
         self.ready_called = true;
         self.negotiated = Some(negotiated.clone());
     }
 
-    fn on_frame_ack(&mut self, frame_id: u32, queue_depth: u32) {
+    fn on_frame_ack(&mut self, frame_id: u32, queue_depth: u32, _ctx: &mut GfxContext) {
         self.frame_acks.push((frame_id, queue_depth));
     }
 
-    fn on_qoe_metrics(&mut self, _metrics: QoeMetrics) {}
+    fn on_qoe_metrics(&mut self, _metrics: QoeMetrics, _ctx: &mut GfxContext) {}
 
-    fn on_surface_created(&mut self, surface: &Surface) {
+    fn on_surface_created(&mut self, surface: &Surface, _ctx: &mut GfxContext) {
         self.surfaces_created.push(surface.id);
     }
 
-    fn on_surface_deleted(&mut self, surface_id: u16) {
+    fn on_surface_deleted(&mut self, surface_id: u16, _ctx: &mut GfxContext) {
         self.surfaces_deleted.push(surface_id);
     }
 }
 
+/// Handler driving the pipeline from its callbacks
+struct StreamingHandler {
+    surface_id: Option<u16>,
+}
+
+impl GraphicsPipelineHandler for StreamingHandler {
+    fn capabilities_advertise(&mut self, _pdu: &CapabilitiesAdvertisePdu, _ctx: &mut GfxContext) {}
+
+    fn on_ready(&mut self, _negotiated: &CapabilitySet, ctx: &mut GfxContext) {
+        self.surface_id = ctx.create_surface(1920, 1080);
+    }
+
+    fn on_surface_created(&mut self, surface: &Surface, ctx: &mut GfxContext) {
+        assert!(ctx.map_surface_to_output(surface.id, 0, 0));
+    }
+
+    fn on_frame_ack(&mut self, _frame_id: u32, _queue_depth: u32, ctx: &mut GfxContext) {
+        let surface_id = self.surface_id.expect("surface created when ready");
+        let regions = [Avc420Region::full_frame(1920, 1080, 22)];
+
+        assert!(ctx
+            .send_avc420_frame(surface_id, &[0x00, 0x00, 0x00, 0x01, 0x67], &regions, 0)
+            .is_some());
+    }
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
@@ -134,6 +170,88 @@ fn test_capability_negotiation_v10_avc444() {
     assert!(server.supports_avc444());
 }
 
+#[test]
+fn test_preferred_capabilities_override() {
+    let handler = Box::new(TestHandler::new());
+    let mut server = GraphicsPipelineServer::new(handler);
+    server.set_preferred_capabilities(vec![CapabilitySet::V8 {
+        flags: CapabilitiesV8Flags::SMALL_CACHE,
+    }]);
+
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![
+        CapabilitySet::V8 {
+            flags: CapabilitiesV8Flags::SMALL_CACHE,
+        },
+        CapabilitySet::V10 {
+            flags: CapabilitiesV10Flags::SMALL_CACHE,
+        },
+    ]));
+
+    let payload = encode_pdu(&client_caps_pdu);
+    let _output = server.process(0, &payload).expect("process failed");
+
+    assert!(server.is_ready());
+    assert!(matches!(
+        server.negotiated_capabilities(),
+        Some(CapabilitySet::V8 { .. })
+    ));
+    assert!(!server.supports_avc420());
+}
+
+/// Selected capability set and the ones advertised by the client
+type Negotiation = Option<(CapabilitySet, Vec<CapabilitySet>)>;
+
+/// Handler recording the result of the capability negotiation
+struct NegotiationHandler {
+    negotiated: Arc<Mutex<Negotiation>>,
+}
+
+impl GraphicsPipelineHandler for NegotiationHandler {
+    fn capabilities_advertise(&mut self, _pdu: &CapabilitiesAdvertisePdu, _ctx: &mut GfxContext) {}
+
+    fn on_capabilities_negotiated(
+        &mut self,
+        caps: &CapabilitySet,
+        client_caps: &[CapabilitySet],
+        ctx: &mut GfxContext,
+    ) {
+        assert!(ctx.is_ready());
+        *self.negotiated.lock().unwrap() = Some((caps.clone(), client_caps.to_vec()));
+    }
+
+    fn on_ready(&mut self, _negotiated: &CapabilitySet, _ctx: &mut GfxContext) {}
+}
+
+#[test]
+fn test_capabilities_negotiated_callback() {
+    let negotiated = Arc::new(Mutex::new(None));
+    let handler = Box::new(NegotiationHandler {
+        negotiated: Arc::clone(&negotiated),
+    });
+    let mut server = GraphicsPipelineServer::new(handler);
+    server.set_preferred_capabilities(vec![CapabilitySet::V8_1 {
+        flags: CapabilitiesV81Flags::AVC420_ENABLED,
+    }]);
+
+    let client_caps = vec![
+        CapabilitySet::V8_1 {
+            flags: CapabilitiesV81Flags::AVC420_ENABLED,
+        },
+        CapabilitySet::V10 {
+            flags: CapabilitiesV10Flags::SMALL_CACHE,
+        },
+    ];
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(client_caps.clone()));
+    server
+        .process(0, &encode_pdu(&client_caps_pdu))
+        .expect("process failed");
+
+    let (caps, advertised) = negotiated.lock().unwrap().take().expect("callback called");
+    assert!(matches!(caps, CapabilitySet::V8_1 { .. }));
+    assert_eq!(advertised, client_caps);
+    assert_eq!(server.client_capabilities(), client_caps.as_slice());
+}
+
 #[test]
 fn test_server_not_ready_before_capabilities() {
     let handler = Box::new(TestHandler::new());
@@ -212,6 +330,222 @@ fn test_resize() {
     assert!(server.has_pending_output());
 }
 
+fn dual_monitor_layout() -> MonitorLayout {
+    MonitorLayout::new(vec![
+        OutputMonitor {
+            left: 0,
+            top: 0,
+            width: 1920,
+            height: 1080,
+            is_primary: true,
+        },
+        OutputMonitor {
+            left: -1280,
+            top: 56,
+            width: 1280,
+            height: 1024,
+            is_primary: false,
+        },
+    ])
+    .expect("valid layout")
+}
+
+#[test]
+fn test_monitor_layout() {
+    let layout = dual_monitor_layout();
+
+    assert_eq!(layout.output_size(), (3200, 1080));
+    assert_eq!(layout.output_origin(0), Some((1280, 0)));
+    assert_eq!(layout.output_origin(1), Some((0, 56)));
+    assert_eq!(layout.output_origin(2), None);
+
+    assert!(MonitorLayout::new(Vec::new()).is_none());
+    assert!(MonitorLayout::single(0, 1080).is_none());
+
+    // No primary monitor
+    let mut monitors = layout.monitors().to_vec();
+    monitors[0].is_primary = false;
+    assert!(MonitorLayout::new(monitors).is_none());
+
+    // Bounding rectangle too large
+    let mut monitors = layout.monitors().to_vec();
+    monitors[1].left = -32_000;
+    assert!(MonitorLayout::new(monitors).is_none());
+}
+
+#[test]
+fn test_map_surface_to_monitor() {
+    let handler = Box::new(TestHandler::new());
+    let mut server = GraphicsPipelineServer::new(handler);
+
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![CapabilitySet::V8 {
+        flags: CapabilitiesV8Flags::SMALL_CACHE,
+    }]));
+    let payload = encode_pdu(&client_caps_pdu);
+    let _output = server.process(0, &payload).expect("process failed");
+
+    server.set_monitor_layout(dual_monitor_layout());
+    assert_eq!(server.output_dimensions(), (3200, 1080));
+
+    let primary = server.create_surface(1920, 1080).unwrap();
+    let secondary = server.create_surface(1280, 1024).unwrap();
+
+    assert!(server.map_surface_to_monitor(primary, 0));
+    assert!(server.map_surface_to_monitor(secondary, 1));
+    assert!(!server.map_surface_to_monitor(secondary, 2));
+
+    let surface = server.get_surface(primary).unwrap();
+    assert_eq!((surface.output_origin_x, surface.output_origin_y), (1280, 0));
+    let surface = server.get_surface(secondary).unwrap();
+    assert_eq!((surface.output_origin_x, surface.output_origin_y), (0, 56));
+
+    // ResetGraphics, 2 CreateSurface and 2 MapSurfaceToOutput
+    assert_eq!(server.drain_output().len(), 5);
+
+    // Back to a single monitor
+    server.resize_with_layout(MonitorLayout::single(1920, 1080).unwrap());
+    assert!(server.get_surface(primary).is_none());
+    assert_eq!(server.output_dimensions(), (1920, 1080));
+    assert_eq!(server.monitor_layout().unwrap().monitors().len(), 1);
+
+    // Explicit monitor definitions replace the layout
+    server.resize(1024, 768);
+    assert!(server.monitor_layout().is_none());
+}
+
+#[test]
+fn test_map_surface_to_scaled_monitor() {
+    let handler = Box::new(TestHandler::new());
+    let mut server = GraphicsPipelineServer::new(handler);
+
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![CapabilitySet::V8 {
+        flags: CapabilitiesV8Flags::SMALL_CACHE,
+    }]));
+    let payload = encode_pdu(&client_caps_pdu);
+    let _output = server.process(0, &payload).expect("process failed");
+
+    server.set_monitor_layout(dual_monitor_layout());
+
+    // Surfaces rendered at half the resolution of the monitors
+    let primary = server.create_surface(960, 540).unwrap();
+    let secondary = server.create_surface(640, 512).unwrap();
+
+    assert!(server.map_surface_to_scaled_monitor(primary, 0));
+    assert!(server.map_surface_to_scaled_monitor(secondary, 1));
+    assert!(!server.map_surface_to_scaled_monitor(secondary, 2));
+    assert!(!server.map_surface_to_scaled_output(42, 0, 0, 1920, 1080));
+
+    let surface = server.get_surface(secondary).unwrap();
+    assert!(surface.is_mapped);
+    assert_eq!((surface.output_origin_x, surface.output_origin_y), (0, 56));
+
+    // ResetGraphics, 2 CreateSurface and 2 MapSurfaceToScaledOutput
+    assert_eq!(server.drain_output().len(), 5);
+}
+
+#[test]
+fn test_map_surface_to_window() {
+    let handler = Box::new(TestHandler::new());
+    let mut server = GraphicsPipelineServer::new(handler);
+
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![CapabilitySet::V8 {
+        flags: CapabilitiesV8Flags::SMALL_CACHE,
+    }]));
+    let payload = encode_pdu(&client_caps_pdu);
+    let _output = server.process(0, &payload).expect("process failed");
+
+    let surface_id = server.create_surface(640, 480).unwrap();
+    assert!(server.map_surface_to_window(surface_id, 0x0001_0002, 640, 480));
+    assert!(!server.map_surface_to_window(42, 0x0001_0002, 640, 480));
+    assert!(server.get_surface(surface_id).unwrap().is_mapped);
+
+    // ResetGraphics, CreateSurface and MapSurfaceToWindow
+    assert_eq!(server.drain_output().len(), 3);
+}
+
+#[test]
+fn test_window_surface_alpha() {
+    let handler = Box::new(TestHandler::new());
+    let mut server = GraphicsPipelineServer::new(handler);
+
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![CapabilitySet::V8 {
+        flags: CapabilitiesV8Flags::SMALL_CACHE,
+    }]));
+    let payload = encode_pdu(&client_caps_pdu);
+    let _output = server.process(0, &payload).expect("process failed");
+
+    let window_surface = server.create_window_surface(0x0001_0002, 4, 2).unwrap();
+    let surface = server.get_surface(window_surface).unwrap();
+    assert_eq!(surface.pixel_format, PixelFormat::ARgb);
+    assert!(surface.is_mapped);
+
+    let rect = InclusiveRectangle {
+        left: 0,
+        top: 0,
+        right: 3,
+        bottom: 1,
+    };
+    assert!(server.send_alpha(window_surface, rect.clone(), &[0xFF; 8], 0).is_some());
+    assert!(server.send_alpha(window_surface, rect.clone(), &[0xFF; 7], 0).is_none());
+
+    let opaque_surface = server.create_surface(4, 2).unwrap();
+    assert!(server.send_alpha(opaque_surface, rect, &[0xFF; 8], 0).is_none());
+
+    server.set_default_pixel_format(PixelFormat::ARgb);
+    let surface_id = server.create_surface(4, 2).unwrap();
+    assert_eq!(server.get_surface(surface_id).unwrap().pixel_format, PixelFormat::ARgb);
+}
+
+#[test]
+fn test_alpha_codec_bitmap() {
+    // A single run of 300 opaque pixels
+    assert_eq!(
+        encode_alpha_codec_bitmap(&[0xFF; 300]),
+        [0x4C, 0x41, 0x01, 0x00, 0xFF, 0xFF, 0x2C, 0x01]
+    );
+
+    // Sent raw when the runs are longer than the values
+    assert_eq!(
+        encode_alpha_codec_bitmap(&[0x00, 0x80, 0xFF]),
+        [0x4C, 0x41, 0x00, 0x00, 0x00, 0x80, 0xFF]
+    );
+}
+
+#[test]
+fn test_surfaces_restored_on_reopen() {
+    let handler = Box::new(TestHandler::new());
+    let mut server = GraphicsPipelineServer::new(handler);
+    server.set_output_dimensions(1920, 1080);
+
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![CapabilitySet::V8_1 {
+        flags: CapabilitiesV81Flags::AVC420_ENABLED,
+    }]));
+    let payload = encode_pdu(&client_caps_pdu);
+
+    server.start(1).unwrap();
+    server.process(1, &payload).expect("process failed");
+    let surface_id = server.create_surface(1920, 1080).unwrap();
+    assert!(server.map_surface_to_output(surface_id, 0, 0));
+    let regions = [Avc420Region::full_frame(1920, 1080, 22)];
+    assert!(server
+        .send_avc420_frame(surface_id, &[0x00, 0x00, 0x00, 0x01, 0x67], &regions, 0)
+        .is_some());
+
+    server.close(1);
+    assert!(!server.is_ready());
+    assert_eq!(server.frames_in_flight(), 0);
+    assert!(server.drain_output().is_empty());
+
+    // CapabilitiesConfirm, ResetGraphics, CreateSurface and MapSurfaceToOutput
+    server.start(2).unwrap();
+    let output = server.process(2, &payload).expect("process failed");
+    assert_eq!(output.len(), 4);
+    assert!(server.is_ready());
+    assert_eq!(server.channel_id(), Some(2));
+    assert!(server.get_surface(surface_id).unwrap().is_mapped);
+    assert_eq!(server.output_dimensions(), (1920, 1080));
+}
+
 #[test]
 fn test_frame_flow_control() {
     let handler = Box::new(TestHandler::new());
@@ -247,3 +581,91 @@ fn test_frame_flow_control() {
     let frame3 = server.send_avc420_frame(surface_id, &h264_data, &regions, 33);
     assert!(frame3.is_none());
 }
+
+/// Waker counting its wake-ups
+#[derive(Default)]
+struct CountingWaker(AtomicUsize);
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_ready_to_send_resolves_on_ack() {
+    let handler = Box::new(TestHandler::new());
+    let mut server = GraphicsPipelineServer::new(handler);
+    server.set_max_frames_in_flight(1);
+
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![CapabilitySet::V8_1 {
+        flags: CapabilitiesV81Flags::AVC420_ENABLED,
+    }]));
+    server
+        .process(0, &encode_pdu(&client_caps_pdu))
+        .expect("process failed");
+    let surface_id = server.create_surface(1920, 1080).unwrap();
+
+    let counter = Arc::new(CountingWaker::default());
+    let waker = Waker::from(Arc::clone(&counter));
+    let mut cx = Context::from_waker(&waker);
+
+    let mut ready = pin!(server.ready_to_send());
+    assert!(ready.as_mut().poll(&mut cx).is_ready());
+
+    let regions = [Avc420Region::full_frame(1920, 1080, 22)];
+    let frame_id = server
+        .send_avc420_frame(surface_id, &[0x00, 0x00, 0x00, 0x01, 0x67], &regions, 0)
+        .unwrap();
+
+    let mut ready = pin!(server.ready_to_send());
+    assert!(ready.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+    let ack = GfxPdu::FrameAcknowledge(FrameAcknowledgePdu {
+        queue_depth: QueueDepth::AvailableBytes(0),
+        frame_id,
+        total_frames_decoded: 1,
+    });
+    server.process(0, &encode_pdu(&ack)).expect("process failed");
+
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    assert!(ready.as_mut().poll(&mut cx).is_ready());
+}
+
+#[test]
+fn test_handler_drives_pipeline_from_callbacks() {
+    let handler = Box::new(StreamingHandler { surface_id: None });
+    let mut server = GraphicsPipelineServer::new(handler);
+
+    let client_caps_pdu = GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![CapabilitySet::V8_1 {
+        flags: CapabilitiesV81Flags::AVC420_ENABLED,
+    }]));
+    let output = server
+        .process(0, &encode_pdu(&client_caps_pdu))
+        .expect("process failed");
+
+    // CapabilitiesConfirm, then ResetGraphics, CreateSurface and MapSurfaceToOutput from the callbacks
+    assert_eq!(output.len(), 4);
+
+    let surface = server.context().get_surface(0).expect("surface created by the handler");
+    assert!(surface.is_mapped);
+
+    // Acknowledging a frame lets the handler queue the next one
+    let frame_id = server
+        .send_avc420_frame(0, &[0x00, 0x00, 0x00, 0x01, 0x67], &[], 0)
+        .unwrap();
+    server.drain_output();
+
+    let ack = GfxPdu::FrameAcknowledge(FrameAcknowledgePdu {
+        queue_depth: QueueDepth::AvailableBytes(0),
+        frame_id,
+        total_frames_decoded: 1,
+    });
+    let output = server.process(0, &encode_pdu(&ack)).expect("process failed");
+
+    // StartFrame, WireToSurface1 and EndFrame
+    assert_eq!(output.len(), 3);
+    assert_eq!(server.context().frames_in_flight(), 1);
+    assert_eq!(server.context().frame_stats().total_acked(), 1);
+}
@@ -0,0 +1,82 @@
+use core::time::Duration;
+
+use ironrdp_egfx::pdu::{Avc420Region, CapabilitySet};
+use ironrdp_egfx::server::{GfxContext, GraphicsPipelineHandler, GraphicsPipelineServer};
+use ironrdp_egfx_sim::{SimulatedClient, SimulatedClientConfig};
+
+struct Handler;
+
+impl GraphicsPipelineHandler for Handler {
+    fn capabilities_advertise(&mut self, _pdu: &ironrdp_egfx::pdu::CapabilitiesAdvertisePdu, _ctx: &mut GfxContext) {}
+
+    fn on_ready(&mut self, _negotiated: &CapabilitySet, _ctx: &mut GfxContext) {}
+}
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Sends a frame every 10 ms for a second, unless the server applies backpressure
+fn run(config: SimulatedClientConfig) -> (GraphicsPipelineServer, SimulatedClient, u32) {
+    let mut server = GraphicsPipelineServer::new(Box::new(Handler));
+    server.set_max_frames_in_flight(3);
+
+    let mut client = SimulatedClient::new(config);
+    client.connect(&mut server).unwrap();
+    assert!(server.is_ready());
+    assert!(client.negotiated_capabilities().is_some());
+
+    let surface_id = server.create_surface(64, 64).unwrap();
+    let regions = [Avc420Region::full_frame(64, 64, 22)];
+    let mut sent = 0;
+
+    let mut now = Duration::ZERO;
+    while now < Duration::from_secs(1) {
+        if server
+            .send_avc420_frame(surface_id, &[0x00, 0x00, 0x00, 0x01, 0x67], &regions, 0)
+            .is_some()
+        {
+            sent += 1;
+        }
+
+        client.exchange(&mut server, now).unwrap();
+        now += FRAME_INTERVAL;
+    }
+
+    (server, client, sent)
+}
+
+#[test]
+fn fast_client_acknowledges_every_frame() {
+    let (server, client, sent) = run(SimulatedClientConfig::default());
+
+    assert_eq!(sent, 100);
+    assert_eq!(client.stats().frames_received, 100);
+    assert_eq!(client.stats().frames_acked, 100);
+    assert_eq!(server.frames_in_flight(), 0);
+}
+
+#[test]
+fn slow_client_triggers_backpressure() {
+    let (server, client, sent) = run(SimulatedClientConfig {
+        ack_delay: Duration::from_millis(100),
+        ..SimulatedClientConfig::default()
+    });
+
+    // Three frames are sent every 100 ms
+    assert!(sent < 40, "{sent} frames sent");
+    assert_eq!(client.stats().frames_received, sent);
+    assert_eq!(server.frames_in_flight(), u32::try_from(client.pending_acks()).unwrap());
+}
+
+#[test]
+fn lost_acknowledgements_stall_the_server() {
+    let (server, client, sent) = run(SimulatedClientConfig {
+        ack_loss_percent: 50,
+        seed: 42,
+        ..SimulatedClientConfig::default()
+    });
+
+    assert_eq!(client.stats().frames_lost, 3);
+    assert_eq!(server.frames_in_flight(), 3);
+    assert!(server.should_backpressure());
+    assert_eq!(client.stats().frames_acked, sent - 3);
+}
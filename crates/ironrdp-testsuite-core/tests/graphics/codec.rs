@@ -0,0 +1,206 @@
+use ironrdp_graphics::codec::{
+    BitmapCodec, BitmapDesc, BitmapRef, CodecError, CodecId, CodecRegistry, PlanarCodec, CODEC1_TYPE_PLANAR,
+    CODEC1_TYPE_UNCOMPRESSED,
+};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_graphics::rdp6::ColorLoss;
+use ironrdp_pdu::rdp::capability_sets::{BitmapCodecs, Codec, CodecProperty, Guid, NsCodec};
+
+const DESC: BitmapDesc = BitmapDesc {
+    width: 16,
+    height: 8,
+    format: PixelFormat::BgrX32,
+};
+
+fn gradient(desc: BitmapDesc, stride: usize) -> Vec<u8> {
+    let mut data = vec![0; stride * usize::from(desc.height)];
+
+    for y in 0..usize::from(desc.height) {
+        for x in 0..usize::from(desc.width) {
+            let pixel = &mut data[y * stride + x * 4..][..4];
+            let x = u8::try_from(x * 16).unwrap();
+            let y = u8::try_from(y * 32).unwrap();
+            pixel.copy_from_slice(&[x, y, 0x80, 0xff]);
+        }
+    }
+
+    data
+}
+
+#[test]
+fn builtin_codecs_are_registered() {
+    let registry = CodecRegistry::with_builtin_codecs();
+
+    assert!(registry.contains(CodecId::Codec1Type(CODEC1_TYPE_UNCOMPRESSED)));
+    assert!(registry.contains(CodecId::Codec1Type(CODEC1_TYPE_PLANAR)));
+    assert!(!registry.contains(CodecId::Codec1Type(0x3)));
+}
+
+#[test]
+fn uncompressed_round_trip_strips_stride_padding() {
+    let stride = usize::from(DESC.width) * 4 + 12;
+    let data = gradient(DESC, stride);
+    let bitmap = BitmapRef {
+        desc: DESC,
+        stride,
+        data: &data,
+    };
+
+    let mut registry = CodecRegistry::with_builtin_codecs();
+    let id = CodecId::Codec1Type(CODEC1_TYPE_UNCOMPRESSED);
+
+    let mut encoded = Vec::new();
+    registry.encode(id, &bitmap, &mut encoded).unwrap();
+    assert_eq!(encoded.len(), DESC.len());
+
+    let mut decoded = Vec::new();
+    registry.decode(id, &encoded, DESC, &mut decoded).unwrap();
+    assert_eq!(decoded, encoded);
+}
+
+#[test]
+fn planar_round_trip() {
+    let stride = usize::from(DESC.width) * 4;
+    let data = gradient(DESC, stride);
+    let bitmap = BitmapRef {
+        desc: DESC,
+        stride,
+        data: &data,
+    };
+
+    let mut registry = CodecRegistry::with_builtin_codecs();
+    let id = CodecId::Codec1Type(CODEC1_TYPE_PLANAR);
+
+    let mut encoded = Vec::new();
+    registry.encode(id, &bitmap, &mut encoded).unwrap();
+    assert!(encoded.len() < DESC.len());
+
+    let mut decoded = Vec::new();
+    registry.decode(id, &encoded, DESC, &mut decoded).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn planar_with_color_loss_round_trip() {
+    // The chroma of the gradient colors are multiples of 8, which a color loss level of 3 keeps
+    let stride = usize::from(DESC.width) * 4;
+    let data = gradient(DESC, stride);
+    let bitmap = BitmapRef {
+        desc: DESC,
+        stride,
+        data: &data,
+    };
+
+    let mut codec = PlanarCodec::default().with_color_loss(ColorLoss {
+        level: 3,
+        chroma_subsampling: false,
+    });
+
+    let mut encoded = Vec::new();
+    codec.encode(&bitmap, &mut encoded).unwrap();
+    assert_eq!(encoded[0] & 0x07, 3);
+
+    let mut decoded = Vec::new();
+    codec.decode(&encoded, DESC, &mut decoded).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn unknown_codec_is_reported() {
+    let mut registry = CodecRegistry::new();
+    let id = CodecId::Codec1Type(CODEC1_TYPE_PLANAR);
+
+    let err = registry.decode(id, &[], DESC, &mut Vec::new()).unwrap_err();
+    assert!(matches!(err, CodecError::UnknownCodec(unknown) if unknown == id));
+}
+
+#[test]
+fn too_small_bitmap_is_rejected() {
+    let data = vec![0; DESC.len() - 1];
+    let bitmap = BitmapRef {
+        desc: DESC,
+        stride: DESC.row_len(),
+        data: &data,
+    };
+
+    let mut registry = CodecRegistry::with_builtin_codecs();
+    let err = registry
+        .encode(CodecId::Codec1Type(CODEC1_TYPE_UNCOMPRESSED), &bitmap, &mut Vec::new())
+        .unwrap_err();
+    assert!(matches!(err, CodecError::InvalidInput(_)));
+}
+
+const CUSTOM_GUID: Guid = Guid::new(0x1234_5678, 0x9abc, 0xdef0, [0, 1, 2, 3, 4, 5, 6, 7]);
+
+/// Decode-only codec filling the bitmap with a constant byte
+struct FillCodec(u8);
+
+impl BitmapCodec for FillCodec {
+    fn name(&self) -> &'static str {
+        "fill"
+    }
+
+    fn ids(&self) -> &[CodecId] {
+        &[CodecId::Guid(CUSTOM_GUID), CodecId::Codec1Type(CODEC1_TYPE_PLANAR)]
+    }
+
+    fn can_decode(&self) -> bool {
+        true
+    }
+
+    fn decode(&mut self, _: &[u8], desc: BitmapDesc, dst: &mut Vec<u8>) -> Result<(), CodecError> {
+        dst.resize(dst.len() + desc.len(), self.0);
+        Ok(())
+    }
+}
+
+#[test]
+fn third_party_codec_overrides_builtin() {
+    let mut registry = CodecRegistry::with_builtin_codecs();
+    registry.register(Box::new(FillCodec(0xAB)));
+
+    for id in [CodecId::Guid(CUSTOM_GUID), CodecId::Codec1Type(CODEC1_TYPE_PLANAR)] {
+        assert_eq!(registry.get(id).unwrap().name(), "fill");
+
+        let mut decoded = Vec::new();
+        registry.decode(id, &[], DESC, &mut decoded).unwrap();
+        assert_eq!(decoded, vec![0xAB; DESC.len()]);
+    }
+
+    let data = gradient(DESC, DESC.row_len());
+    let bitmap = BitmapRef {
+        desc: DESC,
+        stride: DESC.row_len(),
+        data: &data,
+    };
+    let err = registry
+        .encode(CodecId::Guid(CUSTOM_GUID), &bitmap, &mut Vec::new())
+        .unwrap_err();
+    assert!(matches!(err, CodecError::Unsupported { codec: "fill", .. }));
+}
+
+#[test]
+fn negotiate_keeps_registered_client_codecs() {
+    let mut registry = CodecRegistry::with_builtin_codecs();
+    registry.register(Box::new(FillCodec(0)));
+
+    let client = BitmapCodecs(vec![
+        Codec {
+            id: 1,
+            property: CodecProperty::NsCodec(NsCodec {
+                is_dynamic_fidelity_allowed: true,
+                is_subsampling_allowed: true,
+                color_loss_level: 3,
+            }),
+        },
+        Codec {
+            id: 7,
+            property: CodecProperty::Unknown {
+                guid: CUSTOM_GUID,
+                data: vec![1, 2, 3],
+            },
+        },
+    ]);
+
+    assert_eq!(registry.negotiate(&client), vec![(CodecId::Guid(CUSTOM_GUID), 7)]);
+}
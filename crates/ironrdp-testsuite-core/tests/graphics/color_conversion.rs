@@ -1595,3 +1595,47 @@ const XRGB_BUFFER: [u8; 4 * 64 * 64] = [
     0xf7, 0x00, 0x14, 0x9d, 0xf7, 0x00, 0x13, 0x9c, 0xf6, 0x00, 0x12, 0x9b, 0xf5, 0x00, 0x12, 0x9b, 0xf5, 0x00, 0x12,
     0x9b, 0xf5, 0x00, 0x12, 0x9b, 0xf5,
 ];
+
+fn all_colors() -> impl Iterator<Item = Rgb> {
+    (0..=0xff_ffff_u32).map(|color| {
+        let [r, g, b, _] = color.to_le_bytes();
+        Rgb { r, g, b }
+    })
+}
+
+#[test]
+fn ycocg_round_trip_is_exact() {
+    for color in all_colors() {
+        assert_eq!(ycocg_to_rgb(rgb_to_ycocg(color)), color);
+    }
+}
+
+#[test]
+fn ycocg_with_color_loss_round_trip_is_exact() {
+    for color_loss_level in 1..=7 {
+        let lost_bits = (1 << color_loss_level) - 1;
+
+        for color in all_colors() {
+            let ycocg = rgb_to_ycocg(color);
+            let planes = (
+                ycocg.y,
+                reduce_chroma(ycocg.co, color_loss_level),
+                reduce_chroma(ycocg.cg, color_loss_level),
+            );
+            let restored = YCoCg {
+                y: planes.0,
+                co: restore_chroma(planes.1, color_loss_level),
+                cg: restore_chroma(planes.2, color_loss_level),
+            };
+
+            // Only the least significant bits of the chroma are lost
+            assert_eq!(restored.co, ycocg.co & !lost_bits);
+            assert_eq!(restored.cg, ycocg.cg & !lost_bits);
+
+            // Colors whose chroma has no bits to lose are kept
+            if ycocg.co & lost_bits == 0 && ycocg.cg & lost_bits == 0 {
+                assert_eq!(ycocg_to_rgb(restored), color);
+            }
+        }
+    }
+}
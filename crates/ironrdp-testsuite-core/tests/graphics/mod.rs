@@ -1,5 +1,8 @@
+mod codec;
 mod color_conversion;
 mod dwt;
 mod image_processing;
+mod progressive;
+mod quality;
 mod rle;
 mod rlgr;
@@ -0,0 +1,411 @@
+use ironrdp_core::encode_vec;
+use ironrdp_graphics::progressive::{ProgressiveDecoder, ProgressiveError};
+use ironrdp_graphics::rlgr;
+use ironrdp_pdu::codecs::progressive::{
+    Block, ComponentCodecQuant, FirstTile, ProgressiveCodecQuant, RegionFlags, RegionPdu, Tile, TileFlags, UpgradeTile,
+    FULL_QUALITY,
+};
+use ironrdp_pdu::codecs::rfx::{EntropyAlgorithm, RfxRectangle};
+use ironrdp_pdu::geometry::InclusiveRectangle;
+
+const SURFACE_ID: u16 = 1;
+const STRIDE: usize = 64 * 4;
+
+const QUANT: ComponentCodecQuant = uniform_quant(6);
+
+/// Bits of the coefficients left to the upgrade pass
+const UPGRADE_BITS: u8 = 2;
+
+const fn uniform_quant(value: u8) -> ComponentCodecQuant {
+    ComponentCodecQuant {
+        ll3: value,
+        hl3: value,
+        lh3: value,
+        hh3: value,
+        hl2: value,
+        lh2: value,
+        hh2: value,
+        hl1: value,
+        lh1: value,
+        hh1: value,
+    }
+}
+
+/// Offset of the LL3 sub-band in the coefficients of a tile
+fn ll3_offset(flags: RegionFlags) -> usize {
+    if flags.contains(RegionFlags::DWT_REDUCE_EXTRAPOLATE) {
+        4015
+    } else {
+        4032
+    }
+}
+
+/// Pseudo-random quantized coefficients, mostly zero outside of LL3
+fn coefficients(seed: u32, flags: RegionFlags) -> [i16; 4096] {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        i16::try_from((state >> 16) % 256).unwrap()
+    };
+
+    let ll3 = ll3_offset(flags);
+    let mut coefficients = [0; 4096];
+
+    for value in &mut coefficients[..ll3] {
+        let random = next();
+        if random < 96 {
+            *value = random % 13 - 6;
+        }
+    }
+    for value in &mut coefficients[ll3..] {
+        *value = next() - 128;
+    }
+
+    coefficients
+}
+
+/// RLGR1 encoded component, the LL3 sub-band being differentially encoded
+fn encode_component(coefficients: &[i16; 4096], flags: RegionFlags) -> Vec<u8> {
+    let mut input = *coefficients;
+    let ll3 = &mut input[ll3_offset(flags)..];
+    for i in (1..ll3.len()).rev() {
+        ll3[i] -= ll3[i - 1];
+    }
+
+    let mut output = vec![0; 4096 * 4];
+    let len = rlgr::encode(EntropyAlgorithm::Rlgr1, &input, &mut output).unwrap();
+    output.truncate(len);
+    output
+}
+
+/// Coefficients of a first pass leaving the `UPGRADE_BITS` least significant bits to the upgrade pass
+fn first_pass(coefficients: &[i16; 4096], flags: RegionFlags) -> [i16; 4096] {
+    let ll3 = ll3_offset(flags);
+    let mut first = [0; 4096];
+
+    for (first, value) in first[..ll3].iter_mut().zip(&coefficients[..ll3]) {
+        *first = value.signum() * (value.abs() >> UPGRADE_BITS);
+    }
+    for (first, value) in first[ll3..].iter_mut().zip(&coefficients[ll3..]) {
+        *first = value >> UPGRADE_BITS;
+    }
+
+    first
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u8) {
+        for bit in (0..count).rev() {
+            if self.len % 8 == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> bit) & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+/// SRL and RAW streams of the upgrade pass of a component, see [MS-RDPEGFX] 3.3.8.2.2
+fn upgrade_pass(coefficients: &[i16; 4096], first: &[i16; 4096], flags: RegionFlags) -> (Vec<u8>, Vec<u8>) {
+    let mask = (1 << UPGRADE_BITS) - 1;
+    let signed_mask = (1 << UPGRADE_BITS) - 1;
+    let ll3 = ll3_offset(flags);
+
+    let mut raw = BitWriter::default();
+    let mut srl_values = Vec::new();
+
+    for (&value, &first) in coefficients[..ll3].iter().zip(&first[..ll3]) {
+        if first == 0 {
+            srl_values.push(value);
+        } else {
+            raw.write(u32::from(value.unsigned_abs()) & mask, UPGRADE_BITS);
+        }
+    }
+    for &value in &coefficients[ll3..] {
+        raw.write(u32::try_from(value & signed_mask).unwrap(), UPGRADE_BITS);
+    }
+
+    let mut srl = BitWriter::default();
+    let mut kp = 8u32;
+    let mut i = 0;
+
+    while i < srl_values.len() {
+        let k = kp >> 3;
+        let run = srl_values[i..].iter().take_while(|&&value| value == 0).count();
+
+        if run >= 1 << k || i + run == srl_values.len() {
+            srl.write(0, 1);
+            kp = (kp + 4).min(80);
+            i += 1 << k;
+            continue;
+        }
+
+        let value = srl_values[i + run];
+        srl.write(1, 1);
+        srl.write(u32::try_from(run).unwrap(), u8::try_from(k).unwrap());
+        srl.write(u32::from(value < 0), 1);
+        kp = kp.saturating_sub(6);
+
+        let max = (1 << UPGRADE_BITS) - 1;
+        let magnitude = value.unsigned_abs();
+        for _ in 1..magnitude {
+            srl.write(0, 1);
+        }
+        if magnitude < max {
+            srl.write(1, 1);
+        }
+
+        i += run + 1;
+    }
+
+    (srl.bytes, raw.bytes)
+}
+
+fn first_tile<'a>(quality: u8, flags: TileFlags, data: &'a [Vec<u8>; 3]) -> FirstTile<'a> {
+    FirstTile {
+        quant_idx_y: 0,
+        quant_idx_cb: 0,
+        quant_idx_cr: 0,
+        x_idx: 0,
+        y_idx: 0,
+        flags,
+        quality,
+        y_data: &data[0],
+        cb_data: &data[1],
+        cr_data: &data[2],
+        tail_data: &[],
+    }
+}
+
+fn region(flags: RegionFlags, tiles: Vec<Tile<'_>>) -> Vec<u8> {
+    let quant_prog = uniform_quant(UPGRADE_BITS);
+
+    encode_vec(&Block::Region(RegionPdu {
+        rectangles: vec![RfxRectangle {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 64,
+        }],
+        quant_values: vec![QUANT],
+        quant_prog_values: vec![ProgressiveCodecQuant {
+            quality: 50,
+            y: quant_prog,
+            cb: quant_prog,
+            cr: quant_prog,
+        }],
+        flags,
+        tiles,
+    }))
+    .unwrap()
+}
+
+fn decoder() -> ProgressiveDecoder {
+    let mut decoder = ProgressiveDecoder::new();
+    decoder.create_surface(SURFACE_ID, 64, 64);
+    decoder
+}
+
+/// Decodes the tile with the given coefficients in a single pass at full quality
+fn decode_simple(components: &[[i16; 4096]; 3], flags: RegionFlags) -> Vec<u8> {
+    let data = components.map(|coefficients| encode_component(&coefficients, flags));
+    let stream = region(
+        flags,
+        vec![Tile::Simple(first_tile(FULL_QUALITY, TileFlags::empty(), &data))],
+    );
+
+    let mut image = vec![0; 64 * STRIDE];
+    decoder().decode(SURFACE_ID, &stream, &mut image, STRIDE).unwrap();
+    image
+}
+
+fn upgrade_equals_full_quality(flags: RegionFlags) {
+    let components = [1, 2, 3].map(|seed| coefficients(seed, flags));
+    let first = components.map(|coefficients| first_pass(&coefficients, flags));
+
+    let first_data = first.map(|coefficients| encode_component(&coefficients, flags));
+    let first_stream = region(flags, vec![Tile::First(first_tile(0, TileFlags::empty(), &first_data))]);
+
+    let [y, cb, cr] = [0, 1, 2].map(|i| upgrade_pass(&components[i], &first[i], flags));
+    let upgrade_stream = region(
+        flags,
+        vec![Tile::Upgrade(UpgradeTile {
+            quant_idx_y: 0,
+            quant_idx_cb: 0,
+            quant_idx_cr: 0,
+            x_idx: 0,
+            y_idx: 0,
+            quality: FULL_QUALITY,
+            y_srl_data: &y.0,
+            y_raw_data: &y.1,
+            cb_srl_data: &cb.0,
+            cb_raw_data: &cb.1,
+            cr_srl_data: &cr.0,
+            cr_raw_data: &cr.1,
+        })],
+    );
+
+    let mut decoder = decoder();
+    let mut image = vec![0; 64 * STRIDE];
+
+    decoder.decode(SURFACE_ID, &first_stream, &mut image, STRIDE).unwrap();
+    assert_ne!(image, decode_simple(&components, flags));
+
+    let updated = decoder.decode(SURFACE_ID, &upgrade_stream, &mut image, STRIDE).unwrap();
+    assert_eq!(
+        updated,
+        [InclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: 63,
+            bottom: 63,
+        }]
+    );
+    assert_eq!(image, decode_simple(&components, flags));
+}
+
+#[test]
+fn flat_tile_is_uniform() {
+    let mut coefficients = [0; 4096];
+    coefficients[4032..].fill(40);
+    let data = [coefficients, [0; 4096], [0; 4096]].map(|c| encode_component(&c, RegionFlags::empty()));
+    let stream = region(
+        RegionFlags::empty(),
+        vec![Tile::Simple(first_tile(FULL_QUALITY, TileFlags::empty(), &data))],
+    );
+
+    let mut image = vec![0; 64 * STRIDE];
+    decoder().decode(SURFACE_ID, &stream, &mut image, STRIDE).unwrap();
+
+    let pixel = &image[..4];
+    assert_ne!(pixel, [0, 0, 0, 0]);
+    assert!(image.chunks_exact(4).all(|p| p == pixel));
+}
+
+#[test]
+fn upgrade_pass_completes_first_pass() {
+    upgrade_equals_full_quality(RegionFlags::empty());
+}
+
+#[test]
+fn upgrade_pass_completes_first_pass_with_reduce_extrapolate() {
+    upgrade_equals_full_quality(RegionFlags::DWT_REDUCE_EXTRAPOLATE);
+}
+
+#[test]
+fn difference_tile_is_added_to_previous_tile() {
+    let flags = RegionFlags::empty();
+    let previous = [1, 2, 3].map(|seed| coefficients(seed, flags));
+    let difference = [4, 5, 6].map(|seed| coefficients(seed, flags));
+
+    let previous_data = previous.map(|coefficients| encode_component(&coefficients, flags));
+    let difference_data = difference.map(|coefficients| encode_component(&coefficients, flags));
+
+    let mut decoder = decoder();
+    let mut image = vec![0; 64 * STRIDE];
+
+    let stream = region(
+        flags,
+        vec![Tile::Simple(first_tile(
+            FULL_QUALITY,
+            TileFlags::empty(),
+            &previous_data,
+        ))],
+    );
+    decoder.decode(SURFACE_ID, &stream, &mut image, STRIDE).unwrap();
+
+    let stream = region(
+        flags,
+        vec![Tile::Simple(first_tile(
+            FULL_QUALITY,
+            TileFlags::DIFFERENCE,
+            &difference_data,
+        ))],
+    );
+    decoder.decode(SURFACE_ID, &stream, &mut image, STRIDE).unwrap();
+
+    let mut sum = previous;
+    for (sum, difference) in sum.iter_mut().zip(&difference) {
+        for (sum, difference) in sum.iter_mut().zip(difference) {
+            *sum += difference;
+        }
+    }
+
+    assert_eq!(image, decode_simple(&sum, flags));
+}
+
+#[test]
+fn upgrade_without_first_pass_is_rejected() {
+    let stream = region(
+        RegionFlags::empty(),
+        vec![Tile::Upgrade(UpgradeTile {
+            quant_idx_y: 0,
+            quant_idx_cb: 0,
+            quant_idx_cr: 0,
+            x_idx: 0,
+            y_idx: 0,
+            quality: FULL_QUALITY,
+            y_srl_data: &[],
+            y_raw_data: &[],
+            cb_srl_data: &[],
+            cb_raw_data: &[],
+            cr_srl_data: &[],
+            cr_raw_data: &[],
+        })],
+    );
+
+    let mut image = vec![0; 64 * STRIDE];
+    let error = decoder().decode(SURFACE_ID, &stream, &mut image, STRIDE).unwrap_err();
+
+    assert!(matches!(
+        error,
+        ProgressiveError::MissingFirstPass { x_idx: 0, y_idx: 0 }
+    ));
+}
+
+#[test]
+fn tile_out_of_surface_is_rejected() {
+    let data = [Vec::new(), Vec::new(), Vec::new()];
+    let mut tile = first_tile(FULL_QUALITY, TileFlags::empty(), &data);
+    tile.x_idx = 1;
+    let stream = region(RegionFlags::empty(), vec![Tile::Simple(tile)]);
+
+    let mut image = vec![0; 64 * STRIDE];
+    let error = decoder().decode(SURFACE_ID, &stream, &mut image, STRIDE).unwrap_err();
+
+    assert!(matches!(
+        error,
+        ProgressiveError::TileOutOfBounds { x_idx: 1, y_idx: 0 }
+    ));
+}
+
+#[test]
+fn repeated_tile_is_rejected() {
+    let data = [Vec::new(), Vec::new(), Vec::new()];
+    let tile = first_tile(FULL_QUALITY, TileFlags::empty(), &data);
+    let stream = region(
+        RegionFlags::empty(),
+        vec![Tile::Simple(tile.clone()), Tile::Simple(tile)],
+    );
+
+    let mut image = vec![0; 64 * STRIDE];
+    let error = decoder().decode(SURFACE_ID, &stream, &mut image, STRIDE).unwrap_err();
+
+    assert!(matches!(error, ProgressiveError::DuplicateTile { x_idx: 0, y_idx: 0 }));
+}
+
+#[test]
+fn unknown_surface_is_rejected() {
+    let mut image = vec![0; 64 * STRIDE];
+    let error = decoder().decode(2, &[], &mut image, STRIDE).unwrap_err();
+
+    assert!(matches!(error, ProgressiveError::UnknownSurface(2)));
+}
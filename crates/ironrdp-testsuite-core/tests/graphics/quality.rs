@@ -0,0 +1,78 @@
+use ironrdp_graphics::codec::{BitmapDesc, BitmapRef};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_graphics::quality::{psnr, ssim};
+
+const DESC: BitmapDesc = BitmapDesc {
+    width: 12,
+    height: 10,
+    format: PixelFormat::BgrX32,
+};
+
+fn checkerboard() -> Vec<u8> {
+    let mut data = Vec::new();
+
+    for y in 0..usize::from(DESC.height) {
+        for x in 0..usize::from(DESC.width) {
+            let value = if (x + y) % 2 == 0 { 0x20 } else { 0xe0 };
+            data.extend_from_slice(&[value, value, value, 0xff]);
+        }
+    }
+
+    data
+}
+
+fn bitmap(data: &[u8]) -> BitmapRef<'_> {
+    BitmapRef {
+        desc: DESC,
+        stride: usize::from(DESC.width) * 4,
+        data,
+    }
+}
+
+#[test]
+fn identical_bitmaps() {
+    let data = checkerboard();
+
+    assert!(psnr(&bitmap(&data), &bitmap(&data)).unwrap().is_infinite());
+    assert!((ssim(&bitmap(&data), &bitmap(&data)).unwrap() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn uniform_error() {
+    let data = checkerboard();
+    // Every gray level shifted by 16: the MSE is 256
+    let shifted: Vec<u8> = data
+        .iter()
+        .map(|&value| if value == 0xff { value } else { value + 16 })
+        .collect();
+
+    let psnr = psnr(&bitmap(&data), &bitmap(&shifted)).unwrap();
+    assert!(
+        (psnr - 10.0 * (255.0f64 * 255.0 / 256.0).log10()).abs() < 1e-6,
+        "{psnr}"
+    );
+
+    // The structure is preserved
+    assert!(ssim(&bitmap(&data), &bitmap(&shifted)).unwrap() > 0.99);
+}
+
+#[test]
+fn lost_structure() {
+    let data = checkerboard();
+    let flat = vec![0x80; data.len()];
+
+    assert!(ssim(&bitmap(&data), &bitmap(&flat)).unwrap() < 0.1);
+}
+
+#[test]
+fn different_sizes() {
+    let data = checkerboard();
+    let smaller = BitmapRef {
+        desc: BitmapDesc { width: 11, ..DESC },
+        stride: usize::from(DESC.width) * 4,
+        data: &data,
+    };
+
+    assert!(psnr(&bitmap(&data), &smaller).is_err());
+    assert!(ssim(&bitmap(&data), &smaller).is_err());
+}
@@ -0,0 +1,98 @@
+//! Loopback harness shared by the tests of the virtual channels
+//!
+//! The client and server processors are connected back to back: the messages of one side are encoded, then given
+//! to the other side, and the test backends record what they are notified of.
+
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::{decode, encode_vec, Decode, Encode};
+use ironrdp_dvc::{DvcMessage, DvcProcessor};
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage};
+
+/// Identifier of the dynamic channel under test, on both sides
+pub(crate) const CHANNEL_ID: u32 = 3;
+
+/// Events recorded by the test backends, in the order they occurred
+pub(crate) type Events<E> = Arc<Mutex<Vec<E>>>;
+
+/// Test backend recording the events it is notified of
+#[derive(Debug)]
+pub(crate) struct Recorder<E> {
+    events: Events<E>,
+}
+
+impl<E> Recorder<E> {
+    pub(crate) fn new(events: &Events<E>) -> Self {
+        Self {
+            events: Arc::clone(events),
+        }
+    }
+
+    pub(crate) fn record(&self, event: E) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+/// Delivers the messages to the peer, until there is no reply
+pub(crate) fn exchange(server: &mut dyn DvcProcessor, client: &mut dyn DvcProcessor, messages: Vec<DvcMessage>) {
+    let mut to_client = messages;
+
+    while !to_client.is_empty() {
+        let mut to_server = Vec::new();
+        for message in to_client {
+            to_server.extend(process(client, message.as_ref()));
+        }
+
+        to_client = Vec::new();
+        for message in to_server {
+            to_client.extend(process(server, message.as_ref()));
+        }
+    }
+}
+
+/// Starts the channel on both sides, then runs the exchange initiated by the server
+pub(crate) fn open(server: &mut dyn DvcProcessor, client: &mut dyn DvcProcessor) {
+    assert!(client.start(CHANNEL_ID).unwrap().is_empty());
+    let messages = server.start(CHANNEL_ID).unwrap();
+    exchange(server, client, messages);
+}
+
+/// Static channel counterpart of [`exchange`], the messages being chunked as on the wire
+pub(crate) fn exchange_static(
+    server: &mut StaticVirtualChannel,
+    client: &mut StaticVirtualChannel,
+    messages: Vec<SvcMessage>,
+) {
+    let mut to_client = messages;
+
+    while !to_client.is_empty() {
+        let mut to_server = Vec::new();
+        for chunk in StaticVirtualChannel::chunkify(to_client).unwrap() {
+            to_server.extend(client.process(chunk.filled()).unwrap());
+        }
+
+        to_client = Vec::new();
+        for chunk in StaticVirtualChannel::chunkify(to_server).unwrap() {
+            to_client.extend(server.process(chunk.filled()).unwrap());
+        }
+    }
+}
+
+/// Gives the encoded `pdu` to the processor, returning its replies
+pub(crate) fn process<P: Encode + ?Sized>(processor: &mut dyn DvcProcessor, pdu: &P) -> Vec<DvcMessage> {
+    processor.process(CHANNEL_ID, &encode_vec(pdu).unwrap()).unwrap()
+}
+
+pub(crate) fn decode_messages<T: for<'a> Decode<'a>>(messages: Vec<DvcMessage>) -> Vec<T> {
+    messages
+        .into_iter()
+        .map(|message| decode(&encode_vec(message.as_ref()).unwrap()).unwrap())
+        .collect()
+}
+
+/// Checks that `pdu` can't be decoded once its last byte is missing
+pub(crate) fn assert_truncated_is_rejected<T: for<'a> Decode<'a>>(pdu: &impl Encode) {
+    let encoded = encode_vec(pdu).unwrap();
+
+    assert!(decode::<T>(&encoded[..encoded.len() - 1]).is_err());
+}
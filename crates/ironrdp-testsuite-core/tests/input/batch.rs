@@ -0,0 +1,133 @@
+use core::time::Duration;
+
+use ironrdp_input::*;
+use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags};
+use ironrdp_pdu::input::mouse::PointerFlags;
+use ironrdp_pdu::input::mouse_rel::PointerRelFlags;
+use ironrdp_pdu::input::{MousePdu, MouseRelPdu};
+
+const INTERVAL: Duration = Duration::from_millis(10);
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+fn mouse_move(x: u16, y: u16) -> FastPathInputEvent {
+    FastPathInputEvent::MouseEvent(MousePdu {
+        flags: PointerFlags::MOVE,
+        number_of_wheel_rotation_units: 0,
+        x_position: x,
+        y_position: y,
+    })
+}
+
+#[test]
+fn coalesces_mouse_moves() {
+    let mut db = Database::new();
+    let mut batcher = InputBatcher::new(INTERVAL);
+
+    for x in 1..=50 {
+        let events = db.apply([Operation::MouseMove(MousePosition { x, y: 10 })]);
+        batcher.push(events, ms(u64::from(x) / 10));
+    }
+    assert_eq!(batcher.pending(), 1);
+
+    let pdu = batcher.poll(ms(10)).expect("due PDU");
+    assert_eq!(pdu.input_events(), [mouse_move(50, 10)]);
+    assert_eq!(batcher.pending(), 0);
+}
+
+#[test]
+fn sums_relative_mouse_moves() {
+    let mut db = Database::new();
+    let mut batcher = InputBatcher::new(INTERVAL);
+
+    for _ in 0..3 {
+        let events = db.apply([Operation::MouseRelMove(MouseDelta { x: 2, y: -1 })]);
+        batcher.push(events, ms(0));
+    }
+
+    let pdu = batcher.poll(ms(10)).expect("due PDU");
+    assert_eq!(
+        pdu.input_events(),
+        [FastPathInputEvent::MouseEventRel(MouseRelPdu {
+            flags: PointerRelFlags::MOVE,
+            x_delta: 6,
+            y_delta: -3,
+        })]
+    );
+}
+
+#[test]
+fn keeps_the_order_of_other_events() {
+    let mut db = Database::new();
+    let mut batcher = InputBatcher::new(INTERVAL);
+
+    let events = db.apply([
+        Operation::MouseMove(MousePosition { x: 1, y: 1 }),
+        Operation::MouseMove(MousePosition { x: 2, y: 2 }),
+        Operation::MouseButtonPressed(MouseButton::Left),
+        Operation::MouseMove(MousePosition { x: 3, y: 3 }),
+        Operation::KeyPressed(Scancode::from_u8(false, 0x1E)),
+        Operation::MouseMove(MousePosition { x: 4, y: 4 }),
+        Operation::MouseMove(MousePosition { x: 5, y: 5 }),
+    ]);
+    batcher.push(events, ms(0));
+
+    let pdu = batcher.flush().expect("pending events");
+    assert_eq!(
+        pdu.input_events(),
+        [
+            mouse_move(2, 2),
+            FastPathInputEvent::MouseEvent(MousePdu {
+                flags: PointerFlags::DOWN | PointerFlags::LEFT_BUTTON,
+                number_of_wheel_rotation_units: 0,
+                x_position: 2,
+                y_position: 2,
+            }),
+            mouse_move(3, 3),
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1E),
+            mouse_move(5, 5),
+        ]
+    );
+    assert!(batcher.flush().is_none());
+}
+
+#[test]
+fn flushes_after_the_interval() {
+    let mut batcher = InputBatcher::new(INTERVAL);
+
+    assert_eq!(batcher.next_flush(), None);
+    assert!(batcher.poll(ms(100)).is_none());
+
+    batcher.push([mouse_move(1, 1)], ms(100));
+    batcher.push(
+        [FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1E)],
+        ms(105),
+    );
+
+    // Due after the interval since the oldest event
+    assert_eq!(batcher.next_flush(), Some(ms(110)));
+    assert!(batcher.poll(ms(109)).is_none());
+    assert_eq!(batcher.poll(ms(110)).expect("due PDU").input_events().len(), 2);
+    assert_eq!(batcher.next_flush(), None);
+
+    // A zero interval sends the events on the next poll
+    batcher.set_flush_interval(Duration::ZERO);
+    batcher.push([mouse_move(2, 2)], ms(200));
+    assert!(batcher.poll(ms(200)).is_some());
+}
+
+#[test]
+fn splits_large_batches() {
+    let mut batcher = InputBatcher::new(INTERVAL);
+
+    let events = (0..300).map(|_| FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1E));
+    batcher.push(events, ms(0));
+
+    // Full PDUs are due right away
+    assert_eq!(batcher.next_flush(), Some(ms(0)));
+    assert_eq!(batcher.poll(ms(0)).expect("full PDU").input_events().len(), 255);
+    assert!(batcher.poll(ms(0)).is_none());
+    assert_eq!(batcher.poll(ms(10)).expect("due PDU").input_events().len(), 45);
+}
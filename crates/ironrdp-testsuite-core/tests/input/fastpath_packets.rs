@@ -1,8 +1,9 @@
 use ironrdp_input::*;
 use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags, SynchronizeFlags};
 use ironrdp_pdu::input::mouse::PointerFlags;
+use ironrdp_pdu::input::mouse_rel::PointerRelFlags;
 use ironrdp_pdu::input::mouse_x::PointerXFlags;
-use ironrdp_pdu::input::{MousePdu, MouseXPdu};
+use ironrdp_pdu::input::{MousePdu, MouseRelPdu, MouseXPdu};
 use rstest::rstest;
 
 enum MouseFlags {
@@ -328,3 +329,67 @@ fn wheel_rotations() {
 
     assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
 }
+
+#[test]
+fn mouse_relative_move() {
+    let mut db = Database::new();
+
+    let ops = [
+        Operation::MouseRelMove(MouseDelta { x: 5, y: -3 }),
+        Operation::MouseRelMove(MouseDelta { x: 0, y: 0 }),
+    ];
+
+    let actual_inputs = db.apply(ops);
+
+    let expected_inputs = [FastPathInputEvent::MouseEventRel(MouseRelPdu {
+        flags: PointerRelFlags::MOVE,
+        x_delta: 5,
+        y_delta: -3,
+    })];
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+}
+
+#[test]
+fn relative_mouse_buttons() {
+    let mut db = Database::new();
+    db.set_relative_mouse(true);
+
+    let ops = [
+        Operation::MouseButtonPressed(MouseButton::Left),
+        Operation::MouseButtonPressed(MouseButton::X1),
+        Operation::MouseButtonReleased(MouseButton::Left),
+    ];
+
+    let actual_inputs = db.apply(ops);
+
+    let expected_inputs = [
+        FastPathInputEvent::MouseEventRel(MouseRelPdu {
+            flags: PointerRelFlags::DOWN | PointerRelFlags::BUTTON1,
+            x_delta: 0,
+            y_delta: 0,
+        }),
+        FastPathInputEvent::MouseEventRel(MouseRelPdu {
+            flags: PointerRelFlags::DOWN | PointerRelFlags::XBUTTON1,
+            x_delta: 0,
+            y_delta: 0,
+        }),
+        FastPathInputEvent::MouseEventRel(MouseRelPdu {
+            flags: PointerRelFlags::BUTTON1,
+            x_delta: 0,
+            y_delta: 0,
+        }),
+    ];
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+
+    let expected_inputs = [FastPathInputEvent::MouseEventRel(MouseRelPdu {
+        flags: PointerRelFlags::XBUTTON1,
+        x_delta: 0,
+        y_delta: 0,
+    })];
+
+    let actual_inputs = db.release_all();
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+}
@@ -0,0 +1,181 @@
+use ironrdp_input::{Database, Hotkey, Hotkeys, Modifier, Operation, Scancode};
+use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags};
+
+const CONTROL_LEFT: Scancode = Scancode::from_u8(false, 0x1D);
+const ALT_LEFT: Scancode = Scancode::from_u8(false, 0x38);
+const SHIFT_LEFT: Scancode = Scancode::from_u8(false, 0x2A);
+const ENTER: Scancode = Scancode::from_u8(false, 0x1C);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LocalAction {
+    ToggleFullscreen,
+    ReleasePointer,
+}
+
+fn hotkeys() -> Hotkeys<LocalAction> {
+    let mut hotkeys = Hotkeys::new();
+    hotkeys.bind(
+        Hotkey::new(&[Modifier::Control, Modifier::Alt], ENTER),
+        LocalAction::ToggleFullscreen,
+    );
+    hotkeys
+}
+
+fn apply(
+    hotkeys: &mut Hotkeys<LocalAction>,
+    db: &mut Database,
+    operations: impl IntoIterator<Item = Operation>,
+) -> (Vec<FastPathInputEvent>, Vec<LocalAction>) {
+    let mut actions = Vec::new();
+    let events = hotkeys.apply(db, operations, |action| actions.push(*action));
+    (events.into_iter().collect(), actions)
+}
+
+fn key(flags: KeyboardFlags, scancode: Scancode) -> FastPathInputEvent {
+    FastPathInputEvent::KeyboardEvent(flags, scancode.as_u8().1)
+}
+
+#[test]
+fn hotkey_is_intercepted() {
+    let mut hotkeys = hotkeys();
+    let mut db = Database::new();
+
+    let (events, actions) = apply(
+        &mut hotkeys,
+        &mut db,
+        [Operation::KeyPressed(CONTROL_LEFT), Operation::KeyPressed(ALT_LEFT)],
+    );
+    assert_eq!(
+        events,
+        [
+            key(KeyboardFlags::empty(), CONTROL_LEFT),
+            key(KeyboardFlags::empty(), ALT_LEFT)
+        ]
+    );
+    assert!(actions.is_empty());
+
+    // The modifiers are released on the server side, the key is not sent.
+    let (events, actions) = apply(&mut hotkeys, &mut db, [Operation::KeyPressed(ENTER)]);
+    assert_eq!(
+        events,
+        [
+            key(KeyboardFlags::RELEASE, CONTROL_LEFT),
+            key(KeyboardFlags::RELEASE, ALT_LEFT)
+        ]
+    );
+    assert_eq!(actions, [LocalAction::ToggleFullscreen]);
+    assert!(!db.is_key_pressed(CONTROL_LEFT));
+    assert!(!db.is_key_pressed(ENTER));
+
+    // Key repeats and releases are swallowed.
+    let (events, actions) = apply(
+        &mut hotkeys,
+        &mut db,
+        [
+            Operation::KeyPressed(ENTER),
+            Operation::KeyReleased(ENTER),
+            Operation::KeyReleased(ALT_LEFT),
+            Operation::KeyReleased(CONTROL_LEFT),
+        ],
+    );
+    assert!(events.is_empty());
+    assert!(actions.is_empty());
+
+    // The key is sent again once released.
+    let (events, _) = apply(&mut hotkeys, &mut db, [Operation::KeyPressed(ENTER)]);
+    assert_eq!(events, [key(KeyboardFlags::empty(), ENTER)]);
+}
+
+#[test]
+fn modifiers_must_match_exactly() {
+    let mut hotkeys = hotkeys();
+    let mut db = Database::new();
+
+    let (events, actions) = apply(
+        &mut hotkeys,
+        &mut db,
+        [
+            Operation::KeyPressed(CONTROL_LEFT),
+            Operation::KeyPressed(SHIFT_LEFT),
+            Operation::KeyPressed(ALT_LEFT),
+            Operation::KeyPressed(ENTER),
+        ],
+    );
+    assert_eq!(events.len(), 4);
+    assert!(actions.is_empty());
+
+    let (events, actions) = apply(&mut hotkeys, &mut db, [Operation::KeyReleased(ENTER)]);
+    assert_eq!(events, [key(KeyboardFlags::RELEASE, ENTER)]);
+    assert!(actions.is_empty());
+}
+
+#[test]
+fn right_modifiers_trigger_hotkeys() {
+    let mut hotkeys = hotkeys();
+    let mut db = Database::new();
+
+    let [_, control_right] = Modifier::Control.scancodes();
+    let [_, alt_right] = Modifier::Alt.scancodes();
+
+    let (_, actions) = apply(
+        &mut hotkeys,
+        &mut db,
+        [
+            Operation::KeyPressed(control_right),
+            Operation::KeyPressed(alt_right),
+            Operation::KeyPressed(ENTER),
+        ],
+    );
+    assert_eq!(actions, [LocalAction::ToggleFullscreen]);
+    assert!(!db.is_key_pressed(control_right));
+    assert!(!db.is_key_pressed(alt_right));
+}
+
+#[test]
+fn bind_and_unbind() {
+    let mut hotkeys = hotkeys();
+    let mut db = Database::new();
+
+    let hotkey = Hotkey::new(&[Modifier::Alt, Modifier::Control, Modifier::Alt], ENTER);
+    assert_eq!(hotkey.modifiers(), [Modifier::Control, Modifier::Alt]);
+    assert_eq!(
+        hotkeys.bind(hotkey.clone(), LocalAction::ReleasePointer),
+        Some(LocalAction::ToggleFullscreen)
+    );
+
+    let (_, actions) = apply(
+        &mut hotkeys,
+        &mut db,
+        [
+            Operation::KeyPressed(CONTROL_LEFT),
+            Operation::KeyPressed(ALT_LEFT),
+            Operation::KeyPressed(ENTER),
+        ],
+    );
+    assert_eq!(actions, [LocalAction::ReleasePointer]);
+
+    assert_eq!(hotkeys.unbind(&hotkey), Some(LocalAction::ReleasePointer));
+    assert_eq!(hotkeys.unbind(&hotkey), None);
+}
+
+#[test]
+fn release_all_forgets_swallowed_keys() {
+    let mut hotkeys = hotkeys();
+    let mut db = Database::new();
+
+    apply(
+        &mut hotkeys,
+        &mut db,
+        [
+            Operation::KeyPressed(CONTROL_LEFT),
+            Operation::KeyPressed(ALT_LEFT),
+            Operation::KeyPressed(ENTER),
+        ],
+    );
+
+    // The focus is lost before the key is released.
+    assert!(hotkeys.release_all(&mut db).is_empty());
+
+    let (events, _) = apply(&mut hotkeys, &mut db, [Operation::KeyPressed(ENTER)]);
+    assert_eq!(events, [key(KeyboardFlags::empty(), ENTER)]);
+}
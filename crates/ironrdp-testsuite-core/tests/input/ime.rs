@@ -0,0 +1,77 @@
+use ironrdp_input::{unicode_text_operations, Database, ImeComposition, Operation};
+use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags};
+
+#[test]
+fn commit_types_unicode_keys() {
+    let mut ime = ImeComposition::new();
+    ime.set_preedit("\u{306B}\u{307B}", Some((3, 6)));
+    assert!(ime.is_composing());
+
+    let operations = ime.commit("\u{65E5}\u{672C}");
+    assert!(!ime.is_composing());
+    assert_eq!(ime.preedit(), "");
+    assert_eq!(ime.preedit_cursor(), None);
+    assert_eq!(
+        operations,
+        [
+            Operation::UnicodeKeyPressed('\u{65E5}'),
+            Operation::UnicodeKeyReleased('\u{65E5}'),
+            Operation::UnicodeKeyPressed('\u{672C}'),
+            Operation::UnicodeKeyReleased('\u{672C}'),
+        ]
+    );
+}
+
+#[test]
+fn commit_sends_surrogate_pairs() {
+    let mut database = Database::new();
+    let events = database.apply(unicode_text_operations(['\u{1F600}']));
+
+    assert_eq!(
+        events.as_slice(),
+        [
+            FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0xD83D),
+            FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0xDE00),
+            FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0xD83D),
+            FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0xDE00),
+        ]
+    );
+    assert!(!database.is_unicode_key_pressed('\u{1F600}'));
+}
+
+#[test]
+fn commit_utf16_replaces_unpaired_surrogates() {
+    let mut ime = ImeComposition::new();
+
+    assert_eq!(
+        ime.commit_utf16(&[0xD83D, 0xDE00]),
+        unicode_text_operations(['\u{1F600}'])
+    );
+    assert_eq!(
+        ime.commit_utf16(&[0x0041, 0xDE00, 0xD83D]),
+        unicode_text_operations(['A', char::REPLACEMENT_CHARACTER, char::REPLACEMENT_CHARACTER])
+    );
+}
+
+#[test]
+fn preedit_cursor_is_checked() {
+    let mut ime = ImeComposition::new();
+    assert!(!ime.is_composing());
+
+    // U+304B is three bytes long in UTF-8.
+    ime.set_preedit("\u{304B}a", Some((0, 3)));
+    assert_eq!(ime.preedit(), "\u{304B}a");
+    assert_eq!(ime.preedit_cursor(), Some((0, 3)));
+
+    ime.set_preedit("\u{304B}a", Some((1, 3)));
+    assert_eq!(ime.preedit_cursor(), None);
+
+    ime.set_preedit("\u{304B}a", Some((4, 3)));
+    assert_eq!(ime.preedit_cursor(), None);
+
+    ime.set_preedit("\u{304B}a", Some((4, 5)));
+    assert_eq!(ime.preedit_cursor(), None);
+
+    ime.set_preedit("", None);
+    assert!(!ime.is_composing());
+}
@@ -0,0 +1,168 @@
+use ironrdp_input::{KeyPress, KeyboardLayout, Keystroke, Operation, Scancode};
+
+const SHIFT_LEFT: Scancode = Scancode::from_u8(false, 0x2A);
+const CONTROL_LEFT: Scancode = Scancode::from_u8(false, 0x1D);
+const ALT_RIGHT: Scancode = Scancode::from_u8(true, 0x38);
+const KEY_E: Scancode = Scancode::from_u8(false, 0x12);
+const KEY_H: Scancode = Scancode::from_u8(false, 0x23);
+const KEY_I: Scancode = Scancode::from_u8(false, 0x17);
+const QUOTE: Scancode = Scancode::from_u8(false, 0x28);
+const SPACE: Scancode = Scancode::from_u8(false, 0x39);
+
+fn key(scancode: Scancode, shift: bool, altgr: bool) -> KeyPress {
+    KeyPress { scancode, shift, altgr }
+}
+
+#[test]
+fn web_codes() {
+    assert_eq!(Scancode::from_web_code("KeyA"), Some(Scancode::from_u8(false, 0x1E)));
+    assert_eq!(
+        Scancode::from_web_code("NumpadEnter"),
+        Some(Scancode::from_u8(true, 0x1C))
+    );
+    assert_eq!(Scancode::from_web_code("ArrowUp"), Some(Scancode::from_u8(true, 0x48)));
+    assert_eq!(Scancode::from_web_code("Pause"), None);
+    assert_eq!(Scancode::from_web_code("keya"), None);
+
+    // The legacy names are only accepted as input.
+    let meta_left = Scancode::from_web_code("OSLeft").unwrap();
+    assert_eq!(Scancode::from_web_code("MetaLeft"), Some(meta_left));
+    assert_eq!(meta_left.web_code(), Some("MetaLeft"));
+
+    for extended in [false, true] {
+        for code in 0..=u8::MAX {
+            let scancode = Scancode::from_u8(extended, code);
+
+            if let Some(web_code) = scancode.web_code() {
+                assert_eq!(Scancode::from_web_code(web_code), Some(scancode), "{web_code}");
+            }
+        }
+    }
+}
+
+#[test]
+fn set2_scancodes() {
+    assert_eq!(Scancode::from_u8(false, 0x1E).to_set2(), Some((false, 0x1C)));
+    assert_eq!(Scancode::from_u8(false, 0x41).to_set2(), Some((false, 0x83)));
+    assert_eq!(Scancode::from_u8(true, 0x48).to_set2(), Some((true, 0x75)));
+    assert_eq!(Scancode::from_u8(true, 0x5B).to_set2(), Some((true, 0x1F)));
+    assert_eq!(Scancode::from_set2(false, 0x76), Some(Scancode::from_u8(false, 0x01)));
+    assert_eq!(Scancode::from_set2(true, 0x76), None);
+    assert_eq!(Scancode::from_u8(false, 0x00).to_set2(), None);
+
+    let mut mapped = 0;
+
+    for extended in [false, true] {
+        for code in 0..=u8::MAX {
+            let scancode = Scancode::from_u8(extended, code);
+
+            if let Some((set2_extended, set2_code)) = scancode.to_set2() {
+                assert_eq!(Scancode::from_set2(set2_extended, set2_code), Some(scancode));
+                mapped += 1;
+            }
+        }
+    }
+
+    assert_eq!(mapped, 147);
+
+    // All keys with a web code have a set 2 scan code.
+    for extended in [false, true] {
+        for code in 0..=u8::MAX {
+            let scancode = Scancode::from_u8(extended, code);
+
+            if scancode.web_code().is_some() {
+                assert!(scancode.to_set2().is_some(), "{scancode:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn us_layout() {
+    let layout = KeyboardLayout::us();
+    assert_eq!(layout.id(), 0x0409);
+
+    assert_eq!(
+        layout.keystroke('"'),
+        Some(Keystroke {
+            dead_key: None,
+            key: key(QUOTE, true, false),
+        })
+    );
+    assert_eq!(layout.keystroke('\u{E9}'), None);
+
+    assert_eq!(
+        layout.type_text("Hi\r\n\u{E9}"),
+        [
+            Operation::KeyPressed(SHIFT_LEFT),
+            Operation::KeyPressed(KEY_H),
+            Operation::KeyReleased(KEY_H),
+            Operation::KeyReleased(SHIFT_LEFT),
+            Operation::KeyPressed(KEY_I),
+            Operation::KeyReleased(KEY_I),
+            Operation::KeyPressed(Scancode::from_u8(false, 0x1C)),
+            Operation::KeyReleased(Scancode::from_u8(false, 0x1C)),
+            Operation::UnicodeKeyPressed('\u{E9}'),
+            Operation::UnicodeKeyReleased('\u{E9}'),
+        ]
+    );
+}
+
+#[test]
+fn us_international_layout() {
+    let layout = KeyboardLayout::from_id(KeyboardLayout::US_INTERNATIONAL).unwrap();
+
+    // The quote keys are dead keys, followed by a space to type the quotes.
+    assert_eq!(
+        layout.keystroke('\''),
+        Some(Keystroke {
+            dead_key: Some(key(QUOTE, false, false)),
+            key: key(SPACE, false, false),
+        })
+    );
+    assert_eq!(
+        layout.keystroke('"'),
+        Some(Keystroke {
+            dead_key: Some(key(QUOTE, true, false)),
+            key: key(SPACE, false, false),
+        })
+    );
+
+    // Typed with AltGr rather than with the dead key.
+    assert_eq!(
+        layout.keystroke('\u{C9}'),
+        Some(Keystroke {
+            dead_key: None,
+            key: key(KEY_E, true, true),
+        })
+    );
+
+    // ë is only typed with the dead key.
+    assert_eq!(
+        layout.keystroke('\u{EB}'),
+        Some(Keystroke {
+            dead_key: Some(key(QUOTE, true, false)),
+            key: key(KEY_E, false, false),
+        })
+    );
+
+    assert_eq!(
+        layout.type_text("\u{E9}\u{EB}"),
+        [
+            Operation::KeyPressed(CONTROL_LEFT),
+            Operation::KeyPressed(ALT_RIGHT),
+            Operation::KeyPressed(KEY_E),
+            Operation::KeyReleased(KEY_E),
+            Operation::KeyReleased(ALT_RIGHT),
+            Operation::KeyReleased(CONTROL_LEFT),
+            Operation::KeyPressed(SHIFT_LEFT),
+            Operation::KeyPressed(QUOTE),
+            Operation::KeyReleased(QUOTE),
+            Operation::KeyReleased(SHIFT_LEFT),
+            Operation::KeyPressed(KEY_E),
+            Operation::KeyReleased(KEY_E),
+        ]
+    );
+
+    assert!(KeyboardLayout::from_id(0x0000_040C).is_none());
+}
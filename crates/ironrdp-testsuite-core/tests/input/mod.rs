@@ -1,2 +1,6 @@
+mod batch;
 mod fastpath_packets;
+mod hotkeys;
+mod ime;
+mod keyboard;
 mod smoke;
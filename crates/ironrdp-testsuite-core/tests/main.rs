@@ -12,18 +12,28 @@
 //! Cargo will run all tests from a single binary in parallel, but
 //! binaries themselves are run sequentially.
 
+mod accessibility;
+mod audioinput;
 mod clipboard;
+mod cursor;
 mod displaycontrol;
 mod dvc;
 mod egfx;
 mod fuzz_regression;
 mod graphics;
+mod harness;
 mod input;
 mod pcb;
 mod pdu;
 mod propertyset;
+mod rail;
 mod rdcleanpath;
+mod rdpdr;
+mod rdpecam;
+mod rdpeusb;
+mod rdpevor;
 mod rdpsnd;
 mod server;
 mod server_name;
 mod session;
+mod wire_compat;
@@ -0,0 +1,172 @@
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_pdu::rdp::autodetect::{AutoDetectPhase, AutoDetectRequest, AutoDetectResponse, BandwidthPhase};
+use ironrdp_testsuite_core::encode_decode_test;
+
+encode_decode_test! {
+    rtt_request_connect_time:
+        AutoDetectRequest::RttRequest {
+            sequence_number: 1,
+            phase: AutoDetectPhase::Connection,
+        },
+        [
+            0x00, 0x10, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_REQ
+            0x06, // headerLength
+            0x00, // headerTypeId: TYPE_ID_AUTODETECT_REQUEST
+            0x01, 0x00, // sequenceNumber
+            0x01, 0x10, // requestType
+        ];
+    bandwidth_start_continuous:
+        AutoDetectRequest::BandwidthStart {
+            sequence_number: 2,
+            phase: BandwidthPhase::Session,
+        },
+        [
+            0x00, 0x10, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_REQ
+            0x06, 0x00, 0x02, 0x00, // headerLength, headerTypeId, sequenceNumber
+            0x14, 0x00, // requestType
+        ];
+    bandwidth_payload:
+        AutoDetectRequest::BandwidthPayload {
+            sequence_number: 3,
+            payload: vec![0xAA, 0xBB, 0xCC],
+        },
+        [
+            0x00, 0x10, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_REQ
+            0x08, 0x00, 0x03, 0x00, // headerLength, headerTypeId, sequenceNumber
+            0x02, 0x00, // requestType
+            0x03, 0x00, // payloadLength
+            0xAA, 0xBB, 0xCC, // payload
+        ];
+    bandwidth_stop_connect_time:
+        AutoDetectRequest::BandwidthStop {
+            sequence_number: 4,
+            phase: BandwidthPhase::Connection,
+            payload: vec![0x11],
+        },
+        [
+            0x00, 0x10, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_REQ
+            0x08, 0x00, 0x04, 0x00, // headerLength, headerTypeId, sequenceNumber
+            0x2B, 0x00, // requestType
+            0x01, 0x00, // payloadLength
+            0x11, // payload
+        ];
+    bandwidth_stop_tunnel:
+        AutoDetectRequest::BandwidthStop {
+            sequence_number: 5,
+            phase: BandwidthPhase::Tunnel,
+            payload: Vec::new(),
+        },
+        [
+            0x00, 0x10, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_REQ
+            0x06, 0x00, 0x05, 0x00, // headerLength, headerTypeId, sequenceNumber
+            0x29, 0x06, // requestType
+        ];
+    network_characteristics_result:
+        AutoDetectRequest::NetworkCharacteristicsResult {
+            sequence_number: 6,
+            base_rtt: Some(10),
+            bandwidth: Some(20_000),
+            average_rtt: 15,
+        },
+        [
+            0x00, 0x10, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_REQ
+            0x12, 0x00, 0x06, 0x00, // headerLength, headerTypeId, sequenceNumber
+            0xC0, 0x08, // requestType
+            0x0A, 0x00, 0x00, 0x00, // baseRTT
+            0x20, 0x4E, 0x00, 0x00, // bandwidth
+            0x0F, 0x00, 0x00, 0x00, // averageRTT
+        ];
+    network_characteristics_result_without_base_rtt:
+        AutoDetectRequest::NetworkCharacteristicsResult {
+            sequence_number: 7,
+            base_rtt: None,
+            bandwidth: Some(20_000),
+            average_rtt: 15,
+        },
+        [
+            0x00, 0x10, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_REQ
+            0x0E, 0x00, 0x07, 0x00, // headerLength, headerTypeId, sequenceNumber
+            0x80, 0x08, // requestType
+            0x20, 0x4E, 0x00, 0x00, // bandwidth
+            0x0F, 0x00, 0x00, 0x00, // averageRTT
+        ];
+    rtt_response:
+        AutoDetectResponse::RttResponse { sequence_number: 1 },
+        [
+            0x00, 0x20, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_RSP
+            0x06, // headerLength
+            0x01, // headerTypeId: TYPE_ID_AUTODETECT_RESPONSE
+            0x01, 0x00, // sequenceNumber
+            0x00, 0x00, // responseType
+        ];
+    bandwidth_results:
+        AutoDetectResponse::BandwidthResults {
+            sequence_number: 4,
+            phase: AutoDetectPhase::Connection,
+            time_delta: 25,
+            byte_count: 0x0001_0000,
+        },
+        [
+            0x00, 0x20, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_RSP
+            0x0E, 0x01, 0x04, 0x00, // headerLength, headerTypeId, sequenceNumber
+            0x03, 0x00, // responseType
+            0x19, 0x00, 0x00, 0x00, // timeDelta
+            0x00, 0x00, 0x01, 0x00, // byteCount
+        ];
+    network_characteristics_sync:
+        AutoDetectResponse::NetworkCharacteristicsSync {
+            sequence_number: 0,
+            bandwidth: 20_000,
+            rtt: 15,
+        },
+        [
+            0x00, 0x20, 0x00, 0x00, // securityHeader: SEC_AUTODETECT_RSP
+            0x0E, 0x01, 0x00, 0x00, // headerLength, headerTypeId, sequenceNumber
+            0x18, 0x00, // responseType
+            0x20, 0x4E, 0x00, 0x00, // bandwidth
+            0x0F, 0x00, 0x00, 0x00, // rtt
+        ];
+}
+
+#[test]
+fn network_characteristics_result_needs_a_measure() {
+    let result = AutoDetectRequest::NetworkCharacteristicsResult {
+        sequence_number: 0,
+        base_rtt: None,
+        bandwidth: None,
+        average_rtt: 15,
+    };
+
+    encode_vec(&result).unwrap_err();
+}
+
+#[test]
+fn continuous_bandwidth_stop_has_no_payload() {
+    let stop = AutoDetectRequest::BandwidthStop {
+        sequence_number: 0,
+        phase: BandwidthPhase::Session,
+        payload: vec![0x11],
+    };
+
+    encode_vec(&stop).unwrap_err();
+}
+
+#[test]
+fn response_is_not_a_request() {
+    let buffer = [0x00, 0x20, 0x00, 0x00, 0x06, 0x01, 0x01, 0x00, 0x00, 0x00];
+
+    decode::<AutoDetectRequest>(&buffer).unwrap_err();
+    assert_eq!(
+        decode::<AutoDetectResponse>(&buffer).unwrap(),
+        AutoDetectResponse::RttResponse { sequence_number: 1 }
+    );
+}
+
+#[test]
+fn truncated_payload_is_rejected() {
+    let buffer = [
+        0x00, 0x10, 0x00, 0x00, 0x08, 0x00, 0x03, 0x00, 0x02, 0x00, 0x04, 0x00, 0xAA,
+    ];
+
+    decode::<AutoDetectRequest>(&buffer).unwrap_err();
+}
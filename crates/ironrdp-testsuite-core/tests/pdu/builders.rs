@@ -0,0 +1,199 @@
+use ironrdp_core::{decode, encode_vec, EncodeError, EncodeErrorKind};
+use ironrdp_pdu::fast_path::{FastPathUpdatePdu, FastPathUpdatePduBuilder, Fragmentation, UpdateCode};
+use ironrdp_pdu::gcc::{
+    ChannelOptions, ClientCoreData, ClientCoreDataBuilder, ClientGccBlocks, ClientGccBlocksBuilder, ConnectionType,
+    HighColorDepth, Monitor, MonitorFlags,
+};
+use ironrdp_pdu::rdp::capability_sets::{
+    Bitmap, BitmapDrawingFlags, CapabilitySet, DemandActive, DemandActiveBuilder, General,
+};
+
+fn invalid_field(err: &EncodeError) -> &'static str {
+    match err.kind() {
+        EncodeErrorKind::InvalidField { field, .. } => field,
+        kind => panic!("unexpected error kind: {kind:?}"),
+    }
+}
+
+fn monitor(left: i32, flags: MonitorFlags) -> Monitor {
+    Monitor {
+        left,
+        top: 0,
+        right: left + 1023,
+        bottom: 767,
+        flags,
+    }
+}
+
+#[test]
+fn client_core_data_builder_round_trips() {
+    let core = ClientCoreDataBuilder::new()
+        .desktop_size(1280, 1024)
+        .client_name("builder")
+        .connection_type(ConnectionType::Lan)
+        .build()
+        .unwrap();
+
+    let buffer = encode_vec(&core).unwrap();
+    assert_eq!(core, decode::<ClientCoreData>(&buffer).unwrap());
+}
+
+#[test]
+fn client_core_data_builder_fills_preceding_optional_fields() {
+    let core = ClientCoreDataBuilder::new()
+        .high_color_depth(HighColorDepth::Bpp16)
+        .build()
+        .unwrap();
+
+    let optional = &core.optional_data;
+    assert!(optional.post_beta2_color_depth.is_some());
+    assert!(optional.client_product_id.is_some());
+    assert!(optional.serial_number.is_some());
+    assert_eq!(optional.high_color_depth, Some(HighColorDepth::Bpp16));
+    assert!(optional.supported_color_depths.is_none());
+}
+
+#[test]
+fn client_core_data_builder_rejects_out_of_range_fields() {
+    let err = ClientCoreDataBuilder::new().desktop_size(0, 768).build().unwrap_err();
+    assert_eq!(invalid_field(&err), "desktopWidth");
+
+    let err = ClientCoreDataBuilder::new()
+        .desktop_size(1024, 8193)
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "desktopHeight");
+
+    let err = ClientCoreDataBuilder::new()
+        .client_name("a-very-long-client-name")
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "clientName");
+
+    let err = ClientCoreDataBuilder::new()
+        .desktop_orientation(45)
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "desktopOrientation");
+
+    let err = ClientCoreDataBuilder::new()
+        .scale_factors(100, 120)
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "deviceScaleFactor");
+}
+
+#[test]
+fn client_gcc_blocks_builder_round_trips() {
+    let core = ClientCoreDataBuilder::new().build().unwrap();
+    let blocks = ClientGccBlocksBuilder::new(core)
+        .channel("rdpdr", ChannelOptions::INITIALIZED)
+        .channel("cliprdr", ChannelOptions::INITIALIZED)
+        .monitor(monitor(0, MonitorFlags::PRIMARY))
+        .monitor(monitor(1024, MonitorFlags::empty()))
+        .build()
+        .unwrap();
+
+    assert_eq!(blocks.network.as_ref().unwrap().channels.len(), 2);
+
+    let buffer = encode_vec(&blocks).unwrap();
+    assert_eq!(blocks, decode::<ClientGccBlocks>(&buffer).unwrap());
+}
+
+#[test]
+fn client_gcc_blocks_builder_rejects_invalid_channels_and_monitors() {
+    let core = ClientCoreDataBuilder::new().build().unwrap();
+
+    let err = ClientGccBlocksBuilder::new(core.clone())
+        .channel("too_long_name", ChannelOptions::INITIALIZED)
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "channelDefArray");
+
+    let err = ClientGccBlocksBuilder::new(core)
+        .monitor(monitor(0, MonitorFlags::empty()))
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "monitorDefArray");
+}
+
+#[test]
+fn demand_active_builder_round_trips() {
+    let pdu = DemandActiveBuilder::new("RDP")
+        .capability(CapabilitySet::General(General::default()))
+        .capability(CapabilitySet::Bitmap(Bitmap {
+            pref_bits_per_pix: 32,
+            desktop_width: 1920,
+            desktop_height: 1080,
+            desktop_resize_flag: true,
+            drawing_flags: BitmapDrawingFlags::ALLOW_SKIP_ALPHA,
+        }))
+        .build()
+        .unwrap();
+
+    let buffer = encode_vec(&pdu).unwrap();
+    assert_eq!(pdu, decode::<DemandActive>(&buffer).unwrap());
+}
+
+#[test]
+fn demand_active_builder_rejects_invalid_capabilities() {
+    let err = DemandActiveBuilder::new("RDP")
+        .capabilities([
+            CapabilitySet::General(General::default()),
+            CapabilitySet::General(General::default()),
+        ])
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "capabilitySets");
+
+    let err = DemandActiveBuilder::new("RDP")
+        .capability(CapabilitySet::Bitmap(Bitmap {
+            pref_bits_per_pix: 12,
+            desktop_width: 1024,
+            desktop_height: 768,
+            desktop_resize_flag: false,
+            drawing_flags: BitmapDrawingFlags::empty(),
+        }))
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "preferredBitsPerPixel");
+}
+
+#[test]
+fn fast_path_update_builder_fragments_data() {
+    let data: Vec<u8> = (0..=255).collect();
+
+    let fragments = FastPathUpdatePduBuilder::new(UpdateCode::Bitmap, &data)
+        .build_fragments(100)
+        .unwrap();
+
+    let fragmentation: Vec<_> = fragments.iter().map(|pdu| pdu.fragmentation).collect();
+    assert_eq!(
+        fragmentation,
+        [Fragmentation::First, Fragmentation::Next, Fragmentation::Last]
+    );
+
+    let mut reassembled = Vec::new();
+    for fragment in &fragments {
+        let buffer = encode_vec(fragment).unwrap();
+        let decoded = decode::<FastPathUpdatePdu<'_>>(&buffer).unwrap();
+        assert_eq!(decoded.update_code, UpdateCode::Bitmap);
+        reassembled.extend_from_slice(decoded.data);
+    }
+    assert_eq!(reassembled, data);
+}
+
+#[test]
+fn fast_path_update_builder_rejects_oversized_data() {
+    let data = vec![0; 70_000];
+
+    let err = FastPathUpdatePduBuilder::new(UpdateCode::Bitmap, &data)
+        .build()
+        .unwrap_err();
+    assert_eq!(invalid_field(&err), "size");
+
+    let fragments = FastPathUpdatePduBuilder::new(UpdateCode::Bitmap, &data)
+        .build_fragments(usize::from(u16::MAX))
+        .unwrap();
+    assert_eq!(fragments.len(), 2);
+}
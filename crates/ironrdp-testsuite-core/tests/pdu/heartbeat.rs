@@ -0,0 +1,26 @@
+use ironrdp_core::decode;
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+use ironrdp_testsuite_core::encode_decode_test;
+
+encode_decode_test! {
+    heartbeat:
+        HeartbeatPdu {
+            period: 5,
+            warning_count: 2,
+            reconnect_count: 6,
+        },
+        [
+            0x00, 0x40, 0x00, 0x00, // securityHeader: SEC_HEARTBEAT
+            0x00, // reserved
+            0x05, // period
+            0x02, // count1
+            0x06, // count2
+        ];
+}
+
+#[test]
+fn heartbeat_without_heartbeat_flag_is_rejected() {
+    let buffer = [0x00, 0x10, 0x00, 0x00, 0x00, 0x05, 0x02, 0x06];
+
+    decode::<HeartbeatPdu>(&buffer).unwrap_err();
+}
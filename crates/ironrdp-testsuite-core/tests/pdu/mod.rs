@@ -1,12 +1,19 @@
+mod autodetect;
+mod builders;
 mod gcc;
 mod gfx;
+mod heartbeat;
 mod input;
 mod mcs;
+mod multitransport;
 #[expect(
     clippy::needless_raw_strings,
     reason = "the lint is disable to not interfere with expect! macro"
 )]
 mod pointer;
+mod progressive;
 mod rdp;
+mod rdstls;
 mod rfx;
+mod server_redirection;
 mod x224;
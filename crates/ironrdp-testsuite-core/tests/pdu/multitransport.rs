@@ -0,0 +1,49 @@
+use ironrdp_core::decode;
+use ironrdp_pdu::rdp::multitransport::{
+    InitiateMultitransportRequest, InitiateMultitransportResponse, MultitransportProtocol, MultitransportResult,
+};
+use ironrdp_testsuite_core::encode_decode_test;
+
+const COOKIE: [u8; 16] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+];
+
+encode_decode_test! {
+    initiate_multitransport_request:
+        InitiateMultitransportRequest {
+            request_id: 2,
+            requested_protocol: MultitransportProtocol::UdpFecReliable,
+            security_cookie: COOKIE,
+        },
+        [
+            0x02, 0x00, 0x00, 0x00, // securityHeader: SEC_TRANSPORT_REQ
+            0x02, 0x00, 0x00, 0x00, // requestId
+            0x01, 0x00, // requestedProtocol: INITITATE_REQUEST_PROTOCOL_UDPFECR
+            0x00, 0x00, // reserved
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, // securityCookie
+        ];
+    initiate_multitransport_response:
+        InitiateMultitransportResponse::abort(2),
+        [
+            0x04, 0x00, 0x00, 0x00, // securityHeader: SEC_TRANSPORT_RSP
+            0x02, 0x00, 0x00, 0x00, // requestId
+            0x04, 0x40, 0x00, 0x80, // hrResponse: E_ABORT
+        ];
+}
+
+#[test]
+fn request_without_transport_flag_is_rejected() {
+    let mut buffer = [0; 28];
+    buffer[0] = 0x40; // SEC_INFO_PKT
+    buffer[8] = 0x04;
+
+    decode::<InitiateMultitransportRequest>(&buffer).unwrap_err();
+}
+
+#[test]
+fn response_with_unknown_result_is_rejected() {
+    let buffer = [0x04, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x05, 0x40, 0x00, 0x80];
+
+    decode::<InitiateMultitransportResponse>(&buffer).unwrap_err();
+    assert_eq!(MultitransportResult::from_u32(0), Some(MultitransportResult::Success));
+}
@@ -1,7 +1,7 @@
 use std::io::Cursor;
 
 use expect_test::expect;
-use ironrdp_graphics::pointer::{DecodedPointer, PointerBitmapTarget};
+use ironrdp_graphics::pointer::{composite_pixel, DecodedPointer, PointerBitmapTarget};
 use ironrdp_pdu::pointer::{
     CachedPointerAttribute, ColorPointerAttribute, LargePointerAttribute, Point16, PointerAttribute,
     PointerPositionAttribute,
@@ -185,6 +185,149 @@ fn color_pointer_16bpp() {
     expect_pointer_png(&decoded, "pdu/pointer/color_pointer_16bpp.png");
 }
 
+fn decode_pointer(
+    xor_bpp: u16,
+    (width, height): (u16, u16),
+    xor_mask: &[u8],
+    and_mask: &[u8],
+    target: PointerBitmapTarget,
+) -> DecodedPointer {
+    let value = PointerAttribute {
+        xor_bpp,
+        color_pointer: ColorPointerAttribute {
+            cache_index: 0,
+            hot_spot: Point16 { x: 0, y: 0 },
+            width,
+            height,
+            xor_mask,
+            and_mask,
+        },
+    };
+
+    DecodedPointer::decode_pointer_attribute(&value, target).unwrap()
+}
+
+#[test]
+fn pointer_32bpp_alpha_is_premultiplied() {
+    // BGRA pixels: half-transparent red, opaque green
+    const XOR_MASK_32BPP: &[u8] = &[0x00, 0x00, 0xFF, 0x80, 0x00, 0xFF, 0x00, 0xFF];
+
+    let decoded = decode_pointer(32, (2, 1), XOR_MASK_32BPP, &[], PointerBitmapTarget::Software);
+    assert_eq!(decoded.bitmap_data, [0x80, 0x00, 0x00, 0x80, 0x00, 0xFF, 0x00, 0xFF]);
+    assert!(!decoded.inverted);
+
+    let decoded = decode_pointer(32, (2, 1), XOR_MASK_32BPP, &[], PointerBitmapTarget::Accelerated);
+    assert_eq!(decoded.bitmap_data, [0xFF, 0x00, 0x00, 0x80, 0x00, 0xFF, 0x00, 0xFF]);
+}
+
+#[test]
+fn pointer_32bpp_without_alpha_is_opaque() {
+    // The alpha channel is zero everywhere, only the and mask makes the second pixel transparent
+    const AND_MASK_32BPP: &[u8] = &[0b01000000, 0b00000000];
+    const XOR_MASK_32BPP: &[u8] = &[0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+    let decoded = decode_pointer(
+        32,
+        (2, 1),
+        XOR_MASK_32BPP,
+        AND_MASK_32BPP,
+        PointerBitmapTarget::Software,
+    );
+    assert_eq!(decoded.bitmap_data, [0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn pointer_xor_pixels() {
+    // White inverts the screen, other colors are XORed with it
+    const AND_MASK_24BPP: &[u8] = &[0b11000000, 0b00000000];
+    const XOR_MASK_24BPP: &[u8] = &[0xFF, 0xFF, 0xFF, 0x00, 0x00, 0xFF];
+
+    let decoded = decode_pointer(
+        24,
+        (2, 1),
+        XOR_MASK_24BPP,
+        AND_MASK_24BPP,
+        PointerBitmapTarget::Software,
+    );
+    assert_eq!(decoded.bitmap_data, [0xFF, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00]);
+    assert!(decoded.inverted);
+
+    let screen = [0x12, 0x34, 0x56, 0xFF];
+    assert_eq!(
+        composite_pixel([0xFF, 0xFF, 0xFF, 0x00], screen),
+        [0xED, 0xCB, 0xA9, 0xFF]
+    );
+    assert_eq!(
+        composite_pixel([0xFF, 0x00, 0x00, 0x00], screen),
+        [0xED, 0x34, 0x56, 0xFF]
+    );
+
+    let decoded = decode_pointer(
+        24,
+        (2, 1),
+        XOR_MASK_24BPP,
+        AND_MASK_24BPP,
+        PointerBitmapTarget::Accelerated,
+    );
+    assert_eq!(decoded.bitmap_data, [0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0xFF]);
+    assert!(decoded.inverted);
+}
+
+#[test]
+fn composite_pointer_pixel() {
+    let screen = [0x12, 0x34, 0x56, 0xFF];
+
+    // Transparent, opaque and half-transparent premultiplied pixels
+    assert_eq!(composite_pixel([0x00, 0x00, 0x00, 0x00], screen), screen);
+    assert_eq!(
+        composite_pixel([0x01, 0x02, 0x03, 0xFF], screen),
+        [0x01, 0x02, 0x03, 0xFF]
+    );
+    assert_eq!(
+        composite_pixel([0x80, 0x00, 0x00, 0x80], screen),
+        [0x89, 0x1A, 0x2B, 0xFF]
+    );
+}
+
+#[test]
+fn monochrome_pointer_17x3() {
+    // Rows are stored top to bottom, 17 bits padded to 4 bytes
+    const AND_MASK_1BPP: &[u8] = &[
+        0xFF, 0xFF, 0x80, 0x00, // transparent and inverted pixels
+        0x00, 0x00, 0x00, 0x00, // black and white pixels
+        0xFF, 0xFF, 0x00, 0x00, // last pixel is opaque
+    ];
+    const XOR_MASK_1BPP: &[u8] = &[
+        0x00, 0x00, 0x80, 0x00, //
+        0xAA, 0xAA, 0x80, 0x00, //
+        0x00, 0x00, 0x80, 0x00, //
+    ];
+
+    let decoded = decode_pointer(1, (17, 3), XOR_MASK_1BPP, AND_MASK_1BPP, PointerBitmapTarget::Software);
+    let pixels: Vec<[u8; 4]> = decoded
+        .bitmap_data
+        .chunks_exact(4)
+        .map(|pixel| pixel.try_into().unwrap())
+        .collect();
+
+    const TRANSPARENT: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
+    const INVERTED: [u8; 4] = [0xFF, 0xFF, 0xFF, 0x00];
+    const BLACK: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+    const WHITE: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+
+    assert_eq!(pixels.len(), 17 * 3);
+    assert!(pixels[..16].iter().all(|pixel| *pixel == TRANSPARENT));
+    assert_eq!(pixels[16], INVERTED);
+    assert!(pixels[17..33]
+        .iter()
+        .enumerate()
+        .all(|(idx, pixel)| *pixel == if idx % 2 == 0 { WHITE } else { BLACK }));
+    assert_eq!(pixels[33], WHITE);
+    assert!(pixels[34..50].iter().all(|pixel| *pixel == TRANSPARENT));
+    assert_eq!(pixels[50], WHITE);
+    assert!(decoded.inverted);
+}
+
 #[test]
 fn cached_pointer() {
     let value = CachedPointerAttribute { cache_index: 42 };
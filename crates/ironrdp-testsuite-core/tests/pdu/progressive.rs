@@ -0,0 +1,198 @@
+use std::sync::LazyLock;
+
+use ironrdp_pdu::codecs::progressive::*;
+use ironrdp_pdu::codecs::rfx::RfxRectangle;
+use ironrdp_pdu::decode;
+use ironrdp_testsuite_core::encode_decode_test;
+
+const SYNC_PDU_BUFFER: [u8; 12] = [
+    0xc0, 0xcc, // RFX_PROGRESSIVE_SYNC::blockType = WBT_SYNC
+    0x0c, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_SYNC::blockLen = 12
+    0xca, 0xac, 0xcc, 0xca, // RFX_PROGRESSIVE_SYNC::magic = 0xCACCACCA
+    0x00, 0x01, // RFX_PROGRESSIVE_SYNC::version = 0x0100
+];
+
+const SYNC_PDU_BUFFER_WITH_INVALID_MAGIC: [u8; 12] = [
+    0xc0, 0xcc, // RFX_PROGRESSIVE_SYNC::blockType = WBT_SYNC
+    0x0c, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_SYNC::blockLen = 12
+    0xca, 0xac, 0xcc, 0xcb, // RFX_PROGRESSIVE_SYNC::magic = 0xCBCCACCA
+    0x00, 0x01, // RFX_PROGRESSIVE_SYNC::version = 0x0100
+];
+
+const FRAME_BEGIN_PDU_BUFFER: [u8; 12] = [
+    0xc1, 0xcc, // RFX_PROGRESSIVE_FRAME_BEGIN::blockType = WBT_FRAME_BEGIN
+    0x0c, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_FRAME_BEGIN::blockLen = 12
+    0x05, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_FRAME_BEGIN::frameIndex = 5
+    0x01, 0x00, // RFX_PROGRESSIVE_FRAME_BEGIN::regionCount = 1
+];
+
+const FRAME_END_PDU_BUFFER: [u8; 6] = [
+    0xc2, 0xcc, // RFX_PROGRESSIVE_FRAME_END::blockType = WBT_FRAME_END
+    0x06, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_FRAME_END::blockLen = 6
+];
+
+const CONTEXT_PDU_BUFFER: [u8; 10] = [
+    0xc3, 0xcc, // RFX_PROGRESSIVE_CONTEXT::blockType = WBT_CONTEXT
+    0x0a, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_CONTEXT::blockLen = 10
+    0x00, // RFX_PROGRESSIVE_CONTEXT::ctxId = 0
+    0x40, 0x00, // RFX_PROGRESSIVE_CONTEXT::tileSize = 64
+    0x01, // RFX_PROGRESSIVE_CONTEXT::flags = RFX_SUBBAND_DIFFING
+];
+
+const CONTEXT_PDU_BUFFER_WITH_INVALID_TILE_SIZE: [u8; 10] = [
+    0xc3, 0xcc, // RFX_PROGRESSIVE_CONTEXT::blockType = WBT_CONTEXT
+    0x0a, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_CONTEXT::blockLen = 10
+    0x00, // RFX_PROGRESSIVE_CONTEXT::ctxId = 0
+    0x20, 0x00, // RFX_PROGRESSIVE_CONTEXT::tileSize = 32
+    0x01, // RFX_PROGRESSIVE_CONTEXT::flags = RFX_SUBBAND_DIFFING
+];
+
+const REGION_PDU_BUFFER: [u8; 101] = [
+    0xc4, 0xcc, // RFX_PROGRESSIVE_REGION::blockType = WBT_REGION
+    0x65, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_REGION::blockLen = 101
+    0x40, // RFX_PROGRESSIVE_REGION::tileSize = 64
+    0x01, 0x00, // RFX_PROGRESSIVE_REGION::numRects = 1
+    0x01, // RFX_PROGRESSIVE_REGION::numQuant = 1
+    0x01, // RFX_PROGRESSIVE_REGION::numProgQuant = 1
+    0x01, // RFX_PROGRESSIVE_REGION::flags = RFX_DWT_REDUCE_EXTRAPOLATE
+    0x02, 0x00, // RFX_PROGRESSIVE_REGION::numTiles = 2
+    0x36, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_REGION::tileDataSize = 54
+    0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x20, 0x00, // RFX_PROGRESSIVE_REGION::rects[0] = (0, 0, 64, 32)
+    0x66, 0x66, 0x77, 0x88, 0x98, // RFX_PROGRESSIVE_REGION::quantVals[0]
+    0x32, // RFX_PROGRESSIVE_CODEC_QUANT::quality = 50
+    0x22, 0x22, 0x22, 0x22, 0x22, // RFX_PROGRESSIVE_CODEC_QUANT::yQuantValues
+    0x33, 0x33, 0x33, 0x33, 0x33, // RFX_PROGRESSIVE_CODEC_QUANT::cbQuantValues
+    0x33, 0x33, 0x33, 0x33, 0x33, // RFX_PROGRESSIVE_CODEC_QUANT::crQuantValues
+    0xc6, 0xcc, // RFX_PROGRESSIVE_TILE_FIRST::blockType = WBT_TILE_PROGRESSIVE_FIRST
+    0x1a, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_TILE_FIRST::blockLen = 26
+    0x00, 0x00, 0x00, // RFX_PROGRESSIVE_TILE_FIRST::quantIdxY/Cb/Cr = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_FIRST::xIdx = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_FIRST::yIdx = 0
+    0x01, // RFX_PROGRESSIVE_TILE_FIRST::flags = RFX_TILE_DIFFERENCE
+    0x00, // RFX_PROGRESSIVE_TILE_FIRST::quality = 0
+    0x02, 0x00, // RFX_PROGRESSIVE_TILE_FIRST::yLen = 2
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_FIRST::cbLen = 1
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_FIRST::crLen = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_FIRST::tailLen = 0
+    0x01, 0x02, // RFX_PROGRESSIVE_TILE_FIRST::yData
+    0x03, // RFX_PROGRESSIVE_TILE_FIRST::cbData
+    0xc7, 0xcc, // RFX_PROGRESSIVE_TILE_UPGRADE::blockType = WBT_TILE_PROGRESSIVE_UPGRADE
+    0x1c, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::blockLen = 28
+    0x00, 0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::quantIdxY/Cb/Cr = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::xIdx = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::yIdx = 0
+    0xff, // RFX_PROGRESSIVE_TILE_UPGRADE::quality = 0xFF
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::ySrlLen = 1
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::yRawLen = 1
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::cbSrlLen = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::cbRawLen = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::crSrlLen = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::crRawLen = 0
+    0xaa, // RFX_PROGRESSIVE_TILE_UPGRADE::ySrlData
+    0xbb, // RFX_PROGRESSIVE_TILE_UPGRADE::yRawData
+];
+
+const SYNC_PDU: Block<'_> = Block::Sync;
+const FRAME_BEGIN_PDU: Block<'_> = Block::FrameBegin(FrameBeginPdu {
+    frame_index: 5,
+    region_count: 1,
+});
+const FRAME_END_PDU: Block<'_> = Block::FrameEnd;
+const CONTEXT_PDU: Block<'_> = Block::Context(ContextPdu {
+    flags: ContextFlags::SUBBAND_DIFFING,
+});
+
+static REGION_PDU: LazyLock<Block<'static>> = LazyLock::new(|| {
+    let prog_quant = |value| ComponentCodecQuant {
+        ll3: value,
+        hl3: value,
+        lh3: value,
+        hh3: value,
+        hl2: value,
+        lh2: value,
+        hh2: value,
+        hl1: value,
+        lh1: value,
+        hh1: value,
+    };
+
+    Block::Region(RegionPdu {
+        rectangles: vec![RfxRectangle {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 32,
+        }],
+        quant_values: vec![ComponentCodecQuant {
+            ll3: 6,
+            hl3: 6,
+            lh3: 6,
+            hh3: 6,
+            hl2: 7,
+            lh2: 7,
+            hh2: 8,
+            hl1: 8,
+            lh1: 8,
+            hh1: 9,
+        }],
+        quant_prog_values: vec![ProgressiveCodecQuant {
+            quality: 50,
+            y: prog_quant(2),
+            cb: prog_quant(3),
+            cr: prog_quant(3),
+        }],
+        flags: RegionFlags::DWT_REDUCE_EXTRAPOLATE,
+        tiles: vec![
+            Tile::First(FirstTile {
+                quant_idx_y: 0,
+                quant_idx_cb: 0,
+                quant_idx_cr: 0,
+                x_idx: 0,
+                y_idx: 0,
+                flags: TileFlags::DIFFERENCE,
+                quality: 0,
+                y_data: &[0x01, 0x02],
+                cb_data: &[0x03],
+                cr_data: &[],
+                tail_data: &[],
+            }),
+            Tile::Upgrade(UpgradeTile {
+                quant_idx_y: 0,
+                quant_idx_cb: 0,
+                quant_idx_cr: 0,
+                x_idx: 0,
+                y_idx: 0,
+                quality: FULL_QUALITY,
+                y_srl_data: &[0xaa],
+                y_raw_data: &[0xbb],
+                cb_srl_data: &[],
+                cb_raw_data: &[],
+                cr_srl_data: &[],
+                cr_raw_data: &[],
+            }),
+        ],
+    })
+});
+
+encode_decode_test! {
+    sync: SYNC_PDU, SYNC_PDU_BUFFER;
+    frame_begin: FRAME_BEGIN_PDU, FRAME_BEGIN_PDU_BUFFER;
+    frame_end: FRAME_END_PDU, FRAME_END_PDU_BUFFER;
+    context: CONTEXT_PDU, CONTEXT_PDU_BUFFER;
+    region: REGION_PDU.clone(), REGION_PDU_BUFFER;
+}
+
+#[test]
+fn decode_returns_error_on_invalid_sync_magic() {
+    decode::<Block<'_>>(SYNC_PDU_BUFFER_WITH_INVALID_MAGIC.as_ref()).unwrap_err();
+}
+
+#[test]
+fn decode_returns_error_on_invalid_context_tile_size() {
+    decode::<Block<'_>>(CONTEXT_PDU_BUFFER_WITH_INVALID_TILE_SIZE.as_ref()).unwrap_err();
+}
+
+#[test]
+fn decode_returns_error_on_truncated_region() {
+    decode::<Block<'_>>(&REGION_PDU_BUFFER[..REGION_PDU_BUFFER.len() - 1]).unwrap_err();
+}
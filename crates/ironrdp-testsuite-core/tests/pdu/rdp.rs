@@ -1,4 +1,6 @@
 use ironrdp_core::{decode, encode_vec, Encode as _};
+use ironrdp_pdu::rdp::client_info::{PerformanceFlags, VisualExperience};
+use ironrdp_pdu::rdp::finalization_messages::{PersistentKeyListFlags, PersistentKeyListPdu};
 use ironrdp_testsuite_core::capsets::*;
 use ironrdp_testsuite_core::client_info::*;
 use ironrdp_testsuite_core::rdp::*;
@@ -447,3 +449,67 @@ fn buffer_length_is_correct_for_client_demand_active() {
 
     assert_eq!(expected_buffer_len, len);
 }
+
+#[test]
+fn persistent_key_list_pdu_round_trip() {
+    let buf = [
+        0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, // numEntriesCache0..4
+        0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, // totalEntriesCache0..4
+        0x01, // bBitMask = PERSIST_FIRST_PDU
+        0x00, 0x00, 0x00, // padding
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // cache 0, key 0
+        0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, // cache 0, key 1
+        0x05, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, // cache 2, key 0
+    ];
+
+    let pdu = PersistentKeyListPdu {
+        total_entries: [2, 0, 3, 0, 0],
+        flags: PersistentKeyListFlags::PERSIST_FIRST_PDU,
+        entries: [
+            vec![0x0000_0002_0000_0001, 0x0000_0004_0000_0003],
+            vec![],
+            vec![0x0000_0006_0000_0005],
+            vec![],
+            vec![],
+        ],
+    };
+
+    assert_eq!(pdu, decode(buf.as_slice()).unwrap());
+    assert_eq!(buf.as_slice(), encode_vec(&pdu).unwrap());
+}
+
+#[test]
+fn visual_experience_from_performance_flags() {
+    let flags = CLIENT_INFO_UNICODE
+        .extra_info
+        .optional_data
+        .performance_flags()
+        .unwrap();
+
+    let experience = VisualExperience::from(flags);
+
+    assert_eq!(
+        experience,
+        VisualExperience {
+            wallpaper: false,
+            full_window_drag: true,
+            menu_animations: true,
+            theming: true,
+            cursor_shadow: true,
+            cursor_blinking: true,
+            font_smoothing: false,
+            desktop_composition: false,
+        }
+    );
+
+    let experience = VisualExperience::from(
+        PerformanceFlags::DISABLE_MENUANIMATIONS
+            | PerformanceFlags::ENABLE_FONT_SMOOTHING
+            | PerformanceFlags::ENABLE_DESKTOP_COMPOSITION,
+    );
+
+    assert!(!experience.menu_animations);
+    assert!(experience.wallpaper);
+    assert!(experience.font_smoothing);
+    assert!(experience.desktop_composition);
+}
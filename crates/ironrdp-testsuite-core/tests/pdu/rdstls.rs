@@ -0,0 +1,100 @@
+use ironrdp_pdu::rdstls::{
+    RdstlsAuthenticationRequest, RdstlsAuthenticationResponse, RdstlsCapabilities, RdstlsPasswordCredentials,
+    RdstlsResultCode, RDSTLS_HINT,
+};
+use ironrdp_pdu::PduHint as _;
+use ironrdp_testsuite_core::encode_decode_test;
+
+const PASSWORD_AUTH_REQUEST: [u8; 30] = [
+    0x01, 0x00, // version
+    0x02, 0x00, // pduType: RDSTLS_TYPE_AUTHREQ
+    0x01, 0x00, // dataType: RDSTLS_DATA_PASSWORD_CREDS
+    0x02, 0x00, // redirectionGuidLength
+    0xAB, 0xCD, // redirectionGuid
+    0x06, 0x00, // userNameLength
+    0x62, 0x00, 0x6F, 0x00, 0x00, 0x00, // userName: "bo"
+    0x04, 0x00, // domainLength
+    0x64, 0x00, 0x00, 0x00, // domain: "d"
+    0x04, 0x00, // passwordLength
+    0x01, 0x02, 0x03, 0x04, // password
+];
+
+fn password_auth_request() -> RdstlsAuthenticationRequest {
+    RdstlsAuthenticationRequest::Password(RdstlsPasswordCredentials {
+        redirection_guid: vec![0xAB, 0xCD],
+        username: "bo".to_owned(),
+        domain: "d".to_owned(),
+        password: vec![0x01, 0x02, 0x03, 0x04],
+    })
+}
+
+encode_decode_test! {
+    rdstls_capabilities:
+        RdstlsCapabilities::default(),
+        [
+            0x01, 0x00, // version
+            0x01, 0x00, // pduType: RDSTLS_TYPE_CAPABILITIES
+            0x01, 0x00, // dataType: RDSTLS_DATA_CAPABILITIES
+            0x01, 0x00, // supportedVersions: RDSTLS_VERSION_1
+        ];
+    rdstls_auth_request_password:
+        password_auth_request(),
+        PASSWORD_AUTH_REQUEST;
+    rdstls_auth_request_auto_reconnect_cookie:
+        RdstlsAuthenticationRequest::AutoReconnectCookie {
+            session_id: 0x0102_0304,
+            cookie: vec![0xEE; 3],
+        },
+        [
+            0x01, 0x00, // version
+            0x02, 0x00, // pduType: RDSTLS_TYPE_AUTHREQ
+            0x02, 0x00, // dataType: RDSTLS_DATA_AUTORECONNECT_COOKIE
+            0x04, 0x03, 0x02, 0x01, // sessionId
+            0x03, 0x00, // autoReconnectCookieLength
+            0xEE, 0xEE, 0xEE, // autoReconnectCookie
+        ];
+    rdstls_auth_response_logon_failure:
+        RdstlsAuthenticationResponse {
+            result_code: RdstlsResultCode::LOGON_FAILURE,
+        },
+        [
+            0x01, 0x00, // version
+            0x04, 0x00, // pduType: RDSTLS_TYPE_AUTHRSP
+            0x01, 0x00, // dataType: RDSTLS_DATA_RESULT_CODE
+            0x2E, 0x05, 0x00, 0x00, // resultCode: ERROR_LOGON_FAILURE
+        ];
+}
+
+#[test]
+fn rdstls_hint_finds_auth_request_size() {
+    assert_eq!(RDSTLS_HINT.find_size(&PASSWORD_AUTH_REQUEST).unwrap(), Some((true, 30)));
+
+    for len in 0..PASSWORD_AUTH_REQUEST.len() {
+        assert_eq!(RDSTLS_HINT.find_size(&PASSWORD_AUTH_REQUEST[..len]).unwrap(), None);
+    }
+}
+
+#[test]
+fn rdstls_hint_finds_fixed_size() {
+    assert_eq!(
+        RDSTLS_HINT.find_size(&[0x01, 0x00, 0x04, 0x00, 0x01, 0x00]).unwrap(),
+        Some((true, 10))
+    );
+    assert!(RDSTLS_HINT.find_size(&[0x01, 0x00, 0x08, 0x00, 0x01, 0x00]).is_err());
+}
+
+#[test]
+fn rdstls_password_is_redacted() {
+    let debug = format!("{:?}", password_auth_request());
+    assert!(debug.contains("<redacted>"));
+    assert!(!debug.contains("[1, 2, 3, 4]"));
+}
+
+#[test]
+fn rdstls_result_code_display() {
+    assert_eq!(RdstlsResultCode::ACCOUNT_LOCKED_OUT.to_string(), "account locked out");
+    assert_eq!(
+        RdstlsResultCode::from(0x1234).to_string(),
+        "unknown result code 0x00001234"
+    );
+}
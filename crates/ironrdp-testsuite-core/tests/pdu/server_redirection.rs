@@ -0,0 +1,84 @@
+use ironrdp_core::decode;
+use ironrdp_pdu::rdp::headers::{ShareControlHeader, ShareControlPdu};
+use ironrdp_pdu::rdp::server_redirection::{EnhancedSecurityServerRedirection, RedirectionFlags, ServerRedirectionPdu};
+use ironrdp_testsuite_core::encode_decode_test;
+
+fn redirection() -> ServerRedirectionPdu {
+    ServerRedirectionPdu {
+        session_id: 2,
+        flags: RedirectionFlags::TARGET_NET_ADDRESS
+            | RedirectionFlags::LOAD_BALANCE_INFO
+            | RedirectionFlags::USERNAME
+            | RedirectionFlags::PASSWORD
+            | RedirectionFlags::DONT_STORE_USERNAME,
+        target_net_address: Some("h".to_owned()),
+        load_balance_info: Some(b"lb".to_vec()),
+        username: Some("u".to_owned()),
+        password: Some(vec![0xAA, 0xBB]),
+        ..Default::default()
+    }
+}
+
+const SERVER_REDIRECTION: [u8; 48] = [
+    0x30, 0x00, // totalLength
+    0x1A, 0x00, // pduType: PDUTYPE_SERVER_REDIR_PKT | TS_PROTOCOL_VERSION
+    0xEA, 0x03, // pduSource
+    0x00, 0x00, // pad2Octets
+    0x00, 0x04, // flags: SEC_REDIRECTION_PKT
+    0x28, 0x00, // length
+    0x02, 0x00, 0x00, 0x00, // sessionId
+    0x37, 0x00, 0x00, 0x00, // redirFlags
+    0x04, 0x00, 0x00, 0x00, // targetNetAddressLength
+    0x68, 0x00, 0x00, 0x00, // targetNetAddress: "h"
+    0x02, 0x00, 0x00, 0x00, // loadBalanceInfoLength
+    0x6C, 0x62, // loadBalanceInfo: "lb"
+    0x04, 0x00, 0x00, 0x00, // userNameLength
+    0x75, 0x00, 0x00, 0x00, // userName: "u"
+    0x02, 0x00, 0x00, 0x00, // passwordLength
+    0xAA, 0xBB, // password
+];
+
+encode_decode_test! {
+    server_redirection:
+        ShareControlHeader {
+            share_control_pdu: ShareControlPdu::ServerRedirection(EnhancedSecurityServerRedirection {
+                redirection: redirection(),
+            }),
+            pdu_source: 1002,
+            share_id: 0,
+        },
+        SERVER_REDIRECTION;
+}
+
+#[test]
+fn server_redirection_with_padding() {
+    let mut buffer = SERVER_REDIRECTION.to_vec();
+    // totalLength and length, covering the 8 bytes of padding of the packet and the trailing padding byte
+    buffer[0] = 0x39;
+    buffer[10] = 0x30;
+    buffer.extend_from_slice(&[0; 9]);
+
+    let header = decode::<ShareControlHeader>(&buffer).unwrap();
+    let ShareControlPdu::ServerRedirection(pdu) = header.share_control_pdu else {
+        panic!("unexpected PDU: {:?}", header.share_control_pdu);
+    };
+
+    assert_eq!(pdu.redirection, redirection());
+}
+
+#[test]
+fn server_redirection_target_host() {
+    let mut pdu = redirection();
+    assert_eq!(pdu.target_host(), Some("h"));
+
+    pdu.target_fqdn = Some("target.example.com".to_owned());
+    assert_eq!(pdu.target_host(), Some("target.example.com"));
+
+    pdu.flags |= RedirectionFlags::NO_REDIRECT;
+    assert_eq!(pdu.target_host(), None);
+}
+
+#[test]
+fn server_redirection_password_is_redacted() {
+    assert!(!format!("{:?}", redirection()).contains("170"));
+}
@@ -0,0 +1,163 @@
+use ironrdp_core::{decode, encode_vec, Encode as _};
+use ironrdp_pdu::geometry::ExclusiveRectangle;
+use ironrdp_rail::pdu;
+use ironrdp_testsuite_core::encode_decode_test;
+
+mod window;
+
+encode_decode_test! {
+    handshake: pdu::RailPdu::Handshake(pdu::HandshakePdu { build_number: 7600 }),
+    [
+        0x05, 0x00, 0x08, 0x00, 0xb0, 0x1d, 0x00, 0x00,
+    ];
+    handshake_ex: pdu::RailPdu::HandshakeEx(pdu::HandshakeExPdu {
+        build_number: 7601,
+        flags: pdu::HandshakeExFlags::HIDEF | pdu::HandshakeExFlags::EXTENDED_SPI_SUPPORTED,
+    }),
+    [
+        0x13, 0x00, 0x0c, 0x00, 0xb1, 0x1d, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    ];
+    client_status: pdu::RailPdu::ClientStatus(
+        pdu::ClientStatusFlags::ALLOWLOCALMOVESIZE | pdu::ClientStatusFlags::AUTORECONNECT
+    ),
+    [
+        0x0b, 0x00, 0x08, 0x00, 0x03, 0x00, 0x00, 0x00,
+    ];
+    sys_param_drag_full_windows: pdu::RailPdu::SysParam(pdu::SysParam::DragFullWindows(true)),
+    [
+        0x03, 0x00, 0x09, 0x00, 0x25, 0x00, 0x00, 0x00, 0x01,
+    ];
+    sys_param_work_area: pdu::RailPdu::SysParam(pdu::SysParam::WorkArea(ExclusiveRectangle {
+        left: 0,
+        top: 0,
+        right: 1024,
+        bottom: 768,
+    })),
+    [
+        0x03, 0x00, 0x10, 0x00, 0x2f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x03,
+    ];
+    sys_param_other: pdu::RailPdu::SysParam(pdu::SysParam::Other {
+        param: 0x2007,
+        body: vec![0x02, 0x00, 0x00, 0x00],
+    }),
+    [
+        0x03, 0x00, 0x0c, 0x00, 0x07, 0x20, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+    ];
+    exec: pdu::RailPdu::Exec(pdu::ExecPdu {
+        flags: pdu::ExecFlags::EXPAND_ARGUMENTS,
+        exe_or_file: "notepad".to_owned(),
+        working_dir: String::new(),
+        arguments: "x".to_owned(),
+    }),
+    [
+        0x01, 0x00, 0x1c, 0x00, 0x08, 0x00, 0x0e, 0x00, 0x00, 0x00, 0x02, 0x00,
+        b'n', 0x00, b'o', 0x00, b't', 0x00, b'e', 0x00, b'p', 0x00, b'a', 0x00, b'd', 0x00,
+        b'x', 0x00,
+    ];
+    exec_result: pdu::RailPdu::ExecResult(pdu::ExecResultPdu {
+        flags: pdu::ExecFlags::EXPAND_ARGUMENTS,
+        result: pdu::ExecResult::FILE_NOT_FOUND,
+        raw_result: 2,
+        exe_or_file: "notepad".to_owned(),
+    }),
+    [
+        0x80, 0x00, 0x1e, 0x00, 0x08, 0x00, 0x05, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0e, 0x00,
+        b'n', 0x00, b'o', 0x00, b't', 0x00, b'e', 0x00, b'p', 0x00, b'a', 0x00, b'd', 0x00,
+    ];
+    activate: pdu::RailPdu::Activate(pdu::ActivatePdu {
+        window_id: 0x2a,
+        enabled: 1,
+    }),
+    [
+        0x02, 0x00, 0x09, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x01,
+    ];
+    sys_command: pdu::RailPdu::SysCommand(pdu::SysCommandPdu {
+        window_id: 0x2a,
+        command: pdu::SysCommand::CLOSE,
+    }),
+    [
+        0x04, 0x00, 0x0a, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x60, 0xf0,
+    ];
+    window_move: pdu::RailPdu::WindowMove(pdu::WindowMovePdu {
+        window_id: 0x2a,
+        left: 10,
+        top: 20,
+        right: 810,
+        bottom: 620,
+    }),
+    [
+        0x08, 0x00, 0x10, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x14, 0x00, 0x2a, 0x03, 0x6c, 0x02,
+    ];
+    local_move_size: pdu::RailPdu::LocalMoveSize(pdu::LocalMoveSizePdu {
+        window_id: 0x2a,
+        is_move_size_start: 1,
+        move_size_type: pdu::MoveSizeType::MOVE,
+        x: 100,
+        y: -1,
+    }),
+    [
+        0x09, 0x00, 0x10, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x64, 0x00, 0xff, 0xff,
+    ];
+    min_max_info: pdu::RailPdu::MinMaxInfo(pdu::MinMaxInfoPdu {
+        window_id: 0x2a,
+        max_width: 1920,
+        max_height: 1080,
+        max_pos_x: 0,
+        max_pos_y: 0,
+        min_track_width: 100,
+        min_track_height: 50,
+        max_track_width: 1920,
+        max_track_height: 1080,
+    }),
+    [
+        0x0a, 0x00, 0x18, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x80, 0x07, 0x38, 0x04, 0x00, 0x00, 0x00, 0x00,
+        0x64, 0x00, 0x32, 0x00, 0x80, 0x07, 0x38, 0x04,
+    ];
+    z_order_sync: pdu::RailPdu::ZOrderSync(pdu::ZOrderSyncPdu { window_id_marker: 0x2a }),
+    [
+        0x14, 0x00, 0x08, 0x00, 0x2a, 0x00, 0x00, 0x00,
+    ];
+    rail_capability_set: pdu::RailCapabilitySet {
+        support_level: pdu::RailSupportLevel::SUPPORTED | pdu::RailSupportLevel::HANDSHAKE_EX_SUPPORTED,
+    },
+    [
+        0x81, 0x00, 0x00, 0x00,
+    ];
+    window_list_capability_set: pdu::WindowListCapabilitySet {
+        support_level: pdu::WindowSupportLevel::SUPPORTED_EX,
+        num_icon_caches: 3,
+        num_icon_cache_entries: 12,
+    },
+    [
+        0x02, 0x00, 0x00, 0x00, 0x03, 0x0c, 0x00,
+    ];
+}
+
+#[test]
+fn get_app_id_resp_is_padded() {
+    let pdu = pdu::RailPdu::GetAppIdResp(pdu::GetAppIdRespPdu {
+        window_id: 0x2a,
+        application_id: "Microsoft.WindowsNotepad".to_owned(),
+    });
+
+    let encoded = encode_vec(&pdu).unwrap();
+
+    assert_eq!(encoded.len(), 4 + 4 + 520);
+    assert_eq!(pdu, decode(&encoded).unwrap());
+}
+
+#[test]
+fn get_app_id_resp_too_long() {
+    let pdu = pdu::GetAppIdRespPdu {
+        window_id: 0x2a,
+        application_id: "a".repeat(260),
+    };
+
+    assert!(encode_vec(&pdu).is_err());
+    assert_eq!(pdu.size(), 4 + 520);
+}
+
+#[test]
+fn unknown_order_type() {
+    assert!(decode::<pdu::RailPdu>(&[0x42, 0x00, 0x04, 0x00]).is_err());
+}
@@ -0,0 +1,176 @@
+use ironrdp_pdu::orders::{
+    CachedIconOrder, DesktopOrder, IconInfo, MonitoredDesktop, WindowIconOrder, WindowInfoOrder, WindowOrder,
+};
+use ironrdp_rail::window::{WindowEvent, WindowTree};
+
+fn new_window(window_id: u32, owner_window_id: u32) -> WindowOrder {
+    WindowOrder::Window(WindowInfoOrder {
+        window_id,
+        new: true,
+        owner_window_id: Some(owner_window_id),
+        title: Some(format!("Window {window_id}")),
+        window_offset: Some((10, 20)),
+        window_size: Some((640, 480)),
+        taskbar_button: Some(0),
+        ..WindowInfoOrder::default()
+    })
+}
+
+fn icon(cache_entry: u16, cache_id: u8) -> IconInfo {
+    IconInfo {
+        cache_entry,
+        cache_id,
+        bits_per_pixel: 32,
+        width: 1,
+        height: 1,
+        color_table: Vec::new(),
+        bits_mask: vec![0x00, 0x00, 0x00, 0x00],
+        bits_color: vec![0xff, 0x00, 0x00, 0xff],
+    }
+}
+
+#[test]
+fn window_lifecycle() {
+    let mut tree = WindowTree::new();
+
+    assert_eq!(tree.apply(new_window(1, 0)), [WindowEvent::Created { window_id: 1 }]);
+    assert_eq!(tree.apply(new_window(2, 1)), [WindowEvent::Created { window_id: 2 }]);
+
+    let window = tree.window(1).unwrap();
+    assert_eq!(window.title, "Window 1");
+    assert_eq!(window.owner_id, None);
+    assert_eq!((window.position, window.size), ((10, 20), (640, 480)));
+    assert!(window.taskbar_button);
+    assert_eq!(tree.owned_windows(1).map(|window| window.id).collect::<Vec<_>>(), [2]);
+
+    let moved = WindowOrder::Window(WindowInfoOrder {
+        window_id: 1,
+        window_offset: Some((100, 200)),
+        taskbar_button: Some(1),
+        ..WindowInfoOrder::default()
+    });
+    assert_eq!(
+        tree.apply(moved),
+        [
+            WindowEvent::Moved { window_id: 1 },
+            WindowEvent::Taskbar { window_id: 1 }
+        ]
+    );
+    assert_eq!(tree.window(1).unwrap().position, (100, 200));
+    assert!(!tree.window(1).unwrap().taskbar_button);
+
+    // Unchanged properties don't produce events.
+    let unchanged = WindowOrder::Window(WindowInfoOrder {
+        window_id: 1,
+        title: Some("Window 1".to_owned()),
+        ..WindowInfoOrder::default()
+    });
+    assert_eq!(tree.apply(unchanged), []);
+
+    assert_eq!(
+        tree.apply(WindowOrder::Deleted { window_id: 2 }),
+        [WindowEvent::Destroyed { window_id: 2 }]
+    );
+    assert_eq!(tree.apply(WindowOrder::Deleted { window_id: 2 }), []);
+    assert!(tree.window(2).is_none());
+}
+
+#[test]
+fn window_icons() {
+    let mut tree = WindowTree::new();
+    tree.apply(new_window(1, 0));
+    tree.apply(new_window(2, 0));
+
+    let order = WindowOrder::Icon(WindowIconOrder {
+        window_id: 1,
+        big: true,
+        icon: icon(3, 0),
+    });
+    assert_eq!(
+        tree.apply(order),
+        [WindowEvent::Icon {
+            window_id: 1,
+            big: true
+        }]
+    );
+
+    let cached = WindowOrder::CachedIcon(CachedIconOrder {
+        window_id: 2,
+        big: false,
+        cache_entry: 3,
+        cache_id: 0,
+    });
+    assert_eq!(
+        tree.apply(cached),
+        [WindowEvent::Icon {
+            window_id: 2,
+            big: false
+        }]
+    );
+    assert_eq!(tree.window(2).unwrap().small_icon, tree.window(1).unwrap().big_icon);
+
+    // Icons which are not cached can't be referenced later.
+    let order = WindowOrder::Icon(WindowIconOrder {
+        window_id: 1,
+        big: false,
+        icon: icon(0xffff, 0xff),
+    });
+    tree.apply(order);
+    let unknown = WindowOrder::CachedIcon(CachedIconOrder {
+        window_id: 2,
+        big: true,
+        cache_entry: 0xffff,
+        cache_id: 0xff,
+    });
+    assert_eq!(tree.apply(unknown), []);
+}
+
+#[test]
+fn desktop_synchronization() {
+    let mut tree = WindowTree::new();
+    tree.apply(new_window(1, 0));
+    tree.apply(new_window(2, 0));
+
+    let began = WindowOrder::Desktop(DesktopOrder::Monitored(MonitoredDesktop {
+        hooked: true,
+        arc_began: true,
+        ..MonitoredDesktop::default()
+    }));
+    assert_eq!(tree.apply(began), []);
+
+    assert_eq!(tree.apply(new_window(3, 0)), [WindowEvent::Created { window_id: 3 }]);
+    tree.apply(WindowOrder::Window(WindowInfoOrder {
+        window_id: 2,
+        ..WindowInfoOrder::default()
+    }));
+
+    let completed = WindowOrder::Desktop(DesktopOrder::Monitored(MonitoredDesktop {
+        arc_completed: true,
+        active_window_id: Some(3),
+        z_order: Some(vec![3, 2]),
+        ..MonitoredDesktop::default()
+    }));
+    assert_eq!(
+        tree.apply(completed),
+        [
+            WindowEvent::Destroyed { window_id: 1 },
+            WindowEvent::ZOrder,
+            WindowEvent::Activated { window_id: Some(3) },
+        ]
+    );
+    assert_eq!(tree.z_order(), [3, 2]);
+    assert_eq!(tree.active_window_id(), Some(3));
+
+    // No window of the remote applications is active.
+    let deactivated = WindowOrder::Desktop(DesktopOrder::Monitored(MonitoredDesktop {
+        active_window_id: Some(0xffff_ffff),
+        ..MonitoredDesktop::default()
+    }));
+    assert_eq!(tree.apply(deactivated), [WindowEvent::Activated { window_id: None }]);
+
+    assert_eq!(
+        tree.apply(WindowOrder::Desktop(DesktopOrder::NonMonitored)),
+        [WindowEvent::Unmonitored]
+    );
+    assert_eq!(tree.windows().count(), 0);
+}
@@ -0,0 +1,351 @@
+mod printer;
+mod scard;
+
+use std::sync::Arc;
+
+use ironrdp_core::impl_as_any;
+use ironrdp_pdu::PduResult;
+use ironrdp_rdpdr::pdu::efs::{
+    ClientDriveQueryDirectoryResponse, CreateDisposition, CreateOptions, DesiredAccess, DeviceCloseResponse,
+    DeviceControlRequest, DeviceCreateResponse, DeviceIoResponse, DeviceReadResponse, DeviceWriteResponse,
+    FileAttributes, FileDirectoryInformation, FileInformationClass, Information, NtStatus,
+    ServerDeviceAnnounceResponse, ServerDriveIoRequest,
+};
+use ironrdp_rdpdr::pdu::esc::{ScardCall, ScardIoCtlCode};
+use ironrdp_rdpdr::pdu::RdpdrPdu;
+use ironrdp_rdpdr::server::{
+    DrivePolicy, DriveRequest, DriveResponse, FileSystemBackend, RdpdrServer, RedirectedDrive,
+};
+use ironrdp_rdpdr::{Rdpdr, RdpdrBackend};
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage};
+
+use crate::harness::{exchange_static, Events};
+
+const DRIVE_ID: u32 = 1;
+const FILE_ID: u32 = 7;
+
+type Completions = Events<(u32, Result<DriveResponse, NtStatus>)>;
+
+#[derive(Debug)]
+struct Recorder {
+    completions: Completions,
+    accept: bool,
+}
+
+impl FileSystemBackend for Recorder {
+    fn drive_announced(&mut self, _drive: &RedirectedDrive) -> bool {
+        self.accept
+    }
+
+    fn io_completed(&mut self, device_id: u32, completion_id: u32, result: Result<DriveResponse, NtStatus>) {
+        assert_eq!(device_id, DRIVE_ID);
+        self.completions.lock().unwrap().push((completion_id, result));
+    }
+}
+
+/// Client drive containing a single `\file.txt` file
+#[derive(Debug)]
+struct ClientDrive;
+
+impl_as_any!(ClientDrive);
+
+fn directory_entry() -> FileDirectoryInformation {
+    FileDirectoryInformation::new(
+        0,
+        0,
+        0,
+        0,
+        5,
+        FileAttributes::FILE_ATTRIBUTE_ARCHIVE,
+        "file.txt".to_owned(),
+    )
+}
+
+impl RdpdrBackend for ClientDrive {
+    fn handle_server_device_announce_response(&mut self, _pdu: ServerDeviceAnnounceResponse) -> PduResult<()> {
+        Ok(())
+    }
+
+    fn handle_scard_call(&mut self, _req: DeviceControlRequest<ScardIoCtlCode>, _call: ScardCall) -> PduResult<()> {
+        Ok(())
+    }
+
+    fn handle_drive_io_request(&mut self, req: ServerDriveIoRequest) -> PduResult<Vec<SvcMessage>> {
+        let pdu = match req {
+            ServerDriveIoRequest::ServerCreateDriveRequest(req) => {
+                let (file_id, io_status) = if req.path == "\\file.txt" {
+                    (FILE_ID, NtStatus::SUCCESS)
+                } else {
+                    (0, NtStatus::NO_SUCH_FILE)
+                };
+
+                RdpdrPdu::from(DeviceCreateResponse {
+                    device_io_reply: DeviceIoResponse::new(req.device_io_request, io_status),
+                    file_id,
+                    information: Information::FILE_OPENED,
+                })
+            }
+            ServerDriveIoRequest::DeviceReadRequest(req) => {
+                assert_eq!(req.device_io_request.file_id, FILE_ID);
+                let data = &b"hello"[..usize::try_from(req.length).unwrap()];
+
+                RdpdrPdu::from(DeviceReadResponse {
+                    device_io_reply: DeviceIoResponse::new(req.device_io_request, NtStatus::SUCCESS),
+                    read_data: data.to_vec(),
+                })
+            }
+            ServerDriveIoRequest::DeviceWriteRequest(req) => RdpdrPdu::from(DeviceWriteResponse {
+                device_io_reply: DeviceIoResponse::new(req.device_io_request, NtStatus::SUCCESS),
+                length: u32::try_from(req.write_data.len()).unwrap(),
+            }),
+            ServerDriveIoRequest::ServerDriveQueryDirectoryRequest(req) => {
+                let (io_status, buffer) = if req.initial_query != 0 {
+                    assert_eq!(req.path, "\\*");
+                    (
+                        NtStatus::SUCCESS,
+                        Some(FileInformationClass::Directory(directory_entry())),
+                    )
+                } else {
+                    (NtStatus::NO_MORE_FILES, None)
+                };
+
+                RdpdrPdu::from(ClientDriveQueryDirectoryResponse {
+                    device_io_reply: DeviceIoResponse::new(req.device_io_request, io_status),
+                    buffer,
+                })
+            }
+            ServerDriveIoRequest::DeviceCloseRequest(req) => RdpdrPdu::from(DeviceCloseResponse {
+                device_io_response: DeviceIoResponse::new(req.device_io_request, NtStatus::SUCCESS),
+            }),
+            req => panic!("unexpected request: {req:?}"),
+        };
+
+        Ok(vec![SvcMessage::from(pdu)])
+    }
+}
+
+/// Denies the writes, and the drives when `deny_drives` is set
+#[derive(Debug)]
+struct ReadOnly {
+    deny_drives: bool,
+}
+
+impl DrivePolicy for ReadOnly {
+    fn allow_drive(&self, _drive: &RedirectedDrive) -> bool {
+        !self.deny_drives
+    }
+
+    fn allow_request(&self, _device_id: u32, request: &DriveRequest) -> bool {
+        !matches!(request, DriveRequest::Write { .. })
+    }
+}
+
+fn connect(accept: bool) -> (StaticVirtualChannel, StaticVirtualChannel, Completions) {
+    connect_with(accept, None)
+}
+
+fn connect_with(
+    accept: bool,
+    policy: Option<Arc<dyn DrivePolicy>>,
+) -> (StaticVirtualChannel, StaticVirtualChannel, Completions) {
+    let completions = Completions::default();
+
+    let mut server = RdpdrServer::new(Box::new(Recorder {
+        completions: Arc::clone(&completions),
+        accept,
+    }));
+    if let Some(policy) = policy {
+        server = server.with_drive_policy(policy);
+    }
+    let mut server = StaticVirtualChannel::new(server);
+    let mut client = StaticVirtualChannel::new(
+        Rdpdr::new(Box::new(ClientDrive), "client".to_owned()).with_drives(Some(vec![(DRIVE_ID, "share".to_owned())])),
+    );
+
+    let messages = server.start().unwrap();
+    exchange_static(&mut server, &mut client, messages);
+
+    (server, client, completions)
+}
+
+fn rdpdr_server(channel: &mut StaticVirtualChannel) -> &mut RdpdrServer {
+    channel.channel_processor_downcast_mut().unwrap()
+}
+
+fn request(
+    server: &mut StaticVirtualChannel,
+    client: &mut StaticVirtualChannel,
+    completion_id: u32,
+    request: DriveRequest,
+) {
+    let messages = rdpdr_server(server)
+        .drive_request(DRIVE_ID, completion_id, request)
+        .unwrap();
+    exchange_static(server, client, messages.into());
+}
+
+fn open(path: &str) -> DriveRequest {
+    DriveRequest::Create {
+        path: path.to_owned(),
+        desired_access: DesiredAccess::GENERIC_READ,
+        create_disposition: CreateDisposition::FILE_OPEN,
+        create_options: CreateOptions::empty(),
+    }
+}
+
+#[test]
+fn drive_announced() {
+    let (mut server, _, _) = connect(true);
+
+    let server = rdpdr_server(&mut server);
+    assert_eq!(server.client_name(), Some("client"));
+    assert_eq!(
+        server.drives().collect::<Vec<_>>(),
+        [&RedirectedDrive {
+            device_id: DRIVE_ID,
+            name: "share".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn drive_rejected() {
+    let (mut server, _, _) = connect(false);
+
+    let server = rdpdr_server(&mut server);
+    assert_eq!(server.drives().count(), 0);
+    assert!(server.drive_request(DRIVE_ID, 1, open("\\file.txt")).is_err());
+}
+
+#[test]
+fn file_operations() {
+    let (mut server, mut client, completions) = connect(true);
+
+    request(&mut server, &mut client, 1, open("\\file.txt"));
+    request(&mut server, &mut client, 2, open("\\missing.txt"));
+    request(
+        &mut server,
+        &mut client,
+        3,
+        DriveRequest::Read {
+            file_id: FILE_ID,
+            offset: 0,
+            length: 4,
+        },
+    );
+    request(
+        &mut server,
+        &mut client,
+        4,
+        DriveRequest::Write {
+            file_id: FILE_ID,
+            offset: 0,
+            data: vec![1, 2, 3],
+        },
+    );
+    request(&mut server, &mut client, 5, DriveRequest::Close { file_id: FILE_ID });
+
+    assert_eq!(
+        *completions.lock().unwrap(),
+        [
+            (
+                1,
+                Ok(DriveResponse::Create {
+                    file_id: FILE_ID,
+                    information: Information::FILE_OPENED,
+                })
+            ),
+            (2, Err(NtStatus::NO_SUCH_FILE)),
+            (3, Ok(DriveResponse::Read(b"hell".to_vec()))),
+            (4, Ok(DriveResponse::Write(3))),
+            (5, Ok(DriveResponse::Close)),
+        ]
+    );
+}
+
+#[test]
+fn directory_enumeration() {
+    let (mut server, mut client, completions) = connect(true);
+
+    for (completion_id, initial_query) in [(1, true), (2, false)] {
+        request(
+            &mut server,
+            &mut client,
+            completion_id,
+            DriveRequest::QueryDirectory {
+                file_id: FILE_ID,
+                initial_query,
+                pattern: "\\*".to_owned(),
+            },
+        );
+    }
+
+    assert_eq!(
+        *completions.lock().unwrap(),
+        [
+            (1, Ok(DriveResponse::QueryDirectory(directory_entry()))),
+            (2, Err(NtStatus::NO_MORE_FILES)),
+        ]
+    );
+}
+
+#[test]
+fn pending_completion_id() {
+    let (mut server, _, _) = connect(true);
+
+    let server = rdpdr_server(&mut server);
+    server.drive_request(DRIVE_ID, 1, open("\\file.txt")).unwrap();
+    assert!(server.drive_request(DRIVE_ID, 1, open("\\file.txt")).is_err());
+    assert!(server.drive_request(DRIVE_ID + 1, 2, open("\\file.txt")).is_err());
+}
+
+#[test]
+fn drive_policy() {
+    let (mut server, _, _) = connect_with(true, Some(Arc::new(ReadOnly { deny_drives: true })));
+    assert_eq!(rdpdr_server(&mut server).drives().count(), 0);
+
+    let (mut server, mut client, completions) = connect_with(true, Some(Arc::new(ReadOnly { deny_drives: false })));
+
+    // The denied request is completed without being sent.
+    let messages = rdpdr_server(&mut server)
+        .drive_request(
+            DRIVE_ID,
+            1,
+            DriveRequest::Write {
+                file_id: FILE_ID,
+                offset: 0,
+                data: vec![1, 2, 3],
+            },
+        )
+        .unwrap();
+    assert!(Vec::<SvcMessage>::from(messages).is_empty());
+
+    request(
+        &mut server,
+        &mut client,
+        2,
+        DriveRequest::Read {
+            file_id: FILE_ID,
+            offset: 0,
+            length: 5,
+        },
+    );
+
+    assert_eq!(
+        *completions.lock().unwrap(),
+        [
+            (1, Err(NtStatus::ACCESS_DENIED)),
+            (2, Ok(DriveResponse::Read(b"hello".to_vec()))),
+        ]
+    );
+}
+
+#[test]
+fn request_before_initialization() {
+    let mut server = RdpdrServer::new(Box::new(Recorder {
+        completions: Completions::default(),
+        accept: true,
+    }));
+
+    assert!(server.drive_request(DRIVE_ID, 1, open("\\file.txt")).is_err());
+}
@@ -0,0 +1,171 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::{ReadCursor, WriteCursor};
+use ironrdp_rdpdr::pdu::efs::NtStatus;
+use ironrdp_rdpdr::pdu::epc::{PrinterDeviceAnnounce, PrinterFlags};
+use ironrdp_rdpdr::server::{PrinterHandler, RdpdrServer, RedirectedPrinter};
+use ironrdp_rdpdr::{PrinterBackend, Rdpdr};
+use ironrdp_svc::StaticVirtualChannel;
+
+use super::{rdpdr_server, ClientDrive, Completions, Recorder};
+use crate::harness::{exchange_static, Events};
+
+const PRINTER_ID: u32 = 3;
+const JOB_ID: u32 = 9;
+
+type Jobs = Events<(u32, Result<(), NtStatus>)>;
+
+#[derive(Debug)]
+struct JobRecorder {
+    jobs: Jobs,
+}
+
+impl PrinterHandler for JobRecorder {
+    fn job_completed(&mut self, device_id: u32, completion_id: u32, result: Result<(), NtStatus>) {
+        assert_eq!(device_id, PRINTER_ID);
+        self.jobs.lock().unwrap().push((completion_id, result));
+    }
+}
+
+#[derive(Debug, Default)]
+struct Spooler {
+    data: Vec<u8>,
+    writes: usize,
+    ended: bool,
+    /// Fails the writes with this status
+    failure: Option<NtStatus>,
+}
+
+/// Client printer spooling the jobs in memory
+#[derive(Debug)]
+struct ClientPrinter(Arc<Mutex<Spooler>>);
+
+impl PrinterBackend for ClientPrinter {
+    fn job_started(&mut self, device_id: u32) -> Result<u32, NtStatus> {
+        assert_eq!(device_id, PRINTER_ID);
+        Ok(JOB_ID)
+    }
+
+    fn job_data(&mut self, _device_id: u32, job_id: u32, data: &[u8]) -> Result<(), NtStatus> {
+        assert_eq!(job_id, JOB_ID);
+        let mut spooler = self.0.lock().unwrap();
+        if let Some(failure) = spooler.failure {
+            return Err(failure);
+        }
+
+        spooler.data.extend_from_slice(data);
+        spooler.writes += 1;
+        Ok(())
+    }
+
+    fn job_ended(&mut self, _device_id: u32, job_id: u32) -> Result<(), NtStatus> {
+        assert_eq!(job_id, JOB_ID);
+        self.0.lock().unwrap().ended = true;
+        Ok(())
+    }
+}
+
+fn printer() -> PrinterDeviceAnnounce {
+    let mut printer = PrinterDeviceAnnounce::new("Office Printer".to_owned(), "MS Publisher Imagesetter".to_owned());
+    printer.flags = PrinterFlags::RDPDR_PRINTER_ANNOUNCE_FLAG_DEFAULTPRINTER;
+    printer
+}
+
+fn connect(printer_handler: bool) -> (StaticVirtualChannel, StaticVirtualChannel, Jobs, Arc<Mutex<Spooler>>) {
+    let jobs = Jobs::default();
+    let spooler = Arc::new(Mutex::new(Spooler::default()));
+
+    let mut server = RdpdrServer::new(Box::new(Recorder {
+        completions: Completions::default(),
+        accept: true,
+    }));
+    if printer_handler {
+        server = server.with_printer(Box::new(JobRecorder {
+            jobs: Arc::clone(&jobs),
+        }));
+    }
+
+    let mut server = StaticVirtualChannel::new(server);
+    let mut client = StaticVirtualChannel::new(
+        Rdpdr::new(Box::new(ClientDrive), "client".to_owned())
+            .with_printers(
+                Box::new(ClientPrinter(Arc::clone(&spooler))),
+                vec![(PRINTER_ID, printer())],
+            )
+            .unwrap(),
+    );
+
+    let messages = server.start().unwrap();
+    exchange_static(&mut server, &mut client, messages);
+
+    (server, client, jobs, spooler)
+}
+
+fn print(server: &mut StaticVirtualChannel, client: &mut StaticVirtualChannel, completion_id: u32, data: Vec<u8>) {
+    let messages = rdpdr_server(server).print_job(PRINTER_ID, completion_id, data).unwrap();
+    exchange_static(server, client, messages.into());
+}
+
+#[test]
+fn printer_announce_roundtrip() {
+    let mut printer = printer();
+    printer.flags.insert(PrinterFlags::RDPDR_PRINTER_ANNOUNCE_FLAG_ASCII);
+    printer.cached_fields = vec![1, 2, 3];
+
+    let mut buffer = vec![0; printer.size()];
+    printer.encode(&mut WriteCursor::new(&mut buffer)).unwrap();
+
+    let decoded = PrinterDeviceAnnounce::decode(&mut ReadCursor::new(&buffer)).unwrap();
+    assert_eq!(decoded, printer);
+}
+
+#[test]
+fn printer_announced() {
+    let (mut server, _, _, _) = connect(true);
+
+    let server = rdpdr_server(&mut server);
+    assert_eq!(
+        server.printers().collect::<Vec<_>>(),
+        [&RedirectedPrinter {
+            device_id: PRINTER_ID,
+            name: "Office Printer".to_owned(),
+            driver_name: "MS Publisher Imagesetter".to_owned(),
+            flags: PrinterFlags::RDPDR_PRINTER_ANNOUNCE_FLAG_DEFAULTPRINTER,
+        }]
+    );
+}
+
+#[test]
+fn printer_rejected_without_handler() {
+    let (mut server, _, _, _) = connect(false);
+
+    let server = rdpdr_server(&mut server);
+    assert_eq!(server.printers().count(), 0);
+    assert!(server.print_job(PRINTER_ID, 1, b"%!PS".to_vec()).is_err());
+}
+
+#[test]
+fn print_job_is_written_in_chunks() {
+    let (mut server, mut client, jobs, spooler) = connect(true);
+
+    let data: Vec<u8> = (0..0x18000u32).map(|i| i.to_le_bytes()[0]).collect();
+    print(&mut server, &mut client, 1, data.clone());
+
+    assert_eq!(*jobs.lock().unwrap(), [(1, Ok(()))]);
+
+    let spooler = spooler.lock().unwrap();
+    assert_eq!(spooler.data, data);
+    assert_eq!(spooler.writes, 2);
+    assert!(spooler.ended);
+}
+
+#[test]
+fn failed_print_job_is_closed() {
+    let (mut server, mut client, jobs, spooler) = connect(true);
+    spooler.lock().unwrap().failure = Some(NtStatus::ACCESS_DENIED);
+
+    print(&mut server, &mut client, 1, b"%!PS".to_vec());
+
+    assert_eq!(*jobs.lock().unwrap(), [(1, Err(NtStatus::ACCESS_DENIED))]);
+    assert!(spooler.lock().unwrap().ended);
+}
@@ -0,0 +1,367 @@
+use std::sync::Arc;
+
+use ironrdp_pdu::utils::CharacterSet;
+use ironrdp_rdpdr::pdu::efs::NtStatus;
+use ironrdp_rdpdr::pdu::esc::{
+    CardProtocol, CardState, ConnectCall, ConnectCommon, ConnectReturn, ContextCall, EstablishContextCall,
+    EstablishContextReturn, ListReadersCall, ListReadersReturn, LongReturn, ReaderState, ReaderStateCommonCall,
+    ReturnCode, SCardIORequest, ScardCall, ScardContext, ScardHandle, ScardIoCtlCode, ScardReturn, Scope, StatusCall,
+    StatusReturn, TransmitCall, TransmitReturn,
+};
+use ironrdp_rdpdr::server::{RdpdrServer, SmartCardHandler};
+use ironrdp_rdpdr::{CardStatus, Rdpdr, SmartCardBackend};
+use ironrdp_svc::StaticVirtualChannel;
+
+use super::{rdpdr_server, ClientDrive, Completions, Recorder};
+use crate::harness::{exchange_static, Events};
+
+const SCARD_ID: u32 = 2;
+const CONTEXT: ScardContext = ScardContext { value: 0x10 };
+const READER: &str = "Reader 0";
+const ATR: [u8; 4] = [0x3B, 0x8F, 0x80, 0x01];
+
+type Calls = Events<(u32, ScardIoCtlCode, Result<ScardReturn, NtStatus>)>;
+
+#[derive(Debug)]
+struct CallRecorder {
+    calls: Calls,
+}
+
+impl SmartCardHandler for CallRecorder {
+    fn call_completed(
+        &mut self,
+        device_id: u32,
+        completion_id: u32,
+        io_control_code: ScardIoCtlCode,
+        result: Result<ScardReturn, NtStatus>,
+    ) {
+        assert_eq!(device_id, SCARD_ID);
+        self.calls
+            .lock()
+            .unwrap()
+            .push((completion_id, io_control_code, result));
+    }
+}
+
+/// Client with a single reader, the card replies to the APDUs with `90 00`
+#[derive(Debug)]
+struct FakeCard;
+
+impl SmartCardBackend for FakeCard {
+    fn establish_context(&mut self, scope: Scope) -> Result<ScardContext, ReturnCode> {
+        assert_eq!(scope, Scope::System);
+        Ok(CONTEXT)
+    }
+
+    fn release_context(&mut self, _context: ScardContext) -> Result<(), ReturnCode> {
+        Ok(())
+    }
+
+    fn list_readers(&mut self, context: ScardContext, _groups: &[String]) -> Result<Vec<String>, ReturnCode> {
+        assert_eq!(context, CONTEXT);
+        Ok(vec![READER.to_owned()])
+    }
+
+    fn get_status_change(
+        &mut self,
+        _context: ScardContext,
+        _timeout: u32,
+        _states: &[ReaderState],
+    ) -> Result<Vec<ReaderStateCommonCall>, ReturnCode> {
+        Err(ReturnCode::Timeout)
+    }
+
+    fn connect(
+        &mut self,
+        context: ScardContext,
+        reader: &str,
+        _share_mode: u32,
+        preferred_protocols: CardProtocol,
+    ) -> Result<(ScardHandle, CardProtocol), ReturnCode> {
+        if reader != READER {
+            return Err(ReturnCode::UnknownReader);
+        }
+
+        assert!(preferred_protocols.contains(CardProtocol::SCARD_PROTOCOL_T1));
+        Ok((ScardHandle::new(context, 1), CardProtocol::SCARD_PROTOCOL_T1))
+    }
+
+    fn disconnect(&mut self, _handle: &ScardHandle, _disposition: u32) -> Result<(), ReturnCode> {
+        Ok(())
+    }
+
+    fn begin_transaction(&mut self, _handle: &ScardHandle) -> Result<(), ReturnCode> {
+        Ok(())
+    }
+
+    fn end_transaction(&mut self, _handle: &ScardHandle, _disposition: u32) -> Result<(), ReturnCode> {
+        Ok(())
+    }
+
+    fn transmit(
+        &mut self,
+        _handle: &ScardHandle,
+        send_pci: &SCardIORequest,
+        send_buffer: &[u8],
+    ) -> Result<Vec<u8>, ReturnCode> {
+        assert_eq!(send_pci.protocol, CardProtocol::SCARD_PROTOCOL_T1);
+        assert_eq!(send_buffer, [0x00, 0xA4, 0x04, 0x00]);
+        Ok(vec![0x90, 0x00])
+    }
+
+    fn status(&mut self, _handle: &ScardHandle) -> Result<CardStatus, ReturnCode> {
+        Ok(CardStatus {
+            reader_names: vec![READER.to_owned()],
+            state: CardState::SpecificMode,
+            protocol: CardProtocol::SCARD_PROTOCOL_T1,
+            atr: ATR.to_vec(),
+        })
+    }
+}
+
+fn connect(smartcard: bool) -> (StaticVirtualChannel, StaticVirtualChannel, Calls) {
+    let calls = Calls::default();
+
+    let mut server = RdpdrServer::new(Box::new(Recorder {
+        completions: Completions::default(),
+        accept: true,
+    }));
+    if smartcard {
+        server = server.with_smartcard(Box::new(CallRecorder {
+            calls: Arc::clone(&calls),
+        }));
+    }
+
+    let mut server = StaticVirtualChannel::new(server);
+    let mut client = StaticVirtualChannel::new(
+        Rdpdr::new(Box::new(ClientDrive), "client".to_owned()).with_smartcard_backend(SCARD_ID, Box::new(FakeCard)),
+    );
+
+    let messages = server.start().unwrap();
+    exchange_static(&mut server, &mut client, messages);
+
+    (server, client, calls)
+}
+
+fn call(
+    server: &mut StaticVirtualChannel,
+    client: &mut StaticVirtualChannel,
+    completion_id: u32,
+    io_control_code: ScardIoCtlCode,
+    call: ScardCall,
+) {
+    let messages = rdpdr_server(server)
+        .smartcard_call(SCARD_ID, completion_id, io_control_code, call)
+        .unwrap();
+    exchange_static(server, client, messages.into());
+}
+
+fn handle() -> ScardHandle {
+    ScardHandle::new(CONTEXT, 1)
+}
+
+fn connect_call(reader: &str) -> ScardCall {
+    ScardCall::ConnectCall(ConnectCall {
+        reader: reader.to_owned(),
+        common: ConnectCommon {
+            context: CONTEXT,
+            share_mode: 2, // SCARD_SHARE_SHARED
+            preferred_protocols: CardProtocol::SCARD_PROTOCOL_TX,
+        },
+    })
+}
+
+#[test]
+fn smartcard_announced() {
+    let (mut server, _, _) = connect(true);
+
+    let server = rdpdr_server(&mut server);
+    assert_eq!(server.smartcard_devices().collect::<Vec<_>>(), [SCARD_ID]);
+}
+
+#[test]
+fn smartcard_rejected_without_handler() {
+    let (mut server, _, _) = connect(false);
+
+    let server = rdpdr_server(&mut server);
+    assert_eq!(server.smartcard_devices().count(), 0);
+    assert!(server
+        .smartcard_call(
+            SCARD_ID,
+            1,
+            ScardIoCtlCode::EstablishContext,
+            ScardCall::EstablishContextCall(EstablishContextCall { scope: Scope::System }),
+        )
+        .is_err());
+}
+
+#[test]
+fn smartcard_logon_sequence() {
+    let (mut server, mut client, calls) = connect(true);
+
+    call(
+        &mut server,
+        &mut client,
+        1,
+        ScardIoCtlCode::EstablishContext,
+        ScardCall::EstablishContextCall(EstablishContextCall { scope: Scope::System }),
+    );
+    call(
+        &mut server,
+        &mut client,
+        2,
+        ScardIoCtlCode::ListReadersW,
+        ScardCall::ListReadersCall(ListReadersCall {
+            context: CONTEXT,
+            groups_ptr_length: 0,
+            groups_length: 0,
+            groups_ptr: 0,
+            groups: Vec::new(),
+            readers_is_null: false,
+            readers_size: 0xFFFF_FFFF, // SCARD_AUTOALLOCATE
+        }),
+    );
+    call(
+        &mut server,
+        &mut client,
+        3,
+        ScardIoCtlCode::ConnectW,
+        connect_call(READER),
+    );
+    call(
+        &mut server,
+        &mut client,
+        4,
+        ScardIoCtlCode::Transmit,
+        ScardCall::TransmitCall(TransmitCall {
+            handle: handle(),
+            send_pci: SCardIORequest {
+                protocol: CardProtocol::SCARD_PROTOCOL_T1,
+                extra_bytes_length: 0,
+                extra_bytes: Vec::new(),
+            },
+            send_length: 4,
+            send_buffer: vec![0x00, 0xA4, 0x04, 0x00],
+            recv_pci: None,
+            recv_buffer_is_null: false,
+            recv_length: 258,
+        }),
+    );
+    call(
+        &mut server,
+        &mut client,
+        5,
+        ScardIoCtlCode::StatusW,
+        ScardCall::StatusCall(StatusCall {
+            handle: handle(),
+            reader_names_is_null: false,
+            reader_length: 0xFFFF_FFFF,
+            atr_length: 32,
+        }),
+    );
+    call(
+        &mut server,
+        &mut client,
+        6,
+        ScardIoCtlCode::ReleaseContext,
+        ScardCall::ContextCall(ContextCall { context: CONTEXT }),
+    );
+
+    let mut atr = [0; 32];
+    atr[..ATR.len()].copy_from_slice(&ATR);
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(
+        *calls,
+        [
+            (
+                1,
+                ScardIoCtlCode::EstablishContext,
+                Ok(ScardReturn::EstablishContextReturn(
+                    EstablishContextReturn::new(ReturnCode::Success, CONTEXT).into_inner()
+                ))
+            ),
+            (
+                2,
+                ScardIoCtlCode::ListReadersW,
+                Ok(ScardReturn::ListReadersReturn(
+                    ListReadersReturn::new(ReturnCode::Success, vec![READER.to_owned()]).into_inner()
+                ))
+            ),
+            (
+                3,
+                ScardIoCtlCode::ConnectW,
+                Ok(ScardReturn::ConnectReturn(
+                    ConnectReturn::new(ReturnCode::Success, handle(), CardProtocol::SCARD_PROTOCOL_T1).into_inner()
+                ))
+            ),
+            (
+                4,
+                ScardIoCtlCode::Transmit,
+                Ok(ScardReturn::TransmitReturn(
+                    TransmitReturn::new(ReturnCode::Success, None, vec![0x90, 0x00]).into_inner()
+                ))
+            ),
+            (
+                5,
+                ScardIoCtlCode::StatusW,
+                Ok(ScardReturn::StatusReturn(
+                    StatusReturn::new(
+                        ReturnCode::Success,
+                        vec![READER.to_owned()],
+                        CardState::SpecificMode,
+                        CardProtocol::SCARD_PROTOCOL_T1,
+                        atr,
+                        4,
+                        CharacterSet::Unicode,
+                    )
+                    .into_inner()
+                ))
+            ),
+            (
+                6,
+                ScardIoCtlCode::ReleaseContext,
+                Ok(ScardReturn::LongReturn(
+                    LongReturn::new(ReturnCode::Success).into_inner()
+                ))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn failed_call_returns_code() {
+    let (mut server, mut client, calls) = connect(true);
+
+    call(
+        &mut server,
+        &mut client,
+        1,
+        ScardIoCtlCode::ConnectW,
+        connect_call("Missing"),
+    );
+
+    let calls = calls.lock().unwrap();
+    let [(1, ScardIoCtlCode::ConnectW, Ok(result))] = calls.as_slice() else {
+        panic!("unexpected calls: {calls:?}");
+    };
+    assert_eq!(result.return_code(), ReturnCode::UnknownReader);
+}
+
+#[test]
+fn mismatched_io_control_code() {
+    let (mut server, _, _) = connect(true);
+
+    let server = rdpdr_server(&mut server);
+    assert!(server
+        .smartcard_call(SCARD_ID, 1, ScardIoCtlCode::ConnectA, connect_call(READER))
+        .is_err());
+    assert!(server
+        .smartcard_call(
+            SCARD_ID,
+            1,
+            ScardIoCtlCode::Transmit,
+            ScardCall::ContextCall(ContextCall { context: CONTEXT }),
+        )
+        .is_err());
+}
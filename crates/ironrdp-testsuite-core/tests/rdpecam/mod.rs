@@ -0,0 +1,404 @@
+use ironrdp_core::encode_vec;
+use ironrdp_dvc::pdu::{CapabilitiesResponsePdu, CapsVersion, DrdynvcClientPdu};
+use ironrdp_dvc::{DrdynvcServer, DvcProcessor};
+use ironrdp_rdpecam::client::{CameraDevice, CameraDeviceClient, CameraEnumeratorClient, CameraInfo};
+use ironrdp_rdpecam::pdu::{
+    CameraMessage, CameraPdu, DeviceAddedNotification, ErrorCode, MediaFormat, MediaTypeDescription,
+    MediaTypeListResponse, PropertyDescription, PropertyRequest, PropertyValue, SampleResponse,
+    SetPropertyValueRequest, StartStreamInfo, StartStreamsRequest, StreamDescription, StreamRequest, VERSION_1,
+    VERSION_2,
+};
+use ironrdp_rdpecam::server::{
+    CameraConsumer, CameraDeviceServer, CameraEnumeratorServer, CameraEnumeratorServerHandler,
+};
+use ironrdp_svc::SvcProcessor as _;
+use ironrdp_testsuite_core::encode_decode_test;
+
+use crate::harness::{assert_truncated_is_rejected, decode_messages, open, Events, Recorder, CHANNEL_ID};
+
+const DEVICE_CHANNEL_NAME: &str = "RDCamera_Device_0";
+
+fn vga_nv12() -> MediaTypeDescription {
+    MediaTypeDescription {
+        format: MediaFormat::NV12,
+        width: 640,
+        height: 480,
+        frame_rate_numerator: 30,
+        frame_rate_denominator: 1,
+        pixel_aspect_ratio_numerator: 1,
+        pixel_aspect_ratio_denominator: 1,
+        flags: 0,
+    }
+}
+
+encode_decode_test! {
+    select_version_request: CameraPdu::new(VERSION_2, CameraMessage::SelectVersionRequest),
+    [
+        0x02, // Version
+        0x03, // MessageId
+    ];
+
+    device_added_notification: CameraPdu::new(
+        VERSION_2,
+        CameraMessage::DeviceAdded(DeviceAddedNotification {
+            device_name: String::from("Cam"),
+            virtual_channel_name: String::from("Ch"),
+        }),
+    ),
+    [
+        0x02, 0x05,
+        b'C', 0x00, b'a', 0x00, b'm', 0x00, 0x00, 0x00, // DeviceName
+        b'C', b'h', 0x00, // VirtualChannelName
+    ];
+
+    error_response: CameraPdu::new(VERSION_2, CameraMessage::Error(ErrorCode::INVALID_STREAM_NUMBER)),
+    [
+        0x02, 0x02,
+        0x05, 0x00, 0x00, 0x00, // ErrorCode
+    ];
+
+    media_type_list_response: CameraPdu::new(
+        VERSION_2,
+        CameraMessage::MediaTypeListResponse(MediaTypeListResponse {
+            media_types: vec![vga_nv12()],
+        }),
+    ),
+    [
+        0x02, 0x0C,
+        0x04, // Format
+        0x80, 0x02, 0x00, 0x00, // Width
+        0xE0, 0x01, 0x00, 0x00, // Height
+        0x1E, 0x00, 0x00, 0x00, // FrameRateNumerator
+        0x01, 0x00, 0x00, 0x00, // FrameRateDenominator
+        0x01, 0x00, 0x00, 0x00, // PixelAspectRatioNumerator
+        0x01, 0x00, 0x00, 0x00, // PixelAspectRatioDenominator
+        0x00, // Flags
+    ];
+
+    sample_response: CameraPdu::new(
+        VERSION_1,
+        CameraMessage::SampleResponse(SampleResponse {
+            stream_index: 0,
+            sample: vec![0xAA, 0xBB, 0xCC],
+        }),
+    ),
+    [
+        0x01, 0x12,
+        0x00, // StreamIndex
+        0xAA, 0xBB, 0xCC, // Sample
+    ];
+
+    set_property_value_request: CameraPdu::new(
+        VERSION_2,
+        CameraMessage::SetPropertyValueRequest(SetPropertyValueRequest {
+            property: PropertyRequest {
+                property_set: PropertyRequest::VIDEO_PROC_AMP,
+                property_id: 0x01,
+            },
+            value: PropertyValue {
+                mode: PropertyValue::MODE_MANUAL,
+                value: -5,
+            },
+        }),
+    ),
+    [
+        0x02, 0x18,
+        0x02, // PropertySet
+        0x01, // PropertyId
+        0x01, // Mode
+        0xFB, 0xFF, 0xFF, 0xFF, // Value
+    ];
+}
+
+#[test]
+fn truncated_start_streams_request_is_rejected() {
+    let pdu = CameraPdu::new(
+        VERSION_2,
+        CameraMessage::StartStreamsRequest(StartStreamsRequest {
+            streams: vec![StartStreamInfo {
+                stream_index: 0,
+                media_type: vga_nv12(),
+            }],
+        }),
+    );
+
+    assert_truncated_is_rejected::<CameraPdu>(&pdu);
+}
+
+impl CameraEnumeratorServerHandler for Recorder<(u8, DeviceAddedNotification)> {
+    fn device_added(&mut self, version: u8, device: DeviceAddedNotification) {
+        self.record((version, device));
+    }
+}
+
+#[test]
+fn device_enumeration() {
+    let camera = CameraInfo {
+        device_name: String::from("Integrated Camera"),
+        channel_name: String::from(DEVICE_CHANNEL_NAME),
+    };
+    let mut client = CameraEnumeratorClient::new(vec![camera.clone()]);
+    let devices = Events::<(u8, DeviceAddedNotification)>::default();
+    let mut server = CameraEnumeratorServer::new(Box::new(Recorder::new(&devices)));
+
+    assert!(server.start(CHANNEL_ID).unwrap().is_empty());
+    let request = client.start(CHANNEL_ID).unwrap();
+
+    let replies = server
+        .process(CHANNEL_ID, &encode_vec(request[0].as_ref()).unwrap())
+        .unwrap();
+    assert_eq!(
+        decode_messages::<CameraPdu>(replies),
+        [CameraPdu::new(VERSION_2, CameraMessage::SelectVersionResponse)]
+    );
+    assert_eq!(server.version(), Some(VERSION_2));
+
+    let response = encode_vec(&CameraPdu::new(VERSION_2, CameraMessage::SelectVersionResponse)).unwrap();
+    for notification in client.process(CHANNEL_ID, &response).unwrap() {
+        let replies = server
+            .process(CHANNEL_ID, &encode_vec(notification.as_ref()).unwrap())
+            .unwrap();
+        assert!(replies.is_empty());
+    }
+    assert_eq!(client.version(), Some(VERSION_2));
+    assert_eq!(
+        *devices.lock().unwrap(),
+        [(
+            VERSION_2,
+            DeviceAddedNotification {
+                device_name: camera.device_name.clone(),
+                virtual_channel_name: camera.channel_name.clone(),
+            }
+        )]
+    );
+
+    // Hot-plugged cameras are announced right away.
+    assert!(client.add_camera(camera.clone()).is_err());
+    let other = CameraInfo {
+        device_name: String::from("USB Camera"),
+        channel_name: String::from("RDCamera_Device_1"),
+    };
+    assert_eq!(client.add_camera(other).unwrap().len(), 1);
+    assert_eq!(client.remove_camera(DEVICE_CHANNEL_NAME).unwrap().len(), 1);
+    assert!(client.remove_camera(DEVICE_CHANNEL_NAME).is_err());
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    Activate,
+    Start(Vec<StartStreamInfo>),
+    RequestSample(u8),
+    Stop,
+    Started(Vec<StartStreamInfo>),
+    Sample(u8, Vec<u8>),
+    Properties(Vec<PropertyDescription>),
+    Stopped,
+}
+
+#[derive(Debug)]
+struct TestCamera(Recorder<Event>);
+
+fn brightness() -> PropertyDescription {
+    PropertyDescription {
+        property: PropertyRequest {
+            property_set: PropertyRequest::VIDEO_PROC_AMP,
+            property_id: 0x01,
+        },
+        capabilities: PropertyValue::MODE_MANUAL,
+        min_value: -64,
+        max_value: 64,
+        step: 1,
+        default_value: 0,
+    }
+}
+
+impl CameraDevice for TestCamera {
+    fn activate(&mut self) -> Result<(), ErrorCode> {
+        self.0.record(Event::Activate);
+        Ok(())
+    }
+
+    fn deactivate(&mut self) -> Result<(), ErrorCode> {
+        Ok(())
+    }
+
+    fn streams(&self) -> Vec<StreamDescription> {
+        vec![StreamDescription::color()]
+    }
+
+    fn media_types(&self, stream_index: u8) -> Result<Vec<MediaTypeDescription>, ErrorCode> {
+        match stream_index {
+            0 => Ok(vec![vga_nv12()]),
+            _ => Err(ErrorCode::INVALID_STREAM_NUMBER),
+        }
+    }
+
+    fn current_media_type(&self, _stream_index: u8) -> Result<MediaTypeDescription, ErrorCode> {
+        Ok(vga_nv12())
+    }
+
+    fn start_streams(&mut self, streams: &[StartStreamInfo]) -> Result<(), ErrorCode> {
+        self.0.record(Event::Start(streams.to_vec()));
+        Ok(())
+    }
+
+    fn stop_streams(&mut self) -> Result<(), ErrorCode> {
+        self.0.record(Event::Stop);
+        Ok(())
+    }
+
+    fn request_sample(&mut self, stream_index: u8) {
+        self.0.record(Event::RequestSample(stream_index));
+    }
+
+    fn properties(&self) -> Vec<PropertyDescription> {
+        vec![brightness()]
+    }
+}
+
+impl CameraConsumer for Recorder<Event> {
+    fn started(&mut self, streams: &[StartStreamInfo]) {
+        self.record(Event::Started(streams.to_vec()));
+    }
+
+    fn sample(&mut self, stream_index: u8, sample: &[u8]) {
+        self.record(Event::Sample(stream_index, sample.to_vec()));
+    }
+
+    fn properties(&mut self, properties: &[PropertyDescription]) {
+        self.record(Event::Properties(properties.to_vec()));
+    }
+
+    fn stopped(&mut self) {
+        self.record(Event::Stopped);
+    }
+}
+
+fn connect(version: u8) -> (CameraDeviceServer, CameraDeviceClient, Events<Event>) {
+    let events = Events::default();
+    let mut client = CameraDeviceClient::new(DEVICE_CHANNEL_NAME, Box::new(TestCamera(Recorder::new(&events))));
+    let mut server = CameraDeviceServer::new(DEVICE_CHANNEL_NAME, version, Box::new(Recorder::new(&events)));
+
+    open(&mut server, &mut client);
+
+    (server, client, events)
+}
+
+#[test]
+fn camera_streaming() {
+    let (mut server, mut client, events) = connect(VERSION_2);
+
+    let streams = vec![StartStreamInfo {
+        stream_index: 0,
+        media_type: vga_nv12(),
+    }];
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Event::Activate,
+            Event::Start(streams.clone()),
+            Event::Started(streams),
+            Event::RequestSample(0),
+        ]
+    );
+    assert!(server.is_streaming());
+    assert!(client.is_streaming());
+    assert!(client.is_sample_requested(0));
+
+    // Samples are only sent when requested.
+    assert_eq!(client.encode_sample(0, vec![0x10; 8]).unwrap().len(), 1);
+    assert!(!client.is_sample_requested(0));
+    assert!(client.encode_sample(0, vec![0x10; 8]).is_err());
+
+    events.lock().unwrap().clear();
+    let sample = CameraPdu::new(
+        VERSION_2,
+        CameraMessage::SampleResponse(SampleResponse {
+            stream_index: 0,
+            sample: vec![0x10; 8],
+        }),
+    );
+    let requests = server.process(CHANNEL_ID, &encode_vec(&sample).unwrap()).unwrap();
+    assert_eq!(
+        decode_messages::<CameraPdu>(requests),
+        [CameraPdu::new(
+            VERSION_2,
+            CameraMessage::SampleRequest(StreamRequest { stream_index: 0 })
+        )]
+    );
+    assert_eq!(*events.lock().unwrap(), [Event::Sample(0, vec![0x10; 8])]);
+
+    assert_eq!(server.stop_streams().unwrap().len(), 1);
+    assert!(!server.is_streaming());
+}
+
+#[test]
+fn camera_properties() {
+    let (mut server, mut client, events) = connect(VERSION_2);
+    events.lock().unwrap().clear();
+
+    assert_eq!(server.request_properties().unwrap().len(), 1);
+
+    let replies = client
+        .process(
+            CHANNEL_ID,
+            &encode_vec(&CameraPdu::new(VERSION_2, CameraMessage::PropertyListRequest)).unwrap(),
+        )
+        .unwrap();
+    for reply in replies {
+        let payload = encode_vec(reply.as_ref()).unwrap();
+        assert!(server.process(CHANNEL_ID, &payload).unwrap().is_empty());
+    }
+    assert_eq!(*events.lock().unwrap(), [Event::Properties(vec![brightness()])]);
+
+    // The test camera doesn't support changing its properties.
+    let request = SetPropertyValueRequest {
+        property: brightness().property,
+        value: PropertyValue {
+            mode: PropertyValue::MODE_MANUAL,
+            value: 10,
+        },
+    };
+    let replies = client
+        .process(
+            CHANNEL_ID,
+            &encode_vec(&CameraPdu::new(
+                VERSION_2,
+                CameraMessage::SetPropertyValueRequest(request),
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        decode_messages::<CameraPdu>(replies),
+        [CameraPdu::new(
+            VERSION_2,
+            CameraMessage::Error(ErrorCode::OPERATION_NOT_SUPPORTED)
+        )]
+    );
+}
+
+#[test]
+fn camera_properties_require_version_2() {
+    let (mut server, _client, _events) = connect(VERSION_1);
+
+    assert!(server.request_properties().is_err());
+}
+
+#[test]
+fn camera_channel_attached_during_session() {
+    let mut drdynvc = DrdynvcServer::new();
+    assert_eq!(drdynvc.start().unwrap().len(), 1);
+
+    // Before the capabilities are exchanged, the channel is created along with the other ones.
+    let consumer = Recorder::new(&Events::<Event>::default());
+    let channel = CameraDeviceServer::new(DEVICE_CHANNEL_NAME, VERSION_2, Box::new(consumer));
+    assert!(drdynvc.attach_dynamic_channel(channel).unwrap().is_empty());
+
+    let capabilities = DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(CapsVersion::V1));
+    assert_eq!(drdynvc.process(&encode_vec(&capabilities).unwrap()).unwrap().len(), 1);
+
+    let consumer = Recorder::new(&Events::<Event>::default());
+    let channel = CameraDeviceServer::new("RDCamera_Device_1", VERSION_2, Box::new(consumer));
+    assert_eq!(drdynvc.attach_dynamic_channel(channel).unwrap().len(), 1);
+}
@@ -0,0 +1,450 @@
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_dvc::DvcProcessor;
+use ironrdp_rdpeusb::client::{UrbdrcClient, UsbBackend};
+use ironrdp_rdpeusb::pdu::{
+    AddDevice, ChannelCreated, ClientMessage, ExchangeCapabilityRequest, ExchangeCapabilityResponse, IoControl,
+    QueryDeviceText, QueryDeviceTextResponse, RegisterRequestCallback, RetractDevice, ServerMessage, TransferInRequest,
+    TransferOutRequest, TsUrb, TsUrbResult, UrbCompletion, UrbdrcClientPdu, UrbdrcServerPdu, UsbDevice,
+    UsbDeviceCapabilities, CAPABILITIES_INTERFACE, CLIENT_CHANNEL_NOTIFICATION_INTERFACE, DEVICE_SINK_INTERFACE,
+    E_FAIL, RIM_CAPABILITY_VERSION_01, SERVER_CHANNEL_NOTIFICATION_INTERFACE, S_OK,
+    USB_RETRACT_REASON_BLOCKED_BY_POLICY,
+};
+use ironrdp_testsuite_core::encode_decode_test;
+
+use crate::harness::{decode_messages, Events, Recorder};
+
+const CONTROL_CHANNEL_ID: u32 = 3;
+const DEVICE_CHANNEL_ID: u32 = 4;
+const DEVICE_ID: u32 = 5;
+const REQUEST_COMPLETION: u32 = 0x10;
+
+fn bulk_in_urb() -> TsUrb {
+    TsUrb {
+        urb_function: 0x0009,
+        request_id: 7,
+        no_ack: false,
+        data: vec![0x81, 0x00, 0x00, 0x00],
+    }
+}
+
+encode_decode_test! {
+    exchange_capability_request: UrbdrcServerPdu {
+        interface_id: CAPABILITIES_INTERFACE,
+        message_id: 1,
+        message: ServerMessage::ExchangeCapabilityRequest(ExchangeCapabilityRequest {
+            capability_value: RIM_CAPABILITY_VERSION_01,
+        }),
+    },
+    [
+        0x00, 0x00, 0x00, 0x00, // InterfaceId + Mask
+        0x01, 0x00, 0x00, 0x00, // MessageId
+        0x00, 0x01, 0x00, 0x00, // FunctionId
+        0x01, 0x00, 0x00, 0x00, // CapabilityValue
+    ];
+
+    exchange_capability_response: UrbdrcClientPdu {
+        interface_id: CAPABILITIES_INTERFACE,
+        message_id: 1,
+        message: ClientMessage::ExchangeCapabilityResponse(ExchangeCapabilityResponse {
+            capability_value: RIM_CAPABILITY_VERSION_01,
+            result: S_OK,
+        }),
+    },
+    [
+        0x00, 0x00, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00, // CapabilityValue
+        0x00, 0x00, 0x00, 0x00, // Result
+    ];
+
+    server_channel_created: UrbdrcServerPdu {
+        interface_id: SERVER_CHANNEL_NOTIFICATION_INTERFACE,
+        message_id: 2,
+        message: ServerMessage::ChannelCreated(ChannelCreated::V1_0),
+    },
+    [
+        0x02, 0x00, 0x00, 0x40,
+        0x02, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00, // MajorVersion
+        0x00, 0x00, 0x00, 0x00, // MinorVersion
+        0x00, 0x00, 0x00, 0x00, // Capabilities
+    ];
+
+    add_virtual_channel: UrbdrcClientPdu {
+        interface_id: DEVICE_SINK_INTERFACE,
+        message_id: 3,
+        message: ClientMessage::AddVirtualChannel,
+    },
+    [
+        0x01, 0x00, 0x00, 0x40,
+        0x03, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x00,
+    ];
+
+    register_request_callback: UrbdrcServerPdu {
+        interface_id: DEVICE_ID,
+        message_id: 4,
+        message: ServerMessage::RegisterRequestCallback(RegisterRequestCallback {
+            request_completion: Some(REQUEST_COMPLETION),
+        }),
+    },
+    [
+        0x05, 0x00, 0x00, 0x40,
+        0x04, 0x00, 0x00, 0x00,
+        0x01, 0x01, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00, // NumRequestCompletion
+        0x10, 0x00, 0x00, 0x00, // RequestCompletion
+    ];
+
+    transfer_in_request: UrbdrcServerPdu {
+        interface_id: DEVICE_ID,
+        message_id: 5,
+        message: ServerMessage::TransferIn(TransferInRequest {
+            ts_urb: bulk_in_urb(),
+            output_buffer_size: 64,
+        }),
+    },
+    [
+        0x05, 0x00, 0x00, 0x40,
+        0x05, 0x00, 0x00, 0x00,
+        0x05, 0x01, 0x00, 0x00,
+        0x0c, 0x00, 0x00, 0x00, // CbTsUrb
+        0x0c, 0x00, // Size
+        0x09, 0x00, // URB_Function
+        0x07, 0x00, 0x00, 0x00, // RequestId + NoAck
+        0x81, 0x00, 0x00, 0x00,
+        0x40, 0x00, 0x00, 0x00, // OutputBufferSize
+    ];
+
+    urb_completion: UrbdrcClientPdu {
+        interface_id: REQUEST_COMPLETION,
+        message_id: 6,
+        message: ClientMessage::UrbCompletion(UrbCompletion {
+            request_id: 7,
+            ts_urb_result: TsUrbResult { usbd_status: 0, data: Vec::new() },
+            hresult: S_OK,
+            output_buffer: vec![0x01, 0x02, 0x03],
+        }),
+    },
+    [
+        0x10, 0x00, 0x00, 0x40,
+        0x06, 0x00, 0x00, 0x00,
+        0x01, 0x01, 0x00, 0x00,
+        0x07, 0x00, 0x00, 0x00, // RequestId
+        0x08, 0x00, 0x00, 0x00, // CbTsUrbResult
+        0x08, 0x00, 0x00, 0x00, // Size + Padding
+        0x00, 0x00, 0x00, 0x00, // UsbdStatus
+        0x00, 0x00, 0x00, 0x00, // HResult
+        0x03, 0x00, 0x00, 0x00, // OutputBufferSize
+        0x01, 0x02, 0x03,
+    ];
+
+    query_device_text_response: UrbdrcClientPdu {
+        interface_id: DEVICE_ID,
+        message_id: 8,
+        message: ClientMessage::QueryDeviceTextResponse(QueryDeviceTextResponse {
+            device_description: "Key".to_owned(),
+            hresult: S_OK,
+        }),
+    },
+    [
+        0x05, 0x00, 0x00, 0x80,
+        0x08, 0x00, 0x00, 0x00,
+        0x04, 0x00, 0x00, 0x00, // cchDeviceDescription
+        0x4b, 0x00, 0x65, 0x00, 0x79, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, // HResult
+    ];
+}
+
+fn usb_device() -> UsbDevice {
+    UsbDevice {
+        device_instance_id: r"USB\VID_1050&PID_0407\0001".to_owned(),
+        hardware_ids: vec![
+            r"USB\VID_1050&PID_0407&REV_0543".to_owned(),
+            r"USB\VID_1050&PID_0407".to_owned(),
+        ],
+        compatibility_ids: vec![r"USB\Class_03&SubClass_01&Prot_01".to_owned()],
+        container_id: "{6a6d5e3b-5d3a-4e8b-9a0f-1c2d3e4f5a6b}".to_owned(),
+        capabilities: UsbDeviceCapabilities::default(),
+    }
+}
+
+#[test]
+fn add_device_roundtrip() {
+    let pdu = UrbdrcClientPdu {
+        interface_id: DEVICE_SINK_INTERFACE,
+        message_id: 9,
+        message: ClientMessage::AddDevice(AddDevice {
+            usb_device: DEVICE_ID,
+            device: usb_device(),
+        }),
+    };
+
+    let encoded = encode_vec(&pdu).unwrap();
+    assert_eq!(decode::<UrbdrcClientPdu>(&encoded).unwrap(), pdu);
+}
+
+#[test]
+fn no_ack_transfer_out_roundtrip() {
+    let pdu = UrbdrcServerPdu {
+        interface_id: DEVICE_ID,
+        message_id: 10,
+        message: ServerMessage::TransferOut(TransferOutRequest {
+            ts_urb: TsUrb {
+                no_ack: true,
+                ..bulk_in_urb()
+            },
+            output_buffer: vec![0xaa; 16],
+        }),
+    };
+
+    let encoded = encode_vec(&pdu).unwrap();
+    assert_eq!(decode::<UrbdrcServerPdu>(&encoded).unwrap(), pdu);
+}
+
+#[test]
+fn truncated_urb_is_rejected() {
+    let mut encoded = encode_vec(&UrbdrcServerPdu {
+        interface_id: DEVICE_ID,
+        message_id: 5,
+        message: ServerMessage::TransferIn(TransferInRequest {
+            ts_urb: bulk_in_urb(),
+            output_buffer_size: 64,
+        }),
+    })
+    .unwrap();
+    // The URB claims to be smaller than its header.
+    encoded[16] = 0x04;
+
+    assert!(decode::<UrbdrcServerPdu>(&encoded).is_err());
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    IoControl(u32, IoControl),
+    TransferIn(u32, TransferInRequest),
+    TransferOut(u32, TransferOutRequest),
+    Cancel(u32, u32),
+    Retract(u32, u32),
+}
+
+impl UsbBackend for Recorder<Event> {
+    fn io_control(&mut self, device_id: u32, request: &IoControl) {
+        self.record(Event::IoControl(device_id, request.clone()));
+    }
+
+    fn internal_io_control(&mut self, device_id: u32, request: &IoControl) {
+        self.io_control(device_id, request);
+    }
+
+    fn query_device_text(&mut self, _device_id: u32, request: &QueryDeviceText) -> Option<String> {
+        (request.text_type == QueryDeviceText::DEVICE_TEXT_DESCRIPTION).then(|| "Security Key".to_owned())
+    }
+
+    fn transfer_in(&mut self, device_id: u32, request: &TransferInRequest) {
+        self.record(Event::TransferIn(device_id, request.clone()));
+    }
+
+    fn transfer_out(&mut self, device_id: u32, request: &TransferOutRequest) {
+        self.record(Event::TransferOut(device_id, request.clone()));
+    }
+
+    fn cancel_request(&mut self, device_id: u32, request_id: u32) {
+        self.record(Event::Cancel(device_id, request_id));
+    }
+
+    fn retract_device(&mut self, device_id: u32, reason: u32) {
+        self.record(Event::Retract(device_id, reason));
+    }
+}
+
+/// Sends a server message to the client, and decodes the replies
+fn send(client: &mut UrbdrcClient, channel_id: u32, interface_id: u32, message: ServerMessage) -> Vec<ClientMessage> {
+    let pdu = UrbdrcServerPdu {
+        interface_id,
+        message_id: 0,
+        message,
+    };
+
+    let replies = client.process(channel_id, &encode_vec(&pdu).unwrap()).unwrap();
+
+    decode_messages::<UrbdrcClientPdu>(replies)
+        .into_iter()
+        .map(|pdu| pdu.message)
+        .collect()
+}
+
+fn negotiate(client: &mut UrbdrcClient, channel_id: u32) -> Vec<ClientMessage> {
+    assert!(client.start(channel_id).unwrap().is_empty());
+
+    let replies = send(
+        client,
+        channel_id,
+        CAPABILITIES_INTERFACE,
+        ServerMessage::ExchangeCapabilityRequest(ExchangeCapabilityRequest {
+            capability_value: RIM_CAPABILITY_VERSION_01,
+        }),
+    );
+    assert_eq!(
+        replies,
+        [ClientMessage::ExchangeCapabilityResponse(ExchangeCapabilityResponse {
+            capability_value: RIM_CAPABILITY_VERSION_01,
+            result: S_OK,
+        })]
+    );
+
+    send(
+        client,
+        channel_id,
+        SERVER_CHANNEL_NOTIFICATION_INTERFACE,
+        ServerMessage::ChannelCreated(ChannelCreated::V1_0),
+    )
+}
+
+/// Opens the control channel, then the channel of the device
+fn connect() -> (UrbdrcClient, Events<Event>) {
+    let events = Events::default();
+    let mut client = UrbdrcClient::new(Box::new(Recorder::new(&events))).with_devices(vec![(DEVICE_ID, usb_device())]);
+
+    assert_eq!(
+        negotiate(&mut client, CONTROL_CHANNEL_ID),
+        [
+            ClientMessage::ChannelCreated(ChannelCreated::V1_0),
+            ClientMessage::AddVirtualChannel
+        ]
+    );
+    assert!(!client.is_redirected(DEVICE_ID));
+
+    assert_eq!(
+        negotiate(&mut client, DEVICE_CHANNEL_ID),
+        [
+            ClientMessage::ChannelCreated(ChannelCreated::V1_0),
+            ClientMessage::AddDevice(AddDevice {
+                usb_device: DEVICE_ID,
+                device: usb_device(),
+            })
+        ]
+    );
+    assert!(client.is_redirected(DEVICE_ID));
+
+    (client, events)
+}
+
+#[test]
+fn device_redirection() {
+    let (mut client, events) = connect();
+
+    // Completions can't be sent before the server registers a callback.
+    let completion = UrbCompletion {
+        request_id: 7,
+        ts_urb_result: TsUrbResult {
+            usbd_status: 0,
+            data: Vec::new(),
+        },
+        hresult: S_OK,
+        output_buffer: vec![0x01, 0x02],
+    };
+    assert!(client.encode_urb_completion(DEVICE_ID, completion.clone()).is_err());
+
+    let replies = send(
+        &mut client,
+        DEVICE_CHANNEL_ID,
+        DEVICE_ID,
+        ServerMessage::RegisterRequestCallback(RegisterRequestCallback {
+            request_completion: Some(REQUEST_COMPLETION),
+        }),
+    );
+    assert!(replies.is_empty());
+
+    let request = TransferInRequest {
+        ts_urb: bulk_in_urb(),
+        output_buffer_size: 64,
+    };
+    let replies = send(
+        &mut client,
+        DEVICE_CHANNEL_ID,
+        DEVICE_ID,
+        ServerMessage::TransferIn(request.clone()),
+    );
+    assert!(replies.is_empty());
+    assert_eq!(*events.lock().unwrap(), [Event::TransferIn(DEVICE_ID, request)]);
+
+    assert_eq!(client.encode_urb_completion(DEVICE_ID, completion).unwrap().len(), 1);
+
+    let replies = send(
+        &mut client,
+        DEVICE_CHANNEL_ID,
+        DEVICE_ID,
+        ServerMessage::QueryDeviceText(QueryDeviceText {
+            text_type: QueryDeviceText::DEVICE_TEXT_LOCATION_INFORMATION,
+            locale_id: 0x409,
+        }),
+    );
+    assert_eq!(
+        replies,
+        [ClientMessage::QueryDeviceTextResponse(QueryDeviceTextResponse {
+            device_description: String::new(),
+            hresult: E_FAIL,
+        })]
+    );
+
+    // Unplugging the device closes its channel.
+    assert_eq!(client.remove_device(DEVICE_ID).len(), 1);
+    assert!(client.device_ids().next().is_none());
+}
+
+#[test]
+fn hot_plugged_device() {
+    let events = Events::<Event>::default();
+    let mut client = UrbdrcClient::new(Box::new(Recorder::new(&events)));
+
+    // The device is announced once the control channel is ready.
+    assert!(client.add_device(DEVICE_ID, usb_device()).unwrap().is_empty());
+    assert_eq!(
+        negotiate(&mut client, CONTROL_CHANNEL_ID),
+        [
+            ClientMessage::ChannelCreated(ChannelCreated::V1_0),
+            ClientMessage::AddVirtualChannel
+        ]
+    );
+
+    assert_eq!(client.add_device(DEVICE_ID + 1, usb_device()).unwrap().len(), 1);
+    assert!(client.add_device(DEVICE_ID + 1, usb_device()).is_err());
+    assert!(client
+        .add_device(CLIENT_CHANNEL_NOTIFICATION_INTERFACE, usb_device())
+        .is_err());
+}
+
+#[test]
+fn retracted_device() {
+    let (mut client, events) = connect();
+
+    let replies = send(
+        &mut client,
+        DEVICE_CHANNEL_ID,
+        DEVICE_ID,
+        ServerMessage::RetractDevice(RetractDevice {
+            reason: USB_RETRACT_REASON_BLOCKED_BY_POLICY,
+        }),
+    );
+    assert!(replies.is_empty());
+    assert_eq!(
+        *events.lock().unwrap(),
+        [Event::Retract(DEVICE_ID, USB_RETRACT_REASON_BLOCKED_BY_POLICY)]
+    );
+    assert!(!client.is_redirected(DEVICE_ID));
+
+    // Requests for the retracted device are ignored.
+    let replies = send(
+        &mut client,
+        DEVICE_CHANNEL_ID,
+        DEVICE_ID,
+        ServerMessage::TransferIn(TransferInRequest {
+            ts_urb: bulk_in_urb(),
+            output_buffer_size: 64,
+        }),
+    );
+    assert!(replies.is_empty());
+    assert_eq!(events.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,319 @@
+use ironrdp_dvc::DvcProcessor;
+use ironrdp_rdpevor::client::{VideoPresenter, VideoRedirection, VideoSample};
+use ironrdp_rdpevor::pdu::{
+    ClientNotification, GeometryPdu, GeometryRect, GeometryUpdate, MappedGeometry, Notification, PresentationCommand,
+    PresentationRequest, PresentationResponse, VideoData, VideoDataFlags, VideoPdu, VideoSubtype,
+};
+use ironrdp_testsuite_core::encode_decode_test;
+
+use crate::harness::{assert_truncated_is_rejected, decode_messages, process, Events, Recorder, CHANNEL_ID};
+
+const MAPPING_ID: u64 = 0x11;
+
+fn presentation_request(presentation_id: u8, command: PresentationCommand) -> PresentationRequest {
+    PresentationRequest {
+        presentation_id,
+        command,
+        frame_rate: 30,
+        average_bitrate_kbps: 1000,
+        source_width: 640,
+        source_height: 360,
+        scaled_width: 1280,
+        scaled_height: 720,
+        timestamp_offset: 0x10,
+        geometry_mapping_id: MAPPING_ID,
+        video_subtype: VideoSubtype::H264,
+        extra_data: vec![0x67, 0x68],
+    }
+}
+
+fn video_data(sample_number: u32, current_packet_index: u16, packets_in_sample: u16, sample: &[u8]) -> VideoData {
+    VideoData {
+        presentation_id: 1,
+        flags: VideoDataFlags::HAS_TIMESTAMPS | VideoDataFlags::KEYFRAME,
+        timestamp: 400_000,
+        duration: 333_333,
+        current_packet_index,
+        packets_in_sample,
+        sample_number,
+        sample: sample.to_vec(),
+    }
+}
+
+fn mapped_geometry() -> MappedGeometry {
+    MappedGeometry {
+        top_level_id: 0x22,
+        rect: GeometryRect {
+            left: 10,
+            top: 20,
+            right: 650,
+            bottom: 380,
+        },
+        top_level_rect: GeometryRect {
+            left: 0,
+            top: 0,
+            right: 800,
+            bottom: 600,
+        },
+        region: vec![GeometryRect {
+            left: 10,
+            top: 20,
+            right: 650,
+            bottom: 380,
+        }],
+    }
+}
+
+encode_decode_test! {
+    presentation_request: VideoPdu::PresentationRequest(presentation_request(1, PresentationCommand::Start)),
+    [
+        0x47, 0x00, 0x00, 0x00, // cbSize
+        0x01, 0x00, 0x00, 0x00, // PacketType
+        0x01, // PresentationId
+        0x01, // Version
+        0x01, // Command
+        0x1E, // FrameRate
+        0xE8, 0x03, // AverageBitrateKbps
+        0x00, 0x00, // Reserved
+        0x80, 0x02, 0x00, 0x00, // SourceWidth
+        0x68, 0x01, 0x00, 0x00, // SourceHeight
+        0x00, 0x05, 0x00, 0x00, // ScaledWidth
+        0xD0, 0x02, 0x00, 0x00, // ScaledHeight
+        0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // hnsTimestampOffset
+        0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // GeometryMappingId
+        0x48, 0x32, 0x36, 0x34, 0x00, 0x00, 0x10, 0x00,
+        0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71, // VideoSubtypeId
+        0x02, 0x00, 0x00, 0x00, // cbExtra
+        0x67, 0x68, // pExtraData
+        0x00, // Reserved2
+    ];
+
+    presentation_response: VideoPdu::PresentationResponse(PresentationResponse { presentation_id: 1 }),
+    [
+        0x0C, 0x00, 0x00, 0x00, // cbSize
+        0x02, 0x00, 0x00, 0x00, // PacketType
+        0x01, // PresentationId
+        0x00, 0x00, 0x00, // Reserved
+    ];
+
+    frame_rate_override: VideoPdu::ClientNotification(ClientNotification {
+        presentation_id: 1,
+        notification: Notification::FrameRateOverride { desired_frame_rate: 15 },
+    }),
+    [
+        0x20, 0x00, 0x00, 0x00, // cbSize
+        0x03, 0x00, 0x00, 0x00, // PacketType
+        0x01, // PresentationId
+        0x02, // NotificationType
+        0x00, 0x00, // Reserved
+        0x10, 0x00, 0x00, 0x00, // cbData
+        0x01, 0x00, 0x00, 0x00, // Flags
+        0x0F, 0x00, 0x00, 0x00, // DesiredFrameRate
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Reserved
+    ];
+
+    network_error: VideoPdu::ClientNotification(ClientNotification {
+        presentation_id: 1,
+        notification: Notification::NetworkError,
+    }),
+    [
+        0x10, 0x00, 0x00, 0x00, // cbSize
+        0x03, 0x00, 0x00, 0x00, // PacketType
+        0x01, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, // cbData
+    ];
+
+    video_data: VideoPdu::Data(video_data(7, 1, 1, &[0x00, 0x00, 0x01, 0x65])),
+    [
+        0x2C, 0x00, 0x00, 0x00, // cbSize
+        0x04, 0x00, 0x00, 0x00, // PacketType
+        0x01, // PresentationId
+        0x01, // Version
+        0x03, // Flags
+        0x00, // Reserved
+        0x80, 0x1A, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, // hnsTimestamp
+        0x15, 0x16, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, // hnsDuration
+        0x01, 0x00, // CurrentPacketIndex
+        0x01, 0x00, // PacketsInSample
+        0x07, 0x00, 0x00, 0x00, // SampleNumber
+        0x04, 0x00, 0x00, 0x00, // cbSample
+        0x00, 0x00, 0x01, 0x65, // pSample
+    ];
+
+    geometry_update: GeometryPdu {
+        mapping_id: MAPPING_ID,
+        update: GeometryUpdate::Update(mapped_geometry()),
+    },
+    [
+        0x78, 0x00, 0x00, 0x00, // cbGeometryBuffer
+        0x01, 0x00, 0x00, 0x00, // Version
+        0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // MappingId
+        0x00, 0x00, 0x00, 0x00, // UpdateType
+        0x00, 0x00, 0x00, 0x00, // Flags
+        0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TopLevelId
+        0x0A, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x8A, 0x02, 0x00, 0x00, 0x7C, 0x01, 0x00, 0x00, // Left..Bottom
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x03, 0x00, 0x00, 0x58, 0x02, 0x00, 0x00, // TopLevel
+        0x01, 0x00, 0x00, 0x00, // GeometryType
+        0x30, 0x00, 0x00, 0x00, // cbGeometryData
+        0x20, 0x00, 0x00, 0x00, // dwSize
+        0x01, 0x00, 0x00, 0x00, // iType
+        0x01, 0x00, 0x00, 0x00, // nCount
+        0x10, 0x00, 0x00, 0x00, // nRgnSize
+        0x0A, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x8A, 0x02, 0x00, 0x00, 0x7C, 0x01, 0x00, 0x00, // rcBound
+        0x0A, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x8A, 0x02, 0x00, 0x00, 0x7C, 0x01, 0x00, 0x00, // Buffer
+    ];
+
+    geometry_clear: GeometryPdu {
+        mapping_id: MAPPING_ID,
+        update: GeometryUpdate::Clear,
+    },
+    [
+        0x18, 0x00, 0x00, 0x00, // cbGeometryBuffer
+        0x01, 0x00, 0x00, 0x00, // Version
+        0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // MappingId
+        0x01, 0x00, 0x00, 0x00, // UpdateType
+        0x00, 0x00, 0x00, 0x00, // Flags
+    ];
+}
+
+#[test]
+fn truncated_video_data_is_rejected() {
+    assert_truncated_is_rejected::<VideoPdu>(&VideoPdu::Data(video_data(1, 1, 1, &[0xAA; 8])));
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    Start(u8, Option<MappedGeometry>),
+    Stop(u8),
+    Sample(u8, VideoSample),
+    Geometry(u64, Option<MappedGeometry>),
+    Closed,
+}
+
+impl VideoPresenter for Recorder<Event> {
+    fn start_presentation(&mut self, request: &PresentationRequest, geometry: Option<&MappedGeometry>) -> bool {
+        self.record(Event::Start(request.presentation_id, geometry.cloned()));
+
+        request.video_subtype == VideoSubtype::H264
+    }
+
+    fn stop_presentation(&mut self, presentation_id: u8) {
+        self.record(Event::Stop(presentation_id));
+    }
+
+    fn sample(&mut self, presentation_id: u8, sample: VideoSample) {
+        self.record(Event::Sample(presentation_id, sample));
+    }
+
+    fn geometry_changed(&mut self, mapping_id: u64, geometry: Option<&MappedGeometry>) {
+        self.record(Event::Geometry(mapping_id, geometry.cloned()));
+    }
+
+    fn closed(&mut self) {
+        self.record(Event::Closed);
+    }
+}
+
+#[test]
+fn video_presentation() {
+    let events = Events::<Event>::default();
+    let redirection = VideoRedirection::new(Box::new(Recorder::new(&events)));
+    let mut geometry = redirection.geometry_client();
+    let mut control = redirection.control_client();
+    let mut data = redirection.data_client();
+
+    assert!(geometry.start(CHANNEL_ID).unwrap().is_empty());
+    assert!(control.start(CHANNEL_ID).unwrap().is_empty());
+    assert!(data.start(CHANNEL_ID).unwrap().is_empty());
+
+    let update = GeometryPdu {
+        mapping_id: MAPPING_ID,
+        update: GeometryUpdate::Update(mapped_geometry()),
+    };
+    assert!(process(&mut geometry, &update).is_empty());
+    assert_eq!(geometry.geometry(MAPPING_ID), Some(mapped_geometry()));
+
+    // The presentation is acknowledged once accepted by the presenter.
+    let start = VideoPdu::PresentationRequest(presentation_request(1, PresentationCommand::Start));
+    assert_eq!(
+        decode_messages::<VideoPdu>(process(&mut control, &start)),
+        [VideoPdu::PresentationResponse(PresentationResponse {
+            presentation_id: 1
+        })]
+    );
+    assert!(control.is_presenting(1));
+
+    let mut unsupported = presentation_request(2, PresentationCommand::Start);
+    unsupported.video_subtype = VideoSubtype([0; 16]);
+    assert!(process(&mut control, &VideoPdu::PresentationRequest(unsupported)).is_empty());
+    assert!(!control.is_presenting(2));
+
+    // Samples split in several packets are handed over once complete.
+    assert!(process(&mut data, &VideoPdu::Data(video_data(1, 1, 2, &[0x01, 0x02]))).is_empty());
+    assert!(process(&mut data, &VideoPdu::Data(video_data(1, 2, 2, &[0x03]))).is_empty());
+
+    // A sample missing a packet is dropped.
+    process(&mut data, &VideoPdu::Data(video_data(2, 1, 3, &[0x04])));
+    process(&mut data, &VideoPdu::Data(video_data(2, 3, 3, &[0x06])));
+
+    let mut other = video_data(3, 1, 1, &[0x07]);
+    other.flags = VideoDataFlags::empty();
+    process(&mut data, &VideoPdu::Data(other));
+
+    assert_eq!(
+        control
+            .encode_notification(1, Notification::NetworkError)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(control.encode_notification(2, Notification::NetworkError).is_err());
+
+    let stop = VideoPdu::PresentationRequest(presentation_request(1, PresentationCommand::Stop));
+    assert!(process(&mut control, &stop).is_empty());
+    assert!(!control.is_presenting(1));
+
+    let clear = GeometryPdu {
+        mapping_id: MAPPING_ID,
+        update: GeometryUpdate::Clear,
+    };
+    process(&mut geometry, &clear);
+    assert_eq!(geometry.geometry(MAPPING_ID), None);
+
+    process(&mut control, &start);
+    control.close(CHANNEL_ID);
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Event::Geometry(MAPPING_ID, Some(mapped_geometry())),
+            Event::Start(1, Some(mapped_geometry())),
+            Event::Start(2, Some(mapped_geometry())),
+            Event::Sample(
+                1,
+                VideoSample {
+                    sample_number: 1,
+                    timestamp: Some(400_000),
+                    duration: Some(333_333),
+                    keyframe: true,
+                    data: vec![0x01, 0x02, 0x03],
+                }
+            ),
+            Event::Sample(
+                1,
+                VideoSample {
+                    sample_number: 3,
+                    timestamp: None,
+                    duration: None,
+                    keyframe: false,
+                    data: vec![0x07],
+                }
+            ),
+            Event::Stop(1),
+            Event::Geometry(MAPPING_ID, None),
+            Event::Start(1, None),
+            Event::Stop(1),
+            Event::Closed,
+        ]
+    );
+}
@@ -3,6 +3,8 @@ use std::borrow::Cow;
 use ironrdp_rdpsnd::pdu;
 use ironrdp_testsuite_core::encode_decode_test;
 
+mod server;
+
 encode_decode_test! {
     server_format: pdu::ServerAudioOutputPdu::AudioFormat(pdu::ServerAudioFormatPdu {
         version: pdu::Version::V5,
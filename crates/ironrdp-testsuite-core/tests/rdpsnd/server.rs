@@ -0,0 +1,217 @@
+use ironrdp_core::encode_vec;
+use ironrdp_rdpsnd::pcm::{self, PcmConverter, PcmFormat};
+use ironrdp_rdpsnd::pdu::{self, AudioFormat, ClientAudioFormatPdu, WaveFormat};
+use ironrdp_rdpsnd::server::{negotiate_format, AudioFrame, RdpsndServer, RdpsndServerHandler};
+use ironrdp_svc::{SvcMessage, SvcProcessor as _};
+
+fn pcm_format(n_channels: u16, n_samples_per_sec: u32, bits_per_sample: u16) -> AudioFormat {
+    let n_block_align = n_channels * bits_per_sample / 8;
+    AudioFormat {
+        format: WaveFormat::PCM,
+        n_channels,
+        n_samples_per_sec,
+        n_avg_bytes_per_sec: n_samples_per_sec * u32::from(n_block_align),
+        n_block_align,
+        bits_per_sample,
+        data: None,
+    }
+}
+
+fn opus_format() -> AudioFormat {
+    AudioFormat {
+        format: WaveFormat::OPUS,
+        ..pcm_format(2, 48000, 16)
+    }
+}
+
+#[test]
+fn negotiate_server_preference() {
+    let server = [opus_format(), pcm_format(2, 44100, 16)];
+    let client = [pcm_format(2, 44100, 16), opus_format()];
+
+    assert_eq!(negotiate_format(&server, &client), Some(1));
+    assert_eq!(negotiate_format(&server[1..], &client), Some(0));
+}
+
+#[test]
+fn negotiate_pcm_fallback() {
+    let server = [opus_format()];
+    let client = [
+        AudioFormat {
+            format: WaveFormat::AAC_MS,
+            ..pcm_format(2, 48000, 16)
+        },
+        pcm_format(2, 22050, 4),
+        pcm_format(1, 22050, 8),
+    ];
+
+    assert_eq!(negotiate_format(&server, &client), Some(2));
+    assert_eq!(negotiate_format(&server, &client[..2]), None);
+}
+
+#[test]
+fn convert_channels() {
+    let stereo = PcmFormat {
+        n_channels: 2,
+        n_samples_per_sec: 8000,
+    };
+    let mono = PcmFormat {
+        n_channels: 1,
+        n_samples_per_sec: 8000,
+    };
+
+    let mut converter = PcmConverter::new(stereo, mono);
+    assert_eq!(converter.convert(&[100, 300, -100, -300, 7]), [200, -200]);
+
+    let mut converter = PcmConverter::new(mono, stereo);
+    assert_eq!(converter.convert(&[1, 2]), [1, 1, 2, 2]);
+}
+
+#[test]
+fn convert_sample_rate() {
+    let input = PcmFormat {
+        n_channels: 1,
+        n_samples_per_sec: 8000,
+    };
+    let output = PcmFormat {
+        n_channels: 1,
+        n_samples_per_sec: 16000,
+    };
+    let mut converter = PcmConverter::new(input, output);
+
+    // The last input sample is kept to interpolate with the next one.
+    assert_eq!(converter.convert(&[0, 100, 200]), [0, 50, 100, 150]);
+    assert_eq!(converter.convert(&[400]), [200, 300]);
+
+    let mut converter = PcmConverter::new(output, input);
+    assert_eq!(converter.convert(&[0, 10, 20, 30, 40]), [0, 20]);
+    assert_eq!(converter.convert(&[50, 60]), [40]);
+}
+
+#[test]
+fn encode_samples() {
+    assert_eq!(pcm::decode_samples(&[0x01, 0x02, 0xff, 0xff, 0x03]), [0x0201, -1]);
+    assert_eq!(
+        pcm::encode_samples(&[0x0201, -1], 16).unwrap(),
+        [0x01, 0x02, 0xff, 0xff]
+    );
+    assert_eq!(
+        pcm::encode_samples(&[i16::MIN, 0, i16::MAX], 8).unwrap(),
+        [0x00, 0x80, 0xff]
+    );
+    assert!(pcm::encode_samples(&[0], 24).is_none());
+}
+
+#[derive(Debug)]
+struct Handler {
+    formats: Vec<AudioFormat>,
+}
+
+impl RdpsndServerHandler for Handler {
+    fn get_formats(&self) -> &[AudioFormat] {
+        &self.formats
+    }
+
+    fn start(&mut self, _client_format: &ClientAudioFormatPdu) -> Option<u16> {
+        None
+    }
+
+    fn stop(&mut self) {}
+}
+
+fn client_pdu(pdu: pdu::ClientAudioOutputPdu) -> Vec<u8> {
+    encode_vec(&pdu).unwrap()
+}
+
+fn ready_server(client_formats: Vec<AudioFormat>) -> RdpsndServer {
+    let mut server = RdpsndServer::new(Box::new(Handler {
+        formats: vec![pcm_format(2, 44100, 16)],
+    }));
+    assert_eq!(server.start().unwrap().len(), 1);
+
+    let client_format = pdu::ClientAudioOutputPdu::AudioFormat(ClientAudioFormatPdu {
+        version: pdu::Version::V8,
+        flags: pdu::AudioFormatFlags::ALIVE,
+        formats: client_formats,
+        volume_left: 0xffff,
+        volume_right: 0xffff,
+        pitch: 0,
+        dgram_port: 0,
+    });
+    assert!(server.process(&client_pdu(client_format)).unwrap().is_empty());
+
+    let quality_mode = pdu::ClientAudioOutputPdu::QualityMode(pdu::QualityModePdu {
+        quality_mode: pdu::QualityMode::High,
+    });
+    // Training
+    assert_eq!(server.process(&client_pdu(quality_mode)).unwrap().len(), 1);
+    assert!(server.latency().is_none());
+
+    let training_confirm = pdu::ClientAudioOutputPdu::TrainingConfirm(pdu::TrainingConfirmPdu {
+        timestamp: 0,
+        pack_size: 0,
+    });
+    assert!(server.process(&client_pdu(training_confirm)).unwrap().is_empty());
+    assert!(server.latency().is_some());
+
+    server
+}
+
+fn samples(frames: usize) -> Vec<u8> {
+    (0..frames)
+        .flat_map(|frame| i16::try_from(frame).unwrap().to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn push_audio() {
+    let mut server = ready_server(vec![opus_format(), pcm_format(1, 8000, 16)]);
+    assert_eq!(server.format(), Some(&pcm_format(1, 8000, 16)));
+
+    // 20 ms blocks of 160 frames at 8 kHz.
+    let audio = |data, timestamp| AudioFrame {
+        data,
+        n_channels: 1,
+        n_samples_per_sec: 8000,
+        timestamp,
+    };
+    let sent = |messages: ironrdp_rdpsnd::server::RdpsndSvcMessages| Vec::<SvcMessage>::from(messages).len();
+
+    assert_eq!(sent(server.push_audio(audio(samples(320), 1000)).unwrap()), 2);
+    assert_eq!(sent(server.push_audio(audio(samples(100), 1040)).unwrap()), 0);
+    assert_eq!(sent(server.push_audio(audio(samples(60), 1052)).unwrap()), 1);
+
+    // Stereo audio is down-mixed, and doesn't fill a block.
+    let stereo = AudioFrame {
+        n_channels: 2,
+        ..audio(samples(200), 1060)
+    };
+    assert_eq!(sent(server.push_audio(stereo).unwrap()), 0);
+
+    assert!(server.playback_delay().is_none());
+    let wave_confirm = pdu::ClientAudioOutputPdu::WaveConfirm(pdu::WaveConfirmPdu {
+        timestamp: 0,
+        block_no: 2,
+    });
+    assert!(server.process(&client_pdu(wave_confirm)).unwrap().is_empty());
+    assert!(server.playback_delay().is_some());
+}
+
+#[test]
+fn push_audio_unsupported_format() {
+    let mut server = ready_server(vec![AudioFormat {
+        format: WaveFormat::AAC_MS,
+        ..pcm_format(2, 44100, 16)
+    }]);
+
+    // None of the client formats can be converted to.
+    assert_eq!(server.format().map(|format| format.format), None);
+
+    let frame = AudioFrame {
+        data: samples(160),
+        n_channels: 1,
+        n_samples_per_sec: 8000,
+        timestamp: 0,
+    };
+    assert!(server.push_audio(frame).is_err());
+}
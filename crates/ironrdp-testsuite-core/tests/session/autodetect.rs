@@ -0,0 +1,113 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_pdu::rdp::autodetect::{AutoDetectPhase, AutoDetectRequest, AutoDetectResponse, BandwidthPhase};
+use ironrdp_session::autodetect::{AutoDetectResponder, NetworkCharacteristics};
+
+#[test]
+fn answers_rtt_requests() {
+    let mut responder = AutoDetectResponder::new();
+
+    let request = AutoDetectRequest::RttRequest {
+        sequence_number: 7,
+        phase: AutoDetectPhase::Session,
+    };
+
+    assert_eq!(
+        responder.handle_request(&request, 10, Instant::now()),
+        Some(AutoDetectResponse::RttResponse { sequence_number: 7 })
+    );
+}
+
+#[test]
+fn measures_connect_time_bandwidth() {
+    let mut responder = AutoDetectResponder::new();
+    let start = Instant::now();
+
+    let request = AutoDetectRequest::BandwidthStart {
+        sequence_number: 1,
+        phase: BandwidthPhase::Connection,
+    };
+    assert_eq!(responder.handle_request(&request, 10, start), None);
+
+    let request = AutoDetectRequest::BandwidthPayload {
+        sequence_number: 2,
+        payload: vec![0; 1000],
+    };
+    assert_eq!(
+        responder.handle_request(&request, 1012, start + Duration::from_millis(5)),
+        None
+    );
+
+    let request = AutoDetectRequest::BandwidthStop {
+        sequence_number: 3,
+        phase: BandwidthPhase::Connection,
+        payload: vec![0; 500],
+    };
+    assert_eq!(
+        responder.handle_request(&request, 512, start + Duration::from_millis(20)),
+        Some(AutoDetectResponse::BandwidthResults {
+            sequence_number: 3,
+            phase: AutoDetectPhase::Connection,
+            time_delta: 20,
+            byte_count: 1524,
+        })
+    );
+}
+
+#[test]
+fn measures_continuous_bandwidth_over_session_data() {
+    let mut responder = AutoDetectResponder::new();
+    let start = Instant::now();
+
+    // Not counted, no measure in progress
+    responder.bytes_received(100);
+
+    let request = AutoDetectRequest::BandwidthStart {
+        sequence_number: 1,
+        phase: BandwidthPhase::Session,
+    };
+    responder.handle_request(&request, 10, start);
+    responder.bytes_received(4000);
+
+    let request = AutoDetectRequest::BandwidthStop {
+        sequence_number: 2,
+        phase: BandwidthPhase::Session,
+        payload: Vec::new(),
+    };
+    assert_eq!(
+        responder.handle_request(&request, 10, start + Duration::from_millis(8)),
+        Some(AutoDetectResponse::BandwidthResults {
+            sequence_number: 2,
+            phase: AutoDetectPhase::Session,
+            time_delta: 8,
+            byte_count: 4010,
+        })
+    );
+
+    // A stop without a start is ignored
+    assert_eq!(responder.handle_request(&request, 10, start), None);
+}
+
+#[test]
+fn keeps_network_characteristics() {
+    let mut responder = AutoDetectResponder::new();
+    assert_eq!(responder.network_characteristics(), NetworkCharacteristics::default());
+
+    let request = AutoDetectRequest::NetworkCharacteristicsResult {
+        sequence_number: 4,
+        base_rtt: Some(10),
+        bandwidth: Some(20_000),
+        average_rtt: 15,
+    };
+    assert_eq!(responder.handle_request(&request, 28, Instant::now()), None);
+
+    assert_eq!(
+        responder.network_characteristics(),
+        NetworkCharacteristics {
+            base_rtt: Some(Duration::from_millis(10)),
+            average_rtt: Some(Duration::from_millis(15)),
+            bandwidth: Some(20_000),
+        }
+    );
+}
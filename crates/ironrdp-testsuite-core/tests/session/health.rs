@@ -0,0 +1,107 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+use ironrdp_session::health::{ConnectionHealth, HealthEvent, HealthMonitor};
+
+const HEARTBEAT: HeartbeatPdu = HeartbeatPdu {
+    period: 5,
+    warning_count: 2,
+    reconnect_count: 4,
+};
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+#[test]
+fn reports_missed_heartbeats() {
+    let start = Instant::now();
+    let mut monitor = HealthMonitor::new(start);
+
+    // No heartbeat configured yet
+    assert_eq!(monitor.poll(start + secs(60)), []);
+
+    monitor.heartbeat_received(HEARTBEAT, start);
+    assert_eq!(monitor.poll(start + secs(9)), []);
+    assert_eq!(monitor.metrics().missed_heartbeats, 0);
+
+    assert_eq!(
+        monitor.poll(start + secs(15)),
+        [HealthEvent::Unstable { missed_heartbeats: 2 }]
+    );
+    assert_eq!(monitor.health(), ConnectionHealth::Unstable);
+    assert_eq!(monitor.poll(start + secs(16)), []);
+
+    assert_eq!(
+        monitor.poll(start + secs(25)),
+        [HealthEvent::ReconnectAdvised { missed_heartbeats: 4 }]
+    );
+    assert_eq!(monitor.health(), ConnectionHealth::Lost);
+
+    monitor.heartbeat_received(HEARTBEAT, start + secs(26));
+    assert_eq!(monitor.poll(start + secs(27)), [HealthEvent::Recovered]);
+    assert_eq!(monitor.health(), ConnectionHealth::Healthy);
+}
+
+#[test]
+fn disabled_heartbeats_are_not_missed() {
+    let start = Instant::now();
+    let mut monitor = HealthMonitor::new(start);
+
+    monitor.heartbeat_received(HeartbeatPdu { period: 0, ..HEARTBEAT }, start);
+
+    assert_eq!(monitor.poll(start + secs(3600)), []);
+    assert_eq!(monitor.health(), ConnectionHealth::Healthy);
+}
+
+#[test]
+fn reports_high_jitter() {
+    let mut monitor = HealthMonitor::new(Instant::now()).with_jitter_threshold(Duration::from_millis(5));
+
+    monitor.rtt_measured(Duration::from_millis(20));
+    monitor.rtt_measured(Duration::from_millis(20));
+    assert_eq!(monitor.metrics().jitter, Duration::ZERO);
+
+    // Alternating between 20 and 180 ms, the jitter grows by 10 ms at first
+    monitor.rtt_measured(Duration::from_millis(180));
+    assert_eq!(monitor.metrics().jitter, Duration::from_millis(10));
+    assert_eq!(monitor.metrics().last_rtt, Some(Duration::from_millis(180)));
+
+    let events = monitor.poll(Instant::now());
+    assert!(matches!(events[..], [HealthEvent::HighJitter { .. }]));
+
+    // Reported once
+    monitor.rtt_measured(Duration::from_millis(20));
+    assert_eq!(monitor.poll(Instant::now()), []);
+}
+
+#[test]
+fn reports_stalled_channels() {
+    let start = Instant::now();
+    let mut monitor = HealthMonitor::new(start).with_stall_timeout(secs(10));
+
+    monitor.channel_waiting(1004, start);
+    monitor.channel_waiting(1005, start + secs(5));
+    // Still waiting since the first request
+    monitor.channel_waiting(1004, start + secs(8));
+
+    assert_eq!(
+        monitor.poll(start + secs(12)),
+        [HealthEvent::ChannelStalled {
+            channel_id: 1004,
+            waiting: secs(12),
+        }]
+    );
+    assert_eq!(monitor.metrics().stalled_channels, 1);
+
+    // Answered before the timeout
+    monitor.channel_activity(1005);
+    monitor.channel_activity(1004);
+
+    assert_eq!(
+        monitor.poll(start + secs(20)),
+        [HealthEvent::ChannelResumed { channel_id: 1004 }]
+    );
+    assert_eq!(monitor.metrics().stalled_channels, 0);
+}
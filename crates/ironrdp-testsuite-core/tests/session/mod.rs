@@ -1,3 +1,7 @@
+mod autodetect;
+mod health;
+mod overlay;
+mod replay;
 mod rfx;
 
 #[cfg(test)]
@@ -0,0 +1,168 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_session::overlay::{OverlayPosition, Stats, StatsCollector, StatsOverlay};
+
+const WIDTH: u16 = 200;
+const HEIGHT: u16 = 100;
+
+fn stats() -> Stats {
+    Stats {
+        fps: 60,
+        bitrate: 2_500_000,
+        rtt: Some(Duration::from_millis(12)),
+        codec: Some("RemoteFX".to_owned()),
+        dropped_frames: 3,
+    }
+}
+
+/// Opaque frame filled with a mid gray, in `BgrX32`
+fn frame(width: u16, height: u16) -> Vec<u8> {
+    vec![0x80; usize::from(width) * usize::from(height) * 4]
+}
+
+fn pixel(data: &[u8], x: usize, y: usize) -> &[u8] {
+    let start = (y * usize::from(WIDTH) + x) * 4;
+    &data[start..start + 4]
+}
+
+#[test]
+fn stats_lines() {
+    assert_eq!(
+        stats().lines(),
+        [
+            "FPS: 60",
+            "Bitrate: 2.5 Mbps",
+            "RTT: 12 ms",
+            "Codec: RemoteFX",
+            "Dropped: 3"
+        ]
+    );
+
+    assert_eq!(
+        Stats::default().lines(),
+        ["FPS: 0", "Bitrate: 0 kbps", "RTT: -", "Codec: -", "Dropped: 0"]
+    );
+}
+
+#[test]
+fn collector_averages_over_a_second() {
+    let start = Instant::now();
+    let mut collector = StatsCollector::new(start);
+
+    for i in 0..30 {
+        let now = start + Duration::from_millis(i * 30);
+        collector.bytes_received(1_000, now);
+        collector.frame_rendered(now);
+    }
+    collector.frame_dropped();
+
+    // The window isn't over yet
+    assert_eq!(collector.stats().fps, 0);
+
+    collector.update(start + Duration::from_secs(1));
+    assert_eq!(collector.stats().fps, 30);
+    assert_eq!(collector.stats().bitrate, 240_000);
+    assert_eq!(collector.stats().dropped_frames, 1);
+
+    // No frames received during the next window
+    collector.update(start + Duration::from_secs(2));
+    assert_eq!(collector.stats().fps, 0);
+    assert_eq!(collector.stats().bitrate, 0);
+}
+
+#[test]
+fn draw_top_left() {
+    let mut data = frame(WIDTH, HEIGHT);
+
+    let area = StatsOverlay::default()
+        .draw(&stats(), &mut data, PixelFormat::BgrX32, WIDTH, HEIGHT)
+        .unwrap();
+
+    // 17 columns ("Bitrate: 2.5 Mbps") and 5 lines with the padding
+    assert_eq!(
+        area,
+        InclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: 17 * 6 - 1 + 8 - 1,
+            bottom: 5 * 9 - 2 + 8 - 1,
+        }
+    );
+
+    // The background is blended with the frame
+    assert_eq!(pixel(&data, 0, 0), [0x30, 0x30, 0x30, 0xFF]);
+    // Top left pixel of the vertical bar of "F"
+    assert_eq!(pixel(&data, 4, 4), [0xFF, 0xFF, 0xFF, 0xFF]);
+    // Outside of the overlay
+    assert_eq!(pixel(&data, 150, 80), [0x80, 0x80, 0x80, 0x80]);
+}
+
+#[test]
+fn draw_bottom_right() {
+    let mut data = frame(WIDTH, HEIGHT);
+    let overlay = StatsOverlay {
+        position: OverlayPosition::BottomRight,
+        ..StatsOverlay::default()
+    };
+
+    let area = overlay
+        .draw(&stats(), &mut data, PixelFormat::BgrX32, WIDTH, HEIGHT)
+        .unwrap();
+
+    assert_eq!(
+        area,
+        InclusiveRectangle {
+            left: WIDTH - (17 * 6 - 1 + 8),
+            top: HEIGHT - (5 * 9 - 2 + 8),
+            right: WIDTH - 1,
+            bottom: HEIGHT - 1,
+        }
+    );
+    assert_eq!(pixel(&data, 0, 0), [0x80, 0x80, 0x80, 0x80]);
+    assert_eq!(
+        pixel(&data, usize::from(WIDTH) - 1, usize::from(HEIGHT) - 1),
+        [0x30, 0x30, 0x30, 0xFF]
+    );
+}
+
+#[test]
+fn draw_scaled_is_clipped() {
+    let mut data = frame(WIDTH, HEIGHT);
+    let overlay = StatsOverlay {
+        scale: 2,
+        ..StatsOverlay::default()
+    };
+
+    let area = overlay
+        .draw(&stats(), &mut data, PixelFormat::BgrX32, WIDTH, HEIGHT)
+        .unwrap();
+
+    // The overlay is larger than the frame
+    assert_eq!(
+        area,
+        InclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: WIDTH - 1,
+            bottom: HEIGHT - 1,
+        }
+    );
+    // Each pixel of the font is doubled
+    assert_eq!(pixel(&data, 8, 8), [0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(pixel(&data, 9, 9), [0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(pixel(&data, 7, 7), [0x30, 0x30, 0x30, 0xFF]);
+}
+
+#[test]
+fn draw_on_empty_frame() {
+    let overlay = StatsOverlay::default();
+
+    assert!(overlay.draw(&stats(), &mut [], PixelFormat::BgrX32, 0, 0).is_none());
+    // The buffer doesn't match the dimensions
+    assert!(overlay
+        .draw(&stats(), &mut [0; 16], PixelFormat::BgrX32, WIDTH, HEIGHT)
+        .is_none());
+}
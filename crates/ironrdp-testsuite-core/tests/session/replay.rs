@@ -0,0 +1,86 @@
+use ironrdp_connector::DesktopSize;
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags};
+use ironrdp_pdu::Action;
+use ironrdp_session::image::DecodedImage;
+use ironrdp_session::replay::{Capture, CaptureEvent, FrameDigest, Recorder};
+
+const DESKTOP_SIZE: DesktopSize = DesktopSize { width: 64, height: 32 };
+
+fn record() -> Capture {
+    let image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_SIZE.width, DESKTOP_SIZE.height);
+
+    let mut recorder = Recorder::new(DESKTOP_SIZE, PixelFormat::RgbA32);
+    recorder.frame(Action::FastPath, &[0x00, 0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+    recorder.input(&[
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1E),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE, 0x1E),
+    ]);
+    recorder.frame(Action::X224, &[0x03, 0x00, 0x00, 0x04]);
+    recorder.checkpoint(&image);
+    recorder.finish()
+}
+
+#[test]
+fn capture_roundtrip() {
+    let capture = record();
+    assert_eq!(capture.records.len(), 4);
+
+    let encoded = encode_vec(&capture).unwrap();
+    let decoded = decode::<Capture>(&encoded).unwrap();
+
+    // The timestamps are stored in milliseconds.
+    assert_eq!(decoded.desktop_size, capture.desktop_size);
+    assert_eq!(decoded.pixel_format, capture.pixel_format);
+    for (decoded, recorded) in decoded.records.iter().zip(&capture.records) {
+        assert_eq!(decoded.event, recorded.event);
+        assert_eq!(decoded.timestamp.as_millis(), recorded.timestamp.as_millis());
+    }
+}
+
+#[test]
+fn capture_bad_magic() {
+    let mut encoded = encode_vec(&record()).unwrap();
+    encoded[0] = b'X';
+
+    assert!(decode::<Capture>(&encoded).is_err());
+}
+
+#[test]
+fn capture_truncated() {
+    let encoded = encode_vec(&record()).unwrap();
+
+    assert!(decode::<Capture>(&encoded[..encoded.len() - 1]).is_err());
+}
+
+#[test]
+fn recorder_skips_empty_input() {
+    let mut recorder = Recorder::new(DESKTOP_SIZE, PixelFormat::RgbA32);
+    recorder.input(&[]);
+
+    assert!(recorder.finish().records.is_empty());
+}
+
+#[test]
+fn frame_digest() {
+    let image = DecodedImage::new(PixelFormat::RgbA32, 64, 32);
+    let same = DecodedImage::new(PixelFormat::RgbA32, 64, 32);
+    // Same pixels, laid out differently.
+    let transposed = DecodedImage::new(PixelFormat::RgbA32, 32, 64);
+
+    assert_eq!(FrameDigest::of(&image), FrameDigest::of(&same));
+    assert_ne!(FrameDigest::of(&image), FrameDigest::of(&transposed));
+    assert_eq!(FrameDigest(0xAB).to_string(), "00000000000000ab");
+}
+
+#[test]
+fn checkpoint_holds_digest() {
+    let capture = record();
+    let image = capture.new_image();
+
+    assert_eq!(
+        capture.records.last().unwrap().event,
+        CaptureEvent::Checkpoint(FrameDigest::of(&image))
+    );
+}
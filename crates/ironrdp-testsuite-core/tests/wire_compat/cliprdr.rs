@@ -0,0 +1,71 @@
+use ironrdp_cliprdr::pdu::{
+    Capabilities, ClipboardFormat, ClipboardFormatId, ClipboardFormatName, ClipboardGeneralCapabilityFlags,
+    ClipboardPdu, ClipboardProtocolVersion, FileContentsFlags, FileContentsRequest, FileContentsResponse,
+    FormatDataRequest, FormatDataResponse, FormatList, FormatListResponse, LockDataId,
+};
+
+use super::Corpus;
+
+#[test]
+fn cliprdr() {
+    let formats = [
+        ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT),
+        ClipboardFormat::new(ClipboardFormatId::new(0xC0A4)).with_name(ClipboardFormatName::new("HTML Format")),
+    ];
+
+    Corpus::new("ironrdp-cliprdr")
+        .add(
+            "capabilities",
+            &ClipboardPdu::Capabilities(Capabilities::new(
+                ClipboardProtocolVersion::V2,
+                ClipboardGeneralCapabilityFlags::USE_LONG_FORMAT_NAMES
+                    | ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED
+                    | ClipboardGeneralCapabilityFlags::CAN_LOCK_CLIPDATA,
+            )),
+        )
+        .add("monitor_ready", &ClipboardPdu::MonitorReady)
+        .add(
+            "format_list_long",
+            &ClipboardPdu::FormatList(FormatList::new_unicode(&formats, true).unwrap()),
+        )
+        .add(
+            "format_list_short",
+            &ClipboardPdu::FormatList(FormatList::new_unicode(&formats, false).unwrap()),
+        )
+        .add(
+            "format_list_response",
+            &ClipboardPdu::FormatListResponse(FormatListResponse::Ok),
+        )
+        .add(
+            "format_data_request",
+            &ClipboardPdu::FormatDataRequest(FormatDataRequest {
+                format: ClipboardFormatId::CF_UNICODETEXT,
+            }),
+        )
+        .add(
+            "format_data_response",
+            &ClipboardPdu::FormatDataResponse(FormatDataResponse::new_unicode_string("IronRDP")),
+        )
+        .add(
+            "format_data_response_error",
+            &ClipboardPdu::FormatDataResponse(FormatDataResponse::new_error()),
+        )
+        .add(
+            "file_contents_request",
+            &ClipboardPdu::FileContentsRequest(FileContentsRequest {
+                stream_id: 1,
+                index: 2,
+                flags: FileContentsFlags::DATA,
+                position: 0x1_0000_0000,
+                requested_size: 4096,
+                data_id: Some(3),
+            }),
+        )
+        .add(
+            "file_contents_response_size",
+            &ClipboardPdu::FileContentsResponse(FileContentsResponse::new_size_response(1, 0x1_0000_0001)),
+        )
+        .add("lock_data", &ClipboardPdu::LockData(LockDataId(3)))
+        .add("unlock_data", &ClipboardPdu::UnlockData(LockDataId(3)))
+        .check();
+}
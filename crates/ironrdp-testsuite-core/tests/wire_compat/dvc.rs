@@ -0,0 +1,80 @@
+use ironrdp_dvc::pdu::{
+    CapabilitiesRequestPdu, CapabilitiesResponsePdu, CapsVersion, ChannelPriority, ClosePdu, CreateRequestPdu,
+    CreateResponsePdu, CreationStatus, DataFirstPdu, DataPdu, DrdynvcClientPdu, DrdynvcDataPdu, DrdynvcServerPdu,
+    SoftSyncChannelList, SoftSyncFlags, SoftSyncRequestPdu, SoftSyncResponsePdu, TunnelType,
+};
+
+use super::Corpus;
+
+#[test]
+fn drdynvc() {
+    Corpus::new("ironrdp-dvc")
+        .add(
+            "capabilities_request_v1",
+            &DrdynvcServerPdu::Capabilities(CapabilitiesRequestPdu::new(CapsVersion::V1, None)),
+        )
+        .add(
+            "capabilities_request_v2",
+            &DrdynvcServerPdu::Capabilities(CapabilitiesRequestPdu::new(
+                CapsVersion::V2,
+                Some(CapabilitiesRequestPdu::DEFAULT_PRIORITY_CHARGES),
+            )),
+        )
+        .add(
+            "capabilities_response_v3",
+            &DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(CapsVersion::V3)),
+        )
+        .add(
+            "create_request",
+            &DrdynvcServerPdu::Create(CreateRequestPdu::new(0x03, "testdvc".to_owned())),
+        )
+        .add(
+            "create_request_priority",
+            &DrdynvcServerPdu::Create(
+                CreateRequestPdu::new(0x1234, "Microsoft::Windows::RDS::Graphics".to_owned())
+                    .with_priority(ChannelPriority::Low),
+            ),
+        )
+        .add(
+            "create_response",
+            &DrdynvcClientPdu::Create(CreateResponsePdu::new(0x03, CreationStatus::OK)),
+        )
+        .add(
+            "create_response_no_listener",
+            &DrdynvcClientPdu::Create(CreateResponsePdu::new(0x0012_3456, CreationStatus::NO_LISTENER)),
+        )
+        .add(
+            "data_first",
+            &DrdynvcServerPdu::Data(DrdynvcDataPdu::DataFirst(DataFirstPdu::new(
+                0x03,
+                0x0001_0000,
+                vec![0x01, 0x02, 0x03, 0x04],
+            ))),
+        )
+        .add(
+            "data",
+            &DrdynvcClientPdu::Data(DrdynvcDataPdu::Data(DataPdu::new(0x1234, vec![0x05, 0x06, 0x07]))),
+        )
+        .add(
+            "data_compressed",
+            &DrdynvcServerPdu::Data(DrdynvcDataPdu::Data(
+                DataPdu::new(0x03, vec![0xE0, 0x04, 0x61, 0x62, 0x63]).with_compressed_data(),
+            )),
+        )
+        .add("close", &DrdynvcServerPdu::Close(ClosePdu::new(0x03)))
+        .add(
+            "soft_sync_request",
+            &DrdynvcServerPdu::SoftSyncRequest(SoftSyncRequestPdu::new(
+                SoftSyncFlags::TCP_FLUSHED,
+                vec![SoftSyncChannelList {
+                    tunnel: TunnelType::UDP_FECR,
+                    channel_ids: vec![3, 4],
+                }],
+            )),
+        )
+        .add(
+            "soft_sync_response",
+            &DrdynvcClientPdu::SoftSyncResponse(SoftSyncResponsePdu::new(vec![TunnelType::UDP_FECL])),
+        )
+        .check();
+}
@@ -0,0 +1,79 @@
+use ironrdp_egfx::pdu::{
+    CapabilitiesAdvertisePdu, CapabilitiesConfirmPdu, CapabilitiesV107Flags, CapabilitiesV81Flags, CapabilitySet,
+    Color, CreateSurfacePdu, EndFramePdu, FrameAcknowledgePdu, GfxPdu, PixelFormat, QueueDepth, SolidFillPdu,
+    StartFramePdu, Timestamp,
+};
+use ironrdp_pdu::geometry::InclusiveRectangle;
+
+use super::Corpus;
+
+#[test]
+fn egfx() {
+    Corpus::new("ironrdp-egfx")
+        .add(
+            "capabilities_advertise",
+            &GfxPdu::CapabilitiesAdvertise(CapabilitiesAdvertisePdu(vec![
+                CapabilitySet::V8_1 {
+                    flags: CapabilitiesV81Flags::AVC420_ENABLED,
+                },
+                CapabilitySet::V10_7 {
+                    flags: CapabilitiesV107Flags::SMALL_CACHE,
+                },
+            ])),
+        )
+        .add(
+            "capabilities_confirm",
+            &GfxPdu::CapabilitiesConfirm(CapabilitiesConfirmPdu(CapabilitySet::V10_7 {
+                flags: CapabilitiesV107Flags::SMALL_CACHE,
+            })),
+        )
+        .add(
+            "create_surface",
+            &GfxPdu::CreateSurface(CreateSurfacePdu {
+                surface_id: 1,
+                width: 1920,
+                height: 1080,
+                pixel_format: PixelFormat::XRgb,
+            }),
+        )
+        .add(
+            "start_frame",
+            &GfxPdu::StartFrame(StartFramePdu {
+                timestamp: Timestamp {
+                    milliseconds: 250,
+                    seconds: 30,
+                    minutes: 15,
+                    hours: 10,
+                },
+                frame_id: 7,
+            }),
+        )
+        .add(
+            "solid_fill",
+            &GfxPdu::SolidFill(SolidFillPdu {
+                surface_id: 1,
+                fill_pixel: Color {
+                    b: 0x10,
+                    g: 0x20,
+                    r: 0x30,
+                    xa: 0xFF,
+                },
+                rectangles: vec![InclusiveRectangle {
+                    left: 0,
+                    top: 0,
+                    right: 63,
+                    bottom: 63,
+                }],
+            }),
+        )
+        .add("end_frame", &GfxPdu::EndFrame(EndFramePdu { frame_id: 7 }))
+        .add(
+            "frame_acknowledge",
+            &GfxPdu::FrameAcknowledge(FrameAcknowledgePdu {
+                queue_depth: QueueDepth::AvailableBytes(1024),
+                frame_id: 7,
+                total_frames_decoded: 8,
+            }),
+        )
+        .check();
+}
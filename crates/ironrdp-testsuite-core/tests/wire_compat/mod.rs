@@ -0,0 +1,114 @@
+//! Wire compatibility corpus
+//!
+//! Each module encodes a fixed set of scenarios with the encoders of a crate, and compares the bytes against the golden
+//! file recorded for this crate in `test_data/wire_compat/`. The golden files are kept across releases: a difference
+//! means the bytes sent to the peers changed.
+//!
+//! When the change is intended, re-record the golden files with `UPDATE_EXPECT=1 cargo test wire_compat` and mention
+//! the wire change in the changelog of the crate.
+
+mod cliprdr;
+mod dvc;
+mod egfx;
+mod pdu;
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use ironrdp_core::{encode_vec, Encode};
+
+struct Corpus {
+    crate_name: &'static str,
+    scenarios: BTreeMap<&'static str, Vec<u8>>,
+}
+
+impl Corpus {
+    fn new(crate_name: &'static str) -> Self {
+        Self {
+            crate_name,
+            scenarios: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, name: &'static str, pdu: &impl Encode) -> &mut Self {
+        let encoded = encode_vec(pdu).unwrap_or_else(|e| panic!("failed to encode scenario {name}: {e}"));
+        assert!(
+            self.scenarios.insert(name, encoded).is_none(),
+            "duplicated scenario {name}"
+        );
+        self
+    }
+
+    fn check(&self) {
+        let path = format!(
+            "{}/test_data/wire_compat/{}.txt",
+            env!("CARGO_MANIFEST_DIR"),
+            self.crate_name
+        );
+        let version = crate_version(self.crate_name);
+
+        if std::env::var("UPDATE_EXPECT").unwrap_or_default() == "1" {
+            let mut golden = format!("# {} {version}\n", self.crate_name);
+            for (name, encoded) in &self.scenarios {
+                writeln!(golden, "{name} = {}", hex::encode(encoded)).unwrap();
+            }
+            std::fs::write(&path, golden).unwrap();
+            return;
+        }
+
+        let golden = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"));
+        let mut lines = golden.lines();
+        let recorded_version = lines
+            .next()
+            .and_then(|header| header.strip_prefix('#'))
+            .and_then(|header| header.split_whitespace().nth(1))
+            .unwrap_or_else(|| panic!("missing header in {path}"));
+        let recorded: BTreeMap<&str, &str> = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.split_once(" = ")
+                    .unwrap_or_else(|| panic!("invalid line in {path}: {line}"))
+            })
+            .collect();
+
+        let mut differences = Vec::new();
+
+        for (name, encoded) in &self.scenarios {
+            let encoded = hex::encode(encoded);
+            match recorded.get(name) {
+                Some(expected) if *expected == encoded => {}
+                Some(expected) => differences.push(format!(
+                    "{name}: changed\n  recorded: {expected}\n  encoded:  {encoded}"
+                )),
+                None => differences.push(format!("{name}: not recorded")),
+            }
+        }
+
+        for name in recorded.keys() {
+            if !self.scenarios.contains_key(name) {
+                differences.push(format!("{name}: removed from the corpus"));
+            }
+        }
+
+        assert!(
+            differences.is_empty(),
+            "wire format of {} {version} differs from the one recorded with {recorded_version}:\n{}\n\
+             If the change is intended, re-record {path} with UPDATE_EXPECT=1",
+            self.crate_name,
+            differences.join("\n"),
+        );
+    }
+}
+
+/// Version of a workspace crate, read from its manifest
+fn crate_version(crate_name: &str) -> String {
+    let path = format!("{}/../{crate_name}/Cargo.toml", env!("CARGO_MANIFEST_DIR"));
+    let manifest = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"));
+
+    manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("version = \""))
+        .and_then(|version| version.strip_suffix('"'))
+        .unwrap_or_else(|| panic!("missing version in {path}"))
+        .to_owned()
+}
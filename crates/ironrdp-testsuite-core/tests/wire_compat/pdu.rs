@@ -0,0 +1,36 @@
+use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent, KeyboardFlags};
+use ironrdp_pdu::input::mouse::PointerFlags;
+use ironrdp_pdu::input::MousePdu;
+use ironrdp_pdu::nego::{ConnectionRequest, Cookie, NegoRequestData, RequestFlags, SecurityProtocol};
+use ironrdp_pdu::x224::X224;
+
+use super::Corpus;
+
+#[test]
+fn pdu() {
+    Corpus::new("ironrdp-pdu")
+        .add(
+            "connection_request",
+            &X224(ConnectionRequest {
+                nego_data: Some(NegoRequestData::Cookie(Cookie("User".to_owned()))),
+                flags: RequestFlags::empty(),
+                protocol: SecurityProtocol::SSL | SecurityProtocol::HYBRID,
+            }),
+        )
+        .add(
+            "fast_path_input",
+            &FastPathInput::new(vec![
+                FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1E),
+                FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE, 0x1E),
+                FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0x00E9),
+                FastPathInputEvent::MouseEvent(MousePdu {
+                    flags: PointerFlags::DOWN | PointerFlags::LEFT_BUTTON,
+                    number_of_wheel_rotation_units: 0,
+                    x_position: 26,
+                    y_position: 1062,
+                }),
+            ])
+            .unwrap(),
+        )
+        .check();
+}
@@ -2,18 +2,28 @@
 #![allow(clippy::unwrap_used, reason = "unwrap is fine in tests")]
 
 use core::future::Future;
+use core::net::SocketAddr;
+use core::num::{NonZeroU16, NonZeroUsize};
 use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::Result;
-use ironrdp::connector;
+use ironrdp::connector::connection_activation::ConnectionActivationSequence;
+use ironrdp::connector::{self, ClientConnector, ClientConnectorState, Sequence as _};
+use ironrdp::pdu::geometry::InclusiveRectangle;
+use ironrdp::pdu::mcs::{SendDataIndication, SendDataRequest};
 use ironrdp::pdu::rdp::capability_sets::MajorPlatformType;
-use ironrdp::pdu::{self, gcc};
+use ironrdp::pdu::rdp::multitransport::{
+    InitiateMultitransportRequest, InitiateMultitransportResponse, MultitransportProtocol,
+};
+use ironrdp::pdu::x224::X224;
+use ironrdp::pdu::{self, gcc, nego, WriteBuf};
 use ironrdp::server::{
-    self, DesktopSize, DisplayUpdate, KeyboardEvent, MouseEvent, PixelFormat, RdpServer, RdpServerDisplay,
-    RdpServerDisplayUpdates, RdpServerInputHandler, ServerEvent, TlsIdentityCtx,
+    self, BitmapUpdate, DesktopSize, DisplayUpdate, KeyboardEvent, MouseEvent, PixelFormat, RdpServer,
+    RdpServerDisplay, RdpServerDisplayUpdates, RdpServerInputHandler, ServerEvent, SessionAccess, TlsIdentityCtx,
 };
 use ironrdp::session::image::DecodedImage;
+use ironrdp::session::x224::ControlStatus;
 use ironrdp::session::{self, ActiveStage, ActiveStageOutput};
 use ironrdp_async::{Framed, FramedWrite as _};
 use ironrdp_testsuite_extra as _;
@@ -37,6 +47,64 @@ async fn test_client_server() {
     .await
 }
 
+#[tokio::test]
+async fn test_client_server_nla() {
+    let mut client_config = default_client_config();
+    client_config.credentials = connector::Credentials::UsernamePassword {
+        username: "user".into(),
+        password: "pass".into(),
+    };
+
+    client_server_with_security(
+        ServerSecurity::Hybrid,
+        client_config,
+        |stage, framed, _display_tx| async { (stage, framed) },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_nla_required() {
+    let mut client_config = default_client_config();
+    client_config.enable_credssp = false;
+
+    let (_display_tx, display_rx) = mpsc::unbounded_channel();
+    let mut server = build_server(ServerSecurity::Hybrid, SessionAccess::Full, &client_config, display_rx);
+    let ev = server.event_sender().clone();
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async move {
+            let server = tokio::task::spawn_local(async move {
+                server.run().await.unwrap();
+            });
+
+            let client = tokio::task::spawn_local(async move {
+                let (tx, rx) = oneshot::channel();
+                ev.send(ServerEvent::GetLocalAddr(tx)).unwrap();
+                let server_addr = rx.await.unwrap().unwrap();
+                let tcp_stream = TcpStream::connect(server_addr).await.expect("TCP connect");
+                let client_addr = tcp_stream.local_addr().expect("local_addr");
+                let mut framed = ironrdp_tokio::TokioFramed::new(tcp_stream);
+                let mut connector = connector::ClientConnector::new(client_config, client_addr);
+
+                let error = ironrdp_async::connect_begin(&mut framed, &mut connector)
+                    .await
+                    .expect_err("TLS only is refused");
+                assert!(matches!(
+                    error.kind(),
+                    connector::ConnectorErrorKind::Negotiation(failure)
+                        if failure.code() == nego::FailureCode::HYBRID_REQUIRED_BY_SERVER
+                ));
+
+                ev.send(ServerEvent::Quit("bye".into())).unwrap();
+            });
+
+            tokio::try_join!(server, client).expect("join");
+        })
+        .await;
+}
+
 #[tokio::test]
 async fn test_deactivation_reactivation() {
     let client_config = default_client_config();
@@ -115,6 +183,206 @@ async fn test_deactivation_reactivation() {
     .await
 }
 
+#[tokio::test]
+async fn test_request_control() {
+    let client_config = default_client_config();
+    let mut image = DecodedImage::new(
+        PixelFormat::RgbA32,
+        client_config.desktop_size.width,
+        client_config.desktop_size.height,
+    );
+    client_server(client_config, |mut stage, mut framed, _display_tx| async move {
+        let outputs = [stage.detach_control().unwrap(), stage.request_control().unwrap()];
+        for out in outputs.into_iter().flatten() {
+            let ActiveStageOutput::ResponseFrame(frame) = out else {
+                unreachable!()
+            };
+            framed.write_all(&frame).await.unwrap();
+        }
+
+        'control: loop {
+            let (action, payload) = framed.read_pdu().await.expect("valid PDU");
+            for out in stage.process(&mut image, action, &payload).expect("stage process") {
+                match out {
+                    ActiveStageOutput::Control(status) => {
+                        assert_eq!(status, ControlStatus::Granted);
+                        break 'control;
+                    }
+                    ActiveStageOutput::ResponseFrame(frame) => framed.write_all(&frame).await.unwrap(),
+                    _ => {}
+                }
+            }
+        }
+        (stage, framed)
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_view_only() {
+    let client_config = default_client_config();
+    let mut image = DecodedImage::new(
+        PixelFormat::RgbA32,
+        client_config.desktop_size.width,
+        client_config.desktop_size.height,
+    );
+    let access = SessionAccess::ViewOnly { notify: true };
+    client_server_with(
+        ServerSecurity::Tls,
+        access,
+        client_config,
+        |mut stage, mut framed, _display_tx| async move {
+            // Notified once connected, then denied on request
+            assert_eq!(
+                wait_control(&mut stage, &mut framed, &mut image).await,
+                ControlStatus::Denied
+            );
+
+            for out in stage.request_control().unwrap() {
+                let ActiveStageOutput::ResponseFrame(frame) = out else {
+                    unreachable!()
+                };
+                framed.write_all(&frame).await.unwrap();
+            }
+            assert_eq!(
+                wait_control(&mut stage, &mut framed, &mut image).await,
+                ControlStatus::Denied
+            );
+
+            (stage, framed)
+        },
+    )
+    .await
+}
+
+#[test]
+fn test_multitransport_request_declined() {
+    const IO_CHANNEL_ID: u16 = 1003;
+    const MESSAGE_CHANNEL_ID: u16 = 1004;
+    const USER_CHANNEL_ID: u16 = 1007;
+
+    let client_config = default_client_config();
+    let mut connector = ClientConnector::new(client_config.clone(), SocketAddr::from(([127, 0, 0, 1], 0)));
+    connector.state = ClientConnectorState::CapabilitiesExchange {
+        connection_activation: ConnectionActivationSequence::new(client_config, IO_CHANNEL_ID, USER_CHANNEL_ID),
+    };
+
+    let request = InitiateMultitransportRequest {
+        request_id: 2,
+        requested_protocol: MultitransportProtocol::UdpFecReliable,
+        security_cookie: [0x42; 16],
+    };
+    let input = pdu::encode_vec(&X224(SendDataIndication {
+        initiator_id: 1002,
+        channel_id: MESSAGE_CHANNEL_ID,
+        user_data: pdu::encode_vec(&request).unwrap().into(),
+    }))
+    .unwrap();
+
+    let mut output = WriteBuf::new();
+    let written = connector.step(&input, &mut output).unwrap();
+
+    let response = pdu::decode::<X224<SendDataRequest<'_>>>(&output.filled()[..written.size().unwrap()])
+        .unwrap()
+        .0;
+    assert_eq!(response.initiator_id, USER_CHANNEL_ID);
+    assert_eq!(response.channel_id, MESSAGE_CHANNEL_ID);
+    assert_eq!(
+        pdu::decode::<InitiateMultitransportResponse>(&response.user_data).unwrap(),
+        InitiateMultitransportResponse::abort(2)
+    );
+
+    // Still waiting for the Demand Active PDU.
+    assert!(matches!(
+        connector.state,
+        ClientConnectorState::CapabilitiesExchange { .. }
+    ));
+}
+
+async fn wait_control(
+    stage: &mut ActiveStage,
+    framed: &mut Framed<TokioStream<TlsStream<TcpStream>>>,
+    image: &mut DecodedImage,
+) -> ControlStatus {
+    loop {
+        let (action, payload) = framed.read_pdu().await.expect("valid PDU");
+        for out in stage.process(image, action, &payload).expect("stage process") {
+            match out {
+                ActiveStageOutput::Control(status) => return status,
+                ActiveStageOutput::ResponseFrame(frame) => framed.write_all(&frame).await.unwrap(),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_refresh_and_suppress_output() {
+    let client_config = default_client_config();
+    let mut image = DecodedImage::new(
+        PixelFormat::RgbA32,
+        client_config.desktop_size.width,
+        client_config.desktop_size.height,
+    );
+    client_server(client_config, |mut stage, mut framed, display_tx| async move {
+        let stride = usize::from(DESKTOP_WIDTH) * 4;
+        display_tx
+            .send(DisplayUpdate::Bitmap(BitmapUpdate {
+                x: 0,
+                y: 0,
+                width: NonZeroU16::new(DESKTOP_WIDTH).unwrap(),
+                height: NonZeroU16::new(DESKTOP_HEIGHT).unwrap(),
+                format: PixelFormat::BgrA32,
+                data: vec![0xFF; stride * usize::from(DESKTOP_HEIGHT)].into(),
+                stride: NonZeroUsize::new(stride).unwrap(),
+            }))
+            .unwrap();
+        wait_graphics_update(&mut stage, &mut framed, &mut image).await;
+
+        let area = InclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: 63,
+            bottom: 63,
+        };
+        let outputs = [
+            stage.request_refresh(&[area]).unwrap(),
+            stage.suppress_output(true).unwrap(),
+            stage.suppress_output(false).unwrap(),
+        ];
+        for out in outputs.into_iter().flatten() {
+            let ActiveStageOutput::ResponseFrame(frame) = out else {
+                unreachable!()
+            };
+            framed.write_all(&frame).await.unwrap();
+        }
+
+        // The refreshed area, then the full update when resuming.
+        wait_graphics_update(&mut stage, &mut framed, &mut image).await;
+        wait_graphics_update(&mut stage, &mut framed, &mut image).await;
+
+        (stage, framed)
+    })
+    .await
+}
+
+async fn wait_graphics_update(
+    stage: &mut ActiveStage,
+    framed: &mut Framed<TokioStream<TlsStream<TcpStream>>>,
+    image: &mut DecodedImage,
+) {
+    loop {
+        let (action, payload) = framed.read_pdu().await.expect("valid PDU");
+        for out in stage.process(image, action, &payload).expect("stage process") {
+            match out {
+                ActiveStageOutput::GraphicsUpdate(_) => return,
+                ActiveStageOutput::ResponseFrame(frame) => framed.write_all(&frame).await.unwrap(),
+                _ => {}
+            }
+        }
+    }
+}
+
 type DisplayUpdatesRx = Arc<Mutex<UnboundedReceiver<DisplayUpdate>>>;
 
 struct TestDisplayUpdates {
@@ -150,17 +418,32 @@ impl RdpServerDisplay for TestDisplay {
     }
 }
 
-struct TestInputHandler;
+struct TestInputHandler {
+    access: SessionAccess,
+}
+
 impl RdpServerInputHandler for TestInputHandler {
     fn keyboard(&mut self, _: KeyboardEvent) {}
     fn mouse(&mut self, _: MouseEvent) {}
+
+    fn session_access(&mut self, _: &server::ConnectionContext) -> SessionAccess {
+        self.access
+    }
 }
 
-async fn client_server<F, Fut>(client_config: connector::Config, clientfn: F)
-where
-    F: FnOnce(ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>, UnboundedSender<DisplayUpdate>) -> Fut + 'static,
-    Fut: Future<Output = (ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>)>,
-{
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ServerSecurity {
+    Tls,
+    /// TLS with the Network Level Authentication
+    Hybrid,
+}
+
+fn build_server(
+    security: ServerSecurity,
+    access: SessionAccess,
+    client_config: &connector::Config,
+    display_rx: UnboundedReceiver<DisplayUpdate>,
+) -> RdpServer {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .try_init();
@@ -170,20 +453,57 @@ where
     let identity = TlsIdentityCtx::init_from_paths(&cert_path, &key_path).expect("failed to init TLS identity");
     let acceptor = identity.make_acceptor().expect("failed to build TLS acceptor");
 
-    let (display_tx, display_rx) = mpsc::unbounded_channel();
-    let mut server = RdpServer::builder()
-        .with_addr(([127, 0, 0, 1], 0))
-        .with_tls(acceptor)
-        .with_input_handler(TestInputHandler)
+    let builder = RdpServer::builder().with_addr(([127, 0, 0, 1], 0));
+    let builder = match security {
+        ServerSecurity::Tls => builder.with_tls(acceptor),
+        ServerSecurity::Hybrid => builder.with_hybrid(acceptor, identity.pub_key),
+    };
+    let mut server = builder
+        .with_input_handler(TestInputHandler { access })
         .with_display_handler(TestDisplay {
             rx: Arc::new(Mutex::new(display_rx)),
         })
         .build();
+
+    let connector::Credentials::UsernamePassword { username, password } = &client_config.credentials else {
+        unreachable!()
+    };
     server.set_credentials(Some(server::Credentials {
-        username: USERNAME.into(),
-        password: PASSWORD.into(),
-        domain: None,
+        username: username.clone(),
+        password: password.clone(),
+        domain: client_config.domain.clone(),
     }));
+
+    server
+}
+
+async fn client_server<F, Fut>(client_config: connector::Config, clientfn: F)
+where
+    F: FnOnce(ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>, UnboundedSender<DisplayUpdate>) -> Fut + 'static,
+    Fut: Future<Output = (ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>)>,
+{
+    client_server_with_security(ServerSecurity::Tls, client_config, clientfn).await
+}
+
+async fn client_server_with_security<F, Fut>(security: ServerSecurity, client_config: connector::Config, clientfn: F)
+where
+    F: FnOnce(ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>, UnboundedSender<DisplayUpdate>) -> Fut + 'static,
+    Fut: Future<Output = (ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>)>,
+{
+    client_server_with(security, SessionAccess::Full, client_config, clientfn).await
+}
+
+async fn client_server_with<F, Fut>(
+    security: ServerSecurity,
+    access: SessionAccess,
+    client_config: connector::Config,
+    clientfn: F,
+) where
+    F: FnOnce(ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>, UnboundedSender<DisplayUpdate>) -> Fut + 'static,
+    Fut: Future<Output = (ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>)>,
+{
+    let (display_tx, display_rx) = mpsc::unbounded_channel();
+    let mut server = build_server(security, access, &client_config, display_rx);
     let ev = server.event_sender().clone();
 
     let local = tokio::task::LocalSet::new();
@@ -256,6 +576,7 @@ fn default_client_config() -> connector::Config {
             height: DESKTOP_HEIGHT,
         },
         desktop_scale_factor: 0, // Default to 0 per FreeRDP
+        monitors: Vec::new(),
         enable_tls: true,
         enable_credssp: true,
         credentials: connector::Credentials::UsernamePassword {
@@ -263,6 +584,8 @@ fn default_client_config() -> connector::Config {
             password: PASSWORD.into(),
         },
         domain: None,
+        redirection_credentials: None,
+        auto_reconnect_cookie: None,
         client_build: semver::Version::parse(env!("CARGO_PKG_VERSION"))
             .map(|version| version.major * 100 + version.minor * 10 + version.patch)
             .unwrap_or(0)
@@ -301,6 +624,7 @@ fn default_client_config() -> connector::Config {
         request_data: None,
         autologon: false,
         enable_audio_playback: true,
+        remote_app: false,
         license_cache: None,
         enable_server_pointer: true,
         pointer_software_rendering: true,
@@ -13,6 +13,9 @@ mod impl_;
 #[path = "stub.rs"]
 mod impl_;
 
+#[cfg(feature = "rustls")]
+mod revocation;
+
 #[cfg(any(
     not(any(feature = "stub", feature = "native-tls", feature = "rustls")),
     all(feature = "stub", feature = "native-tls"),
@@ -21,10 +24,21 @@ mod impl_;
 ))]
 compile_error!("a TLS backend must be selected by enabling a single feature out of: `rustls`, `native-tls`, `stub`");
 
+// Only used by the tests of the rustls backend.
+#[cfg(all(test, not(feature = "rustls")))]
+use rcgen as _;
+
 // The whole public API of this crate.
 #[cfg(any(feature = "stub", feature = "native-tls", feature = "rustls"))]
 pub use impl_::{upgrade, TlsStream};
+#[cfg(feature = "rustls")]
+pub use impl_::{upgrade_with_config, TlsConfig, TlsVersion};
+#[cfg(feature = "rustls")]
+pub use revocation::{CertificateStatus, CrlFetcher, OcspChecker, RevocationConfig};
 
+/// Returns the subject public key of the certificate, as bound by CredSSP (pubKeyAuth)
+///
+/// The binding doesn't depend on the TLS version: the same key is returned for TLS 1.2 and TLS 1.3.
 pub fn extract_tls_server_public_key(cert: &x509_cert::Certificate) -> Option<&[u8]> {
     cert.tbs_certificate
         .subject_public_key_info
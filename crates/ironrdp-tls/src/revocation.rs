@@ -0,0 +1,350 @@
+use core::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use tokio_rustls::rustls::{CertRevocationListError, CertificateError, Error};
+use x509_cert::crl::CertificateList;
+use x509_cert::der::Decode as _;
+use x509_cert::ext::pkix::name::{DistributionPointName, GeneralName};
+use x509_cert::ext::pkix::CrlDistributionPoints;
+
+/// Revocation checks of the server certificate performed during the TLS handshake
+///
+/// The certificate chain itself is not verified, as RDP servers commonly present self-signed certificates:
+/// these checks are meant for deployments where the server certificates are issued by an enterprise PKI.
+/// By default, no check is performed.
+#[derive(Debug, Clone, Default)]
+pub struct RevocationConfig {
+    /// Rejects the server when it doesn't staple an OCSP response to its certificate
+    ///
+    /// Stapled responses can't be verified without an [`OcspChecker`]: when none is set, the server is rejected.
+    pub require_stapled_ocsp: bool,
+    /// Checks the OCSP response stapled by the server, if any
+    pub ocsp_checker: Option<Arc<dyn OcspChecker>>,
+    /// Fetches the CRLs published at the distribution points of the server certificate
+    ///
+    /// When set, the server is rejected if no CRL can be fetched, or if the certificate is revoked.
+    /// Certificates without a distribution point are not checked, unless `require_crl` is set.
+    pub crl_fetcher: Option<Arc<dyn CrlFetcher>>,
+    /// Rejects the server when its certificate has no CRL distribution point
+    ///
+    /// CRLs can't be fetched without a [`CrlFetcher`]: when none is set, the server is rejected.
+    pub require_crl: bool,
+}
+
+/// Status of a certificate in an OCSP response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CertificateStatus {
+    Good,
+    Revoked,
+    Unknown,
+}
+
+/// Checks the OCSP responses stapled by the servers
+pub trait OcspChecker: fmt::Debug + Send + Sync {
+    /// Returns the status of `cert` in the DER-encoded `response`
+    ///
+    /// The checker is responsible for the authenticity and the freshness of the response: anything else than
+    /// [`CertificateStatus::Good`] rejects the server.
+    fn check(&self, cert: &x509_cert::Certificate, response: &[u8]) -> CertificateStatus;
+}
+
+/// Fetches the CRLs of the server certificates
+///
+/// The fetcher is called during the handshake, which waits for it to return.
+pub trait CrlFetcher: fmt::Debug + Send + Sync {
+    /// Returns the DER-encoded CRL published at `uri`, e.g. `http://pki.example.com/issuing-ca.crl`
+    ///
+    /// The fetcher is responsible for the authenticity of the CRL, typically by verifying its signature with
+    /// the certificate of the issuing CA. Each distribution point is tried until one succeeds.
+    fn fetch(&self, uri: &str) -> io::Result<Vec<u8>>;
+}
+
+impl RevocationConfig {
+    pub(crate) fn check(&self, end_entity: &[u8], ocsp_response: &[u8]) -> Result<(), Error> {
+        if !self.require_stapled_ocsp && !self.require_crl && self.ocsp_checker.is_none() && self.crl_fetcher.is_none()
+        {
+            return Ok(());
+        }
+
+        let cert = x509_cert::Certificate::from_der(end_entity)
+            .map_err(|_| Error::InvalidCertificate(CertificateError::BadEncoding))?;
+
+        if ocsp_response.is_empty() {
+            if self.require_stapled_ocsp {
+                return Err(Error::InvalidCertificate(CertificateError::UnknownRevocationStatus));
+            }
+        } else if let Some(checker) = &self.ocsp_checker {
+            match checker.check(&cert, ocsp_response) {
+                CertificateStatus::Good => {}
+                CertificateStatus::Revoked => return Err(Error::InvalidCertificate(CertificateError::Revoked)),
+                CertificateStatus::Unknown => {
+                    return Err(Error::InvalidCertificate(CertificateError::UnknownRevocationStatus))
+                }
+            }
+        } else if self.require_stapled_ocsp {
+            return Err(Error::InvalidCertificate(CertificateError::UnknownRevocationStatus));
+        }
+
+        match &self.crl_fetcher {
+            Some(fetcher) => check_crl(fetcher.as_ref(), &cert, self.require_crl)?,
+            None if self.require_crl => {
+                return Err(Error::InvalidCertificate(CertificateError::UnknownRevocationStatus))
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn check_crl(fetcher: &dyn CrlFetcher, cert: &x509_cert::Certificate, require_crl: bool) -> Result<(), Error> {
+    let distribution_points = cert
+        .tbs_certificate
+        .get::<CrlDistributionPoints>()
+        .map_err(|_| Error::InvalidCertificate(CertificateError::BadEncoding))?
+        .map(|(_, distribution_points)| distribution_points.0)
+        .unwrap_or_default();
+
+    let mut uris = distribution_points
+        .into_iter()
+        .filter_map(|distribution_point| match distribution_point.distribution_point {
+            Some(DistributionPointName::FullName(names)) => Some(names),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|name| match name {
+            GeneralName::UniformResourceIdentifier(uri) => Some(uri.to_string()),
+            _ => None,
+        })
+        .peekable();
+
+    if uris.peek().is_none() {
+        return if require_crl {
+            Err(Error::InvalidCertificate(CertificateError::UnknownRevocationStatus))
+        } else {
+            Ok(())
+        };
+    }
+
+    let crl = uris
+        .find_map(|uri| fetcher.fetch(&uri).ok())
+        .ok_or(Error::InvalidCertificate(CertificateError::UnknownRevocationStatus))?;
+
+    let crl = CertificateList::from_der(&crl)
+        .map_err(|_| Error::InvalidCertRevocationList(CertRevocationListError::ParseError))?;
+
+    if crl.tbs_cert_list.issuer != cert.tbs_certificate.issuer {
+        return Err(Error::InvalidCertificate(CertificateError::UnknownRevocationStatus));
+    }
+
+    if crl
+        .tbs_cert_list
+        .next_update
+        .is_some_and(|next_update| next_update.to_system_time() < SystemTime::now())
+    {
+        return Err(Error::InvalidCertificate(CertificateError::ExpiredRevocationList));
+    }
+
+    let is_revoked = crl
+        .tbs_cert_list
+        .revoked_certificates
+        .iter()
+        .flatten()
+        .any(|revoked| revoked.serial_number == cert.tbs_certificate.serial_number);
+
+    if is_revoked {
+        return Err(Error::InvalidCertificate(CertificateError::Revoked));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serial number 0x1001, with a distribution point at `http://pki.example.com/ca.crl`
+    const CERTIFICATE_WITH_CDP: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBmDCCAT6gAwIBAgICEAEwCgYIKoZIzj0EAwIwGjEYMBYGA1UEAwwPSXJvblJE
+UCBUZXN0IENBMCAXDTI2MTAxNjEyNDk0MFoYDzIxMjYwOTIyMTI0OTQwWjAaMRgw
+FgYDVQQDDA9yZHAuZXhhbXBsZS5jb20wWTATBgcqhkjOPQIBBggqhkjOPQMBBwNC
+AAQh7/3FRR/DuVMWS1+7Jz6VIehsn1qEHLA4VO6OiQaUMWWd7rSu2xjJqSQlIjbQ
+O/1moqHZqVuD3TUO1lCpYgDWo3IwcDAuBgNVHR8EJzAlMCOgIaAfhh1odHRwOi8v
+cGtpLmV4YW1wbGUuY29tL2NhLmNybDAdBgNVHQ4EFgQUDZfmmvo5p8xWh9pCBL69
+6ly715QwHwYDVR0jBBgwFoAU44v7BBblgIw1TBC90G2zHOXOw7MwCgYIKoZIzj0E
+AwIDSAAwRQIhAKlWCDCCqZV+K7lmjhQ0CrKSXulnhrppyHRn/ujWA1/KAiAr0JXz
+QBzuNuWPeSXZBwvIbTY0DdYT+ETz1lviCZcBVw==
+-----END CERTIFICATE-----";
+
+    /// Serial number 0x1002, issued by the same CA as `CERTIFICATE_WITH_CDP`
+    const CERTIFICATE_WITHOUT_CDP: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBZzCCAQ6gAwIBAgICEAIwCgYIKoZIzj0EAwIwGjEYMBYGA1UEAwwPSXJvblJE
+UCBUZXN0IENBMCAXDTI2MTAxNjEyNDk0MFoYDzIxMjYwOTIyMTI0OTQwWjAaMRgw
+FgYDVQQDDA9yZHAuZXhhbXBsZS5jb20wWTATBgcqhkjOPQIBBggqhkjOPQMBBwNC
+AAQh7/3FRR/DuVMWS1+7Jz6VIehsn1qEHLA4VO6OiQaUMWWd7rSu2xjJqSQlIjbQ
+O/1moqHZqVuD3TUO1lCpYgDWo0IwQDAdBgNVHQ4EFgQUDZfmmvo5p8xWh9pCBL69
+6ly715QwHwYDVR0jBBgwFoAU44v7BBblgIw1TBC90G2zHOXOw7MwCgYIKoZIzj0E
+AwIDRwAwRAIgJtqh1p8fIN79mk45S2TakzjIvVRWBdib9BwbQ6Ic49wCIGhGkKmW
+c2Gs3E8AaQ6Q/rZbso/rlNNrlNPB8kI+b3YX
+-----END CERTIFICATE-----";
+
+    const EMPTY_CRL: &str = "\
+-----BEGIN X509 CRL-----
+MIGzMFwCAQEwCgYIKoZIzj0EAwIwGjEYMBYGA1UEAwwPSXJvblJEUCBUZXN0IENB
+Fw0yNjEwMTYxMjQ5NDBaGA8yMTI2MDkyMjEyNDk0MFqgDzANMAsGA1UdFAQEAgIQ
+ADAKBggqhkjOPQQDAgNHADBEAiBR7NL+GOkG86/oYzB3MtCUkZ4q/v5AMBkPXZce
+iWHndgIgB7nWbSBep2gUIeDLLl/uVWpNeu+Ym61Jl5OQKFixoh4=
+-----END X509 CRL-----";
+
+    /// Revokes the certificate 0x1001
+    const REVOKED_CRL: &str = "\
+-----BEGIN X509 CRL-----
+MIHLMHMCAQEwCgYIKoZIzj0EAwIwGjEYMBYGA1UEAwwPSXJvblJEUCBUZXN0IENB
+Fw0yNjEwMTYxMjQ5NDBaGA8yMTI2MDkyMjEyNDk0MFowFTATAgIQARcNMjYxMDE2
+MTI0OTQwWqAPMA0wCwYDVR0UBAQCAhABMAoGCCqGSM49BAMCA0gAMEUCIDAUO404
+4e1ntVTvk/ySa0HIL7LiEyS0c/cnk9kZi/KQAiEA1WkofjuFeCY5txhdQ8MV4o43
+JNkmHMxNGZ5ZvqFje24=
+-----END X509 CRL-----";
+
+    const CRL_URI: &str = "http://pki.example.com/ca.crl";
+
+    /// The response is opaque to the fake checker
+    const OCSP_RESPONSE: &[u8] = b"stapled OCSP response";
+
+    #[derive(Debug)]
+    struct FakeOcspChecker(CertificateStatus);
+
+    impl OcspChecker for FakeOcspChecker {
+        fn check(&self, _cert: &x509_cert::Certificate, response: &[u8]) -> CertificateStatus {
+            assert_eq!(response, OCSP_RESPONSE);
+            self.0
+        }
+    }
+
+    /// Publishes `crl` at `CRL_URI`, or fails when `None`
+    #[derive(Debug)]
+    struct FakeCrlFetcher(Option<&'static str>);
+
+    impl CrlFetcher for FakeCrlFetcher {
+        fn fetch(&self, uri: &str) -> io::Result<Vec<u8>> {
+            assert_eq!(uri, CRL_URI);
+            self.0
+                .map(decode_pem)
+                .ok_or_else(|| io::Error::other("distribution point unreachable"))
+        }
+    }
+
+    fn decode_pem(pem: &str) -> Vec<u8> {
+        x509_cert::der::pem::decode_vec(pem.as_bytes()).unwrap().1
+    }
+
+    fn ocsp_config(status: CertificateStatus) -> RevocationConfig {
+        RevocationConfig {
+            require_stapled_ocsp: true,
+            ocsp_checker: Some(Arc::new(FakeOcspChecker(status))),
+            ..RevocationConfig::default()
+        }
+    }
+
+    fn crl_config(crl: Option<&'static str>, require_crl: bool) -> RevocationConfig {
+        RevocationConfig {
+            crl_fetcher: Some(Arc::new(FakeCrlFetcher(crl))),
+            require_crl,
+            ..RevocationConfig::default()
+        }
+    }
+
+    const UNKNOWN: Result<(), Error> = Err(Error::InvalidCertificate(CertificateError::UnknownRevocationStatus));
+    const REVOKED: Result<(), Error> = Err(Error::InvalidCertificate(CertificateError::Revoked));
+
+    #[test]
+    fn no_check_by_default() {
+        assert_eq!(RevocationConfig::default().check(b"not a certificate", &[]), Ok(()));
+    }
+
+    #[test]
+    fn stapled_ocsp_good() {
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(ocsp_config(CertificateStatus::Good).check(&cert, OCSP_RESPONSE), Ok(()));
+    }
+
+    #[test]
+    fn stapled_ocsp_revoked() {
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(
+            ocsp_config(CertificateStatus::Revoked).check(&cert, OCSP_RESPONSE),
+            REVOKED
+        );
+    }
+
+    #[test]
+    fn stapled_ocsp_unknown() {
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(
+            ocsp_config(CertificateStatus::Unknown).check(&cert, OCSP_RESPONSE),
+            UNKNOWN
+        );
+    }
+
+    #[test]
+    fn missing_stapled_ocsp() {
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(ocsp_config(CertificateStatus::Good).check(&cert, &[]), UNKNOWN);
+    }
+
+    #[test]
+    fn stapled_ocsp_without_checker() {
+        let config = RevocationConfig {
+            require_stapled_ocsp: true,
+            ..RevocationConfig::default()
+        };
+
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(config.check(&cert, OCSP_RESPONSE), UNKNOWN);
+    }
+
+    #[test]
+    fn crl_good() {
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(crl_config(Some(EMPTY_CRL), false).check(&cert, &[]), Ok(()));
+    }
+
+    #[test]
+    fn crl_revoked() {
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(crl_config(Some(REVOKED_CRL), false).check(&cert, &[]), REVOKED);
+    }
+
+    #[test]
+    fn crl_unreachable() {
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(crl_config(None, false).check(&cert, &[]), UNKNOWN);
+    }
+
+    #[test]
+    fn no_distribution_point() {
+        let cert = decode_pem(CERTIFICATE_WITHOUT_CDP);
+        assert_eq!(crl_config(None, false).check(&cert, &[]), Ok(()));
+    }
+
+    #[test]
+    fn no_distribution_point_with_required_crl() {
+        let cert = decode_pem(CERTIFICATE_WITHOUT_CDP);
+        assert_eq!(crl_config(None, true).check(&cert, &[]), UNKNOWN);
+    }
+
+    #[test]
+    fn required_crl_without_fetcher() {
+        let config = RevocationConfig {
+            require_crl: true,
+            ..RevocationConfig::default()
+        };
+
+        let cert = decode_pem(CERTIFICATE_WITH_CDP);
+        assert_eq!(config.check(&cert, &[]), UNKNOWN);
+    }
+}
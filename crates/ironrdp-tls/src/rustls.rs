@@ -1,32 +1,130 @@
 use std::io;
+use std::sync::Arc;
 
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt as _};
 use tokio_rustls::rustls;
+use tokio_rustls::rustls::crypto::CryptoProvider;
 use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{CipherSuite, SupportedProtocolVersion};
+
+use crate::RevocationConfig;
 
 pub type TlsStream<S> = tokio_rustls::client::TlsStream<S>;
 
+/// TLS protocol version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+
+impl TlsVersion {
+    fn supported(self) -> &'static SupportedProtocolVersion {
+        match self {
+            Self::Tls12 => &rustls::version::TLS12,
+            Self::Tls13 => &rustls::version::TLS13,
+        }
+    }
+}
+
+/// Parameters of the TLS handshake performed by [`upgrade_with_config`]
+///
+/// The default configuration accepts TLS 1.2 and TLS 1.3, the cipher suites of the crypto provider and no ALPN,
+/// and doesn't check the revocation of the server certificate.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// Oldest version accepted
+    pub min_version: TlsVersion,
+    /// Newest version accepted
+    pub max_version: TlsVersion,
+    /// Cipher suites offered, in order of preference
+    ///
+    /// When empty, all the cipher suites of the crypto provider are offered.
+    pub cipher_suites: Vec<CipherSuite>,
+    /// ALPN protocols offered, in order of preference, e.g. `b"http/1.1"` for an RD Gateway
+    pub alpn_protocols: Vec<Vec<u8>>,
+    /// Revocation checks of the server certificate
+    pub revocation: RevocationConfig,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            min_version: TlsVersion::Tls12,
+            max_version: TlsVersion::Tls13,
+            cipher_suites: Vec::new(),
+            alpn_protocols: Vec::new(),
+            revocation: RevocationConfig::default(),
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Versions between `min_version` and `max_version`, fails when there is none
+    pub fn protocol_versions(&self) -> io::Result<Vec<&'static SupportedProtocolVersion>> {
+        let versions: Vec<_> = [TlsVersion::Tls12, TlsVersion::Tls13]
+            .into_iter()
+            .filter(|version| (self.min_version..=self.max_version).contains(version))
+            .map(TlsVersion::supported)
+            .collect();
+
+        if versions.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no TLS version between the minimum and the maximum",
+            ));
+        }
+
+        Ok(versions)
+    }
+
+    /// Restricts `provider` to `cipher_suites`, fails when the provider doesn't implement one of them
+    pub fn crypto_provider(&self, provider: &CryptoProvider) -> io::Result<Arc<CryptoProvider>> {
+        let mut provider = provider.clone();
+
+        if !self.cipher_suites.is_empty() {
+            provider.cipher_suites = self
+                .cipher_suites
+                .iter()
+                .map(|id| {
+                    provider
+                        .cipher_suites
+                        .iter()
+                        .find(|suite| suite.suite() == *id)
+                        .copied()
+                        .ok_or_else(|| {
+                            io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported cipher suite {id:?}"))
+                        })
+                })
+                .collect::<io::Result<_>>()?;
+        }
+
+        Ok(Arc::new(provider))
+    }
+}
+
 pub async fn upgrade<S>(stream: S, server_name: &str) -> io::Result<(TlsStream<S>, x509_cert::Certificate)>
 where
     S: Unpin + AsyncRead + AsyncWrite,
 {
-    let mut tls_stream = {
-        let mut config = rustls::client::ClientConfig::builder()
-            .dangerous()
-            .with_custom_certificate_verifier(std::sync::Arc::new(danger::NoCertificateVerification))
-            .with_no_client_auth();
-
-        // This adds support for the SSLKEYLOGFILE env variable (https://wiki.wireshark.org/TLS#using-the-pre-master-secret)
-        config.key_log = std::sync::Arc::new(rustls::KeyLogFile::new());
-
-        // Disable TLS resumption because it’s not supported by some services such as CredSSP.
-        //
-        // > The CredSSP Protocol does not extend the TLS wire protocol. TLS session resumption is not supported.
-        //
-        // source: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-cssp/385a7489-d46b-464c-b224-f7340e308a5c
-        config.resumption = rustls::client::Resumption::disabled();
+    upgrade_with_config(stream, server_name, &TlsConfig::default()).await
+}
 
-        let config = std::sync::Arc::new(config);
+/// Performs the TLS handshake with the given versions, cipher suites and ALPN protocols
+///
+/// The certificate of the server is returned for the CredSSP public key binding (see
+/// [`extract_tls_server_public_key`](crate::extract_tls_server_public_key)). It is read once the handshake
+/// is complete: with TLS 1.3, the certificate is sent encrypted and is not known before.
+pub async fn upgrade_with_config<S>(
+    stream: S,
+    server_name: &str,
+    tls_config: &TlsConfig,
+) -> io::Result<(TlsStream<S>, x509_cert::Certificate)>
+where
+    S: Unpin + AsyncRead + AsyncWrite,
+{
+    let mut tls_stream = {
+        let config = Arc::new(client_config(tls_config)?);
 
         let domain = ServerName::try_from(server_name.to_owned()).map_err(io::Error::other)?;
 
@@ -51,22 +149,57 @@ where
     Ok((tls_stream, tls_cert))
 }
 
+fn client_config(tls_config: &TlsConfig) -> io::Result<rustls::ClientConfig> {
+    let default_provider = Arc::clone(rustls::client::ClientConfig::builder().crypto_provider());
+
+    let mut config =
+        rustls::client::ClientConfig::builder_with_provider(tls_config.crypto_provider(&default_provider)?)
+            .with_protocol_versions(&tls_config.protocol_versions()?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(danger::NoCertificateVerification {
+                revocation: tls_config.revocation.clone(),
+            }))
+            .with_no_client_auth();
+
+    // This adds support for the SSLKEYLOGFILE env variable (https://wiki.wireshark.org/TLS#using-the-pre-master-secret)
+    config.key_log = Arc::new(rustls::KeyLogFile::new());
+
+    // Disable TLS resumption because it’s not supported by some services such as CredSSP.
+    //
+    // > The CredSSP Protocol does not extend the TLS wire protocol. TLS session resumption is not supported.
+    //
+    // source: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-cssp/385a7489-d46b-464c-b224-f7340e308a5c
+    config.resumption = rustls::client::Resumption::disabled();
+
+    config.alpn_protocols.clone_from(&tls_config.alpn_protocols);
+
+    Ok(config)
+}
+
 mod danger {
     use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
     use tokio_rustls::rustls::{pki_types, DigitallySignedStruct, Error, SignatureScheme};
 
+    use crate::RevocationConfig;
+
+    /// Accepts any certificate chain, only performing the configured revocation checks
     #[derive(Debug)]
-    pub(super) struct NoCertificateVerification;
+    pub(super) struct NoCertificateVerification {
+        pub(super) revocation: RevocationConfig,
+    }
 
     impl ServerCertVerifier for NoCertificateVerification {
         fn verify_server_cert(
             &self,
-            _: &pki_types::CertificateDer<'_>,
+            end_entity: &pki_types::CertificateDer<'_>,
             _: &[pki_types::CertificateDer<'_>],
             _: &pki_types::ServerName<'_>,
-            _: &[u8],
+            ocsp_response: &[u8],
             _: pki_types::UnixTime,
         ) -> Result<ServerCertVerified, Error> {
+            self.revocation.check(end_entity, ocsp_response)?;
+
             Ok(ServerCertVerified::assertion())
         }
 
@@ -107,3 +240,168 @@ mod danger {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use tokio_rustls::rustls::ProtocolVersion;
+    use x509_cert::der::Encode as _;
+
+    use super::*;
+
+    fn install_crypto_provider() {
+        // Another crate of the workspace may enable a second provider, none being the default then.
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    }
+
+    fn versions(tls_config: &TlsConfig) -> io::Result<Vec<ProtocolVersion>> {
+        Ok(tls_config
+            .protocol_versions()?
+            .into_iter()
+            .map(|supported| supported.version)
+            .collect())
+    }
+
+    fn server_config(alpn_protocols: &[&[u8]]) -> (CertificateDer<'static>, rustls::ServerConfig) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let cert = certified.cert.der().clone();
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der()));
+
+        let mut config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.clone()], key)
+            .unwrap();
+        config.alpn_protocols = alpn_protocols.iter().map(|protocol| protocol.to_vec()).collect();
+
+        (cert, config)
+    }
+
+    #[test]
+    fn versions_between_min_and_max() {
+        let tls12 = TlsConfig {
+            max_version: TlsVersion::Tls12,
+            ..TlsConfig::default()
+        };
+        let tls13 = TlsConfig {
+            min_version: TlsVersion::Tls13,
+            ..TlsConfig::default()
+        };
+
+        assert_eq!(
+            versions(&TlsConfig::default()).unwrap(),
+            [ProtocolVersion::TLSv1_2, ProtocolVersion::TLSv1_3]
+        );
+        assert_eq!(versions(&tls12).unwrap(), [ProtocolVersion::TLSv1_2]);
+        assert_eq!(versions(&tls13).unwrap(), [ProtocolVersion::TLSv1_3]);
+    }
+
+    #[test]
+    fn min_version_above_max_version_is_rejected() {
+        let tls_config = TlsConfig {
+            min_version: TlsVersion::Tls13,
+            max_version: TlsVersion::Tls12,
+            ..TlsConfig::default()
+        };
+
+        assert_eq!(versions(&tls_config).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn cipher_suites_are_restricted_in_order() {
+        let cipher_suites = vec![
+            CipherSuite::TLS13_AES_256_GCM_SHA384,
+            CipherSuite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+        ];
+        let tls_config = TlsConfig {
+            cipher_suites: cipher_suites.clone(),
+            ..TlsConfig::default()
+        };
+
+        let provider = tls_config
+            .crypto_provider(&rustls::crypto::aws_lc_rs::default_provider())
+            .unwrap();
+        let offered: Vec<_> = provider.cipher_suites.iter().map(|suite| suite.suite()).collect();
+
+        assert_eq!(offered, cipher_suites);
+    }
+
+    #[test]
+    fn unknown_cipher_suite_is_rejected() {
+        let tls_config = TlsConfig {
+            cipher_suites: vec![
+                CipherSuite::TLS13_AES_128_GCM_SHA256,
+                CipherSuite::TLS_RSA_WITH_RC4_128_SHA,
+            ],
+            ..TlsConfig::default()
+        };
+
+        let error = tls_config
+            .crypto_provider(&rustls::crypto::aws_lc_rs::default_provider())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn client_config_offers_alpn_protocols() {
+        install_crypto_provider();
+
+        let tls_config = TlsConfig {
+            alpn_protocols: vec![b"http/1.1".to_vec()],
+            ..TlsConfig::default()
+        };
+
+        let config = client_config(&tls_config).unwrap();
+        assert_eq!(config.alpn_protocols, [b"http/1.1"]);
+    }
+
+    #[test]
+    fn client_config_without_version_for_cipher_suites_is_rejected() {
+        install_crypto_provider();
+
+        let tls_config = TlsConfig {
+            min_version: TlsVersion::Tls13,
+            cipher_suites: vec![CipherSuite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256],
+            ..TlsConfig::default()
+        };
+
+        assert_eq!(
+            client_config(&tls_config).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[tokio::test]
+    async fn upgrade_negotiates_the_configured_parameters() {
+        install_crypto_provider();
+
+        let (cert, server_config) = server_config(&[b"x-other", b"http/1.1"]);
+        let (client, server) = tokio::io::duplex(16 * 1024);
+
+        let server = tokio::spawn(async move {
+            tokio_rustls::TlsAcceptor::from(Arc::new(server_config))
+                .accept(server)
+                .await
+                .unwrap()
+        });
+
+        let tls_config = TlsConfig {
+            max_version: TlsVersion::Tls12,
+            cipher_suites: vec![CipherSuite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384],
+            alpn_protocols: vec![b"http/1.1".to_vec()],
+            ..TlsConfig::default()
+        };
+
+        let (tls_stream, server_cert) = upgrade_with_config(client, "localhost", &tls_config).await.unwrap();
+        let _server_stream = server.await.unwrap();
+
+        let (_, connection) = tls_stream.get_ref();
+        assert_eq!(connection.protocol_version(), Some(ProtocolVersion::TLSv1_2));
+        assert_eq!(
+            connection.negotiated_cipher_suite().map(|suite| suite.suite()),
+            Some(CipherSuite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384)
+        );
+        assert_eq!(connection.alpn_protocol(), Some(&b"http/1.1"[..]));
+
+        assert_eq!(server_cert.to_der().unwrap(), cert.as_ref());
+    }
+}
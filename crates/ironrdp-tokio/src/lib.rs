@@ -7,6 +7,15 @@ pub use ironrdp_async::*;
 #[cfg(feature = "reqwest")]
 pub mod reqwest;
 
+#[cfg(feature = "reconnect")]
+pub mod reconnect;
+
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+#[cfg(feature = "quic")]
+pub mod quic;
+
 use core::pin::Pin;
 use std::io;
 
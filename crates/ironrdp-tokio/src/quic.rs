@@ -0,0 +1,567 @@
+//! QUIC transport (experimental)
+//!
+//! Carries an RDP connection over QUIC instead of TCP, e.g. to avoid the head-of-line blocking of TCP on lossy
+//! links. This is not part of the RDP specifications: both ends must be IronRDP, agreeing on [`ALPN_PROTOCOL`].
+//!
+//! The RDP connection runs on the first bidirectional stream of the QUIC connection, opened by [`connect`] and
+//! accepted by [`accept`]. [`QuicStream`] behaves like a TCP stream: it is wrapped in a
+//! [`TokioFramed`](crate::TokioFramed), and the connection sequence, including the TLS security upgrade, is the
+//! same as over TCP.
+//!
+//! The virtual channels can be moved to streams of their own with [`QuicChannels`], so that bulk transfers (e.g.
+//! clipboard or file redirection) don't delay the graphics and the input. The dynamic channels are carried by the
+//! DRDYNVC static channel: moving it moves all of them.
+//!
+//! The QUIC endpoints are configured by the caller: certificates, verification and transport parameters. The
+//! TLS configurations must advertise [`ALPN_PROTOCOL`].
+
+use core::any::TypeId;
+use core::net::SocketAddr;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::collections::HashMap;
+use std::io;
+
+use ironrdp_svc::pdu::gcc::ChannelName;
+use ironrdp_svc::pdu::PduResult;
+use ironrdp_svc::{
+    StaticChannelSet, StaticVirtualChannel, SvcMessage, SvcProcessor, SvcProcessorMessages, CHANNEL_CHUNK_LENGTH,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt as _, ReadBuf};
+use tokio::sync::mpsc;
+
+/// ALPN protocol identifier of RDP over QUIC
+pub const ALPN_PROTOCOL: &[u8] = b"x-ironrdp-quic";
+
+/// Opens a QUIC connection to `server_addr` and the stream of the RDP connection
+///
+/// `server_name` is the name the certificate of the server is checked against.
+pub async fn connect(
+    endpoint: &quinn::Endpoint,
+    server_addr: SocketAddr,
+    server_name: &str,
+) -> io::Result<(quinn::Connection, QuicStream)> {
+    let connection = endpoint
+        .connect(server_addr, server_name)
+        .map_err(io::Error::other)?
+        .await?;
+
+    let (send, recv) = connection.open_bi().await?;
+
+    Ok((connection, QuicStream::new(send, recv)))
+}
+
+/// Accepts a QUIC connection and the stream of the RDP connection opened by the client
+pub async fn accept(incoming: quinn::Incoming) -> io::Result<(quinn::Connection, QuicStream)> {
+    let connection = incoming.await?;
+
+    let (send, recv) = connection.accept_bi().await?;
+
+    Ok((connection, QuicStream::new(send, recv)))
+}
+
+/// Opens a stream carrying the data of the virtual channel `channel_name`
+///
+/// The peer receives the stream and the name of the channel with [`accept_channel`].
+pub async fn open_channel(connection: &quinn::Connection, channel_name: &str) -> io::Result<QuicStream> {
+    let name_len = u8::try_from(channel_name.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "channel name too long"))?;
+
+    let (mut send, recv) = connection.open_bi().await?;
+
+    // The stream is announced to the peer with its first bytes.
+    send.write_u8(name_len).await?;
+    send.write_all(channel_name.as_bytes()).await?;
+
+    Ok(QuicStream::new(send, recv))
+}
+
+/// Accepts a stream opened with [`open_channel`], and returns the name of its virtual channel
+pub async fn accept_channel(connection: &quinn::Connection) -> io::Result<(String, QuicStream)> {
+    let (send, mut recv) = connection.accept_bi().await?;
+
+    let mut channel_name = vec![0; usize::from(recv.read_u8().await?)];
+    // The inherent `read_exact` of `RecvStream` doesn't return an I/O error.
+    AsyncReadExt::read_exact(&mut recv, &mut channel_name).await?;
+
+    let channel_name = String::from_utf8(channel_name)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "channel name is not UTF-8"))?;
+
+    Ok((channel_name, QuicStream::new(send, recv)))
+}
+
+/// Static virtual channels carried by QUIC streams, one per channel
+///
+/// The processors of these channels live in a [`StaticChannelSet`] of their own: the channels are not part of the
+/// connection sequence, and both ends must move the same channels. Each chunk of channel data is sent on the
+/// stream of its channel, prefixed with its length (32-bit, little-endian).
+#[derive(Debug)]
+pub struct QuicChannels {
+    channels: StaticChannelSet,
+    streams: HashMap<TypeId, quinn::SendStream>,
+    frames: mpsc::Receiver<(TypeId, io::Result<Option<Vec<u8>>>)>,
+}
+
+impl QuicChannels {
+    /// Opens a stream for each channel of `channels`
+    pub async fn open(connection: &quinn::Connection, channels: StaticChannelSet) -> io::Result<Self> {
+        let channel_names = channels
+            .iter()
+            .map(|(type_id, channel)| {
+                let channel_name = channel
+                    .channel_name()
+                    .as_str()
+                    .map(str::to_owned)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "channel name is not ASCII"))?;
+
+                Ok((type_id, channel_name))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut streams = Vec::new();
+
+        for (type_id, channel_name) in channel_names {
+            streams.push((type_id, open_channel(connection, &channel_name).await?));
+        }
+
+        Ok(Self::new(channels, streams))
+    }
+
+    /// Accepts the streams opened by the peer for the channels of `channels`
+    pub async fn accept(connection: &quinn::Connection, channels: StaticChannelSet) -> io::Result<Self> {
+        let mut streams = Vec::new();
+
+        for _ in 0..channels.values().count() {
+            let (channel_name, stream) = accept_channel(connection).await?;
+
+            let (type_id, _) = ChannelName::from_utf8(&channel_name)
+                .and_then(|channel_name| channels.get_by_channel_name(&channel_name))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unexpected virtual channel"))?;
+
+            streams.push((type_id, stream));
+        }
+
+        Ok(Self::new(channels, streams))
+    }
+
+    fn new(channels: StaticChannelSet, streams: Vec<(TypeId, QuicStream)>) -> Self {
+        let (frames_tx, frames) = mpsc::channel(16);
+
+        let streams = streams
+            .into_iter()
+            .map(|(type_id, stream)| {
+                let (send, recv) = stream.into_inner();
+                tokio::spawn(read_frames(type_id, recv, frames_tx.clone()));
+                (type_id, send)
+            })
+            .collect();
+
+        Self {
+            channels,
+            streams,
+            frames,
+        }
+    }
+
+    pub fn channels(&self) -> &StaticChannelSet {
+        &self.channels
+    }
+
+    pub fn channels_mut(&mut self) -> &mut StaticChannelSet {
+        &mut self.channels
+    }
+
+    /// Starts the processors of the channels, sending their first messages
+    pub async fn start(&mut self) -> io::Result<()> {
+        let messages = self
+            .channels
+            .iter_mut()
+            .map(|(type_id, channel, _)| Ok((type_id, channel.start()?)))
+            .collect::<PduResult<Vec<_>>>()
+            .map_err(io::Error::other)?;
+
+        for (type_id, messages) in messages {
+            self.send_messages(type_id, messages).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the messages of the processor `P`, e.g. returned by one of its methods
+    pub async fn send<P: SvcProcessor + 'static>(&mut self, messages: SvcProcessorMessages<P>) -> io::Result<()> {
+        self.send_messages(TypeId::of::<P>(), messages.into()).await
+    }
+
+    /// Waits for the next chunk of channel data, gives it to the processor of the channel, then sends the replies
+    ///
+    /// Returns the type of the processor, or `None` once the peer finished all the streams.
+    pub async fn process(&mut self) -> io::Result<Option<TypeId>> {
+        while let Some((type_id, frame)) = self.frames.recv().await {
+            // The stream is finished.
+            let Some(frame) = frame? else {
+                continue;
+            };
+
+            let replies = self
+                .channels
+                .get_by_type_id_mut(type_id)
+                .ok_or_else(|| io::Error::other("virtual channel removed from the set"))?
+                .process(&frame)
+                .map_err(io::Error::other)?;
+
+            self.send_messages(type_id, replies).await?;
+
+            return Ok(Some(type_id));
+        }
+
+        Ok(None)
+    }
+
+    async fn send_messages(&mut self, type_id: TypeId, messages: Vec<SvcMessage>) -> io::Result<()> {
+        let stream = self
+            .streams
+            .get_mut(&type_id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "virtual channel not carried by QUIC"))?;
+
+        for chunk in StaticVirtualChannel::chunkify(messages).map_err(io::Error::other)? {
+            let length = u32::try_from(chunk.filled().len()).map_err(io::Error::other)?;
+            stream.write_u32_le(length).await?;
+            stream.write_all(chunk.filled()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Maximum size of a chunk of channel data: CHANNEL_PDU_HEADER and data
+const MAX_CHUNK_SIZE: usize = 8 + CHANNEL_CHUNK_LENGTH;
+
+/// Reads the chunks received on a channel stream, until it is finished or the [`QuicChannels`] are dropped
+async fn read_frames(
+    type_id: TypeId,
+    mut recv: quinn::RecvStream,
+    frames: mpsc::Sender<(TypeId, io::Result<Option<Vec<u8>>>)>,
+) {
+    loop {
+        let frame = read_frame(&mut recv).await;
+        let is_last = !matches!(frame, Ok(Some(_)));
+
+        if frames.send((type_id, frame)).await.is_err() || is_last {
+            break;
+        }
+    }
+}
+
+async fn read_frame(recv: &mut quinn::RecvStream) -> io::Result<Option<Vec<u8>>> {
+    let length = match recv.read_u32_le().await {
+        Ok(length) => length,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let length = usize::try_from(length)
+        .ok()
+        .filter(|length| *length <= MAX_CHUNK_SIZE)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "channel chunk too long"))?;
+
+    let mut frame = vec![0; length];
+    AsyncReadExt::read_exact(recv, &mut frame).await?;
+
+    Ok(Some(frame))
+}
+
+/// Bidirectional QUIC stream
+///
+/// Shutting down the stream finishes its sending side, the QUIC connection staying open for the other
+/// streams.
+#[derive(Debug)]
+pub struct QuicStream {
+    send: quinn::SendStream,
+    recv: quinn::RecvStream,
+}
+
+impl QuicStream {
+    pub fn new(send: quinn::SendStream, recv: quinn::RecvStream) -> Self {
+        Self { send, recv }
+    }
+
+    pub fn into_inner(self) -> (quinn::SendStream, quinn::RecvStream) {
+        (self.send, self.recv)
+    }
+}
+
+// The streams have inherent methods of the same names, returning QUIC errors.
+impl AsyncRead for QuicStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        AsyncRead::poll_read(Pin::new(&mut self.get_mut().recv), cx, buf)
+    }
+}
+
+impl AsyncWrite for QuicStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(Pin::new(&mut self.get_mut().send), cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(Pin::new(&mut self.get_mut().send), cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_shutdown(Pin::new(&mut self.get_mut().send), cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use ironrdp_svc::impl_as_any;
+    use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+    use quinn::rustls;
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+
+    use super::*;
+
+    const CHANNEL_NAME: ChannelName = ChannelName::from_static(b"ECHO\0\0\0\0");
+
+    /// Replies to each message with the same payload
+    #[derive(Debug)]
+    struct Echo;
+
+    impl_as_any!(Echo);
+
+    impl SvcProcessor for Echo {
+        fn channel_name(&self) -> ChannelName {
+            CHANNEL_NAME
+        }
+
+        fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+            Ok(vec![SvcMessage::from(payload.to_vec())])
+        }
+    }
+
+    /// Records the messages received
+    #[derive(Debug, Default)]
+    struct Recorder {
+        messages: Vec<Vec<u8>>,
+    }
+
+    impl_as_any!(Recorder);
+
+    impl SvcProcessor for Recorder {
+        fn channel_name(&self) -> ChannelName {
+            CHANNEL_NAME
+        }
+
+        fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+            self.messages.push(payload.to_vec());
+            Ok(Vec::new())
+        }
+    }
+
+    struct Peers {
+        // The endpoints are kept alive for the duration of the test.
+        _endpoints: (quinn::Endpoint, quinn::Endpoint),
+        client: (quinn::Connection, QuicStream),
+        server: (quinn::Connection, QuicStream),
+    }
+
+    fn endpoints() -> (quinn::Endpoint, quinn::Endpoint) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let cert = certified.cert.der().clone();
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der()));
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+        let mut server_crypto = rustls::ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.clone()], key)
+            .unwrap();
+        server_crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+
+        let server_config =
+            quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(server_crypto).unwrap()));
+        let server = quinn::Endpoint::server(server_config, (Ipv4Addr::LOCALHOST, 0).into()).unwrap();
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(cert).unwrap();
+
+        let mut client_crypto = rustls::ClientConfig::builder_with_provider(provider)
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        client_crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+
+        let mut client = quinn::Endpoint::client((Ipv4Addr::LOCALHOST, 0).into()).unwrap();
+        client.set_default_client_config(quinn::ClientConfig::new(Arc::new(
+            QuicClientConfig::try_from(client_crypto).unwrap(),
+        )));
+
+        (server, client)
+    }
+
+    /// Connects a client to a server over loopback, the client sending `hello` on the RDP stream
+    async fn peers(hello: &[u8]) -> Peers {
+        let (server, client) = endpoints();
+        let server_addr = server.local_addr().unwrap();
+
+        let accepted = tokio::spawn({
+            let server = server.clone();
+            async move { accept(server.accept().await.unwrap()).await }
+        });
+
+        let (client_connection, mut client_stream) = connect(&client, server_addr, "localhost").await.unwrap();
+
+        // The stream is only announced to the server with its first bytes.
+        client_stream.write_all(hello).await.unwrap();
+
+        Peers {
+            _endpoints: (server, client),
+            client: (client_connection, client_stream),
+            server: accepted.await.unwrap().unwrap(),
+        }
+    }
+
+    fn channel_set(processor: impl SvcProcessor + 'static) -> StaticChannelSet {
+        let mut channels = StaticChannelSet::new();
+        channels.insert(processor);
+        channels
+    }
+
+    #[tokio::test]
+    async fn rdp_stream_roundtrip() {
+        let Peers {
+            _endpoints,
+            client: (_client_connection, mut client_stream),
+            server: (server_connection, mut server_stream),
+        } = peers(b"request").await;
+
+        let handshake_data = server_connection
+            .handshake_data()
+            .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+            .unwrap();
+        assert_eq!(handshake_data.protocol.as_deref(), Some(ALPN_PROTOCOL));
+
+        let mut request = [0; 7];
+        server_stream.read_exact(&mut request).await.unwrap();
+        assert_eq!(&request, b"request");
+
+        server_stream.write_all(b"response").await.unwrap();
+        server_stream.shutdown().await.unwrap();
+
+        let mut response = Vec::new();
+        client_stream.read_to_end(&mut response).await.unwrap();
+        assert_eq!(response, b"response");
+    }
+
+    #[tokio::test]
+    async fn channels_roundtrip() {
+        let Peers {
+            _endpoints,
+            client: (client_connection, _),
+            server: (server_connection, _),
+        } = peers(b"request").await;
+
+        let server = tokio::spawn(async move {
+            let mut channels = QuicChannels::accept(&server_connection, channel_set(Echo))
+                .await
+                .unwrap();
+
+            // The message spans several chunks, and is echoed once complete.
+            for _ in 0..3 {
+                assert_eq!(channels.process().await.unwrap(), Some(TypeId::of::<Echo>()));
+            }
+
+            channels
+        });
+
+        let mut channels = QuicChannels::open(&client_connection, channel_set(Recorder::default()))
+            .await
+            .unwrap();
+
+        let message = vec![0xA5; 2 * CHANNEL_CHUNK_LENGTH + 1];
+        channels
+            .send(SvcProcessorMessages::<Recorder>::new(vec![SvcMessage::from(
+                message.clone(),
+            )]))
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(channels.process().await.unwrap(), Some(TypeId::of::<Recorder>()));
+        }
+
+        let recorder = channels
+            .channels()
+            .get_by_type::<Recorder>()
+            .and_then(|channel| channel.channel_processor_downcast_ref::<Recorder>())
+            .unwrap();
+        assert_eq!(recorder.messages, [message]);
+
+        let _server_channels = server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn finished_channels_end_processing() {
+        let Peers {
+            _endpoints,
+            client: (client_connection, _client_stream),
+            server: (server_connection, _server_stream),
+        } = peers(b"request").await;
+
+        let channels = QuicChannels::open(&client_connection, channel_set(Recorder::default()))
+            .await
+            .unwrap();
+
+        let mut server_channels = QuicChannels::accept(&server_connection, channel_set(Echo))
+            .await
+            .unwrap();
+
+        // Dropping the send streams finishes them.
+        drop(channels);
+
+        assert_eq!(server_channels.process().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn unexpected_channel_is_rejected() {
+        let Peers {
+            _endpoints,
+            client: (client_connection, _client_stream),
+            server: (server_connection, _server_stream),
+        } = peers(b"request").await;
+
+        let mut stream = open_channel(&client_connection, "OTHER").await.unwrap();
+        stream.flush().await.unwrap();
+
+        let error = QuicChannels::accept(&server_connection, channel_set(Echo))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn oversized_chunk_is_rejected() {
+        let Peers {
+            _endpoints,
+            client: (client_connection, _client_stream),
+            server: (server_connection, _server_stream),
+        } = peers(b"request").await;
+
+        let mut stream = open_channel(&client_connection, "ECHO").await.unwrap();
+        stream
+            .write_u32_le(u32::try_from(MAX_CHUNK_SIZE + 1).unwrap())
+            .await
+            .unwrap();
+
+        let mut channels = QuicChannels::accept(&server_connection, channel_set(Echo))
+            .await
+            .unwrap();
+
+        let error = channels.process().await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
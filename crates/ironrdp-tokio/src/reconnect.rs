@@ -0,0 +1,316 @@
+//! Automatic reconnection after a transport loss
+//!
+//! The embedder reports the loss of the transport with [`ReconnectManager::connection_lost`], along with the
+//! auto-reconnect cookie of the session when the server sent one, and calls [`ReconnectManager::connect`]
+//! again when allowed: the connection sequence is replayed, retrying with an exponential backoff, and the session
+//! is resumed without prompting for the credentials when the cookie is accepted by the server.
+
+use core::fmt;
+use core::future::Future;
+use core::time::Duration;
+use std::io;
+
+use ironrdp_connector::{Config, ConnectorError, ConnectorResult};
+use ironrdp_pdu::rdp::session_info::ServerAutoReconnect;
+
+/// Retry schedule of a [`ReconnectManager`]
+///
+/// The delay before a retry starts at `initial_delay` and doubles after each failed attempt, up to `max_delay`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Maximum number of connection attempts of a reconnection, including the first one
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Maximum number of reconnections over the lifetime of the [`ReconnectManager`]
+    pub max_reconnections: u32,
+    /// Whether to reconnect when the server sent no auto-reconnect cookie, logging on again with the credentials
+    ///
+    /// Disabled by default: the user would be logged on silently, possibly to a new session.
+    pub reconnect_without_cookie: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_reconnections: 10,
+            reconnect_without_cookie: false,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before the attempt following the failed `attempt`, starting at 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+/// State change of a [`ReconnectManager`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconnectEvent {
+    /// The connection sequence is starting
+    Connecting {
+        /// Attempt number, starting at 1
+        attempt: u32,
+    },
+    /// The connection sequence completed
+    Connected { attempt: u32 },
+    /// The transport of an established connection was lost
+    ConnectionLost {
+        /// Whether the session can be resumed with an auto-reconnect cookie
+        resumable: bool,
+    },
+    /// An attempt failed on a transport error, and is retried after `delay`
+    RetryScheduled { attempt: u32, delay: Duration },
+    /// The last attempt of a reconnection allowed by the policy failed
+    GaveUp { attempts: u32 },
+}
+
+type EventHandler = Box<dyn FnMut(&ReconnectEvent) + Send>;
+
+/// Reconnect loop shared by the clients
+///
+/// Only reconnections are retried, and only on transport errors (see [`is_transport_error`]): the initial
+/// connection and other failures, such as a denied access, are returned immediately.
+#[derive(Default)]
+pub struct ReconnectManager {
+    policy: ReconnectPolicy,
+    event_handler: Option<EventHandler>,
+    /// Number of reconnections allowed by [`Self::connection_lost`] so far
+    reconnections: u32,
+    /// Whether the next connection is a reconnection
+    reconnecting: bool,
+}
+
+impl fmt::Debug for ReconnectManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectManager")
+            .field("policy", &self.policy)
+            .field("reconnections", &self.reconnections)
+            .field("reconnecting", &self.reconnecting)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReconnectManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the function called on each [`ReconnectEvent`]
+    #[must_use]
+    pub fn with_event_handler(mut self, event_handler: impl FnMut(&ReconnectEvent) + Send + 'static) -> Self {
+        self.event_handler = Some(Box::new(event_handler));
+        self
+    }
+
+    /// Reports the loss of the transport of an established connection, returning whether to reconnect
+    ///
+    /// `auto_reconnect_cookie`, the latest cookie sent by the server during the session if any, is set in
+    /// `config`. Without cookie, the connection is only resumed when the policy allows to log on again with the
+    /// credentials of `config`. The connection is not resumed either once `max_reconnections` is reached.
+    #[must_use]
+    pub fn connection_lost(&mut self, config: &mut Config, auto_reconnect_cookie: Option<ServerAutoReconnect>) -> bool {
+        let resumable = auto_reconnect_cookie.is_some();
+        self.emit(ReconnectEvent::ConnectionLost { resumable });
+
+        if !resumable && !self.policy.reconnect_without_cookie {
+            return false;
+        }
+
+        if self.reconnections >= self.policy.max_reconnections {
+            return false;
+        }
+
+        self.reconnections += 1;
+        self.reconnecting = true;
+        config.auto_reconnect_cookie = auto_reconnect_cookie;
+
+        true
+    }
+
+    /// Runs the connection sequence, retrying on transport errors according to the [`ReconnectPolicy`]
+    ///
+    /// `connect` runs the whole connection sequence: transport, security upgrade and connector. It is only
+    /// retried when reconnecting after [`Self::connection_lost`].
+    pub async fn connect<F, Fut, T>(&mut self, mut connect: F) -> ConnectorResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ConnectorResult<T>>,
+    {
+        let reconnecting = core::mem::take(&mut self.reconnecting);
+        let max_attempts = if reconnecting { self.policy.max_attempts } else { 1 };
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            self.emit(ReconnectEvent::Connecting { attempt });
+
+            let error = match connect().await {
+                Ok(connected) => {
+                    self.emit(ReconnectEvent::Connected { attempt });
+                    return Ok(connected);
+                }
+                Err(error) => error,
+            };
+
+            if !is_transport_error(&error) {
+                return Err(error);
+            }
+
+            if attempt >= max_attempts {
+                if reconnecting {
+                    self.emit(ReconnectEvent::GaveUp { attempts: attempt });
+                }
+                return Err(error);
+            }
+
+            let delay = self.policy.delay(attempt);
+            self.emit(ReconnectEvent::RetryScheduled { attempt, delay });
+
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn emit(&mut self, event: ReconnectEvent) {
+        if let Some(event_handler) = &mut self.event_handler {
+            event_handler(&event);
+        }
+    }
+}
+
+/// Whether the connection failed because of the transport, e.g. an unreachable host or a reset connection
+pub fn is_transport_error(error: &ConnectorError) -> bool {
+    let mut source = core::error::Error::source(error);
+
+    while let Some(error) = source {
+        if error.is::<io::Error>() {
+            return true;
+        }
+
+        source = error.source();
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use ironrdp_connector::{custom_err, general_err, reason_err};
+
+    use super::*;
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            ..ReconnectPolicy::default()
+        }
+    }
+
+    fn reset() -> ConnectorError {
+        custom_err!("TCP connect", io::Error::from(io::ErrorKind::ConnectionReset))
+    }
+
+    #[test]
+    fn delay_doubles_after_each_attempt() {
+        let policy = policy();
+
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(5), Duration::from_secs(16));
+    }
+
+    #[test]
+    fn delay_is_clamped_to_max_delay() {
+        let policy = policy();
+
+        assert_eq!(policy.delay(6), Duration::from_secs(30));
+        assert_eq!(policy.delay(32), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn delay_does_not_overflow() {
+        let policy = policy();
+
+        // The shift overflows from the 33rd attempt.
+        assert_eq!(policy.delay(33), Duration::from_secs(30));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(30));
+
+        // The multiplication overflows.
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::MAX,
+            max_delay: Duration::MAX,
+            ..ReconnectPolicy::default()
+        };
+        assert_eq!(policy.delay(2), Duration::MAX);
+    }
+
+    #[test]
+    fn io_errors_are_transport_errors() {
+        assert!(is_transport_error(&reset()));
+
+        let nested = custom_err!("TLS upgrade", reset());
+        assert!(is_transport_error(&nested));
+    }
+
+    #[test]
+    fn other_errors_are_not_transport_errors() {
+        assert!(!is_transport_error(&general_err!("connect")));
+        assert!(!is_transport_error(&reason_err!("CredSSP", "access denied")));
+        assert!(!is_transport_error(&custom_err!("TLS upgrade", fmt::Error)));
+    }
+
+    #[tokio::test]
+    async fn initial_connection_is_not_retried() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut manager = ReconnectManager::new().with_event_handler(move |event| {
+            let _ = tx.send(event.clone());
+        });
+
+        let mut calls = 0;
+        let result = manager
+            .connect(|| {
+                calls += 1;
+                async { Err::<(), _>(reset()) }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        let events: Vec<_> = rx.try_iter().collect();
+        assert_eq!(events, [ReconnectEvent::Connecting { attempt: 1 }]);
+    }
+
+    #[tokio::test]
+    async fn denied_access_is_not_retried() {
+        let mut calls = 0;
+        let result = ReconnectManager::new()
+            .connect(|| {
+                calls += 1;
+                async { Err::<(), _>(reason_err!("CredSSP", "access denied")) }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,277 @@
+//! WebSocket transport
+//!
+//! Tunnels an RDP connection through a `ws://` or `wss://` endpoint, such as a Devolutions Gateway or a
+//! WebSocket proxy in front of a server. [`WebSocketStream`] carries the RDP bytes in binary messages, and
+//! behaves like any other byte stream: it is wrapped in a [`TokioFramed`](crate::TokioFramed), and the TLS
+//! security upgrade of the RDP connection runs inside it, e.g. with `ironrdp_tls::upgrade` on the client side
+//! or a `TlsAcceptor` on the server side.
+//!
+//! For `wss://`, the stream given to [`connect`] or [`accept`] is the TLS stream of the endpoint, the RDP TLS
+//! session then being nested in it.
+//!
+//! The native client uses it for the RDCleanPath connections through a Devolutions Gateway. `ironrdp-server`
+//! only listens on TCP: a server running the acceptor behind a WebSocket accepts it with [`accept`].
+
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use std::io;
+
+use bytes::{Buf as _, Bytes};
+use futures_util::{Sink as _, Stream as _};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+/// Opens a WebSocket to `request`, usually the URL of the endpoint, over `stream`
+///
+/// `stream` is connected to the endpoint: a TCP stream for `ws://`, a TLS stream for `wss://`.
+pub async fn connect<S>(request: impl IntoClientRequest + Unpin, stream: S) -> io::Result<WebSocketStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (ws, _) = tokio_tungstenite::client_async(request, stream)
+        .await
+        .map_err(into_io_error)?;
+
+    Ok(WebSocketStream::new(ws))
+}
+
+/// Accepts a WebSocket opened by a client on `stream`, the RDP connection being then accepted over it
+pub async fn accept<S>(stream: S) -> io::Result<WebSocketStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let ws = tokio_tungstenite::accept_async(stream).await.map_err(into_io_error)?;
+
+    Ok(WebSocketStream::new(ws))
+}
+
+/// Byte stream over a WebSocket
+///
+/// Each write is sent in a binary message, and the payload of the received binary (or text) messages is read
+/// in order. Ping and pong messages are handled by the WebSocket, and a close message ends the stream.
+#[derive(Debug)]
+pub struct WebSocketStream<S> {
+    inner: tokio_tungstenite::WebSocketStream<S>,
+    /// Received payload not read yet
+    payload: Bytes,
+    closed: bool,
+}
+
+impl<S> WebSocketStream<S> {
+    /// Wraps a WebSocket whose opening handshake is done
+    pub fn new(inner: tokio_tungstenite::WebSocketStream<S>) -> Self {
+        Self {
+            inner,
+            payload: Bytes::new(),
+            closed: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &tokio_tungstenite::WebSocketStream<S> {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut tokio_tungstenite::WebSocketStream<S> {
+        &mut self.inner
+    }
+}
+
+impl<S> AsyncRead for WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        while this.payload.is_empty() {
+            if this.closed {
+                return Poll::Ready(Ok(()));
+            }
+
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Ok(Message::Binary(payload))) => this.payload = payload,
+                Some(Ok(Message::Text(payload))) => this.payload = Bytes::from(payload),
+                Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => {}
+                Some(Ok(Message::Close(_))) | None => this.closed = true,
+                Some(Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed)) => {
+                    this.closed = true;
+                }
+                Some(Err(error)) => return Poll::Ready(Err(into_io_error(error))),
+            }
+        }
+
+        let len = buf.remaining().min(this.payload.len());
+        buf.put_slice(&this.payload[..len]);
+        this.payload.advance(len);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> AsyncWrite for WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let mut inner = Pin::new(&mut self.get_mut().inner);
+
+        ready!(inner.as_mut().poll_ready(cx)).map_err(into_io_error)?;
+        inner
+            .start_send(Message::Binary(Bytes::copy_from_slice(buf)))
+            .map_err(into_io_error)?;
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_flush(cx)
+            .map_err(into_io_error)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match ready!(Pin::new(&mut self.get_mut().inner).poll_close(cx)) {
+            Ok(()) | Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                Poll::Ready(Ok(()))
+            }
+            Err(error) => Poll::Ready(Err(into_io_error(error))),
+        }
+    }
+}
+
+fn into_io_error(error: tungstenite::Error) -> io::Error {
+    match error {
+        tungstenite::Error::Io(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt as _, StreamExt as _};
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _, DuplexStream};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    async fn pair() -> (WebSocketStream<DuplexStream>, WebSocketStream<DuplexStream>) {
+        let (client, server) = tokio::io::duplex(4096);
+        let (client, server) = tokio::join!(connect("ws://localhost/rdp", client), accept(server));
+
+        (client.unwrap(), server.unwrap())
+    }
+
+    #[tokio::test]
+    async fn reads_span_messages() {
+        let (mut client, mut server) = pair().await;
+
+        client.write_all(b"hello ").await.unwrap();
+        client.write_all(b"world").await.unwrap();
+        client.flush().await.unwrap();
+
+        // A read doesn't cross the end of a message.
+        let mut buf = [0; 4];
+        assert_eq!(server.read(&mut buf).await.unwrap(), 4);
+        assert_eq!(&buf, b"hell");
+        assert_eq!(server.read(&mut buf).await.unwrap(), 2);
+        assert_eq!(&buf[..2], b"o ");
+
+        let mut buf = [0; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"world");
+    }
+
+    #[tokio::test]
+    async fn text_messages_are_read() {
+        let (mut client, mut server) = pair().await;
+
+        server.get_mut().send(Message::text("text")).await.unwrap();
+
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"text");
+    }
+
+    #[tokio::test]
+    async fn pings_are_answered_and_skipped() {
+        let (mut client, mut server) = pair().await;
+
+        server
+            .get_mut()
+            .send(Message::Ping(Bytes::from_static(b"ping")))
+            .await
+            .unwrap();
+        server.get_mut().send(Message::binary(&b"data"[..])).await.unwrap();
+
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"data");
+
+        // The pong is sent with the next flush.
+        client.flush().await.unwrap();
+
+        let message = server.get_mut().next().await.unwrap().unwrap();
+        assert_eq!(message, Message::Pong(Bytes::from_static(b"ping")));
+    }
+
+    #[tokio::test]
+    async fn close_ends_the_stream() {
+        let (mut client, mut server) = pair().await;
+
+        server.write_all(b"bye").await.unwrap();
+        server.shutdown().await.unwrap();
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, b"bye");
+
+        // The stream stays at its end, and is closed already.
+        assert_eq!(client.read(&mut [0; 1]).await.unwrap(), 0);
+        client.shutdown().await.unwrap();
+
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).await.unwrap();
+        assert!(received.is_empty());
+    }
+
+    #[tokio::test]
+    async fn shutdown_sends_a_close_message() {
+        let (mut client, mut server) = pair().await;
+
+        client.shutdown().await.unwrap();
+
+        let message = server.get_mut().next().await.unwrap().unwrap();
+        assert!(matches!(message, Message::Close(_)));
+    }
+
+    #[tokio::test]
+    async fn tcp_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = accept(stream).await.unwrap();
+
+            let mut request = [0; 7];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request, b"request");
+
+            stream.write_all(b"response").await.unwrap();
+            stream.shutdown().await.unwrap();
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let mut stream = connect(format!("ws://{server_addr}/rdp"), stream).await.unwrap();
+
+        stream.write_all(b"request").await.unwrap();
+        stream.flush().await.unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        assert_eq!(response, b"response");
+
+        server.await.unwrap();
+    }
+}
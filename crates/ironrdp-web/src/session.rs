@@ -754,10 +754,23 @@ impl iron_remote_desktop::Session for Session {
                                     .build(),
                                 );
                                 active_stage.set_enable_server_pointer(enable_server_pointer);
+                                active_stage
+                                    .set_relative_mouse_supported(box_connection_activation.relative_mouse_supported());
+                                active_stage.set_desktop_size(desktop_size);
                                 break 'activation_seq;
                             }
                         }
                     }
+                    ActiveStageOutput::Control(status) => {
+                        info!(?status, "Session control changed");
+                    }
+                    ActiveStageOutput::WindowOrders(orders) => {
+                        debug!(count = orders.len(), "Ignored windowing orders");
+                    }
+                    ActiveStageOutput::ServerRedirection(redirection) => {
+                        warn!(target = ?redirection.target_host(), "Server redirection is not supported");
+                        break 'outer GracefulDisconnectReason::Other("server redirection".to_owned());
+                    }
                     ActiveStageOutput::Terminate(reason) => break 'outer reason,
                 }
             }
@@ -868,6 +881,8 @@ fn build_config(
     connector::Config {
         credentials: Credentials::UsernamePassword { username, password },
         domain,
+        redirection_credentials: None,
+        auto_reconnect_cookie: None,
         // TODO(#327): expose these options from the WASM module.
         enable_tls: true,
         enable_credssp: true,
@@ -902,10 +917,12 @@ fn build_config(
         enable_server_pointer: false,
         autologon: false,
         enable_audio_playback: false,
+        remote_app: false,
         request_data: None,
         pointer_software_rendering: false,
         performance_flags: PerformanceFlags::default(),
         desktop_scale_factor: 0,
+        monitors: Vec::new(),
         hardware_id: None,
         license_cache: None,
         timezone_info: TimezoneInfo::default(),
@@ -1007,6 +1024,7 @@ async fn connect(
                 // HACK: It's supposed to be the computer name of the client, but since it's not easy to retrieve this information in the browser,
                 // we set the destination hostname instead because it happens to work.
                 hostname: Some(destination),
+                ..KerberosConfig::default()
             }),
     )
     .await?;
@@ -0,0 +1,848 @@
+//! End-to-end RemoteApp server publishing a single application.
+//!
+//! This example ties together the remote applications (RAIL) and graphics pipeline (EGFX) stacks of the server:
+//!
+//! ```text
+//! host process ─► WindowBackend ─► BGRA to I420 ─► H264Encoder ─► GraphicsPipelineServer (surface mapped to the window)
+//!                      ▲
+//!                      └─ input of the client     window orders ─► RailServer
+//! ```
+//!
+//! The application is launched as a host process whose window is published alone, the client showing it as a local
+//! window. The window backend is synthetic, so the example runs anywhere: it draws the window, shows the pointer and
+//! the typed characters, and forwards the typed characters to the standard input of the process. Implement
+//! [`WindowBackend`] on top of a platform window capture and input injection to publish an actual application.
+//!
+//! Connect with a RemoteApp client supporting EGFX with AVC420, e.g. `mstsc` with the `remoteapplicationmode:i:1` and
+//! `remoteapplicationprogram:s:||<PROGRAM NAME>` settings.
+
+#![allow(unused_crate_dependencies)] // False positives because there are both a library and a binary.
+#![allow(clippy::print_stdout)]
+
+use core::fmt;
+use core::net::SocketAddr;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::Context as _;
+use ironrdp::dvc::encode_dvc_messages;
+use ironrdp::egfx::pdu::{annex_b_to_avc, Avc420Region, CapabilitiesAdvertisePdu, CapabilitySet};
+use ironrdp::egfx::server::{GfxContext, GraphicsPipelineHandler, GraphicsPipelineServer};
+use ironrdp::graphics::color_conversion::{bgra_to_yuv, ChromaSubsampling, YuvBuffer, YuvPlanes};
+use ironrdp::pdu::geometry::ExclusiveRectangle;
+use ironrdp::pdu::orders::{WindowInfoOrder, WindowOrder, WindowStyle};
+use ironrdp::rail::pdu::{ClientStatusFlags, ExecPdu, ExecResult, SysCommand, SysCommandPdu, WindowMovePdu};
+use ironrdp::rail::server::ExecOutcome;
+use ironrdp::server::tokio::sync::mpsc::UnboundedSender;
+use ironrdp::server::tokio::time::{self, Duration};
+use ironrdp::server::{
+    tokio, window_list_sync, ConnectionContext, Credentials, DesktopSize, DisplayUpdate, EgfxServerMessage,
+    GfxDvcBridge, GfxServerConfig, GfxServerFactory, GfxServerHandle, KeyboardEvent, MouseEvent, RailServerFactory,
+    RailServerHandler, RailServerMessage, RdpServer, RdpServerDisplay, RdpServerDisplayUpdates, RdpServerInputHandler,
+    ServerEvent, ServerEventSender, TlsIdentityCtx,
+};
+use ironrdp::svc::ChannelFlags;
+use openh264::encoder::{Encoder, EncoderConfig};
+use openh264::formats::YUVSource;
+use openh264::OpenH264API;
+use tracing::{debug, info, trace, warn};
+
+const HELP: &str = "\
+USAGE:
+  cargo run --example=remote_app_server -- [--bind-addr <SOCKET ADDRESS>] [--cert <CERTIFICATE>] [--key <CERTIFICATE KEY>] [--user USERNAME] [--pass PASSWORD] [--fps FPS] -- <PROGRAM> [ARGS...]
+";
+
+const DESKTOP_WIDTH: u16 = 1920;
+const DESKTOP_HEIGHT: u16 = 1080;
+
+const WINDOW_ID: u32 = 0x0001_0001;
+const WINDOW_WIDTH: u16 = 640;
+const WINDOW_HEIGHT: u16 = 480;
+/// Initial position of the window on the desktop of the client
+const WINDOW_POSITION: (i32, i32) = (100, 100);
+
+const WS_OVERLAPPEDWINDOW: u32 = 0x00CF_0000;
+const WS_VISIBLE: u32 = 0x1000_0000;
+const WS_EX_APPWINDOW: u32 = 0x0004_0000;
+const SW_SHOW: u8 = 5;
+
+/// H.264 quantization parameter advertised for the window
+const QP: u8 = 22;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), anyhow::Error> {
+    let action = match parse_args() {
+        Ok(action) => action,
+        Err(e) => {
+            println!("{HELP}");
+            return Err(e.context("invalid argument(s)"));
+        }
+    };
+
+    setup_logging()?;
+
+    match action {
+        Action::ShowHelp => {
+            println!("{HELP}");
+            Ok(())
+        }
+        Action::Run {
+            bind_addr,
+            user,
+            pass,
+            cert,
+            key,
+            fps,
+            program,
+            arguments,
+        } => run(bind_addr, user, pass, cert, key, fps, program, arguments).await,
+    }
+}
+
+#[derive(Debug)]
+enum Action {
+    ShowHelp,
+    Run {
+        bind_addr: SocketAddr,
+        user: String,
+        pass: String,
+        cert: Option<PathBuf>,
+        key: Option<PathBuf>,
+        fps: u32,
+        program: PathBuf,
+        arguments: Vec<String>,
+    },
+}
+
+fn parse_args() -> anyhow::Result<Action> {
+    let mut args = pico_args::Arguments::from_env();
+
+    let action = if args.contains(["-h", "--help"]) {
+        Action::ShowHelp
+    } else {
+        let bind_addr = args
+            .opt_value_from_str("--bind-addr")?
+            .unwrap_or_else(|| "127.0.0.1:3389".parse().expect("valid hardcoded SocketAddr string"));
+
+        let cert = args.opt_value_from_str("--cert")?;
+        let key = args.opt_value_from_str("--key")?;
+
+        let user = args.opt_value_from_str("--user")?.unwrap_or_else(|| "user".to_owned());
+        let pass = args.opt_value_from_str("--pass")?.unwrap_or_else(|| "pass".to_owned());
+
+        let fps = args.opt_value_from_str("--fps")?.unwrap_or(30);
+        anyhow::ensure!((1..=60).contains(&fps), "FPS must be between 1 and 60");
+
+        let mut command = args
+            .finish()
+            .into_iter()
+            .map(|arg| arg.into_string().map_err(|_| anyhow::anyhow!("non UTF-8 argument")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if command.first().is_some_and(|arg| arg == "--") {
+            command.remove(0);
+        }
+        anyhow::ensure!(!command.is_empty(), "the program to publish is missing");
+        let program = PathBuf::from(command.remove(0));
+
+        Action::Run {
+            bind_addr,
+            user,
+            pass,
+            cert,
+            key,
+            fps,
+            program,
+            arguments: command,
+        }
+    };
+
+    Ok(action)
+}
+
+fn setup_logging() -> anyhow::Result<()> {
+    use tracing::metadata::LevelFilter;
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::EnvFilter;
+
+    let fmt_layer = tracing_subscriber::fmt::layer().compact();
+
+    let env_filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::WARN.into())
+        .with_env_var("IRONRDP_LOG")
+        .from_env_lossy();
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(env_filter)
+        .try_init()
+        .context("failed to set tracing global subscriber")?;
+
+    Ok(())
+}
+
+// ============================================================================
+// Host application
+// ============================================================================
+
+/// A captured BGRA frame of the window
+struct CapturedFrame<'a> {
+    data: &'a [u8],
+    stride: usize,
+}
+
+/// Window of the published application
+trait WindowBackend: Send {
+    fn title(&self) -> String;
+
+    fn size(&self) -> DesktopSize;
+
+    /// Captures the content of the window, `None` when it did not change since the previous capture
+    fn capture(&mut self) -> anyhow::Result<Option<CapturedFrame<'_>>>;
+
+    fn keyboard(&mut self, event: KeyboardEvent) -> anyhow::Result<()>;
+
+    /// Mouse event, the positions being relative to the window
+    fn mouse(&mut self, event: MouseEvent);
+
+    /// The user closed the window on the client
+    fn close(&mut self) -> anyhow::Result<()>;
+
+    /// Whether the application exited
+    fn has_exited(&mut self) -> bool;
+}
+
+/// Draws the window of a host process, whose standard input receives the typed characters
+struct SyntheticWindow {
+    name: String,
+    process: Child,
+    stdin: Option<ChildStdin>,
+    frame: Vec<u8>,
+    typed: Vec<char>,
+    pointer: Option<(u16, u16)>,
+    dirty: bool,
+}
+
+impl SyntheticWindow {
+    const BACKGROUND: [u8; 4] = [0xF0, 0xF0, 0xF0, 0xFF];
+    const POINTER_COLOR: [u8; 4] = [0x20, 0x20, 0xD0, 0xFF];
+    const CELL_SIZE: usize = 16;
+    const MAX_TYPED: usize = 256;
+
+    fn launch(program: &Path, arguments: &[String]) -> anyhow::Result<Self> {
+        let mut process = Command::new(program)
+            .args(arguments)
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to launch {}", program.display()))?;
+        let stdin = process.stdin.take();
+
+        let name = program.file_stem().map_or_else(
+            || program.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        info!(%name, pid = process.id(), "Application launched");
+
+        Ok(Self {
+            name,
+            process,
+            stdin,
+            frame: vec![0; usize::from(WINDOW_WIDTH) * usize::from(WINDOW_HEIGHT) * 4],
+            typed: Vec::new(),
+            pointer: None,
+            dirty: true,
+        })
+    }
+
+    fn fill(frame: &mut [u8], left: usize, top: usize, size: usize, color: [u8; 4]) {
+        let (width, height) = (usize::from(WINDOW_WIDTH), usize::from(WINDOW_HEIGHT));
+
+        for y in top..(top + size).min(height) {
+            for x in left..(left + size).min(width) {
+                let offset = (y * width + x) * 4;
+                frame[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Draws a cell per typed character, colored after the character, and the pointer
+    fn draw(&mut self) {
+        for pixel in self.frame.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&Self::BACKGROUND);
+        }
+
+        let columns = usize::from(WINDOW_WIDTH) / Self::CELL_SIZE;
+        for (index, c) in self.typed.iter().enumerate() {
+            let [b, g, r, _] = u32::from(*c).wrapping_mul(0x9E37_79B9).to_le_bytes();
+            let (left, top) = ((index % columns) * Self::CELL_SIZE, (index / columns) * Self::CELL_SIZE);
+            Self::fill(&mut self.frame, left + 1, top + 1, Self::CELL_SIZE - 2, [b, g, r, 0xFF]);
+        }
+
+        if let Some((x, y)) = self.pointer {
+            Self::fill(&mut self.frame, usize::from(x), usize::from(y), 6, Self::POINTER_COLOR);
+        }
+    }
+
+    fn type_char(&mut self, c: char) -> anyhow::Result<()> {
+        if self.typed.len() == Self::MAX_TYPED {
+            self.typed.clear();
+        }
+        self.typed.push(c);
+        self.dirty = true;
+
+        if let Some(stdin) = self.stdin.as_mut() {
+            let mut buffer = [0; 4];
+            stdin.write_all(c.encode_utf8(&mut buffer).as_bytes())?;
+            stdin.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl WindowBackend for SyntheticWindow {
+    fn title(&self) -> String {
+        self.name.clone()
+    }
+
+    fn size(&self) -> DesktopSize {
+        DesktopSize {
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+        }
+    }
+
+    fn capture(&mut self) -> anyhow::Result<Option<CapturedFrame<'_>>> {
+        if !core::mem::take(&mut self.dirty) {
+            return Ok(None);
+        }
+
+        self.draw();
+
+        Ok(Some(CapturedFrame {
+            data: &self.frame,
+            stride: usize::from(WINDOW_WIDTH) * 4,
+        }))
+    }
+
+    fn keyboard(&mut self, event: KeyboardEvent) -> anyhow::Result<()> {
+        const SCANCODE_ENTER: u8 = 0x1C;
+
+        match event {
+            KeyboardEvent::UnicodePressed(code) => {
+                if let Some(c) = char::from_u32(u32::from(code)) {
+                    self.type_char(c)?;
+                }
+            }
+            KeyboardEvent::Pressed {
+                code: SCANCODE_ENTER, ..
+            } => self.type_char('\n')?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn mouse(&mut self, event: MouseEvent) {
+        if let MouseEvent::Move { x, y } = event {
+            self.pointer = Some((x, y));
+            self.dirty = true;
+        }
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        // Closing the standard input lets a well-behaved process exit by itself.
+        self.stdin = None;
+        self.process.kill().context("failed to kill the application")
+    }
+
+    fn has_exited(&mut self) -> bool {
+        !matches!(self.process.try_wait(), Ok(None))
+    }
+}
+
+/// The published application, and the position of its window on the desktop of the client
+struct PublishedApp {
+    window: Box<dyn WindowBackend>,
+    position: (i32, i32),
+}
+
+type SharedApp = Arc<Mutex<PublishedApp>>;
+
+impl PublishedApp {
+    fn window_info(&self) -> WindowInfoOrder {
+        let size = self.window.size();
+        let area = (u32::from(size.width), u32::from(size.height));
+        let shape = vec![ExclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: size.width,
+            bottom: size.height,
+        }];
+
+        WindowInfoOrder {
+            window_id: WINDOW_ID,
+            new: true,
+            owner_window_id: Some(0),
+            style: Some(WindowStyle {
+                style: WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                extended_style: WS_EX_APPWINDOW,
+            }),
+            show_state: Some(SW_SHOW),
+            title: Some(self.window.title()),
+            client_offset: Some(self.position),
+            client_area_size: Some(area),
+            window_offset: Some(self.position),
+            window_client_delta: Some((0, 0)),
+            window_size: Some(area),
+            window_rects: Some(shape.clone()),
+            visible_offset: Some(self.position),
+            visibility_rects: Some(shape),
+            taskbar_button: Some(0),
+            ..WindowInfoOrder::default()
+        }
+    }
+
+    /// Translates a position on the desktop to the window, `None` when outside of it
+    fn to_window(&self, x: u16, y: u16) -> Option<(u16, u16)> {
+        let size = self.window.size();
+        let x = u16::try_from(i32::from(x) - self.position.0).ok()?;
+        let y = u16::try_from(i32::from(y) - self.position.1).ok()?;
+
+        (x < size.width && y < size.height).then_some((x, y))
+    }
+}
+
+// ============================================================================
+// Remote applications
+// ============================================================================
+
+/// Publishes the window of the application to a client
+struct AppRail {
+    app: SharedApp,
+    events: UnboundedSender<ServerEvent>,
+}
+
+impl fmt::Debug for AppRail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppRail").finish_non_exhaustive()
+    }
+}
+
+impl AppRail {
+    fn send_orders(&self, orders: Vec<WindowOrder>) {
+        let message = ServerEvent::Rail(RailServerMessage::WindowOrders(orders));
+        if self.events.send(message).is_err() {
+            warn!("Server is gone, dropping window orders");
+        }
+    }
+}
+
+impl RailServerHandler for AppRail {
+    fn client_status(&mut self, flags: ClientStatusFlags) {
+        info!(?flags, "RemoteApp client ready");
+
+        let window = self.app.lock().expect("poisoned").window_info();
+        self.send_orders(window_list_sync(vec![window], Some(WINDOW_ID)));
+    }
+
+    fn exec(&mut self, exec: &ExecPdu) -> ExecOutcome {
+        // The application is already running, the client only attaches to it.
+        let requested = exec.exe_or_file.trim_start_matches("||");
+        let title = self.app.lock().expect("poisoned").window.title();
+
+        let result = if requested.eq_ignore_ascii_case(&title) {
+            ExecResult::OK
+        } else {
+            warn!(requested, published = %title, "Request for an application which is not published");
+            ExecResult::NOT_IN_ALLOWLIST
+        };
+
+        ExecOutcome { result, raw_result: 0 }
+    }
+
+    fn system_command(&mut self, command: &SysCommandPdu) {
+        debug!(?command, "System command");
+
+        if command.window_id == WINDOW_ID && command.command == SysCommand::CLOSE {
+            if let Err(error) = self.app.lock().expect("poisoned").window.close() {
+                warn!(%error, "Failed to close the application");
+            }
+        }
+    }
+
+    fn window_move(&mut self, window_move: &WindowMovePdu) {
+        if window_move.window_id != WINDOW_ID {
+            return;
+        }
+
+        // The window is not resizable, only its new position is applied.
+        let position = (i32::from(window_move.left), i32::from(window_move.top));
+        self.app.lock().expect("poisoned").position = position;
+        debug!(?position, "Window moved");
+
+        self.send_orders(vec![WindowOrder::Window(WindowInfoOrder {
+            window_id: WINDOW_ID,
+            client_offset: Some(position),
+            window_offset: Some(position),
+            visible_offset: Some(position),
+            ..WindowInfoOrder::default()
+        })]);
+    }
+}
+
+struct RailFactory {
+    app: SharedApp,
+    events: Option<UnboundedSender<ServerEvent>>,
+}
+
+impl ServerEventSender for RailFactory {
+    fn set_sender(&mut self, sender: UnboundedSender<ServerEvent>) {
+        self.events = Some(sender);
+    }
+}
+
+impl RailServerFactory for RailFactory {
+    fn build_backend(&self, ctx: &ConnectionContext) -> Box<dyn RailServerHandler> {
+        debug!(?ctx, "RemoteApp connection");
+
+        Box::new(AppRail {
+            app: Arc::clone(&self.app),
+            events: self.events.clone().expect("sender set by the server"),
+        })
+    }
+}
+
+// ============================================================================
+// Encoding
+// ============================================================================
+
+struct OpenH264Encoder {
+    encoder: Encoder,
+}
+
+impl OpenH264Encoder {
+    fn new() -> anyhow::Result<Self> {
+        let encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())
+            .context("failed to create OpenH264 encoder")?;
+
+        Ok(Self { encoder })
+    }
+
+    fn encode(&mut self, frame: &YuvPlanes<'_>) -> anyhow::Result<Vec<u8>> {
+        let bitstream = self.encoder.encode(&I420Source(frame)).context("H.264 encoding")?;
+
+        Ok(bitstream.to_vec())
+    }
+}
+
+struct I420Source<'a>(&'a YuvPlanes<'a>);
+
+impl YUVSource for I420Source<'_> {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.0.width, self.0.height)
+    }
+
+    fn strides(&self) -> (usize, usize, usize) {
+        (self.0.y_stride, self.0.u_stride, self.0.v_stride)
+    }
+
+    fn y(&self) -> &[u8] {
+        self.0.y
+    }
+
+    fn u(&self) -> &[u8] {
+        self.0.u
+    }
+
+    fn v(&self) -> &[u8] {
+        self.0.v
+    }
+}
+
+// ============================================================================
+// Graphics pipeline
+// ============================================================================
+
+struct GfxHandler;
+
+impl GraphicsPipelineHandler for GfxHandler {
+    fn capabilities_advertise(&mut self, pdu: &CapabilitiesAdvertisePdu, _ctx: &mut GfxContext) {
+        debug!(?pdu, "EGFX capabilities advertised");
+    }
+
+    fn on_ready(&mut self, negotiated: &CapabilitySet, _ctx: &mut GfxContext) {
+        info!(?negotiated, "EGFX channel ready");
+    }
+}
+
+/// Hands the graphics pipeline server of the current connection over to the [`WindowStreamer`]
+#[derive(Clone, Default)]
+struct GfxFactory {
+    current: Arc<Mutex<Option<GfxServerHandle>>>,
+}
+
+impl GfxServerFactory for GfxFactory {
+    fn build_gfx_handler(&self, _config: &GfxServerConfig) -> Box<dyn GraphicsPipelineHandler> {
+        Box::new(GfxHandler)
+    }
+
+    fn build_server_with_handle(&self, config: &GfxServerConfig) -> Option<(GfxDvcBridge, GfxServerHandle)> {
+        let server = Arc::new(Mutex::new(GraphicsPipelineServer::new(self.build_gfx_handler(config))));
+        *self.current.lock().expect("poisoned") = Some(Arc::clone(&server));
+
+        Some((GfxDvcBridge::new(Arc::clone(&server)), server))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ActiveSurface {
+    channel_id: u32,
+    surface_id: u16,
+}
+
+/// Streams the window over EGFX, on a surface mapped to the window of the client
+struct WindowStreamer {
+    app: SharedApp,
+    encoder: OpenH264Encoder,
+    yuv: YuvBuffer,
+    gfx: Arc<Mutex<Option<GfxServerHandle>>>,
+    events: UnboundedSender<ServerEvent>,
+    surface: Option<ActiveSurface>,
+    start: Instant,
+}
+
+impl WindowStreamer {
+    fn new(
+        app: SharedApp,
+        encoder: OpenH264Encoder,
+        gfx: Arc<Mutex<Option<GfxServerHandle>>>,
+        events: UnboundedSender<ServerEvent>,
+    ) -> Self {
+        let size = app.lock().expect("poisoned").window.size();
+
+        Self {
+            app,
+            encoder,
+            yuv: YuvBuffer::new(size.width.into(), size.height.into(), ChromaSubsampling::Yuv420),
+            gfx,
+            events,
+            surface: None,
+            start: Instant::now(),
+        }
+    }
+
+    /// Streams the window until the application exits
+    async fn run(mut self, fps: u32) -> anyhow::Result<()> {
+        let mut interval = time::interval(Duration::from_secs(1) / fps);
+
+        loop {
+            interval.tick().await;
+
+            if self.app.lock().expect("poisoned").window.has_exited() {
+                // Stopping the server closes the connection of the client.
+                info!("Application exited");
+                return Ok(());
+            }
+
+            self.step()?;
+        }
+    }
+
+    fn step(&mut self) -> anyhow::Result<()> {
+        let Some(handle) = self.gfx.lock().expect("poisoned").clone() else {
+            return Ok(());
+        };
+        let mut server = handle.lock().expect("poisoned");
+        let mut app = self.app.lock().expect("poisoned");
+
+        let Some(channel_id) = server.channel_id().filter(|_| server.is_ready()) else {
+            self.surface = None;
+            return Ok(());
+        };
+
+        if !server.supports_avc420() {
+            trace!("Client does not support AVC420");
+            return Ok(());
+        }
+
+        let size = app.window.size();
+        let surface_id = match self.surface {
+            Some(surface) if surface.channel_id == channel_id => surface.surface_id,
+            _ => {
+                let surface_id = server
+                    .create_surface(size.width, size.height)
+                    .context("failed to create surface")?;
+                server.map_surface_to_window(
+                    surface_id,
+                    u64::from(WINDOW_ID),
+                    u32::from(size.width),
+                    u32::from(size.height),
+                );
+
+                // The new client has nothing to predict from.
+                self.encoder.encoder.force_intra_frame();
+                app.window.mouse(MouseEvent::Move { x: 0, y: 0 });
+
+                self.surface = Some(ActiveSurface { channel_id, surface_id });
+                surface_id
+            }
+        };
+
+        if !server.should_backpressure() {
+            if let Some(frame) = app.window.capture()? {
+                bgra_to_yuv(frame.data, frame.stride, &mut self.yuv.as_planes_mut())?;
+                let bitstream = self.encoder.encode(&self.yuv.as_planes())?;
+
+                let region = Avc420Region::new(0, 0, size.width - 1, size.height - 1, QP, 100);
+                let timestamp = u32::try_from(self.start.elapsed().as_millis()).unwrap_or(u32::MAX);
+
+                if let Some(frame_id) =
+                    server.send_avc420_frame(surface_id, &annex_b_to_avc(&bitstream), &[region], timestamp)
+                {
+                    trace!(frame_id, "Window frame sent");
+                }
+            }
+        }
+
+        let messages = server.drain_output();
+        if !messages.is_empty() {
+            let messages = encode_dvc_messages(channel_id, messages, ChannelFlags::SHOW_PROTOCOL)?;
+            self.events
+                .send(ServerEvent::Egfx(EgfxServerMessage::SendMessages {
+                    channel_id,
+                    messages,
+                }))
+                .context("server is gone")?;
+        }
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+/// Forwards the input of the client to the window of the application
+struct InputForwarder {
+    app: SharedApp,
+}
+
+impl RdpServerInputHandler for InputForwarder {
+    fn keyboard(&mut self, event: KeyboardEvent) {
+        if let Err(error) = self.app.lock().expect("poisoned").window.keyboard(event) {
+            warn!(%error, "Failed to forward keyboard input");
+        }
+    }
+
+    fn mouse(&mut self, event: MouseEvent) {
+        let mut app = self.app.lock().expect("poisoned");
+
+        let event = match event {
+            MouseEvent::Move { x, y } => match app.to_window(x, y) {
+                Some((x, y)) => MouseEvent::Move { x, y },
+                None => return,
+            },
+            event => event,
+        };
+
+        app.window.mouse(event);
+    }
+}
+
+/// The window is streamed over EGFX only, so there are never any legacy bitmap updates
+struct NoDisplayUpdates;
+
+#[async_trait::async_trait]
+impl RdpServerDisplayUpdates for NoDisplayUpdates {
+    async fn next_update(&mut self) -> anyhow::Result<Option<DisplayUpdate>> {
+        core::future::pending().await
+    }
+}
+
+struct Desktop;
+
+#[async_trait::async_trait]
+impl RdpServerDisplay for Desktop {
+    async fn size(&mut self) -> DesktopSize {
+        DesktopSize {
+            width: DESKTOP_WIDTH,
+            height: DESKTOP_HEIGHT,
+        }
+    }
+
+    async fn updates(&mut self) -> anyhow::Result<Box<dyn RdpServerDisplayUpdates>> {
+        Ok(Box::new(NoDisplayUpdates))
+    }
+}
+
+#[expect(clippy::too_many_arguments)]
+async fn run(
+    bind_addr: SocketAddr,
+    username: String,
+    password: String,
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+    fps: u32,
+    program: PathBuf,
+    arguments: Vec<String>,
+) -> anyhow::Result<()> {
+    info!(%bind_addr, ?cert, ?key, fps, ?program, "run");
+
+    let app = Arc::new(Mutex::new(PublishedApp {
+        window: Box::new(SyntheticWindow::launch(&program, &arguments)?),
+        position: WINDOW_POSITION,
+    }));
+
+    let server_builder = RdpServer::builder().with_addr(bind_addr);
+
+    let server_builder = if let Some((cert_path, key_path)) = cert.as_deref().zip(key.as_deref()) {
+        let identity = TlsIdentityCtx::init_from_paths(cert_path, key_path).context("failed to init TLS identity")?;
+        let acceptor = identity.make_acceptor().context("failed to build TLS acceptor")?;
+
+        server_builder.with_hybrid(acceptor, identity.pub_key)
+    } else {
+        server_builder.with_no_security()
+    };
+
+    let gfx = GfxFactory::default();
+    let rail = RailFactory {
+        app: Arc::clone(&app),
+        events: None,
+    };
+
+    let mut server = server_builder
+        .with_input_handler(InputForwarder { app: Arc::clone(&app) })
+        .with_display_handler(Desktop)
+        .with_rail_factory(Some(Box::new(rail)))
+        .with_gfx_factory(Some(Box::new(gfx.clone())))
+        .build();
+
+    server.set_credentials(Some(Credentials {
+        username,
+        password,
+        domain: None,
+    }));
+
+    let streamer = WindowStreamer::new(
+        Arc::clone(&app),
+        OpenH264Encoder::new()?,
+        gfx.current,
+        server.event_sender().clone(),
+    );
+
+    let result = tokio::select! {
+        result = streamer.run(fps) => result,
+        result = server.run() => result,
+    };
+
+    let mut app = app.lock().expect("poisoned");
+    if !app.window.has_exited() {
+        app.window.close()?;
+    }
+
+    result
+}
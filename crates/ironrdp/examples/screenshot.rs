@@ -167,6 +167,8 @@ fn build_config(username: String, password: String, domain: Option<String>) -> c
     connector::Config {
         credentials: Credentials::UsernamePassword { username, password },
         domain,
+        redirection_credentials: None,
+        auto_reconnect_cookie: None,
         enable_tls: false, // This example does not expose any frontend.
         enable_credssp: true,
         keyboard_type: KeyboardType::IbmEnhanced,
@@ -207,9 +209,11 @@ fn build_config(username: String, password: String, domain: Option<String>) -> c
         request_data: None,
         autologon: false,
         enable_audio_playback: false,
+        remote_app: false,
         pointer_software_rendering: true,
         performance_flags: PerformanceFlags::default(),
         desktop_scale_factor: 0,
+        monitors: Vec::new(),
         hardware_id: None,
         license_cache: None,
         timezone_info: TimezoneInfo::default(),
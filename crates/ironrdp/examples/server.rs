@@ -16,9 +16,9 @@ use ironrdp::rdpsnd::server::{RdpsndServerHandler, RdpsndServerMessage};
 use ironrdp::server::tokio::sync::mpsc::UnboundedSender;
 use ironrdp::server::tokio::time::{self, sleep, Duration};
 use ironrdp::server::{
-    tokio, BitmapUpdate, CliprdrServerFactory, Credentials, DisplayUpdate, KeyboardEvent, MouseEvent, PixelFormat,
-    RdpServer, RdpServerDisplay, RdpServerDisplayUpdates, RdpServerInputHandler, ServerEvent, ServerEventSender,
-    SoundServerFactory, TlsIdentityCtx,
+    tokio, BitmapUpdate, CliprdrServerFactory, ConnectionContext, Credentials, DisplayUpdate, KeyboardEvent,
+    MouseEvent, PixelFormat, RdpServer, RdpServerDisplay, RdpServerDisplayUpdates, RdpServerInputHandler, ServerEvent,
+    ServerEventSender, SoundServerFactory, TlsIdentityCtx,
 };
 use ironrdp_cliprdr_native::StubCliprdrBackend;
 use rand::prelude::*;
@@ -241,7 +241,7 @@ impl ServerEventSender for StubSoundServerFactory {
 }
 
 impl SoundServerFactory for StubSoundServerFactory {
-    fn build_backend(&self) -> Box<dyn RdpsndServerHandler> {
+    fn build_backend(&self, _ctx: &ConnectionContext) -> Box<dyn RdpsndServerHandler> {
         Box::new(SndHandler {
             inner: Arc::clone(&self.inner),
             task: None,
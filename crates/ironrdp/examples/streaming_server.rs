@@ -0,0 +1,604 @@
+//! End-to-end "share my screen over RDP" server.
+//!
+//! This example ties together every stage of a graphics pipeline server:
+//!
+//! ```text
+//! CaptureBackend ─► DamageTracker ─► BGRA to I420 ─► H264Encoder ─► GraphicsPipelineServer (AVC420)
+//! ```
+//!
+//! The capture backend is a synthetic test pattern, so the example runs anywhere. Implement
+//! [`CaptureBackend`] on top of a real screen grabber to share an actual desktop.
+//!
+//! Frames are only streamed to clients supporting the graphics pipeline extension (EGFX) with AVC420.
+
+#![allow(unused_crate_dependencies)] // False positives because there are both a library and a binary.
+#![allow(clippy::print_stdout)]
+
+use core::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::Context as _;
+use ironrdp::connector::DesktopSize;
+use ironrdp::dvc::encode_dvc_messages;
+use ironrdp::egfx::pdu::{annex_b_to_avc, Avc420Region, CapabilitiesAdvertisePdu, CapabilitySet};
+use ironrdp::egfx::server::{GfxContext, GraphicsPipelineHandler, GraphicsPipelineServer};
+use ironrdp::graphics::color_conversion::{bgra_to_yuv, ChromaSubsampling, YuvBuffer, YuvPlanes};
+use ironrdp::graphics::diff::{find_different_rects_sub, Rect};
+use ironrdp::server::tokio::sync::mpsc::UnboundedSender;
+use ironrdp::server::tokio::time::{self, Duration};
+use ironrdp::server::{
+    tokio, Credentials, DisplayUpdate, EgfxServerMessage, GfxDvcBridge, GfxServerConfig, GfxServerFactory,
+    GfxServerHandle, KeyboardEvent, MouseEvent, RdpServer, RdpServerDisplay, RdpServerDisplayUpdates,
+    RdpServerInputHandler, ServerEvent, TlsIdentityCtx,
+};
+use ironrdp::svc::ChannelFlags;
+use openh264::encoder::{Encoder, EncoderConfig};
+use openh264::formats::YUVSource;
+use openh264::OpenH264API;
+use tracing::{debug, info, trace};
+
+const HELP: &str = "\
+USAGE:
+  cargo run --example=streaming_server -- [--bind-addr <SOCKET ADDRESS>] [--cert <CERTIFICATE>] [--key <CERTIFICATE KEY>] [--user USERNAME] [--pass PASSWORD] [--fps FPS]
+";
+
+const WIDTH: u16 = 1280;
+const HEIGHT: u16 = 720;
+
+/// H.264 quantization parameter advertised for the updated regions
+const QP: u8 = 22;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), anyhow::Error> {
+    let action = match parse_args() {
+        Ok(action) => action,
+        Err(e) => {
+            println!("{HELP}");
+            return Err(e.context("invalid argument(s)"));
+        }
+    };
+
+    setup_logging()?;
+
+    match action {
+        Action::ShowHelp => {
+            println!("{HELP}");
+            Ok(())
+        }
+        Action::Run {
+            bind_addr,
+            user,
+            pass,
+            cert,
+            key,
+            fps,
+        } => run(bind_addr, user, pass, cert, key, fps).await,
+    }
+}
+
+#[derive(Debug)]
+enum Action {
+    ShowHelp,
+    Run {
+        bind_addr: SocketAddr,
+        user: String,
+        pass: String,
+        cert: Option<PathBuf>,
+        key: Option<PathBuf>,
+        fps: u32,
+    },
+}
+
+fn parse_args() -> anyhow::Result<Action> {
+    let mut args = pico_args::Arguments::from_env();
+
+    let action = if args.contains(["-h", "--help"]) {
+        Action::ShowHelp
+    } else {
+        let bind_addr = args
+            .opt_value_from_str("--bind-addr")?
+            .unwrap_or_else(|| "127.0.0.1:3389".parse().expect("valid hardcoded SocketAddr string"));
+
+        let cert = args.opt_value_from_str("--cert")?;
+        let key = args.opt_value_from_str("--key")?;
+
+        let user = args.opt_value_from_str("--user")?.unwrap_or_else(|| "user".to_owned());
+        let pass = args.opt_value_from_str("--pass")?.unwrap_or_else(|| "pass".to_owned());
+
+        let fps = args.opt_value_from_str("--fps")?.unwrap_or(30);
+        anyhow::ensure!((1..=60).contains(&fps), "FPS must be between 1 and 60");
+
+        Action::Run {
+            bind_addr,
+            user,
+            pass,
+            cert,
+            key,
+            fps,
+        }
+    };
+
+    Ok(action)
+}
+
+fn setup_logging() -> anyhow::Result<()> {
+    use tracing::metadata::LevelFilter;
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::EnvFilter;
+
+    let fmt_layer = tracing_subscriber::fmt::layer().compact();
+
+    let env_filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::WARN.into())
+        .with_env_var("IRONRDP_LOG")
+        .from_env_lossy();
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(env_filter)
+        .try_init()
+        .context("failed to set tracing global subscriber")?;
+
+    Ok(())
+}
+
+// ============================================================================
+// Capture
+// ============================================================================
+
+/// A captured BGRA frame
+struct CapturedFrame<'a> {
+    data: &'a [u8],
+    stride: usize,
+}
+
+/// Source of the frames to share
+trait CaptureBackend {
+    fn size(&self) -> DesktopSize;
+
+    fn capture(&mut self) -> anyhow::Result<CapturedFrame<'_>>;
+}
+
+/// Gradient background with a square bouncing across the screen
+struct TestPatternCapture {
+    width: usize,
+    height: usize,
+    frame: Vec<u8>,
+    position: usize,
+}
+
+impl TestPatternCapture {
+    const SQUARE_SIZE: usize = 128;
+    const SQUARE_COLOR: [u8; 4] = [0x20, 0x80, 0xF0, 0xFF];
+    const SPEED: usize = 8;
+
+    fn new(width: u16, height: u16) -> Self {
+        let mut this = Self {
+            width: width.into(),
+            height: height.into(),
+            frame: vec![0; usize::from(width) * usize::from(height) * 4],
+            position: 0,
+        };
+
+        this.fill(0, 0, this.width, this.height, None);
+
+        this
+    }
+
+    fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, color: Option<[u8; 4]>) {
+        for y in top..top + height {
+            for x in left..left + width {
+                let pixel = color.unwrap_or_else(|| self.background(x, y));
+                let offset = (y * self.width + x) * 4;
+                self.frame[offset..offset + 4].copy_from_slice(&pixel);
+            }
+        }
+    }
+
+    fn background(&self, x: usize, y: usize) -> [u8; 4] {
+        let blue = u8::try_from(x * 255 / self.width).expect("x < width");
+        let green = u8::try_from(y * 255 / self.height).expect("y < height");
+
+        [blue, green, 0x40, 0xFF]
+    }
+
+    fn square_origin(&self) -> (usize, usize) {
+        let course = self.width - Self::SQUARE_SIZE;
+        let offset = self.position % (2 * course);
+        let left = if offset < course { offset } else { 2 * course - offset };
+
+        (left, (self.height - Self::SQUARE_SIZE) / 2)
+    }
+}
+
+impl CaptureBackend for TestPatternCapture {
+    fn size(&self) -> DesktopSize {
+        DesktopSize {
+            width: u16::try_from(self.width).expect("width comes from an u16"),
+            height: u16::try_from(self.height).expect("height comes from an u16"),
+        }
+    }
+
+    fn capture(&mut self) -> anyhow::Result<CapturedFrame<'_>> {
+        let size = Self::SQUARE_SIZE;
+
+        let (left, top) = self.square_origin();
+        self.fill(left, top, size, size, None);
+
+        self.position += Self::SPEED;
+
+        let (left, top) = self.square_origin();
+        self.fill(left, top, size, size, Some(Self::SQUARE_COLOR));
+
+        Ok(CapturedFrame {
+            data: &self.frame,
+            stride: self.width * 4,
+        })
+    }
+}
+
+// ============================================================================
+// Damage tracking
+// ============================================================================
+
+/// Finds the regions which changed since the previous frame
+struct DamageTracker {
+    width: usize,
+    height: usize,
+    previous: Option<Vec<u8>>,
+}
+
+impl DamageTracker {
+    fn new(size: DesktopSize) -> Self {
+        Self {
+            width: size.width.into(),
+            height: size.height.into(),
+            previous: None,
+        }
+    }
+
+    /// Forgets the previous frame, so that the next one is entirely damaged
+    fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    fn update(&mut self, frame: &CapturedFrame<'_>) -> Vec<Rect> {
+        let (width, height) = (self.width, self.height);
+        let row_size = width * 4;
+
+        let damage = match self.previous.as_deref() {
+            Some(previous) => find_different_rects_sub::<4>(
+                previous,
+                row_size,
+                width,
+                height,
+                frame.data,
+                frame.stride,
+                width,
+                height,
+                0,
+                0,
+            ),
+            None => vec![Rect::new(0, 0, width, height)],
+        };
+
+        let previous = self.previous.get_or_insert_with(|| vec![0; row_size * height]);
+        for (dst, src) in previous.chunks_exact_mut(row_size).zip(frame.data.chunks(frame.stride)) {
+            dst.copy_from_slice(&src[..row_size]);
+        }
+
+        damage
+    }
+}
+
+// ============================================================================
+// Encoding
+// ============================================================================
+
+/// Encodes I420 frames into an H.264 Annex B bitstream
+trait H264Encoder {
+    fn encode(&mut self, frame: &YuvPlanes<'_>) -> anyhow::Result<Vec<u8>>;
+
+    /// Makes the next encoded frame an IDR frame, e.g.: when a new client connects
+    fn force_keyframe(&mut self);
+}
+
+struct OpenH264Encoder {
+    encoder: Encoder,
+}
+
+impl OpenH264Encoder {
+    fn new() -> anyhow::Result<Self> {
+        let encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())
+            .context("failed to create OpenH264 encoder")?;
+
+        Ok(Self { encoder })
+    }
+}
+
+impl H264Encoder for OpenH264Encoder {
+    fn encode(&mut self, frame: &YuvPlanes<'_>) -> anyhow::Result<Vec<u8>> {
+        let bitstream = self.encoder.encode(&I420Source(frame)).context("H.264 encoding")?;
+
+        Ok(bitstream.to_vec())
+    }
+
+    fn force_keyframe(&mut self) {
+        self.encoder.force_intra_frame();
+    }
+}
+
+struct I420Source<'a>(&'a YuvPlanes<'a>);
+
+impl YUVSource for I420Source<'_> {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.0.width, self.0.height)
+    }
+
+    fn strides(&self) -> (usize, usize, usize) {
+        (self.0.y_stride, self.0.u_stride, self.0.v_stride)
+    }
+
+    fn y(&self) -> &[u8] {
+        self.0.y
+    }
+
+    fn u(&self) -> &[u8] {
+        self.0.u
+    }
+
+    fn v(&self) -> &[u8] {
+        self.0.v
+    }
+}
+
+// ============================================================================
+// Graphics pipeline
+// ============================================================================
+
+struct GfxHandler;
+
+impl GraphicsPipelineHandler for GfxHandler {
+    fn capabilities_advertise(&mut self, pdu: &CapabilitiesAdvertisePdu, _ctx: &mut GfxContext) {
+        debug!(?pdu, "EGFX capabilities advertised");
+    }
+
+    fn on_ready(&mut self, negotiated: &CapabilitySet, _ctx: &mut GfxContext) {
+        info!(?negotiated, "EGFX channel ready");
+    }
+}
+
+/// Hands the graphics pipeline server of the current connection over to the [`Streamer`]
+#[derive(Clone, Default)]
+struct GfxFactory {
+    current: Arc<Mutex<Option<GfxServerHandle>>>,
+}
+
+impl GfxServerFactory for GfxFactory {
+    fn build_gfx_handler(&self, _config: &GfxServerConfig) -> Box<dyn GraphicsPipelineHandler> {
+        Box::new(GfxHandler)
+    }
+
+    fn build_server_with_handle(&self, config: &GfxServerConfig) -> Option<(GfxDvcBridge, GfxServerHandle)> {
+        let server = Arc::new(Mutex::new(GraphicsPipelineServer::new(self.build_gfx_handler(config))));
+        *self.current.lock().expect("poisoned") = Some(Arc::clone(&server));
+
+        Some((GfxDvcBridge::new(Arc::clone(&server)), server))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ActiveSurface {
+    channel_id: u32,
+    surface_id: u16,
+}
+
+/// Drives the capture, damage tracking and encoding stages, and sends the frames over EGFX
+struct Streamer {
+    capture: Box<dyn CaptureBackend>,
+    damage: DamageTracker,
+    encoder: Box<dyn H264Encoder>,
+    yuv: YuvBuffer,
+    gfx: Arc<Mutex<Option<GfxServerHandle>>>,
+    events: UnboundedSender<ServerEvent>,
+    surface: Option<ActiveSurface>,
+    start: Instant,
+}
+
+impl Streamer {
+    fn new(
+        capture: Box<dyn CaptureBackend>,
+        encoder: Box<dyn H264Encoder>,
+        gfx: Arc<Mutex<Option<GfxServerHandle>>>,
+        events: UnboundedSender<ServerEvent>,
+    ) -> Self {
+        let size = capture.size();
+
+        Self {
+            damage: DamageTracker::new(size),
+            yuv: YuvBuffer::new(size.width.into(), size.height.into(), ChromaSubsampling::Yuv420),
+            capture,
+            encoder,
+            gfx,
+            events,
+            surface: None,
+            start: Instant::now(),
+        }
+    }
+
+    async fn run(mut self, fps: u32) -> anyhow::Result<()> {
+        let mut interval = time::interval(Duration::from_secs(1) / fps);
+
+        loop {
+            interval.tick().await;
+            self.step()?;
+        }
+    }
+
+    fn step(&mut self) -> anyhow::Result<()> {
+        let Some(handle) = self.gfx.lock().expect("poisoned").clone() else {
+            return Ok(());
+        };
+        let mut server = handle.lock().expect("poisoned");
+
+        let Some(channel_id) = server.channel_id().filter(|_| server.is_ready()) else {
+            self.surface = None;
+            return Ok(());
+        };
+
+        if !server.supports_avc420() {
+            trace!("Client does not support AVC420");
+            return Ok(());
+        }
+
+        let surface_id = match self.surface {
+            Some(surface) if surface.channel_id == channel_id => surface.surface_id,
+            _ => {
+                let size = self.capture.size();
+                let surface_id = server
+                    .create_surface(size.width, size.height)
+                    .context("failed to create surface")?;
+                server.map_surface_to_output(surface_id, 0, 0);
+
+                // The new client has nothing to predict from.
+                self.damage.reset();
+                self.encoder.force_keyframe();
+
+                self.surface = Some(ActiveSurface { channel_id, surface_id });
+                surface_id
+            }
+        };
+
+        if !server.should_backpressure() {
+            let frame = self.capture.capture()?;
+            let damage = self.damage.update(&frame);
+
+            if !damage.is_empty() {
+                bgra_to_yuv(frame.data, frame.stride, &mut self.yuv.as_planes_mut())?;
+                let bitstream = self.encoder.encode(&self.yuv.as_planes())?;
+
+                let regions = damage.iter().map(avc420_region).collect::<anyhow::Result<Vec<_>>>()?;
+                let timestamp = u32::try_from(self.start.elapsed().as_millis()).unwrap_or(u32::MAX);
+
+                if let Some(frame_id) =
+                    server.send_avc420_frame(surface_id, &annex_b_to_avc(&bitstream), &regions, timestamp)
+                {
+                    trace!(frame_id, regions = regions.len(), "Frame sent");
+                }
+            }
+        }
+
+        let messages = server.drain_output();
+        if !messages.is_empty() {
+            let messages = encode_dvc_messages(channel_id, messages, ChannelFlags::SHOW_PROTOCOL)?;
+            self.events
+                .send(ServerEvent::Egfx(EgfxServerMessage::SendMessages {
+                    channel_id,
+                    messages,
+                }))
+                .context("server is gone")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn avc420_region(rect: &Rect) -> anyhow::Result<Avc420Region> {
+    let left = u16::try_from(rect.x)?;
+    let top = u16::try_from(rect.y)?;
+    let right = u16::try_from(rect.x + rect.width - 1)?;
+    let bottom = u16::try_from(rect.y + rect.height - 1)?;
+
+    Ok(Avc420Region::new(left, top, right, bottom, QP, 100))
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[derive(Clone, Debug)]
+struct Handler;
+
+impl RdpServerInputHandler for Handler {
+    fn keyboard(&mut self, event: KeyboardEvent) {
+        info!(?event, "keyboard");
+    }
+
+    fn mouse(&mut self, event: MouseEvent) {
+        info!(?event, "mouse");
+    }
+}
+
+/// The screen is streamed over EGFX only, so there are never any legacy bitmap updates
+struct NoDisplayUpdates;
+
+#[async_trait::async_trait]
+impl RdpServerDisplayUpdates for NoDisplayUpdates {
+    async fn next_update(&mut self) -> anyhow::Result<Option<DisplayUpdate>> {
+        core::future::pending().await
+    }
+}
+
+#[async_trait::async_trait]
+impl RdpServerDisplay for Handler {
+    async fn size(&mut self) -> DesktopSize {
+        DesktopSize {
+            width: WIDTH,
+            height: HEIGHT,
+        }
+    }
+
+    async fn updates(&mut self) -> anyhow::Result<Box<dyn RdpServerDisplayUpdates>> {
+        Ok(Box::new(NoDisplayUpdates))
+    }
+}
+
+async fn run(
+    bind_addr: SocketAddr,
+    username: String,
+    password: String,
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+    fps: u32,
+) -> anyhow::Result<()> {
+    info!(%bind_addr, ?cert, ?key, fps, "run");
+
+    let server_builder = RdpServer::builder().with_addr(bind_addr);
+
+    let server_builder = if let Some((cert_path, key_path)) = cert.as_deref().zip(key.as_deref()) {
+        let identity = TlsIdentityCtx::init_from_paths(cert_path, key_path).context("failed to init TLS identity")?;
+        let acceptor = identity.make_acceptor().context("failed to build TLS acceptor")?;
+
+        server_builder.with_hybrid(acceptor, identity.pub_key)
+    } else {
+        server_builder.with_no_security()
+    };
+
+    let gfx = GfxFactory::default();
+
+    let mut server = server_builder
+        .with_input_handler(Handler)
+        .with_display_handler(Handler)
+        .with_gfx_factory(Some(Box::new(gfx.clone())))
+        .build();
+
+    server.set_credentials(Some(Credentials {
+        username,
+        password,
+        domain: None,
+    }));
+
+    let streamer = Streamer::new(
+        Box::new(TestPatternCapture::new(WIDTH, HEIGHT)),
+        Box::new(OpenH264Encoder::new()?),
+        gfx.current,
+        server.event_sender().clone(),
+    );
+
+    tokio::select! {
+        result = streamer.run(fps) => result,
+        result = server.run() => result,
+    }
+}
@@ -4,14 +4,24 @@
 
 #[cfg(test)]
 use {
-    anyhow as _, async_trait as _, image as _, ironrdp_blocking as _, ironrdp_cliprdr_native as _, opus2 as _,
+    anyhow as _, async_trait as _, image as _, ironrdp_blocking as _, ironrdp_cliprdr_native as _, openh264 as _, opus2 as _,
     pico_args as _, rand as _, sspi as _, tokio_rustls as _, tracing as _, tracing_subscriber as _, x509_cert as _,
 };
 
+pub mod prelude;
+
+#[cfg(feature = "accessibility")]
+#[doc(inline)]
+pub use ironrdp_accessibility as accessibility;
+
 #[cfg(feature = "acceptor")]
 #[doc(inline)]
 pub use ironrdp_acceptor as acceptor;
 
+#[cfg(feature = "audioinput")]
+#[doc(inline)]
+pub use ironrdp_audioinput as audioinput;
+
 #[cfg(feature = "cliprdr")]
 #[doc(inline)]
 pub use ironrdp_cliprdr as cliprdr;
@@ -32,6 +42,10 @@ pub use ironrdp_displaycontrol as displaycontrol;
 #[doc(inline)]
 pub use ironrdp_dvc as dvc;
 
+#[cfg(feature = "egfx")]
+#[doc(inline)]
+pub use ironrdp_egfx as egfx;
+
 #[cfg(feature = "graphics")]
 #[doc(inline)]
 pub use ironrdp_graphics as graphics;
@@ -44,10 +58,26 @@ pub use ironrdp_input as input;
 #[doc(inline)]
 pub use ironrdp_pdu as pdu;
 
+#[cfg(feature = "rail")]
+#[doc(inline)]
+pub use ironrdp_rail as rail;
+
 #[cfg(feature = "rdpdr")]
 #[doc(inline)]
 pub use ironrdp_rdpdr as rdpdr;
 
+#[cfg(feature = "rdpecam")]
+#[doc(inline)]
+pub use ironrdp_rdpecam as rdpecam;
+
+#[cfg(feature = "rdpevor")]
+#[doc(inline)]
+pub use ironrdp_rdpevor as rdpevor;
+
+#[cfg(feature = "rdpeusb")]
+#[doc(inline)]
+pub use ironrdp_rdpeusb as rdpeusb;
+
 #[cfg(feature = "rdpsnd")]
 #[doc(inline)]
 pub use ironrdp_rdpsnd as rdpsnd;
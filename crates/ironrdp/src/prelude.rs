@@ -0,0 +1,76 @@
+//! Curated set of the items most applications need, meant to be glob-imported
+//!
+//! ```ignore
+//! use ironrdp::prelude::*;
+//! ```
+//!
+//! Unlike the modules re-exporting the IronRDP crates, which follow the versions of these crates, the prelude is
+//! covered by the semver guarantees of this crate: an item is never removed or renamed here without a major release,
+//! whatever happens to the crate it comes from. When an item moves across the internal crates, its prelude path is
+//! kept. An item that is to be dropped is first replaced by a deprecated shim (a type alias or a wrapper, since
+//! `#[deprecated]` has no effect on re-exports) for at least one minor release.
+//!
+//! Items are only available when the feature of their crate is enabled.
+
+#![cfg_attr(rustfmt, rustfmt_skip)] // Keeps the grouping of the sections.
+
+// -- Common types -- //
+
+#[cfg(feature = "core")]
+#[doc(no_inline)]
+pub use ironrdp_core::{
+    decode, encode_vec, Decode, DecodeError, DecodeResult, Encode, EncodeError, EncodeResult, ReadCursor, WriteBuf,
+    WriteCursor,
+};
+
+#[cfg(feature = "pdu")]
+#[doc(no_inline)]
+pub use ironrdp_pdu::{Action, PduError, PduHint, PduResult};
+
+#[cfg(feature = "connector")]
+#[doc(no_inline)]
+pub use ironrdp_connector::DesktopSize;
+
+#[cfg(all(feature = "acceptor", not(feature = "connector")))]
+#[doc(no_inline)]
+pub use ironrdp_acceptor::DesktopSize;
+
+#[cfg(all(feature = "server", not(any(feature = "connector", feature = "acceptor"))))]
+#[doc(no_inline)]
+pub use ironrdp_server::DesktopSize;
+
+#[cfg(feature = "graphics")]
+#[doc(no_inline)]
+pub use ironrdp_graphics::image_processing::PixelFormat;
+
+#[cfg(feature = "svc")]
+#[doc(no_inline)]
+pub use ironrdp_svc::{SvcMessage, SvcProcessor};
+
+#[cfg(feature = "dvc")]
+#[doc(no_inline)]
+pub use ironrdp_dvc::{DvcMessage, DvcProcessor};
+
+// -- Client -- //
+
+#[cfg(feature = "connector")]
+#[doc(no_inline)]
+pub use ironrdp_connector::{
+    credssp::KerberosConfig, ClientConnector, ClientConnectorState, ConnectionResult, ConnectorError, ConnectorResult,
+    Credentials, Sequence, ServerName,
+};
+
+#[cfg(feature = "session")]
+#[doc(no_inline)]
+pub use ironrdp_session::{
+    image::DecodedImage, ActiveStage, ActiveStageOutput, GracefulDisconnectReason, SessionError, SessionResult,
+};
+
+// -- Server -- //
+
+#[cfg(feature = "server")]
+#[doc(no_inline)]
+pub use ironrdp_server::{
+    BitmapUpdate, DisplayUpdate, KeyboardEvent, MouseEvent, RdpServer, RdpServerDisplay, RdpServerDisplayUpdates,
+    RdpServerInputHandler, RdpServerSecurity, ServerEvent,
+};
@@ -15,6 +15,14 @@ public partial class ActiveStageOutput: IDisposable
 {
     private unsafe Raw.ActiveStageOutput* _inner;
 
+    public bool ControlGranted
+    {
+        get
+        {
+            return GetControlGranted();
+        }
+    }
+
     public ConnectionActivationSequence DeactivateAll
     {
         get
@@ -233,6 +241,25 @@ public partial class ActiveStageOutput: IDisposable
         }
     }
 
+    /// <exception cref="IronRdpException"></exception>
+    public bool GetControlGranted()
+    {
+        unsafe
+        {
+            if (_inner == null)
+            {
+                throw new ObjectDisposedException("ActiveStageOutput");
+            }
+            Raw.SessionFfiResultBoolBoxIronRdpError result = Raw.ActiveStageOutput.GetControlGranted(_inner);
+            if (!result.isOk)
+            {
+                throw new IronRdpException(new IronRdpError(result.Err));
+            }
+            bool retVal = result.Ok;
+            return retVal;
+        }
+    }
+
     /// <summary>
     /// Returns the underlying raw handle.
     /// </summary>
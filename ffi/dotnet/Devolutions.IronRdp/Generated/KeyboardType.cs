@@ -20,4 +20,5 @@ public enum KeyboardType
     Nokia1050 = 4,
     Nokia9140 = 5,
     Japanese = 6,
+    Korean = 7,
 }
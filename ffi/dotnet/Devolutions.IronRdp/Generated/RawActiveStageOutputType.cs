@@ -21,4 +21,6 @@ public enum ActiveStageOutputType
     PointerBitmap = 5,
     Terminate = 6,
     DeactivateAll = 7,
+    Control = 8,
+    WindowOrders = 9,
 }
@@ -63,6 +63,7 @@ pub mod ffi {
         Nokia1050,
         Nokia9140,
         Japanese,
+        Korean,
     }
 
     #[diplomat::opaque]
@@ -174,6 +175,8 @@ pub mod ffi {
             let connector = ironrdp::connector::Config {
                 credentials: self.credentials.clone().ok_or("credentials not set")?,
                 domain: self.domain.clone(),
+                redirection_credentials: None,
+                auto_reconnect_cookie: None,
                 enable_tls: self.enable_tls.unwrap_or(false),
                 enable_credssp: self.enable_credssp.unwrap_or(true),
                 keyboard_layout: self.keyboard_layout.unwrap_or(0),
@@ -212,10 +215,12 @@ pub mod ffi {
                 enable_server_pointer: self.enable_server_pointer.unwrap_or(false),
                 autologon: self.autologon.unwrap_or(false),
                 enable_audio_playback: self.no_audio_playback.unwrap_or(true),
+                remote_app: false,
                 request_data: None,
                 pointer_software_rendering: self.pointer_software_rendering.unwrap_or(false),
                 performance_flags: self.performance_flags.ok_or("performance flag is missing")?,
                 desktop_scale_factor: 0,
+                monitors: Vec::new(),
                 hardware_id: None,
                 license_cache: None,
                 timezone_info: self.timezone_info.clone().unwrap_or_default(),
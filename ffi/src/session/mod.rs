@@ -192,6 +192,9 @@ pub mod ffi {
         PointerBitmap,
         Terminate,
         DeactivateAll,
+        Control,
+        WindowOrders,
+        ServerRedirection,
     }
 
     impl ActiveStageOutput {
@@ -205,6 +208,11 @@ pub mod ffi {
                 ironrdp::session::ActiveStageOutput::PointerBitmap { .. } => ActiveStageOutputType::PointerBitmap,
                 ironrdp::session::ActiveStageOutput::Terminate { .. } => ActiveStageOutputType::Terminate,
                 ironrdp::session::ActiveStageOutput::DeactivateAll { .. } => ActiveStageOutputType::DeactivateAll,
+                ironrdp::session::ActiveStageOutput::Control { .. } => ActiveStageOutputType::Control,
+                ironrdp::session::ActiveStageOutput::WindowOrders { .. } => ActiveStageOutputType::WindowOrders,
+                ironrdp::session::ActiveStageOutput::ServerRedirection { .. } => {
+                    ActiveStageOutputType::ServerRedirection
+                }
             }
         }
 
@@ -270,6 +278,17 @@ pub mod ffi {
             }
             .map(Box::new)
         }
+
+        pub fn get_control_granted(&self) -> Result<bool, Box<IronRdpError>> {
+            match &self.0 {
+                ironrdp::session::ActiveStageOutput::Control(status) => {
+                    Ok(*status == ironrdp::session::x224::ControlStatus::Granted)
+                }
+                _ => Err(IncorrectEnumTypeError::on_variant("Control")
+                    .of_enum("ActiveStageOutput")
+                    .into()),
+            }
+        }
     }
 
     #[diplomat::opaque]
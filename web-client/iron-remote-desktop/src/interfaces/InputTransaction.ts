@@ -2,4 +2,5 @@ import type { DeviceEvent } from './DeviceEvent';
 
 export interface InputTransaction {
     addEvent(event: DeviceEvent): void;
+    addText(text: string): void;
 }
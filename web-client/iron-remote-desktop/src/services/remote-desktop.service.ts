@@ -110,6 +110,15 @@ export class RemoteDesktopService {
         this.releaseAllInputs();
     }
 
+    sendCompositionEvent(evt: CompositionEvent) {
+        // Only the committed text is sent, the input method shows the text being composed.
+        if (evt.type === 'compositionend' && evt.data) {
+            const transaction = new this.module.InputTransaction();
+            transaction.addText(evt.data);
+            this.session?.applyInputs(transaction);
+        }
+    }
+
     sendKeyboardEvent(evt: KeyboardEvent) {
         this.sendKeyboard(evt);
     }
@@ -314,6 +323,12 @@ export class RemoteDesktopService {
     }
 
     private sendKeyboard(evt: KeyboardEvent) {
+        // The keys pressed while composing are handled by the input method (IME).
+        // Key code 229 is reported by some browsers for the key starting the composition.
+        if (evt.type === 'keydown' && (evt.isComposing || evt.keyCode === 229)) {
+            return;
+        }
+
         evt.preventDefault();
 
         let keyEvent;
@@ -4,6 +4,7 @@
 use crate::bin_install::CargoPackage;
 
 pub const CARGO_FUZZ: CargoPackage = CargoPackage::new("cargo-fuzz", "0.12.0");
+pub const CARGO_SEMVER_CHECKS: CargoPackage = CargoPackage::new("cargo-semver-checks", "0.41.0");
 pub const CARGO_LLVM_COV: CargoPackage = CargoPackage::new("cargo-llvm-cov", "0.6.16");
 pub const GRCOV: CargoPackage = CargoPackage::new("grcov", "0.8.20");
 pub const WASM_PACK: CargoPackage = CargoPackage::new("wasm-pack", "0.13.1");
@@ -42,10 +42,29 @@ pub fn typos(sh: &Shell) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub fn semver(sh: &Shell) -> anyhow::Result<()> {
+    let _s = Section::new("SEMVER");
+
+    if !is_installed(sh, "cargo-semver-checks") {
+        anyhow::bail!("`cargo-semver-checks` binary is missing. Please run `cargo xtask check install`.");
+    }
+
+    // Only the meta crate, whose prelude is the stable API, is checked against its last release.
+    cmd!(
+        sh,
+        "{CARGO} semver-checks check-release --package ironrdp --all-features"
+    )
+    .run()?;
+
+    println!("All good!");
+    Ok(())
+}
+
 pub fn install(sh: &Shell) -> anyhow::Result<()> {
     let _s = Section::new("TYPOS-CLI-INSTALL");
 
     cargo_install(sh, &TYPOS_CLI)?;
+    cargo_install(sh, &CARGO_SEMVER_CHECKS)?;
 
     Ok(())
 }
@@ -15,6 +15,7 @@ TASKS:
   check locks             Check for dirty or staged lock files not yet committed
   check tests [--no-run]  Compile tests and, unless specified otherwise, run them
   check typos             Check for typos in the codebase
+  check semver            Check the meta crate for semver violations against its last release
   check install           Install all requirements for check tasks
   ci                      Run all checks required on CI
   clean                   Clean workspace
@@ -62,6 +63,7 @@ pub enum Action {
         no_run: bool,
     },
     CheckTypos,
+    CheckSemver,
     CheckInstall,
     Ci,
     Clean,
@@ -116,6 +118,7 @@ pub fn parse_args() -> anyhow::Result<Args> {
                     no_run: args.contains("--no-run"),
                 },
                 Some("typos") => Action::CheckTypos,
+                Some("semver") => Action::CheckSemver,
                 Some("install") => Action::CheckInstall,
                 Some(unknown) => anyhow::bail!("unknown check action: {unknown}"),
                 None => Action::ShowHelp,
@@ -80,6 +80,9 @@ fn main() -> anyhow::Result<()> {
         Action::CheckTypos => {
             check::typos(&sh)?;
         }
+        Action::CheckSemver => {
+            check::semver(&sh)?;
+        }
         Action::CheckInstall => {
             check::install(&sh)?;
         }